@@ -0,0 +1,88 @@
+//! Build script.
+//!
+//! Script records the git revision, the compiler and the
+//! time of the build as environment variables, which
+//! `core::build` reads with `option_env!`. Builds outside
+//! of a git checkout simply leave the revision unset.
+//!
+//! With the `grpc` feature the script also generates the
+//! gRPC service of `proto/` with the vendored `protoc`.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    watch_git();
+
+    if let Some(sha) = output("git", &["rev-parse", "--short=12", "HEAD"]) {
+        println!("cargo:rustc-env=AXUM_AUTH_GIT_SHA={}", sha);
+    }
+    if let Some(status) = output("git", &["status", "--porcelain", "--untracked-files=no"]) {
+        println!("cargo:rustc-env=AXUM_AUTH_GIT_DIRTY={}", !status.is_empty());
+    }
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    if let Some(version) = output(&rustc, &["--version"]) {
+        println!("cargo:rustc-env=AXUM_AUTH_RUSTC={}", version);
+    }
+
+    for var in ["PROFILE", "TARGET"] {
+        if let Ok(value) = std::env::var(var) {
+            println!("cargo:rustc-env=AXUM_AUTH_{}={}", var, value);
+        }
+    }
+
+    if let Ok(elapsed) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        println!("cargo:rustc-env=AXUM_AUTH_BUILT_AT={}", elapsed.as_secs());
+    }
+
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+/// Generates the server of the gRPC service.
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("Vendored protoc is available");
+    std::env::set_var("PROTOC", protoc);
+
+    println!("cargo:rerun-if-changed=proto");
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/axum_auth/v1/auth.proto"], &["proto"])
+        .expect("Failed to compile the gRPC protos");
+}
+
+/// Reruns the script when the checked out revision or the index changes.
+fn watch_git() {
+    let head = Path::new(".git/HEAD");
+    if !head.is_file() {
+        return;
+    }
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+    if let Some(reference) = std::fs::read_to_string(head)
+        .ok()
+        .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string()))
+    {
+        let path = Path::new(".git").join(reference);
+        if path.is_file() {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+}
+
+/// Runs the command and returns its trimmed output, `None` if it failed.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|output| output.trim().to_string())
+}
@@ -0,0 +1,306 @@
+//! Resource server example.
+//!
+//! Separate axum service that stores notes and trusts
+//! the access tokens of a running auth server. Keys are
+//! fetched from the `/.well-known/jwks.json` endpoint of
+//! the auth server, reading notes requires the
+//! `notes:read` scope and writing them `notes:write`.
+//!
+//! Start the auth server with `tokens.signing_key` set,
+//! then run the example and call it with a token:
+//! ```text
+//! AUTH_URL=http://localhost:8080 cargo run --example resource_server
+//! TOKEN=$(cargo run -q -- token --subject billing --scope notes:read --scope notes:write)
+//! curl -X POST -H "Authorization: Bearer $TOKEN" -H "Content-Type: application/json" \
+//!      -d '{"text":"hello"}' http://localhost:3000/notes
+//! curl -H "Authorization: Bearer $TOKEN" http://localhost:3000/notes
+//! ```
+//!
+//! Environment variables:
+//! + `AUTH_URL` - URL of the auth server, `http://localhost:8080` by default.
+//! + `AUTH_ISSUER` - Expected `iss` claim, `AUTH_URL` by default.
+//! + `AUTH_AUDIENCE` - Expected `aud` claim, `axum-auth` by default.
+//! + `LISTEN` - Address of the example, `127.0.0.1:3000` by default.
+
+// Imports from external crates
+use axum::{
+    extract::State, handler::Handler, http::StatusCode, middleware, routing::get, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    sync::{Arc, Mutex},
+};
+
+// Imports of the auth crate
+use axum_auth::client::{authenticate, require_scope, AuthClient, TokenValidator};
+use axum_auth::core::auth::jwt::{AccessClaims, Validation};
+use axum_auth::core::err::AppError;
+
+/// ## Note struct.
+///
+/// ## Fields
+/// + `owner`: `String` - Subject of the token that created the note.
+/// + `text`: `String` - Text of the note.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Note {
+    owner: String,
+    text: String,
+}
+
+/// ## New note struct.
+#[derive(Debug, Deserialize)]
+struct NewNote {
+    text: String,
+}
+
+/// Notes of every owner.
+type Notes = Arc<Mutex<Vec<Note>>>;
+
+/// ## Builds the router of the example.
+///
+/// Every route except `/healthz` requires a valid token,
+/// the scope is checked per handler.
+fn app(validator: Arc<TokenValidator>) -> Router {
+    let notes: Notes = Arc::default();
+
+    Router::new()
+        .route(
+            "/notes",
+            get(list_notes.layer(middleware::from_fn_with_state("notes:read", require_scope)))
+                .post(
+                    create_note.layer(middleware::from_fn_with_state("notes:write", require_scope)),
+                ),
+        )
+        .layer(middleware::from_fn_with_state(validator, authenticate))
+        .route("/healthz", get(|| async { "ok" }))
+        .with_state(notes)
+}
+
+/// ## Lists the notes of the caller.
+async fn list_notes(claims: AccessClaims, State(notes): State<Notes>) -> Json<Vec<Note>> {
+    let notes = notes.lock().unwrap_or_else(|e| e.into_inner());
+
+    Json(
+        notes
+            .iter()
+            .filter(|note| note.owner == claims.sub)
+            .cloned()
+            .collect(),
+    )
+}
+
+/// ## Creates a note of the caller.
+async fn create_note(
+    claims: AccessClaims,
+    State(notes): State<Notes>,
+    Json(new): Json<NewNote>,
+) -> (StatusCode, Json<Note>) {
+    let note = Note {
+        owner: claims.sub,
+        text: new.text,
+    };
+    notes
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(note.clone());
+
+    (StatusCode::CREATED, Json(note))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), AppError> {
+    let auth_url = env::var("AUTH_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
+    let issuer = env::var("AUTH_ISSUER").unwrap_or_else(|_| auth_url.clone());
+    let audience = env::var("AUTH_AUDIENCE").unwrap_or_else(|_| "axum-auth".to_string());
+    let listen = env::var("LISTEN").unwrap_or_else(|_| "127.0.0.1:3000".to_string());
+
+    let validator = Arc::new(TokenValidator::new(
+        AuthClient::new(&auth_url)?,
+        Validation::new(&issuer, &audience),
+    ));
+
+    let listener = tokio::net::TcpListener::bind(&listen)
+        .await
+        .map_err(|e| AppError::io(format!("Failed to bind '{}'", listen)).with_source(e))?;
+    println!(
+        "Resource server listening on {}, trusting {}",
+        listen, auth_url
+    );
+
+    axum::serve(listener, app(validator))
+        .await
+        .map_err(|e| AppError::io("Server failed").with_source(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::header::{AUTHORIZATION, WWW_AUTHENTICATE},
+        response::Response,
+    };
+    use axum_auth::core::auth::{jwt::SigningKey, keys::KeyManager};
+    use axum_auth::core::config::TokenSettings;
+    use tower::ServiceExt;
+
+    // Starts the key set route of the auth server.
+    async fn auth_server(key: &Arc<SigningKey>) -> String {
+        let app = axum_auth::routes::jwks::router(Arc::new(KeyManager::new(key.clone())));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        format!("http://{}", addr)
+    }
+
+    fn resource_server(auth_url: &str, settings: &TokenSettings) -> Router {
+        app(Arc::new(TokenValidator::new(
+            AuthClient::new(auth_url).unwrap(),
+            Validation::new(&settings.issuer, &settings.audience),
+        )))
+    }
+
+    async fn send(app: &Router, method: &str, token: Option<&str>) -> Response {
+        let mut request = Request::builder()
+            .method(method)
+            .uri("/notes")
+            .header("Content-Type", "application/json");
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let body = match method {
+            "POST" => Body::from(r#"{"text":"hello"}"#),
+            _ => Body::empty(),
+        };
+
+        app.clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    // Test checks if tokens of the auth server are accepted with their scopes.
+    #[tokio::test]
+    async fn test_scopes() {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let app = resource_server(&auth_server(&key).await, &settings);
+        let writer = key
+            .issue(&settings, "billing", &["notes:read", "notes:write"])
+            .unwrap();
+        let reader = key.issue(&settings, "billing", &["notes:read"]).unwrap();
+
+        let response = send(&app, "POST", Some(&writer)).await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(&app, "GET", Some(&reader)).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json(response).await,
+            serde_json::json!([{ "owner": "billing", "text": "hello" }])
+        );
+
+        let response = send(&app, "POST", Some(&reader)).await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            "Bearer error=\"insufficient_scope\", scope=\"notes:write\""
+        );
+        assert_eq!(
+            json(response).await["error"]["code"],
+            "token.insufficient_scope"
+        );
+    }
+
+    // Test checks if missing, foreign and expired tokens are rejected.
+    #[tokio::test]
+    async fn test_rejected_tokens() {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let app = resource_server(&auth_server(&key).await, &settings);
+
+        let response = send(&app, "GET", None).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+
+        let foreign = SigningKey::generate("foreign").unwrap();
+        let token = foreign
+            .issue(&settings, "billing", &["notes:read"])
+            .unwrap();
+        let response = send(&app, "GET", Some(&token)).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            "Bearer error=\"invalid_token\""
+        );
+        assert_eq!(json(response).await["error"]["code"], "token.unknown_key");
+
+        let now = chrono::Utc::now().timestamp();
+        let expired = key
+            .sign(&AccessClaims {
+                iss: settings.issuer.clone(),
+                sub: "billing".to_string(),
+                aud: settings.audience.clone(),
+                iat: now - 1200,
+                exp: now - 300,
+                scope: "notes:read".to_string(),
+                sid: None,
+            })
+            .unwrap();
+        let response = send(&app, "GET", Some(&expired)).await;
+
+        assert_eq!(json(response).await["error"]["code"], "token.expired");
+
+        let other_audience = TokenSettings {
+            audience: "billing".to_string(),
+            ..settings
+        };
+        let token = key
+            .issue(&other_audience, "billing", &["notes:read"])
+            .unwrap();
+
+        assert_eq!(
+            send(&app, "GET", Some(&token)).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    // Test checks if an unreachable auth server is reported as a gateway error.
+    #[tokio::test]
+    async fn test_auth_server_unreachable() {
+        let settings = TokenSettings::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let auth_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let app = resource_server(&auth_url, &settings);
+        let key = SigningKey::generate(&settings.key_id).unwrap();
+        let token = key.issue(&settings, "billing", &["notes:read"]).unwrap();
+
+        let response = send(&app, "GET", Some(&token)).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            app.oneshot(Request::get("/healthz").body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+    }
+}
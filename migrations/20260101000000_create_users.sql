@@ -0,0 +1,14 @@
+-- Users of the application
+CREATE TABLE IF NOT EXISTS users (
+    id                      UUID PRIMARY KEY,
+    email                   TEXT NOT NULL UNIQUE,
+    display_name            TEXT,
+    password_hash           TEXT,
+    org_id                  UUID,
+    disabled                BOOLEAN NOT NULL DEFAULT FALSE,
+    password_reset_required BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS users_org_id_idx ON users (org_id);
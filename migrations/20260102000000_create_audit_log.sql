@@ -0,0 +1,13 @@
+-- Security relevant events
+CREATE TABLE IF NOT EXISTS audit_log (
+    id          BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    event       TEXT NOT NULL,
+    actor_id    UUID,
+    user_id     UUID,
+    ip          TEXT,
+    details     JSONB NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_user_id_idx ON audit_log (user_id, occurred_at);
+CREATE INDEX IF NOT EXISTS audit_log_event_idx ON audit_log (event, occurred_at);
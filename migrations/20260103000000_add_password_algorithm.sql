@@ -0,0 +1,3 @@
+-- Algorithm of the password hash, hashes are upgraded
+-- on login when the configured algorithm changes
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_algorithm TEXT;
@@ -0,0 +1,12 @@
+-- Roles granted to users, `source` tells who manages
+-- the grant, directory grants are reconciled by the
+-- group sync and local grants are never touched by it
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id    UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role       TEXT NOT NULL,
+    source     TEXT NOT NULL DEFAULT 'local',
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, role)
+);
+
+CREATE INDEX IF NOT EXISTS user_roles_source_idx ON user_roles (source);
@@ -0,0 +1,31 @@
+-- Pending webhook deliveries, one row per event and
+-- endpoint, removed once the endpoint accepted it
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id              BIGSERIAL PRIMARY KEY,
+    event_id        UUID NOT NULL,
+    endpoint        TEXT NOT NULL,
+    event           TEXT NOT NULL,
+    payload         JSONB NOT NULL,
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error      TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_next_attempt_idx ON webhook_deliveries (next_attempt_at);
+
+-- Deliveries that failed every attempt, kept for
+-- inspection and manual replay
+CREATE TABLE IF NOT EXISTS webhook_dead_letters (
+    id         BIGSERIAL PRIMARY KEY,
+    event_id   UUID NOT NULL,
+    endpoint   TEXT NOT NULL,
+    event      TEXT NOT NULL,
+    payload    JSONB NOT NULL,
+    attempts   INTEGER NOT NULL,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    failed_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS webhook_dead_letters_endpoint_idx ON webhook_dead_letters (endpoint, failed_at);
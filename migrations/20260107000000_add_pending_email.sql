@@ -0,0 +1,3 @@
+-- Email address waiting for confirmation, it replaces
+-- `email` once the user confirms the emailed token
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_email TEXT;
@@ -0,0 +1,10 @@
+-- OAuth clients of the client credentials grant, only
+-- the SHA-256 hash of the secret is stored
+CREATE TABLE IF NOT EXISTS oauth_clients (
+    client_id   TEXT PRIMARY KEY,
+    secret_hash TEXT NOT NULL,
+    name        TEXT NOT NULL,
+    scopes      TEXT[] NOT NULL DEFAULT '{}',
+    disabled    BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
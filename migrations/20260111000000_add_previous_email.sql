@@ -0,0 +1,3 @@
+-- Email address of the user when the pending change was
+-- requested, restored by the undo link sent to it
+ALTER TABLE users ADD COLUMN IF NOT EXISTS previous_email TEXT;
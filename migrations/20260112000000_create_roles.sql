@@ -0,0 +1,12 @@
+-- Roles defined by administrators, `version` is increased
+-- by every update so concurrent edits are detected
+CREATE TABLE IF NOT EXISTS roles (
+    tenant_id   TEXT NOT NULL DEFAULT 'default' REFERENCES tenants (id),
+    name        TEXT NOT NULL,
+    description TEXT,
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    version     INTEGER NOT NULL DEFAULT 1,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, name)
+);
@@ -0,0 +1,16 @@
+-- Devices the users logged in from, told apart by the
+-- SHA-256 hash of the user agent
+CREATE TABLE IF NOT EXISTS login_devices (
+    id            UUID PRIMARY KEY,
+    tenant_id     TEXT NOT NULL DEFAULT 'default' REFERENCES tenants (id),
+    user_id       UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    fingerprint   TEXT NOT NULL,
+    user_agent    TEXT,
+    ip            TEXT,
+    countries     TEXT[] NOT NULL DEFAULT '{}',
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, fingerprint)
+);
+
+CREATE INDEX IF NOT EXISTS login_devices_tenant_id_idx ON login_devices (tenant_id);
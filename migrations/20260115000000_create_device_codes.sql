@@ -0,0 +1,18 @@
+-- Codes of the OAuth device authorization grant, only the
+-- SHA-256 hash of the device code is stored. The tenant is
+-- the one of the user who decided.
+CREATE TABLE IF NOT EXISTS device_codes (
+    id               UUID PRIMARY KEY,
+    client_id        TEXT NOT NULL REFERENCES oauth_clients (client_id) ON DELETE CASCADE,
+    device_code_hash TEXT NOT NULL UNIQUE,
+    user_code        TEXT NOT NULL UNIQUE,
+    scopes           TEXT[] NOT NULL DEFAULT '{}',
+    status           TEXT NOT NULL DEFAULT 'pending',
+    user_id          UUID REFERENCES users (id) ON DELETE CASCADE,
+    tenant_id        TEXT REFERENCES tenants (id),
+    polled_at        TIMESTAMPTZ,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at       TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS device_codes_expires_at_idx ON device_codes (expires_at);
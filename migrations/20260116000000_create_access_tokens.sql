@@ -0,0 +1,16 @@
+-- Opaque access tokens, only the SHA-256 hash of a token
+-- is stored with the claims it stands for. Tokens of a
+-- user belong to the tenant of their session.
+CREATE TABLE IF NOT EXISTS access_tokens (
+    token_hash TEXT PRIMARY KEY,
+    issuer     TEXT NOT NULL,
+    subject    TEXT NOT NULL,
+    audience   TEXT NOT NULL,
+    scope      TEXT NOT NULL DEFAULT '',
+    session_id TEXT,
+    issued_at  TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS access_tokens_expires_at_idx ON access_tokens (expires_at);
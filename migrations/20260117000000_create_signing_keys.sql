@@ -0,0 +1,11 @@
+-- Signing keys of the access tokens, shared by every
+-- server and the worker. The current key has no expiry,
+-- retired keys verify tokens until `expires_at`.
+CREATE TABLE IF NOT EXISTS signing_keys (
+    kid        TEXT PRIMARY KEY,
+    document   BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS signing_keys_expires_at_idx ON signing_keys (expires_at);
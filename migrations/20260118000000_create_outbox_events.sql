@@ -0,0 +1,9 @@
+-- Domain events written by the transaction of the state
+-- change, removed once the worker relayed them
+CREATE TABLE IF NOT EXISTS outbox_events (
+    id         BIGSERIAL PRIMARY KEY,
+    event_id   UUID NOT NULL,
+    event      TEXT NOT NULL,
+    payload    JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,5 @@
+-- Age of the passwords and expiry warnings already sent,
+-- existing passwords count from the migration on
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_expiry_warned_at TIMESTAMPTZ;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS expiry_warned_at TIMESTAMPTZ;
@@ -0,0 +1,74 @@
+//! Audit log types module.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+// Local imports
+use super::pagination::Paginated;
+
+/// ## Recorded audit event struct.
+///
+/// ## Fields
+/// + `id`: `i64` - ID of the record.
+/// + `occurred_at`: `DateTime<Utc>` - Time of the event.
+/// + `event`: `String` - Name of the event.
+/// + `actor_id`: `Option<Uuid>` - User that performed the action.
+/// + `user_id`: `Option<Uuid>` - User the event is about.
+/// + `ip`: `Option<String>` - Client address of the request.
+/// + `details`: `serde_json::Value` - Event specific details.
+/// + `occurred_at_display`: `Option<String>` - Time of the event formatted
+///   for the locale and timezone of the caller, when localization is enabled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub event: String,
+    pub actor_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub details: Json,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occurred_at_display: Option<String>,
+}
+
+/// ## List audit events query struct.
+///
+/// ## Fields
+/// + `page`: `Option<u32>` - Page number, starting at 1.
+/// + `per_page`: `Option<u32>` - Events per page, at most `MAX_PER_PAGE`.
+/// + `cursor`: `Option<String>` - `next_cursor` of the previous page, see `PageQuery`.
+/// + `sort`: `Option<String>` - `-occurred_at` (default), `occurred_at`,
+///   `event` or `-event`.
+/// + `user_id`: `Option<Uuid>` - User the events are about.
+/// + `event`: `Option<String>` - Name of the event, e.g. `login.failed`.
+/// + `from`: `Option<DateTime<Utc>>` - Earliest time, inclusive.
+/// + `to`: `Option<DateTime<Utc>>` - Latest time, exclusive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListAuditEventsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// ## Page of audit events.
+///
+/// Events are not counted, `total` is never set.
+pub type AuditPage = Paginated<AuditRecord>;
@@ -0,0 +1,23 @@
+//! Claim types module.
+
+// Imports from external crates
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// ## Mapped claims struct.
+///
+/// Local view of the claims sent by an identity
+/// provider, see `core::auth::claims`.
+///
+/// ## Fields
+/// + `email`: `Option<String>` - Email address.
+/// + `display_name`: `Option<String>` - Name shown in the UI.
+/// + `picture`: `Option<String>` - URL of the profile picture.
+/// + `roles`: `Vec<String>` - Local roles, sorted and without duplicates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MappedClaims {
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub picture: Option<String>,
+    pub roles: Vec<String>,
+}
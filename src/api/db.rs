@@ -0,0 +1,25 @@
+//! Database types module.
+
+// Imports from external crates
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// ## Statement statistics struct.
+///
+/// ## Fields
+/// + `statement`: `String` - Name of the statement, its command and
+///   table, e.g. `select users`.
+/// + `count`: `u64` - Executions since the start or the last reset.
+/// + `slow`: `u64` - Executions above `database.slow_query_threshold_ms`.
+/// + `total_ms`: `f64` - Milliseconds spent in every execution.
+/// + `mean_ms`: `f64` - Milliseconds of an execution on average.
+/// + `max_ms`: `f64` - Milliseconds of the slowest execution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct StatementStats {
+    pub statement: String,
+    pub count: u64,
+    pub slow: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
@@ -0,0 +1,81 @@
+//! Error envelope module.
+//!
+//! Every failed request is answered with `ErrorBody`, or
+//! with `ProblemDetails` when RFC 7807 problem details
+//! were asked for.
+
+// Imports from external crates
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// ## JSON error body struct.
+///
+/// ## Fields
+/// + `error`: `ErrorDetails` - Details of the error.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::api::error::ErrorBody;
+///
+/// let body: ErrorBody = serde_json::from_str(
+///     r#"{"error":{"kind":"NotFound","code":"not_found","message":"User not found","request_id":null}}"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(body.error.code, "not_found");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: ErrorDetails,
+}
+
+/// ## JSON error details struct.
+///
+/// ## Fields
+/// + `kind`: `String` - Kind of the error.
+/// + `code`: `String` - Stable machine-readable code of the error.
+/// + `message`: `String` - Human readable message.
+/// + `request_id`: `Option<String>` - ID of the failed request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorDetails {
+    pub kind: String,
+    pub code: String,
+    pub message: String,
+    pub request_id: Option<String>,
+}
+
+/// ## RFC 7807 problem details struct.
+///
+/// Sent as `application/problem+json`, `code` and
+/// `request_id` are extension members.
+///
+/// ## Fields
+/// + `problem_type`: `String` - URI of the error kind, `type` on the wire.
+/// + `title`: `String` - Summary of the error kind.
+/// + `status`: `u16` - HTTP status of the response.
+/// + `detail`: `String` - Human readable message.
+/// + `code`: `String` - Stable machine-readable code of the error.
+/// + `request_id`: `Option<String>` - ID of the failed request.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::api::error::ProblemDetails;
+///
+/// let problem: ProblemDetails = serde_json::from_str(
+///     r#"{"type":"urn:axum-auth:error:not_found","title":"Resource not found","status":404,
+///         "detail":"User not found","code":"not_found","request_id":null}"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(problem.status, 404);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: String,
+    pub request_id: Option<String>,
+}
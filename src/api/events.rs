@@ -0,0 +1,47 @@
+//! Event message types module.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// Local imports
+use super::webhooks::WebhookPayload;
+
+/// Version of the event message schema, raised on breaking changes.
+pub const EVENT_MESSAGE_VERSION: u32 = 1;
+
+/// ## Event message struct.
+///
+/// Body of every message published to the broker. The
+/// `id` is the one of the webhook deliveries of the
+/// event, messages published again carry the same `id`.
+///
+/// ## Fields
+/// + `version`: `u32` - Version of the schema, `EVENT_MESSAGE_VERSION`.
+/// + `id`: `Uuid` - ID of the event.
+/// + `event`: `String` - Name of the event, e.g. `user.created`.
+/// + `occurred_at`: `DateTime<Utc>` - Time of the event.
+/// + `data`: `serde_json::Value` - Event specific data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EventMessage {
+    pub version: u32,
+    pub id: Uuid,
+    pub event: String,
+    pub occurred_at: DateTime<Utc>,
+    pub data: Json,
+}
+
+impl From<WebhookPayload> for EventMessage {
+    fn from(payload: WebhookPayload) -> Self {
+        EventMessage {
+            version: EVENT_MESSAGE_VERSION,
+            id: payload.id,
+            event: payload.event,
+            occurred_at: payload.occurred_at,
+            data: payload.data,
+        }
+    }
+}
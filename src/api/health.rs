@@ -0,0 +1,48 @@
+//! Health report types module.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// ## Health status enum.
+///
+/// # Variants
+/// - `Up` - Check passed.
+/// - `Down` - Check failed or timed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+/// ## Result of a single check struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the check.
+/// + `status`: `HealthStatus` - Result of the check.
+/// + `critical`: `bool` - Failing check marks the service as down.
+/// + `latency_ms`: `u64` - Time the check took.
+/// + `error`: `Option<String>` - Reason of the failure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: HealthStatus,
+    pub critical: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// ## Health report struct.
+///
+/// ## Fields
+/// + `status`: `HealthStatus` - `Down` if any critical check failed.
+/// + `checked_at`: `DateTime<Utc>` - Time the checks ran.
+/// + `checks`: `Vec<CheckResult>` - Results, database first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checked_at: DateTime<Utc>,
+    pub checks: Vec<CheckResult>,
+}
@@ -0,0 +1,38 @@
+//! Mail types module.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// ## Rendered email struct.
+///
+/// ## Fields
+/// + `id`: `Uuid` - ID of the email.
+/// + `event`: `String` - Webhook event the email was rendered from,
+///   e.g. `magic_link.requested`.
+/// + `to`: `String` - Address of the recipient.
+/// + `subject`: `String` - Subject line.
+/// + `body`: `String` - Plain text body.
+/// + `sent_at`: `DateTime<Utc>` - Time the email was sent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Email {
+    pub id: Uuid,
+    pub event: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// ## Mailbox query struct.
+///
+/// ## Fields
+/// + `to`: `Option<String>` - Recipient of the emails, ignoring case.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MailboxQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
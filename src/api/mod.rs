@@ -0,0 +1,35 @@
+//! API types module.
+//!
+//! Module contains the request and response bodies of
+//! the HTTP API, together with their serde settings.
+//! Types only depend on `serde`, `chrono`, `uuid` and
+//! `utoipa`, never on the server, database or runtime
+//! crates, so clients can use the exact wire types the
+//! server sends.
+//!
+//! Server modules re-export the types they use, e.g.
+//! `core::users::User` is `api::users::User`.
+
+// References to submodules
+pub mod audit;
+pub mod claims;
+pub mod db;
+pub mod error;
+pub mod events;
+pub mod health;
+pub mod mail;
+pub mod oauth;
+pub mod pagination;
+pub mod roles;
+pub mod scim;
+pub mod sessions;
+pub mod tokens;
+pub mod users;
+pub mod version;
+pub mod webhooks;
+
+/// Default number of items per page.
+pub const DEFAULT_PER_PAGE: u32 = 50;
+
+/// Maximum number of items per page.
+pub const MAX_PER_PAGE: u32 = 200;
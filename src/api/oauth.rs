@@ -0,0 +1,240 @@
+//! OAuth types module.
+//!
+//! Bodies of the client credentials grant (RFC 6749),
+//! of the device authorization grant (RFC 8628) and of
+//! token introspection (RFC 7662). Requests are form
+//! encoded, responses are JSON.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// ## Token request struct.
+///
+/// Client credentials are sent with HTTP Basic
+/// authentication or, as a fallback, in the body.
+///
+/// ## Fields
+/// + `grant_type`: `String` - `client_credentials` or
+///   `urn:ietf:params:oauth:grant-type:device_code`.
+/// + `scope`: `Option<String>` - Space separated scopes, every allowed
+///   scope of the client when not set.
+/// + `client_id`: `Option<String>` - ID of the client.
+/// + `client_secret`: `Option<String>` - Secret of the client.
+/// + `device_code`: `Option<String>` - Device code of the device grant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub device_code: Option<String>,
+}
+
+/// ## Token response struct.
+///
+/// ## Fields
+/// + `access_token`: `String` - Access token of the client.
+/// + `token_type`: `String` - Always `Bearer`.
+/// + `expires_in`: `u64` - Seconds until the access token expires.
+/// + `scope`: `String` - Space separated granted scopes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub scope: String,
+}
+
+/// ## Device authorization request struct.
+///
+/// Devices that can not keep a secret only send
+/// their `client_id`.
+///
+/// ## Fields
+/// + `client_id`: `Option<String>` - ID of the client, unless sent
+///   with HTTP Basic authentication.
+/// + `client_secret`: `Option<String>` - Secret of the client.
+/// + `scope`: `Option<String>` - Space separated scopes, every allowed
+///   scope of the client when not set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceAuthorizationRequest {
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// ## Device authorization response struct.
+///
+/// ## Fields
+/// + `device_code`: `String` - Code the device polls the token endpoint with.
+/// + `user_code`: `String` - Code the user enters, e.g. `BCDF-GHJK`.
+/// + `verification_uri`: `String` - Page the user enters the code on.
+/// + `verification_uri_complete`: `String` - Page with the code filled in.
+/// + `expires_in`: `u64` - Seconds until both codes expire.
+/// + `interval`: `u64` - Seconds the device waits between polls.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: u64,
+    pub interval: u64,
+}
+
+/// ## Device verification query struct.
+///
+/// ## Fields
+/// + `user_code`: `String` - Code shown by the device, case and
+///   dashes are ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeviceVerificationQuery {
+    pub user_code: String,
+}
+
+/// ## Pending device authorization struct.
+///
+/// Shown to the user before they approve, so they can
+/// tell which client asks for which scopes.
+///
+/// ## Fields
+/// + `user_code`: `String` - Code shown by the device.
+/// + `client_id`: `String` - ID of the client.
+/// + `client_name`: `String` - Display name of the client.
+/// + `scopes`: `Vec<String>` - Scopes the device asks for.
+/// + `expires_at`: `DateTime<Utc>` - Time the code expires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceVerification {
+    pub user_code: String,
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// ## Device verification request struct.
+///
+/// ## Fields
+/// + `user_code`: `String` - Code shown by the device.
+/// + `approve`: `bool` - Approve or deny the device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceVerificationRequest {
+    pub user_code: String,
+    pub approve: bool,
+}
+
+/// ## Introspection request struct.
+///
+/// ## Fields
+/// + `token`: `String` - Access token to inspect.
+/// + `token_type_hint`: `Option<String>` - Ignored, only access
+///   tokens are issued.
+/// + `client_id`: `Option<String>` - ID of the resource server.
+/// + `client_secret`: `Option<String>` - Secret of the resource server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    #[serde(default)]
+    pub token_type_hint: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+}
+
+/// ## Revocation request struct.
+///
+/// ## Fields
+/// + `token`: `String` - Token to revoke.
+/// + `token_type_hint`: `Option<String>` - Ignored, only access
+///   tokens are issued.
+/// + `client_id`: `Option<String>` - ID of the client.
+/// + `client_secret`: `Option<String>` - Secret of the client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RevocationRequest {
+    pub token: String,
+    #[serde(default)]
+    pub token_type_hint: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+}
+
+/// ## Introspection response struct.
+///
+/// Inactive tokens only have `active` set, nothing
+/// about them is disclosed.
+///
+/// ## Fields
+/// + `active`: `bool` - If the token is valid and not revoked.
+/// + `scope`: `Option<String>` - Space separated granted scopes.
+/// + `client_id`: `Option<String>` - Client the token was issued to.
+/// + `sub`: `Option<String>` - User or client the token was issued to.
+/// + `token_type`: `Option<String>` - Always `Bearer`.
+/// + `exp`: `Option<i64>` - Expiry as Unix timestamp.
+/// + `iat`: `Option<i64>` - Issue time as Unix timestamp.
+/// + `iss`: `Option<String>` - Issuer of the token.
+/// + `aud`: `Option<String>` - Audience of the token.
+/// + `sid`: `Option<String>` - Login session of user tokens.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+/// ## OAuth error struct.
+///
+/// Errors of the OAuth endpoints use the body of
+/// RFC 6749 instead of `ErrorBody`, so standard
+/// OAuth clients understand them.
+///
+/// ## Fields
+/// + `error`: `String` - Error code, e.g. `invalid_client`.
+/// + `error_description`: `Option<String>` - Human readable details.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct OAuthError {
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if inactive tokens disclose nothing but `active`.
+    #[test]
+    fn test_inactive_introspection() {
+        let body = serde_json::to_string(&IntrospectionResponse::default()).unwrap();
+
+        assert_eq!(body, r#"{"active":false}"#);
+    }
+}
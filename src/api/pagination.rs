@@ -0,0 +1,88 @@
+//! Pagination types module.
+
+// Imports from external crates
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// ## Paging and sorting query struct.
+///
+/// Lists are paged by `page` or, when `cursor` is sent,
+/// by keyset. Send an empty `cursor` for the first page
+/// and `next_cursor` of the response for the following
+/// ones. `page` and `cursor` can not be combined.
+///
+/// ## Fields
+/// + `page`: `Option<u32>` - Page number, starting at 1.
+/// + `per_page`: `Option<u32>` - Items per page, at most `MAX_PER_PAGE`.
+/// + `cursor`: `Option<String>` - `next_cursor` of the previous page.
+/// + `sort`: `Option<String>` - Sort field, prefixed with `-` for
+///   descending order, e.g. `-created_at`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
+/// ## Paginated response struct.
+///
+/// Envelope of every list endpoint.
+///
+/// ## Fields
+/// + `items`: `Vec<T>` - Items of the page.
+/// + `page`: `Option<u32>` - Page number, not set when paging by cursor.
+/// + `per_page`: `u32` - Items per page.
+/// + `total`: `Option<i64>` - Number of items matching the filter,
+///   when the endpoint counts them.
+/// + `total_display`: `Option<String>` - `total` formatted for the
+///   locale of the caller, when localization is enabled.
+/// + `next_cursor`: `Option<String>` - Cursor of the next page, not set
+///   on the last page or when paging by page number.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    pub per_page: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if fields of the other paging mode are left out.
+    #[test]
+    fn test_paginated_skips_unset() {
+        let page = Paginated {
+            items: vec![1, 2],
+            page: None,
+            per_page: 2,
+            total: None,
+            total_display: None,
+            next_cursor: Some("abc".to_string()),
+        };
+
+        let json = serde_json::to_value(&page).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({ "items": [1, 2], "per_page": 2, "next_cursor": "abc" })
+        );
+        assert_eq!(
+            serde_json::from_value::<Paginated<i32>>(json).unwrap(),
+            page
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Role types module.
+//!
+//! Bodies of the role administration routes. Roles are
+//! defined per tenant with a list of permission names,
+//! e.g. `users.manage`, and carry a version that every
+//! update has to send back, so concurrent edits of the
+//! same definition are rejected instead of overwritten.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// ## Role definition struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the role, unique within the tenant.
+/// + `description`: `Option<String>` - What the role is meant for.
+/// + `permissions`: `Vec<String>` - Names of the granted permissions.
+/// + `version`: `i32` - Version of the definition, increased by every update.
+/// + `created_at`: `DateTime<Utc>` - Time the role was defined.
+/// + `updated_at`: `DateTime<Utc>` - Time the role was last updated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Role {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub version: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// ## Create role request struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the new role.
+/// + `description`: `Option<String>` - What the role is meant for.
+/// + `permissions`: `Vec<String>` - Names of the granted permissions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+/// ## Update role request struct.
+///
+/// Definition is replaced as a whole.
+///
+/// ## Fields
+/// + `description`: `Option<String>` - What the role is meant for.
+/// + `permissions`: `Vec<String>` - Names of the granted permissions.
+/// + `version`: `i32` - Version of the definition the update is based on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UpdateRoleRequest {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    pub version: i32,
+}
+
+/// ## Assign role request struct.
+///
+/// ## Fields
+/// + `role`: `String` - Name of the role to grant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AssignRoleRequest {
+    pub role: String,
+}
+
+/// ## Role of a user struct.
+///
+/// ## Fields
+/// + `role`: `String` - Name of the granted role.
+/// + `source`: `String` - Who manages the grant, `local`, `directory`
+///   or `scim`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UserRole {
+    pub role: String,
+    pub source: String,
+}
@@ -0,0 +1,260 @@
+//! SCIM types module.
+//!
+//! Resources and messages of the SCIM 2.0 provisioning
+//! API (RFC 7643, RFC 7644). Attributes are camelCase on
+//! the wire, every resource and message names its schema
+//! in `schemas`.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Schema of the user resource.
+pub const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+
+/// Schema of the group resource.
+pub const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+
+/// Schema of the list response.
+pub const LIST_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+
+/// Schema of the patch request.
+pub const PATCH_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+
+/// Schema of the error response.
+pub const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// ## Resource metadata struct.
+///
+/// ## Fields
+/// + `resource_type`: `String` - `User` or `Group`.
+/// + `created`: `DateTime<Utc>` - Time the resource was created.
+/// + `last_modified`: `DateTime<Utc>` - Time the resource was last changed.
+/// + `location`: `String` - URI of the resource, relative to the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMeta {
+    pub resource_type: String,
+    pub created: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+    pub location: String,
+}
+
+/// ## Email address of a user struct.
+///
+/// ## Fields
+/// + `value`: `String` - Email address.
+/// + `primary`: `bool` - Address the user logs in with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ScimEmail {
+    pub value: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// ## SCIM user struct.
+///
+/// Users are provisioned without a password, they log
+/// in through magic links or an external provider.
+/// `externalId` is accepted but not stored.
+///
+/// ## Fields
+/// + `schemas`: `Vec<String>` - Always `USER_SCHEMA`.
+/// + `id`: `Option<Uuid>` - ID of the user, set by the server.
+/// + `user_name`: `String` - Email address the user logs in with.
+/// + `display_name`: `Option<String>` - Name shown instead of the email.
+/// + `active`: `bool` - Inactive users are disabled.
+/// + `emails`: `Vec<ScimEmail>` - Email addresses, the login address only.
+/// + `meta`: `Option<ScimMeta>` - Metadata, set by the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub user_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default = "active")]
+    pub active: bool,
+    #[serde(default)]
+    pub emails: Vec<ScimEmail>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+/// ## Returns `true`, users are active unless told otherwise.
+fn active() -> bool {
+    true
+}
+
+/// ## Member of a group struct.
+///
+/// ## Fields
+/// + `value`: `Uuid` - ID of the user.
+/// + `display`: `Option<String>` - Email address of the user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ScimMember {
+    pub value: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+/// ## SCIM group struct.
+///
+/// Groups are the roles of the tenant, the ID of a
+/// group is the name of its role.
+///
+/// ## Fields
+/// + `schemas`: `Vec<String>` - Always `GROUP_SCHEMA`.
+/// + `id`: `Option<String>` - Name of the role, set by the server.
+/// + `display_name`: `String` - Name of the group in the provider.
+/// + `members`: `Vec<ScimMember>` - Users granted the role.
+/// + `meta`: `Option<ScimMeta>` - Metadata, set by the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimGroup {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub display_name: String,
+    #[serde(default)]
+    pub members: Vec<ScimMember>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+/// ## List query struct.
+///
+/// ## Fields
+/// + `filter`: `Option<String>` - Equality filter, e.g.
+///   `userName eq "ada@example.com"`.
+/// + `start_index`: `Option<u64>` - 1-based index of the first resource.
+/// + `count`: `Option<u32>` - Resources per page, at most `MAX_PER_PAGE`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct ScimListQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+/// ## List response struct.
+///
+/// ## Fields
+/// + `schemas`: `Vec<String>` - Always `LIST_SCHEMA`.
+/// + `total_results`: `u64` - Number of resources matching the filter.
+/// + `start_index`: `u64` - 1-based index of the first returned resource.
+/// + `items_per_page`: `u64` - Number of returned resources.
+/// + `resources`: `Vec<T>` - Resources of the page, `Resources` on the wire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponse<T> {
+    pub schemas: Vec<String>,
+    pub total_results: u64,
+    pub start_index: u64,
+    pub items_per_page: u64,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<T>,
+}
+
+/// ## Patch operation struct.
+///
+/// ## Fields
+/// + `op`: `String` - `add`, `replace` or `remove`, in any case.
+/// + `path`: `Option<String>` - Attribute to change, the attributes of
+///   `value` when not set.
+/// + `value`: `Option<serde_json::Value>` - New value of the attribute.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ScimPatchOperation {
+    pub op: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub value: Option<Json>,
+}
+
+/// ## Patch request struct.
+///
+/// ## Fields
+/// + `schemas`: `Vec<String>` - Always `PATCH_SCHEMA`.
+/// + `operations`: `Vec<ScimPatchOperation>` - Changes, applied in order,
+///   `Operations` on the wire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ScimPatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+/// ## SCIM error struct.
+///
+/// ## Fields
+/// + `schemas`: `Vec<String>` - Always `ERROR_SCHEMA`.
+/// + `status`: `String` - HTTP status code as a string.
+/// + `scim_type`: `Option<String>` - SCIM error type, e.g. `uniqueness`.
+/// + `detail`: `String` - Human-readable description.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimError {
+    pub schemas: Vec<String>,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scim_type: Option<String>,
+    pub detail: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if a user sent by an identity provider is read with its defaults.
+    #[test]
+    fn test_scim_user_defaults() {
+        let user: ScimUser = serde_json::from_str(
+            r#"{
+                "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+                "externalId": "00u1",
+                "userName": "ada@example.com"
+            }"#,
+        )
+        .unwrap();
+
+        assert!(user.active);
+        assert_eq!(user.user_name, "ada@example.com");
+        assert!(user.emails.is_empty());
+    }
+
+    // Test checks if lists and patches use the capitalized attribute names of RFC 7644.
+    #[test]
+    fn test_scim_message_names() {
+        let list = ScimListResponse::<ScimGroup> {
+            schemas: vec![LIST_SCHEMA.to_string()],
+            total_results: 0,
+            start_index: 1,
+            items_per_page: 0,
+            resources: Vec::new(),
+        };
+        let json = serde_json::to_value(&list).unwrap();
+
+        assert_eq!(json["totalResults"], 0);
+        assert!(json["Resources"].is_array());
+
+        let patch: ScimPatchRequest = serde_json::from_str(
+            r#"{"Operations": [{"op": "Replace", "path": "active", "value": false}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(patch.operations[0].value, Some(Json::Bool(false)));
+    }
+}
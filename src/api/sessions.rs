@@ -0,0 +1,119 @@
+//! Session types module.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// ## Login session struct.
+///
+/// ## Fields
+/// + `id`: `Uuid` - ID of the session.
+/// + `user_id`: `Uuid` - User the session belongs to.
+/// + `ip`: `Option<String>` - Client address of the login.
+/// + `user_agent`: `Option<String>` - User agent of the login.
+/// + `created_at`: `DateTime<Utc>` - Time of the login.
+/// + `last_seen_at`: `DateTime<Utc>` - Time the session was last used.
+/// + `expires_at`: `DateTime<Utc>` - Time the session expires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// ## Known login device struct.
+///
+/// Device is told apart by the fingerprint of its user
+/// agent, its address and countries are updated by every
+/// login from it.
+///
+/// ## Fields
+/// + `id`: `Uuid` - ID of the device.
+/// + `user_id`: `Uuid` - User that logged in from the device.
+/// + `fingerprint`: `String` - SHA-256 hash of the user agent.
+/// + `user_agent`: `Option<String>` - User agent of the device.
+/// + `ip`: `Option<String>` - Client address of the last login.
+/// + `countries`: `Vec<String>` - Countries the device logged in from.
+/// + `first_seen_at`: `DateTime<Utc>` - Time of the first login.
+/// + `last_seen_at`: `DateTime<Utc>` - Time of the last login.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Device {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub fingerprint: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub countries: Vec<String>,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+/// ## CSRF token struct.
+///
+/// ## Fields
+/// + `token`: `String` - Token to echo in the CSRF header of
+///   state-changing requests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CsrfToken {
+    pub token: String,
+}
+
+/// ## Magic link request struct.
+///
+/// ## Fields
+/// + `email`: `String` - Address the login link is sent to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+/// ## Magic link verification struct.
+///
+/// ## Fields
+/// + `token`: `String` - Token of the login link.
+/// + `captcha_token`: `Option<String>` - Token of the solved CAPTCHA,
+///   required after repeated failures of the client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VerifyMagicLinkRequest {
+    pub token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captcha_token: Option<String>,
+}
+
+/// ## Login response struct.
+///
+/// ## Fields
+/// + `access_token`: `String` - Access token of the new session.
+/// + `token_type`: `String` - Always `Bearer`.
+/// + `expires_in`: `u64` - Seconds until the access token expires.
+/// + `session_id`: `Uuid` - ID of the new session.
+/// + `refresh_token`: `Option<String>` - Single-use token exchanged for
+///   the next access token at `/auth/refresh`, only in the `jwt` auth mode.
+/// + `password_change_required`: `bool` - Password has expired, the access
+///   token only allows `/me` and `/me/password` until it is changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LoginResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub session_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub password_change_required: bool,
+}
+
+/// ## Refresh request struct.
+///
+/// ## Fields
+/// + `refresh_token`: `String` - Refresh token of the last login or refresh.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
@@ -0,0 +1,107 @@
+//! Token types module.
+
+// Imports from external crates
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// ## JSON Web Key struct.
+///
+/// Public Ed25519 key of the server, see RFC 8037.
+///
+/// ## Fields
+/// + `kty`: `String` - Key type, always `OKP`.
+/// + `crv`: `String` - Curve, always `Ed25519`.
+/// + `kid`: `String` - ID of the key, sent in the token header.
+/// + `alg`: `String` - Algorithm of the signatures, always `EdDSA`.
+/// + `key_use`: `String` - `use` of the key, always `sig`.
+/// + `x`: `String` - Base64url encoded public key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Jwk {
+    pub kty: String,
+    pub crv: String,
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    pub x: String,
+}
+
+/// ## JSON Web Key Set struct.
+///
+/// Body of `/.well-known/jwks.json`.
+///
+/// ## Fields
+/// + `keys`: `Vec<Jwk>` - Keys access tokens may be signed with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+impl JwkSet {
+    /// ## Finds the key with the ID.
+    pub fn find(&self, kid: &str) -> Option<&Jwk> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+}
+
+/// ## Access token claims struct.
+///
+/// ## Fields
+/// + `iss`: `String` - Server that issued the token.
+/// + `sub`: `String` - User or client the token was issued to.
+/// + `aud`: `String` - Service the token is meant for.
+/// + `iat`: `i64` - Unix time the token was issued.
+/// + `exp`: `i64` - Unix time the token expires.
+/// + `scope`: `String` - Granted scopes, separated by spaces.
+/// + `sid`: `Option<String>` - Login session the token belongs to,
+///   revoking the session revokes the token on the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AccessClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    #[serde(default)]
+    pub scope: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+impl AccessClaims {
+    /// ## Returns the granted scopes.
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scope.split_whitespace()
+    }
+
+    /// ## Checks if the scope was granted.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes().any(|granted| granted == scope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if scopes are split on whitespace and matched exactly.
+    #[test]
+    fn test_has_scope() {
+        let claims = AccessClaims {
+            iss: "https://auth.example.com".to_string(),
+            sub: "user".to_string(),
+            aud: "notes".to_string(),
+            iat: 0,
+            exp: 0,
+            scope: "notes:read  notes:write".to_string(),
+            sid: None,
+        };
+
+        assert_eq!(
+            claims.scopes().collect::<Vec<_>>(),
+            ["notes:read", "notes:write"]
+        );
+        assert!(claims.has_scope("notes:write"));
+        assert!(!claims.has_scope("notes"));
+    }
+}
@@ -0,0 +1,291 @@
+//! User types module.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+// Local imports
+use super::audit::AuditRecord;
+use super::pagination::Paginated;
+use super::sessions::{Device, Session};
+
+/// ## User struct.
+///
+/// Password hash is never part of this struct,
+/// so users can be serialized into responses as is.
+///
+/// ## Fields
+/// + `id`: `Uuid` - ID of the user.
+/// + `email`: `String` - Unique email address.
+/// + `display_name`: `Option<String>` - Name shown in the UI.
+/// + `org_id`: `Option<Uuid>` - Organization of the user.
+/// + `disabled`: `bool` - Disabled users can not log in.
+/// + `password_reset_required`: `bool` - Password has to be
+///   changed on the next login.
+/// + `created_at`: `DateTime<Utc>` - Creation time.
+/// + `updated_at`: `DateTime<Utc>` - Time of the last change.
+/// + `created_at_display`: `Option<String>` - Creation time formatted
+///   for the locale and timezone of the caller, when localization is enabled.
+/// + `updated_at_display`: `Option<String>` - Time of the last change,
+///   formatted like `created_at_display`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct User {
+    pub id: Uuid,
+    pub email: String,
+    pub display_name: Option<String>,
+    pub org_id: Option<Uuid>,
+    pub disabled: bool,
+    pub password_reset_required: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at_display: Option<String>,
+}
+
+/// ## List users query struct.
+///
+/// ## Fields
+/// + `page`: `Option<u32>` - Page number, starting at 1.
+/// + `per_page`: `Option<u32>` - Users per page, at most `MAX_PER_PAGE`.
+/// + `cursor`: `Option<String>` - `next_cursor` of the previous page, see `PageQuery`.
+/// + `sort`: `Option<String>` - `created_at` (default), `updated_at` or
+///   `email`, prefixed with `-` for descending order.
+/// + `email`: `Option<String>` - Case-insensitive part of the email.
+/// + `org_id`: `Option<Uuid>` - Organization of the users.
+/// + `disabled`: `Option<bool>` - Disabled state of the users.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListUsersQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+}
+
+/// ## Page of users.
+///
+/// `total` is always set, users are counted on every page.
+pub type UserPage = Paginated<User>;
+
+/// ## Email change request struct.
+///
+/// ## Fields
+/// + `email`: `String` - New email address, confirmed with the token
+///   sent to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChangeEmailRequest {
+    pub email: String,
+}
+
+/// ## Email change confirmation struct.
+///
+/// ## Fields
+/// + `token`: `String` - Token sent to the new email address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ConfirmEmailRequest {
+    pub token: String,
+}
+
+/// ## Email change undo struct.
+///
+/// ## Fields
+/// + `token`: `String` - Token sent to the previous email address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UndoEmailRequest {
+    pub token: String,
+}
+
+/// ## Account deletion cancellation struct.
+///
+/// ## Fields
+/// + `token`: `String` - Token sent to the email address of the account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CancelDeletionRequest {
+    pub token: String,
+}
+
+/// ## Scheduled account deletion struct.
+///
+/// ## Fields
+/// + `delete_after`: `DateTime<Utc>` - Time the account is removed
+///   unless the deletion is cancelled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AccountDeletion {
+    pub delete_after: DateTime<Utc>,
+}
+
+/// ## Password change request struct.
+///
+/// ## Fields
+/// + `current_password`: `String` - Password the user logs in with.
+/// + `new_password`: `String` - Password replacing it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// ## Expiring credential kind enum.
+///
+/// # Variants
+/// - `Password` - `password`, password the user logs in with.
+/// - `ApiKey` - `api_key`, API key issued to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    Password,
+    ApiKey,
+}
+
+/// ## Credential expiry warning struct.
+///
+/// ## Fields
+/// + `credential`: `CredentialKind` - Kind of the credential.
+/// + `id`: `Option<Uuid>` - ID of the API key, none for the password.
+/// + `expires_at`: `DateTime<Utc>` - Time the credential expires.
+/// + `expired`: `bool` - Credential already expired and has to be replaced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CredentialWarning {
+    pub credential: CredentialKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub expired: bool,
+}
+
+/// ## Profile of the caller struct.
+///
+/// ## Fields
+/// + `user`: `User` - User, its fields are part of the profile.
+/// + `credential_warnings`: `Vec<CredentialWarning>` - Passwords and API
+///   keys that expire soon or expired, soonest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Profile {
+    #[serde(flatten)]
+    pub user: User,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub credential_warnings: Vec<CredentialWarning>,
+}
+
+/// ## Personal data export struct.
+///
+/// Archive of everything stored about a user, sent by
+/// `/me/export`.
+///
+/// ## Fields
+/// + `exported_at`: `DateTime<Utc>` - Time of the export.
+/// + `user`: `User` - Profile of the user.
+/// + `roles`: `Vec<String>` - Roles granted to the user.
+/// + `sessions`: `Vec<Session>` - Active login sessions.
+/// + `devices`: `Vec<Device>` - Devices the user logged in from.
+/// + `audit_events`: `Vec<AuditRecord>` - Events the user performed
+///   or is about, oldest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UserExport {
+    pub exported_at: DateTime<Utc>,
+    pub user: User,
+    pub roles: Vec<String>,
+    pub sessions: Vec<Session>,
+    pub devices: Vec<Device>,
+    pub audit_events: Vec<AuditRecord>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if a page sent by the server is read back unchanged.
+    #[test]
+    fn test_user_page_round_trip() {
+        let page = UserPage {
+            items: vec![User {
+                id: Uuid::new_v4(),
+                email: "ada@example.com".to_string(),
+                display_name: None,
+                org_id: Some(Uuid::new_v4()),
+                disabled: false,
+                password_reset_required: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                created_at_display: None,
+                updated_at_display: Some("1/1/2026, 9:00:00 AM UTC".to_string()),
+            }],
+            page: Some(1),
+            per_page: 50,
+            total: Some(1),
+            total_display: None,
+            next_cursor: None,
+        };
+
+        let json = serde_json::to_string(&page).unwrap();
+
+        assert_eq!(serde_json::from_str::<UserPage>(&json).unwrap(), page);
+    }
+
+    // Test checks if an export is read back unchanged.
+    #[test]
+    fn test_user_export_round_trip() {
+        let user_id = Uuid::new_v4();
+        let export = UserExport {
+            exported_at: Utc::now(),
+            user: User {
+                id: user_id,
+                email: "ada@example.com".to_string(),
+                display_name: Some("Ada".to_string()),
+                org_id: None,
+                disabled: false,
+                password_reset_required: false,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                created_at_display: None,
+                updated_at_display: None,
+            },
+            roles: vec!["viewer".to_string()],
+            sessions: Vec::new(),
+            devices: Vec::new(),
+            audit_events: vec![AuditRecord {
+                id: 1,
+                occurred_at: Utc::now(),
+                event: "login.succeeded".to_string(),
+                actor_id: Some(user_id),
+                user_id: Some(user_id),
+                ip: None,
+                details: serde_json::json!({}),
+                occurred_at_display: None,
+            }],
+        };
+
+        let json = serde_json::to_string(&export).unwrap();
+
+        assert_eq!(serde_json::from_str::<UserExport>(&json).unwrap(), export);
+    }
+
+    // Test checks if only the filters that are set are sent.
+    #[test]
+    fn test_list_users_query_skips_unset() {
+        let query = ListUsersQuery {
+            page: Some(2),
+            disabled: Some(false),
+            ..ListUsersQuery::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&query).unwrap(),
+            serde_json::json!({ "page": 2, "disabled": false })
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! Version types module.
+
+// Imports from external crates
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// ## Version information struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the crate.
+/// + `version`: `String` - Version of the crate.
+/// + `tls`: `TlsInfo` - TLS policy the server accepts connections with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VersionInfo {
+    pub name: String,
+    pub version: String,
+    pub tls: TlsInfo,
+}
+
+/// ## Active TLS policy struct.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Server is served over HTTPS.
+/// + `policy`: `Option<String>` - Preset, `modern` or `intermediate`.
+/// + `min_version`: `Option<String>` - Lowest protocol version accepted.
+/// + `cipher_suites`: `Vec<String>` - Cipher suites offered, strongest first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TlsInfo {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cipher_suites: Vec<String>,
+}
@@ -0,0 +1,150 @@
+//! Webhook types module.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## Webhook payload struct.
+///
+/// Body of every webhook request. Retries of an event
+/// carry the same `id`, so receivers can drop duplicates.
+///
+/// ## Fields
+/// + `id`: `Uuid` - ID of the event.
+/// + `event`: `String` - Name of the event, e.g. `user.deleted`.
+/// + `occurred_at`: `DateTime<Utc>` - Time of the event.
+/// + `data`: `serde_json::Value` - Event specific data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WebhookPayload {
+    pub id: Uuid,
+    pub event: String,
+    pub occurred_at: DateTime<Utc>,
+    pub data: Json,
+}
+
+/// ## Webhook event enum.
+///
+/// # Variants
+/// - `UserCreated` - `user.created`, user account was created.
+/// - `UserDisabled` - `user.disabled`, user account was disabled.
+/// - `UserDeleted` - `user.deleted`, user account was deleted.
+/// - `PasswordChanged` - `password.changed`, user changed the password.
+/// - `LoginSucceeded` - `login.succeeded`, user logged in.
+/// - `LoginFailed` - `login.failed`, login was rejected.
+/// - `LoginAnomaly` - `login.anomaly`, user logged in from a new device
+///   or country, the data carries what to tell the address.
+/// - `EmailChangeRequested` - `email.change_requested`, user asked to
+///   change the email, the data carries the confirmation token for the
+///   new address.
+/// - `EmailChangeWarning` - `email.change_warning`, user asked to change
+///   the email, the data carries the undo token for the current address.
+/// - `MagicLinkRequested` - `magic_link.requested`, user asked for a
+///   login link, the data carries the link to send to the address.
+/// - `AccountDeletionScheduled` - `account.deletion_scheduled`, user asked
+///   to delete the account, the data carries the cancellation token for
+///   the address.
+/// - `CredentialExpiring` - `credential.expiring`, password or API key of
+///   the user expires soon, the data carries the time to tell the address.
+/// - `CredentialExpired` - `credential.expired`, password has to be
+///   changed on the next login or the API key was revoked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    UserCreated,
+    UserDisabled,
+    UserDeleted,
+    PasswordChanged,
+    LoginSucceeded,
+    LoginFailed,
+    LoginAnomaly,
+    EmailChangeRequested,
+    EmailChangeWarning,
+    MagicLinkRequested,
+    AccountDeletionScheduled,
+    CredentialExpiring,
+    CredentialExpired,
+}
+
+impl WebhookEvent {
+    /// Every event.
+    pub const ALL: [WebhookEvent; 13] = [
+        WebhookEvent::UserCreated,
+        WebhookEvent::UserDisabled,
+        WebhookEvent::UserDeleted,
+        WebhookEvent::PasswordChanged,
+        WebhookEvent::LoginSucceeded,
+        WebhookEvent::LoginFailed,
+        WebhookEvent::LoginAnomaly,
+        WebhookEvent::EmailChangeRequested,
+        WebhookEvent::EmailChangeWarning,
+        WebhookEvent::MagicLinkRequested,
+        WebhookEvent::AccountDeletionScheduled,
+        WebhookEvent::CredentialExpiring,
+        WebhookEvent::CredentialExpired,
+    ];
+
+    /// ## Returns the name sent in the payload.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::UserCreated => "user.created",
+            WebhookEvent::UserDisabled => "user.disabled",
+            WebhookEvent::UserDeleted => "user.deleted",
+            WebhookEvent::PasswordChanged => "password.changed",
+            WebhookEvent::LoginSucceeded => "login.succeeded",
+            WebhookEvent::LoginFailed => "login.failed",
+            WebhookEvent::LoginAnomaly => "login.anomaly",
+            WebhookEvent::EmailChangeRequested => "email.change_requested",
+            WebhookEvent::EmailChangeWarning => "email.change_warning",
+            WebhookEvent::MagicLinkRequested => "magic_link.requested",
+            WebhookEvent::AccountDeletionScheduled => "account.deletion_scheduled",
+            WebhookEvent::CredentialExpiring => "credential.expiring",
+            WebhookEvent::CredentialExpired => "credential.expired",
+        }
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        WebhookEvent::ALL
+            .into_iter()
+            .find(|event| event.as_str() == value)
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::Validation,
+                    format!("Unknown webhook event '{}'", value),
+                    None,
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if every event is parsed back from its name.
+    #[test]
+    fn test_webhook_event_round_trip() {
+        for event in WebhookEvent::ALL {
+            assert_eq!(event.as_str().parse::<WebhookEvent>(), Ok(event));
+        }
+
+        let result = "user.renamed".parse::<WebhookEvent>().unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Validation);
+    }
+}
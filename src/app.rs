@@ -0,0 +1,636 @@
+//! Run modes module.
+//!
+//! Module contains the entry points of the binary: the
+//! server, the worker, the migrations and the maintenance
+//! subcommands. Every mode starts with the config and
+//! logging phases of `core::bootstrap` and runs the later
+//! phases it needs.
+
+// Imports from external crates
+use std::sync::Arc;
+#[cfg(feature = "server")]
+use std::time::Duration;
+#[cfg(feature = "server")]
+use tokio::sync::watch;
+
+// Local imports
+use crate::core;
+#[cfg(feature = "server")]
+use crate::core::audit::AuditLog;
+#[cfg(feature = "server")]
+use crate::core::auth::directory::{GroupSync, LdapDirectory};
+#[cfg(feature = "server")]
+use crate::core::auth::expiry::CredentialExpiry;
+#[cfg(feature = "server")]
+use crate::core::auth::keys::{KeyManager, KeyRotation};
+#[cfg(feature = "cli")]
+use crate::core::auth::password::Passwords;
+use crate::core::bootstrap::Bootstrap;
+#[cfg(feature = "cli")]
+use crate::core::cli::{
+    Cli, ClientArgs, Command, ConfigArgs, SeedArgs, TenantArgs, TokenArgs, UsersArgs,
+};
+#[cfg(feature = "cli")]
+use crate::core::config::read_config;
+#[cfg(feature = "server")]
+use crate::core::config::reload;
+#[cfg(feature = "server")]
+use crate::core::config::secrets::MasterKey;
+use crate::core::config::AppConfig;
+use crate::core::config::StartupPhase;
+use crate::core::config::CONFIG_FILE_PATH;
+use crate::core::config::DEFAULT_CONFIG_FILE;
+#[cfg(feature = "cli")]
+use crate::core::db::seed::SeedTarget;
+use crate::core::env::map::EnvMap;
+use crate::core::err::{AppError, ErrorKind};
+#[cfg(any(feature = "nats", feature = "kafka"))]
+use crate::core::events::broker::Broker;
+#[cfg(feature = "server")]
+use crate::core::events::outbox::OutboxRelay;
+use crate::core::logging::LogHandle;
+#[cfg(feature = "server")]
+use crate::core::mail::{providers, templates::MailTemplates, MailCapture};
+#[cfg(feature = "server")]
+use crate::core::repo::{
+    cleanup::{AccountDeletion, TokenCleanup, UserPurge},
+    postgres::{
+        PgAccessTokenRepo, PgClientRepo, PgDeviceCodeRepo, PgRoleDefinitionRepo, PgSigningKeyRepo,
+        PgTenantRepo, PgTokenRepo, PgUserRepo,
+    },
+};
+#[cfg(feature = "server")]
+use crate::core::server::shutdown::ShutdownCoordinator;
+#[cfg(feature = "server")]
+use crate::core::state::AppState;
+#[cfg(feature = "server")]
+use crate::core::webhooks::{WebhookDispatcher, Webhooks};
+#[cfg(feature = "server")]
+use crate::core::worker::Worker;
+
+/// Runs the command given on the command line.
+///
+/// Function starts the HTTP server when no subcommand
+/// is given, otherwise it runs the subcommand. The
+/// configuration profile is passed to every mode that
+/// loads the configuration.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the command runs successfully.
+///   - `AppError`: If the command fails to run.
+#[cfg(feature = "cli")]
+pub async fn run(cli: Cli) -> Result<(), AppError> {
+    let profile = cli.profile.as_deref();
+
+    match cli.command {
+        None | Some(Command::Serve) => run_app(profile).await,
+        Some(Command::Worker) => run_worker(profile).await,
+        Some(Command::Migrate) => run_migrations(profile).await,
+        Some(Command::MigrateConfig(args)) => core::cli::migrate_config(&args),
+        Some(Command::Replay(args)) => core::cli::replay(&args).await,
+        Some(Command::Doctor) => run_doctor(profile).await,
+        Some(Command::Config(args)) => run_config(&args, profile),
+        Some(Command::Env(args)) => core::cli::env(&args),
+        Some(Command::Token(args)) => run_token(&args, profile),
+        Some(Command::Client(args)) => run_client(&args, profile).await,
+        Some(Command::Tenant(args)) => run_tenant(&args, profile).await,
+        Some(Command::Routes) => run_routes(profile),
+        Some(Command::BreachFilter(args)) => core::cli::breach_filter(&args),
+        Some(Command::Users(args)) => run_users(&args, profile).await,
+        Some(Command::Seed(args)) => run_seed(&args, profile).await,
+    }
+}
+
+/// Runs the application.
+///
+/// Function runs every startup phase: it loads and
+/// validates the configuration and environment, opens
+/// the connection pools, waits for the cache, applies the
+/// migrations and builds the router, then starts the HTTP
+/// server. On SIGINT/SIGTERM the server drains in-flight
+/// requests and the connection pool is closed before the
+/// function returns.
+///
+/// # Examples
+/// ```
+/// use axum_auth::run_app;
+///
+/// run_app(None);
+/// ```
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the function runs successfully.
+///   - `AppError`: If the function fails to run.
+#[cfg(feature = "server")]
+pub async fn run_app(profile: Option<&str>) -> Result<(), AppError> {
+    let mut boot = Bootstrap::new();
+    let (app_config, log, env) = bootstrap(&mut boot, profile).await?;
+    let (coordinator, updates) = start_coordinator(&app_config, log.clone(), profile);
+    core::db::stats::enable(app_config.database.query_metrics);
+
+    // Open the connection pools and close them once
+    // the server stopped serving requests
+    let db = boot.db(&app_config, &env).await?;
+    boot.cache(&app_config).await?;
+    boot.migrations(&app_config, db.write()).await?;
+    let checks = db.start_health_checks(Duration::from_secs(
+        app_config.database.replica_check_interval,
+    ));
+
+    // Queued webhooks are stored before the pool closes
+    let webhooks = Webhooks::new(db.write().clone(), &app_config.webhooks)
+        .with_outbox(app_config.outbox.enabled);
+    let sender = webhooks.start();
+    let router = boot
+        .step(StartupPhase::Server, async {
+            let mut state = AppState::builder(app_config.clone())
+                .db(db.clone())
+                .webhooks(webhooks.clone());
+            if let Some(mailer) = providers::connect(&app_config.mail, &env)? {
+                state = state.mailer(mailer);
+            }
+            if let Some(updates) = updates {
+                state = state.updates(updates);
+            }
+            let state = state.build()?;
+            #[cfg(feature = "grpc")]
+            if app_config.grpc.enabled {
+                start_grpc(&app_config, &state, &coordinator);
+            }
+
+            core::server::router(&state)
+        })
+        .await?;
+    boot.finish();
+    coordinator.on_shutdown("webhook queue", async move { webhooks.drain(sender).await });
+    coordinator.on_shutdown("database pool", async move {
+        if let Some(checks) = checks {
+            checks.abort();
+        }
+        db.close().await
+    });
+    // Errors of the hooks above are captured too
+    #[cfg(feature = "sentry")]
+    coordinator.on_shutdown("sentry", core::sentry::flush());
+    // Logs of every hook above are written before returning
+    coordinator.on_shutdown("logs", async move { log.flush() });
+
+    core::server::start(&app_config.server, router, coordinator).await
+}
+
+/// Runs the background worker.
+///
+/// Function validates the configuration and environment
+/// like the server does, then runs the background jobs
+/// without serving HTTP until SIGINT/SIGTERM.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the worker stopped gracefully.
+///   - `AppError`: If the worker fails to start.
+#[cfg(feature = "server")]
+pub async fn run_worker(profile: Option<&str>) -> Result<(), AppError> {
+    let mut boot = Bootstrap::new();
+    let (app_config, log, env) = bootstrap(&mut boot, profile).await?;
+    let (coordinator, _) = start_coordinator(&app_config, log.clone(), profile);
+
+    let pool = boot.pool(&app_config, &env).await?;
+
+    // Built-in jobs are registered here
+    let jobs = &app_config.jobs;
+    let mut worker = Worker::new()
+        .register(
+            TokenCleanup::new(
+                Arc::new(PgTokenRepo::new(pool.clone())),
+                jobs.schedule("jobs.cleanup", &jobs.cleanup)?,
+            )
+            .with_device_codes(Arc::new(PgDeviceCodeRepo::new(pool.clone())))
+            .with_access_tokens(Arc::new(PgAccessTokenRepo::new(pool.clone()))),
+        )
+        .register(UserPurge::new(
+            Arc::new(PgUserRepo::new(pool.clone())),
+            jobs.purge_after,
+            jobs.schedule("jobs.purge", &jobs.purge)?,
+        ))
+        .register(AccountDeletion::new(
+            Arc::new(PgUserRepo::new(pool.clone())),
+            AuditLog::new(pool.clone()),
+            jobs.schedule("jobs.deletion", &jobs.deletion)?,
+        ));
+    if app_config.group_sync.enabled {
+        worker = worker.register(GroupSync::new(
+            LdapDirectory::new(app_config.group_sync.ldap.clone()),
+            pool.clone(),
+            app_config.group_sync.clone(),
+            jobs.schedule("group_sync.schedule", &app_config.group_sync.schedule)?,
+        ));
+    }
+    if app_config.webhooks.enabled {
+        worker = worker.register(WebhookDispatcher::new(
+            pool.clone(),
+            app_config.webhooks.clone(),
+            jobs.schedule("webhooks.schedule", &app_config.webhooks.schedule)?,
+        ));
+    }
+    if app_config.outbox.enabled {
+        let webhooks = Webhooks::new(pool.clone(), &app_config.webhooks);
+        let relay = OutboxRelay::new(
+            pool.clone(),
+            &app_config.outbox,
+            jobs.schedule("outbox.schedule", &app_config.outbox.schedule)?,
+        )
+        .with_relay(Arc::new(webhooks));
+        #[cfg(any(feature = "nats", feature = "kafka"))]
+        let relay = match app_config.broker.enabled {
+            true => relay.with_relay(Arc::new(Broker::connect(&app_config.broker).await?)),
+            false => relay,
+        };
+        worker = worker.register(relay);
+    }
+    if app_config.key_rotation.enabled {
+        let store = Arc::new(PgSigningKeyRepo::new(pool.clone()));
+        let master_key = MasterKey::required("key_rotation")?;
+        let keys = KeyManager::load(&app_config.tokens)?.with_store(
+            store,
+            master_key,
+            &app_config.key_rotation,
+        );
+        worker = worker.register(KeyRotation::new(
+            Arc::new(keys),
+            jobs.schedule("key_rotation.schedule", &app_config.key_rotation.schedule)?,
+        ));
+    }
+    if app_config.credential_expiry.enabled {
+        let expiry = &app_config.credential_expiry;
+        // Worker has no mailbox, captured mail is only logged
+        let mut webhooks = Webhooks::new(pool.clone(), &app_config.webhooks);
+        if app_config.mail.enabled {
+            let templates = MailTemplates::load(&app_config.mail, app_config.app.is_development())?;
+            webhooks = webhooks.with_templates(Arc::new(templates));
+        }
+        match providers::connect(&app_config.mail, &env)? {
+            Some(mailer) => webhooks = webhooks.with_mailer(mailer),
+            None if app_config.app.is_development() => {
+                webhooks = webhooks.with_mailer(Arc::new(MailCapture::default()))
+            }
+            None => {}
+        }
+        worker = worker.register(CredentialExpiry::new(
+            Arc::new(PgUserRepo::new(pool.clone())),
+            Arc::new(PgTokenRepo::new(pool.clone())),
+            webhooks,
+            expiry,
+            jobs.schedule("credential_expiry.schedule", &expiry.schedule)?,
+        ));
+    }
+
+    boot.finish();
+    coordinator.on_shutdown("database pool", async move { pool.close().await });
+    #[cfg(feature = "sentry")]
+    coordinator.on_shutdown("sentry", core::sentry::flush());
+    coordinator.on_shutdown("logs", async move { log.flush() });
+    worker.run(coordinator).await;
+
+    Ok(())
+}
+
+/// Applies the pending database migrations and exits.
+///
+/// Migrations run even if `bootstrap.skip` lists them.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the schema is up to date.
+///   - `AppError`: If the database is unreachable or a migration failed.
+pub async fn run_migrations(profile: Option<&str>) -> Result<(), AppError> {
+    let mut boot = Bootstrap::new();
+    let (app_config, _, env) = bootstrap(&mut boot, profile).await?;
+
+    let pool = boot.pool(&app_config, &env).await?;
+    let result = boot
+        .step(StartupPhase::Migrations, core::db::migrate(&pool))
+        .await;
+    pool.close().await;
+    result?;
+
+    println!("Database schema is up to date");
+
+    Ok(())
+}
+
+/// Runs the startup and integration checks and exits.
+///
+/// Function does not stop at the first problem, every
+/// check runs and the results are printed as a table.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If every check passed.
+///   - `AppError`: If any check failed.
+#[cfg(feature = "cli")]
+pub async fn run_doctor(profile: Option<&str>) -> Result<(), AppError> {
+    // * Temporary code
+    set_config_file_path("./custom_config.toml".to_string())?;
+
+    let config_file_path = CONFIG_FILE_PATH.get().map_or(DEFAULT_CONFIG_FILE, |p| p);
+    core::cli::doctor::doctor(config_file_path, profile).await
+}
+
+/// Runs the `config` subcommand and exits.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the output was printed, or the configuration is valid.
+///   - `AppError`: If the output can not be generated, or the
+///     configuration is invalid.
+#[cfg(feature = "cli")]
+pub fn run_config(args: &ConfigArgs, profile: Option<&str>) -> Result<(), AppError> {
+    // * Temporary code
+    set_config_file_path("./custom_config.toml".to_string())?;
+
+    let config_file_path = CONFIG_FILE_PATH.get().map_or(DEFAULT_CONFIG_FILE, |p| p);
+    core::cli::config(args, config_file_path, profile)
+}
+
+/// Issues an access token and exits.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the token was printed.
+///   - `AppError`: If the configuration or signing key is invalid.
+#[cfg(feature = "cli")]
+pub fn run_token(args: &TokenArgs, profile: Option<&str>) -> Result<(), AppError> {
+    // * Temporary code
+    set_config_file_path("./custom_config.toml".to_string())?;
+
+    let app_config = load_config(profile)?;
+    core::cli::token(args, &app_config.tokens, &app_config.key_rotation)
+}
+
+/// Prints the route table of the server and exits.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the table was printed.
+///   - `AppError`: If the configuration is invalid.
+#[cfg(feature = "cli")]
+pub fn run_routes(profile: Option<&str>) -> Result<(), AppError> {
+    // * Temporary code
+    set_config_file_path("./custom_config.toml".to_string())?;
+
+    core::cli::routes(load_config(profile)?)
+}
+
+/// Registers or disables an OAuth client and exits.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the client was registered or updated.
+///   - `AppError`: If the database is unreachable or the client is invalid.
+#[cfg(feature = "cli")]
+pub async fn run_client(args: &ClientArgs, profile: Option<&str>) -> Result<(), AppError> {
+    let mut boot = Bootstrap::new();
+    let (app_config, _, env) = bootstrap(&mut boot, profile).await?;
+
+    let pool = boot.pool(&app_config, &env).await?;
+    let result = core::cli::client(args, &PgClientRepo::new(pool.clone())).await;
+    pool.close().await;
+
+    result
+}
+
+/// Creates or disables a tenant and exits.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If the tenant was created or updated.
+///   - `AppError`: If the database is unreachable or the tenant is invalid.
+#[cfg(feature = "cli")]
+pub async fn run_tenant(args: &TenantArgs, profile: Option<&str>) -> Result<(), AppError> {
+    let mut boot = Bootstrap::new();
+    let (app_config, _, env) = bootstrap(&mut boot, profile).await?;
+
+    let pool = boot.pool(&app_config, &env).await?;
+    let result = core::cli::tenant(args, &PgTenantRepo::new(pool.clone())).await;
+    pool.close().await;
+
+    result
+}
+
+/// Imports or exports users and exits.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If every user was imported or the users were exported.
+///   - `AppError`: If the database is unreachable, a file can not be
+///     read or written, or a row was not imported.
+#[cfg(feature = "cli")]
+pub async fn run_users(args: &UsersArgs, profile: Option<&str>) -> Result<(), AppError> {
+    let mut boot = Bootstrap::new();
+    let (app_config, _, env) = bootstrap(&mut boot, profile).await?;
+
+    let pool = boot.pool(&app_config, &env).await?;
+    let users = PgUserRepo::new(pool.clone()).with_outbox(app_config.outbox.enabled);
+    let roles = PgRoleDefinitionRepo::new(pool.clone());
+    let result = core::cli::users(args, &users, &roles).await;
+    pool.close().await;
+
+    result
+}
+
+/// Upserts the fixtures of a file and exits.
+///
+/// #Parameters
+/// - `profile`: `Option<&str>` - Configuration profile, the one of
+///   `app.env` when not set.
+///
+/// #Returns
+/// + `Result<(), AppError>`
+///   - `()`: If every fixture was applied.
+///   - `AppError`: If the database is unreachable or the fixtures are
+///     invalid.
+#[cfg(feature = "cli")]
+pub async fn run_seed(args: &SeedArgs, profile: Option<&str>) -> Result<(), AppError> {
+    let mut boot = Bootstrap::new();
+    let (app_config, _, env) = bootstrap(&mut boot, profile).await?;
+
+    let passwords = Passwords::new(&app_config.password)?;
+    let pool = boot.pool(&app_config, &env).await?;
+    let tenants = PgTenantRepo::new(pool.clone());
+    let roles = PgRoleDefinitionRepo::new(pool.clone());
+    let users = PgUserRepo::new(pool.clone()).with_outbox(app_config.outbox.enabled);
+    let target = SeedTarget {
+        tenants: &tenants,
+        roles: &roles,
+        users: &users,
+        passwords: &passwords,
+    };
+    let result = core::cli::seed(args, &target).await;
+    pool.close().await;
+
+    result
+}
+
+/// Runs the config and logging phases of the startup.
+///
+/// Every run mode starts with this function, so the
+/// server, the worker and the migrations validate the
+/// deployment the same way. The subscriber is installed
+/// first, so warnings of the config phase are logged.
+async fn bootstrap(
+    boot: &mut Bootstrap,
+    profile: Option<&str>,
+) -> Result<(Arc<AppConfig>, LogHandle, EnvMap), AppError> {
+    let log = core::logging::init()?;
+
+    // * Temporary code
+    let config_file_path: String = "./custom_config.toml".to_string();
+    // Set the configuration file path
+    set_config_file_path(config_file_path)?;
+
+    let config_file = CONFIG_FILE_PATH.get().map_or(DEFAULT_CONFIG_FILE, |p| p);
+    let (app_config, env) = boot.config(config_file, profile).await?;
+    boot.logging(&log, &app_config, &env).await?;
+    #[cfg(feature = "server")]
+    {
+        #[cfg(feature = "sentry")]
+        let reporter = core::sentry::init(&app_config.observability.sentry, &env)?;
+        #[cfg(not(feature = "sentry"))]
+        let reporter = None;
+        core::panic::install(&app_config.crash_reports, reporter);
+    }
+
+    Ok((app_config, log, env))
+}
+
+/// Reads the configuration file set with `set_config_file_path`.
+#[cfg(feature = "cli")]
+fn load_config(profile: Option<&str>) -> Result<Arc<AppConfig>, AppError> {
+    let config_file = CONFIG_FILE_PATH.get().map_or(DEFAULT_CONFIG_FILE, |p| p);
+
+    read_config(config_file, profile).map(Arc::new)
+}
+
+/// Creates the shutdown coordinator of a long running mode.
+///
+/// Function also starts the configuration watcher, which
+/// reloads the file with the same profile and stops
+/// together with the coordinator. The log level is applied
+/// here, the configuration it publishes is returned for
+/// the other subscribers.
+#[cfg(feature = "server")]
+fn start_coordinator(
+    app_config: &AppConfig,
+    log: LogHandle,
+    profile: Option<&str>,
+) -> (ShutdownCoordinator, Option<watch::Receiver<Arc<AppConfig>>>) {
+    let coordinator =
+        ShutdownCoordinator::new(Duration::from_secs(app_config.server.shutdown_grace_period));
+    coordinator.listen_for_signals();
+
+    // Apply hot-reloadable changes of the configuration file
+    let mut updates = None;
+    if app_config.reload.enabled {
+        let path = reload::resolve_path(CONFIG_FILE_PATH.get().map_or(DEFAULT_CONFIG_FILE, |p| p));
+        let mut config = reload::watch(
+            path,
+            profile.map(str::to_string),
+            Arc::new(app_config.clone()),
+            Duration::from_secs(app_config.reload.interval),
+            coordinator.clone(),
+        );
+        updates = Some(config.clone());
+
+        tokio::spawn(async move {
+            while config.changed().await.is_ok() {
+                let logging = config.borrow_and_update().logging.clone();
+                if let Err(e) = log.apply(&logging) {
+                    tracing::warn!(error = %e, "Failed to apply the log level");
+                }
+            }
+        });
+    }
+
+    (coordinator, updates)
+}
+
+/// Starts the gRPC service next to the HTTP server.
+///
+/// Service shares the state of the server and stops with
+/// the coordinator. Failing to serve, e.g. because the port
+/// is in use, shuts the whole application down.
+#[cfg(feature = "grpc")]
+fn start_grpc(app_config: &AppConfig, state: &AppState, coordinator: &ShutdownCoordinator) {
+    let settings = app_config.grpc.clone();
+    let service = core::grpc::AuthService::from_state(state);
+    let serving = coordinator.clone();
+    let task = tokio::spawn(async move {
+        if let Err(e) = core::grpc::serve(&settings, service, serving.clone()).await {
+            tracing::error!(error = %e, "gRPC server failed");
+            serving.trigger();
+        }
+    });
+
+    coordinator.on_shutdown("grpc server", async move {
+        let _ = task.await;
+    });
+}
+
+// * Temporary code
+fn set_config_file_path(path: String) -> Result<(), AppError> {
+    // !! This is a simulation, this parameter will come
+    // !! from the command line arguments
+    if CONFIG_FILE_PATH.set(path).is_err() {
+        let kind = ErrorKind::Config;
+        let message = "Failed to set configuration file path".to_string();
+        let source = None;
+
+        let err = AppError::new(kind, message, source);
+
+        return Err(err);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,421 @@
+//! Client module.
+//!
+//! Module lets other axum services accept the access
+//! tokens of a running auth server. `AuthClient` talks to
+//! the server, `TokenValidator` verifies tokens against
+//! its cached key set, and the `authenticate` and
+//! `require_scope` middleware protect the routes.
+//!
+//! ## Examples
+//! ```no_run
+//! use axum::{handler::Handler, middleware, routing::get, Router};
+//! use axum_auth::client::{authenticate, require_scope, AuthClient, TokenValidator};
+//! use axum_auth::core::auth::jwt::{AccessClaims, Validation};
+//! use std::sync::Arc;
+//!
+//! async fn notes(claims: AccessClaims) -> String {
+//!     format!("Notes of {}", claims.sub)
+//! }
+//!
+//! let client = AuthClient::new("https://auth.example.com").unwrap();
+//! let validator = Arc::new(TokenValidator::new(
+//!     client,
+//!     Validation::new("https://auth.example.com", "notes"),
+//! ));
+//!
+//! let app: Router = Router::new()
+//!     .route(
+//!         "/notes",
+//!         get(notes.layer(middleware::from_fn_with_state("notes:read", require_scope))),
+//!     )
+//!     .layer(middleware::from_fn_with_state(validator, authenticate));
+//! ```
+
+// Imports from external crates
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+        request::Parts,
+        HeaderValue,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+// Local imports
+use crate::core::auth::jwt::{key_id, verify, AccessClaims, JwkSet, Validation};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::types::{AppType, HTTP_SCHEMES};
+use crate::routes::jwks::JWKS_PATH;
+
+/// Seconds a fetched key set is used before it is fetched again.
+const KEYS_TTL: Duration = Duration::from_secs(300);
+
+/// Seconds between fetches caused by tokens of unknown keys.
+const MIN_REFRESH: Duration = Duration::from_secs(30);
+
+/// ## Auth server client struct.
+///
+/// ## Fields
+/// + `base_url`: `String` - URL of the auth server, without trailing slash.
+#[derive(Debug, Clone)]
+pub struct AuthClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl AuthClient {
+    /// ## Creates a new `AuthClient` instance.
+    ///
+    /// ## Parameters
+    /// - `base_url`: `&str` - URL of the auth server, e.g. `https://auth.example.com`.
+    ///
+    /// ## Returns
+    /// + `Result<AuthClient, AppError>`
+    ///     - `Ok(AuthClient)`: Client of the server.
+    ///     - `Err(AppError)`: `Validation` if the URL is not http:// or https://.
+    pub fn new(base_url: &str) -> Result<Self, AppError> {
+        AppType::Url(HTTP_SCHEMES).verify(base_url)?;
+
+        Ok(AuthClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// ## Fetches the key set of the server.
+    ///
+    /// ## Returns
+    /// + `Result<JwkSet, AppError>`
+    ///     - `Ok(JwkSet)`: Keys access tokens are signed with.
+    ///     - `Err(AppError)`: `External` if the server is unreachable or
+    ///       responded with an error.
+    pub async fn jwks(&self) -> Result<JwkSet, AppError> {
+        let url = format!("{}{}", self.base_url, JWKS_PATH);
+        let unavailable = |e: reqwest::Error| {
+            AppError::external(format!("Failed to fetch keys from '{}'", url)).with_source(e)
+        };
+
+        let response = match self
+            .http
+            .get(&url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(response) => response,
+            Err(e) => return Err(unavailable(e)),
+        };
+
+        response.json::<JwkSet>().await.map_err(unavailable)
+    }
+}
+
+/// ## Cached key set.
+struct CachedKeys {
+    keys: Arc<JwkSet>,
+    fetched_at: Instant,
+}
+
+/// ## Token validator struct.
+///
+/// Keys are fetched on the first request and cached.
+/// Tokens of an unknown key refresh the cache, so keys
+/// added by the server are picked up without a restart.
+/// While the server is unreachable the cached keys are
+/// used.
+pub struct TokenValidator {
+    client: AuthClient,
+    validation: Validation,
+    cache: RwLock<Option<CachedKeys>>,
+}
+
+impl TokenValidator {
+    /// ## Creates a new `TokenValidator` instance.
+    ///
+    /// ## Parameters
+    /// - `client`: `AuthClient` - Client of the server issuing the tokens.
+    /// - `validation`: `Validation` - Expected issuer and audience.
+    pub fn new(client: AuthClient, validation: Validation) -> Self {
+        TokenValidator {
+            client,
+            validation,
+            cache: RwLock::new(None),
+        }
+    }
+
+    /// ## Validates the token.
+    ///
+    /// ## Returns
+    /// + `Result<AccessClaims, AppError>`
+    ///     - `Ok(AccessClaims)`: Claims of a valid token.
+    ///     - `Err(AppError)`: `Auth` if the token is invalid, `External`
+    ///       if no keys could be fetched.
+    pub async fn validate(&self, token: &str) -> Result<AccessClaims, AppError> {
+        let kid = key_id(token)?;
+        let keys = self.keys(&kid).await?;
+
+        verify(token, &keys, &self.validation)
+    }
+
+    /// ## Returns the key set to verify a token of the key with.
+    async fn keys(&self, kid: &str) -> Result<Arc<JwkSet>, AppError> {
+        let cached = self
+            .cache
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|cached| (cached.keys.clone(), cached.fetched_at.elapsed()));
+
+        match cached {
+            Some((keys, age)) if age < KEYS_TTL && keys.find(kid).is_some() => return Ok(keys),
+            Some((keys, age)) if age < MIN_REFRESH => return Ok(keys),
+            _ => {}
+        }
+
+        let fetched = self.client.jwks().await;
+        let keys = match (fetched, cached) {
+            (Ok(keys), _) => Arc::new(keys),
+            (Err(e), Some((keys, _))) => {
+                tracing::warn!(error = %e, "Using cached keys");
+                keys
+            }
+            (Err(e), None) => return Err(e),
+        };
+
+        *self.cache.write().unwrap_or_else(|e| e.into_inner()) = Some(CachedKeys {
+            keys: keys.clone(),
+            fetched_at: Instant::now(),
+        });
+
+        Ok(keys)
+    }
+}
+
+/// ## Authenticates the request.
+///
+/// Middleware validates the bearer token of the request
+/// and stores its `AccessClaims` in the request
+/// extensions. Requests without a valid token are rejected
+/// with 401 and a `WWW-Authenticate` challenge.
+pub async fn authenticate(
+    State(validator): State<Arc<TokenValidator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .map(str::to_string);
+
+    let Some(token) = token else {
+        return challenge(
+            AppError::auth("Bearer token required"),
+            "Bearer".to_string(),
+        );
+    };
+
+    let claims = match validator.validate(&token).await {
+        Ok(claims) => claims,
+        Err(e) if e.kind == ErrorKind::Auth => {
+            return challenge(e, "Bearer error=\"invalid_token\"".to_string())
+        }
+        Err(e) => return e.into_response(),
+    };
+
+    request.extensions_mut().insert(claims);
+    next.run(request).await
+}
+
+/// ## Requires a scope.
+///
+/// Middleware runs after `authenticate` and rejects
+/// tokens without the scope with 403.
+///
+/// ## Examples
+/// ```
+/// use axum::{middleware, routing::post, Router};
+/// use axum_auth::client::require_scope;
+///
+/// let app: Router = Router::new()
+///     .route("/notes", post(|| async { "created" }))
+///     .route_layer(middleware::from_fn_with_state("notes:write", require_scope));
+/// ```
+pub async fn require_scope(
+    State(scope): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let granted = match request.extensions().get::<AccessClaims>() {
+        Some(claims) => claims.has_scope(scope),
+        None => {
+            return challenge(
+                AppError::auth("Bearer token required"),
+                "Bearer".to_string(),
+            )
+        }
+    };
+
+    match granted {
+        true => next.run(request).await,
+        false => challenge(
+            AppError::new(
+                ErrorKind::Forbidden,
+                format!("Token lacks the '{}' scope", scope),
+                None,
+            )
+            .with_code("token.insufficient_scope"),
+            format!("Bearer error=\"insufficient_scope\", scope=\"{}\"", scope),
+        ),
+    }
+}
+
+/// ## Renders the error with a `WWW-Authenticate` challenge.
+fn challenge(error: AppError, challenge: String) -> Response {
+    let mut response = error.into_response();
+    if let Ok(value) = HeaderValue::from_str(&challenge) {
+        response.headers_mut().insert(WWW_AUTHENTICATE, value);
+    }
+
+    response
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AccessClaims
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    /// Extracts the claims stored by `authenticate`.
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AccessClaims>()
+            .cloned()
+            .ok_or_else(|| AppError::auth("Bearer token required"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::jwt::SigningKey;
+    use axum::{routing::get, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Serves the keys of the set and counts the fetches.
+    async fn serve_keys(keys: Arc<RwLock<JwkSet>>, fetches: Arc<AtomicUsize>) -> String {
+        let app = Router::new().route(
+            JWKS_PATH,
+            get(move || async move {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                axum::Json(keys.read().unwrap().clone())
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        format!("http://{}", addr)
+    }
+
+    fn claims() -> AccessClaims {
+        AccessClaims {
+            iss: "https://auth.example.com".to_string(),
+            sub: "user".to_string(),
+            aud: "notes".to_string(),
+            iat: chrono::Utc::now().timestamp(),
+            exp: i64::MAX,
+            scope: String::new(),
+            sid: None,
+        }
+    }
+
+    // Test checks if keys are cached and refreshed for unknown keys only.
+    #[tokio::test]
+    async fn test_validator_caches_keys() {
+        let first = SigningKey::generate("first").unwrap();
+        let second = SigningKey::generate("second").unwrap();
+        let keys = Arc::new(RwLock::new(JwkSet {
+            keys: vec![first.jwk()],
+        }));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let url = serve_keys(keys.clone(), fetches.clone()).await;
+        let validator = TokenValidator::new(
+            AuthClient::new(&url).unwrap(),
+            Validation::new("https://auth.example.com", "notes"),
+        );
+
+        let token = first.sign(&claims()).unwrap();
+        for _ in 0..3 {
+            assert_eq!(validator.validate(&token).await.unwrap(), claims());
+        }
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Unknown keys within the refresh interval are not fetched
+        keys.write().unwrap().keys.push(second.jwk());
+        let token = second.sign(&claims()).unwrap();
+        let result = validator.validate(&token).await.unwrap_err();
+
+        assert_eq!(result.code, "token.unknown_key");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Once the interval passed, the new key is picked up
+        validator
+            .cache
+            .write()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .fetched_at -= MIN_REFRESH;
+
+        assert!(validator.validate(&token).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    // Test checks if an unreachable server fails only without cached keys.
+    #[tokio::test]
+    async fn test_validator_server_unreachable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let validator = TokenValidator::new(
+            AuthClient::new(&url).unwrap(),
+            Validation::new("https://auth.example.com", "notes"),
+        );
+        let key = SigningKey::generate("first").unwrap();
+        let token = key.sign(&claims()).unwrap();
+
+        let result = validator.validate(&token).await.unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::External);
+
+        *validator.cache.write().unwrap() = Some(CachedKeys {
+            keys: Arc::new(JwkSet {
+                keys: vec![key.jwk()],
+            }),
+            fetched_at: Instant::now() - KEYS_TTL,
+        });
+
+        assert!(validator.validate(&token).await.is_ok());
+    }
+
+    // Test checks if only http and https servers are accepted.
+    #[test]
+    fn test_auth_client_url() {
+        assert!(AuthClient::new("https://auth.example.com/").is_ok());
+        assert!(AuthClient::new("auth.example.com").is_err());
+    }
+}
@@ -0,0 +1,583 @@
+//! Audit log module.
+//!
+//! Module records security relevant events into the
+//! `audit_log` table and queries them for administrators.
+//! Every recorded event is also emitted on the `audit`
+//! tracing target, so it shows up in the logs even when
+//! nobody queries the table. Events are recorded and
+//! queried in the tenant of the current task.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use serde_json::Value as Json;
+use sqlx::{postgres::PgRow, FromRow, Postgres, QueryBuilder, Row};
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+// Local imports
+use super::auth::AUDIT_TARGET;
+use super::db::DbExecutor;
+use super::err::{AppError, ErrorKind};
+use super::http::pagination::{timestamp_key, Keyed, SortField, Sorting, Window};
+use super::tenancy::current_tenant;
+
+// Re-exports of the wire types
+pub use crate::api::audit::AuditRecord;
+
+/// ## Audit event enum.
+///
+/// # Variants
+/// - `LoginSucceeded` - `login.succeeded`, user logged in.
+/// - `LoginFailed` - `login.failed`, login was rejected.
+/// - `PasswordChanged` - `password.changed`, user changed the password.
+/// - `RoleGranted` - `role.granted`, role was granted to a user.
+/// - `RoleRevoked` - `role.revoked`, role was revoked from a user.
+/// - `RoleCreated` - `role.created`, administrator defined a role.
+/// - `RoleUpdated` - `role.updated`, administrator changed a role definition.
+/// - `RoleDeleted` - `role.deleted`, administrator deleted a role definition.
+/// - `TokenRevoked` - `token.revoked`, token or session was revoked.
+/// - `UserDisabled` - `admin.user.disabled`, administrator disabled a user.
+/// - `UserDeleted` - `admin.user.deleted`, administrator deleted a user.
+/// - `PasswordResetForced` - `admin.user.password_reset`, administrator
+///   forced a password reset.
+/// - `DeletionScheduled` - `account.deletion_scheduled`, user asked to
+///   delete the account.
+/// - `DeletionCancelled` - `account.deletion_cancelled`, user cancelled
+///   the deletion during the grace period.
+/// - `AccountPurged` - `account.purged`, account was removed once the
+///   grace period was over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEvent {
+    LoginSucceeded,
+    LoginFailed,
+    PasswordChanged,
+    RoleGranted,
+    RoleRevoked,
+    RoleCreated,
+    RoleUpdated,
+    RoleDeleted,
+    TokenRevoked,
+    UserDisabled,
+    UserDeleted,
+    PasswordResetForced,
+    DeletionScheduled,
+    DeletionCancelled,
+    AccountPurged,
+}
+
+impl AuditEvent {
+    /// Every event.
+    pub const ALL: [AuditEvent; 15] = [
+        AuditEvent::LoginSucceeded,
+        AuditEvent::LoginFailed,
+        AuditEvent::PasswordChanged,
+        AuditEvent::RoleGranted,
+        AuditEvent::RoleRevoked,
+        AuditEvent::RoleCreated,
+        AuditEvent::RoleUpdated,
+        AuditEvent::RoleDeleted,
+        AuditEvent::TokenRevoked,
+        AuditEvent::UserDisabled,
+        AuditEvent::UserDeleted,
+        AuditEvent::PasswordResetForced,
+        AuditEvent::DeletionScheduled,
+        AuditEvent::DeletionCancelled,
+        AuditEvent::AccountPurged,
+    ];
+
+    /// ## Returns the name stored in the `event` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEvent::LoginSucceeded => "login.succeeded",
+            AuditEvent::LoginFailed => "login.failed",
+            AuditEvent::PasswordChanged => "password.changed",
+            AuditEvent::RoleGranted => "role.granted",
+            AuditEvent::RoleRevoked => "role.revoked",
+            AuditEvent::RoleCreated => "role.created",
+            AuditEvent::RoleUpdated => "role.updated",
+            AuditEvent::RoleDeleted => "role.deleted",
+            AuditEvent::TokenRevoked => "token.revoked",
+            AuditEvent::UserDisabled => "admin.user.disabled",
+            AuditEvent::UserDeleted => "admin.user.deleted",
+            AuditEvent::PasswordResetForced => "admin.user.password_reset",
+            AuditEvent::DeletionScheduled => "account.deletion_scheduled",
+            AuditEvent::DeletionCancelled => "account.deletion_cancelled",
+            AuditEvent::AccountPurged => "account.purged",
+        }
+    }
+}
+
+impl fmt::Display for AuditEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for AuditEvent {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        AuditEvent::ALL
+            .into_iter()
+            .find(|event| event.as_str() == value)
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::Validation,
+                    format!("Unknown audit event '{}'", value),
+                    None,
+                )
+            })
+    }
+}
+
+/// ## Audit entry struct.
+///
+/// Event to record.
+///
+/// ## Fields
+/// + `event`: `AuditEvent` - Kind of the event.
+/// + `actor_id`: `Option<Uuid>` - User that performed the action.
+/// + `user_id`: `Option<Uuid>` - User the event is about.
+/// + `ip`: `Option<String>` - Client address of the request.
+/// + `details`: `serde_json::Value` - Event specific details.
+///
+/// ## Examples
+/// ```
+/// use uuid::Uuid;
+/// use axum_auth::core::audit::{AuditEntry, AuditEvent};
+///
+/// let user_id = Uuid::new_v4();
+/// let entry = AuditEntry::new(AuditEvent::LoginFailed)
+///     .user(user_id)
+///     .ip("203.0.113.7")
+///     .details(serde_json::json!({ "reason": "invalid password" }));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub event: AuditEvent,
+    pub actor_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub details: Json,
+}
+
+impl AuditEntry {
+    /// ## Creates a new entry without actor, user and details.
+    pub fn new(event: AuditEvent) -> Self {
+        AuditEntry {
+            event,
+            actor_id: None,
+            user_id: None,
+            ip: None,
+            details: Json::Object(Default::default()),
+        }
+    }
+
+    /// ## Sets the user that performed the action.
+    pub fn actor(mut self, actor_id: Uuid) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    /// ## Sets the user the event is about.
+    pub fn user(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    /// ## Sets the client address.
+    pub fn ip(mut self, ip: &str) -> Self {
+        self.ip = Some(ip.to_string());
+        self
+    }
+
+    /// ## Sets the event specific details.
+    pub fn details(mut self, details: Json) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// Maps the row into `AuditRecord`.
+impl<'r> FromRow<'r, PgRow> for AuditRecord {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(AuditRecord {
+            id: row.try_get("id")?,
+            occurred_at: row.try_get("occurred_at")?,
+            event: row.try_get("event")?,
+            actor_id: row.try_get("actor_id")?,
+            user_id: row.try_get("user_id")?,
+            ip: row.try_get("ip")?,
+            details: row.try_get("details")?,
+            occurred_at_display: None,
+        })
+    }
+}
+
+/// Sortable fields of the audit log, newest first by default.
+pub const AUDIT_SORTING: Sorting = Sorting {
+    fields: &[
+        SortField {
+            name: "occurred_at",
+            column: "occurred_at",
+            cast: "timestamptz",
+        },
+        SortField {
+            name: "event",
+            column: "event",
+            cast: "text",
+        },
+    ],
+    default: "-occurred_at",
+    id: SortField {
+        name: "id",
+        column: "id",
+        cast: "bigint",
+    },
+};
+
+impl Keyed for AuditRecord {
+    fn sort_key(&self, field: &str) -> String {
+        match field {
+            "event" => self.event.clone(),
+            _ => timestamp_key(self.occurred_at),
+        }
+    }
+
+    fn id_key(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+/// ## Audit filter struct.
+///
+/// Filters that are not set match every record.
+///
+/// ## Fields
+/// + `user_id`: `Option<Uuid>` - User the events are about.
+/// + `event`: `Option<AuditEvent>` - Kind of the events.
+/// + `from`: `Option<DateTime<Utc>>` - Earliest time, inclusive.
+/// + `to`: `Option<DateTime<Utc>>` - Latest time, exclusive.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditFilter {
+    pub user_id: Option<Uuid>,
+    pub event: Option<AuditEvent>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// ## Audit log service struct.
+///
+/// Events are recorded on the primary, queries are
+/// read from the replicas of the executor. Logs created
+/// with `memory` keep the events in memory instead, so
+/// handlers can be tested without a running database.
+/// Clones share the same events.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    store: Store,
+}
+
+/// Storage of the events with their tenants.
+#[derive(Debug, Clone)]
+enum Store {
+    Db(DbExecutor),
+    Memory(Arc<Mutex<Vec<(String, AuditRecord)>>>),
+}
+
+impl AuditLog {
+    /// ## Creates a new `AuditLog` instance.
+    ///
+    /// ## Parameters
+    /// - `db`: `impl Into<DbExecutor>` - Executor or pool of the log.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        AuditLog {
+            store: Store::Db(db.into()),
+        }
+    }
+
+    /// ## Creates a new `AuditLog` keeping the events in memory.
+    pub fn memory() -> Self {
+        AuditLog {
+            store: Store::Memory(Arc::default()),
+        }
+    }
+
+    /// ## Records the event.
+    ///
+    /// ## Parameters
+    /// - `entry`: `AuditEntry` - Event to record.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the event was stored.
+    ///     - `Err(AppError)`: If the insert failed.
+    pub async fn record(&self, entry: AuditEntry) -> Result<(), AppError> {
+        let tenant = current_tenant();
+        tracing::info!(
+            target: AUDIT_TARGET,
+            event = entry.event.as_str(),
+            tenant = %tenant,
+            actor_id = ?entry.actor_id,
+            user_id = ?entry.user_id,
+            ip = ?entry.ip,
+            details = %entry.details,
+            "Audit event"
+        );
+        let db = match &self.store {
+            Store::Db(db) => db,
+            Store::Memory(records) => {
+                let mut records = records.lock().unwrap_or_else(|e| e.into_inner());
+                let record = AuditRecord {
+                    id: records.len() as i64 + 1,
+                    occurred_at: Utc::now(),
+                    event: entry.event.as_str().to_string(),
+                    actor_id: entry.actor_id,
+                    user_id: entry.user_id,
+                    ip: entry.ip,
+                    details: entry.details,
+                    occurred_at_display: None,
+                };
+                records.push((tenant, record));
+                return Ok(());
+            }
+        };
+
+        sqlx::query(
+            "INSERT INTO audit_log (event, actor_id, user_id, ip, details, tenant_id) \
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(entry.event.as_str())
+        .bind(entry.actor_id)
+        .bind(entry.user_id)
+        .bind(entry.ip)
+        .bind(entry.details)
+        .bind(tenant)
+        .execute(db.write())
+        .await
+        .map(|_| ())
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Db,
+                "Failed to record audit event".to_string(),
+                Some(Box::new(e)),
+            )
+        })
+    }
+
+    /// ## Queries recorded events.
+    ///
+    /// ## Parameters
+    /// - `filter`: `&AuditFilter` - Filters of the query.
+    /// - `window`: `&Window` - Page and sort of the result.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<AuditRecord>, AppError>`
+    ///     - `Ok(Vec<AuditRecord>)`: Records of the page, followed by the
+    ///       first record of the next page if there is one.
+    ///     - `Err(AppError)`: If the query failed.
+    pub async fn query(
+        &self,
+        filter: &AuditFilter,
+        window: &Window,
+    ) -> Result<Vec<AuditRecord>, AppError> {
+        let db = match &self.store {
+            Store::Db(db) => db,
+            Store::Memory(_) => {
+                let records = self.records(|record| {
+                    filter.user_id.is_none_or(|id| record.user_id == Some(id))
+                        && filter
+                            .event
+                            .is_none_or(|event| record.event == event.as_str())
+                        && filter.from.is_none_or(|from| record.occurred_at >= from)
+                        && filter.to.is_none_or(|to| record.occurred_at < to)
+                });
+                return Ok(window.apply(records));
+            }
+        };
+
+        query(filter, window)
+            .build_query_as::<AuditRecord>()
+            .fetch_all(db.read())
+            .await
+            .map_err(|e| {
+                AppError::new(
+                    ErrorKind::Db,
+                    "Failed to query audit log".to_string(),
+                    Some(Box::new(e)),
+                )
+            })
+    }
+
+    /// ## Returns every event the user performed or is about.
+    ///
+    /// ## Parameters
+    /// - `user_id`: `Uuid` - Actor or subject of the events.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<AuditRecord>, AppError>`
+    ///     - `Ok(Vec<AuditRecord>)`: Events, oldest first.
+    ///     - `Err(AppError)`: If the query failed.
+    pub async fn export(&self, user_id: Uuid) -> Result<Vec<AuditRecord>, AppError> {
+        let db = match &self.store {
+            Store::Db(db) => db,
+            Store::Memory(_) => {
+                return Ok(self.records(|record| {
+                    record.user_id == Some(user_id) || record.actor_id == Some(user_id)
+                }))
+            }
+        };
+
+        sqlx::query_as::<_, AuditRecord>(
+            "SELECT id, occurred_at, event, actor_id, user_id, ip, details FROM audit_log \
+             WHERE (user_id = $1 OR actor_id = $1) AND tenant_id = $2 ORDER BY occurred_at, id",
+        )
+        .bind(user_id)
+        .bind(current_tenant())
+        .fetch_all(db.read())
+        .await
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Db,
+                "Failed to export audit log".to_string(),
+                Some(Box::new(e)),
+            )
+        })
+    }
+
+    /// ## Returns the events in memory of the current tenant matching the predicate.
+    fn records(&self, matches: impl Fn(&AuditRecord) -> bool) -> Vec<AuditRecord> {
+        let Store::Memory(records) = &self.store else {
+            return Vec::new();
+        };
+        let tenant = current_tenant();
+
+        records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(owner, record)| *owner == tenant && matches(record))
+            .map(|(_, record)| record.clone())
+            .collect()
+    }
+}
+
+/// ## Builds the query of the filter.
+fn query(filter: &AuditFilter, window: &Window) -> QueryBuilder<'static, Postgres> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, occurred_at, event, actor_id, user_id, ip, details FROM audit_log \
+         WHERE tenant_id = ",
+    );
+    query.push_bind(current_tenant());
+
+    if let Some(user_id) = filter.user_id {
+        query.push(" AND user_id = ");
+        query.push_bind(user_id);
+    }
+    if let Some(event) = filter.event {
+        query.push(" AND event = ");
+        query.push_bind(event.as_str());
+    }
+    if let Some(from) = filter.from {
+        query.push(" AND occurred_at >= ");
+        query.push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        query.push(" AND occurred_at < ");
+        query.push_bind(to);
+    }
+
+    window.push_keyset(&mut query);
+    window.push_order(&mut query);
+
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::http::pagination::Pagination;
+    use crate::core::tenancy::with_tenant;
+
+    // Test checks if every event is parsed back from its name.
+    #[test]
+    fn test_audit_event_round_trip() {
+        for event in AuditEvent::ALL {
+            assert_eq!(event.as_str().parse::<AuditEvent>(), Ok(event));
+        }
+
+        let result = "login.maybe".parse::<AuditEvent>().unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Validation);
+    }
+
+    // Test checks if the filter is bound as parameters.
+    #[test]
+    fn test_query_filtered() {
+        let filter = AuditFilter {
+            user_id: Some(Uuid::new_v4()),
+            event: Some(AuditEvent::LoginFailed),
+            from: Some(Utc::now()),
+            to: Some(Utc::now()),
+        };
+        let window = Pagination::default().window(&AUDIT_SORTING).unwrap();
+
+        assert_eq!(
+            query(&filter, &window).sql(),
+            "SELECT id, occurred_at, event, actor_id, user_id, ip, details FROM audit_log \
+             WHERE tenant_id = $1 AND user_id = $2 AND event = $3 AND occurred_at >= $4 \
+             AND occurred_at < $5 ORDER BY occurred_at DESC, id DESC LIMIT $6 OFFSET $7"
+        );
+    }
+
+    // Test checks if the entry builder sets every field.
+    #[test]
+    fn test_audit_entry_builder() {
+        let actor = Uuid::new_v4();
+        let user = Uuid::new_v4();
+
+        let entry = AuditEntry::new(AuditEvent::RoleGranted)
+            .actor(actor)
+            .user(user)
+            .ip("203.0.113.7")
+            .details(serde_json::json!({ "role": "admin" }));
+
+        assert_eq!(entry.actor_id, Some(actor));
+        assert_eq!(entry.user_id, Some(user));
+        assert_eq!(entry.ip.as_deref(), Some("203.0.113.7"));
+        assert_eq!(entry.details["role"], "admin");
+    }
+
+    // Test checks if the log in memory keeps the events of each tenant apart.
+    #[tokio::test]
+    async fn test_memory_log() {
+        let log = AuditLog::memory();
+        let user = Uuid::new_v4();
+        log.record(AuditEntry::new(AuditEvent::LoginSucceeded).user(user))
+            .await
+            .unwrap();
+        with_tenant("acme", async {
+            log.record(AuditEntry::new(AuditEvent::LoginFailed).user(user))
+                .await
+                .unwrap();
+        })
+        .await;
+
+        let events = log.export(user).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "login.succeeded");
+
+        let filter = AuditFilter {
+            event: Some(AuditEvent::LoginFailed),
+            ..AuditFilter::default()
+        };
+        let window = Pagination::default().window(&AUDIT_SORTING).unwrap();
+        assert!(log.query(&filter, &window).await.unwrap().is_empty());
+        let events = with_tenant("acme", log.query(&filter, &window)).await;
+        assert_eq!(events.unwrap()[0].user_id, Some(user));
+    }
+}
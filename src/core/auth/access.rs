@@ -0,0 +1,560 @@
+//! Access policy module.
+//!
+//! Module enforces the login windows and country
+//! allowlists configured per role and tenant. Denied
+//! logins are reported to the audit log target.
+
+// Imports from external crates
+use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, Utc, Weekday};
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::Arc,
+};
+
+// Local imports
+use super::AUDIT_TARGET;
+use crate::core::config::{AccessPolicySettings, AccessSettings, LoginWindowSettings};
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## GeoIP resolver trait.
+///
+/// Trait resolves an address into an ISO 3166 country code.
+pub trait GeoIpResolver: Send + Sync {
+    /// Returns the country code of the address, `None`
+    /// when the address is unknown.
+    fn country(&self, ip: IpAddr) -> Option<String>;
+}
+
+/// ## GeoIP resolver backed by a MaxMind database.
+pub struct MaxMindResolver {
+    reader: maxminddb::Reader<Vec<u8>>,
+}
+
+impl MaxMindResolver {
+    /// ## Opens the MaxMind country database.
+    ///
+    /// ## Parameters
+    /// - `path`: `&str` - Path to the `.mmdb` file.
+    ///
+    /// ## Returns
+    /// + `Result<MaxMindResolver, AppError>`
+    ///     - `Ok(MaxMindResolver)`: If the database was opened.
+    ///     - `Err(AppError)`: If the file is not a valid database.
+    pub fn open(path: &str) -> Result<Self, AppError> {
+        let reader = maxminddb::Reader::open_readfile(path).map_err(|e| {
+            AppError::new(
+                ErrorKind::Config,
+                format!("Failed to open GeoIP database '{}'", path),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        Ok(MaxMindResolver { reader })
+    }
+}
+
+impl GeoIpResolver for MaxMindResolver {
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        let record: maxminddb::geoip2::Country = self.reader.lookup(ip).ok()?;
+
+        record
+            .country
+            .and_then(|country| country.iso_code)
+            .map(str::to_string)
+    }
+}
+
+/// ## Login attempt context.
+///
+/// ## Fields
+/// + `roles`: `&[String]` - Roles of the user.
+/// + `tenant`: `Option<&str>` - Tenant of the user.
+/// + `ip`: `IpAddr` - Address of the client.
+/// + `at`: `DateTime<Utc>` - Time of the attempt.
+pub struct LoginContext<'a> {
+    pub roles: &'a [String],
+    pub tenant: Option<&'a str>,
+    pub ip: IpAddr,
+    pub at: DateTime<Utc>,
+}
+
+/// ## Compiled login window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    days: Vec<Weekday>,
+    offset: FixedOffset,
+}
+
+impl LoginWindow {
+    /// ## Compiles the login window settings.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&LoginWindowSettings` - Window settings.
+    ///
+    /// ## Returns
+    /// + `Result<LoginWindow, AppError>`
+    ///     - `Ok(LoginWindow)`: Compiled window.
+    ///     - `Err(AppError)`: If a time, day or offset is invalid.
+    pub fn compile(settings: &LoginWindowSettings) -> Result<Self, AppError> {
+        let start = parse_time(&settings.start)?;
+        let end = parse_time(&settings.end)?;
+
+        let days = settings
+            .days
+            .iter()
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| invalid_policy(format!("Invalid week day '{}'", day)))
+            })
+            .collect::<Result<Vec<Weekday>, AppError>>()?;
+
+        let offset = settings
+            .utc_offset
+            .parse::<FixedOffset>()
+            .map_err(|_| invalid_policy(format!("Invalid UTC offset '{}'", settings.utc_offset)))?;
+
+        Ok(LoginWindow {
+            start,
+            end,
+            days,
+            offset,
+        })
+    }
+
+    /// ## Checks if the time is inside the window.
+    ///
+    /// ## Parameters
+    /// - `at`: `DateTime<Utc>` - Time to check.
+    ///
+    /// ## Returns
+    /// - `bool`: `true` if the time is inside the window.
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.offset);
+
+        if !self.days.is_empty() && !self.days.contains(&local.weekday()) {
+            return false;
+        }
+
+        let time = local.time();
+
+        match self.start <= self.end {
+            true => self.start <= time && time < self.end,
+            false => time >= self.start || time < self.end,
+        }
+    }
+}
+
+/// ## Compiled access policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessPolicy {
+    countries: Option<HashSet<String>>,
+    window: Option<LoginWindow>,
+}
+
+impl AccessPolicy {
+    /// ## Compiles the access policy settings.
+    ///
+    /// Country codes are compared case-insensitively.
+    pub fn compile(settings: &AccessPolicySettings) -> Result<Self, AppError> {
+        let countries = settings.allowed_countries.as_ref().map(|countries| {
+            countries
+                .iter()
+                .map(|country| country.to_ascii_uppercase())
+                .collect()
+        });
+
+        let window = match &settings.login_window {
+            Some(window) => Some(LoginWindow::compile(window)?),
+            None => None,
+        };
+
+        Ok(AccessPolicy { countries, window })
+    }
+}
+
+/// ## Access policy engine struct.
+///
+/// Struct holds the compiled policies and checks every
+/// login attempt against all policies that apply to it.
+pub struct AccessPolicyEngine {
+    roles: HashMap<String, AccessPolicy>,
+    tenants: HashMap<String, AccessPolicy>,
+    geoip: Option<Arc<dyn GeoIpResolver>>,
+}
+
+impl AccessPolicyEngine {
+    /// ## Creates a new `AccessPolicyEngine` instance.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&AccessSettings` - Access settings.
+    /// - `geoip`: `Option<Arc<dyn GeoIpResolver>>` - Country resolver.
+    ///
+    /// ## Returns
+    /// + `Result<AccessPolicyEngine, AppError>`
+    ///     - `Ok(AccessPolicyEngine)`: Engine with compiled policies.
+    ///     - `Err(AppError)`: If any policy is invalid or a country
+    ///       allowlist is configured without a resolver.
+    pub fn new(
+        settings: &AccessSettings,
+        geoip: Option<Arc<dyn GeoIpResolver>>,
+    ) -> Result<Self, AppError> {
+        let mut engine = Self::compile(settings)?;
+
+        let uses_countries = engine
+            .roles
+            .values()
+            .chain(engine.tenants.values())
+            .any(|policy| policy.countries.is_some());
+
+        if uses_countries && geoip.is_none() {
+            return Err(invalid_policy(
+                "Country allowlists require a GeoIP database".to_string(),
+            ));
+        }
+
+        engine.geoip = geoip;
+
+        Ok(engine)
+    }
+
+    /// ## Creates the engine of the settings.
+    ///
+    /// Countries are resolved with the MaxMind database
+    /// of `geoip_db_path`, if one is set.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&AccessSettings` - Access settings.
+    ///
+    /// ## Returns
+    /// + `Result<AccessPolicyEngine, AppError>`
+    ///     - `Ok(AccessPolicyEngine)`: Engine with compiled policies.
+    ///     - `Err(AppError)`: If the database can not be opened or
+    ///       the policies are invalid, see `new`.
+    pub fn from_settings(settings: &AccessSettings) -> Result<Self, AppError> {
+        let geoip = match &settings.geoip_db_path {
+            Some(path) => Some(Arc::new(MaxMindResolver::open(path)?) as Arc<dyn GeoIpResolver>),
+            None => None,
+        };
+
+        Self::new(settings, geoip)
+    }
+
+    /// ## Compiles the policies without a resolver.
+    ///
+    /// Function is used to validate the configuration.
+    pub fn compile(settings: &AccessSettings) -> Result<Self, AppError> {
+        let compile_all = |policies: &HashMap<String, AccessPolicySettings>| {
+            policies
+                .iter()
+                .map(|(name, policy)| Ok((name.clone(), AccessPolicy::compile(policy)?)))
+                .collect::<Result<HashMap<String, AccessPolicy>, AppError>>()
+        };
+
+        Ok(AccessPolicyEngine {
+            roles: compile_all(&settings.roles)?,
+            tenants: compile_all(&settings.tenants)?,
+            geoip: None,
+        })
+    }
+
+    /// ## Checks the login attempt.
+    ///
+    /// Function checks the policies of every role of the
+    /// user and the policy of the tenant. Addresses without
+    /// a known country are denied by country allowlists.
+    ///
+    /// ## Parameters
+    /// - `ctx`: `&LoginContext` - Login attempt context.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the login is allowed.
+    ///     - `Err(AppError)`: `LoginWindowDenied` or `CountryDenied`.
+    pub fn check(&self, ctx: &LoginContext) -> Result<(), AppError> {
+        let role_policies = ctx.roles.iter().filter_map(|role| {
+            self.roles
+                .get(role)
+                .map(|policy| ("role", role.as_str(), policy))
+        });
+        let tenant_policy = ctx.tenant.and_then(|tenant| {
+            self.tenants
+                .get(tenant)
+                .map(|policy| ("tenant", tenant, policy))
+        });
+
+        let mut country: Option<Option<String>> = None;
+
+        for (scope, name, policy) in role_policies.chain(tenant_policy) {
+            if let Some(window) = &policy.window {
+                if !window.contains(ctx.at) {
+                    return Err(deny(
+                        ctx,
+                        ErrorKind::LoginWindowDenied,
+                        format!("Login is not allowed at this time for {} '{}'", scope, name),
+                    ));
+                }
+            }
+
+            if let Some(allowed) = &policy.countries {
+                let country = country.get_or_insert_with(|| {
+                    self.geoip.as_ref().and_then(|geoip| geoip.country(ctx.ip))
+                });
+
+                let is_allowed = country
+                    .as_ref()
+                    .is_some_and(|country| allowed.contains(&country.to_ascii_uppercase()));
+
+                if !is_allowed {
+                    return Err(deny(
+                        ctx,
+                        ErrorKind::CountryDenied,
+                        format!(
+                            "Login is not allowed from this country for {} '{}'",
+                            scope, name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Records the denial and builds the error.
+fn deny(ctx: &LoginContext, kind: ErrorKind, message: String) -> AppError {
+    tracing::warn!(
+        target: AUDIT_TARGET,
+        event = "login.denied",
+        reason = ?kind,
+        ip = %ctx.ip,
+        tenant = ctx.tenant,
+        "{}",
+        message
+    );
+
+    AppError::new(kind, message, None)
+}
+
+/// ## Parses a `HH:MM` time.
+fn parse_time(val: &str) -> Result<NaiveTime, AppError> {
+    NaiveTime::parse_from_str(val, "%H:%M").map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            format!("Invalid login window time '{}'", val),
+            Some(Box::new(e)),
+        )
+    })
+}
+
+/// ## Builds an invalid policy error.
+fn invalid_policy(message: String) -> AppError {
+    AppError::new(ErrorKind::Config, message, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Resolver returning the same country for every address.
+    struct FixedResolver(Option<&'static str>);
+
+    impl GeoIpResolver for FixedResolver {
+        fn country(&self, _ip: IpAddr) -> Option<String> {
+            self.0.map(str::to_string)
+        }
+    }
+
+    fn window(start: &str, end: &str, days: &[&str], utc_offset: &str) -> LoginWindowSettings {
+        LoginWindowSettings {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|day| day.to_string()).collect(),
+            utc_offset: utc_offset.to_string(),
+        }
+    }
+
+    fn settings(policy: AccessPolicySettings) -> AccessSettings {
+        AccessSettings {
+            geoip_db_path: None,
+            roles: HashMap::from([("admin".to_string(), policy)]),
+            tenants: HashMap::new(),
+        }
+    }
+
+    fn login(roles: &[String], hour: u32) -> LoginContext<'_> {
+        LoginContext {
+            roles,
+            tenant: None,
+            ip: "192.0.2.1".parse().unwrap(),
+            // 2024-01-01 is a Monday
+            at: Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap(),
+        }
+    }
+
+    // Test checks if a daytime window allows times inside and denies times outside.
+    #[test]
+    fn test_window_contains() {
+        let window = LoginWindow::compile(&window("08:00", "18:00", &[], "+00:00")).unwrap();
+
+        assert!(window.contains(Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap()));
+        assert!(!window.contains(Utc.with_ymd_and_hms(2024, 1, 1, 18, 0, 0).unwrap()));
+        assert!(!window.contains(Utc.with_ymd_and_hms(2024, 1, 1, 3, 0, 0).unwrap()));
+    }
+
+    // Test checks if a window spanning midnight is handled.
+    #[test]
+    fn test_window_overnight() {
+        let window = LoginWindow::compile(&window("22:00", "06:00", &[], "+00:00")).unwrap();
+
+        assert!(window.contains(Utc.with_ymd_and_hms(2024, 1, 1, 23, 0, 0).unwrap()));
+        assert!(window.contains(Utc.with_ymd_and_hms(2024, 1, 1, 5, 59, 0).unwrap()));
+        assert!(!window.contains(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()));
+    }
+
+    // Test checks if week days and the UTC offset are applied.
+    #[test]
+    fn test_window_days_and_offset() {
+        let window = LoginWindow::compile(&window("08:00", "18:00", &["tue"], "+02:00")).unwrap();
+
+        // Monday 23:00 UTC is Tuesday 01:00 local, outside of the hours
+        assert!(!window.contains(Utc.with_ymd_and_hms(2024, 1, 1, 23, 0, 0).unwrap()));
+        // Tuesday 07:00 UTC is Tuesday 09:00 local
+        assert!(window.contains(Utc.with_ymd_and_hms(2024, 1, 2, 7, 0, 0).unwrap()));
+        // Monday 09:00 local is not an allowed day
+        assert!(!window.contains(Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap()));
+    }
+
+    // Test checks if invalid window settings are rejected.
+    #[test]
+    fn test_window_invalid() {
+        let invalid = [
+            window("8am", "18:00", &[], "+00:00"),
+            window("08:00", "18:00", &["someday"], "+00:00"),
+            window("08:00", "18:00", &[], "CET"),
+        ];
+
+        for settings in invalid {
+            let result = LoginWindow::compile(&settings).unwrap_err();
+
+            assert_eq!(result.kind, ErrorKind::Config);
+        }
+    }
+
+    // Test checks if a login outside of the role window is denied.
+    #[test]
+    fn test_check_window_denied() {
+        let policy = AccessPolicySettings {
+            allowed_countries: None,
+            login_window: Some(window("08:00", "18:00", &[], "+00:00")),
+        };
+        let engine = AccessPolicyEngine::new(&settings(policy), None).unwrap();
+        let roles = vec!["admin".to_string()];
+
+        assert_eq!(engine.check(&login(&roles, 12)), Ok(()));
+        assert_eq!(
+            engine.check(&login(&roles, 20)).unwrap_err().kind,
+            ErrorKind::LoginWindowDenied
+        );
+        assert_eq!(engine.check(&login(&[], 20)), Ok(()));
+    }
+
+    // Test checks if logins from countries outside of the allowlist are denied.
+    #[test]
+    fn test_check_country() {
+        let policy = AccessPolicySettings {
+            allowed_countries: Some(vec!["de".to_string()]),
+            login_window: None,
+        };
+        let roles = vec!["admin".to_string()];
+
+        let cases = [
+            (Some("DE"), true),
+            (Some("US"), false),
+            // Unknown countries are denied
+            (None, false),
+        ];
+
+        for (country, allowed) in cases {
+            let geoip: Arc<dyn GeoIpResolver> = Arc::new(FixedResolver(country));
+            let engine = AccessPolicyEngine::new(&settings(policy.clone()), Some(geoip)).unwrap();
+
+            let result = engine.check(&login(&roles, 12));
+
+            match allowed {
+                true => assert_eq!(result, Ok(())),
+                false => assert_eq!(result.unwrap_err().kind, ErrorKind::CountryDenied),
+            }
+        }
+    }
+
+    // Test checks if tenant policies are enforced.
+    #[test]
+    fn test_check_tenant() {
+        let settings = AccessSettings {
+            geoip_db_path: None,
+            roles: HashMap::new(),
+            tenants: HashMap::from([(
+                "acme".to_string(),
+                AccessPolicySettings {
+                    allowed_countries: None,
+                    login_window: Some(window("08:00", "18:00", &[], "+00:00")),
+                },
+            )]),
+        };
+        let engine = AccessPolicyEngine::new(&settings, None).unwrap();
+        let mut ctx = login(&[], 20);
+        ctx.tenant = Some("acme");
+
+        assert_eq!(
+            engine.check(&ctx).unwrap_err().kind,
+            ErrorKind::LoginWindowDenied
+        );
+    }
+
+    // Test checks if country allowlists without a resolver are rejected.
+    #[test]
+    fn test_new_without_geoip() {
+        let policy = AccessPolicySettings {
+            allowed_countries: Some(vec!["DE".to_string()]),
+            login_window: None,
+        };
+
+        let result = AccessPolicyEngine::new(&settings(policy), None);
+
+        assert_eq!(result.err().unwrap().kind, ErrorKind::Config);
+    }
+
+    // Test checks if the engine of the settings needs a readable GeoIP database.
+    #[test]
+    fn test_from_settings() {
+        let policy = AccessPolicySettings {
+            allowed_countries: Some(vec!["DE".to_string()]),
+            login_window: None,
+        };
+        let unreadable = AccessSettings {
+            geoip_db_path: Some("Cargo.toml".to_string()),
+            ..settings(policy.clone())
+        };
+
+        assert!(AccessPolicyEngine::from_settings(&AccessSettings::default()).is_ok());
+        assert_eq!(
+            AccessPolicyEngine::from_settings(&settings(policy))
+                .err()
+                .unwrap()
+                .kind,
+            ErrorKind::Config
+        );
+        assert_eq!(
+            AccessPolicyEngine::from_settings(&unreadable)
+                .err()
+                .unwrap()
+                .message,
+            "Failed to open GeoIP database 'Cargo.toml'"
+        );
+    }
+}
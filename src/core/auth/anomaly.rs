@@ -0,0 +1,318 @@
+//! Login anomaly module.
+//!
+//! Every login records the device it came from, told
+//! apart by the hash of its user agent, together with the
+//! client address and its country. Logins from a device or
+//! country the user never logged in from are reported to
+//! the user through the `login.anomaly` webhook, the
+//! receiving endpoint emails the address.
+
+// Imports from external crates
+use serde::Serialize;
+use std::{net::IpAddr, sync::Arc};
+
+// Local imports
+use super::access::{GeoIpResolver, MaxMindResolver};
+use crate::core::config::{AppConfig, LoginAnomalySettings};
+use crate::core::db::DbExecutor;
+use crate::core::err::AppError;
+use crate::core::repo::postgres::PgDeviceRepo;
+use crate::core::repo::{hash_token, Device, DeviceLogin, DeviceRepo};
+use crate::core::users::User;
+use crate::core::webhooks::{WebhookEvent, Webhooks};
+
+/// ## Reason of a login anomaly.
+///
+/// # Variants
+/// - `NewDevice` - `new_device`, user never logged in from the device.
+/// - `NewCountry` - `new_country`, user never logged in from the country.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyReason {
+    NewDevice,
+    NewCountry,
+}
+
+/// ## Login anomaly struct.
+///
+/// ## Fields
+/// + `reasons`: `Vec<AnomalyReason>` - What was new about the login.
+/// + `device`: `Device` - Device of the login.
+/// + `country`: `Option<String>` - Country of the client address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub reasons: Vec<AnomalyReason>,
+    pub device: Device,
+    pub country: Option<String>,
+}
+
+/// ## Login anomaly detector struct.
+///
+/// ## Fields
+/// + `devices`: `Arc<dyn DeviceRepo>` - Known devices of the users.
+/// + `geoip`: `Option<Arc<dyn GeoIpResolver>>` - Country resolver, countries
+///   are unknown without it.
+/// + `webhooks`: `Webhooks` - Webhooks delivering the reports.
+/// + `settings`: `LoginAnomalySettings` - Which anomalies are reported.
+#[derive(Clone)]
+pub struct LoginAnomalies {
+    pub devices: Arc<dyn DeviceRepo>,
+    pub geoip: Option<Arc<dyn GeoIpResolver>>,
+    pub webhooks: Webhooks,
+    pub settings: LoginAnomalySettings,
+}
+
+impl LoginAnomalies {
+    /// ## Creates a new `LoginAnomalies` backed by Postgres.
+    ///
+    /// GeoIP database is only opened when reports of new
+    /// countries are enabled.
+    ///
+    /// ## Parameters
+    /// - `config`: `&AppConfig` - Login anomaly and GeoIP settings.
+    /// - `db`: `impl Into<DbExecutor>` - Executor or pool of the devices.
+    /// - `webhooks`: `Webhooks` - Queue of the webhook events.
+    ///
+    /// ## Returns
+    /// + `Result<LoginAnomalies, AppError>`
+    ///     - `Ok(LoginAnomalies)`: New detector.
+    ///     - `Err(AppError)`: If the GeoIP database can't be opened.
+    pub fn new(
+        config: &AppConfig,
+        db: impl Into<DbExecutor>,
+        webhooks: Webhooks,
+    ) -> Result<Self, AppError> {
+        let settings = config.login_anomalies.clone();
+        let geoip = match &config.access.geoip_db_path {
+            Some(path) if settings.enabled && settings.new_country => {
+                Some(Arc::new(MaxMindResolver::open(path)?) as Arc<dyn GeoIpResolver>)
+            }
+            _ => None,
+        };
+
+        Ok(LoginAnomalies {
+            devices: Arc::new(PgDeviceRepo::new(db)),
+            geoip,
+            webhooks,
+            settings,
+        })
+    }
+
+    /// ## Records the login and reports it if it is an anomaly.
+    ///
+    /// Nothing is recorded while the detector is disabled.
+    /// The first login of a user is recorded without being
+    /// reported, there is nothing to compare it to.
+    ///
+    /// ## Parameters
+    /// - `user`: `&User` - User that logged in.
+    /// - `ip`: `Option<IpAddr>` - Client address of the login.
+    /// - `user_agent`: `Option<&str>` - User agent of the login.
+    ///
+    /// ## Returns
+    /// + `Result<Option<Anomaly>, AppError>`
+    ///     - `Ok(Some(Anomaly))`: Reported anomaly.
+    ///     - `Ok(None)`: If the login is not reported.
+    ///     - `Err(AppError)`: If the device or the report could not be stored.
+    pub async fn observe(
+        &self,
+        user: &User,
+        ip: Option<IpAddr>,
+        user_agent: Option<&str>,
+    ) -> Result<Option<Anomaly>, AppError> {
+        if !self.settings.enabled {
+            return Ok(None);
+        }
+
+        let country = ip.zip(self.geoip.as_ref()).and_then(|(ip, geoip)| {
+            geoip
+                .country(ip)
+                .map(|country| country.to_ascii_uppercase())
+        });
+        let sighting = self
+            .devices
+            .record(DeviceLogin {
+                user_id: user.id,
+                fingerprint: fingerprint(user_agent),
+                user_agent: user_agent.map(str::to_string),
+                ip: ip.map(|ip| ip.to_string()),
+                country: country.clone(),
+            })
+            .await?;
+        if sighting.first_login {
+            return Ok(None);
+        }
+
+        let reasons: Vec<AnomalyReason> = [
+            (sighting.new_device && self.settings.new_device).then_some(AnomalyReason::NewDevice),
+            (sighting.new_country && self.settings.new_country)
+                .then_some(AnomalyReason::NewCountry),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if reasons.is_empty() {
+            return Ok(None);
+        }
+
+        let anomaly = Anomaly {
+            reasons,
+            device: sighting.device,
+            country,
+        };
+        let data = serde_json::json!({
+            "user_id": user.id,
+            "email": user.email,
+            "reasons": anomaly.reasons,
+            "device_id": anomaly.device.id,
+            "user_agent": anomaly.device.user_agent,
+            "ip": anomaly.device.ip,
+            "country": anomaly.country,
+            "occurred_at": anomaly.device.last_seen_at,
+        });
+        self.webhooks.emit(WebhookEvent::LoginAnomaly, data).await?;
+
+        Ok(Some(anomaly))
+    }
+}
+
+/// ## Returns the fingerprint of the device.
+///
+/// Fingerprint is the SHA-256 hash of the trimmed user
+/// agent, logins without one share a single device.
+pub fn fingerprint(user_agent: Option<&str>) -> String {
+    hash_token(user_agent.unwrap_or_default().trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::repo::memory::MemoryDeviceRepo;
+    use chrono::Utc;
+    use sqlx::postgres::PgPoolOptions;
+    use uuid::Uuid;
+
+    /// Resolves `192.0.2.0/24` to Germany and everything else to France.
+    struct FixedResolver;
+
+    impl GeoIpResolver for FixedResolver {
+        fn country(&self, ip: IpAddr) -> Option<String> {
+            match ip.to_string().starts_with("192.0.2.") {
+                true => Some("de".to_string()),
+                false => Some("fr".to_string()),
+            }
+        }
+    }
+
+    /// Returns an enabled detector, webhooks never connect.
+    fn anomalies(settings: LoginAnomalySettings) -> LoginAnomalies {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+
+        LoginAnomalies {
+            devices: Arc::new(MemoryDeviceRepo::default()),
+            geoip: Some(Arc::new(FixedResolver)),
+            webhooks: Webhooks::new(pool, &Default::default()),
+            settings,
+        }
+    }
+
+    fn user() -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: "ada@example.com".to_string(),
+            display_name: None,
+            org_id: None,
+            disabled: false,
+            password_reset_required: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_at_display: None,
+            updated_at_display: None,
+        }
+    }
+
+    // Test checks if new devices and countries are reported after the first login.
+    #[tokio::test]
+    async fn test_observe() {
+        let anomalies = anomalies(LoginAnomalySettings {
+            enabled: true,
+            ..LoginAnomalySettings::default()
+        });
+        let user = user();
+        let home = "192.0.2.10".parse().ok();
+        let abroad = "198.51.100.1".parse().ok();
+
+        assert_eq!(
+            anomalies
+                .observe(&user, home, Some("Laptop"))
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            anomalies
+                .observe(&user, home, Some("Laptop"))
+                .await
+                .unwrap(),
+            None
+        );
+
+        let phone = anomalies
+            .observe(&user, home, Some("Phone"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(phone.reasons, [AnomalyReason::NewDevice]);
+        assert_eq!(phone.country.as_deref(), Some("DE"));
+
+        let travel = anomalies
+            .observe(&user, abroad, Some("Laptop"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(travel.reasons, [AnomalyReason::NewCountry]);
+        assert_eq!(travel.device.countries, ["DE", "FR"]);
+        assert_eq!(anomalies.devices.list(user.id).await.unwrap().len(), 2);
+    }
+
+    // Test checks if disabled detectors and reasons record or report nothing.
+    #[tokio::test]
+    async fn test_observe_disabled() {
+        let disabled = anomalies(LoginAnomalySettings::default());
+        let user = user();
+
+        disabled.observe(&user, None, Some("Laptop")).await.unwrap();
+
+        assert!(disabled.devices.list(user.id).await.unwrap().is_empty());
+
+        let devices_only = anomalies(LoginAnomalySettings {
+            enabled: true,
+            new_country: false,
+            ..LoginAnomalySettings::default()
+        });
+        let abroad = "198.51.100.1".parse().ok();
+        devices_only
+            .observe(&user, "192.0.2.10".parse().ok(), Some("Laptop"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            devices_only
+                .observe(&user, abroad, Some("Laptop"))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    // Test checks if the fingerprint ignores surrounding whitespace.
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(Some(" Laptop ")), fingerprint(Some("Laptop")));
+        assert_ne!(fingerprint(Some("Laptop")), fingerprint(None));
+    }
+}
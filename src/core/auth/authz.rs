@@ -0,0 +1,318 @@
+//! Route authorization module.
+//!
+//! Module compiles the `[authz]` rules of the configuration
+//! into a matcher of request paths. A rule lists the roles
+//! and scopes of which the caller needs at least one, every
+//! rule matching the path has to be satisfied. Rules only
+//! tighten access, the routes still check their own policy.
+
+// Imports from external crates
+use std::fmt;
+
+// Local imports
+use crate::core::config::AuthzSettings;
+use crate::core::err::AppError;
+
+/// Prefix of the requirements naming a scope of the token.
+pub const SCOPE_PREFIX: &str = "scope:";
+
+/// ## Requirement of a rule.
+///
+/// # Variants
+/// - `Role` - Role granted to the user.
+/// - `Scope` - Scope of the access token, written `scope:<name>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Requirement {
+    Role(String),
+    Scope(String),
+}
+
+impl Requirement {
+    /// ## Parses a requirement of the configuration.
+    ///
+    /// ## Parameters
+    /// - `value`: `&str` - Role name or `scope:` followed by the scope.
+    ///
+    /// ## Returns
+    /// + `Option<Requirement>`
+    ///     - `Some(Requirement)`: If the value names a role or scope.
+    ///     - `None`: If the name is empty or contains whitespace.
+    pub fn parse(value: &str) -> Option<Self> {
+        let requirement = match value.strip_prefix(SCOPE_PREFIX) {
+            Some(scope) => Requirement::Scope(scope.to_string()),
+            None => Requirement::Role(value.to_string()),
+        };
+        let name = match &requirement {
+            Requirement::Role(name) | Requirement::Scope(name) => name,
+        };
+
+        match name.is_empty() || name.chars().any(char::is_whitespace) {
+            true => None,
+            false => Some(requirement),
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Requirement::Role(role) => write!(f, "{}", role),
+            Requirement::Scope(scope) => write!(f, "{}{}", SCOPE_PREFIX, scope),
+        }
+    }
+}
+
+/// ## Segment of a route pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Segment matching itself.
+    Literal(String),
+    /// `*` or `:name`, matching any one segment.
+    Any,
+    /// Trailing `*`, matching the rest of the path.
+    Rest,
+}
+
+/// ## Rule struct.
+///
+/// ## Fields
+/// + `pattern`: `String` - Pattern of the rule as configured.
+/// + `requirements`: `Vec<Requirement>` - Roles and scopes of which
+///   the caller needs at least one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub pattern: String,
+    pub requirements: Vec<Requirement>,
+    segments: Vec<Segment>,
+}
+
+impl Rule {
+    /// ## Checks if the rule applies to the path.
+    ///
+    /// ## Parameters
+    /// - `path`: `&str` - Path of the request or route.
+    pub fn matches(&self, path: &str) -> bool {
+        let mut parts = path.split('/').filter(|part| !part.is_empty());
+        for segment in &self.segments {
+            match (segment, parts.next()) {
+                (Segment::Rest, _) => return true,
+                (Segment::Any, Some(_)) => {}
+                (Segment::Literal(literal), Some(part)) if literal == part => {}
+                _ => return false,
+            }
+        }
+
+        parts.next().is_none()
+    }
+
+    /// ## Checks if the roles or scopes meet a requirement of the rule.
+    ///
+    /// ## Parameters
+    /// - `roles`: `&[String]` - Roles granted to the user.
+    /// - `scopes`: `&[String]` - Scopes of the access token.
+    pub fn permits(&self, roles: &[String], scopes: &[String]) -> bool {
+        self.requirements
+            .iter()
+            .any(|requirement| match requirement {
+                Requirement::Role(role) => roles.contains(role),
+                Requirement::Scope(scope) => scopes.contains(scope),
+            })
+    }
+
+    /// ## Checks if the rule can be met without the roles.
+    fn scopes_only(&self) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| matches!(requirement, Requirement::Scope(_)))
+    }
+}
+
+/// ## Route policy struct.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::authz::RoutePolicy;
+/// use axum_auth::core::config::AuthzSettings;
+/// use std::collections::HashMap;
+///
+/// let policy = RoutePolicy::compile(&AuthzSettings {
+///     routes: HashMap::from([
+///         ("/admin/*".to_string(), vec!["admin".to_string()]),
+///         ("/me/sessions".to_string(), vec!["scope:sessions".to_string()]),
+///     ]),
+/// })
+/// .unwrap();
+///
+/// assert_eq!(policy.rules("/admin/users/42").len(), 1);
+/// assert!(policy.rules("/me").is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoutePolicy {
+    rules: Vec<Rule>,
+}
+
+impl RoutePolicy {
+    /// ## Compiles the rules of the settings.
+    ///
+    /// ## Returns
+    /// + `Result<RoutePolicy, AppError>`
+    ///     - `Ok(RoutePolicy)`: Rules ordered by pattern.
+    ///     - `Err(AppError)`: `Config` if a pattern does not start with `/`,
+    ///       has a `*` inside a segment or lists no requirement,
+    ///       or a requirement is empty.
+    pub fn compile(settings: &AuthzSettings) -> Result<Self, AppError> {
+        let mut rules = settings
+            .routes
+            .iter()
+            .map(|(pattern, requirements)| compile_rule(pattern, requirements))
+            .collect::<Result<Vec<_>, _>>()?;
+        rules.sort_by(|a, b| a.pattern.cmp(&b.pattern));
+
+        Ok(RoutePolicy { rules })
+    }
+
+    /// ## Checks if the policy has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// ## Returns the rules applying to the path.
+    pub fn rules(&self, path: &str) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(path))
+            .collect()
+    }
+
+    /// ## Checks if every rule of the path can be met without the roles.
+    pub fn scopes_only(&self, path: &str) -> bool {
+        self.rules(path).into_iter().all(Rule::scopes_only)
+    }
+}
+
+/// ## Compiles the rule of a pattern.
+fn compile_rule(pattern: &str, requirements: &[String]) -> Result<Rule, AppError> {
+    let Some(path) = pattern.strip_prefix('/') else {
+        return Err(AppError::config(format!(
+            "Route pattern '{}' of authz.routes has to start with '/'",
+            pattern
+        )));
+    };
+
+    let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+    let segments = parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| match *part {
+            "*" if i + 1 == parts.len() => Ok(Segment::Rest),
+            "*" => Ok(Segment::Any),
+            part if part.starts_with(':') => Ok(Segment::Any),
+            part if part.contains('*') => Err(AppError::config(format!(
+                "Route pattern '{}' of authz.routes has a '*' inside a segment",
+                pattern
+            ))),
+            part => Ok(Segment::Literal(part.to_string())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if requirements.is_empty() {
+        return Err(AppError::config(format!(
+            "Route pattern '{}' of authz.routes lists no role or scope",
+            pattern
+        )));
+    }
+    let requirements = requirements
+        .iter()
+        .map(|value| {
+            Requirement::parse(value).ok_or_else(|| {
+                AppError::config(format!(
+                    "'{}' of authz.routes '{}' is not a valid role or scope",
+                    value, pattern
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Rule {
+        pattern: pattern.to_string(),
+        requirements,
+        segments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn policy(routes: &[(&str, &[&str])]) -> Result<RoutePolicy, AppError> {
+        RoutePolicy::compile(&AuthzSettings {
+            routes: routes
+                .iter()
+                .map(|(pattern, requirements)| {
+                    let requirements = requirements.iter().map(|r| r.to_string()).collect();
+                    (pattern.to_string(), requirements)
+                })
+                .collect::<HashMap<_, _>>(),
+        })
+    }
+
+    // Test checks if wildcards, parameters and trailing slashes are matched.
+    #[test]
+    fn test_matches() {
+        let policy = policy(&[
+            ("/admin/*", &["admin"]),
+            ("/me/sessions/:id", &["scope:sessions"]),
+            ("/oauth/*/code", &["ops"]),
+        ])
+        .unwrap();
+        let patterns = |path: &str| -> Vec<String> {
+            policy
+                .rules(path)
+                .into_iter()
+                .map(|rule| rule.pattern.clone())
+                .collect()
+        };
+
+        assert_eq!(patterns("/admin"), vec!["/admin/*"]);
+        assert_eq!(patterns("/admin/users/42/roles/"), vec!["/admin/*"]);
+        assert_eq!(patterns("/me/sessions/42"), vec!["/me/sessions/:id"]);
+        assert_eq!(patterns("/me/sessions/:id"), vec!["/me/sessions/:id"]);
+        assert_eq!(patterns("/oauth/device/code"), vec!["/oauth/*/code"]);
+        assert!(patterns("/me/sessions").is_empty());
+        assert!(patterns("/oauth/code").is_empty());
+        assert!(patterns("/administration").is_empty());
+    }
+
+    // Test checks if one role or scope of a rule is enough.
+    #[test]
+    fn test_permits() {
+        let policy = policy(&[("/admin/*", &["admin", "scope:admin:read"])]).unwrap();
+        let rule = policy.rules("/admin/users")[0];
+        let strings = |values: &[&str]| -> Vec<String> {
+            values.iter().map(|value| value.to_string()).collect()
+        };
+
+        assert!(rule.permits(&strings(&["viewer", "admin"]), &[]));
+        assert!(rule.permits(&[], &strings(&["admin:read"])));
+        assert!(!rule.permits(&strings(&["admin:read"]), &strings(&["admin"])));
+        assert!(!policy.scopes_only("/admin/users"));
+        assert!(policy.scopes_only("/me"));
+    }
+
+    // Test checks if invalid patterns and requirements are rejected.
+    #[test]
+    fn test_compile_invalid() {
+        for (pattern, requirements) in [
+            ("admin/*", &["admin"][..]),
+            ("/ad*min", &["admin"][..]),
+            ("/admin", &[][..]),
+            ("/admin", &["scope:"][..]),
+            ("/admin", &["org admin"][..]),
+        ] {
+            let result = policy(&[(pattern, requirements)]);
+
+            assert!(result.is_err(), "{} {:?}", pattern, requirements);
+        }
+    }
+}
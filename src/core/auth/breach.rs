@@ -0,0 +1,457 @@
+//! Breached password module.
+//!
+//! Module defines the `BreachedPasswords` trait the
+//! password policy consults for new passwords, the client
+//! of the Have I Been Pwned range API, built with the
+//! `hibp` feature, and the offline Bloom filter answering
+//! when the API can not.
+//!
+//! Passwords are only ever compared by their SHA-1 hash,
+//! the range API learns the first 5 hex characters of it.
+
+// Imports from external crates
+use async_trait::async_trait;
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+#[cfg(feature = "hibp")]
+use std::time::Duration;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+
+/// Range API of Have I Been Pwned.
+pub const HIBP_RANGE_URL: &str = "https://api.pwnedpasswords.com/range/";
+
+/// Leading bytes of a Bloom filter file.
+const BLOOM_MAGIC: &[u8; 4] = b"AABF";
+
+/// Length of the Bloom filter header, magic, hashes and bits.
+const BLOOM_HEADER_LEN: usize = 16;
+
+/// ## Breached passwords trait.
+///
+/// Trait is implemented by the sources of passwords
+/// known from data breaches.
+///
+/// ## Examples
+/// ```
+/// use async_trait::async_trait;
+/// use axum_auth::core::auth::breach::BreachedPasswords;
+/// use axum_auth::core::err::AppError;
+///
+/// struct Blocklist(Vec<String>);
+///
+/// #[async_trait]
+/// impl BreachedPasswords for Blocklist {
+///     fn name(&self) -> &str {
+///         "blocklist"
+///     }
+///
+///     async fn contains(&self, password: &str) -> Result<bool, AppError> {
+///         Ok(self.0.iter().any(|known| known == password))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait BreachedPasswords: Send + Sync {
+    /// Name of the source, used in logs.
+    fn name(&self) -> &str;
+
+    /// Checks if the password appeared in a breach.
+    async fn contains(&self, password: &str) -> Result<bool, AppError>;
+}
+
+/// ## Returns the SHA-1 hash of the password.
+fn sha1(password: &str) -> [u8; 20] {
+    let mut hash = [0; 20];
+    hash.copy_from_slice(digest(&SHA1_FOR_LEGACY_USE_ONLY, password.as_bytes()).as_ref());
+    hash
+}
+
+/// ## Client of the Have I Been Pwned range API struct.
+///
+/// Responses are padded with fake hashes, so their size
+/// does not tell the prefix apart either.
+///
+/// ## Examples
+/// ```
+/// use std::time::Duration;
+/// use axum_auth::core::auth::breach::{BreachedPasswords, HibpRange, HIBP_RANGE_URL};
+///
+/// let hibp = HibpRange::new(HIBP_RANGE_URL, Duration::from_secs(3)).unwrap();
+///
+/// assert_eq!(hibp.name(), "hibp");
+/// ```
+#[cfg(feature = "hibp")]
+pub struct HibpRange {
+    url: String,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "hibp")]
+impl HibpRange {
+    /// ## Creates the client of the range API.
+    ///
+    /// ## Parameters
+    /// - `url`: `&str` - URL of the API, the hash prefix is appended.
+    /// - `timeout`: `Duration` - Time the API may take to answer.
+    ///
+    /// ## Returns
+    /// + `Result<HibpRange, AppError>`
+    ///     - `Ok(HibpRange)`: New client.
+    ///     - `Err(AppError)`: If the HTTP client can not be built.
+    pub fn new(url: &str, timeout: Duration) -> Result<Self, AppError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(concat!("axum_auth/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| {
+                AppError::new(
+                    ErrorKind::Internal,
+                    "Failed to build the Have I Been Pwned client".to_string(),
+                    Some(Box::new(e)),
+                )
+            })?;
+
+        Ok(HibpRange {
+            url: url.to_string(),
+            client,
+        })
+    }
+}
+
+#[cfg(feature = "hibp")]
+#[async_trait]
+impl BreachedPasswords for HibpRange {
+    fn name(&self) -> &str {
+        "hibp"
+    }
+
+    async fn contains(&self, password: &str) -> Result<bool, AppError> {
+        let failed = |e: reqwest::Error| {
+            AppError::new(
+                ErrorKind::External,
+                "Have I Been Pwned range API failed".to_string(),
+                Some(Box::new(e)),
+            )
+        };
+
+        let hash = hex::encode_upper(sha1(password));
+        let (prefix, suffix) = hash.split_at(5);
+        let response = self
+            .client
+            .get(format!("{}{}", self.url, prefix))
+            .header("Add-Padding", "true")
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        let body = match response {
+            Ok(response) => response.text().await,
+            Err(e) => Err(e),
+        }
+        .map_err(failed)?;
+
+        // Padding entries have a count of 0
+        Ok(body.lines().any(|line| {
+            line.split_once(':').is_some_and(|(candidate, count)| {
+                candidate.trim().eq_ignore_ascii_case(suffix)
+                    && count.trim().parse::<u64>().is_ok_and(|count| count > 0)
+            })
+        }))
+    }
+}
+
+/// ## Bloom filter of breached passwords struct.
+///
+/// Filter holds the SHA-1 hashes of a breach list and
+/// answers offline. It never misses a listed password,
+/// but takes other passwords for breached ones at the
+/// rate it was built with.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::breach::BloomFilter;
+///
+/// // SHA-1 of "password", as listed by Have I Been Pwned
+/// let list = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\n";
+/// let filter = BloomFilter::build(list.as_bytes(), 1, 0.001).unwrap();
+///
+/// assert!(filter.contains_password("password"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    len: u64,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// ## Creates an empty filter sized for the items.
+    ///
+    /// ## Parameters
+    /// - `items`: `u64` - Number of hashes the filter will hold.
+    /// - `false_positive_rate`: `f64` - Share of unlisted passwords taken
+    ///   for breached ones, between 0 and 1.
+    pub fn new(items: u64, false_positive_rate: f64) -> Self {
+        let items = items.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let len = (-items * rate.ln() / std::f64::consts::LN_2.powi(2)).ceil() as u64;
+        let len = len.max(8);
+        let hashes = ((len as f64 / items) * std::f64::consts::LN_2).round() as u32;
+
+        BloomFilter {
+            bits: vec![0; len.div_ceil(8) as usize],
+            len,
+            hashes: hashes.clamp(1, 32),
+        }
+    }
+
+    /// ## Builds the filter of a breach list.
+    ///
+    /// List has one hex SHA-1 hash per line, optionally
+    /// followed by `:` and a count, like the downloads of
+    /// Have I Been Pwned.
+    ///
+    /// ## Parameters
+    /// - `list`: `impl BufRead` - Breach list.
+    /// - `items`: `u64` - Number of hashes in the list.
+    /// - `false_positive_rate`: `f64` - Share of unlisted passwords taken
+    ///   for breached ones.
+    ///
+    /// ## Returns
+    /// + `Result<BloomFilter, AppError>`
+    ///     - `Ok(BloomFilter)`: Filter holding every hash of the list.
+    ///     - `Err(AppError)`: If the list can not be read or a line is
+    ///       not a SHA-1 hash.
+    pub fn build(
+        list: impl BufRead,
+        items: u64,
+        false_positive_rate: f64,
+    ) -> Result<Self, AppError> {
+        let mut filter = BloomFilter::new(items, false_positive_rate);
+
+        for (number, line) in list.lines().enumerate() {
+            let line = line.map_err(|e| {
+                AppError::new(
+                    ErrorKind::Internal,
+                    "Failed to read the breach list".to_string(),
+                    Some(Box::new(e)),
+                )
+            })?;
+            let hash = line.split(':').next().unwrap_or_default().trim();
+            if hash.is_empty() {
+                continue;
+            }
+
+            let mut digest = [0; 20];
+            hex::decode_to_slice(hash, &mut digest).map_err(|_| {
+                AppError::validation(format!(
+                    "Line {} of the breach list is not a SHA-1 hash",
+                    number + 1
+                ))
+            })?;
+            filter.insert(&digest);
+        }
+
+        Ok(filter)
+    }
+
+    /// ## Builds the filter of a breach list file.
+    ///
+    /// File is read twice, first to count its hashes.
+    ///
+    /// ## Parameters
+    /// - `path`: `&Path` - Breach list, see `build`.
+    /// - `false_positive_rate`: `f64` - Share of unlisted passwords taken
+    ///   for breached ones.
+    ///
+    /// ## Returns
+    /// + `Result<BloomFilter, AppError>`
+    ///     - `Ok(BloomFilter)`: Filter holding every hash of the file.
+    ///     - `Err(AppError)`: If the file can not be read or is no list.
+    pub fn build_file(path: &Path, false_positive_rate: f64) -> Result<Self, AppError> {
+        let open = || {
+            File::open(path).map(BufReader::new).map_err(|e| {
+                AppError::new(
+                    ErrorKind::Internal,
+                    format!("Failed to open the breach list '{}'", path.display()),
+                    Some(Box::new(e)),
+                )
+            })
+        };
+
+        let items = open()?
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.trim().is_empty())
+            .count() as u64;
+
+        BloomFilter::build(open()?, items, false_positive_rate)
+    }
+
+    /// ## Reads a filter written by `to_bytes`.
+    ///
+    /// ## Parameters
+    /// - `bytes`: `&[u8]` - Content of the filter file.
+    ///
+    /// ## Returns
+    /// + `Result<BloomFilter, AppError>`
+    ///     - `Ok(BloomFilter)`: Filter of the file.
+    ///     - `Err(AppError)`: `Config` if the file is no filter.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AppError> {
+        let invalid = || AppError::config("Bloom filter file is invalid or truncated");
+        if bytes.len() < BLOOM_HEADER_LEN || &bytes[..4] != BLOOM_MAGIC {
+            return Err(invalid());
+        }
+
+        let hashes = u32::from_le_bytes(bytes[4..8].try_into().map_err(|_| invalid())?);
+        let len = u64::from_le_bytes(bytes[8..16].try_into().map_err(|_| invalid())?);
+        let bits = bytes[BLOOM_HEADER_LEN..].to_vec();
+        if hashes == 0 || len == 0 || len.div_ceil(8) != bits.len() as u64 {
+            return Err(invalid());
+        }
+
+        Ok(BloomFilter { bits, len, hashes })
+    }
+
+    /// ## Returns the filter as stored in a file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(BLOOM_HEADER_LEN + self.bits.len());
+        bytes.extend_from_slice(BLOOM_MAGIC);
+        bytes.extend_from_slice(&self.hashes.to_le_bytes());
+        bytes.extend_from_slice(&self.len.to_le_bytes());
+        bytes.extend_from_slice(&self.bits);
+        bytes
+    }
+
+    /// ## Loads the filter file.
+    ///
+    /// ## Parameters
+    /// - `path`: `impl AsRef<Path>` - Filter written by `axum_auth breach-filter`.
+    ///
+    /// ## Returns
+    /// + `Result<BloomFilter, AppError>`
+    ///     - `Ok(BloomFilter)`: Filter of the file.
+    ///     - `Err(AppError)`: `Config` if the file can not be read or is
+    ///       no filter.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| {
+            AppError::new(
+                ErrorKind::Config,
+                format!("Failed to read the Bloom filter '{}'", path.display()),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        BloomFilter::from_bytes(&bytes)
+    }
+
+    /// ## Returns the bits of the hash, by double hashing.
+    fn positions(&self, hash: &[u8; 20]) -> impl Iterator<Item = u64> + '_ {
+        let mut first = [0; 8];
+        let mut second = [0; 8];
+        first.copy_from_slice(&hash[..8]);
+        second.copy_from_slice(&hash[8..16]);
+        let first = u64::from_le_bytes(first);
+        let second = u64::from_le_bytes(second) | 1;
+
+        (0..u64::from(self.hashes))
+            .map(move |i| first.wrapping_add(i.wrapping_mul(second)) % self.len)
+    }
+
+    /// ## Adds the SHA-1 hash of a password.
+    pub fn insert(&mut self, hash: &[u8; 20]) {
+        let positions: Vec<u64> = self.positions(hash).collect();
+        for position in positions {
+            self.bits[(position / 8) as usize] |= 1 << (position % 8);
+        }
+    }
+
+    /// ## Checks if the password may be in the filter.
+    pub fn contains_password(&self, password: &str) -> bool {
+        let hash = sha1(password);
+
+        self.positions(&hash)
+            .all(|position| self.bits[(position / 8) as usize] & (1 << (position % 8)) != 0)
+    }
+}
+
+#[async_trait]
+impl BreachedPasswords for BloomFilter {
+    fn name(&self) -> &str {
+        "bloom_filter"
+    }
+
+    async fn contains(&self, password: &str) -> Result<bool, AppError> {
+        Ok(self.contains_password(password))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(passwords: &[&str]) -> String {
+        passwords
+            .iter()
+            .map(|password| format!("{}:1\n", hex::encode_upper(sha1(password))))
+            .collect()
+    }
+
+    // Test checks if built filters find their passwords and survive a round trip.
+    #[test]
+    fn test_bloom_filter() {
+        let breached: Vec<String> = (0..1000).map(|i| format!("breached-{}", i)).collect();
+        let breached: Vec<&str> = breached.iter().map(String::as_str).collect();
+        let filter = BloomFilter::build(list(&breached).as_bytes(), 1000, 0.001).unwrap();
+
+        assert!(breached
+            .iter()
+            .all(|password| filter.contains_password(password)));
+        let false_positives = (0..1000)
+            .filter(|i| filter.contains_password(&format!("unlisted-{}", i)))
+            .count();
+        assert!(false_positives < 10, "{}", false_positives);
+
+        let loaded = BloomFilter::from_bytes(&filter.to_bytes()).unwrap();
+        assert_eq!(loaded, filter);
+        assert!(BloomFilter::from_bytes(&filter.to_bytes()[..20]).is_err());
+        assert!(BloomFilter::build("not a hash\n".as_bytes(), 1, 0.001).is_err());
+    }
+
+    // Test checks if the range API is asked by prefix and padding entries are ignored.
+    #[cfg(feature = "hibp")]
+    #[tokio::test]
+    async fn test_hibp_range() {
+        use axum::{extract::Path, http::HeaderMap, routing::get, Router};
+
+        let breached = hex::encode_upper(sha1("password"));
+        let padding = hex::encode_upper(sha1("padding"));
+        let body = format!("{}:3\r\n{}:0\r\n", &breached[5..], &padding[5..]);
+        let app = Router::new().route(
+            "/range/:prefix",
+            get(
+                move |Path(prefix): Path<String>, headers: HeaderMap| async move {
+                    match prefix.len() == 5 && headers.contains_key("add-padding") {
+                        true => body,
+                        false => String::new(),
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let hibp =
+            HibpRange::new(&format!("http://{}/range/", addr), Duration::from_secs(3)).unwrap();
+
+        assert_eq!(hibp.contains("password").await, Ok(true));
+        assert_eq!(hibp.contains("padding").await, Ok(false));
+    }
+}
@@ -0,0 +1,213 @@
+//! CAPTCHA module.
+//!
+//! Module defines the `CaptchaVerifier` trait the login
+//! throttle consults once a client failed too often, and
+//! the verifier of the `siteverify` API shared by hCaptcha
+//! and Cloudflare Turnstile.
+
+// Imports from external crates
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::net::IpAddr;
+
+// Local imports
+use crate::core::config::CaptchaProvider;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::types::secret::Secret;
+
+/// Verification endpoint of hCaptcha.
+pub const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+
+/// Verification endpoint of Cloudflare Turnstile.
+pub const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+
+/// ## CAPTCHA verifier trait.
+///
+/// Trait is implemented by the services checking the
+/// response token of a solved CAPTCHA.
+///
+/// ## Examples
+/// ```
+/// use std::net::IpAddr;
+/// use async_trait::async_trait;
+/// use axum_auth::core::auth::captcha::CaptchaVerifier;
+/// use axum_auth::core::err::AppError;
+///
+/// struct AlwaysSolved;
+///
+/// #[async_trait]
+/// impl CaptchaVerifier for AlwaysSolved {
+///     fn name(&self) -> &str {
+///         "always-solved"
+///     }
+///
+///     async fn verify(&self, _token: &str, _ip: Option<IpAddr>) -> Result<bool, AppError> {
+///         Ok(true)
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait CaptchaVerifier: Send + Sync {
+    /// Name of the service, used in error messages.
+    fn name(&self) -> &str;
+
+    /// Checks if the token belongs to a solved CAPTCHA
+    /// of the client.
+    async fn verify(&self, token: &str, ip: Option<IpAddr>) -> Result<bool, AppError>;
+}
+
+/// ## Answer of the `siteverify` API struct.
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(rename = "error-codes", default)]
+    error_codes: Vec<String>,
+}
+
+/// ## Verifier of the `siteverify` API struct.
+///
+/// ## Fields
+/// + `name`: `&'static str` - Name of the service.
+/// + `url`: `String` - Verification endpoint.
+/// + `secret`: `Secret<String>` - Secret key of the site.
+/// + `client`: `reqwest::Client` - HTTP client.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::captcha::{CaptchaVerifier, SiteVerify};
+///
+/// let verifier = SiteVerify::turnstile("0x4AAAAAAA-secret".into());
+///
+/// assert_eq!(verifier.name(), "turnstile");
+/// ```
+pub struct SiteVerify {
+    name: &'static str,
+    url: String,
+    secret: Secret<String>,
+    client: reqwest::Client,
+}
+
+impl SiteVerify {
+    /// ## Creates the verifier of the provider.
+    pub fn new(provider: CaptchaProvider, secret: Secret<String>) -> Self {
+        match provider {
+            CaptchaProvider::Hcaptcha => SiteVerify::hcaptcha(secret),
+            CaptchaProvider::Turnstile => SiteVerify::turnstile(secret),
+        }
+    }
+
+    /// ## Creates the hCaptcha verifier.
+    pub fn hcaptcha(secret: Secret<String>) -> Self {
+        SiteVerify {
+            name: "hcaptcha",
+            url: HCAPTCHA_VERIFY_URL.to_string(),
+            secret,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// ## Creates the Cloudflare Turnstile verifier.
+    pub fn turnstile(secret: Secret<String>) -> Self {
+        SiteVerify {
+            name: "turnstile",
+            url: TURNSTILE_VERIFY_URL.to_string(),
+            secret,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// ## Replaces the verification endpoint, e.g. with a mock.
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+}
+
+#[async_trait]
+impl CaptchaVerifier for SiteVerify {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn verify(&self, token: &str, ip: Option<IpAddr>) -> Result<bool, AppError> {
+        let failed = |e: reqwest::Error| {
+            AppError::new(
+                ErrorKind::External,
+                format!("CAPTCHA service '{}' failed", self.name),
+                Some(Box::new(e)),
+            )
+        };
+
+        let mut form = vec![
+            ("secret", self.secret.expose().clone()),
+            ("response", token.to_string()),
+        ];
+        if let Some(ip) = ip {
+            form.push(("remoteip", ip.to_string()));
+        }
+        let response = self
+            .client
+            .post(&self.url)
+            .form(&form)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        let response: SiteVerifyResponse = match response {
+            Ok(response) => response.json().await,
+            Err(e) => Err(e),
+        }
+        .map_err(failed)?;
+
+        if !response.success && !response.error_codes.is_empty() {
+            tracing::debug!(
+                service = self.name,
+                errors = ?response.error_codes,
+                "CAPTCHA was not solved"
+            );
+        }
+
+        Ok(response.success)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Form, Json, Router};
+    use std::collections::HashMap;
+
+    // Test checks if the secret, token and address are sent and the answer is read.
+    #[tokio::test]
+    async fn test_site_verify() {
+        let app = Router::new().route(
+            "/siteverify",
+            post(|Form(form): Form<HashMap<String, String>>| async move {
+                let success = form["secret"] == "site-secret"
+                    && form["response"] == "solved"
+                    && form.get("remoteip").map(String::as_str) == Some("192.0.2.1");
+
+                Json(serde_json::json!({
+                    "success": success,
+                    "error-codes": if success { vec![] } else { vec!["invalid-input-response"] },
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let verifier = SiteVerify::new(CaptchaProvider::Hcaptcha, "site-secret".into())
+            .with_url(format!("http://{}/siteverify", addr));
+        let ip = Some("192.0.2.1".parse().unwrap());
+
+        assert_eq!(verifier.name(), "hcaptcha");
+        assert_eq!(verifier.verify("solved", ip).await, Ok(true));
+        assert_eq!(verifier.verify("guessed", ip).await, Ok(false));
+
+        let unreachable = SiteVerify::turnstile("site-secret".into())
+            .with_url(format!("http://{}/missing", addr));
+        let error = unreachable.verify("solved", ip).await.unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::External);
+    }
+}
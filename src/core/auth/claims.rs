@@ -0,0 +1,694 @@
+//! Claim mapping module.
+//!
+//! Module maps the claims or attributes returned by an
+//! external identity provider (OAuth, OIDC, SAML) into
+//! local user fields and roles. Mappings are written in
+//! a small expression language and compiled at startup,
+//! so typos are reported before the first login.
+//!
+//! ## Expressions
+//! + `email`, `address.country` - Claim at the dotted path.
+//! + `claim("https://example.com/groups")` - Claim with the exact name.
+//! + `"text"` - Constant.
+//! + `expr | lower` - Transformation, see `Transform`.
+//! + `a ?? b` - First expression with a value.
+
+// Imports from external crates
+use serde_json::Value as Json;
+use std::collections::HashMap;
+
+// Local imports
+use crate::core::config::{ClaimMappingSettings, ClaimsSettings};
+use crate::core::err::{AppError, ErrorKind};
+
+// Re-exports of the wire types
+pub use crate::api::claims::MappedClaims;
+
+/// Local fields claims can be mapped into.
+pub const LOCAL_FIELDS: [&str; 3] = ["email", "display_name", "picture"];
+
+/// Mappings used for fields that are not configured,
+/// standard OIDC claims.
+pub const DEFAULT_FIELDS: [(&str, &str); 3] = [
+    ("email", "email"),
+    ("display_name", "name ?? preferred_username"),
+    ("picture", "picture"),
+];
+
+/// ## Claim mapper struct.
+///
+/// ## Examples
+/// ```
+/// use std::collections::HashMap;
+/// use serde_json::json;
+/// use axum_auth::core::auth::claims::ClaimMapper;
+/// use axum_auth::core::config::{ClaimMappingSettings, ClaimsSettings};
+///
+/// let settings = ClaimsSettings {
+///     providers: HashMap::from([(
+///         "okta".to_string(),
+///         ClaimMappingSettings {
+///             fields: HashMap::from([("email".to_string(), "email | lower".to_string())]),
+///             roles: Some("groups | lower".to_string()),
+///             role_map: HashMap::from([("engineering".to_string(), "admin".to_string())]),
+///         },
+///     )]),
+/// };
+///
+/// let mapper = ClaimMapper::compile(&settings).unwrap();
+/// let claims = json!({ "email": "Ada@Example.com", "groups": ["Engineering", "Sales"] });
+/// let mapped = mapper.map("okta", &claims);
+///
+/// assert_eq!(mapped.email.as_deref(), Some("ada@example.com"));
+/// assert_eq!(mapped.roles, ["admin"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClaimMapper {
+    providers: HashMap<String, ProviderMapping>,
+    default: ProviderMapping,
+}
+
+impl ClaimMapper {
+    /// ## Compiles the claim mappings.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&ClaimsSettings` - Mappings per provider.
+    ///
+    /// ## Returns
+    /// + `Result<ClaimMapper, AppError>`
+    ///     - `Ok(ClaimMapper)`: Compiled mappings.
+    ///     - `Err(AppError)`: If a field is unknown or an expression is invalid.
+    pub fn compile(settings: &ClaimsSettings) -> Result<Self, AppError> {
+        let providers = settings
+            .providers
+            .iter()
+            .map(|(provider, mapping)| {
+                ProviderMapping::compile(mapping)
+                    .map(|mapping| (provider.clone(), mapping))
+                    .map_err(|e| invalid(format!("Claim mapping of '{}': {}", provider, e.message)))
+            })
+            .collect::<Result<HashMap<String, ProviderMapping>, AppError>>()?;
+
+        Ok(ClaimMapper {
+            providers,
+            default: ProviderMapping::compile(&ClaimMappingSettings::default())?,
+        })
+    }
+
+    /// ## Maps the claims of a provider.
+    ///
+    /// Providers without a mapping use `DEFAULT_FIELDS`
+    /// and grant no roles.
+    ///
+    /// ## Parameters
+    /// - `provider`: `&str` - Name of the identity provider.
+    /// - `claims`: `&serde_json::Value` - Claims or attributes of the user.
+    ///
+    /// ## Returns
+    /// - `MappedClaims`: Local fields and roles.
+    pub fn map(&self, provider: &str, claims: &Json) -> MappedClaims {
+        self.providers
+            .get(provider)
+            .unwrap_or(&self.default)
+            .map(claims)
+    }
+}
+
+/// ## Compiled mapping of a single provider.
+#[derive(Debug, Clone)]
+struct ProviderMapping {
+    fields: HashMap<String, Expr>,
+    roles: Option<Expr>,
+    role_map: HashMap<String, String>,
+}
+
+impl ProviderMapping {
+    /// ## Compiles the mapping, filling fields that are not configured.
+    fn compile(settings: &ClaimMappingSettings) -> Result<Self, AppError> {
+        let mut fields = HashMap::new();
+
+        for (field, source) in DEFAULT_FIELDS {
+            fields.insert(field.to_string(), parse(source)?);
+        }
+        for (field, source) in &settings.fields {
+            if !LOCAL_FIELDS.contains(&field.as_str()) {
+                return Err(invalid(format!(
+                    "Unknown claim mapping field '{}', expected one of {:?}",
+                    field, LOCAL_FIELDS
+                )));
+            }
+
+            let expr = parse(source)
+                .map_err(|e| invalid(format!("Invalid mapping of '{}': {}", field, e.message)))?;
+            fields.insert(field.clone(), expr);
+        }
+
+        let roles = match &settings.roles {
+            Some(source) => Some(
+                parse(source)
+                    .map_err(|e| invalid(format!("Invalid roles mapping: {}", e.message)))?,
+            ),
+            None => None,
+        };
+
+        if let Some((group, _)) = settings.role_map.iter().find(|(_, role)| role.is_empty()) {
+            return Err(invalid(format!(
+                "Group '{}' is mapped to an empty role",
+                group
+            )));
+        }
+        if roles.is_none() && !settings.role_map.is_empty() {
+            return Err(invalid(
+                "Claim mapping has a role_map but no roles expression".to_string(),
+            ));
+        }
+
+        Ok(ProviderMapping {
+            fields,
+            roles,
+            role_map: settings.role_map.clone(),
+        })
+    }
+
+    /// ## Maps the claims.
+    fn map(&self, claims: &Json) -> MappedClaims {
+        let field = |name: &str| {
+            self.fields
+                .get(name)
+                .and_then(|expr| expr.eval(claims).first())
+        };
+
+        let mut roles: Vec<String> = match &self.roles {
+            Some(expr) => expr
+                .eval(claims)
+                .into_list()
+                .iter()
+                .filter_map(|group| self.role_map.get(group).cloned())
+                .collect(),
+            None => Vec::new(),
+        };
+        roles.sort();
+        roles.dedup();
+
+        MappedClaims {
+            email: field("email"),
+            display_name: field("display_name"),
+            picture: field("picture"),
+            roles,
+        }
+    }
+}
+
+/// ## Value of an expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Val {
+    Null,
+    Str(String),
+    List(Vec<String>),
+}
+
+impl Val {
+    /// ## Converts a JSON claim into a value.
+    fn from_json(json: &Json) -> Self {
+        let scalar = |json: &Json| match json {
+            Json::String(value) => Some(value.clone()),
+            Json::Number(value) => Some(value.to_string()),
+            Json::Bool(value) => Some(value.to_string()),
+            _ => None,
+        };
+
+        match json {
+            Json::Array(values) => Val::List(values.iter().filter_map(scalar).collect()),
+            json => scalar(json).map_or(Val::Null, Val::Str),
+        }
+    }
+
+    /// ## Returns the value, or the first element of a list.
+    fn first(self) -> Option<String> {
+        match self {
+            Val::Null => None,
+            Val::Str(value) => Some(value),
+            Val::List(values) => values.into_iter().next(),
+        }
+    }
+
+    /// ## Returns the value as a list.
+    fn into_list(self) -> Vec<String> {
+        match self {
+            Val::Null => Vec::new(),
+            Val::Str(value) => vec![value],
+            Val::List(values) => values,
+        }
+    }
+
+    /// ## Applies the function to the value or every element.
+    fn map(self, f: impl Fn(String) -> String) -> Self {
+        match self {
+            Val::Null => Val::Null,
+            Val::Str(value) => Val::Str(f(value)),
+            Val::List(values) => Val::List(values.into_iter().map(f).collect()),
+        }
+    }
+}
+
+/// ## Compiled expression.
+#[derive(Debug, Clone, PartialEq)]
+struct Expr {
+    // Alternatives separated by `??`
+    alternatives: Vec<Term>,
+}
+
+impl Expr {
+    /// ## Evaluates the first alternative with a value.
+    fn eval(&self, claims: &Json) -> Val {
+        self.alternatives
+            .iter()
+            .map(|term| term.eval(claims))
+            .find(|value| match value {
+                Val::Null => false,
+                Val::Str(value) => !value.is_empty(),
+                Val::List(values) => !values.is_empty(),
+            })
+            .unwrap_or(Val::Null)
+    }
+}
+
+/// ## Source with its transformations.
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    source: Source,
+    transforms: Vec<Transform>,
+}
+
+impl Term {
+    fn eval(&self, claims: &Json) -> Val {
+        let value = match &self.source {
+            Source::Path(path) => path
+                .iter()
+                .try_fold(claims, |json, key| json.get(key))
+                .map_or(Val::Null, Val::from_json),
+            Source::Claim(name) => claims.get(name).map_or(Val::Null, Val::from_json),
+            Source::Const(value) => Val::Str(value.clone()),
+        };
+
+        self.transforms
+            .iter()
+            .fold(value, |value, transform| transform.apply(value))
+    }
+}
+
+/// ## Source of a term.
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    Path(Vec<String>),
+    Claim(String),
+    Const(String),
+}
+
+/// ## Transformation of a value.
+///
+/// # Variants
+/// - `Lower` - `lower`, lowercases the value.
+/// - `Upper` - `upper`, uppercases the value.
+/// - `Trim` - `trim`, removes surrounding whitespace.
+/// - `First` - `first`, first element of a list.
+/// - `Split` - `split(",")`, splits a value into a list.
+/// - `Join` - `join(",")`, joins a list into a value.
+/// - `Prefix` - `prefix("x")`, prepends the text.
+/// - `StripPrefix` - `strip_prefix("x")`, removes the text from the start.
+/// - `Replace` - `replace("a", "b")`, replaces every occurrence.
+/// - `Default` - `default("x")`, used when there is no value.
+#[derive(Debug, Clone, PartialEq)]
+enum Transform {
+    Lower,
+    Upper,
+    Trim,
+    First,
+    Split(String),
+    Join(String),
+    Prefix(String),
+    StripPrefix(String),
+    Replace(String, String),
+    Default(String),
+}
+
+impl Transform {
+    /// ## Builds the transformation from its name and arguments.
+    fn new(name: &str, args: Vec<String>) -> Result<Self, AppError> {
+        let mut args = args.into_iter();
+        let expected = match name {
+            "lower" | "upper" | "trim" | "first" => 0,
+            "split" | "join" | "prefix" | "strip_prefix" | "default" => 1,
+            "replace" => 2,
+            name => return Err(invalid(format!("Unknown transformation '{}'", name))),
+        };
+
+        if args.len() != expected {
+            return Err(invalid(format!(
+                "Transformation '{}' takes {} argument(s), {} given",
+                name,
+                expected,
+                args.len()
+            )));
+        }
+
+        let mut arg = || args.next().unwrap_or_default();
+        let transform = match name {
+            "lower" => Transform::Lower,
+            "upper" => Transform::Upper,
+            "trim" => Transform::Trim,
+            "first" => Transform::First,
+            "split" => Transform::Split(arg()),
+            "join" => Transform::Join(arg()),
+            "prefix" => Transform::Prefix(arg()),
+            "strip_prefix" => Transform::StripPrefix(arg()),
+            "default" => Transform::Default(arg()),
+            _ => Transform::Replace(arg(), arg()),
+        };
+
+        Ok(transform)
+    }
+
+    fn apply(&self, value: Val) -> Val {
+        match self {
+            Transform::Lower => value.map(|value| value.to_lowercase()),
+            Transform::Upper => value.map(|value| value.to_uppercase()),
+            Transform::Trim => value.map(|value| value.trim().to_string()),
+            Transform::First => value.first().map_or(Val::Null, Val::Str),
+            Transform::Split(separator) => match value {
+                Val::Str(value) => Val::List(
+                    value
+                        .split(separator.as_str())
+                        .map(|part| part.trim().to_string())
+                        .filter(|part| !part.is_empty())
+                        .collect(),
+                ),
+                value => value,
+            },
+            Transform::Join(separator) => match value {
+                Val::List(values) => Val::Str(values.join(separator)),
+                value => value,
+            },
+            Transform::Prefix(prefix) => value.map(|value| format!("{}{}", prefix, value)),
+            Transform::StripPrefix(prefix) => value.map(|value| {
+                value
+                    .strip_prefix(prefix.as_str())
+                    .map(str::to_string)
+                    .unwrap_or(value)
+            }),
+            Transform::Replace(from, to) => value.map(|value| value.replace(from, to)),
+            Transform::Default(default) => match value {
+                Val::Null => Val::Str(default.clone()),
+                value => value,
+            },
+        }
+    }
+}
+
+/// ## Token of an expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Pipe,
+    Coalesce,
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// ## Splits the expression into tokens.
+fn tokenize(source: &str) -> Result<Vec<Token>, AppError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '|' => tokens.push(Token::Pipe),
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ',' => tokens.push(Token::Comma),
+            '?' if chars.next_if_eq(&'?').is_some() => tokens.push(Token::Coalesce),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => value.extend(chars.next()),
+                        Some(c) => value.push(c),
+                        None => return Err(invalid("Unterminated string".to_string())),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_alphanumeric() || ['_', '.', '-'].contains(c))
+                {
+                    ident.push(c);
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c => return Err(invalid(format!("Unexpected character '{}'", c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// ## Parses the expression.
+fn parse(source: &str) -> Result<Expr, AppError> {
+    let mut tokens = tokenize(source)?.into_iter().peekable();
+    let mut alternatives = vec![parse_term(&mut tokens)?];
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Coalesce => alternatives.push(parse_term(&mut tokens)?),
+            token => return Err(invalid(format!("Unexpected {:?} in '{}'", token, source))),
+        }
+    }
+
+    Ok(Expr { alternatives })
+}
+
+type Tokens = std::iter::Peekable<std::vec::IntoIter<Token>>;
+
+/// ## Parses a source followed by its transformations.
+fn parse_term(tokens: &mut Tokens) -> Result<Term, AppError> {
+    let source = match tokens.next() {
+        Some(Token::Str(value)) => Source::Const(value),
+        Some(Token::Ident(name)) if name == "claim" && tokens.peek() == Some(&Token::LParen) => {
+            let mut args = parse_args(tokens)?;
+            match (args.pop(), args.is_empty()) {
+                (Some(name), true) => Source::Claim(name),
+                _ => return Err(invalid("claim() takes exactly 1 argument".to_string())),
+            }
+        }
+        Some(Token::Ident(path)) => {
+            let path: Vec<String> = path.split('.').map(str::to_string).collect();
+            if path.iter().any(String::is_empty) {
+                return Err(invalid(format!("Invalid claim path '{}'", path.join("."))));
+            }
+            Source::Path(path)
+        }
+        token => return Err(invalid(format!("Expected a claim, found {:?}", token))),
+    };
+
+    let mut transforms = Vec::new();
+    while tokens.next_if_eq(&Token::Pipe).is_some() {
+        let name = match tokens.next() {
+            Some(Token::Ident(name)) => name,
+            token => {
+                return Err(invalid(format!(
+                    "Expected a transformation, found {:?}",
+                    token
+                )))
+            }
+        };
+        let args = match tokens.peek() {
+            Some(Token::LParen) => parse_args(tokens)?,
+            _ => Vec::new(),
+        };
+        transforms.push(Transform::new(&name, args)?);
+    }
+
+    Ok(Term { source, transforms })
+}
+
+/// ## Parses parenthesized string arguments.
+fn parse_args(tokens: &mut Tokens) -> Result<Vec<String>, AppError> {
+    tokens.next();
+    let mut args = Vec::new();
+
+    loop {
+        match tokens.next() {
+            Some(Token::RParen) if args.is_empty() => return Ok(args),
+            Some(Token::Str(arg)) => args.push(arg),
+            token => return Err(invalid(format!("Expected a string, found {:?}", token))),
+        }
+        match tokens.next() {
+            Some(Token::Comma) => {}
+            Some(Token::RParen) => return Ok(args),
+            token => return Err(invalid(format!("Expected ',' or ')', found {:?}", token))),
+        }
+    }
+}
+
+/// ## Builds an invalid claim mapping error.
+fn invalid(message: String) -> AppError {
+    AppError::new(ErrorKind::Config, message, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(source: &str, claims: &Json) -> Val {
+        parse(source).unwrap().eval(claims)
+    }
+
+    fn settings(mapping: ClaimMappingSettings) -> ClaimsSettings {
+        ClaimsSettings {
+            providers: HashMap::from([("idp".to_string(), mapping)]),
+        }
+    }
+
+    // Test checks if paths, claim names, constants and fallbacks are evaluated.
+    #[test]
+    fn test_eval_sources() {
+        let claims = json!({
+            "address": { "country": "DE" },
+            "https://example.com/groups": ["a", "b"],
+            "email_verified": true,
+        });
+
+        assert_eq!(eval("address.country", &claims), Val::Str("DE".to_string()));
+        assert_eq!(
+            eval("claim(\"https://example.com/groups\")", &claims),
+            Val::List(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            eval("email_verified", &claims),
+            Val::Str("true".to_string())
+        );
+        assert_eq!(
+            eval("name ?? address.country ?? \"x\"", &claims),
+            Val::Str("DE".to_string())
+        );
+        assert_eq!(eval("missing", &claims), Val::Null);
+    }
+
+    // Test checks if transformations are applied in order.
+    #[test]
+    fn test_eval_transforms() {
+        let claims = json!({ "roles": " Admin, Dev ,", "name": "  Ada  " });
+
+        assert_eq!(
+            eval("roles | split(\",\") | lower | prefix(\"idp:\")", &claims),
+            Val::List(vec!["idp:admin".to_string(), "idp:dev".to_string()])
+        );
+        assert_eq!(
+            eval(
+                "roles | split(\",\") | join(\"+\") | replace(\"+\", \"/\")",
+                &claims
+            ),
+            Val::Str("Admin/Dev".to_string())
+        );
+        assert_eq!(
+            eval("name | trim | upper | strip_prefix(\"A\")", &claims),
+            Val::Str("DA".to_string())
+        );
+        assert_eq!(
+            eval("missing | default(\"none\")", &claims),
+            Val::Str("none".to_string())
+        );
+    }
+
+    // Test checks if invalid expressions are rejected.
+    #[test]
+    fn test_parse_invalid() {
+        for source in [
+            "",
+            "email |",
+            "email | shout",
+            "email | split",
+            "email | replace(\"a\")",
+            "\"open",
+            "email ??",
+            "a..b",
+            "email $",
+            "claim(\"a\", \"b\")",
+        ] {
+            assert!(parse(source).is_err(), "'{}' should be rejected", source);
+        }
+    }
+
+    // Test checks if providers without a mapping use the standard claims.
+    #[test]
+    fn test_map_default() {
+        let mapper = ClaimMapper::compile(&ClaimsSettings::default()).unwrap();
+        let claims = json!({
+            "email": "ada@example.com",
+            "preferred_username": "ada",
+            "groups": ["admins"],
+        });
+
+        assert_eq!(
+            mapper.map("github", &claims),
+            MappedClaims {
+                email: Some("ada@example.com".to_string()),
+                display_name: Some("ada".to_string()),
+                picture: None,
+                roles: Vec::new(),
+            }
+        );
+    }
+
+    // Test checks if groups are mapped into sorted, unique local roles.
+    #[test]
+    fn test_map_roles() {
+        let mapper = ClaimMapper::compile(&settings(ClaimMappingSettings {
+            fields: HashMap::from([("picture".to_string(), "avatar_url".to_string())]),
+            roles: Some("groups | lower".to_string()),
+            role_map: HashMap::from([
+                ("ops".to_string(), "admin".to_string()),
+                ("sre".to_string(), "admin".to_string()),
+                ("dev".to_string(), "developer".to_string()),
+            ]),
+        }))
+        .unwrap();
+        let claims =
+            json!({ "groups": ["SRE", "Ops", "Dev", "Sales"], "avatar_url": "https://a/b.png" });
+
+        let mapped = mapper.map("idp", &claims);
+
+        assert_eq!(mapped.roles, ["admin", "developer"]);
+        assert_eq!(mapped.picture.as_deref(), Some("https://a/b.png"));
+    }
+
+    // Test checks if invalid mappings are rejected at startup.
+    #[test]
+    fn test_compile_invalid() {
+        let unknown_field = ClaimMappingSettings {
+            fields: HashMap::from([("password".to_string(), "pwd".to_string())]),
+            ..ClaimMappingSettings::default()
+        };
+        let invalid_expression = ClaimMappingSettings {
+            fields: HashMap::from([("email".to_string(), "mail | shout".to_string())]),
+            ..ClaimMappingSettings::default()
+        };
+        let role_map_without_roles = ClaimMappingSettings {
+            role_map: HashMap::from([("ops".to_string(), "admin".to_string())]),
+            ..ClaimMappingSettings::default()
+        };
+
+        for mapping in [unknown_field, invalid_expression, role_map_without_roles] {
+            let result = ClaimMapper::compile(&settings(mapping)).unwrap_err();
+
+            assert_eq!(result.kind, ErrorKind::Config);
+        }
+    }
+}
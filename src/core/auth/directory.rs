@@ -0,0 +1,460 @@
+//! Directory group sync module.
+//!
+//! Module maps the group memberships of an external
+//! directory to local roles. The sync runs as a job of
+//! the `worker` run mode, compares the roles the groups
+//! grant with the stored grants and applies the difference.
+//! Only grants created by the sync are ever revoked, so
+//! roles granted by an administrator are left untouched.
+//!
+//! Identity providers that send the groups as claims are
+//! mapped at login instead, see `core::auth::claims`.
+
+// Imports from external crates
+use async_trait::async_trait;
+use ldap3::{LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use serde_json::json;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    time::Duration,
+};
+use uuid::Uuid;
+
+// Local imports
+use crate::core::audit::{AuditEntry, AuditEvent, AuditLog};
+use crate::core::config::{GroupSyncSettings, LdapSettings};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::repo::{postgres::PgUserRepo, UserRepo};
+use crate::core::users::roles::{RoleGrant, RoleRepo, RoleSource};
+use crate::core::worker::{cron::Schedule, Job};
+
+/// Groups of every directory user, keyed by the lowercase email.
+pub type Memberships = HashMap<String, Vec<String>>;
+
+/// ## Group directory trait.
+///
+/// ## Examples
+/// ```
+/// use async_trait::async_trait;
+/// use axum_auth::core::auth::directory::{GroupDirectory, Memberships};
+/// use axum_auth::core::err::AppError;
+///
+/// struct Fixed;
+///
+/// #[async_trait]
+/// impl GroupDirectory for Fixed {
+///     async fn memberships(&self) -> Result<Memberships, AppError> {
+///         Ok(Memberships::from([(
+///             "ada@example.com".to_string(),
+///             vec!["admins".to_string()],
+///         )]))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait GroupDirectory: Send + Sync {
+    /// Returns the groups of every user in the directory.
+    async fn memberships(&self) -> Result<Memberships, AppError>;
+}
+
+/// ## LDAP group directory struct.
+///
+/// Users are searched once per sync, their groups are
+/// read from the group attribute (`memberOf` by default).
+#[derive(Debug, Clone)]
+pub struct LdapDirectory {
+    settings: LdapSettings,
+}
+
+impl LdapDirectory {
+    /// ## Creates a new `LdapDirectory` instance.
+    pub fn new(settings: LdapSettings) -> Self {
+        LdapDirectory { settings }
+    }
+}
+
+#[async_trait]
+impl GroupDirectory for LdapDirectory {
+    async fn memberships(&self) -> Result<Memberships, AppError> {
+        let settings = &self.settings;
+        let timeout = Duration::from_secs(settings.timeout);
+
+        let (conn, mut ldap) = LdapConnAsync::with_settings(
+            LdapConnSettings::new().set_conn_timeout(timeout),
+            &settings.url,
+        )
+        .await
+        .map_err(ldap_error("Failed to connect to the LDAP server"))?;
+        ldap3::drive!(conn);
+
+        if !settings.bind_dn.is_empty() {
+            ldap.with_timeout(timeout)
+                .simple_bind(&settings.bind_dn, settings.bind_password.expose())
+                .await
+                .and_then(|result| result.success())
+                .map_err(ldap_error("Failed to bind to the LDAP server"))?;
+        }
+
+        let (entries, _) = ldap
+            .with_timeout(timeout)
+            .search(
+                &settings.base_dn,
+                Scope::Subtree,
+                &settings.user_filter,
+                vec![
+                    settings.email_attribute.as_str(),
+                    settings.group_attribute.as_str(),
+                ],
+            )
+            .await
+            .and_then(|result| result.success())
+            .map_err(ldap_error("Failed to search the LDAP directory"))?;
+
+        // Connection is closed either way, a failed unbind
+        // does not invalidate the search result
+        if let Err(e) = ldap.unbind().await {
+            tracing::debug!(error = %e, "Failed to unbind from the LDAP server");
+        }
+
+        let mut memberships = Memberships::new();
+        for entry in entries.into_iter().map(SearchEntry::construct) {
+            let Some(email) =
+                attribute(&entry, &settings.email_attribute).and_then(|values| values.first())
+            else {
+                continue;
+            };
+            let groups = attribute(&entry, &settings.group_attribute)
+                .cloned()
+                .unwrap_or_default();
+
+            memberships
+                .entry(email.to_lowercase())
+                .or_default()
+                .extend(groups);
+        }
+
+        Ok(memberships)
+    }
+}
+
+/// ## Returns the values of the attribute, ignoring the case of its name.
+fn attribute<'a>(entry: &'a SearchEntry, name: &str) -> Option<&'a Vec<String>> {
+    entry
+        .attrs
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, values)| values)
+}
+
+/// ## Maps an LDAP error into an external error.
+fn ldap_error(message: &'static str) -> impl FnOnce(ldap3::LdapError) -> AppError {
+    move |e| AppError::new(ErrorKind::External, message.to_string(), Some(Box::new(e)))
+}
+
+/// ## Returns the names a group is matched by.
+///
+/// Names are the group itself and, for a DN, the value
+/// of its first relative DN, both in lowercase.
+pub fn group_names(group: &str) -> Vec<String> {
+    let group = group.trim().to_lowercase();
+    let common_name = group
+        .split(',')
+        .next()
+        .and_then(|rdn| rdn.split_once('='))
+        .map(|(_, value)| value.trim().to_string());
+
+    match common_name {
+        Some(common_name) if common_name != group => vec![group, common_name],
+        _ => vec![group],
+    }
+}
+
+/// ## Role change plan struct.
+///
+/// ## Fields
+/// + `grants`: `Vec<(Uuid, String)>` - Roles to grant per user.
+/// + `revokes`: `Vec<(Uuid, String)>` - Directory roles to revoke per user.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncPlan {
+    pub grants: Vec<(Uuid, String)>,
+    pub revokes: Vec<(Uuid, String)>,
+}
+
+impl SyncPlan {
+    /// ## Checks if the plan changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.grants.is_empty() && self.revokes.is_empty()
+    }
+}
+
+/// ## Plans the role changes.
+///
+/// Role is granted when a group of the user maps to it and
+/// the user has no grant of it yet. Directory grants are
+/// revoked when no group maps to them anymore, including
+/// users that were removed from the directory.
+///
+/// ## Parameters
+/// - `users`: `&[(Uuid, String)]` - ID and email of every local user.
+/// - `grants`: `&[RoleGrant]` - Stored role grants.
+/// - `memberships`: `&Memberships` - Groups from the directory.
+/// - `role_map`: `&HashMap<String, String>` - Local role per group.
+///
+/// ## Returns
+/// - `SyncPlan`: Changes, ordered by user and role.
+pub fn plan(
+    users: &[(Uuid, String)],
+    grants: &[RoleGrant],
+    memberships: &Memberships,
+    role_map: &HashMap<String, String>,
+) -> SyncPlan {
+    let role_map: HashMap<String, &String> = role_map
+        .iter()
+        .map(|(group, role)| (group.trim().to_lowercase(), role))
+        .collect();
+
+    let mut current: HashMap<Uuid, HashSet<&str>> = HashMap::new();
+    let mut synced: HashMap<Uuid, HashSet<&str>> = HashMap::new();
+    for grant in grants {
+        current
+            .entry(grant.user_id)
+            .or_default()
+            .insert(&grant.role);
+        if grant.source == RoleSource::Directory {
+            synced.entry(grant.user_id).or_default().insert(&grant.role);
+        }
+    }
+
+    let mut plan = SyncPlan::default();
+    for (user_id, email) in users {
+        let desired: BTreeSet<&str> = memberships
+            .get(&email.to_lowercase())
+            .into_iter()
+            .flatten()
+            .flat_map(|group| group_names(group))
+            .filter_map(|name| role_map.get(&name).map(|role| role.as_str()))
+            .collect();
+        let current = current.get(user_id);
+        let synced: BTreeSet<&str> = synced
+            .get(user_id)
+            .map(|roles| roles.iter().copied().collect())
+            .unwrap_or_default();
+
+        plan.grants.extend(
+            desired
+                .iter()
+                .filter(|role| !current.is_some_and(|roles| roles.contains(*role)))
+                .map(|role| (*user_id, role.to_string())),
+        );
+        plan.revokes.extend(
+            synced
+                .difference(&desired)
+                .map(|role| (*user_id, role.to_string())),
+        );
+    }
+
+    plan.grants.sort();
+    plan.revokes.sort();
+    plan
+}
+
+/// ## Directory group sync job struct.
+///
+/// Every applied change is recorded in the audit log with
+/// `"source": "directory"` in the details. In dry-run mode
+/// the plan is only logged.
+pub struct GroupSync {
+    directory: Box<dyn GroupDirectory>,
+    users: PgUserRepo,
+    roles: RoleRepo,
+    audit: AuditLog,
+    settings: GroupSyncSettings,
+    schedule: Schedule,
+}
+
+impl GroupSync {
+    /// ## Creates a new `GroupSync` instance.
+    ///
+    /// ## Parameters
+    /// - `directory`: `impl GroupDirectory` - Directory the groups are read from.
+    /// - `pool`: `PgPool` - Connection pool of the users, roles and audit log.
+    /// - `settings`: `GroupSyncSettings` - Role map and dry-run mode.
+    /// - `schedule`: `Schedule` - Schedule of the syncs.
+    pub fn new(
+        directory: impl GroupDirectory + 'static,
+        pool: sqlx::PgPool,
+        settings: GroupSyncSettings,
+        schedule: Schedule,
+    ) -> Self {
+        GroupSync {
+            directory: Box::new(directory),
+            users: PgUserRepo::new(pool.clone()),
+            roles: RoleRepo::new(pool.clone()),
+            audit: AuditLog::new(pool),
+            settings,
+            schedule,
+        }
+    }
+
+    /// ## Applies the plan and records the audit events.
+    async fn apply(&self, plan: SyncPlan) -> Result<(), AppError> {
+        for (user_id, role) in plan.grants {
+            if self
+                .roles
+                .grant(user_id, &role, RoleSource::Directory)
+                .await?
+            {
+                self.record(AuditEvent::RoleGranted, user_id, &role).await?;
+            }
+        }
+        for (user_id, role) in plan.revokes {
+            if self
+                .roles
+                .revoke(user_id, &role, RoleSource::Directory)
+                .await?
+            {
+                self.record(AuditEvent::RoleRevoked, user_id, &role).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// ## Records the role change in the audit log.
+    async fn record(&self, event: AuditEvent, user_id: Uuid, role: &str) -> Result<(), AppError> {
+        self.audit
+            .record(
+                AuditEntry::new(event)
+                    .user(user_id)
+                    .details(json!({ "role": role, "source": RoleSource::Directory.as_str() })),
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl Job for GroupSync {
+    fn name(&self) -> &str {
+        "group_sync"
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn run(&self) -> Result<(), AppError> {
+        let memberships = self.directory.memberships().await?;
+
+        // Empty result is far more likely a broken filter or
+        // base DN than a directory without users, syncing it
+        // would revoke every directory role
+        if memberships.is_empty() {
+            return Err(AppError::new(
+                ErrorKind::External,
+                "Directory returned no users, roles are left unchanged".to_string(),
+                None,
+            ));
+        }
+
+        let users = self.users.emails().await?;
+        let grants = self.roles.list().await?;
+        let plan = plan(&users, &grants, &memberships, &self.settings.role_map);
+
+        tracing::info!(
+            grants = plan.grants.len(),
+            revokes = plan.revokes.len(),
+            dry_run = self.settings.dry_run,
+            "Group sync planned"
+        );
+
+        if self.settings.dry_run {
+            for (user_id, role) in &plan.grants {
+                tracing::info!(%user_id, role, "Dry run, role would be granted");
+            }
+            for (user_id, role) in &plan.revokes {
+                tracing::info!(%user_id, role, "Dry run, role would be revoked");
+            }
+            return Ok(());
+        }
+
+        self.apply(plan).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(user_id: Uuid, role: &str, source: RoleSource) -> RoleGrant {
+        RoleGrant {
+            user_id,
+            role: role.to_string(),
+            source,
+        }
+    }
+
+    fn role_map() -> HashMap<String, String> {
+        HashMap::from([
+            ("Admins".to_string(), "admin".to_string()),
+            (
+                "cn=auditors,ou=groups,dc=example,dc=com".to_string(),
+                "auditor".to_string(),
+            ),
+        ])
+    }
+
+    // Test checks if groups are matched by DN and by common name.
+    #[test]
+    fn test_group_names() {
+        assert_eq!(
+            group_names("CN=Admins,OU=Groups,DC=example,DC=com"),
+            ["cn=admins,ou=groups,dc=example,dc=com", "admins"]
+        );
+        assert_eq!(group_names("admins"), ["admins"]);
+    }
+
+    // Test checks if missing roles are granted and only stale directory roles are revoked.
+    #[test]
+    fn test_plan() {
+        let ada = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+        let users = [
+            (ada, "Ada@Example.com".to_string()),
+            (bob, "bob@example.com".to_string()),
+        ];
+        let grants = [
+            grant(ada, "auditor", RoleSource::Directory),
+            grant(bob, "admin", RoleSource::Local),
+            grant(bob, "auditor", RoleSource::Directory),
+        ];
+        let memberships = Memberships::from([(
+            "ada@example.com".to_string(),
+            vec![
+                "cn=admins,ou=groups,dc=example,dc=com".to_string(),
+                "cn=Auditors,ou=groups,dc=example,dc=com".to_string(),
+                "cn=sales,ou=groups,dc=example,dc=com".to_string(),
+            ],
+        )]);
+
+        let plan = plan(&users, &grants, &memberships, &role_map());
+
+        assert_eq!(plan.grants, [(ada, "admin".to_string())]);
+        assert_eq!(plan.revokes, [(bob, "auditor".to_string())]);
+    }
+
+    // Test checks if roles granted by an administrator are never granted or revoked again.
+    #[test]
+    fn test_plan_keeps_local_grants() {
+        let ada = Uuid::new_v4();
+        let users = [(ada, "ada@example.com".to_string())];
+        let grants = [grant(ada, "admin", RoleSource::Local)];
+        let memberships = Memberships::from([("ada@example.com".to_string(), vec![])]);
+
+        assert!(plan(&users, &grants, &memberships, &role_map()).is_empty());
+
+        let memberships =
+            Memberships::from([("ada@example.com".to_string(), vec!["admins".to_string()])]);
+
+        assert!(plan(&users, &grants, &memberships, &role_map()).is_empty());
+    }
+}
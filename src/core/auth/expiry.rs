@@ -0,0 +1,429 @@
+//! Credential expiry module.
+//!
+//! Passwords and API keys expire after the maximum age of
+//! `credential_expiry`. The `CredentialExpiry` job warns
+//! the users ahead of time and then expires them through
+//! the `credential.expiring` and `credential.expired`
+//! webhooks, whose receivers email the users. Expired
+//! passwords have to be changed on the next login, its
+//! access token only carries `PASSWORD_CHANGE_SCOPE`.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Local imports
+use crate::api::users::{CredentialKind, CredentialWarning};
+use crate::core::config::CredentialExpirySettings;
+use crate::core::err::AppError;
+use crate::core::repo::{Token, TokenKind, TokenRepo, UserRepo};
+use crate::core::tenancy::with_tenant;
+use crate::core::users::User;
+use crate::core::webhooks::{WebhookEvent, Webhooks};
+use crate::core::worker::{cron::Schedule, Job};
+
+/// Only scope of the access token of a login whose
+/// password has to be changed first.
+pub const PASSWORD_CHANGE_SCOPE: &str = "password:change";
+
+/// ## Returns the time the seconds before `now`.
+///
+/// Ages beyond the supported time range give the
+/// earliest time, so nothing is older.
+fn before(now: DateTime<Utc>, seconds: u64) -> DateTime<Utc> {
+    i64::try_from(seconds)
+        .ok()
+        .and_then(Duration::try_seconds)
+        .and_then(|age| now.checked_sub_signed(age))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// ## Returns the expiry of the API key under the settings.
+///
+/// ## Parameters
+/// - `settings`: `&CredentialExpirySettings` - Maximum age of the keys.
+/// - `token`: `&Token` - API key.
+///
+/// ## Returns
+/// - `DateTime<Utc>`: Its own expiry, or the end of the maximum
+///   age when that comes first.
+pub fn api_key_expires_at(settings: &CredentialExpirySettings, token: &Token) -> DateTime<Utc> {
+    CredentialExpirySettings::expires_at(token.created_at, settings.api_key_max_age)
+        .map_or(token.expires_at, |expires_at| {
+            expires_at.min(token.expires_at)
+        })
+}
+
+/// ## Returns the expiry warnings of the user.
+///
+/// Credentials are listed once their expiry is less
+/// than `warn_before` away, soonest first.
+///
+/// ## Parameters
+/// - `settings`: `&CredentialExpirySettings` - Maximum ages and warning period.
+/// - `users`: `&dyn UserRepo` - User repository.
+/// - `tokens`: `&dyn TokenRepo` - Repository of the API keys.
+/// - `user`: `&User` - User asking.
+///
+/// ## Returns
+/// + `Result<Vec<CredentialWarning>, AppError>`
+///     - `Ok(Vec<CredentialWarning>)`: Warnings, none while the expiry
+///       is disabled.
+///     - `Err(AppError)`: If a repository failed.
+pub async fn warnings(
+    settings: &CredentialExpirySettings,
+    users: &dyn UserRepo,
+    tokens: &dyn TokenRepo,
+    user: &User,
+) -> Result<Vec<CredentialWarning>, AppError> {
+    if !settings.enabled {
+        return Ok(Vec::new());
+    }
+
+    let now = Utc::now();
+    let warn_after = |expires_at: DateTime<Utc>| before(expires_at, settings.warn_before) <= now;
+    let mut warnings = Vec::new();
+
+    let password_expires_at = users
+        .password_changed_at(user.id)
+        .await?
+        .and_then(|changed_at| {
+            CredentialExpirySettings::expires_at(changed_at, settings.password_max_age)
+        });
+    if let Some(expires_at) = password_expires_at.filter(|expires_at| warn_after(*expires_at)) {
+        warnings.push(CredentialWarning {
+            credential: CredentialKind::Password,
+            id: None,
+            expires_at,
+            expired: expires_at <= now || user.password_reset_required,
+        });
+    }
+    for token in tokens.list(user.id, TokenKind::ApiKey).await? {
+        let expires_at = api_key_expires_at(settings, &token);
+        if warn_after(expires_at) {
+            warnings.push(CredentialWarning {
+                credential: CredentialKind::ApiKey,
+                id: Some(token.id),
+                expires_at,
+                expired: expires_at <= now,
+            });
+        }
+    }
+    warnings.sort_by_key(|warning| warning.expires_at);
+
+    Ok(warnings)
+}
+
+/// ## Credential expiry job struct.
+///
+/// ## Examples
+/// ```no_run
+/// use std::sync::Arc;
+/// use axum_auth::core::auth::expiry::CredentialExpiry;
+/// use axum_auth::core::config::CredentialExpirySettings;
+/// use axum_auth::core::repo::memory::{MemoryTokenRepo, MemoryUserRepo};
+/// use axum_auth::core::webhooks::Webhooks;
+/// use axum_auth::core::worker::cron::Schedule;
+///
+/// # fn example(pool: sqlx::PgPool) {
+/// let settings = CredentialExpirySettings::default();
+/// let expiry = CredentialExpiry::new(
+///     Arc::new(MemoryUserRepo::default()),
+///     Arc::new(MemoryTokenRepo::default()),
+///     Webhooks::new(pool, &Default::default()),
+///     &settings,
+///     Schedule::new(&settings.schedule, "UTC").unwrap(),
+/// );
+/// # }
+/// ```
+pub struct CredentialExpiry {
+    users: Arc<dyn UserRepo>,
+    tokens: Arc<dyn TokenRepo>,
+    webhooks: Webhooks,
+    settings: CredentialExpirySettings,
+    schedule: Schedule,
+}
+
+impl CredentialExpiry {
+    /// ## Creates a new `CredentialExpiry` instance.
+    ///
+    /// ## Parameters
+    /// - `users`: `Arc<dyn UserRepo>` - User repository.
+    /// - `tokens`: `Arc<dyn TokenRepo>` - Repository of the API keys.
+    /// - `webhooks`: `Webhooks` - Queue of the warnings.
+    /// - `settings`: `&CredentialExpirySettings` - Maximum ages and warning period.
+    /// - `schedule`: `Schedule` - Schedule of the runs.
+    pub fn new(
+        users: Arc<dyn UserRepo>,
+        tokens: Arc<dyn TokenRepo>,
+        webhooks: Webhooks,
+        settings: &CredentialExpirySettings,
+        schedule: Schedule,
+    ) -> Self {
+        CredentialExpiry {
+            users,
+            tokens,
+            webhooks,
+            settings: settings.clone(),
+            schedule,
+        }
+    }
+
+    /// ## Expires and warns about the credentials as of `now`.
+    ///
+    /// Credentials are expired before the warnings are
+    /// sent, so an expired one is never warned about too.
+    async fn expire(&self, now: DateTime<Utc>) -> Result<(), AppError> {
+        let settings = &self.settings;
+
+        if settings.password_max_age > 0 {
+            let changed_before = before(now, settings.password_max_age);
+            let expired = self.users.expire_passwords(changed_before).await?;
+            for (user, tenant) in expired {
+                self.notify(WebhookEvent::CredentialExpired, &user, &tenant, None, None)
+                    .await?;
+            }
+
+            let warn_before = before(now, settings.password_max_age - settings.warn_before);
+            let expiring = self.users.warn_password_expiry(warn_before).await?;
+            for (user, tenant) in expiring {
+                let changed_at =
+                    with_tenant(tenant.clone(), self.users.password_changed_at(user.id)).await?;
+                let expires_at = changed_at.and_then(|changed_at| {
+                    CredentialExpirySettings::expires_at(changed_at, settings.password_max_age)
+                });
+                self.notify(
+                    WebhookEvent::CredentialExpiring,
+                    &user,
+                    &tenant,
+                    None,
+                    expires_at,
+                )
+                .await?;
+            }
+        }
+
+        if settings.api_key_max_age > 0 {
+            let issued_before = before(now, settings.api_key_max_age);
+            let expired = self
+                .tokens
+                .expire_issued_before(TokenKind::ApiKey, issued_before)
+                .await?;
+            for (token, tenant) in expired {
+                let user = with_tenant(tenant.clone(), self.users.get(token.user_id)).await?;
+                self.notify(
+                    WebhookEvent::CredentialExpired,
+                    &user,
+                    &tenant,
+                    Some(token.id),
+                    None,
+                )
+                .await?;
+            }
+        }
+
+        let issued_before = match settings.api_key_max_age {
+            0 => DateTime::<Utc>::MIN_UTC,
+            max_age => before(now, max_age - settings.warn_before),
+        };
+        let expires_before = now + Duration::seconds(settings.warn_before as i64);
+        let expiring = self
+            .tokens
+            .warn_expiry(TokenKind::ApiKey, issued_before, expires_before)
+            .await?;
+        for (token, tenant) in expiring {
+            let user = with_tenant(tenant.clone(), self.users.get(token.user_id)).await?;
+            let expires_at = api_key_expires_at(settings, &token);
+            self.notify(
+                WebhookEvent::CredentialExpiring,
+                &user,
+                &tenant,
+                Some(token.id),
+                Some(expires_at),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// ## Emits the event about a credential of the user.
+    ///
+    /// ## Parameters
+    /// - `event`: `WebhookEvent` - `credential.expiring` or `credential.expired`.
+    /// - `user`: `&User` - Owner of the credential.
+    /// - `tenant`: `&str` - Tenant of the user.
+    /// - `key_id`: `Option<Uuid>` - ID of the API key, none for the password.
+    /// - `expires_at`: `Option<DateTime<Utc>>` - Expiry to tell the user.
+    async fn notify(
+        &self,
+        event: WebhookEvent,
+        user: &User,
+        tenant: &str,
+        key_id: Option<Uuid>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), AppError> {
+        let credential = match key_id {
+            Some(_) => CredentialKind::ApiKey,
+            None => CredentialKind::Password,
+        };
+        let data = serde_json::json!({
+            "user_id": user.id,
+            "email": user.email,
+            "tenant": tenant,
+            "credential": credential,
+            "id": key_id,
+            "expires_at": expires_at,
+        });
+
+        self.webhooks.emit(event, data).await
+    }
+}
+
+#[async_trait]
+impl Job for CredentialExpiry {
+    fn name(&self) -> &str {
+        "credential_expiry"
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn run(&self) -> Result<(), AppError> {
+        self.expire(Utc::now()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::password::{Algorithm, StoredHash};
+    use crate::core::repo::memory::{MemoryTokenRepo, MemoryUserRepo};
+    use crate::core::repo::NewToken;
+    use sqlx::postgres::PgPoolOptions;
+
+    fn user() -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: "user@example.com".to_string(),
+            display_name: None,
+            org_id: None,
+            disabled: false,
+            password_reset_required: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_at_display: None,
+            updated_at_display: None,
+        }
+    }
+
+    fn settings() -> CredentialExpirySettings {
+        CredentialExpirySettings {
+            enabled: true,
+            password_max_age: 90 * 86400,
+            api_key_max_age: 30 * 86400,
+            warn_before: 14 * 86400,
+            ..CredentialExpirySettings::default()
+        }
+    }
+
+    // Test checks if passwords and API keys are warned about once, then expired.
+    #[tokio::test]
+    async fn test_credential_expiry() {
+        let users = Arc::new(MemoryUserRepo::default());
+        let tokens = Arc::new(MemoryTokenRepo::default());
+        let user = user();
+        let password = StoredHash {
+            algorithm: Algorithm::Argon2id,
+            hash: "hash".to_string(),
+        };
+        users.insert(user.clone(), Some(password));
+        tokens
+            .store(NewToken {
+                kind: TokenKind::ApiKey,
+                user_id: user.id,
+                session_id: None,
+                token: "api-key".to_string(),
+                expires_at: Utc::now() + Duration::days(365),
+            })
+            .await
+            .unwrap();
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+        let expiry = CredentialExpiry::new(
+            users.clone(),
+            tokens.clone(),
+            Webhooks::new(pool, &Default::default()),
+            &settings(),
+            Schedule::new("0 6 * * *", "UTC").unwrap(),
+        );
+        let in_days = |days: i64| Utc::now() + Duration::days(days);
+
+        // Key is warned about after 16 days and expired after 30
+        expiry.expire(in_days(20)).await.unwrap();
+        assert!(tokens
+            .warn_expiry(TokenKind::ApiKey, in_days(365), in_days(365))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            tokens.list(user.id, TokenKind::ApiKey).await.unwrap().len(),
+            1
+        );
+
+        expiry.expire(in_days(31)).await.unwrap();
+        assert!(tokens
+            .list(user.id, TokenKind::ApiKey)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Password is warned about after 76 days and expired after 90
+        expiry.expire(in_days(80)).await.unwrap();
+        assert!(users
+            .warn_password_expiry(in_days(365))
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(!users.get(user.id).await.unwrap().password_reset_required);
+
+        expiry.expire(in_days(91)).await.unwrap();
+        assert!(users.get(user.id).await.unwrap().password_reset_required);
+    }
+
+    // Test checks if the warnings list credentials inside the warning period only.
+    #[tokio::test]
+    async fn test_warnings() {
+        let users = MemoryUserRepo::default();
+        let tokens = MemoryTokenRepo::default();
+        let user = user();
+        users.insert(user.clone(), None);
+        let store = |token: &str, days: i64| NewToken {
+            kind: TokenKind::ApiKey,
+            user_id: user.id,
+            session_id: None,
+            token: token.to_string(),
+            expires_at: Utc::now() + Duration::days(days),
+        };
+        tokens.store(store("later", 100)).await.unwrap();
+        let soon = tokens.store(store("soon", 3)).await.unwrap();
+
+        let warnings = warnings(&settings(), &users, &tokens, &user).await.unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].credential, CredentialKind::ApiKey);
+        assert_eq!(warnings[0].id, Some(soon.id));
+        assert_eq!(warnings[0].expires_at, soon.expires_at);
+        assert!(!warnings[0].expired);
+
+        let disabled = CredentialExpirySettings::default();
+        assert!(super::warnings(&disabled, &users, &tokens, &user)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+//! Login funnel module.
+//!
+//! Module counts how many logins reach each step of the
+//! login flow and why the others failed, so drop-offs and
+//! concentrated attacks show up in the metrics. Every step
+//! and failure is also emitted on the `login_funnel`
+//! tracing target together with the client address.
+
+// Imports from external crates
+use std::{fmt, net::IpAddr};
+use uuid::Uuid;
+
+/// Tracing target of the funnel events.
+pub const FUNNEL_TARGET: &str = "login_funnel";
+
+/// Counter of the reached steps, labelled by `method` and `step`.
+pub const STEPS_METRIC: &str = "auth_login_funnel_steps_total";
+
+/// Counter of the failed steps, labelled by `method`, `step` and `reason`.
+pub const FAILURES_METRIC: &str = "auth_login_funnel_failures_total";
+
+/// ## Login funnel step enum.
+///
+/// # Variants
+/// - `CaptchaOk` - `captcha_ok`, client passed the reputation, throttle
+///   and CAPTCHA checks.
+/// - `CredentialsOk` - `credentials_ok`, email and password were verified.
+/// - `TokenValid` - `token_valid`, token of a magic link or device code
+///   was verified.
+/// - `MfaPrompted` - `mfa_prompted`, user was asked for a second factor.
+/// - `MfaOk` - `mfa_ok`, second factor was verified.
+/// - `TokenIssued` - `token_issued`, login completed with a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginStep {
+    CaptchaOk,
+    CredentialsOk,
+    TokenValid,
+    MfaPrompted,
+    MfaOk,
+    TokenIssued,
+}
+
+impl LoginStep {
+    /// Every step, in the order of the flow.
+    pub const ALL: [LoginStep; 6] = [
+        LoginStep::CaptchaOk,
+        LoginStep::CredentialsOk,
+        LoginStep::TokenValid,
+        LoginStep::MfaPrompted,
+        LoginStep::MfaOk,
+        LoginStep::TokenIssued,
+    ];
+
+    /// ## Returns the value of the `step` label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginStep::CaptchaOk => "captcha_ok",
+            LoginStep::CredentialsOk => "credentials_ok",
+            LoginStep::TokenValid => "token_valid",
+            LoginStep::MfaPrompted => "mfa_prompted",
+            LoginStep::MfaOk => "mfa_ok",
+            LoginStep::TokenIssued => "token_issued",
+        }
+    }
+}
+
+impl fmt::Display for LoginStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// ## Login failure reason enum.
+///
+/// # Variants
+/// - `UnknownUser` - `unknown_user`, no user has the email.
+/// - `InvalidPassword` - `invalid_password`, password does not match.
+/// - `UserDisabled` - `user_disabled`, password matches a disabled user.
+/// - `AccessDenied` - `access_denied`, rejected by the access policies.
+/// - `Blocked` - `blocked`, client address is blocked by its reputation.
+/// - `CaptchaFailed` - `captcha_failed`, required CAPTCHA is missing or
+///   was not solved.
+/// - `InvalidToken` - `invalid_token`, token is unknown or was used.
+/// - `TokenExpired` - `token_expired`, token was used too late.
+/// - `Denied` - `denied`, user denied the device.
+/// - `MfaInvalid` - `mfa_invalid`, second factor does not match.
+/// - `MfaExpired` - `mfa_expired`, second factor was entered too late.
+/// - `Error` - `error`, step failed on an internal error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginFailure {
+    UnknownUser,
+    InvalidPassword,
+    UserDisabled,
+    AccessDenied,
+    Blocked,
+    CaptchaFailed,
+    InvalidToken,
+    TokenExpired,
+    Denied,
+    MfaInvalid,
+    MfaExpired,
+    Error,
+}
+
+impl LoginFailure {
+    /// ## Returns the value of the `reason` label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginFailure::UnknownUser => "unknown_user",
+            LoginFailure::InvalidPassword => "invalid_password",
+            LoginFailure::UserDisabled => "user_disabled",
+            LoginFailure::AccessDenied => "access_denied",
+            LoginFailure::Blocked => "blocked",
+            LoginFailure::CaptchaFailed => "captcha_failed",
+            LoginFailure::InvalidToken => "invalid_token",
+            LoginFailure::TokenExpired => "token_expired",
+            LoginFailure::Denied => "denied",
+            LoginFailure::MfaInvalid => "mfa_invalid",
+            LoginFailure::MfaExpired => "mfa_expired",
+            LoginFailure::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for LoginFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// ## Login funnel struct.
+///
+/// Instruments one login attempt. Steps of a flow spread
+/// over several requests are recorded by a funnel per
+/// request, the counters add up to the same funnel.
+///
+/// ## Fields
+/// + `method`: `&'static str` - Login method, e.g. `password`.
+/// + `client`: `Option<IpAddr>` - Client address, only logged.
+/// + `user_id`: `Option<Uuid>` - User, once identified, only logged.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::funnel::{LoginFailure, LoginFunnel, LoginStep};
+///
+/// let funnel = LoginFunnel::new("password").client("203.0.113.7".parse().unwrap());
+///
+/// funnel.reached(LoginStep::CredentialsOk);
+/// funnel.failed(LoginStep::MfaOk, LoginFailure::MfaInvalid);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginFunnel {
+    pub method: &'static str,
+    pub client: Option<IpAddr>,
+    pub user_id: Option<Uuid>,
+}
+
+impl LoginFunnel {
+    /// ## Creates a new funnel of the login method.
+    pub fn new(method: &'static str) -> Self {
+        LoginFunnel {
+            method,
+            client: None,
+            user_id: None,
+        }
+    }
+
+    /// ## Sets the client address.
+    pub fn client(mut self, client: IpAddr) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// ## Sets the user once the credentials identified it.
+    pub fn identify(&mut self, user_id: Uuid) {
+        self.user_id = Some(user_id);
+    }
+
+    /// ## Records a reached step.
+    pub fn reached(&self, step: LoginStep) {
+        metrics::counter!(STEPS_METRIC, "method" => self.method, "step" => step.as_str())
+            .increment(1);
+
+        tracing::info!(
+            target: FUNNEL_TARGET,
+            method = self.method,
+            step = step.as_str(),
+            client = ?self.client,
+            user_id = ?self.user_id,
+            "Login step reached"
+        );
+    }
+
+    /// ## Returns a callback recording an internal error of the step.
+    ///
+    /// Callback is meant for `Result::inspect_err`.
+    pub fn error<E>(&self, step: LoginStep) -> impl Fn(&E) + '_ {
+        move |_| self.failed(step, LoginFailure::Error)
+    }
+
+    /// ## Records a failed step.
+    ///
+    /// ## Parameters
+    /// - `step`: `LoginStep` - Step the login did not reach.
+    /// - `reason`: `LoginFailure` - Why the step failed.
+    pub fn failed(&self, step: LoginStep, reason: LoginFailure) {
+        metrics::counter!(
+            FAILURES_METRIC,
+            "method" => self.method,
+            "step" => step.as_str(),
+            "reason" => reason.as_str()
+        )
+        .increment(1);
+
+        tracing::info!(
+            target: FUNNEL_TARGET,
+            method = self.method,
+            step = step.as_str(),
+            reason = reason.as_str(),
+            client = ?self.client,
+            user_id = ?self.user_id,
+            "Login step failed"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    // Test checks if steps and failures are counted with their labels.
+    #[test]
+    fn test_funnel_metrics() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            let mut funnel = LoginFunnel::new("password");
+            funnel.failed(LoginStep::CredentialsOk, LoginFailure::InvalidPassword);
+            funnel.identify(Uuid::new_v4());
+            for step in LoginStep::ALL {
+                funnel.reached(step);
+            }
+            funnel.reached(LoginStep::CredentialsOk);
+        });
+
+        let rendered = handle.render();
+
+        assert!(rendered.contains(
+            "auth_login_funnel_steps_total{method=\"password\",step=\"credentials_ok\"} 2"
+        ));
+        assert!(rendered.contains(
+            "auth_login_funnel_steps_total{method=\"password\",step=\"token_issued\"} 1"
+        ));
+        assert!(rendered.contains(
+            "auth_login_funnel_failures_total{method=\"password\",step=\"credentials_ok\",reason=\"invalid_password\"} 1"
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+//! Token issuer module.
+//!
+//! Issuer hands out the access tokens of the server in
+//! the format of `auth.token_format`. JWTs carry their
+//! claims and are verified offline with the published
+//! keys. Opaque tokens are random references, prefixed
+//! with `at_`, whose claims are stored server-side, so
+//! they are short and revoked at once.
+
+// Imports from external crates
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Local imports
+use crate::core::auth::jwt::{self, AccessClaims, Validation};
+use crate::core::auth::keys::KeyManager;
+use crate::core::config::TokenSettings;
+use crate::core::err::AppError;
+use crate::core::repo::AccessTokenRepo;
+
+/// Prefix of opaque access tokens.
+pub const OPAQUE_PREFIX: &str = "at_";
+
+/// Random bytes of an opaque access token.
+const OPAQUE_LEN: usize = 32;
+
+/// ## Checks if the token is an opaque access token.
+pub fn is_opaque(token: &str) -> bool {
+    token.starts_with(OPAQUE_PREFIX)
+}
+
+/// ## Token issuer struct.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::{issuer::TokenIssuer, jwt::SigningKey, keys::KeyManager};
+/// use axum_auth::core::config::TokenSettings;
+/// use axum_auth::core::repo::memory::MemoryAccessTokenRepo;
+/// use std::sync::Arc;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let settings = TokenSettings::default();
+/// let keys = Arc::new(KeyManager::new(SigningKey::generate(&settings.key_id).unwrap()));
+/// let tokens = TokenIssuer::new(keys, &settings)
+///     .with_store(Arc::new(MemoryAccessTokenRepo::default()));
+///
+/// let token = tokens.issue("billing", &["users:read"]).await.unwrap();
+/// assert!(token.starts_with("at_"));
+/// assert_eq!(tokens.verify(&token).await.unwrap().sub, "billing");
+///
+/// assert!(tokens.revoke(&token).await.unwrap());
+/// assert!(tokens.verify(&token).await.is_err());
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct TokenIssuer {
+    keys: Arc<KeyManager>,
+    settings: TokenSettings,
+    store: Option<Arc<dyn AccessTokenRepo>>,
+}
+
+impl TokenIssuer {
+    /// ## Creates a new `TokenIssuer` of JWTs.
+    ///
+    /// ## Parameters
+    /// - `keys`: `Arc<KeyManager>` - Keys the tokens are signed and verified with.
+    /// - `settings`: `&TokenSettings` - Issuer, audience and lifetime.
+    pub fn new(keys: Arc<KeyManager>, settings: &TokenSettings) -> Self {
+        TokenIssuer {
+            keys,
+            settings: settings.clone(),
+            store: None,
+        }
+    }
+
+    /// ## Issues opaque tokens stored in the repository.
+    ///
+    /// JWTs signed with the keys are still verified, so tokens
+    /// issued before the switch stay valid until they expire.
+    ///
+    /// ## Parameters
+    /// - `store`: `Arc<dyn AccessTokenRepo>` - Store of the opaque tokens.
+    pub fn with_store(mut self, store: Arc<dyn AccessTokenRepo>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// ## Returns the issuer, audience and lifetime of the tokens.
+    pub fn settings(&self) -> &TokenSettings {
+        &self.settings
+    }
+
+    /// ## Issues an access token.
+    ///
+    /// ## Parameters
+    /// - `subject`: `&str` - User or client the token is issued to.
+    /// - `scopes`: `&[&str]` - Granted scopes.
+    pub async fn issue(&self, subject: &str, scopes: &[&str]) -> Result<String, AppError> {
+        self.grant(jwt::claims(&self.settings, subject, scopes))
+            .await
+    }
+
+    /// ## Issues an access token of a login session.
+    ///
+    /// ## Parameters
+    /// - `user_id`: `Uuid` - User the token is issued to.
+    /// - `session_id`: `Uuid` - Session of the login, the `sid` claim.
+    /// - `scopes`: `&[&str]` - Granted scopes.
+    pub async fn issue_for_session(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+        scopes: &[&str],
+    ) -> Result<String, AppError> {
+        self.grant(AccessClaims {
+            sid: Some(session_id.to_string()),
+            ..jwt::claims(&self.settings, &user_id.to_string(), scopes)
+        })
+        .await
+    }
+
+    /// ## Verifies the token.
+    ///
+    /// ## Returns
+    /// + `Result<AccessClaims, AppError>`
+    ///     - `Ok(AccessClaims)`: Claims of a valid token.
+    ///     - `Err(AppError)`: `Auth` if the token is invalid, expired or,
+    ///       when opaque, revoked or unknown.
+    pub async fn verify(&self, token: &str) -> Result<AccessClaims, AppError> {
+        let validation = Validation::new(&self.settings.issuer, &self.settings.audience);
+
+        resolve(token, &self.keys, &validation, self.store.as_deref()).await
+    }
+
+    /// ## Revokes the token.
+    ///
+    /// JWTs can not be revoked, they are only rejected with
+    /// the session they belong to.
+    ///
+    /// ## Returns
+    /// + `Result<bool, AppError>`
+    ///     - `Ok(bool)`: If an active opaque token was revoked.
+    ///     - `Err(AppError)`: If the token store failed.
+    pub async fn revoke(&self, token: &str) -> Result<bool, AppError> {
+        match &self.store {
+            Some(store) if is_opaque(token) => store.revoke(token).await,
+            _ => Ok(false),
+        }
+    }
+
+    /// ## Signs or stores the claims in the configured format.
+    async fn grant(&self, claims: AccessClaims) -> Result<String, AppError> {
+        let Some(store) = &self.store else {
+            return self.keys.current().await?.sign(&claims);
+        };
+
+        let token = opaque_token()?;
+        store.store(&token, &claims).await?;
+
+        Ok(token)
+    }
+}
+
+/// ## Resolves the claims of a JWT or an opaque token.
+///
+/// ## Parameters
+/// - `token`: `&str` - Bearer token of the request.
+/// - `keys`: `&KeyManager` - Keys JWTs are verified with.
+/// - `validation`: `&Validation` - Expected issuer and audience of JWTs.
+/// - `store`: `Option<&dyn AccessTokenRepo>` - Store of opaque tokens,
+///   without one opaque tokens are rejected.
+pub(crate) async fn resolve(
+    token: &str,
+    keys: &KeyManager,
+    validation: &Validation,
+    store: Option<&dyn AccessTokenRepo>,
+) -> Result<AccessClaims, AppError> {
+    match store {
+        Some(store) if is_opaque(token) => store.resolve(token).await?.ok_or_else(|| {
+            AppError::auth("Token is revoked, expired or unknown").with_code("token.revoked")
+        }),
+        _ => jwt::verify(token, &keys.verification_keys(token).await?, validation),
+    }
+}
+
+/// ## Generates a random opaque token.
+fn opaque_token() -> Result<String, AppError> {
+    let mut bytes = [0u8; OPAQUE_LEN];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| AppError::internal("Failed to generate a token"))?;
+
+    Ok(format!(
+        "{}{}",
+        OPAQUE_PREFIX,
+        URL_SAFE_NO_PAD.encode(bytes)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::jwt::SigningKey;
+    use crate::core::repo::memory::MemoryAccessTokenRepo;
+
+    fn issuer() -> TokenIssuer {
+        let settings = TokenSettings::default();
+        let key = SigningKey::generate(&settings.key_id).unwrap();
+
+        TokenIssuer::new(Arc::new(KeyManager::new(key)), &settings)
+    }
+
+    // Test checks if JWTs are verified offline and can not be revoked.
+    #[tokio::test]
+    async fn test_jwt_tokens() {
+        let tokens = issuer();
+        let user_id = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+
+        let token = tokens
+            .issue_for_session(user_id, session_id, &["profile"])
+            .await
+            .unwrap();
+        assert!(!is_opaque(&token));
+
+        let claims = tokens.verify(&token).await.unwrap();
+        assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.sid, Some(session_id.to_string()));
+        assert!(!tokens.revoke(&token).await.unwrap());
+    }
+
+    // Test checks if opaque tokens resolve until revoked and JWTs keep working.
+    #[tokio::test]
+    async fn test_opaque_tokens() {
+        let jwt = issuer();
+        let tokens = jwt
+            .clone()
+            .with_store(Arc::new(MemoryAccessTokenRepo::default()));
+
+        let token = tokens.issue("billing", &["users:read"]).await.unwrap();
+        assert!(is_opaque(&token));
+        assert_eq!(token.len(), OPAQUE_PREFIX.len() + 43);
+        assert!(tokens.verify(&token).await.unwrap().has_scope("users:read"));
+        assert!(jwt.verify(&token).await.is_err());
+
+        let signed = jwt.issue("billing", &[]).await.unwrap();
+        assert_eq!(tokens.verify(&signed).await.unwrap().sub, "billing");
+
+        assert!(tokens.revoke(&token).await.unwrap());
+        assert_eq!(
+            tokens.verify(&token).await.unwrap_err().code,
+            "token.revoked"
+        );
+    }
+}
@@ -0,0 +1,589 @@
+//! Signing key manager module.
+//!
+//! Manager holds the key access tokens are signed with
+//! and the retired keys that still verify tokens. With
+//! `key_rotation` enabled the keys are stored in the
+//! database: the worker replaces the current key on its
+//! schedule and every server reloads the keys after
+//! `key_rotation.refresh` seconds, or at once when a
+//! token names a key it does not know yet. Retired keys
+//! stay published on `/.well-known/jwks.json` until their
+//! grace period is over. Stored keys are sealed with the
+//! master key of the `enc:` configuration values and bound
+//! to their `kid`.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use tokio::sync::Mutex;
+
+// Local imports
+use crate::core::auth::jwt::{self, key_id, JwkSet, SigningKey};
+use crate::core::config::secrets::MasterKey;
+use crate::core::config::{KeyRotationSettings, TokenSettings};
+use crate::core::err::AppError;
+use crate::core::repo::{NewSigningKey, SigningKeyRepo, StoredSigningKey};
+use crate::core::worker::{cron::Schedule, Job};
+
+/// Seconds between reloads forced by unknown key IDs.
+const MIN_RELOAD: i64 = 10;
+
+/// Prefix of the stored documents sealed with the master key.
+const SEALED_PREFIX: &[u8] = b"sealed:";
+
+/// ## Loaded keys.
+struct KeyRing {
+    current: Arc<SigningKey>,
+    retired: Vec<(Arc<SigningKey>, DateTime<Utc>)>,
+    loaded_at: Option<DateTime<Utc>>,
+}
+
+/// ## Signing key manager struct.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::{jwt::SigningKey, keys::KeyManager};
+/// use axum_auth::core::config::{secrets::MasterKey, KeyRotationSettings, TokenSettings};
+/// use axum_auth::core::repo::memory::MemorySigningKeyRepo;
+/// use std::sync::Arc;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let master_key = MasterKey::parse(&MasterKey::generate().unwrap()).unwrap();
+/// let keys = KeyManager::load(&TokenSettings::default())
+///     .unwrap()
+///     .with_store(
+///         Arc::new(MemorySigningKeyRepo::default()),
+///         master_key,
+///         &KeyRotationSettings::default(),
+///     );
+///
+/// let first = keys.current().await.unwrap();
+/// let kid = keys.rotate().await.unwrap();
+///
+/// assert_eq!(keys.current().await.unwrap().kid(), kid);
+/// assert!(keys.jwks().await.unwrap().find(first.kid()).is_some());
+/// # });
+/// ```
+pub struct KeyManager {
+    ring: RwLock<KeyRing>,
+    reload: Mutex<()>,
+    store: Option<(Arc<dyn SigningKeyRepo>, MasterKey)>,
+    initial: Option<NewSigningKey>,
+    kid_prefix: String,
+    grace: Duration,
+    refresh: Duration,
+}
+
+impl KeyManager {
+    /// ## Creates a new `KeyManager` of a single key.
+    ///
+    /// ## Parameters
+    /// - `key`: `impl Into<Arc<SigningKey>>` - Key the tokens are signed with.
+    pub fn new(key: impl Into<Arc<SigningKey>>) -> Self {
+        let key = key.into();
+        let kid_prefix = key.kid().to_string();
+
+        KeyManager {
+            ring: RwLock::new(KeyRing {
+                current: key,
+                retired: Vec::new(),
+                loaded_at: None,
+            }),
+            reload: Mutex::new(()),
+            store: None,
+            initial: None,
+            kid_prefix,
+            grace: Duration::zero(),
+            refresh: Duration::zero(),
+        }
+    }
+
+    /// ## Loads the configured key.
+    ///
+    /// Without a configured file a random key is generated,
+    /// its tokens are rejected after a restart.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&TokenSettings` - Key file and ID.
+    pub fn load(settings: &TokenSettings) -> Result<Self, AppError> {
+        let document = jwt::read_document(settings)?;
+        let key = SigningKey::from_pkcs8(&settings.key_id, &document)?;
+
+        Ok(KeyManager {
+            initial: Some(NewSigningKey {
+                kid: settings.key_id.clone(),
+                document,
+            }),
+            ..KeyManager::new(key)
+        })
+    }
+
+    /// ## Shares the keys through the store.
+    ///
+    /// An empty store is seeded with the configured key,
+    /// so tokens it signed stay valid after the switch.
+    /// Keys are sealed before they are stored.
+    ///
+    /// ## Parameters
+    /// - `store`: `Arc<dyn SigningKeyRepo>` - Keys of every server.
+    /// - `master_key`: `MasterKey` - Key sealing the stored keys.
+    /// - `settings`: `&KeyRotationSettings` - Grace period and refresh interval.
+    pub fn with_store(
+        mut self,
+        store: Arc<dyn SigningKeyRepo>,
+        master_key: MasterKey,
+        settings: &KeyRotationSettings,
+    ) -> Self {
+        self.store = Some((store, master_key));
+        self.grace = seconds(settings.grace);
+        self.refresh = seconds(settings.refresh);
+        self
+    }
+
+    /// ## Returns the key new tokens are signed with.
+    pub async fn current(&self) -> Result<Arc<SigningKey>, AppError> {
+        self.refresh(false).await?;
+
+        Ok(self.read().current.clone())
+    }
+
+    /// ## Returns the public keys of the current and the retired keys.
+    pub async fn jwks(&self) -> Result<JwkSet, AppError> {
+        self.refresh(false).await?;
+
+        Ok(self.key_set())
+    }
+
+    /// ## Returns the keys verifying the token.
+    ///
+    /// Keys are reloaded when the token names an unknown
+    /// key, another server may have loaded a newer one.
+    ///
+    /// ## Parameters
+    /// - `token`: `&str` - Token about to be verified.
+    pub async fn verification_keys(&self, token: &str) -> Result<JwkSet, AppError> {
+        self.refresh(false).await?;
+        let keys = self.key_set();
+        let unknown = key_id(token).is_ok_and(|kid| keys.find(&kid).is_none());
+        if !unknown {
+            return Ok(keys);
+        }
+        self.refresh(true).await?;
+
+        Ok(self.key_set())
+    }
+
+    /// ## Replaces the current key with a new random key.
+    ///
+    /// Retired key verifies tokens during the grace period.
+    ///
+    /// ## Returns
+    /// + `Result<String, AppError>`
+    ///     - `Ok(String)`: ID of the new current key.
+    ///     - `Err(AppError)`: If the key could not be generated or stored.
+    pub async fn rotate(&self) -> Result<String, AppError> {
+        let now = Utc::now();
+        let kid = format!("{}-{}", self.kid_prefix, now.format("%Y%m%d%H%M%S"));
+        let document = jwt::generate_document()?;
+        let grace_until = now + self.grace;
+
+        let Some((store, master_key)) = &self.store else {
+            let key = Arc::new(SigningKey::from_pkcs8(&kid, &document)?);
+            let mut ring = self.ring.write().unwrap_or_else(|e| e.into_inner());
+            let retired = std::mem::replace(&mut ring.current, key);
+            ring.retired.push((retired, grace_until));
+            return Ok(kid);
+        };
+
+        // Seeds an empty store first, the configured key is retired then
+        self.refresh(false).await?;
+        let key = seal(
+            master_key,
+            NewSigningKey {
+                kid: kid.clone(),
+                document,
+            },
+        )?;
+        if !store.rotate(key, grace_until).await? {
+            return Err(AppError::internal(format!(
+                "Signing key '{}' already exists",
+                kid
+            )));
+        }
+        self.fetch(store.as_ref(), master_key).await?;
+
+        Ok(kid)
+    }
+
+    /// ## Deletes stored keys past their grace period, returns how many.
+    pub async fn purge(&self) -> Result<u64, AppError> {
+        match &self.store {
+            Some((store, _)) => store.purge().await,
+            None => Ok(0),
+        }
+    }
+
+    /// ## Reloads the keys of the store when they are stale.
+    ///
+    /// Forced reloads happen at most every `MIN_RELOAD`
+    /// seconds. A failed reload keeps the loaded keys.
+    async fn refresh(&self, force: bool) -> Result<(), AppError> {
+        let Some((store, master_key)) = &self.store else {
+            return Ok(());
+        };
+        let max_age = match force {
+            true => Duration::seconds(MIN_RELOAD).min(self.refresh),
+            false => self.refresh,
+        };
+        let stale = |loaded_at: Option<DateTime<Utc>>| {
+            loaded_at.is_none_or(|loaded_at| loaded_at + max_age <= Utc::now())
+        };
+        if !stale(self.read().loaded_at) {
+            return Ok(());
+        }
+
+        // Requests waiting for the reload find the keys loaded
+        let _reload = self.reload.lock().await;
+        let loaded_at = self.read().loaded_at;
+        if !stale(loaded_at) {
+            return Ok(());
+        }
+        match self.fetch(store.as_ref(), master_key).await {
+            Ok(()) => Ok(()),
+            Err(e) if loaded_at.is_some() => {
+                tracing::warn!(error = %e, "Failed to reload signing keys");
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// ## Loads the keys of the store, seeding an empty one.
+    async fn fetch(
+        &self,
+        store: &dyn SigningKeyRepo,
+        master_key: &MasterKey,
+    ) -> Result<(), AppError> {
+        let mut stored = store.active().await?;
+        if stored.is_empty() {
+            let seed = match &self.initial {
+                Some(initial) => initial.clone(),
+                None => NewSigningKey {
+                    kid: self.read().current.kid().to_string(),
+                    document: jwt::generate_document()?,
+                },
+            };
+            let seed = seal(master_key, seed)?;
+            store.rotate(seed, Utc::now() + self.grace).await?;
+            stored = store.active().await?;
+        }
+
+        let mut current = None;
+        let mut retired = Vec::new();
+        for key in stored {
+            let document = open(master_key, &key)?;
+            let signing_key = Arc::new(SigningKey::from_pkcs8(&key.kid, &document)?);
+            match key.expires_at {
+                None if current.is_none() => current = Some(signing_key),
+                expires_at => {
+                    retired.push((signing_key, expires_at.unwrap_or(Utc::now() + self.grace)))
+                }
+            }
+        }
+        let Some(current) = current.or_else(|| retired.first().map(|(key, _)| key.clone())) else {
+            return Err(AppError::internal("No signing key is stored"));
+        };
+        retired.retain(|(key, _)| key.kid() != current.kid());
+
+        tracing::debug!(kid = %current.kid(), retired = retired.len(), "Loaded signing keys");
+        *self.ring.write().unwrap_or_else(|e| e.into_inner()) = KeyRing {
+            current,
+            retired,
+            loaded_at: Some(Utc::now()),
+        };
+
+        Ok(())
+    }
+
+    /// ## Returns the public keys of the loaded keys.
+    fn key_set(&self) -> JwkSet {
+        let now = Utc::now();
+        let ring = self.read();
+
+        JwkSet {
+            keys: std::iter::once(&ring.current)
+                .chain(
+                    ring.retired
+                        .iter()
+                        .filter(|(_, expires_at)| *expires_at > now)
+                        .map(|(key, _)| key),
+                )
+                .map(|key| key.jwk())
+                .collect(),
+        }
+    }
+
+    /// ## Locks the loaded keys for reading, a poisoned lock is recovered.
+    fn read(&self) -> RwLockReadGuard<'_, KeyRing> {
+        self.ring.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// ## Seals the document of the key with the master key, bound to its ID.
+fn seal(master_key: &MasterKey, key: NewSigningKey) -> Result<NewSigningKey, AppError> {
+    let mut document = SEALED_PREFIX.to_vec();
+    document.extend(master_key.seal(&key.document, key.kid.as_bytes())?);
+
+    Ok(NewSigningKey {
+        kid: key.kid,
+        document,
+    })
+}
+
+/// ## Opens the sealed document of the stored key.
+///
+/// Keys stored before they were sealed are read as they
+/// are, they are gone once their grace period is over.
+fn open(master_key: &MasterKey, key: &StoredSigningKey) -> Result<Vec<u8>, AppError> {
+    let Some(sealed) = key.document.strip_prefix(SEALED_PREFIX) else {
+        tracing::warn!(kid = %key.kid, "Signing key is stored unsealed, rotate the keys to seal it");
+        return Ok(key.document.clone());
+    };
+
+    master_key.open(sealed, key.kid.as_bytes()).map_err(|e| {
+        AppError::internal(format!("Failed to open signing key '{}'", key.kid)).with_source(e)
+    })
+}
+
+/// ## Converts seconds of the configuration into a duration.
+fn seconds(value: u64) -> Duration {
+    Duration::try_seconds(i64::try_from(value).unwrap_or(i64::MAX)).unwrap_or(Duration::MAX)
+}
+
+/// ## Key rotation job struct.
+///
+/// Job replaces the current key and removes the keys
+/// whose grace period is over.
+pub struct KeyRotation {
+    keys: Arc<KeyManager>,
+    schedule: Schedule,
+}
+
+impl KeyRotation {
+    /// ## Creates a new `KeyRotation` instance.
+    ///
+    /// ## Parameters
+    /// - `keys`: `Arc<KeyManager>` - Manager of the stored keys.
+    /// - `schedule`: `Schedule` - Schedule of the rotations.
+    pub fn new(keys: Arc<KeyManager>, schedule: Schedule) -> Self {
+        KeyRotation { keys, schedule }
+    }
+}
+
+#[async_trait]
+impl Job for KeyRotation {
+    fn name(&self) -> &str {
+        "key_rotation"
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn run(&self) -> Result<(), AppError> {
+        let kid = self.keys.rotate().await?;
+        tracing::info!(%kid, "Rotated signing key");
+        let removed = self.keys.purge().await?;
+        tracing::info!(removed, "Removed expired signing keys");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::repo::memory::MemorySigningKeyRepo;
+
+    fn settings(grace: u64, refresh: u64) -> KeyRotationSettings {
+        KeyRotationSettings {
+            enabled: true,
+            grace,
+            refresh,
+            ..KeyRotationSettings::default()
+        }
+    }
+
+    fn master_key() -> MasterKey {
+        MasterKey::parse(&MasterKey::generate().unwrap()).unwrap()
+    }
+
+    fn kids(keys: &JwkSet) -> Vec<&str> {
+        keys.keys.iter().map(|key| key.kid.as_str()).collect()
+    }
+
+    // Test checks if an empty store is seeded with the configured key.
+    #[tokio::test]
+    async fn test_seed_store() {
+        let store = Arc::new(MemorySigningKeyRepo::default());
+        let keys = KeyManager::load(&TokenSettings::default())
+            .unwrap()
+            .with_store(store.clone(), master_key(), &settings(3600, 300));
+        let configured = keys.read().current.jwk();
+
+        assert_eq!(keys.current().await.unwrap().jwk(), configured);
+
+        let stored = store.active().await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].kid, TokenSettings::default().key_id);
+    }
+
+    // Test checks if stored keys are sealed and only open with the master key.
+    #[tokio::test]
+    async fn test_sealed_store() {
+        let store = Arc::new(MemorySigningKeyRepo::default());
+        let configured = NewSigningKey {
+            kid: "default".to_string(),
+            document: jwt::generate_document().unwrap(),
+        };
+        let sealing = MasterKey::parse(&MasterKey::generate().unwrap()).unwrap();
+        let encoded = MasterKey::generate().unwrap();
+        let keys = KeyManager {
+            initial: Some(configured.clone()),
+            ..KeyManager::new(SigningKey::from_pkcs8("default", &configured.document).unwrap())
+        }
+        .with_store(
+            store.clone(),
+            MasterKey::parse(&encoded).unwrap(),
+            &settings(3600, 300),
+        );
+
+        let kid = keys.rotate().await.unwrap();
+        let stored = store.active().await.unwrap();
+        assert_eq!(stored.len(), 2);
+        for key in &stored {
+            assert!(key.document.starts_with(SEALED_PREFIX));
+            assert!(!key
+                .document
+                .windows(configured.document.len())
+                .any(|window| window == configured.document));
+        }
+
+        // Documents are bound to their key ID
+        let mut swapped = stored[0].clone();
+        swapped.kid = stored[1].kid.clone();
+        assert!(open(&MasterKey::parse(&encoded).unwrap(), &swapped).is_err());
+        assert!(open(&sealing, &stored[0]).is_err());
+
+        let server = KeyManager::new(SigningKey::generate("default").unwrap()).with_store(
+            store.clone(),
+            MasterKey::parse(&encoded).unwrap(),
+            &settings(3600, 300),
+        );
+        assert_eq!(server.current().await.unwrap().kid(), kid);
+        let other = KeyManager::new(SigningKey::generate("default").unwrap()).with_store(
+            store,
+            sealing,
+            &settings(3600, 300),
+        );
+        assert!(other.current().await.is_err());
+    }
+
+    // Test checks if keys stored before sealing are still read.
+    #[test]
+    fn test_open_unsealed() {
+        let key = StoredSigningKey {
+            kid: "default".to_string(),
+            document: jwt::generate_document().unwrap(),
+            created_at: Utc::now(),
+            expires_at: None,
+        };
+
+        assert_eq!(open(&master_key(), &key).unwrap(), key.document);
+    }
+
+    // Test checks if retired keys verify tokens until their grace period is over.
+    #[tokio::test]
+    async fn test_rotate_grace() {
+        let store = Arc::new(MemorySigningKeyRepo::default());
+        let keys = KeyManager::new(SigningKey::generate("default").unwrap()).with_store(
+            store.clone(),
+            master_key(),
+            &settings(3600, 300),
+        );
+        let first = keys.current().await.unwrap();
+        let token = first
+            .sign(&jwt::claims(&TokenSettings::default(), "user", &[]))
+            .unwrap();
+
+        let kid = keys.rotate().await.unwrap();
+        assert!(kid.starts_with("default-"));
+        assert_eq!(keys.current().await.unwrap().kid(), kid);
+        assert_eq!(kids(&keys.jwks().await.unwrap()), [kid.as_str(), "default"]);
+        assert!(keys
+            .verification_keys(&token)
+            .await
+            .unwrap()
+            .find("default")
+            .is_some());
+
+        let expired = KeyManager::new(SigningKey::generate("default").unwrap()).with_store(
+            Arc::new(MemorySigningKeyRepo::default()),
+            master_key(),
+            &settings(0, 300),
+        );
+        expired.current().await.unwrap();
+        let kid = expired.rotate().await.unwrap();
+        assert_eq!(kids(&expired.jwks().await.unwrap()), [kid.as_str()]);
+        assert_eq!(expired.purge().await.unwrap(), 1);
+    }
+
+    // Test checks if servers pick up keys rotated by another process.
+    #[tokio::test]
+    async fn test_reload_unknown_kid() {
+        let store = Arc::new(MemorySigningKeyRepo::default());
+        let encoded = MasterKey::generate().unwrap();
+        let server = KeyManager::new(SigningKey::generate("default").unwrap()).with_store(
+            store.clone(),
+            MasterKey::parse(&encoded).unwrap(),
+            &settings(3600, 3600),
+        );
+        let worker = KeyManager::new(SigningKey::generate("default").unwrap()).with_store(
+            store,
+            MasterKey::parse(&encoded).unwrap(),
+            &settings(3600, 3600),
+        );
+        server.current().await.unwrap();
+
+        let kid = worker.rotate().await.unwrap();
+        let token = worker
+            .current()
+            .await
+            .unwrap()
+            .sign(&jwt::claims(&TokenSettings::default(), "user", &[]))
+            .unwrap();
+
+        // Unknown key IDs reload the keys at most every MIN_RELOAD seconds
+        assert_eq!(server.current().await.unwrap().kid(), "default");
+        let verification = server.verification_keys(&token).await.unwrap();
+        assert!(verification.find(&kid).is_none());
+
+        server.ring.write().unwrap().loaded_at = Some(Utc::now() - Duration::seconds(MIN_RELOAD));
+        let verification = server.verification_keys(&token).await.unwrap();
+        assert!(verification.find(&kid).is_some());
+        assert_eq!(server.current().await.unwrap().kid(), kid);
+    }
+
+    // Test checks if a manager without store rotates in memory.
+    #[tokio::test]
+    async fn test_rotate_in_memory() {
+        let keys = KeyManager::new(SigningKey::generate("default").unwrap());
+
+        let kid = keys.rotate().await.unwrap();
+
+        assert_eq!(keys.current().await.unwrap().kid(), kid);
+        assert_eq!(kids(&keys.jwks().await.unwrap()), [kid.as_str()]);
+        assert_eq!(keys.purge().await.unwrap(), 0);
+    }
+}
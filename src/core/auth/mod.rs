@@ -0,0 +1,38 @@
+//! Authentication module.
+//!
+//! Module contains the building blocks used by the
+//! login and registration flows.
+
+// References to submodules
+pub mod access;
+pub mod authz;
+pub mod claims;
+pub mod jwt;
+pub mod password;
+pub mod rbac;
+
+#[cfg(feature = "server")]
+pub mod anomaly;
+#[cfg(feature = "server")]
+pub mod breach;
+#[cfg(feature = "server")]
+pub mod captcha;
+#[cfg(feature = "server")]
+pub mod directory;
+#[cfg(feature = "server")]
+pub mod expiry;
+#[cfg(feature = "server")]
+pub mod funnel;
+#[cfg(feature = "server")]
+pub mod issuer;
+#[cfg(feature = "server")]
+pub mod keys;
+#[cfg(feature = "server")]
+pub mod rate_limit;
+#[cfg(feature = "server")]
+pub mod reputation;
+#[cfg(feature = "server")]
+pub mod throttle;
+
+/// Tracing target of the audit log events.
+pub const AUDIT_TARGET: &str = "audit";
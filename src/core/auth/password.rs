@@ -0,0 +1,732 @@
+//! Password hashing module.
+//!
+//! Module defines the `PasswordHasher` trait with Argon2id
+//! and bcrypt implementations. Every hash is stored with
+//! the identifier of its algorithm, so hashes created with
+//! an older algorithm or weaker parameters keep working
+//! and are upgraded the next time the user logs in.
+//! New passwords have to pass the `PasswordPolicy`, which
+//! also rejects passwords known from data breaches.
+
+// Imports from external crates
+use argon2::{Argon2, Params, PasswordHash, PasswordVerifier, Version};
+use password_hash::{rand_core::OsRng, PasswordHasher as _, SaltString};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+// Local imports
+#[cfg(feature = "hibp")]
+use super::breach::HibpRange;
+#[cfg(feature = "server")]
+use super::breach::{BloomFilter, BreachedPasswords};
+#[cfg(feature = "server")]
+use super::funnel::{LoginFailure, LoginFunnel, LoginStep};
+#[cfg(feature = "server")]
+use crate::core::config::BreachSettings;
+use crate::core::config::PasswordSettings;
+use crate::core::err::{AppError, ErrorKind};
+#[cfg(feature = "server")]
+use crate::core::repo::UserRepo;
+#[cfg(feature = "server")]
+use crate::core::users::User;
+
+/// ## Password hashing algorithm enum.
+///
+/// # Variants
+/// - `Argon2id` - Argon2id, the default.
+/// - `Bcrypt` - bcrypt, for hashes imported from other systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    Argon2id,
+    Bcrypt,
+}
+
+impl Algorithm {
+    /// ## Returns the identifier stored next to the hash.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Algorithm::Argon2id => "argon2id",
+            Algorithm::Bcrypt => "bcrypt",
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "argon2id" => Ok(Algorithm::Argon2id),
+            "bcrypt" => Ok(Algorithm::Bcrypt),
+            _ => Err(AppError::new(
+                ErrorKind::Validation,
+                format!("Unknown password algorithm '{}'", value),
+                None,
+            )),
+        }
+    }
+}
+
+/// ## Stored password hash struct.
+///
+/// ## Fields
+/// + `algorithm`: `Algorithm` - Algorithm that created the hash.
+/// + `hash`: `String` - Encoded hash, including salt and parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredHash {
+    pub algorithm: Algorithm,
+    pub hash: String,
+}
+
+impl StoredHash {
+    /// ## Parses a hash created by another system.
+    ///
+    /// The algorithm is detected from the prefix, `$argon2id$`
+    /// for Argon2id and `$2a$`, `$2b$` or `$2y$` for bcrypt.
+    ///
+    /// ## Parameters
+    /// - `hash`: `&str` - Encoded hash, including salt and parameters.
+    ///
+    /// ## Returns
+    /// + `Result<StoredHash, AppError>`
+    ///     - `Ok(StoredHash)`: Hash with its algorithm.
+    ///     - `Err(AppError)`: `Validation` if the hash is of neither
+    ///       algorithm or malformed.
+    ///
+    /// ## Examples
+    /// ```
+    /// use axum_auth::core::auth::password::{Algorithm, StoredHash};
+    ///
+    /// let hash = "$2b$04$EGdrhbKUv8Oc9vGiXX0HQOxSg445d458Muh7DAHskb6QbtCvdxcie";
+    /// assert_eq!(StoredHash::parse(hash).unwrap().algorithm, Algorithm::Bcrypt);
+    /// assert!(StoredHash::parse("plaintext").is_err());
+    /// ```
+    pub fn parse(hash: &str) -> Result<Self, AppError> {
+        let algorithm = if hash.starts_with("$argon2id$") {
+            PasswordHash::new(hash)
+                .map(|_| Algorithm::Argon2id)
+                .map_err(|e| invalid_hash(Algorithm::Argon2id, e))?
+        } else if ["$2a$", "$2b$", "$2y$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+        {
+            hash.parse::<bcrypt::HashParts>()
+                .map(|_| Algorithm::Bcrypt)
+                .map_err(|e| invalid_hash(Algorithm::Bcrypt, e))?
+        } else {
+            return Err(AppError::new(
+                ErrorKind::Validation,
+                "Password hash is neither Argon2id nor bcrypt".to_string(),
+                None,
+            ));
+        };
+
+        Ok(StoredHash {
+            algorithm,
+            hash: hash.to_string(),
+        })
+    }
+}
+
+/// ## Returns the error of a malformed imported hash.
+fn invalid_hash(algorithm: Algorithm, e: impl fmt::Display) -> AppError {
+    AppError::new(
+        ErrorKind::Validation,
+        format!("Invalid {} password hash: {}", algorithm, e),
+        None,
+    )
+}
+
+/// ## Password hasher trait.
+///
+/// Hashing is CPU bound by design, callers on the
+/// async runtime should expect it to take milliseconds.
+pub trait PasswordHasher: Send + Sync {
+    /// Algorithm of the hasher.
+    fn algorithm(&self) -> Algorithm;
+
+    /// Hashes the password with a new random salt.
+    fn hash(&self, password: &str) -> Result<String, AppError>;
+
+    /// Checks the password against a hash of this algorithm.
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, AppError>;
+
+    /// Checks if the hash was created with other parameters
+    /// than the hasher uses now.
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+/// ## Argon2id hasher struct.
+///
+/// ## Fields
+/// + `params`: `argon2::Params` - Memory, iterations and parallelism.
+#[derive(Debug, Clone)]
+pub struct Argon2idHasher {
+    params: Params,
+}
+
+impl Argon2idHasher {
+    /// ## Creates a new `Argon2idHasher` instance.
+    ///
+    /// ## Parameters
+    /// - `memory_kib`: `u32` - Memory cost in KiB.
+    /// - `iterations`: `u32` - Number of passes.
+    /// - `parallelism`: `u32` - Number of lanes.
+    ///
+    /// ## Returns
+    /// + `Result<Argon2idHasher, AppError>`
+    ///     - `Ok(Argon2idHasher)`: New hasher.
+    ///     - `Err(AppError)`: If the parameters are out of range.
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, AppError> {
+        let params = Params::new(memory_kib, iterations, parallelism, None).map_err(|e| {
+            AppError::new(
+                ErrorKind::Config,
+                format!("Invalid Argon2id parameters: {}", e),
+                None,
+            )
+        })?;
+
+        Ok(Argon2idHasher { params })
+    }
+
+    /// ## Returns the Argon2id context of the parameters.
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(
+            argon2::Algorithm::Argon2id,
+            Version::V0x13,
+            self.params.clone(),
+        )
+    }
+}
+
+impl PasswordHasher for Argon2idHasher {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Argon2id
+    }
+
+    fn hash(&self, password: &str) -> Result<String, AppError> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| hash_error(Algorithm::Argon2id, e))
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, AppError> {
+        let hash = PasswordHash::new(hash).map_err(|e| hash_error(Algorithm::Argon2id, e))?;
+
+        match self.argon2().verify_password(password.as_bytes(), &hash) {
+            Ok(()) => Ok(true),
+            Err(password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(hash_error(Algorithm::Argon2id, e)),
+        }
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&hash) else {
+            return true;
+        };
+
+        hash.algorithm != argon2::Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+    }
+}
+
+/// ## bcrypt hasher struct.
+///
+/// ## Fields
+/// + `cost`: `u32` - Logarithmic cost factor.
+#[derive(Debug, Clone)]
+pub struct BcryptHasher {
+    cost: u32,
+}
+
+impl BcryptHasher {
+    /// ## Creates a new `BcryptHasher` instance.
+    ///
+    /// ## Returns
+    /// + `Result<BcryptHasher, AppError>`
+    ///     - `Ok(BcryptHasher)`: New hasher.
+    ///     - `Err(AppError)`: If the cost is outside of 4..=31.
+    pub fn new(cost: u32) -> Result<Self, AppError> {
+        if !(4..=31).contains(&cost) {
+            return Err(AppError::new(
+                ErrorKind::Config,
+                format!("Invalid bcrypt cost {}, expected 4 to 31", cost),
+                None,
+            ));
+        }
+
+        Ok(BcryptHasher { cost })
+    }
+}
+
+impl PasswordHasher for BcryptHasher {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Bcrypt
+    }
+
+    fn hash(&self, password: &str) -> Result<String, AppError> {
+        bcrypt::hash(password, self.cost).map_err(|e| hash_error(Algorithm::Bcrypt, e))
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, AppError> {
+        bcrypt::verify(password, hash).map_err(|e| hash_error(Algorithm::Bcrypt, e))
+    }
+
+    // Hashes look like `$2b$12$<salt and hash>`
+    fn needs_rehash(&self, hash: &str) -> bool {
+        hash.split('$').nth(2).and_then(|cost| cost.parse().ok()) != Some(self.cost)
+    }
+}
+
+/// ## Result of a password verification.
+///
+/// # Variants
+/// - `Invalid` - Password does not match.
+/// - `Valid` - Password matches, `rehash` holds the upgraded
+///   hash when the stored one is outdated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verification {
+    Invalid,
+    Valid { rehash: Option<StoredHash> },
+}
+
+/// ## Password service struct.
+///
+/// Service hashes new passwords with the configured
+/// algorithm and verifies hashes of every known algorithm.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::password::{Passwords, Verification};
+/// use axum_auth::core::config::PasswordSettings;
+///
+/// let passwords = Passwords::new(&PasswordSettings::default()).unwrap();
+/// let stored = passwords.hash("correct horse").unwrap();
+///
+/// assert_eq!(
+///     passwords.verify("correct horse", &stored).unwrap(),
+///     Verification::Valid { rehash: None }
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Passwords {
+    current: Algorithm,
+    hashers: Arc<HashMap<Algorithm, Box<dyn PasswordHasher>>>,
+}
+
+impl fmt::Debug for Passwords {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Passwords")
+            .field("current", &self.current)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Passwords {
+    /// ## Creates the service from the settings.
+    ///
+    /// ## Returns
+    /// + `Result<Passwords, AppError>`
+    ///     - `Ok(Passwords)`: New service.
+    ///     - `Err(AppError)`: If the parameters are invalid.
+    pub fn new(settings: &PasswordSettings) -> Result<Self, AppError> {
+        let argon2 = &settings.argon2;
+        let hashers: Vec<Box<dyn PasswordHasher>> = vec![
+            Box::new(Argon2idHasher::new(
+                argon2.memory_kib,
+                argon2.iterations,
+                argon2.parallelism,
+            )?),
+            Box::new(BcryptHasher::new(settings.bcrypt_cost)?),
+        ];
+
+        Ok(Passwords {
+            current: settings.algorithm,
+            hashers: Arc::new(
+                hashers
+                    .into_iter()
+                    .map(|hasher| (hasher.algorithm(), hasher))
+                    .collect(),
+            ),
+        })
+    }
+
+    /// ## Hashes the password with the configured algorithm.
+    pub fn hash(&self, password: &str) -> Result<StoredHash, AppError> {
+        Ok(StoredHash {
+            algorithm: self.current,
+            hash: self.hasher(self.current).hash(password)?,
+        })
+    }
+
+    /// ## Verifies the password against the stored hash.
+    ///
+    /// Hashes of another algorithm or with other parameters
+    /// than configured are re-hashed when the password matches.
+    ///
+    /// ## Returns
+    /// + `Result<Verification, AppError>`
+    ///     - `Ok(Verification)`: Result of the verification.
+    ///     - `Err(AppError)`: If the stored hash is malformed.
+    pub fn verify(&self, password: &str, stored: &StoredHash) -> Result<Verification, AppError> {
+        let hasher = self.hasher(stored.algorithm);
+
+        if !hasher.verify(password, &stored.hash)? {
+            return Ok(Verification::Invalid);
+        }
+
+        let outdated = stored.algorithm != self.current || hasher.needs_rehash(&stored.hash);
+        let rehash = match outdated {
+            true => Some(self.hash(password)?),
+            false => None,
+        };
+
+        Ok(Verification::Valid { rehash })
+    }
+
+    /// ## Returns the hasher of the algorithm.
+    fn hasher(&self, algorithm: Algorithm) -> &dyn PasswordHasher {
+        // Every algorithm is registered in `new`
+        self.hashers[&algorithm].as_ref()
+    }
+}
+
+/// ## Authenticates a user with email and password.
+///
+/// Outdated hashes are upgraded after a successful
+/// verification. Failing to store the new hash does
+/// not fail the login, the upgrade is retried next time.
+/// The outcome is recorded as the `credentials_ok` step
+/// of the login funnel.
+///
+/// ## Parameters
+/// - `users`: `&dyn UserRepo` - User repository.
+/// - `passwords`: `&Passwords` - Password service.
+/// - `funnel`: `&mut LoginFunnel` - Funnel of the login attempt.
+/// - `email`: `&str` - Email of the user.
+/// - `password`: `&str` - Password to verify.
+///
+/// ## Returns
+/// + `Result<User, AppError>`
+///     - `Ok(User)`: Authenticated user.
+///     - `Err(AppError)`: `Auth` if the credentials are wrong
+///       or the user is disabled.
+#[cfg(feature = "server")]
+pub async fn authenticate(
+    users: &dyn UserRepo,
+    passwords: &Passwords,
+    funnel: &mut LoginFunnel,
+    email: &str,
+    password: &str,
+) -> Result<User, AppError> {
+    let step = LoginStep::CredentialsOk;
+    let rejected = |reason: LoginFailure| {
+        funnel.failed(step, reason);
+        AppError::new(
+            ErrorKind::Auth,
+            "Invalid email or password".to_string(),
+            None,
+        )
+    };
+    let failed = |_: &AppError| funnel.failed(step, LoginFailure::Error);
+
+    let credentials = users.credentials(email).await.inspect_err(failed)?;
+    let Some((user, stored)) = credentials else {
+        // Spend the same time as a real verification,
+        // so response times do not reveal known emails
+        passwords.hash(password).inspect_err(failed)?;
+        return Err(rejected(LoginFailure::UnknownUser));
+    };
+
+    let rehash = match passwords.verify(password, &stored).inspect_err(failed)? {
+        Verification::Valid { rehash } if !user.disabled => rehash,
+        Verification::Valid { .. } => return Err(rejected(LoginFailure::UserDisabled)),
+        Verification::Invalid => return Err(rejected(LoginFailure::InvalidPassword)),
+    };
+
+    funnel.identify(user.id);
+    funnel.reached(step);
+
+    if let Some(rehash) = rehash {
+        if let Err(e) = users.set_password(user.id, &rehash).await {
+            tracing::warn!(user_id = %user.id, error = %e, "Failed to upgrade password hash");
+        }
+    }
+
+    Ok(user)
+}
+
+/// ## Maps a hashing error into an application error.
+fn hash_error(algorithm: Algorithm, e: impl fmt::Display) -> AppError {
+    AppError::new(
+        ErrorKind::Internal,
+        format!("Failed to process {} password hash: {}", algorithm, e),
+        None,
+    )
+}
+
+/// Minimum length of a new password in characters.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// ## Password policy struct.
+///
+/// Policy checks new passwords before they are hashed.
+/// Breach sources are asked in order, a later one only
+/// answers when the ones before failed. Without any
+/// answer the password is accepted, an outage of the
+/// breach check never blocks password changes.
+///
+/// ## Examples
+/// ```
+/// use std::sync::Arc;
+/// use axum_auth::core::auth::breach::BloomFilter;
+/// use axum_auth::core::auth::password::PasswordPolicy;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let list = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\n";
+/// let policy = PasswordPolicy::default()
+///     .with_breaches(Arc::new(BloomFilter::build(list.as_bytes(), 1, 0.001).unwrap()));
+///
+/// assert!(policy.check("correct horse battery").await.is_ok());
+/// assert!(policy.check("password").await.is_err());
+/// # });
+/// ```
+#[cfg(feature = "server")]
+#[derive(Clone, Default)]
+pub struct PasswordPolicy {
+    breaches: Vec<Arc<dyn BreachedPasswords>>,
+}
+
+#[cfg(feature = "server")]
+impl PasswordPolicy {
+    /// ## Creates the policy of the breach settings.
+    ///
+    /// Range API is asked first when it is built in, the
+    /// Bloom filter is loaded as its fallback.
+    ///
+    /// ## Returns
+    /// + `Result<PasswordPolicy, AppError>`
+    ///     - `Ok(PasswordPolicy)`: New policy.
+    ///     - `Err(AppError)`: If the Bloom filter can not be loaded.
+    pub fn new(settings: &BreachSettings) -> Result<Self, AppError> {
+        let mut policy = PasswordPolicy::default();
+        if !settings.enabled {
+            return Ok(policy);
+        }
+
+        #[cfg(feature = "hibp")]
+        if settings.hibp {
+            let timeout = std::time::Duration::from_secs(settings.timeout);
+            policy = policy.with_breaches(Arc::new(HibpRange::new(&settings.api_url, timeout)?));
+        }
+        if let Some(path) = &settings.bloom_filter_path {
+            policy = policy.with_breaches(Arc::new(BloomFilter::load(path)?));
+        }
+
+        Ok(policy)
+    }
+
+    /// ## Adds a source of breached passwords after the others.
+    pub fn with_breaches(mut self, breaches: Arc<dyn BreachedPasswords>) -> Self {
+        self.breaches.push(breaches);
+        self
+    }
+
+    /// ## Checks a new password.
+    ///
+    /// ## Parameters
+    /// - `password`: `&str` - Password chosen by the user.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the password may be used.
+    ///     - `Err(AppError)`: `Validation` if it is shorter than
+    ///       `MIN_PASSWORD_LENGTH`, `password.breached` if it appeared
+    ///       in a data breach.
+    pub async fn check(&self, password: &str) -> Result<(), AppError> {
+        if password.chars().count() < MIN_PASSWORD_LENGTH {
+            return Err(AppError::validation(format!(
+                "New password must be at least {} characters long",
+                MIN_PASSWORD_LENGTH
+            )));
+        }
+
+        for source in &self.breaches {
+            match source.contains(password).await {
+                Ok(true) => {
+                    return Err(AppError::validation(
+                        "Password appeared in a data breach, choose another one",
+                    )
+                    .with_code("password.breached"));
+                }
+                Ok(false) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!(source = source.name(), error = %e, "Breached password check failed");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::Argon2Settings;
+
+    // Cheap parameters, the defaults are slow in debug builds.
+    fn settings(algorithm: Algorithm) -> PasswordSettings {
+        PasswordSettings {
+            algorithm,
+            argon2: Argon2Settings {
+                memory_kib: 1024,
+                iterations: 1,
+                parallelism: 1,
+            },
+            bcrypt_cost: 4,
+            ..PasswordSettings::default()
+        }
+    }
+
+    // Test checks if both hashers verify their own hashes.
+    #[test]
+    fn test_hash_and_verify() {
+        for algorithm in [Algorithm::Argon2id, Algorithm::Bcrypt] {
+            let passwords = Passwords::new(&settings(algorithm)).unwrap();
+            let stored = passwords.hash("hunter2").unwrap();
+
+            assert_eq!(stored.algorithm, algorithm);
+            assert_eq!(
+                passwords.verify("hunter2", &stored).unwrap(),
+                Verification::Valid { rehash: None }
+            );
+            assert_eq!(
+                passwords.verify("hunter3", &stored).unwrap(),
+                Verification::Invalid
+            );
+        }
+    }
+
+    // Test checks if hashes of another algorithm are upgraded.
+    #[test]
+    fn test_verify_rehash_algorithm() {
+        let stored = Passwords::new(&settings(Algorithm::Bcrypt))
+            .unwrap()
+            .hash("hunter2")
+            .unwrap();
+        let passwords = Passwords::new(&settings(Algorithm::Argon2id)).unwrap();
+
+        let Verification::Valid {
+            rehash: Some(rehash),
+        } = passwords.verify("hunter2", &stored).unwrap()
+        else {
+            panic!("bcrypt hash was not upgraded");
+        };
+
+        assert_eq!(rehash.algorithm, Algorithm::Argon2id);
+        assert_eq!(
+            passwords.verify("hunter2", &rehash).unwrap(),
+            Verification::Valid { rehash: None }
+        );
+    }
+
+    // Test checks if changed parameters are detected.
+    #[test]
+    fn test_needs_rehash_parameters() {
+        let weak = Argon2idHasher::new(1024, 1, 1).unwrap();
+        let strong = Argon2idHasher::new(2048, 1, 1).unwrap();
+        let hash = weak.hash("hunter2").unwrap();
+
+        assert!(!weak.needs_rehash(&hash));
+        assert!(strong.needs_rehash(&hash));
+
+        let hash = BcryptHasher::new(4).unwrap().hash("hunter2").unwrap();
+
+        assert!(!BcryptHasher::new(4).unwrap().needs_rehash(&hash));
+        assert!(BcryptHasher::new(5).unwrap().needs_rehash(&hash));
+    }
+
+    // Test checks if algorithm identifiers round trip.
+    #[test]
+    fn test_algorithm_from_str() {
+        for algorithm in [Algorithm::Argon2id, Algorithm::Bcrypt] {
+            assert_eq!(algorithm.as_str().parse::<Algorithm>(), Ok(algorithm));
+        }
+
+        assert_eq!(
+            "md5".parse::<Algorithm>().unwrap_err().kind,
+            ErrorKind::Validation
+        );
+    }
+
+    // Test checks if logins are rejected and outdated hashes upgraded on success.
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_authenticate() {
+        use crate::core::repo::memory::MemoryUserRepo;
+
+        let stored = Passwords::new(&settings(Algorithm::Bcrypt))
+            .unwrap()
+            .hash("hunter2")
+            .unwrap();
+        let passwords = Passwords::new(&settings(Algorithm::Argon2id)).unwrap();
+        let user = User {
+            id: uuid::Uuid::new_v4(),
+            email: "user@example.com".to_string(),
+            display_name: None,
+            org_id: None,
+            disabled: false,
+            password_reset_required: false,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            created_at_display: None,
+            updated_at_display: None,
+        };
+        let users = MemoryUserRepo::default();
+        users.insert(user.clone(), Some(stored));
+        let mut funnel = LoginFunnel::new("password");
+
+        for (email, password) in [("user@example.com", "hunter3"), ("nobody@example.com", "x")] {
+            let result = authenticate(&users, &passwords, &mut funnel, email, password).await;
+
+            assert_eq!(result.unwrap_err().kind, ErrorKind::Auth);
+        }
+
+        let authenticated = authenticate(
+            &users,
+            &passwords,
+            &mut funnel,
+            "user@example.com",
+            "hunter2",
+        )
+        .await
+        .unwrap();
+        let (_, upgraded) = users
+            .credentials("user@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(authenticated.id, user.id);
+        assert_eq!(funnel.user_id, Some(user.id));
+        assert_eq!(upgraded.algorithm, Algorithm::Argon2id);
+    }
+}
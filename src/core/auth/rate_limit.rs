@@ -0,0 +1,194 @@
+//! Rate limit module.
+//!
+//! Fixed window counters of attempts, kept in memory
+//! per instance. Every flow limits its own scope, e.g.
+//! `magic_link`, with the rule it sets from its settings,
+//! so the flows share one limiter without sharing counters.
+//! Rules may be replaced while the limiter is in use, e.g.
+//! when the configuration file is reloaded.
+
+// Imports from external crates
+use std::{
+    collections::HashMap,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+// Local imports
+use crate::core::config::RateLimitRule;
+use crate::core::err::{AppError, ErrorKind};
+
+/// Number of counters from which expired ones are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// ## Counter of a window struct.
+#[derive(Debug, Clone, Copy)]
+struct Counter {
+    started: Instant,
+    attempts: u32,
+}
+
+/// ## Rate limiter struct.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::rate_limit::RateLimiter;
+/// use axum_auth::core::config::RateLimitRule;
+///
+/// let limiter = RateLimiter::default();
+/// limiter.set_rule("magic_link", RateLimitRule { limit: 1, window: 60 });
+///
+/// assert!(limiter.hit("magic_link", "user@example.com").is_ok());
+/// assert!(limiter.hit("magic_link", "user@example.com").is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    counters: Mutex<HashMap<(String, String), Counter>>,
+    rules: RwLock<HashMap<String, RateLimitRule>>,
+}
+
+impl RateLimiter {
+    /// ## Sets the rule of the scope.
+    ///
+    /// A replaced rule applies to the next attempt, the
+    /// counters of the current windows are kept.
+    ///
+    /// ## Parameters
+    /// - `scope`: `&str` - Flow the rule belongs to.
+    /// - `rule`: `RateLimitRule` - Allowed attempts per window.
+    pub fn set_rule(&self, scope: &str, rule: RateLimitRule) {
+        let mut rules = self.rules.write().unwrap_or_else(|e| e.into_inner());
+        rules.insert(scope.to_string(), rule);
+    }
+
+    /// ## Returns the rule of the scope.
+    pub fn rule(&self, scope: &str) -> Option<RateLimitRule> {
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        rules.get(scope).copied()
+    }
+
+    /// ## Counts an attempt of the key.
+    ///
+    /// ## Parameters
+    /// - `scope`: `&str` - Flow the attempt belongs to.
+    /// - `key`: `&str` - Limited subject, e.g. an email address.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the attempt is allowed.
+    ///     - `Err(AppError)`: `rate_limited` with status 429 if the
+    ///       key used up the attempts of the current window, or
+    ///       `Internal` if the scope has no rule.
+    pub fn hit(&self, scope: &str, key: &str) -> Result<(), AppError> {
+        let Some(rule) = self.rule(scope) else {
+            return Err(AppError::internal(format!(
+                "Rate limit scope '{}' has no rule",
+                scope
+            )));
+        };
+        let now = Instant::now();
+        let window = Duration::from_secs(rule.window);
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+
+        if counters.len() >= PRUNE_THRESHOLD {
+            counters.retain(|_, counter| now.duration_since(counter.started) < window);
+        }
+
+        let counter = counters
+            .entry((scope.to_string(), key.to_string()))
+            .or_insert(Counter {
+                started: now,
+                attempts: 0,
+            });
+        if now.duration_since(counter.started) >= window {
+            *counter = Counter {
+                started: now,
+                attempts: 0,
+            };
+        }
+
+        if counter.attempts >= rule.limit {
+            return Err(AppError::new(
+                ErrorKind::Forbidden,
+                "Too many attempts, try again later".to_string(),
+                None,
+            )
+            .with_code("rate_limited")
+            .with_status_hint(429));
+        }
+        counter.attempts += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if keys and scopes are counted separately.
+    #[test]
+    fn test_hit_per_key() {
+        let limiter = RateLimiter::default();
+        let rule = RateLimitRule {
+            limit: 2,
+            window: 60,
+        };
+        limiter.set_rule("magic_link", rule);
+        limiter.set_rule("login", rule);
+
+        assert!(limiter.hit("magic_link", "a").is_ok());
+        assert!(limiter.hit("magic_link", "a").is_ok());
+
+        let error = limiter.hit("magic_link", "a").unwrap_err();
+        assert_eq!(error.code, "rate_limited");
+        assert_eq!(error.status_hint, Some(429));
+
+        assert!(limiter.hit("magic_link", "b").is_ok());
+        assert!(limiter.hit("login", "a").is_ok());
+        assert!(limiter.hit("signup", "a").is_err());
+    }
+
+    // Test checks if a replaced rule applies to the current window.
+    #[test]
+    fn test_set_rule() {
+        let limiter = RateLimiter::default();
+        limiter.set_rule(
+            "magic_link",
+            RateLimitRule {
+                limit: 1,
+                window: 60,
+            },
+        );
+
+        assert!(limiter.hit("magic_link", "a").is_ok());
+        assert!(limiter.hit("magic_link", "a").is_err());
+
+        limiter.set_rule(
+            "magic_link",
+            RateLimitRule {
+                limit: 2,
+                window: 60,
+            },
+        );
+        assert!(limiter.hit("magic_link", "a").is_ok());
+        assert!(limiter.hit("magic_link", "a").is_err());
+    }
+
+    // Test checks if a new window starts once the old one is over.
+    #[test]
+    fn test_hit_window_resets() {
+        let limiter = RateLimiter::default();
+        limiter.set_rule(
+            "magic_link",
+            RateLimitRule {
+                limit: 1,
+                window: 0,
+            },
+        );
+
+        // Every attempt opens a new window of zero seconds
+        assert!(limiter.hit("magic_link", "a").is_ok());
+        assert!(limiter.hit("magic_link", "a").is_ok());
+    }
+}
@@ -0,0 +1,521 @@
+//! Role based access control module.
+//!
+//! Module decides which administrative actions a principal
+//! may perform. Roles are granted either globally or within
+//! a single organization, organization scoped admins manage
+//! users, roles and API keys of their organization only.
+//! REST, SCIM and CLI paths all authorize through the same
+//! `Policy`, so the isolation rules are enforced consistently.
+
+// Imports from external crates
+use std::collections::{HashMap, HashSet};
+use std::{fmt, str::FromStr};
+use uuid::Uuid;
+
+// Local imports
+use super::AUDIT_TARGET;
+use crate::core::err::{AppError, ErrorKind};
+
+/// Role granting every permission.
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Role granting every permission within an organization.
+pub const ORG_ADMIN_ROLE: &str = "org_admin";
+
+/// ## Permission enum.
+///
+/// ## Variants
+/// - `ManageUsers`: `users.manage`, create, update, disable and delete users.
+/// - `ManageRoles`: `roles.manage`, define roles and grant them to users.
+/// - `ManageApiKeys`: `api_keys.manage`, issue and revoke API keys.
+/// - `ReadAuditLog`: `audit.read`, query the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    ManageUsers,
+    ManageRoles,
+    ManageApiKeys,
+    ReadAuditLog,
+}
+
+impl Permission {
+    /// Every permission.
+    pub const ALL: [Permission; 4] = [
+        Permission::ManageUsers,
+        Permission::ManageRoles,
+        Permission::ManageApiKeys,
+        Permission::ReadAuditLog,
+    ];
+
+    /// ## Returns the name of the permission in role definitions.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ManageUsers => "users.manage",
+            Permission::ManageRoles => "roles.manage",
+            Permission::ManageApiKeys => "api_keys.manage",
+            Permission::ReadAuditLog => "audit.read",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == value)
+            .ok_or_else(|| {
+                AppError::validation(format!("Unknown permission '{}'", value))
+                    .with_code("roles.unknown_permission")
+            })
+    }
+}
+
+/// ## Channel enum.
+///
+/// Channel the action was requested through,
+/// used in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Rest,
+    Scim,
+    Cli,
+}
+
+/// ## Role grant struct.
+///
+/// ## Fields
+/// + `role`: `String` - Name of the granted role.
+/// + `org_id`: `Option<Uuid>` - Organization the grant is
+///   limited to, `None` for global grants.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RoleGrant {
+    pub role: String,
+    pub org_id: Option<Uuid>,
+}
+
+impl RoleGrant {
+    /// ## Creates a global grant.
+    pub fn global(role: &str) -> Self {
+        RoleGrant {
+            role: role.to_string(),
+            org_id: None,
+        }
+    }
+
+    /// ## Creates a grant limited to an organization.
+    pub fn org(role: &str, org_id: Uuid) -> Self {
+        RoleGrant {
+            role: role.to_string(),
+            org_id: Some(org_id),
+        }
+    }
+}
+
+/// ## Principal struct.
+///
+/// ## Fields
+/// + `user_id`: `Uuid` - ID of the acting user.
+/// + `grants`: `Vec<RoleGrant>` - Roles granted to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principal {
+    pub user_id: Uuid,
+    pub grants: Vec<RoleGrant>,
+}
+
+/// ## Scope enum.
+///
+/// Set of organizations a permission applies to,
+/// used to filter list queries.
+///
+/// ## Variants
+/// - `All`: Every organization and global resources.
+/// - `Orgs`: Listed organizations only.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    All,
+    Orgs(HashSet<Uuid>),
+}
+
+impl Scope {
+    /// ## Checks if the scope contains the organization.
+    ///
+    /// Global resources (`None`) are contained
+    /// in the `All` scope only.
+    pub fn contains(&self, org_id: Option<Uuid>) -> bool {
+        match (self, org_id) {
+            (Scope::All, _) => true,
+            (Scope::Orgs(orgs), Some(org_id)) => orgs.contains(&org_id),
+            (Scope::Orgs(_), None) => false,
+        }
+    }
+}
+
+/// ## Policy struct.
+///
+/// Struct maps roles to their permissions.
+///
+/// ## Examples
+/// ```
+/// use uuid::Uuid;
+/// use axum_auth::core::auth::rbac::{
+///     Channel, Permission, Policy, Principal, RoleGrant, ORG_ADMIN_ROLE,
+/// };
+///
+/// let org_id = Uuid::new_v4();
+/// let principal = Principal {
+///     user_id: Uuid::new_v4(),
+///     grants: vec![RoleGrant::org(ORG_ADMIN_ROLE, org_id)],
+/// };
+///
+/// let policy = Policy::default();
+///
+/// assert!(policy
+///     .authorize(&principal, Permission::ManageUsers, Some(org_id), Channel::Rest)
+///     .is_ok());
+/// assert!(policy
+///     .authorize(&principal, Permission::ManageUsers, Some(Uuid::new_v4()), Channel::Rest)
+///     .is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    roles: HashMap<String, HashSet<Permission>>,
+}
+
+impl Default for Policy {
+    /// Policy with the built-in `admin` and `org_admin` roles.
+    fn default() -> Self {
+        let all: HashSet<Permission> = Permission::ALL.into_iter().collect();
+
+        Policy::new(HashMap::from([
+            (ADMIN_ROLE.to_string(), all.clone()),
+            (ORG_ADMIN_ROLE.to_string(), all),
+        ]))
+    }
+}
+
+impl Policy {
+    /// ## Creates a new `Policy` instance.
+    ///
+    /// ## Parameters
+    /// - `roles`: Permissions of every role.
+    pub fn new(roles: HashMap<String, HashSet<Permission>>) -> Self {
+        Policy { roles }
+    }
+
+    /// ## Adds a role defined at runtime.
+    ///
+    /// Roles already known to the policy, e.g. the
+    /// built-in ones, are kept as they are.
+    ///
+    /// ## Parameters
+    /// - `role`: `&str` - Name of the role.
+    /// - `permissions`: `impl IntoIterator<Item = Permission>` - Permissions
+    ///   of the role.
+    pub fn with_role(
+        mut self,
+        role: &str,
+        permissions: impl IntoIterator<Item = Permission>,
+    ) -> Self {
+        self.roles
+            .entry(role.to_string())
+            .or_insert_with(|| permissions.into_iter().collect());
+        self
+    }
+
+    /// ## Returns the scope of the permission.
+    ///
+    /// ## Parameters
+    /// - `principal`: `&Principal` - Acting principal.
+    /// - `permission`: `Permission` - Requested permission.
+    ///
+    /// ## Returns
+    /// - `Scope`: Organizations the principal holds the permission in.
+    pub fn scope(&self, principal: &Principal, permission: Permission) -> Scope {
+        let mut orgs = HashSet::new();
+
+        for grant in &principal.grants {
+            if !self.grants_permission(&grant.role, permission) {
+                continue;
+            }
+
+            match grant.org_id {
+                None => return Scope::All,
+                Some(org_id) => {
+                    orgs.insert(org_id);
+                }
+            }
+        }
+
+        Scope::Orgs(orgs)
+    }
+
+    /// ## Authorizes an action on a resource.
+    ///
+    /// ## Parameters
+    /// - `principal`: `&Principal` - Acting principal.
+    /// - `permission`: `Permission` - Requested permission.
+    /// - `target_org`: `Option<Uuid>` - Organization of the resource,
+    ///   `None` for global resources.
+    /// - `channel`: `Channel` - Channel of the request.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the action is allowed.
+    ///     - `Err(AppError)`: `Forbidden` if the action is not allowed.
+    pub fn authorize(
+        &self,
+        principal: &Principal,
+        permission: Permission,
+        target_org: Option<Uuid>,
+        channel: Channel,
+    ) -> Result<(), AppError> {
+        if self.scope(principal, permission).contains(target_org) {
+            return Ok(());
+        }
+
+        tracing::warn!(
+            target: AUDIT_TARGET,
+            event = "authz.denied",
+            user_id = %principal.user_id,
+            permission = ?permission,
+            target_org = ?target_org,
+            channel = ?channel,
+            "Administrative action denied"
+        );
+
+        Err(AppError::new(
+            ErrorKind::Forbidden,
+            format!("Not allowed to {:?} in this organization", permission),
+            None,
+        ))
+    }
+
+    /// ## Authorizes granting a role.
+    ///
+    /// Principal needs `ManageRoles` in the organization of
+    /// the grant, and may only hand out permissions it holds
+    /// in that organization itself, which keeps org admins
+    /// from escalating to global roles.
+    ///
+    /// ## Parameters
+    /// - `principal`: `&Principal` - Acting principal.
+    /// - `grant`: `&RoleGrant` - Grant to hand out.
+    /// - `channel`: `Channel` - Channel of the request.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the grant is allowed.
+    ///     - `Err(AppError)`: `Forbidden` if the grant is not allowed.
+    pub fn authorize_grant(
+        &self,
+        principal: &Principal,
+        grant: &RoleGrant,
+        channel: Channel,
+    ) -> Result<(), AppError> {
+        self.authorize(principal, Permission::ManageRoles, grant.org_id, channel)?;
+
+        let granted = self.roles.get(&grant.role).into_iter().flatten();
+
+        for permission in granted {
+            self.authorize(principal, *permission, grant.org_id, channel)?;
+        }
+
+        Ok(())
+    }
+
+    /// ## Checks if the role holds the permission.
+    fn grants_permission(&self, role: &str, permission: Permission) -> bool {
+        self.roles
+            .get(role)
+            .is_some_and(|permissions| permissions.contains(&permission))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANNELS: [Channel; 3] = [Channel::Rest, Channel::Scim, Channel::Cli];
+
+    // Test checks if every permission is parsed back from its name.
+    #[test]
+    fn test_permission_round_trip() {
+        for permission in Permission::ALL {
+            assert_eq!(permission.as_str().parse::<Permission>(), Ok(permission));
+        }
+
+        let result = "users.impersonate".parse::<Permission>().unwrap_err();
+
+        assert_eq!(result.code, "roles.unknown_permission");
+    }
+
+    fn principal(grants: Vec<RoleGrant>) -> Principal {
+        Principal {
+            user_id: Uuid::new_v4(),
+            grants,
+        }
+    }
+
+    // Test checks if org admins manage only their organization on every channel.
+    #[test]
+    fn test_org_admin_isolation() {
+        let policy = Policy::default();
+        let own_org = Uuid::new_v4();
+        let other_org = Uuid::new_v4();
+        let org_admin = principal(vec![RoleGrant::org(ORG_ADMIN_ROLE, own_org)]);
+
+        for channel in CHANNELS {
+            for permission in Permission::ALL {
+                assert_eq!(
+                    policy.authorize(&org_admin, permission, Some(own_org), channel),
+                    Ok(())
+                );
+
+                let other = policy
+                    .authorize(&org_admin, permission, Some(other_org), channel)
+                    .unwrap_err();
+                let global = policy
+                    .authorize(&org_admin, permission, None, channel)
+                    .unwrap_err();
+
+                assert_eq!(other.kind, ErrorKind::Forbidden);
+                assert_eq!(global.kind, ErrorKind::Forbidden);
+            }
+        }
+    }
+
+    // Test checks if global admins manage every organization.
+    #[test]
+    fn test_global_admin() {
+        let policy = Policy::default();
+        let admin = principal(vec![RoleGrant::global(ADMIN_ROLE)]);
+
+        for channel in CHANNELS {
+            assert!(policy
+                .authorize(
+                    &admin,
+                    Permission::ManageUsers,
+                    Some(Uuid::new_v4()),
+                    channel
+                )
+                .is_ok());
+            assert!(policy
+                .authorize(&admin, Permission::ManageRoles, None, channel)
+                .is_ok());
+        }
+    }
+
+    // Test checks if users without admin roles are denied.
+    #[test]
+    fn test_no_admin_role() {
+        let policy = Policy::default();
+        let org_id = Uuid::new_v4();
+        let user = principal(vec![RoleGrant::org("member", org_id)]);
+
+        let result = policy.authorize(&user, Permission::ManageUsers, Some(org_id), Channel::Rest);
+
+        assert_eq!(result.unwrap_err().kind, ErrorKind::Forbidden);
+    }
+
+    // Test checks if the scope lists the organizations of the org admin.
+    #[test]
+    fn test_scope() {
+        let policy = Policy::default();
+        let (org_a, org_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let org_admin = principal(vec![
+            RoleGrant::org(ORG_ADMIN_ROLE, org_a),
+            RoleGrant::org(ORG_ADMIN_ROLE, org_b),
+            RoleGrant::org("member", Uuid::new_v4()),
+        ]);
+        let admin = principal(vec![RoleGrant::global(ADMIN_ROLE)]);
+
+        assert_eq!(
+            policy.scope(&org_admin, Permission::ManageApiKeys),
+            Scope::Orgs(HashSet::from([org_a, org_b]))
+        );
+        assert_eq!(policy.scope(&admin, Permission::ManageApiKeys), Scope::All);
+    }
+
+    // Test checks if org admins can not grant roles outside of their organization.
+    #[test]
+    fn test_authorize_grant_escalation() {
+        let policy = Policy::default();
+        let own_org = Uuid::new_v4();
+        let org_admin = principal(vec![RoleGrant::org(ORG_ADMIN_ROLE, own_org)]);
+
+        let own = RoleGrant::org(ORG_ADMIN_ROLE, own_org);
+        let other = RoleGrant::org(ORG_ADMIN_ROLE, Uuid::new_v4());
+        let global = RoleGrant::global(ADMIN_ROLE);
+
+        for channel in CHANNELS {
+            assert!(policy.authorize_grant(&org_admin, &own, channel).is_ok());
+            assert!(policy.authorize_grant(&org_admin, &other, channel).is_err());
+            assert!(policy
+                .authorize_grant(&org_admin, &global, channel)
+                .is_err());
+        }
+    }
+
+    // Test checks if a role can not be granted with permissions the granter lacks.
+    #[test]
+    fn test_authorize_grant_more_permissions() {
+        let org_id = Uuid::new_v4();
+        let policy = Policy::new(HashMap::from([
+            (
+                "role_manager".to_string(),
+                HashSet::from([Permission::ManageRoles]),
+            ),
+            (
+                ORG_ADMIN_ROLE.to_string(),
+                Permission::ALL.into_iter().collect(),
+            ),
+        ]));
+        let manager = principal(vec![RoleGrant::org("role_manager", org_id)]);
+
+        let result = policy.authorize_grant(
+            &manager,
+            &RoleGrant::org(ORG_ADMIN_ROLE, org_id),
+            Channel::Cli,
+        );
+
+        assert_eq!(result.unwrap_err().kind, ErrorKind::Forbidden);
+    }
+
+    // Test checks if defined roles are authorized by their permissions and built-in roles are kept.
+    #[test]
+    fn test_with_role() {
+        let org_id = Uuid::new_v4();
+        let policy = Policy::default()
+            .with_role("auditor", [Permission::ReadAuditLog])
+            .with_role(ADMIN_ROLE, []);
+        let auditor = principal(vec![RoleGrant::org("auditor", org_id)]);
+        let admin = principal(vec![RoleGrant::global(ADMIN_ROLE)]);
+
+        assert!(policy
+            .authorize(
+                &auditor,
+                Permission::ReadAuditLog,
+                Some(org_id),
+                Channel::Rest
+            )
+            .is_ok());
+        assert!(policy
+            .authorize(
+                &auditor,
+                Permission::ManageUsers,
+                Some(org_id),
+                Channel::Rest
+            )
+            .is_err());
+        assert!(policy
+            .authorize(&admin, Permission::ManageUsers, None, Channel::Rest)
+            .is_ok());
+    }
+}
@@ -0,0 +1,396 @@
+//! IP reputation module.
+//!
+//! Module defines the `ReputationProvider` trait that is
+//! consulted on login and registration, and the
+//! `ReputationGuard` that caches provider scores and turns
+//! them into verdicts based on the configured thresholds.
+
+// Imports from external crates
+use async_trait::async_trait;
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Local imports
+use crate::core::config::ReputationSettings;
+use crate::core::err::{AppError, ErrorKind};
+
+/// Highest possible reputation score.
+pub const MAX_SCORE: u8 = 100;
+
+/// ## Reputation provider trait.
+///
+/// Trait is implemented by the sources of IP reputation
+/// data, e.g. AbuseIPDB or internal block lists.
+///
+/// ## Examples
+/// ```
+/// use std::net::IpAddr;
+/// use async_trait::async_trait;
+/// use axum_auth::core::auth::reputation::ReputationProvider;
+/// use axum_auth::core::err::AppError;
+///
+/// struct Clean;
+///
+/// #[async_trait]
+/// impl ReputationProvider for Clean {
+///     fn name(&self) -> &str {
+///         "clean"
+///     }
+///
+///     async fn score(&self, _ip: IpAddr) -> Result<u8, AppError> {
+///         Ok(0)
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait ReputationProvider: Send + Sync {
+    /// Name of the provider, used in error messages.
+    fn name(&self) -> &str;
+
+    /// Returns the abuse score of the address, from
+    /// 0 (clean) to 100 (known abuser).
+    async fn score(&self, ip: IpAddr) -> Result<u8, AppError>;
+}
+
+/// ## Reputation verdict enum.
+///
+/// ## Variants
+/// - `Allow`: Request is allowed.
+/// - `Annotate`: Request is allowed, audit events are annotated with the score.
+/// - `RequireCaptcha`: Request is allowed only after a solved captcha.
+/// - `Block`: Request is rejected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReputationVerdict {
+    Allow,
+    Annotate(u8),
+    RequireCaptcha(u8),
+    Block(u8),
+}
+
+impl ReputationVerdict {
+    /// ## Returns the score of the verdict, `None` for `Allow`.
+    pub fn score(&self) -> Option<u8> {
+        match self {
+            ReputationVerdict::Allow => None,
+            ReputationVerdict::Annotate(score)
+            | ReputationVerdict::RequireCaptcha(score)
+            | ReputationVerdict::Block(score) => Some(*score),
+        }
+    }
+}
+
+/// ## Provider backed by static lists.
+///
+/// Addresses on the block list score `MAX_SCORE`,
+/// every other address scores 0.
+///
+/// ## Examples
+/// ```
+/// use std::collections::HashSet;
+/// use axum_auth::core::auth::reputation::StaticListProvider;
+///
+/// let blocked = HashSet::from(["10.0.0.1".parse().unwrap()]);
+/// let provider = StaticListProvider::new(blocked);
+/// ```
+pub struct StaticListProvider {
+    blocked: HashSet<IpAddr>,
+}
+
+impl StaticListProvider {
+    /// ## Creates a new `StaticListProvider` instance.
+    ///
+    /// ## Parameters
+    /// - `blocked`: Addresses to block.
+    pub fn new(blocked: HashSet<IpAddr>) -> Self {
+        StaticListProvider { blocked }
+    }
+}
+
+#[async_trait]
+impl ReputationProvider for StaticListProvider {
+    fn name(&self) -> &str {
+        "static-list"
+    }
+
+    async fn score(&self, ip: IpAddr) -> Result<u8, AppError> {
+        match self.blocked.contains(&ip) {
+            true => Ok(MAX_SCORE),
+            false => Ok(0),
+        }
+    }
+}
+
+/// ## Reputation guard struct.
+///
+/// Struct consults the provider, caches the scores
+/// for the configured TTL and maps them to verdicts.
+pub struct ReputationGuard {
+    provider: Arc<dyn ReputationProvider>,
+    settings: ReputationSettings,
+    cache: Mutex<HashMap<IpAddr, (u8, Instant)>>,
+}
+
+impl ReputationGuard {
+    /// ## Creates a new `ReputationGuard` instance.
+    ///
+    /// ## Parameters
+    /// - `provider`: Source of the reputation scores.
+    /// - `settings`: Thresholds, cache TTL and failure mode.
+    ///
+    /// ## Returns
+    /// - New `ReputationGuard` instance.
+    pub fn new(provider: Arc<dyn ReputationProvider>, settings: ReputationSettings) -> Self {
+        ReputationGuard {
+            provider,
+            settings,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// ## Creates the guard of the settings.
+    ///
+    /// Scores are read from the `StaticListProvider` of
+    /// the `blocked` addresses.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&ReputationSettings` - Thresholds and blocked addresses.
+    ///
+    /// ## Returns
+    /// + `Result<ReputationGuard, AppError>`
+    ///     - `Ok(ReputationGuard)`: New guard.
+    ///     - `Err(AppError)`: If a blocked address is invalid.
+    pub fn from_settings(settings: &ReputationSettings) -> Result<Self, AppError> {
+        let provider = StaticListProvider::new(settings.blocked_addresses()?);
+
+        Ok(ReputationGuard::new(Arc::new(provider), settings.clone()))
+    }
+
+    /// ## Checks the reputation of the address.
+    ///
+    /// Function returns `Allow` without consulting the
+    /// provider when checks are disabled. When the provider
+    /// fails, the request is allowed if the guard fails open,
+    /// otherwise the provider error is returned.
+    ///
+    /// ## Parameters
+    /// - `ip`: `IpAddr` - Address of the client.
+    ///
+    /// ## Returns
+    /// + `Result<ReputationVerdict, AppError>`
+    ///     - `Ok(ReputationVerdict)`: Verdict for the address.
+    ///     - `Err(AppError)`: If the provider failed and the guard fails closed.
+    pub async fn check(&self, ip: IpAddr) -> Result<ReputationVerdict, AppError> {
+        if !self.settings.enabled {
+            return Ok(ReputationVerdict::Allow);
+        }
+
+        if let Some(score) = self.cached(ip) {
+            return Ok(self.verdict(score));
+        }
+
+        match self.provider.score(ip).await {
+            Ok(score) => {
+                self.cache_score(ip, score);
+                Ok(self.verdict(score))
+            }
+            Err(e) if self.settings.fail_open => {
+                tracing::warn!(
+                    provider = self.provider.name(),
+                    error = %e,
+                    "Reputation provider failed, allowing request"
+                );
+                Ok(ReputationVerdict::Allow)
+            }
+            Err(e) => Err(AppError::new(
+                ErrorKind::External,
+                format!("Reputation provider '{}' failed", self.provider.name()),
+                Some(Box::new(e)),
+            )),
+        }
+    }
+
+    /// ## Maps the score to a verdict.
+    fn verdict(&self, score: u8) -> ReputationVerdict {
+        if score >= self.settings.block_threshold {
+            ReputationVerdict::Block(score)
+        } else if score >= self.settings.captcha_threshold {
+            ReputationVerdict::RequireCaptcha(score)
+        } else if score >= self.settings.annotate_threshold {
+            ReputationVerdict::Annotate(score)
+        } else {
+            ReputationVerdict::Allow
+        }
+    }
+
+    /// ## Returns the cached score if it has not expired.
+    fn cached(&self, ip: IpAddr) -> Option<u8> {
+        let ttl = Duration::from_secs(self.settings.cache_ttl);
+        let mut cache = self.cache.lock().expect("Reputation cache lock poisoned");
+
+        match cache.get(&ip) {
+            Some((score, cached_at)) if cached_at.elapsed() < ttl => Some(*score),
+            Some(_) => {
+                cache.remove(&ip);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// ## Stores the score in the cache.
+    fn cache_score(&self, ip: IpAddr, score: u8) {
+        self.cache
+            .lock()
+            .expect("Reputation cache lock poisoned")
+            .insert(ip, (score, Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Provider returning a fixed score and counting the calls.
+    struct FixedProvider {
+        score: Option<u8>,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ReputationProvider for FixedProvider {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        async fn score(&self, _ip: IpAddr) -> Result<u8, AppError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            self.score.ok_or(AppError::new(
+                ErrorKind::External,
+                "Provider unavailable".to_string(),
+                None,
+            ))
+        }
+    }
+
+    fn provider(score: Option<u8>) -> Arc<FixedProvider> {
+        Arc::new(FixedProvider {
+            score,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    fn enabled() -> ReputationSettings {
+        ReputationSettings {
+            enabled: true,
+            ..ReputationSettings::default()
+        }
+    }
+
+    fn ip() -> IpAddr {
+        "192.0.2.1".parse().unwrap()
+    }
+
+    // Test checks if scores are mapped to verdicts based on the thresholds.
+    #[tokio::test]
+    async fn test_check_thresholds() {
+        let cases = [
+            (0, ReputationVerdict::Allow),
+            (25, ReputationVerdict::Annotate(25)),
+            (50, ReputationVerdict::RequireCaptcha(50)),
+            (90, ReputationVerdict::Block(90)),
+        ];
+
+        for (score, expected) in cases {
+            let guard = ReputationGuard::new(provider(Some(score)), enabled());
+
+            assert_eq!(guard.check(ip()).await, Ok(expected));
+        }
+    }
+
+    // Test checks if the provider is not consulted when checks are disabled.
+    #[tokio::test]
+    async fn test_check_disabled() {
+        let provider = provider(Some(MAX_SCORE));
+        let guard = ReputationGuard::new(provider.clone(), ReputationSettings::default());
+
+        assert_eq!(guard.check(ip()).await, Ok(ReputationVerdict::Allow));
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 0);
+    }
+
+    // Test checks if scores are cached until the TTL expires.
+    #[tokio::test]
+    async fn test_check_cache() {
+        let provider = provider(Some(10));
+        let guard = ReputationGuard::new(provider.clone(), enabled());
+
+        guard.check(ip()).await.unwrap();
+        guard.check(ip()).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+
+        let settings = ReputationSettings {
+            cache_ttl: 0,
+            ..enabled()
+        };
+        let guard = ReputationGuard::new(provider.clone(), settings);
+
+        guard.check(ip()).await.unwrap();
+        guard.check(ip()).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+    }
+
+    // Test checks if the request is allowed when the provider fails.
+    #[tokio::test]
+    async fn test_check_fail_open() {
+        let guard = ReputationGuard::new(provider(None), enabled());
+
+        assert_eq!(guard.check(ip()).await, Ok(ReputationVerdict::Allow));
+    }
+
+    // Test checks if the provider error is returned when failing closed.
+    #[tokio::test]
+    async fn test_check_fail_closed() {
+        let settings = ReputationSettings {
+            fail_open: false,
+            ..enabled()
+        };
+        let guard = ReputationGuard::new(provider(None), settings);
+
+        let result = guard.check(ip()).await.unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::External);
+    }
+
+    // Test checks if the static list provider blocks listed addresses only.
+    #[tokio::test]
+    async fn test_static_list_provider() {
+        let provider = StaticListProvider::new(HashSet::from([ip()]));
+        let other: IpAddr = "192.0.2.2".parse().unwrap();
+
+        assert_eq!(provider.score(ip()).await, Ok(MAX_SCORE));
+        assert_eq!(provider.score(other).await, Ok(0));
+    }
+
+    // Test checks if the guard of the settings blocks the listed addresses.
+    #[tokio::test]
+    async fn test_from_settings() {
+        let settings = ReputationSettings {
+            blocked: vec![ip().to_string()],
+            ..enabled()
+        };
+        let guard = ReputationGuard::from_settings(&settings).unwrap();
+        let other: IpAddr = "192.0.2.2".parse().unwrap();
+
+        assert_eq!(
+            guard.check(ip()).await,
+            Ok(ReputationVerdict::Block(MAX_SCORE))
+        );
+        assert_eq!(guard.check(other).await, Ok(ReputationVerdict::Allow));
+        assert_eq!(ReputationVerdict::Block(MAX_SCORE).score(), Some(MAX_SCORE));
+    }
+}
@@ -0,0 +1,352 @@
+//! Login throttle module.
+//!
+//! Failed logins are counted per client address, kept in
+//! memory per instance like the rate limiter. Clients that
+//! keep failing are answered later and later, and from
+//! `captcha_after` failures on have to solve a CAPTCHA
+//! checked by the `CaptchaVerifier`. Delays and the window
+//! may be replaced while the throttle is in use, e.g. when
+//! the configuration file is reloaded.
+
+// Imports from external crates
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard},
+    time::{Duration, Instant},
+};
+
+// Local imports
+use super::captcha::{CaptchaVerifier, SiteVerify};
+use crate::core::config::LoginThrottleSettings;
+use crate::core::err::{AppError, ErrorKind};
+
+/// Number of clients from which forgotten ones are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// ## Failures of a client struct.
+#[derive(Debug, Clone, Copy)]
+struct Failures {
+    count: u32,
+    last: Instant,
+}
+
+/// ## Login throttle struct.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::throttle::LoginThrottle;
+/// use axum_auth::core::config::LoginThrottleSettings;
+/// use std::time::Duration;
+///
+/// let throttle = LoginThrottle::new(&LoginThrottleSettings {
+///     enabled: true,
+///     free_failures: 1,
+///     ..LoginThrottleSettings::default()
+/// });
+/// let ip = "192.0.2.1".parse().unwrap();
+///
+/// throttle.failed(ip);
+/// throttle.failed(ip);
+///
+/// assert_eq!(throttle.delay(ip), Duration::from_secs(1));
+/// ```
+pub struct LoginThrottle {
+    settings: RwLock<LoginThrottleSettings>,
+    captcha: Option<Arc<dyn CaptchaVerifier>>,
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl LoginThrottle {
+    /// ## Creates the throttle of the settings.
+    ///
+    /// CAPTCHA is verified by the configured provider
+    /// when `captcha_secret` is set.
+    pub fn new(settings: &LoginThrottleSettings) -> Self {
+        let captcha = (!settings.captcha_secret.is_empty()).then(|| {
+            let verifier =
+                SiteVerify::new(settings.captcha_provider, settings.captcha_secret.clone());
+            Arc::new(verifier) as Arc<dyn CaptchaVerifier>
+        });
+
+        LoginThrottle {
+            settings: RwLock::new(settings.clone()),
+            captcha,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// ## Replaces the CAPTCHA verifier.
+    pub fn with_captcha(mut self, captcha: Arc<dyn CaptchaVerifier>) -> Self {
+        self.captcha = Some(captcha);
+        self
+    }
+
+    /// ## Applies changed settings.
+    ///
+    /// Free failures, delays and the window are replaced,
+    /// the failures counted so far are kept. Whether the
+    /// throttle is enabled and the CAPTCHA settings require
+    /// a restart, as the verifier is built once.
+    pub fn apply(&self, changed: &LoginThrottleSettings) {
+        let mut settings = self.settings.write().unwrap_or_else(|e| e.into_inner());
+        settings.free_failures = changed.free_failures;
+        settings.base_delay = changed.base_delay;
+        settings.max_delay = changed.max_delay;
+        settings.window = changed.window;
+    }
+
+    /// ## Returns the settings in use (private).
+    fn settings(&self) -> RwLockReadGuard<'_, LoginThrottleSettings> {
+        self.settings.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// ## Returns the failures of the client that are not forgotten.
+    fn failures(&self, ip: IpAddr) -> u32 {
+        let window = Duration::from_secs(self.settings().window);
+        let failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+
+        failures
+            .get(&ip)
+            .filter(|failures| failures.last.elapsed() < window)
+            .map_or(0, |failures| failures.count)
+    }
+
+    /// ## Returns the delay of the next login of the client.
+    ///
+    /// ## Parameters
+    /// - `ip`: `IpAddr` - Address of the client.
+    ///
+    /// ## Returns
+    /// - `Duration`: Zero up to `free_failures`, then `base_delay`
+    ///   doubled with every failure, at most `max_delay`.
+    pub fn delay(&self, ip: IpAddr) -> Duration {
+        let settings = self.settings().clone();
+        if !settings.enabled {
+            return Duration::ZERO;
+        }
+
+        match self.failures(ip).checked_sub(settings.free_failures + 1) {
+            Some(doublings) => {
+                let delay = settings
+                    .base_delay
+                    .saturating_mul(1u64 << doublings.min(32));
+                Duration::from_secs(delay.min(settings.max_delay))
+            }
+            None => Duration::ZERO,
+        }
+    }
+
+    /// ## Checks if the next login of the client needs a CAPTCHA.
+    pub fn requires_captcha(&self, ip: IpAddr) -> bool {
+        let (enabled, captcha_after) = {
+            let settings = self.settings();
+            (settings.enabled, settings.captcha_after)
+        };
+
+        enabled && captcha_after > 0 && self.failures(ip) >= captcha_after
+    }
+
+    /// ## Throttles a login of the client.
+    ///
+    /// Function waits for the delay of the client, then
+    /// verifies the CAPTCHA if the failures of the client
+    /// or the caller require one, e.g. for addresses of
+    /// poor reputation.
+    ///
+    /// ## Parameters
+    /// - `ip`: `IpAddr` - Address of the client.
+    /// - `captcha_token`: `Option<&str>` - Token of the solved CAPTCHA.
+    /// - `required`: `bool` - Require a CAPTCHA regardless of the failures.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the login may continue.
+    ///     - `Err(AppError)`: `captcha.required` or `captcha.invalid` if a
+    ///       CAPTCHA is required, or the error of the CAPTCHA service.
+    pub async fn check(
+        &self,
+        ip: IpAddr,
+        captcha_token: Option<&str>,
+        required: bool,
+    ) -> Result<(), AppError> {
+        let delay = self.delay(ip);
+        if !delay.is_zero() {
+            tracing::debug!(%ip, ?delay, "Delaying login of a failing client");
+            tokio::time::sleep(delay).await;
+        }
+
+        let required = required || self.requires_captcha(ip);
+        let Some(captcha) = self.captcha.as_ref().filter(|_| required) else {
+            return Ok(());
+        };
+        let Some(token) = captcha_token.filter(|token| !token.is_empty()) else {
+            return Err(AppError::new(
+                ErrorKind::Forbidden,
+                "Solve the CAPTCHA to continue".to_string(),
+                None,
+            )
+            .with_code("captcha.required"));
+        };
+
+        match captcha.verify(token, Some(ip)).await? {
+            true => Ok(()),
+            false => Err(AppError::new(
+                ErrorKind::Forbidden,
+                "CAPTCHA was not solved".to_string(),
+                None,
+            )
+            .with_code("captcha.invalid")),
+        }
+    }
+
+    /// ## Counts a failed login of the client.
+    pub fn failed(&self, ip: IpAddr) {
+        let (enabled, window) = {
+            let settings = self.settings();
+            (settings.enabled, Duration::from_secs(settings.window))
+        };
+        if !enabled {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        if failures.len() >= PRUNE_THRESHOLD {
+            failures.retain(|_, failures| now.duration_since(failures.last) < window);
+        }
+
+        let entry = failures.entry(ip).or_insert(Failures {
+            count: 0,
+            last: now,
+        });
+        if now.duration_since(entry.last) >= window {
+            entry.count = 0;
+        }
+        entry.count = entry.count.saturating_add(1);
+        entry.last = now;
+    }
+
+    /// ## Forgets the failures of the client after a login.
+    pub fn succeeded(&self, ip: IpAddr) {
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        failures.remove(&ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct Solved;
+
+    #[async_trait]
+    impl CaptchaVerifier for Solved {
+        fn name(&self) -> &str {
+            "solved"
+        }
+
+        async fn verify(&self, token: &str, _ip: Option<IpAddr>) -> Result<bool, AppError> {
+            Ok(token == "solved")
+        }
+    }
+
+    fn throttle(free_failures: u32, captcha_after: u32) -> LoginThrottle {
+        LoginThrottle::new(&LoginThrottleSettings {
+            enabled: true,
+            free_failures,
+            base_delay: 1,
+            max_delay: 5,
+            captcha_after,
+            ..LoginThrottleSettings::default()
+        })
+        .with_captcha(Arc::new(Solved))
+    }
+
+    // Test checks if delays double per failure up to the maximum and reset on success.
+    #[test]
+    fn test_delay() {
+        let throttle = throttle(2, 0);
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let other: IpAddr = "192.0.2.2".parse().unwrap();
+        let mut delays = Vec::new();
+
+        for _ in 0..7 {
+            throttle.failed(ip);
+            delays.push(throttle.delay(ip).as_secs());
+        }
+
+        assert_eq!(delays, [0, 0, 1, 2, 4, 5, 5]);
+        assert_eq!(throttle.delay(other), Duration::ZERO);
+        assert!(!throttle.requires_captcha(ip));
+
+        throttle.succeeded(ip);
+
+        assert_eq!(throttle.delay(ip), Duration::ZERO);
+    }
+
+    // Test checks if a CAPTCHA is required after the threshold and verified.
+    #[tokio::test]
+    async fn test_check_captcha() {
+        // Failures stay below the delays, so checks answer at once
+        let throttle = throttle(10, 3);
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+
+        throttle.failed(ip);
+        throttle.failed(ip);
+
+        assert_eq!(throttle.check(ip, None, false).await, Ok(()));
+
+        throttle.failed(ip);
+
+        assert_eq!(
+            throttle.check(ip, None, false).await.unwrap_err().code,
+            "captcha.required"
+        );
+        assert_eq!(
+            throttle
+                .check(ip, Some("guessed"), false)
+                .await
+                .unwrap_err()
+                .code,
+            "captcha.invalid"
+        );
+        assert_eq!(throttle.check(ip, Some("solved"), false).await, Ok(()));
+    }
+
+    // Test checks if callers require a CAPTCHA of clients without failures.
+    #[tokio::test]
+    async fn test_check_captcha_required() {
+        let throttle = throttle(10, 0);
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+
+        assert_eq!(throttle.check(ip, None, false).await, Ok(()));
+        assert_eq!(
+            throttle.check(ip, None, true).await.unwrap_err().code,
+            "captcha.required"
+        );
+        assert_eq!(throttle.check(ip, Some("solved"), true).await, Ok(()));
+    }
+
+    // Test checks if applied delays take effect for the failures counted so far.
+    #[test]
+    fn test_apply() {
+        let throttle = throttle(2, 0);
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+
+        throttle.failed(ip);
+        assert_eq!(throttle.delay(ip), Duration::ZERO);
+
+        throttle.apply(&LoginThrottleSettings {
+            free_failures: 0,
+            base_delay: 3,
+            max_delay: 10,
+            ..LoginThrottleSettings::default()
+        });
+
+        assert_eq!(throttle.delay(ip), Duration::from_secs(3));
+        throttle.failed(ip);
+        assert_eq!(throttle.delay(ip), Duration::from_secs(6));
+    }
+}
@@ -0,0 +1,520 @@
+//! Startup module.
+//!
+//! Every run mode starts with the phases it needs, in the
+//! order config → logging → db → cache → migrations →
+//! server. Each phase is timed and logged. A failing phase
+//! stops the startup with an error of a kind expected from
+//! the phase, unexpected kinds are wrapped, so a failed
+//! startup is attributed to the right dependency.
+//!
+//! The subscriber is installed before the first phase, so
+//! warnings of the config phase are not lost, the logging
+//! phase applies the configured level.
+
+// Imports from external crates
+use sqlx::PgPool;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Local imports
+use super::config::lint;
+use super::config::summary::StartupSummary;
+use super::config::{read_config, AppConfig, StartupPhase};
+use super::db::DbExecutor;
+use super::env::map::EnvMap;
+use super::env::vars::{EnvVar, RequiredEnvVar};
+use super::err::{AppError, ErrorKind};
+use super::logging::LogHandle;
+
+/// Delay between the checks of the `cache` phase.
+#[cfg(feature = "redis")]
+const CACHE_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// ## Outcome of a startup phase.
+///
+/// # Variants
+/// - `Done` - Phase finished.
+/// - `Skipped` - Phase did not run, with the reason.
+/// - `Failed` - Phase failed with an error of the kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Done,
+    Skipped(&'static str),
+    Failed(ErrorKind),
+}
+
+/// ## Report of a startup phase struct.
+///
+/// ## Fields
+/// + `phase`: `StartupPhase` - Phase the report is about.
+/// + `outcome`: `Outcome` - How the phase ended.
+/// + `duration`: `Duration` - Time the phase took, zero when skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseReport {
+    pub phase: StartupPhase,
+    pub outcome: Outcome,
+    pub duration: Duration,
+}
+
+/// ## Startup struct.
+///
+/// Records a report per phase. Run modes call the phase
+/// methods in order, `step` runs phases without a method
+/// of their own.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::bootstrap::Bootstrap;
+///
+/// # async fn example() -> Result<(), axum_auth::AppError> {
+/// let log = axum_auth::core::logging::init()?;
+/// let mut boot = Bootstrap::new();
+/// let (config, env) = boot.config("./config.toml", None).await?;
+/// boot.logging(&log, &config, &env).await?;
+/// let pool = boot.pool(&config, &env).await?;
+/// boot.migrations(&config, &pool).await?;
+/// boot.finish();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Bootstrap {
+    started: Instant,
+    reports: Vec<PhaseReport>,
+}
+
+impl Default for Bootstrap {
+    fn default() -> Self {
+        Bootstrap::new()
+    }
+}
+
+impl Bootstrap {
+    /// ## Creates a new `Bootstrap` instance, the startup is timed from here.
+    pub fn new() -> Self {
+        Bootstrap {
+            started: Instant::now(),
+            reports: Vec::new(),
+        }
+    }
+
+    /// ## Returns the reports of the phases so far.
+    pub fn reports(&self) -> &[PhaseReport] {
+        &self.reports
+    }
+
+    /// ## Runs a phase and records its report.
+    ///
+    /// ## Parameters
+    /// - `phase`: `StartupPhase` - Phase the step belongs to.
+    /// - `step`: `F` - Work of the phase.
+    ///
+    /// ## Returns
+    /// + `Result<T, AppError>`
+    ///     - `Ok(T)`: Output of the step.
+    ///     - `Err(AppError)`: Error of the step, wrapped in the
+    ///       kind of the phase unless its kind is expected.
+    pub async fn step<T, F>(&mut self, phase: StartupPhase, step: F) -> Result<T, AppError>
+    where
+        F: Future<Output = Result<T, AppError>>,
+    {
+        let started = Instant::now();
+        let result = step.await;
+        let duration = started.elapsed();
+
+        match result {
+            Ok(output) => {
+                tracing::info!(
+                    phase = phase.as_str(),
+                    duration_ms = duration.as_millis() as u64,
+                    "Startup phase finished"
+                );
+                self.record(phase, Outcome::Done, duration);
+
+                Ok(output)
+            }
+            Err(e) => {
+                let e = attribute(phase, e);
+                tracing::error!(
+                    phase = phase.as_str(),
+                    duration_ms = duration.as_millis() as u64,
+                    kind = ?e.kind,
+                    code = e.code,
+                    "Startup phase failed: {}",
+                    e.message
+                );
+                self.record(phase, Outcome::Failed(e.kind), duration);
+
+                Err(e)
+            }
+        }
+    }
+
+    /// ## Records a phase that does not run.
+    pub fn skip(&mut self, phase: StartupPhase, reason: &'static str) {
+        tracing::info!(phase = phase.as_str(), reason, "Startup phase skipped");
+        self.record(phase, Outcome::Skipped(reason), Duration::ZERO);
+    }
+
+    /// ## Logs the duration of the whole startup.
+    pub fn finish(&self) {
+        let phases: Vec<String> = self
+            .reports
+            .iter()
+            .map(|report| match report.outcome {
+                Outcome::Skipped(_) => format!("{} skipped", report.phase.as_str()),
+                _ => format!(
+                    "{} {}ms",
+                    report.phase.as_str(),
+                    report.duration.as_millis()
+                ),
+            })
+            .collect();
+
+        tracing::info!(
+            duration_ms = self.started.elapsed().as_millis() as u64,
+            "Startup finished: {}",
+            phases.join(", ")
+        );
+    }
+
+    /// ## Runs the `config` phase.
+    ///
+    /// Phase reads the configuration file and the environment,
+    /// checks the constraints between them and prints the
+    /// security lint report.
+    ///
+    /// ## Parameters
+    /// - `config_file`: `&str` - Path of the configuration file.
+    /// - `profile`: `Option<&str>` - Profile selected on the command line.
+    ///
+    /// ## Returns
+    /// + `Result<(Arc<AppConfig>, EnvMap), AppError>`
+    ///     - `Ok((Arc<AppConfig>, EnvMap))`: Configuration and environment.
+    ///     - `Err(AppError)`: If either is invalid or the lint blocks the start.
+    pub async fn config(
+        &mut self,
+        config_file: &str,
+        profile: Option<&str>,
+    ) -> Result<(Arc<AppConfig>, EnvMap), AppError> {
+        self.step(StartupPhase::Config, async {
+            let config = read_config(config_file, profile).map(Arc::new)?;
+
+            // Load environment variables from files
+            let env = super::env::load(
+                &config.app.env_files(),
+                &config.app.prefix,
+                RequiredEnvVar::all(),
+                config.app.unknown_env_vars,
+            )?;
+
+            // Check the constraints between the configuration and the environment
+            super::config::constraints::check(&config, &env)?;
+
+            // Report dangerous deployment combinations
+            let report = lint::check(&config, |var| env.get(&var).ok().map(str::to_string))?;
+            match report.findings.is_empty() {
+                true => tracing::info!(score = report.score(), "{}", report),
+                false => tracing::warn!(score = report.score(), "{}", report),
+            }
+
+            Ok((config, env))
+        })
+        .await
+    }
+
+    /// ## Runs the `logging` phase.
+    ///
+    /// Phase applies the configured log level and logs the
+    /// startup summary, secrets masked.
+    pub async fn logging(
+        &mut self,
+        log: &LogHandle,
+        config: &AppConfig,
+        env: &EnvMap,
+    ) -> Result<(), AppError> {
+        self.step(StartupPhase::Logging, async {
+            log.apply(&config.logging)?;
+
+            if config.logging.startup_summary {
+                let summary = StartupSummary::new(config, env)?;
+                tracing::info!("{}", summary);
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// ## Runs the `db` phase with the primary and the read replicas.
+    pub async fn db(&mut self, config: &AppConfig, env: &EnvMap) -> Result<DbExecutor, AppError> {
+        self.step(StartupPhase::Db, DbExecutor::connect(env, &config.database))
+            .await
+    }
+
+    /// ## Runs the `db` phase with the primary only.
+    pub async fn pool(&mut self, config: &AppConfig, env: &EnvMap) -> Result<PgPool, AppError> {
+        self.step(StartupPhase::Db, super::db::connect(env, &config.database))
+            .await
+    }
+
+    /// ## Runs the `cache` phase.
+    ///
+    /// Phase is a readiness gate, it waits until the critical
+    /// Redis health checks are up, at most `cache_timeout`
+    /// seconds. Phase is skipped without such checks, without
+    /// the `redis` feature or when `bootstrap.skip` lists it.
+    pub async fn cache(&mut self, config: &AppConfig) -> Result<(), AppError> {
+        if config.bootstrap.skips(StartupPhase::Cache) {
+            self.skip(StartupPhase::Cache, "listed in bootstrap.skip");
+            return Ok(());
+        }
+
+        #[cfg(feature = "redis")]
+        {
+            let health = super::config::HealthSettings {
+                checks: config
+                    .health
+                    .checks
+                    .iter()
+                    .filter(|check| {
+                        check.critical && check.kind == super::config::HealthCheckKind::Redis
+                    })
+                    .cloned()
+                    .collect(),
+                ..config.health.clone()
+            };
+            if health.checks.is_empty() {
+                self.skip(StartupPhase::Cache, "no critical Redis health checks");
+                return Ok(());
+            }
+
+            let timeout = Duration::from_secs(config.bootstrap.cache_timeout);
+            self.step(StartupPhase::Cache, wait_for_cache(&health, timeout))
+                .await
+        }
+
+        #[cfg(not(feature = "redis"))]
+        {
+            self.skip(StartupPhase::Cache, "needs the `redis` feature");
+            Ok(())
+        }
+    }
+
+    /// ## Runs the `migrations` phase, unless `bootstrap.skip` lists it.
+    pub async fn migrations(&mut self, config: &AppConfig, pool: &PgPool) -> Result<(), AppError> {
+        if config.bootstrap.skips(StartupPhase::Migrations) {
+            self.skip(StartupPhase::Migrations, "listed in bootstrap.skip");
+            return Ok(());
+        }
+
+        self.step(StartupPhase::Migrations, super::db::migrate(pool))
+            .await
+    }
+
+    /// ## Appends the report of a phase.
+    fn record(&mut self, phase: StartupPhase, outcome: Outcome, duration: Duration) {
+        self.reports.push(PhaseReport {
+            phase,
+            outcome,
+            duration,
+        });
+    }
+}
+
+/// ## Returns the error kinds a phase may fail with, the first is its own.
+pub fn expected_kinds(phase: StartupPhase) -> &'static [ErrorKind] {
+    match phase {
+        StartupPhase::Config | StartupPhase::Logging => &[ErrorKind::Config, ErrorKind::Env],
+        StartupPhase::Db => &[ErrorKind::Db, ErrorKind::Env],
+        StartupPhase::Cache => &[ErrorKind::External],
+        StartupPhase::Migrations => &[ErrorKind::Db],
+        StartupPhase::Server => &[ErrorKind::Internal, ErrorKind::Config, ErrorKind::Io],
+    }
+}
+
+/// ## Wraps errors of an unexpected kind in the kind of the phase.
+///
+/// Code of the error is kept, so it stays machine-readable.
+fn attribute(phase: StartupPhase, e: AppError) -> AppError {
+    let kinds = expected_kinds(phase);
+    if kinds.contains(&e.kind) {
+        return e;
+    }
+
+    let message = format!("Startup phase '{}' failed: {}", phase.as_str(), e.message);
+    let code = e.code;
+
+    AppError::new(kinds[0], message, None)
+        .with_code(code)
+        .with_source(e)
+}
+
+/// ## Checks the Redis health checks until they are up or the timeout passes.
+#[cfg(feature = "redis")]
+async fn wait_for_cache(
+    health: &super::config::HealthSettings,
+    timeout: Duration,
+) -> Result<(), AppError> {
+    use super::health::{check_integrations, HealthStatus};
+
+    let started = Instant::now();
+    loop {
+        let checks = check_integrations(health).await;
+        let Some(down) = checks
+            .into_iter()
+            .find(|check| check.status == HealthStatus::Down)
+        else {
+            return Ok(());
+        };
+
+        if started.elapsed() + CACHE_RETRY_INTERVAL > timeout {
+            return Err(AppError::external(format!(
+                "Cache '{}' is not ready after {}s: {}",
+                down.name,
+                timeout.as_secs(),
+                down.error.unwrap_or_default()
+            )));
+        }
+
+        tracing::info!(check = down.name, "Waiting for the cache to come up");
+        tokio::time::sleep(CACHE_RETRY_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::{read_config, DEFAULT_CONFIG_FILE};
+
+    fn config() -> AppConfig {
+        read_config(DEFAULT_CONFIG_FILE, None).unwrap()
+    }
+
+    // Test checks if finished and failed steps are recorded with their outcome.
+    #[tokio::test]
+    async fn test_step() {
+        let mut boot = Bootstrap::new();
+
+        let output = boot
+            .step(StartupPhase::Db, async { Ok::<_, AppError>(42) })
+            .await;
+
+        assert_eq!(output, Ok(42));
+
+        let err = boot
+            .step(StartupPhase::Migrations, async {
+                Err::<(), _>(AppError::db("Migration 3 failed"))
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err, AppError::db("Migration 3 failed"));
+        assert_eq!(
+            boot.reports()
+                .iter()
+                .map(|report| (report.phase, report.outcome))
+                .collect::<Vec<_>>(),
+            [
+                (StartupPhase::Db, Outcome::Done),
+                (StartupPhase::Migrations, Outcome::Failed(ErrorKind::Db)),
+            ]
+        );
+    }
+
+    // Test checks if errors of an unexpected kind are wrapped in the kind of the phase.
+    #[tokio::test]
+    async fn test_step_attributes_kind() {
+        let mut boot = Bootstrap::new();
+
+        let err = boot
+            .step(StartupPhase::Cache, async {
+                Err::<(), _>(AppError::io("Connection refused").with_code("io.refused"))
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind, ErrorKind::External);
+        assert_eq!(err.code, "io.refused");
+        assert_eq!(
+            err.message,
+            "Startup phase 'cache' failed: Connection refused"
+        );
+        assert!(err.source.is_some());
+        assert_eq!(
+            boot.reports()[0].outcome,
+            Outcome::Failed(ErrorKind::External)
+        );
+    }
+
+    // Test checks if phases listed in bootstrap.skip do not run.
+    #[tokio::test]
+    async fn test_skip_from_config() {
+        let mut config = config();
+        config.bootstrap.skip = vec![StartupPhase::Cache];
+        let mut boot = Bootstrap::new();
+
+        boot.cache(&config).await.unwrap();
+
+        assert_eq!(
+            boot.reports(),
+            [PhaseReport {
+                phase: StartupPhase::Cache,
+                outcome: Outcome::Skipped("listed in bootstrap.skip"),
+                duration: Duration::ZERO,
+            }]
+        );
+    }
+
+    // Test checks if the cache phase waits for critical Redis checks only.
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    async fn test_cache() {
+        use crate::core::config::{HealthCheckKind, HealthCheckSettings};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let check = |target: String, critical| HealthCheckSettings {
+            name: "cache".to_string(),
+            kind: HealthCheckKind::Redis,
+            target,
+            timeout: Some(1),
+            critical,
+        };
+        let mut config = config();
+        config.bootstrap.cache_timeout = 0;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        config.health.checks = vec![check(closed.clone(), false)];
+        let mut boot = Bootstrap::new();
+        boot.cache(&config).await.unwrap();
+
+        assert_eq!(
+            boot.reports()[0].outcome,
+            Outcome::Skipped("no critical Redis health checks")
+        );
+
+        config.health.checks = vec![check(closed, true)];
+        let err = boot.cache(&config).await.unwrap_err();
+
+        assert_eq!(err.kind, ErrorKind::External);
+        assert!(err
+            .message
+            .starts_with("Cache 'cache' is not ready after 0s"));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        config.health.checks = vec![check(listener.local_addr().unwrap().to_string(), true)];
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 64];
+            let _ = stream.read(&mut buf).await;
+            stream.write_all(b"+PONG\r\n").await.unwrap();
+        });
+        boot.cache(&config).await.unwrap();
+
+        assert_eq!(boot.reports()[2].outcome, Outcome::Done);
+    }
+}
@@ -0,0 +1,154 @@
+//! Build information module.
+//!
+//! Module exposes what the build script recorded about
+//! the binary: the git revision, the compiler, the
+//! profile and target, the time of the build and the
+//! enabled cargo features. Values the build script could
+//! not determine, e.g. the revision outside of a git
+//! checkout, are `None`.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+/// Cargo features and whether they are enabled.
+const FEATURES: [(&str, bool); 4] = [
+    ("cli", cfg!(feature = "cli")),
+    ("postgres", cfg!(feature = "postgres")),
+    ("redis", cfg!(feature = "redis")),
+    ("server", cfg!(feature = "server")),
+];
+
+/// ## Build information struct.
+///
+/// ## Fields
+/// + `name`: `&'static str` - Name of the crate.
+/// + `version`: `&'static str` - Version of the crate.
+/// + `git_sha`: `Option<&'static str>` - Abbreviated git revision.
+/// + `git_dirty`: `bool` - Tracked files had uncommitted changes.
+/// + `rustc`: `Option<&'static str>` - Version of the compiler.
+/// + `profile`: `Option<&'static str>` - Cargo profile, e.g. `release`.
+/// + `target`: `Option<&'static str>` - Target triple.
+/// + `built_at`: `Option<DateTime<Utc>>` - Time of the build.
+/// + `features`: `Vec<&'static str>` - Enabled cargo features.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::build::BuildInfo;
+///
+/// let build = BuildInfo::current();
+///
+/// assert_eq!(build.version, env!("CARGO_PKG_VERSION"));
+/// println!("{}", build);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub git_sha: Option<&'static str>,
+    pub git_dirty: bool,
+    pub rustc: Option<&'static str>,
+    pub profile: Option<&'static str>,
+    pub target: Option<&'static str>,
+    pub built_at: Option<DateTime<Utc>>,
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    /// ## Returns the information of the running binary.
+    pub fn current() -> Self {
+        BuildInfo {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: option_env!("AXUM_AUTH_GIT_SHA"),
+            git_dirty: option_env!("AXUM_AUTH_GIT_DIRTY") == Some("true"),
+            rustc: option_env!("AXUM_AUTH_RUSTC"),
+            profile: option_env!("AXUM_AUTH_PROFILE"),
+            target: option_env!("AXUM_AUTH_TARGET"),
+            built_at: option_env!("AXUM_AUTH_BUILT_AT")
+                .and_then(|secs| secs.parse().ok())
+                .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+            features: FEATURES
+                .into_iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(feature, _)| feature)
+                .collect(),
+        }
+    }
+
+    /// ## Returns the revision, suffixed with `-dirty` for uncommitted changes.
+    pub fn revision(&self) -> Option<String> {
+        self.git_sha.map(|sha| match self.git_dirty {
+            true => format!("{}-dirty", sha),
+            false => sha.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    /// Formats the information as a single line, e.g.
+    /// `axum_auth 0.1.0 (3f2a9c1e0b7d, release, x86_64-unknown-linux-gnu)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.version)?;
+
+        let details: Vec<String> = [
+            self.revision(),
+            self.profile.map(str::to_string),
+            self.target.map(str::to_string),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if the build script values are read and the features match the cfg.
+    #[test]
+    fn test_current() {
+        let build = BuildInfo::current();
+
+        assert_eq!(build.name, "axum_auth");
+        assert!(build.profile.is_some());
+        assert!(build.built_at.is_some());
+        assert_eq!(build.features.contains(&"server"), cfg!(feature = "server"));
+    }
+
+    // Test checks if the revision is marked dirty and missing values are left out.
+    #[test]
+    fn test_display() {
+        let build = BuildInfo {
+            name: "axum_auth",
+            version: "1.2.3",
+            git_sha: Some("3f2a9c1e0b7d"),
+            git_dirty: true,
+            rustc: None,
+            profile: Some("release"),
+            target: None,
+            built_at: None,
+            features: Vec::new(),
+        };
+
+        assert_eq!(
+            build.to_string(),
+            "axum_auth 1.2.3 (3f2a9c1e0b7d-dirty, release)"
+        );
+        assert_eq!(
+            BuildInfo {
+                git_sha: None,
+                profile: None,
+                ..build
+            }
+            .to_string(),
+            "axum_auth 1.2.3"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! Startup self-check module.
+//!
+//! Module runs the checks done at startup, and the checks
+//! of the external integrations, without starting the
+//! server. Unlike the startup, which stops at the first
+//! problem, every check runs and the results are printed
+//! as a table, so one run shows everything to fix.
+
+// Imports from external crates
+use std::{
+    error::Error,
+    fmt,
+    io::{self, IsTerminal},
+};
+
+// Local imports
+use crate::core::auth::access::MaxMindResolver;
+use crate::core::config::{lint, read_config, AppConfig, DatabaseRetrySettings, DatabaseSettings};
+use crate::core::env::{self, map::EnvMap, vars::EnvVar, vars::RequiredEnvVar};
+use crate::core::err::AppError;
+use crate::core::health::{self, HealthStatus};
+use crate::core::server::tls::rustls_config;
+
+/// ## Status of a doctor check enum.
+///
+/// # Variants
+/// - `Pass` - Check succeeded.
+/// - `Fail` - Check failed, the deployment would not work.
+/// - `Skip` - Check was not run, because a check it depends on failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl CheckStatus {
+    /// ## Returns the label shown in the table.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "SKIP",
+        }
+    }
+
+    /// ## Returns the ANSI color code of the label.
+    fn color(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "32",
+            CheckStatus::Fail => "31",
+            CheckStatus::Skip => "33",
+        }
+    }
+}
+
+/// ## Doctor check result struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the check.
+/// + `status`: `CheckStatus` - Result of the check.
+/// + `detail`: `String` - What was checked, or why it failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// ## Doctor report struct.
+///
+/// ## Fields
+/// + `checks`: `Vec<DoctorCheck>` - Results in the order the checks ran.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    /// ## Returns the number of failed checks.
+    pub fn failed(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count()
+    }
+
+    /// ## Renders the report as a table.
+    ///
+    /// ## Parameters
+    /// - `color`: `bool` - Color the status column.
+    pub fn render(&self, color: bool) -> String {
+        let width = self
+            .checks
+            .iter()
+            .map(|check| check.name.len())
+            .chain(std::iter::once("CHECK".len()))
+            .max()
+            .unwrap_or_default();
+
+        let mut table = format!("{:<width$}  STATUS  DETAIL", "CHECK");
+        for check in &self.checks {
+            let label = check.status.label();
+            let status = match color {
+                true => format!("\x1b[{}m{}\x1b[0m", check.status.color(), label),
+                false => label.to_string(),
+            };
+
+            table.push_str(&format!(
+                "\n{:<width$}  {}{}  {}",
+                check.name,
+                status,
+                " ".repeat("STATUS".len() - label.len()),
+                check.detail
+            ));
+        }
+
+        table
+    }
+
+    /// ## Adds a check result.
+    fn push(&mut self, name: impl Into<String>, status: CheckStatus, detail: impl Into<String>) {
+        self.checks.push(DoctorCheck {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    /// ## Adds a passed or failed check from the result.
+    fn record<T>(&mut self, name: &str, result: Result<T, AppError>, detail: impl Into<String>) {
+        match result {
+            Ok(_) => self.push(name, CheckStatus::Pass, detail),
+            Err(e) => self.push(name, CheckStatus::Fail, describe(&e)),
+        }
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+/// ## Runs the `doctor` subcommand.
+///
+/// Function runs every check, prints the table and a
+/// summary. Colors are used when stdout is a terminal
+/// and `NO_COLOR` is not set.
+///
+/// ## Parameters
+/// - `config_path`: `&str` - Path to the configuration file.
+/// - `profile`: `Option<&str>` - Profile selected on the command line.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If every check passed.
+///     - `Err(AppError)`: If any check failed.
+pub async fn doctor(config_path: &str, profile: Option<&str>) -> Result<(), AppError> {
+    let report = diagnose(config_path, profile).await;
+    let color = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+
+    println!("{}", report.render(color));
+
+    match report.failed() {
+        0 => {
+            println!("All {} check(s) passed", report.checks.len());
+            Ok(())
+        }
+        failed => Err(AppError::config(format!(
+            "{} of {} doctor check(s) failed",
+            failed,
+            report.checks.len()
+        ))),
+    }
+}
+
+/// ## Runs every check.
+///
+/// Checks that depend on the configuration or the
+/// environment are skipped when those failed to load.
+///
+/// ## Parameters
+/// - `config_path`: `&str` - Path to the configuration file.
+/// - `profile`: `Option<&str>` - Profile selected on the command line.
+///
+/// ## Returns
+/// - `DoctorReport`: Result of every check.
+pub async fn diagnose(config_path: &str, profile: Option<&str>) -> DoctorReport {
+    let mut report = DoctorReport::default();
+
+    let config = match read_config(config_path, profile) {
+        Ok(config) => {
+            report.push("configuration", CheckStatus::Pass, config_path);
+            config
+        }
+        Err(e) => {
+            report.push("configuration", CheckStatus::Fail, describe(&e));
+            for name in ["environment", "security lint", "key files", "database"] {
+                report.push(name, CheckStatus::Skip, "Configuration failed to load");
+            }
+            return report;
+        }
+    };
+
+    let env = env::load(
+        &config.app.env_files(),
+        &config.app.prefix,
+        RequiredEnvVar::all(),
+        config.app.unknown_env_vars,
+    );
+    let env = match env {
+        Ok(env) => {
+            report.push(
+                "environment",
+                CheckStatus::Pass,
+                config.app.env_files().join(", "),
+            );
+            Some(env)
+        }
+        Err(e) => {
+            report.push("environment", CheckStatus::Fail, describe(&e));
+            None
+        }
+    };
+
+    let lint = lint::check(&config, |var| {
+        env.as_ref()
+            .and_then(|env| env.get(&var).ok().map(str::to_string))
+    });
+    match lint {
+        Ok(lint) => report.push(
+            "security lint",
+            CheckStatus::Pass,
+            format!(
+                "Score {}/{}, {} finding(s)",
+                lint.score(),
+                lint::MAX_SCORE,
+                lint.findings.len()
+            ),
+        ),
+        Err(e) => report.push("security lint", CheckStatus::Fail, e.message),
+    }
+
+    check_key_files(&config, &mut report).await;
+
+    match &env {
+        Some(env) => check_database(env, &config.database, &mut report).await,
+        None => report.push("database", CheckStatus::Skip, "Environment failed to load"),
+    }
+
+    for check in health::check_integrations(&config.health).await {
+        let name = format!("health: {}", check.name);
+        match (check.status, check.error) {
+            (HealthStatus::Up, _) => report.push(
+                name,
+                CheckStatus::Pass,
+                format!("Responded in {}ms", check.latency_ms),
+            ),
+            (HealthStatus::Down, error) => {
+                report.push(name, CheckStatus::Fail, error.unwrap_or_default())
+            }
+        }
+    }
+
+    report
+}
+
+/// ## Checks the files the configuration points to.
+async fn check_key_files(config: &AppConfig, report: &mut DoctorReport) {
+    let mut checked = false;
+
+    let tls = &config.server.tls;
+    if tls.enabled {
+        let result = rustls_config(tls).await;
+        report.record(
+            "tls certificate",
+            result,
+            format!("{}, {}", tls.cert_path, tls.key_path),
+        );
+        checked = true;
+    }
+
+    if let Some(path) = &config.access.geoip_db_path {
+        report.record("geoip database", MaxMindResolver::open(path), path.as_str());
+        checked = true;
+    }
+
+    if !checked {
+        report.push("key files", CheckStatus::Skip, "No key files configured");
+    }
+}
+
+/// ## Connects to the database and runs a query.
+async fn check_database(env: &EnvMap, settings: &DatabaseSettings, report: &mut DoctorReport) {
+    // Doctor reports an unreachable database right away
+    let settings = DatabaseSettings {
+        retry: DatabaseRetrySettings {
+            max_attempts: 1,
+            ..settings.retry.clone()
+        },
+        ..settings.clone()
+    };
+    let pool = match crate::core::db::connect(env, &settings).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            report.push("database", CheckStatus::Fail, describe(&e));
+            return;
+        }
+    };
+
+    let result = sqlx::query("SELECT 1").execute(&pool).await;
+    pool.close().await;
+
+    match result {
+        Ok(_) => report.push(
+            "database",
+            CheckStatus::Pass,
+            format!(
+                "{}:{}",
+                env.get(&RequiredEnvVar::DbHost).unwrap_or_default(),
+                env.get(&RequiredEnvVar::DbPort).unwrap_or_default()
+            ),
+        ),
+        Err(e) => report.push("database", CheckStatus::Fail, e.to_string()),
+    }
+}
+
+/// ## Returns the message of the error and of its source.
+fn describe(error: &AppError) -> String {
+    match error.source() {
+        Some(source) => format!("{}: {}", error.message, source),
+        None => error.message.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> DoctorReport {
+        let mut report = DoctorReport::default();
+        report.push("configuration", CheckStatus::Pass, "config.toml");
+        report.push("database", CheckStatus::Fail, "Connection refused");
+        report.push("key files", CheckStatus::Skip, "No key files configured");
+        report
+    }
+
+    // Test checks if the table is aligned and only colored when asked to.
+    #[test]
+    fn test_render() {
+        let report = report();
+
+        assert_eq!(
+            report.render(false),
+            "CHECK          STATUS  DETAIL\n\
+             configuration  PASS    config.toml\n\
+             database       FAIL    Connection refused\n\
+             key files      SKIP    No key files configured"
+        );
+        assert!(report
+            .render(true)
+            .contains("\x1b[31mFAIL\x1b[0m    Connection refused"));
+        assert_eq!(report.failed(), 1);
+    }
+
+    // Test checks if the checks depending on the configuration are skipped.
+    #[tokio::test]
+    async fn test_diagnose_missing_config() {
+        let report = diagnose("./does-not-exist.toml", None).await;
+
+        assert_eq!(report.checks[0].status, CheckStatus::Fail);
+        assert!(report.checks[1..]
+            .iter()
+            .all(|check| check.status == CheckStatus::Skip));
+        assert_eq!(report.failed(), 1);
+    }
+}
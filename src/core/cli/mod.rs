@@ -0,0 +1,1379 @@
+//! Command line module.
+//!
+//! Module parses the command line and runs the
+//! maintenance subcommands. Without a subcommand
+//! the HTTP server is started.
+
+// References to submodules
+pub mod doctor;
+pub mod users;
+pub mod validate;
+
+// Imports from external crates
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+use uuid::Uuid;
+
+// Local imports
+use super::auth::breach::BloomFilter;
+use super::auth::jwt::SigningKey;
+use super::config::migrate::migrate_file;
+use super::config::schema::{example_toml, schema};
+use super::config::secrets::{MasterKey, CONFIG_KEY, CONFIG_KEY_FILE};
+use super::config::{AppConfig, KeyRotationSettings, TokenSettings};
+use super::db::seed::{self, SeedTarget};
+use super::env::diff::diff as env_diff;
+use super::env::example::example as example_env;
+use super::env::vars::check_prefix;
+use super::err::{AppError, ErrorKind};
+use super::http::journal::{self, read_entries};
+use super::repo::{ClientRepo, NewClient, RoleDefinitionRepo, TenantRepo, UserRepo};
+use super::server::registry;
+use super::state::AppState;
+use super::tenancy::{validate_tenant_id, with_tenant, DEFAULT_TENANT};
+use crate::routes::me::random_token;
+use users::{UserFormat, UserWriter};
+use validate::ValidateFormat;
+
+/// ## Command line arguments struct.
+///
+/// ## Fields
+/// + `profile`: `Option<String>` - Configuration profile merged over
+///   the base values, the one of `app.env` when not set.
+/// + `command`: `Option<Command>` - Subcommand, `serve` when not set.
+#[derive(Debug, Parser)]
+#[command(version, about = "Authentication server built on axum", long_about = None)]
+pub struct Cli {
+    /// Configuration profile to merge over the base values, app.env when not set
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// ## Subcommands.
+///
+/// # Variants
+/// - `Serve` - Starts the HTTP server.
+/// - `Worker` - Runs the background jobs without serving HTTP.
+/// - `Migrate` - Applies the pending database migrations and exits.
+/// - `MigrateConfig` - Upgrades a configuration file to the current format.
+/// - `Replay` - Resends requests recorded by the request journal.
+/// - `Doctor` - Runs the startup and integration checks and exits.
+/// - `Config` - Prints the configuration schema, example files or
+///   encrypted values.
+/// - `Env` - Compares environment files and prints the example one.
+/// - `Token` - Issues an access token signed with the configured key.
+/// - `Client` - Registers and disables OAuth clients.
+/// - `Tenant` - Creates and disables tenants.
+/// - `Routes` - Prints the route table of the server.
+/// - `BreachFilter` - Builds the offline filter of breached passwords.
+/// - `Users` - Imports and exports users as CSV or NDJSON.
+/// - `Seed` - Upserts the tenants, roles and users of a fixture file.
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum Command {
+    /// Start the HTTP server (default)
+    Serve,
+    /// Run the background jobs without serving HTTP
+    Worker,
+    /// Apply the pending database migrations and exit
+    Migrate,
+    /// Upgrade a configuration file written for an older version
+    MigrateConfig(MigrateConfigArgs),
+    /// Resend requests recorded by the request journal
+    Replay(ReplayArgs),
+    /// Check the configuration, environment and integrations and exit
+    Doctor,
+    /// Print the configuration schema, example files or encrypted values
+    Config(ConfigArgs),
+    /// Compare environment files or print the example one
+    Env(EnvArgs),
+    /// Issue an access token signed with the configured key
+    Token(TokenArgs),
+    /// Register or disable OAuth clients of the client credentials grant
+    Client(ClientArgs),
+    /// Create or disable tenants
+    Tenant(TenantArgs),
+    /// Print the method, path, auth policy and middleware of every route
+    Routes,
+    /// Build the offline filter of breached passwords from a hash list
+    BreachFilter(BreachFilterArgs),
+    /// Import or export users as CSV or NDJSON
+    Users(UsersArgs),
+    /// Create or update the tenants, roles and users of a fixture file
+    Seed(SeedArgs),
+}
+
+/// ## `client` arguments struct.
+///
+/// ## Fields
+/// + `command`: `ClientCommand` - What to do with the client.
+#[derive(Debug, Args, PartialEq)]
+pub struct ClientArgs {
+    #[command(subcommand)]
+    pub command: ClientCommand,
+}
+
+/// ## `client` subcommands.
+///
+/// # Variants
+/// - `Create` - Registers a client and prints its secret.
+/// - `Disable` - Stops issuing tokens to the client.
+/// - `Enable` - Issues tokens to a disabled client again.
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum ClientCommand {
+    /// Register a client and print its secret
+    Create(CreateClientArgs),
+    /// Stop issuing tokens to the client, its tokens turn inactive
+    Disable {
+        /// ID of the client
+        client_id: String,
+    },
+    /// Issue tokens to a disabled client again
+    Enable {
+        /// ID of the client
+        client_id: String,
+    },
+}
+
+/// ## `client create` arguments struct.
+///
+/// ## Fields
+/// + `client_id`: `String` - ID of the client, used as `sub` of its tokens.
+/// + `name`: `Option<String>` - Display name, the ID when not set.
+/// + `scope`: `Vec<String>` - Scopes the client may request.
+#[derive(Debug, Args, PartialEq)]
+pub struct CreateClientArgs {
+    /// ID of the client, used as subject of its tokens
+    pub client_id: String,
+    /// Display name of the client
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Scope the client may request, may be repeated
+    #[arg(long)]
+    pub scope: Vec<String>,
+}
+
+/// ## `tenant` arguments struct.
+///
+/// ## Fields
+/// + `command`: `TenantCommand` - What to do with the tenant.
+#[derive(Debug, Args, PartialEq)]
+pub struct TenantArgs {
+    #[command(subcommand)]
+    pub command: TenantCommand,
+}
+
+/// ## `tenant` subcommands.
+///
+/// # Variants
+/// - `Create` - Creates a tenant.
+/// - `Disable` - Rejects the requests of the tenant.
+/// - `Enable` - Accepts the requests of a disabled tenant again.
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum TenantCommand {
+    /// Create a tenant
+    Create {
+        /// ID of the tenant, a lowercase DNS label
+        id: String,
+        /// Display name of the tenant
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Reject the requests of the tenant
+    Disable {
+        /// ID of the tenant
+        id: String,
+    },
+    /// Accept the requests of a disabled tenant again
+    Enable {
+        /// ID of the tenant
+        id: String,
+    },
+}
+
+/// ## `token` arguments struct.
+///
+/// ## Fields
+/// + `subject`: `String` - User or client the token is issued to.
+/// + `scope`: `Vec<String>` - Granted scopes.
+#[derive(Debug, Args, PartialEq)]
+pub struct TokenArgs {
+    /// User or client the token is issued to
+    #[arg(long)]
+    pub subject: String,
+    /// Granted scope, may be repeated
+    #[arg(long)]
+    pub scope: Vec<String>,
+}
+
+/// ## `config` arguments struct.
+///
+/// ## Fields
+/// + `command`: `ConfigCommand` - What to print.
+#[derive(Debug, Args, PartialEq)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+/// ## `config` subcommands.
+///
+/// # Variants
+/// - `Schema` - Prints the JSON Schema of the configuration file.
+/// - `Example` - Prints an example configuration or environment file.
+/// - `Key` - Prints a new master key for encrypted values.
+/// - `Encrypt` - Prints a value encrypted with the master key.
+/// - `Validate` - Checks the configuration and environment, exits 1
+///   when they are invalid.
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum ConfigCommand {
+    /// Print the JSON Schema of the configuration file
+    Schema,
+    /// Print a commented example configuration or environment file
+    Example(ExampleArgs),
+    /// Print a new master key for encrypted configuration values
+    Key,
+    /// Encrypt a value with the master key of CONFIG_KEY or CONFIG_KEY_FILE
+    Encrypt(EncryptArgs),
+    /// Check the configuration and environment, exit 1 when invalid
+    Validate(ValidateArgs),
+}
+
+/// ## `env` arguments struct.
+///
+/// ## Fields
+/// + `command`: `EnvCommand` - What to do with the environment files.
+#[derive(Debug, Args, PartialEq)]
+pub struct EnvArgs {
+    #[command(subcommand)]
+    pub command: EnvCommand,
+}
+
+/// ## `env` subcommands.
+///
+/// # Variants
+/// - `Diff` - Prints the differences of two environment files.
+/// - `Example` - Prints the example environment file.
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum EnvCommand {
+    /// Print the added, removed and changed variables of two files
+    Diff(EnvDiffArgs),
+    /// Print the example environment file with every known variable
+    Example {
+        /// Prefix of the environment variables
+        #[arg(long, default_value = "AXA_")]
+        prefix: String,
+    },
+}
+
+/// ## `env diff` arguments struct.
+///
+/// ## Fields
+/// + `file_a`: `PathBuf` - First file, e.g. staging.
+/// + `file_b`: `PathBuf` - Second file, e.g. production.
+/// + `prefix`: `String` - Prefix of the environment variables.
+/// + `json`: `bool` - Prints the diff as JSON.
+#[derive(Debug, Args, PartialEq)]
+pub struct EnvDiffArgs {
+    /// First environment file
+    pub file_a: PathBuf,
+    /// Second environment file, checked for missing variables
+    pub file_b: PathBuf,
+    /// Prefix of the environment variables
+    #[arg(long, default_value = "AXA_")]
+    pub prefix: String,
+    /// Print the diff as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// ## `config validate` arguments struct.
+///
+/// ## Fields
+/// + `file`: `Option<PathBuf>` - Configuration file to check, the
+///   one the server loads when not set.
+/// + `format`: `ValidateFormat` - Output format.
+#[derive(Debug, Args, PartialEq)]
+pub struct ValidateArgs {
+    /// Configuration file to check instead of the one the server loads
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ValidateFormat::Human)]
+    pub format: ValidateFormat,
+}
+
+/// ## `config encrypt` arguments struct.
+///
+/// ## Fields
+/// + `value`: `Option<String>` - Value to encrypt, read from
+///   stdin when not set so it stays out of the shell history.
+#[derive(Debug, Args, PartialEq)]
+pub struct EncryptArgs {
+    /// Value to encrypt, read from stdin when not set
+    pub value: Option<String>,
+}
+
+/// ## `config example` arguments struct.
+///
+/// ## Fields
+/// + `format`: `ExampleFormat` - File to generate.
+/// + `prefix`: `String` - Prefix of the environment variables.
+#[derive(Debug, Args, PartialEq)]
+pub struct ExampleArgs {
+    /// File to generate
+    #[arg(long, value_enum, default_value_t = ExampleFormat::Toml)]
+    pub format: ExampleFormat,
+    /// Prefix of the environment variables
+    #[arg(long, default_value = "AXA_")]
+    pub prefix: String,
+}
+
+/// ## Format of the generated example.
+///
+/// # Variants
+/// - `Toml` - Configuration file.
+/// - `Env` - Environment file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExampleFormat {
+    Toml,
+    Env,
+}
+
+/// ## `migrate-config` arguments struct.
+///
+/// ## Fields
+/// + `input`: `PathBuf` - Configuration file to upgrade.
+/// + `output`: `Option<PathBuf>` - Path of the upgraded file, the
+///   input file is upgraded in place when not set.
+/// + `dry_run`: `bool` - Only print the changes.
+#[derive(Debug, Args, PartialEq)]
+pub struct MigrateConfigArgs {
+    /// Configuration file to upgrade
+    pub input: PathBuf,
+    /// Write the upgraded file here instead of upgrading in place
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Print the changes without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// ## `replay` arguments struct.
+///
+/// ## Fields
+/// + `dir`: `PathBuf` - Journal directory.
+/// + `target`: `String` - Base URL of the instance to replay against.
+/// + `seq`: `Vec<u64>` - Entries to replay, every entry when empty.
+#[derive(Debug, Args, PartialEq)]
+pub struct ReplayArgs {
+    /// Journal directory
+    #[arg(long, default_value = "./journal")]
+    pub dir: PathBuf,
+    /// Base URL of the instance to replay against
+    #[arg(long, default_value = "http://127.0.0.1:8080")]
+    pub target: String,
+    /// Sequence number of an entry to replay, may be repeated
+    #[arg(long)]
+    pub seq: Vec<u64>,
+}
+
+/// ## `users` arguments struct.
+///
+/// ## Fields
+/// + `tenant`: `String` - Tenant of the users.
+/// + `command`: `UsersCommand` - Import or export.
+#[derive(Debug, Args, PartialEq)]
+pub struct UsersArgs {
+    /// Tenant of the users
+    #[arg(long, default_value = DEFAULT_TENANT)]
+    pub tenant: String,
+    #[command(subcommand)]
+    pub command: UsersCommand,
+}
+
+/// ## `users` subcommands.
+///
+/// # Variants
+/// - `Import` - Creates the users of a file.
+/// - `Export` - Writes every user to a file.
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum UsersCommand {
+    /// Create the users of a CSV or NDJSON file
+    Import(ImportUsersArgs),
+    /// Write every user to a CSV or NDJSON file
+    Export(ExportUsersArgs),
+}
+
+/// ## `users import` arguments struct.
+///
+/// ## Fields
+/// + `input`: `PathBuf` - File to import.
+/// + `format`: `Option<UserFormat>` - Format of the file, detected from
+///   the extension when not set.
+/// + `dry_run`: `bool` - Validates the rows without creating users.
+#[derive(Debug, Args, PartialEq)]
+pub struct ImportUsersArgs {
+    /// File to import, with the columns email, display_name, org_id,
+    /// disabled, password_hash and roles separated by ";"
+    pub input: PathBuf,
+    /// Format of the file, detected from the extension when not set
+    #[arg(long, value_enum)]
+    pub format: Option<UserFormat>,
+    /// Validate the rows without creating users
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// ## `users export` arguments struct.
+///
+/// ## Fields
+/// + `output`: `Option<PathBuf>` - File to write, stdout when not set.
+/// + `format`: `Option<UserFormat>` - Format of the file, detected from
+///   the extension when not set, NDJSON on stdout.
+/// + `password_hashes`: `bool` - Exports the password hashes too.
+#[derive(Debug, Args, PartialEq)]
+pub struct ExportUsersArgs {
+    /// File to write, stdout when not set
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Format of the file, detected from the extension when not set
+    #[arg(long, value_enum)]
+    pub format: Option<UserFormat>,
+    /// Include the password hashes of the users
+    #[arg(long)]
+    pub password_hashes: bool,
+}
+
+/// ## `seed` arguments struct.
+///
+/// ## Fields
+/// + `path`: `PathBuf` - TOML or YAML fixture file.
+#[derive(Debug, Args, PartialEq)]
+pub struct SeedArgs {
+    /// TOML or YAML file with the tenants, roles and users to seed
+    pub path: PathBuf,
+}
+
+/// ## `breach-filter` arguments struct.
+///
+/// ## Fields
+/// + `input`: `PathBuf` - List of SHA-1 hashes, like the Have I Been
+///   Pwned downloads.
+/// + `output`: `PathBuf` - Filter file to write.
+/// + `false_positive_rate`: `f64` - Share of other passwords the filter
+///   takes for breached ones.
+#[derive(Debug, Args, PartialEq)]
+pub struct BreachFilterArgs {
+    /// List of SHA-1 hashes, one per line, optionally followed by ":count"
+    pub input: PathBuf,
+    /// Filter file to write
+    #[arg(short, long, default_value = "./pwned.bloom")]
+    pub output: PathBuf,
+    /// Share of other passwords taken for breached ones
+    #[arg(long, default_value_t = 0.001)]
+    pub false_positive_rate: f64,
+}
+
+/// ## Runs the `migrate-config` subcommand.
+///
+/// Function upgrades the file and prints a summary
+/// of the changes.
+///
+/// ## Parameters
+/// - `args`: `&MigrateConfigArgs` - Subcommand arguments.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the file was upgraded or is already current.
+///     - `Err(AppError)`: If the file can not be upgraded.
+pub fn migrate_config(args: &MigrateConfigArgs) -> Result<(), AppError> {
+    let output = args.output.as_ref().unwrap_or(&args.input);
+    let changes = migrate_file(&args.input, output, args.dry_run)?;
+
+    if changes.is_empty() {
+        println!("'{}' is already up to date", args.input.display());
+        return Ok(());
+    }
+
+    for change in &changes {
+        println!("  {}", change);
+    }
+
+    match args.dry_run {
+        true => println!("{} change(s), nothing written (dry run)", changes.len()),
+        false => println!(
+            "{} change(s) written to '{}'",
+            changes.len(),
+            output.display()
+        ),
+    }
+
+    Ok(())
+}
+
+/// ## Runs the `breach-filter` subcommand.
+///
+/// Function builds the filter of the list and writes
+/// it for `password.breach.bloom_filter_path`.
+///
+/// ## Parameters
+/// - `args`: `&BreachFilterArgs` - Subcommand arguments.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the filter was written.
+///     - `Err(AppError)`: If the list can not be read or the filter
+///       not written.
+pub fn breach_filter(args: &BreachFilterArgs) -> Result<(), AppError> {
+    if !(args.false_positive_rate > 0.0 && args.false_positive_rate < 1.0) {
+        return Err(AppError::validation(
+            "--false-positive-rate must be between 0 and 1",
+        ));
+    }
+
+    let filter = BloomFilter::build_file(&args.input, args.false_positive_rate)?;
+    let bytes = filter.to_bytes();
+    std::fs::write(&args.output, &bytes).map_err(|e| {
+        AppError::new(
+            ErrorKind::Internal,
+            format!("Failed to write '{}'", args.output.display()),
+            Some(Box::new(e)),
+        )
+    })?;
+    println!(
+        "Bloom filter of {} bytes written to '{}'",
+        bytes.len(),
+        args.output.display()
+    );
+
+    Ok(())
+}
+
+/// ## Runs the `config` subcommand.
+///
+/// Function prints the generated file, or the validation
+/// report, to stdout.
+///
+/// ## Parameters
+/// - `args`: `&ConfigArgs` - Subcommand arguments.
+/// - `config_path`: `&str` - Path to the configuration file the
+///   server loads.
+/// - `profile`: `Option<&str>` - Profile selected on the command line.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the file was generated or the configuration is valid.
+///     - `Err(AppError)`: If the file can not be generated or the
+///       configuration is invalid.
+pub fn config(args: &ConfigArgs, config_path: &str, profile: Option<&str>) -> Result<(), AppError> {
+    let output = match &args.command {
+        ConfigCommand::Validate(validate) => {
+            let path = match &validate.file {
+                Some(file) => file.to_string_lossy().to_string(),
+                None => config_path.to_string(),
+            };
+            return validate::validate(&path, profile, validate.format);
+        }
+        ConfigCommand::Schema => serde_json::to_string_pretty(&schema()).map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                "Failed to serialize the configuration schema".to_string(),
+                Some(Box::new(e)),
+            )
+        })?,
+        ConfigCommand::Example(example) => match example.format {
+            ExampleFormat::Toml => example_toml(&example.prefix)?,
+            ExampleFormat::Env => example_env(&example.prefix),
+        },
+        ConfigCommand::Key => MasterKey::generate()?,
+        ConfigCommand::Encrypt(encrypt) => {
+            let key = MasterKey::from_env()?.ok_or_else(|| {
+                AppError::config(format!(
+                    "Set {} or {} to encrypt",
+                    CONFIG_KEY, CONFIG_KEY_FILE
+                ))
+            })?;
+            let value = match &encrypt.value {
+                Some(value) => value.clone(),
+                None => {
+                    let mut value = String::new();
+                    std::io::stdin().read_to_string(&mut value).map_err(|e| {
+                        AppError::io("Failed to read the value from stdin").with_source(e)
+                    })?;
+                    value.trim_end_matches(['\r', '\n']).to_string()
+                }
+            };
+            key.encrypt(&value)?
+        }
+    };
+
+    print!("{}", output);
+    if !output.ends_with('\n') {
+        println!();
+    }
+
+    Ok(())
+}
+
+/// ## Runs the `env` subcommand.
+///
+/// Function prints the diff or the example file to
+/// stdout, values of secret variables are masked.
+///
+/// ## Parameters
+/// - `args`: `&EnvArgs` - Subcommand arguments.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the diff or example was printed.
+///     - `Err(AppError)`: If a file can not be loaded.
+pub fn env(args: &EnvArgs) -> Result<(), AppError> {
+    match &args.command {
+        EnvCommand::Diff(diff) => {
+            let env_diff = env_diff(
+                &diff.file_a.to_string_lossy(),
+                &diff.file_b.to_string_lossy(),
+                &diff.prefix,
+            )?;
+
+            match diff.json {
+                true => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&env_diff).map_err(|e| {
+                        AppError::internal("Failed to serialize the environment diff")
+                            .with_source(e)
+                    })?
+                ),
+                false => print!("{}", env_diff),
+            }
+        }
+        EnvCommand::Example { prefix } => {
+            check_prefix(prefix)?;
+            print!("{}", example_env(prefix));
+        }
+    }
+
+    Ok(())
+}
+
+/// ## Runs the `token` subcommand.
+///
+/// Function prints a token for tests and service
+/// accounts, signed with the key the server publishes.
+///
+/// ## Parameters
+/// - `args`: `&TokenArgs` - Subcommand arguments.
+/// - `settings`: `&TokenSettings` - Issuer, audience and signing key.
+/// - `rotation`: `&KeyRotationSettings` - Rotation of the signing key.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the token was printed.
+///     - `Err(AppError)`: If no signing key is configured, it is
+///       rotated or it can not be read.
+pub fn token(
+    args: &TokenArgs,
+    settings: &TokenSettings,
+    rotation: &KeyRotationSettings,
+) -> Result<(), AppError> {
+    if settings.signing_key.is_empty() {
+        return Err(AppError::config(
+            "Issuing tokens requires 'tokens.signing_key', the server does not know temporary keys",
+        ));
+    }
+    if rotation.enabled {
+        return Err(AppError::config(
+            "Issuing tokens requires 'key_rotation' to be disabled, the configured key is retired",
+        ));
+    }
+
+    let key = SigningKey::load(settings)?;
+    let scopes: Vec<&str> = args.scope.iter().map(String::as_str).collect();
+
+    println!("{}", key.issue(settings, &args.subject, &scopes)?);
+
+    Ok(())
+}
+
+/// ## Runs the `routes` subcommand.
+///
+/// Routes are registered like the server does, but
+/// never served, so the database is not connected.
+///
+/// ## Parameters
+/// - `config`: `Arc<AppConfig>` - Application configuration.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the route table was printed.
+///     - `Err(AppError)`: If a route can not be built from the configuration.
+pub fn routes(config: Arc<AppConfig>) -> Result<(), AppError> {
+    let pool = PgPoolOptions::new().connect_lazy_with(PgConnectOptions::new());
+    let state = AppState::builder(config).db(pool).build()?;
+
+    println!("{}", registry(&state)?.table());
+
+    Ok(())
+}
+
+/// ## Runs the `client` subcommand.
+///
+/// Secret of a new client is printed once, only its
+/// hash is stored.
+///
+/// ## Parameters
+/// - `args`: `&ClientArgs` - Subcommand arguments.
+/// - `clients`: `&dyn ClientRepo` - Registered clients.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the client was registered or updated.
+///     - `Err(AppError)`: If the ID is invalid or taken, or there is
+///       no such client.
+pub async fn client(args: &ClientArgs, clients: &dyn ClientRepo) -> Result<(), AppError> {
+    match &args.command {
+        ClientCommand::Create(create) => {
+            validate_client_id(&create.client_id)?;
+            let secret = random_token()?;
+            let client = clients
+                .create(NewClient {
+                    client_id: create.client_id.clone(),
+                    secret: secret.clone(),
+                    name: create.name.clone().unwrap_or(create.client_id.clone()),
+                    scopes: create.scope.clone(),
+                })
+                .await?;
+
+            println!("client_id:     {}", client.client_id);
+            println!("client_secret: {}", secret);
+            println!("scopes:        {}", client.scopes.join(" "));
+        }
+        ClientCommand::Disable { client_id } => {
+            clients.set_disabled(client_id, true).await?;
+            println!("Client '{}' disabled", client_id);
+        }
+        ClientCommand::Enable { client_id } => {
+            clients.set_disabled(client_id, false).await?;
+            println!("Client '{}' enabled", client_id);
+        }
+    }
+
+    Ok(())
+}
+
+/// ## Runs the `tenant` subcommand.
+///
+/// ## Parameters
+/// - `args`: `&TenantArgs` - Subcommand arguments.
+/// - `tenants`: `&dyn TenantRepo` - Known tenants.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the tenant was created or updated.
+///     - `Err(AppError)`: If the ID is invalid or taken, or there is
+///       no such tenant.
+pub async fn tenant(args: &TenantArgs, tenants: &dyn TenantRepo) -> Result<(), AppError> {
+    match &args.command {
+        TenantCommand::Create { id, name } => {
+            validate_tenant_id(id)?;
+            let tenant = tenants.create(id, name.as_deref().unwrap_or(id)).await?;
+            println!("Tenant '{}' created", tenant.id);
+        }
+        TenantCommand::Disable { id } => {
+            tenants.set_disabled(id, true).await?;
+            println!("Tenant '{}' disabled", id);
+        }
+        TenantCommand::Enable { id } => {
+            tenants.set_disabled(id, false).await?;
+            println!("Tenant '{}' enabled", id);
+        }
+    }
+
+    Ok(())
+}
+
+/// ## Runs the `users` subcommand.
+///
+/// Failed import rows are printed with their line number
+/// after the other rows were imported.
+///
+/// ## Parameters
+/// - `args`: `&UsersArgs` - Subcommand arguments.
+/// - `users`: `&dyn UserRepo` - Users of the tenants.
+/// - `roles`: `&dyn RoleDefinitionRepo` - Defined roles.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If every row was imported or the users were exported.
+///     - `Err(AppError)`: If a row failed, or a file can not be read or
+///       written.
+pub async fn users(
+    args: &UsersArgs,
+    users: &dyn UserRepo,
+    roles: &dyn RoleDefinitionRepo,
+) -> Result<(), AppError> {
+    validate_tenant_id(&args.tenant)?;
+
+    match &args.command {
+        UsersCommand::Import(import) => {
+            let format = match import.format {
+                Some(format) => format,
+                None => UserFormat::detect(&import.input)?,
+            };
+            let file = File::open(&import.input).map_err(|e| {
+                AppError::new(
+                    ErrorKind::Io,
+                    format!("Failed to open '{}'", import.input.display()),
+                    Some(Box::new(e)),
+                )
+            })?;
+            let rows = users::read_records(format, BufReader::new(file))?;
+            let summary = with_tenant(
+                args.tenant.clone(),
+                users::import(rows, users, roles, import.dry_run),
+            )
+            .await;
+
+            for failed in &summary.failed {
+                eprintln!("{}", failed);
+            }
+            let verb = match import.dry_run {
+                true => "valid (dry run)",
+                false => "imported",
+            };
+            println!(
+                "{} user(s) {}, {} failed",
+                summary.imported,
+                verb,
+                summary.failed.len()
+            );
+
+            match summary.failed.len() {
+                0 => Ok(()),
+                failed => Err(AppError::validation(format!(
+                    "{} row(s) of '{}' were not imported",
+                    failed,
+                    import.input.display()
+                ))),
+            }
+        }
+        UsersCommand::Export(export) => {
+            let format = match (export.format, &export.output) {
+                (Some(format), _) => format,
+                (None, Some(output)) => UserFormat::detect(output)?,
+                (None, None) => UserFormat::Ndjson,
+            };
+            let output: Box<dyn Write> = match &export.output {
+                Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|e| {
+                    AppError::new(
+                        ErrorKind::Io,
+                        format!("Failed to create '{}'", path.display()),
+                        Some(Box::new(e)),
+                    )
+                })?)),
+                None => Box::new(io::stdout().lock()),
+            };
+            let mut writer = UserWriter::new(format, output)?;
+            let exported = with_tenant(
+                args.tenant.clone(),
+                users::export(users, &mut writer, export.password_hashes),
+            )
+            .await?;
+
+            if let Some(path) = &export.output {
+                println!("{} user(s) written to '{}'", exported, path.display());
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// ## Runs the `seed` subcommand.
+///
+/// ## Parameters
+/// - `args`: `&SeedArgs` - Subcommand arguments.
+/// - `target`: `&SeedTarget` - Repositories to write to.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If every fixture was applied.
+///     - `Err(AppError)`: If the file can not be loaded, a fixture is
+///       invalid or a row can not be written.
+pub async fn seed(args: &SeedArgs, target: &SeedTarget<'_>) -> Result<(), AppError> {
+    let fixtures = seed::load(&args.path)?;
+    let report = seed::seed(&fixtures, target).await?;
+    println!("Seeded '{}': {}", args.path.display(), report);
+
+    Ok(())
+}
+
+/// ## Checks if the ID can be used by a client.
+///
+/// IDs are sent with HTTP Basic, so they can not contain
+/// `:`, and become the `sub` of the tokens, so they can
+/// not be a UUID, which would be taken for a user.
+fn validate_client_id(client_id: &str) -> Result<(), AppError> {
+    let valid = !client_id.is_empty()
+        && client_id.len() <= 64
+        && client_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && Uuid::parse_str(client_id).is_err();
+
+    match valid {
+        true => Ok(()),
+        false => Err(AppError::validation(format!(
+            "Client ID '{}' must be 1 to 64 letters, digits, '-', '_' or '.' and not a UUID",
+            client_id
+        ))),
+    }
+}
+
+/// ## Runs the `replay` subcommand.
+///
+/// Function resends the recorded requests in order and
+/// prints the new status next to the recorded one.
+///
+/// ## Parameters
+/// - `args`: `&ReplayArgs` - Subcommand arguments.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If every selected request was resent.
+///     - `Err(AppError)`: If the journal can not be read, a selected
+///       entry is missing or the target can not be reached.
+pub async fn replay(args: &ReplayArgs) -> Result<(), AppError> {
+    let entries = read_entries(&args.dir)?
+        .into_iter()
+        .filter(|entry| args.seq.is_empty() || args.seq.contains(&entry.seq))
+        .collect::<Vec<_>>();
+
+    if let Some(missing) = args
+        .seq
+        .iter()
+        .find(|seq| !entries.iter().any(|entry| entry.seq == **seq))
+    {
+        return Err(AppError::new(
+            ErrorKind::NotFound,
+            format!(
+                "Journal entry {} not found in '{}'",
+                missing,
+                args.dir.display()
+            ),
+            None,
+        ));
+    }
+
+    let client = reqwest::Client::new();
+    for entry in &entries {
+        let status = journal::replay(&client, &args.target, entry).await?;
+        let marker = match status == entry.response.status {
+            true => "",
+            false => " (differs)",
+        };
+
+        println!(
+            "#{} {} {} -> {}, recorded {}{}",
+            entry.seq,
+            entry.request.method,
+            entry.request.uri,
+            status,
+            entry.response.status,
+            marker
+        );
+    }
+
+    println!(
+        "{} request(s) replayed against '{}'",
+        entries.len(),
+        args.target
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::repo::memory::{MemoryClientRepo, MemoryTenantRepo};
+
+    // Test checks if the server is started when no subcommand is given
+    // and the profile is accepted after any subcommand.
+    #[test]
+    fn test_parse_default() {
+        let cli = Cli::try_parse_from(["axum_auth"]).unwrap();
+
+        assert_eq!(cli.command, None);
+        assert_eq!(cli.profile, None);
+
+        let cli = Cli::try_parse_from(["axum_auth", "doctor", "--profile", "production"]).unwrap();
+
+        assert_eq!(cli.command, Some(Command::Doctor));
+        assert_eq!(cli.profile.as_deref(), Some("production"));
+    }
+
+    // Test checks if the run modes are parsed.
+    #[test]
+    fn test_parse_run_modes() {
+        for (name, command) in [
+            ("serve", Command::Serve),
+            ("worker", Command::Worker),
+            ("migrate", Command::Migrate),
+            ("doctor", Command::Doctor),
+            ("routes", Command::Routes),
+        ] {
+            let cli = Cli::try_parse_from(["axum_auth", name]).unwrap();
+
+            assert_eq!(cli.command, Some(command));
+        }
+    }
+
+    // Test checks if the migrate-config arguments are parsed.
+    #[test]
+    fn test_parse_migrate_config() {
+        let cli =
+            Cli::try_parse_from(["axum_auth", "migrate-config", "old.toml", "--dry-run"]).unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::MigrateConfig(MigrateConfigArgs {
+                input: PathBuf::from("old.toml"),
+                output: None,
+                dry_run: true,
+            }))
+        );
+        assert!(Cli::try_parse_from(["axum_auth", "migrate-config"]).is_err());
+    }
+
+    // Test checks if the users subcommands are parsed with their defaults.
+    #[test]
+    fn test_parse_users() {
+        let cli = Cli::try_parse_from(["axum_auth", "users", "import", "users.csv", "--dry-run"])
+            .unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Users(UsersArgs {
+                tenant: DEFAULT_TENANT.to_string(),
+                command: UsersCommand::Import(ImportUsersArgs {
+                    input: PathBuf::from("users.csv"),
+                    format: None,
+                    dry_run: true,
+                }),
+            }))
+        );
+
+        let cli = Cli::try_parse_from([
+            "axum_auth",
+            "users",
+            "--tenant",
+            "acme",
+            "export",
+            "--format",
+            "csv",
+            "--password-hashes",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Users(UsersArgs {
+                tenant: "acme".to_string(),
+                command: UsersCommand::Export(ExportUsersArgs {
+                    output: None,
+                    format: Some(UserFormat::Csv),
+                    password_hashes: true,
+                }),
+            }))
+        );
+    }
+
+    // Test checks if the seed fixture file is required.
+    #[test]
+    fn test_parse_seed() {
+        let cli = Cli::try_parse_from(["axum_auth", "seed", "seed.yaml"]).unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Seed(SeedArgs {
+                path: PathBuf::from("seed.yaml"),
+            }))
+        );
+        assert!(Cli::try_parse_from(["axum_auth", "seed"]).is_err());
+    }
+
+    // Test checks if the breach filter is built from a hash list.
+    #[test]
+    fn test_breach_filter() {
+        let dir = std::env::temp_dir().join(format!("axum_auth-breach-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("pwned.txt");
+        // SHA-1 of "password"
+        std::fs::write(
+            &input,
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\n",
+        )
+        .unwrap();
+
+        let cli = Cli::try_parse_from([
+            "axum_auth",
+            "breach-filter",
+            input.to_str().unwrap(),
+            "--output",
+            dir.join("pwned.bloom").to_str().unwrap(),
+        ])
+        .unwrap();
+        let Some(Command::BreachFilter(args)) = cli.command else {
+            panic!("breach-filter was not parsed");
+        };
+        assert_eq!(args.false_positive_rate, 0.001);
+
+        breach_filter(&args).unwrap();
+        let filter = BloomFilter::load(&args.output).unwrap();
+
+        assert!(filter.contains_password("password"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Test checks if the config subcommands are parsed with their defaults.
+    #[test]
+    fn test_parse_config() {
+        let cli = Cli::try_parse_from(["axum_auth", "config", "schema"]).unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Config(ConfigArgs {
+                command: ConfigCommand::Schema,
+            }))
+        );
+
+        let cli =
+            Cli::try_parse_from(["axum_auth", "config", "example", "--format", "env"]).unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Config(ConfigArgs {
+                command: ConfigCommand::Example(ExampleArgs {
+                    format: ExampleFormat::Env,
+                    prefix: "AXA_".to_string(),
+                }),
+            }))
+        );
+        assert!(
+            Cli::try_parse_from(["axum_auth", "config", "example", "--format", "yaml"]).is_err()
+        );
+
+        let cli = Cli::try_parse_from([
+            "axum_auth",
+            "config",
+            "validate",
+            "--file",
+            "prod.toml",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Config(ConfigArgs {
+                command: ConfigCommand::Validate(ValidateArgs {
+                    file: Some(PathBuf::from("prod.toml")),
+                    format: ValidateFormat::Json,
+                }),
+            }))
+        );
+
+        let cli = Cli::try_parse_from(["axum_auth", "config", "encrypt", "s3cret"]).unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Config(ConfigArgs {
+                command: ConfigCommand::Encrypt(EncryptArgs {
+                    value: Some("s3cret".to_string()),
+                }),
+            }))
+        );
+    }
+
+    // Test checks if the env arguments are parsed.
+    #[test]
+    fn test_parse_env() {
+        let cli = Cli::try_parse_from([
+            "axum_auth",
+            "env",
+            "diff",
+            ".env.staging",
+            ".env.production",
+            "--json",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Env(EnvArgs {
+                command: EnvCommand::Diff(EnvDiffArgs {
+                    file_a: PathBuf::from(".env.staging"),
+                    file_b: PathBuf::from(".env.production"),
+                    prefix: "AXA_".to_string(),
+                    json: true,
+                }),
+            }))
+        );
+        assert!(Cli::try_parse_from(["axum_auth", "env", "diff", ".env"]).is_err());
+
+        let cli = Cli::try_parse_from(["axum_auth", "env", "example"]).unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Env(EnvArgs {
+                command: EnvCommand::Example {
+                    prefix: "AXA_".to_string(),
+                },
+            }))
+        );
+    }
+
+    // Test checks if the token arguments are parsed.
+    #[test]
+    fn test_parse_token() {
+        let cli = Cli::try_parse_from([
+            "axum_auth",
+            "token",
+            "--subject",
+            "billing",
+            "--scope",
+            "notes:read",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Token(TokenArgs {
+                subject: "billing".to_string(),
+                scope: vec!["notes:read".to_string()],
+            }))
+        );
+        assert!(Cli::try_parse_from(["axum_auth", "token"]).is_err());
+        assert!(token(
+            &TokenArgs {
+                subject: "billing".to_string(),
+                scope: Vec::new(),
+            },
+            &TokenSettings::default(),
+            &KeyRotationSettings::default()
+        )
+        .is_err());
+    }
+
+    // Test checks if the client arguments are parsed.
+    #[test]
+    fn test_parse_client() {
+        let cli = Cli::try_parse_from([
+            "axum_auth",
+            "client",
+            "create",
+            "billing",
+            "--scope",
+            "users:read",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Client(ClientArgs {
+                command: ClientCommand::Create(CreateClientArgs {
+                    client_id: "billing".to_string(),
+                    name: None,
+                    scope: vec!["users:read".to_string()],
+                }),
+            }))
+        );
+        assert!(Cli::try_parse_from(["axum_auth", "client", "disable"]).is_err());
+    }
+
+    // Test checks if clients are registered and IDs that clash with users are rejected.
+    #[tokio::test]
+    async fn test_client() {
+        let clients = MemoryClientRepo::default();
+        let create = |client_id: &str| ClientArgs {
+            command: ClientCommand::Create(CreateClientArgs {
+                client_id: client_id.to_string(),
+                name: None,
+                scope: vec!["users:read".to_string()],
+            }),
+        };
+
+        client(&create("billing"), &clients).await.unwrap();
+        let stored = clients.get("billing").await.unwrap().unwrap();
+        assert_eq!(stored.name, "billing");
+        assert_eq!(stored.scopes, ["users:read"]);
+
+        for client_id in ["", "a:b", "9b2d3c1e-8f4a-4c2b-9d1e-3f5a6b7c8d9e"] {
+            assert!(client(&create(client_id), &clients).await.is_err());
+        }
+
+        let disable = ClientArgs {
+            command: ClientCommand::Disable {
+                client_id: "billing".to_string(),
+            },
+        };
+        client(&disable, &clients).await.unwrap();
+        assert!(clients.get("billing").await.unwrap().unwrap().disabled);
+    }
+
+    // Test checks if tenants are created with valid IDs and can be disabled.
+    #[tokio::test]
+    async fn test_tenant() {
+        let tenants = MemoryTenantRepo::default();
+        let cli = Cli::try_parse_from(["axum_auth", "tenant", "create", "acme", "--name", "Acme"])
+            .unwrap();
+        let Some(Command::Tenant(create)) = cli.command else {
+            panic!("tenant command expected");
+        };
+
+        tenant(&create, &tenants).await.unwrap();
+        assert_eq!(tenants.get("acme").await.unwrap().unwrap().name, "Acme");
+        assert!(tenant(&create, &tenants).await.is_err());
+
+        let invalid = TenantArgs {
+            command: TenantCommand::Create {
+                id: "Acme Corp".to_string(),
+                name: None,
+            },
+        };
+        assert!(tenant(&invalid, &tenants).await.is_err());
+
+        let disable = TenantArgs {
+            command: TenantCommand::Disable {
+                id: "acme".to_string(),
+            },
+        };
+        tenant(&disable, &tenants).await.unwrap();
+        assert!(tenants.get("acme").await.unwrap().unwrap().disabled);
+    }
+
+    // Test checks if the replay arguments are parsed with their defaults.
+    #[test]
+    fn test_parse_replay() {
+        let cli = Cli::try_parse_from(["axum_auth", "replay", "--seq", "3", "--seq", "7"]).unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Replay(ReplayArgs {
+                dir: PathBuf::from("./journal"),
+                target: "http://127.0.0.1:8080".to_string(),
+                seq: vec![3, 7],
+            }))
+        );
+    }
+}
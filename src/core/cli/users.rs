@@ -0,0 +1,611 @@
+//! User import and export module.
+//!
+//! Module moves users in and out of the server as CSV or
+//! NDJSON. Rows are read and written one at a time, so
+//! files larger than the memory can be imported, and a
+//! failed row is reported with its line number while the
+//! import goes on with the next one. Imported passwords
+//! are hashes created by another system, plain passwords
+//! are never read from a file.
+
+// Imports from external crates
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, Write},
+    path::Path,
+};
+use uuid::Uuid;
+
+// Local imports
+use crate::core::auth::password::StoredHash;
+use crate::core::auth::rbac::Scope;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::pagination::{Cursor, Keyed, Window};
+use crate::core::repo::{email_taken, RoleDefinitionRepo, UserRepo};
+use crate::core::users::{roles::is_builtin, NewUser, UserFilter, USER_SORTING};
+use crate::routes::me::validate_email;
+
+/// Columns of a CSV file, in the order they are written.
+pub const CSV_COLUMNS: [&str; 6] = [
+    "email",
+    "display_name",
+    "org_id",
+    "disabled",
+    "password_hash",
+    "roles",
+];
+
+/// Separator of the roles in a CSV cell.
+const ROLE_SEPARATOR: char = ';';
+
+/// Users fetched per page of an export.
+const EXPORT_PAGE: u32 = 500;
+
+/// ## File format of imported and exported users enum.
+///
+/// # Variants
+/// - `Csv` - Comma separated values with a header row.
+/// - `Ndjson` - One JSON object per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UserFormat {
+    Csv,
+    Ndjson,
+}
+
+impl UserFormat {
+    /// ## Detects the format from the file extension.
+    ///
+    /// ## Parameters
+    /// - `path`: `&Path` - File to read or write.
+    ///
+    /// ## Returns
+    /// + `Result<UserFormat, AppError>`
+    ///     - `Ok(UserFormat)`: `Csv` for `.csv`, `Ndjson` for `.ndjson`
+    ///       and `.jsonl`.
+    ///     - `Err(AppError)`: If the extension is neither.
+    ///
+    /// ## Examples
+    /// ```
+    /// use axum_auth::core::cli::users::UserFormat;
+    /// use std::path::Path;
+    ///
+    /// assert_eq!(UserFormat::detect(Path::new("users.csv")).unwrap(), UserFormat::Csv);
+    /// assert!(UserFormat::detect(Path::new("users.txt")).is_err());
+    /// ```
+    pub fn detect(path: &Path) -> Result<Self, AppError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => Ok(UserFormat::Csv),
+            Some("ndjson" | "jsonl") => Ok(UserFormat::Ndjson),
+            _ => Err(AppError::validation(format!(
+                "Can not tell the format of '{}', pass --format",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// ## Imported or exported user struct.
+///
+/// ## Fields
+/// + `email`: `String` - Email address of the user.
+/// + `display_name`: `Option<String>` - Name shown instead of the email.
+/// + `org_id`: `Option<Uuid>` - Organization of the user.
+/// + `disabled`: `bool` - If the user can not sign in.
+/// + `password_hash`: `Option<String>` - Argon2id or bcrypt hash of the
+///   password, `None` for users without a password.
+/// + `roles`: `Vec<String>` - Roles granted to the user.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct UserRecord {
+    pub email: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub org_id: Option<Uuid>,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl UserRecord {
+    /// ## Reads the record from a CSV row.
+    ///
+    /// Empty cells are treated as missing values.
+    fn from_csv(headers: &csv::StringRecord, row: &csv::StringRecord) -> Result<Self, AppError> {
+        let cell = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header.trim() == name)
+                .and_then(|index| row.get(index))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let org_id = cell("org_id")
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| AppError::validation(format!("'{}' is not a valid org_id", value)))
+            })
+            .transpose()?;
+        let disabled = match cell("disabled") {
+            None | Some("false" | "0") => false,
+            Some("true" | "1") => true,
+            Some(value) => {
+                return Err(AppError::validation(format!(
+                    "'{}' is not a valid disabled flag, expected true or false",
+                    value
+                )))
+            }
+        };
+        let roles = cell("roles")
+            .map(|value| {
+                value
+                    .split(ROLE_SEPARATOR)
+                    .map(str::trim)
+                    .filter(|role| !role.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(UserRecord {
+            email: cell("email").unwrap_or_default().to_string(),
+            display_name: cell("display_name").map(str::to_string),
+            org_id,
+            disabled,
+            password_hash: cell("password_hash").map(str::to_string),
+            roles,
+        })
+    }
+
+    /// ## Returns the cells of the record in `CSV_COLUMNS` order.
+    fn to_csv(&self) -> [String; 6] {
+        [
+            self.email.clone(),
+            self.display_name.clone().unwrap_or_default(),
+            self.org_id.map(|id| id.to_string()).unwrap_or_default(),
+            self.disabled.to_string(),
+            self.password_hash.clone().unwrap_or_default(),
+            self.roles.join(&ROLE_SEPARATOR.to_string()),
+        ]
+    }
+}
+
+/// Row of an import, the line number with the record or
+/// the reason it could not be read.
+pub type UserRow = (u64, Result<UserRecord, AppError>);
+
+/// ## Reads the records of the input one at a time.
+///
+/// A row that can not be parsed is returned as an error,
+/// the rows after it are still read.
+///
+/// ## Parameters
+/// - `format`: `UserFormat` - Format of the input.
+/// - `input`: `impl BufRead` - Input to read.
+///
+/// ## Returns
+/// + `Result<Box<dyn Iterator<Item = UserRow>>, AppError>`
+///     - `Ok(Box<dyn Iterator<Item = UserRow>>)`: Rows of the input.
+///     - `Err(AppError)`: If the CSV header can not be read or has no
+///       `email` column.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::cli::users::{read_records, UserFormat};
+///
+/// let input = "email,roles\nada@example.com,admin;auditor\n";
+/// let rows: Vec<_> = read_records(UserFormat::Csv, input.as_bytes()).unwrap().collect();
+///
+/// let (line, record) = &rows[0];
+/// assert_eq!(*line, 2);
+/// assert_eq!(record.as_ref().unwrap().roles, vec!["admin", "auditor"]);
+/// ```
+pub fn read_records<'a>(
+    format: UserFormat,
+    input: impl BufRead + 'a,
+) -> Result<Box<dyn Iterator<Item = UserRow> + 'a>, AppError> {
+    match format {
+        UserFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+            let headers = reader.headers().map_err(csv_error)?.clone();
+            if !headers.iter().any(|header| header.trim() == "email") {
+                return Err(AppError::validation("CSV header has no 'email' column"));
+            }
+
+            Ok(Box::new(reader.into_records().map(move |row| match row {
+                Ok(row) => {
+                    let line = row.position().map_or(0, |position| position.line());
+                    (line, UserRecord::from_csv(&headers, &row))
+                }
+                Err(e) => {
+                    let line = e.position().map_or(0, |position| position.line());
+                    (line, Err(csv_error(e)))
+                }
+            })))
+        }
+        UserFormat::Ndjson => Ok(Box::new(
+            input
+                .lines()
+                .enumerate()
+                .map(|(index, line)| (index as u64 + 1, line))
+                .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+                .map(|(number, line)| {
+                    let record = line
+                        .map_err(|e| {
+                            AppError::new(
+                                ErrorKind::Io,
+                                "Failed to read the input".to_string(),
+                                Some(Box::new(e)),
+                            )
+                        })
+                        .and_then(|line| {
+                            serde_json::from_str(&line)
+                                .map_err(|e| AppError::validation(format!("Invalid JSON: {}", e)))
+                        });
+                    (number, record)
+                }),
+        )),
+    }
+}
+
+/// ## Builds the error of an unreadable CSV row.
+fn csv_error(e: csv::Error) -> AppError {
+    AppError::validation(format!("Invalid CSV: {}", e))
+}
+
+/// ## Writer of exported users enum.
+///
+/// # Variants
+/// - `Csv` - Writes a header row, then one row per user.
+/// - `Ndjson` - Writes one JSON object per line.
+pub enum UserWriter<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Ndjson(W),
+}
+
+impl<W: Write> UserWriter<W> {
+    /// ## Creates a new `UserWriter` instance.
+    ///
+    /// The CSV header is written right away, so an export
+    /// without users still names its columns.
+    ///
+    /// ## Returns
+    /// + `Result<UserWriter<W>, AppError>`
+    ///     - `Ok(UserWriter<W>)`: New writer.
+    ///     - `Err(AppError)`: If the header can not be written.
+    pub fn new(format: UserFormat, output: W) -> Result<Self, AppError> {
+        match format {
+            UserFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(output);
+                writer.write_record(CSV_COLUMNS).map_err(write_error)?;
+                Ok(UserWriter::Csv(Box::new(writer)))
+            }
+            UserFormat::Ndjson => Ok(UserWriter::Ndjson(output)),
+        }
+    }
+
+    /// ## Writes the record.
+    pub fn write(&mut self, record: &UserRecord) -> Result<(), AppError> {
+        match self {
+            UserWriter::Csv(writer) => writer.write_record(record.to_csv()).map_err(write_error),
+            UserWriter::Ndjson(output) => {
+                serde_json::to_writer(&mut *output, record).map_err(write_error)?;
+                output.write_all(b"\n").map_err(write_error)
+            }
+        }
+    }
+
+    /// ## Flushes the buffered rows.
+    pub fn flush(&mut self) -> Result<(), AppError> {
+        match self {
+            UserWriter::Csv(writer) => writer.flush().map_err(write_error),
+            UserWriter::Ndjson(output) => output.flush().map_err(write_error),
+        }
+    }
+}
+
+/// ## Builds the error of a failed export write.
+fn write_error(e: impl std::error::Error + 'static) -> AppError {
+    AppError::new(
+        ErrorKind::Io,
+        "Failed to write the users".to_string(),
+        Some(Box::new(e)),
+    )
+}
+
+/// ## Failed import row struct.
+///
+/// ## Fields
+/// + `line`: `u64` - Line of the row in the input.
+/// + `email`: `Option<String>` - Email of the row, if it was read.
+/// + `message`: `String` - Why the row was not imported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    pub line: u64,
+    pub email: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.email {
+            Some(email) => write!(f, "line {} ({}): {}", self.line, email, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+/// ## Result of an import struct.
+///
+/// ## Fields
+/// + `imported`: `usize` - Number of imported users.
+/// + `failed`: `Vec<RowError>` - Rows that were not imported.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub failed: Vec<RowError>,
+}
+
+/// ## Imports the users of the rows.
+///
+/// Every row is validated, its email, password hash and
+/// roles, before it is stored. Rows are stored one by one,
+/// each with its grants at once, so a failed row does not
+/// undo the rows before it.
+///
+/// ## Parameters
+/// - `rows`: `impl Iterator<Item = UserRow>` - Rows to import.
+/// - `users`: `&dyn UserRepo` - Users to import into.
+/// - `roles`: `&dyn RoleDefinitionRepo` - Defined roles.
+/// - `dry_run`: `bool` - Validates the rows without storing them.
+///
+/// ## Returns
+/// + `ImportSummary` - Imported and failed rows.
+pub async fn import(
+    rows: impl Iterator<Item = UserRow>,
+    users: &dyn UserRepo,
+    roles: &dyn RoleDefinitionRepo,
+    dry_run: bool,
+) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    let mut known = HashMap::new();
+
+    for (line, record) in rows {
+        let (email, result) = match record {
+            Ok(record) => {
+                let email = record.email.trim().to_lowercase();
+                let result = import_record(record, users, roles, &mut known, dry_run).await;
+                (Some(email), result)
+            }
+            Err(e) => (None, Err(e)),
+        };
+
+        match result {
+            Ok(()) => summary.imported += 1,
+            Err(e) => summary.failed.push(RowError {
+                line,
+                email,
+                message: e.message,
+            }),
+        }
+    }
+
+    summary
+}
+
+/// ## Validates and stores one record.
+async fn import_record(
+    record: UserRecord,
+    users: &dyn UserRepo,
+    roles: &dyn RoleDefinitionRepo,
+    known: &mut HashMap<String, bool>,
+    dry_run: bool,
+) -> Result<(), AppError> {
+    let email = record.email.trim().to_lowercase();
+    validate_email(&email)?;
+    let password = record
+        .password_hash
+        .as_deref()
+        .map(StoredHash::parse)
+        .transpose()?;
+
+    for role in &record.roles {
+        if !known.contains_key(role) {
+            let defined = is_builtin(role) || roles.get(role).await?.is_some();
+            known.insert(role.clone(), defined);
+        }
+        if known.get(role) != Some(&true) {
+            return Err(AppError::validation(format!(
+                "Role '{}' is not defined",
+                role
+            )));
+        }
+    }
+
+    if dry_run {
+        let existing = users.find_by_email(&email).await?;
+        return match existing {
+            Some(_) => Err(email_taken()),
+            None => Ok(()),
+        };
+    }
+
+    let user = NewUser {
+        email,
+        display_name: record.display_name,
+        org_id: record.org_id,
+        password,
+    };
+    users.import(user, record.disabled, &record.roles).await?;
+
+    Ok(())
+}
+
+/// ## Exports every user of the current tenant.
+///
+/// Users are read page by page, oldest first, so the
+/// export does not hold every user in memory.
+///
+/// ## Parameters
+/// - `users`: `&dyn UserRepo` - Users to export.
+/// - `writer`: `&mut UserWriter<W>` - Output of the records.
+/// - `password_hashes`: `bool` - Exports the password hashes too.
+///
+/// ## Returns
+/// + `Result<usize, AppError>`
+///     - `Ok(usize)`: Number of exported users.
+///     - `Err(AppError)`: If the users can not be read or written.
+pub async fn export<W: Write>(
+    users: &dyn UserRepo,
+    writer: &mut UserWriter<W>,
+    password_hashes: bool,
+) -> Result<usize, AppError> {
+    let sort = USER_SORTING.parse(None)?;
+    let mut after = None;
+    let mut exported = 0;
+
+    loop {
+        let window = Window {
+            sort,
+            after,
+            page: None,
+            per_page: EXPORT_PAGE,
+        };
+        let (mut page, _) = users
+            .list(&UserFilter::default(), &Scope::All, &window)
+            .await?;
+        let more = page.len() > EXPORT_PAGE as usize;
+        page.truncate(EXPORT_PAGE as usize);
+
+        for user in &page {
+            let password_hash = match password_hashes {
+                true => {
+                    let credentials = users.credentials(&user.email).await?;
+                    credentials.map(|(_, stored)| stored.hash)
+                }
+                false => None,
+            };
+            let roles = users.roles(user.id).await?;
+
+            writer.write(&UserRecord {
+                email: user.email.clone(),
+                display_name: user.display_name.clone(),
+                org_id: user.org_id,
+                disabled: user.disabled,
+                password_hash,
+                roles,
+            })?;
+            exported += 1;
+        }
+
+        after = match (more, page.last()) {
+            (true, Some(last)) => Some(Cursor {
+                sort: sort.name(),
+                key: last.sort_key(sort.field.name),
+                id: last.id_key(),
+            }),
+            _ => break,
+        };
+    }
+    writer.flush()?;
+
+    Ok(exported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::repo::memory::{MemoryRoleDefinitionRepo, MemoryUserRepo};
+
+    const BCRYPT_HASH: &str = "$2b$04$EGdrhbKUv8Oc9vGiXX0HQOxSg445d458Muh7DAHskb6QbtCvdxcie";
+
+    // Test checks if CSV and NDJSON rows are read with their line numbers
+    #[test]
+    fn test_read_records() {
+        let csv = "email,disabled,roles,org_id\n\
+                   ada@example.com,true,admin;,\n\
+                   bob@example.com,maybe,,\n";
+        let rows: Vec<UserRow> = read_records(UserFormat::Csv, csv.as_bytes())
+            .unwrap()
+            .collect();
+
+        assert_eq!(rows.len(), 2);
+        let ada = rows[0].1.as_ref().unwrap();
+        assert_eq!(ada.email, "ada@example.com");
+        assert!(ada.disabled);
+        assert_eq!(ada.roles, vec!["admin"]);
+        assert_eq!(ada.org_id, None);
+        assert_eq!(rows[1].0, 3);
+        assert!(rows[1].1.is_err());
+
+        let ndjson = "{\"email\":\"ada@example.com\"}\n\nnot json\n";
+        let rows: Vec<UserRow> = read_records(UserFormat::Ndjson, ndjson.as_bytes())
+            .unwrap()
+            .collect();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].1.as_ref().unwrap().email, "ada@example.com");
+        assert_eq!(rows[1].0, 3);
+        assert!(rows[1].1.is_err());
+
+        assert!(read_records(UserFormat::Csv, "name\nada\n".as_bytes()).is_err());
+    }
+
+    // Test checks if an import reports failed rows and an export writes the imported users back
+    #[tokio::test]
+    async fn test_import_export() {
+        let users = MemoryUserRepo::default();
+        let roles = MemoryRoleDefinitionRepo::default();
+        let input = format!(
+            "email,display_name,disabled,password_hash,roles\n\
+             Ada@Example.com,Ada,false,{},admin\n\
+             bob@example.com,,true,,\n\
+             eve@example.com,,false,plaintext,\n\
+             mallory@example.com,,false,,unknown\n\
+             ada@example.com,,false,,\n",
+            BCRYPT_HASH
+        );
+
+        let rows = read_records(UserFormat::Csv, input.as_bytes()).unwrap();
+        let summary = import(rows, &users, &roles, true).await;
+        assert_eq!(summary.imported, 3);
+        assert!(users.emails().await.unwrap().is_empty());
+
+        let rows = read_records(UserFormat::Csv, input.as_bytes()).unwrap();
+        let summary = import(rows, &users, &roles, false).await;
+        assert_eq!(summary.imported, 2);
+        let lines: Vec<u64> = summary.failed.iter().map(|row| row.line).collect();
+        assert_eq!(lines, vec![4, 5, 6]);
+        assert!(summary.failed[2].message.contains("already taken"));
+
+        let mut output = Vec::new();
+        let mut writer = UserWriter::new(UserFormat::Ndjson, &mut output).unwrap();
+        assert_eq!(export(&users, &mut writer, true).await.unwrap(), 2);
+        drop(writer);
+
+        let exported: Vec<UserRecord> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let ada = exported
+            .iter()
+            .find(|record| record.email == "ada@example.com")
+            .unwrap();
+        assert_eq!(ada.password_hash.as_deref(), Some(BCRYPT_HASH));
+        assert_eq!(ada.roles, vec!["admin"]);
+        let bob = exported
+            .iter()
+            .find(|record| record.email == "bob@example.com")
+            .unwrap();
+        assert!(bob.disabled);
+        assert_eq!(bob.password_hash, None);
+    }
+}
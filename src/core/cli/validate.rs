@@ -0,0 +1,297 @@
+//! Configuration validation module.
+//!
+//! Module runs every check the startup runs on the
+//! configuration and the environment, without connecting
+//! to anything: the sections, the environment variables
+//! and their types, the constraints between them and the
+//! security lint. Unlike the startup, all constraint
+//! violations are reported at once. The report is printed
+//! for humans or as JSON, for CI pipelines and pre-deploy
+//! checks, which read the result from the exit code.
+
+// Imports from external crates
+use clap::ValueEnum;
+use serde::Serialize;
+use std::error::Error;
+
+// Local imports
+use crate::core::config::{constraints, lint, read_config};
+use crate::core::env::{self, vars::EnvVar, vars::RequiredEnvVar};
+use crate::core::err::AppError;
+
+/// ## Output format of the validation enum.
+///
+/// # Variants
+/// - `Human` - Summary and one line per problem.
+/// - `Json` - `ValidationReport` as JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ValidateFormat {
+    Human,
+    Json,
+}
+
+/// ## Validation problem struct.
+///
+/// ## Fields
+/// + `stage`: `&'static str` - Check that found the problem:
+///   `configuration`, `environment`, `constraints` or `lint`.
+/// + `rule`: `Option<&'static str>` - Violated constraint or lint rule.
+/// + `keys`: `Vec<String>` - Configuration keys and environment
+///   variables involved.
+/// + `message`: `String` - Explanation for the operator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationProblem {
+    pub stage: &'static str,
+    pub rule: Option<&'static str>,
+    pub keys: Vec<String>,
+    pub message: String,
+}
+
+/// ## Validation report struct.
+///
+/// ## Fields
+/// + `file`: `String` - Validated configuration file.
+/// + `valid`: `bool` - No check found an error.
+/// + `errors`: `Vec<ValidationProblem>` - Problems that stop the start.
+/// + `warnings`: `Vec<ValidationProblem>` - Lint findings that do not,
+///   empty when the lint itself stops the start.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub file: String,
+    pub valid: bool,
+    pub errors: Vec<ValidationProblem>,
+    pub warnings: Vec<ValidationProblem>,
+}
+
+impl ValidationReport {
+    /// ## Renders the report for humans.
+    ///
+    /// ## Examples
+    /// ```
+    /// use axum_auth::core::cli::validate::ValidationReport;
+    ///
+    /// let report = ValidationReport {
+    ///     file: "config.toml".to_string(),
+    ///     valid: true,
+    ///     errors: vec![],
+    ///     warnings: vec![],
+    /// };
+    ///
+    /// assert_eq!(report.render(), "Configuration 'config.toml' is valid");
+    /// ```
+    pub fn render(&self) -> String {
+        let mut output = match self.valid {
+            true => format!("Configuration '{}' is valid", self.file),
+            false => format!(
+                "Configuration '{}' is invalid, {} error(s)",
+                self.file,
+                self.errors.len()
+            ),
+        };
+
+        let lines = self
+            .errors
+            .iter()
+            .map(|problem| ("error", problem))
+            .chain(self.warnings.iter().map(|problem| ("warning", problem)));
+        for (level, problem) in lines {
+            let rule = problem
+                .rule
+                .map(|rule| format!(" [{}]", rule))
+                .unwrap_or_default();
+            output.push_str(&format!(
+                "\n{} {}{}: {}",
+                level, problem.stage, rule, problem.message
+            ));
+        }
+
+        output
+    }
+}
+
+/// ## Runs the `config validate` subcommand.
+///
+/// Function prints the report to stdout.
+///
+/// ## Parameters
+/// - `config_path`: `&str` - Path to the configuration file.
+/// - `profile`: `Option<&str>` - Profile selected on the command line.
+/// - `format`: `ValidateFormat` - Output format.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the configuration is valid, the process exits 0.
+///     - `Err(AppError)`: If any check found an error, the process exits 1.
+pub fn validate(
+    config_path: &str,
+    profile: Option<&str>,
+    format: ValidateFormat,
+) -> Result<(), AppError> {
+    let report = check(config_path, profile);
+
+    match format {
+        ValidateFormat::Human => println!("{}", report.render()),
+        ValidateFormat::Json => {
+            let json = serde_json::to_string_pretty(&report).map_err(|e| {
+                AppError::internal("Failed to serialize the validation report").with_source(e)
+            })?;
+            println!("{}", json);
+        }
+    }
+
+    match report.valid {
+        true => Ok(()),
+        false => Err(AppError::config(format!(
+            "Configuration '{}' is invalid",
+            config_path
+        ))),
+    }
+}
+
+/// ## Runs every check.
+///
+/// Environment and constraints are only checked when the
+/// configuration loaded, the constraints also need the
+/// environment.
+///
+/// ## Parameters
+/// - `config_path`: `&str` - Path to the configuration file.
+/// - `profile`: `Option<&str>` - Profile selected on the command line.
+///
+/// ## Returns
+/// - `ValidationReport`: Problems found by the checks.
+pub fn check(config_path: &str, profile: Option<&str>) -> ValidationReport {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let problem = |stage, error: &AppError| ValidationProblem {
+        stage,
+        rule: None,
+        keys: Vec::new(),
+        message: describe(error),
+    };
+
+    match read_config(config_path, profile) {
+        Err(e) => errors.push(problem("configuration", &e)),
+        Ok(config) => {
+            let env = env::load(
+                &config.app.env_files(),
+                &config.app.prefix,
+                RequiredEnvVar::all(),
+                config.app.unknown_env_vars,
+            );
+
+            match &env {
+                Err(e) => errors.push(problem("environment", e)),
+                Ok(env) => errors.extend(constraints::violations(&config, env).into_iter().map(
+                    |violation| ValidationProblem {
+                        stage: "constraints",
+                        rule: Some(violation.rule),
+                        keys: violation.keys,
+                        message: violation.message,
+                    },
+                )),
+            }
+
+            let lookup = |var: RequiredEnvVar| {
+                env.as_ref()
+                    .ok()
+                    .and_then(|env| env.get(&var).ok().map(str::to_string))
+            };
+            // Blocking lint errors list the findings themselves
+            match lint::check(&config, lookup) {
+                Err(e) => errors.push(problem("lint", &e)),
+                Ok(report) => {
+                    warnings.extend(
+                        report
+                            .findings
+                            .into_iter()
+                            .map(|finding| ValidationProblem {
+                                stage: "lint",
+                                rule: Some(finding.rule),
+                                keys: Vec::new(),
+                                message: finding.message,
+                            }),
+                    )
+                }
+            }
+        }
+    }
+
+    ValidationReport {
+        file: config_path.to_string(),
+        valid: errors.is_empty(),
+        errors,
+        warnings,
+    }
+}
+
+/// ## Returns the message of the error and of its source.
+fn describe(error: &AppError) -> String {
+    match error.source() {
+        Some(source) => format!("{}: {}", error.message, source),
+        None => error.message.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::DEFAULT_CONFIG_FILE;
+
+    // Test checks if problems are listed after the summary.
+    #[test]
+    fn test_render() {
+        let report = ValidationReport {
+            file: "config.toml".to_string(),
+            valid: false,
+            errors: vec![ValidationProblem {
+                stage: "constraints",
+                rule: Some("sentry-dsn"),
+                keys: vec!["observability.sentry.enabled".to_string()],
+                message: "Sentry error tracking needs AXA_SENTRY_DSN".to_string(),
+            }],
+            warnings: vec![ValidationProblem {
+                stage: "lint",
+                rule: Some("cors-wildcard"),
+                keys: Vec::new(),
+                message: "Any origin may call the API".to_string(),
+            }],
+        };
+
+        assert_eq!(
+            report.render(),
+            "Configuration 'config.toml' is invalid, 1 error(s)\n\
+             error constraints [sentry-dsn]: Sentry error tracking needs AXA_SENTRY_DSN\n\
+             warning lint [cors-wildcard]: Any origin may call the API"
+        );
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["errors"][0]["keys"][0],
+            "observability.sentry.enabled"
+        );
+    }
+
+    // Test checks if a missing file is reported as a configuration error.
+    #[test]
+    fn test_check_missing_file() {
+        let report = check("./does-not-exist.toml", None);
+
+        assert!(!report.valid);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].stage, "configuration");
+        assert!(validate("./does-not-exist.toml", None, ValidateFormat::Json).is_err());
+        assert!(check(DEFAULT_CONFIG_FILE, None)
+            .errors
+            .iter()
+            .all(|e| e.stage != "configuration"));
+    }
+
+    // Test checks if the selected profile is applied when checking.
+    #[test]
+    fn test_check_profile() {
+        let report = check(DEFAULT_CONFIG_FILE, Some("undefined"));
+
+        assert!(!report.valid);
+        assert_eq!(report.errors[0].stage, "configuration");
+        assert!(report.errors[0].message.contains("Profile 'undefined'"));
+    }
+}
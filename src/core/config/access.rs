@@ -0,0 +1,284 @@
+//! Access settings module.
+//!
+//! IP reputation checks, and the login windows and
+//! country policies per role and tenant.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+};
+
+// Local imports
+use super::LoginThrottleSettings;
+use crate::core::auth::access::AccessPolicyEngine;
+use crate::core::err::AppError;
+use crate::core::types::AppType;
+
+/// ## IP reputation settings struct.
+///
+/// Scores range from 0 (clean) to 100 (known abuser),
+/// thresholds are inclusive.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Consult the reputation provider on
+///   login and registration.
+/// + `annotate_threshold`: `u8` - Score from which audit events
+///   are annotated with the score.
+/// + `captcha_threshold`: `u8` - Score from which a captcha is required.
+/// + `block_threshold`: `u8` - Score from which the request is blocked.
+/// + `cache_ttl`: `u64` - Seconds a score is cached for.
+/// + `fail_open`: `bool` - Allow the request when the provider fails.
+/// + `blocked`: `Vec<String>` - Addresses the built-in static list
+///   scores as known abusers.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::ReputationSettings;
+///
+/// let reputation_settings = ReputationSettings {
+///   enabled: true,
+///   block_threshold: 75,
+///   ..ReputationSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ReputationSettings {
+    pub enabled: bool,
+    pub annotate_threshold: u8,
+    pub captcha_threshold: u8,
+    pub block_threshold: u8,
+    pub cache_ttl: u64,
+    pub fail_open: bool,
+    pub blocked: Vec<String>,
+}
+
+impl Default for ReputationSettings {
+    fn default() -> Self {
+        ReputationSettings {
+            enabled: false,
+            annotate_threshold: 25,
+            captcha_threshold: 50,
+            block_threshold: 90,
+            cache_ttl: 3600,
+            fail_open: true,
+            blocked: Vec::new(),
+        }
+    }
+}
+
+impl ReputationSettings {
+    /// ## Validates the reputation settings when checks are enabled.
+    ///
+    /// ## Parameters
+    /// - `throttle`: `&LoginThrottleSettings` - CAPTCHA of the addresses
+    ///   scoring the captcha threshold.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If a blocked address is invalid or a CAPTCHA
+    ///      can be required without a CAPTCHA secret.
+    pub fn validate(&self, throttle: &LoginThrottleSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.blocked_addresses()?;
+        if self.captcha_threshold < self.block_threshold && throttle.captcha_secret.is_empty() {
+            return Err(AppError::config(
+                "login_throttle.captcha_secret is required when reputation.captcha_threshold is below reputation.block_threshold",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// ## Parses the blocked addresses.
+    ///
+    /// ## Returns
+    /// + `Result<HashSet<IpAddr>, AppError>`
+    ///    - `Ok(HashSet<IpAddr>)` - Blocked addresses.
+    ///    - `Err(AppError)` - If an address is invalid.
+    pub fn blocked_addresses(&self) -> Result<HashSet<IpAddr>, AppError> {
+        self.blocked
+            .iter()
+            .map(|address| {
+                address.parse().map_err(|_| {
+                    AppError::config(format!(
+                        "'{}' of reputation.blocked is not a valid address",
+                        address
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+/// ## Access policy settings struct.
+///
+/// ## Fields
+/// + `geoip_db_path`: `Option<String>` - Path to the MaxMind
+///   country database, required by country allowlists.
+/// + `roles`: `HashMap<String, AccessPolicySettings>` - Policies per role.
+/// + `tenants`: `HashMap<String, AccessPolicySettings>` - Policies per tenant.
+///
+/// ## Examples
+/// ```
+/// use std::collections::HashMap;
+/// use axum_auth::core::config::{AccessPolicySettings, AccessSettings};
+///
+/// let access_settings = AccessSettings {
+///   geoip_db_path: None,
+///   roles: HashMap::from([("admin".to_string(), AccessPolicySettings::default())]),
+///   tenants: HashMap::new(),
+/// };
+/// ```
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct AccessSettings {
+    pub geoip_db_path: Option<String>,
+    pub roles: HashMap<String, AccessPolicySettings>,
+    pub tenants: HashMap<String, AccessPolicySettings>,
+}
+
+impl AccessSettings {
+    /// ## Validates the access policies.
+    ///
+    /// Function verifies the GeoIP database path and
+    /// checks that every policy can be compiled.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the policies are valid.
+    ///    - `Err(AppError)` - If the path or any policy is invalid.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(path) = &self.geoip_db_path {
+            AppType::FilePath.verify(path)?;
+        }
+
+        AccessPolicyEngine::compile(self)?;
+
+        Ok(())
+    }
+}
+
+/// ## Access policy struct.
+///
+/// ## Fields
+/// + `allowed_countries`: `Option<Vec<String>>` - ISO 3166 country
+///   codes logins are allowed from, any country when not set.
+/// + `login_window`: `Option<LoginWindowSettings>` - Time window
+///   logins are allowed in, any time when not set.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct AccessPolicySettings {
+    pub allowed_countries: Option<Vec<String>>,
+    pub login_window: Option<LoginWindowSettings>,
+}
+
+/// ## Login window struct.
+///
+/// Windows where `end` is before `start` span midnight.
+///
+/// ## Fields
+/// + `start`: `String` - Start of the window, `HH:MM`.
+/// + `end`: `String` - End of the window (exclusive), `HH:MM`.
+/// + `days`: `Vec<String>` - Allowed week days (`mon`, `tue`, ...),
+///   every day when empty.
+/// + `utc_offset`: `String` - Offset of the window times, `+HH:MM`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+pub struct LoginWindowSettings {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub days: Vec<String>,
+    #[serde(default = "default_utc_offset")]
+    pub utc_offset: String,
+}
+
+/// Default offset of login windows.
+fn default_utc_offset() -> String {
+    "+00:00".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::err::ErrorKind;
+
+    // Test checks if a missing GeoIP database is rejected.
+    #[test]
+    fn test_access_validate_missing_geoip_db() {
+        let settings = AccessSettings {
+            geoip_db_path: Some("/path/to/missing.mmdb".to_string()),
+            ..AccessSettings::default()
+        };
+
+        let result = settings.validate().unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Validation);
+    }
+
+    // Test checks if an invalid login window is rejected.
+    #[test]
+    fn test_access_validate_invalid_window() {
+        let policy = AccessPolicySettings {
+            allowed_countries: None,
+            login_window: Some(LoginWindowSettings {
+                start: "25:00".to_string(),
+                end: "18:00".to_string(),
+                days: Vec::new(),
+                utc_offset: default_utc_offset(),
+            }),
+        };
+        let settings = AccessSettings {
+            roles: HashMap::from([("admin".to_string(), policy)]),
+            ..AccessSettings::default()
+        };
+
+        let result = settings.validate().unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Config);
+    }
+
+    // Test checks if blocked addresses are parsed and a CAPTCHA needs a secret.
+    #[test]
+    fn test_reputation_validate() {
+        let throttle = LoginThrottleSettings {
+            captcha_secret: "secret".into(),
+            ..LoginThrottleSettings::default()
+        };
+        let settings = ReputationSettings {
+            enabled: true,
+            blocked: vec!["203.0.113.7".to_string(), "2001:db8::1".to_string()],
+            ..ReputationSettings::default()
+        };
+
+        assert!(settings.validate(&throttle).is_ok());
+        assert_eq!(settings.blocked_addresses().unwrap().len(), 2);
+        assert!(settings
+            .validate(&LoginThrottleSettings::default())
+            .is_err());
+        assert!(ReputationSettings {
+            captcha_threshold: 90,
+            ..settings.clone()
+        }
+        .validate(&LoginThrottleSettings::default())
+        .is_ok());
+
+        let result = ReputationSettings {
+            blocked: vec!["203.0.113.0/24".to_string()],
+            ..settings
+        }
+        .validate(&throttle)
+        .unwrap_err();
+
+        assert_eq!(
+            result.message,
+            "'203.0.113.0/24' of reputation.blocked is not a valid address"
+        );
+    }
+}
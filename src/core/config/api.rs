@@ -0,0 +1,133 @@
+//! API settings module.
+//!
+//! OpenAPI document and the format of the error responses.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use crate::core::err::AppError;
+
+/// ## OpenAPI settings struct.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Serve the OpenAPI document at `/openapi.json`.
+/// + `swagger_ui`: `bool` - Serve the Swagger UI at `/docs`,
+///   requires `enabled`.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::OpenApiSettings;
+///
+/// let openapi_settings = OpenApiSettings {
+///   enabled: true,
+///   swagger_ui: true,
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct OpenApiSettings {
+    pub enabled: bool,
+    pub swagger_ui: bool,
+}
+
+impl Default for OpenApiSettings {
+    fn default() -> Self {
+        OpenApiSettings {
+            enabled: true,
+            swagger_ui: false,
+        }
+    }
+}
+
+/// ## Format of the error responses.
+///
+/// # Variants
+/// - `Json` - `{"error": {...}}` envelope with kind, code and message.
+/// - `Problem` - RFC 7807 `application/problem+json` details.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorFormat {
+    #[default]
+    Json,
+    Problem,
+}
+
+/// ## Error settings struct.
+///
+/// Clients asking for `application/problem+json` get
+/// problem details regardless of `format`, clients only
+/// accepting `application/json` get the envelope.
+///
+/// ## Fields
+/// + `format`: `ErrorFormat` - Format when the `Accept` header does not
+///   decide it, `json` or `problem`.
+/// + `type_base`: `String` - URI the error kind is appended to,
+///   giving the `type` of the problem details.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{ErrorFormat, ErrorSettings};
+///
+/// let error_settings = ErrorSettings {
+///   format: ErrorFormat::Problem,
+///   type_base: "https://docs.example.com/errors/".to_string(),
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ErrorSettings {
+    pub format: ErrorFormat,
+    pub type_base: String,
+}
+
+impl Default for ErrorSettings {
+    fn default() -> Self {
+        ErrorSettings {
+            format: ErrorFormat::Json,
+            type_base: "urn:axum-auth:error:".to_string(),
+        }
+    }
+}
+
+impl ErrorSettings {
+    /// ## Validates the error settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the types are not absolute URIs.
+    pub fn validate(&self) -> Result<(), AppError> {
+        match url::Url::parse(&format!("{}internal", self.type_base)) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(AppError::config(format!(
+                "errors.type_base '{}' has to be the start of an absolute URI",
+                self.type_base
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if problem types have to be absolute URIs.
+    #[test]
+    fn test_errors_validate() {
+        assert!(ErrorSettings::default().validate().is_ok());
+        assert!(ErrorSettings {
+            type_base: "https://docs.example.com/errors/".to_string(),
+            ..ErrorSettings::default()
+        }
+        .validate()
+        .is_ok());
+        assert!(ErrorSettings {
+            type_base: "/errors/".to_string(),
+            ..ErrorSettings::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Application settings module.
+//!
+//! Environment of the application, the prefix and files
+//! of its environment variables, and the watching of the
+//! configuration file.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Environment names treated as production.
+pub const PRODUCTION_ENVS: [&str; 2] = ["production", "prod"];
+
+/// Environment names treated as development, their mail is captured.
+pub const DEVELOPMENT_ENVS: [&str; 2] = ["development", "dev"];
+
+/// ## Application settings struct.
+///
+/// ## Fields
+/// + `env`: `String` - Application environment, mail of `development`
+///   and `dev` is captured at `/dev/mailbox` instead of sent.
+/// + `prefix`: `String` - Prefix for environment variables.
+/// + `env_file_path`: `String` - Path to the base environment file,
+///   see `env_files` for the files loaded on top of it.
+/// + `unknown_env_vars`: `UnknownEnvVars` - Handling of prefixed
+///   environment variables that are not known, `strict` by default.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{AppSettings, UnknownEnvVars};
+///
+/// let app_settings = AppSettings {
+///   env: "development".to_string(),
+///   prefix: "APP".to_string(),
+///   env_file_path: ".env".to_string(),
+///   unknown_env_vars: UnknownEnvVars::Strict,
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+pub struct AppSettings {
+    pub env: String,
+    pub prefix: String,
+    pub env_file_path: String,
+    #[serde(default)]
+    pub unknown_env_vars: UnknownEnvVars,
+}
+
+/// ## Handling of unknown environment variables.
+///
+/// Deploy platforms may inject extra variables with the
+/// application prefix, which are not an error there.
+///
+/// # Variants
+/// - `Strict` - Unknown variables fail the startup.
+/// - `Warn` - Unknown variables are logged.
+/// - `Ignore` - Unknown variables are ignored.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownEnvVars {
+    #[default]
+    Strict,
+    Warn,
+    Ignore,
+}
+
+impl AppSettings {
+    /// ## Checks if the application runs in production.
+    ///
+    /// ## Returns
+    /// - `bool`: If `env` is one of `PRODUCTION_ENVS`, ignoring case.
+    pub fn is_production(&self) -> bool {
+        PRODUCTION_ENVS
+            .iter()
+            .any(|env| self.env.eq_ignore_ascii_case(env))
+    }
+
+    /// ## Checks if the application runs in development.
+    ///
+    /// ## Returns
+    /// - `bool`: If `env` is one of `DEVELOPMENT_ENVS`, ignoring case.
+    pub fn is_development(&self) -> bool {
+        DEVELOPMENT_ENVS
+            .iter()
+            .any(|env| self.env.eq_ignore_ascii_case(env))
+    }
+
+    /// ## Returns the environment files to load, in order.
+    ///
+    /// Files are the base file, its `.local` variant and
+    /// its variant for the application environment. Later
+    /// files override earlier ones.
+    ///
+    /// ## Examples
+    /// ```
+    /// use axum_auth::core::config::{AppSettings, UnknownEnvVars};
+    ///
+    /// let app_settings = AppSettings {
+    ///   env: "dev".to_string(),
+    ///   prefix: "APP".to_string(),
+    ///   env_file_path: ".env".to_string(),
+    ///   unknown_env_vars: UnknownEnvVars::Strict,
+    /// };
+    ///
+    /// assert_eq!(app_settings.env_files(), [".env", ".env.local", ".env.dev"]);
+    /// ```
+    pub fn env_files(&self) -> Vec<String> {
+        vec![
+            self.env_file_path.clone(),
+            format!("{}.local", self.env_file_path),
+            format!("{}.{}", self.env_file_path, self.env.to_lowercase()),
+        ]
+    }
+}
+
+/// ## Configuration reload settings struct.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Watch the configuration file for changes.
+/// + `interval`: `u64` - Seconds between checks of the file.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::ReloadSettings;
+///
+/// let reload_settings = ReloadSettings {
+///   enabled: true,
+///   interval: 10,
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ReloadSettings {
+    pub enabled: bool,
+    pub interval: u64,
+}
+
+impl Default for ReloadSettings {
+    fn default() -> Self {
+        ReloadSettings {
+            enabled: true,
+            interval: 5,
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Auth settings module.
+//!
+//! Bearer or cookie session authentication, and the roles
+//! and scopes route patterns require.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Local imports
+use super::app::AppSettings;
+use crate::core::auth::authz::RoutePolicy;
+use crate::core::err::AppError;
+use crate::core::types::secret::Secret;
+
+/// Minimum length of the master secret of the cookies.
+pub const MIN_COOKIE_SECRET_LEN: usize = 32;
+
+/// ## Authentication mode of the browser clients.
+///
+/// # Variants
+/// - `Jwt` - Clients send the access token as a bearer token.
+/// - `Session` - Clients keep the session in a cookie, state-changing
+///   requests carry a CSRF token.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    #[default]
+    Jwt,
+    Session,
+}
+
+/// ## Format of the issued access tokens.
+///
+/// # Variants
+/// - `Jwt` - Signed tokens, verified with the published keys.
+/// - `Opaque` - Random references to claims stored by the server,
+///   resolved on every request and revocable at once.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenFormat {
+    #[default]
+    Jwt,
+    Opaque,
+}
+
+/// ## `SameSite` attribute of the cookies.
+///
+/// # Variants
+/// - `Strict` - Cookies are only sent with requests of the site.
+/// - `Lax` - Cookies are also sent when navigating to the site.
+/// - `None` - Cookies are sent with every request, requires
+///   secure cookies.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSite {
+    Strict,
+    #[default]
+    Lax,
+    None,
+}
+
+/// ## Authentication settings struct.
+///
+/// In `session` mode the session ID is read from a signed
+/// cookie and the double-submit CSRF check is enforced, the
+/// CSRF token is the HMAC of the session ID. Keys of the
+/// signed and encrypted cookies are derived from
+/// `cookie_secret`.
+///
+/// ## Fields
+/// + `mode`: `AuthMode` - How browser clients authenticate.
+/// + `session_cookie`: `String` - Cookie holding the session ID.
+/// + `csrf_cookie`: `String` - Cookie holding the CSRF token.
+/// + `csrf_header`: `String` - Header the CSRF token is echoed in.
+/// + `csrf_secret`: `Secret<String>` - Key of the CSRF tokens, derived from
+///   `cookie_secret` when empty.
+/// + `cookie_secret`: `Secret<String>` - Master secret of the cookie keys, a
+///   random secret is generated on every start when empty.
+/// + `cookie_secure`: `Option<bool>` - If the cookies are only sent over
+///   HTTPS, in production environments only when not set.
+/// + `cookie_same_site`: `SameSite` - `SameSite` attribute of the cookies.
+/// + `session_ttl`: `u64` - Seconds a login session is valid.
+/// + `token_format`: `TokenFormat` - Format of the issued access tokens.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{AuthMode, AuthSettings};
+///
+/// let auth_settings = AuthSettings {
+///   mode: AuthMode::Session,
+///   ..AuthSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct AuthSettings {
+    pub mode: AuthMode,
+    pub session_cookie: String,
+    pub csrf_cookie: String,
+    pub csrf_header: String,
+    pub csrf_secret: Secret<String>,
+    pub cookie_secret: Secret<String>,
+    pub cookie_secure: Option<bool>,
+    pub cookie_same_site: SameSite,
+    pub session_ttl: u64,
+    pub token_format: TokenFormat,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        AuthSettings {
+            mode: AuthMode::Jwt,
+            session_cookie: "session".to_string(),
+            csrf_cookie: "csrf_token".to_string(),
+            csrf_header: "x-csrf-token".to_string(),
+            csrf_secret: Secret::default(),
+            cookie_secret: Secret::default(),
+            cookie_secure: None,
+            cookie_same_site: SameSite::Lax,
+            session_ttl: 14 * 86400,
+            token_format: TokenFormat::Jwt,
+        }
+    }
+}
+
+impl AuthSettings {
+    /// ## Returns if the cookies are only sent over HTTPS.
+    ///
+    /// ## Parameters
+    /// - `app`: `&AppSettings` - Environment of the application.
+    ///
+    /// ## Returns
+    /// - `bool`: `cookie_secure`, or if the environment is a production
+    ///   environment when it is not set.
+    pub fn secure_cookies(&self, app: &AppSettings) -> bool {
+        self.cookie_secure.unwrap_or_else(|| app.is_production())
+    }
+
+    /// ## Validates the authentication settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If a cookie or header name is empty or
+    ///      has invalid characters, both cookies share a name, the
+    ///      cookie secret is too short or sessions do not last.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let is_token = |name: &str, extra: &[char]| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || extra.contains(&c))
+        };
+
+        for (field, name) in [
+            ("auth.session_cookie", &self.session_cookie),
+            ("auth.csrf_cookie", &self.csrf_cookie),
+        ] {
+            if !is_token(name, &['_', '.']) {
+                return Err(AppError::config(format!(
+                    "'{}' is not a valid cookie name for {}",
+                    name, field
+                )));
+            }
+        }
+        if self.session_cookie == self.csrf_cookie {
+            return Err(AppError::config(
+                "Session and CSRF cookies must have different names",
+            ));
+        }
+        if !is_token(&self.csrf_header, &[]) {
+            return Err(AppError::config(format!(
+                "'{}' is not a valid header name for auth.csrf_header",
+                self.csrf_header
+            )));
+        }
+        let secret = self.cookie_secret.expose();
+        if !secret.is_empty() && secret.len() < MIN_COOKIE_SECRET_LEN {
+            return Err(AppError::config(format!(
+                "auth.cookie_secret must be at least {} characters",
+                MIN_COOKIE_SECRET_LEN
+            )));
+        }
+        if self.session_ttl == 0 {
+            return Err(AppError::config(
+                "Sessions must be valid for at least 1 second",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Route authorization settings struct.
+///
+/// Maps route patterns to the roles and scopes of which the
+/// caller needs at least one, scopes are written
+/// `scope:<name>`. Segments of a pattern match literally,
+/// `*` and `:name` match any one segment, a trailing `*`
+/// matches the rest of the path. Every matching pattern
+/// has to be satisfied. Patterns match the path below the
+/// tenant prefix of the `path` tenancy strategy, `/metrics`,
+/// the key set and the OpenAPI document are not covered.
+///
+/// ## Fields
+/// + `routes`: `HashMap<String, Vec<String>>` - Requirements per pattern.
+///
+/// ## Examples
+/// ```
+/// use std::collections::HashMap;
+/// use axum_auth::core::config::AuthzSettings;
+///
+/// let authz_settings = AuthzSettings {
+///   routes: HashMap::from([("/admin/*".to_string(), vec!["admin".to_string()])]),
+/// };
+/// ```
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct AuthzSettings {
+    pub routes: HashMap<String, Vec<String>>,
+}
+
+impl AuthzSettings {
+    /// ## Validates the route authorization settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If every rule compiles.
+    ///    - `Err(AppError)` - If a pattern or requirement is invalid.
+    pub fn validate(&self) -> Result<(), AppError> {
+        RoutePolicy::compile(self)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::UnknownEnvVars;
+
+    // Test checks if the cookie and header names of the auth settings are validated.
+    #[test]
+    fn test_auth_validate() {
+        assert!(AuthSettings::default().validate().is_ok());
+
+        let shared = AuthSettings {
+            csrf_cookie: "session".to_string(),
+            ..AuthSettings::default()
+        };
+
+        assert_eq!(
+            shared.validate().unwrap_err().message,
+            "Session and CSRF cookies must have different names"
+        );
+
+        let spaced = AuthSettings {
+            session_cookie: "my session".to_string(),
+            ..AuthSettings::default()
+        };
+
+        assert!(spaced.validate().is_err());
+
+        let header = AuthSettings {
+            csrf_header: "x_csrf".to_string(),
+            ..AuthSettings::default()
+        };
+
+        assert!(header.validate().is_err());
+
+        let secret = AuthSettings {
+            cookie_secret: "too-short".into(),
+            ..AuthSettings::default()
+        };
+
+        assert!(secret.validate().is_err());
+    }
+
+    // Test checks if cookies are secure in production unless configured.
+    #[test]
+    fn test_auth_secure_cookies() {
+        let app = |env: &str| AppSettings {
+            env: env.to_string(),
+            prefix: "APP".to_string(),
+            env_file_path: ".env".to_string(),
+            unknown_env_vars: UnknownEnvVars::default(),
+        };
+        let (development, production) = (app("development"), app("Production"));
+        let insecure = AuthSettings {
+            cookie_secure: Some(false),
+            ..AuthSettings::default()
+        };
+
+        assert!(!AuthSettings::default().secure_cookies(&development));
+        assert!(AuthSettings::default().secure_cookies(&production));
+        assert!(!insecure.secure_cookies(&production));
+    }
+
+    // Test checks if the route patterns are compiled.
+    #[test]
+    fn test_authz_validate() {
+        let settings = AuthzSettings {
+            routes: HashMap::from([("/admin/*".to_string(), vec!["admin".to_string()])]),
+        };
+
+        assert!(AuthzSettings::default().validate().is_ok());
+        assert!(settings.validate().is_ok());
+
+        let relative = AuthzSettings {
+            routes: HashMap::from([("admin/*".to_string(), vec!["admin".to_string()])]),
+        };
+
+        assert_eq!(
+            relative.validate().unwrap_err().message,
+            "Route pattern 'admin/*' of authz.routes has to start with '/'"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Bootstrap settings module.
+//!
+//! Startup phases that are skipped and how long the
+//! startup waits for the cache.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use crate::core::err::AppError;
+
+/// ## Phase of the startup.
+///
+/// Phases run in the order of the variants.
+///
+/// # Variants
+/// - `Config` - Reads the configuration and environment and checks their constraints.
+/// - `Logging` - Applies the configured log level and logs the startup summary.
+/// - `Db` - Opens the connection pools.
+/// - `Cache` - Waits for the Redis health checks, needs the `redis` feature.
+/// - `Migrations` - Applies the pending database migrations.
+/// - `Server` - Builds the state and router of the HTTP server.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupPhase {
+    Config,
+    Logging,
+    Db,
+    Cache,
+    Migrations,
+    Server,
+}
+
+impl StartupPhase {
+    /// ## Returns the name used in the configuration and logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StartupPhase::Config => "config",
+            StartupPhase::Logging => "logging",
+            StartupPhase::Db => "db",
+            StartupPhase::Cache => "cache",
+            StartupPhase::Migrations => "migrations",
+            StartupPhase::Server => "server",
+        }
+    }
+
+    /// ## Checks if the phase may be skipped.
+    ///
+    /// Every other phase produces something the later
+    /// phases depend on.
+    pub fn is_skippable(&self) -> bool {
+        matches!(self, StartupPhase::Cache | StartupPhase::Migrations)
+    }
+}
+
+/// ## Startup settings struct.
+///
+/// ## Fields
+/// + `skip`: `Vec<StartupPhase>` - Phases not run on startup, only
+///   `cache` and `migrations` may be skipped.
+/// + `cache_timeout`: `u64` - Seconds the `cache` phase waits for the
+///   critical Redis health checks to come up.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{BootstrapSettings, StartupPhase};
+///
+/// let bootstrap_settings = BootstrapSettings {
+///   skip: vec![StartupPhase::Migrations],
+///   ..BootstrapSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct BootstrapSettings {
+    pub skip: Vec<StartupPhase>,
+    pub cache_timeout: u64,
+}
+
+impl Default for BootstrapSettings {
+    fn default() -> Self {
+        BootstrapSettings {
+            skip: Vec::new(),
+            cache_timeout: 30,
+        }
+    }
+}
+
+impl BootstrapSettings {
+    /// ## Validates the startup settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If a phase that can not be skipped is skipped.
+    pub fn validate(&self) -> Result<(), AppError> {
+        match self.skip.iter().find(|phase| !phase.is_skippable()) {
+            Some(phase) => Err(AppError::config(format!(
+                "Startup phase '{}' can not be skipped, only 'cache' and 'migrations' can",
+                phase.as_str()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// ## Checks if the phase is skipped.
+    pub fn skips(&self, phase: StartupPhase) -> bool {
+        self.skip.contains(&phase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if only the cache and migrations phases can be skipped.
+    #[test]
+    fn test_bootstrap_validate() {
+        let settings = BootstrapSettings {
+            skip: vec![StartupPhase::Cache, StartupPhase::Migrations],
+            ..BootstrapSettings::default()
+        };
+
+        assert!(settings.validate().is_ok());
+        assert!(settings.skips(StartupPhase::Migrations));
+        assert!(!BootstrapSettings::default().skips(StartupPhase::Migrations));
+        assert_eq!(
+            BootstrapSettings {
+                skip: vec![StartupPhase::Db],
+                ..BootstrapSettings::default()
+            }
+            .validate()
+            .unwrap_err()
+            .message,
+            "Startup phase 'db' can not be skipped, only 'cache' and 'migrations' can"
+        );
+    }
+}
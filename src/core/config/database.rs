@@ -0,0 +1,322 @@
+//! Database settings module.
+//!
+//! Size, timeouts and read replicas of the connection
+//! pool, and the retries of failed connections.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## Database pool settings struct.
+///
+/// Connection parameters and credentials are read from
+/// the `DB_*` environment variables, the section sizes
+/// the pool. Read replicas share the credentials of the
+/// primary, reads are spread over the healthy ones.
+///
+/// ## Fields
+/// + `max_connections`: `u32` - Connections the pool opens at most.
+/// + `min_connections`: `u32` - Idle connections the pool keeps open.
+/// + `connect_timeout`: `u64` - Seconds to wait for a connection
+///   before the query fails.
+/// + `idle_timeout`: `u64` - Seconds an idle connection is kept,
+///   `0` keeps them until `max_lifetime`.
+/// + `max_lifetime`: `u64` - Seconds a connection is reused, `0`
+///   reuses them forever.
+/// + `retry`: `DatabaseRetrySettings` - Retries while the database
+///   is not reachable at startup.
+/// + `replicas`: `Vec<String>` - Read replicas as `host` or
+///   `host:port`, IPv6 addresses in brackets.
+/// + `replica_check_interval`: `u64` - Seconds between the health
+///   checks of the replicas.
+/// + `query_metrics`: `bool` - Record the duration of every statement
+///   on `/metrics` and `/metrics/queries`.
+/// + `slow_query_threshold_ms`: `u64` - Milliseconds after which a
+///   statement is logged as a warning, `0` disables the warnings.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::DatabaseSettings;
+///
+/// let database_settings = DatabaseSettings {
+///   max_connections: 20,
+///   min_connections: 2,
+///   replicas: vec!["db-replica-1".to_string(), "10.0.0.12:5433".to_string()],
+///   ..DatabaseSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct DatabaseSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout: u64,
+    pub idle_timeout: u64,
+    pub max_lifetime: u64,
+    pub retry: DatabaseRetrySettings,
+    pub replicas: Vec<String>,
+    pub replica_check_interval: u64,
+    pub query_metrics: bool,
+    pub slow_query_threshold_ms: u64,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        DatabaseSettings {
+            max_connections: 10,
+            min_connections: 0,
+            connect_timeout: 5,
+            idle_timeout: 600,
+            max_lifetime: 1800,
+            retry: DatabaseRetrySettings::default(),
+            replicas: Vec::new(),
+            replica_check_interval: 10,
+            query_metrics: false,
+            slow_query_threshold_ms: 1000,
+        }
+    }
+}
+
+impl DatabaseSettings {
+    /// ## Validates the pool settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the pool can not open a connection,
+    ///      keeps more idle connections than it may open, never
+    ///      waits for a connection, a replica is invalid or listed
+    ///      twice, or the retries are invalid.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let invalid = |message: String| AppError::new(ErrorKind::Config, message, None);
+
+        if self.max_connections == 0 {
+            return Err(invalid(
+                "Database pool must allow at least 1 connection".to_string(),
+            ));
+        }
+        if self.min_connections > self.max_connections {
+            return Err(invalid(format!(
+                "Database pool keeps {} idle connections but opens at most {}",
+                self.min_connections, self.max_connections
+            )));
+        }
+        if self.connect_timeout == 0 {
+            return Err(invalid(
+                "Database connect timeout must be at least 1 second".to_string(),
+            ));
+        }
+
+        let replicas = self.replica_addresses()?;
+        for (index, replica) in replicas.iter().enumerate() {
+            if replicas[..index].contains(replica) {
+                return Err(invalid(format!(
+                    "Database replica '{}' is defined twice",
+                    self.replicas[index]
+                )));
+            }
+        }
+        if !replicas.is_empty() && self.replica_check_interval == 0 {
+            return Err(invalid(
+                "Database replica check interval must be at least 1 second".to_string(),
+            ));
+        }
+
+        self.retry.validate()
+    }
+
+    /// ## Returns the host and port of every replica.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<(String, Option<u16>)>, AppError>`
+    ///    - `Ok(Vec<(String, Option<u16>)>)` - Hosts and ports, the port
+    ///      is `None` if the replica uses the port of the primary.
+    ///    - `Err(AppError)` - If a host is empty or a port is invalid.
+    pub fn replica_addresses(&self) -> Result<Vec<(String, Option<u16>)>, AppError> {
+        self.replicas
+            .iter()
+            .map(|replica| {
+                let invalid = || {
+                    AppError::new(
+                        ErrorKind::Config,
+                        format!("Database replica '{}' is not a valid address", replica),
+                        None,
+                    )
+                };
+                let replica = replica.trim();
+                let (host, port) = match replica.strip_prefix('[') {
+                    Some(rest) => match rest.split_once(']') {
+                        Some((host, "")) => (host, None),
+                        Some((host, port)) => {
+                            (host, Some(port.strip_prefix(':').ok_or_else(invalid)?))
+                        }
+                        None => return Err(invalid()),
+                    },
+                    None => match replica.split_once(':') {
+                        Some((host, port)) => (host, Some(port)),
+                        None => (replica, None),
+                    },
+                };
+                let port = port
+                    .map(|port| port.parse::<u16>().map_err(|_| invalid()))
+                    .transpose()?;
+
+                match host.is_empty() {
+                    true => Err(invalid()),
+                    false => Ok((host.to_string(), port)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// ## Database retry settings struct.
+///
+/// Opening the pool is retried while the database is not
+/// reachable yet, e.g. while its container starts. Delays
+/// start at `backoff`, double with every attempt up to
+/// `max_backoff` and are shortened by a random share of
+/// up to `jitter`, so replicas don't retry in lockstep.
+///
+/// ## Fields
+/// + `max_attempts`: `u32` - Attempts before startup fails, `1`
+///   disables retries.
+/// + `backoff`: `u64` - Seconds before the first retry.
+/// + `max_backoff`: `u64` - Upper limit of the seconds between retries.
+/// + `jitter`: `f64` - Share of the delay that is randomized, `0.0`
+///   to `1.0`.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::DatabaseRetrySettings;
+///
+/// let retry_settings = DatabaseRetrySettings {
+///   max_attempts: 30,
+///   max_backoff: 10,
+///   ..DatabaseRetrySettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct DatabaseRetrySettings {
+    pub max_attempts: u32,
+    pub backoff: u64,
+    pub max_backoff: u64,
+    pub jitter: f64,
+}
+
+impl Default for DatabaseRetrySettings {
+    fn default() -> Self {
+        DatabaseRetrySettings {
+            max_attempts: 10,
+            backoff: 1,
+            max_backoff: 30,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl DatabaseRetrySettings {
+    /// ## Validates the retry settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If there is no attempt or the jitter
+    ///      is outside of `0.0` to `1.0`.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let invalid = |message: String| AppError::new(ErrorKind::Config, message, None);
+
+        if self.max_attempts == 0 {
+            return Err(invalid(
+                "Database connection needs at least 1 attempt".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(invalid(format!(
+                "Database retry jitter must be between 0 and 1, got {}",
+                self.jitter
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if pools that can not hand out connections are rejected.
+    #[test]
+    fn test_database_validate() {
+        let mut settings = DatabaseSettings::default();
+
+        assert!(settings.validate().is_ok());
+
+        settings.min_connections = 20;
+        let result = settings.validate().unwrap_err();
+        assert_eq!(
+            result.message,
+            "Database pool keeps 20 idle connections but opens at most 10"
+        );
+
+        settings.min_connections = 0;
+        settings.max_connections = 0;
+        assert!(settings.validate().is_err());
+
+        settings.max_connections = 10;
+        settings.connect_timeout = 0;
+        assert!(settings.validate().is_err());
+
+        settings.connect_timeout = 5;
+        settings.retry.jitter = 1.5;
+        let result = settings.validate().unwrap_err();
+        assert_eq!(
+            result.message,
+            "Database retry jitter must be between 0 and 1, got 1.5"
+        );
+
+        settings.retry.jitter = 0.0;
+        settings.retry.max_attempts = 0;
+        assert!(settings.validate().is_err());
+    }
+
+    // Test checks if replica addresses are parsed and duplicates are rejected.
+    #[test]
+    fn test_database_replicas() {
+        let mut settings = DatabaseSettings {
+            replicas: vec![
+                "db-replica-1".to_string(),
+                "10.0.0.12:5433".to_string(),
+                "[2001:db8::1]:5432".to_string(),
+            ],
+            ..DatabaseSettings::default()
+        };
+
+        assert!(settings.validate().is_ok());
+        assert_eq!(
+            settings.replica_addresses().unwrap(),
+            [
+                ("db-replica-1".to_string(), None),
+                ("10.0.0.12".to_string(), Some(5433)),
+                ("2001:db8::1".to_string(), Some(5432)),
+            ]
+        );
+
+        settings.replicas.push("db-replica-1".to_string());
+        let result = settings.validate().unwrap_err();
+        assert_eq!(
+            result.message,
+            "Database replica 'db-replica-1' is defined twice"
+        );
+
+        for replica in ["db:port", ":5432", "[::1"] {
+            settings.replicas = vec![replica.to_string()];
+            assert!(settings.validate().is_err(), "{}", replica);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! Event settings module.
+//!
+//! Domain events stored with the state change and their
+//! publishing to NATS or Kafka.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Local imports
+use super::jobs::JobSettings;
+use crate::api::webhooks::WebhookEvent;
+use crate::core::err::AppError;
+
+/// ## Outbox settings struct.
+///
+/// `user.created` and `login.succeeded` are stored in the
+/// `outbox_events` table by the transaction that creates
+/// the user or session. The worker relays them to the
+/// webhook deliveries and removes them in one transaction,
+/// so an event is never lost once the change committed.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Store the events and register the relay job.
+/// + `schedule`: `String` - Cron expression of the relay runs.
+/// + `batch_size`: `u32` - Events relayed per run.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::OutboxSettings;
+///
+/// let outbox_settings = OutboxSettings {
+///   enabled: true,
+///   batch_size: 500,
+///   ..OutboxSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct OutboxSettings {
+    pub enabled: bool,
+    pub schedule: String,
+    pub batch_size: u32,
+}
+
+impl Default for OutboxSettings {
+    fn default() -> Self {
+        OutboxSettings {
+            enabled: false,
+            schedule: "*/5 * * * * *".to_string(),
+            batch_size: 100,
+        }
+    }
+}
+
+impl OutboxSettings {
+    /// ## Validates the outbox settings when the outbox is enabled.
+    ///
+    /// ## Parameters
+    /// - `jobs`: `&JobSettings` - Timezone the schedule is evaluated in.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the schedule is invalid or the batch
+    ///      size is zero.
+    pub fn validate(&self, jobs: &JobSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        jobs.schedule("outbox.schedule", &self.schedule)?;
+        if self.batch_size == 0 {
+            return Err(AppError::config(
+                "Outbox batch size must be at least 1 event",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Message broker enum.
+///
+/// # Variants
+/// - `Nats` - Events are published to NATS subjects, needs the
+///   `nats` feature.
+/// - `Kafka` - Events are produced to Kafka topics, needs the
+///   `kafka` feature.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BrokerKind {
+    #[default]
+    Nats,
+    Kafka,
+}
+
+impl BrokerKind {
+    /// ## Returns the name of the broker.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrokerKind::Nats => "nats",
+            BrokerKind::Kafka => "kafka",
+        }
+    }
+
+    /// ## Checks if the broker was compiled in.
+    pub fn available(&self) -> bool {
+        match self {
+            BrokerKind::Nats => cfg!(feature = "nats"),
+            BrokerKind::Kafka => cfg!(feature = "kafka"),
+        }
+    }
+}
+
+/// ## Broker settings struct.
+///
+/// The outbox relay publishes every stored event to the
+/// broker, in the same run that hands it to the webhooks.
+/// Subject of an event is `prefix` followed by its name,
+/// unless `subjects` maps the event to another one.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Publish the events of the outbox.
+/// + `kind`: `BrokerKind` - NATS or Kafka.
+/// + `url`: `String` - NATS server, or comma separated Kafka bootstrap
+///   servers.
+/// + `timeout`: `u64` - Seconds the broker may take to accept an event.
+/// + `prefix`: `String` - Prefix of the default subjects.
+/// + `events`: `Vec<String>` - Events to publish, all when empty.
+/// + `subjects`: `HashMap<String, String>` - Subject or topic per event.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{BrokerKind, BrokerSettings};
+/// use std::collections::HashMap;
+///
+/// let broker_settings = BrokerSettings {
+///   enabled: true,
+///   kind: BrokerKind::Kafka,
+///   url: "kafka-1:9092,kafka-2:9092".to_string(),
+///   subjects: HashMap::from([("login.succeeded".to_string(), "auth-logins".to_string())]),
+///   ..BrokerSettings::default()
+/// };
+///
+/// assert_eq!(broker_settings.subject("user.created"), "auth.user.created");
+/// assert_eq!(broker_settings.subject("login.succeeded"), "auth-logins");
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct BrokerSettings {
+    pub enabled: bool,
+    pub kind: BrokerKind,
+    pub url: String,
+    pub timeout: u64,
+    pub prefix: String,
+    pub events: Vec<String>,
+    pub subjects: HashMap<String, String>,
+}
+
+impl Default for BrokerSettings {
+    fn default() -> Self {
+        BrokerSettings {
+            enabled: false,
+            kind: BrokerKind::Nats,
+            url: "nats://localhost:4222".to_string(),
+            timeout: 5,
+            prefix: "auth.".to_string(),
+            events: Vec::new(),
+            subjects: HashMap::new(),
+        }
+    }
+}
+
+impl BrokerSettings {
+    /// ## Checks if the event is published.
+    pub fn publishes(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|name| name == event)
+    }
+
+    /// ## Returns the subject or topic of the event.
+    pub fn subject(&self, event: &str) -> String {
+        match self.subjects.get(event) {
+            Some(subject) => subject.clone(),
+            None => format!("{}{}", self.prefix, event),
+        }
+    }
+
+    /// ## Validates the broker settings when publishing is enabled.
+    ///
+    /// ## Parameters
+    /// - `outbox`: `&OutboxSettings` - Outbox the events are relayed from.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the outbox is disabled, the URL is empty,
+    ///      the timeout is zero, an event or subject is invalid, or the
+    ///      broker was not compiled in.
+    pub fn validate(&self, outbox: &OutboxSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if !outbox.enabled {
+            return Err(AppError::config(
+                "Broker publishing requires the outbox to be enabled",
+            ));
+        }
+        if self.url.trim().is_empty() {
+            return Err(AppError::config("Broker URL must not be empty"));
+        }
+        if self.timeout == 0 {
+            return Err(AppError::config("Broker timeout must be at least 1 second"));
+        }
+
+        for event in self.events.iter().chain(self.subjects.keys()) {
+            event.parse::<WebhookEvent>().map_err(|_| {
+                AppError::config(format!("Broker publishes unknown event '{}'", event))
+            })?;
+        }
+        for event in WebhookEvent::ALL {
+            let subject = self.subject(event.as_str());
+            if subject.is_empty() || subject.chars().any(char::is_whitespace) {
+                return Err(AppError::config(format!(
+                    "Broker subject '{}' of event '{}' is not valid",
+                    subject, event
+                )));
+            }
+        }
+        if !self.kind.available() {
+            return Err(AppError::config(format!(
+                "Broker '{}' requires the '{}' feature",
+                self.kind.as_str(),
+                self.kind.as_str()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if the broker needs the outbox, known events and valid subjects.
+    #[test]
+    fn test_broker_validate() {
+        let outbox = OutboxSettings {
+            enabled: true,
+            ..OutboxSettings::default()
+        };
+        let settings = BrokerSettings {
+            enabled: true,
+            ..BrokerSettings::default()
+        };
+
+        assert!(BrokerSettings::default()
+            .validate(&OutboxSettings::default())
+            .is_ok());
+        assert!(settings.validate(&OutboxSettings::default()).is_err());
+        assert_eq!(
+            settings.validate(&outbox).is_ok(),
+            BrokerKind::Nats.available()
+        );
+
+        let unknown = BrokerSettings {
+            events: vec!["user.renamed".to_string()],
+            ..settings.clone()
+        };
+
+        assert_eq!(
+            unknown.validate(&outbox).unwrap_err().message,
+            "Broker publishes unknown event 'user.renamed'"
+        );
+
+        let subject = BrokerSettings {
+            subjects: HashMap::from([("user.created".to_string(), "auth users".to_string())]),
+            ..settings
+        };
+
+        assert_eq!(
+            subject.validate(&outbox).unwrap_err().message,
+            "Broker subject 'auth users' of event 'user.created' is not valid"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! gRPC settings module.
+//!
+//! Address of the gRPC token verification service.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use super::server::ServerSettings;
+use crate::core::err::AppError;
+
+/// ## gRPC settings struct.
+///
+/// The `axum_auth.v1.Auth` service verifies tokens for
+/// internal services on its own port, next to the HTTP
+/// server. It has no authentication of its own, so the
+/// port should only be reachable from internal networks.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Serve the gRPC service, needs the `grpc` feature.
+/// + `host`: `String` - Address the service binds to.
+/// + `port`: `u16` - Port of the service.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::GrpcSettings;
+///
+/// let grpc_settings = GrpcSettings {
+///   enabled: true,
+///   port: 50052,
+///   ..GrpcSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct GrpcSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for GrpcSettings {
+    fn default() -> Self {
+        GrpcSettings {
+            enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 50051,
+        }
+    }
+}
+
+impl GrpcSettings {
+    /// ## Validates the gRPC settings when the service is enabled.
+    ///
+    /// ## Parameters
+    /// - `server`: `&ServerSettings` - HTTP server the port is shared with.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the port is the one of the HTTP server,
+    ///      or the service was not compiled in.
+    pub fn validate(&self, server: &ServerSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.port == server.port {
+            return Err(AppError::config(format!(
+                "gRPC port {} is already used by the HTTP server",
+                self.port
+            )));
+        }
+        if !cfg!(feature = "grpc") {
+            return Err(AppError::config("gRPC service requires the 'grpc' feature"));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+//! Health settings module.
+//!
+//! Checks of the external integrations reported by the
+//! health endpoint.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::types::{AppType, HTTP_SCHEMES};
+
+/// ## Health check settings struct.
+///
+/// Database is always checked, `checks` adds the
+/// external integrations the deployment depends on.
+///
+/// ## Fields
+/// + `cache_ttl`: `u64` - Seconds the results of a run are reused.
+/// + `timeout`: `u64` - Seconds a check may take, unless overridden.
+/// + `checks`: `Vec<HealthCheckSettings>` - Checks of external integrations.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{HealthCheckKind, HealthCheckSettings, HealthSettings};
+///
+/// let health_settings = HealthSettings {
+///   checks: vec![HealthCheckSettings {
+///     name: "mail".to_string(),
+///     kind: HealthCheckKind::Smtp,
+///     target: "smtp.example.com:587".to_string(),
+///     timeout: None,
+///     critical: false,
+///   }],
+///   ..HealthSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct HealthSettings {
+    pub cache_ttl: u64,
+    pub timeout: u64,
+    pub checks: Vec<HealthCheckSettings>,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        HealthSettings {
+            cache_ttl: 10,
+            timeout: 2,
+            checks: Vec::new(),
+        }
+    }
+}
+
+impl HealthSettings {
+    /// ## Validates the health checks.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If every check is valid.
+    ///    - `Err(AppError)` - If a name is used twice or a
+    ///      target does not match the kind of the check.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let invalid = |message: String| AppError::new(ErrorKind::Config, message, None);
+
+        for (index, check) in self.checks.iter().enumerate() {
+            if self.checks[..index]
+                .iter()
+                .any(|other| other.name == check.name)
+            {
+                return Err(invalid(format!(
+                    "Health check '{}' is defined twice",
+                    check.name
+                )));
+            }
+
+            if cfg!(not(feature = "redis")) && check.kind == HealthCheckKind::Redis {
+                return Err(invalid(format!(
+                    "Health check '{}' needs the `redis` feature",
+                    check.name
+                )));
+            }
+
+            let valid = match check.kind {
+                HealthCheckKind::Smtp | HealthCheckKind::Redis => check
+                    .target
+                    .rsplit_once(':')
+                    .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok()),
+                HealthCheckKind::OidcDiscovery | HealthCheckKind::HttpHead => {
+                    AppType::Url(HTTP_SCHEMES).verify(&check.target).is_ok()
+                }
+            };
+            if !valid {
+                return Err(invalid(format!(
+                    "Health check '{}' has invalid target '{}'",
+                    check.name, check.target
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Health check of an external integration struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name shown in the health report.
+/// + `kind`: `HealthCheckKind` - How the integration is checked.
+/// + `target`: `String` - `host:port` for `smtp` and `redis`,
+///   URL for `oidc_discovery` and `http_head`.
+/// + `timeout`: `Option<u64>` - Seconds the check may take,
+///   `HealthSettings::timeout` when not set.
+/// + `critical`: `bool` - Failing check marks the service as down.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+pub struct HealthCheckSettings {
+    pub name: String,
+    pub kind: HealthCheckKind,
+    pub target: String,
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    #[serde(default = "default_critical")]
+    pub critical: bool,
+}
+
+/// Checks are critical unless configured otherwise.
+fn default_critical() -> bool {
+    true
+}
+
+/// ## Kind of a health check.
+///
+/// # Variants
+/// - `Smtp` - Connects and expects the `220` greeting.
+/// - `Redis` - Sends `PING` and expects a reply, needs the `redis` feature.
+/// - `OidcDiscovery` - Fetches the OpenID provider configuration.
+/// - `HttpHead` - Sends a `HEAD` request, server errors fail the check.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheckKind {
+    Smtp,
+    Redis,
+    OidcDiscovery,
+    HttpHead,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if health check targets are validated against their kind.
+    #[test]
+    fn test_health_validate_targets() {
+        let check = |kind, target: &str| HealthCheckSettings {
+            name: target.to_string(),
+            kind,
+            target: target.to_string(),
+            timeout: None,
+            critical: true,
+        };
+        let settings = |checks| HealthSettings {
+            checks,
+            ..HealthSettings::default()
+        };
+
+        assert!(settings(vec![
+            check(HealthCheckKind::Smtp, "localhost:25"),
+            check(HealthCheckKind::OidcDiscovery, "https://id.example.com"),
+        ])
+        .validate()
+        .is_ok());
+
+        for invalid in [
+            check(HealthCheckKind::Smtp, "smtp.example.com"),
+            check(HealthCheckKind::HttpHead, "ftp://hooks.example.com"),
+        ] {
+            let result = settings(vec![invalid]).validate().unwrap_err();
+
+            assert_eq!(result.kind, ErrorKind::Config);
+        }
+
+        let duplicate = check(HealthCheckKind::Smtp, "localhost:25");
+        let result = settings(vec![duplicate.clone(), duplicate])
+            .validate()
+            .unwrap_err();
+
+        assert_eq!(
+            result.message,
+            "Health check 'localhost:25' is defined twice"
+        );
+    }
+
+    // Test checks if Redis checks are accepted only with the redis feature.
+    #[test]
+    fn test_health_validate_redis_feature() {
+        let settings = HealthSettings {
+            checks: vec![HealthCheckSettings {
+                name: "cache".to_string(),
+                kind: HealthCheckKind::Redis,
+                target: "localhost:6379".to_string(),
+                timeout: None,
+                critical: true,
+            }],
+            ..HealthSettings::default()
+        };
+
+        assert_eq!(settings.validate().is_ok(), cfg!(feature = "redis"));
+    }
+}
@@ -0,0 +1,187 @@
+//! Identity provider settings module.
+//!
+//! Mapping of the claims of identity providers and the
+//! sync of directory groups to local roles.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Local imports
+use super::jobs::JobSettings;
+use crate::core::auth::claims::ClaimMapper;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::types::{secret::Secret, AppType, LDAP_SCHEMES};
+
+/// ## Claim mapping settings struct.
+///
+/// ## Fields
+/// + `providers`: `HashMap<String, ClaimMappingSettings>` - Mapping
+///   per identity provider, providers without a mapping use the
+///   standard OIDC claims.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ClaimsSettings {
+    pub providers: HashMap<String, ClaimMappingSettings>,
+}
+
+impl ClaimsSettings {
+    /// ## Validates the claim mappings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If every mapping compiles.
+    ///    - `Err(AppError)` - If a field is unknown or an expression is invalid.
+    pub fn validate(&self) -> Result<(), AppError> {
+        ClaimMapper::compile(self).map(|_| ())
+    }
+}
+
+/// ## Claim mapping of an identity provider struct.
+///
+/// See `core::auth::claims` for the expression syntax.
+///
+/// ## Fields
+/// + `fields`: `HashMap<String, String>` - Expression per local
+///   field (`email`, `display_name`, `picture`).
+/// + `roles`: `Option<String>` - Expression returning the groups
+///   of the user.
+/// + `role_map`: `HashMap<String, String>` - Local role per group,
+///   groups that are not listed grant no role.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ClaimMappingSettings {
+    pub fields: HashMap<String, String>,
+    pub roles: Option<String>,
+    pub role_map: HashMap<String, String>,
+}
+
+/// ## Directory group sync settings struct.
+///
+/// Sync runs as a job of the `worker` run mode and maps the
+/// directory groups of every user to local roles. Only roles
+/// granted by the sync are revoked by it.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Register the sync job in the worker.
+/// + `schedule`: `String` - Cron expression of the syncs.
+/// + `dry_run`: `bool` - Log the planned changes without applying them.
+/// + `role_map`: `HashMap<String, String>` - Local role per group,
+///   groups are matched by DN or by common name, ignoring case.
+/// + `ldap`: `LdapSettings` - Directory the groups are read from.
+///
+/// ## Examples
+/// ```
+/// use std::collections::HashMap;
+/// use axum_auth::core::config::{GroupSyncSettings, LdapSettings};
+///
+/// let group_sync_settings = GroupSyncSettings {
+///   enabled: true,
+///   dry_run: true,
+///   role_map: HashMap::from([("admins".to_string(), "admin".to_string())]),
+///   ldap: LdapSettings {
+///     url: "ldaps://ldap.example.com".to_string(),
+///     base_dn: "ou=people,dc=example,dc=com".to_string(),
+///     ..LdapSettings::default()
+///   },
+///   ..GroupSyncSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct GroupSyncSettings {
+    pub enabled: bool,
+    pub schedule: String,
+    pub dry_run: bool,
+    pub role_map: HashMap<String, String>,
+    pub ldap: LdapSettings,
+}
+
+impl Default for GroupSyncSettings {
+    fn default() -> Self {
+        GroupSyncSettings {
+            enabled: false,
+            schedule: "@hourly".to_string(),
+            dry_run: false,
+            role_map: HashMap::new(),
+            ldap: LdapSettings::default(),
+        }
+    }
+}
+
+impl GroupSyncSettings {
+    /// ## Validates the sync settings when the sync is enabled.
+    ///
+    /// ## Parameters
+    /// - `jobs`: `&JobSettings` - Timezone the schedule is evaluated in.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid or the sync is disabled.
+    ///    - `Err(AppError)` - If the schedule is invalid, the role map is
+    ///      empty or the directory settings are invalid.
+    pub fn validate(&self, jobs: &JobSettings) -> Result<(), AppError> {
+        let invalid = |message: &str| AppError::new(ErrorKind::Config, message.to_string(), None);
+
+        if !self.enabled {
+            return Ok(());
+        }
+        jobs.schedule("group_sync.schedule", &self.schedule)?;
+        if self.role_map.is_empty() {
+            return Err(invalid(
+                "Group sync requires at least one group in the role map",
+            ));
+        }
+        if AppType::Url(LDAP_SCHEMES).verify(&self.ldap.url).is_err() {
+            return Err(invalid(
+                "Group sync LDAP URL must be an ldap:// or ldaps:// URL",
+            ));
+        }
+        if self.ldap.base_dn.trim().is_empty() {
+            return Err(invalid("Group sync LDAP base DN must not be empty"));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## LDAP directory settings struct.
+///
+/// ## Fields
+/// + `url`: `String` - `ldap://` or `ldaps://` URL of the server.
+/// + `bind_dn`: `String` - DN to bind as, anonymous bind when empty.
+/// + `bind_password`: `Secret<String>` - Password of the bind DN.
+/// + `base_dn`: `String` - Subtree the users are searched in.
+/// + `user_filter`: `String` - Search filter of the users.
+/// + `email_attribute`: `String` - Attribute holding the email,
+///   users are matched to local users by it.
+/// + `group_attribute`: `String` - Attribute listing the groups.
+/// + `timeout`: `u64` - Seconds the connection and search may take.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct LdapSettings {
+    pub url: String,
+    pub bind_dn: String,
+    pub bind_password: Secret<String>,
+    pub base_dn: String,
+    pub user_filter: String,
+    pub email_attribute: String,
+    pub group_attribute: String,
+    pub timeout: u64,
+}
+
+impl Default for LdapSettings {
+    fn default() -> Self {
+        LdapSettings {
+            url: "ldap://localhost:389".to_string(),
+            bind_dn: String::new(),
+            bind_password: Secret::default(),
+            base_dn: String::new(),
+            user_filter: "(objectClass=person)".to_string(),
+            email_attribute: "mail".to_string(),
+            group_attribute: "memberOf".to_string(),
+            timeout: 30,
+        }
+    }
+}
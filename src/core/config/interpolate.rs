@@ -0,0 +1,268 @@
+//! Configuration interpolation module.
+//!
+//! String values may reference other sources with
+//! `${...}`, which is replaced when the configuration is
+//! loaded, so values are composed instead of duplicated:
+//!
+//! + `${ENV:NAME}` - Value of the process environment
+//!   variable `NAME`, the name is not prefixed.
+//! + `${file:/path}` - Contents of the file, without the
+//!   trailing line break, e.g. a mounted secret.
+//! + `${section.key}` - Value of another configuration key,
+//!   which may reference further keys.
+//!
+//! Values read from the environment and from files are
+//! used as they are. Write `$${` for a literal `${`.
+
+// Imports from external crates
+use config::{Value, ValueKind};
+use std::{env, fs};
+
+// Local imports
+use crate::core::err::AppError;
+
+/// Start of a reference.
+const REFERENCE_START: &str = "${";
+
+/// ## Replaces the `${...}` references of the configuration.
+///
+/// Function walks the tables and arrays of the value and
+/// replaces every reference in a string, references to
+/// configuration keys are resolved against the value as
+/// it was loaded.
+///
+/// ## Parameters
+/// - `value`: `&mut Value` - Loaded configuration.
+///
+/// ## Returns
+/// + `Result<usize, AppError>`
+///     - `Ok(usize)`: Number of interpolated values.
+///     - `Err(AppError)`: If a reference can not be resolved or
+///       references form a cycle.
+pub fn interpolate_values(value: &mut Value) -> Result<usize, AppError> {
+    let root = value.clone();
+
+    interpolate_at(value, "", &root)
+}
+
+fn interpolate_at(value: &mut Value, path: &str, root: &Value) -> Result<usize, AppError> {
+    match &mut value.kind {
+        ValueKind::String(text) if text.contains(REFERENCE_START) => {
+            *text = expand(text, path, root, &mut vec![path.to_string()])?;
+            Ok(1)
+        }
+        ValueKind::Table(table) => table.iter_mut().try_fold(0, |count, (name, value)| {
+            let path = match path.is_empty() {
+                true => name.clone(),
+                false => format!("{}.{}", path, name),
+            };
+            Ok(count + interpolate_at(value, &path, root)?)
+        }),
+        ValueKind::Array(array) => {
+            array
+                .iter_mut()
+                .enumerate()
+                .try_fold(0, |count, (index, value)| {
+                    Ok(count + interpolate_at(value, &format!("{}[{}]", path, index), root)?)
+                })
+        }
+        _ => Ok(0),
+    }
+}
+
+/// ## Replaces the references of a string (private).
+///
+/// ## Parameters
+/// - `text`: `&str` - String to expand.
+/// - `path`: `&str` - Key of the interpolated value, for errors.
+/// - `root`: `&Value` - Configuration the keys are looked up in.
+/// - `keys`: `&mut Vec<String>` - Keys being resolved, a key
+///   referenced again closes a cycle.
+fn expand(
+    text: &str,
+    path: &str,
+    root: &Value,
+    keys: &mut Vec<String>,
+) -> Result<String, AppError> {
+    let mut output = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(REFERENCE_START) {
+        if let Some(before) = rest[..start].strip_suffix('$') {
+            output.push_str(before);
+            output.push_str(REFERENCE_START);
+            rest = &rest[start + REFERENCE_START.len()..];
+            continue;
+        }
+        output.push_str(&rest[..start]);
+
+        let reference_start = start + REFERENCE_START.len();
+        let end = rest[reference_start..]
+            .find('}')
+            .map(|end| reference_start + end)
+            .ok_or_else(|| {
+                AppError::config(format!(
+                    "Configuration value '{}' has an unclosed '{}'",
+                    path, REFERENCE_START
+                ))
+            })?;
+        output.push_str(&resolve(&rest[reference_start..end], path, root, keys)?);
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// ## Returns the value of a reference (private).
+fn resolve(
+    reference: &str,
+    path: &str,
+    root: &Value,
+    keys: &mut Vec<String>,
+) -> Result<String, AppError> {
+    match reference.split_once(':') {
+        Some(("ENV", name)) => env::var(name).map_err(|_| {
+            AppError::config(format!(
+                "Configuration value '{}' references environment variable '{}', which is not set",
+                path, name
+            ))
+        }),
+        Some(("file", file)) => fs::read_to_string(file)
+            .map(|contents| contents.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|e| {
+                AppError::config(format!(
+                    "Configuration value '{}' references file '{}', which can not be read",
+                    path, file
+                ))
+                .with_source(e)
+            }),
+        Some((source, _)) => Err(AppError::config(format!(
+            "Configuration value '{}' references unknown source '{}', use 'ENV' or 'file'",
+            path, source
+        ))),
+        None => {
+            if keys.iter().any(|key| key == reference) {
+                keys.push(reference.to_string());
+                return Err(AppError::config(format!(
+                    "Configuration value '{}' has a reference cycle: {}",
+                    path,
+                    keys.join(" -> ")
+                )));
+            }
+
+            let text = lookup(root, reference).ok_or_else(|| {
+                AppError::config(format!(
+                    "Configuration value '{}' references '{}', which is not set to a string, number or boolean",
+                    path, reference
+                ))
+            })?;
+            keys.push(reference.to_string());
+            let expanded = expand(&text, path, root, keys)?;
+            keys.pop();
+
+            Ok(expanded)
+        }
+    }
+}
+
+/// ## Returns the scalar value at the dotted key (private).
+fn lookup(root: &Value, key: &str) -> Option<String> {
+    let value = key
+        .split('.')
+        .try_fold(root, |value, name| match &value.kind {
+            ValueKind::Table(table) => table.get(name),
+            _ => None,
+        })?;
+
+    match &value.kind {
+        ValueKind::String(text) => Some(text.clone()),
+        ValueKind::Boolean(_)
+        | ValueKind::I64(_)
+        | ValueKind::I128(_)
+        | ValueKind::U64(_)
+        | ValueKind::U128(_)
+        | ValueKind::Float(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{Config, File, FileFormat};
+
+    fn load(toml: &str) -> Value {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize::<Value>()
+            .unwrap()
+    }
+
+    fn get(value: &Value, key: &str) -> String {
+        lookup(value, key).unwrap()
+    }
+
+    // Test checks if environment, file and key references are replaced.
+    #[test]
+    fn test_interpolate_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret = dir.path().join("secret");
+        std::fs::write(&secret, "hunter2\n").unwrap();
+        std::env::set_var("INTERPOLATE_TEST_HOST", "db.internal");
+
+        let mut value = load(&format!(
+            r#"
+            [server]
+            host = "auth.example.com"
+            port = 8443
+            url = "https://${{server.host}}:${{server.port}}"
+            [db]
+            host = "${{ENV:INTERPOLATE_TEST_HOST}}"
+            password = "${{file:{}}}"
+            hosts = ["${{db.host}}", "$${{literal}}"]
+            "#,
+            secret.display()
+        ));
+
+        assert_eq!(interpolate_values(&mut value).unwrap(), 5);
+        assert_eq!(get(&value, "server.url"), "https://auth.example.com:8443");
+        assert_eq!(get(&value, "db.host"), "db.internal");
+        assert_eq!(get(&value, "db.password"), "hunter2");
+
+        let json = value.try_deserialize::<serde_json::Value>().unwrap();
+        assert_eq!(
+            json["db"]["hosts"],
+            serde_json::json!(["db.internal", "${literal}"])
+        );
+    }
+
+    // Test checks if unresolvable references and cycles are rejected.
+    #[test]
+    fn test_interpolate_errors() {
+        for (toml, message) in [
+            (r#"a = "${ENV:INTERPOLATE_TEST_UNSET}""#, "which is not set"),
+            (r#"a = "${file:./does-not-exist}""#, "can not be read"),
+            (r#"a = "${vault:db}""#, "unknown source 'vault'"),
+            (r#"a = "${b}""#, "which is not set"),
+            (
+                r#"a = "${b"
+                b = 1"#,
+                "unclosed",
+            ),
+            (r#"a = "${a}""#, "cycle: a -> a"),
+            (
+                r#"a = "${b}"
+                   b = "x${c}"
+                   c = "${a}""#,
+                "reference cycle",
+            ),
+        ] {
+            let err = interpolate_values(&mut load(toml)).unwrap_err();
+
+            assert!(err.message.contains(message), "{}", err.message);
+        }
+    }
+}
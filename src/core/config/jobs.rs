@@ -0,0 +1,169 @@
+//! Job settings module.
+//!
+//! Timezone and schedules of the background jobs.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::worker::cron::Schedule;
+
+/// ## Background job settings struct.
+///
+/// Jobs of the `worker` run mode run on cron expressions
+/// with five fields, or six with leading seconds. A
+/// `CRON_TZ=<zone>` prefix overrides the timezone of a
+/// single expression.
+///
+/// ## Fields
+/// + `timezone`: `String` - IANA timezone the schedules are evaluated in.
+/// + `cleanup`: `String` - Cron expression of the removal of expired
+///   and revoked tokens and of expired device codes.
+/// + `purge`: `String` - Cron expression of the removal of deleted users.
+/// + `purge_after`: `u64` - Seconds a deleted user is kept before it
+///   is removed.
+/// + `deletion`: `String` - Cron expression of the removal of accounts
+///   whose deletion grace period is over.
+/// + `deletion_grace`: `u64` - Seconds an account scheduled for
+///   deletion on `/me/delete` can still be restored.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::JobSettings;
+///
+/// let job_settings = JobSettings {
+///   timezone: "Europe/Berlin".to_string(),
+///   cleanup: "30 4 * * sun".to_string(),
+///   ..JobSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct JobSettings {
+    pub timezone: String,
+    pub cleanup: String,
+    pub purge: String,
+    pub purge_after: u64,
+    pub deletion: String,
+    pub deletion_grace: u64,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        JobSettings {
+            timezone: "UTC".to_string(),
+            cleanup: "0 3 * * *".to_string(),
+            purge: "0 4 * * *".to_string(),
+            purge_after: 2_592_000,
+            deletion: "0 * * * *".to_string(),
+            deletion_grace: 1_209_600,
+        }
+    }
+}
+
+impl JobSettings {
+    /// ## Parses the schedule of a job.
+    ///
+    /// ## Parameters
+    /// - `key`: `&str` - Configuration key of the expression, named in errors.
+    /// - `expression`: `&str` - Cron expression of the job.
+    ///
+    /// ## Returns
+    /// + `Result<Schedule, AppError>`
+    ///    - `Ok(Schedule)` - Schedule in the configured timezone.
+    ///    - `Err(AppError)` - If the expression or timezone is invalid.
+    pub fn schedule(&self, key: &str, expression: &str) -> Result<Schedule, AppError> {
+        Schedule::new(expression, &self.timezone)
+            .map_err(|e| AppError::new(ErrorKind::Config, format!("{}: {}", key, e.message), None))
+    }
+
+    /// ## Validates the timezone and the built-in schedules.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the timezone or a schedule is invalid,
+    ///      or the deletion grace period is zero.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.timezone.parse::<chrono_tz::Tz>().is_err() {
+            return Err(AppError::new(
+                ErrorKind::Config,
+                format!("Unknown job timezone '{}'", self.timezone),
+                None,
+            ));
+        }
+        self.schedule("jobs.cleanup", &self.cleanup)?;
+        self.schedule("jobs.purge", &self.purge)?;
+        self.schedule("jobs.deletion", &self.deletion)?;
+        if self.deletion_grace == 0 {
+            return Err(AppError::new(
+                ErrorKind::Config,
+                "Account deletion grace period must be at least 1 second".to_string(),
+                None,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::GroupSyncSettings;
+
+    // Test checks if job schedules are validated with the name of their key.
+    #[test]
+    fn test_jobs_validate() {
+        let jobs = JobSettings::default();
+
+        assert!(jobs.validate().is_ok());
+        assert_eq!(
+            JobSettings {
+                cleanup: "0 3 * *".to_string(),
+                ..JobSettings::default()
+            }
+            .validate()
+            .unwrap_err()
+            .message,
+            "jobs.cleanup: Invalid cron expression '0 3 * *': expected 5 or 6 fields, found 4"
+        );
+        assert!(JobSettings {
+            purge: "every night".to_string(),
+            ..JobSettings::default()
+        }
+        .validate()
+        .unwrap_err()
+        .message
+        .starts_with("jobs.purge: "));
+        assert!(JobSettings {
+            deletion_grace: 0,
+            ..JobSettings::default()
+        }
+        .validate()
+        .is_err());
+        assert_eq!(
+            JobSettings {
+                timezone: "Europe/Atlantis".to_string(),
+                ..JobSettings::default()
+            }
+            .validate()
+            .unwrap_err()
+            .message,
+            "Unknown job timezone 'Europe/Atlantis'"
+        );
+
+        let group_sync = GroupSyncSettings {
+            enabled: true,
+            schedule: "0 */0 * * *".to_string(),
+            ..GroupSyncSettings::default()
+        };
+
+        assert_eq!(
+            group_sync.validate(&jobs).unwrap_err().message,
+            "group_sync.schedule: Invalid cron expression '0 */0 * * *': invalid hour step '0'"
+        );
+    }
+}
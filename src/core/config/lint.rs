@@ -0,0 +1,652 @@
+//! Security lint module.
+//!
+//! Module inspects the loaded configuration and the
+//! environment for dangerous deployment combinations
+//! and scores the result at startup. Whether critical
+//! findings refuse the startup is set by `LintSettings`.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Local imports
+use super::{AppConfig, AuthMode, CORS_WILDCARD};
+use crate::core::env::vars::RequiredEnvVar;
+use crate::core::err::{AppError, ErrorKind};
+use crate::strings::postgres::{ALLOW_SSL, DISABLE_SSL, PREFER_SSL};
+
+/// Best possible lint score.
+pub const MAX_SCORE: u8 = 100;
+
+/// Database passwords shipped in examples and images.
+pub const DEFAULT_DB_PASSWORDS: [&str; 6] = [
+    "postgres", "password", "changeme", "admin", "root", "secret",
+];
+
+/// Secrets shipped in the documentation and the examples.
+pub const EXAMPLE_SECRETS: [&str; 3] = [
+    "0123456789abcdef0123456789abcdef",
+    "at-least-16-characters",
+    "0x4AAAAAAA-secret",
+];
+
+/// ## Severity of a lint finding.
+///
+/// # Variants
+/// - `Info` - Worth knowing, no action required.
+/// - `Warning` - Weakens the deployment.
+/// - `Critical` - Must not be deployed to production.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    /// ## Returns the points subtracted from the score.
+    pub fn penalty(&self) -> u8 {
+        match self {
+            Severity::Info => 0,
+            Severity::Warning => 10,
+            Severity::Critical => 30,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// ## Lint finding struct.
+///
+/// ## Fields
+/// + `rule`: `&'static str` - Identifier of the rule.
+/// + `severity`: `Severity` - Severity of the finding.
+/// + `message`: `String` - Explanation for the operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// ## Lint report struct.
+///
+/// ## Fields
+/// + `findings`: `Vec<Finding>` - Findings, most severe first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintReport {
+    pub findings: Vec<Finding>,
+}
+
+impl LintReport {
+    /// ## Returns the score of the deployment.
+    ///
+    /// ## Returns
+    /// - `u8`: `MAX_SCORE` minus the penalties of all findings, at least 0.
+    pub fn score(&self) -> u8 {
+        self.findings.iter().fold(MAX_SCORE, |score, finding| {
+            score.saturating_sub(finding.severity.penalty())
+        })
+    }
+
+    /// ## Checks if the report contains a critical finding.
+    pub fn has_critical(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Critical)
+    }
+
+    /// ## Adds a finding to the report.
+    fn push(&mut self, rule: &'static str, severity: Severity, message: String) {
+        self.findings.push(Finding {
+            rule,
+            severity,
+            message,
+        });
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Security lint score: {}/{}", self.score(), MAX_SCORE)?;
+
+        for finding in &self.findings {
+            write!(
+                f,
+                "\n  [{}] {}: {}",
+                finding.severity, finding.rule, finding.message
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Lints the deployment.
+///
+/// ## Parameters
+/// - `config`: `&AppConfig` - Loaded configuration.
+/// - `env`: `F` - Returns the value of an environment variable,
+///   `None` if it is not set.
+///
+/// ## Returns
+/// - `LintReport`: Findings, most severe first.
+pub fn lint<F>(config: &AppConfig, env: F) -> LintReport
+where
+    F: Fn(RequiredEnvVar) -> Option<String>,
+{
+    let production = config.app.is_production();
+    let mut report = LintReport::default();
+
+    // TLS
+    let tls = &config.server.tls;
+    if production && !tls.enabled {
+        report.push(
+            "tls-disabled",
+            Severity::Critical,
+            "TLS is disabled in production, credentials travel in plain text".to_string(),
+        );
+    }
+    if tls.enabled && tls.redirect_http_port.is_none() {
+        report.push(
+            "tls-no-redirect",
+            Severity::Info,
+            "Plain HTTP clients are not redirected to HTTPS".to_string(),
+        );
+    }
+
+    // Proxy protocol
+    let proxy = &config.server.proxy_protocol;
+    if proxy.enabled && proxy.trusted_proxies.is_empty() {
+        report.push(
+            "proxy-protocol-untrusted",
+            Severity::Warning,
+            "PROXY headers are accepted from any peer, clients can spoof their address".to_string(),
+        );
+    }
+
+    // CORS
+    let cors = &config.cors;
+    if cors
+        .allowed_origins
+        .iter()
+        .any(|origin| origin == CORS_WILDCARD)
+    {
+        match cors.allow_credentials {
+            true => report.push(
+                "cors-wildcard-credentials",
+                Severity::Critical,
+                "Any origin is allowed to send credentialed requests".to_string(),
+            ),
+            false => report.push(
+                "cors-wildcard-origin",
+                Severity::Warning,
+                "Any origin is allowed to call the API".to_string(),
+            ),
+        }
+    }
+    if production {
+        for origin in cors
+            .allowed_origins
+            .iter()
+            .filter(|origin| origin.starts_with("http://"))
+        {
+            report.push(
+                "cors-insecure-origin",
+                Severity::Warning,
+                format!("Origin '{}' is not served over HTTPS", origin),
+            );
+        }
+    }
+
+    // Cookies
+    let auth = &config.auth;
+    if auth.mode == AuthMode::Session && production {
+        if !auth.secure_cookies(&config.app) {
+            report.push(
+                "cookie-insecure",
+                Severity::Critical,
+                "Session cookies are sent over plain HTTP in production".to_string(),
+            );
+        }
+        if auth.cookie_secret.is_empty() {
+            report.push(
+                "cookie-random-secret",
+                Severity::Warning,
+                "Session cookies are signed with a random secret, sessions end on restart \
+                 and are rejected by other servers"
+                    .to_string(),
+            );
+        }
+    }
+
+    // Database
+    match env(RequiredEnvVar::DbPass) {
+        Some(password) if password.is_empty() => report.push(
+            "db-empty-password",
+            Severity::Critical,
+            "Database password is empty".to_string(),
+        ),
+        Some(password) if DEFAULT_DB_PASSWORDS.contains(&password.to_lowercase().as_str()) => {
+            report.push(
+                "db-default-password",
+                Severity::Critical,
+                "Database password is a well-known default".to_string(),
+            )
+        }
+        _ => {}
+    }
+    if let Some(ssl_mode) = env(RequiredEnvVar::DbSslMode) {
+        if production && [DISABLE_SSL, ALLOW_SSL, PREFER_SSL].contains(&ssl_mode.as_str()) {
+            report.push(
+                "db-ssl-optional",
+                Severity::Critical,
+                format!(
+                    "Database SSL mode '{}' allows unencrypted connections",
+                    ssl_mode
+                ),
+            );
+        }
+    }
+
+    // Signing key and secrets
+    if production && config.tokens.signing_key.is_empty() && !config.key_rotation.enabled {
+        report.push(
+            "signing-key-temporary",
+            Severity::Warning,
+            "Tokens are signed with a temporary key, they are rejected after a restart \
+             and by other servers"
+                .to_string(),
+        );
+    }
+    let secrets = [
+        ("auth.cookie_secret", &config.auth.cookie_secret),
+        ("auth.csrf_secret", &config.auth.csrf_secret),
+        (
+            "login_throttle.captcha_secret",
+            &config.login_throttle.captcha_secret,
+        ),
+    ]
+    .into_iter()
+    .map(|(name, secret)| (name.to_string(), secret))
+    .chain(config.webhooks.endpoints.iter().map(|endpoint| {
+        let name = format!("webhooks.endpoints.{}.secret", endpoint.name);
+        (name, &endpoint.secret)
+    }));
+    for (name, _) in secrets.filter(|(_, secret)| is_example_secret(secret.as_str())) {
+        report.push(
+            "secret-example",
+            Severity::Critical,
+            format!("'{}' is a well-known example value", name),
+        );
+    }
+
+    // Rate limits
+    let magic_link = &config.magic_link;
+    let limits = [
+        ("magic_link.rate_limit", magic_link.rate_limit),
+        ("magic_link.client_rate_limit", magic_link.client_rate_limit),
+    ];
+    for (name, _) in limits
+        .iter()
+        .filter(|(_, rule)| magic_link.enabled && (rule.limit == 0 || rule.window == 0))
+    {
+        report.push(
+            "rate-limit-disabled",
+            Severity::Critical,
+            format!("'{}' is 0, magic link requests are not limited", name),
+        );
+    }
+    let throttle = &config.login_throttle;
+    if production && (!throttle.enabled || throttle.max_delay == 0) {
+        report.push(
+            "login-throttle-disabled",
+            Severity::Warning,
+            "Failed logins are not throttled, passwords and tokens can be guessed quickly"
+                .to_string(),
+        );
+    }
+
+    // IP reputation
+    if production && !config.reputation.enabled {
+        report.push(
+            "reputation-disabled",
+            Severity::Warning,
+            "Logins from known abusive addresses are not checked".to_string(),
+        );
+    }
+
+    report
+        .findings
+        .sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+
+    report
+}
+
+/// ## Checks if the secret is a default or an example value.
+fn is_example_secret(secret: &str) -> bool {
+    let lowercase = secret.to_lowercase();
+
+    DEFAULT_DB_PASSWORDS.contains(&lowercase.as_str())
+        || EXAMPLE_SECRETS
+            .iter()
+            .any(|example| example.eq_ignore_ascii_case(secret))
+}
+
+/// ## Lints the deployment and enforces the result.
+///
+/// Function refuses to start when `[lint] deny_critical`
+/// is set, the application runs in production and the
+/// report contains a critical finding.
+///
+/// ## Parameters
+/// - `config`: `&AppConfig` - Loaded configuration.
+/// - `env`: `F` - Returns the value of an environment variable.
+///
+/// ## Returns
+/// + `Result<LintReport, AppError>`
+///     - `Ok(LintReport)`: If the application may start.
+///     - `Err(AppError)`: If a critical finding blocks the start.
+pub fn check<F>(config: &AppConfig, env: F) -> Result<LintReport, AppError>
+where
+    F: Fn(RequiredEnvVar) -> Option<String>,
+{
+    let report = lint(config, env);
+
+    if config.lint.deny_critical && config.app.is_production() && report.has_critical() {
+        return Err(AppError::new(
+            ErrorKind::Config,
+            format!("Refusing to start in production\n{}", report),
+            None,
+        ));
+    }
+
+    Ok(report)
+}
+
+/// ## Startup security lint settings struct.
+///
+/// ## Fields
+/// + `deny_critical`: `bool` - Refuse to start in production when
+///   the lint reports a critical finding.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::LintSettings;
+///
+/// let lint_settings = LintSettings {
+///   deny_critical: true,
+/// };
+/// ```
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct LintSettings {
+    pub deny_critical: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::{
+        AccessLogSettings, AccessSettings, AppSettings, AuthSettings, AuthzSettings,
+        BootstrapSettings, BrokerSettings, ClaimsSettings, ClientIpSettings, CorsSettings,
+        CrashReportSettings, CredentialExpirySettings, DatabaseSettings, DeviceCodeSettings,
+        ErrorSettings, GroupSyncSettings, GrpcSettings, HealthSettings, IpFilterSettings,
+        JobSettings, JournalSettings, KeyRotationSettings, LintSettings, LocalizationSettings,
+        LoggingSettings, LoginAnomalySettings, LoginThrottleSettings, MagicLinkSettings,
+        MailSettings, MetricsSettings, ObservabilitySettings, OpenApiSettings, OutboxSettings,
+        PasswordSettings, ReloadSettings, ReputationSettings, ServerSettings, StaticFilesSettings,
+        TenancySettings, TokenSettings, TraceSettings, UiSettings, UnknownEnvVars, WebhookSettings,
+    };
+
+    fn config(env: &str) -> AppConfig {
+        AppConfig {
+            app: AppSettings {
+                env: env.to_string(),
+                prefix: "APP_".to_string(),
+                env_file_path: ".env".to_string(),
+                unknown_env_vars: UnknownEnvVars::default(),
+            },
+            server: ServerSettings::default(),
+            database: DatabaseSettings::default(),
+            reputation: ReputationSettings::default(),
+            access: AccessSettings::default(),
+            cors: CorsSettings::default(),
+            ip_filter: IpFilterSettings::default(),
+            client_ip: ClientIpSettings::default(),
+            lint: LintSettings::default(),
+            openapi: OpenApiSettings::default(),
+            claims: ClaimsSettings::default(),
+            journal: JournalSettings::default(),
+            health: HealthSettings::default(),
+            password: PasswordSettings::default(),
+            logging: LoggingSettings::default(),
+            reload: ReloadSettings::default(),
+            trace: TraceSettings::default(),
+            group_sync: GroupSyncSettings::default(),
+            webhooks: WebhookSettings::default(),
+            metrics: MetricsSettings::default(),
+            localization: LocalizationSettings::default(),
+            jobs: JobSettings::default(),
+            tokens: TokenSettings::default(),
+            auth: AuthSettings::default(),
+            access_log: AccessLogSettings::default(),
+            magic_link: MagicLinkSettings::default(),
+            login_anomalies: LoginAnomalySettings::default(),
+            device_code: DeviceCodeSettings::default(),
+            key_rotation: KeyRotationSettings::default(),
+            tenancy: TenancySettings::default(),
+            authz: AuthzSettings::default(),
+            outbox: OutboxSettings::default(),
+            broker: BrokerSettings::default(),
+            grpc: GrpcSettings::default(),
+            ui: UiSettings::default(),
+            static_files: StaticFilesSettings::default(),
+            errors: ErrorSettings::default(),
+            login_throttle: LoginThrottleSettings::default(),
+            credential_expiry: CredentialExpirySettings::default(),
+            mail: MailSettings::default(),
+            crash_reports: CrashReportSettings::default(),
+            observability: ObservabilitySettings::default(),
+            bootstrap: BootstrapSettings::default(),
+        }
+    }
+
+    fn no_env(_: RequiredEnvVar) -> Option<String> {
+        None
+    }
+
+    fn rules(report: &LintReport) -> Vec<&'static str> {
+        report.findings.iter().map(|finding| finding.rule).collect()
+    }
+
+    // Test checks if a development deployment with defaults is clean.
+    #[test]
+    fn test_lint_development_defaults() {
+        let report = lint(&config("development"), no_env);
+
+        assert!(report.findings.is_empty());
+        assert_eq!(report.score(), MAX_SCORE);
+    }
+
+    // Test checks if production without TLS is critical.
+    #[test]
+    fn test_lint_production_without_tls() {
+        let report = lint(&config("Production"), no_env);
+
+        assert_eq!(
+            rules(&report),
+            [
+                "tls-disabled",
+                "signing-key-temporary",
+                "login-throttle-disabled",
+                "reputation-disabled"
+            ]
+        );
+        assert!(report.has_critical());
+        assert_eq!(report.score(), 40);
+    }
+
+    // Test checks if wildcard CORS origins are reported.
+    #[test]
+    fn test_lint_cors_wildcard() {
+        let mut config = config("development");
+        config.cors.allowed_origins = vec!["*".to_string()];
+
+        assert_eq!(rules(&lint(&config, no_env)), ["cors-wildcard-origin"]);
+
+        config.cors.allow_credentials = true;
+
+        assert_eq!(rules(&lint(&config, no_env)), ["cors-wildcard-credentials"]);
+    }
+
+    // Test checks if the proxy protocol without trusted proxies is reported.
+    #[test]
+    fn test_lint_proxy_protocol() {
+        let mut config = config("development");
+        config.server.proxy_protocol.enabled = true;
+
+        assert_eq!(rules(&lint(&config, no_env)), ["proxy-protocol-untrusted"]);
+
+        config.server.proxy_protocol.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+
+        assert!(lint(&config, no_env).findings.is_empty());
+    }
+
+    // Test checks if insecure and randomly signed session cookies are reported in production.
+    #[test]
+    fn test_lint_cookies() {
+        let mut config = config("prod");
+        config.server.tls.enabled = true;
+        config.reputation.enabled = true;
+        config.key_rotation.enabled = true;
+        config.login_throttle.enabled = true;
+        config.auth.mode = AuthMode::Session;
+        config.auth.cookie_secure = Some(false);
+
+        assert_eq!(
+            rules(&lint(&config, no_env)),
+            ["cookie-insecure", "cookie-random-secret", "tls-no-redirect"]
+        );
+
+        config.auth.cookie_secure = None;
+        config.auth.cookie_secret = "f3c9a07e5b2d48e1a6c0d9b7e4f21a85".into();
+
+        assert_eq!(rules(&lint(&config, no_env)), ["tls-no-redirect"]);
+    }
+
+    // Test checks if default database passwords and optional SSL are reported.
+    #[test]
+    fn test_lint_database() {
+        let env = |var: RequiredEnvVar| match var {
+            RequiredEnvVar::DbPass => Some("Postgres".to_string()),
+            RequiredEnvVar::DbSslMode => Some("prefer".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(rules(&lint(&config("dev"), env)), ["db-default-password"]);
+        assert!(rules(&lint(&config("prod"), env)).contains(&"db-ssl-optional"));
+    }
+
+    // Test checks if temporary signing keys and example secrets are reported.
+    #[test]
+    fn test_lint_secrets() {
+        let mut config = config("prod");
+        config.server.tls.enabled = true;
+        config.server.tls.redirect_http_port = Some(8080);
+        config.reputation.enabled = true;
+        config.login_throttle.enabled = true;
+
+        assert_eq!(rules(&lint(&config, no_env)), ["signing-key-temporary"]);
+
+        config.tokens.signing_key = "/etc/axum-auth/signing.pem".to_string();
+        config.auth.cookie_secret = "ChangeMe".into();
+        config.login_throttle.captcha_secret = "0x4AAAAAAA-secret".into();
+
+        let report = lint(&config, no_env);
+        assert_eq!(rules(&report), ["secret-example", "secret-example"]);
+        assert_eq!(
+            report.findings[0].message,
+            "'auth.cookie_secret' is a well-known example value"
+        );
+
+        config.auth.cookie_secret = "f3c9a07e5b2d48e1a6c0d9b7e4f21a85".into();
+        config.login_throttle.captcha_secret = Default::default();
+
+        assert!(lint(&config, no_env).findings.is_empty());
+    }
+
+    // Test checks if empty rate limits and a disabled login throttle are reported.
+    #[test]
+    fn test_lint_rate_limits() {
+        let mut config = config("development");
+        config.magic_link.enabled = true;
+        config.magic_link.client_rate_limit.limit = 0;
+
+        let report = lint(&config, no_env);
+        assert_eq!(rules(&report), ["rate-limit-disabled"]);
+        assert_eq!(
+            report.findings[0].message,
+            "'magic_link.client_rate_limit' is 0, magic link requests are not limited"
+        );
+
+        config.app.env = "prod".to_string();
+        config.server.tls.enabled = true;
+        config.server.tls.redirect_http_port = Some(8080);
+        config.reputation.enabled = true;
+        config.key_rotation.enabled = true;
+        config.magic_link.client_rate_limit.limit = 20;
+        config.login_throttle.enabled = true;
+        config.login_throttle.max_delay = 0;
+
+        assert_eq!(rules(&lint(&config, no_env)), ["login-throttle-disabled"]);
+    }
+
+    // Test checks if critical findings block the start only when denied in production.
+    #[test]
+    fn test_check_deny_critical() {
+        let mut production = config("prod");
+
+        assert!(check(&production, no_env).is_ok());
+
+        production.lint.deny_critical = true;
+        let result = check(&production, no_env).unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Config);
+
+        let mut development = config("development");
+        development.lint.deny_critical = true;
+        development.cors.allowed_origins = vec!["*".to_string()];
+        development.cors.allow_credentials = true;
+
+        assert!(check(&development, no_env).is_ok());
+    }
+
+    // Test checks if the report lists the score and findings.
+    #[test]
+    fn test_report_display() {
+        let report = lint(&config("prod"), no_env);
+
+        assert_eq!(
+            report.to_string(),
+            "Security lint score: 40/100\n  \
+             [critical] tls-disabled: TLS is disabled in production, credentials travel in plain text\n  \
+             [warning] signing-key-temporary: Tokens are signed with a temporary key, they are \
+             rejected after a restart and by other servers\n  \
+             [warning] login-throttle-disabled: Failed logins are not throttled, passwords and \
+             tokens can be guessed quickly\n  \
+             [warning] reputation-disabled: Logins from known abusive addresses are not checked"
+        );
+    }
+}
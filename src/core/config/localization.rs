@@ -0,0 +1,97 @@
+//! Localization settings module.
+//!
+//! Localized display fields of timestamps and numbers.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::locale::{Locale, LOCALES};
+
+/// ## Response localization settings struct.
+///
+/// Timestamps are always sent as RFC 3339, localization
+/// adds `*_display` fields formatted for the caller.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Add the localized display fields to responses.
+/// + `default_locale`: `String` - Locale when `Accept-Language` names
+///   no supported language, e.g. `en-US`.
+/// + `default_timezone`: `String` - IANA timezone of callers without a
+///   tenant timezone, e.g. `UTC`.
+/// + `tenants`: `HashMap<String, String>` - IANA timezone per organization ID.
+///
+/// ## Examples
+/// ```
+/// use std::collections::HashMap;
+/// use axum_auth::core::config::LocalizationSettings;
+///
+/// let localization_settings = LocalizationSettings {
+///   enabled: true,
+///   tenants: HashMap::from([(
+///     "6f1c3a52-8b1d-4f0e-9c55-2d7f3b9e0a41".to_string(),
+///     "Europe/Berlin".to_string(),
+///   )]),
+///   ..LocalizationSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct LocalizationSettings {
+    pub enabled: bool,
+    pub default_locale: String,
+    pub default_timezone: String,
+    pub tenants: HashMap<String, String>,
+}
+
+impl Default for LocalizationSettings {
+    fn default() -> Self {
+        LocalizationSettings {
+            enabled: false,
+            default_locale: "en-US".to_string(),
+            default_timezone: "UTC".to_string(),
+            tenants: HashMap::new(),
+        }
+    }
+}
+
+impl LocalizationSettings {
+    /// ## Validates the locale and timezones.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the locale is not supported, a timezone
+    ///      is unknown or a tenant is not an organization ID.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let invalid = |message: String| AppError::new(ErrorKind::Config, message, None);
+
+        if Locale::find(&self.default_locale).is_none() {
+            return Err(invalid(format!(
+                "Default locale '{}' is not supported, use one of: {}",
+                self.default_locale,
+                LOCALES.map(|locale| locale.tag).join(", ")
+            )));
+        }
+
+        let timezones = std::iter::once(&self.default_timezone).chain(self.tenants.values());
+        for timezone in timezones {
+            if timezone.parse::<chrono_tz::Tz>().is_err() {
+                return Err(invalid(format!("Unknown timezone '{}'", timezone)));
+            }
+        }
+        for tenant in self.tenants.keys() {
+            if uuid::Uuid::parse_str(tenant).is_err() {
+                return Err(invalid(format!(
+                    "Localization tenant '{}' must be an organization ID",
+                    tenant
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
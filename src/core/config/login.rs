@@ -0,0 +1,586 @@
+//! Login settings module.
+//!
+//! Magic links and their rate limits, the OAuth device
+//! grant, reports of login anomalies and the throttling of
+//! failing logins.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use super::server::ServerSettings;
+use super::webhooks::WebhookSettings;
+use crate::api::webhooks::WebhookEvent;
+use crate::core::err::AppError;
+use crate::core::types::{secret::Secret, AppType, HTTP_SCHEMES};
+
+/// ## Rate limit rule struct.
+///
+/// At most `limit` attempts are allowed per key in a
+/// fixed window of `window` seconds.
+///
+/// ## Fields
+/// + `limit`: `u32` - Attempts allowed per window.
+/// + `window`: `u64` - Length of the window in seconds.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::RateLimitRule;
+///
+/// let rule = RateLimitRule {
+///   limit: 5,
+///   window: 3600,
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct RateLimitRule {
+    pub limit: u32,
+    pub window: u64,
+}
+
+impl RateLimitRule {
+    /// ## Validates the rule.
+    ///
+    /// ## Parameters
+    /// - `name`: `&str` - Path of the rule, used in the error message.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the rule is valid.
+    ///    - `Err(AppError)` - If no attempt is allowed or the window is empty.
+    pub fn validate(&self, name: &str) -> Result<(), AppError> {
+        if self.limit == 0 || self.window == 0 {
+            return Err(AppError::config(format!(
+                "Rate limit '{}' must allow at least 1 attempt in at least 1 second",
+                name
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Magic link settings struct.
+///
+/// Passwordless login, the link is delivered through the
+/// `magic_link.requested` webhook.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Serve the `/auth/magic-link` routes.
+/// + `url`: `String` - Page the link opens, the token is added
+///   as the `token` query parameter.
+/// + `ttl`: `u64` - Seconds a link is valid.
+/// + `rate_limit`: `RateLimitRule` - Links sent per email address,
+///   reloaded without a restart.
+/// + `client_rate_limit`: `RateLimitRule` - Links requested per
+///   client address, whatever the email address, reloaded without
+///   a restart.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::MagicLinkSettings;
+///
+/// let magic_link_settings = MagicLinkSettings {
+///   enabled: true,
+///   url: "https://app.example.com/login/magic".to_string(),
+///   ..MagicLinkSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct MagicLinkSettings {
+    pub enabled: bool,
+    pub url: String,
+    pub ttl: u64,
+    pub rate_limit: RateLimitRule,
+    pub client_rate_limit: RateLimitRule,
+}
+
+impl Default for MagicLinkSettings {
+    fn default() -> Self {
+        MagicLinkSettings {
+            enabled: false,
+            url: "http://localhost:8080/auth/magic-link/verify".to_string(),
+            ttl: 900,
+            rate_limit: RateLimitRule {
+                limit: 5,
+                window: 3600,
+            },
+            client_rate_limit: RateLimitRule {
+                limit: 20,
+                window: 3600,
+            },
+        }
+    }
+}
+
+impl MagicLinkSettings {
+    /// ## Validates the magic link settings.
+    ///
+    /// ## Parameters
+    /// - `webhooks`: `&WebhookSettings` - Webhooks delivering the links.
+    /// - `captured`: `bool` - If mail is captured by `/dev/mailbox` instead.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the URL is not an HTTP URL, links
+    ///      expire immediately, the rate limit is empty or no
+    ///      webhook endpoint delivers the enabled links.
+    pub fn validate(&self, webhooks: &WebhookSettings, captured: bool) -> Result<(), AppError> {
+        let delivered = captured
+            || webhooks.enabled
+                && webhooks
+                    .endpoints
+                    .iter()
+                    .any(|endpoint| endpoint.subscribes(WebhookEvent::MagicLinkRequested));
+        if self.enabled && !delivered {
+            return Err(AppError::config(
+                "Magic links need a webhook endpoint receiving 'magic_link.requested'",
+            ));
+        }
+        if AppType::Url(HTTP_SCHEMES).verify(&self.url).is_err() {
+            return Err(AppError::config(
+                "Magic link URL must be an http:// or https:// URL",
+            ));
+        }
+        if self.ttl == 0 {
+            return Err(AppError::config(
+                "Magic links must be valid for at least 1 second",
+            ));
+        }
+
+        self.rate_limit.validate("magic_link.rate_limit")?;
+        self.client_rate_limit
+            .validate("magic_link.client_rate_limit")
+    }
+}
+
+/// ## Login anomaly settings struct.
+///
+/// Every login records the device it came from. Logins
+/// from a new device or country are sent to the user
+/// through the `login.anomaly` webhook, the first login
+/// of a user is never reported.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Record the devices and report anomalies.
+/// + `new_device`: `bool` - Report logins from a new device.
+/// + `new_country`: `bool` - Report logins from a new country, needs
+///   `access.geoip_db_path`.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::LoginAnomalySettings;
+///
+/// let login_anomaly_settings = LoginAnomalySettings {
+///   enabled: true,
+///   new_country: false,
+///   ..LoginAnomalySettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct LoginAnomalySettings {
+    pub enabled: bool,
+    pub new_device: bool,
+    pub new_country: bool,
+}
+
+impl Default for LoginAnomalySettings {
+    fn default() -> Self {
+        LoginAnomalySettings {
+            enabled: false,
+            new_device: true,
+            new_country: true,
+        }
+    }
+}
+
+impl LoginAnomalySettings {
+    /// ## Validates the login anomaly settings.
+    ///
+    /// ## Parameters
+    /// - `webhooks`: `&WebhookSettings` - Webhooks delivering the reports.
+    /// - `captured`: `bool` - If mail is captured by `/dev/mailbox` instead.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If no webhook endpoint delivers the
+    ///      enabled reports.
+    pub fn validate(&self, webhooks: &WebhookSettings, captured: bool) -> Result<(), AppError> {
+        let delivered = captured
+            || webhooks.enabled
+                && webhooks
+                    .endpoints
+                    .iter()
+                    .any(|endpoint| endpoint.subscribes(WebhookEvent::LoginAnomaly));
+        if self.enabled && !delivered {
+            return Err(AppError::config(
+                "Login anomalies need a webhook endpoint receiving 'login.anomaly'",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Device code settings struct.
+///
+/// OAuth device authorization grant of CLI and IoT
+/// clients. The device shows a user code, the user
+/// enters it on the verification page and the device
+/// polls `/oauth/token` until the user decided.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Serve `/oauth/device/code` and `/device`.
+/// + `verification_url`: `String` - Page the user enters the code on.
+/// + `ttl`: `u64` - Seconds the codes are valid.
+/// + `interval`: `u64` - Seconds a device waits between polls.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::DeviceCodeSettings;
+///
+/// let device_code_settings = DeviceCodeSettings {
+///   enabled: true,
+///   verification_url: "https://app.example.com/device".to_string(),
+///   ..DeviceCodeSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct DeviceCodeSettings {
+    pub enabled: bool,
+    pub verification_url: String,
+    pub ttl: u64,
+    pub interval: u64,
+}
+
+impl Default for DeviceCodeSettings {
+    fn default() -> Self {
+        DeviceCodeSettings {
+            enabled: false,
+            verification_url: "http://localhost:8080/device".to_string(),
+            ttl: 600,
+            interval: 5,
+        }
+    }
+}
+
+impl DeviceCodeSettings {
+    /// ## Validates the device code settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the URL is not an HTTP URL, codes
+    ///      expire immediately or expire before the first poll.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if AppType::Url(HTTP_SCHEMES)
+            .verify(&self.verification_url)
+            .is_err()
+        {
+            return Err(AppError::config(
+                "Device verification URL must be an http:// or https:// URL",
+            ));
+        }
+        if self.ttl == 0 {
+            return Err(AppError::config(
+                "Device codes must be valid for at least 1 second",
+            ));
+        }
+        if self.interval == 0 || self.interval >= self.ttl {
+            return Err(AppError::config(
+                "Device poll interval must be at least 1 second and shorter than the code TTL",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## CAPTCHA service of the login throttle.
+///
+/// # Variants
+/// - `Hcaptcha` - hCaptcha.
+/// - `Turnstile` - Cloudflare Turnstile.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaProvider {
+    #[default]
+    Hcaptcha,
+    Turnstile,
+}
+
+/// ## Login throttle settings struct.
+///
+/// Failed logins are counted per client address. After
+/// `free_failures` every attempt is delayed, starting at
+/// `base_delay` and doubling up to `max_delay`. From
+/// `captcha_after` failures on, logins also need a solved
+/// CAPTCHA. A successful login forgets the failures.
+/// Free failures, delays and the window are reloaded
+/// without a restart.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Throttle failing logins.
+/// + `free_failures`: `u32` - Failures answered without a delay.
+/// + `base_delay`: `u64` - Seconds of the first delay.
+/// + `max_delay`: `u64` - Seconds a login is delayed at most.
+/// + `window`: `u64` - Seconds after the last failure the failures
+///   are forgotten.
+/// + `captcha_after`: `u32` - Failures after which a CAPTCHA is required,
+///   never when 0.
+/// + `captcha_provider`: `CaptchaProvider` - Service verifying the CAPTCHA.
+/// + `captcha_secret`: `Secret<String>` - Secret key of the CAPTCHA site.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{CaptchaProvider, LoginThrottleSettings};
+///
+/// let login_throttle_settings = LoginThrottleSettings {
+///   enabled: true,
+///   captcha_after: 5,
+///   captcha_provider: CaptchaProvider::Turnstile,
+///   captcha_secret: "0x4AAAAAAA-secret".into(),
+///   ..LoginThrottleSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct LoginThrottleSettings {
+    pub enabled: bool,
+    pub free_failures: u32,
+    pub base_delay: u64,
+    pub max_delay: u64,
+    pub window: u64,
+    pub captcha_after: u32,
+    pub captcha_provider: CaptchaProvider,
+    pub captcha_secret: Secret<String>,
+}
+
+impl Default for LoginThrottleSettings {
+    fn default() -> Self {
+        LoginThrottleSettings {
+            enabled: false,
+            free_failures: 3,
+            base_delay: 1,
+            max_delay: 10,
+            window: 900,
+            captcha_after: 0,
+            captcha_provider: CaptchaProvider::Hcaptcha,
+            captcha_secret: Secret::default(),
+        }
+    }
+}
+
+impl LoginThrottleSettings {
+    /// ## Validates the login throttle settings when it is enabled.
+    ///
+    /// ## Parameters
+    /// - `server`: `&ServerSettings` - Timeout the delays have to fit in.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the delays are out of order or reach the
+    ///      request timeout, the window is 0 or the CAPTCHA has no secret.
+    pub fn validate(&self, server: &ServerSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.base_delay == 0 || self.base_delay > self.max_delay {
+            return Err(AppError::config(
+                "login_throttle.base_delay must be between 1 and max_delay",
+            ));
+        }
+        if self.max_delay >= server.request_timeout {
+            return Err(AppError::config(format!(
+                "login_throttle.max_delay must be below server.request_timeout of {} seconds",
+                server.request_timeout
+            )));
+        }
+        if self.window == 0 {
+            return Err(AppError::config(
+                "login_throttle.window must be greater than 0",
+            ));
+        }
+        if self.captcha_after > 0 && self.captcha_secret.is_empty() {
+            return Err(AppError::config(
+                "login_throttle.captcha_secret is required when captcha_after is set",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::WebhookEndpoint;
+
+    // Test checks if the delays have to fit the request timeout and the CAPTCHA needs a secret.
+    #[test]
+    fn test_login_throttle_validate() {
+        let server = ServerSettings::default();
+        let enabled = LoginThrottleSettings {
+            enabled: true,
+            ..LoginThrottleSettings::default()
+        };
+
+        assert!(enabled.validate(&server).is_ok());
+        assert!(LoginThrottleSettings {
+            captcha_after: 5,
+            captcha_secret: "secret".into(),
+            ..enabled.clone()
+        }
+        .validate(&server)
+        .is_ok());
+
+        for settings in [
+            LoginThrottleSettings {
+                base_delay: 0,
+                ..enabled.clone()
+            },
+            LoginThrottleSettings {
+                base_delay: 20,
+                ..enabled.clone()
+            },
+            LoginThrottleSettings {
+                max_delay: server.request_timeout,
+                ..enabled.clone()
+            },
+            LoginThrottleSettings {
+                window: 0,
+                ..enabled.clone()
+            },
+            LoginThrottleSettings {
+                captcha_after: 5,
+                ..enabled.clone()
+            },
+        ] {
+            assert!(settings.validate(&server).is_err(), "{:?}", settings);
+        }
+    }
+
+    // Test checks if the magic link URL, lifetime, rate limit and delivery are validated.
+    #[test]
+    fn test_magic_link_validate() {
+        let webhooks = WebhookSettings::default();
+
+        assert!(MagicLinkSettings::default()
+            .validate(&webhooks, false)
+            .is_ok());
+
+        let enabled = MagicLinkSettings {
+            enabled: true,
+            ..MagicLinkSettings::default()
+        };
+
+        assert!(enabled.validate(&webhooks, false).is_err());
+        assert!(enabled.validate(&webhooks, true).is_ok());
+
+        let webhooks = WebhookSettings {
+            enabled: true,
+            endpoints: vec![WebhookEndpoint {
+                name: "mailer".to_string(),
+                url: "https://mailer.example.com/hooks".to_string(),
+                secret: "0123456789abcdef0123456789abcdef".into(),
+                events: vec!["magic_link.requested".to_string()],
+            }],
+            ..WebhookSettings::default()
+        };
+
+        assert!(enabled.validate(&webhooks, false).is_ok());
+
+        let relative = MagicLinkSettings {
+            url: "/login/magic".to_string(),
+            ..MagicLinkSettings::default()
+        };
+
+        assert!(relative.validate(&webhooks, false).is_err());
+
+        let unlimited = MagicLinkSettings {
+            rate_limit: RateLimitRule {
+                limit: 0,
+                window: 60,
+            },
+            ..MagicLinkSettings::default()
+        };
+
+        assert_eq!(
+            unlimited.validate(&webhooks, false).unwrap_err().message,
+            "Rate limit 'magic_link.rate_limit' must allow at least 1 attempt in at least 1 second"
+        );
+    }
+
+    // Test checks if enabled login anomalies need a webhook endpoint delivering them.
+    #[test]
+    fn test_login_anomalies_validate() {
+        let enabled = LoginAnomalySettings {
+            enabled: true,
+            ..LoginAnomalySettings::default()
+        };
+        let webhooks = |events: &[&str]| WebhookSettings {
+            enabled: true,
+            endpoints: vec![WebhookEndpoint {
+                name: "mailer".to_string(),
+                url: "https://mailer.example.com/hooks".to_string(),
+                secret: "0123456789abcdef0123456789abcdef".into(),
+                events: events.iter().map(|event| event.to_string()).collect(),
+            }],
+            ..WebhookSettings::default()
+        };
+
+        assert!(LoginAnomalySettings::default()
+            .validate(&WebhookSettings::default(), false)
+            .is_ok());
+        assert_eq!(
+            enabled
+                .validate(&webhooks(&["magic_link.requested"]), false)
+                .unwrap_err()
+                .message,
+            "Login anomalies need a webhook endpoint receiving 'login.anomaly'"
+        );
+        assert!(enabled
+            .validate(&webhooks(&["login.anomaly"]), false)
+            .is_ok());
+    }
+
+    // Test checks if the device verification URL, TTL and poll interval are validated.
+    #[test]
+    fn test_device_code_validate() {
+        assert!(DeviceCodeSettings::default().validate().is_ok());
+
+        for (settings, message) in [
+            (
+                DeviceCodeSettings {
+                    verification_url: "/device".to_string(),
+                    ..DeviceCodeSettings::default()
+                },
+                "Device verification URL must be an http:// or https:// URL",
+            ),
+            (
+                DeviceCodeSettings {
+                    ttl: 0,
+                    ..DeviceCodeSettings::default()
+                },
+                "Device codes must be valid for at least 1 second",
+            ),
+            (
+                DeviceCodeSettings {
+                    ttl: 5,
+                    interval: 5,
+                    ..DeviceCodeSettings::default()
+                },
+                "Device poll interval must be at least 1 second and shorter than the code TTL",
+            ),
+        ] {
+            assert_eq!(settings.validate().unwrap_err().message, message);
+        }
+    }
+}
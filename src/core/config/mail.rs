@@ -0,0 +1,176 @@
+//! Mail settings module.
+//!
+//! Provider and templates of the emails.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use crate::core::err::AppError;
+use crate::core::types::{AppType, HTTP_SCHEMES};
+
+/// ## Mail provider.
+///
+/// # Variants
+/// - `Webhooks` - Mail leaves as webhook events for a mail service.
+/// - `Smtp` - SMTP server, authenticated with `MAIL_SMTP_USER` and
+///   `MAIL_SMTP_PASS` when they are set.
+/// - `Sendgrid` - SendGrid v3 API, authenticated with `MAIL_SENDGRID_API_KEY`.
+/// - `Ses` - Amazon SES v2 API, authenticated with `MAIL_SES_ACCESS_KEY_ID`
+///   and `MAIL_SES_SECRET_ACCESS_KEY`.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MailProvider {
+    #[default]
+    Webhooks,
+    Smtp,
+    Sendgrid,
+    Ses,
+}
+
+impl MailProvider {
+    /// ## Returns the name used in the configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MailProvider::Webhooks => "webhooks",
+            MailProvider::Smtp => "smtp",
+            MailProvider::Sendgrid => "sendgrid",
+            MailProvider::Ses => "ses",
+        }
+    }
+}
+
+/// ## Transport security of the SMTP connection.
+///
+/// # Variants
+/// - `None` - Plain text, only meant for local relays.
+/// - `Starttls` - Upgrades the connection with `STARTTLS`, usually port 587.
+/// - `Tls` - TLS from the start, usually port 465.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    None,
+    #[default]
+    Starttls,
+    Tls,
+}
+
+/// ## Mail settings struct.
+///
+/// Emails are rendered from the handlebars templates of the
+/// directory instead of the built-in texts. Templates live in
+/// a directory per locale, e.g. `de-DE/magic_link.subject.hbs`
+/// and `de-DE/magic_link.body.hbs`. In development they are
+/// read again for every email, so edits show up without a
+/// restart.
+///
+/// With a provider other than `webhooks`, the server sends
+/// the emails itself, also in development, and the readiness
+/// probe checks the provider. Credentials of the providers
+/// are read from the environment.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Render emails from the templates of the directory.
+/// + `templates_dir`: `String` - Directory of the templates, one directory per locale.
+/// + `default_locale`: `String` - Locale of the emails to users without a locale,
+///   and the fallback of locales without templates.
+/// + `provider`: `MailProvider` - Sender of the emails.
+/// + `from`: `String` - Sender address of the emails.
+/// + `smtp_host`: `String` - Host of the SMTP server.
+/// + `smtp_port`: `u16` - Port of the SMTP server.
+/// + `smtp_security`: `SmtpSecurity` - Transport security of the SMTP connection.
+/// + `sendgrid_url`: `String` - Base URL of the SendGrid API.
+/// + `ses_region`: `String` - AWS region of SES, e.g. `eu-central-1`.
+/// + `ses_url`: `Option<String>` - Base URL of the SES API, defaults to
+///   the endpoint of the region.
+/// + `critical`: `bool` - Provider being down fails the readiness probe.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{MailProvider, MailSettings};
+///
+/// let mail_settings = MailSettings {
+///   enabled: true,
+///   templates_dir: "./templates/mail".to_string(),
+///   provider: MailProvider::Smtp,
+///   smtp_host: "smtp.example.com".to_string(),
+///   ..MailSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct MailSettings {
+    pub enabled: bool,
+    pub templates_dir: String,
+    pub default_locale: String,
+    pub provider: MailProvider,
+    pub from: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub sendgrid_url: String,
+    pub ses_region: String,
+    pub ses_url: Option<String>,
+    pub critical: bool,
+}
+
+impl Default for MailSettings {
+    fn default() -> Self {
+        MailSettings {
+            enabled: false,
+            templates_dir: "./templates/mail".to_string(),
+            default_locale: "en-US".to_string(),
+            provider: MailProvider::Webhooks,
+            from: "no-reply@example.com".to_string(),
+            smtp_host: "localhost".to_string(),
+            smtp_port: 587,
+            smtp_security: SmtpSecurity::Starttls,
+            sendgrid_url: "https://api.sendgrid.com".to_string(),
+            ses_region: "us-east-1".to_string(),
+            ses_url: None,
+            critical: false,
+        }
+    }
+}
+
+impl MailSettings {
+    /// ## Validates the templates and the provider in use.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the default locale is empty, the
+    ///      directory can not be read, the sender is not an email
+    ///      address or the provider has no server.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.enabled {
+            if self.default_locale.trim().is_empty() {
+                return Err(AppError::config("mail.default_locale must not be empty"));
+            }
+            AppType::DirPath { writable: false }.verify(&self.templates_dir)?;
+        }
+
+        if self.provider != MailProvider::Webhooks {
+            AppType::Email.verify(&self.from)?;
+        }
+        match self.provider {
+            MailProvider::Webhooks => {}
+            MailProvider::Smtp if self.smtp_host.trim().is_empty() => {
+                return Err(AppError::config("mail.smtp_host must not be empty"));
+            }
+            MailProvider::Smtp => {}
+            MailProvider::Sendgrid => AppType::Url(HTTP_SCHEMES).verify(&self.sendgrid_url)?,
+            MailProvider::Ses => {
+                if self.ses_region.trim().is_empty() {
+                    return Err(AppError::config("mail.ses_region must not be empty"));
+                }
+                if let Some(url) = &self.ses_url {
+                    AppType::Url(HTTP_SCHEMES).verify(url)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
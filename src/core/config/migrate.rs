@@ -0,0 +1,754 @@
+//! Configuration migration module.
+//!
+//! Module upgrades configuration files written for
+//! older versions of the crate: renamed and moved keys
+//! are mapped to their current location and sections
+//! added since then are filled with their defaults.
+//! Comments and formatting of the file are kept.
+
+// Imports from external crates
+use std::{fmt, fs, path::Path};
+use toml_edit::{DocumentMut, Item, Table, TableLike, Value};
+
+// Local imports
+use super::{
+    AccessLogSettings, AccessSettings, AppConfig, AppSettings, AuthSettings, AuthzSettings,
+    BootstrapSettings, BrokerSettings, ClaimsSettings, ClientIpSettings, CorsSettings,
+    CrashReportSettings, CredentialExpirySettings, DatabaseSettings, DeviceCodeSettings,
+    ErrorSettings, GroupSyncSettings, GrpcSettings, HealthSettings, IpFilterSettings, JobSettings,
+    JournalSettings, KeyRotationSettings, LintSettings, LocalizationSettings, LoggingSettings,
+    LoginAnomalySettings, LoginThrottleSettings, MagicLinkSettings, MailSettings, MetricsSettings,
+    ObservabilitySettings, OpenApiSettings, OutboxSettings, PasswordSettings, ReloadSettings,
+    ReputationSettings, ServerSettings, StaticFilesSettings, TenancySettings, TokenSettings,
+    TraceSettings, UiSettings, UnknownEnvVars, WebhookSettings,
+};
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## Renamed or moved configuration key.
+///
+/// ## Fields
+/// + `from`: `&'static str` - Dotted path in older files.
+/// + `to`: `&'static str` - Dotted path in the current format.
+/// + `convert`: `Conversion` - Conversion of the moved value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyMove {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub convert: Conversion,
+}
+
+/// ## Conversion of a moved value.
+///
+/// # Variants
+/// - `Keep` - Value is moved as it is.
+/// - `Schedule` - Interval in seconds becomes a cron expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Conversion {
+    Keep,
+    Schedule,
+}
+
+impl Conversion {
+    /// ## Converts the value moved from the path.
+    ///
+    /// ## Parameters
+    /// - `from`: `&str` - Dotted path in the older file, used in the error message.
+    /// - `item`: `Item` - Value to convert.
+    ///
+    /// ## Returns
+    /// + `Result<Item, AppError>`
+    ///     - `Ok(Item)`: Converted value, its comments are kept.
+    ///     - `Err(AppError)`: If the value has no equivalent in the current format.
+    fn apply(&self, from: &str, item: Item) -> Result<Item, AppError> {
+        let Conversion::Schedule = self else {
+            return Ok(item);
+        };
+
+        let Item::Value(Value::Integer(seconds)) = item else {
+            return Err(AppError::config(format!(
+                "'{}' must be an interval in seconds",
+                from
+            )));
+        };
+        let expression = schedule(*seconds.value()).ok_or_else(|| {
+            AppError::config(format!(
+                "Interval of {} seconds of '{}' has no cron equivalent, set the schedule by hand",
+                seconds.value(),
+                from
+            ))
+        })?;
+
+        let mut value = Value::from(expression);
+        *value.decor_mut() = seconds.decor().clone();
+
+        Ok(Item::Value(value))
+    }
+}
+
+/// Keys renamed or moved since the first release, oldest
+/// first. Append an entry whenever a key changes place.
+pub const KEY_MOVES: &[KeyMove] = &[
+    KeyMove {
+        from: "group_sync.interval",
+        to: "group_sync.schedule",
+        convert: Conversion::Schedule,
+    },
+    KeyMove {
+        from: "webhooks.interval",
+        to: "webhooks.schedule",
+        convert: Conversion::Schedule,
+    },
+    KeyMove {
+        from: "ip_filter.trusted_proxies",
+        to: "client_ip.trusted_proxies",
+        convert: Conversion::Keep,
+    },
+    KeyMove {
+        from: "crash_reports.sentry",
+        to: "observability.sentry.enabled",
+        convert: Conversion::Keep,
+    },
+    KeyMove {
+        from: "crash_reports.environment",
+        to: "observability.sentry.environment",
+        convert: Conversion::Keep,
+    },
+];
+
+/// ## Change made by the migration.
+///
+/// # Variants
+/// - `Moved` - Key was moved to its current location.
+/// - `Dropped` - Old key was removed because the current key is already set.
+/// - `Added` - Missing key or section was filled with its default.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Moved { from: String, to: String },
+    Dropped { from: String, to: String },
+    Added(String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Moved { from, to } => write!(f, "moved   {} -> {}", from, to),
+            Change::Dropped { from, to } => {
+                write!(f, "dropped {} ({} is already set)", from, to)
+            }
+            Change::Added(path) => write!(f, "added   {} (default)", path),
+        }
+    }
+}
+
+/// ## Migration result struct.
+///
+/// ## Fields
+/// + `document`: `String` - Upgraded configuration file.
+/// + `changes`: `Vec<Change>` - Changes in the order they were made.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub document: String,
+    pub changes: Vec<Change>,
+}
+
+/// ## Migrates a configuration document.
+///
+/// Function applies the key moves, fills the missing
+/// defaults and checks that the result deserializes
+/// into the current `AppConfig`.
+///
+/// ## Parameters
+/// - `source`: `&str` - Contents of the old configuration file.
+/// - `moves`: `&[KeyMove]` - Key moves to apply, usually `KEY_MOVES`.
+///
+/// ## Returns
+/// + `Result<Migration, AppError>`
+///     - `Ok(Migration)`: Upgraded document and the changes made.
+///     - `Err(AppError)`: If the file is not valid TOML or can not be upgraded.
+pub fn migrate(source: &str, moves: &[KeyMove]) -> Result<Migration, AppError> {
+    let mut document: DocumentMut = source.parse().map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            "Configuration file is not valid TOML".to_string(),
+            Some(Box::new(e)),
+        )
+    })?;
+    let mut changes = Vec::new();
+
+    for key_move in moves {
+        let Some(item) = take(&mut document, key_move.from) else {
+            continue;
+        };
+
+        if get(&document, key_move.to).is_some() {
+            changes.push(Change::Dropped {
+                from: key_move.from.to_string(),
+                to: key_move.to.to_string(),
+            });
+            continue;
+        }
+
+        let item = key_move.convert.apply(key_move.from, item)?;
+        put(&mut document, key_move.to, item)?;
+        changes.push(Change::Moved {
+            from: key_move.from.to_string(),
+            to: key_move.to.to_string(),
+        });
+    }
+
+    let defaults = defaults()?;
+    fill(
+        document.as_table_mut(),
+        defaults.as_table(),
+        "",
+        &mut changes,
+    );
+
+    let document = document.to_string();
+    check(&document)?;
+
+    Ok(Migration { document, changes })
+}
+
+/// ## Migrates a configuration file.
+///
+/// When the output is the input file, the original
+/// file is kept next to it with a `.bak` extension.
+///
+/// ## Parameters
+/// - `input`: `&Path` - Old configuration file.
+/// - `output`: `&Path` - Path the upgraded file is written to.
+/// - `dry_run`: `bool` - Only report the changes, write nothing.
+///
+/// ## Returns
+/// + `Result<Vec<Change>, AppError>`
+///     - `Ok(Vec<Change>)`: Changes made to the file.
+///     - `Err(AppError)`: If the file can not be read, upgraded or written.
+pub fn migrate_file(input: &Path, output: &Path, dry_run: bool) -> Result<Vec<Change>, AppError> {
+    let source = fs::read_to_string(input).map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            format!("Failed to read '{}'", input.display()),
+            Some(Box::new(e)),
+        )
+    })?;
+
+    let migration = migrate(&source, KEY_MOVES)?;
+
+    if dry_run || migration.changes.is_empty() {
+        return Ok(migration.changes);
+    }
+
+    if input == output {
+        let backup = input.with_extension("toml.bak");
+        write(&backup, &source)?;
+    }
+    write(output, &migration.document)?;
+
+    Ok(migration.changes)
+}
+
+/// ## Writes the file.
+fn write(path: &Path, contents: &str) -> Result<(), AppError> {
+    fs::write(path, contents).map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            format!("Failed to write '{}'", path.display()),
+            Some(Box::new(e)),
+        )
+    })
+}
+
+/// ## Builds the document with the current defaults.
+///
+/// The `[app]` section has no defaults and is left out.
+pub(super) fn defaults() -> Result<DocumentMut, AppError> {
+    let config = AppConfig {
+        app: AppSettings {
+            env: String::new(),
+            prefix: String::new(),
+            env_file_path: String::new(),
+            unknown_env_vars: UnknownEnvVars::default(),
+        },
+        server: ServerSettings::default(),
+        database: DatabaseSettings::default(),
+        reputation: ReputationSettings::default(),
+        access: AccessSettings::default(),
+        cors: CorsSettings::default(),
+        ip_filter: IpFilterSettings::default(),
+        client_ip: ClientIpSettings::default(),
+        lint: LintSettings::default(),
+        openapi: OpenApiSettings::default(),
+        claims: ClaimsSettings::default(),
+        journal: JournalSettings::default(),
+        health: HealthSettings::default(),
+        password: PasswordSettings::default(),
+        logging: LoggingSettings::default(),
+        reload: ReloadSettings::default(),
+        trace: TraceSettings::default(),
+        group_sync: GroupSyncSettings::default(),
+        webhooks: WebhookSettings::default(),
+        metrics: MetricsSettings::default(),
+        localization: LocalizationSettings::default(),
+        jobs: JobSettings::default(),
+        tokens: TokenSettings::default(),
+        auth: AuthSettings::default(),
+        access_log: AccessLogSettings::default(),
+        magic_link: MagicLinkSettings::default(),
+        login_anomalies: LoginAnomalySettings::default(),
+        device_code: DeviceCodeSettings::default(),
+        key_rotation: KeyRotationSettings::default(),
+        tenancy: TenancySettings::default(),
+        authz: AuthzSettings::default(),
+        outbox: OutboxSettings::default(),
+        broker: BrokerSettings::default(),
+        grpc: GrpcSettings::default(),
+        ui: UiSettings::default(),
+        static_files: StaticFilesSettings::default(),
+        errors: ErrorSettings::default(),
+        login_throttle: LoginThrottleSettings::default(),
+        credential_expiry: CredentialExpirySettings::default(),
+        mail: MailSettings::default(),
+        crash_reports: CrashReportSettings::default(),
+        observability: ObservabilitySettings::default(),
+        bootstrap: BootstrapSettings::default(),
+    };
+
+    let mut document = toml_edit::ser::to_document(&config).map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            "Failed to serialize the default configuration".to_string(),
+            Some(Box::new(e)),
+        )
+    })?;
+    document.remove("app");
+
+    Ok(document)
+}
+
+/// ## Fills missing keys of the table with defaults.
+fn fill(
+    table: &mut dyn TableLike,
+    defaults: &dyn TableLike,
+    path: &str,
+    changes: &mut Vec<Change>,
+) {
+    for (key, default) in defaults.iter() {
+        let key_path = match path {
+            "" => key.to_string(),
+            path => format!("{}.{}", path, key),
+        };
+
+        match table.get_mut(key) {
+            Some(item) => {
+                if let (Some(table), Some(defaults)) =
+                    (item.as_table_like_mut(), default.as_table_like())
+                {
+                    fill(table, defaults, &key_path, changes);
+                }
+            }
+            None => {
+                // Maps like `access.roles` have no defaults to fill
+                if is_empty_section(default) {
+                    continue;
+                }
+
+                let mut section = as_section(default.clone());
+                prune(&mut section);
+
+                table.insert(key, section);
+                changes.push(Change::Added(key_path));
+            }
+        }
+    }
+}
+
+/// ## Checks if the item is a table without any values.
+fn is_empty_section(item: &Item) -> bool {
+    item.as_table_like()
+        .is_some_and(|table| table.iter().all(|(_, item)| is_empty_section(item)))
+}
+
+/// ## Removes the empty tables nested in the item.
+///
+/// Empty tables are read back as unit values, so maps
+/// like `group_sync.role_map` would fail to deserialize.
+pub(super) fn prune(item: &mut Item) {
+    if let Some(table) = item.as_table_like_mut() {
+        let empty: Vec<String> = table
+            .iter()
+            .filter(|(_, item)| is_empty_section(item))
+            .map(|(key, _)| key.to_string())
+            .collect();
+
+        for key in empty {
+            table.remove(&key);
+        }
+        for (_, item) in table.iter_mut() {
+            prune(item);
+        }
+    }
+}
+
+/// ## Turns inline tables into `[section]` tables.
+pub(super) fn as_section(item: Item) -> Item {
+    match item {
+        Item::Value(Value::InlineTable(inline)) => {
+            let mut table = inline.into_table();
+            let keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+
+            for key in keys {
+                if let Some(item) = table.remove(&key) {
+                    table.insert(&key, as_section(item));
+                }
+            }
+
+            Item::Table(table)
+        }
+        item => item,
+    }
+}
+
+/// ## Returns the cron expression running every interval.
+///
+/// Only intervals dividing a minute, an hour or a day
+/// evenly have an equivalent expression.
+fn schedule(seconds: i64) -> Option<String> {
+    match seconds {
+        3600 => Some("@hourly".to_string()),
+        86400 => Some("@daily".to_string()),
+        1..=59 if 60 % seconds == 0 => Some(format!("*/{} * * * * *", seconds)),
+        60..=3599 if seconds % 60 == 0 && 60 % (seconds / 60) == 0 => {
+            Some(format!("*/{} * * * *", seconds / 60))
+        }
+        3600..=86399 if seconds % 3600 == 0 && 24 % (seconds / 3600) == 0 => {
+            Some(format!("0 */{} * * *", seconds / 3600))
+        }
+        _ => None,
+    }
+}
+
+/// ## Returns the item at the dotted path.
+fn get<'a>(document: &'a DocumentMut, path: &str) -> Option<&'a Item> {
+    path.split('.')
+        .try_fold(document.as_item(), |item, key| item.get(key))
+}
+
+/// ## Removes and returns the item at the dotted path.
+fn take(document: &mut DocumentMut, path: &str) -> Option<Item> {
+    let (parents, key) = split(path);
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+
+    for parent in parents {
+        table = table.get_mut(parent)?.as_table_like_mut()?;
+    }
+
+    table.remove(key)
+}
+
+/// ## Inserts the item at the dotted path, creating missing tables.
+fn put(document: &mut DocumentMut, path: &str, item: Item) -> Result<(), AppError> {
+    let (parents, key) = split(path);
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+
+    for parent in parents {
+        table = table
+            .entry(parent)
+            .or_insert(Item::Table(Table::new()))
+            .as_table_like_mut()
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::Config,
+                    format!(
+                        "Can not move a key to '{}', '{}' is not a table",
+                        path, parent
+                    ),
+                    None,
+                )
+            })?;
+    }
+
+    table.insert(key, item);
+
+    Ok(())
+}
+
+/// ## Splits the dotted path into the parent tables and the key.
+fn split(path: &str) -> (Vec<&str>, &str) {
+    let mut parents: Vec<&str> = path.split('.').collect();
+    let key = parents.pop().unwrap_or_default();
+
+    (parents, key)
+}
+
+/// ## Checks that the document is a valid configuration.
+fn check(document: &str) -> Result<(), AppError> {
+    config::Config::builder()
+        .add_source(config::File::from_str(document, config::FileFormat::Toml))
+        .build()
+        .and_then(|config| config.try_deserialize::<AppConfig>())
+        .map(|_| ())
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Config,
+                format!("Upgraded configuration is still invalid: {}", e),
+                Some(Box::new(e)),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const BASELINE: &str = r#"[app]
+env = "dev"                    # dev, prod
+prefix = "AXA_"                # prefix for env variables
+env_file_path = ".env"         # path to env file
+"#;
+
+    // Test checks if missing sections are filled with defaults and comments are kept.
+    #[test]
+    fn test_migrate_fills_defaults() {
+        let migration = migrate(BASELINE, &[]).unwrap();
+
+        assert!(migration.document.starts_with(BASELINE));
+        assert!(migration.document.contains("[server.tls]"));
+        assert_eq!(
+            migration.changes,
+            [
+                "server",
+                "database",
+                "reputation",
+                "cors",
+                "ip_filter",
+                "client_ip",
+                "lint",
+                "openapi",
+                "journal",
+                "health",
+                "password",
+                "logging",
+                "reload",
+                "trace",
+                "group_sync",
+                "webhooks",
+                "metrics",
+                "localization",
+                "jobs",
+                "tokens",
+                "auth",
+                "access_log",
+                "magic_link",
+                "login_anomalies",
+                "device_code",
+                "key_rotation",
+                "tenancy",
+                "outbox",
+                "broker",
+                "grpc",
+                "ui",
+                "static_files",
+                "errors",
+                "login_throttle",
+                "credential_expiry",
+                "mail",
+                "crash_reports",
+                "observability",
+                "bootstrap",
+            ]
+            .map(|path| Change::Added(path.to_string()))
+        );
+
+        // Current files are left untouched
+        let again = migrate(&migration.document, &[]).unwrap();
+
+        assert!(again.changes.is_empty());
+        assert_eq!(again.document, migration.document);
+    }
+
+    // Test checks if keys missing from an existing section are filled.
+    #[test]
+    fn test_migrate_fills_nested_defaults() {
+        let source = format!("{}\n[server]\nport = 9000\n", BASELINE);
+        let migration = migrate(&source, &[]).unwrap();
+
+        assert!(migration.document.contains("port = 9000"));
+        assert!(migration
+            .changes
+            .contains(&Change::Added("server.tls".to_string())));
+        assert!(!migration
+            .changes
+            .contains(&Change::Added("server.port".to_string())));
+    }
+
+    // Test checks if moved keys are mapped and already set keys win.
+    #[test]
+    fn test_migrate_moves_keys() {
+        let moves = [
+            KeyMove {
+                from: "server.grace",
+                to: "server.shutdown_grace_period",
+                convert: Conversion::Keep,
+            },
+            KeyMove {
+                from: "tls_cert",
+                to: "server.tls.cert_path",
+                convert: Conversion::Keep,
+            },
+        ];
+        let source = format!(
+            "tls_cert = \"./cert.pem\"\n{}\n[server]\ngrace = 5\nshutdown_grace_period = 10\n",
+            BASELINE
+        );
+
+        let migration = migrate(&source, &moves).unwrap();
+
+        assert_eq!(
+            &migration.changes[..2],
+            [
+                Change::Dropped {
+                    from: "server.grace".to_string(),
+                    to: "server.shutdown_grace_period".to_string(),
+                },
+                Change::Moved {
+                    from: "tls_cert".to_string(),
+                    to: "server.tls.cert_path".to_string(),
+                },
+            ]
+        );
+        assert!(!migration.document.contains("grace = 5"));
+        assert!(migration.document.contains("cert_path = \"./cert.pem\""));
+    }
+
+    // Test checks if the job intervals become cron schedules.
+    #[test]
+    fn test_migrate_job_intervals() {
+        let source = format!(
+            "{}\n[group_sync]\ninterval = 3600 # seconds\n\n[webhooks]\ninterval = 10\n",
+            BASELINE
+        );
+
+        let migration = migrate(&source, KEY_MOVES).unwrap();
+
+        assert_eq!(
+            &migration.changes[..2],
+            [
+                Change::Moved {
+                    from: "group_sync.interval".to_string(),
+                    to: "group_sync.schedule".to_string(),
+                },
+                Change::Moved {
+                    from: "webhooks.interval".to_string(),
+                    to: "webhooks.schedule".to_string(),
+                },
+            ]
+        );
+        assert!(migration
+            .document
+            .contains("schedule = \"@hourly\" # seconds"));
+        assert!(migration.document.contains("schedule = \"*/10 * * * * *\""));
+        let document: DocumentMut = migration.document.parse().unwrap();
+        assert!(get(&document, "group_sync.interval").is_none());
+        assert!(get(&document, "webhooks.interval").is_none());
+
+        assert_eq!(schedule(300).as_deref(), Some("*/5 * * * *"));
+        assert_eq!(schedule(21600).as_deref(), Some("0 */6 * * *"));
+        let source = format!("{}\n[webhooks]\ninterval = 7\n", BASELINE);
+        assert!(migrate(&source, KEY_MOVES).is_err());
+    }
+
+    // Test checks if the trusted proxies of the IP filter move to the client address section.
+    #[test]
+    fn test_migrate_trusted_proxies() {
+        let source = format!(
+            "{}\n[ip_filter]\nenabled = true\ntrusted_proxies = [\"10.0.0.0/8\"]\n",
+            BASELINE
+        );
+
+        let migration = migrate(&source, KEY_MOVES).unwrap();
+
+        assert_eq!(
+            migration.changes[0],
+            Change::Moved {
+                from: "ip_filter.trusted_proxies".to_string(),
+                to: "client_ip.trusted_proxies".to_string(),
+            }
+        );
+        let document: DocumentMut = migration.document.parse().unwrap();
+        assert!(get(&document, "ip_filter.trusted_proxies").is_none());
+        assert_eq!(
+            get(&document, "client_ip.trusted_proxies")
+                .and_then(|item| item.as_array())
+                .map(|proxies| proxies.len()),
+            Some(1)
+        );
+        assert!(get(&document, "client_ip.header").is_some());
+    }
+
+    // Test checks if the Sentry keys of the crash reports move to the observability section.
+    #[test]
+    fn test_migrate_sentry() {
+        let source = format!(
+            "{}\n[crash_reports]\nbacktrace = false\nsentry = true\nenvironment = \"staging\"\n",
+            BASELINE
+        );
+
+        let migration = migrate(&source, KEY_MOVES).unwrap();
+
+        assert_eq!(
+            &migration.changes[..2],
+            [
+                Change::Moved {
+                    from: "crash_reports.sentry".to_string(),
+                    to: "observability.sentry.enabled".to_string(),
+                },
+                Change::Moved {
+                    from: "crash_reports.environment".to_string(),
+                    to: "observability.sentry.environment".to_string(),
+                },
+            ]
+        );
+        let document: DocumentMut = migration.document.parse().unwrap();
+        assert!(get(&document, "crash_reports.sentry").is_none());
+        assert!(get(&document, "crash_reports.environment").is_none());
+        assert_eq!(
+            get(&document, "observability.sentry.enabled").and_then(|item| item.as_bool()),
+            Some(true)
+        );
+        assert_eq!(
+            get(&document, "observability.sentry.environment").and_then(|item| item.as_str()),
+            Some("staging")
+        );
+        assert!(get(&document, "observability.sentry.sample_rate").is_some());
+    }
+
+    // Test checks if invalid files are rejected.
+    #[test]
+    fn test_migrate_invalid() {
+        assert!(migrate("[app", &[]).is_err());
+        assert!(migrate("[server]\nport = 8080\n", &[]).is_err());
+    }
+
+    // Test checks if the file is upgraded in place with a backup, and not written on dry run.
+    #[test]
+    fn test_migrate_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, BASELINE).unwrap();
+
+        let changes = migrate_file(&path, &path, true).unwrap();
+
+        assert!(!changes.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), BASELINE);
+
+        migrate_file(&path, &path, false).unwrap();
+
+        assert!(fs::read_to_string(&path).unwrap().contains("[cors]"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("config.toml.bak")).unwrap(),
+            BASELINE
+        );
+    }
+}
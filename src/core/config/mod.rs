@@ -1,140 +1,438 @@
 //! Application configuration module.
 //!
-//! Module is responsible for loading,
-//! validating and holding the application
-//! configuration settings.
+//! Module is responsible for loading and
+//! validating the application configuration
+//! settings. The loaded configuration is held
+//! by `core::state::AppState`, not by a global.
+//! Settings of every section, their validation and
+//! their tests live in the submodule of the section,
+//! they are re-exported here.
+
+// References to submodules
+pub mod access;
+pub mod api;
+pub mod app;
+pub mod auth;
+pub mod bootstrap;
+pub mod constraints;
+pub mod database;
+pub mod events;
+pub mod grpc;
+pub mod health;
+pub mod identity;
+pub mod interpolate;
+pub mod jobs;
+pub mod lint;
+pub mod localization;
+pub mod login;
+pub mod mail;
+pub mod migrate;
+pub mod network;
+pub mod observability;
+pub mod password;
+pub mod profiles;
+#[cfg(feature = "server")]
+pub mod reload;
+pub mod schema;
+pub mod secrets;
+pub mod server;
+pub mod summary;
+pub mod tenancy;
+pub mod tls;
+pub mod tokens;
+pub mod ui;
+pub mod webhooks;
 
 // Imports from external crates
 use config::Config;
-use once_cell::sync::{Lazy, OnceCell};
-use serde::Deserialize;
+use once_cell::sync::OnceCell;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 // Local imports
 use super::err::{AppError, ErrorKind};
 
+// Re-exports of the section settings
+pub use access::{AccessPolicySettings, AccessSettings, LoginWindowSettings, ReputationSettings};
+pub use api::{ErrorFormat, ErrorSettings, OpenApiSettings};
+pub use app::{AppSettings, ReloadSettings, UnknownEnvVars, DEVELOPMENT_ENVS, PRODUCTION_ENVS};
+pub use auth::{
+    AuthMode, AuthSettings, AuthzSettings, SameSite, TokenFormat, MIN_COOKIE_SECRET_LEN,
+};
+pub use bootstrap::{BootstrapSettings, StartupPhase};
+pub use database::{DatabaseRetrySettings, DatabaseSettings};
+pub use events::{BrokerKind, BrokerSettings, OutboxSettings};
+pub use grpc::GrpcSettings;
+pub use health::{HealthCheckKind, HealthCheckSettings, HealthSettings};
+pub use identity::{ClaimMappingSettings, ClaimsSettings, GroupSyncSettings, LdapSettings};
+pub use jobs::JobSettings;
+pub use lint::LintSettings;
+pub use localization::LocalizationSettings;
+pub use login::{
+    CaptchaProvider, DeviceCodeSettings, LoginAnomalySettings, LoginThrottleSettings,
+    MagicLinkSettings, RateLimitRule,
+};
+pub use mail::{MailProvider, MailSettings, SmtpSecurity};
+pub use network::{
+    parse_networks, ClientIpHeader, ClientIpSettings, CorsSettings, IpFilterSettings, CORS_WILDCARD,
+};
+pub use observability::{
+    AccessLogSettings, CrashReportSettings, JournalSettings, LoggingSettings, MetricsSettings,
+    ObservabilitySettings, RouteSampling, SentrySettings, TraceSettings,
+};
+pub use password::{Argon2Settings, BreachSettings, CredentialExpirySettings, PasswordSettings};
+pub use server::{
+    ProxyProtocolSettings, ServerSettings, DEFAULT_MAX_BODY_SIZE, DEFAULT_REQUEST_TIMEOUT,
+    DEFAULT_SERVER_HOST, DEFAULT_SERVER_PORT, DEFAULT_SHUTDOWN_GRACE_PERIOD, DEFAULT_SOCKET_MODE,
+    SOCKET_ACTIVATION, UNIX_SOCKET_PREFIX,
+};
+pub use tenancy::{TenancySettings, TenantStrategy};
+pub use tls::TlsSettings;
+pub use tokens::{KeyRotationSettings, TokenSettings};
+pub use ui::{StaticFilesSettings, UiSettings};
+pub use webhooks::{
+    OverflowPolicy, QueueSettings, WebhookEndpoint, WebhookSettings, MIN_WEBHOOK_SECRET_LEN,
+};
+
 /// Default configuration file name.
 pub const DEFAULT_CONFIG_FILE: &str = "./config";
 
 /// Holds the name of the configuration file.
 pub static CONFIG_FILE_PATH: OnceCell<String> = OnceCell::new();
 
-/// Application configuration.
-pub static APP_CONFIG: Lazy<Option<AppConfig>> = Lazy::new(|| {
-    // Get the configuration file path, if it is not set
-    // use the default configuration file name
-    let config_file = match CONFIG_FILE_PATH.get() {
-        Some(file) => file,
-        None => &DEFAULT_CONFIG_FILE.to_string(),
-    };
-
-    // Load the configuration from the file and return it
-    load_config(config_file)
-});
-
 /// ## Application configuration struct.
 ///
 /// ## Fields
 /// + `app`: `AppSettings` - Application settings.
+/// + `server`: `ServerSettings` - HTTP server settings, defaults
+///   are used when the section is missing.
+/// + `database`: `DatabaseSettings` - Size and timeouts of the
+///   connection pool.
+/// + `reputation`: `ReputationSettings` - IP reputation checks,
+///   disabled when the section is missing.
+/// + `access`: `AccessSettings` - Login window and country
+///   policies per role and tenant.
+/// + `cors`: `CorsSettings` - Cross-origin resource sharing,
+///   disabled when the section is missing.
+/// + `ip_filter`: `IpFilterSettings` - Client addresses allowed to reach
+///   `/admin` and `/scim/v2`, disabled when the section is missing.
+/// + `client_ip`: `ClientIpSettings` - Trusted proxies and the header
+///   the client address is read from.
+/// + `lint`: `LintSettings` - Startup security lint, reports
+///   without refusing to start when the section is missing.
+/// + `openapi`: `OpenApiSettings` - OpenAPI document and Swagger UI.
+/// + `claims`: `ClaimsSettings` - Mapping of identity provider claims.
+/// + `journal`: `JournalSettings` - Development request journal,
+///   disabled when the section is missing.
+/// + `health`: `HealthSettings` - Health checks of external integrations.
+/// + `password`: `PasswordSettings` - Password hashing algorithm and parameters.
+/// + `logging`: `LoggingSettings` - Log level, reloaded without a restart.
+/// + `reload`: `ReloadSettings` - Watching of the configuration file.
+/// + `trace`: `TraceSettings` - Sampling of request traces.
+/// + `group_sync`: `GroupSyncSettings` - Sync of directory groups
+///   to local roles, disabled when the section is missing.
+/// + `webhooks`: `WebhookSettings` - Account lifecycle webhooks,
+///   disabled when the section is missing.
+/// + `metrics`: `MetricsSettings` - Prometheus metrics endpoint,
+///   disabled when the section is missing.
+/// + `localization`: `LocalizationSettings` - Localized display fields
+///   of timestamps and numbers, disabled when the section is missing.
+/// + `jobs`: `JobSettings` - Timezone and schedules of the background jobs.
+/// + `tokens`: `TokenSettings` - Issuer, audience and signing key of access tokens.
+/// + `auth`: `AuthSettings` - Bearer or cookie session authentication.
+/// + `access_log`: `AccessLogSettings` - Redacted log line of every request.
+/// + `magic_link`: `MagicLinkSettings` - Passwordless login links,
+///   disabled when the section is missing.
+/// + `login_anomalies`: `LoginAnomalySettings` - Reports of logins from
+///   new devices and countries, disabled when the section is missing.
+/// + `device_code`: `DeviceCodeSettings` - OAuth device authorization
+///   grant, disabled when the section is missing.
+/// + `key_rotation`: `KeyRotationSettings` - Scheduled rotation of the
+///   signing keys, disabled when the section is missing.
+/// + `tenancy`: `TenancySettings` - Tenant resolution of requests,
+///   disabled when the section is missing.
+/// + `authz`: `AuthzSettings` - Roles and scopes required by route
+///   patterns, none when the section is missing.
+/// + `outbox`: `OutboxSettings` - Domain events stored with the state
+///   change, disabled when the section is missing.
+/// + `broker`: `BrokerSettings` - Publishing of the outbox events to
+///   NATS or Kafka, disabled when the section is missing.
+/// + `grpc`: `GrpcSettings` - gRPC token verification service,
+///   disabled when the section is missing.
+/// + `ui`: `UiSettings` - Embedded login and consent pages,
+///   disabled when the section is missing.
+/// + `static_files`: `StaticFilesSettings` - Files served from a directory,
+///   disabled when the section is missing.
+/// + `errors`: `ErrorSettings` - JSON envelope or RFC 7807 problem details
+///   of the error responses.
+/// + `login_throttle`: `LoginThrottleSettings` - Progressive delays and
+///   CAPTCHA of failing logins, disabled when the section is missing.
+/// + `credential_expiry`: `CredentialExpirySettings` - Maximum age of
+///   passwords and API keys, disabled when the section is missing.
+/// + `mail`: `MailSettings` - Handlebars templates of the emails.
+/// + `crash_reports`: `CrashReportSettings` - Logging and forwarding of panics.
+/// + `observability`: `ObservabilitySettings` - Error tracking with Sentry.
+/// + `bootstrap`: `BootstrapSettings` - Startup phases that are skipped
+///   and how long the startup waits for the cache.
 ///
 /// ## Examples
 /// ```
-/// use axum_auth::core::config::AppConfig;
+/// use axum_auth::core::config::{
+///     AccessLogSettings, AccessSettings, AppConfig, AppSettings, AuthSettings, AuthzSettings, BootstrapSettings, BrokerSettings, ClaimsSettings,
+///     ClientIpSettings, CorsSettings, CrashReportSettings, CredentialExpirySettings, DatabaseSettings, DeviceCodeSettings, ErrorSettings, GroupSyncSettings, GrpcSettings, IpFilterSettings,
+///     HealthSettings, JobSettings, JournalSettings, KeyRotationSettings, LintSettings, LocalizationSettings,
+///     LoggingSettings, LoginAnomalySettings, LoginThrottleSettings, MagicLinkSettings, MailSettings, MetricsSettings,
+///     ObservabilitySettings, OpenApiSettings, OutboxSettings, PasswordSettings, ReloadSettings, ReputationSettings, ServerSettings, StaticFilesSettings, TenancySettings,
+///     TokenSettings, TraceSettings, UiSettings, UnknownEnvVars, WebhookSettings,
+/// };
 ///
 /// let app_config = AppConfig {
 ///    app: AppSettings {
 ///       env: "development".to_string(),
 ///       prefix: "APP".to_string(),
 ///       env_file_path: ".env".to_string(),
+///       unknown_env_vars: UnknownEnvVars::Strict,
 ///    },
+///    server: ServerSettings::default(),
+///    database: DatabaseSettings::default(),
+///    reputation: ReputationSettings::default(),
+///    access: AccessSettings::default(),
+///    cors: CorsSettings::default(),
+///    ip_filter: IpFilterSettings::default(),
+///    client_ip: ClientIpSettings::default(),
+///    lint: LintSettings::default(),
+///    openapi: OpenApiSettings::default(),
+///    claims: ClaimsSettings::default(),
+///    journal: JournalSettings::default(),
+///    health: HealthSettings::default(),
+///    password: PasswordSettings::default(),
+///    logging: LoggingSettings::default(),
+///    reload: ReloadSettings::default(),
+///    trace: TraceSettings::default(),
+///    group_sync: GroupSyncSettings::default(),
+///    webhooks: WebhookSettings::default(),
+///    metrics: MetricsSettings::default(),
+///    localization: LocalizationSettings::default(),
+///    jobs: JobSettings::default(),
+///    tokens: TokenSettings::default(),
+///    auth: AuthSettings::default(),
+///    access_log: AccessLogSettings::default(),
+///    magic_link: MagicLinkSettings::default(),
+///    login_anomalies: LoginAnomalySettings::default(),
+///    device_code: DeviceCodeSettings::default(),
+///    key_rotation: KeyRotationSettings::default(),
+///    tenancy: TenancySettings::default(),
+///    authz: AuthzSettings::default(),
+///    outbox: OutboxSettings::default(),
+///    broker: BrokerSettings::default(),
+///    grpc: GrpcSettings::default(),
+///    ui: UiSettings::default(),
+///    static_files: StaticFilesSettings::default(),
+///    errors: ErrorSettings::default(),
+///    login_throttle: LoginThrottleSettings::default(),
+///    credential_expiry: CredentialExpirySettings::default(),
+///    mail: MailSettings::default(),
+///    crash_reports: CrashReportSettings::default(),
+///    observability: ObservabilitySettings::default(),
+///    bootstrap: BootstrapSettings::default(),
 /// };
 /// ```
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
 pub struct AppConfig {
     pub app: AppSettings,
+    #[serde(default)]
+    pub server: ServerSettings,
+    #[serde(default)]
+    pub database: DatabaseSettings,
+    #[serde(default)]
+    pub reputation: ReputationSettings,
+    #[serde(default)]
+    pub access: AccessSettings,
+    #[serde(default)]
+    pub cors: CorsSettings,
+    #[serde(default)]
+    pub ip_filter: IpFilterSettings,
+    #[serde(default)]
+    pub client_ip: ClientIpSettings,
+    #[serde(default)]
+    pub lint: LintSettings,
+    #[serde(default)]
+    pub openapi: OpenApiSettings,
+    #[serde(default)]
+    pub claims: ClaimsSettings,
+    #[serde(default)]
+    pub journal: JournalSettings,
+    #[serde(default)]
+    pub health: HealthSettings,
+    #[serde(default)]
+    pub password: PasswordSettings,
+    #[serde(default)]
+    pub logging: LoggingSettings,
+    #[serde(default)]
+    pub reload: ReloadSettings,
+    #[serde(default)]
+    pub trace: TraceSettings,
+    #[serde(default)]
+    pub group_sync: GroupSyncSettings,
+    #[serde(default)]
+    pub webhooks: WebhookSettings,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    #[serde(default)]
+    pub localization: LocalizationSettings,
+    #[serde(default)]
+    pub jobs: JobSettings,
+    #[serde(default)]
+    pub tokens: TokenSettings,
+    #[serde(default)]
+    pub auth: AuthSettings,
+    #[serde(default)]
+    pub access_log: AccessLogSettings,
+    #[serde(default)]
+    pub magic_link: MagicLinkSettings,
+    #[serde(default)]
+    pub login_anomalies: LoginAnomalySettings,
+    #[serde(default)]
+    pub device_code: DeviceCodeSettings,
+    #[serde(default)]
+    pub key_rotation: KeyRotationSettings,
+    #[serde(default)]
+    pub tenancy: TenancySettings,
+    #[serde(default)]
+    pub authz: AuthzSettings,
+    #[serde(default)]
+    pub outbox: OutboxSettings,
+    #[serde(default)]
+    pub broker: BrokerSettings,
+    #[serde(default)]
+    pub grpc: GrpcSettings,
+    #[serde(default)]
+    pub ui: UiSettings,
+    #[serde(default)]
+    pub static_files: StaticFilesSettings,
+    #[serde(default)]
+    pub errors: ErrorSettings,
+    #[serde(default)]
+    pub login_throttle: LoginThrottleSettings,
+    #[serde(default)]
+    pub credential_expiry: CredentialExpirySettings,
+    #[serde(default)]
+    pub mail: MailSettings,
+    #[serde(default)]
+    pub crash_reports: CrashReportSettings,
+    #[serde(default)]
+    pub observability: ObservabilitySettings,
+    #[serde(default)]
+    pub bootstrap: BootstrapSettings,
 }
 
-/// ## Application settings struct.
-///
-/// ## Fields
-/// + `env`: `String` - Application environment.
-/// + `prefix`: `String` - Prefix for environment variables.
-/// + `env_file_path`: `String` - Path to the environment file.
-///
-/// ## Examples
-/// ```
-/// use axum_auth::core::config::AppSettings;
-///
-/// let app_settings = AppSettings {
-///   env: "development".to_string(),
-///   prefix: "APP".to_string(),
-///   env_file_path: ".env".to_string(),
-/// };
-/// ```
-#[derive(Debug, Deserialize, PartialEq)]
-pub struct AppSettings {
-    pub env: String,
-    pub prefix: String,
-    pub env_file_path: String,
-}
+impl AppConfig {
+    /// ## Validates the loaded configuration.
+    ///
+    /// Function checks the values that can not be
+    /// verified during deserialization.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the configuration is valid.
+    ///    - `Err(AppError)` - If any of the sections is invalid.
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.server.validate()?;
+        self.server.tls.validate(&self.app)?;
+        self.database.validate()?;
+        self.access.validate()?;
+        self.cors.validate()?;
+        self.ip_filter.validate()?;
+        self.client_ip.validate()?;
+        self.claims.validate()?;
+        self.journal.validate(&self.app)?;
+        self.health.validate()?;
+        self.password.validate()?;
+        self.logging.validate()?;
+        self.trace.validate()?;
+        self.jobs.validate()?;
+        self.group_sync.validate(&self.jobs)?;
+        self.webhooks.validate(&self.jobs)?;
+        self.localization.validate()?;
+        self.tokens.validate()?;
+        self.auth.validate()?;
+        self.access_log.validate()?;
+        self.magic_link
+            .validate(&self.webhooks, self.app.is_development())?;
+        self.login_anomalies
+            .validate(&self.webhooks, self.app.is_development())?;
+        self.device_code.validate()?;
+        self.key_rotation.validate(&self.jobs)?;
+        self.tenancy.validate()?;
+        self.authz.validate()?;
+        self.outbox.validate(&self.jobs)?;
+        self.broker.validate(&self.outbox)?;
+        self.grpc.validate(&self.server)?;
+        self.ui
+            .validate(&self.auth, &self.magic_link, &self.device_code)?;
+        self.static_files.validate()?;
+        self.errors.validate()?;
+        self.login_throttle.validate(&self.server)?;
+        self.reputation.validate(&self.login_throttle)?;
+        self.credential_expiry.validate(&self.jobs)?;
+        self.mail.validate()?;
+        self.observability.validate()?;
+        self.bootstrap.validate()?;
 
-/// ## Checks if the configuration was loaded successfully.
-///
-/// Function checks if the configuration was loaded successfully.
-/// Function can be used before accessing the configuration to
-/// ensure that it was loaded successfully.
-///
-/// ## Exaples
-/// ```
-/// // todo
-/// ```
-///
-/// ## Returns
-/// + `Result<(), AppError>`
-///    - `Ok(())` - If the configuration was loaded successfully.
-///    - `Err(AppError)` - If the configuration was not loaded successfully.
-pub fn get_config(
-    config_instance: &'static Lazy<Option<AppConfig>>,
-) -> Result<&'static AppConfig, AppError> {
-    match config_instance.as_ref() {
-        Some(config) => Ok(config),
-        None => Err(AppError::new(
-            ErrorKind::InvalidConfig,
-            "Configuration file is missing or failed to load".to_string(),
-            None,
-        )),
+        Ok(())
     }
 }
 
-/// ## Loads the configuration from the file.
+/// ## Reads, deserializes and validates the configuration.
 ///
-/// Function loads the configuration from the
-/// specified file name.
+/// Function is used at startup and by the configuration
+/// watcher, which keeps the running configuration when
+/// the changed file is invalid. The selected profile is
+/// merged over the base values first, see `profiles`,
+/// then `${...}` references are replaced, see
+/// `interpolate`, and values prefixed with `enc:` are
+/// decrypted with the master key, see `secrets`.
 ///
 /// ## Parameters
 /// + `file_name`: `&str` - Name of the configuration file.
+/// + `profile`: `Option<&str>` - Profile selected on the command
+///   line, the one of `app.env` is merged when not set.
 ///
 /// ## Returns
-/// + `Option<AppConfig>` - Loaded configuration.
-///   - `Some(AppConfig)` - If the configuration was loaded successfully.
-///   - `None` - If the configuration failed to load/deserialize.
-fn load_config(file_name: &str) -> Option<AppConfig> {
+/// + `Result<AppConfig, AppError>` - Loaded configuration.
+///   - `Ok(AppConfig)` - If the configuration is valid.
+///   - `Err(AppError)` - If the file failed to load/deserialize/validate.
+pub fn read_config(file_name: &str, profile: Option<&str>) -> Result<AppConfig, AppError> {
     // Load configuration from the file in the current working directory
-    let app_config = match build_config_from_file(file_name) {
-        Ok(config) => config,
-        Err(e) => {
-            eprintln!("Failed to load configuration: {}", e);
-            return None;
-        }
-    };
-
-    // Deserialize into the AppConfig struct
-    match app_config.try_deserialize::<AppConfig>() {
-        Ok(app_config) => Some(app_config),
-        Err(e) => {
-            eprintln!("Failed to deserialize configuration: {}", e);
-            return None;
-        }
-    }
+    let mut value = build_config_from_file(file_name)?
+        .try_deserialize::<config::Value>()
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Config,
+                "Failed to deserialize configuration".to_string(),
+                Some(Box::new(e)),
+            )
+        })?;
+
+    // Merge the profile before its values are interpolated
+    profiles::apply_profile(&mut value, profile)?;
+
+    // Replace references and encrypted values before they are deserialized
+    interpolate::interpolate_values(&mut value)?;
+    secrets::decrypt_values(&mut value, secrets::MasterKey::from_env()?.as_ref())?;
+
+    let app_config = value.try_deserialize::<AppConfig>().map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            "Failed to deserialize configuration".to_string(),
+            Some(Box::new(e)),
+        )
+    })?;
+
+    // Validate values that deserialization can not check
+    app_config.validate()?;
+
+    Ok(app_config)
 }
 
 /// ## Builds the configuration from the file.
@@ -155,7 +453,7 @@ fn build_config_from_file(file_path: &str) -> Result<Config, AppError> {
         .build()
         .map_err(|e| {
             AppError::new(
-                ErrorKind::InvalidConfig,
+                ErrorKind::Config,
                 format!("Failed to load configuration: {}", e),
                 Some(Box::new(e)),
             )
@@ -165,4 +463,31 @@ fn build_config_from_file(file_path: &str) -> Result<Config, AppError> {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    // Test checks if debug prints of the configuration never contain its secrets.
+    #[test]
+    fn test_debug_masks_secrets() {
+        let mut config = read_config(DEFAULT_CONFIG_FILE, None).unwrap();
+        config.auth.csrf_secret = "csrf-secret-value".into();
+        config.group_sync.ldap.bind_password = "ldap-password-value".into();
+        config.webhooks.endpoints = vec![WebhookEndpoint {
+            name: "crm".to_string(),
+            url: "https://crm.example.com/hooks".to_string(),
+            secret: "webhook-secret-value".into(),
+            events: Vec::new(),
+        }];
+
+        let debug = format!("{:?}", config);
+
+        for secret in [
+            "csrf-secret-value",
+            "ldap-password-value",
+            "webhook-secret-value",
+        ] {
+            assert!(!debug.contains(secret), "{} leaked", secret);
+        }
+        assert!(debug.contains("csrf_secret: ***"));
+    }
+}
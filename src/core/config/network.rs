@@ -0,0 +1,242 @@
+//! Network settings module.
+//!
+//! Cross-origin resource sharing, the client addresses
+//! allowed to reach the administration routes, and the
+//! trusted proxies the client address is read behind.
+
+// Imports from external crates
+use ipnetwork::IpNetwork;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+#[cfg(feature = "server")]
+use crate::core::server::cors::cors_layer;
+
+/// Value allowing any CORS origin, method or header.
+pub const CORS_WILDCARD: &str = "*";
+
+/// ## CORS settings struct.
+///
+/// CORS headers are sent only when at least one
+/// origin is allowed.
+///
+/// ## Fields
+/// + `allowed_origins`: `Vec<String>` - Allowed origins, `*` allows any.
+/// + `allowed_methods`: `Vec<String>` - Allowed HTTP methods.
+/// + `allowed_headers`: `Vec<String>` - Allowed request headers.
+/// + `max_age`: `u64` - Seconds browsers may cache preflight responses.
+/// + `allow_credentials`: `bool` - Allow cookies and authorization headers.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::CorsSettings;
+///
+/// let cors_settings = CorsSettings {
+///   allowed_origins: vec!["https://app.example.com".to_string()],
+///   allow_credentials: true,
+///   ..CorsSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct CorsSettings {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub max_age: u64,
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        CorsSettings {
+            allowed_origins: Vec::new(),
+            allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE"]
+                .map(str::to_string)
+                .to_vec(),
+            allowed_headers: ["authorization", "content-type"]
+                .map(str::to_string)
+                .to_vec(),
+            max_age: 600,
+            allow_credentials: false,
+        }
+    }
+}
+
+impl CorsSettings {
+    /// ## Validates the CORS settings.
+    ///
+    /// Function checks that origins, methods and headers
+    /// are valid and that credentials are not combined
+    /// with a wildcard origin.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If any value is invalid.
+    #[cfg(feature = "server")]
+    pub fn validate(&self) -> Result<(), AppError> {
+        cors_layer(self).map(|_| ())
+    }
+
+    /// ## Validates the CORS settings.
+    ///
+    /// Without the `server` feature the values are not
+    /// parsed, only a wildcard origin combined with
+    /// credentials is rejected.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If credentials are allowed for any origin.
+    #[cfg(not(feature = "server"))]
+    pub fn validate(&self) -> Result<(), AppError> {
+        let wildcard = self
+            .allowed_origins
+            .iter()
+            .any(|origin| origin == CORS_WILDCARD);
+        if wildcard && self.allow_credentials {
+            return Err(AppError::config(
+                "Wildcard CORS origin can not be combined with allow_credentials",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## IP filter settings struct.
+///
+/// Filter guards `/admin` and `/scim/v2`, and `/metrics` when `metrics` is
+/// set. The client address is resolved as configured in
+/// `[client_ip]`. Denied networks win over allowed ones.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Filter the guarded routes.
+/// + `allow`: `Vec<String>` - Networks in CIDR notation allowed to
+///   reach the routes, every address when empty.
+/// + `deny`: `Vec<String>` - Networks in CIDR notation refused.
+/// + `metrics`: `bool` - Guard `/metrics` as well.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::IpFilterSettings;
+///
+/// let ip_filter_settings = IpFilterSettings {
+///   enabled: true,
+///   allow: vec!["10.0.0.0/8".to_string()],
+///   ..IpFilterSettings::default()
+/// };
+/// ```
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct IpFilterSettings {
+    pub enabled: bool,
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub metrics: bool,
+}
+
+impl IpFilterSettings {
+    /// ## Validates the IP filter settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If an entry is not a valid network.
+    pub fn validate(&self) -> Result<(), AppError> {
+        for (key, networks) in [
+            ("ip_filter.allow", &self.allow),
+            ("ip_filter.deny", &self.deny),
+        ] {
+            parse_networks(key, networks)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Header the client address is forwarded in.
+///
+/// # Variants
+/// - `XForwardedFor` - `X-Forwarded-For`, the addresses of every hop.
+/// - `Forwarded` - The `for` parameters of the RFC 7239 `Forwarded` header.
+/// - `CfConnectingIp` - `CF-Connecting-IP`, set by Cloudflare.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClientIpHeader {
+    #[default]
+    XForwardedFor,
+    Forwarded,
+    CfConnectingIp,
+}
+
+/// ## Client address settings struct.
+///
+/// Client address is the connection's peer, or read from
+/// `header` when the peer is a trusted proxy. Rate limits,
+/// audit entries, sessions, traces and the IP filter all
+/// use the resolved address.
+///
+/// ## Fields
+/// + `trusted_proxies`: `Vec<String>` - Networks in CIDR notation of
+///   the proxies whose header is read, none when empty.
+/// + `header`: `ClientIpHeader` - Header the proxies forward the
+///   client address in.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{ClientIpHeader, ClientIpSettings};
+///
+/// let client_ip_settings = ClientIpSettings {
+///   trusted_proxies: vec!["10.0.0.0/24".to_string()],
+///   header: ClientIpHeader::Forwarded,
+/// };
+/// ```
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ClientIpSettings {
+    pub trusted_proxies: Vec<String>,
+    pub header: ClientIpHeader,
+}
+
+impl ClientIpSettings {
+    /// ## Validates the client address settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If a trusted proxy is not a valid network.
+    pub fn validate(&self) -> Result<(), AppError> {
+        parse_networks("client_ip.trusted_proxies", &self.trusted_proxies)?;
+
+        Ok(())
+    }
+}
+
+/// ## Parses networks in CIDR notation.
+///
+/// ## Parameters
+/// - `key`: `&str` - Configuration key of the networks.
+/// - `networks`: `&[String]` - Networks to parse.
+///
+/// ## Returns
+/// + `Result<Vec<IpNetwork>, AppError>`
+///    - `Ok(Vec<IpNetwork>)` - Parsed networks.
+///    - `Err(AppError)` - If an entry is not a valid network.
+pub fn parse_networks(key: &str, networks: &[String]) -> Result<Vec<IpNetwork>, AppError> {
+    networks
+        .iter()
+        .map(|network| {
+            network.parse().map_err(|e| {
+                AppError::new(
+                    ErrorKind::Config,
+                    format!("'{}' of {} is not a valid network", network, key),
+                    Some(Box::new(e)),
+                )
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,536 @@
+//! Observability settings module.
+//!
+//! Logs, request traces, metrics, the development request
+//! journal, the access log and the reporting of panics and
+//! errors.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use super::app::AppSettings;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::logging::{parse_filter, DEFAULT_LOG_FILTER};
+
+/// ## Request journal settings struct.
+///
+/// Journal is a development tool and can not be
+/// enabled in production.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Record requests and responses.
+/// + `dir`: `String` - Directory of the journal files.
+/// + `capacity`: `u64` - Number of entries kept, the oldest
+///   entries are overwritten first.
+/// + `max_body_bytes`: `usize` - Larger bodies are not recorded.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::JournalSettings;
+///
+/// let journal_settings = JournalSettings {
+///   enabled: true,
+///   dir: "./journal".to_string(),
+///   capacity: 100,
+///   max_body_bytes: 65536,
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct JournalSettings {
+    pub enabled: bool,
+    pub dir: String,
+    pub capacity: u64,
+    pub max_body_bytes: usize,
+}
+
+impl Default for JournalSettings {
+    fn default() -> Self {
+        JournalSettings {
+            enabled: false,
+            dir: "./journal".to_string(),
+            capacity: 100,
+            max_body_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl JournalSettings {
+    /// ## Validates the journal settings.
+    ///
+    /// ## Parameters
+    /// - `app`: `&AppSettings` - Application settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the journal is enabled in production
+    ///      or the capacity is zero.
+    pub fn validate(&self, app: &AppSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let message = match (app.is_production(), self.capacity) {
+            (true, _) => "Request journal can not be enabled in production",
+            (_, 0) => "Request journal capacity must be greater than 0",
+            _ => return Ok(()),
+        };
+
+        Err(AppError::new(ErrorKind::Config, message.to_string(), None))
+    }
+}
+
+/// ## Logging settings struct.
+///
+/// ## Fields
+/// + `level`: `String` - Log filter, e.g. `info` or
+///   `info,axum_auth=debug`. Ignored when `RUST_LOG` is set.
+/// + `startup_summary`: `bool` - Log the build, the listening addresses,
+///   the configuration and the environment at startup, secrets masked.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::LoggingSettings;
+///
+/// let logging_settings = LoggingSettings {
+///   level: "debug".to_string(),
+///   startup_summary: false,
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct LoggingSettings {
+    pub level: String,
+    pub startup_summary: bool,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        LoggingSettings {
+            level: DEFAULT_LOG_FILTER.to_string(),
+            startup_summary: true,
+        }
+    }
+}
+
+impl LoggingSettings {
+    /// ## Validates the log level.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the level is a valid filter.
+    ///    - `Err(AppError)` - If the level can not be parsed.
+    pub fn validate(&self) -> Result<(), AppError> {
+        parse_filter(&self.level).map(|_| ())
+    }
+}
+
+/// ## Trace sampling settings struct.
+///
+/// Sampling is decided when the request starts, from its
+/// request ID, so retries with the same ID share the
+/// decision.
+///
+/// ## Fields
+/// + `sample_ratio`: `f64` - Share of requests traced, `0.0` to `1.0`.
+/// + `always_sample_errors`: `bool` - Log failed requests that
+///   were not sampled.
+/// + `routes`: `Vec<RouteSampling>` - Overrides for path prefixes,
+///   the longest matching prefix wins.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{RouteSampling, TraceSettings};
+///
+/// let trace_settings = TraceSettings {
+///   sample_ratio: 0.1,
+///   routes: vec![RouteSampling {
+///     path: "/healthz".to_string(),
+///     sample_ratio: 0.001,
+///     always_sample_errors: true,
+///   }],
+///   ..TraceSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct TraceSettings {
+    pub sample_ratio: f64,
+    pub always_sample_errors: bool,
+    pub routes: Vec<RouteSampling>,
+}
+
+impl Default for TraceSettings {
+    fn default() -> Self {
+        TraceSettings {
+            sample_ratio: 1.0,
+            always_sample_errors: false,
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl TraceSettings {
+    /// ## Validates the sampling ratios and route paths.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If a ratio is outside of `0.0` to `1.0`,
+    ///      or a path is relative or defined twice.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let invalid = |message: String| AppError::new(ErrorKind::Config, message, None);
+        let ratios = std::iter::once(("trace", self.sample_ratio)).chain(
+            self.routes
+                .iter()
+                .map(|route| (route.path.as_str(), route.sample_ratio)),
+        );
+
+        for (name, ratio) in ratios {
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(invalid(format!(
+                    "Sample ratio of '{}' must be between 0 and 1, got {}",
+                    name, ratio
+                )));
+            }
+        }
+
+        for (index, route) in self.routes.iter().enumerate() {
+            if !route.path.starts_with('/') {
+                return Err(invalid(format!(
+                    "Sampled route '{}' must start with '/'",
+                    route.path
+                )));
+            }
+            if self.routes[..index]
+                .iter()
+                .any(|other| other.path == route.path)
+            {
+                return Err(invalid(format!(
+                    "Sampled route '{}' is defined twice",
+                    route.path
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Sampling override of a route struct.
+///
+/// ## Fields
+/// + `path`: `String` - Path prefix, matched on segment boundaries.
+/// + `sample_ratio`: `f64` - Share of requests traced, `0.0` to `1.0`.
+/// + `always_sample_errors`: `bool` - Log failed requests that
+///   were not sampled.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+pub struct RouteSampling {
+    pub path: String,
+    pub sample_ratio: f64,
+    #[serde(default)]
+    pub always_sample_errors: bool,
+}
+
+/// ## Metrics settings struct.
+///
+/// Metrics are recorded either way, the settings only
+/// control their exposition.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Serve the metrics in the Prometheus
+///   text format on `/metrics`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct MetricsSettings {
+    pub enabled: bool,
+}
+
+/// ## Crash report settings struct.
+///
+/// Panics are logged through tracing with the request
+/// they interrupted, and panicking handlers answer with
+/// a 500 response. With `observability.sentry.enabled`,
+/// panics are also sent to Sentry.
+///
+/// ## Fields
+/// + `backtrace`: `bool` - Log panics with their backtrace.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::CrashReportSettings;
+///
+/// let crash_report_settings = CrashReportSettings { backtrace: false };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct CrashReportSettings {
+    pub backtrace: bool,
+}
+
+impl Default for CrashReportSettings {
+    fn default() -> Self {
+        CrashReportSettings { backtrace: true }
+    }
+}
+
+/// ## Observability settings struct.
+///
+/// ## Fields
+/// + `sentry`: `SentrySettings` - Error tracking with Sentry.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{ObservabilitySettings, SentrySettings};
+///
+/// let observability_settings = ObservabilitySettings {
+///   sentry: SentrySettings {
+///     enabled: true,
+///     sample_rate: 0.25,
+///     ..SentrySettings::default()
+///   },
+/// };
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ObservabilitySettings {
+    pub sentry: SentrySettings,
+}
+
+impl ObservabilitySettings {
+    /// ## Validates the observability settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the Sentry settings are invalid.
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.sentry.validate()
+    }
+}
+
+/// ## Sentry settings struct.
+///
+/// Server errors, the `AppError`s logged at the error
+/// level, are sent to the project of the `SENTRY_DSN`
+/// variable, tagged with their kind and code and enriched
+/// with the request and its user. Panics are always sent.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Send errors and panics, needs the `sentry` feature.
+/// + `environment`: `String` - Environment the events are filed under.
+/// + `sample_rate`: `f64` - Share of errors sent, `0.0` to `1.0`.
+/// + `send_user`: `bool` - Attach the ID of the authenticated user.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::SentrySettings;
+///
+/// let sentry_settings = SentrySettings {
+///   enabled: true,
+///   environment: "staging".to_string(),
+///   ..SentrySettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct SentrySettings {
+    pub enabled: bool,
+    pub environment: String,
+    pub sample_rate: f64,
+    pub send_user: bool,
+}
+
+impl Default for SentrySettings {
+    fn default() -> Self {
+        SentrySettings {
+            enabled: false,
+            environment: "production".to_string(),
+            sample_rate: 1.0,
+            send_user: true,
+        }
+    }
+}
+
+impl SentrySettings {
+    /// ## Validates the Sentry settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the sample rate is out of range, the
+    ///      environment is empty, or Sentry was not compiled in.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(0.0..=1.0).contains(&self.sample_rate) {
+            return Err(AppError::config(format!(
+                "observability.sentry.sample_rate must be between 0.0 and 1.0, got {}",
+                self.sample_rate
+            )));
+        }
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.environment.trim().is_empty() {
+            return Err(AppError::config(
+                "observability.sentry.environment must not be empty",
+            ));
+        }
+        if !cfg!(feature = "sentry") {
+            return Err(AppError::config(
+                "observability.sentry requires the 'sentry' feature",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Access log settings struct.
+///
+/// Every request is logged with its method, path, status,
+/// latency, user and request ID. Credentials and fields
+/// matching `redact` are replaced before logging.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Log every request.
+/// + `headers`: `bool` - Add the request headers.
+/// + `bodies`: `bool` - Add the request bodies.
+/// + `max_body_bytes`: `usize` - Larger bodies are not logged.
+/// + `redact`: `Vec<String>` - Parts of header, query and body
+///   field names to redact, next to the built-in credentials.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::AccessLogSettings;
+///
+/// let access_log_settings = AccessLogSettings {
+///   bodies: true,
+///   redact: vec!["ssn".to_string()],
+///   ..AccessLogSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct AccessLogSettings {
+    pub enabled: bool,
+    pub headers: bool,
+    pub bodies: bool,
+    pub max_body_bytes: usize,
+    pub redact: Vec<String>,
+}
+
+impl Default for AccessLogSettings {
+    fn default() -> Self {
+        AccessLogSettings {
+            enabled: true,
+            headers: false,
+            bodies: false,
+            max_body_bytes: 4096,
+            redact: Vec::new(),
+        }
+    }
+}
+
+impl AccessLogSettings {
+    /// ## Validates the access log settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If bodies are logged without a size
+    ///      limit or a redacted name is empty.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.bodies && self.max_body_bytes == 0 {
+            return Err(AppError::config(
+                "Access log body limit must be greater than 0",
+            ));
+        }
+        if self.redact.iter().any(|name| name.trim().is_empty()) {
+            return Err(AppError::config(
+                "Redacted names of the access log must not be empty",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::UnknownEnvVars;
+
+    // Test checks if the journal is refused in production.
+    #[test]
+    fn test_journal_validate_production() {
+        let settings = JournalSettings {
+            enabled: true,
+            ..JournalSettings::default()
+        };
+        let app = |env: &str| AppSettings {
+            env: env.to_string(),
+            prefix: "APP_".to_string(),
+            env_file_path: ".env".to_string(),
+            unknown_env_vars: UnknownEnvVars::Strict,
+        };
+
+        assert!(settings.validate(&app("dev")).is_ok());
+
+        let result = settings.validate(&app("prod")).unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Config);
+    }
+
+    // Test checks if sampling ratios and route paths are validated.
+    #[test]
+    fn test_trace_validate() {
+        let settings = |sample_ratio: f64, path: &str| TraceSettings {
+            routes: vec![RouteSampling {
+                path: path.to_string(),
+                sample_ratio,
+                always_sample_errors: false,
+            }],
+            ..TraceSettings::default()
+        };
+
+        assert!(settings(0.01, "/healthz").validate().is_ok());
+
+        assert_eq!(
+            settings(1.5, "/healthz").validate().unwrap_err().message,
+            "Sample ratio of '/healthz' must be between 0 and 1, got 1.5"
+        );
+        assert_eq!(
+            settings(0.5, "healthz").validate().unwrap_err().message,
+            "Sampled route 'healthz' must start with '/'"
+        );
+        assert!(TraceSettings {
+            sample_ratio: -0.1,
+            ..TraceSettings::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    // Test checks if logged bodies need a limit and redacted names must not be empty.
+    #[test]
+    fn test_access_log_validate() {
+        assert!(AccessLogSettings::default().validate().is_ok());
+
+        let unlimited = AccessLogSettings {
+            bodies: true,
+            max_body_bytes: 0,
+            ..AccessLogSettings::default()
+        };
+
+        assert!(unlimited.validate().is_err());
+
+        let blank = AccessLogSettings {
+            redact: vec![" ".to_string()],
+            ..AccessLogSettings::default()
+        };
+
+        assert!(blank.validate().is_err());
+    }
+}
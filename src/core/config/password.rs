@@ -0,0 +1,376 @@
+//! Password settings module.
+//!
+//! Hashing algorithm and parameters, the breached password
+//! check and the maximum age of passwords and API keys.
+
+// Imports from external crates
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use super::jobs::JobSettings;
+use crate::core::auth::password::{Algorithm, Passwords};
+use crate::core::err::AppError;
+use crate::core::types::AppType;
+
+/// ## Password hashing settings struct.
+///
+/// Hashes of the other algorithm keep working and are
+/// upgraded to `algorithm` on the next successful login.
+///
+/// ## Fields
+/// + `algorithm`: `Algorithm` - Algorithm of new hashes.
+/// + `argon2`: `Argon2Settings` - Argon2id parameters.
+/// + `bcrypt_cost`: `u32` - bcrypt cost factor, 4 to 31.
+/// + `breach`: `BreachSettings` - Check of new passwords against known breaches.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::auth::password::Algorithm;
+/// use axum_auth::core::config::PasswordSettings;
+///
+/// let password_settings = PasswordSettings {
+///   algorithm: Algorithm::Bcrypt,
+///   ..PasswordSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct PasswordSettings {
+    pub algorithm: Algorithm,
+    pub argon2: Argon2Settings,
+    pub bcrypt_cost: u32,
+    pub breach: BreachSettings,
+}
+
+impl Default for PasswordSettings {
+    fn default() -> Self {
+        PasswordSettings {
+            algorithm: Algorithm::Argon2id,
+            argon2: Argon2Settings::default(),
+            bcrypt_cost: 12,
+            breach: BreachSettings::default(),
+        }
+    }
+}
+
+impl PasswordSettings {
+    /// ## Validates the hashing parameters and the breach check.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the parameters are valid.
+    ///    - `Err(AppError)` - If a parameter is out of range.
+    pub fn validate(&self) -> Result<(), AppError> {
+        Passwords::new(self)?;
+        self.breach.validate()
+    }
+}
+
+/// ## Breached password check settings struct.
+///
+/// New passwords are looked up in the Have I Been Pwned
+/// range API, which only ever sees the first 5 characters
+/// of the SHA-1 hash. When the API can not be reached, or
+/// is turned off, the offline Bloom filter built with
+/// `axum_auth breach-filter` answers instead.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Reject passwords found in known breaches.
+/// + `hibp`: `bool` - Query the range API, needs the `hibp` feature.
+/// + `api_url`: `String` - URL of the range API, the hash prefix is appended.
+/// + `timeout`: `u64` - Seconds the range API may take to answer.
+/// + `bloom_filter_path`: `Option<String>` - Path to the offline filter.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::BreachSettings;
+///
+/// let breach_settings = BreachSettings {
+///   enabled: true,
+///   hibp: false,
+///   bloom_filter_path: Some("./pwned.bloom".to_string()),
+///   ..BreachSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct BreachSettings {
+    pub enabled: bool,
+    pub hibp: bool,
+    pub api_url: String,
+    pub timeout: u64,
+    pub bloom_filter_path: Option<String>,
+}
+
+impl Default for BreachSettings {
+    fn default() -> Self {
+        BreachSettings {
+            enabled: false,
+            hibp: true,
+            api_url: "https://api.pwnedpasswords.com/range/".to_string(),
+            timeout: 3,
+            bloom_filter_path: None,
+        }
+    }
+}
+
+impl BreachSettings {
+    /// ## Validates the breach check when it is enabled.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If there is nothing to check against, the
+    ///      range API is not built in or misconfigured, or the filter
+    ///      does not exist.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.hibp && !cfg!(feature = "hibp") {
+            return Err(AppError::config(
+                "password.breach.hibp needs the hibp feature, build with it or use bloom_filter_path",
+            ));
+        }
+        if !self.hibp && self.bloom_filter_path.is_none() {
+            return Err(AppError::config(
+                "password.breach needs hibp or bloom_filter_path",
+            ));
+        }
+        if self.hibp {
+            url::Url::parse(&self.api_url).map_err(|e| {
+                AppError::config(format!("password.breach.api_url is invalid: {}", e))
+            })?;
+            if self.timeout == 0 {
+                return Err(AppError::config(
+                    "password.breach.timeout must be greater than 0",
+                ));
+            }
+        }
+        if let Some(path) = &self.bloom_filter_path {
+            AppType::FilePath.verify(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Argon2id parameters struct.
+///
+/// Defaults follow the OWASP recommendation.
+///
+/// ## Fields
+/// + `memory_kib`: `u32` - Memory cost in KiB.
+/// + `iterations`: `u32` - Number of passes.
+/// + `parallelism`: `u32` - Number of lanes.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct Argon2Settings {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Settings {
+    fn default() -> Self {
+        Argon2Settings {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// ## Credential expiry settings struct.
+///
+/// Passwords older than `password_max_age` and API keys
+/// older than `api_key_max_age` expire, an age of 0 never
+/// does. The worker warns the users `warn_before` seconds
+/// ahead with `credential.expiring`, then requires a new
+/// password or revokes the key with `credential.expired`.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Register the expiry job.
+/// + `schedule`: `String` - Cron expression of the expiry runs.
+/// + `password_max_age`: `u64` - Seconds a password is valid after
+///   it was set.
+/// + `api_key_max_age`: `u64` - Seconds an API key is valid after
+///   it was issued.
+/// + `warn_before`: `u64` - Seconds before the expiry the user is warned.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::CredentialExpirySettings;
+///
+/// let credential_expiry_settings = CredentialExpirySettings {
+///   enabled: true,
+///   password_max_age: 180 * 86400,
+///   ..CredentialExpirySettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct CredentialExpirySettings {
+    pub enabled: bool,
+    pub schedule: String,
+    pub password_max_age: u64,
+    pub api_key_max_age: u64,
+    pub warn_before: u64,
+}
+
+impl Default for CredentialExpirySettings {
+    fn default() -> Self {
+        CredentialExpirySettings {
+            enabled: false,
+            schedule: "0 6 * * *".to_string(),
+            password_max_age: 90 * 86400,
+            api_key_max_age: 365 * 86400,
+            warn_before: 14 * 86400,
+        }
+    }
+}
+
+impl CredentialExpirySettings {
+    /// ## Validates the expiry settings when the expiry is enabled.
+    ///
+    /// ## Parameters
+    /// - `jobs`: `&JobSettings` - Timezone the schedule is evaluated in.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the schedule is invalid, nothing expires
+    ///      or the warning does not come after the credential was set.
+    pub fn validate(&self, jobs: &JobSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        jobs.schedule("credential_expiry.schedule", &self.schedule)?;
+        if self.password_max_age == 0 && self.api_key_max_age == 0 {
+            return Err(AppError::config(
+                "credential_expiry needs password_max_age or api_key_max_age",
+            ));
+        }
+        for (name, max_age) in [
+            ("password_max_age", self.password_max_age),
+            ("api_key_max_age", self.api_key_max_age),
+        ] {
+            if max_age > 0 && self.warn_before >= max_age {
+                return Err(AppError::config(format!(
+                    "credential_expiry.warn_before must be below {}",
+                    name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// ## Returns the expiry of a credential set at the time.
+    ///
+    /// ## Parameters
+    /// - `set_at`: `DateTime<Utc>` - Time the credential was set.
+    /// - `max_age`: `u64` - Seconds the credential is valid, never expires
+    ///   when 0.
+    ///
+    /// ## Returns
+    /// - `Option<DateTime<Utc>>`: Time of the expiry, `None` if it never
+    ///   expires or the expiry is beyond the supported time range.
+    pub fn expires_at(set_at: DateTime<Utc>, max_age: u64) -> Option<DateTime<Utc>> {
+        if max_age == 0 {
+            return None;
+        }
+
+        i64::try_from(max_age)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|max_age| set_at.checked_add_signed(max_age))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if credential expiry needs a max age and an earlier warning.
+    #[test]
+    fn test_credential_expiry_validate() {
+        let jobs = JobSettings::default();
+        let enabled = CredentialExpirySettings {
+            enabled: true,
+            ..CredentialExpirySettings::default()
+        };
+
+        assert!(enabled.validate(&jobs).is_ok());
+        assert!(CredentialExpirySettings {
+            api_key_max_age: 0,
+            ..enabled.clone()
+        }
+        .validate(&jobs)
+        .is_ok());
+
+        for settings in [
+            CredentialExpirySettings {
+                schedule: "never".to_string(),
+                ..enabled.clone()
+            },
+            CredentialExpirySettings {
+                password_max_age: 0,
+                api_key_max_age: 0,
+                ..enabled.clone()
+            },
+            CredentialExpirySettings {
+                warn_before: 90 * 86400,
+                ..enabled.clone()
+            },
+        ] {
+            assert!(settings.validate(&jobs).is_err(), "{:?}", settings);
+        }
+
+        let set_at = Utc::now();
+        assert_eq!(
+            CredentialExpirySettings::expires_at(set_at, 60),
+            Some(set_at + chrono::Duration::seconds(60))
+        );
+        assert_eq!(CredentialExpirySettings::expires_at(set_at, 0), None);
+        assert_eq!(CredentialExpirySettings::expires_at(set_at, u64::MAX), None);
+    }
+
+    // Test checks if the breach check needs a source that is built in and exists.
+    #[test]
+    fn test_breach_validate() {
+        let offline = BreachSettings {
+            enabled: true,
+            hibp: false,
+            bloom_filter_path: Some("Cargo.toml".to_string()),
+            ..BreachSettings::default()
+        };
+
+        assert!(BreachSettings::default().validate().is_ok());
+        assert!(offline.validate().is_ok());
+        assert_eq!(
+            BreachSettings {
+                hibp: true,
+                ..offline.clone()
+            }
+            .validate()
+            .is_ok(),
+            cfg!(feature = "hibp")
+        );
+
+        for settings in [
+            BreachSettings {
+                bloom_filter_path: None,
+                ..offline.clone()
+            },
+            BreachSettings {
+                bloom_filter_path: Some("missing.bloom".to_string()),
+                ..offline.clone()
+            },
+        ] {
+            assert!(settings.validate().is_err(), "{:?}", settings);
+        }
+    }
+}
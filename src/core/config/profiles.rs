@@ -0,0 +1,190 @@
+//! Configuration profiles module.
+//!
+//! One configuration file may hold the overrides of
+//! every environment in `[profile.<name>]` tables, e.g.
+//! `[profile.production.server]`, instead of one nearly
+//! identical file per environment. The profile named by
+//! `--profile`, or else by `app.env`, is merged over the
+//! base values: tables are merged key by key, any other
+//! value is replaced.
+
+// Imports from external crates
+use config::{Map, Value, ValueKind};
+
+// Local imports
+use crate::core::err::AppError;
+
+/// Table holding the profiles.
+pub const PROFILE_TABLE: &str = "profile";
+
+/// ## Merges the selected profile over the configuration.
+///
+/// The profile table is removed, so it never reaches
+/// deserialization. A profile selected on the command
+/// line must exist, one selected by `app.env` may not.
+/// Names are compared ignoring case, like `app.env`.
+///
+/// ## Parameters
+/// - `value`: `&mut Value` - Loaded configuration.
+/// - `selected`: `Option<&str>` - Profile given on the command line,
+///   `app.env` is used when not set.
+///
+/// ## Returns
+/// + `Result<Option<String>, AppError>`
+///     - `Ok(Some(String))`: Name of the merged profile.
+///     - `Ok(None)`: If no profile applies.
+///     - `Err(AppError)`: If the profiles are not tables or the
+///       selected profile is not defined.
+pub fn apply_profile(
+    value: &mut Value,
+    selected: Option<&str>,
+) -> Result<Option<String>, AppError> {
+    let ValueKind::Table(root) = &mut value.kind else {
+        return Ok(None);
+    };
+
+    let profiles = match root.remove(PROFILE_TABLE).map(|profiles| profiles.kind) {
+        Some(ValueKind::Table(profiles)) => profiles,
+        Some(_) => {
+            return Err(AppError::config(format!(
+                "'{}' must be a table of profiles, e.g. [{}.production]",
+                PROFILE_TABLE, PROFILE_TABLE
+            )))
+        }
+        None => Map::new(),
+    };
+
+    let name = match selected {
+        Some(name) => name.to_string(),
+        None => match root.get("app").map(|app| &app.kind) {
+            Some(ValueKind::Table(app)) => match app.get("env").map(|env| &env.kind) {
+                Some(ValueKind::String(env)) => env.clone(),
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        },
+    };
+
+    let Some((_, overrides)) = profiles
+        .into_iter()
+        .find(|(profile, _)| profile.eq_ignore_ascii_case(&name))
+    else {
+        return match selected {
+            Some(_) => Err(AppError::config(format!(
+                "Profile '{}' is not defined, add a [{}.{}] table",
+                name, PROFILE_TABLE, name
+            ))),
+            None => Ok(None),
+        };
+    };
+
+    if !matches!(overrides.kind, ValueKind::Table(_)) {
+        return Err(AppError::config(format!(
+            "Profile '{}' must be a table",
+            name
+        )));
+    }
+    merge(value, overrides);
+
+    Ok(Some(name))
+}
+
+/// ## Merges the overrides into the base value (private).
+fn merge(base: &mut Value, overrides: Value) {
+    match (&mut base.kind, overrides.kind) {
+        (ValueKind::Table(base), ValueKind::Table(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (_, kind) => base.kind = kind,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{Config, File, FileFormat};
+
+    const TOML: &str = r#"
+        [app]
+        env = "Production"
+
+        [server]
+        host = "127.0.0.1"
+        port = 8080
+        allowed_origins = ["http://localhost:3000"]
+
+        [profile.production.server]
+        host = "0.0.0.0"
+        allowed_origins = ["https://example.com"]
+
+        [profile.staging.server]
+        port = 9090
+    "#;
+
+    fn load(toml: &str) -> Value {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize::<Value>()
+            .unwrap()
+    }
+
+    fn json(value: Value) -> serde_json::Value {
+        value.try_deserialize().unwrap()
+    }
+
+    // Test checks if the profile of app.env is merged over the base values.
+    #[test]
+    fn test_apply_profile_app_env() {
+        let mut value = load(TOML);
+
+        assert_eq!(
+            apply_profile(&mut value, None).unwrap(),
+            Some("Production".to_string())
+        );
+
+        let json = json(value);
+        assert_eq!(json["server"]["host"], "0.0.0.0");
+        assert_eq!(json["server"]["port"], 8080);
+        assert_eq!(
+            json["server"]["allowed_origins"],
+            serde_json::json!(["https://example.com"])
+        );
+        assert!(json.get(PROFILE_TABLE).is_none());
+    }
+
+    // Test checks if the selected profile wins over app.env.
+    #[test]
+    fn test_apply_profile_selected() {
+        let mut value = load(TOML);
+
+        assert_eq!(
+            apply_profile(&mut value, Some("staging")).unwrap(),
+            Some("staging".to_string())
+        );
+
+        let json = json(value);
+        assert_eq!(json["server"]["host"], "127.0.0.1");
+        assert_eq!(json["server"]["port"], 9090);
+    }
+
+    // Test checks if only a selected profile has to exist.
+    #[test]
+    fn test_apply_profile_missing() {
+        let mut value = load(&TOML.replace("Production", "development"));
+        assert_eq!(apply_profile(&mut value, None).unwrap(), None);
+        assert_eq!(json(value)["server"]["host"], "127.0.0.1");
+
+        assert!(apply_profile(&mut load(TOML), Some("qa")).is_err());
+        assert!(apply_profile(&mut load("[app]\nenv = \"dev\"\n"), Some("dev")).is_err());
+        assert!(apply_profile(&mut load("profile = 1\n"), None).is_err());
+    }
+}
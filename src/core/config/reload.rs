@@ -0,0 +1,364 @@
+//! Configuration reload module.
+//!
+//! Module watches the configuration file and classifies
+//! every changed key as hot-reloadable or restart-required.
+//! Hot changes are published to the subscribers right
+//! away, restart-required changes are only reported, so
+//! the published configuration always matches what the
+//! running process actually uses.
+
+// Imports from external crates
+use serde_json::Value as Json;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::watch;
+
+// Local imports
+use super::{read_config, AppConfig};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::server::shutdown::ShutdownCoordinator;
+
+/// ## Reload class of a configuration key.
+///
+/// # Variants
+/// - `Hot` - Applied to the running process.
+/// - `Restart` - Takes effect after a restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reload {
+    Hot,
+    Restart,
+}
+
+/// Keys that are applied without a restart. A key is only
+/// listed once a subscriber of `watch` applies it, every
+/// other key requires a restart. The longest matching
+/// prefix wins.
+pub const RELOAD_RULES: &[(&str, Reload)] = &[
+    ("logging.level", Reload::Hot),
+    ("magic_link.rate_limit", Reload::Hot),
+    ("magic_link.client_rate_limit", Reload::Hot),
+    ("login_throttle.free_failures", Reload::Hot),
+    ("login_throttle.base_delay", Reload::Hot),
+    ("login_throttle.max_delay", Reload::Hot),
+    ("login_throttle.window", Reload::Hot),
+];
+
+/// ## Reload plan struct.
+///
+/// ## Fields
+/// + `hot`: `Vec<String>` - Changed keys applied right away.
+/// + `restart`: `Vec<String>` - Changed keys that need a restart.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReloadPlan {
+    pub hot: Vec<String>,
+    pub restart: Vec<String>,
+}
+
+/// ## Returns the reload class of the key.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::reload::{classify, Reload};
+///
+/// assert_eq!(classify("logging.level"), Reload::Hot);
+/// assert_eq!(classify("server.port"), Reload::Restart);
+/// ```
+pub fn classify(key: &str) -> Reload {
+    RELOAD_RULES
+        .iter()
+        .filter(|(prefix, _)| {
+            key == *prefix
+                || key
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or(Reload::Restart, |(_, reload)| *reload)
+}
+
+/// ## Classifies the keys that differ between two configurations.
+///
+/// ## Returns
+/// + `Result<ReloadPlan, AppError>`
+///     - `Ok(ReloadPlan)`: Changed keys, sorted by their path.
+///     - `Err(AppError)`: If a configuration can not be serialized.
+pub fn plan(current: &AppConfig, changed: &AppConfig) -> Result<ReloadPlan, AppError> {
+    let mut keys = Vec::new();
+    changed_keys("", &to_json(current)?, &to_json(changed)?, &mut keys);
+
+    let mut plan = ReloadPlan::default();
+    for key in keys {
+        match classify(&key) {
+            Reload::Hot => plan.hot.push(key),
+            Reload::Restart => plan.restart.push(key),
+        }
+    }
+
+    Ok(plan)
+}
+
+/// ## Returns the current configuration with the hot keys applied.
+///
+/// ## Parameters
+/// - `current`: `&AppConfig` - Configuration in use.
+/// - `changed`: `&AppConfig` - Configuration read from the file.
+/// - `keys`: `&[String]` - Hot keys to take from `changed`.
+///
+/// ## Returns
+/// + `Result<AppConfig, AppError>`
+///     - `Ok(AppConfig)`: Configuration to publish.
+///     - `Err(AppError)`: If the merged configuration is invalid.
+pub fn apply_hot(
+    current: &AppConfig,
+    changed: &AppConfig,
+    keys: &[String],
+) -> Result<AppConfig, AppError> {
+    let mut merged = to_json(current)?;
+    let changed = to_json(changed)?;
+
+    for key in keys {
+        let pointer = format!("/{}", key.replace('.', "/"));
+        if let (Some(target), Some(value)) =
+            (merged.pointer_mut(&pointer), changed.pointer(&pointer))
+        {
+            *target = value.clone();
+        }
+    }
+
+    serde_json::from_value(merged).map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            "Failed to apply configuration changes".to_string(),
+            Some(Box::new(e)),
+        )
+    })
+}
+
+/// ## Watches the configuration file.
+///
+/// Function spawns a task that checks the modification
+/// time of the file every `interval` until shutdown.
+/// Invalid files are ignored with a warning and the
+/// running configuration is kept.
+///
+/// ## Parameters
+/// - `path`: `PathBuf` - Configuration file.
+/// - `profile`: `Option<String>` - Profile the configuration was
+///   loaded with, see `read_config`.
+/// - `current`: `Arc<AppConfig>` - Configuration loaded at startup.
+/// - `interval`: `Duration` - Time between checks.
+/// - `coordinator`: `ShutdownCoordinator` - Stops the watcher.
+///
+/// ## Returns
+/// - `watch::Receiver<Arc<AppConfig>>`: Configuration in use, updated
+///   whenever hot keys change.
+pub fn watch(
+    path: PathBuf,
+    profile: Option<String>,
+    current: Arc<AppConfig>,
+    interval: Duration,
+    coordinator: ShutdownCoordinator,
+) -> watch::Receiver<Arc<AppConfig>> {
+    let (sender, receiver) = watch::channel(current);
+
+    tokio::spawn(async move {
+        let mut modified = modified_at(&path);
+
+        loop {
+            tokio::select! {
+                _ = coordinator.wait() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+
+            let now = modified_at(&path);
+            if now == modified {
+                continue;
+            }
+            modified = now;
+
+            // Borrow has to end before the new value is sent
+            let config = reload(&path, profile.as_deref(), &sender.borrow());
+            if let Some(config) = config {
+                sender.send_replace(Arc::new(config));
+            }
+        }
+    });
+
+    receiver
+}
+
+/// ## Resolves the file the configuration was loaded from.
+///
+/// Configuration names may omit the `.toml` extension.
+pub fn resolve_path(file_name: &str) -> PathBuf {
+    let path = PathBuf::from(file_name);
+
+    match path.exists() {
+        true => path,
+        false => PathBuf::from(format!("{}.toml", file_name)),
+    }
+}
+
+/// ## Reads the changed file and returns the configuration to publish.
+///
+/// ## Returns
+/// - `Option<AppConfig>`: Configuration with the hot keys applied,
+///   `None` when there is nothing to publish.
+fn reload(path: &Path, profile: Option<&str>, current: &AppConfig) -> Option<AppConfig> {
+    let result = read_config(&path.to_string_lossy(), profile)
+        .and_then(|changed| Ok((plan(current, &changed)?, changed)))
+        .and_then(|(plan, changed)| {
+            if !plan.restart.is_empty() {
+                tracing::warn!(
+                    keys = %plan.restart.join(", "),
+                    "Configuration changed, restart required to apply"
+                );
+            }
+            if plan.hot.is_empty() {
+                return Ok(None);
+            }
+
+            let config = apply_hot(current, &changed, &plan.hot)?;
+            tracing::info!(keys = %plan.hot.join(", "), "Configuration changes applied");
+
+            Ok(Some(config))
+        });
+
+    result.unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Ignoring invalid configuration change");
+        None
+    })
+}
+
+/// ## Collects the paths of the leaves that differ.
+///
+/// Tables are compared key by key, any other value
+/// (including arrays) is compared as a whole.
+fn changed_keys(prefix: &str, current: &Json, changed: &Json, keys: &mut Vec<String>) {
+    match (current, changed) {
+        (Json::Object(current), Json::Object(changed)) => {
+            let mut names = current.keys().chain(changed.keys()).collect::<Vec<_>>();
+            names.sort();
+            names.dedup();
+
+            for name in names {
+                let path = match prefix.is_empty() {
+                    true => name.to_string(),
+                    false => format!("{}.{}", prefix, name),
+                };
+                let (current, changed) = (
+                    current.get(name).unwrap_or(&Json::Null),
+                    changed.get(name).unwrap_or(&Json::Null),
+                );
+
+                changed_keys(&path, current, changed, keys);
+            }
+        }
+        (current, changed) if current != changed => keys.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+/// ## Serializes the configuration for comparison.
+fn to_json(config: &AppConfig) -> Result<Json, AppError> {
+    serde_json::to_value(config).map_err(|e| {
+        AppError::new(
+            ErrorKind::Internal,
+            "Failed to serialize configuration".to_string(),
+            Some(Box::new(e)),
+        )
+    })
+}
+
+/// ## Returns the modification time of the file.
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"[app]
+env = "dev"
+prefix = "APP_"
+env_file_path = ".env"
+"#;
+
+    fn config(extra: &str) -> AppConfig {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, format!("{}{}", CONFIG, extra)).unwrap();
+
+        read_config(&path.to_string_lossy(), None).unwrap()
+    }
+
+    // Test checks if keys are classified by their longest matching rule.
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("logging.level"), Reload::Hot);
+        assert_eq!(classify("logging.levels"), Reload::Restart);
+        assert_eq!(classify("server.host"), Reload::Restart);
+        assert_eq!(classify("magic_link.rate_limit.limit"), Reload::Hot);
+        assert_eq!(classify("login_throttle.max_delay"), Reload::Hot);
+        assert_eq!(classify("login_throttle.captcha_after"), Reload::Restart);
+    }
+
+    // Test checks if changed keys are split into hot and restart keys.
+    #[test]
+    fn test_plan() {
+        let current = config("");
+        let changed = config("[logging]\nlevel = \"debug\"\n[server]\nport = 9000\n");
+
+        let plan = plan(&current, &changed).unwrap();
+
+        assert_eq!(plan.hot, ["logging.level"]);
+        assert_eq!(plan.restart, ["server.port"]);
+    }
+
+    // Test checks if changed rate limits and throttle delays are applied.
+    #[test]
+    fn test_apply_hot_limits() {
+        let current = config("");
+        let changed = config(
+            "[magic_link.rate_limit]\nlimit = 50\nwindow = 60\n\
+             [login_throttle]\nmax_delay = 120\ncaptcha_after = 9\n",
+        );
+
+        let plan = plan(&current, &changed).unwrap();
+        let applied = apply_hot(&current, &changed, &plan.hot).unwrap();
+
+        assert_eq!(plan.restart, ["login_throttle.captcha_after"]);
+        assert_eq!(applied.magic_link.rate_limit.limit, 50);
+        assert_eq!(applied.login_throttle.max_delay, 120);
+        assert_eq!(
+            applied.login_throttle.captcha_after,
+            current.login_throttle.captcha_after
+        );
+    }
+
+    // Test checks if only hot keys are applied to the running configuration.
+    #[test]
+    fn test_apply_hot() {
+        let current = config("");
+        let changed = config("[logging]\nlevel = \"debug\"\n[server]\nport = 9000\n");
+
+        let applied = apply_hot(&current, &changed, &["logging.level".to_string()]).unwrap();
+
+        assert_eq!(applied.logging.level, "debug");
+        assert_eq!(applied.server.port, current.server.port);
+    }
+
+    // Test checks if invalid files keep the running configuration.
+    #[test]
+    fn test_reload_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[logging]\nlevel = \"debug\"\n").unwrap();
+
+        assert_eq!(reload(&path, None, &config("")), None);
+    }
+}
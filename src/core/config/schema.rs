@@ -0,0 +1,300 @@
+//! Configuration schema module.
+//!
+//! Module generates the JSON Schema of `AppConfig` and
+//! an example configuration file from the code, so the
+//! documentation of the configuration can not drift from
+//! what the application actually reads. Descriptions are
+//! taken from the `## Fields` lists of the doc comments.
+
+// Imports from external crates
+use serde_json::Value;
+use toml_edit::{DocumentMut, Item, Table};
+
+// Local imports
+use super::migrate::{as_section, defaults, prune};
+use super::{AppConfig, AppSettings, UnknownEnvVars};
+use crate::core::err::{AppError, ErrorKind};
+
+/// Environment used in the generated example file.
+pub const EXAMPLE_ENV: &str = "dev";
+
+/// ## Generates the JSON Schema of the configuration file.
+///
+/// Titles of the sections lose their `struct.` suffix,
+/// the `## Fields` list of a section becomes the
+/// descriptions of its properties and the code examples
+/// are dropped.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::schema::schema;
+///
+/// let schema = schema();
+///
+/// assert!(schema["properties"]["server"].is_object());
+/// ```
+///
+/// ## Returns
+/// - `Value`: JSON Schema, draft 2020-12.
+pub fn schema() -> Value {
+    let mut schema = schemars::schema_for!(AppConfig).to_value();
+
+    describe(&mut schema);
+    if let Some(Value::Object(defs)) = schema.get_mut("$defs") {
+        for def in defs.values_mut() {
+            describe(def);
+        }
+    }
+
+    schema
+}
+
+/// ## Generates an example configuration file.
+///
+/// Every key is set to its default and preceded by its
+/// description. Keys without a default are listed in the
+/// comment of their section. The `[app]` section is filled
+/// with example values.
+///
+/// ## Parameters
+/// - `prefix`: `&str` - Prefix of the environment variables.
+///
+/// ## Returns
+/// + `Result<String, AppError>`
+///     - `Ok(String)`: Example configuration file.
+///     - `Err(AppError)`: If the defaults can not be serialized.
+pub fn example_toml(prefix: &str) -> Result<String, AppError> {
+    let app = AppSettings {
+        env: EXAMPLE_ENV.to_string(),
+        prefix: prefix.to_string(),
+        env_file_path: ".env".to_string(),
+        unknown_env_vars: UnknownEnvVars::default(),
+    };
+    let app = toml_edit::ser::to_document(&app).map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            "Failed to serialize the application settings".to_string(),
+            Some(Box::new(e)),
+        )
+    })?;
+
+    let mut document = DocumentMut::new();
+    document.insert("app", Item::Table(app.as_table().clone()));
+    for (key, item) in defaults()?.iter() {
+        document.insert(key, as_section(item.clone()));
+    }
+    prune(document.as_item_mut());
+
+    let schema = schema();
+    annotate(document.as_table_mut(), &schema, &schema, "");
+
+    Ok(format!(
+        "# Generated by `axum_auth config example`, do not edit.\n\
+         # Every key is set to its default and can be left out.\n\
+         # Secrets can be encrypted with `axum_auth config encrypt`.\n{}",
+        document
+    ))
+}
+
+/// ## Moves the `## Fields` list of the schema into its properties.
+fn describe(schema: &mut Value) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+
+    if let Some(Value::String(title)) = object.get_mut("title") {
+        for suffix in [" struct.", " enum."] {
+            if let Some(stripped) = title.strip_suffix(suffix) {
+                *title = stripped.to_string();
+            }
+        }
+    }
+
+    let Some(Value::String(description)) = object.get("description") else {
+        return;
+    };
+    let fields = fields(description);
+    let summary = summary(description);
+
+    match summary.is_empty() {
+        true => object.remove("description"),
+        false => object.insert("description".to_string(), Value::String(summary)),
+    };
+
+    let Some(Value::Object(properties)) = object.get_mut("properties") else {
+        return;
+    };
+    for (name, text) in fields {
+        if let Some(Value::Object(property)) = properties.get_mut(&name) {
+            property.entry("description").or_insert(Value::String(text));
+        }
+    }
+}
+
+/// ## Returns the description without the fields and examples.
+fn summary(description: &str) -> String {
+    let end = ["## Fields", "## Examples"]
+        .iter()
+        .filter_map(|section| description.find(section))
+        .min()
+        .unwrap_or(description.len());
+
+    description[..end].trim().to_string()
+}
+
+/// ## Parses the `## Fields` list into names and descriptions.
+///
+/// Bullets look like ``+ `name`: `Type` - Description``,
+/// indented lines continue the previous bullet.
+fn fields(description: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for line in description.lines() {
+        if let Some(bullet) = line.strip_prefix("+ `") {
+            let Some((name, rest)) = bullet.split_once('`') else {
+                continue;
+            };
+            let text = rest.split_once(" - ").map_or("", |(_, text)| text);
+
+            fields.push((name.to_string(), text.trim().to_string()));
+        } else if line.starts_with("  ") {
+            if let Some((_, text)) = fields.last_mut() {
+                text.push(' ');
+                text.push_str(line.trim());
+            }
+        } else if !fields.is_empty() {
+            break;
+        }
+    }
+
+    fields
+}
+
+/// ## Adds the descriptions of the schema as comments to the table.
+fn annotate(table: &mut Table, schema: &Value, root: &Value, header: &str) {
+    let properties = resolve(schema, root)
+        .and_then(|schema| schema.get("properties"))
+        .and_then(Value::as_object);
+
+    let mut comment = String::from(header);
+    if let Some(properties) = properties {
+        let optional: Vec<String> = properties
+            .iter()
+            .filter(|(key, _)| !table.contains_key(key))
+            .map(|(key, property)| format!("# - {}: {}\n", key, text(property, root)))
+            .collect();
+
+        if !optional.is_empty() {
+            comment.push_str("#\n# Optional keys, not set by default:\n");
+            comment.extend(optional);
+        }
+    }
+    table.decor_mut().set_prefix(comment);
+
+    let keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+    for key in keys {
+        let property = properties
+            .and_then(|properties| properties.get(&key))
+            .cloned()
+            .unwrap_or(Value::Null);
+
+        match table.get_mut(&key) {
+            Some(Item::Table(nested)) => {
+                let header = format!("\n# {}\n", text(&property, root));
+                annotate(nested, &property, root, &header);
+            }
+            Some(_) => {
+                if let Some(mut key) = table.key_mut(&key) {
+                    let description = text(&property, root);
+                    if !description.is_empty() {
+                        key.leaf_decor_mut()
+                            .set_prefix(format!("# {}\n", description));
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// ## Returns the description of the property.
+///
+/// Falls back to the title of the referenced section.
+fn text(property: &Value, root: &Value) -> String {
+    property
+        .get("description")
+        .or_else(|| resolve(property, root).and_then(|schema| schema.get("title")))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// ## Follows the `$ref` of the schema, also inside `anyOf`.
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> Option<&'a Value> {
+    let reference = schema.get("$ref").and_then(Value::as_str).or_else(|| {
+        schema
+            .get("anyOf")
+            .and_then(Value::as_array)?
+            .iter()
+            .find_map(|variant| variant.get("$ref").and_then(Value::as_str))
+    });
+
+    match reference {
+        Some(reference) => {
+            let name = reference.strip_prefix("#/$defs/")?;
+            root.get("$defs").and_then(|defs| defs.get(name))
+        }
+        None => Some(schema),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if the field lists become property descriptions.
+    #[test]
+    fn test_schema_descriptions() {
+        let schema = schema();
+        let server = &schema["$defs"]["ServerSettings"];
+
+        assert_eq!(server["title"], "HTTP server settings");
+        assert!(server.get("description").is_none());
+        assert_eq!(
+            server["properties"]["shutdown_grace_period"]["description"],
+            "Seconds to wait for in-flight requests to finish after a shutdown signal is received."
+        );
+        assert_eq!(
+            schema["properties"]["app"]["description"],
+            "Application settings."
+        );
+    }
+
+    // Test checks if the example file is a valid configuration.
+    #[test]
+    fn test_example_toml_is_valid() {
+        let example = example_toml("APP_").unwrap();
+
+        let config = config::Config::builder()
+            .add_source(config::File::from_str(&example, config::FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize::<AppConfig>())
+            .unwrap();
+
+        assert!(config.validate().is_ok());
+        assert_eq!(config.app.prefix, "APP_");
+        assert!(example.contains("# Port the server listens on.\nport = 8080"));
+    }
+
+    // Test checks if the checked in example file is up to date.
+    #[test]
+    fn test_example_toml_is_current() {
+        let example = std::fs::read_to_string("config.example.toml").unwrap();
+
+        assert_eq!(
+            example,
+            example_toml("AXA_").unwrap(),
+            "run `axum_auth config example > config.example.toml`"
+        );
+    }
+}
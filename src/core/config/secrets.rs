@@ -0,0 +1,405 @@
+//! Encrypted configuration values module.
+//!
+//! String values prefixed with `enc:` are decrypted when
+//! the configuration is loaded, so passwords and signing
+//! keys don't sit in plaintext configuration files.
+//!
+//! Values are encrypted with AES-256-GCM under a master
+//! key read from `CONFIG_KEY`, or from the file named by
+//! `CONFIG_KEY_FILE`. The names are not prefixed, the
+//! prefix is only known once the configuration is loaded.
+//! Use `config key` to generate a master key and
+//! `config encrypt` to encrypt a value.
+
+// Imports from external crates
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use config::{Value, ValueKind};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+use std::{env, fs};
+
+// Local imports
+use crate::core::err::AppError;
+
+/// Prefix of encrypted values.
+pub const ENCRYPTED_PREFIX: &str = "enc:";
+
+/// Environment variable holding the base64 master key.
+pub const CONFIG_KEY: &str = "CONFIG_KEY";
+
+/// Environment variable holding the path of the master key file.
+pub const CONFIG_KEY_FILE: &str = "CONFIG_KEY_FILE";
+
+/// Length of the master key in bytes.
+const KEY_LEN: usize = 32;
+
+/// ## Master key struct.
+///
+/// Key the `enc:` values of the configuration are
+/// encrypted with.
+pub struct MasterKey {
+    key: LessSafeKey,
+}
+
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MasterKey(..)")
+    }
+}
+
+impl MasterKey {
+    /// ## Generates a new master key.
+    ///
+    /// ## Returns
+    /// + `Result<String, AppError>`
+    ///     - `Ok(String)`: Base64 encoded key.
+    ///     - `Err(AppError)`: If the system has no random source.
+    pub fn generate() -> Result<String, AppError> {
+        let mut bytes = [0u8; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| AppError::internal("Failed to generate the master key"))?;
+
+        Ok(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    /// ## Parses a base64 encoded master key.
+    ///
+    /// Lines starting with `#` are skipped, so key files
+    /// can carry a comment.
+    ///
+    /// ## Parameters
+    /// - `encoded`: `&str` - Base64 encoded 32 byte key.
+    ///
+    /// ## Returns
+    /// + `Result<MasterKey, AppError>`
+    ///     - `Ok(MasterKey)`: Parsed key.
+    ///     - `Err(AppError)`: If the key is not 32 bytes of base64.
+    pub fn parse(encoded: &str) -> Result<Self, AppError> {
+        let encoded = encoded
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .unwrap_or_default();
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded.trim_end_matches('='))
+            .ok()
+            .filter(|bytes| bytes.len() == KEY_LEN)
+            .ok_or_else(|| AppError::config("Master key must be 32 bytes of base64"))?;
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| AppError::config("Master key is invalid"))?;
+
+        Ok(Self {
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    /// ## Reads the master key from the environment.
+    ///
+    /// `CONFIG_KEY` takes precedence over `CONFIG_KEY_FILE`.
+    ///
+    /// ## Returns
+    /// + `Result<Option<MasterKey>, AppError>`
+    ///     - `Ok(Some(MasterKey))`: If a key is set.
+    ///     - `Ok(None)`: If neither variable is set.
+    ///     - `Err(AppError)`: If the key file can not be read or
+    ///       the key is invalid.
+    pub fn from_env() -> Result<Option<Self>, AppError> {
+        if let Ok(encoded) = env::var(CONFIG_KEY) {
+            return Self::parse(&encoded).map(Some);
+        }
+
+        match env::var(CONFIG_KEY_FILE) {
+            Ok(path) => {
+                let encoded = fs::read_to_string(&path).map_err(|e| {
+                    AppError::config(format!("Failed to read master key file '{}'", path))
+                        .with_source(e)
+                })?;
+                Self::parse(&encoded).map(Some)
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// ## Reads the master key the feature can not do without.
+    ///
+    /// ## Parameters
+    /// - `feature`: `&str` - Feature needing the key, used in the error message.
+    ///
+    /// ## Returns
+    /// + `Result<MasterKey, AppError>`
+    ///     - `Ok(MasterKey)`: Key of the environment.
+    ///     - `Err(AppError)`: If no key is set or it is invalid.
+    pub fn required(feature: &str) -> Result<Self, AppError> {
+        Self::from_env()?.ok_or_else(|| {
+            AppError::config(format!(
+                "{} needs a master key in {} or {}",
+                feature, CONFIG_KEY, CONFIG_KEY_FILE
+            ))
+        })
+    }
+
+    /// ## Encrypts a value.
+    ///
+    /// ## Parameters
+    /// - `plaintext`: `&str` - Value to encrypt.
+    ///
+    /// ## Returns
+    /// + `Result<String, AppError>`
+    ///     - `Ok(String)`: `enc:` prefixed value for the configuration file.
+    ///     - `Err(AppError)`: If the value can not be encrypted.
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, AppError> {
+        let sealed = self.seal(plaintext.as_bytes(), &[])?;
+
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            URL_SAFE_NO_PAD.encode(sealed)
+        ))
+    }
+
+    /// ## Decrypts an `enc:` prefixed value.
+    ///
+    /// ## Parameters
+    /// - `value`: `&str` - Value of the configuration file.
+    ///
+    /// ## Returns
+    /// + `Result<String, AppError>`
+    ///     - `Ok(String)`: Plaintext value.
+    ///     - `Err(AppError)`: If the value is malformed or was
+    ///       encrypted with another key.
+    pub fn decrypt(&self, value: &str) -> Result<String, AppError> {
+        let sealed = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .and_then(|encoded| URL_SAFE_NO_PAD.decode(encoded).ok())
+            .ok_or_else(|| AppError::config("Encrypted value is malformed"))?;
+        let plaintext = self.open(&sealed, &[])?;
+
+        String::from_utf8(plaintext).map_err(|_| AppError::config("Encrypted value is not UTF-8"))
+    }
+
+    /// ## Seals bytes stored outside the configuration.
+    ///
+    /// ## Parameters
+    /// - `plaintext`: `&[u8]` - Bytes to seal.
+    /// - `aad`: `&[u8]` - Data the sealed bytes are bound to, e.g. their ID.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<u8>, AppError>`
+    ///     - `Ok(Vec<u8>)`: Nonce followed by the ciphertext and its tag.
+    ///     - `Err(AppError)`: If the bytes can not be encrypted.
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, AppError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| AppError::internal("Failed to generate a nonce"))?;
+
+        let mut sealed = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut sealed,
+            )
+            .map_err(|_| AppError::internal("Failed to encrypt the value"))?;
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend(sealed);
+
+        Ok(bytes)
+    }
+
+    /// ## Opens bytes sealed by `seal`.
+    ///
+    /// ## Parameters
+    /// - `sealed`: `&[u8]` - Nonce followed by the ciphertext and its tag.
+    /// - `aad`: `&[u8]` - Data the bytes were bound to.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<u8>, AppError>`
+    ///     - `Ok(Vec<u8>)`: Plaintext bytes.
+    ///     - `Err(AppError)`: If the bytes are malformed, were sealed
+    ///       with another key or bound to other data.
+    pub fn open(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, AppError> {
+        if sealed.len() <= NONCE_LEN {
+            return Err(AppError::config("Encrypted value is malformed"));
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| AppError::config("Encrypted value is malformed"))?;
+
+        let mut sealed = sealed.to_vec();
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::from(aad), &mut sealed)
+            .map_err(|_| AppError::config("Encrypted value does not match the master key"))?;
+
+        Ok(plaintext.to_vec())
+    }
+}
+
+/// ## Decrypts the `enc:` values of the configuration.
+///
+/// Function walks the tables and arrays of the value and
+/// replaces every encrypted string with its plaintext.
+///
+/// ## Parameters
+/// - `value`: `&mut Value` - Loaded configuration.
+/// - `key`: `Option<&MasterKey>` - Master key, if one is set.
+///
+/// ## Returns
+/// + `Result<usize, AppError>`
+///     - `Ok(usize)`: Number of decrypted values.
+///     - `Err(AppError)`: If a value can not be decrypted or no
+///       master key is set.
+pub fn decrypt_values(value: &mut Value, key: Option<&MasterKey>) -> Result<usize, AppError> {
+    decrypt_at(value, "", key)
+}
+
+fn decrypt_at(value: &mut Value, path: &str, key: Option<&MasterKey>) -> Result<usize, AppError> {
+    match &mut value.kind {
+        ValueKind::String(text) if text.starts_with(ENCRYPTED_PREFIX) => {
+            let key = key.ok_or_else(|| {
+                AppError::config(format!(
+                    "Configuration value '{}' is encrypted, but neither {} nor {} is set",
+                    path, CONFIG_KEY, CONFIG_KEY_FILE
+                ))
+            })?;
+            *text = key.decrypt(text).map_err(|e| {
+                AppError::config(format!(
+                    "Failed to decrypt configuration value '{}': {}",
+                    path, e.message
+                ))
+            })?;
+            Ok(1)
+        }
+        ValueKind::Table(table) => table.iter_mut().try_fold(0, |count, (name, value)| {
+            let path = match path.is_empty() {
+                true => name.clone(),
+                false => format!("{}.{}", path, name),
+            };
+            Ok(count + decrypt_at(value, &path, key)?)
+        }),
+        ValueKind::Array(array) => {
+            array
+                .iter_mut()
+                .enumerate()
+                .try_fold(0, |count, (index, value)| {
+                    Ok(count + decrypt_at(value, &format!("{}[{}]", path, index), key)?)
+                })
+        }
+        _ => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{Config, File, FileFormat};
+
+    fn key() -> MasterKey {
+        MasterKey::parse(&MasterKey::generate().unwrap()).unwrap()
+    }
+
+    fn load(toml: &str) -> Value {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize::<Value>()
+            .unwrap()
+    }
+
+    // Test checks if encrypted values decrypt only with their key.
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = key();
+        let encrypted = key.encrypt("s3cret").unwrap();
+
+        assert!(encrypted.starts_with(ENCRYPTED_PREFIX));
+        assert_ne!(encrypted, key.encrypt("s3cret").unwrap());
+        assert_eq!(key.decrypt(&encrypted).unwrap(), "s3cret");
+        assert!(self::key().decrypt(&encrypted).is_err());
+        assert!(key.decrypt("enc:not-base64!").is_err());
+    }
+
+    // Test checks if sealed bytes open only with their key and data.
+    #[test]
+    fn test_seal_round_trip() {
+        let key = key();
+        let sealed = key.seal(b"document", b"kid-1").unwrap();
+
+        assert_eq!(key.open(&sealed, b"kid-1").unwrap(), b"document");
+        assert!(key.open(&sealed, b"kid-2").is_err());
+        assert!(self::key().open(&sealed, b"kid-1").is_err());
+        assert!(key.open(&sealed[..NONCE_LEN], b"kid-1").is_err());
+    }
+
+    // Test checks if malformed master keys are rejected and comments are skipped.
+    #[test]
+    fn test_parse_key() {
+        let encoded = MasterKey::generate().unwrap();
+
+        assert!(MasterKey::parse(&format!("# created 2026-10-16\n{}\n", encoded)).is_ok());
+        assert!(MasterKey::parse("c2hvcnQ").is_err());
+        assert!(MasterKey::parse("").is_err());
+    }
+
+    // Test checks if nested values are decrypted and plain values are left alone.
+    #[test]
+    fn test_decrypt_values() {
+        let key = key();
+        let toml = format!(
+            "[group_sync.ldap]\nbind_password = \"{}\"\n\n[[webhooks.endpoints]]\nurl = \"https://hooks.example.com\"\nsecret = \"{}\"\n",
+            key.encrypt("ldap").unwrap(),
+            key.encrypt("hook").unwrap()
+        );
+        let mut value = load(&toml);
+
+        assert_eq!(decrypt_values(&mut value, Some(&key)).unwrap(), 2);
+
+        let text = |value: &Value, path: &[&str]| {
+            let mut value = value.clone();
+            for part in path {
+                value = match part.parse::<usize>() {
+                    Ok(index) => value.into_array().unwrap().remove(index),
+                    Err(_) => value.into_table().unwrap().remove(*part).unwrap(),
+                };
+            }
+            value.into_string().unwrap()
+        };
+        assert_eq!(
+            text(&value, &["group_sync", "ldap", "bind_password"]),
+            "ldap"
+        );
+        assert_eq!(
+            text(&value, &["webhooks", "endpoints", "0", "secret"]),
+            "hook"
+        );
+        assert_eq!(
+            text(&value, &["webhooks", "endpoints", "0", "url"]),
+            "https://hooks.example.com"
+        );
+    }
+
+    // Test checks if encrypted values without a master key fail with their path.
+    #[test]
+    fn test_decrypt_values_without_key() {
+        let toml = format!(
+            "[tokens]\nsigning_key = \"{}\"\n",
+            key().encrypt("pem").unwrap()
+        );
+        let mut value = load(&toml);
+
+        let error = decrypt_values(&mut value, None).unwrap_err();
+
+        assert!(error.message.contains("'tokens.signing_key'"));
+        assert!(error.message.contains(CONFIG_KEY));
+        assert_eq!(
+            decrypt_values(&mut load("name = \"plain\""), None).unwrap(),
+            0
+        );
+    }
+}
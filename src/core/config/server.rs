@@ -0,0 +1,357 @@
+//! Server settings module.
+//!
+//! Address, limits and shutdown of the HTTP server, and
+//! the PROXY protocol headers of the load balancers in
+//! front of it.
+
+// Imports from external crates
+use ipnetwork::IpNetwork;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use super::network::parse_networks;
+use super::tls::TlsSettings;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::types::AppType;
+
+/// Default address the HTTP server binds to.
+pub const DEFAULT_SERVER_HOST: &str = "127.0.0.1";
+
+/// Default port the HTTP server listens on.
+pub const DEFAULT_SERVER_PORT: u16 = 8080;
+
+/// Default number of seconds to wait for in-flight
+/// requests during shutdown.
+pub const DEFAULT_SHUTDOWN_GRACE_PERIOD: u64 = 30;
+
+/// Default number of seconds a request may take.
+pub const DEFAULT_REQUEST_TIMEOUT: u64 = 30;
+
+/// Default maximum size of a request body in bytes.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// Prefix of `server.listen` naming a Unix socket.
+pub const UNIX_SOCKET_PREFIX: &str = "unix:";
+
+/// Value of `server.listen` using the socket passed by systemd.
+pub const SOCKET_ACTIVATION: &str = "systemd";
+
+/// Default octal permissions of the Unix socket.
+pub const DEFAULT_SOCKET_MODE: &str = "660";
+
+/// ## HTTP server settings struct.
+///
+/// ## Fields
+/// + `host`: `String` - Address the server binds to.
+/// + `port`: `u16` - Port the server listens on.
+/// + `shutdown_grace_period`: `u64` - Seconds to wait for in-flight
+///   requests to finish after a shutdown signal is received.
+/// + `request_timeout`: `u64` - Seconds a request may take, answered
+///   with 408 when exceeded.
+/// + `max_body_size`: `usize` - Bytes a request body may have, answered
+///   with 413 when exceeded.
+/// + `concurrency_limit`: `Option<usize>` - Requests handled at once,
+///   further requests are answered with 503, unlimited when not set.
+/// + `tls`: `TlsSettings` - HTTPS settings, disabled by default.
+/// + `proxy_protocol`: `ProxyProtocolSettings` - PROXY protocol v2
+///   headers of load balancers, disabled by default.
+/// + `listen`: `String` - Empty to bind `host` and `port`, `unix:<path>`
+///   for a Unix socket or `systemd` for the socket of `LISTEN_FDS`.
+/// + `socket_mode`: `String` - Octal permissions of the Unix socket,
+///   e.g. `660` for the owner and group.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::ServerSettings;
+///
+/// let server_settings = ServerSettings {
+///   host: "127.0.0.1".to_string(),
+///   port: 8080,
+///   shutdown_grace_period: 30,
+///   request_timeout: 10,
+///   max_body_size: 64 * 1024,
+///   concurrency_limit: Some(512),
+///   ..ServerSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: u16,
+    pub shutdown_grace_period: u64,
+    pub request_timeout: u64,
+    pub max_body_size: usize,
+    pub concurrency_limit: Option<usize>,
+    pub tls: TlsSettings,
+    pub proxy_protocol: ProxyProtocolSettings,
+    pub listen: String,
+    pub socket_mode: String,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            host: DEFAULT_SERVER_HOST.to_string(),
+            port: DEFAULT_SERVER_PORT,
+            shutdown_grace_period: DEFAULT_SHUTDOWN_GRACE_PERIOD,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            concurrency_limit: None,
+            tls: TlsSettings::default(),
+            proxy_protocol: ProxyProtocolSettings::default(),
+            listen: String::new(),
+            socket_mode: DEFAULT_SOCKET_MODE.to_string(),
+        }
+    }
+}
+
+impl ServerSettings {
+    /// ## Returns the path of the Unix socket to listen on.
+    pub fn unix_socket(&self) -> Option<&str> {
+        self.listen.strip_prefix(UNIX_SOCKET_PREFIX)
+    }
+
+    /// ## Checks if the listener is passed by systemd.
+    pub fn socket_activated(&self) -> bool {
+        self.listen == SOCKET_ACTIVATION
+    }
+
+    /// ## Returns the permissions of the Unix socket.
+    ///
+    /// ## Returns
+    /// + `Result<u32, AppError>`
+    ///     - `Ok(u32)`: Permission bits of `socket_mode`.
+    ///     - `Err(AppError)`: If the mode is not octal or above `777`.
+    pub fn socket_permissions(&self) -> Result<u32, AppError> {
+        match u32::from_str_radix(&self.socket_mode, 8) {
+            Ok(mode) if mode <= 0o777 => Ok(mode),
+            _ => Err(AppError::config(format!(
+                "server.socket_mode '{}' has to be octal permissions like '660'",
+                self.socket_mode
+            ))),
+        }
+    }
+
+    /// ## Validates the server settings.
+    ///
+    /// Function verifies the certificate and key paths
+    /// when TLS is enabled.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If a TLS file is missing or unreadable,
+    ///      the redirect port equals the server port, a request limit
+    ///      is zero, the proxy protocol settings are invalid or the
+    ///      Unix socket can not be created.
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.proxy_protocol.validate()?;
+        self.socket_permissions()?;
+
+        if let Some(path) = self.unix_socket() {
+            let parent = match std::path::Path::new(path).parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => std::path::Path::new("."),
+            };
+            if path.is_empty() || path.ends_with('/') {
+                return Err(AppError::config(format!(
+                    "server.listen '{}' names no socket file",
+                    self.listen
+                )));
+            }
+            AppType::DirPath { writable: true }.verify(&parent.to_string_lossy())?;
+
+            // TLS is terminated by the local proxy
+            if self.tls.enabled {
+                return Err(AppError::config(
+                    "TLS can not be enabled when listening on a Unix socket",
+                ));
+            }
+        } else if !self.listen.is_empty() && !self.socket_activated() {
+            return Err(AppError::config(format!(
+                "server.listen '{}' has to be empty, '{}' or '{}<path>'",
+                self.listen, SOCKET_ACTIVATION, UNIX_SOCKET_PREFIX
+            )));
+        }
+
+        if self.request_timeout == 0 || self.max_body_size == 0 || self.concurrency_limit == Some(0)
+        {
+            return Err(AppError::config(
+                "Request timeout, body size and concurrency limits must be greater than 0",
+            ));
+        }
+
+        if self.tls.enabled {
+            AppType::FilePath.verify(&self.tls.cert_path)?;
+            AppType::FilePath.verify(&self.tls.key_path)?;
+
+            if self.tls.redirect_http_port == Some(self.port) {
+                return Err(AppError::new(
+                    ErrorKind::Config,
+                    format!(
+                        "Redirect port {} is already used by the HTTPS server",
+                        self.port
+                    ),
+                    None,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## PROXY protocol settings struct.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Read the PROXY protocol v2 header of
+///   connections from trusted proxies.
+/// + `trusted_proxies`: `Vec<String>` - Networks of the proxies in CIDR
+///   notation, every peer has to send a header when empty.
+/// + `header_timeout`: `u64` - Seconds to wait for the header.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::ProxyProtocolSettings;
+///
+/// let proxy_protocol_settings = ProxyProtocolSettings {
+///   enabled: true,
+///   trusted_proxies: vec!["10.0.0.0/8".to_string()],
+///   header_timeout: 5,
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ProxyProtocolSettings {
+    pub enabled: bool,
+    pub trusted_proxies: Vec<String>,
+    pub header_timeout: u64,
+}
+
+impl Default for ProxyProtocolSettings {
+    fn default() -> Self {
+        ProxyProtocolSettings {
+            enabled: false,
+            trusted_proxies: Vec::new(),
+            header_timeout: 5,
+        }
+    }
+}
+
+impl ProxyProtocolSettings {
+    /// ## Validates the proxy protocol settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If a trusted proxy is not a valid
+    ///      network or the header timeout is zero.
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.networks()?;
+
+        if self.enabled && self.header_timeout == 0 {
+            return Err(AppError::config(
+                "Proxy protocol header timeout must be greater than 0",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// ## Parses the trusted proxies.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<IpNetwork>, AppError>`
+    ///    - `Ok(Vec<IpNetwork>)` - Networks of the trusted proxies.
+    ///    - `Err(AppError)` - If an entry is not a valid network.
+    pub fn networks(&self) -> Result<Vec<IpNetwork>, AppError> {
+        parse_networks("proxy_protocol.trusted_proxies", &self.trusted_proxies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if zero request limits are rejected.
+    #[test]
+    fn test_server_validate_limits() {
+        assert!(ServerSettings::default().validate().is_ok());
+
+        for settings in [
+            ServerSettings {
+                request_timeout: 0,
+                ..ServerSettings::default()
+            },
+            ServerSettings {
+                max_body_size: 0,
+                ..ServerSettings::default()
+            },
+            ServerSettings {
+                concurrency_limit: Some(0),
+                ..ServerSettings::default()
+            },
+        ] {
+            assert!(settings.validate().is_err());
+        }
+    }
+
+    // Test checks if Unix sockets need a writable directory and octal permissions.
+    #[test]
+    fn test_server_validate_listen() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = format!("unix:{}/app.sock", dir.path().display());
+        let settings = ServerSettings {
+            listen: socket.clone(),
+            socket_mode: "600".to_string(),
+            ..ServerSettings::default()
+        };
+
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.socket_permissions(), Ok(0o600));
+        assert!(ServerSettings {
+            listen: "systemd".to_string(),
+            ..ServerSettings::default()
+        }
+        .validate()
+        .is_ok());
+
+        for (listen, socket_mode) in [
+            ("unix:/path/to/missing/app.sock", "660"),
+            ("unix:", "660"),
+            ("tcp:0.0.0.0:80", "660"),
+            (socket.as_str(), "0999"),
+            (socket.as_str(), "1777"),
+        ] {
+            let settings = ServerSettings {
+                listen: listen.to_string(),
+                socket_mode: socket_mode.to_string(),
+                ..ServerSettings::default()
+            };
+
+            assert!(settings.validate().is_err(), "{} {}", listen, socket_mode);
+        }
+    }
+
+    // Test checks if invalid trusted proxies are rejected.
+    #[test]
+    fn test_proxy_protocol_validate() {
+        let mut settings = ProxyProtocolSettings {
+            enabled: true,
+            trusted_proxies: vec!["10.0.0.0/8".to_string(), "2001:db8::1".to_string()],
+            ..ProxyProtocolSettings::default()
+        };
+
+        assert!(settings.validate().is_ok());
+
+        settings.trusted_proxies.push("10.0.0.0/33".to_string());
+        let result = settings.validate().unwrap_err();
+
+        assert_eq!(
+            result.message,
+            "'10.0.0.0/33' of proxy_protocol.trusted_proxies is not a valid network"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Tenancy settings module.
+//!
+//! Resolution of the tenant of a request.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use crate::core::err::AppError;
+
+/// ## Tenant resolution strategy enum.
+///
+/// # Variants
+/// - `Header` - Tenant ID is sent in a request header.
+/// - `Subdomain` - Tenant ID is the subdomain of `tenancy.domain`,
+///   e.g. `acme.auth.example.com`.
+/// - `Path` - Tenant ID is the path segment after `tenancy.path_prefix`,
+///   e.g. `/t/acme/me`.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TenantStrategy {
+    #[default]
+    Header,
+    Subdomain,
+    Path,
+}
+
+/// ## Tenancy settings struct.
+///
+/// Users, sessions and audit records belong to a tenant.
+/// When disabled, everything belongs to the `default`
+/// tenant.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Resolve the tenant of every request.
+/// + `strategy`: `TenantStrategy` - Where the tenant ID is read from.
+/// + `header`: `String` - Header of the `header` strategy.
+/// + `domain`: `String` - Parent domain of the `subdomain` strategy.
+/// + `path_prefix`: `String` - Prefix of the `path` strategy.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{TenancySettings, TenantStrategy};
+///
+/// let tenancy_settings = TenancySettings {
+///   enabled: true,
+///   strategy: TenantStrategy::Subdomain,
+///   domain: "auth.example.com".to_string(),
+///   ..TenancySettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct TenancySettings {
+    pub enabled: bool,
+    pub strategy: TenantStrategy,
+    pub header: String,
+    pub domain: String,
+    pub path_prefix: String,
+}
+
+impl Default for TenancySettings {
+    fn default() -> Self {
+        TenancySettings {
+            enabled: false,
+            strategy: TenantStrategy::Header,
+            header: "x-tenant-id".to_string(),
+            domain: String::new(),
+            path_prefix: "/t".to_string(),
+        }
+    }
+}
+
+impl TenancySettings {
+    /// ## Validates the tenancy settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the header name is invalid, the
+    ///      `subdomain` strategy has no domain or the path prefix
+    ///      does not start with `/`.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let header = !self.header.is_empty()
+            && self
+                .header
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !header {
+            return Err(AppError::config(format!(
+                "'{}' is not a valid header name for tenancy.header",
+                self.header
+            )));
+        }
+        if self.strategy == TenantStrategy::Subdomain && self.domain.trim_matches('.').is_empty() {
+            return Err(AppError::config(
+                "Tenancy strategy 'subdomain' requires tenancy.domain",
+            ));
+        }
+        let prefix = self.path_prefix.strip_prefix('/');
+        if prefix.is_none_or(|prefix| prefix.is_empty() || prefix.ends_with('/')) {
+            return Err(AppError::config(format!(
+                "'{}' is not a valid tenancy.path_prefix, use e.g. '/t'",
+                self.path_prefix
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if the tenancy header, domain and path prefix are validated.
+    #[test]
+    fn test_tenancy_validate() {
+        assert!(TenancySettings::default().validate().is_ok());
+
+        let subdomain = TenancySettings {
+            strategy: TenantStrategy::Subdomain,
+            ..TenancySettings::default()
+        };
+
+        assert_eq!(
+            subdomain.validate().unwrap_err().message,
+            "Tenancy strategy 'subdomain' requires tenancy.domain"
+        );
+
+        let header = TenancySettings {
+            header: "x tenant".to_string(),
+            ..TenancySettings::default()
+        };
+
+        assert!(header.validate().is_err());
+
+        for prefix in ["", "/", "t", "/t/"] {
+            let path = TenancySettings {
+                path_prefix: prefix.to_string(),
+                ..TenancySettings::default()
+            };
+
+            assert!(path.validate().is_err(), "{}", prefix);
+        }
+    }
+}
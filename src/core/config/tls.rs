@@ -0,0 +1,488 @@
+//! TLS policy module.
+//!
+//! Module resolves the protocol versions and cipher
+//! suites of the `[server.tls]` section and reports
+//! settings weaker than the configured preset. The
+//! section itself is `TlsSettings`.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Local imports
+use super::AppSettings;
+use crate::core::err::AppError;
+
+/// TLS 1.3 cipher suites supported by the ring provider.
+pub const TLS13_CIPHER_SUITES: &[&str] = &[
+    "TLS13_AES_256_GCM_SHA384",
+    "TLS13_AES_128_GCM_SHA256",
+    "TLS13_CHACHA20_POLY1305_SHA256",
+];
+
+/// TLS 1.2 cipher suites supported by the ring provider,
+/// all of them use ECDHE and an AEAD cipher.
+pub const TLS12_CIPHER_SUITES: &[&str] = &[
+    "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+];
+
+/// Parts of cipher suite names known to be weak.
+pub const WEAK_CIPHER_MARKERS: &[&str] = &["NULL", "EXPORT", "ANON", "RC4", "DES", "MD5", "CBC"];
+
+/// ## TLS policy preset enum.
+///
+/// Presets follow the Mozilla server side TLS
+/// recommendations.
+///
+/// # Variants
+/// - `Modern` - TLS 1.3 only.
+/// - `Intermediate` - TLS 1.2 and 1.3 with forward secret AEAD suites.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsPolicy {
+    Modern,
+    #[default]
+    Intermediate,
+}
+
+impl TlsPolicy {
+    /// ## Returns the name used in the configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TlsPolicy::Modern => "modern",
+            TlsPolicy::Intermediate => "intermediate",
+        }
+    }
+
+    /// ## Returns the lowest protocol version of the preset.
+    pub fn min_version(&self) -> TlsVersion {
+        match self {
+            TlsPolicy::Modern => TlsVersion::Tls13,
+            TlsPolicy::Intermediate => TlsVersion::Tls12,
+        }
+    }
+
+    /// ## Returns the cipher suites of the preset, strongest first.
+    pub fn cipher_suites(&self) -> Vec<&'static str> {
+        match self {
+            TlsPolicy::Modern => TLS13_CIPHER_SUITES.to_vec(),
+            TlsPolicy::Intermediate => [TLS13_CIPHER_SUITES, TLS12_CIPHER_SUITES].concat(),
+        }
+    }
+}
+
+/// ## TLS protocol version enum.
+///
+/// Versions before 1.2 can be configured only to be
+/// rejected with a clear message, they are never served.
+///
+/// # Variants
+/// - `Tls10` - `1.0`, deprecated by RFC 8996.
+/// - `Tls11` - `1.1`, deprecated by RFC 8996.
+/// - `Tls12` - `1.2`.
+/// - `Tls13` - `1.3`.
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+pub enum TlsVersion {
+    #[serde(rename = "1.0")]
+    Tls10,
+    #[serde(rename = "1.1")]
+    Tls11,
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    /// ## Returns the name used in the configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TlsVersion::Tls10 => "1.0",
+            TlsVersion::Tls11 => "1.1",
+            TlsVersion::Tls12 => "1.2",
+            TlsVersion::Tls13 => "1.3",
+        }
+    }
+}
+
+/// ## Weak TLS setting struct.
+///
+/// ## Fields
+/// + `key`: `&'static str` - Dotted path of the setting.
+/// + `value`: `String` - Configured value.
+/// + `reason`: `String` - Why the value is rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsWeakness {
+    pub key: &'static str,
+    pub value: String,
+    pub reason: String,
+}
+
+impl fmt::Display for TlsWeakness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = \"{}\": {}", self.key, self.value, self.reason)
+    }
+}
+
+/// ## Active TLS policy struct.
+///
+/// Policy actually served, weak settings are left out.
+///
+/// ## Fields
+/// + `policy`: `TlsPolicy` - Configured preset.
+/// + `min_version`: `TlsVersion` - Lowest protocol version accepted.
+/// + `cipher_suites`: `Vec<String>` - Cipher suites offered, strongest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveTlsPolicy {
+    pub policy: TlsPolicy,
+    pub min_version: TlsVersion,
+    pub cipher_suites: Vec<String>,
+}
+
+/// ## Finds the weak settings of the TLS configuration.
+///
+/// A setting is weak when it allows a deprecated protocol
+/// version, names a weak or unsupported cipher suite or is
+/// weaker than the configured preset.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::TlsSettings;
+/// use axum_auth::core::config::tls::{weaknesses, TlsVersion};
+///
+/// let settings = TlsSettings {
+///     min_version: Some(TlsVersion::Tls10),
+///     cipher_suites: vec!["TLS_RSA_WITH_3DES_EDE_CBC_SHA".to_string()],
+///     ..TlsSettings::default()
+/// };
+///
+/// assert_eq!(weaknesses(&settings).len(), 2);
+/// ```
+///
+/// ## Parameters
+/// - `settings`: `&TlsSettings` - TLS settings.
+///
+/// ## Returns
+/// - `Vec<TlsWeakness>`: Weak settings, empty when the configuration is sound.
+pub fn weaknesses(settings: &TlsSettings) -> Vec<TlsWeakness> {
+    let mut weaknesses = Vec::new();
+    let policy = settings.policy;
+
+    if let Some(version) = settings.min_version {
+        let reason = if version < TlsVersion::Tls12 {
+            Some("TLS 1.0 and 1.1 are deprecated".to_string())
+        } else if version < policy.min_version() {
+            Some(format!(
+                "{} policy requires TLS {} or later",
+                policy.as_str(),
+                policy.min_version().as_str()
+            ))
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            weaknesses.push(TlsWeakness {
+                key: "server.tls.min_version",
+                value: version.as_str().to_string(),
+                reason,
+            });
+        }
+    }
+
+    let min_version = min_version(settings);
+    for suite in &settings.cipher_suites {
+        let name = suite.to_uppercase();
+        let reason = if is_weak_cipher(&name) {
+            "cipher suite is weak".to_string()
+        } else if !TLS13_CIPHER_SUITES.contains(&name.as_str())
+            && !TLS12_CIPHER_SUITES.contains(&name.as_str())
+        {
+            "cipher suite is not supported".to_string()
+        } else if !policy.cipher_suites().contains(&name.as_str()) {
+            format!("{} policy does not allow TLS 1.2 suites", policy.as_str())
+        } else if min_version == TlsVersion::Tls13 && !name.starts_with("TLS13_") {
+            "TLS 1.2 suite is never used with TLS 1.3 only".to_string()
+        } else {
+            continue;
+        };
+
+        weaknesses.push(TlsWeakness {
+            key: "server.tls.cipher_suites",
+            value: suite.clone(),
+            reason,
+        });
+    }
+
+    weaknesses
+}
+
+/// ## Resolves the policy actually served.
+///
+/// Weak settings are left out: the protocol version
+/// is never lower than the preset's and only suites of
+/// the preset are offered. Preset suites are used when
+/// no configured suite is left.
+///
+/// ## Parameters
+/// - `settings`: `&TlsSettings` - TLS settings.
+///
+/// ## Returns
+/// - `ActiveTlsPolicy`: Policy to serve.
+pub fn active_policy(settings: &TlsSettings) -> ActiveTlsPolicy {
+    let policy = settings.policy;
+    let min_version = min_version(settings);
+    let allowed = |name: &str| {
+        policy.cipher_suites().contains(&name)
+            && (min_version < TlsVersion::Tls13 || name.starts_with("TLS13_"))
+    };
+
+    let mut cipher_suites: Vec<String> = settings
+        .cipher_suites
+        .iter()
+        .map(|suite| suite.to_uppercase())
+        .filter(|suite| allowed(suite))
+        .collect();
+    if cipher_suites.is_empty() {
+        cipher_suites = policy
+            .cipher_suites()
+            .into_iter()
+            .filter(|suite| allowed(suite))
+            .map(str::to_string)
+            .collect();
+    }
+
+    ActiveTlsPolicy {
+        policy,
+        min_version,
+        cipher_suites,
+    }
+}
+
+/// ## Checks if the cipher suite is known to be weak.
+///
+/// Suites without forward secrecy and suites using SHA-1
+/// for the MAC are weak too.
+fn is_weak_cipher(name: &str) -> bool {
+    WEAK_CIPHER_MARKERS
+        .iter()
+        .any(|marker| name.contains(marker))
+        || name.ends_with("_SHA")
+        || name.starts_with("TLS_") && !name.contains("ECDHE")
+}
+
+/// ## Returns the lowest protocol version to serve.
+fn min_version(settings: &TlsSettings) -> TlsVersion {
+    let preset = settings.policy.min_version();
+
+    settings
+        .min_version
+        .map_or(preset, |version| version.max(preset))
+}
+
+/// ## TLS settings struct.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Serve HTTPS instead of HTTP.
+/// + `cert_path`: `String` - Path to the PEM certificate chain.
+/// + `key_path`: `String` - Path to the PEM private key.
+/// + `redirect_http_port`: `Option<u16>` - Port of the plain HTTP
+///   listener redirecting to HTTPS, no redirect when not set.
+/// + `policy`: `TlsPolicy` - Preset of protocol versions and cipher
+///   suites, `modern` or `intermediate`.
+/// + `min_version`: `Option<TlsVersion>` - Lowest protocol version,
+///   `1.2` or `1.3`, the preset's when not set.
+/// + `cipher_suites`: `Vec<String>` - Cipher suites to offer, a subset
+///   of the preset's, every suite of the preset when empty.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::TlsSettings;
+/// use axum_auth::core::config::tls::{TlsPolicy, TlsVersion};
+///
+/// let tls_settings = TlsSettings {
+///   enabled: true,
+///   cert_path: "./cert.pem".to_string(),
+///   key_path: "./key.pem".to_string(),
+///   redirect_http_port: Some(80),
+///   policy: TlsPolicy::Intermediate,
+///   min_version: Some(TlsVersion::Tls12),
+///   cipher_suites: vec![],
+/// };
+/// ```
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct TlsSettings {
+    pub enabled: bool,
+    pub cert_path: String,
+    pub key_path: String,
+    pub redirect_http_port: Option<u16>,
+    pub policy: TlsPolicy,
+    pub min_version: Option<TlsVersion>,
+    pub cipher_suites: Vec<String>,
+}
+
+impl TlsSettings {
+    /// ## Validates the TLS policy.
+    ///
+    /// Weak settings refuse the startup in production.
+    /// Elsewhere they are logged and left out of the
+    /// served policy, see `active_policy`.
+    ///
+    /// ## Parameters
+    /// - `app`: `&AppSettings` - Application settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If TLS is disabled, the policy is sound
+    ///      or the application does not run in production.
+    ///    - `Err(AppError)` - If a setting is weak in production,
+    ///      listing every weak setting.
+    pub fn validate(&self, app: &AppSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let weaknesses = weaknesses(self);
+        if weaknesses.is_empty() {
+            return Ok(());
+        }
+
+        if !app.is_production() {
+            for weakness in &weaknesses {
+                tracing::warn!("Ignoring weak TLS setting {}", weakness);
+            }
+            return Ok(());
+        }
+
+        let weaknesses: Vec<String> = weaknesses.iter().map(ToString::to_string).collect();
+
+        Err(AppError::config(format!(
+            "Weak TLS configuration refused in production: {}",
+            weaknesses.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::{ServerSettings, UnknownEnvVars};
+    use crate::core::err::ErrorKind;
+
+    // Test checks if weak settings are reported with their reason.
+    #[test]
+    fn test_weaknesses() {
+        let settings = TlsSettings {
+            policy: TlsPolicy::Modern,
+            min_version: Some(TlsVersion::Tls12),
+            cipher_suites: vec![
+                "TLS13_AES_256_GCM_SHA384".to_string(),
+                "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256".to_string(),
+                "TLS_RSA_WITH_AES_128_GCM_SHA256".to_string(),
+                "TLS13_AES_128_CCM_SHA256".to_string(),
+            ],
+            ..TlsSettings::default()
+        };
+
+        let reasons: Vec<String> = weaknesses(&settings)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            reasons,
+            [
+                "server.tls.min_version = \"1.2\": modern policy requires TLS 1.3 or later",
+                "server.tls.cipher_suites = \"TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256\": modern policy does not allow TLS 1.2 suites",
+                "server.tls.cipher_suites = \"TLS_RSA_WITH_AES_128_GCM_SHA256\": cipher suite is weak",
+                "server.tls.cipher_suites = \"TLS13_AES_128_CCM_SHA256\": cipher suite is not supported",
+            ]
+        );
+        assert!(weaknesses(&TlsSettings::default()).is_empty());
+    }
+
+    // Test checks if the active policy never goes below the preset.
+    #[test]
+    fn test_active_policy() {
+        let settings = TlsSettings {
+            min_version: Some(TlsVersion::Tls13),
+            cipher_suites: vec!["TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256".to_string()],
+            ..TlsSettings::default()
+        };
+
+        let active = active_policy(&settings);
+
+        assert_eq!(active.policy, TlsPolicy::Intermediate);
+        assert_eq!(active.min_version, TlsVersion::Tls13);
+        assert_eq!(active.cipher_suites, TLS13_CIPHER_SUITES);
+
+        let active = active_policy(&TlsSettings {
+            min_version: Some(TlsVersion::Tls11),
+            ..TlsSettings::default()
+        });
+
+        assert_eq!(active.min_version, TlsVersion::Tls12);
+        assert_eq!(active.cipher_suites.len(), 9);
+    }
+
+    // Test checks if missing TLS files are rejected only when TLS is enabled.
+    #[test]
+    fn test_server_validate_tls_missing_files() {
+        let mut settings = ServerSettings {
+            tls: TlsSettings {
+                enabled: false,
+                cert_path: "/path/to/missing.pem".to_string(),
+                key_path: "/path/to/missing.key".to_string(),
+                ..TlsSettings::default()
+            },
+            ..ServerSettings::default()
+        };
+
+        assert!(settings.validate().is_ok());
+
+        settings.tls.enabled = true;
+        let result = settings.validate().unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Validation);
+    }
+
+    // Test checks if weak TLS settings refuse the startup only in production.
+    #[test]
+    fn test_tls_validate_weak_settings() {
+        let mut app = AppSettings {
+            env: "dev".to_string(),
+            prefix: "APP_".to_string(),
+            env_file_path: ".env".to_string(),
+            unknown_env_vars: UnknownEnvVars::Strict,
+        };
+        let settings = TlsSettings {
+            enabled: true,
+            min_version: Some(TlsVersion::Tls10),
+            cipher_suites: vec!["TLS_RSA_WITH_RC4_128_SHA".to_string()],
+            ..TlsSettings::default()
+        };
+
+        assert!(settings.validate(&app).is_ok());
+
+        app.env = "production".to_string();
+        let result = settings.validate(&app).unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Config);
+        assert_eq!(
+            result.message,
+            "Weak TLS configuration refused in production: \
+             server.tls.min_version = \"1.0\": TLS 1.0 and 1.1 are deprecated; \
+             server.tls.cipher_suites = \"TLS_RSA_WITH_RC4_128_SHA\": cipher suite is weak"
+        );
+        assert!(TlsSettings::default().validate(&app).is_ok());
+    }
+}
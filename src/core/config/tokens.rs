@@ -0,0 +1,264 @@
+//! Token settings module.
+//!
+//! Issuer, audience, lifetime and signing key of the
+//! access tokens, and the scheduled rotation of the key.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use super::jobs::JobSettings;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::types::{AppType, HTTP_SCHEMES};
+
+/// ## Access token settings struct.
+///
+/// Access tokens are JWTs signed with an Ed25519 key,
+/// its public half is served on `/.well-known/jwks.json`.
+///
+/// ## Fields
+/// + `issuer`: `String` - URL of the server, the `iss` claim.
+/// + `audience`: `String` - Service the tokens are meant for, the `aud` claim.
+/// + `ttl`: `u64` - Seconds an access token is valid.
+/// + `key_id`: `String` - ID of the signing key, the `kid` header.
+/// + `signing_key`: `String` - Path to the PKCS#8 Ed25519 key, a
+///   temporary key is generated on every start when empty.
+/// + `email_change_ttl`: `u64` - Seconds a token confirming a new
+///   email address is valid.
+/// + `email_undo_ttl`: `u64` - Seconds the link sent to the old email
+///   address can undo the change.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::TokenSettings;
+///
+/// let token_settings = TokenSettings {
+///   issuer: "https://auth.example.com".to_string(),
+///   signing_key: "/etc/axum-auth/signing.pem".to_string(),
+///   ..TokenSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct TokenSettings {
+    pub issuer: String,
+    pub audience: String,
+    pub ttl: u64,
+    pub key_id: String,
+    pub signing_key: String,
+    pub email_change_ttl: u64,
+    pub email_undo_ttl: u64,
+}
+
+impl Default for TokenSettings {
+    fn default() -> Self {
+        TokenSettings {
+            issuer: "http://localhost:8080".to_string(),
+            audience: "axum-auth".to_string(),
+            ttl: 900,
+            key_id: "default".to_string(),
+            signing_key: String::new(),
+            email_change_ttl: 86400,
+            email_undo_ttl: 604800,
+        }
+    }
+}
+
+impl TokenSettings {
+    /// ## Validates the token settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the issuer is not a URL, the audience
+    ///      or key ID is empty, a lifetime is zero or the key file
+    ///      does not exist.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let invalid = |message: &str| AppError::new(ErrorKind::Config, message.to_string(), None);
+
+        if AppType::Url(HTTP_SCHEMES).verify(&self.issuer).is_err() {
+            return Err(invalid("Token issuer must be an http:// or https:// URL"));
+        }
+        if self.audience.trim().is_empty() {
+            return Err(invalid("Token audience must not be empty"));
+        }
+        if self.key_id.trim().is_empty() {
+            return Err(invalid("Token key ID must not be empty"));
+        }
+        if self.ttl == 0 {
+            return Err(invalid("Access tokens must be valid for at least 1 second"));
+        }
+        if self.email_change_ttl == 0 {
+            return Err(invalid(
+                "Email change tokens must be valid for at least 1 second",
+            ));
+        }
+        if self.email_undo_ttl == 0 {
+            return Err(invalid(
+                "Email undo tokens must be valid for at least 1 second",
+            ));
+        }
+        if !self.signing_key.is_empty() {
+            AppType::FilePath.verify(&self.signing_key)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Key rotation settings struct.
+///
+/// Signing keys are stored in the database, sealed with
+/// the master key of the `enc:` values, the worker
+/// replaces the current key on the schedule. Retired keys
+/// still verify tokens during the grace period and stay
+/// published on `/.well-known/jwks.json` until then.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Store the keys and register the rotation job,
+///   needs the master key in `CONFIG_KEY` or `CONFIG_KEY_FILE`.
+/// + `schedule`: `String` - Cron expression of the rotations.
+/// + `grace`: `u64` - Seconds a retired key still verifies tokens.
+/// + `refresh`: `u64` - Seconds a server keeps the loaded keys before
+///   it reloads them.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::KeyRotationSettings;
+///
+/// let key_rotation_settings = KeyRotationSettings {
+///   enabled: true,
+///   schedule: "0 2 * * mon".to_string(),
+///   ..KeyRotationSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct KeyRotationSettings {
+    pub enabled: bool,
+    pub schedule: String,
+    pub grace: u64,
+    pub refresh: u64,
+}
+
+impl Default for KeyRotationSettings {
+    fn default() -> Self {
+        KeyRotationSettings {
+            enabled: false,
+            schedule: "0 0 1 * *".to_string(),
+            grace: 86400,
+            refresh: 300,
+        }
+    }
+}
+
+impl KeyRotationSettings {
+    /// ## Validates the rotation settings when the rotation is enabled.
+    ///
+    /// ## Parameters
+    /// - `jobs`: `&JobSettings` - Timezone the schedule is evaluated in.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the schedule is invalid, or the grace
+    ///      period or the refresh interval is zero.
+    pub fn validate(&self, jobs: &JobSettings) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        jobs.schedule("key_rotation.schedule", &self.schedule)?;
+        if self.grace == 0 || self.refresh == 0 {
+            return Err(AppError::config(
+                "Key grace period and refresh interval must be at least 1 second",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if the token issuer, audience and lifetime are validated.
+    #[test]
+    fn test_tokens_validate() {
+        assert!(TokenSettings::default().validate().is_ok());
+
+        let relative = TokenSettings {
+            issuer: "auth.example.com".to_string(),
+            ..TokenSettings::default()
+        };
+
+        assert_eq!(
+            relative.validate().unwrap_err().message,
+            "Token issuer must be an http:// or https:// URL"
+        );
+
+        let forever = TokenSettings {
+            ttl: 0,
+            ..TokenSettings::default()
+        };
+
+        assert!(forever.validate().is_err());
+
+        let unconfirmable = TokenSettings {
+            email_change_ttl: 0,
+            ..TokenSettings::default()
+        };
+
+        assert!(unconfirmable.validate().is_err());
+
+        let irreversible = TokenSettings {
+            email_undo_ttl: 0,
+            ..TokenSettings::default()
+        };
+
+        assert!(irreversible.validate().is_err());
+
+        let missing = TokenSettings {
+            signing_key: "/nonexistent/signing.pem".to_string(),
+            ..TokenSettings::default()
+        };
+
+        assert!(missing.validate().is_err());
+    }
+
+    // Test checks if the rotation schedule and periods are only validated when enabled.
+    #[test]
+    fn test_key_rotation_validate() {
+        let jobs = JobSettings::default();
+        let disabled = KeyRotationSettings {
+            schedule: "never".to_string(),
+            ..KeyRotationSettings::default()
+        };
+        assert!(disabled.validate(&jobs).is_ok());
+
+        let enabled = KeyRotationSettings {
+            enabled: true,
+            ..KeyRotationSettings::default()
+        };
+        assert!(enabled.validate(&jobs).is_ok());
+        assert!(KeyRotationSettings {
+            schedule: "never".to_string(),
+            ..enabled.clone()
+        }
+        .validate(&jobs)
+        .unwrap_err()
+        .message
+        .starts_with("key_rotation.schedule: "));
+        assert_eq!(
+            KeyRotationSettings {
+                refresh: 0,
+                ..enabled
+            }
+            .validate(&jobs)
+            .unwrap_err()
+            .message,
+            "Key grace period and refresh interval must be at least 1 second"
+        );
+    }
+}
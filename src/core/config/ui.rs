@@ -0,0 +1,308 @@
+//! UI settings module.
+//!
+//! Embedded login and consent pages, and the files served
+//! from a directory.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use super::auth::{AuthMode, AuthSettings};
+use super::login::{DeviceCodeSettings, MagicLinkSettings};
+use crate::core::err::AppError;
+use crate::core::types::{AppType, HTTP_SCHEMES};
+
+/// ## Embedded UI settings struct.
+///
+/// Pages under `/ui` let users sign in with a magic link
+/// and approve devices without a frontend of their own.
+/// Point `magic_link.url` at `/ui/verify` to land the
+/// links on the embedded page. Registration and password
+/// reset have no pages, they have no public routes.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Serve the pages, needs the `ui` feature.
+/// + `title`: `String` - Name shown in the title and header of the pages.
+/// + `logo_url`: `String` - Logo shown above the forms, URL or path
+///   of a static file, none when empty.
+/// + `primary_color`: `String` - Color of buttons and links, `#rrggbb`.
+/// + `background_color`: `String` - Color of the page, `#rrggbb`.
+/// + `stylesheet_url`: `String` - Stylesheet loaded after the built-in
+///   one, URL or path of a static file, none when empty.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::UiSettings;
+///
+/// let ui_settings = UiSettings {
+///   enabled: true,
+///   title: "Acme".to_string(),
+///   primary_color: "#e11d48".to_string(),
+///   ..UiSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct UiSettings {
+    pub enabled: bool,
+    pub title: String,
+    pub logo_url: String,
+    pub primary_color: String,
+    pub background_color: String,
+    pub stylesheet_url: String,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        UiSettings {
+            enabled: false,
+            title: "Sign in".to_string(),
+            logo_url: String::new(),
+            primary_color: "#2563eb".to_string(),
+            background_color: "#f8fafc".to_string(),
+            stylesheet_url: String::new(),
+        }
+    }
+}
+
+impl UiSettings {
+    /// ## Validates the UI settings when the pages are enabled.
+    ///
+    /// ## Parameters
+    /// - `auth`: `&AuthSettings` - Pages need the `session` auth mode.
+    /// - `magic_link`: `&MagicLinkSettings` - Login served by the pages.
+    /// - `device_code`: `&DeviceCodeSettings` - Consent served by the pages.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If sessions are not cookies, neither flow is
+    ///      enabled, a color or URL is invalid, or the pages were not
+    ///      compiled in.
+    pub fn validate(
+        &self,
+        auth: &AuthSettings,
+        magic_link: &MagicLinkSettings,
+        device_code: &DeviceCodeSettings,
+    ) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if auth.mode != AuthMode::Session {
+            return Err(AppError::config(
+                "UI requires the 'session' auth mode, browsers keep the session in a cookie",
+            ));
+        }
+        if !magic_link.enabled && !device_code.enabled {
+            return Err(AppError::config(
+                "UI needs magic_link or device_code to be enabled",
+            ));
+        }
+        if self.title.trim().is_empty() {
+            return Err(AppError::config("UI title can not be empty"));
+        }
+        for (name, color) in [
+            ("primary_color", &self.primary_color),
+            ("background_color", &self.background_color),
+        ] {
+            if !is_hex_color(color) {
+                return Err(AppError::config(format!(
+                    "ui.{} '{}' is not a #rrggbb color",
+                    name, color
+                )));
+            }
+        }
+        for (name, url) in [
+            ("logo_url", &self.logo_url),
+            ("stylesheet_url", &self.stylesheet_url),
+        ] {
+            // Paths point at the static files of the service
+            let path = url.starts_with('/') && !url.starts_with("//");
+            if !url.is_empty() && !path && AppType::Url(HTTP_SCHEMES).verify(url).is_err() {
+                return Err(AppError::config(format!(
+                    "ui.{} '{}' is not a valid HTTP(S) URL or path",
+                    name, url
+                )));
+            }
+        }
+        if !cfg!(feature = "ui") {
+            return Err(AppError::config("UI requires the 'ui' feature"));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Checks if the value is a `#rrggbb` color.
+fn is_hex_color(value: &str) -> bool {
+    value
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// ## Static files settings struct.
+///
+/// Files of the directory are served below `path` with
+/// an `ETag` and `Last-Modified`, so browsers revalidate
+/// them cheaply. Names carrying a content hash, e.g.
+/// `app.3f9a1c2b.js`, never change and are cached for a
+/// year. Hidden files are never served.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Serve the files of the directory.
+/// + `dir`: `String` - Directory the files are served from.
+/// + `path`: `String` - Path prefix of the files, e.g. `/static`.
+/// + `max_age`: `u64` - Seconds browsers may cache files without a hash.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::StaticFilesSettings;
+///
+/// let static_files_settings = StaticFilesSettings {
+///   enabled: true,
+///   dir: "./public".to_string(),
+///   ..StaticFilesSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct StaticFilesSettings {
+    pub enabled: bool,
+    pub dir: String,
+    pub path: String,
+    pub max_age: u64,
+}
+
+impl Default for StaticFilesSettings {
+    fn default() -> Self {
+        StaticFilesSettings {
+            enabled: false,
+            dir: "./static".to_string(),
+            path: "/static".to_string(),
+            max_age: 3600,
+        }
+    }
+}
+
+impl StaticFilesSettings {
+    /// ## Validates the static files settings when they are served.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the path is not a plain absolute prefix
+    ///      or the directory can not be read.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let plain = self.path.len() > 1
+            && self.path.starts_with('/')
+            && !self.path.ends_with('/')
+            && !self.path.contains(['*', ':', '{', '}']);
+        if !plain {
+            return Err(AppError::config(format!(
+                "static_files.path '{}' has to be a path like '/static'",
+                self.path
+            )));
+        }
+        AppType::DirPath { writable: false }.verify(&self.dir)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if the UI needs cookie sessions, a flow, hex colors and HTTP(S) URLs.
+    #[test]
+    fn test_ui_validate() {
+        let auth = AuthSettings {
+            mode: AuthMode::Session,
+            ..AuthSettings::default()
+        };
+        let magic_link = MagicLinkSettings {
+            enabled: true,
+            ..MagicLinkSettings::default()
+        };
+        let device_code = DeviceCodeSettings::default();
+        let settings = UiSettings {
+            enabled: true,
+            ..UiSettings::default()
+        };
+
+        assert!(UiSettings::default()
+            .validate(&auth, &MagicLinkSettings::default(), &device_code)
+            .is_ok());
+        assert!(settings
+            .validate(&auth, &MagicLinkSettings::default(), &device_code)
+            .is_err());
+        assert!(settings
+            .validate(&AuthSettings::default(), &magic_link, &device_code)
+            .is_err());
+        assert_eq!(
+            settings.validate(&auth, &magic_link, &device_code).is_ok(),
+            cfg!(feature = "ui")
+        );
+        assert_eq!(
+            UiSettings {
+                primary_color: "blue".to_string(),
+                ..settings.clone()
+            }
+            .validate(&auth, &magic_link, &device_code)
+            .unwrap_err()
+            .message,
+            "ui.primary_color 'blue' is not a #rrggbb color"
+        );
+        assert_eq!(
+            UiSettings {
+                logo_url: "ftp://example.com/logo.png".to_string(),
+                ..settings.clone()
+            }
+            .validate(&auth, &magic_link, &device_code)
+            .unwrap_err()
+            .message,
+            "ui.logo_url 'ftp://example.com/logo.png' is not a valid HTTP(S) URL or path"
+        );
+        assert_eq!(
+            UiSettings {
+                stylesheet_url: "/static/theme.css".to_string(),
+                ..settings
+            }
+            .validate(&auth, &magic_link, &device_code)
+            .is_ok(),
+            cfg!(feature = "ui")
+        );
+    }
+
+    // Test checks if the static files need a plain path and a readable directory.
+    #[test]
+    fn test_static_files_validate() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = StaticFilesSettings {
+            enabled: true,
+            dir: dir.path().to_str().unwrap().to_string(),
+            ..StaticFilesSettings::default()
+        };
+
+        assert!(StaticFilesSettings::default().validate().is_ok());
+        assert!(settings.validate().is_ok());
+        for path in ["", "/", "static", "/static/", "/static/*path"] {
+            let invalid = StaticFilesSettings {
+                path: path.to_string(),
+                ..settings.clone()
+            };
+
+            assert!(invalid.validate().is_err(), "{}", path);
+        }
+        assert!(StaticFilesSettings {
+            dir: "/path/to/dir/that/does/not/exist".to_string(),
+            ..settings
+        }
+        .validate()
+        .is_err());
+    }
+}
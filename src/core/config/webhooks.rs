@@ -0,0 +1,342 @@
+//! Webhook settings module.
+//!
+//! Endpoints of the account lifecycle webhooks and the
+//! queue the events wait in.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Local imports
+use super::jobs::JobSettings;
+use crate::api::webhooks::WebhookEvent;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::types::{secret::Secret, AppType, HTTP_SCHEMES};
+
+/// Minimum length of a webhook signing secret.
+pub const MIN_WEBHOOK_SECRET_LEN: usize = 16;
+
+/// ## Webhook settings struct.
+///
+/// Events are queued in memory and posted by the server
+/// right away. Events that do not fit into the queue, and
+/// failed deliveries, are stored in the database and
+/// delivered by a job of the `worker` run mode. Failed
+/// deliveries are retried with exponential backoff and
+/// moved to the dead-letter table after the last attempt.
+///
+/// ## Fields
+/// + `enabled`: `bool` - Queue events and register the delivery job.
+/// + `schedule`: `String` - Cron expression of the delivery runs.
+/// + `timeout`: `u64` - Seconds an endpoint may take to respond.
+/// + `max_attempts`: `u32` - Attempts before a delivery is dead-lettered.
+/// + `backoff`: `u64` - Seconds before the first retry, doubled on
+///   every further retry.
+/// + `max_backoff`: `u64` - Upper limit of the seconds between retries.
+/// + `endpoints`: `Vec<WebhookEndpoint>` - Endpoints the events are sent to.
+/// + `queue`: `QueueSettings` - Queue between the requests and the sender.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{WebhookEndpoint, WebhookSettings};
+///
+/// let webhook_settings = WebhookSettings {
+///   enabled: true,
+///   endpoints: vec![WebhookEndpoint {
+///     name: "crm".to_string(),
+///     url: "https://crm.example.com/hooks/auth".to_string(),
+///     secret: "0123456789abcdef0123456789abcdef".into(),
+///     events: vec!["user.created".to_string(), "user.deleted".to_string()],
+///   }],
+///   ..WebhookSettings::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct WebhookSettings {
+    pub enabled: bool,
+    pub schedule: String,
+    pub timeout: u64,
+    pub max_attempts: u32,
+    pub backoff: u64,
+    pub max_backoff: u64,
+    pub endpoints: Vec<WebhookEndpoint>,
+    pub queue: QueueSettings,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        WebhookSettings {
+            enabled: false,
+            schedule: "*/10 * * * * *".to_string(),
+            timeout: 10,
+            max_attempts: 8,
+            backoff: 30,
+            max_backoff: 3600,
+            endpoints: Vec::new(),
+            queue: QueueSettings::default(),
+        }
+    }
+}
+
+impl WebhookSettings {
+    /// ## Validates the webhook settings when webhooks are enabled.
+    ///
+    /// ## Parameters
+    /// - `jobs`: `&JobSettings` - Timezone the schedule is evaluated in.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid or webhooks are disabled.
+    ///    - `Err(AppError)` - If the schedule is invalid, the timeout or
+    ///      the attempts are zero, or an endpoint is invalid or defined twice.
+    pub fn validate(&self, jobs: &JobSettings) -> Result<(), AppError> {
+        let invalid = |message: String| AppError::new(ErrorKind::Config, message, None);
+
+        if !self.enabled {
+            return Ok(());
+        }
+        jobs.schedule("webhooks.schedule", &self.schedule)?;
+        if self.timeout == 0 {
+            return Err(invalid(
+                "Webhook timeout must be at least 1 second".to_string(),
+            ));
+        }
+        if self.max_attempts == 0 {
+            return Err(invalid(
+                "Webhook deliveries need at least 1 attempt".to_string(),
+            ));
+        }
+        if self.endpoints.is_empty() {
+            return Err(invalid(
+                "Webhooks require at least one endpoint".to_string(),
+            ));
+        }
+        self.queue.validate("webhooks.queue")?;
+
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            if endpoint.name.trim().is_empty() {
+                return Err(invalid(
+                    "Webhook endpoint name must not be empty".to_string(),
+                ));
+            }
+            if self.endpoints[..index]
+                .iter()
+                .any(|other| other.name == endpoint.name)
+            {
+                return Err(invalid(format!(
+                    "Webhook endpoint '{}' is defined twice",
+                    endpoint.name
+                )));
+            }
+            if AppType::Url(HTTP_SCHEMES).verify(&endpoint.url).is_err() {
+                return Err(invalid(format!(
+                    "Webhook endpoint '{}' must have an http:// or https:// URL",
+                    endpoint.name
+                )));
+            }
+            if endpoint.secret.expose().len() < MIN_WEBHOOK_SECRET_LEN {
+                return Err(invalid(format!(
+                    "Secret of webhook endpoint '{}' must have at least {} characters",
+                    endpoint.name, MIN_WEBHOOK_SECRET_LEN
+                )));
+            }
+            for event in &endpoint.events {
+                event.parse::<WebhookEvent>().map_err(|_| {
+                    invalid(format!(
+                        "Webhook endpoint '{}' subscribes to unknown event '{}'",
+                        endpoint.name, event
+                    ))
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Webhook endpoint struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the endpoint, stored with its deliveries.
+/// + `url`: `String` - URL the events are posted to.
+/// + `secret`: `Secret<String>` - Key of the HMAC-SHA256 signature.
+/// + `events`: `Vec<String>` - Names of the events sent to the
+///   endpoint, every event when empty.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+pub struct WebhookEndpoint {
+    pub name: String,
+    pub url: String,
+    pub secret: Secret<String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl WebhookEndpoint {
+    /// ## Checks if the endpoint receives the event.
+    pub fn subscribes(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.iter().any(|name| name == event.as_str())
+    }
+}
+
+/// ## Notification queue settings struct.
+///
+/// Bounds the memory held by outbound notifications
+/// when the receiving server is slow.
+///
+/// ## Fields
+/// + `capacity`: `usize` - Messages held in memory, `0` spills every
+///   message with the `spill` policy.
+/// + `overflow`: `OverflowPolicy` - What happens to messages once the
+///   queue is full.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{OverflowPolicy, QueueSettings};
+///
+/// let queue_settings = QueueSettings {
+///   capacity: 100,
+///   overflow: OverflowPolicy::Block,
+/// };
+///
+/// assert!(queue_settings.validate("webhooks.queue").is_ok());
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct QueueSettings {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        QueueSettings {
+            capacity: 1000,
+            overflow: OverflowPolicy::Spill,
+        }
+    }
+}
+
+impl QueueSettings {
+    /// ## Validates the queue settings.
+    ///
+    /// ## Parameters
+    /// - `key`: `&str` - Key of the settings, prefixed to errors.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///    - `Ok(())` - If the settings are valid.
+    ///    - `Err(AppError)` - If the capacity is zero and messages
+    ///      are not spilled.
+    pub fn validate(&self, key: &str) -> Result<(), AppError> {
+        if self.capacity == 0 && self.overflow != OverflowPolicy::Spill {
+            return Err(AppError::new(
+                ErrorKind::Config,
+                format!("{}: capacity 0 requires the 'spill' overflow policy", key),
+                None,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Overflow policy of a notification queue.
+///
+/// # Variants
+/// - `DropOldest` - Oldest queued message is dropped.
+/// - `Block` - Producer waits until the sender made room.
+/// - `Spill` - Message is stored in the database and sent by the worker.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    DropOldest,
+    Block,
+    #[default]
+    Spill,
+}
+
+impl OverflowPolicy {
+    /// ## Returns the name used in the configuration and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverflowPolicy::DropOldest => "drop_oldest",
+            OverflowPolicy::Block => "block",
+            OverflowPolicy::Spill => "spill",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if webhook endpoints are validated only when webhooks are enabled.
+    #[test]
+    fn test_webhooks_validate() {
+        let endpoint = WebhookEndpoint {
+            name: "crm".to_string(),
+            url: "https://crm.example.com/hooks".to_string(),
+            secret: "0123456789abcdef".into(),
+            events: vec!["user.deleted".to_string()],
+        };
+        let settings = |endpoints: Vec<WebhookEndpoint>| WebhookSettings {
+            enabled: true,
+            endpoints,
+            ..WebhookSettings::default()
+        };
+        let jobs = JobSettings::default();
+
+        assert!(settings(vec![endpoint.clone()]).validate(&jobs).is_ok());
+        assert!(WebhookSettings::default().validate(&jobs).is_ok());
+
+        assert_eq!(
+            settings(Vec::new()).validate(&jobs).unwrap_err().message,
+            "Webhooks require at least one endpoint"
+        );
+        assert_eq!(
+            settings(vec![endpoint.clone(), endpoint.clone()])
+                .validate(&jobs)
+                .unwrap_err()
+                .message,
+            "Webhook endpoint 'crm' is defined twice"
+        );
+
+        let weak = WebhookEndpoint {
+            secret: "short".into(),
+            ..endpoint.clone()
+        };
+
+        assert_eq!(
+            settings(vec![weak]).validate(&jobs).unwrap_err().message,
+            "Secret of webhook endpoint 'crm' must have at least 16 characters"
+        );
+
+        let unknown = WebhookEndpoint {
+            events: vec!["user.renamed".to_string()],
+            ..endpoint
+        };
+
+        assert_eq!(
+            settings(vec![unknown]).validate(&jobs).unwrap_err().message,
+            "Webhook endpoint 'crm' subscribes to unknown event 'user.renamed'"
+        );
+    }
+
+    // Test checks if a queue without capacity must spill.
+    #[test]
+    fn test_queue_validate() {
+        let queue = |capacity, overflow| QueueSettings { capacity, overflow };
+
+        assert!(QueueSettings::default().validate("queue").is_ok());
+        assert!(queue(0, OverflowPolicy::Spill).validate("queue").is_ok());
+        assert!(queue(1, OverflowPolicy::Block).validate("queue").is_ok());
+        assert_eq!(
+            queue(0, OverflowPolicy::DropOldest)
+                .validate("webhooks.queue")
+                .unwrap_err()
+                .message,
+            "webhooks.queue: capacity 0 requires the 'spill' overflow policy"
+        );
+    }
+}
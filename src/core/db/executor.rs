@@ -0,0 +1,297 @@
+//! Database executor module.
+//!
+//! Executor routes writes to the primary and spreads
+//! reads round-robin over the read replicas. Replicas
+//! are probed periodically, dead ones are evicted from
+//! the rotation until they answer again. Without healthy
+//! replicas reads go to the primary.
+
+// Imports from external crates
+use sqlx::PgPool;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+// Local imports
+use super::{connect, connect_options, pool_options};
+use crate::core::config::DatabaseSettings;
+use crate::core::env::map::EnvMap;
+use crate::core::err::AppError;
+
+/// ## Database executor struct.
+///
+/// Cloning the executor shares the pools.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::db::DbExecutor;
+/// use sqlx::PgPool;
+///
+/// # async fn example(pool: PgPool) -> Result<(), sqlx::Error> {
+/// let db = DbExecutor::from(pool);
+///
+/// sqlx::query("SELECT 1").execute(db.read()).await?;
+/// sqlx::query("UPDATE users SET email_verified = TRUE").execute(db.write()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DbExecutor {
+    inner: Arc<Inner>,
+}
+
+/// Pools of the executor.
+#[derive(Debug)]
+struct Inner {
+    primary: PgPool,
+    replicas: Vec<Replica>,
+    next: AtomicUsize,
+    probe_timeout: Duration,
+}
+
+/// Read replica and its last health check result.
+#[derive(Debug)]
+struct Replica {
+    name: String,
+    pool: PgPool,
+    healthy: AtomicBool,
+}
+
+impl DbExecutor {
+    /// ## Creates an executor with read replicas.
+    ///
+    /// Replicas start healthy until the first check.
+    ///
+    /// ## Parameters
+    /// - `primary`: `PgPool` - Pool of the primary.
+    /// - `replicas`: `Vec<(String, PgPool)>` - Names and pools of the replicas.
+    pub fn new(primary: PgPool, replicas: Vec<(String, PgPool)>) -> Self {
+        Self::with_probe_timeout(primary, replicas, Duration::from_secs(5))
+    }
+
+    /// ## Creates an executor whose health checks time out after `probe_timeout`.
+    fn with_probe_timeout(
+        primary: PgPool,
+        replicas: Vec<(String, PgPool)>,
+        probe_timeout: Duration,
+    ) -> Self {
+        DbExecutor {
+            inner: Arc::new(Inner {
+                primary,
+                replicas: replicas
+                    .into_iter()
+                    .map(|(name, pool)| Replica {
+                        name,
+                        pool,
+                        healthy: AtomicBool::new(true),
+                    })
+                    .collect(),
+                next: AtomicUsize::new(0),
+                probe_timeout,
+            }),
+        }
+    }
+
+    /// ## Opens the primary and replica pools.
+    ///
+    /// Primary is opened with `connect`, including its
+    /// retries. Replicas connect lazily and are checked
+    /// once before the executor is returned, so a replica
+    /// that is down does not block startup.
+    ///
+    /// ## Parameters
+    /// - `env`: `&EnvMap` - Loaded and validated environment.
+    /// - `settings`: `&DatabaseSettings` - Pool settings and replicas.
+    ///
+    /// ## Returns
+    /// + `Result<DbExecutor, AppError>`
+    ///     - `Ok(DbExecutor)`: If the primary was opened.
+    ///     - `Err(AppError)`: If the primary is unreachable or a replica
+    ///       address is invalid.
+    pub async fn connect(env: &EnvMap, settings: &DatabaseSettings) -> Result<Self, AppError> {
+        let primary = connect(env, settings).await?;
+        let options = connect_options(env, settings)?;
+
+        let replicas = settings
+            .replica_addresses()?
+            .into_iter()
+            .map(|(host, port)| {
+                let name = match port {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.clone(),
+                };
+                let mut options = options.clone().host(&host);
+                if let Some(port) = port {
+                    options = options.port(port);
+                }
+
+                (name, pool_options(settings).connect_lazy_with(options))
+            })
+            .collect();
+
+        let executor = Self::with_probe_timeout(
+            primary,
+            replicas,
+            Duration::from_secs(settings.connect_timeout),
+        );
+        executor.check_replicas().await;
+
+        Ok(executor)
+    }
+
+    /// ## Returns the pool of the writes.
+    pub fn write(&self) -> &PgPool {
+        &self.inner.primary
+    }
+
+    /// ## Returns the pool of the next read.
+    ///
+    /// Healthy replicas take turns, the primary serves the
+    /// reads when no replica is healthy.
+    pub fn read(&self) -> &PgPool {
+        let replicas = &self.inner.replicas;
+        let start = self.inner.next.fetch_add(1, Ordering::Relaxed);
+
+        (0..replicas.len())
+            .map(|offset| &replicas[(start + offset) % replicas.len()])
+            .find(|replica| replica.healthy.load(Ordering::Relaxed))
+            .map_or(&self.inner.primary, |replica| &replica.pool)
+    }
+
+    /// ## Returns the names of the healthy replicas.
+    pub fn healthy_replicas(&self) -> Vec<&str> {
+        self.inner
+            .replicas
+            .iter()
+            .filter(|replica| replica.healthy.load(Ordering::Relaxed))
+            .map(|replica| replica.name.as_str())
+            .collect()
+    }
+
+    /// ## Checks every replica with `SELECT 1`.
+    ///
+    /// Replicas that fail or time out are evicted from the
+    /// rotation, evicted replicas that answer rejoin it.
+    /// Changes are logged.
+    pub async fn check_replicas(&self) {
+        for replica in &self.inner.replicas {
+            let probe = sqlx::query("SELECT 1").execute(&replica.pool);
+            let error = match tokio::time::timeout(self.inner.probe_timeout, probe).await {
+                Ok(Ok(_)) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(_) => Some("health check timed out".to_string()),
+            };
+
+            let healthy = error.is_none();
+            if replica.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+                match error {
+                    Some(error) => tracing::warn!(
+                        replica = %replica.name,
+                        error = %error,
+                        "Evicted database replica"
+                    ),
+                    None => tracing::info!(replica = %replica.name, "Database replica recovered"),
+                }
+            }
+        }
+    }
+
+    /// ## Checks the replicas periodically in the background.
+    ///
+    /// ## Parameters
+    /// - `interval`: `Duration` - Time between the checks.
+    ///
+    /// ## Returns
+    /// - `Option<JoinHandle<()>>`: Task of the checks, `None` without replicas.
+    pub fn start_health_checks(&self, interval: Duration) -> Option<JoinHandle<()>> {
+        if self.inner.replicas.is_empty() {
+            return None;
+        }
+
+        let executor = self.clone();
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                executor.check_replicas().await;
+            }
+        }))
+    }
+
+    /// ## Closes the primary and replica pools.
+    pub async fn close(&self) {
+        for replica in &self.inner.replicas {
+            replica.pool.close().await;
+        }
+        self.inner.primary.close().await;
+    }
+}
+
+impl From<PgPool> for DbExecutor {
+    fn from(pool: PgPool) -> Self {
+        Self::new(pool, Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+
+    fn pool(database: &str) -> PgPool {
+        PgPoolOptions::new()
+            .connect_lazy(&format!("postgres://localhost/{}", database))
+            .unwrap()
+    }
+
+    fn database(pool: &PgPool) -> String {
+        pool.connect_options()
+            .get_database()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    // Test checks if reads rotate over healthy replicas and fall back to the primary.
+    #[tokio::test]
+    async fn test_read_rotation() {
+        let db = DbExecutor::new(
+            pool("primary"),
+            vec![("a".to_string(), pool("a")), ("b".to_string(), pool("b"))],
+        );
+
+        let reads = (0..4).map(|_| database(db.read())).collect::<Vec<_>>();
+        assert_eq!(reads, ["a", "b", "a", "b"]);
+        assert_eq!(database(db.write()), "primary");
+
+        db.inner.replicas[0].healthy.store(false, Ordering::Relaxed);
+        assert!((0..4).all(|_| database(db.read()) == "b"));
+        assert_eq!(db.healthy_replicas(), ["b"]);
+
+        db.inner.replicas[1].healthy.store(false, Ordering::Relaxed);
+        assert_eq!(database(db.read()), "primary");
+    }
+
+    // Test checks if unreachable replicas are evicted by the health check.
+    #[tokio::test]
+    async fn test_check_replicas_evicts() {
+        let dead = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://127.0.0.1:1/replica")
+            .unwrap();
+        let db = DbExecutor::new(pool("primary"), vec![("dead".to_string(), dead)]);
+
+        db.check_replicas().await;
+
+        assert!(db.healthy_replicas().is_empty());
+        assert_eq!(database(db.read()), "primary");
+        assert!(DbExecutor::from(pool("primary"))
+            .start_health_checks(Duration::from_secs(1))
+            .is_none());
+    }
+}
@@ -0,0 +1,251 @@
+//! Database module.
+//!
+//! Module is responsible for building the Postgres
+//! connection pool from the validated environment
+//! variables and the `[database]` section. Opening
+//! the pool is retried with exponential backoff while
+//! the database is not reachable yet. `DbExecutor`
+//! routes reads to the read replicas, `tx` runs
+//! statements atomically, `seed` loads fixtures and
+//! `stats` records the duration of the statements.
+
+// References to submodules
+pub mod dsn;
+pub mod executor;
+#[cfg(feature = "server")]
+pub mod seed;
+#[cfg(feature = "server")]
+pub mod stats;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod tx;
+
+// Imports from external crates
+use log::LevelFilter;
+use ring::rand::{SecureRandom, SystemRandom};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPool, PgPoolOptions},
+    ConnectOptions,
+};
+use std::time::Duration;
+
+// Local imports
+use super::config::{DatabaseRetrySettings, DatabaseSettings};
+use super::env::map::EnvMap;
+use super::err::{AppError, ErrorKind};
+
+// Re-exports
+pub use dsn::Dsn;
+pub use executor::DbExecutor;
+pub use tx::with_transaction;
+
+/// ## Opens the database connection pool.
+///
+/// Function builds the connection options from the
+/// required environment variables and opens the pool
+/// sized by the `[database]` section. Transient failures
+/// are retried as configured in `[database.retry]`, every
+/// retry is logged with the attempt and the delay.
+///
+/// ## Parameters
+/// - `env`: `&EnvMap` - Loaded and validated environment.
+/// - `settings`: `&DatabaseSettings` - Pool sizes, timeouts and retries.
+///
+/// ## Returns
+/// + `Result<PgPool, AppError>`
+///     - `Ok(PgPool)`: If the pool was opened.
+///     - `Err(AppError)`: If the database is still unreachable after
+///       the last attempt, or the failure is not transient.
+pub async fn connect(env: &EnvMap, settings: &DatabaseSettings) -> Result<PgPool, AppError> {
+    let options = connect_options(env, settings)?;
+    let max_attempts = settings.retry.max_attempts.max(1);
+    let mut attempt = 1;
+
+    let error = loop {
+        match pool_options(settings).connect_with(options.clone()).await {
+            Ok(pool) => {
+                if attempt > 1 {
+                    tracing::info!(attempt, "Database connection established");
+                }
+                return Ok(pool);
+            }
+            Err(e) if attempt < max_attempts && is_transient(&e) => {
+                let delay = retry_delay(&settings.retry, attempt, random_share());
+                tracing::warn!(
+                    attempt,
+                    max_attempts,
+                    delay_ms = delay.as_millis() as u64,
+                    error = %e,
+                    "Database is not reachable, retrying"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => break e,
+        }
+    };
+
+    Err(AppError::new(
+        ErrorKind::Db,
+        format!(
+            "Failed to open database connection pool after {} attempt(s)",
+            attempt
+        ),
+        Some(Box::new(error)),
+    ))
+}
+
+/// ## Applies the pending migrations.
+///
+/// Migrations from the `migrations` directory are
+/// embedded into the binary at compile time.
+///
+/// ## Parameters
+/// - `pool`: `&PgPool` - Connection pool.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the schema is up to date.
+///     - `Err(AppError)`: If a migration failed.
+pub async fn migrate(pool: &PgPool) -> Result<(), AppError> {
+    sqlx::migrate!("./migrations").run(pool).await.map_err(|e| {
+        AppError::new(
+            ErrorKind::Db,
+            "Failed to apply database migrations".to_string(),
+            Some(Box::new(e)),
+        )
+    })
+}
+
+/// ## Builds the pool options.
+///
+/// A zero `idle_timeout` or `max_lifetime` disables
+/// the limit.
+fn pool_options(settings: &DatabaseSettings) -> PgPoolOptions {
+    let limit = |seconds: u64| (seconds > 0).then(|| Duration::from_secs(seconds));
+
+    PgPoolOptions::new()
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .acquire_timeout(Duration::from_secs(settings.connect_timeout))
+        .idle_timeout(limit(settings.idle_timeout))
+        .max_lifetime(limit(settings.max_lifetime))
+}
+
+/// ## Checks if a connection failure may resolve itself.
+///
+/// Unreachable hosts, timeouts and the connection errors
+/// of Postgres, class `08` and `57P03` while the server
+/// starts, are transient. Wrong credentials or a missing
+/// database are not, retrying them only delays startup.
+fn is_transient(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(e) => e
+            .code()
+            .is_some_and(|code| code.starts_with("08") || code == "57P03"),
+        _ => false,
+    }
+}
+
+/// ## Returns the delay before the next attempt.
+///
+/// Delay starts at `backoff` and doubles with every
+/// failed attempt, up to `max_backoff`. Jitter then
+/// shortens it by up to its share of the delay.
+///
+/// ## Parameters
+/// - `settings`: `&DatabaseRetrySettings` - Backoff settings.
+/// - `attempts`: `u32` - Failed attempts so far, at least 1.
+/// - `random`: `f64` - Random share, `0.0` to `1.0`.
+///
+/// ## Returns
+/// - `Duration`: Delay before the next attempt.
+fn retry_delay(settings: &DatabaseRetrySettings, attempts: u32, random: f64) -> Duration {
+    let factor = 1u64
+        .checked_shl(attempts.saturating_sub(1))
+        .unwrap_or(u64::MAX);
+    let delay = Duration::from_secs(
+        settings
+            .backoff
+            .saturating_mul(factor)
+            .min(settings.max_backoff),
+    );
+
+    delay.mul_f64(1.0 - settings.jitter.clamp(0.0, 1.0) * random.clamp(0.0, 1.0))
+}
+
+/// ## Returns a random share between `0.0` and `1.0`.
+///
+/// Without a random source the delay is not jittered.
+fn random_share() -> f64 {
+    let mut bytes = [0u8; 4];
+    match SystemRandom::new().fill(&mut bytes) {
+        Ok(()) => u32::from_be_bytes(bytes) as f64 / u32::MAX as f64,
+        Err(_) => 0.0,
+    }
+}
+
+/// ## Builds the connection options.
+///
+/// Statements slower than `slow_query_threshold_ms` are
+/// logged by sqlx as warnings of the `sqlx::query` target,
+/// the other statements as debug events, which `stats`
+/// records when query metrics are enabled.
+///
+/// ## Returns
+/// + `Result<PgConnectOptions, AppError>`
+///     - `Ok(PgConnectOptions)`: Connection options.
+///     - `Err(AppError)`: If the port or SSL mode is invalid.
+fn connect_options(
+    env: &EnvMap,
+    settings: &DatabaseSettings,
+) -> Result<PgConnectOptions, AppError> {
+    let options = Dsn::from_env(env)?
+        .connect_options()
+        .log_statements(LevelFilter::Debug);
+
+    Ok(match settings.slow_query_threshold_ms {
+        0 => options.log_slow_statements(LevelFilter::Off, Duration::MAX),
+        threshold => {
+            options.log_slow_statements(LevelFilter::Warn, Duration::from_millis(threshold))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if the retry delay doubles, is capped and shortened by jitter.
+    #[test]
+    fn test_retry_delay() {
+        let settings = DatabaseRetrySettings {
+            backoff: 1,
+            max_backoff: 10,
+            jitter: 0.5,
+            ..DatabaseRetrySettings::default()
+        };
+
+        let delays = (1..=6)
+            .map(|attempts| retry_delay(&settings, attempts, 0.0).as_secs())
+            .collect::<Vec<_>>();
+
+        assert_eq!(delays, [1, 2, 4, 8, 10, 10]);
+        assert_eq!(retry_delay(&settings, 4, 1.0), Duration::from_secs(4));
+        assert_eq!(retry_delay(&settings, 80, 0.5), Duration::from_millis(7500));
+    }
+
+    // Test checks if only connection failures are retried.
+    #[test]
+    fn test_is_transient() {
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+
+        assert!(is_transient(&sqlx::Error::Io(refused)));
+        assert!(is_transient(&sqlx::Error::PoolTimedOut));
+        assert!(!is_transient(&sqlx::Error::Configuration(
+            "invalid port".into()
+        )));
+        assert!(!is_transient(&sqlx::Error::RowNotFound));
+    }
+}
@@ -0,0 +1,535 @@
+//! Seed data module.
+//!
+//! Module loads declarative fixtures, tenants, role
+//! definitions and users, from a TOML or YAML file and
+//! upserts them. Seeding is idempotent: rows that already
+//! match the file are left alone, so the same file can be
+//! applied on every start of a development database or
+//! before every integration test run. Fixtures only add,
+//! roles granted outside the file and changed passwords
+//! are kept.
+
+// Imports from external crates
+use serde::Deserialize;
+use std::{collections::HashSet, fmt, path::Path};
+use uuid::Uuid;
+
+// Local imports
+use crate::core::auth::password::{Passwords, StoredHash};
+use crate::core::err::AppError;
+use crate::core::repo::{NewRole, RoleDefinitionRepo, TenantRepo, UserRepo};
+use crate::core::tenancy::{validate_tenant_id, with_tenant, DEFAULT_TENANT};
+use crate::core::users::roles::{is_builtin, validate_role_name};
+use crate::core::users::NewUser;
+use crate::routes::admin::roles::parse_permissions;
+use crate::routes::me::validate_email;
+
+/// ## Seed fixtures struct.
+///
+/// ## Fields
+/// + `tenants`: `Vec<TenantFixture>` - Tenants to create.
+/// + `roles`: `Vec<RoleFixture>` - Role definitions to create or update.
+/// + `users`: `Vec<UserFixture>` - Users to create or update.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixtures {
+    #[serde(default)]
+    pub tenants: Vec<TenantFixture>,
+    #[serde(default)]
+    pub roles: Vec<RoleFixture>,
+    #[serde(default)]
+    pub users: Vec<UserFixture>,
+}
+
+/// ## Tenant fixture struct.
+///
+/// ## Fields
+/// + `id`: `String` - ID of the tenant.
+/// + `name`: `Option<String>` - Display name, the ID when not set.
+/// + `disabled`: `bool` - If the requests of the tenant are rejected.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantFixture {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// ## Role definition fixture struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the role.
+/// + `tenant`: `Option<String>` - Tenant of the role, `default` when not set.
+/// + `description`: `Option<String>` - What the role is meant for.
+/// + `permissions`: `Vec<String>` - Names of the granted permissions.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleFixture {
+    pub name: String,
+    #[serde(default)]
+    pub tenant: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+/// ## User fixture struct.
+///
+/// ## Fields
+/// + `email`: `String` - Email address of the user.
+/// + `tenant`: `Option<String>` - Tenant of the user, `default` when not set.
+/// + `display_name`: `Option<String>` - Name shown instead of the email.
+/// + `org_id`: `Option<Uuid>` - Organization of the user.
+/// + `password`: `Option<String>` - Plain password, hashed with the
+///   configured algorithm.
+/// + `password_hash`: `Option<String>` - Argon2id or bcrypt hash, instead
+///   of `password`.
+/// + `disabled`: `bool` - If the user can not sign in.
+/// + `roles`: `Vec<String>` - Roles granted to the user.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserFixture {
+    pub email: String,
+    #[serde(default)]
+    pub tenant: Option<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub org_id: Option<Uuid>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub password_hash: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// ## Result of a seed struct.
+///
+/// ## Fields
+/// + `created`: `usize` - Rows that did not exist.
+/// + `updated`: `usize` - Rows that differed from the fixtures.
+/// + `unchanged`: `usize` - Rows that already matched the fixtures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeedReport {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+impl SeedReport {
+    /// ## Counts a row as created, updated or unchanged.
+    fn count(&mut self, created: bool, updated: bool) {
+        match (created, updated) {
+            (true, _) => self.created += 1,
+            (false, true) => self.updated += 1,
+            (false, false) => self.unchanged += 1,
+        }
+    }
+}
+
+impl fmt::Display for SeedReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} created, {} updated, {} unchanged",
+            self.created, self.updated, self.unchanged
+        )
+    }
+}
+
+/// ## Repositories the fixtures are written to struct.
+///
+/// ## Fields
+/// + `tenants`: `&dyn TenantRepo` - Known tenants.
+/// + `roles`: `&dyn RoleDefinitionRepo` - Role definitions.
+/// + `users`: `&dyn UserRepo` - Users and their grants.
+/// + `passwords`: `&Passwords` - Hasher of plain fixture passwords.
+pub struct SeedTarget<'a> {
+    pub tenants: &'a dyn TenantRepo,
+    pub roles: &'a dyn RoleDefinitionRepo,
+    pub users: &'a dyn UserRepo,
+    pub passwords: &'a Passwords,
+}
+
+/// ## Loads the fixtures of a TOML or YAML file.
+///
+/// Format is detected from the extension, `.toml`, `.yaml`
+/// or `.yml`.
+///
+/// ## Parameters
+/// - `path`: `&Path` - Fixture file.
+///
+/// ## Returns
+/// + `Result<Fixtures, AppError>`
+///     - `Ok(Fixtures)`: Fixtures of the file.
+///     - `Err(AppError)`: `Config` error if the file can not be read or
+///       has unknown keys.
+pub fn load(path: &Path) -> Result<Fixtures, AppError> {
+    config::Config::builder()
+        .add_source(config::File::from(path))
+        .build()
+        .and_then(|fixtures| fixtures.try_deserialize())
+        .map_err(|e| {
+            AppError::config(format!(
+                "Failed to load fixtures '{}': {}",
+                path.display(),
+                e
+            ))
+        })
+}
+
+impl Fixtures {
+    /// ## Checks the fixtures before anything is written.
+    ///
+    /// Every problem of the file is found before the first
+    /// row is stored, so a typo does not leave a half
+    /// seeded database.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If every fixture is valid.
+    ///     - `Err(AppError)`: `Validation` error naming the first invalid
+    ///       fixture.
+    pub fn validate(&self) -> Result<(), AppError> {
+        for tenant in &self.tenants {
+            validate_tenant_id(&tenant.id)?;
+        }
+
+        for role in &self.roles {
+            validate_tenant_id(tenant_of(&role.tenant))?;
+            validate_role_name(&role.name)?;
+            parse_permissions(&role.permissions)?;
+        }
+
+        let mut emails = HashSet::new();
+        for user in &self.users {
+            let tenant = tenant_of(&user.tenant);
+            validate_tenant_id(tenant)?;
+            validate_email(&user.email)?;
+            if !emails.insert((tenant, user.email.to_lowercase())) {
+                return Err(AppError::validation(format!(
+                    "User '{}' is listed twice",
+                    user.email
+                )));
+            }
+            if user.password.is_some() && user.password_hash.is_some() {
+                return Err(AppError::validation(format!(
+                    "User '{}' sets both password and password_hash",
+                    user.email
+                )));
+            }
+            if let Some(hash) = &user.password_hash {
+                StoredHash::parse(hash)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Returns the tenant of a fixture.
+fn tenant_of(tenant: &Option<String>) -> &str {
+    tenant.as_deref().unwrap_or(DEFAULT_TENANT)
+}
+
+/// ## Upserts the fixtures.
+///
+/// Tenants are seeded first, then role definitions and
+/// then users, so users can be granted the roles of the
+/// same file.
+///
+/// ## Parameters
+/// - `fixtures`: `&Fixtures` - Fixtures to apply.
+/// - `target`: `&SeedTarget` - Repositories to write to.
+///
+/// ## Returns
+/// + `Result<SeedReport, AppError>`
+///     - `Ok(SeedReport)`: Counts of created, updated and unchanged rows.
+///     - `Err(AppError)`: If a fixture is invalid, grants an unknown role
+///       or a row can not be written.
+pub async fn seed(fixtures: &Fixtures, target: &SeedTarget<'_>) -> Result<SeedReport, AppError> {
+    fixtures.validate()?;
+    let mut report = SeedReport::default();
+
+    for tenant in &fixtures.tenants {
+        let (created, updated) = seed_tenant(tenant, target.tenants).await?;
+        report.count(created, updated);
+    }
+
+    for role in &fixtures.roles {
+        let tenant = tenant_of(&role.tenant).to_string();
+        let (created, updated) = with_tenant(tenant, seed_role(role, target.roles)).await?;
+        report.count(created, updated);
+    }
+
+    for user in &fixtures.users {
+        let tenant = tenant_of(&user.tenant).to_string();
+        let (created, updated) = with_tenant(tenant, seed_user(user, target)).await?;
+        report.count(created, updated);
+    }
+
+    Ok(report)
+}
+
+/// ## Creates the tenant, or updates its disabled state.
+async fn seed_tenant(
+    fixture: &TenantFixture,
+    tenants: &dyn TenantRepo,
+) -> Result<(bool, bool), AppError> {
+    let existing = tenants.get(&fixture.id).await?;
+    let (created, disabled) = match existing {
+        Some(tenant) => (false, tenant.disabled),
+        None => {
+            let name = fixture.name.as_deref().unwrap_or(&fixture.id);
+            let tenant = tenants.create(&fixture.id, name).await?;
+            (true, tenant.disabled)
+        }
+    };
+
+    let updated = disabled != fixture.disabled;
+    if updated {
+        tenants.set_disabled(&fixture.id, fixture.disabled).await?;
+    }
+
+    Ok((created, updated))
+}
+
+/// ## Defines the role, or updates a definition that differs.
+async fn seed_role(
+    fixture: &RoleFixture,
+    roles: &dyn RoleDefinitionRepo,
+) -> Result<(bool, bool), AppError> {
+    let role = NewRole {
+        name: fixture.name.clone(),
+        description: fixture.description.clone(),
+        permissions: parse_permissions(&fixture.permissions)?,
+    };
+
+    let existing = roles.get(&fixture.name).await?;
+    match existing {
+        None => {
+            roles.create(role).await?;
+            Ok((true, false))
+        }
+        Some(current)
+            if current.description == role.description
+                && current.permissions == role.permissions =>
+        {
+            Ok((false, false))
+        }
+        Some(current) => {
+            roles.update(role, current.version).await?;
+            Ok((false, true))
+        }
+    }
+}
+
+/// ## Creates the user, or updates a user that differs.
+///
+/// Password of an existing user is not replaced, and
+/// roles are only granted, never revoked.
+async fn seed_user(
+    fixture: &UserFixture,
+    target: &SeedTarget<'_>,
+) -> Result<(bool, bool), AppError> {
+    let users = target.users;
+    let email = fixture.email.trim().to_lowercase();
+
+    for role in &fixture.roles {
+        let defined = is_builtin(role) || target.roles.get(role).await?.is_some();
+        if !defined {
+            return Err(AppError::validation(format!(
+                "Role '{}' of user '{}' is not defined",
+                role, fixture.email
+            )));
+        }
+    }
+
+    let existing = users.find_by_email(&email).await?;
+    let Some(user) = existing else {
+        let password = match (&fixture.password, &fixture.password_hash) {
+            (Some(password), _) => Some(target.passwords.hash(password)?),
+            (None, Some(hash)) => Some(StoredHash::parse(hash)?),
+            (None, None) => None,
+        };
+        let user = NewUser {
+            email,
+            display_name: fixture.display_name.clone(),
+            org_id: fixture.org_id,
+            password,
+        };
+        users.import(user, fixture.disabled, &fixture.roles).await?;
+
+        return Ok((true, false));
+    };
+
+    let mut updated = false;
+    if user.display_name != fixture.display_name {
+        users
+            .set_display_name(user.id, fixture.display_name.as_deref())
+            .await?;
+        updated = true;
+    }
+    if user.disabled != fixture.disabled {
+        users.set_disabled(user.id, fixture.disabled).await?;
+        updated = true;
+    }
+    for role in &fixture.roles {
+        updated |= users.grant(user.id, role).await?;
+    }
+
+    Ok((false, updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::password::Algorithm;
+    use crate::core::config::PasswordSettings;
+    use crate::core::repo::memory::{MemoryRoleDefinitionRepo, MemoryTenantRepo, MemoryUserRepo};
+
+    // Test checks if the example fixtures load and validate, and YAML files load the same way
+    #[test]
+    fn test_load() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("seed.example.toml");
+        let fixtures = load(&path).unwrap();
+
+        assert!(fixtures.validate().is_ok());
+        assert!(!fixtures.users.is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let yaml = dir.path().join("seed.yaml");
+        std::fs::write(
+            &yaml,
+            "users:\n  - email: ada@example.com\n    roles: [admin]\n",
+        )
+        .unwrap();
+        let fixtures = load(&yaml).unwrap();
+
+        assert_eq!(fixtures.users[0].email, "ada@example.com");
+        assert_eq!(fixtures.users[0].roles, vec!["admin"]);
+
+        std::fs::write(&yaml, "groups: []\n").unwrap();
+        assert!(load(&yaml).is_err());
+    }
+
+    // Test checks if fixtures are checked before anything is written
+    #[test]
+    fn test_validate() {
+        let user = UserFixture {
+            email: "ada@example.com".to_string(),
+            tenant: None,
+            display_name: None,
+            org_id: None,
+            password: Some("correct horse".to_string()),
+            password_hash: None,
+            disabled: false,
+            roles: Vec::new(),
+        };
+
+        let twice = Fixtures {
+            users: vec![user.clone(), user.clone()],
+            ..Fixtures::default()
+        };
+        assert!(twice.validate().is_err());
+
+        let both = Fixtures {
+            users: vec![UserFixture {
+                password_hash: Some("$2b$04$invalid".to_string()),
+                ..user.clone()
+            }],
+            ..Fixtures::default()
+        };
+        assert!(both.validate().is_err());
+
+        let permission = Fixtures {
+            roles: vec![RoleFixture {
+                name: "auditor".to_string(),
+                tenant: None,
+                description: None,
+                permissions: vec!["nope".to_string()],
+            }],
+            ..Fixtures::default()
+        };
+        assert!(permission.validate().is_err());
+    }
+
+    // Test checks if seeding twice creates the rows once and reapplies changed fixtures
+    #[tokio::test]
+    async fn test_seed_idempotent() {
+        let tenants = MemoryTenantRepo::default();
+        let roles = MemoryRoleDefinitionRepo::default();
+        let users = MemoryUserRepo::default();
+        let passwords = Passwords::new(&PasswordSettings {
+            algorithm: Algorithm::Bcrypt,
+            bcrypt_cost: 4,
+            ..PasswordSettings::default()
+        })
+        .unwrap();
+        let target = SeedTarget {
+            tenants: &tenants,
+            roles: &roles,
+            users: &users,
+            passwords: &passwords,
+        };
+
+        let mut fixtures = Fixtures {
+            tenants: vec![TenantFixture {
+                id: "acme".to_string(),
+                name: Some("Acme".to_string()),
+                disabled: false,
+            }],
+            roles: vec![RoleFixture {
+                name: "auditor".to_string(),
+                tenant: Some("acme".to_string()),
+                description: None,
+                permissions: vec!["audit.read".to_string()],
+            }],
+            users: vec![UserFixture {
+                email: "Ada@Example.com".to_string(),
+                tenant: Some("acme".to_string()),
+                display_name: Some("Ada".to_string()),
+                org_id: None,
+                password: Some("correct horse".to_string()),
+                password_hash: None,
+                disabled: false,
+                roles: vec!["auditor".to_string()],
+            }],
+        };
+
+        let report = seed(&fixtures, &target).await.unwrap();
+        assert_eq!(report.created, 3);
+
+        let report = seed(&fixtures, &target).await.unwrap();
+        assert_eq!(report.unchanged, 3);
+
+        fixtures.users[0].disabled = true;
+        fixtures.users[0].roles.push("admin".to_string());
+        let report = seed(&fixtures, &target).await.unwrap();
+        assert_eq!((report.updated, report.unchanged), (1, 2));
+
+        let user = with_tenant("acme", users.find_by_email("ada@example.com"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(user.disabled);
+        assert_eq!(
+            users.roles(user.id).await.unwrap(),
+            vec!["admin", "auditor"]
+        );
+        assert!(users
+            .find_by_email("ada@example.com")
+            .await
+            .unwrap()
+            .is_none());
+
+        fixtures.users[0].roles.push("unknown".to_string());
+        assert!(seed(&fixtures, &target).await.is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Query statistics module.
+//!
+//! sqlx reports every statement as an event of the
+//! `sqlx::query` target, slow ones as warnings. `layer`
+//! turns these events into the `db_query_duration_seconds`
+//! histogram and the `db_slow_queries_total` counter of
+//! `/metrics`, labelled with the name of the statement,
+//! and keeps totals per statement for `/metrics/queries`.
+//! Nothing is recorded until `enable` is called, so the
+//! statements are only formatted when somebody reads them.
+
+// Imports from external crates
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    filter::filter_fn,
+    layer::{Context, Layer},
+    registry::LookupSpan,
+};
+
+// Re-exports of the wire types
+pub use crate::api::db::StatementStats;
+
+/// Target of the statement events of sqlx.
+pub const QUERY_TARGET: &str = "sqlx::query";
+
+/// Query statistics are recorded.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Totals per statement name.
+static TOTALS: Lazy<Mutex<HashMap<String, Totals>>> = Lazy::new(Mutex::default);
+
+/// ## Totals of a statement struct.
+#[derive(Debug, Clone, Copy, Default)]
+struct Totals {
+    count: u64,
+    slow: u64,
+    total: Duration,
+    max: Duration,
+}
+
+/// ## Locks the totals, a poisoned lock is recovered.
+fn totals() -> MutexGuard<'static, HashMap<String, Totals>> {
+    TOTALS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// ## Turns the recording of the statements on or off.
+///
+/// Interest of the registered callsites is cached, so the
+/// cache is rebuilt for the filter of `layer` to apply.
+///
+/// ## Parameters
+/// - `enabled`: `bool` - If `database.query_metrics` is set.
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    tracing::callsite::rebuild_interest_cache();
+}
+
+/// ## Returns the tracing layer recording the statements.
+///
+/// Layer only subscribes to `QUERY_TARGET` while the
+/// recording is enabled, other events never reach it.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::db::stats;
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// stats::enable(true);
+/// let subscriber = tracing_subscriber::registry().with(stats::layer());
+/// tracing::subscriber::with_default(subscriber, || {
+///     tracing::debug!(
+///         target: "sqlx::query",
+///         summary = "SELECT id FROM doc_example",
+///         elapsed_secs = 0.004,
+///     );
+/// });
+///
+/// let stats = stats::snapshot();
+/// assert!(stats.iter().any(|stats| stats.statement == "select doc_example"));
+/// ```
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    QueryStatsLayer.with_filter(filter_fn(|metadata| {
+        ENABLED.load(Ordering::Relaxed) && metadata.target() == QUERY_TARGET
+    }))
+}
+
+/// ## Returns the totals per statement, most time spent first.
+pub fn snapshot() -> Vec<StatementStats> {
+    let mut stats: Vec<StatementStats> = totals()
+        .iter()
+        .map(|(statement, totals)| {
+            let total_ms = totals.total.as_secs_f64() * 1000.0;
+            StatementStats {
+                statement: statement.clone(),
+                count: totals.count,
+                slow: totals.slow,
+                total_ms,
+                mean_ms: total_ms / totals.count.max(1) as f64,
+                max_ms: totals.max.as_secs_f64() * 1000.0,
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+
+    stats
+}
+
+/// ## Forgets the totals, the metrics keep counting.
+pub fn reset() {
+    totals().clear();
+}
+
+/// ## Records an execution of a statement.
+///
+/// ## Parameters
+/// - `statement`: `&str` - Name of the statement.
+/// - `elapsed`: `Duration` - Time the execution took.
+/// - `slow`: `bool` - If the execution exceeded the slow threshold.
+pub fn record(statement: &str, elapsed: Duration, slow: bool) {
+    metrics::histogram!("db_query_duration_seconds", "statement" => statement.to_string())
+        .record(elapsed.as_secs_f64());
+    if slow {
+        metrics::counter!("db_slow_queries_total", "statement" => statement.to_string())
+            .increment(1);
+    }
+
+    let mut totals = totals();
+    let totals = totals.entry(statement.to_string()).or_default();
+    totals.count += 1;
+    totals.slow += u64::from(slow);
+    totals.total += elapsed;
+    totals.max = totals.max.max(elapsed);
+}
+
+/// ## Names a statement by its command and table.
+///
+/// Names stay few, so they are usable as metric labels:
+/// `SELECT ... FROM users WHERE ...` is `select users`,
+/// statements without a table are named by the command.
+///
+/// ## Parameters
+/// - `sql`: `&str` - Statement, possibly shortened.
+///
+/// ## Returns
+/// - `String`: Name of the statement, e.g. `insert sessions`.
+pub fn statement_name(sql: &str) -> String {
+    let tokens: Vec<&str> = sql.split_whitespace().collect();
+    let Some(command) = tokens.first() else {
+        return "unknown".to_string();
+    };
+    let command = command.to_ascii_lowercase();
+    let after = |keyword: &str| {
+        tokens
+            .iter()
+            .position(|token| token.eq_ignore_ascii_case(keyword))
+            .and_then(|index| tokens.get(index + 1))
+    };
+
+    let table = match command.as_str() {
+        "select" | "delete" => after("from"),
+        "insert" => after("into"),
+        "update" => tokens.get(1),
+        _ => None,
+    }
+    .map(|table| {
+        table
+            .trim_matches(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+            .to_ascii_lowercase()
+    })
+    .filter(|table| !table.is_empty());
+
+    match table {
+        Some(table) => format!("{} {}", command, table),
+        None => command,
+    }
+}
+
+/// ## Layer recording the statement events struct.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueryStatsLayer;
+
+impl<S: Subscriber> Layer<S> for QueryStatsLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut fields = QueryFields::default();
+        event.record(&mut fields);
+
+        let Some(elapsed) = fields.elapsed_secs else {
+            return;
+        };
+        // Short statements are not repeated in `db.statement`
+        let sql = match fields.statement.trim().is_empty() {
+            true => &fields.summary,
+            false => &fields.statement,
+        };
+
+        record(
+            &statement_name(sql),
+            Duration::from_secs_f64(elapsed.max(0.0)),
+            fields.slow,
+        );
+    }
+}
+
+/// ## Fields of a statement event struct.
+#[derive(Debug, Default)]
+struct QueryFields {
+    summary: String,
+    statement: String,
+    elapsed_secs: Option<f64>,
+    slow: bool,
+}
+
+impl Visit for QueryFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed_secs = Some(value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "summary" => self.summary = value.to_string(),
+            "db.statement" => self.statement = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            // Only slow statements carry the threshold
+            "slow_threshold" => self.slow = true,
+            "summary" => self.summary = format!("{:?}", value),
+            "db.statement" => self.statement = format!("{:?}", value),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    // Test checks if statements are named by their command and table
+    #[test]
+    fn test_statement_name() {
+        let names = [
+            ("SELECT id, email FROM users WHERE id = $1", "select users"),
+            (
+                "select count(*) from public.sessions",
+                "select public.sessions",
+            ),
+            ("INSERT INTO audit_log (id) VALUES ($1)", "insert audit_log"),
+            ("UPDATE \"users\" SET disabled = true", "update users"),
+            (
+                "DELETE FROM refresh_tokens WHERE expires_at < now()",
+                "delete refresh_tokens",
+            ),
+            ("SELECT 1", "select"),
+            ("BEGIN", "begin"),
+            ("", "unknown"),
+        ];
+
+        for (sql, name) in names {
+            assert_eq!(statement_name(sql), name, "{}", sql);
+        }
+    }
+
+    // Test checks if only enabled statement events are recorded
+    #[test]
+    fn test_layer() {
+        let subscriber = tracing_subscriber::registry().with(layer());
+        let statement = "SELECT id\nFROM test_layer\nWHERE id = $1";
+
+        tracing::subscriber::with_default(subscriber, || {
+            enable(true);
+            tracing::debug!(
+                target: QUERY_TARGET,
+                summary = "SELECT id FROM test_layer …",
+                db.statement = statement,
+                elapsed_secs = 0.002,
+            );
+            tracing::warn!(
+                target: QUERY_TARGET,
+                summary = "SELECT id FROM test_layer …",
+                db.statement = statement,
+                elapsed_secs = 1.5,
+                slow_threshold = ?Duration::from_secs(1),
+                "slow statement: execution time exceeded alert threshold"
+            );
+            tracing::debug!(target: "other", elapsed_secs = 1.0, summary = "SELECT 1");
+        });
+
+        let stats = snapshot();
+        let stats = stats
+            .iter()
+            .find(|stats| stats.statement == "select test_layer")
+            .unwrap();
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.slow, 1);
+        assert_eq!(stats.max_ms, 1500.0);
+        assert_eq!(stats.mean_ms, 751.0);
+    }
+}
@@ -0,0 +1,78 @@
+//! Test database module.
+//!
+//! Tests needing Postgres are marked `#[ignore]` and run
+//! with `cargo test -- --ignored` against the database of
+//! `TEST_DATABASE_URL`. Every test works in a schema of
+//! its own, dropped together with its pools at the end.
+
+// Imports from external crates
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Environment variable holding the URL of the test database.
+pub const TEST_DATABASE_URL: &str = "TEST_DATABASE_URL";
+
+/// ## Test database struct.
+///
+/// ## Fields
+/// + `pool`: `PgPool` - Pool searching the schema of the test.
+/// + `schema`: `String` - Schema of the test.
+/// + `admin`: `PgPool` - Pool creating and dropping the schema.
+pub struct TestDb {
+    pub pool: PgPool,
+    pub schema: String,
+    admin: PgPool,
+}
+
+impl TestDb {
+    /// ## Creates an empty schema for the test.
+    ///
+    /// ## Parameters
+    /// - `prefix`: `&str` - Prefix of the schema name.
+    /// - `max_connections`: `u32` - Size of the pool, 1 runs every
+    ///   statement on the connection of the previous one.
+    ///
+    /// ## Panics
+    /// If `TEST_DATABASE_URL` is not set or the database is not reachable.
+    pub async fn new(prefix: &str, max_connections: u32) -> Self {
+        let url = std::env::var(TEST_DATABASE_URL)
+            .unwrap_or_else(|_| panic!("{} must be set for ignored tests", TEST_DATABASE_URL));
+        let schema = format!("{}_{}", prefix, Uuid::new_v4().simple());
+
+        let admin = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .expect("Failed to connect to the test database");
+        sqlx::query(&format!("CREATE SCHEMA {}", schema))
+            .execute(&admin)
+            .await
+            .unwrap();
+
+        let options = PgConnectOptions::from_str(&url)
+            .unwrap()
+            .options([("search_path", schema.as_str())]);
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .unwrap();
+
+        TestDb {
+            pool,
+            schema,
+            admin,
+        }
+    }
+
+    /// ## Drops the schema and closes the pools.
+    pub async fn drop(self) {
+        self.pool.close().await;
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", self.schema))
+            .execute(&self.admin)
+            .await
+            .unwrap();
+        self.admin.close().await;
+    }
+}
@@ -0,0 +1,242 @@
+//! Database transaction module.
+//!
+//! `with_transaction` runs a closure inside a transaction
+//! and commits it when the closure succeeds. Called with
+//! the connection of a running transaction, it opens a
+//! savepoint instead, so helpers that need atomicity can
+//! be composed.
+
+// Imports from external crates
+use sqlx::{Acquire, PgConnection, Postgres};
+use std::{future::Future, pin::Pin};
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+
+/// Future returned by the closure of `with_transaction`.
+pub type TxFuture<'t, T> = Pin<Box<dyn Future<Output = Result<T, AppError>> + Send + 't>>;
+
+/// ## Runs the closure inside a transaction.
+///
+/// Transaction is committed when the closure returns
+/// `Ok`. When it returns `Err` or panics, the transaction
+/// is dropped and rolled back before its connection is
+/// used again. Errors are not held across the rollback,
+/// so callers stay `Send`.
+///
+/// ## Parameters
+/// - `db`: `impl Acquire` - Pool, connection or running transaction,
+///   the latter opens a savepoint.
+/// - `f`: `FnOnce(&mut PgConnection) -> TxFuture<T>` - Statements of
+///   the transaction.
+///
+/// ## Returns
+/// + `Result<T, AppError>`
+///     - `Ok(T)`: Result of the closure, if the transaction was committed.
+///     - `Err(AppError)`: Error of the closure, or a `Db` error if the
+///       transaction could not be opened or committed.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::db::tx::with_transaction;
+/// use axum_auth::core::err::AppError;
+///
+/// # async fn example(pool: sqlx::PgPool) -> Result<(), AppError> {
+/// let moved = with_transaction(&pool, |tx| {
+///     Box::pin(async move {
+///         let result = sqlx::query("UPDATE users SET org_id = NULL WHERE org_id IS NOT NULL")
+///             .execute(&mut *tx)
+///             .await
+///             .map_err(|e| AppError::db("Failed to detach users").with_source(e))?;
+///
+///         Ok(result.rows_affected())
+///     })
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn with_transaction<'a, A, F, T>(db: A, f: F) -> Result<T, AppError>
+where
+    A: Acquire<'a, Database = Postgres>,
+    F: for<'t> FnOnce(&'t mut PgConnection) -> TxFuture<'t, T>,
+{
+    let mut tx = db
+        .begin()
+        .await
+        .map_err(db_error("Failed to begin transaction"))?;
+
+    let value = f(&mut tx).await?;
+
+    tx.commit()
+        .await
+        .map_err(db_error("Failed to commit transaction"))?;
+
+    Ok(value)
+}
+
+/// ## Maps a sqlx error into a database error.
+fn db_error(message: &'static str) -> impl FnOnce(sqlx::Error) -> AppError {
+    move |e| AppError::new(ErrorKind::Db, message.to_string(), Some(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::testing::TestDb;
+    use sqlx::{postgres::PgPoolOptions, PgPool};
+    use std::time::Duration;
+
+    /// Schema of its own with an empty `items` table, the pool has a
+    /// single connection, so every statement runs on the connection
+    /// of the previous one.
+    async fn test_db() -> TestDb {
+        let db = TestDb::new("tx_test", 1).await;
+        sqlx::query("CREATE TABLE items (id INT PRIMARY KEY)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        db
+    }
+
+    /// Returns the IDs stored in the table.
+    async fn ids(pool: &PgPool) -> Vec<i32> {
+        sqlx::query_scalar("SELECT id FROM items ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    /// Inserts the ID in the table.
+    async fn insert(tx: &mut PgConnection, id: i32) -> Result<(), AppError> {
+        sqlx::query("INSERT INTO items (id) VALUES ($1)")
+            .bind(id)
+            .execute(tx)
+            .await
+            .map_err(db_error("Failed to insert"))?;
+
+        Ok(())
+    }
+
+    // Test checks if a transaction that can not be opened skips the closure.
+    #[tokio::test]
+    async fn test_with_transaction_begin_fails() {
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://127.0.0.1:1/axum_auth")
+            .unwrap();
+
+        let result = with_transaction::<_, _, ()>(&pool, |_| {
+            Box::pin(async { panic!("Closure must not run without a transaction") })
+        })
+        .await;
+
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Db);
+        assert_eq!(error.message, "Failed to begin transaction");
+    }
+
+    // Test checks if the future of the helper can be spawned.
+    #[tokio::test]
+    async fn test_with_transaction_is_send() {
+        fn assert_send<T: Send>(_: T) {}
+
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+
+        assert_send(with_transaction(&pool, |tx| {
+            Box::pin(async move {
+                sqlx::query("SELECT 1")
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error("Failed to select"))?;
+                Ok(())
+            })
+        }));
+    }
+
+    // Test checks if a closure returning an error leaves no row behind.
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_with_transaction_rolls_back_error() {
+        let db = test_db().await;
+
+        let result = with_transaction::<_, _, ()>(&db.pool, |tx| {
+            Box::pin(async move {
+                insert(tx, 1).await?;
+                Err(AppError::validation("Closure failed"))
+            })
+        })
+        .await;
+
+        assert_eq!(result.unwrap_err().message, "Closure failed");
+        assert!(ids(&db.pool).await.is_empty());
+        db.drop().await;
+    }
+
+    // Test checks if a panicking closure leaves no row behind and the
+    // rollback is done before its connection is used again.
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_with_transaction_rolls_back_panic() {
+        let db = test_db().await;
+
+        let task = tokio::spawn({
+            let pool = db.pool.clone();
+            async move {
+                with_transaction::<_, _, ()>(&pool, |tx| {
+                    Box::pin(async move {
+                        insert(tx, 1).await?;
+                        panic!("Closure panicked");
+                    })
+                })
+                .await
+                .is_ok()
+            }
+        });
+        assert!(task.await.unwrap_err().is_panic());
+
+        // The pool has a single connection, the one of the panicked transaction
+        assert!(ids(&db.pool).await.is_empty());
+
+        with_transaction(&db.pool, |tx| Box::pin(async move { insert(tx, 2).await }))
+            .await
+            .unwrap();
+
+        assert_eq!(ids(&db.pool).await, [2]);
+        db.drop().await;
+    }
+
+    // Test checks if a failing nested call only rolls back its savepoint
+    // and the outer transaction still commits.
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_with_transaction_savepoint() {
+        let db = test_db().await;
+
+        with_transaction(&db.pool, |tx| {
+            Box::pin(async move {
+                insert(tx, 1).await?;
+
+                let nested = with_transaction::<_, _, ()>(&mut *tx, |tx| {
+                    Box::pin(async move {
+                        insert(tx, 2).await?;
+                        Err(AppError::validation("Nested call failed"))
+                    })
+                })
+                .await
+                .is_ok();
+                assert!(!nested);
+
+                insert(tx, 3).await
+            })
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(ids(&db.pool).await, [1, 3]);
+        db.drop().await;
+    }
+}
@@ -0,0 +1,255 @@
+//! Environment diff module.
+//!
+//! Module compares two environment files, e.g. the
+//! staging and the production one when promoting a
+//! release. Values of secret variables are masked, so
+//! the diff can be shared. Prefixed variables that are
+//! not in `RequiredEnvVar` and required ones that are
+//! not set are flagged, they would stop the start.
+
+// Imports from external crates
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use strum::IntoEnumIterator;
+
+// Local imports
+use super::load_file;
+use super::suggest::suggest;
+use super::validator::EnvVarIssue;
+use super::vars::{check_prefix, EnvVar, RequiredEnvVar};
+use crate::core::config::summary::{mask, MASK};
+use crate::core::err::AppError;
+
+/// ## Kind of change enum.
+///
+/// # Variants
+/// - `Added` - Variable is only set in the second file.
+/// - `Removed` - Variable is only set in the first file.
+/// - `Changed` - Variable has different values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// ## Changed variable struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the variable, prefix included.
+/// + `kind`: `EnvChangeKind` - How the variable changed.
+/// + `old`: `Option<String>` - Masked value in the first file.
+/// + `new`: `Option<String>` - Masked value in the second file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvChange {
+    pub name: String,
+    pub kind: EnvChangeKind,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// ## Environment diff struct.
+///
+/// ## Fields
+/// + `changes`: `Vec<EnvChange>` - Changed variables, sorted by name.
+/// + `unknown`: `Vec<EnvVarIssue>` - Prefixed variables of either file
+///   that are not known, with the likely intended name.
+/// + `missing`: `Vec<String>` - Required variables the second file
+///   does not set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvDiff {
+    pub changes: Vec<EnvChange>,
+    pub unknown: Vec<EnvVarIssue>,
+    pub missing: Vec<String>,
+}
+
+impl fmt::Display for EnvDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.changes.is_empty() {
+            writeln!(f, "No changes")?;
+        }
+        for change in &self.changes {
+            let old = change.old.as_deref().unwrap_or_default();
+            let new = change.new.as_deref().unwrap_or_default();
+            match change.kind {
+                EnvChangeKind::Added => writeln!(f, "+ {}={}", change.name, new)?,
+                EnvChangeKind::Removed => writeln!(f, "- {}={}", change.name, old)?,
+                EnvChangeKind::Changed => writeln!(f, "~ {}: {} -> {}", change.name, old, new)?,
+            }
+        }
+        for var in &self.unknown {
+            match &var.suggestion {
+                Some(suggestion) => {
+                    writeln!(f, "unknown {} (did you mean {}?)", var.name, suggestion)?
+                }
+                None => writeln!(f, "unknown {}", var.name)?,
+            }
+        }
+        for name in &self.missing {
+            writeln!(f, "missing {}", name)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Compares two environment files.
+///
+/// ## Parameters
+/// - `file_a`: `&str` - Path to the first file, e.g. staging.
+/// - `file_b`: `&str` - Path to the second file, e.g. production.
+/// - `prefix`: `&str` - Prefix of the variables.
+///
+/// ## Returns
+/// + `Result<EnvDiff, AppError>`
+///     - `EnvDiff`: Differences of the files.
+///     - `AppError`: If the prefix is invalid or a file can not
+///       be loaded.
+pub fn diff(file_a: &str, file_b: &str, prefix: &str) -> Result<EnvDiff, AppError> {
+    check_prefix(prefix)?;
+
+    let a: BTreeMap<String, String> = load_file(file_a)?.into_iter().collect();
+    let b: BTreeMap<String, String> = load_file(file_b)?.into_iter().collect();
+
+    Ok(compare(&a, &b, prefix))
+}
+
+/// ## Compares the variables of two files (private).
+fn compare(a: &BTreeMap<String, String>, b: &BTreeMap<String, String>, prefix: &str) -> EnvDiff {
+    let known: BTreeMap<String, RequiredEnvVar> = RequiredEnvVar::iter()
+        .map(|var| (var.name(prefix), var))
+        .collect();
+    let masked = |name: &str, value: &str| match known.get(name) {
+        Some(var) if var.is_secret() && !value.is_empty() => MASK.to_string(),
+        _ => mask(name, value),
+    };
+
+    let names: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    let changes = names
+        .iter()
+        .filter_map(|name| {
+            let kind = match (a.get(*name), b.get(*name)) {
+                (None, Some(_)) => EnvChangeKind::Added,
+                (Some(_), None) => EnvChangeKind::Removed,
+                (Some(old), Some(new)) if old != new => EnvChangeKind::Changed,
+                _ => return None,
+            };
+
+            Some(EnvChange {
+                name: name.to_string(),
+                kind,
+                old: a.get(*name).map(|value| masked(name, value)),
+                new: b.get(*name).map(|value| masked(name, value)),
+            })
+        })
+        .collect();
+
+    let unknown = names
+        .iter()
+        .filter(|name| name.starts_with(prefix) && !known.contains_key(**name))
+        .map(|name| EnvVarIssue {
+            name: name.to_string(),
+            suggestion: suggest(name, known.keys().map(String::as_str)).map(str::to_string),
+        })
+        .collect();
+
+    let missing = known
+        .iter()
+        .filter(|(name, var)| !var.optional() && !b.contains_key(*name))
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    EnvDiff {
+        changes,
+        unknown,
+        missing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    // Test checks if changes are listed and secrets masked.
+    #[test]
+    fn test_compare() {
+        let a = vars(&[
+            ("APP_DB_HOST", "staging-db"),
+            ("APP_DB_PASS", "staging"),
+            ("APP_DB_PORT", "5432"),
+            ("APP_DB_PROT", "5432"),
+        ]);
+        let b = vars(&[
+            ("APP_DB_HOST", "prod-db"),
+            ("APP_DB_PASS", "prod"),
+            ("APP_DB_PORT", "5432"),
+            ("APP_DB_NAME", "axum_auth"),
+        ]);
+
+        let diff = compare(&a, &b, "APP_");
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                EnvChange {
+                    name: "APP_DB_HOST".to_string(),
+                    kind: EnvChangeKind::Changed,
+                    old: Some("staging-db".to_string()),
+                    new: Some("prod-db".to_string()),
+                },
+                EnvChange {
+                    name: "APP_DB_NAME".to_string(),
+                    kind: EnvChangeKind::Added,
+                    old: None,
+                    new: Some("axum_auth".to_string()),
+                },
+                EnvChange {
+                    name: "APP_DB_PASS".to_string(),
+                    kind: EnvChangeKind::Changed,
+                    old: Some(MASK.to_string()),
+                    new: Some(MASK.to_string()),
+                },
+                EnvChange {
+                    name: "APP_DB_PROT".to_string(),
+                    kind: EnvChangeKind::Removed,
+                    old: Some("5432".to_string()),
+                    new: None,
+                },
+            ]
+        );
+        assert_eq!(
+            diff.unknown,
+            vec![EnvVarIssue {
+                name: "APP_DB_PROT".to_string(),
+                suggestion: Some("APP_DB_PORT".to_string()),
+            }]
+        );
+        assert_eq!(diff.missing, vec!["APP_DB_SSL_MODE", "APP_DB_USER"]);
+        assert!(diff
+            .to_string()
+            .contains("~ APP_DB_PASS: *** -> ***\n- APP_DB_PROT=5432\nunknown APP_DB_PROT"));
+    }
+
+    // Test checks if the files are read and the prefix checked.
+    #[test]
+    fn test_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join(".env.staging");
+        let b = dir.path().join(".env.production");
+        std::fs::write(&a, "AXA_DB_HOST=staging-db\n").unwrap();
+        std::fs::write(&b, "AXA_DB_HOST=prod-db\n").unwrap();
+        let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+        assert_eq!(diff(a, b, "AXA_").unwrap().changes.len(), 1);
+        assert!(diff(a, b, "axa-").is_err());
+        assert!(diff(a, "./does-not-exist.env", "AXA_").is_err());
+    }
+}
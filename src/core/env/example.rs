@@ -0,0 +1,124 @@
+//! Environment example module.
+//!
+//! Module generates the `.env.example` file from the
+//! `EnvVar` metadata of `RequiredEnvVar`, so the example
+//! lists exactly the variables the application validates
+//! and never drifts from them.
+
+// Imports from external crates
+use strum::IntoEnumIterator;
+
+// Local imports
+use super::vars::{EnvVar, RequiredEnvVar};
+
+/// ## Generates the example environment file.
+///
+/// Every variable is preceded by its description, the
+/// values its type accepts, its default and whether it
+/// is required. Optional variables are commented out,
+/// so a copy of the example only sets required ones.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::env::example::example;
+///
+/// let example = example("APP_");
+///
+/// assert!(example.contains("\nAPP_DB_PORT=5432\n"));
+/// assert!(example.contains("\n# APP_SENTRY_DSN="));
+/// ```
+///
+/// ## Parameters
+/// - `prefix`: `&str` - Prefix of the variables.
+///
+/// ## Returns
+/// - `String`: Contents of the example file.
+pub fn example(prefix: &str) -> String {
+    example_of(RequiredEnvVar::iter(), prefix)
+}
+
+/// ## Generates the example environment file of the variables.
+///
+/// ## Parameters
+/// - `vars`: `impl IntoIterator<Item = V>` - Variables in the order
+///   they are listed.
+/// - `prefix`: `&str` - Prefix of the variables.
+///
+/// ## Returns
+/// - `String`: Contents of the example file.
+pub fn example_of<V: EnvVar>(vars: impl IntoIterator<Item = V>, prefix: &str) -> String {
+    let mut example = String::from("# Generated by `axum_auth env example`, do not edit.\n");
+
+    for var in vars {
+        let default = var
+            .default()
+            .map(|default| format!(", default: {}", default))
+            .unwrap_or_default();
+        let (requiredness, comment) = match var.optional() {
+            true => ("Optional", "# "),
+            false => ("Required", ""),
+        };
+
+        example.push_str(&format!(
+            "\n# {}\n# Type: {}\n# {}{}\n{}{}={}\n",
+            var.description(),
+            var.type_().describe(),
+            requiredness,
+            default,
+            comment,
+            var.name(prefix),
+            var.example()
+        ));
+    }
+
+    example
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if every example value is valid for its type.
+    #[test]
+    fn test_example_values_are_valid() {
+        for var in RequiredEnvVar::iter() {
+            // File paths are checked on disk, the example file does not exist
+            if var == RequiredEnvVar::PathToDbSslRootCert {
+                continue;
+            }
+
+            assert!(var.type_().verify(var.example()).is_ok(), "{:?}", var);
+        }
+    }
+
+    // Test checks if the metadata of a variable is listed.
+    #[test]
+    fn test_example_entry() {
+        let example = example("AXA_");
+
+        assert!(example.contains(
+            "\n# Database SSL mode\n\
+             # Type: one of: disable, allow, prefer, require, verify-ca, verify-full\n\
+             # Required\n\
+             AXA_DB_SSL_MODE=verify-full\n"
+        ));
+        assert!(example.contains(
+            "\n# Database SSL root certificate\n\
+             # Type: path to a readable file\n\
+             # Optional\n\
+             # AXA_PATH_TO_DB_SSL_ROOT_CERT=./certs/root.crt\n"
+        ));
+    }
+
+    // Test checks if the checked in example file is up to date.
+    #[test]
+    fn test_example_is_current() {
+        let example = std::fs::read_to_string(".env.example").unwrap();
+
+        assert_eq!(
+            example,
+            super::example("AXA_"),
+            "run `axum_auth env example > .env.example`"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Module that contains the environment map, which holds
+//! the loaded environment variables without touching the
+//! process environment.
+
+// Importing external crates
+use std::{collections::HashMap, error, str::FromStr};
+
+// Importing local modules
+use super::vars::EnvVar;
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## Environment map struct.
+///
+/// Map is built once at startup from the environment
+/// files and the known process environment variables,
+/// then values are read through the typed accessors.
+/// Variables are looked up by their name with the prefix
+/// of the map. Variables added with `extend_from` remember
+/// where they were loaded from, e.g. the environment file.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::env::map::EnvMap;
+///
+/// let env: EnvMap = [("APP_PORT".to_string(), "8080".to_string())]
+///     .into_iter()
+///     .collect();
+///
+/// assert_eq!(env.with_prefix("APP_").len(), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvMap {
+    vars: HashMap<String, String>,
+    sources: HashMap<String, String>,
+    prefix: String,
+}
+
+impl EnvMap {
+    /// ## Sets the prefix of the variable names.
+    pub fn prefixed(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// ## Returns the prefix of the variable names.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// ## Returns the value of the variable.
+    ///
+    /// ## Returns
+    /// + `Result<&str, AppError>`
+    ///     - `&str`: Value of the variable.
+    ///     - `AppError`: If the variable is not set.
+    pub fn get<V: EnvVar>(&self, var: &V) -> Result<&str, AppError> {
+        let name = var.name(&self.prefix);
+
+        self.vars.get(&name).map(String::as_str).ok_or_else(|| {
+            AppError::new(
+                ErrorKind::Env,
+                format!("Missing environment variable: '{}'", name),
+                None,
+            )
+        })
+    }
+
+    /// ## Returns the value of the variable parsed into `T`.
+    ///
+    /// ## Returns
+    /// + `Result<T, AppError>`
+    ///     - `T`: Parsed value of the variable.
+    ///     - `AppError`: If the variable is not set or invalid.
+    pub fn parse<T, V>(&self, var: &V) -> Result<T, AppError>
+    where
+        T: FromStr,
+        T::Err: error::Error + 'static,
+        V: EnvVar,
+    {
+        self.get(var)?.parse().map_err(|e| {
+            AppError::new(
+                ErrorKind::Validation,
+                format!(
+                    "Failed to parse environment variable: '{}'",
+                    var.name(&self.prefix)
+                ),
+                Some(Box::new(e) as Box<dyn error::Error>),
+            )
+        })
+    }
+
+    /// ## Adds variables and records where they were loaded from.
+    ///
+    /// ## Parameters
+    /// - `source`: `&str` - Origin of the variables, e.g. the path
+    ///   of the environment file.
+    /// - `vars`: `impl IntoIterator<Item = (String, String)>` - Names
+    ///   and values of the variables.
+    pub fn extend_from(&mut self, source: &str, vars: impl IntoIterator<Item = (String, String)>) {
+        for (name, value) in vars {
+            self.sources.insert(name.clone(), source.to_string());
+            self.vars.insert(name, value);
+        }
+    }
+
+    /// ## Returns where the variable was loaded from.
+    ///
+    /// ## Parameters
+    /// - `name`: `&str` - Full name of the variable, prefix included.
+    pub fn source(&self, name: &str) -> Option<&str> {
+        self.sources.get(name).map(String::as_str)
+    }
+
+    /// ## Returns the variables that start with a prefix.
+    pub fn with_prefix(&self, prefix: &str) -> HashMap<String, String> {
+        self.vars
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+impl FromIterator<(String, String)> for EnvMap {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        EnvMap {
+            vars: iter.into_iter().collect(),
+            ..EnvMap::default()
+        }
+    }
+}
+
+impl Extend<(String, String)> for EnvMap {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        for (name, value) in iter {
+            self.sources.remove(&name);
+            self.vars.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::AppType;
+    use std::collections::HashSet;
+
+    struct Var(&'static str);
+
+    impl EnvVar for Var {
+        type VarType = Self;
+
+        fn all() -> HashSet<Self> {
+            HashSet::new()
+        }
+
+        fn name(&self, prefix: &str) -> String {
+            format!("{}{}", prefix, self.0)
+        }
+
+        fn type_(&self) -> AppType {
+            AppType::String
+        }
+
+        fn description(&self) -> &'static str {
+            "Test variable"
+        }
+
+        fn example(&self) -> &'static str {
+            "value"
+        }
+
+        fn verify(&self, env: &EnvMap) -> Result<(), AppError> {
+            self.type_().verify(env.get(self)?)
+        }
+
+        fn verify_all(_env: &EnvMap) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
+    fn env() -> EnvMap {
+        [
+            ("APP_PORT".to_string(), "8080".to_string()),
+            ("APP_HOST".to_string(), "localhost".to_string()),
+            ("OTHER".to_string(), "value".to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    // Tests that values are returned and missing variables are reported.
+    #[test]
+    fn test_get() {
+        let env = env();
+
+        assert_eq!(env.get(&Var("APP_HOST")).unwrap(), "localhost");
+        assert_eq!(
+            env.get(&Var("APP_MISSING")).unwrap_err().to_string(),
+            AppError::new(
+                ErrorKind::Env,
+                "Missing environment variable: 'APP_MISSING'".to_string(),
+                None
+            )
+            .to_string()
+        );
+    }
+
+    // Tests that sources are recorded and dropped when a value is overridden without one.
+    #[test]
+    fn test_sources() {
+        let mut env = env();
+        env.extend_from(".env", [("APP_PORT".to_string(), "9000".to_string())]);
+        env.extend_from(
+            ".env.local",
+            [("APP_HOST".to_string(), "0.0.0.0".to_string())],
+        );
+
+        assert_eq!(env.source("APP_PORT"), Some(".env"));
+        assert_eq!(env.source("APP_HOST"), Some(".env.local"));
+        assert_eq!(env.source("OTHER"), None);
+
+        env.extend([("APP_PORT".to_string(), "8080".to_string())]);
+
+        assert_eq!(env.get(&Var("APP_PORT")).unwrap(), "8080");
+        assert_eq!(env.source("APP_PORT"), None);
+    }
+
+    // Tests that values are parsed into the requested type.
+    #[test]
+    fn test_parse() {
+        let env = env();
+
+        assert_eq!(env.parse::<u16, _>(&Var("APP_PORT")).unwrap(), 8080);
+        assert!(env.parse::<u16, _>(&Var("APP_HOST")).is_err());
+    }
+
+    // Tests that variables are looked up with the prefix of the map.
+    #[test]
+    fn test_prefixed() {
+        let env = env().prefixed("APP_");
+
+        assert_eq!(env.get(&Var("PORT")).unwrap(), "8080");
+        assert!(env.get(&Var("OTHER")).is_err());
+    }
+
+    // Tests that only prefixed variables are collected.
+    #[test]
+    fn test_with_prefix() {
+        let vars = env().with_prefix("APP_");
+
+        assert_eq!(vars.len(), 2);
+        assert!(!vars.contains_key("OTHER"));
+    }
+}
@@ -4,80 +4,142 @@
 
 // References to submodules
 pub mod constants;
+pub mod diff;
+pub mod example;
+pub mod map;
+pub mod suggest;
 pub mod validator;
 pub mod vars;
 
 // Importing external crates
-use std::{collections::HashSet, error, hash::Hash};
+use std::{collections::HashSet, error, hash::Hash, path::Path};
 
 // Importing local modules
+use crate::core::config::UnknownEnvVars;
 use crate::core::err::{AppError, ErrorKind};
+use map::EnvMap;
 use validator::validate;
-use vars::EnvVar;
+use vars::{check_prefix, EnvVar};
+
+/// Source of the variables read from the process environment.
+pub const PROCESS_SOURCE: &str = "process environment";
 
 /// Handles load and validation of application environment.
 ///
-/// Function loads environment file contents at specified
-/// path by calling "load_file" function, then if file
-/// is valid it will validate loaded environment variables
-/// against specified array of environment variables by
-/// calling "validate" function.
+/// Function loads environment files at specified
+/// paths by calling "load_files" function, then it
+/// validates the loaded map against the specified set
+/// of environment variables by calling "validate"
+/// function. The process environment is never modified,
+/// values are read from the returned map instead.
+///
+/// Known variables set in the process environment
+/// override the files, unknown ones are ignored. An
+/// invalid prefix is reported before any file is read.
 ///
 /// # Examples
-/// ```
-/// // todo
+/// ```no_run
+/// use axum_auth::core::config::UnknownEnvVars;
+/// use axum_auth::core::env::{load, vars::{EnvVar, RequiredEnvVar}};
+///
+/// let env = load(&[".env", ".env.local"], "AXA_", RequiredEnvVar::all(), UnknownEnvVars::Strict)?;
+/// let port: u16 = env.parse(&RequiredEnvVar::DbPort)?;
+/// # Ok::<(), axum_auth::AppError>(())
 /// ```
 ///
 /// # Parameters
-/// - `file_path`: Path to file to load
-///   the environment variables from.
+/// - `file_paths`: Ordered paths to files to load
+///   the environment variables from, see "load_files".
 /// - `var_prefix`: Prefix for environment variables to
 ///   use.
-/// - `vars_to_validate`: Variables to validate against
-///   process environment variables.
+/// - `vars_to_validate`: Variables the loaded map must
+///   set, with valid values.
+/// - `unknown_vars`: Handling of prefixed variables that
+///   are not in `vars_to_validate`.
 ///
 /// # Returns
-/// + `Result<(), AppError>`
-///     - `()`: If environment variables are loaded and
-/// validated successfully.
+/// + `Result<EnvMap, AppError>`
+///     - `EnvMap`: If environment variables are loaded and
+///       validated successfully.
 ///     - `AppError`: Error type that contains error kind,
-/// message and source.
-pub fn load<V>(
-    file_path: &str,
+///       message and source.
+pub fn load<P, V>(
+    file_paths: &[P],
     var_prefix: &str,
     vars_to_validate: HashSet<V>,
-) -> Result<(), AppError>
+    unknown_vars: UnknownEnvVars,
+) -> Result<EnvMap, AppError>
 where
+    P: AsRef<str>,
     V: EnvVar,
     V::VarType: Eq + Hash,
 {
-    // Load environment file contents into std::env
-    load_file(file_path)?;
+    // Names of the variables are built with the prefix
+    check_prefix(var_prefix)?;
+
+    // Load environment files contents
+    let mut env = load_files(file_paths)?.prefixed(var_prefix);
+
+    // Override them with the known process variables
+    let process = vars_to_validate.iter().filter_map(|var| {
+        let name = var.name(var_prefix);
+        std::env::var(&name).ok().map(|value| (name, value))
+    });
+    env.extend_from(PROCESS_SOURCE, process);
 
     // Validate loaded environment variables against
     // specified environment variables
-    validate(var_prefix, vars_to_validate)?;
+    validate(var_prefix, vars_to_validate, unknown_vars, &env)?;
+
+    Ok(env)
+}
+
+/// ## Loads ordered environment files (private).
+///
+/// Later files override earlier ones. The first file
+/// is required, the other files are skipped when they
+/// do not exist.
+///
+/// ## Parameters
+/// -  `file_paths`: Paths to environment files to load.
+///
+/// ## Returns
+/// + `Result<EnvMap, AppError>`
+///     - `EnvMap`: If files are loaded successfully.
+///     - `AppError`: Error type that contains error kind,
+///       message and source.
+fn load_files<P: AsRef<str>>(file_paths: &[P]) -> Result<EnvMap, AppError> {
+    let mut env = EnvMap::default();
+
+    for (index, file_path) in file_paths.iter().enumerate() {
+        let file_path = file_path.as_ref();
+        if index > 0 && !Path::new(file_path).is_file() {
+            continue;
+        }
 
-    Ok(())
+        env.extend_from(file_path, load_file(file_path)?);
+    }
+
+    Ok(env)
 }
 
 /// ## Loads environment file contents (private).
 ///
-/// Function uses "from_filename" function from "dotenvy"
-/// crate in order to load environment variables from
+/// Function uses "from_filename_iter" function from "dotenvy"
+/// crate in order to parse environment variables from
 /// file at the specified file path.
 ///
 /// ## Parameters
 /// -  `file_path`: Path to environment file to load.
 ///
 /// ## Returns
-/// + `Result<(), AppError>`
-///     - `()`: If file is loaded successfully.
+/// + `Result<Vec<(String, String)>, AppError>`
+///     - `Vec<(String, String)>`: If file is loaded successfully.
 ///     - `AppError`: Error type that contains error kind,
-/// message and source.
-fn load_file(file_path: &str) -> Result<(), AppError> {
-    match dotenvy::from_filename(file_path) {
-        Ok(_) => Ok(()),
+///       message and source.
+fn load_file(file_path: &str) -> Result<Vec<(String, String)>, AppError> {
+    match dotenvy::from_filename_iter(file_path).and_then(|vars| vars.collect()) {
+        Ok(vars) => Ok(vars),
         Err(e) => {
             let kind: ErrorKind = ErrorKind::Env;
             let message: String = format!(
@@ -157,71 +219,118 @@ mod tests {
     // Tests that "load_file" function loads environment file correctly.
     #[test]
     fn test_load_file_valid() {
-        // // Create a temp file
-        // let mut temp_file: NamedTempFile =
-        //     NamedTempFile::new().expect("Failed to create temp file");
-
-        // // Write some environment variables to the file
-        // let content: &str = "TEST_VAR=example_value\nANOTHER_VAR=42";
-        // temp_file
-        //     .write_all(content.as_bytes())
-        //     .expect("Failed to write to temp file");
-
-        // // Get the file path
-        // let file_path: &str = temp_file.path().to_str().expect("Failed to get file path");
-
-        // let result: Result<(), AppError> = load_file(file_path);
-
-        // // Assert that the function succeeded
-        // assert!(
-        //     result.is_ok(),
-        //     "load_file failed when it was \
-        //     supposed to succeed: {:?}",
-        //     result.err()
-        // );
+        // Create a temp file
+        let mut temp_file: NamedTempFile =
+            NamedTempFile::new().expect("Failed to create temp file");
+
+        // Write some environment variables to the file
+        let content: &str = "TEST_VAR=example_value\nANOTHER_VAR=42";
+        temp_file
+            .write_all(content.as_bytes())
+            .expect("Failed to write to temp file");
+
+        // Get the file path
+        let file_path: &str = temp_file.path().to_str().expect("Failed to get file path");
+
+        let result = load_file(file_path);
+
+        // Assert that the function succeeded
+        assert_eq!(
+            result.expect("load_file failed when it was supposed to succeed"),
+            [
+                ("TEST_VAR".to_string(), "example_value".to_string()),
+                ("ANOTHER_VAR".to_string(), "42".to_string()),
+            ]
+        );
     }
 
     // Tests that "load_file" function returns an error if file is not found.
     #[test]
     fn test_load_file_not_found() {
-        // let file_path: &str = "non_existent_file.env";
+        let file_path: &str = "non_existent_file.env";
 
-        // let result: Result<(), AppError> = load_file(file_path);
+        let result = load_file(file_path);
 
-        // // Assert that the function failed
-        // assert!(
-        //     result.is_err(),
-        //     "load_file succeeded when it \
-        //     was supposed to fail: {:?}",
-        //     result.ok()
-        // );
+        // Assert that the function failed
+        assert!(
+            result.is_err(),
+            "load_file succeeded when it \
+            was supposed to fail: {:?}",
+            result.ok()
+        );
     }
 
     // Test that "load_file" function returns an error if file is invalid.
     #[test]
     fn test_load_file_invalid() {
-        // // Create a temp file
-        // let mut temp_file: NamedTempFile =
-        //     NamedTempFile::new().expect("Failed to create temp file");
-
-        // // Write some invalid content to the file
-        // let content: &str = "TEST_VAR=example_value\nANOTHER_VAR";
-        // temp_file
-        //     .write_all(content.as_bytes())
-        //     .expect("Failed to write to temp file");
+        // Create a temp file
+        let mut temp_file: NamedTempFile =
+            NamedTempFile::new().expect("Failed to create temp file");
+
+        // Write some invalid content to the file
+        let content: &str = "TEST_VAR=example_value\nANOTHER_VAR";
+        temp_file
+            .write_all(content.as_bytes())
+            .expect("Failed to write to temp file");
+
+        // Get the file path
+        let file_path: &str = temp_file.path().to_str().expect("Failed to get file path");
+
+        let result = load_file(file_path);
+
+        // Assert that the function failed
+        assert!(
+            result.is_err(),
+            "load_file succeeded when it \
+             was supposed to fail: {:?}",
+            result.ok()
+        );
+    }
 
-        // // Get the file path
-        // let file_path: &str = temp_file.path().to_str().expect("Failed to get file path");
+    // Tests that later files override earlier ones
+    // and missing optional files are skipped.
+    #[test]
+    fn test_load_files_order() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+
+        std::fs::write(
+            path(".env"),
+            "LOAD_FILES_BASE=base\nLOAD_FILES_LOCAL=base\nLOAD_FILES_ENV=base",
+        )
+        .unwrap();
+        std::fs::write(
+            path(".env.local"),
+            "LOAD_FILES_LOCAL=local\nLOAD_FILES_ENV=local",
+        )
+        .unwrap();
+        std::fs::write(path(".env.dev"), "LOAD_FILES_ENV=dev").unwrap();
+
+        let result = load_files(&[
+            path(".env"),
+            path(".env.local"),
+            path(".env.missing"),
+            path(".env.dev"),
+        ]);
+
+        let env = result.expect("load_files failed");
+        let vars = env.with_prefix("LOAD_FILES_");
+        assert_eq!(vars["LOAD_FILES_BASE"], "base");
+        assert_eq!(vars["LOAD_FILES_LOCAL"], "local");
+        assert_eq!(vars["LOAD_FILES_ENV"], "dev");
+        assert_eq!(
+            env.source("LOAD_FILES_ENV"),
+            Some(path(".env.dev").as_str())
+        );
+    }
 
-        // let result: Result<(), AppError> = load_file(file_path);
+    // Tests that "load_files" function returns an error
+    // if the first file is not found.
+    #[test]
+    fn test_load_files_required_missing() {
+        let result = load_files(&["non_existent_file.env", ".env.local"]);
 
-        // // Assert that the function failed
-        // assert!(
-        //     result.is_err(),
-        //     "load_file succeeded when it \
-        //      was supposed to fail: {:?}",
-        //     result.ok()
-        // );
+        assert!(result.is_err());
     }
 
     // Tests that "verify" function verifies
@@ -0,0 +1,111 @@
+//! Module that contains functions for suggesting
+//! the intended names of misspelled environment
+//! variables.
+
+/// Maximum edit distance of a suggested name.
+pub const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+/// ## Suggests the closest candidate to the name.
+///
+/// Function compares the name with every candidate
+/// using the Levenshtein distance and returns the
+/// closest one, when it is within
+/// `MAX_SUGGESTION_DISTANCE` edits and less than half
+/// of the name has to change.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::env::suggest::suggest;
+///
+/// let known = ["AXA_DB_PORT", "AXA_DB_HOST"];
+///
+/// assert_eq!(suggest("AXA_DB_PROT", known), Some("AXA_DB_PORT"));
+/// assert_eq!(suggest("AXA_SMTP_URL", known), None);
+/// ```
+///
+/// ## Parameters
+/// - `name`: Name to find a suggestion for.
+/// - `candidates`: Names that could have been intended.
+///
+/// ## Returns
+/// - `Option<&str>`: Closest candidate, ties are broken
+///   by the order of the candidates.
+pub fn suggest<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let limit = MAX_SUGGESTION_DISTANCE.min((name.chars().count().max(1) - 1) / 2);
+
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// ## Returns the Levenshtein distance of two strings.
+///
+/// Distance is the number of single character
+/// insertions, deletions and substitutions needed to
+/// turn one string into the other.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::env::suggest::distance;
+///
+/// assert_eq!(distance("kitten", "sitting"), 3);
+/// ```
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks the distance of edge cases.
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("abc", ""), 3);
+        assert_eq!(distance("AXA_DB_PORT", "AXA_DB_PORT"), 0);
+        assert_eq!(distance("AXA_DB_PROT", "AXA_DB_PORT"), 2);
+        assert_eq!(distance("AXA_DB_POR", "AXA_DB_PORT"), 1);
+    }
+
+    // Test checks if the closest candidate is suggested.
+    #[test]
+    fn test_suggest_closest() {
+        let known = ["AXA_DB_USER", "AXA_DB_PASS", "AXA_DB_PORT"];
+
+        assert_eq!(suggest("AXA_DB_PAS", known), Some("AXA_DB_PASS"));
+        assert_eq!(suggest("AXA_DB_USR", known), Some("AXA_DB_USER"));
+        assert_eq!(suggest("AXA_DB_PORTS", known), Some("AXA_DB_PORT"));
+    }
+
+    // Test checks if distant and short names get no suggestion.
+    #[test]
+    fn test_suggest_none() {
+        assert_eq!(suggest("AXA_LOG_FORMAT", ["AXA_DB_PORT"]), None);
+        assert_eq!(suggest("AB", ["CD"]), None);
+        assert_eq!(suggest("AXA_DB_PORT", ["AXA_DB_PORT"]), None);
+        assert_eq!(suggest("AXA_DB_PORT", []), None);
+    }
+}
@@ -2,17 +2,115 @@
 //! loaded environment variables.
 
 // Importing external crates
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
+use std::{error, fmt};
 
 // Importing local modules
-use super::vars::EnvVar;
+use super::map::EnvMap;
+use super::suggest::suggest;
+use super::vars::{check_prefix, EnvVar};
+use crate::core::config::UnknownEnvVars;
 use crate::core::err::{AppError, ErrorKind};
 
+/// ## Problem with environment variables enum.
+///
+/// # Variants
+/// - `Unknown` - Variables are loaded, but not known.
+/// - `Missing` - Required variables are not loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvVarProblem {
+    Unknown,
+    Missing,
+}
+
+/// ## Environment variable issue struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the variable.
+/// + `suggestion`: `Option<String>` - Likely intended name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvVarIssue {
+    pub name: String,
+    pub suggestion: Option<String>,
+}
+
+/// ## Environment variables error struct.
+///
+/// Error is the source of the `AppError` returned by
+/// the validation. It is displayed as JSON, so tools
+/// can read the names and suggestions from the logs.
+///
+/// ## Fields
+/// + `problem`: `EnvVarProblem` - What is wrong with the variables.
+/// + `vars`: `Vec<EnvVarIssue>` - Affected variables, sorted by name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvVarsError {
+    pub problem: EnvVarProblem,
+    pub vars: Vec<EnvVarIssue>,
+}
+
+impl EnvVarsError {
+    /// ## Creates the error with suggestions for every name.
+    ///
+    /// ## Parameters
+    /// - `problem`: What is wrong with the variables.
+    /// - `names`: Affected variables.
+    /// - `candidates`: Names that could have been intended.
+    fn new(problem: EnvVarProblem, mut names: Vec<&str>, candidates: &[&str]) -> Self {
+        names.sort_unstable();
+
+        EnvVarsError {
+            problem,
+            vars: names
+                .into_iter()
+                .map(|name| EnvVarIssue {
+                    name: name.to_string(),
+                    suggestion: suggest(name, candidates.iter().copied()).map(str::to_string),
+                })
+                .collect(),
+        }
+    }
+
+    /// ## Returns the human readable message.
+    ///
+    /// Message looks like `Unknown environment variables:
+    /// 'AXA_DB_PROT (did you mean AXA_DB_PORT?)'`.
+    fn message(&self) -> String {
+        let problem = match self.problem {
+            EnvVarProblem::Unknown => "Unknown",
+            EnvVarProblem::Missing => "Missing",
+        };
+        let vars: Vec<String> = self
+            .vars
+            .iter()
+            .map(|var| match &var.suggestion {
+                Some(suggestion) => format!("{} (did you mean {}?)", var.name, suggestion),
+                None => var.name.clone(),
+            })
+            .collect();
+
+        format!("{} environment variables: '{}'", problem, vars.join(", "))
+    }
+}
+
+impl fmt::Display for EnvVarsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+
+        write!(f, "{}", json)
+    }
+}
+
+impl error::Error for EnvVarsError {}
+
 /// ## Validates loaded environment variables.
 ///
 /// Function validates loaded environment variables
 /// against specified array of environment variables.
+/// The prefix is checked first, see `check_prefix`.
 ///
 /// ## Examples
 /// ```
@@ -23,6 +121,8 @@ use crate::core::err::{AppError, ErrorKind};
 /// ## Parameters
 /// - `var_prefix`: Prefix for environment variables.
 /// - `vars_to_validate`: Variables to validate against.
+/// - `unknown_vars`: Handling of unknown variables.
+/// - `env`: Loaded environment variables.
 ///
 /// ## Returns
 /// + `Result<(), AppError>`
@@ -30,25 +130,32 @@ use crate::core::err::{AppError, ErrorKind};
 ///       have correct types.
 ///     - `AppError`: Error type that contains error kind,
 ///       message and source.
-pub fn validate<V>(var_prefix: &str, vars_to_validate: HashSet<V>) -> Result<(), AppError>
+pub fn validate<V>(
+    var_prefix: &str,
+    vars_to_validate: HashSet<V>,
+    unknown_vars: UnknownEnvVars,
+    env: &EnvMap,
+) -> Result<(), AppError>
 where
     V: EnvVar,             // HashSet of the type that implements the EnvVar trait
     V::VarType: Eq + Hash, // Ensure that the type can be used in a HashSet
 {
+    check_prefix(var_prefix)?;
+
     // Build a map of the variables to validate for
     // easier access and validation
     let vars_to_validate_map = vars_to_validate
         .iter()
-        .map(|var| (var.name(), var))
+        .map(|var| (var.name(var_prefix), var))
         .collect();
 
     // Compare variables to validate with the loaded
     // environment variables, i.e. check if all required
     // variables are present and if there are any unknown
-    compare_required_with_process_env(var_prefix, &vars_to_validate_map)?;
+    compare_required_with_loaded_env(var_prefix, &vars_to_validate_map, unknown_vars, env)?;
 
     // Verify the types of the loaded environment variables
-    verify_types(&vars_to_validate_map)?;
+    verify_types(&vars_to_validate_map, env)?;
 
     Ok(())
 }
@@ -62,14 +169,18 @@ where
 /// ## Parameters
 /// - `var_prefix`: Prefix for environment variables.
 /// - `vars_to_validate`: Variables to validate against.
+/// - `unknown_vars`: Handling of unknown variables.
+/// - `env`: Loaded environment variables.
 ///
 /// ## Returns
 /// + `Result<(), AppError>`
 ///     - `()`: If no missing variables are found.
 ///     - `AppError`: If missing variables are found.
-fn compare_required_with_process_env<V>(
+fn compare_required_with_loaded_env<V>(
     var_prefix: &str,
     vars_to_validate: &HashMap<String, &V>,
+    unknown_vars: UnknownEnvVars,
+    env: &EnvMap,
 ) -> Result<(), AppError>
 where
     V: EnvVar,             // HashSet of the type that implements the EnvVar trait
@@ -77,9 +188,9 @@ where
 {
     // Collect variables from the loaded environment that start
     // with the specified prefix
-    let loaded_vars_with_prefix: HashMap<String, String> = collect_app_vars(var_prefix);
+    let loaded_vars_with_prefix: HashMap<String, String> = env.with_prefix(var_prefix);
 
-    check_unknown(&loaded_vars_with_prefix, vars_to_validate)?;
+    check_unknown(&loaded_vars_with_prefix, vars_to_validate, unknown_vars)?;
 
     check_missing(&loaded_vars_with_prefix, vars_to_validate)?;
 
@@ -89,46 +200,53 @@ where
 /// ## Checks for unknown environment variables.
 ///
 /// Function checks if there are any unknown environment
-/// variables in the loaded environment variables.
+/// variables in the loaded environment variables. Unknown
+/// variables are an error in `strict` mode, a warning in
+/// `warn` mode and are not checked in `ignore` mode.
 ///
 /// ## Parameters
 /// - `loaded_vars`: Loaded environment variables.
 /// - `vars_to_validate`: Variables to validate against.
+/// - `unknown_vars`: Handling of unknown variables.
 ///
 /// ## Returns
 /// + `Result<(), AppError>`
-///    - `()`: If no unknown variables are found.
-///    - `AppError`: If unknown variables are found.
+///    - `()`: If no unknown variables are found or they
+///      are allowed.
+///    - `AppError`: If unknown variables are found in
+///      `strict` mode.
 fn check_unknown<V>(
     loaded_vars: &HashMap<String, String>,
     vars_to_validate: &HashMap<String, &V>,
+    unknown_vars: UnknownEnvVars,
 ) -> Result<(), AppError>
 where
     V: EnvVar,             // HashSet of the type that implements the EnvVar trait
     V::VarType: Eq + Hash, // Ensure that the type can be used in a HashSet
 {
+    if unknown_vars == UnknownEnvVars::Ignore {
+        return Ok(());
+    }
+
     // Collect the keys of the unknown loaded environment variables
-    let unknown_vars: Vec<&str> = loaded_vars
-        .keys()
-        .filter_map(|key| {
-            if !vars_to_validate.contains_key(key) {
-                Some(key.as_str())
-            } else {
-                None
-            }
-        })
-        .collect();
+    let unknown: Vec<&str> = unknown_keys(loaded_vars, vars_to_validate);
 
     // If there are unknown variables, return an error
-    if !unknown_vars.is_empty() {
+    // or log them depending on the mode
+    if !unknown.is_empty() {
+        let known: Vec<&str> = vars_to_validate.keys().map(String::as_str).collect();
+        let err = EnvVarsError::new(EnvVarProblem::Unknown, unknown, &known);
+
+        if unknown_vars == UnknownEnvVars::Warn {
+            tracing::warn!(details = %err, "{}", err.message());
+            return Ok(());
+        }
+
         let kind = ErrorKind::Env;
-        let message = format!(
-            "Unknown environment variables: '{}'",
-            unknown_vars.join(", ")
-        );
-        let source = None;
+        let message = err.message();
+        let source = Some(Box::new(err) as Box<dyn error::Error>);
 
-        return Err(AppError::new(kind, message, source));
+        return Err(AppError::new(kind, message, source).with_code("env.unknown_vars"));
     }
 
     Ok(())
@@ -138,7 +256,8 @@ where
 ///
 /// Function checks if any of the required environment
 /// variables are missing from the loaded environment
-/// variables.
+/// variables. Unknown loaded variables are suggested
+/// as the likely misspelled names.
 ///
 /// ## Parameters
 /// - `loaded_vars`: Loaded environment variables.
@@ -157,9 +276,9 @@ where
     V::VarType: Eq + Hash, // Ensure that the type can be used in a HashSet
 {
     let missing_vars: Vec<&str> = vars_to_validate
-        .keys()
-        .filter_map(|key| {
-            if !loaded_vars.contains_key(key) {
+        .iter()
+        .filter_map(|(key, var)| {
+            if !loaded_vars.contains_key(key) && !var.optional() {
                 Some(key.as_str())
             } else {
                 None
@@ -168,19 +287,31 @@ where
         .collect();
 
     if !missing_vars.is_empty() {
+        let unknown: Vec<&str> = unknown_keys(loaded_vars, vars_to_validate);
+        let err = EnvVarsError::new(EnvVarProblem::Missing, missing_vars, &unknown);
+
         let kind = ErrorKind::Env;
-        let message = format!(
-            "Missing environment variables: '{}'",
-            missing_vars.join(", ")
-        );
-        let source = None;
+        let message = err.message();
+        let source = Some(Box::new(err) as Box<dyn error::Error>);
 
-        return Err(AppError::new(kind, message, source));
+        return Err(AppError::new(kind, message, source).with_code("env.missing_vars"));
     }
 
     Ok(())
 }
 
+/// ## Returns the loaded variables that are not known.
+fn unknown_keys<'a, V>(
+    loaded_vars: &'a HashMap<String, String>,
+    vars_to_validate: &HashMap<String, &V>,
+) -> Vec<&'a str> {
+    loaded_vars
+        .keys()
+        .filter(|key| !vars_to_validate.contains_key(*key))
+        .map(String::as_str)
+        .collect()
+}
+
 /// ## Verifies the types of the loaded environment variables.
 ///
 /// Function verifies the types of the loaded environment
@@ -188,44 +319,131 @@ where
 ///
 /// ## Parameters
 /// - `vars_to_validate`: Variables to validate against.
+/// - `env`: Loaded environment variables.
 ///
 /// ## Returns
 /// + `Result<(), AppError>`
 ///     - `()`: If types of all variables are correct.
 ///     - `AppError`: If any variable has an invalid type.
-fn verify_types<V>(vars_to_validate: &HashMap<String, &V>) -> Result<(), AppError>
+fn verify_types<V>(vars_to_validate: &HashMap<String, &V>, env: &EnvMap) -> Result<(), AppError>
 where
     V: EnvVar,             // HashSet of the type that implements the EnvVar trait
     V::VarType: Eq + Hash, // Ensure that the type can be used in a HashSet
 {
-    for (_, var_data) in vars_to_validate {
-        var_data.verify()?;
+    for var_data in vars_to_validate.values() {
+        var_data.verify(env)?;
     }
     Ok(())
 }
 
-/// ## Collects environment variables that start with a prefix.
-///
-/// Function collects environment variables that start with
-/// a specified prefix.
-///
-/// ## Parameters
-/// - `var_prefix`: Prefix for environment variables.
-///
-/// ## Returns
-/// - `HashMap<String, String>`: Environment variables that start
-fn collect_app_vars(var_prefix: &str) -> HashMap<String, String> {
-    std::env::vars()
-        .filter(|(key, _)| key.starts_with(var_prefix))
-        .collect()
-}
-
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::core::env::vars::RequiredEnvVar;
+
+    // Test `check_unknown` function in every mode
+    // when unknown variables are loaded.
+    #[test]
+    fn test_check_unknown_modes() {
+        let loaded_vars: HashMap<String, String> = HashMap::from([
+            ("APP_PLATFORM_ID".to_string(), "42".to_string()),
+            ("APP_DEPLOY_REGION".to_string(), "eu".to_string()),
+        ]);
+        let known: HashMap<String, &RequiredEnvVar> = HashMap::new();
+
+        let result = check_unknown(&loaded_vars, &known, UnknownEnvVars::Strict);
+
+        let err = result.unwrap_err();
+        assert_eq!(err.code, "env.unknown_vars");
+        assert_eq!(
+            err.message,
+            "Unknown environment variables: 'APP_DEPLOY_REGION, APP_PLATFORM_ID'"
+        );
+        assert_eq!(
+            check_unknown(&loaded_vars, &known, UnknownEnvVars::Warn),
+            Ok(())
+        );
+        assert_eq!(
+            check_unknown(&loaded_vars, &known, UnknownEnvVars::Ignore),
+            Ok(())
+        );
+    }
+
+    // Test `validate` function with a prefix that
+    // can not start variable names.
+    #[test]
+    fn test_validate_invalid_prefix() {
+        for prefix in ["", "axa_", "1APP_", "APP-"] {
+            let result = validate(
+                prefix,
+                RequiredEnvVar::all(),
+                UnknownEnvVars::Ignore,
+                &EnvMap::default(),
+            );
+
+            assert_eq!(result.unwrap_err().code, "env.invalid_prefix", "{}", prefix);
+        }
+    }
+
+    // Test `EnvVarsError` suggestions for misspelled
+    // and missing variables.
+    #[test]
+    fn test_env_vars_error_suggestions() {
+        let known = ["APP_DB_PORT", "APP_DB_HOST"];
+
+        let unknown = EnvVarsError::new(
+            EnvVarProblem::Unknown,
+            vec!["APP_PLATFORM_ID", "APP_DB_PROT"],
+            &known,
+        );
+        let missing = EnvVarsError::new(
+            EnvVarProblem::Missing,
+            vec!["APP_DB_PORT"],
+            &["APP_DB_PROT"],
+        );
+
+        assert_eq!(
+            unknown.message(),
+            "Unknown environment variables: \
+             'APP_DB_PROT (did you mean APP_DB_PORT?), APP_PLATFORM_ID'"
+        );
+        assert_eq!(
+            missing.message(),
+            "Missing environment variables: 'APP_DB_PORT (did you mean APP_DB_PROT?)'"
+        );
+        assert_eq!(
+            unknown.to_string(),
+            r#"{"problem":"unknown","vars":[{"name":"APP_DB_PROT","suggestion":"APP_DB_PORT"},{"name":"APP_PLATFORM_ID","suggestion":null}]}"#
+        );
+    }
+
     // use super::*;
     // use std::error;
 
-    const PREFIX: &str = "APP_";
+    // const PREFIX: &str = "APP_";
+
+    // Test checks if optional variables are not reported as missing.
+    #[test]
+    fn test_check_missing_optional() {
+        let loaded_vars: HashMap<String, String> =
+            HashMap::from([("APP_DB_NAME".to_string(), "my_db".to_string())]);
+        let known: HashMap<String, &RequiredEnvVar> = HashMap::from([
+            ("APP_DB_NAME".to_string(), &RequiredEnvVar::DbName),
+            (
+                "APP_PATH_TO_DB_SSL_ROOT_CERT".to_string(),
+                &RequiredEnvVar::PathToDbSslRootCert,
+            ),
+        ]);
+
+        assert!(check_missing(&loaded_vars, &known).is_ok());
+
+        let known = HashMap::from([("APP_DB_HOST".to_string(), &RequiredEnvVar::DbHost)]);
+
+        assert_eq!(
+            check_missing(&loaded_vars, &known).unwrap_err().code,
+            "env.missing_vars"
+        );
+    }
 
     // Test `validate`function when all
     // required variables are present, have
@@ -5,21 +5,29 @@
 //! that are used to build new errors in the app.
 
 // std library imports
+use std::backtrace::Backtrace;
 use std::error;
 use std::fmt;
 
 /// Application error struct.
 ///
 /// Struct represents an error in the application
-/// with its kind, message and source.
+/// with its kind, message and source. The `code` is
+/// a stable machine-readable identifier, the
+/// `status_hint` overrides the HTTP status derived
+/// from the kind and the `backtrace` is captured in
+/// debug builds only (when `RUST_BACKTRACE` is set).
 ///
 /// # Examples
 /// ```
-/// use axum_auth::err::{AppError, ErrorKind};
+/// use axum_auth::core::err::{AppError, ErrorKind};
 ///
 /// let err = AppError { kind: ErrorKind::Env,
 ///                      message: "Error loading environment variables".to_string(),
-///                      source: None
+///                      source: None,
+///                      code: ErrorKind::Env.code(),
+///                      status_hint: None,
+///                      backtrace: None,
 ///                     };
 /// ```
 #[derive(Debug)]
@@ -27,6 +35,9 @@ pub struct AppError {
     pub kind: ErrorKind,
     pub message: String,
     pub source: Option<Box<dyn error::Error + 'static>>,
+    pub code: &'static str,
+    pub status_hint: Option<u16>,
+    pub backtrace: Option<Backtrace>,
 }
 
 /// Implementation block for `AppError` struct.
@@ -38,14 +49,19 @@ impl AppError {
     ///
     /// # Examples
     /// ```
-    /// use axum_auth::err::{AppError, ErrorKind};
+    /// use axum_auth::core::err::{AppError, ErrorKind};
     ///
     /// let err_msg = "Error loading environment variables".to_string();
     ///
     /// let err = AppError::new(ErrorKind::Env,
     ///                         err_msg.clone(),
     ///                         None);
-    /// let expected = AppError { kind: ErrorKind::Env, message: err_msg, source: None };
+    /// let expected = AppError { kind: ErrorKind::Env,
+    ///                           message: err_msg,
+    ///                           source: None,
+    ///                           code: "env",
+    ///                           status_hint: None,
+    ///                           backtrace: None };
     ///
     /// assert_eq!(err, expected);
     /// ```
@@ -56,29 +72,142 @@ impl AppError {
     /// - `source`: Error source, original error.
     ///
     /// # Returns
-    /// - New `AppError` instance.
+    /// - New `AppError` instance, its code is the code
+    ///   of the kind.
     pub fn new(
         kind: ErrorKind,
         message: String,
         source: Option<Box<dyn error::Error + 'static>>,
     ) -> Self {
         AppError {
+            code: kind.code(),
             kind,
             message,
             source,
+            status_hint: None,
+            backtrace: capture_backtrace(),
         }
     }
+
+    /// Replaces the machine-readable code of the error.
+    ///
+    /// # Examples
+    /// ```
+    /// use axum_auth::core::err::{AppError, ErrorKind};
+    ///
+    /// let err = AppError::new(ErrorKind::Env, "Missing variables".to_string(), None)
+    ///     .with_code("env.missing_vars");
+    ///
+    /// assert_eq!(err.code, "env.missing_vars");
+    /// ```
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Sets the HTTP status the error should be rendered with.
+    ///
+    /// # Examples
+    /// ```
+    /// use axum_auth::core::err::{AppError, ErrorKind};
+    ///
+    /// let err = AppError::new(ErrorKind::Db, "Email is taken".to_string(), None)
+    ///     .with_status_hint(409);
+    ///
+    /// assert_eq!(err.status_hint, Some(409));
+    /// ```
+    pub fn with_status_hint(mut self, status: u16) -> Self {
+        self.status_hint = Some(status);
+        self
+    }
+
+    /// Sets the original error.
+    ///
+    /// # Examples
+    /// ```
+    /// use axum_auth::core::err::AppError;
+    ///
+    /// let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "not found");
+    /// let err = AppError::io("Failed to read file").with_source(io_err);
+    ///
+    /// assert!(err.source.is_some());
+    /// ```
+    pub fn with_source(mut self, source: impl error::Error + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// Creates a `Config` error without source.
+    ///
+    /// # Examples
+    /// ```
+    /// use axum_auth::core::err::{AppError, ErrorKind};
+    ///
+    /// let err = AppError::config("Port must not be 0");
+    ///
+    /// assert_eq!(err, AppError::new(ErrorKind::Config, "Port must not be 0".to_string(), None));
+    /// ```
+    pub fn config(message: impl Into<String>) -> Self {
+        AppError::new(ErrorKind::Config, message.into(), None)
+    }
+
+    /// Creates an `Env` error without source.
+    pub fn env(message: impl Into<String>) -> Self {
+        AppError::new(ErrorKind::Env, message.into(), None)
+    }
+
+    /// Creates a `Db` error without source.
+    pub fn db(message: impl Into<String>) -> Self {
+        AppError::new(ErrorKind::Db, message.into(), None)
+    }
+
+    /// Creates an `Auth` error without source.
+    pub fn auth(message: impl Into<String>) -> Self {
+        AppError::new(ErrorKind::Auth, message.into(), None)
+    }
+
+    /// Creates a `Validation` error without source.
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::new(ErrorKind::Validation, message.into(), None)
+    }
+
+    /// Creates an `Io` error without source.
+    pub fn io(message: impl Into<String>) -> Self {
+        AppError::new(ErrorKind::Io, message.into(), None)
+    }
+
+    /// Creates an `External` error without source.
+    pub fn external(message: impl Into<String>) -> Self {
+        AppError::new(ErrorKind::External, message.into(), None)
+    }
+
+    /// Creates an `Internal` error without source.
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::new(ErrorKind::Internal, message.into(), None)
+    }
+}
+
+/// Captures the backtrace in debug builds.
+///
+/// Capturing respects `RUST_BACKTRACE`, so it is
+/// cheap when backtraces are disabled.
+fn capture_backtrace() -> Option<Backtrace> {
+    match cfg!(debug_assertions) {
+        true => Some(Backtrace::capture()),
+        false => None,
+    }
 }
 
 /// Implementation of `PartialEq` trait for `AppError` struct.
 impl PartialEq for AppError {
     /// Compares two `AppError` instances.
     /// Function compares two `AppError` instances by
-    /// comparing their kind, message and source.
+    /// comparing their kind, message, source, code and
+    /// status hint. Backtraces are not compared.
     ///
     /// # Examples
     /// ```
-    /// use axum_auth::err::{AppError, ErrorKind};
+    /// use axum_auth::core::err::{AppError, ErrorKind};
     ///
     /// let err_msg = "Error loading environment variables".to_string();
     ///
@@ -101,6 +230,8 @@ impl PartialEq for AppError {
     fn eq(&self, other: &Self) -> bool {
         self.kind == other.kind
             && self.message == other.message
+            && self.code == other.code
+            && self.status_hint == other.status_hint
             && self.source.as_ref().map(|e| e.to_string())
                 == other.source.as_ref().map(|e| e.to_string())
     }
@@ -115,7 +246,7 @@ impl fmt::Display for AppError {
     ///
     /// # Examples
     /// ```
-    /// use axum_auth::err::{AppError, ErrorKind};
+    /// use axum_auth::core::err::{AppError, ErrorKind};
     ///
     /// let err_msg = "Error loading environment variables".to_string();
     ///
@@ -139,7 +270,12 @@ impl fmt::Display for AppError {
 }
 
 /// Implementation of `Error` trait for `AppError` struct.
-impl error::Error for AppError {}
+impl error::Error for AppError {
+    /// Returns the original error, so error chains can be walked.
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.source.as_deref()
+    }
+}
 
 /// Error kind enum.
 ///
@@ -147,30 +283,88 @@ impl error::Error for AppError {}
 ///
 /// # Examples
 /// ```
-/// use axum_auth::err::ErrorKind;
+/// use axum_auth::core::err::ErrorKind;
 ///
 /// let kind = ErrorKind::Env;
 /// ```
 ///
 /// # Variants
+/// - `Config`: Invalid or missing configuration.
 /// - `Env`: Error setting up application environment.
-/// - `Parse`: Error parsing data.
-#[derive(Debug, PartialEq)]
+/// - `Db`: Database connection or query error.
+/// - `Auth`: Request is not authenticated.
+/// - `Forbidden`: Principal is not allowed to perform the action.
+/// - `LoginWindowDenied`: Login attempted outside of the allowed window.
+/// - `CountryDenied`: Login attempted from a country that is not allowed.
+/// - `NotFound`: Requested resource does not exist.
+/// - `Validation`: Value is malformed or does not match its declared type.
+/// - `Io`: File system or network I/O error.
+/// - `External`: Failure of an external service.
+/// - `Internal`: Unexpected failure inside the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorKind {
+    // Error kind for invalid configuration and configuration files.
+    Config,
+
     // General error kind for environment setup.
     Env,
 
-    // Error kind for parsing data.
-    Parse,
+    // Error kind for database connection and query failures.
+    Db,
+
+    // Error kind for requests without a valid principal.
+    Auth,
+
+    // Error kind for actions the principal is not allowed to perform.
+    Forbidden,
+
+    // Error kind for logins outside of the allowed time window.
+    LoginWindowDenied,
+
+    // Error kind for logins from countries that are not allowed.
+    CountryDenied,
+
+    // Error kind for resources that do not exist.
+    NotFound,
+
+    // Error kind for malformed values and values of a wrong type.
+    Validation,
+
+    // Error kind for file system and network I/O failures.
+    Io,
 
-    // Error kind for invalid configuration.
-    InvalidConfig,
+    // Error kind for failures of external services.
+    External,
 
-    // Error kind when failed to set configuration file path.
-    ConfigFilePath,
+    // Error kind for unexpected failures, e.g. of the HTTP server.
+    Internal,
+}
 
-    // Error kind for invalid value type
-    InvalidValueType,
+impl ErrorKind {
+    /// Returns the default machine-readable code of the kind.
+    ///
+    /// # Examples
+    /// ```
+    /// use axum_auth::core::err::ErrorKind;
+    ///
+    /// assert_eq!(ErrorKind::Config.code(), "config");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Config => "config",
+            Self::Env => "env",
+            Self::Db => "db",
+            Self::Auth => "auth",
+            Self::Forbidden => "forbidden",
+            Self::LoginWindowDenied => "login_window_denied",
+            Self::CountryDenied => "country_denied",
+            Self::NotFound => "not_found",
+            Self::Validation => "validation",
+            Self::Io => "io",
+            Self::External => "external",
+            Self::Internal => "internal",
+        }
+    }
 }
 
 #[cfg(test)]
@@ -184,6 +378,9 @@ mod tests {
             kind: ErrorKind::Env,
             message: "Error loading environment variables".to_string(),
             source: None,
+            code: ErrorKind::Env.code(),
+            status_hint: None,
+            backtrace: None,
         };
         assert_eq!(err.kind, ErrorKind::Env);
         assert_eq!(err.message, "Error loading environment variables");
@@ -197,11 +394,17 @@ mod tests {
             kind: ErrorKind::Env,
             message: "Some env error".to_string(),
             source: None,
+            code: ErrorKind::Env.code(),
+            status_hint: None,
+            backtrace: None,
         };
         let err: AppError = AppError {
             kind: ErrorKind::Env,
             message: "Error loading environment variables".to_string(),
             source: Some(Box::new(source_err)),
+            code: ErrorKind::Env.code(),
+            status_hint: None,
+            backtrace: None,
         };
 
         assert_eq!(err.kind, ErrorKind::Env);
@@ -219,6 +422,9 @@ mod tests {
             kind: ErrorKind::Env,
             message: err_msg,
             source: None,
+            code: ErrorKind::Env.code(),
+            status_hint: None,
+            backtrace: None,
         };
 
         assert_eq!(err, expected);
@@ -231,11 +437,17 @@ mod tests {
             kind: ErrorKind::Env,
             message: "Some env error".to_string(),
             source: None,
+            code: ErrorKind::Env.code(),
+            status_hint: None,
+            backtrace: None,
         };
         let source_err_copy: AppError = AppError {
             kind: ErrorKind::Env,
             message: "Some env error".to_string(),
             source: None,
+            code: ErrorKind::Env.code(),
+            status_hint: None,
+            backtrace: None,
         };
         let err_msg: String = "Error loading environment variables".to_string();
 
@@ -245,6 +457,9 @@ mod tests {
             kind: ErrorKind::Env,
             message: err_msg,
             source: Some(Box::new(source_err_copy)),
+            code: ErrorKind::Env.code(),
+            status_hint: None,
+            backtrace: None,
         };
 
         assert_eq!(err, expected);
@@ -268,7 +483,7 @@ mod tests {
         let err_msg2: String = "Error parsing environment variables".to_string();
 
         let err1: AppError = AppError::new(ErrorKind::Env, err_msg1.clone(), None);
-        let err2: AppError = AppError::new(ErrorKind::Parse, err_msg2, None);
+        let err2: AppError = AppError::new(ErrorKind::Validation, err_msg2, None);
 
         assert_ne!(err1, err2);
     }
@@ -290,6 +505,84 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    // Tests `AppError` code and status hint overrides.
+    #[test]
+    fn test_app_error_code_and_status_hint() {
+        let err: AppError = AppError::new(ErrorKind::Db, "Email is taken".to_string(), None);
+
+        assert_eq!(err.code, "db");
+        assert_eq!(err.status_hint, None);
+
+        let err = err.with_code("users.email_taken").with_status_hint(409);
+
+        assert_eq!(err.code, "users.email_taken");
+        assert_eq!(err.status_hint, Some(409));
+        assert_ne!(
+            err,
+            AppError::new(ErrorKind::Db, "Email is taken".to_string(), None)
+        );
+    }
+
+    // Tests `Error::source` chaining of `AppError`.
+    #[test]
+    fn test_app_error_source_chain() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
+        let inner = AppError::new(
+            ErrorKind::Env,
+            "Failed to load file".to_string(),
+            Some(Box::new(io_err)),
+        );
+        let outer = AppError::new(
+            ErrorKind::Env,
+            "Failed to load environment".to_string(),
+            Some(Box::new(inner)),
+        );
+
+        let chain: Vec<String> =
+            std::iter::successors(error::Error::source(&outer), |err: &&dyn error::Error| {
+                err.source()
+            })
+            .map(|err| err.to_string())
+            .collect();
+
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[1], "file not found");
+    }
+
+    // Tests that backtraces are only captured in debug builds.
+    #[test]
+    fn test_app_error_backtrace() {
+        let err: AppError = AppError::new(ErrorKind::Internal, "Failed".to_string(), None);
+
+        assert_eq!(err.backtrace.is_some(), cfg!(debug_assertions));
+    }
+
+    // Tests helper constructors of `AppError`.
+    #[test]
+    fn test_app_error_helpers() {
+        let cases = [
+            (AppError::config("message"), ErrorKind::Config),
+            (AppError::env("message"), ErrorKind::Env),
+            (AppError::db("message"), ErrorKind::Db),
+            (AppError::auth("message"), ErrorKind::Auth),
+            (AppError::validation("message"), ErrorKind::Validation),
+            (AppError::io("message"), ErrorKind::Io),
+            (AppError::external("message"), ErrorKind::External),
+            (AppError::internal("message"), ErrorKind::Internal),
+        ];
+
+        for (err, kind) in cases {
+            assert_eq!(err, AppError::new(kind, "message".to_string(), None));
+        }
+
+        let err = AppError::io("Failed to read").with_source(std::io::Error::other("denied"));
+
+        assert_eq!(
+            err.source.map(|e| e.to_string()),
+            Some("denied".to_string())
+        );
+    }
+
     // Tests `ErrorKind` enum equality.
     #[test]
     fn test_error_kind_eq() {
@@ -303,7 +596,7 @@ mod tests {
     #[test]
     fn test_error_kind_eq_false() {
         let kind1: ErrorKind = ErrorKind::Env;
-        let kind2: ErrorKind = ErrorKind::Parse;
+        let kind2: ErrorKind = ErrorKind::Validation;
 
         assert_ne!(kind1, kind2);
     }
@@ -0,0 +1,240 @@
+//! Message broker module.
+//!
+//! Module publishes the events of the outbox to NATS
+//! subjects or Kafka topics as JSON `EventMessage`s. The
+//! broker is a relay of the `outbox` job, an event the
+//! broker did not accept fails the run and its batch is
+//! published again. Consumers see every event at least
+//! once and drop duplicates by its ID, NATS messages also
+//! carry it as `Nats-Msg-Id` for JetStream.
+//!
+//! Kafka messages are keyed by the user of the event, so
+//! the events of a user stay in order.
+
+// Imports from external crates
+use async_trait::async_trait;
+use serde_json::Value as Json;
+use sqlx::PgConnection;
+use std::time::{Duration, Instant};
+
+// Local imports
+use super::outbox::{OutboxEvent, Relay};
+use crate::core::config::{BrokerKind, BrokerSettings};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::webhooks::WebhookPayload;
+
+// Re-exports of the wire types
+pub use crate::api::events::{EventMessage, EVENT_MESSAGE_VERSION};
+
+/// Counter of the published messages per broker, event and result.
+pub const MESSAGES_METRIC: &str = "auth_broker_messages_total";
+
+/// Histogram of the seconds the broker took to accept a message.
+pub const LATENCY_METRIC: &str = "auth_broker_publish_seconds";
+
+/// ## Client of the configured broker.
+enum Client {
+    #[cfg(feature = "nats")]
+    Nats(async_nats::Client),
+    #[cfg(feature = "kafka")]
+    Kafka(rdkafka::producer::FutureProducer),
+}
+
+/// ## Broker struct.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::config::BrokerSettings;
+/// use axum_auth::core::events::broker::Broker;
+///
+/// # async fn example() -> Result<(), axum_auth::core::err::AppError> {
+/// let broker = Broker::connect(&BrokerSettings::default()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Broker {
+    client: Client,
+    settings: BrokerSettings,
+}
+
+impl Broker {
+    /// ## Connects to the broker of the settings.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&BrokerSettings` - Broker, servers and subjects.
+    ///
+    /// ## Returns
+    /// + `Result<Broker, AppError>`
+    ///     - `Ok(Broker)`: Connected broker.
+    ///     - `Err(AppError)`: `External` if the broker can not be reached,
+    ///       `Config` if it was not compiled in.
+    pub async fn connect(settings: &BrokerSettings) -> Result<Self, AppError> {
+        let client = match settings.kind {
+            #[cfg(feature = "nats")]
+            BrokerKind::Nats => Client::Nats(
+                async_nats::ConnectOptions::new()
+                    .name(env!("CARGO_PKG_NAME"))
+                    .connection_timeout(Duration::from_secs(settings.timeout))
+                    .connect(settings.url.as_str())
+                    .await
+                    .map_err(external("Failed to connect to NATS"))?,
+            ),
+            #[cfg(feature = "kafka")]
+            BrokerKind::Kafka => Client::Kafka(
+                rdkafka::ClientConfig::new()
+                    .set("bootstrap.servers", &settings.url)
+                    .set("client.id", env!("CARGO_PKG_NAME"))
+                    .set(
+                        "message.timeout.ms",
+                        settings.timeout.saturating_mul(1000).to_string(),
+                    )
+                    .create()
+                    .map_err(external("Failed to create Kafka producer"))?,
+            ),
+            #[allow(unreachable_patterns)]
+            kind => {
+                return Err(AppError::config(format!(
+                    "Broker '{}' requires the '{}' feature",
+                    kind.as_str(),
+                    kind.as_str()
+                )))
+            }
+        };
+
+        Ok(Broker {
+            client,
+            settings: settings.clone(),
+        })
+    }
+
+    /// ## Publishes the message and waits until the broker accepted it.
+    async fn publish(&self, subject: &str, message: &EventMessage) -> Result<(), AppError> {
+        let timeout = Duration::from_secs(self.settings.timeout);
+        let body = serde_json::to_vec(message).map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                "Failed to serialize event message".to_string(),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        match &self.client {
+            #[cfg(feature = "nats")]
+            Client::Nats(client) => {
+                let mut headers = async_nats::HeaderMap::new();
+                headers.insert("Nats-Msg-Id", message.id.to_string());
+                client
+                    .publish_with_headers(subject.to_string(), headers, body.into())
+                    .await
+                    .map_err(external("Failed to publish to NATS"))?;
+
+                tokio::time::timeout(timeout, client.flush())
+                    .await
+                    .map_err(external("NATS did not accept the event in time"))?
+                    .map_err(external("Failed to flush NATS messages"))
+            }
+            #[cfg(feature = "kafka")]
+            Client::Kafka(producer) => {
+                let key = key(message);
+                let record = rdkafka::producer::FutureRecord::to(subject)
+                    .key(&key)
+                    .payload(&body);
+
+                producer
+                    .send(record, rdkafka::util::Timeout::After(timeout))
+                    .await
+                    .map(|_| ())
+                    .map_err(|(e, _)| external("Failed to produce to Kafka")(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Relay for Broker {
+    fn name(&self) -> &str {
+        self.settings.kind.as_str()
+    }
+
+    /// ## Publishes the event to its subject.
+    ///
+    /// Events that are not published are skipped.
+    async fn relay(&self, _conn: &mut PgConnection, event: &OutboxEvent) -> Result<(), AppError> {
+        if !self.settings.publishes(&event.event) {
+            return Ok(());
+        }
+
+        let payload: WebhookPayload =
+            serde_json::from_value(event.payload.clone()).map_err(|e| {
+                AppError::new(
+                    ErrorKind::Internal,
+                    "Failed to read outbox event".to_string(),
+                    Some(Box::new(e)),
+                )
+            })?;
+        let message = EventMessage::from(payload);
+        let subject = self.settings.subject(&event.event);
+        let broker = self.settings.kind.as_str();
+
+        let started = Instant::now();
+        let result = self.publish(&subject, &message).await;
+        metrics::histogram!(LATENCY_METRIC, "broker" => broker)
+            .record(started.elapsed().as_secs_f64());
+        metrics::counter!(
+            MESSAGES_METRIC,
+            "broker" => broker,
+            "event" => event.event.clone(),
+            "result" => if result.is_ok() { "published" } else { "failed" }
+        )
+        .increment(1);
+
+        result
+    }
+}
+
+/// ## Returns the key of the message.
+///
+/// Key is the user of the event, or the event ID when
+/// the event has no user.
+pub fn key(message: &EventMessage) -> String {
+    message
+        .data
+        .get("user_id")
+        .and_then(Json::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| message.id.to_string())
+}
+
+/// ## Maps a broker error into an external error.
+fn external<E: std::error::Error + 'static>(message: &'static str) -> impl FnOnce(E) -> AppError {
+    move |e| AppError::new(ErrorKind::External, message.to_string(), Some(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    // Test checks if messages are keyed by their user, else by their ID.
+    #[test]
+    fn test_key() {
+        let user_id = Uuid::new_v4();
+        let message = EventMessage::from(WebhookPayload {
+            id: Uuid::new_v4(),
+            event: "user.created".to_string(),
+            occurred_at: Utc::now(),
+            data: serde_json::json!({ "user_id": user_id }),
+        });
+
+        assert_eq!(message.version, EVENT_MESSAGE_VERSION);
+        assert_eq!(key(&message), user_id.to_string());
+
+        let message = EventMessage {
+            data: serde_json::json!({}),
+            ..message
+        };
+
+        assert_eq!(key(&message), message.id.to_string());
+    }
+}
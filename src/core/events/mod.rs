@@ -0,0 +1,11 @@
+//! Domain events module.
+//!
+//! Events of the account lifecycle are published through
+//! the transactional outbox, the relays forward them to
+//! the webhook deliveries and, with the `nats` or `kafka`
+//! feature, to a message broker.
+
+// References to submodules
+#[cfg(any(feature = "nats", feature = "kafka"))]
+pub mod broker;
+pub mod outbox;
@@ -0,0 +1,265 @@
+//! Transactional outbox module.
+//!
+//! Domain events are inserted into `outbox_events` by the
+//! transaction of the state change that caused them, so
+//! an event is stored exactly when the change committed.
+//! The `outbox` job of the worker hands the stored events
+//! to every relay and removes them in one transaction. A
+//! relay that fails rolls the whole batch back, it is
+//! relayed again by the next run.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value as Json;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Local imports
+use crate::core::config::OutboxSettings;
+use crate::core::db::tx::with_transaction;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::webhooks::{WebhookEvent, WebhookPayload};
+use crate::core::worker::{cron::Schedule, Job};
+
+/// Events stored by the state changes while the outbox is enabled.
+pub const EVENTS: [WebhookEvent; 2] = [WebhookEvent::UserCreated, WebhookEvent::LoginSucceeded];
+
+/// ## Stored event struct.
+///
+/// ## Fields
+/// + `id`: `i64` - ID of the row, orders the events.
+/// + `event_id`: `Uuid` - ID of the event, sent to the receivers.
+/// + `event`: `String` - Name of the event.
+/// + `payload`: `serde_json::Value` - Serialized `WebhookPayload`.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub event_id: Uuid,
+    pub event: String,
+    pub payload: Json,
+}
+
+/// ## Builds the stored event of the data.
+///
+/// ## Parameters
+/// - `event`: `WebhookEvent` - Kind of the event.
+/// - `data`: `serde_json::Value` - Event specific data.
+///
+/// ## Returns
+/// + `Result<OutboxEvent, AppError>`
+///     - `Ok(OutboxEvent)`: Event with a new ID, not stored yet.
+///     - `Err(AppError)`: If the payload could not be serialized.
+pub fn event(event: WebhookEvent, data: Json) -> Result<OutboxEvent, AppError> {
+    let payload = WebhookPayload {
+        id: Uuid::new_v4(),
+        event: event.as_str().to_string(),
+        occurred_at: Utc::now(),
+        data,
+    };
+
+    Ok(OutboxEvent {
+        id: 0,
+        event_id: payload.id,
+        event: payload.event.clone(),
+        payload: serde_json::to_value(&payload).map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                "Failed to serialize outbox event".to_string(),
+                Some(Box::new(e)),
+            )
+        })?,
+    })
+}
+
+/// ## Stores the event in the transaction of the state change.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::db::tx::with_transaction;
+/// use axum_auth::core::events::outbox;
+/// use axum_auth::core::webhooks::WebhookEvent;
+///
+/// # async fn example(pool: sqlx::PgPool, user_id: uuid::Uuid) -> Result<(), axum_auth::core::err::AppError> {
+/// with_transaction(&pool, |tx| {
+///     Box::pin(async move {
+///         // ... state change on `tx` ...
+///         let data = serde_json::json!({ "user_id": user_id });
+///         outbox::record(&mut *tx, WebhookEvent::UserCreated, data).await?;
+///
+///         Ok(())
+///     })
+/// })
+/// .await
+/// # }
+/// ```
+///
+/// ## Parameters
+/// - `conn`: `&mut PgConnection` - Connection of the running transaction.
+/// - `event`: `WebhookEvent` - Kind of the event.
+/// - `data`: `serde_json::Value` - Event specific data.
+///
+/// ## Returns
+/// + `Result<Uuid, AppError>`
+///     - `Ok(Uuid)`: ID of the stored event.
+///     - `Err(AppError)`: If the payload could not be serialized
+///       or the insert failed.
+pub async fn record(
+    conn: &mut PgConnection,
+    event: WebhookEvent,
+    data: Json,
+) -> Result<Uuid, AppError> {
+    let stored = self::event(event, data)?;
+
+    sqlx::query("INSERT INTO outbox_events (event_id, event, payload) VALUES ($1, $2, $3)")
+        .bind(stored.event_id)
+        .bind(&stored.event)
+        .bind(&stored.payload)
+        .execute(conn)
+        .await
+        .map_err(db_error("Failed to store outbox event"))?;
+
+    Ok(stored.event_id)
+}
+
+/// ## Relay trait.
+///
+/// Relays receive the connection of the transaction that
+/// removes the events. Writes to the database commit
+/// together with the removal, deliveries to external
+/// systems are repeated if the commit fails.
+#[async_trait]
+pub trait Relay: Send + Sync {
+    /// ## Returns the name of the relay.
+    fn name(&self) -> &str;
+
+    /// ## Forwards the event.
+    ///
+    /// ## Parameters
+    /// - `conn`: `&mut PgConnection` - Connection of the relay transaction.
+    /// - `event`: `&OutboxEvent` - Event to forward.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the event was forwarded.
+    ///     - `Err(AppError)`: If it failed, the batch is relayed again.
+    async fn relay(&self, conn: &mut PgConnection, event: &OutboxEvent) -> Result<(), AppError>;
+}
+
+/// ## Outbox relay job struct.
+///
+/// Every run locks the oldest events, so several workers
+/// never relay the same event at once.
+pub struct OutboxRelay {
+    pool: PgPool,
+    relays: Vec<Arc<dyn Relay>>,
+    batch_size: i64,
+    schedule: Schedule,
+}
+
+impl OutboxRelay {
+    /// ## Creates a new `OutboxRelay` instance without relays.
+    ///
+    /// ## Parameters
+    /// - `pool`: `PgPool` - Connection pool of the outbox table.
+    /// - `settings`: `&OutboxSettings` - Size of the batches.
+    /// - `schedule`: `Schedule` - Schedule of the relay runs.
+    pub fn new(pool: PgPool, settings: &OutboxSettings, schedule: Schedule) -> Self {
+        OutboxRelay {
+            pool,
+            relays: Vec::new(),
+            batch_size: i64::from(settings.batch_size),
+            schedule,
+        }
+    }
+
+    /// ## Adds a relay the events are forwarded to.
+    pub fn with_relay(mut self, relay: Arc<dyn Relay>) -> Self {
+        self.relays.push(relay);
+        self
+    }
+}
+
+#[async_trait]
+impl Job for OutboxRelay {
+    fn name(&self) -> &str {
+        "outbox"
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn run(&self) -> Result<(), AppError> {
+        let relays = self.relays.clone();
+        let batch_size = self.batch_size;
+
+        let relayed = with_transaction(&self.pool, |tx| {
+            Box::pin(async move {
+                let events = sqlx::query_as::<_, OutboxEvent>(
+                    "SELECT id, event_id, event, payload FROM outbox_events \
+                     ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED",
+                )
+                .bind(batch_size)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(db_error("Failed to load outbox events"))?;
+
+                for event in &events {
+                    for relay in &relays {
+                        relay.relay(&mut *tx, event).await.inspect_err(|_| {
+                            tracing::warn!(
+                                relay = relay.name(),
+                                event = event.event,
+                                event_id = %event.event_id,
+                                "Outbox relay failed"
+                            );
+                        })?;
+                    }
+                }
+
+                let ids: Vec<i64> = events.iter().map(|event| event.id).collect();
+                sqlx::query("DELETE FROM outbox_events WHERE id = ANY($1)")
+                    .bind(&ids)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error("Failed to remove relayed outbox events"))?;
+
+                Ok(ids.len())
+            })
+        })
+        .await?;
+
+        if relayed > 0 {
+            tracing::debug!(relayed, "Relayed outbox events");
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Maps a database error into `AppError`.
+fn db_error(message: &'static str) -> impl FnOnce(sqlx::Error) -> AppError {
+    move |e| AppError::new(ErrorKind::Db, message.to_string(), Some(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::webhooks::WebhookPayload;
+
+    // Test checks if the stored payload is the webhook payload of the event.
+    #[test]
+    fn test_event() {
+        let data = serde_json::json!({ "user_id": Uuid::nil() });
+
+        let stored = event(WebhookEvent::UserCreated, data.clone()).unwrap();
+        let payload: WebhookPayload = serde_json::from_value(stored.payload).unwrap();
+
+        assert_eq!(stored.event, "user.created");
+        assert_eq!(payload.id, stored.event_id);
+        assert_eq!(payload.event, "user.created");
+        assert_eq!(payload.data, data);
+    }
+}
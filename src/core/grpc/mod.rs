@@ -0,0 +1,533 @@
+//! gRPC service module.
+//!
+//! Module serves the `axum_auth.v1.Auth` service of
+//! `proto/axum_auth/v1/auth.proto` on its own port, in
+//! the runtime of the HTTP server and with its state.
+//! Tokens are verified by the same `Authenticator` as the
+//! routes and permissions decided by the same `Policy` as
+//! the administration routes, so internal services get
+//! the answers of the HTTP API without its overhead.
+//!
+//! The service has no authentication of its own, its port
+//! is meant to be reachable by internal services only.
+
+// Imports from external crates
+use std::sync::Arc;
+use tonic::{transport::server::TcpIncoming, Request, Response, Status};
+use uuid::Uuid;
+
+// Local imports
+use crate::core::auth::rbac::{Policy, Principal, RoleGrant};
+use crate::core::config::{GrpcSettings, ServerSettings, TokenFormat};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::principal::{Authenticator, CurrentSession};
+use crate::core::repo::postgres::{PgAccessTokenRepo, PgSessionRepo, PgUserRepo};
+use crate::core::repo::UserRepo;
+use crate::core::server::{bind, shutdown::ShutdownCoordinator};
+use crate::core::state::AppState;
+use crate::core::tenancy::{validate_tenant_id, with_tenant, DEFAULT_TENANT};
+use crate::core::users::roles::RoleRepo;
+use crate::core::users::User;
+
+/// Types and server generated from the protos.
+pub mod proto {
+    tonic::include_proto!("axum_auth.v1");
+}
+
+use proto::auth_server::{Auth, AuthServer};
+use proto::{
+    CheckPermissionRequest, CheckPermissionResponse, GetUserRequest, VerifyTokenRequest,
+    VerifyTokenResponse,
+};
+
+/// ## Auth service struct.
+///
+/// ## Fields
+/// + `authenticator`: `Arc<Authenticator>` - Verifier of the tokens.
+/// + `users`: `Arc<dyn UserRepo>` - Users returned by `GetUser`.
+/// + `roles`: `RoleRepo` - Role grants of the users.
+/// + `policy`: `Arc<Policy>` - Permissions of the roles.
+pub struct AuthService {
+    authenticator: Arc<Authenticator>,
+    users: Arc<dyn UserRepo>,
+    roles: RoleRepo,
+    policy: Arc<Policy>,
+}
+
+impl AuthService {
+    /// ## Creates a new `AuthService` instance.
+    ///
+    /// ## Parameters
+    /// - `authenticator`: `Arc<Authenticator>` - Verifier of the tokens.
+    /// - `users`: `Arc<dyn UserRepo>` - User repository.
+    /// - `roles`: `RoleRepo` - Role grants of the users.
+    /// - `policy`: `Arc<Policy>` - Permissions of the roles.
+    pub fn new(
+        authenticator: Arc<Authenticator>,
+        users: Arc<dyn UserRepo>,
+        roles: RoleRepo,
+        policy: Arc<Policy>,
+    ) -> Self {
+        AuthService {
+            authenticator,
+            users,
+            roles,
+            policy,
+        }
+    }
+
+    /// ## Creates the service of the application state.
+    ///
+    /// Tokens are verified like by the routes, opaque
+    /// ones when `auth.token_format` is `opaque`.
+    pub fn from_state(state: &AppState) -> Self {
+        let config = &state.config;
+        let mut authenticator = Authenticator::new(
+            state.keys.clone(),
+            &config.tokens,
+            Arc::new(PgSessionRepo::new(state.db.clone())),
+        );
+        if config.auth.token_format == TokenFormat::Opaque {
+            authenticator = authenticator
+                .with_opaque_tokens(Arc::new(PgAccessTokenRepo::new(state.db.write().clone())));
+        }
+
+        AuthService::new(
+            Arc::new(authenticator),
+            Arc::new(PgUserRepo::new(state.db.clone())),
+            RoleRepo::new(state.db.clone()),
+            Arc::new(Policy::default()),
+        )
+    }
+
+    /// ## Checks if the user of the token holds the permission.
+    async fn check(&self, request: CheckPermissionRequest) -> Result<(Uuid, bool), AppError> {
+        let permission = request.permission.parse()?;
+        let org_id = request.org_id.as_deref().map(parse_id).transpose()?;
+
+        let (principal, _, _) = self.authenticator.verify(&request.token).await?;
+        let grants = self
+            .roles
+            .list_user(principal.user_id)
+            .await?
+            .into_iter()
+            .map(|grant| RoleGrant::global(&grant.role))
+            .collect();
+        let principal = Principal {
+            grants,
+            ..principal
+        };
+        let allowed = self.policy.scope(&principal, permission).contains(org_id);
+
+        Ok((principal.user_id, allowed))
+    }
+}
+
+#[tonic::async_trait]
+impl Auth for AuthService {
+    async fn verify_token(
+        &self,
+        request: Request<VerifyTokenRequest>,
+    ) -> Result<Response<VerifyTokenResponse>, Status> {
+        let request = request.into_inner();
+        let tenant = tenant(&request.tenant).map_err(status)?;
+
+        let result = with_tenant(tenant, self.authenticator.verify(&request.token)).await;
+        let response = match result {
+            Ok((principal, session, scopes)) => VerifyTokenResponse {
+                active: true,
+                user_id: principal.user_id.to_string(),
+                session_id: session.map(|CurrentSession(id)| id.to_string()),
+                scopes,
+            },
+            Err(e) if e.kind == ErrorKind::Auth => VerifyTokenResponse::default(),
+            Err(e) => return Err(status(e)),
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn get_user(
+        &self,
+        request: Request<GetUserRequest>,
+    ) -> Result<Response<proto::User>, Status> {
+        let request = request.into_inner();
+        let tenant = tenant(&request.tenant).map_err(status)?;
+        let id = parse_id(&request.user_id).map_err(status)?;
+
+        with_tenant(tenant, self.users.get(id))
+            .await
+            .map(|user| Response::new(to_proto(user)))
+            .map_err(status)
+    }
+
+    async fn check_permission(
+        &self,
+        request: Request<CheckPermissionRequest>,
+    ) -> Result<Response<CheckPermissionResponse>, Status> {
+        let request = request.into_inner();
+        let tenant = tenant(&request.tenant).map_err(status)?;
+
+        with_tenant(tenant, self.check(request))
+            .await
+            .map(|(user_id, allowed)| {
+                Response::new(CheckPermissionResponse {
+                    allowed,
+                    user_id: user_id.to_string(),
+                })
+            })
+            .map_err(status)
+    }
+}
+
+/// ## Serves the service until the shutdown.
+///
+/// ## Parameters
+/// - `settings`: `&GrpcSettings` - Address of the service.
+/// - `service`: `AuthService` - Service to serve.
+/// - `coordinator`: `ShutdownCoordinator` - Shutdown coordinator.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the service stopped gracefully.
+///     - `Err(AppError)`: If the address is in use or the server failed.
+pub async fn serve(
+    settings: &GrpcSettings,
+    service: AuthService,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError> {
+    let listener = bind(&ServerSettings {
+        host: settings.host.clone(),
+        port: settings.port,
+        ..ServerSettings::default()
+    })
+    .await?;
+    let incoming = TcpIncoming::from_listener(listener, true, None).map_err(|e| {
+        AppError::new(
+            ErrorKind::Io,
+            "Failed to accept gRPC connections".to_string(),
+            Some(e),
+        )
+    })?;
+    tracing::info!("Serving gRPC on {}:{}", settings.host, settings.port);
+
+    tonic::transport::Server::builder()
+        .add_service(AuthServer::new(service))
+        .serve_with_incoming_shutdown(incoming, async move { coordinator.wait().await })
+        .await
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                "gRPC server stopped with an error".to_string(),
+                Some(Box::new(e)),
+            )
+        })
+}
+
+/// ## Returns the tenant of the request, the default one when empty.
+fn tenant(value: &str) -> Result<String, AppError> {
+    if value.is_empty() {
+        return Ok(DEFAULT_TENANT.to_string());
+    }
+    validate_tenant_id(value)?;
+
+    Ok(value.to_string())
+}
+
+/// ## Parses a user or organization ID.
+fn parse_id(value: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(value)
+        .map_err(|_| AppError::validation(format!("'{}' is not a valid ID", value)))
+}
+
+/// ## Converts the user into its message.
+fn to_proto(user: User) -> proto::User {
+    proto::User {
+        id: user.id.to_string(),
+        email: user.email,
+        display_name: user.display_name,
+        org_id: user.org_id.map(|id| id.to_string()),
+        disabled: user.disabled,
+        created_at: user.created_at.timestamp(),
+        updated_at: user.updated_at.timestamp(),
+    }
+}
+
+/// ## Maps the error into the status of the call.
+///
+/// Messages of unexpected errors are logged, not returned.
+fn status(e: AppError) -> Status {
+    match e.kind {
+        ErrorKind::Auth => Status::unauthenticated(e.message),
+        ErrorKind::Forbidden | ErrorKind::LoginWindowDenied | ErrorKind::CountryDenied => {
+            Status::permission_denied(e.message)
+        }
+        ErrorKind::NotFound => Status::not_found(e.message),
+        ErrorKind::Validation => Status::invalid_argument(e.message),
+        _ => {
+            tracing::error!(error = %e, "gRPC call failed");
+            Status::internal("Internal error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::jwt::{claims, SigningKey};
+    use crate::core::auth::keys::KeyManager;
+    use crate::core::auth::rbac::ADMIN_ROLE;
+    use crate::core::config::TokenSettings;
+    use crate::core::db::{migrate, testing::TestDb};
+    use crate::core::repo::memory::{MemorySessionRepo, MemoryUserRepo};
+    use crate::core::users::NewUser;
+    use chrono::Utc;
+    use sqlx::postgres::PgPoolOptions;
+    use sqlx::PgPool;
+    use std::time::Duration;
+    use tonic::Code;
+
+    struct Fixture {
+        service: AuthService,
+        key: Arc<SigningKey>,
+        settings: TokenSettings,
+        user: User,
+    }
+
+    // Service with the users in memory, role grants are read from the pool.
+    fn fixture(pool: PgPool) -> Fixture {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let authenticator = Authenticator::new(
+            Arc::new(KeyManager::new(key.clone())),
+            &settings,
+            Arc::new(MemorySessionRepo::default()),
+        );
+        let users = Arc::new(MemoryUserRepo::default());
+        let user = User {
+            id: Uuid::new_v4(),
+            email: "ada@example.com".to_string(),
+            display_name: None,
+            org_id: None,
+            disabled: false,
+            password_reset_required: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_at_display: None,
+            updated_at_display: None,
+        };
+        users.insert(user.clone(), None);
+
+        Fixture {
+            service: AuthService::new(
+                Arc::new(authenticator),
+                users,
+                RoleRepo::new(pool),
+                Arc::new(Policy::default()),
+            ),
+            key,
+            settings,
+            user,
+        }
+    }
+
+    // Pool of a database that is never reached, acquiring fails fast.
+    fn unreachable_pool() -> PgPool {
+        PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://127.0.0.1:1/axum_auth")
+            .unwrap()
+    }
+
+    impl Fixture {
+        fn token(&self, user_id: Uuid) -> String {
+            self.key
+                .issue(&self.settings, &user_id.to_string(), &["profile"])
+                .unwrap()
+        }
+
+        // Token of the user that expired ten minutes ago.
+        fn expired(&self) -> String {
+            let mut claims = claims(&self.settings, &self.user.id.to_string(), &[]);
+            claims.iat -= 900;
+            claims.exp = claims.iat + 300;
+            self.key.sign(&claims).unwrap()
+        }
+
+        // Token of the user with its signature replaced.
+        fn tampered(&self) -> String {
+            let mut token = self.token(self.user.id);
+            token.replace_range(token.len() - 4.., "AAAA");
+            token
+        }
+
+        async fn verify(&self, token: String) -> Result<VerifyTokenResponse, Status> {
+            self.service
+                .verify_token(Request::new(VerifyTokenRequest {
+                    token,
+                    tenant: String::new(),
+                }))
+                .await
+                .map(Response::into_inner)
+        }
+
+        async fn get_user(&self, user_id: &str) -> Result<proto::User, Status> {
+            self.service
+                .get_user(Request::new(GetUserRequest {
+                    user_id: user_id.to_string(),
+                    tenant: String::new(),
+                }))
+                .await
+                .map(Response::into_inner)
+        }
+
+        async fn check(
+            &self,
+            token: String,
+            permission: &str,
+        ) -> Result<CheckPermissionResponse, Status> {
+            self.service
+                .check_permission(Request::new(CheckPermissionRequest {
+                    token,
+                    permission: permission.to_string(),
+                    org_id: None,
+                    tenant: String::new(),
+                }))
+                .await
+                .map(Response::into_inner)
+        }
+    }
+
+    // Test checks if valid tokens are active and expired or tampered ones inactive.
+    #[tokio::test]
+    async fn test_verify_token() {
+        let fixture = fixture(unreachable_pool());
+
+        let response = fixture
+            .verify(fixture.token(fixture.user.id))
+            .await
+            .unwrap();
+
+        assert!(response.active);
+        assert_eq!(response.user_id, fixture.user.id.to_string());
+        assert_eq!(response.session_id, None);
+        assert_eq!(response.scopes, ["profile"]);
+
+        for token in [fixture.expired(), fixture.tampered(), "invalid".to_string()] {
+            assert_eq!(
+                fixture.verify(token).await.unwrap(),
+                VerifyTokenResponse::default()
+            );
+        }
+
+        let invalid_tenant = fixture
+            .service
+            .verify_token(Request::new(VerifyTokenRequest {
+                token: fixture.token(fixture.user.id),
+                tenant: "Not a tenant".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(invalid_tenant.code(), Code::InvalidArgument);
+    }
+
+    // Test checks if users are returned and unknown or malformed IDs mapped to their status.
+    #[tokio::test]
+    async fn test_get_user() {
+        let fixture = fixture(unreachable_pool());
+
+        let found = fixture
+            .get_user(&fixture.user.id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(found.email, "ada@example.com");
+
+        let unknown = fixture
+            .get_user(&Uuid::new_v4().to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), Code::NotFound);
+
+        let malformed = fixture.get_user("42").await.unwrap_err();
+        assert_eq!(malformed.code(), Code::InvalidArgument);
+    }
+
+    // Test checks if invalid tokens and requests are rejected before roles are read.
+    #[tokio::test]
+    async fn test_check_permission_rejected() {
+        let fixture = fixture(unreachable_pool());
+
+        for token in [fixture.expired(), fixture.tampered()] {
+            let status = fixture.check(token, "users.manage").await.unwrap_err();
+            assert_eq!(status.code(), Code::Unauthenticated);
+        }
+
+        let unknown = fixture
+            .check(fixture.token(fixture.user.id), "users.delete")
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), Code::InvalidArgument);
+
+        // Failing role lookups are reported without their cause
+        let failed = fixture
+            .check(fixture.token(fixture.user.id), "users.manage")
+            .await
+            .unwrap_err();
+        assert_eq!(failed.code(), Code::Internal);
+        assert_eq!(failed.message(), "Internal error");
+    }
+
+    // Test checks if permissions follow the role grants of the user.
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_check_permission() {
+        let db = TestDb::new("grpc_test", 5).await;
+        migrate(&db.pool).await.unwrap();
+        let users = PgUserRepo::new(db.pool.clone());
+        let admin = users
+            .create(NewUser {
+                email: "admin@example.com".to_string(),
+                display_name: None,
+                org_id: None,
+                password: None,
+            })
+            .await
+            .unwrap();
+        let member = users
+            .create(NewUser {
+                email: "member@example.com".to_string(),
+                display_name: None,
+                org_id: None,
+                password: None,
+            })
+            .await
+            .unwrap();
+        users.grant(admin.id, ADMIN_ROLE).await.unwrap();
+        let fixture = fixture(db.pool.clone());
+
+        let allowed = fixture
+            .check(fixture.token(admin.id), "users.manage")
+            .await
+            .unwrap();
+        assert!(allowed.allowed);
+        assert_eq!(allowed.user_id, admin.id.to_string());
+
+        let denied = fixture
+            .check(fixture.token(member.id), "users.manage")
+            .await
+            .unwrap();
+        assert!(!denied.allowed);
+        assert_eq!(denied.user_id, member.id.to_string());
+
+        // Users that do not exist hold no roles
+        let unknown = Uuid::new_v4();
+        let denied = fixture
+            .check(fixture.token(unknown), "audit.read")
+            .await
+            .unwrap();
+        assert!(!denied.allowed);
+        assert_eq!(denied.user_id, unknown.to_string());
+
+        db.drop().await;
+    }
+}
@@ -0,0 +1,490 @@
+//! Health check module.
+//!
+//! Module checks the database, the mail provider and the
+//! external integrations the deployment depends on. Checks run
+//! concurrently, each with its own timeout, and the
+//! report is cached, so frequent probes do not hammer
+//! the integrations.
+
+// Imports from external crates
+use chrono::Utc;
+use sqlx::PgPool;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::Mutex,
+    task::JoinSet,
+};
+
+// Local imports
+use super::config::{HealthCheckKind, HealthCheckSettings, HealthSettings};
+use super::mail::Mailer;
+
+// Re-exports of the wire types
+pub use crate::api::health::{CheckResult, HealthReport, HealthStatus};
+
+/// Name of the built-in database check.
+pub const DATABASE_CHECK: &str = "database";
+
+/// Name of the built-in check of the mail provider.
+pub const MAIL_PROVIDER_CHECK: &str = "mail_provider";
+
+/// Path of the OpenID provider configuration below the issuer.
+pub const OIDC_DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+impl HealthReport {
+    /// ## Creates a report from the check results.
+    fn new(checks: Vec<CheckResult>) -> Self {
+        let status = match checks
+            .iter()
+            .any(|check| check.critical && check.status == HealthStatus::Down)
+        {
+            true => HealthStatus::Down,
+            false => HealthStatus::Up,
+        };
+
+        HealthReport {
+            status,
+            checked_at: Utc::now(),
+            checks,
+        }
+    }
+}
+
+/// ## Health checker struct.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::config::HealthSettings;
+/// use axum_auth::core::health::HealthChecker;
+///
+/// # async fn example(pool: sqlx::PgPool) {
+/// let checker = HealthChecker::new(&HealthSettings::default(), pool);
+/// let report = checker.report().await;
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct HealthChecker {
+    pool: PgPool,
+    checks: Vec<HealthCheckSettings>,
+    timeout: Duration,
+    cache_ttl: Duration,
+    client: reqwest::Client,
+    mailer: Option<(Arc<dyn Mailer>, bool)>,
+    cache: Mutex<Option<(Instant, HealthReport)>>,
+}
+
+impl HealthChecker {
+    /// ## Creates a new `HealthChecker` instance.
+    pub fn new(settings: &HealthSettings, pool: PgPool) -> Self {
+        HealthChecker {
+            pool,
+            checks: settings.checks.clone(),
+            timeout: Duration::from_secs(settings.timeout),
+            cache_ttl: Duration::from_secs(settings.cache_ttl),
+            client: reqwest::Client::new(),
+            mailer: None,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// ## Checks the mail provider as well.
+    ///
+    /// ## Parameters
+    /// - `mailer`: `Arc<dyn Mailer>` - Mailer of the provider.
+    /// - `critical`: `bool` - Provider being down marks the service as down.
+    pub fn with_mailer(mut self, mailer: Arc<dyn Mailer>, critical: bool) -> Self {
+        self.mailer = Some((mailer, critical));
+        self
+    }
+
+    /// ## Returns the health report.
+    ///
+    /// Cached report is returned while it is younger than
+    /// `cache_ttl`. Concurrent callers wait for a single run
+    /// instead of starting their own.
+    pub async fn report(&self) -> HealthReport {
+        let mut cache = self.cache.lock().await;
+
+        if let Some((ran_at, report)) = cache.as_ref() {
+            if ran_at.elapsed() < self.cache_ttl {
+                return report.clone();
+            }
+        }
+
+        let report = self.run().await;
+        *cache = Some((Instant::now(), report.clone()));
+
+        report
+    }
+
+    /// ## Runs every check concurrently.
+    async fn run(&self) -> HealthReport {
+        let mut tasks = JoinSet::new();
+
+        let pool = self.pool.clone();
+        tasks.spawn(timed(
+            DATABASE_CHECK.to_string(),
+            true,
+            self.timeout,
+            async move {
+                sqlx::query("SELECT 1")
+                    .execute(&pool)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            },
+        ));
+
+        if let Some((mailer, critical)) = &self.mailer {
+            let mailer = mailer.clone();
+            tasks.spawn(timed(
+                MAIL_PROVIDER_CHECK.to_string(),
+                *critical,
+                self.timeout,
+                async move { mailer.check().await.map_err(|e| e.to_string()) },
+            ));
+        }
+
+        spawn_probes(&mut tasks, &self.client, &self.checks, self.timeout);
+
+        HealthReport::new(collect(tasks, &self.checks).await)
+    }
+}
+
+/// ## Runs the checks of the external integrations once.
+///
+/// Database is not checked and nothing is cached, the
+/// function is meant for one-off diagnostics.
+///
+/// ## Parameters
+/// - `settings`: `&HealthSettings` - Checks and their timeouts.
+///
+/// ## Returns
+/// - `Vec<CheckResult>`: Results in the configured order.
+pub async fn check_integrations(settings: &HealthSettings) -> Vec<CheckResult> {
+    let mut tasks = JoinSet::new();
+    spawn_probes(
+        &mut tasks,
+        &reqwest::Client::new(),
+        &settings.checks,
+        Duration::from_secs(settings.timeout),
+    );
+
+    collect(tasks, &settings.checks).await
+}
+
+/// ## Spawns a timed probe per configured check.
+fn spawn_probes(
+    tasks: &mut JoinSet<CheckResult>,
+    client: &reqwest::Client,
+    checks: &[HealthCheckSettings],
+    default_timeout: Duration,
+) {
+    for check in checks {
+        let timeout = check.timeout.map_or(default_timeout, Duration::from_secs);
+        let probe = probe(client.clone(), check.kind, check.target.clone());
+
+        tasks.spawn(timed(check.name.clone(), check.critical, timeout, probe));
+    }
+}
+
+/// ## Waits for the checks and keeps the configured order, built-in checks first.
+async fn collect(
+    mut tasks: JoinSet<CheckResult>,
+    settings: &[HealthCheckSettings],
+) -> Vec<CheckResult> {
+    let mut checks = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(check) = result {
+            checks.push(check);
+        }
+    }
+
+    checks.sort_by_key(|check| match check.name.as_str() {
+        DATABASE_CHECK => 0,
+        MAIL_PROVIDER_CHECK => 1,
+        name => settings
+            .iter()
+            .position(|settings| settings.name == name)
+            .map_or(2, |index| index + 2),
+    });
+
+    checks
+}
+
+/// ## Runs the check with a timeout and measures it.
+async fn timed<F>(name: String, critical: bool, timeout: Duration, check: F) -> CheckResult
+where
+    F: std::future::Future<Output = Result<(), String>>,
+{
+    let started = Instant::now();
+    let result = tokio::time::timeout(timeout, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("Timed out after {}s", timeout.as_secs())));
+
+    CheckResult {
+        name,
+        status: match result {
+            Ok(()) => HealthStatus::Up,
+            Err(_) => HealthStatus::Down,
+        },
+        critical,
+        latency_ms: started.elapsed().as_millis() as u64,
+        error: result.err(),
+    }
+}
+
+/// ## Checks an external integration.
+async fn probe(
+    client: reqwest::Client,
+    kind: HealthCheckKind,
+    target: String,
+) -> Result<(), String> {
+    match kind {
+        HealthCheckKind::Smtp => {
+            let reply = exchange(&target, None).await?;
+
+            match reply.starts_with("220") {
+                true => Ok(()),
+                false => Err(format!("Unexpected greeting '{}'", reply)),
+            }
+        }
+        // Servers requiring authentication reply `-NOAUTH`,
+        // which still proves the server is reachable
+        #[cfg(feature = "redis")]
+        HealthCheckKind::Redis => {
+            let reply = exchange(&target, Some(b"PING\r\n")).await?;
+
+            match reply.starts_with("+PONG") || reply.starts_with("-NOAUTH") {
+                true => Ok(()),
+                false => Err(format!("Unexpected reply '{}'", reply)),
+            }
+        }
+        // Rejected by the configuration validation
+        #[cfg(not(feature = "redis"))]
+        HealthCheckKind::Redis => Err("Redis checks need the `redis` feature".to_string()),
+        HealthCheckKind::OidcDiscovery => {
+            let url = match target.ends_with(OIDC_DISCOVERY_PATH) {
+                true => target,
+                false => format!("{}{}", target.trim_end_matches('/'), OIDC_DISCOVERY_PATH),
+            };
+            let document: serde_json::Value = client
+                .get(&url)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| e.to_string())?
+                .json()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            match document
+                .get("issuer")
+                .is_some_and(|issuer| issuer.is_string())
+            {
+                true => Ok(()),
+                false => Err("Discovery document has no issuer".to_string()),
+            }
+        }
+        HealthCheckKind::HttpHead => {
+            let status = client
+                .head(&target)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?
+                .status();
+
+            match status.is_server_error() {
+                true => Err(format!("Responded with {}", status)),
+                false => Ok(()),
+            }
+        }
+    }
+}
+
+/// ## Connects, optionally sends a command and reads the first line.
+async fn exchange(target: &str, command: Option<&[u8]>) -> Result<String, String> {
+    let mut stream = TcpStream::connect(target)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some(command) = command {
+        stream.write_all(command).await.map_err(|e| e.to_string())?;
+    }
+
+    let mut line = String::new();
+    BufReader::new(&mut stream)
+        .read_line(&mut line)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(line.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::err::AppError;
+    use sqlx::postgres::PgPoolOptions;
+    use tokio::net::TcpListener;
+
+    // Pool that fails fast, nothing listens on port 1.
+    fn pool() -> PgPool {
+        PgPoolOptions::new()
+            .connect_lazy("postgres://localhost:1/axum_auth")
+            .unwrap()
+    }
+
+    // Server that sends the greeting and answers every line with the reply.
+    async fn fake_server(greeting: Option<&'static str>, reply: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                if let Some(greeting) = greeting {
+                    stream.write_all(greeting.as_bytes()).await.unwrap();
+                    continue;
+                }
+
+                let mut line = String::new();
+                let (read, mut write) = stream.split();
+                BufReader::new(read).read_line(&mut line).await.unwrap();
+                write.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+
+        addr
+    }
+
+    fn check(
+        name: &str,
+        kind: HealthCheckKind,
+        target: String,
+        critical: bool,
+    ) -> HealthCheckSettings {
+        HealthCheckSettings {
+            name: name.to_string(),
+            kind,
+            target,
+            timeout: None,
+            critical,
+        }
+    }
+
+    // Test checks if SMTP and Redis integrations are probed.
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    async fn test_probe_tcp() {
+        let client = reqwest::Client::new();
+        let smtp = fake_server(Some("220 mail.example.com ESMTP\r\n"), "").await;
+        let redis = fake_server(None, "+PONG\r\n").await;
+        let broken = fake_server(None, "-ERR unknown\r\n").await;
+
+        assert_eq!(
+            probe(client.clone(), HealthCheckKind::Smtp, smtp).await,
+            Ok(())
+        );
+        assert_eq!(
+            probe(client.clone(), HealthCheckKind::Redis, redis).await,
+            Ok(())
+        );
+        assert_eq!(
+            probe(client, HealthCheckKind::Redis, broken).await,
+            Err("Unexpected reply '-ERR unknown'".to_string())
+        );
+    }
+
+    // Test checks if only failing critical checks mark the service as down.
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    async fn test_report_status() {
+        let redis = fake_server(None, "+PONG\r\n").await;
+        let settings = HealthSettings {
+            timeout: 1,
+            checks: vec![
+                check("cache", HealthCheckKind::Redis, redis, true),
+                check(
+                    "mail",
+                    HealthCheckKind::Smtp,
+                    "127.0.0.1:1".to_string(),
+                    false,
+                ),
+            ],
+            ..HealthSettings::default()
+        };
+
+        let report = HealthChecker::new(&settings, pool()).report().await;
+        let names = report
+            .checks
+            .iter()
+            .map(|check| (check.name.as_str(), check.status))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            [
+                (DATABASE_CHECK, HealthStatus::Down),
+                ("cache", HealthStatus::Up),
+                ("mail", HealthStatus::Down),
+            ]
+        );
+        // Database is critical
+        assert_eq!(report.status, HealthStatus::Down);
+
+        let report = HealthReport::new(report.checks[1..].to_vec());
+
+        assert_eq!(report.status, HealthStatus::Up);
+    }
+
+    // Test checks if the mail provider is checked after the database.
+    #[tokio::test]
+    async fn test_report_mailer() {
+        #[derive(Debug)]
+        struct Down;
+
+        #[async_trait::async_trait]
+        impl Mailer for Down {
+            async fn send(&self, _: crate::core::mail::Email) -> Result<(), AppError> {
+                Ok(())
+            }
+
+            async fn check(&self) -> Result<(), AppError> {
+                Err(AppError::external("SMTP server is not reachable"))
+            }
+        }
+
+        let settings = HealthSettings {
+            timeout: 1,
+            ..HealthSettings::default()
+        };
+        let report = HealthChecker::new(&settings, pool())
+            .with_mailer(Arc::new(Down), false)
+            .report()
+            .await;
+
+        assert_eq!(report.checks[1].name, MAIL_PROVIDER_CHECK);
+        assert_eq!(report.checks[1].status, HealthStatus::Down);
+        assert!(!report.checks[1].critical);
+    }
+
+    // Test checks if reports are reused within the cache TTL.
+    #[tokio::test]
+    async fn test_report_cached() {
+        let settings = HealthSettings {
+            timeout: 1,
+            ..HealthSettings::default()
+        };
+        let checker = HealthChecker::new(&settings, pool());
+
+        let first = checker.report().await;
+        let second = checker.report().await;
+
+        assert_eq!(first.checked_at, second.checked_at);
+    }
+}
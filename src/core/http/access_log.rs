@@ -0,0 +1,232 @@
+//! Access log module.
+//!
+//! Middleware writes one log line per request with the
+//! method, path, status, latency, user and request ID.
+//! Query parameters, headers and bodies are redacted
+//! before they are logged, credentials never reach the
+//! log output.
+
+// Imports from external crates
+use axum::{
+    body::Bytes,
+    extract::{Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::Response,
+};
+use std::{sync::Arc, time::Instant};
+use uuid::Uuid;
+
+// Local imports
+use super::journal::buffer;
+use super::redact::Redactor;
+use super::request_id::current_request_id;
+use crate::core::auth::rbac::Principal;
+use crate::core::config::AccessLogSettings;
+
+/// ## Access log entry struct.
+///
+/// ## Fields
+/// + `method`: `String` - HTTP method.
+/// + `path`: `String` - Path and redacted query of the request.
+/// + `headers`: `Option<Vec<(String, String)>>` - Redacted headers,
+///   when headers are logged.
+/// + `body`: `Option<String>` - Redacted body, when bodies are
+///   logged and the body is small enough.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessEntry {
+    pub method: String,
+    pub path: String,
+    pub headers: Option<Vec<(String, String)>>,
+    pub body: Option<String>,
+}
+
+/// ## Access log struct.
+///
+/// ## Examples
+/// ```
+/// use axum::{middleware, Router};
+/// use axum_auth::core::config::AccessLogSettings;
+/// use axum_auth::core::http::access_log::{access_log, AccessLog};
+/// use std::sync::Arc;
+///
+/// let log = Arc::new(AccessLog::new(&AccessLogSettings::default()));
+/// let router: Router = Router::new().layer(middleware::from_fn_with_state(log, access_log));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AccessLog {
+    redactor: Redactor,
+    headers: bool,
+    bodies: bool,
+    max_body_bytes: usize,
+}
+
+impl AccessLog {
+    /// ## Creates a new `AccessLog` instance.
+    pub fn new(settings: &AccessLogSettings) -> Self {
+        AccessLog {
+            redactor: Redactor::new(&settings.redact),
+            headers: settings.headers,
+            bodies: settings.bodies,
+            max_body_bytes: settings.max_body_bytes,
+        }
+    }
+
+    /// ## Returns the redactor of the deny-list.
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
+    /// ## Builds the redacted entry of a request.
+    ///
+    /// ## Parameters
+    /// - `parts`: `&Parts` - Head of the request.
+    /// - `body`: `Option<&Bytes>` - Buffered body of the request.
+    ///
+    /// ## Returns
+    /// - `AccessEntry`: Entry without any credentials.
+    pub fn entry(&self, parts: &Parts, body: Option<&Bytes>) -> AccessEntry {
+        let path = match parts.uri.query() {
+            Some(query) => format!("{}?{}", parts.uri.path(), self.redactor.query(query)),
+            None => parts.uri.path().to_string(),
+        };
+
+        AccessEntry {
+            method: parts.method.to_string(),
+            path,
+            headers: self.headers.then(|| self.redactor.headers(&parts.headers)),
+            body: body.and_then(|bytes| self.redactor.body(&parts.headers, bytes)),
+        }
+    }
+}
+
+/// ## Access log middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`
+/// inside the request ID layer. User of the request is read
+/// from the `Principal` the authentication middleware adds to
+/// the response.
+pub async fn access_log(
+    State(log): State<Arc<AccessLog>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let (parts, body) = request.into_parts();
+    let (body, bytes) = match log.bodies {
+        true => buffer(body, log.max_body_bytes).await,
+        false => (body, None),
+    };
+    let entry = log.entry(&parts, bytes.as_ref());
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let user_id: Option<Uuid> = response
+        .extensions()
+        .get::<Principal>()
+        .map(|principal| principal.user_id);
+    tracing::info!(
+        target: "access",
+        request_id = %current_request_id().unwrap_or_default(),
+        method = %entry.method,
+        path = %entry.path,
+        status = response.status().as_u16(),
+        latency_ms = started.elapsed().as_millis() as u64,
+        user_id = ?user_id,
+        headers = ?entry.headers,
+        body = ?entry.body,
+        "Request completed"
+    );
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::http::redact::REDACTED;
+    use axum::{
+        body::Body,
+        http::{header::CONTENT_TYPE, StatusCode},
+        middleware,
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn log(redact: &[&str]) -> AccessLog {
+        AccessLog::new(&AccessLogSettings {
+            headers: true,
+            bodies: true,
+            redact: redact.iter().map(|name| name.to_string()).collect(),
+            ..AccessLogSettings::default()
+        })
+    }
+
+    // Test checks if the query, headers and body of the entry are redacted.
+    #[test]
+    fn test_entry_redacted() {
+        let request = Request::post("/login?next=%2Fme&token=abc&ssn=1")
+            .header("authorization", "Bearer abc")
+            .header("cookie", "session=1")
+            .header(CONTENT_TYPE, "application/json")
+            .body(())
+            .unwrap();
+        let (parts, _) = request.into_parts();
+        let body = Bytes::from(r#"{"email":"a@example.com","password":"x","ssn":"1"}"#);
+
+        let entry = log(&["ssn"]).entry(&parts, Some(&body));
+
+        assert_eq!(entry.method, "POST");
+        assert_eq!(
+            entry.path,
+            format!("/login?next=%2Fme&token={}&ssn={}", REDACTED, REDACTED)
+        );
+        assert_eq!(
+            entry.headers.unwrap()[..2],
+            [
+                ("authorization".to_string(), REDACTED.to_string()),
+                ("cookie".to_string(), REDACTED.to_string()),
+            ]
+        );
+        let body: serde_json::Value = serde_json::from_str(&entry.body.unwrap()).unwrap();
+        assert_eq!(body["email"], "a@example.com");
+        assert_eq!(body["password"], REDACTED);
+        assert_eq!(body["ssn"], REDACTED);
+    }
+
+    // Test checks if headers and bodies are left out unless enabled.
+    #[test]
+    fn test_entry_minimal() {
+        let log = AccessLog::new(&AccessLogSettings::default());
+        let (parts, _) = Request::get("/me").body(()).unwrap().into_parts();
+
+        let entry = log.entry(&parts, None);
+
+        assert_eq!(entry.path, "/me");
+        assert_eq!(entry.headers, None);
+        assert_eq!(entry.body, None);
+    }
+
+    // Test checks if the logged body still reaches the handler.
+    #[tokio::test]
+    async fn test_access_log_passes_body() {
+        let router = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .layer(middleware::from_fn_with_state(
+                Arc::new(log(&[])),
+                access_log,
+            ));
+
+        let response = router
+            .oneshot(Request::post("/echo").body(Body::from("hello")).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        assert_eq!(bytes, "hello");
+    }
+}
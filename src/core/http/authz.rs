@@ -0,0 +1,208 @@
+//! Route authorization middleware module.
+//!
+//! Middleware enforces the rules of `[authz]` before
+//! the route runs. Requests to a path without rules
+//! pass untouched, others need credentials whose roles
+//! or token scopes meet every matching rule. Roles are
+//! only looked up when a rule names one.
+
+// Imports from external crates
+use axum::{
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+// Local imports
+use super::principal::Authenticator;
+use crate::core::auth::authz::RoutePolicy;
+use crate::core::config::AuthzSettings;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::users::roles::RoleRepo;
+
+/// ## Authorizer struct.
+///
+/// ## Fields
+/// + `policy`: `RoutePolicy` - Compiled rules of the routes.
+/// + `authenticator`: `Arc<Authenticator>` - Verifier of the credentials.
+/// + `roles`: `RoleRepo` - Role grants of the users.
+pub struct Authorizer {
+    pub policy: RoutePolicy,
+    authenticator: Arc<Authenticator>,
+    roles: RoleRepo,
+}
+
+impl Authorizer {
+    /// ## Creates the authorizer of the settings.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&AuthzSettings` - Requirements per route pattern.
+    /// - `authenticator`: `Arc<Authenticator>` - Verifier of the credentials.
+    /// - `roles`: `RoleRepo` - Role grants of the users.
+    ///
+    /// ## Returns
+    /// + `Result<Authorizer, AppError>`
+    ///     - `Ok(Authorizer)`: Authorizer with the compiled rules.
+    ///     - `Err(AppError)`: If a pattern or requirement is invalid.
+    pub fn new(
+        settings: &AuthzSettings,
+        authenticator: Arc<Authenticator>,
+        roles: RoleRepo,
+    ) -> Result<Self, AppError> {
+        Ok(Authorizer {
+            policy: RoutePolicy::compile(settings)?,
+            authenticator,
+            roles,
+        })
+    }
+
+    /// ## Checks if a rule applies to the path.
+    pub fn applies(&self, path: &str) -> bool {
+        !self.policy.rules(path).is_empty()
+    }
+
+    /// ## Checks if the credentials of the request meet the rules of its path.
+    ///
+    /// ## Parameters
+    /// - `path`: `&str` - Path of the request.
+    /// - `headers`: `&HeaderMap` - Headers with the credentials.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If no rule applies or every rule is met.
+    ///     - `Err(AppError)`: `Auth` without valid credentials, `Forbidden`
+    ///       if a rule is not met, or the error of the role lookup.
+    async fn check(&self, path: &str, headers: &HeaderMap) -> Result<(), AppError> {
+        let rules = self.policy.rules(path);
+        if rules.is_empty() {
+            return Ok(());
+        }
+
+        let (principal, scopes) = self.authenticator.identify(headers).await?;
+        let roles = match self.policy.scopes_only(path) {
+            true => Vec::new(),
+            false => self
+                .roles
+                .list_user(principal.user_id)
+                .await?
+                .into_iter()
+                .map(|grant| grant.role)
+                .collect(),
+        };
+
+        match rules.iter().find(|rule| !rule.permits(&roles, &scopes)) {
+            None => Ok(()),
+            Some(rule) => {
+                tracing::warn!(
+                    user_id = %principal.user_id,
+                    path = %path,
+                    pattern = %rule.pattern,
+                    "Route access denied"
+                );
+
+                Err(AppError::new(
+                    ErrorKind::Forbidden,
+                    "Route requires another role or scope".to_string(),
+                    None,
+                )
+                .with_code("authz.denied"))
+            }
+        }
+    }
+}
+
+/// ## Route authorization middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`
+/// inside the tenant resolution, so roles are the ones of
+/// the tenant of the request.
+pub async fn authorize(
+    State(authorizer): State<Arc<Authorizer>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    if let Err(e) = authorizer.check(&path, request.headers()).await {
+        return e.into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::{jwt::SigningKey, keys::KeyManager};
+    use crate::core::config::TokenSettings;
+    use crate::core::repo::memory::MemorySessionRepo;
+    use axum::{
+        body::Body, http::header::AUTHORIZATION, http::StatusCode, middleware, routing::get, Router,
+    };
+    use sqlx::postgres::PgPoolOptions;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    // Test checks if matched paths need credentials and scopes, others pass.
+    #[tokio::test]
+    async fn test_authorize() {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let authenticator = Arc::new(Authenticator::new(
+            Arc::new(KeyManager::new(key.clone())),
+            &settings,
+            Arc::new(MemorySessionRepo::default()),
+        ));
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://127.0.0.1:1/axum_auth")
+            .unwrap();
+        let authorizer = Authorizer::new(
+            &AuthzSettings {
+                routes: HashMap::from([(
+                    "/reports/*".to_string(),
+                    vec!["scope:reports".to_string()],
+                )]),
+            },
+            authenticator,
+            RoleRepo::new(pool),
+        )
+        .unwrap();
+        let router = Router::new()
+            .route("/reports/daily", get(|| async { "ok" }))
+            .route("/open", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                Arc::new(authorizer),
+                authorize,
+            ));
+        let user_id = Uuid::new_v4();
+        let status = |uri: &str, scopes: Option<&[&str]>| {
+            let mut request = Request::builder().uri(uri);
+            if let Some(scopes) = scopes {
+                let token = key.issue(&settings, &user_id.to_string(), scopes).unwrap();
+                request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+            }
+            let router = router.clone();
+
+            async move {
+                let request = request.body(Body::empty()).unwrap();
+                router.oneshot(request).await.unwrap().status()
+            }
+        };
+
+        assert_eq!(status("/open", None).await, StatusCode::OK);
+        assert_eq!(
+            status("/reports/daily", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status("/reports/daily", Some(&["profile"])).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status("/reports/daily", Some(&["profile", "reports"])).await,
+            StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! Client address module.
+//!
+//! Middleware resolves the address of the client once per
+//! request and stores it as `ClientIp`. The address is the
+//! connection's peer, or read from the configured header
+//! when the peer is a trusted proxy. Rate limits, audit
+//! entries, sessions, traces and the IP filter read the
+//! address with the `ClientIp` extractor.
+
+// Imports from external crates
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use ipnetwork::IpNetwork;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+// Local imports
+use crate::core::config::{parse_networks, ClientIpHeader, ClientIpSettings};
+use crate::core::err::{AppError, ErrorKind};
+
+/// Name of the header listing the addresses a request was forwarded for.
+pub const X_FORWARDED_FOR: &str = "x-forwarded-for";
+/// Name of the RFC 7239 forwarding header.
+pub const FORWARDED: &str = "forwarded";
+/// Name of the header Cloudflare puts the client address in.
+pub const CF_CONNECTING_IP: &str = "cf-connecting-ip";
+
+/// ## Client address of the request struct.
+///
+/// Extracting it fails when the server was started without
+/// the peer addresses, handlers take `Option<ClientIp>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// ## Client address resolver struct.
+///
+/// ## Fields
+/// + `trusted_proxies`: `Vec<IpNetwork>` - Proxies whose header is read.
+/// + `header`: `ClientIpHeader` - Header the client address is read from.
+///
+/// ## Examples
+/// ```
+/// use axum::http::HeaderMap;
+/// use axum_auth::core::config::ClientIpSettings;
+/// use axum_auth::core::http::client_ip::ClientIpResolver;
+///
+/// let resolver = ClientIpResolver::new(&ClientIpSettings {
+///     trusted_proxies: vec!["10.0.0.0/8".to_string()],
+///     ..ClientIpSettings::default()
+/// })
+/// .unwrap();
+/// let mut headers = HeaderMap::new();
+/// headers.insert("x-forwarded-for", "192.0.2.10".parse().unwrap());
+///
+/// assert_eq!(
+///     resolver.resolve("10.0.0.1".parse().unwrap(), &headers),
+///     "192.0.2.10".parse::<std::net::IpAddr>().unwrap()
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ClientIpResolver {
+    pub trusted_proxies: Vec<IpNetwork>,
+    pub header: ClientIpHeader,
+}
+
+impl ClientIpResolver {
+    /// ## Creates the resolver of the settings.
+    ///
+    /// ## Returns
+    /// + `Result<ClientIpResolver, AppError>`
+    ///     - `Ok(ClientIpResolver)`: Resolver.
+    ///     - `Err(AppError)`: If a trusted proxy is not a valid network.
+    pub fn new(settings: &ClientIpSettings) -> Result<Self, AppError> {
+        Ok(ClientIpResolver {
+            trusted_proxies: parse_networks(
+                "client_ip.trusted_proxies",
+                &settings.trusted_proxies,
+            )?,
+            header: settings.header,
+        })
+    }
+
+    /// ## Resolves the address of the client.
+    ///
+    /// Hops of `X-Forwarded-For` and `Forwarded` are read
+    /// from right to left while they belong to trusted
+    /// proxies, the first other address is the client.
+    /// Headers of untrusted peers are ignored, clients can
+    /// set them freely.
+    ///
+    /// ## Parameters
+    /// - `peer`: `IpAddr` - Address of the connection.
+    /// - `headers`: `&HeaderMap` - Headers of the request.
+    ///
+    /// ## Returns
+    /// - `IpAddr`: Address of the client.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !contains(&self.trusted_proxies, peer) {
+            return peer;
+        }
+
+        let hops = match self.header {
+            ClientIpHeader::XForwardedFor => values(headers, X_FORWARDED_FOR)
+                .flat_map(|value| value.split(','))
+                .map(|hop| hop.trim().parse().ok())
+                .collect::<Vec<_>>(),
+            ClientIpHeader::Forwarded => values(headers, FORWARDED)
+                .flat_map(|value| value.split(','))
+                .map(forwarded_for)
+                .collect(),
+            ClientIpHeader::CfConnectingIp => {
+                // Cloudflare overwrites the header, the last value is its own
+                return values(headers, CF_CONNECTING_IP)
+                    .last()
+                    .and_then(|value| value.trim().parse().ok())
+                    .unwrap_or(peer);
+            }
+        };
+
+        let mut client = peer;
+        for hop in hops.into_iter().rev() {
+            match hop {
+                Some(ip) => client = ip,
+                // Malformed hop, keep the last known address
+                None => break,
+            }
+            if !contains(&self.trusted_proxies, client) {
+                break;
+            }
+        }
+
+        client
+    }
+}
+
+/// ## Returns the values of the header that are valid strings.
+fn values<'a>(headers: &'a HeaderMap, name: &str) -> impl Iterator<Item = &'a str> {
+    headers
+        .get_all(name)
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+}
+
+/// ## Reads the address of the `for` parameter of a `Forwarded` element.
+///
+/// Quotes, brackets of IPv6 addresses and ports are stripped.
+/// Obfuscated identifiers and `unknown` have no address.
+fn forwarded_for(element: &str) -> Option<IpAddr> {
+    let value = element.split(';').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("for")
+            .then(|| value.trim().trim_matches('"'))
+    })?;
+
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+
+    match value.split_once(':') {
+        Some((ip, _port)) => ip.parse().ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// ## Checks if the address is in one of the networks.
+///
+/// IPv4-mapped IPv6 addresses are matched as IPv4.
+pub(crate) fn contains(networks: &[IpNetwork], ip: IpAddr) -> bool {
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        ip => ip,
+    };
+
+    networks.iter().any(|network| network.contains(ip))
+}
+
+/// ## Client address middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`
+/// outside the trace layer, so every inner layer and handler
+/// sees the resolved address. Requests without a peer address
+/// get no `ClientIp`.
+pub async fn client_ip(
+    State(resolver): State<Arc<ClientIpResolver>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| resolver.resolve(addr.ip(), request.headers()));
+    if let Some(ip) = client {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    next.run(request).await
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    /// Extracts the resolved client address.
+    ///
+    /// Without the middleware the peer address is used,
+    /// requests without either are rejected with `Internal`.
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(client) = parts.extensions.get::<ClientIp>() {
+            return Ok(*client);
+        }
+
+        parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| ClientIp(addr.ip()))
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::Internal,
+                    "Client address is unknown".to_string(),
+                    None,
+                )
+                .with_code("client_ip.unknown")
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::HeaderValue, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    /// Returns a resolver trusting the proxies of `10.0.0.0/24`.
+    fn resolver(header: ClientIpHeader) -> ClientIpResolver {
+        ClientIpResolver::new(&ClientIpSettings {
+            trusted_proxies: vec!["10.0.0.0/24".to_string()],
+            header,
+        })
+        .unwrap()
+    }
+
+    /// Returns headers with the values of the header.
+    fn headers(name: &'static str, values: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(name, HeaderValue::from_static(value));
+        }
+
+        headers
+    }
+
+    /// Parses the address.
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    // Test checks if forwarded addresses are used only behind trusted proxies.
+    #[test]
+    fn test_resolve_x_forwarded_for() {
+        let resolver = resolver(ClientIpHeader::XForwardedFor);
+        let proxy = ip("10.0.0.1");
+        let stranger = ip("203.0.113.9");
+        let forwarded = headers(X_FORWARDED_FOR, &["198.51.100.1, 192.0.2.10", "10.0.0.2"]);
+
+        assert_eq!(resolver.resolve(proxy, &forwarded), ip("192.0.2.10"));
+        assert_eq!(resolver.resolve(stranger, &forwarded), stranger);
+        assert_eq!(resolver.resolve(proxy, &HeaderMap::new()), proxy);
+        assert_eq!(
+            resolver.resolve(proxy, &headers(X_FORWARDED_FOR, &["192.0.2.10, garbage"])),
+            proxy
+        );
+        assert_eq!(
+            ClientIpResolver::default().resolve(proxy, &forwarded),
+            proxy
+        );
+    }
+
+    // Test checks if the for parameters of Forwarded are parsed with quotes, brackets and ports.
+    #[test]
+    fn test_resolve_forwarded() {
+        let resolver = resolver(ClientIpHeader::Forwarded);
+        let proxy = ip("10.0.0.1");
+        let forwarded = |value: &'static str| headers(FORWARDED, &[value]);
+
+        assert_eq!(
+            resolver.resolve(
+                proxy,
+                &forwarded("for=192.0.2.60;proto=http;by=203.0.113.43")
+            ),
+            ip("192.0.2.60")
+        );
+        assert_eq!(
+            resolver.resolve(
+                proxy,
+                &forwarded("for=198.51.100.1, For=\"[2001:db8:cafe::17]:4711\", for=10.0.0.2:80")
+            ),
+            ip("2001:db8:cafe::17")
+        );
+        assert_eq!(
+            resolver.resolve(proxy, &forwarded("for=unknown, for=10.0.0.2")),
+            ip("10.0.0.2")
+        );
+        assert_eq!(
+            resolver.resolve(proxy, &headers(X_FORWARDED_FOR, &["192.0.2.10"])),
+            proxy
+        );
+    }
+
+    // Test checks if CF-Connecting-IP is used as is behind trusted proxies.
+    #[test]
+    fn test_resolve_cf_connecting_ip() {
+        let resolver = resolver(ClientIpHeader::CfConnectingIp);
+        let cloudflare = headers(CF_CONNECTING_IP, &["2001:db8::7"]);
+
+        assert_eq!(
+            resolver.resolve(ip("::ffff:10.0.0.1"), &cloudflare),
+            ip("2001:db8::7")
+        );
+        assert_eq!(
+            resolver.resolve(ip("203.0.113.9"), &cloudflare),
+            ip("203.0.113.9")
+        );
+        assert_eq!(
+            resolver.resolve(ip("10.0.0.1"), &headers(CF_CONNECTING_IP, &["nope"])),
+            ip("10.0.0.1")
+        );
+    }
+
+    // Test checks if handlers extract the resolved address, or the peer without the middleware.
+    #[tokio::test]
+    async fn test_extractor() {
+        let handler = get(|client: Option<ClientIp>| async move {
+            client.map_or("none".to_string(), |ClientIp(ip)| ip.to_string())
+        });
+        let resolved =
+            Router::new()
+                .route("/", handler.clone())
+                .layer(middleware::from_fn_with_state(
+                    Arc::new(resolver(ClientIpHeader::XForwardedFor)),
+                    client_ip,
+                ));
+        let unresolved = Router::new().route("/", handler);
+        let request = |peer: Option<&str>| {
+            let mut request = Request::builder()
+                .uri("/")
+                .header(X_FORWARDED_FOR, "192.0.2.10")
+                .body(Body::empty())
+                .unwrap();
+            if let Some(peer) = peer {
+                let addr: SocketAddr = peer.parse().unwrap();
+                request.extensions_mut().insert(ConnectInfo(addr));
+            }
+
+            request
+        };
+        let body = |router: Router, request: Request| async move {
+            let response = router.oneshot(request).await.unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        assert_eq!(
+            body(resolved.clone(), request(Some("10.0.0.1:4000"))).await,
+            "192.0.2.10"
+        );
+        assert_eq!(body(resolved, request(None)).await, "none");
+        assert_eq!(
+            body(unresolved, request(Some("10.0.0.1:4000"))).await,
+            "10.0.0.1"
+        );
+    }
+}
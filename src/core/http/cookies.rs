@@ -0,0 +1,451 @@
+//! Cookie helpers module.
+//!
+//! Reads single cookies of the `Cookie` request header and
+//! builds `Set-Cookie` values. Signed cookies carry an HMAC
+//! of their name and value, encrypted cookies are sealed
+//! with AES-256-GCM, so clients can not forge them and can
+//! not read encrypted ones. Both keys are derived with HKDF
+//! from `auth.cookie_secret`. The `Secure` and `SameSite`
+//! attributes follow the settings and the environment.
+
+// Imports from external crates
+use axum::http::{header::COOKIE, HeaderMap, HeaderValue};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    hkdf, hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use std::fmt;
+
+// Local imports
+use crate::core::config::{AppSettings, AuthMode, AuthSettings, SameSite};
+use crate::core::err::AppError;
+
+/// Salt of the key derivation.
+const SALT: &[u8] = b"axum-auth cookies";
+
+/// Length of a generated master secret in bytes.
+const SECRET_LEN: usize = 32;
+
+/// ## Returns the value of a request cookie.
+///
+/// Every `Cookie` header is searched, the first
+/// cookie with the name wins.
+///
+/// ## Parameters
+/// - `headers`: `&HeaderMap` - Headers of the request.
+/// - `name`: `&str` - Name of the cookie.
+///
+/// ## Returns
+/// + `Option<&str>`
+///     - `Some(&str)`: Value of the cookie.
+///     - `None`: If the cookie is not sent.
+///
+/// ## Examples
+/// ```
+/// use axum::http::{header::COOKIE, HeaderMap, HeaderValue};
+/// use axum_auth::core::http::cookies;
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert(COOKIE, HeaderValue::from_static("theme=dark; session=abc"));
+///
+/// assert_eq!(cookies::get(&headers, "session"), Some("abc"));
+/// ```
+pub fn get<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim_matches('"'))
+}
+
+/// ## Cookie keys struct.
+///
+/// ## Examples
+/// ```
+/// use axum::http::{header::COOKIE, HeaderMap};
+/// use axum_auth::core::config::SameSite;
+/// use axum_auth::core::http::cookies::Cookies;
+///
+/// let cookies = Cookies::from_secret(b"0123456789abcdef0123456789abcdef", true, SameSite::Lax)
+///     .unwrap();
+/// let cookie = cookies.signed("session", "abc").max_age(60);
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert(COOKIE, format!("session={}", cookie.value()).parse().unwrap());
+///
+/// assert_eq!(cookies.get_signed(&headers, "session"), Some("abc".to_string()));
+/// assert!(cookie.to_string().ends_with("; HttpOnly; SameSite=Lax; Secure"));
+/// ```
+pub struct Cookies {
+    prk: hkdf::Prk,
+    signing: hmac::Key,
+    sealing: LessSafeKey,
+    secure: bool,
+    same_site: SameSite,
+}
+
+impl fmt::Debug for Cookies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cookies")
+            .field("secure", &self.secure)
+            .field("same_site", &self.same_site)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cookies {
+    /// ## Creates a new `Cookies` instance of the settings.
+    ///
+    /// Without `auth.cookie_secret` a random secret is
+    /// generated, its cookies are rejected after a restart.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&AuthSettings` - Master secret and attributes.
+    /// - `app`: `&AppSettings` - Environment the `Secure` default follows.
+    pub fn new(settings: &AuthSettings, app: &AppSettings) -> Result<Self, AppError> {
+        let secret = settings.cookie_secret.expose();
+        let secret = if secret.is_empty() {
+            if settings.mode == AuthMode::Session {
+                tracing::warn!(
+                    "auth.cookie_secret is not set, session cookies are rejected after a restart"
+                );
+            }
+            let mut bytes = vec![0u8; SECRET_LEN];
+            SystemRandom::new()
+                .fill(&mut bytes)
+                .map_err(|_| AppError::internal("Failed to generate the cookie secret"))?;
+            bytes
+        } else {
+            secret.as_bytes().to_vec()
+        };
+
+        Self::from_secret(
+            &secret,
+            settings.secure_cookies(app),
+            settings.cookie_same_site,
+        )
+    }
+
+    /// ## Creates a new `Cookies` instance of a master secret.
+    ///
+    /// ## Parameters
+    /// - `secret`: `&[u8]` - Master secret the keys are derived from.
+    /// - `secure`: `bool` - If the cookies are only sent over HTTPS.
+    /// - `same_site`: `SameSite` - Default `SameSite` attribute.
+    pub fn from_secret(secret: &[u8], secure: bool, same_site: SameSite) -> Result<Self, AppError> {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, SALT).extract(secret);
+        let sealing = prk
+            .expand(&[b"encryption"], &AES_256_GCM)
+            .map(UnboundKey::from)
+            .map_err(|_| AppError::internal("Failed to derive the cookie key"))?;
+
+        Ok(Cookies {
+            signing: derive(&prk, b"signing")?,
+            sealing: LessSafeKey::new(sealing),
+            prk,
+            secure,
+            same_site,
+        })
+    }
+
+    /// ## Derives an HMAC key of the master secret.
+    ///
+    /// ## Parameters
+    /// - `purpose`: `&[u8]` - Purpose of the key, keys of different
+    ///   purposes are independent.
+    pub fn derive_key(&self, purpose: &[u8]) -> Result<hmac::Key, AppError> {
+        derive(&self.prk, purpose)
+    }
+
+    /// ## Returns a cookie with the value as is.
+    ///
+    /// ## Parameters
+    /// - `name`: `&str` - Name of the cookie.
+    /// - `value`: `&str` - Value of the cookie, must be cookie safe.
+    pub fn plain(&self, name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            max_age: None,
+            http_only: true,
+            secure: self.secure,
+            same_site: self.same_site,
+        }
+    }
+
+    /// ## Returns a cookie clients can read but not forge.
+    ///
+    /// ## Parameters
+    /// - `name`: `&str` - Name of the cookie, part of the signature.
+    /// - `value`: `&str` - Value of the cookie.
+    pub fn signed(&self, name: &str, value: &str) -> Cookie {
+        let value = URL_SAFE_NO_PAD.encode(value);
+        let tag = hmac::sign(&self.signing, format!("{}={}", name, value).as_bytes());
+
+        self.plain(name, &format!("{}.{}", value, URL_SAFE_NO_PAD.encode(tag)))
+    }
+
+    /// ## Returns a cookie clients can neither read nor forge.
+    ///
+    /// ## Parameters
+    /// - `name`: `&str` - Name of the cookie, authenticated with the value.
+    /// - `value`: `&str` - Value of the cookie.
+    pub fn encrypted(&self, name: &str, value: &str) -> Result<Cookie, AppError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| AppError::internal("Failed to generate a nonce"))?;
+        let mut sealed = value.as_bytes().to_vec();
+        self.sealing
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(name.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| AppError::internal("Failed to encrypt the cookie"))?;
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend_from_slice(&sealed);
+
+        Ok(self.plain(name, &URL_SAFE_NO_PAD.encode(bytes)))
+    }
+
+    /// ## Returns a cookie removing the cookie of the name.
+    ///
+    /// ## Parameters
+    /// - `name`: `&str` - Name of the cookie.
+    pub fn removal(&self, name: &str) -> Cookie {
+        self.plain(name, "").max_age(0)
+    }
+
+    /// ## Returns the value of a signed request cookie.
+    ///
+    /// ## Returns
+    /// + `Option<String>`
+    ///     - `Some(String)`: Value of the cookie.
+    ///     - `None`: If the cookie is not sent, or is unsigned or forged.
+    pub fn get_signed(&self, headers: &HeaderMap, name: &str) -> Option<String> {
+        let (value, tag) = get(headers, name)?.rsplit_once('.')?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+        hmac::verify(
+            &self.signing,
+            format!("{}={}", name, value).as_bytes(),
+            &tag,
+        )
+        .ok()?;
+
+        String::from_utf8(URL_SAFE_NO_PAD.decode(value).ok()?).ok()
+    }
+
+    /// ## Returns the value of an encrypted request cookie.
+    ///
+    /// ## Returns
+    /// + `Option<String>`
+    ///     - `Some(String)`: Decrypted value of the cookie.
+    ///     - `None`: If the cookie is not sent, or can not be decrypted
+    ///       with the key and its name.
+    pub fn get_encrypted(&self, headers: &HeaderMap, name: &str) -> Option<String> {
+        let bytes = URL_SAFE_NO_PAD.decode(get(headers, name)?).ok()?;
+        if bytes.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut sealed = sealed.to_vec();
+        let value = self
+            .sealing
+            .open_in_place(nonce, Aad::from(name.as_bytes()), &mut sealed)
+            .ok()?;
+
+        String::from_utf8(value.to_vec()).ok()
+    }
+}
+
+/// ## Derives an HMAC key of the pseudorandom key.
+fn derive(prk: &hkdf::Prk, purpose: &[u8]) -> Result<hmac::Key, AppError> {
+    prk.expand(&[purpose], hmac::HMAC_SHA256)
+        .map(hmac::Key::from)
+        .map_err(|_| AppError::internal("Failed to derive the cookie key"))
+}
+
+/// ## Response cookie struct.
+///
+/// Cookies are `HttpOnly` and valid for the whole site
+/// unless changed, session cookies have no `Max-Age`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    max_age: Option<u64>,
+    http_only: bool,
+    secure: bool,
+    same_site: SameSite,
+}
+
+impl Cookie {
+    /// ## Sets the seconds the cookie is kept.
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// ## Sets if scripts can not read the cookie.
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// ## Sets the `SameSite` attribute of the cookie.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// ## Returns the value as sent by the client.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// ## Returns the cookie as `Set-Cookie` header value.
+    pub fn header(&self) -> Result<HeaderValue, AppError> {
+        HeaderValue::from_str(&self.to_string()).map_err(|e| {
+            AppError::internal(format!("Invalid '{}' cookie", self.name)).with_source(e)
+        })
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}; Path=/", self.name, self.value)?;
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age)?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        let same_site = match self.same_site {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        };
+        write!(f, "; SameSite={}", same_site)?;
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookies() -> Cookies {
+        Cookies::from_secret(b"0123456789abcdef0123456789abcdef", false, SameSite::Lax).unwrap()
+    }
+
+    fn request(cookie: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_str(cookie).unwrap());
+        headers
+    }
+
+    // Test checks if cookies are found across headers and missing ones are not.
+    #[test]
+    fn test_get() {
+        let mut headers = HeaderMap::new();
+        headers.append(COOKIE, HeaderValue::from_static("a=1;b=\"2\""));
+        headers.append(COOKIE, HeaderValue::from_static("session=abc; a=3"));
+
+        assert_eq!(get(&headers, "a"), Some("1"));
+        assert_eq!(get(&headers, "b"), Some("2"));
+        assert_eq!(get(&headers, "session"), Some("abc"));
+        assert_eq!(get(&headers, "sess"), None);
+    }
+
+    // Test checks if signed cookies are read back and forged or renamed ones are not.
+    #[test]
+    fn test_signed() {
+        let cookies = cookies();
+        let cookie = cookies.signed("session", "user 1");
+        let value = cookie.value();
+
+        assert_eq!(
+            cookies.get_signed(&request(&format!("session={}", value)), "session"),
+            Some("user 1".to_string())
+        );
+        assert_eq!(
+            cookies.get_signed(&request(&format!("other={}", value)), "other"),
+            None
+        );
+
+        let (_, tag) = value.rsplit_once('.').unwrap();
+        let forged = format!("session={}.{}", URL_SAFE_NO_PAD.encode("user 2"), tag);
+        assert_eq!(cookies.get_signed(&request(&forged), "session"), None);
+        assert_eq!(cookies.get_signed(&request("session=abc"), "session"), None);
+
+        let other = Cookies::from_secret(b"another secret", false, SameSite::Lax).unwrap();
+        assert_eq!(
+            other.get_signed(&request(&format!("session={}", value)), "session"),
+            None
+        );
+    }
+
+    // Test checks if encrypted cookies hide their value and are bound to their name.
+    #[test]
+    fn test_encrypted() {
+        let cookies = cookies();
+        let cookie = cookies.encrypted("state", "return=/me").unwrap();
+        let value = cookie.value();
+
+        assert!(!value.contains("me"));
+        assert_ne!(
+            value,
+            cookies.encrypted("state", "return=/me").unwrap().value()
+        );
+        assert_eq!(
+            cookies.get_encrypted(&request(&format!("state={}", value)), "state"),
+            Some("return=/me".to_string())
+        );
+        assert_eq!(
+            cookies.get_encrypted(&request(&format!("other={}", value)), "other"),
+            None
+        );
+        assert_eq!(cookies.get_encrypted(&request("state=abc"), "state"), None);
+    }
+
+    // Test checks if the attributes follow the settings and the builder.
+    #[test]
+    fn test_attributes() {
+        let cookies = cookies();
+
+        assert_eq!(
+            cookies.plain("session", "abc").max_age(60).to_string(),
+            "session=abc; Path=/; Max-Age=60; HttpOnly; SameSite=Lax"
+        );
+        assert_eq!(
+            cookies
+                .plain("csrf_token", "abc")
+                .http_only(false)
+                .same_site(SameSite::Strict)
+                .to_string(),
+            "csrf_token=abc; Path=/; SameSite=Strict"
+        );
+        assert_eq!(
+            cookies.removal("session").to_string(),
+            "session=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax"
+        );
+
+        let secure = Cookies::from_secret(b"secret", true, SameSite::None).unwrap();
+        assert_eq!(
+            secure.plain("a", "1").to_string(),
+            "a=1; Path=/; HttpOnly; SameSite=None; Secure"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! CSRF protection module.
+//!
+//! Double-submit cookie check of the cookie sessions.
+//! The token is the HMAC of the session ID, keyed with
+//! `auth.csrf_secret` or a key derived from the cookie
+//! secret, sent to the
+//! client in a cookie scripts can read. State-changing
+//! requests carrying the session cookie must echo the
+//! token in the CSRF header, other sites can send the
+//! cookies but can not read them.
+
+// Imports from external crates
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::hmac;
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Local imports
+use crate::core::config::{AuthSettings, SameSite};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::cookies::{self, Cookies};
+
+/// ## CSRF token service struct.
+///
+/// ## Examples
+/// ```
+/// use axum::{middleware, routing::post, Router};
+/// use axum_auth::core::config::{AuthSettings, SameSite};
+/// use axum_auth::core::http::cookies::Cookies;
+/// use axum_auth::core::http::csrf::{protect, Csrf};
+/// use std::sync::Arc;
+///
+/// let cookies = Arc::new(Cookies::from_secret(b"secret", true, SameSite::Lax).unwrap());
+/// let csrf = Arc::new(Csrf::new(&AuthSettings::default(), cookies).unwrap());
+///
+/// let app: Router = Router::new()
+///     .route("/logout", post(|| async {}))
+///     .layer(middleware::from_fn_with_state(csrf, protect));
+/// ```
+pub struct Csrf {
+    key: hmac::Key,
+    session_cookie: String,
+    cookie: String,
+    header: HeaderName,
+    cookies: Arc<Cookies>,
+}
+
+impl Csrf {
+    /// ## Creates a new `Csrf` instance.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&AuthSettings` - Cookie and header names and the key.
+    /// - `cookies`: `Arc<Cookies>` - Keys and attributes of the cookies.
+    ///
+    /// ## Returns
+    /// + `Result<Csrf, AppError>`
+    ///     - `Ok(Csrf)`: New service.
+    ///     - `Err(AppError)`: If the header name is invalid or no
+    ///       key can be derived.
+    pub fn new(settings: &AuthSettings, cookies: Arc<Cookies>) -> Result<Self, AppError> {
+        let key = if settings.csrf_secret.is_empty() {
+            cookies.derive_key(b"csrf")?
+        } else {
+            hmac::Key::new(hmac::HMAC_SHA256, settings.csrf_secret.as_str().as_bytes())
+        };
+        let header = HeaderName::from_bytes(settings.csrf_header.as_bytes()).map_err(|e| {
+            AppError::config(format!(
+                "'{}' is not a valid CSRF header",
+                settings.csrf_header
+            ))
+            .with_source(e)
+        })?;
+
+        Ok(Csrf {
+            key,
+            session_cookie: settings.session_cookie.clone(),
+            cookie: settings.csrf_cookie.clone(),
+            header,
+            cookies,
+        })
+    }
+
+    /// ## Returns the CSRF token of a session.
+    ///
+    /// ## Parameters
+    /// - `session_id`: `Uuid` - ID of the session.
+    ///
+    /// ## Returns
+    /// - `String`: URL safe token, the same for every call.
+    pub fn token(&self, session_id: Uuid) -> String {
+        URL_SAFE_NO_PAD.encode(hmac::sign(&self.key, session_id.as_bytes()))
+    }
+
+    /// ## Verifies the CSRF token of a session.
+    ///
+    /// ## Parameters
+    /// - `session_id`: `Uuid` - ID of the session.
+    /// - `token`: `&str` - Token sent by the client.
+    ///
+    /// ## Returns
+    /// - `bool`: If the token belongs to the session.
+    pub fn verify(&self, session_id: Uuid, token: &str) -> bool {
+        URL_SAFE_NO_PAD
+            .decode(token)
+            .is_ok_and(|tag| hmac::verify(&self.key, session_id.as_bytes(), &tag).is_ok())
+    }
+
+    /// ## Returns the `Set-Cookie` value of a token.
+    ///
+    /// Cookie is not `HttpOnly`, the client reads it
+    /// to echo the token in the CSRF header.
+    ///
+    /// ## Parameters
+    /// - `token`: `&str` - Token of the session.
+    ///
+    /// ## Returns
+    /// - `String`: Value of the `Set-Cookie` header.
+    pub fn set_cookie(&self, token: &str) -> String {
+        self.cookies
+            .plain(&self.cookie, token)
+            .http_only(false)
+            .same_site(SameSite::Strict)
+            .to_string()
+    }
+
+    /// ## Checks the CSRF token of a request.
+    ///
+    /// ## Parameters
+    /// - `method`: `&Method` - Method of the request.
+    /// - `headers`: `&HeaderMap` - Headers of the request.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the method is safe, no session cookie is sent
+    ///       or the cookie and header carry the token of the signed session.
+    ///     - `Err(AppError)`: `Forbidden` otherwise.
+    pub fn check(&self, method: &Method, headers: &HeaderMap) -> Result<(), AppError> {
+        if is_safe(method) {
+            return Ok(());
+        }
+        if cookies::get(headers, &self.session_cookie).is_none() {
+            return Ok(());
+        }
+        let session = self
+            .cookies
+            .get_signed(headers, &self.session_cookie)
+            .and_then(|session| Uuid::parse_str(&session).ok());
+
+        let header = headers
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok());
+        let valid = match (session, header, cookies::get(headers, &self.cookie)) {
+            (Some(session_id), Some(header), Some(cookie)) => {
+                header == cookie && self.verify(session_id, header)
+            }
+            _ => false,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(AppError::new(
+                ErrorKind::Forbidden,
+                "CSRF token is missing or invalid".to_string(),
+                None,
+            )
+            .with_code("csrf.invalid"))
+        }
+    }
+}
+
+/// ## Checks if the method does not change state.
+fn is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// ## CSRF middleware.
+///
+/// State-changing requests of cookie sessions without
+/// a valid CSRF token are rejected with 403, bearer
+/// token requests are not affected.
+pub async fn protect(State(csrf): State<Arc<Csrf>>, request: Request, next: Next) -> Response {
+    if let Err(e) = csrf.check(request.method(), request.headers()) {
+        return e.into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{header::COOKIE, StatusCode},
+        middleware,
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn cookies() -> Arc<Cookies> {
+        Arc::new(Cookies::from_secret(b"cookie-secret", true, SameSite::Lax).unwrap())
+    }
+
+    fn csrf(secret: &str) -> Csrf {
+        let settings = AuthSettings {
+            csrf_secret: secret.into(),
+            ..AuthSettings::default()
+        };
+
+        Csrf::new(&settings, cookies()).unwrap()
+    }
+
+    // Test checks if tokens are bound to the session and the key.
+    #[test]
+    fn test_token_verify() {
+        let csrf = csrf("secret");
+        let session_id = Uuid::new_v4();
+        let token = csrf.token(session_id);
+
+        assert_eq!(token, csrf.token(session_id));
+        assert!(csrf.verify(session_id, &token));
+        assert!(!csrf.verify(Uuid::new_v4(), &token));
+        assert!(!csrf.verify(session_id, "not base64!"));
+        assert!(!self::csrf("other").verify(session_id, &token));
+        assert!(!self::csrf("").verify(session_id, &token));
+    }
+
+    // Test checks if the cookie is readable by scripts and secure by default.
+    #[test]
+    fn test_set_cookie() {
+        assert_eq!(
+            csrf("secret").set_cookie("abc"),
+            "csrf_token=abc; Path=/; SameSite=Strict; Secure"
+        );
+    }
+
+    async fn call(csrf: Csrf, cookies: Option<String>, header: Option<&str>) -> StatusCode {
+        let app = Router::new()
+            .route("/logout", post(|| async {}).get(|| async {}))
+            .layer(middleware::from_fn_with_state(Arc::new(csrf), protect));
+        let mut request = Request::post("/logout");
+        if let Some(cookies) = cookies {
+            request = request.header(COOKIE, cookies);
+        }
+        if let Some(header) = header {
+            request = request.header("x-csrf-token", header);
+        }
+
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    // Test checks if state-changing session requests need the matching token.
+    #[tokio::test]
+    async fn test_protect() {
+        let session_id = Uuid::new_v4();
+        let token = csrf("secret").token(session_id);
+        let session = cookies().signed("session", &session_id.to_string());
+        let cookies =
+            |csrf: &str| Some(format!("session={}; csrf_token={}", session.value(), csrf));
+
+        assert_eq!(
+            call(csrf("secret"), cookies(&token), Some(&token)).await,
+            StatusCode::OK
+        );
+        assert_eq!(call(csrf("secret"), None, None).await, StatusCode::OK);
+        assert_eq!(
+            call(csrf("secret"), cookies(&token), None).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call(csrf("secret"), cookies("forged"), Some(&token)).await,
+            StatusCode::FORBIDDEN
+        );
+
+        let other = csrf("secret").token(Uuid::new_v4());
+        assert_eq!(
+            call(csrf("secret"), cookies(&other), Some(&other)).await,
+            StatusCode::FORBIDDEN
+        );
+
+        let unsigned = Some(format!("session={}; csrf_token={}", session_id, token));
+        assert_eq!(
+            call(csrf("secret"), unsigned, Some(&token)).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! Module maps `AppError` into HTTP responses.
+//!
+//! Every error returned by a handler is rendered as a
+//! JSON body that carries the request ID, so users can
+//! quote it in bug reports.
+//!
+//! Messages are translated into the language negotiated
+//! by `locale::message_language`, logs stay English.
+//!
+//! `error_format` negotiates RFC 7807 problem details,
+//! which carry the same code, message and request ID.
+
+// Imports from external crates
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{ACCEPT, CONTENT_LANGUAGE, CONTENT_TYPE},
+        HeaderValue, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+
+// Local imports
+use super::locale::current_language;
+use super::request_id::current_request_id;
+use crate::core::config::{ErrorFormat, ErrorSettings};
+use crate::core::err::{AppError, ErrorKind};
+use crate::strings::messages::{lookup, DEFAULT_LANGUAGE};
+
+// Re-exports of the wire types
+pub use crate::api::error::{ErrorBody, ErrorDetails, ProblemDetails};
+
+/// Media type of RFC 7807 problem details.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+tokio::task_local! {
+    /// Base of the problem types, set when the request gets problem details.
+    static PROBLEM_TYPE_BASE: String;
+}
+
+/// ## Error format middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`.
+/// Errors of the inner layers and routes are rendered as
+/// problem details when `wants_problem` selects them.
+pub async fn error_format(
+    State(settings): State<Arc<ErrorSettings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let accept = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok());
+
+    match wants_problem(accept, settings.format) {
+        true => {
+            let base = settings.type_base.clone();
+            PROBLEM_TYPE_BASE.scope(base, next.run(request)).await
+        }
+        false => next.run(request).await,
+    }
+}
+
+/// ## Checks if the client gets problem details.
+///
+/// ## Parameters
+/// - `accept`: `Option<&str>` - Value of the `Accept` header.
+/// - `format`: `ErrorFormat` - Format of the configuration.
+///
+/// ## Returns
+/// - `bool`: `true` if `application/problem+json` is accepted, or
+///   problem details are configured and `application/json` is not
+///   named.
+pub fn wants_problem(accept: Option<&str>, format: ErrorFormat) -> bool {
+    let accepts = |media_type: &str| {
+        accept.unwrap_or_default().split(',').any(|range| {
+            range
+                .split(';')
+                .next()
+                .is_some_and(|range| range.trim().eq_ignore_ascii_case(media_type))
+        })
+    };
+
+    match format {
+        _ if accepts(PROBLEM_JSON) => true,
+        ErrorFormat::Problem => !accepts("application/json"),
+        ErrorFormat::Json => false,
+    }
+}
+
+/// ## Returns the summary of the error kind.
+///
+/// Titles are the same for every error of a problem
+/// type, the message of the error is the `detail`.
+pub fn problem_title(kind: &ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::Config => "Invalid configuration",
+        ErrorKind::Env => "Invalid environment",
+        ErrorKind::Db => "Database error",
+        ErrorKind::Auth => "Authentication required",
+        ErrorKind::Forbidden => "Access denied",
+        ErrorKind::LoginWindowDenied => "Login outside of the allowed window",
+        ErrorKind::CountryDenied => "Login from a country that is not allowed",
+        ErrorKind::NotFound => "Resource not found",
+        ErrorKind::Validation => "Invalid request",
+        ErrorKind::Io => "I/O error",
+        ErrorKind::External => "External service failed",
+        ErrorKind::Internal => "Internal server error",
+    }
+}
+
+/// ## Returns the message of the error in the language.
+///
+/// English messages are the ones of the errors, they
+/// carry details like the invalid value. Other languages
+/// get the catalog message of the code, or of the kind
+/// when the code has none, and fall back to English.
+///
+/// ## Parameters
+/// - `error`: `&AppError` - Error sent to the client.
+/// - `language`: `&str` - Language subtag, e.g. `de`.
+///
+/// ## Returns
+/// - `String`: Message of the response.
+pub fn client_message(error: &AppError, language: &str) -> String {
+    if language == DEFAULT_LANGUAGE {
+        return error.message.clone();
+    }
+
+    lookup(error.code, language)
+        .or_else(|| lookup(error.kind.code(), language))
+        .map_or_else(|| error.message.clone(), str::to_string)
+}
+
+/// ## Maps the error kind to an HTTP status code.
+///
+/// ## Parameters
+/// - `kind`: `&ErrorKind` - Kind of the error.
+///
+/// ## Returns
+/// - `StatusCode`: Status code of the response.
+pub fn status_code(kind: &ErrorKind) -> StatusCode {
+    match kind {
+        ErrorKind::Validation => StatusCode::BAD_REQUEST,
+        ErrorKind::LoginWindowDenied | ErrorKind::CountryDenied | ErrorKind::Forbidden => {
+            StatusCode::FORBIDDEN
+        }
+        ErrorKind::Auth => StatusCode::UNAUTHORIZED,
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ErrorKind::External => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl IntoResponse for AppError {
+    /// Converts `AppError` into a JSON response.
+    ///
+    /// Response is problem details when `error_format`
+    /// selected them for the request. Status hint of the error takes precedence over the
+    /// status of its kind. Server side errors are logged with
+    /// their source and backtrace and only a generic message
+    /// is sent to the client, except for 503 which tells the
+    /// client to retry.
+    fn into_response(self) -> Response {
+        let status = self
+            .status_hint
+            .and_then(|status| StatusCode::from_u16(status).ok())
+            .unwrap_or_else(|| status_code(&self.kind));
+        let language = current_language();
+
+        let message = match status.is_server_error() && status != StatusCode::SERVICE_UNAVAILABLE {
+            true => {
+                match &self.backtrace {
+                    Some(backtrace) => {
+                        tracing::error!(error = %self, %backtrace, "Request failed")
+                    }
+                    None => tracing::error!(error = %self, "Request failed"),
+                }
+                #[cfg(feature = "sentry")]
+                crate::core::sentry::capture_error(&self);
+                lookup(ErrorKind::Internal.code(), language)
+                    .unwrap_or("Internal server error")
+                    .to_string()
+            }
+            false => client_message(&self, language),
+        };
+
+        let mut response = match PROBLEM_TYPE_BASE.try_with(String::clone) {
+            Ok(base) => {
+                let problem = ProblemDetails {
+                    problem_type: format!("{}{}", base, self.kind.code()),
+                    title: problem_title(&self.kind).to_string(),
+                    status: status.as_u16(),
+                    detail: message,
+                    code: self.code.to_string(),
+                    request_id: current_request_id(),
+                };
+
+                (
+                    status,
+                    [(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON))],
+                    Json(problem),
+                )
+                    .into_response()
+            }
+            Err(_) => {
+                let body = ErrorBody {
+                    error: ErrorDetails {
+                        kind: format!("{:?}", self.kind),
+                        code: self.code.to_string(),
+                        message,
+                        request_id: current_request_id(),
+                    },
+                };
+
+                (status, Json(body)).into_response()
+            }
+        };
+        response
+            .headers_mut()
+            .insert(CONTENT_LANGUAGE, HeaderValue::from_static(language));
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::http::locale::message_language;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        middleware,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    async fn send(error: fn() -> AppError, accept_language: &str) -> (String, String) {
+        let router = Router::new()
+            .route("/", get(move || async move { Err::<(), _>(error()) }))
+            .layer(middleware::from_fn(message_language));
+        let request = Request::get("/")
+            .header("Accept-Language", accept_language)
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+        let language = response.headers()[CONTENT_LANGUAGE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: ErrorBody = serde_json::from_slice(&body).unwrap();
+
+        (language, body.error.message)
+    }
+
+    // Test checks if problem details are selected by the Accept header or the configuration.
+    #[test]
+    fn test_wants_problem() {
+        let problem = Some("application/problem+json; q=0.9, application/json");
+
+        assert!(wants_problem(problem, ErrorFormat::Json));
+        assert!(!wants_problem(Some("*/*"), ErrorFormat::Json));
+        assert!(wants_problem(Some("*/*"), ErrorFormat::Problem));
+        assert!(wants_problem(None, ErrorFormat::Problem));
+        assert!(!wants_problem(
+            Some("text/html, Application/JSON"),
+            ErrorFormat::Problem
+        ));
+    }
+
+    // Test checks if problem details carry the type, status, code and translated detail.
+    #[tokio::test]
+    async fn test_problem_response() {
+        let router = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    Err::<(), _>(AppError::auth("Token is expired").with_code("token.expired"))
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Arc::new(ErrorSettings::default()),
+                error_format,
+            ))
+            .layer(middleware::from_fn(message_language));
+        let request = Request::get("/")
+            .header("Accept", PROBLEM_JSON)
+            .header("Accept-Language", "de")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[CONTENT_TYPE], PROBLEM_JSON);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: ProblemDetails = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(
+            problem,
+            ProblemDetails {
+                problem_type: "urn:axum-auth:error:auth".to_string(),
+                title: "Authentication required".to_string(),
+                status: 401,
+                detail: "Token ist abgelaufen".to_string(),
+                code: "token.expired".to_string(),
+                request_id: None,
+            }
+        );
+    }
+
+    // Test checks if messages are translated by code, then by kind, then left English.
+    #[test]
+    fn test_client_message() {
+        let expired = AppError::auth("Token is expired").with_code("token.expired");
+        let unknown = AppError::auth("Session is locked").with_code("session.locked");
+        let io = AppError::new(ErrorKind::Io, "Disk is full".to_string(), None);
+
+        assert_eq!(client_message(&expired, "en"), "Token is expired");
+        assert_eq!(client_message(&expired, "de"), "Token ist abgelaufen");
+        assert_eq!(client_message(&unknown, "fr"), "Authentification requise");
+        assert_eq!(client_message(&io, "ja"), "Disk is full");
+    }
+
+    // Test checks if responses use the language of the Accept-Language header.
+    #[tokio::test]
+    async fn test_error_response_language() {
+        let not_found = || AppError::new(ErrorKind::NotFound, "User not found".to_string(), None);
+        let internal = || AppError::db("Connection refused");
+
+        assert_eq!(
+            send(not_found, "es-MX, en;q=0.5").await,
+            ("es".to_string(), "Recurso no encontrado".to_string())
+        );
+        assert_eq!(
+            send(not_found, "nl").await,
+            ("en".to_string(), "User not found".to_string())
+        );
+        assert_eq!(
+            send(internal, "de").await,
+            ("de".to_string(), "Interner Serverfehler".to_string())
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! IP filter module.
+//!
+//! Middleware lets requests through only when the client
+//! address is in an allowed network and in no denied
+//! one. The client address is resolved by the
+//! `client_ip` middleware.
+
+// Imports from external crates
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipnetwork::IpNetwork;
+use std::{net::IpAddr, sync::Arc};
+
+// Local imports
+use super::client_ip::{contains, ClientIp};
+use crate::core::config::{parse_networks, IpFilterSettings};
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## IP filter struct.
+///
+/// ## Fields
+/// + `allow`: `Vec<IpNetwork>` - Allowed networks, every address when empty.
+/// + `deny`: `Vec<IpNetwork>` - Denied networks.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::IpFilterSettings;
+/// use axum_auth::core::http::ip_filter::IpFilter;
+///
+/// let filter = IpFilter::new(&IpFilterSettings {
+///     enabled: true,
+///     allow: vec!["10.0.0.0/8".to_string()],
+///     deny: vec!["10.0.13.0/24".to_string()],
+///     ..IpFilterSettings::default()
+/// })
+/// .unwrap();
+///
+/// assert!(filter.permits("10.1.2.3".parse().unwrap()));
+/// assert!(!filter.permits("10.0.13.7".parse().unwrap()));
+/// assert!(!filter.permits("192.168.0.1".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    pub allow: Vec<IpNetwork>,
+    pub deny: Vec<IpNetwork>,
+}
+
+impl IpFilter {
+    /// ## Creates the filter of the settings.
+    ///
+    /// ## Returns
+    /// + `Result<IpFilter, AppError>`
+    ///     - `Ok(IpFilter)`: Filter.
+    ///     - `Err(AppError)`: If an entry is not a valid network.
+    pub fn new(settings: &IpFilterSettings) -> Result<Self, AppError> {
+        Ok(IpFilter {
+            allow: parse_networks("ip_filter.allow", &settings.allow)?,
+            deny: parse_networks("ip_filter.deny", &settings.deny)?,
+        })
+    }
+
+    /// ## Checks if the address may reach the guarded routes.
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        let allowed = self.allow.is_empty() || contains(&self.allow, ip);
+
+        allowed && !contains(&self.deny, ip)
+    }
+}
+
+/// ## IP filter middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`
+/// on the guarded routes. Requests without a peer address are
+/// refused, the filter can't tell where they come from.
+pub async fn ip_filter(
+    State(filter): State<Arc<IpFilter>>,
+    client: Option<ClientIp>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match client {
+        Some(ClientIp(ip)) if filter.permits(ip) => next.run(request).await,
+        client => {
+            tracing::warn!(client = ?client, path = %request.uri().path(), "Address refused");
+
+            AppError::new(
+                ErrorKind::Forbidden,
+                "Access from this address is not allowed".to_string(),
+                None,
+            )
+            .with_code("ip.denied")
+            .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::ClientIpSettings;
+    use crate::core::http::client_ip::{client_ip, ClientIpResolver, X_FORWARDED_FOR};
+    use axum::{
+        body::Body, extract::ConnectInfo, http::StatusCode, middleware, routing::get, Router,
+    };
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    /// Returns a filter allowing `192.0.2.0/24` except one address.
+    fn filter() -> IpFilter {
+        IpFilter::new(&IpFilterSettings {
+            enabled: true,
+            allow: vec!["192.0.2.0/24".to_string()],
+            deny: vec!["192.0.2.66/32".to_string()],
+            metrics: false,
+        })
+        .unwrap()
+    }
+
+    // Test checks if denied networks win over allowed ones, IPv4-mapped addresses included.
+    #[test]
+    fn test_permits() {
+        let filter = filter();
+
+        assert!(filter.permits("192.0.2.10".parse().unwrap()));
+        assert!(filter.permits("::ffff:192.0.2.10".parse().unwrap()));
+        assert!(!filter.permits("192.0.2.66".parse().unwrap()));
+        assert!(!filter.permits("198.51.100.1".parse().unwrap()));
+        assert!(IpFilter::default().permits("198.51.100.1".parse().unwrap()));
+    }
+
+    // Test checks if the resolved address is filtered and unknown peers are refused.
+    #[tokio::test]
+    async fn test_middleware() {
+        let resolver = ClientIpResolver::new(&ClientIpSettings {
+            trusted_proxies: vec!["10.0.0.0/24".to_string()],
+            ..ClientIpSettings::default()
+        })
+        .unwrap();
+        let router = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                Arc::new(filter()),
+                ip_filter,
+            ))
+            .layer(middleware::from_fn_with_state(
+                Arc::new(resolver),
+                client_ip,
+            ));
+        let request = |peer: Option<&str>| {
+            let mut request = Request::builder()
+                .uri("/")
+                .header(X_FORWARDED_FOR, "192.0.2.10")
+                .body(Body::empty())
+                .unwrap();
+            if let Some(peer) = peer {
+                let addr: SocketAddr = peer.parse().unwrap();
+                request.extensions_mut().insert(ConnectInfo(addr));
+            }
+
+            request
+        };
+
+        let allowed = router.clone().oneshot(request(Some("10.0.0.1:4000"))).await;
+        let refused = router
+            .clone()
+            .oneshot(request(Some("203.0.113.9:4000")))
+            .await;
+        let unknown = router.oneshot(request(None)).await;
+
+        assert_eq!(allowed.unwrap().status(), StatusCode::OK);
+        assert_eq!(refused.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(unknown.unwrap().status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,419 @@
+//! Request journal module.
+//!
+//! Development tool that records sanitized request and
+//! response pairs into a ring buffer of files, so a bug
+//! seen once can be reproduced with the `replay` command.
+//! Credentials are redacted before anything is written,
+//! which also means replayed requests are anonymous
+//! unless the credentials are passed again.
+
+// Imports from external crates
+use axum::{
+    body::{to_bytes, Body, Bytes, HttpBody},
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+// Re-exports of the redaction rules
+pub use super::redact::{REDACTED, SENSITIVE_FIELDS, SENSITIVE_HEADERS};
+
+// Local imports
+use super::redact::Redactor;
+use super::request_id::current_request_id;
+use crate::core::config::JournalSettings;
+use crate::core::err::{AppError, ErrorKind};
+
+/// Extension of the journal files.
+const ENTRY_EXTENSION: &str = "json";
+
+/// ## Recorded request struct.
+///
+/// ## Fields
+/// + `method`: `String` - HTTP method.
+/// + `uri`: `String` - Path and query of the request.
+/// + `headers`: `Vec<(String, String)>` - Sanitized headers.
+/// + `body`: `Option<String>` - Sanitized body, `None` when it
+///   was too large or not text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub uri: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+/// ## Recorded response struct.
+///
+/// ## Fields
+/// + `status`: `u16` - HTTP status code.
+/// + `headers`: `Vec<(String, String)>` - Sanitized headers.
+/// + `body`: `Option<String>` - Sanitized body, `None` when it
+///   was too large or not text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+/// ## Journal entry struct.
+///
+/// ## Fields
+/// + `seq`: `u64` - Sequence number, increases with every request.
+/// + `recorded_at`: `DateTime<Utc>` - Time the response was sent.
+/// + `request_id`: `Option<String>` - ID of the request.
+/// + `request`: `RecordedRequest` - Recorded request.
+/// + `response`: `RecordedResponse` - Recorded response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub recorded_at: DateTime<Utc>,
+    pub request_id: Option<String>,
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// ## Request journal struct.
+///
+/// Entry `seq` is written to slot `seq % capacity`,
+/// so the directory never holds more than `capacity`
+/// files and the oldest entries are overwritten first.
+#[derive(Debug)]
+pub struct Journal {
+    dir: PathBuf,
+    capacity: u64,
+    max_body_bytes: usize,
+    next: AtomicU64,
+    redactor: Redactor,
+}
+
+impl Journal {
+    /// ## Opens the journal directory.
+    ///
+    /// Directory is created when missing. Recording continues
+    /// after the newest entry already in the directory.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&JournalSettings` - Journal settings.
+    ///
+    /// ## Returns
+    /// + `Result<Journal, AppError>`
+    ///     - `Ok(Journal)`: Opened journal.
+    ///     - `Err(AppError)`: If the directory can not be created or read.
+    pub fn open(settings: &JournalSettings) -> Result<Self, AppError> {
+        fs::create_dir_all(&settings.dir).map_err(|e| {
+            AppError::new(
+                ErrorKind::Io,
+                format!("Failed to create journal directory '{}'", settings.dir),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        let next = read_entries(Path::new(&settings.dir))?
+            .last()
+            .map_or(0, |entry| entry.seq + 1);
+
+        Ok(Journal {
+            dir: PathBuf::from(&settings.dir),
+            capacity: settings.capacity.max(1),
+            max_body_bytes: settings.max_body_bytes,
+            next: AtomicU64::new(next),
+            redactor: Redactor::default(),
+        })
+    }
+
+    /// ## Redacts the entries with the redactor.
+    ///
+    /// ## Parameters
+    /// - `redactor`: `Redactor` - Redactor with the configured deny-list.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// ## Writes the entry into its slot.
+    async fn write(&self, entry: &JournalEntry) -> Result<(), AppError> {
+        let path = self.dir.join(format!(
+            "{:06}.{}",
+            entry.seq % self.capacity,
+            ENTRY_EXTENSION
+        ));
+        let content = serde_json::to_vec_pretty(entry).map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                "Failed to serialize journal entry".to_string(),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        tokio::fs::write(&path, content).await.map_err(|e| {
+            AppError::new(
+                ErrorKind::Io,
+                format!("Failed to write journal entry '{}'", path.display()),
+                Some(Box::new(e)),
+            )
+        })
+    }
+}
+
+/// ## Request journal middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`.
+/// Bodies larger than `max_body_bytes` or without a known size
+/// are passed through untouched and recorded as `None`.
+/// Failing to write an entry never fails the request.
+///
+/// ## Examples
+/// ```no_run
+/// use axum::{middleware, Router};
+/// use axum_auth::core::config::JournalSettings;
+/// use axum_auth::core::http::journal::{journal, Journal};
+/// use std::sync::Arc;
+///
+/// let state = Arc::new(Journal::open(&JournalSettings::default()).unwrap());
+/// let router: Router = Router::new().layer(middleware::from_fn_with_state(state, journal));
+/// ```
+pub async fn journal(
+    State(journal): State<Arc<Journal>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let (body, request_body) = buffer(body, journal.max_body_bytes).await;
+
+    let recorded_request = RecordedRequest {
+        method: parts.method.to_string(),
+        uri: parts
+            .uri
+            .path_and_query()
+            .map_or_else(|| parts.uri.path().to_string(), |pq| pq.to_string()),
+        headers: journal.redactor.headers(&parts.headers),
+        body: request_body.and_then(|bytes| journal.redactor.body(&parts.headers, &bytes)),
+    };
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let (parts, body) = response.into_parts();
+    let (body, response_body) = buffer(body, journal.max_body_bytes).await;
+
+    let entry = JournalEntry {
+        seq: journal.next.fetch_add(1, Ordering::Relaxed),
+        recorded_at: Utc::now(),
+        request_id: current_request_id(),
+        request: recorded_request,
+        response: RecordedResponse {
+            status: parts.status.as_u16(),
+            headers: journal.redactor.headers(&parts.headers),
+            body: response_body.and_then(|bytes| journal.redactor.body(&parts.headers, &bytes)),
+        },
+    };
+
+    if let Err(e) = journal.write(&entry).await {
+        tracing::warn!(error = %e, "Failed to record request in the journal");
+    }
+
+    Response::from_parts(parts, body)
+}
+
+/// ## Reads all entries of the journal directory.
+///
+/// ## Parameters
+/// - `dir`: `&Path` - Journal directory.
+///
+/// ## Returns
+/// + `Result<Vec<JournalEntry>, AppError>`
+///     - `Ok(Vec<JournalEntry>)`: Entries, oldest first.
+///     - `Err(AppError)`: If the directory or an entry can not be read.
+pub fn read_entries(dir: &Path) -> Result<Vec<JournalEntry>, AppError> {
+    let read_error = |path: &Path, e: Box<dyn std::error::Error>| {
+        AppError::new(
+            ErrorKind::Io,
+            format!("Failed to read journal '{}'", path.display()),
+            Some(e),
+        )
+    };
+
+    let mut entries = Vec::new();
+    for file in fs::read_dir(dir).map_err(|e| read_error(dir, Box::new(e)))? {
+        let path = file.map_err(|e| read_error(dir, Box::new(e)))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXTENSION) {
+            continue;
+        }
+
+        let content = fs::read(&path).map_err(|e| read_error(&path, Box::new(e)))?;
+        let entry: JournalEntry =
+            serde_json::from_slice(&content).map_err(|e| read_error(&path, Box::new(e)))?;
+        entries.push(entry);
+    }
+
+    entries.sort_by_key(|entry| entry.seq);
+
+    Ok(entries)
+}
+
+/// ## Resends a recorded request.
+///
+/// Redacted headers and headers describing the original
+/// connection are not sent.
+///
+/// ## Parameters
+/// - `client`: `&reqwest::Client` - HTTP client.
+/// - `target`: `&str` - Base URL of the instance, e.g. `http://127.0.0.1:8080`.
+/// - `entry`: `&JournalEntry` - Entry to replay.
+///
+/// ## Returns
+/// + `Result<u16, AppError>`
+///     - `Ok(u16)`: Status code of the response.
+///     - `Err(AppError)`: If the request could not be sent.
+pub async fn replay(
+    client: &reqwest::Client,
+    target: &str,
+    entry: &JournalEntry,
+) -> Result<u16, AppError> {
+    let url = format!("{}{}", target.trim_end_matches('/'), entry.request.uri);
+    let method = reqwest::Method::from_bytes(entry.request.method.as_bytes()).map_err(|e| {
+        AppError::new(
+            ErrorKind::Validation,
+            format!("Invalid method '{}' in journal entry", entry.request.method),
+            Some(Box::new(e)),
+        )
+    })?;
+
+    let mut request = client.request(method, &url);
+    for (name, value) in &entry.request.headers {
+        if value != REDACTED && !matches!(name.as_str(), "host" | "content-length") {
+            request = request.header(name, value);
+        }
+    }
+    if let Some(body) = &entry.request.body {
+        request = request.body(body.clone());
+    }
+
+    let response = request.send().await.map_err(|e| {
+        AppError::new(
+            ErrorKind::External,
+            format!("Failed to replay request to '{}'", url),
+            Some(Box::new(e)),
+        )
+    })?;
+
+    Ok(response.status().as_u16())
+}
+
+/// ## Buffers the body when its size is known and small enough.
+pub(super) async fn buffer(body: Body, max: usize) -> (Body, Option<Bytes>) {
+    let fits = body
+        .size_hint()
+        .upper()
+        .is_some_and(|size| size <= max as u64);
+    if !fits {
+        return (body, None);
+    }
+
+    match to_bytes(body, max).await {
+        Ok(bytes) => (Body::from(bytes.clone()), Some(bytes)),
+        Err(_) => (Body::empty(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, middleware, routing::post, Router};
+    use tower::ServiceExt;
+
+    fn settings(dir: &Path, capacity: u64) -> JournalSettings {
+        JournalSettings {
+            enabled: true,
+            dir: dir.to_string_lossy().to_string(),
+            capacity,
+            max_body_bytes: 1024,
+        }
+    }
+
+    // Test checks if requests are recorded into a ring buffer.
+    #[tokio::test]
+    async fn test_journal_ring_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Arc::new(Journal::open(&settings(dir.path(), 2)).unwrap());
+        let router = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .layer(middleware::from_fn_with_state(journal, super::journal));
+
+        for body in ["first", "second", "third"] {
+            let request = Request::post("/echo").body(Body::from(body)).unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let bytes = to_bytes(response.into_body(), 1024).await.unwrap();
+
+            // Handler still receives the buffered body
+            assert_eq!(bytes, body);
+        }
+
+        let entries = read_entries(dir.path()).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].seq, 1);
+        assert_eq!(entries[1].request.body.as_deref(), Some("third"));
+        assert_eq!(entries[1].response.status, 200);
+
+        // Reopened journal continues after the newest entry
+        let reopened = Journal::open(&settings(dir.path(), 2)).unwrap();
+
+        assert_eq!(reopened.next.load(Ordering::Relaxed), 3);
+    }
+
+    // Test checks if a recorded request is resent to the target.
+    #[tokio::test]
+    async fn test_replay() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route(
+            "/echo",
+            post(|headers: HeaderMap, body: String| async move {
+                match (headers.contains_key("authorization"), body.as_str()) {
+                    (false, "payload") => axum::http::StatusCode::ACCEPTED,
+                    _ => axum::http::StatusCode::BAD_REQUEST,
+                }
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let entry = JournalEntry {
+            seq: 0,
+            recorded_at: Utc::now(),
+            request_id: None,
+            request: RecordedRequest {
+                method: "POST".to_string(),
+                uri: "/echo".to_string(),
+                headers: vec![("authorization".to_string(), REDACTED.to_string())],
+                body: Some("payload".to_string()),
+            },
+            response: RecordedResponse {
+                status: 202,
+                headers: Vec::new(),
+                body: None,
+            },
+        };
+
+        let status = replay(
+            &reqwest::Client::new(),
+            &format!("http://{}/", addr),
+            &entry,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(status, 202);
+    }
+}
@@ -0,0 +1,459 @@
+//! Response localization module.
+//!
+//! Timestamps are always sent as RFC 3339 in UTC. When
+//! localization is enabled, responses additionally carry
+//! `*_display` fields formatted for the locale negotiated
+//! from `Accept-Language` and the timezone of the tenant
+//! of the caller. Wire types implement `Localize`, so the
+//! formatting rules live in this module only.
+//!
+//! Error messages are translated independently of these
+//! settings, `message_language` negotiates their language
+//! for every request.
+
+// Imports from external crates
+#[cfg(feature = "server")]
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{header::ACCEPT_LANGUAGE, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+#[cfg(feature = "server")]
+use std::{convert::Infallible, sync::Arc};
+
+// Local imports
+use crate::api::audit::AuditRecord;
+use crate::api::pagination::Paginated;
+use crate::api::users::User;
+use crate::core::auth::rbac::Principal;
+use crate::core::config::LocalizationSettings;
+#[cfg(feature = "server")]
+use crate::strings::messages::DEFAULT_LANGUAGE;
+
+/// ## Locale struct.
+///
+/// ## Fields
+/// + `tag`: `&'static str` - BCP 47 language tag.
+/// + `datetime`: `&'static str` - `chrono` format of timestamps.
+/// + `group`: `&'static str` - Separator of thousands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub tag: &'static str,
+    pub datetime: &'static str,
+    pub group: &'static str,
+}
+
+/// Supported locales, the first locale of a language is
+/// used for tags of other regions.
+pub const LOCALES: [Locale; 6] = [
+    Locale {
+        tag: "en-US",
+        datetime: "%-m/%-d/%Y, %-I:%M:%S %p %Z",
+        group: ",",
+    },
+    Locale {
+        tag: "en-GB",
+        datetime: "%d/%m/%Y, %H:%M:%S %Z",
+        group: ",",
+    },
+    Locale {
+        tag: "de-DE",
+        datetime: "%d.%m.%Y, %H:%M:%S %Z",
+        group: ".",
+    },
+    Locale {
+        tag: "fr-FR",
+        datetime: "%d/%m/%Y %H:%M:%S %Z",
+        group: "\u{202f}",
+    },
+    Locale {
+        tag: "es-ES",
+        datetime: "%-d/%-m/%Y, %H:%M:%S %Z",
+        group: ".",
+    },
+    Locale {
+        tag: "ja-JP",
+        datetime: "%Y/%m/%d %H:%M:%S %Z",
+        group: ",",
+    },
+];
+
+#[cfg(feature = "server")]
+tokio::task_local! {
+    /// Language of the messages of the current request.
+    static LANGUAGE: &'static str;
+}
+
+impl Locale {
+    /// ## Returns the language subtag, e.g. `de` of `de-DE`.
+    pub fn language(&self) -> &'static str {
+        self.tag.split('-').next().unwrap_or(self.tag)
+    }
+
+    /// ## Finds the locale of the tag.
+    ///
+    /// Tags match exactly, ignoring case, or by their
+    /// language, e.g. `de-AT` matches `de-DE`.
+    pub fn find(tag: &str) -> Option<&'static Locale> {
+        let tag = tag.trim();
+        let language = tag.split('-').next().unwrap_or(tag);
+
+        LOCALES
+            .iter()
+            .find(|locale| locale.tag.eq_ignore_ascii_case(tag))
+            .or_else(|| {
+                LOCALES.iter().find(|locale| {
+                    locale
+                        .tag
+                        .split('-')
+                        .next()
+                        .is_some_and(|other| other.eq_ignore_ascii_case(language))
+                })
+            })
+    }
+
+    /// ## Formats the number with the separator of thousands.
+    pub fn number(&self, value: i64) -> String {
+        let digits = value.unsigned_abs().to_string();
+        let mut formatted = String::new();
+
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                formatted.push_str(self.group);
+            }
+            formatted.push(digit);
+        }
+
+        match value < 0 {
+            true => format!("-{}", formatted),
+            false => formatted,
+        }
+    }
+}
+
+/// ## Negotiates the locale of the `Accept-Language` header.
+///
+/// Languages are tried in the order of their quality,
+/// `*` and unsupported languages fall back to the default.
+///
+/// ## Parameters
+/// - `header`: `Option<&str>` - Value of the header.
+/// - `default`: `&'static Locale` - Locale when nothing matches.
+///
+/// ## Returns
+/// - `&'static Locale`: Best supported locale.
+pub fn negotiate(header: Option<&str>, default: &'static Locale) -> &'static Locale {
+    let mut languages: Vec<(&str, f32)> = header
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|language| {
+            let mut parts = language.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+
+            (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    languages
+        .into_iter()
+        .find_map(|(tag, _)| match tag {
+            "*" => Some(default),
+            tag => Locale::find(tag),
+        })
+        .unwrap_or(default)
+}
+
+/// ## Message language middleware.
+///
+/// Function is used with `axum::middleware::from_fn`.
+/// Negotiates the language of the error messages from
+/// `Accept-Language`, English when nothing matches.
+///
+/// ## Examples
+/// ```
+/// use axum::{middleware, Router};
+/// use axum_auth::core::http::locale::message_language;
+///
+/// let router: Router = Router::new().layer(middleware::from_fn(message_language));
+/// ```
+#[cfg(feature = "server")]
+pub async fn message_language(request: Request, next: Next) -> Response {
+    let header = request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok());
+    let language = negotiate(header, &LOCALES[0]).language();
+
+    LANGUAGE.scope(language, next.run(request)).await
+}
+
+/// ## Returns the message language of the current request.
+///
+/// ## Returns
+/// - `&'static str`: Negotiated language, `DEFAULT_LANGUAGE` when
+///   called outside of the middleware.
+#[cfg(feature = "server")]
+pub fn current_language() -> &'static str {
+    LANGUAGE
+        .try_with(|language| *language)
+        .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+/// ## Localizer struct.
+///
+/// Extracted from the request, handlers pass their
+/// responses through `apply`. Without the localization
+/// settings in the request extensions, or with
+/// localization disabled, `apply` leaves values unchanged.
+///
+/// ## Fields
+/// + `locale`: `Option<&'static Locale>` - Locale of the caller,
+///   `None` when localization is disabled.
+/// + `timezone`: `Tz` - Timezone of the tenant of the caller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Localizer {
+    pub locale: Option<&'static Locale>,
+    pub timezone: Tz,
+}
+
+impl Default for Localizer {
+    fn default() -> Self {
+        Localizer {
+            locale: None,
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+impl Localizer {
+    /// ## Creates the localizer of a request.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&LocalizationSettings` - Localization settings.
+    /// - `accept_language`: `Option<&str>` - Value of the `Accept-Language` header.
+    /// - `principal`: `Option<&Principal>` - Caller, its organization selects
+    ///   the tenant timezone.
+    pub fn new(
+        settings: &LocalizationSettings,
+        accept_language: Option<&str>,
+        principal: Option<&Principal>,
+    ) -> Self {
+        if !settings.enabled {
+            return Localizer::default();
+        }
+
+        let default = Locale::find(&settings.default_locale).unwrap_or(&LOCALES[0]);
+        let tenant = principal
+            .and_then(|principal| principal.grants.iter().find_map(|grant| grant.org_id))
+            .and_then(|org_id| settings.tenants.get(&org_id.to_string()));
+        let timezone = tenant
+            .unwrap_or(&settings.default_timezone)
+            .parse::<Tz>()
+            .unwrap_or(Tz::UTC);
+
+        Localizer {
+            locale: Some(negotiate(accept_language, default)),
+            timezone,
+        }
+    }
+
+    /// ## Formats the timestamp, `None` when localization is disabled.
+    pub fn datetime(&self, value: DateTime<Utc>) -> Option<String> {
+        self.locale.map(|locale| {
+            value
+                .with_timezone(&self.timezone)
+                .format(locale.datetime)
+                .to_string()
+        })
+    }
+
+    /// ## Formats the number, `None` when localization is disabled.
+    pub fn number(&self, value: i64) -> Option<String> {
+        self.locale.map(|locale| locale.number(value))
+    }
+
+    /// ## Fills the display fields of the value.
+    pub fn apply<T: Localize>(&self, mut value: T) -> T {
+        value.localize(self);
+        value
+    }
+}
+
+#[cfg(feature = "server")]
+#[async_trait]
+impl<S> FromRequestParts<S> for Localizer
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    /// Extracts the localizer of the caller.
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(settings) = parts.extensions.get::<Arc<LocalizationSettings>>() else {
+            return Ok(Localizer::default());
+        };
+        let accept_language = parts
+            .headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok());
+
+        Ok(Localizer::new(
+            settings,
+            accept_language,
+            parts.extensions.get::<Principal>(),
+        ))
+    }
+}
+
+/// ## Localizable response trait.
+pub trait Localize {
+    /// Fills the `*_display` fields of the value.
+    fn localize(&mut self, localizer: &Localizer);
+}
+
+impl Localize for User {
+    fn localize(&mut self, localizer: &Localizer) {
+        self.created_at_display = localizer.datetime(self.created_at);
+        self.updated_at_display = localizer.datetime(self.updated_at);
+    }
+}
+
+impl Localize for AuditRecord {
+    fn localize(&mut self, localizer: &Localizer) {
+        self.occurred_at_display = localizer.datetime(self.occurred_at);
+    }
+}
+
+impl<T: Localize> Localize for Paginated<T> {
+    fn localize(&mut self, localizer: &Localizer) {
+        self.items.localize(localizer);
+        self.total_display = self.total.and_then(|total| localizer.number(total));
+    }
+}
+
+impl<T: Localize> Localize for Vec<T> {
+    fn localize(&mut self, localizer: &Localizer) {
+        for item in self.iter_mut() {
+            item.localize(localizer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::rbac::RoleGrant;
+    use crate::strings::messages::lookup;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn settings() -> LocalizationSettings {
+        LocalizationSettings {
+            enabled: true,
+            ..LocalizationSettings::default()
+        }
+    }
+
+    // Test checks if the best supported language of the header is chosen.
+    #[test]
+    fn test_negotiate() {
+        let default = &LOCALES[0];
+
+        assert_eq!(
+            negotiate(Some("de-AT,de;q=0.9,en;q=0.8"), default).tag,
+            "de-DE"
+        );
+        assert_eq!(negotiate(Some("en-GB;q=0.5, fr"), default).tag, "fr-FR");
+        assert_eq!(negotiate(Some("nl-NL, ja;q=0.1"), default).tag, "ja-JP");
+        assert_eq!(negotiate(Some("fr;q=0, *"), default).tag, "en-US");
+        assert_eq!(negotiate(Some("nl"), default).tag, "en-US");
+        assert_eq!(negotiate(None, default).tag, "en-US");
+    }
+
+    // Test checks if numbers are grouped by thousands.
+    #[test]
+    fn test_locale_number() {
+        let german = Locale::find("de").unwrap();
+
+        assert_eq!(german.number(1234567), "1.234.567");
+        assert_eq!(german.number(-1000), "-1.000");
+        assert_eq!(german.number(999), "999");
+        assert_eq!(LOCALES[0].number(0), "0");
+    }
+
+    // Test checks if timestamps use the locale and the tenant timezone.
+    #[test]
+    fn test_localizer_datetime() {
+        let org_id = Uuid::new_v4();
+        let settings = LocalizationSettings {
+            tenants: HashMap::from([(org_id.to_string(), "Europe/Berlin".to_string())]),
+            ..settings()
+        };
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![RoleGrant::org("admin", org_id)],
+        };
+        let at = "2026-07-01T14:05:09Z".parse::<DateTime<Utc>>().unwrap();
+
+        let localizer = Localizer::new(&settings, Some("de"), Some(&principal));
+
+        assert_eq!(
+            localizer.datetime(at).as_deref(),
+            Some("01.07.2026, 16:05:09 CEST")
+        );
+
+        let localizer = Localizer::new(&settings, Some("en-US"), None);
+
+        assert_eq!(
+            localizer.datetime(at).as_deref(),
+            Some("7/1/2026, 2:05:09 PM UTC")
+        );
+        assert_eq!(localizer.number(12500).as_deref(), Some("12,500"));
+    }
+
+    // Test checks if nothing is localized when localization is disabled.
+    #[test]
+    fn test_localizer_disabled() {
+        let localizer = Localizer::new(&LocalizationSettings::default(), Some("de"), None);
+        let record = AuditRecord {
+            id: 1,
+            occurred_at: Utc::now(),
+            event: "login.failed".to_string(),
+            actor_id: None,
+            user_id: None,
+            ip: None,
+            details: serde_json::json!({}),
+            occurred_at_display: None,
+        };
+
+        assert_eq!(localizer, Localizer::default());
+        assert_eq!(localizer.apply(record.clone()), record);
+
+        let localized = Localizer::new(&settings(), None, None).apply(record);
+
+        assert!(localized.occurred_at_display.is_some());
+    }
+
+    // Test checks if every catalog message is translated into every supported language.
+    #[test]
+    fn test_catalog_complete() {
+        for message in crate::strings::messages::CATALOG {
+            for locale in LOCALES {
+                assert!(
+                    lookup(message.code, locale.language()).is_some(),
+                    "'{}' is not translated into '{}'",
+                    message.code,
+                    locale.tag
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! HTTP module.
+//!
+//! Module contains the middleware and response
+//! helpers shared by all routes. Only `locale` is
+//! available without the `server` feature, the
+//! configuration validates locales.
+
+// References to submodules
+pub mod locale;
+
+#[cfg(feature = "server")]
+pub mod access_log;
+#[cfg(feature = "server")]
+pub mod authz;
+#[cfg(feature = "server")]
+pub mod client_ip;
+#[cfg(feature = "server")]
+pub mod cookies;
+#[cfg(feature = "server")]
+pub mod csrf;
+#[cfg(feature = "server")]
+pub mod error;
+#[cfg(feature = "server")]
+pub mod ip_filter;
+#[cfg(feature = "server")]
+pub mod journal;
+#[cfg(feature = "server")]
+pub mod pagination;
+#[cfg(feature = "server")]
+pub mod principal;
+#[cfg(feature = "server")]
+pub mod redact;
+#[cfg(feature = "server")]
+pub mod request_id;
+#[cfg(feature = "server")]
+pub mod trace;
@@ -0,0 +1,636 @@
+//! Pagination module.
+//!
+//! Module reads the paging and sorting parameters of list
+//! endpoints and applies them to the queries. Lists are
+//! paged by page number or, when a `cursor` is sent, by
+//! keyset: the cursor holds the sort key of the last item
+//! of the previous page, so items inserted meanwhile do
+//! not shift the following pages.
+//!
+//! Sort fields are looked up in the allowlist of the
+//! list, only their static column names are written into
+//! SQL and cursor values are always bound.
+
+// Imports from external crates
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+
+// Local imports
+use crate::api::{DEFAULT_PER_PAGE, MAX_PER_PAGE};
+use crate::core::err::AppError;
+
+// Re-exports of the wire types
+pub use crate::api::pagination::{PageQuery, Paginated};
+
+/// ## Sortable field struct.
+///
+/// ## Fields
+/// + `name`: `&'static str` - Name of the field in the `sort` parameter.
+/// + `column`: `&'static str` - Column of the field, must be `NOT NULL`.
+/// + `cast`: `&'static str` - Postgres type cursor values are cast to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortField {
+    pub name: &'static str,
+    pub column: &'static str,
+    pub cast: &'static str,
+}
+
+/// ## Sorting of a list struct.
+///
+/// ## Fields
+/// + `fields`: `&'static [SortField]` - Fields the list may be sorted by.
+/// + `default`: `&'static str` - Sort used when none is requested,
+///   e.g. `-occurred_at`.
+/// + `id`: `SortField` - Unique field ordering items with equal keys.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::http::pagination::{SortField, Sorting};
+///
+/// const NOTES: Sorting = Sorting {
+///     fields: &[SortField {
+///         name: "created_at",
+///         column: "created_at",
+///         cast: "timestamptz",
+///     }],
+///     default: "-created_at",
+///     id: SortField {
+///         name: "id",
+///         column: "id",
+///         cast: "bigint",
+///     },
+/// };
+///
+/// let sort = NOTES.parse(None).unwrap();
+///
+/// assert!(sort.descending);
+/// assert!(NOTES.parse(Some("title")).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sorting {
+    pub fields: &'static [SortField],
+    pub default: &'static str,
+    pub id: SortField,
+}
+
+impl Sorting {
+    /// ## Parses the `sort` parameter.
+    ///
+    /// ## Returns
+    /// + `Result<Sort, AppError>`
+    ///     - `Ok(Sort)`: Requested sort, or the default one.
+    ///     - `Err(AppError)`: `Validation` if the field is not sortable.
+    pub fn parse(&self, value: Option<&str>) -> Result<Sort, AppError> {
+        let value = value
+            .filter(|value| !value.is_empty())
+            .unwrap_or(self.default);
+        let (name, descending) = match value.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (value, false),
+        };
+        let field = self
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.fields.iter().map(|field| field.name).collect();
+                AppError::validation(format!(
+                    "Can not sort by '{}', expected one of: {}",
+                    name,
+                    names.join(", ")
+                ))
+                .with_code("pagination.invalid_sort")
+            })?;
+
+        Ok(Sort {
+            field: *field,
+            id: self.id,
+            descending,
+        })
+    }
+}
+
+/// ## Sort order struct.
+///
+/// ## Fields
+/// + `field`: `SortField` - Field the items are sorted by.
+/// + `id`: `SortField` - Unique field ordering items with equal keys.
+/// + `descending`: `bool` - Largest keys come first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub field: SortField,
+    pub id: SortField,
+    pub descending: bool,
+}
+
+impl Sort {
+    /// ## Returns the sort as sent in the `sort` parameter.
+    pub fn name(&self) -> String {
+        match self.descending {
+            true => format!("-{}", self.field.name),
+            false => self.field.name.to_string(),
+        }
+    }
+}
+
+/// ## Keyset cursor struct.
+///
+/// Cursors are sent to clients as base64url encoded JSON,
+/// clients pass them back unchanged.
+///
+/// ## Fields
+/// + `sort`: `String` - Sort the cursor was issued for.
+/// + `key`: `String` - Sort key of the last item of the page.
+/// + `id`: `String` - ID of the last item of the page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub sort: String,
+    pub key: String,
+    pub id: String,
+}
+
+impl Cursor {
+    /// ## Encodes the cursor for the response.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// ## Decodes a cursor sent by a client.
+    ///
+    /// ## Returns
+    /// + `Result<Cursor, AppError>`
+    ///     - `Ok(Cursor)`: Decoded cursor.
+    ///     - `Err(AppError)`: `Validation` if the cursor was not issued
+    ///       by the server.
+    pub fn decode(value: &str) -> Result<Self, AppError> {
+        URL_SAFE_NO_PAD
+            .decode(value)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| {
+                AppError::validation("Cursor is malformed").with_code("pagination.invalid_cursor")
+            })
+    }
+}
+
+/// ## Keyed item trait.
+///
+/// Implemented by items of keyset paged lists, so the
+/// cursor of the last item of a page can be built.
+pub trait Keyed {
+    /// ## Returns the sort key of the field, formatted like the
+    /// text representation of its Postgres type.
+    fn sort_key(&self, field: &str) -> String;
+
+    /// ## Returns the unique ID of the item.
+    fn id_key(&self) -> String;
+}
+
+/// ## Formats a timestamp as sort key.
+///
+/// Microseconds match the precision of `timestamptz`, so
+/// the key selects the exact row again.
+pub fn timestamp_key(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// ## Pagination extractor struct.
+///
+/// Reads `page`, `per_page`, `cursor` and `sort` from the
+/// query string. Filters of the list are read separately,
+/// e.g. with `Query`, from the same query string.
+///
+/// ## Fields
+/// + `page`: `Option<u32>` - Page number, `None` when paging by cursor.
+/// + `per_page`: `u32` - Items per page, clamped to `MAX_PER_PAGE`.
+/// + `cursor`: `Option<String>` - Cursor of the previous page, empty
+///   for the first page.
+/// + `sort`: `Option<String>` - Requested sort.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pagination {
+    pub page: Option<u32>,
+    pub per_page: u32,
+    pub cursor: Option<String>,
+    pub sort: Option<String>,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Pagination {
+            page: Some(1),
+            per_page: DEFAULT_PER_PAGE,
+            cursor: None,
+            sort: None,
+        }
+    }
+}
+
+impl TryFrom<PageQuery> for Pagination {
+    type Error = AppError;
+
+    /// Clamps the page to valid values.
+    fn try_from(query: PageQuery) -> Result<Self, Self::Error> {
+        let per_page = query
+            .per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE);
+
+        match (query.page, query.cursor) {
+            (Some(_), Some(_)) => Err(AppError::validation(
+                "Parameters 'page' and 'cursor' can not be combined",
+            )
+            .with_code("pagination.invalid_cursor")),
+            (page, None) => Ok(Pagination {
+                page: Some(page.unwrap_or(1).max(1)),
+                per_page,
+                cursor: None,
+                sort: query.sort,
+            }),
+            (None, cursor) => Ok(Pagination {
+                page: None,
+                per_page,
+                cursor,
+                sort: query.sort,
+            }),
+        }
+    }
+}
+
+impl Pagination {
+    /// ## Resolves the sort and cursor of the list.
+    ///
+    /// ## Parameters
+    /// - `sorting`: `&Sorting` - Sortable fields of the list.
+    ///
+    /// ## Returns
+    /// + `Result<Window, AppError>`
+    ///     - `Ok(Window)`: Rows to query.
+    ///     - `Err(AppError)`: `Validation` if the sort is unknown or the
+    ///       cursor was issued for another sort.
+    pub fn window(&self, sorting: &Sorting) -> Result<Window, AppError> {
+        let sort = sorting.parse(self.sort.as_deref())?;
+        let after = match self.cursor.as_deref() {
+            None | Some("") => None,
+            Some(value) => Some(Cursor::decode(value)?),
+        };
+
+        if after
+            .as_ref()
+            .is_some_and(|cursor| cursor.sort != sort.name())
+        {
+            return Err(
+                AppError::validation("Cursor was issued for another sort order")
+                    .with_code("pagination.invalid_cursor"),
+            );
+        }
+
+        Ok(Window {
+            sort,
+            after,
+            page: self.page,
+            per_page: self.per_page,
+        })
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Pagination
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    /// Extracts the paging parameters of the query string.
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PageQuery>::try_from_uri(&parts.uri)
+            .map_err(|e| AppError::validation("Paging parameters are invalid").with_source(e))?;
+
+        Pagination::try_from(query)
+    }
+}
+
+/// ## Window of a list struct.
+///
+/// Rows to query, one row more than `per_page` is
+/// fetched to find out if there is a next page.
+///
+/// ## Fields
+/// + `sort`: `Sort` - Order of the rows.
+/// + `after`: `Option<Cursor>` - Rows after this cursor are returned.
+/// + `page`: `Option<u32>` - Page number, `None` when paging by cursor.
+/// + `per_page`: `u32` - Rows per page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    pub sort: Sort,
+    pub after: Option<Cursor>,
+    pub page: Option<u32>,
+    pub per_page: u32,
+}
+
+impl Window {
+    /// ## Returns the number of rows to fetch.
+    pub fn limit(&self) -> i64 {
+        i64::from(self.per_page) + 1
+    }
+
+    /// ## Returns the number of rows to skip.
+    pub fn offset(&self) -> i64 {
+        self.page
+            .map_or(0, |page| i64::from(page - 1) * i64::from(self.per_page))
+    }
+
+    /// ## Appends the keyset condition of the cursor.
+    ///
+    /// Query has to end with a `WHERE` clause, the condition
+    /// is appended with `AND`.
+    pub fn push_keyset(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        let Some(cursor) = &self.after else {
+            return;
+        };
+        let Sort {
+            field,
+            id,
+            descending,
+        } = self.sort;
+
+        query.push(format!(
+            " AND ({}, {}) {} (CAST(",
+            field.column,
+            id.column,
+            if descending { "<" } else { ">" }
+        ));
+        query.push_bind(cursor.key.clone());
+        query.push(format!(" AS {}), CAST(", field.cast));
+        query.push_bind(cursor.id.clone());
+        query.push(format!(" AS {}))", id.cast));
+    }
+
+    /// ## Appends the `ORDER BY`, `LIMIT` and `OFFSET` clauses.
+    pub fn push_order(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        let direction = if self.sort.descending { " DESC" } else { "" };
+
+        query.push(format!(
+            " ORDER BY {}{}, {}{} LIMIT ",
+            self.sort.field.column, direction, self.sort.id.column, direction
+        ));
+        query.push_bind(self.limit());
+        query.push(" OFFSET ");
+        query.push_bind(self.offset());
+    }
+
+    /// ## Applies the window to items in memory.
+    ///
+    /// Keys are compared as text, which matches the order
+    /// of Postgres for the timestamps and IDs of `Keyed`
+    /// items, but not always for text columns.
+    pub fn apply<T: Keyed>(&self, items: Vec<T>) -> Vec<T> {
+        let name = self.sort.field.name;
+        let mut keyed: Vec<((String, String), T)> = items
+            .into_iter()
+            .map(|item| ((item.sort_key(name), item.id_key()), item))
+            .filter(|(key, _)| match &self.after {
+                None => true,
+                Some(cursor) => {
+                    let after = (cursor.key.clone(), cursor.id.clone());
+                    match self.sort.descending {
+                        true => *key < after,
+                        false => *key > after,
+                    }
+                }
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| match self.sort.descending {
+            true => b.cmp(a),
+            false => a.cmp(b),
+        });
+
+        keyed
+            .into_iter()
+            .map(|(_, item)| item)
+            .skip(self.offset() as usize)
+            .take(self.limit() as usize)
+            .collect()
+    }
+
+    /// ## Builds the response of the fetched rows.
+    ///
+    /// ## Parameters
+    /// - `rows`: `Vec<T>` - Rows fetched with `limit`.
+    ///
+    /// ## Returns
+    /// - `Paginated<T>`: Page without `total`, with `next_cursor` when
+    ///   paging by cursor and more rows are left.
+    pub fn paginate<T: Keyed>(&self, mut rows: Vec<T>) -> Paginated<T> {
+        let more = rows.len() > self.per_page as usize;
+        rows.truncate(self.per_page as usize);
+
+        let next_cursor = match (self.page, more, rows.last()) {
+            (None, true, Some(last)) => Some(
+                Cursor {
+                    sort: self.sort.name(),
+                    key: last.sort_key(self.sort.field.name),
+                    id: last.id_key(),
+                }
+                .encode(),
+            ),
+            _ => None,
+        };
+
+        Paginated {
+            items: rows,
+            page: self.page,
+            per_page: self.per_page,
+            total: None,
+            total_display: None,
+            next_cursor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITEMS: Sorting = Sorting {
+        fields: &[
+            SortField {
+                name: "created_at",
+                column: "created_at",
+                cast: "timestamptz",
+            },
+            SortField {
+                name: "name",
+                column: "name",
+                cast: "text",
+            },
+        ],
+        default: "created_at",
+        id: SortField {
+            name: "id",
+            column: "id",
+            cast: "bigint",
+        },
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Item {
+        id: u32,
+        name: &'static str,
+    }
+
+    impl Keyed for Item {
+        fn sort_key(&self, _field: &str) -> String {
+            self.name.to_string()
+        }
+
+        fn id_key(&self) -> String {
+            format!("{:03}", self.id)
+        }
+    }
+
+    fn pagination(query: &str) -> Result<Pagination, AppError> {
+        let uri = format!("/items?{}", query).parse().unwrap();
+
+        Pagination::try_from(Query::<PageQuery>::try_from_uri(&uri).unwrap().0)
+    }
+
+    fn items() -> Vec<Item> {
+        ["c", "a", "b", "a", "d"]
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| Item {
+                id: id as u32,
+                name,
+            })
+            .collect()
+    }
+
+    // Test checks if the page is clamped and the paging modes are exclusive.
+    #[test]
+    fn test_pagination_query() {
+        assert_eq!(pagination("").unwrap(), Pagination::default());
+
+        let clamped = pagination("page=0&per_page=10000").unwrap();
+
+        assert_eq!(clamped.page, Some(1));
+        assert_eq!(clamped.per_page, MAX_PER_PAGE);
+
+        let cursor = pagination("cursor=&per_page=0").unwrap();
+
+        assert_eq!(cursor.page, None);
+        assert_eq!(cursor.per_page, 1);
+        assert!(pagination("page=2&cursor=abc").is_err());
+    }
+
+    // Test checks if only allowlisted fields are sortable.
+    #[test]
+    fn test_sorting_parse() {
+        let sort = ITEMS.parse(Some("-name")).unwrap();
+
+        assert_eq!(sort.field.column, "name");
+        assert!(sort.descending);
+        assert_eq!(sort.name(), "-name");
+        assert_eq!(ITEMS.parse(None).unwrap().name(), "created_at");
+
+        let result = ITEMS.parse(Some("name; DROP TABLE users")).unwrap_err();
+
+        assert_eq!(result.code, "pagination.invalid_sort");
+    }
+
+    // Test checks if cursors of another sort or not issued by the server are rejected.
+    #[test]
+    fn test_window_cursor() {
+        let cursor = Cursor {
+            sort: "-name".to_string(),
+            key: "b".to_string(),
+            id: "002".to_string(),
+        };
+
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let query = format!("cursor={}&sort=-name", cursor.encode());
+
+        assert!(pagination(&query).unwrap().window(&ITEMS).is_ok());
+
+        let query = format!("cursor={}&sort=name", cursor.encode());
+        let result = pagination(&query).unwrap().window(&ITEMS).unwrap_err();
+
+        assert_eq!(result.code, "pagination.invalid_cursor");
+        assert!(pagination("cursor=abc").unwrap().window(&ITEMS).is_err());
+    }
+
+    // Test checks if the keyset and order are appended with bound values.
+    #[test]
+    fn test_window_sql() {
+        let cursor = Cursor {
+            sort: "-created_at".to_string(),
+            key: "2026-01-01T00:00:00.000000Z".to_string(),
+            id: "7".to_string(),
+        };
+        let window = pagination(&format!("cursor={}&sort=-created_at", cursor.encode()))
+            .unwrap()
+            .window(&ITEMS)
+            .unwrap();
+        let mut query = QueryBuilder::new("SELECT * FROM items WHERE TRUE");
+
+        window.push_keyset(&mut query);
+        window.push_order(&mut query);
+
+        assert_eq!(
+            query.sql(),
+            "SELECT * FROM items WHERE TRUE AND (created_at, id) < \
+             (CAST($1 AS timestamptz), CAST($2 AS bigint)) \
+             ORDER BY created_at DESC, id DESC LIMIT $3 OFFSET $4"
+        );
+        assert_eq!(window.offset(), 0);
+
+        let window = pagination("page=3&per_page=20")
+            .unwrap()
+            .window(&ITEMS)
+            .unwrap();
+
+        assert_eq!((window.limit(), window.offset()), (21, 40));
+    }
+
+    // Test checks if cursors walk through every item once, also with equal keys.
+    #[test]
+    fn test_window_walk() {
+        let mut query = "cursor=&per_page=2&sort=name".to_string();
+        let mut seen = Vec::new();
+
+        loop {
+            let window = pagination(&query).unwrap().window(&ITEMS).unwrap();
+            let page = window.paginate(window.apply(items()));
+            seen.extend(page.items.iter().map(|item| item.id));
+
+            match page.next_cursor {
+                Some(cursor) => query = format!("cursor={}&per_page=2&sort=name", cursor),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, [1, 3, 2, 0, 4]);
+
+        let window = pagination("page=2&per_page=2&sort=-name")
+            .unwrap()
+            .window(&ITEMS)
+            .unwrap();
+        let page = window.paginate(window.apply(items()));
+
+        assert_eq!(
+            page.items.iter().map(|item| item.id).collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(page.page, Some(2));
+        assert_eq!(page.next_cursor, None);
+    }
+}
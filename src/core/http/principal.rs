@@ -0,0 +1,679 @@
+//! Principal extractor module.
+//!
+//! Authentication middleware stores the authenticated
+//! `Principal` in the request extensions, handlers take
+//! it as an argument to require an authenticated caller.
+//!
+//! `authenticate` verifies the access tokens issued by
+//! this server, opaque ones when a token store is set.
+//! Tokens of a login session, with a `sid` claim, are
+//! only accepted while the session is active.
+//! With a session cookie configured, requests without a
+//! bearer token are authenticated by the session ID in
+//! the signed cookie.
+//!
+//! Tokens of a login whose password expired only carry
+//! `PASSWORD_CHANGE_SCOPE`, they reach `/me` and
+//! `/me/password` and nothing else.
+
+// Imports from external crates
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, MatchedPath, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Local imports
+use crate::core::auth::expiry::PASSWORD_CHANGE_SCOPE;
+use crate::core::auth::issuer::resolve;
+use crate::core::auth::jwt::Validation;
+use crate::core::auth::keys::KeyManager;
+use crate::core::auth::rbac::Principal;
+use crate::core::config::TokenSettings;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::cookies::Cookies;
+use crate::core::repo::{AccessTokenRepo, SessionRepo};
+
+tokio::task_local! {
+    /// User of the request handled by the current task.
+    static USER: Uuid;
+}
+
+/// ## Session of the request struct.
+///
+/// Stored next to the `Principal` when the token belongs
+/// to a login session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentSession(pub Uuid);
+
+/// Paths a token restricted to the password change may
+/// reach, matched against the end of the route.
+const PASSWORD_CHANGE_PATHS: [&str; 3] = ["/me", "/me/", "/me/password"];
+
+/// ## Checks if the scopes only allow the password change.
+fn password_change_only(scopes: &[String]) -> bool {
+    scopes.iter().any(|scope| scope == PASSWORD_CHANGE_SCOPE)
+}
+
+/// ## Returns the error of a login that has to change its password.
+fn password_change_required() -> AppError {
+    AppError::new(
+        ErrorKind::Forbidden,
+        "Password has expired, change it to continue".to_string(),
+        None,
+    )
+    .with_code("password.change_required")
+}
+
+/// ## Authenticator struct.
+///
+/// ## Examples
+/// ```
+/// use axum::{middleware, routing::get, Router};
+/// use axum_auth::core::auth::{jwt::SigningKey, keys::KeyManager};
+/// use axum_auth::core::auth::rbac::Principal;
+/// use axum_auth::core::config::TokenSettings;
+/// use axum_auth::core::http::principal::{authenticate, Authenticator};
+/// use axum_auth::core::repo::memory::MemorySessionRepo;
+/// use std::sync::Arc;
+///
+/// let settings = TokenSettings::default();
+/// let key = SigningKey::generate(&settings.key_id).unwrap();
+/// let authenticator = Arc::new(Authenticator::new(
+///     Arc::new(KeyManager::new(key)),
+///     &settings,
+///     Arc::new(MemorySessionRepo::default()),
+/// ));
+///
+/// let app: Router = Router::new()
+///     .route("/whoami", get(|principal: Principal| async move { principal.user_id.to_string() }))
+///     .layer(middleware::from_fn_with_state(authenticator, authenticate));
+/// ```
+pub struct Authenticator {
+    keys: Arc<KeyManager>,
+    validation: Validation,
+    sessions: Arc<dyn SessionRepo>,
+    session_cookie: Option<(String, Arc<Cookies>)>,
+    opaque_tokens: Option<Arc<dyn AccessTokenRepo>>,
+}
+
+impl Authenticator {
+    /// ## Creates a new `Authenticator` instance.
+    ///
+    /// ## Parameters
+    /// - `keys`: `Arc<KeyManager>` - Keys the server signs its tokens with.
+    /// - `settings`: `&TokenSettings` - Expected issuer and audience.
+    /// - `sessions`: `Arc<dyn SessionRepo>` - Sessions of the `sid` claims.
+    pub fn new(
+        keys: Arc<KeyManager>,
+        settings: &TokenSettings,
+        sessions: Arc<dyn SessionRepo>,
+    ) -> Self {
+        Authenticator {
+            keys,
+            validation: Validation::new(&settings.issuer, &settings.audience),
+            sessions,
+            session_cookie: None,
+            opaque_tokens: None,
+        }
+    }
+
+    /// ## Accepts the session ID in a signed cookie.
+    ///
+    /// ## Parameters
+    /// - `name`: `&str` - Name of the session cookie.
+    /// - `cookies`: `Arc<Cookies>` - Keys the cookie is signed with.
+    pub fn with_session_cookie(mut self, name: &str, cookies: Arc<Cookies>) -> Self {
+        self.session_cookie = Some((name.to_string(), cookies));
+        self
+    }
+
+    /// ## Accepts opaque access tokens of the store.
+    ///
+    /// ## Parameters
+    /// - `store`: `Arc<dyn AccessTokenRepo>` - Store of the opaque tokens.
+    pub fn with_opaque_tokens(mut self, store: Arc<dyn AccessTokenRepo>) -> Self {
+        self.opaque_tokens = Some(store);
+        self
+    }
+
+    /// ## Authenticates the token.
+    ///
+    /// ## Parameters
+    /// - `token`: `&str` - Bearer token of the request.
+    ///
+    /// ## Returns
+    /// + `Result<(Principal, Option<CurrentSession>), AppError>`
+    ///     - `Ok((Principal, Option<CurrentSession>))`: User of the token
+    ///       and its session, if the token belongs to one.
+    ///     - `Err(AppError)`: `Auth` if the token is invalid, was not issued
+    ///       to a user or its session is no longer active.
+    pub async fn authenticate(
+        &self,
+        token: &str,
+    ) -> Result<(Principal, Option<CurrentSession>), AppError> {
+        let (principal, session, _) = self.verify(token).await?;
+
+        Ok((principal, session))
+    }
+
+    /// ## Authenticates the session cookie.
+    ///
+    /// ## Parameters
+    /// - `session_id`: `&str` - Value of the session cookie.
+    ///
+    /// ## Returns
+    /// + `Result<(Principal, CurrentSession), AppError>`
+    ///     - `Ok((Principal, CurrentSession))`: User of the session.
+    ///     - `Err(AppError)`: `Auth` if the session is malformed, revoked
+    ///       or expired.
+    pub async fn authenticate_session(
+        &self,
+        session_id: &str,
+    ) -> Result<(Principal, CurrentSession), AppError> {
+        let session_id = Uuid::parse_str(session_id).map_err(|_| {
+            AppError::auth("Session cookie is malformed").with_code("session.revoked")
+        })?;
+        let user_id = self.active(session_id, None).await?;
+        let principal = Principal {
+            user_id,
+            grants: Vec::new(),
+        };
+
+        Ok((principal, CurrentSession(session_id)))
+    }
+
+    /// ## Authenticates the credentials of the request.
+    ///
+    /// Bearer token is preferred over the session cookie.
+    ///
+    /// ## Parameters
+    /// - `headers`: `&HeaderMap` - Headers of the request.
+    ///
+    /// ## Returns
+    /// + `Result<(Principal, Vec<String>), AppError>`
+    ///     - `Ok((Principal, Vec<String>))`: User of the credentials and
+    ///       the scopes of the token, none for the session cookie.
+    ///     - `Err(AppError)`: `Auth` if there are no valid credentials,
+    ///       `password.change_required` if the token only allows the
+    ///       password change.
+    pub async fn identify(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<(Principal, Vec<String>), AppError> {
+        let (principal, _, scopes) = self.credentials(headers).await?;
+        if password_change_only(&scopes) {
+            return Err(password_change_required());
+        }
+
+        Ok((principal, scopes))
+    }
+
+    /// ## Verifies the token.
+    ///
+    /// ## Parameters
+    /// - `token`: `&str` - Access token, JWT or opaque.
+    ///
+    /// ## Returns
+    /// + `Result<(Principal, Option<CurrentSession>, Vec<String>), AppError>`
+    ///     - `Ok((Principal, Option<CurrentSession>, Vec<String>))`: User,
+    ///       session and scopes of the token.
+    ///     - `Err(AppError)`: `Auth` like `authenticate`.
+    pub async fn verify(
+        &self,
+        token: &str,
+    ) -> Result<(Principal, Option<CurrentSession>, Vec<String>), AppError> {
+        let claims = resolve(
+            token,
+            &self.keys,
+            &self.validation,
+            self.opaque_tokens.as_deref(),
+        )
+        .await?;
+        let user_id = Uuid::parse_str(&claims.sub).map_err(|_| {
+            AppError::auth("Token is not issued to a user").with_code("token.invalid")
+        })?;
+        let principal = Principal {
+            user_id,
+            grants: Vec::new(),
+        };
+        let scopes = claims.scopes().map(str::to_string).collect();
+
+        let Some(sid) = claims.sid else {
+            return Ok((principal, None, scopes));
+        };
+        let session_id = Uuid::parse_str(&sid)
+            .map_err(|_| AppError::auth("Token session is malformed").with_code("token.invalid"))?;
+        self.active(session_id, Some(user_id)).await?;
+
+        Ok((principal, Some(CurrentSession(session_id)), scopes))
+    }
+
+    /// ## Authenticates the bearer token or else the session cookie.
+    async fn credentials(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<(Principal, Option<CurrentSession>, Vec<String>), AppError> {
+        let token = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        let cookie = self
+            .session_cookie
+            .as_ref()
+            .and_then(|(name, cookies)| cookies.get_signed(headers, name));
+
+        match (token, cookie) {
+            (Some(token), _) => self.verify(token).await,
+            (None, Some(cookie)) => {
+                let (principal, session) = self.authenticate_session(&cookie).await?;
+                Ok((principal, Some(session), Vec::new()))
+            }
+            (None, None) => Err(AppError::auth("Bearer token required")),
+        }
+    }
+
+    /// ## Checks if the session is active and returns its user.
+    ///
+    /// Sessions of another user than `user_id`, if given,
+    /// are treated as revoked.
+    async fn active(&self, session_id: Uuid, user_id: Option<Uuid>) -> Result<Uuid, AppError> {
+        let session = self
+            .sessions
+            .get(session_id)
+            .await?
+            .filter(|session| user_id.is_none_or(|user_id| session.user_id == user_id));
+        let Some(session) = session else {
+            return Err(
+                AppError::auth("Session is revoked or expired").with_code("session.revoked")
+            );
+        };
+
+        if let Err(e) = self.sessions.touch(session_id).await {
+            tracing::warn!(session_id = %session_id, error = %e, "Failed to touch session");
+        }
+
+        Ok(session.user_id)
+    }
+}
+
+/// ## Authenticates the request.
+///
+/// Middleware verifies the bearer token, or the session
+/// cookie when there is none, and stores the `Principal`,
+/// and the `CurrentSession` of session tokens, in the
+/// request extensions. Requests without valid credentials
+/// are rejected with 401, tokens restricted to the password
+/// change outside of `/me` with 403.
+pub async fn authenticate(
+    State(authenticator): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match authenticator.credentials(request.headers()).await {
+        Ok((_, _, scopes)) if password_change_only(&scopes) && !password_change_path(&request) => {
+            return password_change_required().into_response();
+        }
+        Ok((principal, session, _)) => {
+            request.extensions_mut().insert(principal);
+            if let Some(session) = session {
+                request.extensions_mut().insert(session);
+            }
+        }
+        Err(e) => return e.into_response(),
+    }
+    let principal = request.extensions().get::<Principal>().cloned();
+
+    // Outer layers, like the access log, read the
+    // user of the request from the response
+    let mut response = match &principal {
+        Some(principal) => USER.scope(principal.user_id, next.run(request)).await,
+        None => next.run(request).await,
+    };
+    if let Some(principal) = principal {
+        response.extensions_mut().insert(principal);
+    }
+
+    response
+}
+
+/// ## Returns the user of the current request.
+///
+/// ## Returns
+/// + `Option<Uuid>`
+///     - `Some(Uuid)`: If called while handling an authenticated request.
+///     - `None`: If called outside of `authenticate`.
+pub fn current_user_id() -> Option<Uuid> {
+    USER.try_with(|id| *id).ok()
+}
+
+/// ## Checks if the route of the request belongs to the password change.
+fn password_change_path(request: &Request) -> bool {
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(request.uri().path(), MatchedPath::as_str);
+
+    PASSWORD_CHANGE_PATHS
+        .iter()
+        .any(|allowed| path.ends_with(allowed))
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Principal
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    /// Extracts the authenticated principal.
+    ///
+    /// Requests without a principal are rejected
+    /// with `Auth`.
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Principal>().cloned().ok_or_else(|| {
+            AppError::new(ErrorKind::Auth, "Authentication required".to_string(), None)
+        })
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CurrentSession
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    /// Extracts the session of the request.
+    ///
+    /// Requests authenticated without a session token
+    /// are rejected with `Auth`.
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentSession>()
+            .copied()
+            .ok_or_else(|| AppError::auth("Session token required"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::issuer::TokenIssuer;
+    use crate::core::auth::jwt::SigningKey;
+    use crate::core::config::SameSite;
+    use crate::core::repo::memory::{MemoryAccessTokenRepo, MemorySessionRepo};
+    use crate::core::repo::NewSession;
+    use axum::{
+        body::Body,
+        http::{header::COOKIE, StatusCode},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn keys(key: &Arc<SigningKey>) -> Arc<KeyManager> {
+        Arc::new(KeyManager::new(key.clone()))
+    }
+
+    // Test checks if the principal is read from the request extensions.
+    #[tokio::test]
+    async fn test_principal_extracted() {
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: Vec::new(),
+        };
+        let mut request = Request::new(());
+        request.extensions_mut().insert(principal.clone());
+        let (mut parts, _) = request.into_parts();
+
+        let result = Principal::from_request_parts(&mut parts, &()).await;
+
+        assert_eq!(result, Ok(principal));
+    }
+
+    // Test checks if requests without a principal are unauthorized.
+    #[tokio::test]
+    async fn test_principal_missing() {
+        let (mut parts, _) = Request::new(()).into_parts();
+
+        let result = Principal::from_request_parts(&mut parts, &())
+            .await
+            .unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Auth);
+    }
+
+    fn app(authenticator: Authenticator) -> Router {
+        Router::new()
+            .route(
+                "/whoami",
+                get(
+                    |principal: Principal, session: Option<CurrentSession>| async move {
+                        format!("{} {:?}", principal.user_id, session.map(|s| s.0))
+                    },
+                ),
+            )
+            .layer(middleware::from_fn_with_state(
+                Arc::new(authenticator),
+                authenticate,
+            ))
+    }
+
+    async fn call(app: &Router, token: Option<&str>) -> (StatusCode, String) {
+        let mut request = Request::get("/whoami");
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    // Test checks if the middleware accepts tokens of active sessions only.
+    #[tokio::test]
+    async fn test_authenticate_session() {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let user_id = Uuid::new_v4();
+        let session = sessions
+            .create(NewSession {
+                user_id,
+                ip: None,
+                user_agent: None,
+                expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+            })
+            .await
+            .unwrap();
+        let app = app(Authenticator::new(keys(&key), &settings, sessions.clone()));
+
+        let token = key
+            .issue_for_session(&settings, user_id, session.id, &[])
+            .unwrap();
+        let (status, body) = call(&app, Some(&token)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, format!("{} Some({})", user_id, session.id));
+
+        let stolen = key
+            .issue_for_session(&settings, Uuid::new_v4(), session.id, &[])
+            .unwrap();
+        assert_eq!(call(&app, Some(&stolen)).await.0, StatusCode::UNAUTHORIZED);
+
+        sessions.revoke(session.id).await.unwrap();
+        assert_eq!(call(&app, Some(&token)).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    // Test checks if opaque tokens are accepted with a store and until revoked.
+    #[tokio::test]
+    async fn test_authenticate_opaque() {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let store = Arc::new(MemoryAccessTokenRepo::default());
+        let tokens = TokenIssuer::new(keys(&key), &settings).with_store(store.clone());
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let user_id = Uuid::new_v4();
+        let token = tokens.issue(&user_id.to_string(), &[]).await.unwrap();
+
+        let jwt_only = app(Authenticator::new(keys(&key), &settings, sessions.clone()));
+        assert_eq!(
+            call(&jwt_only, Some(&token)).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let app =
+            app(Authenticator::new(keys(&key), &settings, sessions).with_opaque_tokens(store));
+        let (status, body) = call(&app, Some(&token)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, format!("{} None", user_id));
+
+        tokens.revoke(&token).await.unwrap();
+        assert_eq!(call(&app, Some(&token)).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    // Test checks if the session cookie is only accepted when configured.
+    #[tokio::test]
+    async fn test_authenticate_cookie() {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let user_id = Uuid::new_v4();
+        let session = sessions
+            .create(NewSession {
+                user_id,
+                ip: None,
+                user_agent: None,
+                expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+            })
+            .await
+            .unwrap();
+        let cookies =
+            Arc::new(Cookies::from_secret(b"cookie-secret", false, SameSite::Lax).unwrap());
+        let signed = cookies.signed("session", &session.id.to_string());
+        let call = |app: Router, value: String| async move {
+            let request = Request::get("/whoami")
+                .header(COOKIE, format!("theme=dark; session={}", value))
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(request).await.unwrap().status()
+        };
+
+        let bearer = app(Authenticator::new(keys(&key), &settings, sessions.clone()));
+        assert_eq!(
+            call(bearer, signed.value().to_string()).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let cookie = app(Authenticator::new(keys(&key), &settings, sessions.clone())
+            .with_session_cookie("session", cookies));
+        assert_eq!(
+            call(cookie.clone(), signed.value().to_string()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            call(cookie.clone(), session.id.to_string()).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        sessions.revoke(session.id).await.unwrap();
+        assert_eq!(
+            call(cookie, signed.value().to_string()).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    // Test checks if the middleware rejects missing, foreign and non-user tokens.
+    #[tokio::test]
+    async fn test_authenticate_rejects() {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let other = SigningKey::generate(&settings.key_id).unwrap();
+        let app = app(Authenticator::new(
+            keys(&key),
+            &settings,
+            Arc::new(MemorySessionRepo::default()),
+        ));
+        let user_id = Uuid::new_v4();
+
+        let (status, body) = call(
+            &app,
+            Some(&key.issue(&settings, &user_id.to_string(), &[]).unwrap()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, format!("{} None", user_id));
+
+        assert_eq!(call(&app, None).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            call(
+                &app,
+                Some(&other.issue(&settings, &user_id.to_string(), &[]).unwrap())
+            )
+            .await
+            .0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            call(&app, Some(&key.issue(&settings, "service", &[]).unwrap()))
+                .await
+                .0,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    // Test checks if password change tokens only reach the password change.
+    #[tokio::test]
+    async fn test_authenticate_password_change() {
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let authenticator = Arc::new(Authenticator::new(
+            keys(&key),
+            &settings,
+            Arc::new(MemorySessionRepo::default()),
+        ));
+        let app = Router::new()
+            .route("/whoami", get(|| async { "whoami" }))
+            .route("/me/password", get(|| async { "password" }))
+            .route_layer(middleware::from_fn_with_state(
+                authenticator.clone(),
+                authenticate,
+            ));
+        let user_id = Uuid::new_v4().to_string();
+        let token = key
+            .issue(&settings, &user_id, &[PASSWORD_CHANGE_SCOPE])
+            .unwrap();
+        let call = |path: &'static str| {
+            let request = Request::get(path)
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        assert_eq!(call("/me/password").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            call("/whoami").await.unwrap().status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        let error = authenticator.identify(&headers).await.unwrap_err();
+        assert_eq!(error.code, "password.change_required");
+    }
+}
@@ -0,0 +1,216 @@
+//! Redaction module.
+//!
+//! Removes credentials from the headers, query strings
+//! and bodies before they are written to the request
+//! journal or the access log. Field names are matched
+//! by parts, ignoring case, so `newPassword` is caught
+//! by `password`.
+
+// Imports from external crates
+use axum::http::{header::CONTENT_TYPE, HeaderMap};
+use serde_json::Value as Json;
+
+/// Replacement of redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Headers that are never logged or journaled.
+pub const SENSITIVE_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// Parts of body field names that mark them as secret.
+pub const SENSITIVE_FIELDS: [&str; 5] = ["password", "secret", "token", "api_key", "otp"];
+
+/// ## Redactor struct.
+///
+/// Redacts the built-in sensitive headers and fields
+/// and every name containing a part of the deny-list.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::http::redact::{Redactor, REDACTED};
+///
+/// let redactor = Redactor::new(&["ssn".to_string()]);
+///
+/// assert_eq!(
+///     redactor.query("page=2&user_ssn=123&password=x"),
+///     format!("page=2&user_ssn={}&password={}", REDACTED, REDACTED)
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Redactor {
+    deny_list: Vec<String>,
+}
+
+impl Redactor {
+    /// ## Creates a new `Redactor` instance.
+    ///
+    /// ## Parameters
+    /// - `deny_list`: `&[String]` - Extra parts of names to redact.
+    pub fn new(deny_list: &[String]) -> Self {
+        Redactor {
+            deny_list: deny_list
+                .iter()
+                .map(|part| part.to_lowercase())
+                .filter(|part| !part.is_empty())
+                .collect(),
+        }
+    }
+
+    /// ## Checks if the field name marks a secret.
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+
+        SENSITIVE_FIELDS.iter().any(|part| name.contains(part)) || self.is_denied(&name)
+    }
+
+    /// ## Checks if the lowercase name contains a part of the deny-list.
+    fn is_denied(&self, name: &str) -> bool {
+        self.deny_list
+            .iter()
+            .any(|part| name.contains(part.as_str()))
+    }
+
+    /// ## Returns the headers with sensitive values redacted.
+    pub fn headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let sensitive =
+                    SENSITIVE_HEADERS.contains(&name.as_str()) || self.is_denied(name.as_str());
+                let value = match sensitive {
+                    true => REDACTED.to_string(),
+                    false => value.to_str().unwrap_or(REDACTED).to_string(),
+                };
+
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    /// ## Returns the query string with secret parameters redacted.
+    pub fn query(&self, query: &str) -> String {
+        query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if self.is_sensitive(name) => format!("{}={}", name, REDACTED),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+
+    /// ## Returns the body with secret fields redacted.
+    ///
+    /// JSON and form bodies are redacted field by field,
+    /// other text is kept as is and binary bodies are dropped.
+    pub fn body(&self, headers: &HeaderMap, bytes: &[u8]) -> Option<String> {
+        let text = std::str::from_utf8(bytes).ok()?;
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        if content_type.starts_with("application/x-www-form-urlencoded") {
+            return Some(self.query(text));
+        }
+
+        match serde_json::from_str::<Json>(text) {
+            Ok(mut json) => {
+                self.json(&mut json);
+                Some(json.to_string())
+            }
+            Err(_) => Some(text.to_string()),
+        }
+    }
+
+    /// ## Redacts secret fields of a JSON value in place.
+    pub fn json(&self, value: &mut Json) {
+        match value {
+            Json::Object(fields) => {
+                for (name, field) in fields.iter_mut() {
+                    match self.is_sensitive(name) {
+                        true => *field = Json::String(REDACTED.to_string()),
+                        false => self.json(field),
+                    }
+                }
+            }
+            Json::Array(items) => items.iter_mut().for_each(|item| self.json(item)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    // Test checks if credentials are redacted from headers and bodies.
+    #[test]
+    fn test_redact() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer abc"));
+        headers.insert("accept", HeaderValue::from_static("*/*"));
+
+        assert_eq!(
+            Redactor::default().headers(&headers),
+            vec![
+                ("authorization".to_string(), REDACTED.to_string()),
+                ("accept".to_string(), "*/*".to_string()),
+            ]
+        );
+
+        let body = br#"{"email":"a@example.com","newPassword":"x","mfa":[{"otp":"1"}]}"#;
+        let result: Json =
+            serde_json::from_str(&Redactor::default().body(&headers, body).unwrap()).unwrap();
+
+        assert_eq!(result["email"], "a@example.com");
+        assert_eq!(result["newPassword"], REDACTED);
+        assert_eq!(result["mfa"][0]["otp"], REDACTED);
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+
+        assert_eq!(
+            Redactor::default()
+                .body(&headers, b"user=a&password=b")
+                .unwrap(),
+            format!("user=a&password={}", REDACTED)
+        );
+        assert_eq!(Redactor::default().body(&headers, &[0xff, 0xfe]), None);
+    }
+
+    // Test checks if the deny-list extends the built-in names.
+    #[test]
+    fn test_deny_list() {
+        let redactor = Redactor::new(&["SSN".to_string(), "x-tenant".to_string()]);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant-key", HeaderValue::from_static("abc"));
+        headers.insert("cookie", HeaderValue::from_static("session=1"));
+        headers.insert("accept", HeaderValue::from_static("*/*"));
+
+        assert_eq!(
+            redactor.headers(&headers),
+            vec![
+                ("x-tenant-key".to_string(), REDACTED.to_string()),
+                ("cookie".to_string(), REDACTED.to_string()),
+                ("accept".to_string(), "*/*".to_string()),
+            ]
+        );
+
+        let body = br#"{"name":"a","userSsn":"1","nested":{"api_key":"k"}}"#;
+        let result: Json = serde_json::from_str(&redactor.body(&headers, body).unwrap()).unwrap();
+
+        assert_eq!(result["name"], "a");
+        assert_eq!(result["userSsn"], REDACTED);
+        assert_eq!(result["nested"]["api_key"], REDACTED);
+        assert!(!Redactor::default().is_sensitive("userSsn"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Request ID correlation module.
+//!
+//! Middleware reads the `X-Request-Id` header or generates
+//! a new ID, echoes it in the response and makes it
+//! available to the tracing span and the error responses
+//! of the request.
+
+// Imports from external crates
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+/// Name of the request ID header.
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Maximum accepted length of an incoming request ID.
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    /// ID of the request handled by the current task.
+    static REQUEST_ID: String;
+}
+
+/// ## Request ID middleware.
+///
+/// Function is used with `axum::middleware::from_fn`.
+/// Incoming IDs that are empty, too long or contain
+/// non printable characters are replaced by a new UUID.
+///
+/// ## Examples
+/// ```
+/// use axum::{middleware, Router};
+/// use axum_auth::core::http::request_id::request_id;
+///
+/// let router: Router = Router::new().layer(middleware::from_fn(request_id));
+/// ```
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid(value))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER.clone(), value);
+    }
+
+    response
+}
+
+/// ## Returns the ID of the current request.
+///
+/// ## Returns
+/// + `Option<String>`
+///     - `Some(String)`: If called while handling a request.
+///     - `None`: If called outside of the middleware.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// ## Checks if the incoming request ID can be used.
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.chars().all(|c| c.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::err::{AppError, ErrorKind};
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn test_router() -> Router {
+        Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route(
+                "/fail",
+                get(|| async {
+                    Err::<(), AppError>(AppError::new(
+                        ErrorKind::Validation,
+                        "Invalid input".to_string(),
+                        None,
+                    ))
+                }),
+            )
+            .layer(middleware::from_fn(request_id))
+    }
+
+    fn get_request(path: &str, id: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri(path);
+        if let Some(id) = id {
+            builder = builder.header(&REQUEST_ID_HEADER, id);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    // Test checks if the incoming request ID is echoed in the response.
+    #[tokio::test]
+    async fn test_request_id_echoed() {
+        let response = test_router()
+            .oneshot(get_request("/ok", Some("abc-123")))
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()[&REQUEST_ID_HEADER], "abc-123");
+    }
+
+    // Test checks if a new request ID is generated when the header is missing.
+    #[tokio::test]
+    async fn test_request_id_generated() {
+        let response = test_router()
+            .oneshot(get_request("/ok", None))
+            .await
+            .unwrap();
+
+        let id = response.headers()[&REQUEST_ID_HEADER].to_str().unwrap();
+
+        assert!(Uuid::parse_str(id).is_ok());
+    }
+
+    // Test checks if an invalid incoming request ID is replaced.
+    #[tokio::test]
+    async fn test_request_id_invalid_replaced() {
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        let response = test_router()
+            .oneshot(get_request("/ok", Some(&too_long)))
+            .await
+            .unwrap();
+
+        assert_ne!(response.headers()[&REQUEST_ID_HEADER], too_long.as_str());
+    }
+
+    // Test checks if the request ID is embedded in the JSON error body.
+    #[tokio::test]
+    async fn test_request_id_in_error_body() {
+        let response = test_router()
+            .oneshot(get_request("/fail", Some("abc-123")))
+            .await
+            .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["error"]["request_id"], "abc-123");
+        assert_eq!(body["error"]["message"], "Invalid input");
+    }
+
+    // Test checks if no request ID is available outside of the middleware.
+    #[test]
+    fn test_current_request_id_outside() {
+        assert_eq!(current_request_id(), None);
+    }
+}
@@ -0,0 +1,232 @@
+//! Request tracing module.
+//!
+//! Middleware opens the tracing span of the request when
+//! the request is sampled. Sampling is head-based, the
+//! decision is derived from the request ID when the
+//! request starts, so high traffic routes can be traced
+//! rarely while the rest of the API is traced in full.
+
+// Imports from external crates
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    net::SocketAddr,
+    sync::Arc,
+};
+use tracing::Instrument;
+
+// Local imports
+use super::client_ip::ClientIp;
+use super::request_id::current_request_id;
+use crate::core::config::{RouteSampling, TraceSettings};
+
+/// ## Sampling rule of a request struct.
+///
+/// ## Fields
+/// + `sample_ratio`: `f64` - Share of requests traced.
+/// + `always_sample_errors`: `bool` - Log failed requests that
+///   were not sampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplingRule {
+    pub sample_ratio: f64,
+    pub always_sample_errors: bool,
+}
+
+impl SamplingRule {
+    /// ## Checks if the request with the ID is sampled.
+    ///
+    /// Same request ID always gets the same decision.
+    pub fn is_sampled(&self, request_id: &str) -> bool {
+        if self.sample_ratio >= 1.0 {
+            return true;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        request_id.hash(&mut hasher);
+
+        (hasher.finish() as f64 / u64::MAX as f64) < self.sample_ratio
+    }
+}
+
+/// ## Trace sampler struct.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::TraceSettings;
+/// use axum_auth::core::http::trace::Sampler;
+///
+/// let sampler = Sampler::new(&TraceSettings::default());
+///
+/// assert_eq!(sampler.rule("/healthz").sample_ratio, 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    default: SamplingRule,
+    routes: Vec<RouteSampling>,
+}
+
+impl Sampler {
+    /// ## Creates a new `Sampler` instance.
+    pub fn new(settings: &TraceSettings) -> Self {
+        Sampler {
+            default: SamplingRule {
+                sample_ratio: settings.sample_ratio,
+                always_sample_errors: settings.always_sample_errors,
+            },
+            routes: settings.routes.clone(),
+        }
+    }
+
+    /// ## Returns the rule of the longest matching route.
+    ///
+    /// ## Parameters
+    /// - `path`: `&str` - Path of the request.
+    ///
+    /// ## Returns
+    /// - `SamplingRule`: Rule of the route, the default rule
+    ///   when no route matches.
+    pub fn rule(&self, path: &str) -> SamplingRule {
+        self.routes
+            .iter()
+            .filter(|route| {
+                let prefix = route.path.trim_end_matches('/');
+                path == prefix
+                    || path
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|route| route.path.len())
+            .map_or(self.default, |route| SamplingRule {
+                sample_ratio: route.sample_ratio,
+                always_sample_errors: route.always_sample_errors,
+            })
+    }
+}
+
+/// ## Request tracing middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`
+/// inside the request ID layer. Sampled requests run in the
+/// `request` span, failed requests that were not sampled are
+/// logged when the rule asks for it.
+///
+/// ## Examples
+/// ```
+/// use std::sync::Arc;
+/// use axum::{middleware, Router};
+/// use axum_auth::core::config::TraceSettings;
+/// use axum_auth::core::http::{request_id::request_id, trace::{trace, Sampler}};
+///
+/// let sampler = Arc::new(Sampler::new(&TraceSettings::default()));
+/// let router: Router = Router::new()
+///     .layer(middleware::from_fn_with_state(sampler, trace))
+///     .layer(middleware::from_fn(request_id));
+/// ```
+pub async fn trace(State(sampler): State<Arc<Sampler>>, request: Request, next: Next) -> Response {
+    let id = current_request_id().unwrap_or_default();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let client = request
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| *ip)
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        });
+    let rule = sampler.rule(&path);
+
+    if rule.is_sampled(&id) {
+        let span = tracing::info_span!(
+            "request",
+            request_id = %id,
+            method = %method,
+            path = %path,
+            client = ?client,
+        );
+
+        return next.run(request).instrument(span).await;
+    }
+
+    let response = next.run(request).await;
+
+    let status = response.status();
+    if rule.always_sample_errors && (status.is_client_error() || status.is_server_error()) {
+        tracing::warn!(
+            request_id = %id,
+            method = %method,
+            path = %path,
+            client = ?client,
+            status = status.as_u16(),
+            "Request failed"
+        );
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn route(path: &str, sample_ratio: f64) -> RouteSampling {
+        RouteSampling {
+            path: path.to_string(),
+            sample_ratio,
+            always_sample_errors: true,
+        }
+    }
+
+    // Test checks if the longest matching route overrides the default.
+    #[test]
+    fn test_rule_longest_match() {
+        let sampler = Sampler::new(&TraceSettings {
+            sample_ratio: 0.5,
+            routes: vec![route("/healthz", 0.01), route("/healthz/details", 1.0)],
+            ..TraceSettings::default()
+        });
+
+        assert_eq!(sampler.rule("/healthz").sample_ratio, 0.01);
+        assert_eq!(sampler.rule("/healthz/details").sample_ratio, 1.0);
+        assert_eq!(sampler.rule("/healthzz").sample_ratio, 0.5);
+        assert!(!sampler.rule("/users").always_sample_errors);
+    }
+
+    // Test checks if the decision is stable for the same request ID.
+    #[test]
+    fn test_is_sampled_stable() {
+        let rule = SamplingRule {
+            sample_ratio: 0.5,
+            always_sample_errors: false,
+        };
+        let id = Uuid::new_v4().to_string();
+
+        assert_eq!(rule.is_sampled(&id), rule.is_sampled(&id));
+    }
+
+    // Test checks if roughly the configured share of requests is sampled.
+    #[test]
+    fn test_is_sampled_ratio() {
+        let sampled = |sample_ratio: f64| {
+            let rule = SamplingRule {
+                sample_ratio,
+                always_sample_errors: false,
+            };
+            (0..10_000)
+                .filter(|_| rule.is_sampled(&Uuid::new_v4().to_string()))
+                .count()
+        };
+
+        assert_eq!(sampled(0.0), 0);
+        assert_eq!(sampled(1.0), 10_000);
+        assert!((800..1200).contains(&sampled(0.1)));
+    }
+}
@@ -0,0 +1,146 @@
+//! Logging module.
+//!
+//! Module installs the global tracing subscriber
+//! used by the whole application. The log filter only
+//! applies to the printed logs, so the statement events
+//! of sqlx still reach the query statistics layer.
+//! Logs are written by a background thread, which has
+//! to be flushed before the process exits.
+
+// Imports from external crates
+use std::sync::{Arc, Mutex};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{
+    fmt,
+    layer::{Layer, SubscriberExt},
+    reload,
+    util::SubscriberInitExt,
+    EnvFilter, Registry,
+};
+
+// Local imports
+use super::config::LoggingSettings;
+use super::err::{AppError, ErrorKind};
+
+/// Default filter used when `RUST_LOG` is not set.
+pub const DEFAULT_LOG_FILTER: &str = "info";
+
+/// ## Log filter handle struct.
+///
+/// Handle replaces the filter of the installed subscriber,
+/// so the log level can change without a restart.
+///
+/// ## Fields
+/// + `handle`: `reload::Handle<EnvFilter, Registry>` - Reload handle.
+/// + `from_env`: `bool` - Filter was set by `RUST_LOG`, which
+///   takes precedence over the configuration.
+/// + `guard`: `Arc<Mutex<Option<WorkerGuard>>>` - Guard of the log
+///   writer thread, taken by `flush`.
+#[derive(Debug, Clone)]
+pub struct LogHandle {
+    handle: reload::Handle<EnvFilter, Registry>,
+    from_env: bool,
+    guard: Arc<Mutex<Option<WorkerGuard>>>,
+}
+
+impl LogHandle {
+    /// ## Applies the configured log level.
+    ///
+    /// Function does nothing when the filter was set
+    /// by the `RUST_LOG` environment variable.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&LoggingSettings` - Logging settings.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the filter was replaced or `RUST_LOG` is set.
+    ///     - `Err(AppError)`: If the level is not a valid filter.
+    pub fn apply(&self, settings: &LoggingSettings) -> Result<(), AppError> {
+        if self.from_env {
+            return Ok(());
+        }
+
+        let filter = parse_filter(&settings.level)?;
+
+        self.handle.reload(filter).map_err(|e| {
+            AppError::new(
+                ErrorKind::Env,
+                "Failed to replace the log filter".to_string(),
+                Some(Box::new(e)),
+            )
+        })
+    }
+
+    /// ## Flushes the buffered logs.
+    ///
+    /// Function waits until the writer thread wrote the
+    /// buffered logs and stops it, logs recorded after the
+    /// flush are dropped. It is meant to be the last
+    /// shutdown hook, so the logs of the other hooks are
+    /// written before the process exits.
+    pub fn flush(&self) {
+        let guard = match self.guard.lock() {
+            Ok(mut guard) => guard.take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+
+        drop(guard);
+    }
+}
+
+/// ## Installs the global tracing subscriber.
+///
+/// Function reads the filter from the `RUST_LOG`
+/// environment variable and falls back to
+/// `DEFAULT_LOG_FILTER` until the configured level
+/// is applied through the returned handle. Logs are
+/// written to stdout until the handle is flushed.
+///
+/// ## Returns
+/// + `Result<LogHandle, AppError>`
+///     - `Ok(LogHandle)`: If the subscriber was installed.
+///     - `Err(AppError)`: If a subscriber is already installed.
+pub fn init() -> Result<LogHandle, AppError> {
+    let (filter, from_env) = match EnvFilter::try_from_default_env() {
+        Ok(filter) => (filter, true),
+        Err(_) => (EnvFilter::new(DEFAULT_LOG_FILTER), false),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+    let (writer, guard) = tracing_appender::non_blocking(std::io::stdout());
+
+    let registry =
+        tracing_subscriber::registry().with(fmt::layer().with_writer(writer).with_filter(filter));
+    #[cfg(feature = "server")]
+    let registry = registry.with(super::db::stats::layer());
+
+    registry.try_init().map_err(|e| {
+        AppError::new(
+            ErrorKind::Env,
+            "Failed to install the tracing subscriber".to_string(),
+            Some(Box::new(e)),
+        )
+    })?;
+
+    Ok(LogHandle {
+        handle,
+        from_env,
+        guard: Arc::new(Mutex::new(Some(guard))),
+    })
+}
+
+/// ## Parses a log filter directive.
+///
+/// ## Returns
+/// + `Result<EnvFilter, AppError>`
+///     - `Ok(EnvFilter)`: Parsed filter.
+///     - `Err(AppError)`: If the directive is invalid.
+pub fn parse_filter(level: &str) -> Result<EnvFilter, AppError> {
+    EnvFilter::try_new(level).map_err(|e| {
+        AppError::new(
+            ErrorKind::Config,
+            format!("Invalid log level '{}'", level),
+            Some(Box::new(e)),
+        )
+    })
+}
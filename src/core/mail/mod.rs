@@ -0,0 +1,255 @@
+//! Mail module.
+//!
+//! Mail to the users leaves the server as webhook events,
+//! e.g. `magic_link.requested`, which a mail service turns
+//! into emails. In development no mail service has to be
+//! running: events carrying mail are rendered into plain
+//! text emails by `render` and handed to a `Mailer`
+//! instead, and `MailCapture` keeps the latest of them for
+//! `/dev/mailbox`. With `mail.enabled`, the texts come
+//! from the handlebars templates of `templates`, and
+//! outside development the rendered email is added to the
+//! event data as `mail`, ready to be sent as is. With a
+//! `mail.provider` other than `webhooks`, the server sends
+//! the emails itself through the mailers of `providers`.
+
+// References to submodules
+pub mod providers;
+pub mod templates;
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value as Json;
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use uuid::Uuid;
+
+// Local imports
+use super::err::AppError;
+use super::webhooks::WebhookEvent;
+
+// Re-exports of the wire types
+pub use crate::api::mail::Email;
+
+/// Emails kept by the capture, older ones are dropped.
+pub const CAPTURE_LIMIT: usize = 100;
+
+/// ## Mail sender trait.
+#[async_trait]
+pub trait Mailer: Send + Sync + fmt::Debug {
+    /// Sends the email.
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+
+    /// Checks the connection for the readiness probe.
+    async fn check(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+/// ## Captured mail store struct.
+///
+/// Keeps the latest `CAPTURE_LIMIT` emails in memory instead
+/// of sending them. Clones share the same store.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::mail::{render, MailCapture, Mailer};
+/// use axum_auth::core::webhooks::WebhookEvent;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let capture = MailCapture::default();
+/// let data = serde_json::json!({
+///     "email": "ada@example.com",
+///     "link": "https://example.com/verify?token=abc",
+/// });
+/// let email = render(WebhookEvent::MagicLinkRequested, &data).unwrap();
+///
+/// capture.send(email).await.unwrap();
+/// assert_eq!(capture.list()[0].to, "ada@example.com");
+/// # });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MailCapture {
+    emails: Arc<Mutex<VecDeque<Email>>>,
+}
+
+impl MailCapture {
+    /// ## Locks the emails, a poisoned lock is recovered.
+    fn lock(&self) -> MutexGuard<'_, VecDeque<Email>> {
+        self.emails.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// ## Returns the captured emails, newest first.
+    pub fn list(&self) -> Vec<Email> {
+        self.lock().iter().rev().cloned().collect()
+    }
+
+    /// ## Forgets every captured email.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+#[async_trait]
+impl Mailer for MailCapture {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tracing::info!(
+            to = %email.to,
+            event = %email.event,
+            "Captured email '{}'",
+            email.subject
+        );
+
+        let mut emails = self.lock();
+        if emails.len() >= CAPTURE_LIMIT {
+            emails.pop_front();
+        }
+        emails.push_back(email);
+
+        Ok(())
+    }
+}
+
+/// ## Renders a webhook event into the email it stands for.
+///
+/// ## Parameters
+/// - `event`: `WebhookEvent` - Kind of the event.
+/// - `data`: `&serde_json::Value` - Data of the event.
+///
+/// ## Returns
+/// - `Option<Email>`: Email to the `email` of the data, `None` if the
+///   event does not carry mail or the data has no address.
+pub fn render(event: WebhookEvent, data: &Json) -> Option<Email> {
+    let field = |name: &str| match data.get(name) {
+        Some(Json::String(value)) => value.clone(),
+        Some(Json::Null) | None => String::new(),
+        Some(value) => value.to_string(),
+    };
+    let credential = match field("credential").as_str() {
+        "api_key" => "API key",
+        _ => "password",
+    };
+
+    let (subject, body) = match event {
+        WebhookEvent::MagicLinkRequested => (
+            "Your sign-in link".to_string(),
+            format!(
+                "Sign in with this link:\n\n{}\n\nThe link expires at {}.",
+                field("link"),
+                field("expires_at")
+            ),
+        ),
+        WebhookEvent::LoginAnomaly => (
+            "New sign-in to your account".to_string(),
+            format!(
+                "Your account was signed in to from {} ({}, {}).\n\n\
+                 If this was not you, change your password.",
+                field("user_agent"),
+                field("ip"),
+                field("country")
+            ),
+        ),
+        WebhookEvent::EmailChangeRequested => (
+            "Confirm your new email address".to_string(),
+            format!("Confirm this address with the token:\n\n{}", field("token")),
+        ),
+        WebhookEvent::EmailChangeWarning => (
+            "Your email address is being changed".to_string(),
+            format!(
+                "A change of your email address to {} was requested.\n\n\
+                 If this was not you, undo it with the token:\n\n{}",
+                field("new_email"),
+                field("token")
+            ),
+        ),
+        WebhookEvent::AccountDeletionScheduled => (
+            "Your account will be deleted".to_string(),
+            format!(
+                "Your account will be deleted after {}.\n\n\
+                 Cancel the deletion with the token:\n\n{}",
+                field("delete_after"),
+                field("token")
+            ),
+        ),
+        WebhookEvent::CredentialExpiring => (
+            format!("Your {} expires soon", credential),
+            format!("Your {} expires at {}.", credential, field("expires_at")),
+        ),
+        WebhookEvent::CredentialExpired => (
+            format!("Your {} expired", credential),
+            match credential {
+                "password" => "Change your password on the next sign-in.".to_string(),
+                _ => format!("The API key {} was revoked.", field("id")),
+            },
+        ),
+        _ => return None,
+    };
+
+    let to = field("email");
+    if to.is_empty() {
+        return None;
+    }
+
+    Some(Email {
+        id: Uuid::new_v4(),
+        event: event.as_str().to_string(),
+        to,
+        subject,
+        body,
+        sent_at: Utc::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if only events carrying mail are rendered
+    #[test]
+    fn test_render() {
+        let data = serde_json::json!({
+            "email": "ada@example.com",
+            "new_email": "ada@example.org",
+            "token": "undo-token",
+        });
+        let email = render(WebhookEvent::EmailChangeWarning, &data).unwrap();
+
+        assert_eq!(email.to, "ada@example.com");
+        assert_eq!(email.event, "email.change_warning");
+        assert!(email.body.contains("ada@example.org"));
+        assert!(email.body.contains("undo-token"));
+
+        let expired = serde_json::json!({
+            "email": "ada@example.com",
+            "credential": "api_key",
+            "id": Uuid::nil(),
+        });
+        let email = render(WebhookEvent::CredentialExpired, &expired).unwrap();
+        assert_eq!(email.subject, "Your API key expired");
+
+        assert!(render(WebhookEvent::UserCreated, &data).is_none());
+        assert!(render(WebhookEvent::MagicLinkRequested, &serde_json::json!({})).is_none());
+    }
+
+    // Test checks if the capture keeps the latest emails, newest first
+    #[tokio::test]
+    async fn test_capture_limit() {
+        let capture = MailCapture::default();
+        for index in 0..CAPTURE_LIMIT + 5 {
+            let data = serde_json::json!({ "email": format!("{}@example.com", index) });
+            let email = render(WebhookEvent::MagicLinkRequested, &data).unwrap();
+            capture.send(email).await.unwrap();
+        }
+
+        let emails = capture.list();
+        assert_eq!(emails.len(), CAPTURE_LIMIT);
+        assert_eq!(emails[0].to, format!("{}@example.com", CAPTURE_LIMIT + 4));
+
+        capture.clear();
+        assert!(capture.list().is_empty());
+    }
+}
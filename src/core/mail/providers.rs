@@ -0,0 +1,627 @@
+//! Mail providers module.
+//!
+//! Module implements `Mailer` for the providers of
+//! `mail.provider`: an SMTP server, the SendGrid v3 API
+//! and the Amazon SES v2 API. Credentials are read from
+//! the environment, `config::constraints` reports missing
+//! ones before the server starts. Every provider checks
+//! its connection and credentials for the readiness probe.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use lettre::{
+    message::header::ContentType, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{fmt, sync::Arc, time::Duration};
+use url::Url;
+
+// Local imports
+use super::{Email, Mailer};
+use crate::core::config::{MailProvider, MailSettings, SmtpSecurity};
+use crate::core::env::map::EnvMap;
+use crate::core::env::vars::RequiredEnvVar;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::types::secret::Secret;
+
+/// Time a provider may take to accept an email.
+pub const MAIL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// ## Creates the mailer of the configured provider.
+///
+/// ## Parameters
+/// - `settings`: `&MailSettings` - Provider and its server.
+/// - `env`: `&EnvMap` - Environment with the credentials.
+///
+/// ## Returns
+/// + `Result<Option<Arc<dyn Mailer>>, AppError>`
+///     - `Ok(Some(Arc<dyn Mailer>))`: Mailer of the provider.
+///     - `Ok(None)`: If mail leaves as webhook events.
+///     - `Err(AppError)`: If a credential is missing or the client
+///       can not be built.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::MailSettings;
+/// use axum_auth::core::env::map::EnvMap;
+/// use axum_auth::core::mail::providers::connect;
+///
+/// let mailer = connect(&MailSettings::default(), &EnvMap::default()).unwrap();
+///
+/// assert!(mailer.is_none());
+/// ```
+pub fn connect(settings: &MailSettings, env: &EnvMap) -> Result<Option<Arc<dyn Mailer>>, AppError> {
+    let secret = |var: RequiredEnvVar| var.value(env);
+
+    let mailer: Arc<dyn Mailer> = match settings.provider {
+        MailProvider::Webhooks => return Ok(None),
+        MailProvider::Smtp => {
+            let credentials = match (
+                secret(RequiredEnvVar::MailSmtpUser),
+                secret(RequiredEnvVar::MailSmtpPass),
+            ) {
+                (Ok(user), Ok(pass)) => Some((user, pass)),
+                _ => None,
+            };
+            Arc::new(SmtpMailer::new(settings, credentials)?)
+        }
+        MailProvider::Sendgrid => Arc::new(SendgridMailer::new(
+            &settings.sendgrid_url,
+            &settings.from,
+            secret(RequiredEnvVar::MailSendgridApiKey)?,
+        )?),
+        MailProvider::Ses => {
+            let url = match &settings.ses_url {
+                Some(url) => url.clone(),
+                None => format!("https://email.{}.amazonaws.com", settings.ses_region),
+            };
+            let credentials = SesCredentials {
+                access_key_id: secret(RequiredEnvVar::MailSesAccessKeyId)?.expose().clone(),
+                secret_access_key: secret(RequiredEnvVar::MailSesSecretAccessKey)?,
+            };
+            Arc::new(SesMailer::new(
+                &url,
+                &settings.ses_region,
+                &settings.from,
+                credentials,
+            )?)
+        }
+    };
+
+    Ok(Some(mailer))
+}
+
+/// ## Builds the HTTP client of an API provider.
+fn http_client(provider: &str) -> Result<reqwest::Client, AppError> {
+    reqwest::Client::builder()
+        .timeout(MAIL_TIMEOUT)
+        .user_agent(concat!("axum_auth/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                format!("Failed to build the {} client", provider),
+                Some(Box::new(e)),
+            )
+        })
+}
+
+/// ## Sends the request and fails on error statuses.
+async fn execute(request: reqwest::RequestBuilder, provider: &str) -> Result<(), AppError> {
+    let failed = |e: reqwest::Error| {
+        AppError::new(
+            ErrorKind::External,
+            format!("{} API failed", provider),
+            Some(Box::new(e)),
+        )
+    };
+
+    let response = request.send().await.map_err(failed)?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+
+    Err(AppError::external(format!(
+        "{} API responded with {}: {}",
+        provider,
+        status,
+        body.trim()
+    )))
+}
+
+/// ## SMTP mailer struct.
+///
+/// Connections are pooled by the transport and upgraded
+/// according to `mail.smtp_security`. The pool runs on the
+/// Tokio runtime, so the mailer is created inside of it.
+pub struct SmtpMailer {
+    from: String,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl fmt::Debug for SmtpMailer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SmtpMailer")
+            .field("from", &self.from)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SmtpMailer {
+    /// ## Creates the mailer of the SMTP server.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&MailSettings` - Server, port, security and sender.
+    /// - `credentials`: `Option<(Secret<String>, Secret<String>)>` - User
+    ///   and password, `None` for servers without authentication.
+    ///
+    /// ## Returns
+    /// + `Result<SmtpMailer, AppError>`
+    ///     - `Ok(SmtpMailer)`: New mailer, nothing is connected yet.
+    ///     - `Err(AppError)`: `Config` if the TLS settings of the host
+    ///       can not be built.
+    pub fn new(
+        settings: &MailSettings,
+        credentials: Option<(Secret<String>, Secret<String>)>,
+    ) -> Result<Self, AppError> {
+        let host = settings.smtp_host.as_str();
+        let builder = match settings.smtp_security {
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                host,
+            )),
+            SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+        }
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Config,
+                format!("Invalid SMTP server '{}'", host),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        let mut builder = builder.port(settings.smtp_port).timeout(Some(MAIL_TIMEOUT));
+        if let Some((user, pass)) = credentials {
+            builder = builder.credentials(Credentials::new(
+                user.expose().clone(),
+                pass.expose().clone(),
+            ));
+        }
+
+        Ok(SmtpMailer {
+            from: settings.from.clone(),
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let invalid = |e: &dyn fmt::Display| {
+            AppError::validation(format!("Email to '{}' is invalid: {}", email.to, e))
+        };
+
+        let message = Message::builder()
+            .from(self.from.parse().map_err(|e| invalid(&e))?)
+            .to(email.to.parse().map_err(|e| invalid(&e))?)
+            .subject(email.subject.clone())
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())
+            .map_err(|e| invalid(&e))?;
+
+        self.transport.send(message).await.map_err(|e| {
+            AppError::new(
+                ErrorKind::External,
+                "SMTP server rejected the email".to_string(),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<(), AppError> {
+        let connected = self.transport.test_connection().await.map_err(|e| {
+            AppError::new(
+                ErrorKind::External,
+                "SMTP server is not reachable".to_string(),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        match connected {
+            true => Ok(()),
+            false => Err(AppError::external("SMTP server did not accept NOOP")),
+        }
+    }
+}
+
+/// ## SendGrid mailer struct.
+///
+/// Emails are posted to `/v3/mail/send`, the readiness
+/// probe reads `/v3/scopes` to check the API key.
+#[derive(Debug)]
+pub struct SendgridMailer {
+    url: String,
+    from: String,
+    api_key: Secret<String>,
+    client: reqwest::Client,
+}
+
+impl SendgridMailer {
+    /// ## Creates the mailer of the SendGrid API.
+    ///
+    /// ## Parameters
+    /// - `url`: `&str` - Base URL of the API.
+    /// - `from`: `&str` - Sender address of the emails.
+    /// - `api_key`: `Secret<String>` - API key allowed to send mail.
+    ///
+    /// ## Returns
+    /// + `Result<SendgridMailer, AppError>`
+    ///     - `Ok(SendgridMailer)`: New mailer.
+    ///     - `Err(AppError)`: If the HTTP client can not be built.
+    pub fn new(url: &str, from: &str, api_key: Secret<String>) -> Result<Self, AppError> {
+        Ok(SendgridMailer {
+            url: url.trim_end_matches('/').to_string(),
+            from: from.to_string(),
+            api_key,
+            client: http_client("SendGrid")?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SendgridMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let body = json!({
+            "personalizations": [{ "to": [{ "email": email.to }] }],
+            "from": { "email": self.from },
+            "subject": email.subject,
+            "content": [{ "type": "text/plain", "value": email.body }],
+        });
+        let request = self
+            .client
+            .post(format!("{}/v3/mail/send", self.url))
+            .bearer_auth(self.api_key.expose())
+            .json(&body);
+
+        execute(request, "SendGrid").await
+    }
+
+    async fn check(&self) -> Result<(), AppError> {
+        let request = self
+            .client
+            .get(format!("{}/v3/scopes", self.url))
+            .bearer_auth(self.api_key.expose());
+
+        execute(request, "SendGrid").await
+    }
+}
+
+/// ## AWS credentials of SES struct.
+///
+/// ## Fields
+/// + `access_key_id`: `String` - ID of the access key.
+/// + `secret_access_key`: `Secret<String>` - Secret of the access key.
+#[derive(Debug, Clone)]
+pub struct SesCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: Secret<String>,
+}
+
+/// ## Amazon SES mailer struct.
+///
+/// Emails are sent with the `SendEmail` action of the v2
+/// API, the readiness probe calls `GetAccount`. Requests
+/// are signed with AWS Signature Version 4.
+#[derive(Debug)]
+pub struct SesMailer {
+    url: Url,
+    region: String,
+    from: String,
+    credentials: SesCredentials,
+    client: reqwest::Client,
+}
+
+impl SesMailer {
+    /// ## Creates the mailer of the SES API.
+    ///
+    /// ## Parameters
+    /// - `url`: `&str` - Base URL of the API of the region.
+    /// - `region`: `&str` - AWS region the requests are signed for.
+    /// - `from`: `&str` - Verified sender address of the emails.
+    /// - `credentials`: `SesCredentials` - Access key allowed to send mail.
+    ///
+    /// ## Returns
+    /// + `Result<SesMailer, AppError>`
+    ///     - `Ok(SesMailer)`: New mailer.
+    ///     - `Err(AppError)`: `Config` if the URL is invalid, or if the
+    ///       HTTP client can not be built.
+    pub fn new(
+        url: &str,
+        region: &str,
+        from: &str,
+        credentials: SesCredentials,
+    ) -> Result<Self, AppError> {
+        let url = Url::parse(url).map_err(|e| {
+            AppError::new(
+                ErrorKind::Config,
+                format!("Invalid SES URL '{}'", url),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        Ok(SesMailer {
+            url,
+            region: region.to_string(),
+            from: from.to_string(),
+            credentials,
+            client: http_client("SES")?,
+        })
+    }
+
+    /// ## Builds a signed request of the API.
+    fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Vec<u8>,
+    ) -> reqwest::RequestBuilder {
+        let mut url = self.url.clone();
+        url.set_path(path);
+        let signed = sign_v4(
+            &self.credentials,
+            &self.region,
+            "ses",
+            method.as_str(),
+            &url,
+            &body,
+            Utc::now(),
+        );
+
+        self.client
+            .request(method, url)
+            .header("x-amz-date", signed.amz_date)
+            .header("authorization", signed.authorization)
+            .header("content-type", "application/json")
+            .body(body)
+    }
+}
+
+#[async_trait]
+impl Mailer for SesMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let body = json!({
+            "FromEmailAddress": self.from,
+            "Destination": { "ToAddresses": [email.to] },
+            "Content": {
+                "Simple": {
+                    "Subject": { "Data": email.subject, "Charset": "UTF-8" },
+                    "Body": { "Text": { "Data": email.body, "Charset": "UTF-8" } },
+                },
+            },
+        });
+        let request = self.request(
+            reqwest::Method::POST,
+            "/v2/email/outbound-emails",
+            body.to_string().into_bytes(),
+        );
+
+        execute(request, "SES").await
+    }
+
+    async fn check(&self) -> Result<(), AppError> {
+        let request = self.request(reqwest::Method::GET, "/v2/email/account", Vec::new());
+
+        execute(request, "SES").await
+    }
+}
+
+/// ## Signature of a request struct.
+///
+/// ## Fields
+/// + `amz_date`: `String` - Value of the `x-amz-date` header.
+/// + `authorization`: `String` - Value of the `authorization` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedRequest {
+    pub amz_date: String,
+    pub authorization: String,
+}
+
+/// ## Signs a request with AWS Signature Version 4.
+///
+/// Only `host` and `x-amz-date` are signed, which is all
+/// the SES API requires.
+///
+/// ## Parameters
+/// - `credentials`: `&SesCredentials` - Access key signing the request.
+/// - `region`: `&str` - AWS region of the API.
+/// - `service`: `&str` - Signing name of the service, e.g. `ses`.
+/// - `method`: `&str` - HTTP method of the request.
+/// - `url`: `&Url` - URL of the request.
+/// - `body`: `&[u8]` - Body of the request.
+/// - `now`: `DateTime<Utc>` - Time of the request.
+///
+/// ## Returns
+/// - `SignedRequest`: Headers carrying the signature.
+pub fn sign_v4(
+    credentials: &SesCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    url: &Url,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> SignedRequest {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (encode(&key), encode(&value)))
+        .collect();
+    query.sort();
+    let query = query
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let signed_headers = "host;x-amz-date";
+    let canonical_request = format!(
+        "{}\n{}\n{}\nhost:{}\nx-amz-date:{}\n\n{}\n{}",
+        method,
+        url.path(),
+        query,
+        host,
+        amz_date,
+        signed_headers,
+        hex::encode(Sha256::digest(body))
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let secret = format!("AWS4{}", credentials.secret_access_key.expose());
+    let key = [date.as_str(), region, service, "aws4_request"]
+        .iter()
+        .fold(secret.into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+    let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+    SignedRequest {
+        amz_date,
+        authorization: format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    }
+}
+
+/// ## Returns the HMAC-SHA256 of the data.
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// ## Percent-encodes everything but the unreserved characters.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+
+    // Test checks if the signature matches the get-vanilla case of the AWS test suite
+    #[test]
+    fn test_sign_v4() {
+        let credentials = SesCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: Secret::new("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string()),
+        };
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+
+        let signed = sign_v4(&credentials, "us-east-1", "service", "GET", &url, b"", now);
+
+        assert_eq!(signed.amz_date, "20150830T123600Z");
+        assert_eq!(
+            signed.authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    // Test checks if SendGrid receives the email with the API key
+    #[tokio::test]
+    async fn test_sendgrid_send() {
+        let received = Arc::new(Mutex::new(None));
+        let store = received.clone();
+        let app = Router::new().route(
+            "/v3/mail/send",
+            post(
+                move |headers: HeaderMap, Json(body): Json<serde_json::Value>| {
+                    let store = store.clone();
+                    async move {
+                        let auth = headers["authorization"].to_str().unwrap().to_string();
+                        *store.lock().unwrap() = Some((auth, body));
+                        axum::http::StatusCode::ACCEPTED
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mailer = SendgridMailer::new(
+            &format!("http://{}/", addr),
+            "no-reply@example.com",
+            Secret::new("SG.key".to_string()),
+        )
+        .unwrap();
+        let email = super::super::render(
+            crate::core::webhooks::WebhookEvent::MagicLinkRequested,
+            &json!({ "email": "ada@example.com", "link": "https://example.com/link" }),
+        )
+        .unwrap();
+        mailer.send(email).await.unwrap();
+
+        let (auth, body) = received.lock().unwrap().take().unwrap();
+        assert_eq!(auth, "Bearer SG.key");
+        assert_eq!(
+            body["personalizations"][0]["to"][0]["email"],
+            "ada@example.com"
+        );
+        assert_eq!(body["subject"], "Your sign-in link");
+
+        // Scopes are not served, the check fails
+        assert!(mailer.check().await.is_err());
+    }
+
+    // Test checks if providers other than webhooks need their credentials
+    #[tokio::test]
+    async fn test_connect() {
+        let env = EnvMap::default().prefixed("AXA_");
+        let settings = MailSettings {
+            provider: MailProvider::Sendgrid,
+            ..MailSettings::default()
+        };
+        assert!(connect(&settings, &env).is_err());
+
+        let settings = MailSettings {
+            provider: MailProvider::Smtp,
+            ..MailSettings::default()
+        };
+        assert!(connect(&settings, &env).unwrap().is_some());
+    }
+}
@@ -0,0 +1,424 @@
+//! Mail templates module.
+//!
+//! Registry renders the emails from the handlebars templates
+//! of `mail.templates_dir`. Every locale has a directory with
+//! a subject and a body template per kind of email:
+//!
+//! ```text
+//! templates/mail/
+//!   en-US/magic_link.subject.hbs
+//!   en-US/magic_link.body.hbs
+//!   de/magic_link.subject.hbs
+//!   de/magic_link.body.hbs
+//! ```
+//!
+//! Templates see the data of the event and its name as
+//! `event`. The locale is taken from the `locale` of the
+//! data, falling back to its language, the default locale
+//! and its language. Emails without a template keep the
+//! built-in texts of `render`. Emails are plain text, so
+//! values are not HTML escaped.
+
+// Imports from external crates
+use handlebars::{no_escape, Handlebars};
+use serde_json::Value as Json;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+// Local imports
+use super::{render, Email};
+use crate::core::config::MailSettings;
+use crate::core::err::AppError;
+use crate::core::webhooks::WebhookEvent;
+
+/// ## Kind of a templated email.
+///
+/// # Variants
+/// - `Verification` - `verification`, confirms a new email address.
+/// - `Reset` - `reset`, password has to be changed on the next sign-in.
+/// - `MagicLink` - `magic_link`, sign-in link.
+/// - `Alert` - `alert`, every other notice about the account, the
+///   templates tell them apart by `event`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    Verification,
+    Reset,
+    MagicLink,
+    Alert,
+}
+
+impl Template {
+    /// Every template.
+    pub const ALL: [Template; 4] = [
+        Template::Verification,
+        Template::Reset,
+        Template::MagicLink,
+        Template::Alert,
+    ];
+
+    /// ## Returns the name of the template files.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Template::Verification => "verification",
+            Template::Reset => "reset",
+            Template::MagicLink => "magic_link",
+            Template::Alert => "alert",
+        }
+    }
+
+    /// ## Returns the template of the email an event stands for.
+    ///
+    /// ## Parameters
+    /// - `event`: `WebhookEvent` - Kind of the event.
+    /// - `data`: `&serde_json::Value` - Data of the event.
+    ///
+    /// ## Returns
+    /// - `Option<Template>`: Template of the email, `None` if the
+    ///   event does not carry mail.
+    pub fn for_event(event: WebhookEvent, data: &Json) -> Option<Template> {
+        match event {
+            WebhookEvent::EmailChangeRequested => Some(Template::Verification),
+            WebhookEvent::MagicLinkRequested => Some(Template::MagicLink),
+            WebhookEvent::CredentialExpired
+                if data.get("credential").and_then(Json::as_str) == Some("password") =>
+            {
+                Some(Template::Reset)
+            }
+            WebhookEvent::LoginAnomaly
+            | WebhookEvent::EmailChangeWarning
+            | WebhookEvent::AccountDeletionScheduled
+            | WebhookEvent::CredentialExpiring
+            | WebhookEvent::CredentialExpired => Some(Template::Alert),
+            _ => None,
+        }
+    }
+}
+
+/// ## Mail template registry struct.
+///
+/// ## Fields
+/// + `dir`: `PathBuf` - Directory of the templates.
+/// + `default_locale`: `String` - Locale of emails without a locale.
+/// + `hot_reload`: `bool` - Read the templates again for every email.
+/// + `registry`: `RwLock<Arc<Handlebars>>` - Compiled templates.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::MailSettings;
+/// use axum_auth::core::mail::templates::MailTemplates;
+/// use axum_auth::core::webhooks::WebhookEvent;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// std::fs::create_dir(dir.path().join("en-US")).unwrap();
+/// std::fs::write(dir.path().join("en-US/magic_link.subject.hbs"), "Hi {{name}}").unwrap();
+/// std::fs::write(dir.path().join("en-US/magic_link.body.hbs"), "{{link}}").unwrap();
+///
+/// let settings = MailSettings {
+///     enabled: true,
+///     templates_dir: dir.path().to_string_lossy().to_string(),
+///     ..MailSettings::default()
+/// };
+/// let templates = MailTemplates::load(&settings, false).unwrap();
+/// let data = serde_json::json!({
+///     "email": "ada@example.com",
+///     "name": "Ada",
+///     "link": "https://example.com/verify?token=abc",
+/// });
+/// let email = templates.render(WebhookEvent::MagicLinkRequested, &data).unwrap();
+///
+/// assert_eq!(email.subject, "Hi Ada");
+/// assert_eq!(email.body, "https://example.com/verify?token=abc");
+/// ```
+#[derive(Debug)]
+pub struct MailTemplates {
+    dir: PathBuf,
+    default_locale: String,
+    hot_reload: bool,
+    registry: RwLock<Arc<Handlebars<'static>>>,
+}
+
+impl MailTemplates {
+    /// ## Loads the templates of the directory.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&MailSettings` - Directory and default locale.
+    /// - `hot_reload`: `bool` - Read the templates again for every
+    ///   email, meant for development.
+    ///
+    /// ## Returns
+    /// + `Result<MailTemplates, AppError>`
+    ///     - `Ok(MailTemplates)`: Registry with every template compiled.
+    ///     - `Err(AppError)`: `Config` if the directory can not be read,
+    ///       a file is not a known template or does not compile.
+    pub fn load(settings: &MailSettings, hot_reload: bool) -> Result<MailTemplates, AppError> {
+        let dir = PathBuf::from(&settings.templates_dir);
+        let registry = compile(&dir)?;
+
+        Ok(MailTemplates {
+            dir,
+            default_locale: settings.default_locale.clone(),
+            hot_reload,
+            registry: RwLock::new(Arc::new(registry)),
+        })
+    }
+
+    /// ## Returns the compiled templates.
+    ///
+    /// With hot reload the directory is compiled again, a
+    /// broken edit keeps the last working templates.
+    fn registry(&self) -> Arc<Handlebars<'static>> {
+        if self.hot_reload {
+            match compile(&self.dir) {
+                Ok(registry) => {
+                    let registry = Arc::new(registry);
+                    *self.registry.write().unwrap_or_else(|e| e.into_inner()) = registry.clone();
+                    return registry;
+                }
+                Err(e) => tracing::warn!("Failed to reload mail templates: {}", e),
+            }
+        }
+
+        self.registry
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// ## Returns the locales tried for the data, in order.
+    fn locales(&self, data: &Json) -> Vec<String> {
+        let requested = data.get("locale").and_then(Json::as_str);
+        let mut locales: Vec<String> = Vec::new();
+        for locale in requested.into_iter().chain([self.default_locale.as_str()]) {
+            let language = locale.split(['-', '_']).next().unwrap_or(locale);
+            for candidate in [locale, language] {
+                if !candidate.is_empty() && !locales.iter().any(|l| l == candidate) {
+                    locales.push(candidate.to_string());
+                }
+            }
+        }
+
+        locales
+    }
+
+    /// ## Renders a webhook event into the email it stands for.
+    ///
+    /// Emails without a template of any tried locale, or whose
+    /// template fails to render, keep the built-in texts.
+    ///
+    /// ## Parameters
+    /// - `event`: `WebhookEvent` - Kind of the event.
+    /// - `data`: `&serde_json::Value` - Data of the event.
+    ///
+    /// ## Returns
+    /// - `Option<Email>`: Email to the `email` of the data, `None` if the
+    ///   event does not carry mail or the data has no address.
+    pub fn render(&self, event: WebhookEvent, data: &Json) -> Option<Email> {
+        let mut email = render(event, data)?;
+        let Some(template) = Template::for_event(event, data) else {
+            return Some(email);
+        };
+
+        let mut context = data.clone();
+        if let Json::Object(fields) = &mut context {
+            fields.insert("event".to_string(), Json::from(event.as_str()));
+        }
+        let registry = self.registry();
+        for locale in self.locales(data) {
+            let subject = format!("{}/{}.subject", locale, template.as_str());
+            let body = format!("{}/{}.body", locale, template.as_str());
+            if !registry.has_template(&subject) || !registry.has_template(&body) {
+                continue;
+            }
+
+            match (
+                registry.render(&subject, &context),
+                registry.render(&body, &context),
+            ) {
+                (Ok(subject), Ok(body)) => {
+                    email.subject = subject.split_whitespace().collect::<Vec<_>>().join(" ");
+                    email.body = body;
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    tracing::warn!("Failed to render mail template '{}': {}", subject, e)
+                }
+            }
+            break;
+        }
+
+        Some(email)
+    }
+}
+
+/// ## Compiles every template of the directory.
+fn compile(dir: &Path) -> Result<Handlebars<'static>, AppError> {
+    let unreadable = |path: &Path, e: std::io::Error| {
+        AppError::config(format!(
+            "Failed to read mail templates '{}': {}",
+            path.display(),
+            e
+        ))
+    };
+
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(no_escape);
+
+    for locale in fs::read_dir(dir).map_err(|e| unreadable(dir, e))? {
+        let locale = locale.map_err(|e| unreadable(dir, e))?.path();
+        let Some(tag) = visible_name(&locale) else {
+            continue;
+        };
+        if !locale.is_dir() {
+            continue;
+        }
+
+        for file in fs::read_dir(&locale).map_err(|e| unreadable(&locale, e))? {
+            let file = file.map_err(|e| unreadable(&locale, e))?.path();
+            let Some(name) = visible_name(&file) else {
+                continue;
+            };
+            let Some(name) = name.strip_suffix(".hbs") else {
+                continue;
+            };
+            let known = name.rsplit_once('.').is_some_and(|(template, part)| {
+                Template::ALL.iter().any(|t| t.as_str() == template)
+                    && matches!(part, "subject" | "body")
+            });
+            if !known {
+                return Err(AppError::config(format!(
+                    "Unknown mail template '{}', expected <template>.subject.hbs or \
+                     <template>.body.hbs with a template of: {}",
+                    file.display(),
+                    Template::ALL.map(|t| t.as_str()).join(", ")
+                )));
+            }
+
+            let source = fs::read_to_string(&file).map_err(|e| unreadable(&file, e))?;
+            registry
+                .register_template_string(&format!("{}/{}", tag, name), source)
+                .map_err(|e| {
+                    AppError::config(format!(
+                        "Failed to compile mail template '{}': {}",
+                        file.display(),
+                        e
+                    ))
+                })?;
+        }
+    }
+
+    Ok(registry)
+}
+
+/// ## Returns the file name, `None` for hidden files.
+fn visible_name(path: &Path) -> Option<&str> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| !name.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(dir: &Path) -> MailSettings {
+        MailSettings {
+            enabled: true,
+            templates_dir: dir.to_string_lossy().to_string(),
+            ..MailSettings::default()
+        }
+    }
+
+    fn write(dir: &Path, file: &str, source: &str) {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+
+    // Test checks if the locale falls back to its language and the default locale
+    #[test]
+    fn test_render_locales() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "en-US/alert.subject.hbs", "Notice\n");
+        write(
+            dir.path(),
+            "en-US/alert.body.hbs",
+            "{{#if (eq event \"login.anomaly\")}}Signed in from {{ip}}{{/if}}",
+        );
+        write(dir.path(), "de/alert.subject.hbs", "Hinweis");
+        write(
+            dir.path(),
+            "de/alert.body.hbs",
+            "Anmeldung von {{ip}} <{{email}}>",
+        );
+        let templates = MailTemplates::load(&settings(dir.path()), false).unwrap();
+
+        let mut data = serde_json::json!({ "email": "ada@example.com", "ip": "192.0.2.1" });
+        let email = templates.render(WebhookEvent::LoginAnomaly, &data).unwrap();
+        assert_eq!(email.subject, "Notice");
+        assert_eq!(email.body, "Signed in from 192.0.2.1");
+
+        data["locale"] = Json::from("de-AT");
+        let email = templates.render(WebhookEvent::LoginAnomaly, &data).unwrap();
+        assert_eq!(email.subject, "Hinweis");
+        assert_eq!(email.body, "Anmeldung von 192.0.2.1 <ada@example.com>");
+
+        // No template, the built-in text is kept
+        let email = templates
+            .render(WebhookEvent::MagicLinkRequested, &data)
+            .unwrap();
+        assert_eq!(email.subject, "Your sign-in link");
+        assert!(templates.render(WebhookEvent::UserCreated, &data).is_none());
+    }
+
+    // Test checks if unknown files are rejected and hot reload picks up edits
+    #[test]
+    fn test_load_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "en-US/welcome.subject.hbs", "Hi");
+        assert!(MailTemplates::load(&settings(dir.path()), false).is_err());
+        fs::remove_file(dir.path().join("en-US/welcome.subject.hbs")).unwrap();
+
+        write(
+            dir.path(),
+            "en-US/reset.subject.hbs",
+            "Change your password",
+        );
+        write(dir.path(), "en-US/reset.body.hbs", "{{#if}}");
+        assert!(MailTemplates::load(&settings(dir.path()), false).is_err());
+
+        write(dir.path(), "en-US/reset.body.hbs", "First");
+        let templates = MailTemplates::load(&settings(dir.path()), true).unwrap();
+        let data = serde_json::json!({ "email": "ada@example.com", "credential": "password" });
+        let email = templates.render(WebhookEvent::CredentialExpired, &data);
+        assert_eq!(email.unwrap().body, "First");
+
+        write(dir.path(), "en-US/reset.body.hbs", "Second");
+        let email = templates.render(WebhookEvent::CredentialExpired, &data);
+        assert_eq!(email.unwrap().body, "Second");
+
+        // Broken edit keeps the last working templates
+        write(dir.path(), "en-US/reset.body.hbs", "{{#if}}");
+        let email = templates.render(WebhookEvent::CredentialExpired, &data);
+        assert_eq!(email.unwrap().body, "Second");
+    }
+
+    // Test checks if the shipped templates compile and cover every template
+    #[test]
+    fn test_shipped_templates() {
+        let settings = MailSettings {
+            enabled: true,
+            ..MailSettings::default()
+        };
+        let templates = MailTemplates::load(&settings, false).unwrap();
+        let registry = templates.registry();
+
+        for template in Template::ALL {
+            for part in ["subject", "body"] {
+                let name = format!("en-US/{}.{}", template.as_str(), part);
+                assert!(registry.has_template(&name), "{}", name);
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Metrics module.
+//!
+//! Module installs the Prometheus recorder behind the
+//! `metrics` macros. Without the recorder the macros do
+//! nothing, so modules record their metrics unconditionally
+//! and only the exposition depends on the configuration.
+
+// Imports from external crates
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use once_cell::sync::OnceCell;
+
+// Local imports
+use super::err::{AppError, ErrorKind};
+
+/// Handle of the installed recorder.
+static HANDLE: OnceCell<PrometheusHandle> = OnceCell::new();
+
+/// ## Installs the Prometheus recorder.
+///
+/// Recorder is global, later calls return the handle of
+/// the recorder installed by the first call.
+///
+/// ## Returns
+/// + `Result<PrometheusHandle, AppError>`
+///     - `Ok(PrometheusHandle)`: Handle rendering the metrics.
+///     - `Err(AppError)`: If another recorder is already installed.
+pub fn install() -> Result<PrometheusHandle, AppError> {
+    HANDLE
+        .get_or_try_init(|| {
+            PrometheusBuilder::new().install_recorder().map_err(|e| {
+                AppError::new(
+                    ErrorKind::Internal,
+                    "Failed to install the metrics recorder".to_string(),
+                    Some(Box::new(e)),
+                )
+            })
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if the recorder is installed once and renders recorded metrics.
+    #[test]
+    fn test_install() {
+        let handle = install().unwrap();
+        metrics::counter!("test_install_total").increment(2);
+
+        assert!(install().is_ok());
+        assert!(handle.render().contains("test_install_total 2"));
+    }
+}
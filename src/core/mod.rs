@@ -1,4 +1,46 @@
+pub mod auth;
+pub mod build;
 pub mod config;
 pub mod env;
 pub mod err;
+pub mod http;
+pub mod logging;
 pub mod types;
+pub mod worker;
+
+#[cfg(feature = "server")]
+pub mod audit;
+#[cfg(feature = "postgres")]
+pub mod bootstrap;
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "postgres")]
+pub mod db;
+#[cfg(feature = "server")]
+pub mod events;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "server")]
+pub mod health;
+#[cfg(feature = "server")]
+pub mod mail;
+#[cfg(feature = "server")]
+pub mod metrics;
+#[cfg(feature = "server")]
+pub mod panic;
+#[cfg(feature = "server")]
+pub mod queue;
+#[cfg(feature = "server")]
+pub mod repo;
+#[cfg(feature = "sentry")]
+pub mod sentry;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "server")]
+pub mod state;
+#[cfg(feature = "server")]
+pub mod tenancy;
+#[cfg(feature = "server")]
+pub mod users;
+#[cfg(feature = "server")]
+pub mod webhooks;
@@ -0,0 +1,229 @@
+//! Panic module.
+//!
+//! `install` replaces the default panic hook, which only
+//! prints to stderr, with one logging the panic through
+//! tracing: with its location, thread, backtrace and the
+//! ID of the request it interrupted. Events are logged in
+//! the span of the request, so its method and path are
+//! part of the log line. `catch_panic` turns panicking
+//! handlers into 500 responses instead of dropped
+//! connections. With Sentry enabled, reports are also
+//! forwarded to the client of `core::sentry`.
+
+// Imports from external crates
+use axum::response::{IntoResponse, Response};
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    fmt,
+    panic::{self, PanicHookInfo},
+    sync::Arc,
+    thread,
+};
+use tower_http::catch_panic::CatchPanicLayer;
+
+// Local imports
+use super::config::CrashReportSettings;
+use super::err::AppError;
+use super::http::request_id::current_request_id;
+
+/// ## Crash reporter trait.
+///
+/// Reporter is called from the panic hook, so it must not
+/// panic. A panic may end the process once the hook
+/// returns, e.g. on the main thread or with
+/// `panic = abort`, so the report is sent before `report`
+/// returns, waiting no longer than a bounded timeout.
+pub trait CrashReporter: Send + Sync + fmt::Debug {
+    /// Reports the panic.
+    fn report(&self, report: &PanicReport);
+}
+
+/// ## Panic report struct.
+///
+/// ## Fields
+/// + `message`: `String` - Message the code panicked with.
+/// + `location`: `Option<String>` - Source file, line and column.
+/// + `thread`: `String` - Name of the panicking thread.
+/// + `request_id`: `Option<String>` - ID of the interrupted request.
+/// + `backtrace`: `Option<String>` - Backtrace, when enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicReport {
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: String,
+    pub request_id: Option<String>,
+    pub backtrace: Option<String>,
+}
+
+impl PanicReport {
+    /// ## Creates the report of a panic.
+    ///
+    /// ## Parameters
+    /// - `info`: `&PanicHookInfo` - Panic passed to the hook.
+    /// - `backtrace`: `bool` - If the backtrace is captured.
+    pub fn new(info: &PanicHookInfo<'_>, backtrace: bool) -> Self {
+        PanicReport {
+            message: payload_message(info.payload()),
+            location: info.location().map(|location| {
+                format!(
+                    "{}:{}:{}",
+                    location.file(),
+                    location.line(),
+                    location.column()
+                )
+            }),
+            thread: thread::current().name().unwrap_or("unnamed").to_string(),
+            request_id: current_request_id(),
+            backtrace: backtrace.then(|| Backtrace::force_capture().to_string()),
+        }
+    }
+}
+
+/// ## Installs the panic hook.
+///
+/// ## Parameters
+/// - `settings`: `&CrashReportSettings` - Crash report settings.
+/// - `reporter`: `Option<Arc<dyn CrashReporter>>` - Reporter the panics
+///   are forwarded to.
+pub fn install(settings: &CrashReportSettings, reporter: Option<Arc<dyn CrashReporter>>) {
+    panic::set_hook(Box::new(hook(settings.backtrace, reporter)));
+}
+
+/// ## Returns the panic hook.
+///
+/// ## Parameters
+/// - `backtrace`: `bool` - If panics are logged with their backtrace.
+/// - `reporter`: `Option<Arc<dyn CrashReporter>>` - Reporter the panics
+///   are forwarded to.
+pub fn hook(
+    backtrace: bool,
+    reporter: Option<Arc<dyn CrashReporter>>,
+) -> impl Fn(&PanicHookInfo<'_>) + Send + Sync + 'static {
+    move |info| {
+        let report = PanicReport::new(info, backtrace);
+        let location = report.location.as_deref().unwrap_or("unknown");
+        let request_id = report.request_id.as_deref().unwrap_or("none");
+
+        match &report.backtrace {
+            Some(backtrace) => tracing::error!(
+                thread = %report.thread,
+                %location,
+                %request_id,
+                %backtrace,
+                "Panicked: {}",
+                report.message
+            ),
+            None => tracing::error!(
+                thread = %report.thread,
+                %location,
+                %request_id,
+                "Panicked: {}",
+                report.message
+            ),
+        }
+
+        if let Some(reporter) = &reporter {
+            reporter.report(&report);
+        }
+    }
+}
+
+/// ## Returns the layer turning panics of handlers into 500 responses.
+///
+/// Response is the one of an internal `AppError`, so it has
+/// the error format and the request ID of other errors.
+///
+/// ## Examples
+/// ```
+/// use axum::{routing::get, Router};
+/// use axum_auth::core::panic::catch_panic;
+///
+/// let router: Router = Router::new()
+///     .route("/", get(|| async { "ok" }))
+///     .layer(catch_panic());
+/// ```
+pub fn catch_panic() -> CatchPanicLayer<fn(Box<dyn Any + Send + 'static>) -> Response> {
+    CatchPanicLayer::custom(panic_response as fn(Box<dyn Any + Send + 'static>) -> Response)
+}
+
+/// ## Builds the response of a panicked handler.
+fn panic_response(_: Box<dyn Any + Send + 'static>) -> Response {
+    // The hook has logged the message already
+    AppError::internal("Request handler panicked").into_response()
+}
+
+/// ## Returns the message of a panic payload.
+///
+/// ## Parameters
+/// - `payload`: `&(dyn Any + Send)` - Payload of the panic.
+///
+/// ## Returns
+/// - `String`: Message of `panic!` calls, a placeholder for
+///   payloads of `panic_any`.
+pub fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    match payload.downcast_ref::<String>() {
+        Some(message) => message.clone(),
+        None => "Box<dyn Any>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::http::request_id::{request_id, REQUEST_ID_HEADER};
+    use axum::{body::Body, extract::Request, http::StatusCode, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    // Test checks if messages of string and formatted panics are read
+    #[test]
+    fn test_payload_message() {
+        let payload = panic::catch_unwind(|| panic!("static message")).unwrap_err();
+        assert_eq!(payload_message(payload.as_ref()), "static message");
+
+        let payload = panic::catch_unwind(|| panic!("user {}", 42)).unwrap_err();
+        assert_eq!(payload_message(payload.as_ref()), "user 42");
+
+        let payload = panic::catch_unwind(|| panic::panic_any(42)).unwrap_err();
+        assert_eq!(payload_message(payload.as_ref()), "Box<dyn Any>");
+    }
+
+    // Test checks if a panicking handler answers with a 500 error carrying the request ID
+    #[tokio::test]
+    async fn test_catch_panic() {
+        let router = Router::new()
+            .route(
+                "/panic",
+                get(|| async {
+                    if true {
+                        panic!("handler failed");
+                    }
+                    "ok"
+                }),
+            )
+            .layer(catch_panic())
+            .layer(middleware::from_fn(request_id));
+
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .uri("/panic")
+                    .header(&REQUEST_ID_HEADER, "panic-1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["request_id"], "panic-1");
+        assert!(!body.to_string().contains("handler failed"));
+    }
+}
@@ -0,0 +1,411 @@
+//! Notification queue module.
+//!
+//! Module holds outbound notifications between the code
+//! that raises them and the sender that talks to the slow
+//! external service, e.g. an SMTP server or a webhook
+//! endpoint. Queues are bounded, once full the overflow
+//! policy decides whether the oldest message is dropped,
+//! the producer waits or the message is spilled to the
+//! database. The depth of every queue is exported as a
+//! metric.
+
+// Imports from external crates
+use async_trait::async_trait;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::sync::Notify;
+
+// Local imports
+use super::config::{OverflowPolicy, QueueSettings};
+use super::err::{AppError, ErrorKind};
+
+/// Gauge of the queued messages, labelled by `queue`.
+pub const DEPTH_METRIC: &str = "notification_queue_depth";
+
+/// Counter of the messages that did not fit, labelled by `queue` and `policy`.
+pub const OVERFLOW_METRIC: &str = "notification_queue_overflow_total";
+
+/// ## Spill target trait.
+///
+/// Stores messages that did not fit into the queue,
+/// usually in a database table a worker job reads.
+#[async_trait]
+pub trait Spill<T>: Send + Sync {
+    /// Stores the message outside of the queue.
+    async fn spill(&self, message: T) -> Result<(), AppError>;
+}
+
+/// ## Outcome of a push.
+///
+/// # Variants
+/// - `Queued` - Message was queued.
+/// - `DroppedOldest` - Message was queued, the oldest message was dropped.
+/// - `Spilled` - Message was handed to the spill target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pushed {
+    Queued,
+    DroppedOldest,
+    Spilled,
+}
+
+/// ## Shared state of a queue.
+struct Inner<T> {
+    name: &'static str,
+    settings: QueueSettings,
+    messages: Mutex<State<T>>,
+    not_empty: Notify,
+    not_full: Notify,
+    spill: Option<Arc<dyn Spill<T>>>,
+}
+
+/// ## Messages and the closed flag of a queue.
+struct State<T> {
+    messages: VecDeque<T>,
+    closed: bool,
+}
+
+/// ## Bounded notification queue struct.
+///
+/// Clones share the same queue. Any number of producers
+/// push messages, senders pop them until the queue is
+/// closed and empty.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{OverflowPolicy, QueueSettings};
+/// use axum_auth::core::queue::{NotificationQueue, Pushed};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let settings = QueueSettings {
+///     capacity: 1,
+///     overflow: OverflowPolicy::DropOldest,
+/// };
+/// let queue = NotificationQueue::new("mail", &settings, None);
+///
+/// assert_eq!(queue.push("first").await.unwrap(), Pushed::Queued);
+/// assert_eq!(queue.push("second").await.unwrap(), Pushed::DroppedOldest);
+/// assert_eq!(queue.pop().await, Some("second"));
+/// # });
+/// ```
+pub struct NotificationQueue<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for NotificationQueue<T> {
+    fn clone(&self) -> Self {
+        NotificationQueue {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Send + 'static> std::fmt::Debug for NotificationQueue<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NotificationQueue")
+            .field("name", &self.inner.name)
+            .field("settings", &self.inner.settings)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> NotificationQueue<T> {
+    /// ## Creates a new `NotificationQueue` instance.
+    ///
+    /// ## Parameters
+    /// - `name`: `&'static str` - Name of the queue, the `queue` label of the metrics.
+    /// - `settings`: `&QueueSettings` - Capacity and overflow policy.
+    /// - `spill`: `Option<Arc<dyn Spill<T>>>` - Target of the `spill` policy.
+    pub fn new(
+        name: &'static str,
+        settings: &QueueSettings,
+        spill: Option<Arc<dyn Spill<T>>>,
+    ) -> Self {
+        NotificationQueue {
+            inner: Arc::new(Inner {
+                name,
+                settings: settings.clone(),
+                messages: Mutex::new(State {
+                    messages: VecDeque::new(),
+                    closed: false,
+                }),
+                not_empty: Notify::new(),
+                not_full: Notify::new(),
+                spill,
+            }),
+        }
+    }
+
+    /// ## Returns the number of queued messages.
+    pub fn len(&self) -> usize {
+        self.lock().messages.len()
+    }
+
+    /// ## Checks if no message is queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ## Pushes a message.
+    ///
+    /// With the `block` policy the call waits until a
+    /// sender made room. Messages pushed after the queue
+    /// was closed are spilled when a spill target is set.
+    ///
+    /// ## Returns
+    /// + `Result<Pushed, AppError>`
+    ///     - `Ok(Pushed)`: Where the message went.
+    ///     - `Err(AppError)`: If the message had to be spilled and
+    ///       there is no spill target or it failed.
+    pub async fn push(&self, message: T) -> Result<Pushed, AppError> {
+        let mut message = message;
+
+        loop {
+            let full = self.inner.not_full.notified();
+            tokio::pin!(full);
+            full.as_mut().enable();
+
+            message = match self.try_push(message) {
+                Ok(pushed) => return Ok(pushed),
+                Err(message) => message,
+            };
+
+            let closed = self.lock().closed;
+            if closed || self.inner.settings.overflow == OverflowPolicy::Spill {
+                return self.spill(message).await;
+            }
+
+            full.await;
+        }
+    }
+
+    /// ## Pushes the message if the policy allows it without waiting.
+    fn try_push(&self, message: T) -> Result<Pushed, T> {
+        let settings = &self.inner.settings;
+        let mut state = self.lock();
+
+        if state.closed {
+            return Err(message);
+        }
+
+        let pushed = match state.messages.len() < settings.capacity {
+            true => Pushed::Queued,
+            false if settings.overflow == OverflowPolicy::DropOldest && settings.capacity > 0 => {
+                state.messages.pop_front();
+                self.overflowed();
+                Pushed::DroppedOldest
+            }
+            false => return Err(message),
+        };
+        state.messages.push_back(message);
+        self.record_depth(state.messages.len());
+        drop(state);
+
+        self.inner.not_empty.notify_one();
+
+        Ok(pushed)
+    }
+
+    /// ## Hands the message to the spill target.
+    async fn spill(&self, message: T) -> Result<Pushed, AppError> {
+        let Some(spill) = self.inner.spill.clone() else {
+            return Err(AppError::new(
+                ErrorKind::Internal,
+                format!(
+                    "Queue '{}' is full and has no spill target",
+                    self.inner.name
+                ),
+                None,
+            ));
+        };
+
+        self.overflowed();
+        spill.spill(message).await?;
+
+        Ok(Pushed::Spilled)
+    }
+
+    /// ## Pops the oldest message.
+    ///
+    /// ## Returns
+    /// - `Option<T>`: Oldest message, waits while the queue is
+    ///   empty. `None` once the queue is closed and empty.
+    pub async fn pop(&self) -> Option<T> {
+        loop {
+            let filled = self.inner.not_empty.notified();
+            tokio::pin!(filled);
+            filled.as_mut().enable();
+
+            {
+                let mut state = self.lock();
+                if let Some(message) = state.messages.pop_front() {
+                    self.record_depth(state.messages.len());
+                    drop(state);
+
+                    self.inner.not_full.notify_one();
+                    return Some(message);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+
+            filled.await;
+        }
+    }
+
+    /// ## Closes the queue and returns the queued messages.
+    ///
+    /// Senders waiting in `pop` return `None`, producers
+    /// waiting in `push` spill their message.
+    pub fn close(&self) -> Vec<T> {
+        let mut state = self.lock();
+        state.closed = true;
+        let messages = state.messages.drain(..).collect();
+        self.record_depth(0);
+        drop(state);
+
+        self.inner.not_empty.notify_waiters();
+        self.inner.not_full.notify_waiters();
+
+        messages
+    }
+
+    /// ## Locks the state, a poisoned lock is recovered.
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.inner
+            .messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// ## Records the depth of the queue.
+    fn record_depth(&self, depth: usize) {
+        metrics::gauge!(DEPTH_METRIC, "queue" => self.inner.name).set(depth as f64);
+    }
+
+    /// ## Counts a message that did not fit.
+    fn overflowed(&self) {
+        metrics::counter!(
+            OVERFLOW_METRIC,
+            "queue" => self.inner.name,
+            "policy" => self.inner.settings.overflow.as_str()
+        )
+        .increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use std::time::Duration;
+
+    fn settings(capacity: usize, overflow: OverflowPolicy) -> QueueSettings {
+        QueueSettings { capacity, overflow }
+    }
+
+    #[derive(Default)]
+    struct Spilled(Mutex<Vec<u32>>);
+
+    #[async_trait]
+    impl Spill<u32> for Spilled {
+        async fn spill(&self, message: u32) -> Result<(), AppError> {
+            self.0.lock().unwrap().push(message);
+            Ok(())
+        }
+    }
+
+    // Test checks if the oldest message is dropped once the queue is full.
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let queue = NotificationQueue::new("test", &settings(2, OverflowPolicy::DropOldest), None);
+
+        for message in 1..=3 {
+            queue.push(message).await.unwrap();
+        }
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop().await, Some(2));
+        assert_eq!(queue.pop().await, Some(3));
+    }
+
+    // Test checks if messages that do not fit are spilled.
+    #[tokio::test]
+    async fn test_spill() {
+        let spilled = Arc::new(Spilled::default());
+        let queue = NotificationQueue::new(
+            "test",
+            &settings(1, OverflowPolicy::Spill),
+            Some(spilled.clone() as Arc<dyn Spill<u32>>),
+        );
+
+        assert_eq!(queue.push(1).await.unwrap(), Pushed::Queued);
+        assert_eq!(queue.push(2).await.unwrap(), Pushed::Spilled);
+        assert_eq!(queue.close(), vec![1]);
+        assert_eq!(queue.push(3).await.unwrap(), Pushed::Spilled);
+        assert_eq!(*spilled.0.lock().unwrap(), vec![2, 3]);
+
+        let unspillable = NotificationQueue::new("test", &settings(0, OverflowPolicy::Spill), None);
+
+        assert!(unspillable.push(1).await.is_err());
+    }
+
+    // Test checks if producers wait for room with the block policy.
+    #[tokio::test]
+    async fn test_block() {
+        let queue = NotificationQueue::new("test", &settings(1, OverflowPolicy::Block), None);
+        queue.push(1).await.unwrap();
+
+        let producer = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.push(2).await.ok() })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert!(!producer.is_finished());
+        assert_eq!(queue.pop().await, Some(1));
+        assert_eq!(producer.await.unwrap(), Some(Pushed::Queued));
+        assert_eq!(queue.pop().await, Some(2));
+    }
+
+    // Test checks if waiting senders stop once the queue is closed.
+    #[tokio::test]
+    async fn test_close_wakes_senders() {
+        let queue =
+            NotificationQueue::<u32>::new("test", &settings(1, OverflowPolicy::Block), None);
+        let sender = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.pop().await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        queue.close();
+
+        assert_eq!(sender.await.unwrap(), None);
+    }
+
+    // Test checks if the depth and dropped messages are recorded per queue.
+    #[test]
+    fn test_queue_metrics() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            let queue =
+                NotificationQueue::new("mail", &settings(2, OverflowPolicy::DropOldest), None);
+            for message in 1..=3 {
+                queue.try_push(message).unwrap();
+            }
+        });
+
+        let rendered = handle.render();
+
+        assert!(rendered.contains("notification_queue_depth{queue=\"mail\"} 2"));
+        assert!(rendered.contains(
+            "notification_queue_overflow_total{queue=\"mail\",policy=\"drop_oldest\"} 1"
+        ));
+    }
+}
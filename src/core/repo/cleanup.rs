@@ -0,0 +1,305 @@
+//! Token cleanup module.
+//!
+//! Module removes expired and revoked tokens, opaque
+//! access tokens and expired device codes, so their
+//! tables only grow with the active ones, purges users
+//! once they were deleted long enough and removes
+//! accounts whose deletion grace period is over.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+// Local imports
+use super::{AccessTokenRepo, DeviceCodeRepo, TokenRepo, UserRepo};
+use crate::core::audit::{AuditEntry, AuditEvent, AuditLog};
+use crate::core::err::AppError;
+use crate::core::tenancy::with_tenant;
+use crate::core::worker::{cron::Schedule, Job};
+
+/// ## Token cleanup job struct.
+///
+/// ## Examples
+/// ```
+/// use std::sync::Arc;
+/// use axum_auth::core::repo::{cleanup::TokenCleanup, memory::MemoryTokenRepo};
+/// use axum_auth::core::worker::cron::Schedule;
+///
+/// let schedule = Schedule::new("0 3 * * *", "UTC").unwrap();
+/// let cleanup = TokenCleanup::new(Arc::new(MemoryTokenRepo::default()), schedule);
+/// ```
+pub struct TokenCleanup {
+    tokens: Arc<dyn TokenRepo>,
+    device_codes: Option<Arc<dyn DeviceCodeRepo>>,
+    access_tokens: Option<Arc<dyn AccessTokenRepo>>,
+    schedule: Schedule,
+}
+
+impl TokenCleanup {
+    /// ## Creates a new `TokenCleanup` instance.
+    ///
+    /// ## Parameters
+    /// - `tokens`: `Arc<dyn TokenRepo>` - Token repository.
+    /// - `schedule`: `Schedule` - Schedule of the cleanups.
+    pub fn new(tokens: Arc<dyn TokenRepo>, schedule: Schedule) -> Self {
+        TokenCleanup {
+            tokens,
+            device_codes: None,
+            access_tokens: None,
+            schedule,
+        }
+    }
+
+    /// ## Removes expired device codes in the same run.
+    ///
+    /// ## Parameters
+    /// - `device_codes`: `Arc<dyn DeviceCodeRepo>` - Device code repository.
+    pub fn with_device_codes(mut self, device_codes: Arc<dyn DeviceCodeRepo>) -> Self {
+        self.device_codes = Some(device_codes);
+        self
+    }
+
+    /// ## Removes expired and revoked opaque access tokens in the same run.
+    ///
+    /// ## Parameters
+    /// - `access_tokens`: `Arc<dyn AccessTokenRepo>` - Opaque access token repository.
+    pub fn with_access_tokens(mut self, access_tokens: Arc<dyn AccessTokenRepo>) -> Self {
+        self.access_tokens = Some(access_tokens);
+        self
+    }
+}
+
+#[async_trait]
+impl Job for TokenCleanup {
+    fn name(&self) -> &str {
+        "token_cleanup"
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn run(&self) -> Result<(), AppError> {
+        let removed = self.tokens.purge().await?;
+        tracing::info!(removed, "Removed expired and revoked tokens");
+        if let Some(device_codes) = &self.device_codes {
+            let removed = device_codes.purge().await?;
+            tracing::info!(removed, "Removed expired device codes");
+        }
+        if let Some(access_tokens) = &self.access_tokens {
+            let removed = access_tokens.purge().await?;
+            tracing::info!(removed, "Removed expired and revoked access tokens");
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Deleted user purge job struct.
+///
+/// ## Examples
+/// ```
+/// use std::sync::Arc;
+/// use axum_auth::core::repo::{cleanup::UserPurge, memory::MemoryUserRepo};
+/// use axum_auth::core::worker::cron::Schedule;
+///
+/// let schedule = Schedule::new("0 4 * * *", "UTC").unwrap();
+/// let purge = UserPurge::new(Arc::new(MemoryUserRepo::default()), 2_592_000, schedule);
+/// ```
+pub struct UserPurge {
+    users: Arc<dyn UserRepo>,
+    retention: u64,
+    schedule: Schedule,
+}
+
+impl UserPurge {
+    /// ## Creates a new `UserPurge` instance.
+    ///
+    /// ## Parameters
+    /// - `users`: `Arc<dyn UserRepo>` - User repository.
+    /// - `retention`: `u64` - Seconds a deleted user is kept.
+    /// - `schedule`: `Schedule` - Schedule of the purges.
+    pub fn new(users: Arc<dyn UserRepo>, retention: u64, schedule: Schedule) -> Self {
+        UserPurge {
+            users,
+            retention,
+            schedule,
+        }
+    }
+}
+
+#[async_trait]
+impl Job for UserPurge {
+    fn name(&self) -> &str {
+        "user_purge"
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn run(&self) -> Result<(), AppError> {
+        // Retentions beyond the supported time range purge nobody
+        let before = i64::try_from(self.retention)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .and_then(|retention| Utc::now().checked_sub_signed(retention))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let removed = self.users.purge_deleted(before).await?;
+        tracing::info!(removed, "Removed deleted users");
+
+        Ok(())
+    }
+}
+
+/// ## Scheduled account deletion job struct.
+///
+/// Accounts the users deleted on `/me/delete` are removed
+/// once their grace period is over. Every removal is
+/// recorded in the audit log of the user's tenant.
+///
+/// ## Examples
+/// ```no_run
+/// use std::sync::Arc;
+/// use axum_auth::core::audit::AuditLog;
+/// use axum_auth::core::repo::{cleanup::AccountDeletion, memory::MemoryUserRepo};
+/// use axum_auth::core::worker::cron::Schedule;
+///
+/// # fn example(pool: sqlx::PgPool) {
+/// let schedule = Schedule::new("0 * * * *", "UTC").unwrap();
+/// let deletion = AccountDeletion::new(
+///     Arc::new(MemoryUserRepo::default()),
+///     AuditLog::new(pool),
+///     schedule,
+/// );
+/// # }
+/// ```
+pub struct AccountDeletion {
+    users: Arc<dyn UserRepo>,
+    audit: AuditLog,
+    schedule: Schedule,
+}
+
+impl AccountDeletion {
+    /// ## Creates a new `AccountDeletion` instance.
+    ///
+    /// ## Parameters
+    /// - `users`: `Arc<dyn UserRepo>` - User repository.
+    /// - `audit`: `AuditLog` - Audit log of the removals.
+    /// - `schedule`: `Schedule` - Schedule of the removals.
+    pub fn new(users: Arc<dyn UserRepo>, audit: AuditLog, schedule: Schedule) -> Self {
+        AccountDeletion {
+            users,
+            audit,
+            schedule,
+        }
+    }
+}
+
+#[async_trait]
+impl Job for AccountDeletion {
+    fn name(&self) -> &str {
+        "account_deletion"
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn run(&self) -> Result<(), AppError> {
+        let removed = self.users.purge_scheduled(Utc::now()).await?;
+        for (user_id, tenant) in &removed {
+            let entry = AuditEntry::new(AuditEvent::AccountPurged)
+                .user(*user_id)
+                .details(serde_json::json!({ "channel": "job" }));
+            with_tenant(tenant.clone(), self.audit.record(entry)).await?;
+        }
+        tracing::info!(
+            removed = removed.len(),
+            "Removed accounts scheduled for deletion"
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::repo::memory::MemoryUserRepo;
+    use crate::core::users::User;
+    use sqlx::postgres::PgPoolOptions;
+    use uuid::Uuid;
+
+    fn user() -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: "user@example.com".to_string(),
+            display_name: None,
+            org_id: None,
+            disabled: false,
+            password_reset_required: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_at_display: None,
+            updated_at_display: None,
+        }
+    }
+
+    // Test checks if only users deleted longer than the retention are purged.
+    #[tokio::test]
+    async fn test_user_purge() {
+        let users = Arc::new(MemoryUserRepo::default());
+        let user = user();
+        let id = user.id;
+        users.insert(user, None);
+        users.delete(id).await.unwrap();
+        let schedule = || Schedule::new("0 4 * * *", "UTC").unwrap();
+
+        UserPurge::new(users.clone(), 3600, schedule())
+            .run()
+            .await
+            .unwrap();
+        UserPurge::new(users.clone(), u64::MAX, schedule())
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(users.purge_deleted(Utc::now()).await.unwrap(), 1);
+
+        users.delete(Uuid::new_v4()).await.unwrap_err();
+    }
+
+    // Test checks if accounts are kept until their grace period is over.
+    #[tokio::test]
+    async fn test_account_deletion_grace() {
+        let users = Arc::new(MemoryUserRepo::default());
+        let user = user();
+        users.insert(user.clone(), None);
+        users
+            .schedule_deletion(user.id, Utc::now() + Duration::hours(1))
+            .await
+            .unwrap();
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+
+        AccountDeletion::new(
+            users.clone(),
+            AuditLog::new(pool),
+            Schedule::new("0 * * * *", "UTC").unwrap(),
+        )
+        .run()
+        .await
+        .unwrap();
+
+        assert!(users.get(user.id).await.is_ok());
+        assert_eq!(
+            users
+                .purge_scheduled(Utc::now() + Duration::hours(2))
+                .await
+                .unwrap(),
+            [(user.id, "default".to_string())]
+        );
+    }
+}
@@ -0,0 +1,1866 @@
+//! In-memory repository module.
+//!
+//! Module implements the repository traits on maps
+//! behind a mutex. The repositories behave like the
+//! Postgres ones, so handlers and services can be
+//! tested without a running database. Users, sessions,
+//! devices, tokens and role definitions belong to the
+//! tenant they were stored in, device codes to the
+//! tenant of the user who decided. Opaque access tokens
+//! and signing keys belong to no tenant.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{collections::HashMap, sync::Mutex};
+use uuid::Uuid;
+
+// Local imports
+use super::{
+    client_not_found, client_taken, email_taken, hash_token, no_pending_email, role_not_found,
+    role_taken, role_version_conflict, tenant_not_found, tenant_taken, user_code_taken,
+    user_not_found, AccessClaims, AccessTokenRepo, Client, ClientRepo, Device, DeviceCode,
+    DeviceCodeRepo, DeviceCodeStatus, DeviceLogin, DeviceRepo, DeviceSighting, NewClient,
+    NewDeviceCode, NewRole, NewSession, NewSigningKey, NewToken, RoleDefinitionRepo, Session,
+    SessionRepo, SigningKeyRepo, StoredSigningKey, TenantRepo, Token, TokenKind, TokenRepo,
+    UserRepo,
+};
+use crate::core::auth::password::StoredHash;
+use crate::core::auth::rbac::Scope;
+use crate::core::err::AppError;
+use crate::core::http::pagination::Window;
+use crate::core::tenancy::{current_tenant, Tenant};
+use crate::core::users::{roles::Role, NewUser, User, UserFilter};
+
+/// ## Stored user with its password and tenant.
+#[derive(Debug, Clone)]
+struct UserEntry {
+    user: User,
+    password: Option<StoredHash>,
+    password_changed_at: DateTime<Utc>,
+    expiry_warned: bool,
+    tenant: String,
+}
+
+/// ## In-memory user repository struct.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::repo::memory::MemoryUserRepo;
+/// use axum_auth::core::users::User;
+/// use chrono::Utc;
+/// use uuid::Uuid;
+///
+/// let users = MemoryUserRepo::default();
+/// users.insert(
+///     User {
+///         id: Uuid::new_v4(),
+///         email: "user@example.com".to_string(),
+///         display_name: None,
+///         org_id: None,
+///         disabled: false,
+///         password_reset_required: false,
+///         created_at: Utc::now(),
+///         updated_at: Utc::now(),
+///         created_at_display: None,
+///         updated_at_display: None,
+///     },
+///     None,
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryUserRepo {
+    users: Mutex<HashMap<Uuid, UserEntry>>,
+    pending_emails: Mutex<HashMap<Uuid, String>>,
+    previous_emails: Mutex<HashMap<Uuid, String>>,
+    deleted: Mutex<HashMap<Uuid, DateTime<Utc>>>,
+    scheduled: Mutex<HashMap<Uuid, DateTime<Utc>>>,
+    grants: Mutex<HashMap<Uuid, Vec<String>>>,
+}
+
+impl MemoryUserRepo {
+    /// ## Adds or replaces the user in the current tenant.
+    ///
+    /// ## Parameters
+    /// - `user`: `User` - User to store.
+    /// - `password`: `Option<StoredHash>` - Password hash of the user.
+    pub fn insert(&self, user: User, password: Option<StoredHash>) {
+        let entry = UserEntry {
+            user,
+            password,
+            password_changed_at: Utc::now(),
+            expiry_warned: false,
+            tenant: current_tenant(),
+        };
+        self.lock().insert(entry.user.id, entry);
+    }
+
+    /// ## Locks the users, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, UserEntry>> {
+        self.users.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// ## Returns the users of the current tenant.
+    fn tenant_users(&self) -> Vec<UserEntry> {
+        let tenant = current_tenant();
+
+        self.lock()
+            .values()
+            .filter(|entry| entry.tenant == tenant)
+            .cloned()
+            .collect()
+    }
+
+    /// ## Updates the users of every tenant matching the predicate.
+    fn update_where(
+        &self,
+        matches: impl Fn(&UserEntry) -> bool,
+        f: impl Fn(&mut UserEntry),
+    ) -> Vec<(User, String)> {
+        let mut users = self.lock();
+        let mut updated = Vec::new();
+
+        for entry in users.values_mut().filter(|entry| matches(entry)) {
+            f(entry);
+            entry.user.updated_at = Utc::now();
+            updated.push((entry.user.clone(), entry.tenant.clone()));
+        }
+
+        updated
+    }
+
+    /// ## Updates the user of the current tenant with the closure.
+    fn update(&self, id: Uuid, f: impl FnOnce(&mut UserEntry)) -> Result<User, AppError> {
+        let mut users = self.lock();
+        let entry = users
+            .get_mut(&id)
+            .filter(|entry| entry.tenant == current_tenant())
+            .ok_or_else(|| user_not_found(id))?;
+
+        f(entry);
+        entry.user.updated_at = Utc::now();
+
+        Ok(entry.user.clone())
+    }
+}
+
+#[async_trait]
+impl UserRepo for MemoryUserRepo {
+    async fn list(
+        &self,
+        filter: &UserFilter,
+        scope: &Scope,
+        window: &Window,
+    ) -> Result<(Vec<User>, i64), AppError> {
+        let email = filter.email.as_ref().map(|email| email.to_lowercase());
+        let users: Vec<User> = self
+            .tenant_users()
+            .into_iter()
+            .map(|entry| entry.user)
+            .filter(|user| match scope {
+                Scope::All => true,
+                Scope::Orgs(orgs) => user.org_id.is_some_and(|org_id| orgs.contains(&org_id)),
+            })
+            .filter(|user| {
+                email
+                    .as_ref()
+                    .is_none_or(|email| user.email.to_lowercase().contains(email))
+            })
+            .filter(|user| {
+                filter
+                    .org_id
+                    .is_none_or(|org_id| user.org_id == Some(org_id))
+            })
+            .filter(|user| {
+                filter
+                    .disabled
+                    .is_none_or(|disabled| user.disabled == disabled)
+            })
+            .collect();
+
+        let total = users.len() as i64;
+
+        Ok((window.apply(users), total))
+    }
+
+    async fn get(&self, id: Uuid) -> Result<User, AppError> {
+        self.tenant_users()
+            .into_iter()
+            .find(|entry| entry.user.id == id)
+            .map(|entry| entry.user)
+            .ok_or_else(|| user_not_found(id))
+    }
+
+    async fn credentials(&self, email: &str) -> Result<Option<(User, StoredHash)>, AppError> {
+        Ok(self.tenant_users().into_iter().find_map(|entry| {
+            match (entry.user.email == email, entry.password) {
+                (true, Some(password)) => Some((entry.user, password)),
+                _ => None,
+            }
+        }))
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
+        Ok(self
+            .tenant_users()
+            .into_iter()
+            .find(|entry| entry.user.email == email)
+            .map(|entry| entry.user))
+    }
+
+    async fn emails(&self) -> Result<Vec<(Uuid, String)>, AppError> {
+        let mut emails: Vec<(Uuid, String)> = self
+            .tenant_users()
+            .into_iter()
+            .map(|entry| (entry.user.id, entry.user.email))
+            .collect();
+        emails.sort_by(|a, b| a.1.cmp(&b.1));
+
+        Ok(emails)
+    }
+
+    async fn create(&self, user: NewUser) -> Result<User, AppError> {
+        let taken = self
+            .tenant_users()
+            .iter()
+            .any(|entry| entry.user.email == user.email);
+        if taken {
+            return Err(email_taken());
+        }
+
+        let now = Utc::now();
+        let created = User {
+            id: Uuid::new_v4(),
+            email: user.email,
+            display_name: user.display_name,
+            org_id: user.org_id,
+            disabled: false,
+            password_reset_required: false,
+            created_at: now,
+            updated_at: now,
+            created_at_display: None,
+            updated_at_display: None,
+        };
+        self.insert(created.clone(), user.password);
+
+        Ok(created)
+    }
+
+    async fn import(
+        &self,
+        user: NewUser,
+        disabled: bool,
+        roles: &[String],
+    ) -> Result<User, AppError> {
+        let mut created = self.create(user).await?;
+        if disabled {
+            created = self.set_disabled(created.id, true).await?;
+        }
+
+        let mut roles = roles.to_vec();
+        roles.sort();
+        roles.dedup();
+        self.grants
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(created.id, roles);
+
+        Ok(created)
+    }
+
+    async fn grant(&self, id: Uuid, role: &str) -> Result<bool, AppError> {
+        let mut grants = self.grants.lock().unwrap_or_else(|e| e.into_inner());
+        let roles = grants.entry(id).or_default();
+        if roles.iter().any(|granted| granted == role) {
+            return Ok(false);
+        }
+        roles.push(role.to_string());
+        roles.sort();
+
+        Ok(true)
+    }
+
+    async fn roles(&self, id: Uuid) -> Result<Vec<String>, AppError> {
+        let grants = self.grants.lock().unwrap_or_else(|e| e.into_inner());
+
+        Ok(grants.get(&id).cloned().unwrap_or_default())
+    }
+
+    async fn set_display_name(&self, id: Uuid, name: Option<&str>) -> Result<User, AppError> {
+        self.update(id, |entry| {
+            entry.user.display_name = name.map(str::to_string)
+        })
+    }
+
+    async fn set_password(&self, id: Uuid, hash: &StoredHash) -> Result<(), AppError> {
+        self.update(id, |entry| {
+            entry.password = Some(hash.clone());
+            entry.password_changed_at = Utc::now();
+            entry.expiry_warned = false;
+            entry.user.password_reset_required = false;
+        })
+        .map(|_| ())
+    }
+
+    async fn set_disabled(&self, id: Uuid, disabled: bool) -> Result<User, AppError> {
+        self.update(id, |entry| entry.user.disabled = disabled)
+    }
+
+    async fn require_password_reset(&self, id: Uuid) -> Result<User, AppError> {
+        self.update(id, |entry| entry.user.password_reset_required = true)
+    }
+
+    async fn password_changed_at(&self, id: Uuid) -> Result<Option<DateTime<Utc>>, AppError> {
+        self.tenant_users()
+            .into_iter()
+            .find(|entry| entry.user.id == id)
+            .map(|entry| entry.password.map(|_| entry.password_changed_at))
+            .ok_or_else(|| user_not_found(id))
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+        self.update(id, |_| ())?;
+        self.lock().remove(&id);
+        self.deleted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, Utc::now());
+
+        Ok(())
+    }
+
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        let mut deleted = self.deleted.lock().unwrap_or_else(|e| e.into_inner());
+        let count = deleted.len();
+        deleted.retain(|_, deleted_at| *deleted_at >= before);
+
+        Ok((count - deleted.len()) as u64)
+    }
+
+    async fn set_pending_email(&self, id: Uuid, email: &str) -> Result<(), AppError> {
+        let user = self.update(id, |_| ())?;
+        self.previous_emails
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, user.email);
+        self.pending_emails
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, email.to_string());
+
+        Ok(())
+    }
+
+    async fn confirm_pending_email(&self, id: Uuid) -> Result<User, AppError> {
+        let mut pending = self
+            .pending_emails
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let email = pending
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| no_pending_email(id))?;
+        let taken = self
+            .tenant_users()
+            .iter()
+            .any(|entry| entry.user.id != id && entry.user.email == email);
+        if taken {
+            return Err(email_taken());
+        }
+
+        pending.remove(&id);
+        self.update(id, |entry| entry.user.email = email)
+    }
+
+    async fn undo_email_change(&self, id: Uuid) -> Result<User, AppError> {
+        let mut previous = self
+            .previous_emails
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let email = previous
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| no_pending_email(id))?;
+        let taken = self
+            .tenant_users()
+            .iter()
+            .any(|entry| entry.user.id != id && entry.user.email == email);
+        if taken {
+            return Err(email_taken());
+        }
+
+        previous.remove(&id);
+        self.pending_emails
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+        self.update(id, |entry| entry.user.email = email)
+    }
+
+    async fn schedule_deletion(
+        &self,
+        id: Uuid,
+        delete_after: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        self.update(id, |_| ())?;
+        self.scheduled
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, delete_after);
+
+        Ok(())
+    }
+
+    async fn cancel_deletion(&self, id: Uuid) -> Result<bool, AppError> {
+        self.update(id, |_| ())?;
+
+        Ok(self
+            .scheduled
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id)
+            .is_some())
+    }
+
+    async fn purge_scheduled(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<(Uuid, String)>, AppError> {
+        let mut scheduled = self.scheduled.lock().unwrap_or_else(|e| e.into_inner());
+        let due: Vec<Uuid> = scheduled
+            .iter()
+            .filter(|(_, delete_after)| **delete_after <= before)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut users = self.lock();
+        let removed = due
+            .into_iter()
+            .filter_map(|id| {
+                scheduled.remove(&id);
+                users.remove(&id).map(|entry| (id, entry.tenant))
+            })
+            .collect();
+
+        Ok(removed)
+    }
+
+    async fn warn_password_expiry(
+        &self,
+        changed_before: DateTime<Utc>,
+    ) -> Result<Vec<(User, String)>, AppError> {
+        Ok(self.update_where(
+            |entry| expirable(entry, changed_before) && !entry.expiry_warned,
+            |entry| entry.expiry_warned = true,
+        ))
+    }
+
+    async fn expire_passwords(
+        &self,
+        changed_before: DateTime<Utc>,
+    ) -> Result<Vec<(User, String)>, AppError> {
+        Ok(self.update_where(
+            |entry| expirable(entry, changed_before),
+            |entry| entry.user.password_reset_required = true,
+        ))
+    }
+}
+
+/// ## Checks if the password of the user expires by the time.
+fn expirable(entry: &UserEntry, changed_before: DateTime<Utc>) -> bool {
+    entry.password.is_some()
+        && entry.password_changed_at < changed_before
+        && !entry.user.disabled
+        && !entry.user.password_reset_required
+}
+
+/// ## Stored session with its revocation and tenant.
+#[derive(Debug, Clone)]
+struct SessionEntry {
+    session: Session,
+    revoked: bool,
+    tenant: String,
+}
+
+impl SessionEntry {
+    /// ## Checks if the session of the current tenant is neither revoked nor expired.
+    fn active(&self) -> bool {
+        !self.revoked && self.session.expires_at > Utc::now() && self.tenant == current_tenant()
+    }
+}
+
+/// ## In-memory session repository struct.
+#[derive(Debug, Default)]
+pub struct MemorySessionRepo {
+    sessions: Mutex<HashMap<Uuid, SessionEntry>>,
+}
+
+impl MemorySessionRepo {
+    /// ## Locks the sessions, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, SessionEntry>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl SessionRepo for MemorySessionRepo {
+    async fn create(&self, session: NewSession) -> Result<Session, AppError> {
+        let now = Utc::now();
+        let session = Session {
+            id: Uuid::new_v4(),
+            user_id: session.user_id,
+            ip: session.ip,
+            user_agent: session.user_agent,
+            created_at: now,
+            last_seen_at: now,
+            expires_at: session.expires_at,
+        };
+
+        self.lock().insert(
+            session.id,
+            SessionEntry {
+                session: session.clone(),
+                revoked: false,
+                tenant: current_tenant(),
+            },
+        );
+
+        Ok(session)
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<Session>, AppError> {
+        Ok(self
+            .lock()
+            .get(&id)
+            .filter(|entry| entry.active())
+            .map(|entry| entry.session.clone()))
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>, AppError> {
+        let mut sessions: Vec<Session> = self
+            .lock()
+            .values()
+            .filter(|entry| entry.session.user_id == user_id && entry.active())
+            .map(|entry| entry.session.clone())
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+
+        Ok(sessions)
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), AppError> {
+        if let Some(entry) = self
+            .lock()
+            .get_mut(&id)
+            .filter(|entry| entry.tenant == current_tenant())
+        {
+            entry.session.last_seen_at = Utc::now();
+        }
+
+        Ok(())
+    }
+
+    async fn revoke(&self, id: Uuid) -> Result<bool, AppError> {
+        Ok(match self.lock().get_mut(&id) {
+            Some(entry) if entry.active() => {
+                entry.revoked = true;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn revoke_all(&self, user_id: Uuid) -> Result<u64, AppError> {
+        let mut revoked = 0;
+
+        for entry in self.lock().values_mut() {
+            if entry.session.user_id == user_id && entry.active() {
+                entry.revoked = true;
+                revoked += 1;
+            }
+        }
+
+        Ok(revoked)
+    }
+}
+
+/// ## In-memory login device repository struct.
+///
+/// Devices are stored with their tenant.
+#[derive(Debug, Default)]
+pub struct MemoryDeviceRepo {
+    devices: Mutex<Vec<(String, Device)>>,
+}
+
+impl MemoryDeviceRepo {
+    /// ## Locks the devices, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(String, Device)>> {
+        self.devices.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl DeviceRepo for MemoryDeviceRepo {
+    async fn record(&self, login: DeviceLogin) -> Result<DeviceSighting, AppError> {
+        let tenant = current_tenant();
+        let now = Utc::now();
+        let mut devices = self.lock();
+        let mut known = devices
+            .iter_mut()
+            .filter(|(owner, device)| *owner == tenant && device.user_id == login.user_id)
+            .map(|(_, device)| device)
+            .peekable();
+        let first_login = known.peek().is_none();
+        let mut new_country = login.country.is_some();
+        let mut seen = None;
+        for device in known {
+            if login
+                .country
+                .as_ref()
+                .is_some_and(|country| device.countries.contains(country))
+            {
+                new_country = false;
+            }
+            if device.fingerprint == login.fingerprint {
+                seen = Some(device);
+            }
+        }
+        let new_device = seen.is_none();
+
+        let device = match seen {
+            Some(device) => {
+                device.user_agent = login.user_agent;
+                device.ip = login.ip;
+                device.last_seen_at = now;
+                if let Some(country) = login.country {
+                    if !device.countries.contains(&country) {
+                        device.countries.push(country);
+                    }
+                }
+                device.clone()
+            }
+            None => {
+                let device = Device {
+                    id: Uuid::new_v4(),
+                    user_id: login.user_id,
+                    fingerprint: login.fingerprint,
+                    user_agent: login.user_agent,
+                    ip: login.ip,
+                    countries: login.country.into_iter().collect(),
+                    first_seen_at: now,
+                    last_seen_at: now,
+                };
+                devices.push((tenant, device.clone()));
+                device
+            }
+        };
+
+        Ok(DeviceSighting {
+            device,
+            first_login,
+            new_device,
+            new_country,
+        })
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Device>, AppError> {
+        let tenant = current_tenant();
+        let mut devices: Vec<Device> = self
+            .lock()
+            .iter()
+            .filter(|(owner, device)| *owner == tenant && device.user_id == user_id)
+            .map(|(_, device)| device.clone())
+            .collect();
+        devices.sort_by_key(|device| std::cmp::Reverse(device.last_seen_at));
+
+        Ok(devices)
+    }
+}
+
+/// ## Stored token with its revocation and tenant.
+#[derive(Debug, Clone)]
+struct TokenEntry {
+    token: Token,
+    revoked: bool,
+    expiry_warned: bool,
+    tenant: String,
+}
+
+impl TokenEntry {
+    /// ## Checks if the token is neither revoked nor expired.
+    fn active(&self) -> bool {
+        !self.revoked && self.token.expires_at > Utc::now()
+    }
+
+    /// ## Checks if the active token belongs to the current tenant.
+    fn visible(&self) -> bool {
+        self.active() && self.tenant == current_tenant()
+    }
+}
+
+/// ## In-memory token repository struct.
+#[derive(Debug, Default)]
+pub struct MemoryTokenRepo {
+    tokens: Mutex<HashMap<Uuid, TokenEntry>>,
+}
+
+impl MemoryTokenRepo {
+    /// ## Locks the tokens, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, TokenEntry>> {
+        self.tokens.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// ## Updates the active tokens of every tenant matching the predicate.
+    fn update_where(
+        &self,
+        matches: impl Fn(&TokenEntry) -> bool,
+        f: impl Fn(&mut TokenEntry),
+    ) -> Vec<(Token, String)> {
+        let mut updated = Vec::new();
+
+        for entry in self.lock().values_mut() {
+            if entry.active() && matches(entry) {
+                f(entry);
+                updated.push((entry.token.clone(), entry.tenant.clone()));
+            }
+        }
+
+        updated
+    }
+
+    /// ## Revokes the active tokens matching the predicate.
+    fn revoke_where(&self, matches: impl Fn(&Token) -> bool) -> u64 {
+        let mut revoked = 0;
+
+        for entry in self.lock().values_mut() {
+            if matches(&entry.token) && entry.visible() {
+                entry.revoked = true;
+                revoked += 1;
+            }
+        }
+
+        revoked
+    }
+}
+
+#[async_trait]
+impl TokenRepo for MemoryTokenRepo {
+    async fn store(&self, token: NewToken) -> Result<Token, AppError> {
+        let token = Token {
+            id: Uuid::new_v4(),
+            kind: token.kind,
+            user_id: token.user_id,
+            session_id: token.session_id,
+            hash: hash_token(&token.token),
+            created_at: Utc::now(),
+            expires_at: token.expires_at,
+        };
+
+        self.lock().insert(
+            token.id,
+            TokenEntry {
+                token: token.clone(),
+                revoked: false,
+                expiry_warned: false,
+                tenant: current_tenant(),
+            },
+        );
+
+        Ok(token)
+    }
+
+    async fn find(&self, kind: TokenKind, token: &str) -> Result<Option<Token>, AppError> {
+        let hash = hash_token(token);
+
+        Ok(self
+            .lock()
+            .values()
+            .find(|entry| entry.token.hash == hash && entry.token.kind == kind && entry.visible())
+            .map(|entry| entry.token.clone()))
+    }
+
+    async fn find_revoked(&self, kind: TokenKind, token: &str) -> Result<Option<Token>, AppError> {
+        let hash = hash_token(token);
+
+        Ok(self
+            .lock()
+            .values()
+            .find(|entry| {
+                entry.token.hash == hash
+                    && entry.token.kind == kind
+                    && entry.revoked
+                    && entry.token.expires_at > Utc::now()
+                    && entry.tenant == current_tenant()
+            })
+            .map(|entry| entry.token.clone()))
+    }
+
+    async fn list(&self, user_id: Uuid, kind: TokenKind) -> Result<Vec<Token>, AppError> {
+        let mut tokens: Vec<Token> = self
+            .lock()
+            .values()
+            .filter(|entry| {
+                entry.token.user_id == user_id && entry.token.kind == kind && entry.visible()
+            })
+            .map(|entry| entry.token.clone())
+            .collect();
+        tokens.sort_by_key(|token| std::cmp::Reverse(token.created_at));
+
+        Ok(tokens)
+    }
+
+    async fn revoke(&self, id: Uuid) -> Result<bool, AppError> {
+        Ok(self.revoke_where(|token| token.id == id) > 0)
+    }
+
+    async fn revoke_session(&self, session_id: Uuid) -> Result<u64, AppError> {
+        Ok(self.revoke_where(|token| token.session_id == Some(session_id)))
+    }
+
+    async fn revoke_user(&self, user_id: Uuid) -> Result<u64, AppError> {
+        Ok(self.revoke_where(|token| token.user_id == user_id))
+    }
+
+    async fn purge(&self) -> Result<u64, AppError> {
+        let mut tokens = self.lock();
+        let before = tokens.len();
+
+        tokens.retain(|_, entry| entry.active());
+
+        Ok((before - tokens.len()) as u64)
+    }
+
+    async fn warn_expiry(
+        &self,
+        kind: TokenKind,
+        created_before: DateTime<Utc>,
+        expires_before: DateTime<Utc>,
+    ) -> Result<Vec<(Token, String)>, AppError> {
+        Ok(self.update_where(
+            |entry| {
+                entry.token.kind == kind
+                    && !entry.expiry_warned
+                    && (entry.token.created_at < created_before
+                        || entry.token.expires_at < expires_before)
+            },
+            |entry| entry.expiry_warned = true,
+        ))
+    }
+
+    async fn expire_issued_before(
+        &self,
+        kind: TokenKind,
+        created_before: DateTime<Utc>,
+    ) -> Result<Vec<(Token, String)>, AppError> {
+        Ok(self.update_where(
+            |entry| entry.token.kind == kind && entry.token.created_at < created_before,
+            |entry| entry.revoked = true,
+        ))
+    }
+}
+
+/// ## In-memory OAuth client repository struct.
+#[derive(Debug, Default)]
+pub struct MemoryClientRepo {
+    clients: Mutex<HashMap<String, Client>>,
+}
+
+impl MemoryClientRepo {
+    /// ## Locks the clients, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Client>> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl ClientRepo for MemoryClientRepo {
+    async fn create(&self, client: NewClient) -> Result<Client, AppError> {
+        let mut clients = self.lock();
+        if clients.contains_key(&client.client_id) {
+            return Err(client_taken(&client.client_id));
+        }
+
+        let client = Client {
+            client_id: client.client_id,
+            secret_hash: hash_token(&client.secret),
+            name: client.name,
+            scopes: client.scopes,
+            disabled: false,
+            created_at: Utc::now(),
+        };
+        clients.insert(client.client_id.clone(), client.clone());
+
+        Ok(client)
+    }
+
+    async fn get(&self, client_id: &str) -> Result<Option<Client>, AppError> {
+        Ok(self.lock().get(client_id).cloned())
+    }
+
+    async fn set_disabled(&self, client_id: &str, disabled: bool) -> Result<Client, AppError> {
+        let mut clients = self.lock();
+        let client = clients
+            .get_mut(client_id)
+            .ok_or_else(|| client_not_found(client_id))?;
+        client.disabled = disabled;
+
+        Ok(client.clone())
+    }
+}
+
+/// ## Stored device code with the hash of the polled code.
+#[derive(Debug, Clone)]
+struct DeviceCodeEntry {
+    code: DeviceCode,
+    hash: String,
+}
+
+/// ## In-memory device code repository struct.
+#[derive(Debug, Default)]
+pub struct MemoryDeviceCodeRepo {
+    codes: Mutex<HashMap<Uuid, DeviceCodeEntry>>,
+}
+
+impl MemoryDeviceCodeRepo {
+    /// ## Locks the codes, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, DeviceCodeEntry>> {
+        self.codes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl DeviceCodeRepo for MemoryDeviceCodeRepo {
+    async fn create(&self, code: NewDeviceCode) -> Result<DeviceCode, AppError> {
+        let mut codes = self.lock();
+        if codes
+            .values()
+            .any(|entry| entry.code.user_code == code.user_code)
+        {
+            return Err(user_code_taken());
+        }
+
+        let created = DeviceCode {
+            id: Uuid::new_v4(),
+            client_id: code.client_id,
+            user_code: code.user_code,
+            scopes: code.scopes,
+            status: DeviceCodeStatus::Pending,
+            user_id: None,
+            tenant: None,
+            polled_at: None,
+            created_at: Utc::now(),
+            expires_at: code.expires_at,
+        };
+        codes.insert(
+            created.id,
+            DeviceCodeEntry {
+                code: created.clone(),
+                hash: hash_token(&code.device_code),
+            },
+        );
+
+        Ok(created)
+    }
+
+    async fn find_pending(&self, user_code: &str) -> Result<Option<DeviceCode>, AppError> {
+        let now = Utc::now();
+
+        Ok(self
+            .lock()
+            .values()
+            .find(|entry| {
+                entry.code.user_code == user_code
+                    && entry.code.status == DeviceCodeStatus::Pending
+                    && entry.code.expires_at > now
+            })
+            .map(|entry| entry.code.clone()))
+    }
+
+    async fn decide(&self, id: Uuid, user_id: Uuid, approved: bool) -> Result<bool, AppError> {
+        let mut codes = self.lock();
+        let Some(entry) = codes.get_mut(&id) else {
+            return Ok(false);
+        };
+        if entry.code.status != DeviceCodeStatus::Pending || entry.code.expires_at <= Utc::now() {
+            return Ok(false);
+        }
+
+        entry.code.status = if approved {
+            DeviceCodeStatus::Approved
+        } else {
+            DeviceCodeStatus::Denied
+        };
+        entry.code.user_id = Some(user_id);
+        entry.code.tenant = Some(current_tenant());
+
+        Ok(true)
+    }
+
+    async fn poll(
+        &self,
+        client_id: &str,
+        device_code: &str,
+    ) -> Result<Option<DeviceCode>, AppError> {
+        let hash = hash_token(device_code);
+        let mut codes = self.lock();
+        let Some(entry) = codes
+            .values_mut()
+            .find(|entry| entry.hash == hash && entry.code.client_id == client_id)
+        else {
+            return Ok(None);
+        };
+
+        let previous = entry.code.clone();
+        entry.code.polled_at = Some(Utc::now());
+
+        Ok(Some(previous))
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, AppError> {
+        Ok(self.lock().remove(&id).is_some())
+    }
+
+    async fn purge(&self) -> Result<u64, AppError> {
+        let now = Utc::now();
+        let mut codes = self.lock();
+        let before = codes.len();
+
+        codes.retain(|_, entry| entry.code.expires_at > now);
+
+        Ok((before - codes.len()) as u64)
+    }
+}
+
+/// ## Stored opaque access token with its revocation state.
+#[derive(Debug, Clone)]
+struct AccessTokenEntry {
+    claims: AccessClaims,
+    revoked: bool,
+}
+
+/// ## In-memory opaque access token repository struct.
+#[derive(Debug, Default)]
+pub struct MemoryAccessTokenRepo {
+    tokens: Mutex<HashMap<String, AccessTokenEntry>>,
+}
+
+impl MemoryAccessTokenRepo {
+    /// ## Locks the tokens, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, AccessTokenEntry>> {
+        self.tokens.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl AccessTokenRepo for MemoryAccessTokenRepo {
+    async fn store(&self, token: &str, claims: &AccessClaims) -> Result<(), AppError> {
+        self.lock().insert(
+            hash_token(token),
+            AccessTokenEntry {
+                claims: claims.clone(),
+                revoked: false,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn resolve(&self, token: &str) -> Result<Option<AccessClaims>, AppError> {
+        let now = Utc::now().timestamp();
+
+        Ok(self
+            .lock()
+            .get(&hash_token(token))
+            .filter(|entry| !entry.revoked && entry.claims.exp > now)
+            .map(|entry| entry.claims.clone()))
+    }
+
+    async fn revoke(&self, token: &str) -> Result<bool, AppError> {
+        let now = Utc::now().timestamp();
+        let mut tokens = self.lock();
+        let Some(entry) = tokens.get_mut(&hash_token(token)) else {
+            return Ok(false);
+        };
+        if entry.revoked || entry.claims.exp <= now {
+            return Ok(false);
+        }
+        entry.revoked = true;
+
+        Ok(true)
+    }
+
+    async fn purge(&self) -> Result<u64, AppError> {
+        let now = Utc::now().timestamp();
+        let mut tokens = self.lock();
+        let before = tokens.len();
+
+        tokens.retain(|_, entry| !entry.revoked && entry.claims.exp > now);
+
+        Ok((before - tokens.len()) as u64)
+    }
+}
+
+/// ## In-memory signing key repository struct.
+#[derive(Debug, Default)]
+pub struct MemorySigningKeyRepo {
+    keys: Mutex<Vec<StoredSigningKey>>,
+}
+
+impl MemorySigningKeyRepo {
+    /// ## Locks the keys, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<StoredSigningKey>> {
+        self.keys.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl SigningKeyRepo for MemorySigningKeyRepo {
+    async fn active(&self) -> Result<Vec<StoredSigningKey>, AppError> {
+        let now = Utc::now();
+        let mut keys: Vec<StoredSigningKey> = self
+            .lock()
+            .iter()
+            .filter(|key| key.expires_at.is_none_or(|expires_at| expires_at > now))
+            .cloned()
+            .collect();
+        // Current key first, then the most recently created ones
+        keys.sort_by_key(|key| (key.expires_at.is_some(), std::cmp::Reverse(key.created_at)));
+
+        Ok(keys)
+    }
+
+    async fn rotate(
+        &self,
+        key: NewSigningKey,
+        grace_until: DateTime<Utc>,
+    ) -> Result<bool, AppError> {
+        let mut keys = self.lock();
+        if keys.iter().any(|stored| stored.kid == key.kid) {
+            return Ok(false);
+        }
+
+        for current in keys.iter_mut().filter(|stored| stored.expires_at.is_none()) {
+            current.expires_at = Some(grace_until);
+        }
+        keys.push(StoredSigningKey {
+            kid: key.kid,
+            document: key.document,
+            created_at: Utc::now(),
+            expires_at: None,
+        });
+
+        Ok(true)
+    }
+
+    async fn purge(&self) -> Result<u64, AppError> {
+        let now = Utc::now();
+        let mut keys = self.lock();
+        let before = keys.len();
+
+        keys.retain(|key| key.expires_at.is_none_or(|expires_at| expires_at > now));
+
+        Ok((before - keys.len()) as u64)
+    }
+}
+
+/// ## In-memory tenant repository struct.
+#[derive(Debug, Default)]
+pub struct MemoryTenantRepo {
+    tenants: Mutex<HashMap<String, Tenant>>,
+}
+
+impl MemoryTenantRepo {
+    /// ## Adds or replaces the tenant, named after its ID.
+    ///
+    /// ## Parameters
+    /// - `id`: `&str` - ID of the tenant.
+    /// - `disabled`: `bool` - Whether requests of the tenant are rejected.
+    pub fn insert(&self, id: &str, disabled: bool) {
+        self.lock().insert(
+            id.to_string(),
+            Tenant {
+                id: id.to_string(),
+                name: id.to_string(),
+                disabled,
+                created_at: Utc::now(),
+            },
+        );
+    }
+
+    /// ## Locks the tenants, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Tenant>> {
+        self.tenants.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl TenantRepo for MemoryTenantRepo {
+    async fn create(&self, id: &str, name: &str) -> Result<Tenant, AppError> {
+        let mut tenants = self.lock();
+        if tenants.contains_key(id) {
+            return Err(tenant_taken(id));
+        }
+
+        let tenant = Tenant {
+            id: id.to_string(),
+            name: name.to_string(),
+            disabled: false,
+            created_at: Utc::now(),
+        };
+        tenants.insert(tenant.id.clone(), tenant.clone());
+
+        Ok(tenant)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Tenant>, AppError> {
+        Ok(self.lock().get(id).cloned())
+    }
+
+    async fn set_disabled(&self, id: &str, disabled: bool) -> Result<Tenant, AppError> {
+        let mut tenants = self.lock();
+        let tenant = tenants.get_mut(id).ok_or_else(|| tenant_not_found(id))?;
+        tenant.disabled = disabled;
+
+        Ok(tenant.clone())
+    }
+}
+
+/// ## In-memory role definition repository struct.
+///
+/// Definitions are keyed by tenant and name.
+#[derive(Debug, Default)]
+pub struct MemoryRoleDefinitionRepo {
+    roles: Mutex<HashMap<(String, String), Role>>,
+}
+
+impl MemoryRoleDefinitionRepo {
+    /// ## Locks the definitions, a poisoned lock is recovered.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), Role>> {
+        self.roles.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl RoleDefinitionRepo for MemoryRoleDefinitionRepo {
+    async fn create(&self, role: NewRole) -> Result<Role, AppError> {
+        let mut roles = self.lock();
+        let key = (current_tenant(), role.name.clone());
+        if roles.contains_key(&key) {
+            return Err(role_taken(&role.name));
+        }
+
+        let now = Utc::now();
+        let created = Role {
+            name: role.name,
+            description: role.description,
+            permissions: role.permissions,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+        };
+        roles.insert(key, created.clone());
+
+        Ok(created)
+    }
+
+    async fn list(&self) -> Result<Vec<Role>, AppError> {
+        let tenant = current_tenant();
+        let mut roles: Vec<Role> = self
+            .lock()
+            .iter()
+            .filter(|((role_tenant, _), _)| *role_tenant == tenant)
+            .map(|(_, role)| role.clone())
+            .collect();
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(roles)
+    }
+
+    async fn get(&self, name: &str) -> Result<Option<Role>, AppError> {
+        Ok(self
+            .lock()
+            .get(&(current_tenant(), name.to_string()))
+            .cloned())
+    }
+
+    async fn update(&self, role: NewRole, version: i32) -> Result<Role, AppError> {
+        let mut roles = self.lock();
+        let stored = roles
+            .get_mut(&(current_tenant(), role.name.clone()))
+            .ok_or_else(|| role_not_found(&role.name))?;
+        if stored.version != version {
+            return Err(role_version_conflict(&role.name));
+        }
+
+        stored.description = role.description;
+        stored.permissions = role.permissions;
+        stored.version += 1;
+        stored.updated_at = Utc::now();
+
+        Ok(stored.clone())
+    }
+
+    async fn delete(&self, name: &str) -> Result<bool, AppError> {
+        Ok(self
+            .lock()
+            .remove(&(current_tenant(), name.to_string()))
+            .is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::err::ErrorKind;
+    use crate::core::http::pagination::Pagination;
+    use crate::core::tenancy::{with_tenant, DEFAULT_TENANT};
+    use crate::core::users::USER_SORTING;
+    use chrono::Duration;
+    use std::collections::HashSet;
+
+    fn user(email: &str, org_id: Option<Uuid>, age: i64) -> User {
+        let created_at = Utc::now() - Duration::minutes(age);
+
+        User {
+            id: Uuid::new_v4(),
+            email: email.to_string(),
+            display_name: None,
+            org_id,
+            disabled: false,
+            password_reset_required: false,
+            created_at,
+            updated_at: created_at,
+            created_at_display: None,
+            updated_at_display: None,
+        }
+    }
+
+    // Test checks if users are filtered, scoped and paged like the Postgres query.
+    #[tokio::test]
+    async fn test_memory_users_list() {
+        let org_id = Uuid::new_v4();
+        let users = MemoryUserRepo::default();
+        let first = user("Alice@example.com", Some(org_id), 3);
+        let second = user("bob@example.com", Some(org_id), 2);
+        users.insert(first.clone(), None);
+        users.insert(second.clone(), None);
+        users.insert(user("carol@example.org", None, 1), None);
+
+        let window = Pagination {
+            page: Some(2),
+            per_page: 1,
+            ..Pagination::default()
+        }
+        .window(&USER_SORTING)
+        .unwrap();
+        let scope = Scope::Orgs(HashSet::from([org_id]));
+        let (listed, total) = users
+            .list(&UserFilter::default(), &scope, &window)
+            .await
+            .unwrap();
+
+        assert_eq!(listed, vec![second]);
+        assert_eq!(total, 2);
+
+        let filter = UserFilter {
+            email: Some("alice".to_string()),
+            ..UserFilter::default()
+        };
+        let window = Pagination::default().window(&USER_SORTING).unwrap();
+        let (listed, total) = users.list(&filter, &Scope::All, &window).await.unwrap();
+
+        assert_eq!(listed, vec![first]);
+        assert_eq!(total, 1);
+    }
+
+    // Test checks if updates of unknown users fail with not found.
+    #[tokio::test]
+    async fn test_memory_users_not_found() {
+        let users = MemoryUserRepo::default();
+        let stored = user("user@example.com", None, 0);
+        users.insert(stored.clone(), None);
+
+        let disabled = users.set_disabled(stored.id, true).await.unwrap();
+
+        assert!(disabled.disabled);
+        assert_eq!(users.credentials("user@example.com").await.unwrap(), None);
+
+        users.delete(stored.id).await.unwrap();
+        let result = users.get(stored.id).await.unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::NotFound);
+    }
+
+    // Test checks if an email change is undone once, whether it was confirmed or not.
+    #[tokio::test]
+    async fn test_memory_users_undo_email_change() {
+        let users = MemoryUserRepo::default();
+        let stored = user("user@example.com", None, 0);
+        users.insert(stored.clone(), None);
+
+        users
+            .set_pending_email(stored.id, "user@example.org")
+            .await
+            .unwrap();
+        let confirmed = users.confirm_pending_email(stored.id).await.unwrap();
+
+        assert_eq!(confirmed.email, "user@example.org");
+
+        let restored = users.undo_email_change(stored.id).await.unwrap();
+
+        assert_eq!(restored.email, "user@example.com");
+        assert_eq!(
+            users.undo_email_change(stored.id).await.unwrap_err().kind,
+            ErrorKind::NotFound
+        );
+    }
+
+    // Test checks if created users are found by email and their email stays unique.
+    #[tokio::test]
+    async fn test_memory_users_create() {
+        let users = MemoryUserRepo::default();
+        let new = NewUser {
+            email: "user@example.com".to_string(),
+            display_name: None,
+            org_id: None,
+            password: None,
+        };
+
+        let created = users.create(new.clone()).await.unwrap();
+        let renamed = users
+            .set_display_name(created.id, Some("User"))
+            .await
+            .unwrap();
+
+        assert_eq!(renamed.display_name.as_deref(), Some("User"));
+        assert_eq!(
+            users.find_by_email("user@example.com").await.unwrap(),
+            Some(renamed)
+        );
+        assert_eq!(
+            users.create(new).await.unwrap_err().code,
+            "users.email_taken"
+        );
+    }
+
+    // Test checks if deleted users are hidden until purged.
+    #[tokio::test]
+    async fn test_memory_users_purge_deleted() {
+        let users = MemoryUserRepo::default();
+        let stored = user("user@example.com", None, 0);
+        users.insert(stored.clone(), None);
+
+        users.delete(stored.id).await.unwrap();
+
+        assert!(users.emails().await.unwrap().is_empty());
+        assert_eq!(
+            users.delete(stored.id).await.unwrap_err().kind,
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            users
+                .purge_deleted(Utc::now() - Duration::hours(1))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            users
+                .purge_deleted(Utc::now() + Duration::seconds(1))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    // Test checks if only users scheduled before the time are removed, cancelled ones are kept.
+    #[tokio::test]
+    async fn test_memory_users_purge_scheduled() {
+        let users = MemoryUserRepo::default();
+        let leaving = user("leaving@example.com", None, 0);
+        let staying = user("staying@example.com", None, 1);
+        users.insert(leaving.clone(), None);
+        users.insert(staying.clone(), None);
+
+        users
+            .schedule_deletion(leaving.id, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        users
+            .schedule_deletion(staying.id, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+
+        assert!(users.cancel_deletion(staying.id).await.unwrap());
+        assert!(!users.cancel_deletion(staying.id).await.unwrap());
+        assert_eq!(
+            users
+                .schedule_deletion(Uuid::new_v4(), Utc::now())
+                .await
+                .unwrap_err()
+                .kind,
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            users.purge_scheduled(Utc::now()).await.unwrap(),
+            [(leaving.id, DEFAULT_TENANT.to_string())]
+        );
+        assert_eq!(
+            users.get(leaving.id).await.unwrap_err().kind,
+            ErrorKind::NotFound
+        );
+        assert!(users.get(staying.id).await.is_ok());
+    }
+
+    // Test checks if revoked and expired sessions are not returned.
+    #[tokio::test]
+    async fn test_memory_sessions() {
+        let sessions = MemorySessionRepo::default();
+        let user_id = Uuid::new_v4();
+        let new = |expires_in: Duration| NewSession {
+            user_id,
+            ip: Some("203.0.113.7".to_string()),
+            user_agent: None,
+            expires_at: Utc::now() + expires_in,
+        };
+
+        let active = sessions.create(new(Duration::hours(1))).await.unwrap();
+        let revoked = sessions.create(new(Duration::hours(1))).await.unwrap();
+        sessions.create(new(Duration::hours(-1))).await.unwrap();
+
+        assert!(sessions.revoke(revoked.id).await.unwrap());
+        assert!(!sessions.revoke(revoked.id).await.unwrap());
+        assert_eq!(sessions.get(revoked.id).await.unwrap(), None);
+        assert_eq!(sessions.list(user_id).await.unwrap(), vec![active.clone()]);
+        assert_eq!(sessions.revoke_all(user_id).await.unwrap(), 1);
+        assert_eq!(sessions.get(active.id).await.unwrap(), None);
+    }
+
+    // Test checks if new devices and countries are told apart from known ones.
+    #[tokio::test]
+    async fn test_memory_devices() {
+        let devices = MemoryDeviceRepo::default();
+        let user_id = Uuid::new_v4();
+        let login = |fingerprint: &str, country: Option<&str>| DeviceLogin {
+            user_id,
+            fingerprint: fingerprint.to_string(),
+            user_agent: None,
+            ip: Some("203.0.113.7".to_string()),
+            country: country.map(str::to_string),
+        };
+
+        let first = devices.record(login("laptop", Some("DE"))).await.unwrap();
+        let again = devices.record(login("laptop", Some("DE"))).await.unwrap();
+        let phone = devices.record(login("phone", Some("DE"))).await.unwrap();
+        let abroad = devices.record(login("laptop", Some("FR"))).await.unwrap();
+
+        assert!(first.first_login && first.new_device && first.new_country);
+        assert!(!again.first_login && !again.new_device && !again.new_country);
+        assert!(phone.new_device && !phone.new_country);
+        assert!(!abroad.new_device && abroad.new_country);
+        assert_eq!(abroad.device.countries, ["DE", "FR"]);
+
+        let listed = devices.list(user_id).await.unwrap();
+
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].fingerprint, "laptop");
+        assert!(devices.list(Uuid::new_v4()).await.unwrap().is_empty());
+    }
+
+    // Test checks if tokens are found by kind and hash until revoked.
+    #[tokio::test]
+    async fn test_memory_tokens() {
+        let tokens = MemoryTokenRepo::default();
+        let session_id = Uuid::new_v4();
+        let stored = tokens
+            .store(NewToken {
+                kind: TokenKind::Refresh,
+                user_id: Uuid::new_v4(),
+                session_id: Some(session_id),
+                token: "refresh-token".to_string(),
+                expires_at: Utc::now() + Duration::hours(1),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(stored.hash, hash_token("refresh-token"));
+        assert_eq!(
+            tokens
+                .find(TokenKind::Refresh, "refresh-token")
+                .await
+                .unwrap(),
+            Some(stored.clone())
+        );
+        assert_eq!(
+            tokens
+                .find(TokenKind::Access, "refresh-token")
+                .await
+                .unwrap(),
+            None
+        );
+
+        assert_eq!(
+            tokens
+                .find_revoked(TokenKind::Refresh, "refresh-token")
+                .await
+                .unwrap(),
+            None
+        );
+
+        assert_eq!(tokens.revoke_session(session_id).await.unwrap(), 1);
+        assert_eq!(
+            tokens
+                .find(TokenKind::Refresh, "refresh-token")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            tokens
+                .find_revoked(TokenKind::Refresh, "refresh-token")
+                .await
+                .unwrap(),
+            Some(stored)
+        );
+        assert_eq!(tokens.purge().await.unwrap(), 1);
+    }
+
+    // Test checks if client IDs are unique and clients can be disabled.
+    #[tokio::test]
+    async fn test_clients() {
+        let clients = MemoryClientRepo::default();
+        let new = NewClient {
+            client_id: "billing".to_string(),
+            secret: "secret".to_string(),
+            name: "Billing".to_string(),
+            scopes: vec!["users:read".to_string()],
+        };
+
+        let client = clients.create(new.clone()).await.unwrap();
+        assert_eq!(client.secret_hash, hash_token("secret"));
+        assert_eq!(
+            clients.create(new).await.unwrap_err().code,
+            "oauth.client_taken"
+        );
+
+        assert!(
+            clients
+                .set_disabled("billing", true)
+                .await
+                .unwrap()
+                .disabled
+        );
+        assert!(clients.get("billing").await.unwrap().unwrap().disabled);
+        assert_eq!(clients.get("unknown").await.unwrap(), None);
+        assert_eq!(
+            clients
+                .set_disabled("unknown", true)
+                .await
+                .unwrap_err()
+                .kind,
+            ErrorKind::NotFound
+        );
+    }
+
+    // Test checks if device codes are decided once and polled by their own client only.
+    #[tokio::test]
+    async fn test_memory_device_codes() {
+        let codes = MemoryDeviceCodeRepo::default();
+        let new = |user_code: &str, ttl: i64| NewDeviceCode {
+            client_id: "tv".to_string(),
+            device_code: format!("device-{}", user_code),
+            user_code: user_code.to_string(),
+            scopes: vec!["profile".to_string()],
+            expires_at: Utc::now() + Duration::seconds(ttl),
+        };
+
+        let code = codes.create(new("BCDFGHJK", 600)).await.unwrap();
+        codes.create(new("LMNPQRST", -1)).await.unwrap();
+        assert_eq!(
+            codes.create(new("BCDFGHJK", 600)).await.unwrap_err().code,
+            "oauth.user_code_taken"
+        );
+        assert_eq!(codes.find_pending("LMNPQRST").await.unwrap(), None);
+
+        let user_id = Uuid::new_v4();
+        assert!(codes.decide(code.id, user_id, true).await.unwrap());
+        assert!(!codes.decide(code.id, user_id, false).await.unwrap());
+        assert_eq!(codes.find_pending("BCDFGHJK").await.unwrap(), None);
+
+        assert_eq!(codes.poll("radio", "device-BCDFGHJK").await.unwrap(), None);
+        let first = codes.poll("tv", "device-BCDFGHJK").await.unwrap().unwrap();
+        let second = codes.poll("tv", "device-BCDFGHJK").await.unwrap().unwrap();
+        assert_eq!(first.status, DeviceCodeStatus::Approved);
+        assert_eq!(first.user_id, Some(user_id));
+        assert_eq!(first.tenant.as_deref(), Some(DEFAULT_TENANT));
+        assert_eq!(first.polled_at, None);
+        assert!(second.polled_at.is_some());
+
+        assert_eq!(codes.purge().await.unwrap(), 1);
+        assert!(codes.delete(code.id).await.unwrap());
+        assert!(!codes.delete(code.id).await.unwrap());
+    }
+
+    // Test checks if opaque tokens resolve until they are revoked or expire.
+    #[tokio::test]
+    async fn test_memory_access_tokens() {
+        let tokens = MemoryAccessTokenRepo::default();
+        let claims = |ttl: i64| AccessClaims {
+            iss: "axum-auth".to_string(),
+            sub: "billing".to_string(),
+            aud: "axum-auth".to_string(),
+            iat: Utc::now().timestamp(),
+            exp: Utc::now().timestamp() + ttl,
+            scope: "users:read".to_string(),
+            sid: None,
+        };
+
+        tokens.store("at_live", &claims(600)).await.unwrap();
+        tokens.store("at_expired", &claims(-1)).await.unwrap();
+        assert_eq!(
+            tokens.resolve("at_live").await.unwrap().unwrap().sub,
+            "billing"
+        );
+        assert_eq!(tokens.resolve("at_expired").await.unwrap(), None);
+        assert_eq!(tokens.resolve("at_unknown").await.unwrap(), None);
+
+        assert!(tokens.revoke("at_live").await.unwrap());
+        assert!(!tokens.revoke("at_live").await.unwrap());
+        assert_eq!(tokens.resolve("at_live").await.unwrap(), None);
+        assert_eq!(tokens.purge().await.unwrap(), 2);
+    }
+
+    // Test checks if rotations retire the current key until the end of its grace period.
+    #[tokio::test]
+    async fn test_memory_signing_keys() {
+        let keys = MemorySigningKeyRepo::default();
+        let new = |kid: &str| NewSigningKey {
+            kid: kid.to_string(),
+            document: kid.as_bytes().to_vec(),
+        };
+
+        assert!(keys.rotate(new("first"), Utc::now()).await.unwrap());
+        assert!(keys
+            .rotate(new("second"), Utc::now() + Duration::hours(1))
+            .await
+            .unwrap());
+        assert!(!keys.rotate(new("first"), Utc::now()).await.unwrap());
+
+        let active = keys.active().await.unwrap();
+        let kids: Vec<&str> = active.iter().map(|key| key.kid.as_str()).collect();
+        assert_eq!(kids, ["second", "first"]);
+        assert_eq!(active[0].expires_at, None);
+        assert!(active[1].expires_at.is_some());
+
+        assert!(keys
+            .rotate(new("third"), Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap());
+        let active = keys.active().await.unwrap();
+        let kids: Vec<&str> = active.iter().map(|key| key.kid.as_str()).collect();
+        assert_eq!(kids, ["third", "first"]);
+        assert_eq!(keys.purge().await.unwrap(), 1);
+    }
+
+    // Test checks if users and sessions of one tenant are invisible in another.
+    #[tokio::test]
+    async fn test_memory_tenant_isolation() {
+        let users = MemoryUserRepo::default();
+        let sessions = MemorySessionRepo::default();
+        let stored = user("user@example.com", None, 0);
+        let session = with_tenant("acme", async {
+            users.insert(stored.clone(), None);
+            sessions
+                .create(NewSession {
+                    user_id: stored.id,
+                    ip: None,
+                    user_agent: None,
+                    expires_at: Utc::now() + Duration::hours(1),
+                })
+                .await
+                .unwrap()
+        })
+        .await;
+
+        assert_eq!(
+            users.get(stored.id).await.unwrap_err().kind,
+            ErrorKind::NotFound
+        );
+        assert_eq!(users.find_by_email("user@example.com").await.unwrap(), None);
+        assert_eq!(sessions.get(session.id).await.unwrap(), None);
+        assert!(!sessions.revoke(session.id).await.unwrap());
+
+        with_tenant("acme", async {
+            assert_eq!(users.get(stored.id).await.unwrap(), stored);
+            assert_eq!(sessions.get(session.id).await.unwrap(), Some(session));
+        })
+        .await;
+    }
+
+    // Test checks if tenant IDs are unique and tenants can be disabled.
+    #[tokio::test]
+    async fn test_tenants() {
+        let tenants = MemoryTenantRepo::default();
+
+        let tenant = tenants.create("acme", "Acme Corp").await.unwrap();
+        assert_eq!(tenant.name, "Acme Corp");
+        assert_eq!(
+            tenants.create("acme", "Acme").await.unwrap_err().code,
+            "tenant.taken"
+        );
+        assert!(tenants.set_disabled("acme", true).await.unwrap().disabled);
+        assert!(tenants.get("acme").await.unwrap().unwrap().disabled);
+        assert_eq!(tenants.get("unknown").await.unwrap(), None);
+        assert_eq!(
+            tenants
+                .set_disabled("unknown", true)
+                .await
+                .unwrap_err()
+                .kind,
+            ErrorKind::NotFound
+        );
+    }
+
+    // Test checks if role definitions are versioned and scoped to the tenant.
+    #[tokio::test]
+    async fn test_role_definitions() {
+        let roles = MemoryRoleDefinitionRepo::default();
+        let role = NewRole {
+            name: "support".to_string(),
+            description: None,
+            permissions: vec!["users.manage".to_string()],
+        };
+
+        let created = roles.create(role.clone()).await.unwrap();
+
+        assert_eq!(created.version, 1);
+        assert_eq!(
+            roles.create(role.clone()).await.unwrap_err().code,
+            "roles.taken"
+        );
+
+        let update = NewRole {
+            permissions: vec!["users.manage".to_string(), "audit.read".to_string()],
+            ..role.clone()
+        };
+        let updated = roles.update(update.clone(), 1).await.unwrap();
+
+        assert_eq!(updated.version, 2);
+        assert_eq!(updated.permissions.len(), 2);
+        assert_eq!(
+            roles.update(update.clone(), 1).await.unwrap_err().code,
+            "roles.version_conflict"
+        );
+
+        let other = with_tenant("other".to_string(), async {
+            (roles.list().await.unwrap(), roles.update(update, 2).await)
+        })
+        .await;
+
+        assert!(other.0.is_empty());
+        assert_eq!(other.1.unwrap_err().kind, ErrorKind::NotFound);
+        assert_eq!(roles.list().await.unwrap(), vec![updated]);
+        assert!(roles.delete("support").await.unwrap());
+        assert_eq!(roles.get("support").await.unwrap(), None);
+    }
+}
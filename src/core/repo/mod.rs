@@ -0,0 +1,1122 @@
+//! Repository module.
+//!
+//! Module defines the storage of users, sessions, login
+//! devices, tokens, opaque access tokens, signing keys,
+//! OAuth clients, device codes, tenants and role
+//! definitions as traits. Users, sessions, devices,
+//! tokens and role definitions are scoped to the tenant
+//! of `tenancy::current_tenant`. Handlers and services
+//! depend on the traits only, `postgres` implements them on the database
+//! and `memory` keeps the data in memory, so handler logic
+//! can be tested without a running database.
+
+// References to submodules
+pub mod cleanup;
+pub mod memory;
+pub mod postgres;
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ring::constant_time::verify_slices_are_equal;
+use sha2::{Digest, Sha256};
+use std::{fmt, str::FromStr};
+use uuid::Uuid;
+
+// Local imports
+use super::auth::password::StoredHash;
+use super::auth::rbac::Scope;
+use super::err::{AppError, ErrorKind};
+use super::http::pagination::Window;
+use super::tenancy::Tenant;
+use super::users::{roles::Role, NewUser, User, UserFilter};
+
+// Re-exports of the wire types
+pub use crate::api::sessions::{Device, Session};
+pub use crate::api::tokens::AccessClaims;
+
+/// ## User repository trait.
+#[async_trait]
+pub trait UserRepo: Send + Sync {
+    /// ## Lists users.
+    ///
+    /// ## Parameters
+    /// - `filter`: `&UserFilter` - Filters of the list.
+    /// - `scope`: `&Scope` - Organizations the caller may see.
+    /// - `window`: `&Window` - Page and sort of the list.
+    ///
+    /// ## Returns
+    /// + `Result<(Vec<User>, i64), AppError>`
+    ///     - `Ok((Vec<User>, i64))`: Users of the page, followed by the
+    ///       first user of the next page if there is one, and the number
+    ///       of users matching the filter.
+    ///     - `Err(AppError)`: If the query failed.
+    async fn list(
+        &self,
+        filter: &UserFilter,
+        scope: &Scope,
+        window: &Window,
+    ) -> Result<(Vec<User>, i64), AppError>;
+
+    /// ## Returns the user.
+    ///
+    /// ## Returns
+    /// + `Result<User, AppError>`
+    ///     - `Ok(User)`: User with the ID.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn get(&self, id: Uuid) -> Result<User, AppError>;
+
+    /// ## Returns the user and password hash of the email.
+    ///
+    /// ## Returns
+    /// + `Result<Option<(User, StoredHash)>, AppError>`
+    ///     - `Ok(Some((User, StoredHash)))`: User with a password.
+    ///     - `Ok(None)`: If there is no such user or it has no password.
+    ///     - `Err(AppError)`: If the query failed or the algorithm is unknown.
+    async fn credentials(&self, email: &str) -> Result<Option<(User, StoredHash)>, AppError>;
+
+    /// ## Returns the user of the email.
+    ///
+    /// ## Returns
+    /// + `Result<Option<User>, AppError>`
+    ///     - `Ok(Some(User))`: User with the email.
+    ///     - `Ok(None)`: If there is no such user.
+    ///     - `Err(AppError)`: If the query failed.
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError>;
+
+    /// ## Returns the ID and email of every user, ordered by email.
+    async fn emails(&self) -> Result<Vec<(Uuid, String)>, AppError>;
+
+    /// ## Creates a user without any role.
+    ///
+    /// ## Returns
+    /// + `Result<User, AppError>`
+    ///     - `Ok(User)`: Created user.
+    ///     - `Err(AppError)`: `users.email_taken` if the email is already
+    ///       registered.
+    async fn create(&self, user: NewUser) -> Result<User, AppError>;
+
+    /// ## Creates a user together with its role grants.
+    ///
+    /// The user, the disabled flag and every grant are
+    /// stored at once, so a failed row of an import leaves
+    /// nothing behind.
+    ///
+    /// ## Parameters
+    /// - `user`: `NewUser` - User to create.
+    /// - `disabled`: `bool` - If the user starts disabled.
+    /// - `roles`: `&[String]` - Roles granted to the user.
+    ///
+    /// ## Returns
+    /// + `Result<User, AppError>`
+    ///     - `Ok(User)`: Created user.
+    ///     - `Err(AppError)`: `users.email_taken` if the email is already
+    ///       registered.
+    async fn import(
+        &self,
+        user: NewUser,
+        disabled: bool,
+        roles: &[String],
+    ) -> Result<User, AppError>;
+
+    /// ## Grants the role to the user, unless it was granted already.
+    ///
+    /// ## Returns
+    /// + `Result<bool, AppError>`
+    ///     - `Ok(bool)`: `false` if the user already had the role.
+    ///     - `Err(AppError)`: If the insert failed.
+    async fn grant(&self, id: Uuid, role: &str) -> Result<bool, AppError>;
+
+    /// ## Returns the roles granted to the user, ordered by name.
+    async fn roles(&self, id: Uuid) -> Result<Vec<String>, AppError>;
+
+    /// ## Replaces the display name of the user.
+    ///
+    /// ## Returns
+    /// + `Result<User, AppError>`
+    ///     - `Ok(User)`: Updated user.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn set_display_name(&self, id: Uuid, name: Option<&str>) -> Result<User, AppError>;
+
+    /// ## Stores a new password hash of the user.
+    ///
+    /// Password age starts over and a required password
+    /// change is fulfilled.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the hash was stored.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn set_password(&self, id: Uuid, hash: &StoredHash) -> Result<(), AppError>;
+
+    /// ## Disables or enables the user.
+    ///
+    /// ## Returns
+    /// + `Result<User, AppError>`
+    ///     - `Ok(User)`: Updated user.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn set_disabled(&self, id: Uuid, disabled: bool) -> Result<User, AppError>;
+
+    /// ## Requires the user to change the password on the next login.
+    ///
+    /// ## Returns
+    /// + `Result<User, AppError>`
+    ///     - `Ok(User)`: Updated user.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn require_password_reset(&self, id: Uuid) -> Result<User, AppError>;
+
+    /// ## Returns the time the password of the user was set.
+    ///
+    /// ## Returns
+    /// + `Result<Option<DateTime<Utc>>, AppError>`
+    ///     - `Ok(Some(DateTime<Utc>))`: Time the password was set.
+    ///     - `Ok(None)`: If the user has no password.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn password_changed_at(&self, id: Uuid) -> Result<Option<DateTime<Utc>>, AppError>;
+
+    /// ## Deletes the user.
+    ///
+    /// User is soft deleted. Deleted users are hidden from
+    /// every repository, their sessions and tokens are no
+    /// longer active, until `purge_deleted` removes them.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the user was deleted.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn delete(&self, id: Uuid) -> Result<(), AppError>;
+
+    /// ## Removes users deleted before the time, returns how many.
+    ///
+    /// Sessions, tokens and roles of the users are removed
+    /// with them.
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> Result<u64, AppError>;
+
+    /// ## Stores the email waiting for confirmation.
+    ///
+    /// A pending email of an earlier request is replaced.
+    /// The current email is kept as the previous one, so
+    /// the change can be undone.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the email was stored.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn set_pending_email(&self, id: Uuid, email: &str) -> Result<(), AppError>;
+
+    /// ## Replaces the email with the pending one.
+    ///
+    /// ## Returns
+    /// + `Result<User, AppError>`
+    ///     - `Ok(User)`: User with the new email.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user or no
+    ///       pending email, `users.email_taken` if another user took
+    ///       the address in the meantime.
+    async fn confirm_pending_email(&self, id: Uuid) -> Result<User, AppError>;
+
+    /// ## Undoes the email change.
+    ///
+    /// Pending email is dropped and the email the user had
+    /// when the change was requested is restored, whether
+    /// the change was confirmed or not.
+    ///
+    /// ## Returns
+    /// + `Result<User, AppError>`
+    ///     - `Ok(User)`: User with the previous email.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user or no
+    ///       change to undo, `users.email_taken` if another user took
+    ///       the previous address in the meantime.
+    async fn undo_email_change(&self, id: Uuid) -> Result<User, AppError>;
+
+    /// ## Schedules the removal of the user.
+    ///
+    /// An earlier schedule is replaced. The user stays
+    /// live until the deletion job removes it.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the deletion was scheduled.
+    ///     - `Err(AppError)`: `NotFound` if there is no such user.
+    async fn schedule_deletion(
+        &self,
+        id: Uuid,
+        delete_after: DateTime<Utc>,
+    ) -> Result<(), AppError>;
+
+    /// ## Cancels the scheduled removal, returns if one was scheduled.
+    async fn cancel_deletion(&self, id: Uuid) -> Result<bool, AppError>;
+
+    /// ## Removes users scheduled for deletion before the time.
+    ///
+    /// Sessions, tokens and roles of the users are removed
+    /// with them.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<(Uuid, String)>, AppError>`
+    ///     - `Ok(Vec<(Uuid, String)>)`: ID and tenant of every removed user.
+    ///     - `Err(AppError)`: If the delete failed.
+    async fn purge_scheduled(&self, before: DateTime<Utc>)
+        -> Result<Vec<(Uuid, String)>, AppError>;
+
+    /// ## Marks the expiry warning of passwords set before the time.
+    ///
+    /// Every password is warned about once, setting a new
+    /// password allows the next warning. Users of every
+    /// tenant are marked, disabled ones and those already
+    /// required to change their password are skipped.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<(User, String)>, AppError>`
+    ///     - `Ok(Vec<(User, String)>)`: Every warned user and its tenant.
+    ///     - `Err(AppError)`: If the update failed.
+    async fn warn_password_expiry(
+        &self,
+        changed_before: DateTime<Utc>,
+    ) -> Result<Vec<(User, String)>, AppError>;
+
+    /// ## Requires a new password of the users that set theirs before the time.
+    ///
+    /// Users of every tenant are updated, disabled ones and
+    /// those already required to change their password are
+    /// skipped.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<(User, String)>, AppError>`
+    ///     - `Ok(Vec<(User, String)>)`: Every expired user and its tenant.
+    ///     - `Err(AppError)`: If the update failed.
+    async fn expire_passwords(
+        &self,
+        changed_before: DateTime<Utc>,
+    ) -> Result<Vec<(User, String)>, AppError>;
+}
+
+/// ## New session struct.
+///
+/// ## Fields
+/// + `user_id`: `Uuid` - User that logged in.
+/// + `ip`: `Option<String>` - Client address of the login.
+/// + `user_agent`: `Option<String>` - User agent of the login.
+/// + `expires_at`: `DateTime<Utc>` - Time the session expires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewSession {
+    pub user_id: Uuid,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// ## Session repository trait.
+///
+/// Only active sessions, neither revoked nor expired,
+/// are returned.
+#[async_trait]
+pub trait SessionRepo: Send + Sync {
+    /// ## Creates a session.
+    async fn create(&self, session: NewSession) -> Result<Session, AppError>;
+
+    /// ## Returns the active session.
+    async fn get(&self, id: Uuid) -> Result<Option<Session>, AppError>;
+
+    /// ## Lists the active sessions of the user, newest first.
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>, AppError>;
+
+    /// ## Marks the session as used now.
+    async fn touch(&self, id: Uuid) -> Result<(), AppError>;
+
+    /// ## Revokes the session.
+    ///
+    /// ## Returns
+    /// + `Result<bool, AppError>`
+    ///     - `Ok(bool)`: If an active session was revoked.
+    ///     - `Err(AppError)`: If the update failed.
+    async fn revoke(&self, id: Uuid) -> Result<bool, AppError>;
+
+    /// ## Revokes every session of the user, returns how many.
+    async fn revoke_all(&self, user_id: Uuid) -> Result<u64, AppError>;
+}
+
+/// ## Login from a device struct.
+///
+/// ## Fields
+/// + `user_id`: `Uuid` - User that logged in.
+/// + `fingerprint`: `String` - Fingerprint of the device.
+/// + `user_agent`: `Option<String>` - User agent of the login.
+/// + `ip`: `Option<String>` - Client address of the login.
+/// + `country`: `Option<String>` - Country of the client address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceLogin {
+    pub user_id: Uuid,
+    pub fingerprint: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub country: Option<String>,
+}
+
+/// ## Recorded login from a device struct.
+///
+/// ## Fields
+/// + `device`: `Device` - Device after the login was recorded.
+/// + `first_login`: `bool` - User had no known device before.
+/// + `new_device`: `bool` - Device was not known before.
+/// + `new_country`: `bool` - No device of the user logged in from
+///   the country before.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSighting {
+    pub device: Device,
+    pub first_login: bool,
+    pub new_device: bool,
+    pub new_country: bool,
+}
+
+/// ## Login device repository trait.
+#[async_trait]
+pub trait DeviceRepo: Send + Sync {
+    /// ## Records the login, the device is added when unknown.
+    ///
+    /// ## Returns
+    /// + `Result<DeviceSighting, AppError>`
+    ///     - `Ok(DeviceSighting)`: Device and what was new about the login.
+    ///     - `Err(AppError)`: If the query failed.
+    async fn record(&self, login: DeviceLogin) -> Result<DeviceSighting, AppError>;
+
+    /// ## Lists the known devices of the user, last seen first.
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Device>, AppError>;
+}
+
+/// ## Token kind enum.
+///
+/// # Variants
+/// - `Access` - `access`, short-lived token of the API.
+/// - `Refresh` - `refresh`, exchanged for new access tokens.
+/// - `ApiKey` - `api_key`, long-lived token of scripts and services.
+/// - `EmailChange` - `email_change`, confirms the new email of a user.
+/// - `EmailUndo` - `email_undo`, restores the previous email of a user.
+/// - `MagicLink` - `magic_link`, single-use passwordless login.
+/// - `DeletionCancel` - `deletion_cancel`, cancels the scheduled deletion
+///   of an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Access,
+    Refresh,
+    ApiKey,
+    EmailChange,
+    EmailUndo,
+    MagicLink,
+    DeletionCancel,
+}
+
+impl TokenKind {
+    /// Every kind.
+    pub const ALL: [TokenKind; 7] = [
+        TokenKind::Access,
+        TokenKind::Refresh,
+        TokenKind::ApiKey,
+        TokenKind::EmailChange,
+        TokenKind::EmailUndo,
+        TokenKind::MagicLink,
+        TokenKind::DeletionCancel,
+    ];
+
+    /// ## Returns the name stored in the `kind` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenKind::Access => "access",
+            TokenKind::Refresh => "refresh",
+            TokenKind::ApiKey => "api_key",
+            TokenKind::EmailChange => "email_change",
+            TokenKind::EmailUndo => "email_undo",
+            TokenKind::MagicLink => "magic_link",
+            TokenKind::DeletionCancel => "deletion_cancel",
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TokenKind {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        TokenKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::Validation,
+                    format!("Unknown token kind '{}'", value),
+                    None,
+                )
+            })
+    }
+}
+
+/// ## New token struct.
+///
+/// ## Fields
+/// + `kind`: `TokenKind` - Kind of the token.
+/// + `user_id`: `Uuid` - User the token was issued to.
+/// + `session_id`: `Option<Uuid>` - Session the token belongs to.
+/// + `token`: `String` - Token as given to the client, only its hash is stored.
+/// + `expires_at`: `DateTime<Utc>` - Time the token expires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewToken {
+    pub kind: TokenKind,
+    pub user_id: Uuid,
+    pub session_id: Option<Uuid>,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// ## Stored token struct.
+///
+/// ## Fields
+/// + `id`: `Uuid` - ID of the token.
+/// + `kind`: `TokenKind` - Kind of the token.
+/// + `user_id`: `Uuid` - User the token was issued to.
+/// + `session_id`: `Option<Uuid>` - Session the token belongs to.
+/// + `hash`: `String` - SHA-256 hash of the token, see `hash_token`.
+/// + `created_at`: `DateTime<Utc>` - Time the token was issued.
+/// + `expires_at`: `DateTime<Utc>` - Time the token expires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub id: Uuid,
+    pub kind: TokenKind,
+    pub user_id: Uuid,
+    pub session_id: Option<Uuid>,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// ## Token repository trait.
+///
+/// Only active tokens, neither revoked nor expired,
+/// are returned.
+#[async_trait]
+pub trait TokenRepo: Send + Sync {
+    /// ## Stores the hash of a new token.
+    async fn store(&self, token: NewToken) -> Result<Token, AppError>;
+
+    /// ## Returns the active token of the kind.
+    ///
+    /// ## Parameters
+    /// - `kind`: `TokenKind` - Kind of the token.
+    /// - `token`: `&str` - Token as given by the client.
+    async fn find(&self, kind: TokenKind, token: &str) -> Result<Option<Token>, AppError>;
+
+    /// ## Returns the revoked token of the kind that has not expired.
+    ///
+    /// Used to notice rotated refresh tokens presented
+    /// again, until `purge` deletes them.
+    ///
+    /// ## Parameters
+    /// - `kind`: `TokenKind` - Kind of the token.
+    /// - `token`: `&str` - Token as given by the client.
+    async fn find_revoked(&self, kind: TokenKind, token: &str) -> Result<Option<Token>, AppError>;
+
+    /// ## Lists the active tokens of the kind of the user, newest first.
+    async fn list(&self, user_id: Uuid, kind: TokenKind) -> Result<Vec<Token>, AppError>;
+
+    /// ## Revokes the token, returns if an active token was revoked.
+    async fn revoke(&self, id: Uuid) -> Result<bool, AppError>;
+
+    /// ## Revokes every token of the session, returns how many.
+    async fn revoke_session(&self, session_id: Uuid) -> Result<u64, AppError>;
+
+    /// ## Revokes every token of the user, returns how many.
+    async fn revoke_user(&self, user_id: Uuid) -> Result<u64, AppError>;
+
+    /// ## Deletes expired and revoked tokens, returns how many.
+    async fn purge(&self) -> Result<u64, AppError>;
+
+    /// ## Marks the expiry warning of active tokens of the kind.
+    ///
+    /// Tokens issued before `created_before` or expiring
+    /// before `expires_before` are warned about once. Tokens
+    /// of every tenant are marked.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<(Token, String)>, AppError>`
+    ///     - `Ok(Vec<(Token, String)>)`: Every warned token and the tenant
+    ///       of its user.
+    ///     - `Err(AppError)`: If the update failed.
+    async fn warn_expiry(
+        &self,
+        kind: TokenKind,
+        created_before: DateTime<Utc>,
+        expires_before: DateTime<Utc>,
+    ) -> Result<Vec<(Token, String)>, AppError>;
+
+    /// ## Revokes the active tokens of the kind issued before the time.
+    ///
+    /// Tokens of every tenant are revoked.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<(Token, String)>, AppError>`
+    ///     - `Ok(Vec<(Token, String)>)`: Every revoked token and the tenant
+    ///       of its user.
+    ///     - `Err(AppError)`: If the update failed.
+    async fn expire_issued_before(
+        &self,
+        kind: TokenKind,
+        created_before: DateTime<Utc>,
+    ) -> Result<Vec<(Token, String)>, AppError>;
+}
+
+/// ## Opaque access token repository trait.
+///
+/// Opaque tokens are references to their claims, only
+/// the hash of a token is stored. Tokens are not scoped
+/// to a tenant, the session of user tokens is.
+#[async_trait]
+pub trait AccessTokenRepo: Send + Sync {
+    /// ## Stores the claims of a new token.
+    ///
+    /// ## Parameters
+    /// - `token`: `&str` - Token as given to the client.
+    /// - `claims`: `&AccessClaims` - Claims the token stands for.
+    async fn store(&self, token: &str, claims: &AccessClaims) -> Result<(), AppError>;
+
+    /// ## Returns the claims of the token, unless it is revoked or expired.
+    async fn resolve(&self, token: &str) -> Result<Option<AccessClaims>, AppError>;
+
+    /// ## Revokes the token, returns if an active token was revoked.
+    async fn revoke(&self, token: &str) -> Result<bool, AppError>;
+
+    /// ## Deletes expired and revoked tokens, returns how many.
+    async fn purge(&self) -> Result<u64, AppError>;
+}
+
+/// ## New OAuth client struct.
+///
+/// ## Fields
+/// + `client_id`: `String` - Public ID of the client.
+/// + `secret`: `String` - Secret as given to the client, only its hash is stored.
+/// + `name`: `String` - Display name of the client.
+/// + `scopes`: `Vec<String>` - Scopes the client may request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewClient {
+    pub client_id: String,
+    pub secret: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+}
+
+/// ## Stored OAuth client struct.
+///
+/// ## Fields
+/// + `client_id`: `String` - Public ID of the client.
+/// + `secret_hash`: `String` - SHA-256 hash of the secret, see `hash_token`.
+/// + `name`: `String` - Display name of the client.
+/// + `scopes`: `Vec<String>` - Scopes the client may request.
+/// + `disabled`: `bool` - Disabled clients get no tokens.
+/// + `created_at`: `DateTime<Utc>` - Time the client was registered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Client {
+    pub client_id: String,
+    pub secret_hash: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub disabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Client {
+    /// ## Checks if the secret belongs to the client.
+    ///
+    /// Hashes are compared in constant time, so the time
+    /// of the comparison tells nothing about the secret.
+    pub fn verify_secret(&self, secret: &str) -> bool {
+        verify_slices_are_equal(hash_token(secret).as_bytes(), self.secret_hash.as_bytes()).is_ok()
+    }
+
+    /// ## Checks if the client may request the scope.
+    pub fn allows(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|allowed| allowed == scope)
+    }
+}
+
+/// ## OAuth client repository trait.
+#[async_trait]
+pub trait ClientRepo: Send + Sync {
+    /// ## Registers a client.
+    ///
+    /// ## Returns
+    /// + `Result<Client, AppError>`
+    ///     - `Ok(Client)`: Registered client.
+    ///     - `Err(AppError)`: `oauth.client_taken` if the ID is
+    ///       already registered.
+    async fn create(&self, client: NewClient) -> Result<Client, AppError>;
+
+    /// ## Returns the client, disabled ones included.
+    async fn get(&self, client_id: &str) -> Result<Option<Client>, AppError>;
+
+    /// ## Disables or enables the client.
+    ///
+    /// ## Returns
+    /// + `Result<Client, AppError>`
+    ///     - `Ok(Client)`: Updated client.
+    ///     - `Err(AppError)`: `NotFound` if there is no such client.
+    async fn set_disabled(&self, client_id: &str, disabled: bool) -> Result<Client, AppError>;
+}
+
+/// ## Device code status enum.
+///
+/// # Variants
+/// - `Pending` - `pending`, the user has not decided yet.
+/// - `Approved` - `approved`, the device may get a token.
+/// - `Denied` - `denied`, the user refused the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceCodeStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+impl DeviceCodeStatus {
+    /// Every status.
+    pub const ALL: [DeviceCodeStatus; 3] = [
+        DeviceCodeStatus::Pending,
+        DeviceCodeStatus::Approved,
+        DeviceCodeStatus::Denied,
+    ];
+
+    /// ## Returns the name stored in the `status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceCodeStatus::Pending => "pending",
+            DeviceCodeStatus::Approved => "approved",
+            DeviceCodeStatus::Denied => "denied",
+        }
+    }
+}
+
+impl fmt::Display for DeviceCodeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for DeviceCodeStatus {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        DeviceCodeStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::Validation,
+                    format!("Unknown device code status '{}'", value),
+                    None,
+                )
+            })
+    }
+}
+
+/// ## New device code struct.
+///
+/// ## Fields
+/// + `client_id`: `String` - Client the device authenticates as.
+/// + `device_code`: `String` - Code the device polls with, only its hash is stored.
+/// + `user_code`: `String` - Normalized code the user enters.
+/// + `scopes`: `Vec<String>` - Scopes granted once the user approves.
+/// + `expires_at`: `DateTime<Utc>` - Time both codes expire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewDeviceCode {
+    pub client_id: String,
+    pub device_code: String,
+    pub user_code: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// ## Stored device code struct.
+///
+/// Device codes are requested before any user is known,
+/// so they belong to no tenant until a user decides.
+///
+/// ## Fields
+/// + `id`: `Uuid` - ID of the code.
+/// + `client_id`: `String` - Client the device authenticates as.
+/// + `user_code`: `String` - Normalized code the user enters.
+/// + `scopes`: `Vec<String>` - Scopes granted once the user approves.
+/// + `status`: `DeviceCodeStatus` - Decision of the user.
+/// + `user_id`: `Option<Uuid>` - User who decided.
+/// + `tenant`: `Option<String>` - Tenant of the user who decided.
+/// + `polled_at`: `Option<DateTime<Utc>>` - Time the device last polled.
+/// + `created_at`: `DateTime<Utc>` - Time the code was issued.
+/// + `expires_at`: `DateTime<Utc>` - Time the code expires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceCode {
+    pub id: Uuid,
+    pub client_id: String,
+    pub user_code: String,
+    pub scopes: Vec<String>,
+    pub status: DeviceCodeStatus,
+    pub user_id: Option<Uuid>,
+    pub tenant: Option<String>,
+    pub polled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// ## Device code repository trait.
+///
+/// Codes of the device authorization grant, they are
+/// not scoped to a tenant.
+#[async_trait]
+pub trait DeviceCodeRepo: Send + Sync {
+    /// ## Stores a new device code.
+    ///
+    /// ## Returns
+    /// + `Result<DeviceCode, AppError>`
+    ///     - `Ok(DeviceCode)`: Stored code.
+    ///     - `Err(AppError)`: `oauth.user_code_taken` if an active code
+    ///       has the same user code.
+    async fn create(&self, code: NewDeviceCode) -> Result<DeviceCode, AppError>;
+
+    /// ## Returns the pending, unexpired code with the user code.
+    async fn find_pending(&self, user_code: &str) -> Result<Option<DeviceCode>, AppError>;
+
+    /// ## Records the decision of the user in the current tenant.
+    ///
+    /// ## Returns
+    /// + `Result<bool, AppError>`
+    ///     - `Ok(bool)`: If the code was still pending and unexpired.
+    ///     - `Err(AppError)`: If the update failed.
+    async fn decide(&self, id: Uuid, user_id: Uuid, approved: bool) -> Result<bool, AppError>;
+
+    /// ## Records a poll of the device, expired codes included.
+    ///
+    /// ## Returns
+    /// + `Result<Option<DeviceCode>, AppError>`
+    ///     - `Ok(Some(DeviceCode))`: Code of the client, `polled_at` is
+    ///       the time of the previous poll.
+    ///     - `Ok(None)`: If the client has no such code.
+    ///     - `Err(AppError)`: If the update failed.
+    async fn poll(
+        &self,
+        client_id: &str,
+        device_code: &str,
+    ) -> Result<Option<DeviceCode>, AppError>;
+
+    /// ## Deletes the code, returns if it existed.
+    async fn delete(&self, id: Uuid) -> Result<bool, AppError>;
+
+    /// ## Deletes expired codes, returns how many.
+    async fn purge(&self) -> Result<u64, AppError>;
+}
+
+/// ## New signing key struct.
+///
+/// ## Fields
+/// + `kid`: `String` - ID of the key, the `kid` header.
+/// + `document`: `Vec<u8>` - PKCS#8 document of the private key.
+#[derive(Clone, PartialEq)]
+pub struct NewSigningKey {
+    pub kid: String,
+    pub document: Vec<u8>,
+}
+
+impl fmt::Debug for NewSigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NewSigningKey")
+            .field("kid", &self.kid)
+            .finish_non_exhaustive()
+    }
+}
+
+/// ## Stored signing key struct.
+///
+/// ## Fields
+/// + `kid`: `String` - ID of the key, the `kid` header.
+/// + `document`: `Vec<u8>` - PKCS#8 document of the private key.
+/// + `created_at`: `DateTime<Utc>` - Time the key was stored.
+/// + `expires_at`: `Option<DateTime<Utc>>` - End of the grace period of
+///   a retired key, `None` for the current key.
+#[derive(Clone, PartialEq)]
+pub struct StoredSigningKey {
+    pub kid: String,
+    pub document: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl fmt::Debug for StoredSigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StoredSigningKey")
+            .field("kid", &self.kid)
+            .field("created_at", &self.created_at)
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+/// ## Signing key repository trait.
+///
+/// Keys of the access tokens with their private half,
+/// shared by every server and the worker. Keys are not
+/// scoped to a tenant.
+#[async_trait]
+pub trait SigningKeyRepo: Send + Sync {
+    /// ## Returns the keys that still verify tokens, the current one first.
+    async fn active(&self) -> Result<Vec<StoredSigningKey>, AppError>;
+
+    /// ## Stores the key as the current one and retires the previous one.
+    ///
+    /// ## Parameters
+    /// - `key`: `NewSigningKey` - New current key.
+    /// - `grace_until`: `DateTime<Utc>` - Time the retired key stops
+    ///   verifying tokens.
+    ///
+    /// ## Returns
+    /// + `Result<bool, AppError>`
+    ///     - `Ok(bool)`: If the key was stored, `false` if a key with the
+    ///       same ID exists, nothing is retired then.
+    ///     - `Err(AppError)`: If the update failed.
+    async fn rotate(
+        &self,
+        key: NewSigningKey,
+        grace_until: DateTime<Utc>,
+    ) -> Result<bool, AppError>;
+
+    /// ## Deletes keys past their grace period, returns how many.
+    async fn purge(&self) -> Result<u64, AppError>;
+}
+
+/// ## Tenant repository trait.
+#[async_trait]
+pub trait TenantRepo: Send + Sync {
+    /// ## Creates a tenant.
+    ///
+    /// ## Returns
+    /// + `Result<Tenant, AppError>`
+    ///     - `Ok(Tenant)`: Created tenant.
+    ///     - `Err(AppError)`: `tenant.taken` if the ID already exists.
+    async fn create(&self, id: &str, name: &str) -> Result<Tenant, AppError>;
+
+    /// ## Returns the tenant, disabled ones included.
+    async fn get(&self, id: &str) -> Result<Option<Tenant>, AppError>;
+
+    /// ## Disables or enables the tenant.
+    ///
+    /// ## Returns
+    /// + `Result<Tenant, AppError>`
+    ///     - `Ok(Tenant)`: Updated tenant.
+    ///     - `Err(AppError)`: `NotFound` if there is no such tenant.
+    async fn set_disabled(&self, id: &str, disabled: bool) -> Result<Tenant, AppError>;
+}
+
+/// ## New role definition struct.
+///
+/// ## Fields
+/// + `name`: `String` - Name of the role.
+/// + `description`: `Option<String>` - What the role is meant for.
+/// + `permissions`: `Vec<String>` - Names of the granted permissions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewRole {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+}
+
+/// ## Role definition repository trait.
+///
+/// Definitions are versioned, updates only apply to the
+/// version they are based on.
+#[async_trait]
+pub trait RoleDefinitionRepo: Send + Sync {
+    /// ## Defines a role at version 1.
+    ///
+    /// ## Returns
+    /// + `Result<Role, AppError>`
+    ///     - `Ok(Role)`: Defined role.
+    ///     - `Err(AppError)`: `roles.taken` if the role is already defined.
+    async fn create(&self, role: NewRole) -> Result<Role, AppError>;
+
+    /// ## Lists the defined roles, ordered by name.
+    async fn list(&self) -> Result<Vec<Role>, AppError>;
+
+    /// ## Returns the role definition.
+    async fn get(&self, name: &str) -> Result<Option<Role>, AppError>;
+
+    /// ## Replaces the role definition and increases its version.
+    ///
+    /// ## Parameters
+    /// - `role`: `NewRole` - New definition of the role.
+    /// - `version`: `i32` - Version the update is based on.
+    ///
+    /// ## Returns
+    /// + `Result<Role, AppError>`
+    ///     - `Ok(Role)`: Updated role.
+    ///     - `Err(AppError)`: `NotFound` if the role is not defined,
+    ///       `roles.version_conflict` if it was updated since `version`.
+    async fn update(&self, role: NewRole, version: i32) -> Result<Role, AppError>;
+
+    /// ## Deletes the role definition.
+    ///
+    /// ## Returns
+    /// + `Result<bool, AppError>`
+    ///     - `Ok(bool)`: `false` if the role was not defined.
+    ///     - `Err(AppError)`: If the delete failed.
+    async fn delete(&self, name: &str) -> Result<bool, AppError>;
+}
+
+/// ## Hashes a token for storage.
+///
+/// Tokens are random, so a fast unsalted hash is enough
+/// to keep a leaked table from being usable.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::repo::hash_token;
+///
+/// assert_eq!(hash_token("secret").len(), 64);
+/// ```
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// ## Builds the not found error of the user.
+pub(crate) fn user_not_found(id: Uuid) -> AppError {
+    AppError::new(
+        ErrorKind::NotFound,
+        format!("User '{}' not found", id),
+        None,
+    )
+}
+
+/// ## Builds the not found error of the client.
+pub(crate) fn client_not_found(client_id: &str) -> AppError {
+    AppError::new(
+        ErrorKind::NotFound,
+        format!("OAuth client '{}' not found", client_id),
+        None,
+    )
+}
+
+/// ## Builds the error of a client ID that is already registered.
+pub(crate) fn client_taken(client_id: &str) -> AppError {
+    AppError::validation(format!("OAuth client '{}' already exists", client_id))
+        .with_code("oauth.client_taken")
+        .with_status_hint(409)
+}
+
+/// ## Builds the error of a user code that is already in use.
+pub(crate) fn user_code_taken() -> AppError {
+    AppError::validation("User code is already in use")
+        .with_code("oauth.user_code_taken")
+        .with_status_hint(409)
+}
+
+/// ## Builds the not found error of the tenant.
+pub(crate) fn tenant_not_found(id: &str) -> AppError {
+    AppError::new(
+        ErrorKind::NotFound,
+        format!("Tenant '{}' not found", id),
+        None,
+    )
+}
+
+/// ## Builds the error of a tenant ID that already exists.
+pub(crate) fn tenant_taken(id: &str) -> AppError {
+    AppError::validation(format!("Tenant '{}' already exists", id))
+        .with_code("tenant.taken")
+        .with_status_hint(409)
+}
+
+/// ## Builds the not found error of the role.
+pub(crate) fn role_not_found(name: &str) -> AppError {
+    AppError::new(
+        ErrorKind::NotFound,
+        format!("Role '{}' is not defined", name),
+        None,
+    )
+}
+
+/// ## Builds the error of a role that is already defined.
+pub(crate) fn role_taken(name: &str) -> AppError {
+    AppError::validation(format!("Role '{}' already exists", name))
+        .with_code("roles.taken")
+        .with_status_hint(409)
+}
+
+/// ## Builds the error of an update based on an outdated version.
+pub(crate) fn role_version_conflict(name: &str) -> AppError {
+    AppError::validation(format!("Role '{}' was changed in the meantime", name))
+        .with_code("roles.version_conflict")
+        .with_status_hint(409)
+}
+
+/// ## Builds the error of an email that is already registered.
+pub(crate) fn email_taken() -> AppError {
+    AppError::validation("Email address is already taken")
+        .with_code("users.email_taken")
+        .with_status_hint(409)
+}
+
+/// ## Builds the not found error of a missing pending email.
+pub(crate) fn no_pending_email(id: Uuid) -> AppError {
+    AppError::new(
+        ErrorKind::NotFound,
+        format!("User '{}' has no email change", id),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if every token kind is parsed back from its name.
+    #[test]
+    fn test_token_kind_round_trip() {
+        for kind in TokenKind::ALL {
+            assert_eq!(kind.as_str().parse::<TokenKind>(), Ok(kind));
+        }
+
+        assert!("cookie".parse::<TokenKind>().is_err());
+    }
+
+    // Test checks if every device code status is parsed back from its name.
+    #[test]
+    fn test_device_code_status_round_trip() {
+        for status in DeviceCodeStatus::ALL {
+            assert_eq!(status.as_str().parse::<DeviceCodeStatus>(), Ok(status));
+        }
+
+        assert!("expired".parse::<DeviceCodeStatus>().is_err());
+    }
+
+    // Test checks if only the right secret matches the client.
+    #[test]
+    fn test_client_verify_secret() {
+        let client = Client {
+            client_id: "billing".to_string(),
+            secret_hash: hash_token("secret"),
+            name: "Billing".to_string(),
+            scopes: vec!["users:read".to_string()],
+            disabled: false,
+            created_at: Utc::now(),
+        };
+
+        assert!(client.verify_secret("secret"));
+        assert!(!client.verify_secret("Secret"));
+        assert!(client.allows("users:read"));
+        assert!(!client.allows("users:write"));
+    }
+
+    // Test checks if tokens are hashed with SHA-256.
+    #[test]
+    fn test_hash_token() {
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Postgres access token repository module.
+//!
+//! Module stores opaque access tokens in the
+//! `access_tokens` table, only the hash of a token is
+//! stored with its claims.
+
+// Imports from external crates
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, FromRow, PgPool, Row};
+
+// Local imports
+use super::db_error;
+use crate::core::err::AppError;
+use crate::core::repo::{hash_token, AccessClaims, AccessTokenRepo};
+
+/// Columns selected into `AccessClaims`, times as Unix seconds.
+const ACCESS_TOKEN_COLUMNS: &str = "issuer, subject, audience, scope, session_id, \
+     EXTRACT(EPOCH FROM issued_at)::BIGINT AS issued_at, \
+     EXTRACT(EPOCH FROM expires_at)::BIGINT AS expires_at";
+
+impl<'r> FromRow<'r, PgRow> for AccessClaims {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(AccessClaims {
+            iss: row.try_get("issuer")?,
+            sub: row.try_get("subject")?,
+            aud: row.try_get("audience")?,
+            iat: row.try_get("issued_at")?,
+            exp: row.try_get("expires_at")?,
+            scope: row.try_get("scope")?,
+            sid: row.try_get("session_id")?,
+        })
+    }
+}
+
+/// ## Postgres access token repository struct.
+#[derive(Debug, Clone)]
+pub struct PgAccessTokenRepo {
+    pool: PgPool,
+}
+
+impl PgAccessTokenRepo {
+    /// ## Creates a new `PgAccessTokenRepo` instance.
+    pub fn new(pool: PgPool) -> Self {
+        PgAccessTokenRepo { pool }
+    }
+}
+
+#[async_trait]
+impl AccessTokenRepo for PgAccessTokenRepo {
+    async fn store(&self, token: &str, claims: &AccessClaims) -> Result<(), AppError> {
+        sqlx::query(
+            "INSERT INTO access_tokens \
+             (token_hash, issuer, subject, audience, scope, session_id, issued_at, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, TO_TIMESTAMP($7), TO_TIMESTAMP($8))",
+        )
+        .bind(hash_token(token))
+        .bind(&claims.iss)
+        .bind(&claims.sub)
+        .bind(&claims.aud)
+        .bind(&claims.scope)
+        .bind(&claims.sid)
+        .bind(claims.iat as f64)
+        .bind(claims.exp as f64)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error("Failed to store access token"))?;
+
+        Ok(())
+    }
+
+    async fn resolve(&self, token: &str) -> Result<Option<AccessClaims>, AppError> {
+        let query = format!(
+            "SELECT {} FROM access_tokens \
+             WHERE token_hash = $1 AND revoked_at IS NULL AND expires_at > NOW()",
+            ACCESS_TOKEN_COLUMNS
+        );
+
+        sqlx::query_as::<_, AccessClaims>(&query)
+            .bind(hash_token(token))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error("Failed to load access token"))
+    }
+
+    async fn revoke(&self, token: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE access_tokens SET revoked_at = NOW() \
+             WHERE token_hash = $1 AND revoked_at IS NULL AND expires_at > NOW()",
+        )
+        .bind(hash_token(token))
+        .execute(&self.pool)
+        .await
+        .map_err(db_error("Failed to revoke access token"))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn purge(&self) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            "DELETE FROM access_tokens WHERE revoked_at IS NOT NULL OR expires_at <= NOW()",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(db_error("Failed to purge access tokens"))?;
+
+        Ok(result.rows_affected())
+    }
+}
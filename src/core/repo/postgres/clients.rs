@@ -0,0 +1,95 @@
+//! Postgres OAuth client repository module.
+//!
+//! Module stores the clients of the client credentials
+//! grant in the `oauth_clients` table, only the hash of
+//! a secret is stored.
+
+// Imports from external crates
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+// Local imports
+use super::db_error;
+use crate::core::db::DbExecutor;
+use crate::core::err::AppError;
+use crate::core::repo::{
+    client_not_found, client_taken, hash_token, Client, ClientRepo, NewClient,
+};
+
+/// Columns selected into `Client`.
+const CLIENT_COLUMNS: &str = "client_id, secret_hash, name, scopes, disabled, created_at";
+
+impl<'r> FromRow<'r, PgRow> for Client {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Client {
+            client_id: row.try_get("client_id")?,
+            secret_hash: row.try_get("secret_hash")?,
+            name: row.try_get("name")?,
+            scopes: row.try_get("scopes")?,
+            disabled: row.try_get("disabled")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+/// ## Postgres OAuth client repository struct.
+#[derive(Debug, Clone)]
+pub struct PgClientRepo {
+    db: DbExecutor,
+}
+
+impl PgClientRepo {
+    /// ## Creates a new `PgClientRepo` instance.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        PgClientRepo { db: db.into() }
+    }
+}
+
+#[async_trait]
+impl ClientRepo for PgClientRepo {
+    async fn create(&self, client: NewClient) -> Result<Client, AppError> {
+        let query = format!(
+            "INSERT INTO oauth_clients (client_id, secret_hash, name, scopes) \
+             VALUES ($1, $2, $3, $4) ON CONFLICT (client_id) DO NOTHING RETURNING {}",
+            CLIENT_COLUMNS
+        );
+
+        sqlx::query_as::<_, Client>(&query)
+            .bind(&client.client_id)
+            .bind(hash_token(&client.secret))
+            .bind(&client.name)
+            .bind(&client.scopes)
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to create OAuth client"))?
+            .ok_or_else(|| client_taken(&client.client_id))
+    }
+
+    async fn get(&self, client_id: &str) -> Result<Option<Client>, AppError> {
+        let query = format!(
+            "SELECT {} FROM oauth_clients WHERE client_id = $1",
+            CLIENT_COLUMNS
+        );
+
+        sqlx::query_as::<_, Client>(&query)
+            .bind(client_id)
+            .fetch_optional(self.db.read())
+            .await
+            .map_err(db_error("Failed to load OAuth client"))
+    }
+
+    async fn set_disabled(&self, client_id: &str, disabled: bool) -> Result<Client, AppError> {
+        let query = format!(
+            "UPDATE oauth_clients SET disabled = $2 WHERE client_id = $1 RETURNING {}",
+            CLIENT_COLUMNS
+        );
+
+        sqlx::query_as::<_, Client>(&query)
+            .bind(client_id)
+            .bind(disabled)
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to update OAuth client"))?
+            .ok_or_else(|| client_not_found(client_id))
+    }
+}
@@ -0,0 +1,166 @@
+//! Postgres device code repository module.
+//!
+//! Module stores the codes of the device authorization
+//! grant in the `device_codes` table, only the hash of
+//! a device code is stored.
+
+// Imports from external crates
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, FromRow, PgPool, Row};
+use uuid::Uuid;
+
+// Local imports
+use super::db_error;
+use crate::core::err::AppError;
+use crate::core::repo::{
+    hash_token, user_code_taken, DeviceCode, DeviceCodeRepo, DeviceCodeStatus, NewDeviceCode,
+};
+use crate::core::tenancy::current_tenant;
+
+/// Columns selected into `DeviceCode`.
+const DEVICE_CODE_COLUMNS: &str =
+    "id, client_id, user_code, scopes, status, user_id, tenant_id, polled_at, created_at, expires_at";
+
+impl<'r> FromRow<'r, PgRow> for DeviceCode {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let status: String = row.try_get("status")?;
+
+        Ok(DeviceCode {
+            id: row.try_get("id")?,
+            client_id: row.try_get("client_id")?,
+            user_code: row.try_get("user_code")?,
+            scopes: row.try_get("scopes")?,
+            status: status
+                .parse()
+                .map_err(|e: AppError| sqlx::Error::ColumnDecode {
+                    index: "status".to_string(),
+                    source: e.to_string().into(),
+                })?,
+            user_id: row.try_get("user_id")?,
+            tenant: row.try_get("tenant_id")?,
+            polled_at: row.try_get("polled_at")?,
+            created_at: row.try_get("created_at")?,
+            expires_at: row.try_get("expires_at")?,
+        })
+    }
+}
+
+/// ## Postgres device code repository struct.
+#[derive(Debug, Clone)]
+pub struct PgDeviceCodeRepo {
+    pool: PgPool,
+}
+
+impl PgDeviceCodeRepo {
+    /// ## Creates a new `PgDeviceCodeRepo` instance.
+    pub fn new(pool: PgPool) -> Self {
+        PgDeviceCodeRepo { pool }
+    }
+}
+
+#[async_trait]
+impl DeviceCodeRepo for PgDeviceCodeRepo {
+    async fn create(&self, code: NewDeviceCode) -> Result<DeviceCode, AppError> {
+        let query = format!(
+            "INSERT INTO device_codes \
+             (id, client_id, device_code_hash, user_code, scopes, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (user_code) DO NOTHING RETURNING {}",
+            DEVICE_CODE_COLUMNS
+        );
+
+        sqlx::query_as::<_, DeviceCode>(&query)
+            .bind(Uuid::new_v4())
+            .bind(&code.client_id)
+            .bind(hash_token(&code.device_code))
+            .bind(&code.user_code)
+            .bind(&code.scopes)
+            .bind(code.expires_at)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error("Failed to create device code"))?
+            .ok_or_else(user_code_taken)
+    }
+
+    async fn find_pending(&self, user_code: &str) -> Result<Option<DeviceCode>, AppError> {
+        let query = format!(
+            "SELECT {} FROM device_codes \
+             WHERE user_code = $1 AND status = $2 AND expires_at > NOW()",
+            DEVICE_CODE_COLUMNS
+        );
+
+        sqlx::query_as::<_, DeviceCode>(&query)
+            .bind(user_code)
+            .bind(DeviceCodeStatus::Pending.as_str())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error("Failed to load device code"))
+    }
+
+    async fn decide(&self, id: Uuid, user_id: Uuid, approved: bool) -> Result<bool, AppError> {
+        let status = if approved {
+            DeviceCodeStatus::Approved
+        } else {
+            DeviceCodeStatus::Denied
+        };
+
+        let result = sqlx::query(
+            "UPDATE device_codes SET status = $2, user_id = $3, tenant_id = $4 \
+             WHERE id = $1 AND status = $5 AND expires_at > NOW()",
+        )
+        .bind(id)
+        .bind(status.as_str())
+        .bind(user_id)
+        .bind(current_tenant())
+        .bind(DeviceCodeStatus::Pending.as_str())
+        .execute(&self.pool)
+        .await
+        .map_err(db_error("Failed to update device code"))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn poll(
+        &self,
+        client_id: &str,
+        device_code: &str,
+    ) -> Result<Option<DeviceCode>, AppError> {
+        // Rows of the CTE are read before the update, so the previous poll is returned
+        let query = format!(
+            "WITH previous AS ( \
+                 SELECT {} FROM device_codes \
+                 WHERE device_code_hash = $1 AND client_id = $2 FOR UPDATE \
+             ), polled AS ( \
+                 UPDATE device_codes SET polled_at = NOW() \
+                 WHERE id IN (SELECT id FROM previous) \
+             ) \
+             SELECT {} FROM previous",
+            DEVICE_CODE_COLUMNS, DEVICE_CODE_COLUMNS
+        );
+
+        sqlx::query_as::<_, DeviceCode>(&query)
+            .bind(hash_token(device_code))
+            .bind(client_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error("Failed to poll device code"))
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM device_codes WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error("Failed to delete device code"))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn purge(&self) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM device_codes WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(db_error("Failed to purge device codes"))?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,129 @@
+//! Postgres login device repository module.
+//!
+//! Module stores the devices users logged in from in the
+//! `login_devices` table. Devices of deleted users and of
+//! other tenants are not returned.
+
+// Imports from external crates
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, FromRow, Row};
+use uuid::Uuid;
+
+// Local imports
+use super::{db_error, LIVE_USER};
+use crate::core::db::DbExecutor;
+use crate::core::err::AppError;
+use crate::core::repo::{Device, DeviceLogin, DeviceRepo, DeviceSighting};
+use crate::core::tenancy::current_tenant;
+
+/// Columns selected into `Device`.
+const DEVICE_COLUMNS: &str =
+    "id, user_id, fingerprint, user_agent, ip, countries, first_seen_at, last_seen_at";
+
+impl<'r> FromRow<'r, PgRow> for Device {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Device {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            fingerprint: row.try_get("fingerprint")?,
+            user_agent: row.try_get("user_agent")?,
+            ip: row.try_get("ip")?,
+            countries: row.try_get("countries")?,
+            first_seen_at: row.try_get("first_seen_at")?,
+            last_seen_at: row.try_get("last_seen_at")?,
+        })
+    }
+}
+
+/// ## Postgres login device repository struct.
+#[derive(Debug, Clone)]
+pub struct PgDeviceRepo {
+    db: DbExecutor,
+}
+
+impl PgDeviceRepo {
+    /// ## Creates a new `PgDeviceRepo` instance.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        PgDeviceRepo { db: db.into() }
+    }
+}
+
+#[async_trait]
+impl DeviceRepo for PgDeviceRepo {
+    async fn record(&self, login: DeviceLogin) -> Result<DeviceSighting, AppError> {
+        let tenant = current_tenant();
+        let mut tx = self
+            .db
+            .write()
+            .begin()
+            .await
+            .map_err(db_error("Failed to record device"))?;
+
+        let known: Vec<(String, Vec<String>)> = sqlx::query_as(
+            "SELECT fingerprint, countries FROM login_devices \
+             WHERE user_id = $1 AND tenant_id = $2 FOR UPDATE",
+        )
+        .bind(login.user_id)
+        .bind(&tenant)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error("Failed to load devices"))?;
+
+        let new_device = !known
+            .iter()
+            .any(|(fingerprint, _)| *fingerprint == login.fingerprint);
+        let new_country = login.country.as_ref().is_some_and(|country| {
+            !known
+                .iter()
+                .any(|(_, countries)| countries.contains(country))
+        });
+
+        let query = format!(
+            "INSERT INTO login_devices \
+             (id, user_id, fingerprint, user_agent, ip, countries, tenant_id) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) \
+             ON CONFLICT (user_id, fingerprint) DO UPDATE SET \
+             user_agent = EXCLUDED.user_agent, ip = EXCLUDED.ip, last_seen_at = NOW(), \
+             countries = ARRAY(SELECT DISTINCT unnest(login_devices.countries || EXCLUDED.countries)) \
+             RETURNING {}",
+            DEVICE_COLUMNS
+        );
+        let device = sqlx::query_as::<_, Device>(&query)
+            .bind(Uuid::new_v4())
+            .bind(login.user_id)
+            .bind(&login.fingerprint)
+            .bind(login.user_agent)
+            .bind(login.ip)
+            .bind(login.country.into_iter().collect::<Vec<String>>())
+            .bind(&tenant)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("Failed to record device"))?;
+
+        tx.commit()
+            .await
+            .map_err(db_error("Failed to record device"))?;
+
+        Ok(DeviceSighting {
+            device,
+            first_login: known.is_empty(),
+            new_device,
+            new_country,
+        })
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Device>, AppError> {
+        let query = format!(
+            "SELECT {} FROM login_devices WHERE user_id = $1 AND tenant_id = $2 AND {} \
+             ORDER BY last_seen_at DESC",
+            DEVICE_COLUMNS, LIVE_USER
+        );
+
+        sqlx::query_as::<_, Device>(&query)
+            .bind(user_id)
+            .bind(current_tenant())
+            .fetch_all(self.db.read())
+            .await
+            .map_err(db_error("Failed to list devices"))
+    }
+}
@@ -0,0 +1,54 @@
+//! Postgres repository module.
+//!
+//! Module implements the repository traits on the
+//! database executor of the application. Lists and
+//! lookups are read from the replicas, writes and the
+//! reads that decide authentication, e.g. credentials,
+//! sessions and tokens being consumed, go to the primary,
+//! so they never see a lagging replica.
+
+// References to submodules
+pub mod access_tokens;
+pub mod clients;
+pub mod device_codes;
+pub mod devices;
+pub mod roles;
+pub mod sessions;
+pub mod signing_keys;
+pub mod tenants;
+pub mod tokens;
+pub mod users;
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+
+/// Condition of rows whose `user_id` is a user that is not deleted.
+pub(crate) const LIVE_USER: &str = "user_id IN (SELECT id FROM users WHERE deleted_at IS NULL)";
+
+/// ## Returns the condition of rows of live users of the tenant.
+///
+/// ## Parameters
+/// - `param`: `usize` - Number of the parameter the tenant is bound to.
+pub(crate) fn live_user(param: usize) -> String {
+    format!(
+        "user_id IN (SELECT id FROM users WHERE deleted_at IS NULL AND tenant_id = ${})",
+        param
+    )
+}
+
+// Re-exports of the repositories
+pub use access_tokens::PgAccessTokenRepo;
+pub use clients::PgClientRepo;
+pub use device_codes::PgDeviceCodeRepo;
+pub use devices::PgDeviceRepo;
+pub use roles::PgRoleDefinitionRepo;
+pub use sessions::PgSessionRepo;
+pub use signing_keys::PgSigningKeyRepo;
+pub use tenants::PgTenantRepo;
+pub use tokens::PgTokenRepo;
+pub use users::PgUserRepo;
+
+/// ## Maps a sqlx error into a database error.
+fn db_error(message: &'static str) -> impl FnOnce(sqlx::Error) -> AppError {
+    move |e| AppError::new(ErrorKind::Db, message.to_string(), Some(Box::new(e)))
+}
@@ -0,0 +1,136 @@
+//! Postgres role definition repository module.
+//!
+//! Module stores the role definitions in the `roles`
+//! table, scoped to the tenant of the current task.
+//! Updates compare the version in the `WHERE` clause,
+//! so a concurrent update makes them match no row.
+
+// Imports from external crates
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+// Local imports
+use super::db_error;
+use crate::core::db::DbExecutor;
+use crate::core::err::AppError;
+use crate::core::repo::{
+    role_not_found, role_taken, role_version_conflict, NewRole, RoleDefinitionRepo,
+};
+use crate::core::tenancy::current_tenant;
+use crate::core::users::roles::Role;
+
+/// Columns selected into `Role`.
+const ROLE_COLUMNS: &str = "name, description, permissions, version, created_at, updated_at";
+
+impl<'r> FromRow<'r, PgRow> for Role {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Role {
+            name: row.try_get("name")?,
+            description: row.try_get("description")?,
+            permissions: row.try_get("permissions")?,
+            version: row.try_get("version")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// ## Postgres role definition repository struct.
+#[derive(Debug, Clone)]
+pub struct PgRoleDefinitionRepo {
+    db: DbExecutor,
+}
+
+impl PgRoleDefinitionRepo {
+    /// ## Creates a new `PgRoleDefinitionRepo` instance.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        PgRoleDefinitionRepo { db: db.into() }
+    }
+}
+
+#[async_trait]
+impl RoleDefinitionRepo for PgRoleDefinitionRepo {
+    async fn create(&self, role: NewRole) -> Result<Role, AppError> {
+        let query = format!(
+            "INSERT INTO roles (tenant_id, name, description, permissions) \
+             VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (tenant_id, name) DO NOTHING RETURNING {}",
+            ROLE_COLUMNS
+        );
+
+        sqlx::query_as::<_, Role>(&query)
+            .bind(current_tenant())
+            .bind(&role.name)
+            .bind(&role.description)
+            .bind(&role.permissions)
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to create role"))?
+            .ok_or_else(|| role_taken(&role.name))
+    }
+
+    async fn list(&self) -> Result<Vec<Role>, AppError> {
+        let query = format!(
+            "SELECT {} FROM roles WHERE tenant_id = $1 ORDER BY name",
+            ROLE_COLUMNS
+        );
+
+        sqlx::query_as::<_, Role>(&query)
+            .bind(current_tenant())
+            .fetch_all(self.db.read())
+            .await
+            .map_err(db_error("Failed to list roles"))
+    }
+
+    async fn get(&self, name: &str) -> Result<Option<Role>, AppError> {
+        let query = format!(
+            "SELECT {} FROM roles WHERE tenant_id = $1 AND name = $2",
+            ROLE_COLUMNS
+        );
+
+        sqlx::query_as::<_, Role>(&query)
+            .bind(current_tenant())
+            .bind(name)
+            .fetch_optional(self.db.read())
+            .await
+            .map_err(db_error("Failed to load role"))
+    }
+
+    async fn update(&self, role: NewRole, version: i32) -> Result<Role, AppError> {
+        let query = format!(
+            "UPDATE roles SET description = $3, permissions = $4, \
+             version = version + 1, updated_at = NOW() \
+             WHERE tenant_id = $1 AND name = $2 AND version = $5 RETURNING {}",
+            ROLE_COLUMNS
+        );
+
+        let updated = sqlx::query_as::<_, Role>(&query)
+            .bind(current_tenant())
+            .bind(&role.name)
+            .bind(&role.description)
+            .bind(&role.permissions)
+            .bind(version)
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to update role"))?;
+
+        match updated {
+            Some(updated) => Ok(updated),
+            None => match self.get(&role.name).await? {
+                Some(_) => Err(role_version_conflict(&role.name)),
+                None => Err(role_not_found(&role.name)),
+            },
+        }
+    }
+
+    async fn delete(&self, name: &str) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM roles WHERE tenant_id = $1 AND name = $2")
+            .bind(current_tenant())
+            .bind(name)
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to delete role"))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
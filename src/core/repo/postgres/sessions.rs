@@ -0,0 +1,186 @@
+//! Postgres session repository module.
+//!
+//! Module stores login sessions in the `sessions` table.
+//! Revoked sessions are kept until they expire, so the
+//! revocation stays visible in the table. Sessions of
+//! deleted users and of other tenants are not returned.
+
+// Imports from external crates
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, FromRow, PgExecutor, Row};
+use uuid::Uuid;
+
+// Local imports
+use super::{db_error, LIVE_USER};
+use crate::core::db::{tx::with_transaction, DbExecutor};
+use crate::core::err::AppError;
+use crate::core::events::outbox;
+use crate::core::repo::{NewSession, Session, SessionRepo};
+use crate::core::tenancy::current_tenant;
+use crate::core::webhooks::WebhookEvent;
+
+/// Columns selected into `Session`.
+const SESSION_COLUMNS: &str = "id, user_id, ip, user_agent, created_at, last_seen_at, expires_at";
+
+/// Condition of sessions that are neither revoked nor expired.
+const ACTIVE: &str = "revoked_at IS NULL AND expires_at > NOW()";
+
+impl<'r> FromRow<'r, PgRow> for Session {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Session {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            ip: row.try_get("ip")?,
+            user_agent: row.try_get("user_agent")?,
+            created_at: row.try_get("created_at")?,
+            last_seen_at: row.try_get("last_seen_at")?,
+            expires_at: row.try_get("expires_at")?,
+        })
+    }
+}
+
+/// ## Postgres session repository struct.
+#[derive(Debug, Clone)]
+pub struct PgSessionRepo {
+    db: DbExecutor,
+    outbox: bool,
+}
+
+impl PgSessionRepo {
+    /// ## Creates a new `PgSessionRepo` instance.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        PgSessionRepo {
+            db: db.into(),
+            outbox: false,
+        }
+    }
+
+    /// ## Stores `login.succeeded` in the outbox with every created session.
+    ///
+    /// ## Parameters
+    /// - `enabled`: `bool` - If the outbox is enabled.
+    pub fn with_outbox(mut self, enabled: bool) -> Self {
+        self.outbox = enabled;
+        self
+    }
+}
+
+/// ## Inserts the session into the tenant on the executor.
+async fn insert<'e>(
+    executor: impl PgExecutor<'e>,
+    session: NewSession,
+) -> Result<Session, AppError> {
+    let query = format!(
+        "INSERT INTO sessions (id, user_id, ip, user_agent, expires_at, tenant_id) \
+         VALUES ($1, $2, $3, $4, $5, $6) RETURNING {}",
+        SESSION_COLUMNS
+    );
+
+    sqlx::query_as::<_, Session>(&query)
+        .bind(Uuid::new_v4())
+        .bind(session.user_id)
+        .bind(session.ip)
+        .bind(session.user_agent)
+        .bind(session.expires_at)
+        .bind(current_tenant())
+        .fetch_one(executor)
+        .await
+        .map_err(db_error("Failed to create session"))
+}
+
+#[async_trait]
+impl SessionRepo for PgSessionRepo {
+    async fn create(&self, session: NewSession) -> Result<Session, AppError> {
+        if !self.outbox {
+            return insert(self.db.write(), session).await;
+        }
+
+        // Sessions are only created by successful logins
+        with_transaction(self.db.write(), |tx| {
+            Box::pin(async move {
+                let created = insert(&mut *tx, session).await?;
+                let data = serde_json::json!({
+                    "user_id": created.user_id,
+                    "session_id": created.id,
+                    "ip": created.ip,
+                });
+                outbox::record(&mut *tx, WebhookEvent::LoginSucceeded, data).await?;
+
+                Ok(created)
+            })
+        })
+        .await
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<Session>, AppError> {
+        let query = format!(
+            "SELECT {} FROM sessions WHERE id = $1 AND tenant_id = $2 AND {} AND {}",
+            SESSION_COLUMNS, ACTIVE, LIVE_USER
+        );
+
+        sqlx::query_as::<_, Session>(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to load session"))
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>, AppError> {
+        let query = format!(
+            "SELECT {} FROM sessions WHERE user_id = $1 AND tenant_id = $2 AND {} AND {} \
+             ORDER BY created_at DESC",
+            SESSION_COLUMNS, ACTIVE, LIVE_USER
+        );
+
+        sqlx::query_as::<_, Session>(&query)
+            .bind(user_id)
+            .bind(current_tenant())
+            .fetch_all(self.db.read())
+            .await
+            .map_err(db_error("Failed to list sessions"))
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE sessions SET last_seen_at = NOW() WHERE id = $1 AND tenant_id = $2")
+            .bind(id)
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to update session"))?;
+
+        Ok(())
+    }
+
+    async fn revoke(&self, id: Uuid) -> Result<bool, AppError> {
+        let query = format!(
+            "UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND tenant_id = $2 AND {}",
+            ACTIVE
+        );
+
+        let result = sqlx::query(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to revoke session"))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn revoke_all(&self, user_id: Uuid) -> Result<u64, AppError> {
+        let query = format!(
+            "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND tenant_id = $2 AND {}",
+            ACTIVE
+        );
+
+        let result = sqlx::query(&query)
+            .bind(user_id)
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to revoke sessions"))?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,93 @@
+//! Postgres signing key repository module.
+//!
+//! Module stores the signing keys of the access tokens
+//! in the `signing_keys` table, the current key is the
+//! one without expiry.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, FromRow, PgPool, Row};
+
+// Local imports
+use super::db_error;
+use crate::core::err::AppError;
+use crate::core::repo::{NewSigningKey, SigningKeyRepo, StoredSigningKey};
+
+/// Columns selected into `StoredSigningKey`.
+const SIGNING_KEY_COLUMNS: &str = "kid, document, created_at, expires_at";
+
+impl<'r> FromRow<'r, PgRow> for StoredSigningKey {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(StoredSigningKey {
+            kid: row.try_get("kid")?,
+            document: row.try_get("document")?,
+            created_at: row.try_get("created_at")?,
+            expires_at: row.try_get("expires_at")?,
+        })
+    }
+}
+
+/// ## Postgres signing key repository struct.
+#[derive(Debug, Clone)]
+pub struct PgSigningKeyRepo {
+    pool: PgPool,
+}
+
+impl PgSigningKeyRepo {
+    /// ## Creates a new `PgSigningKeyRepo` instance.
+    pub fn new(pool: PgPool) -> Self {
+        PgSigningKeyRepo { pool }
+    }
+}
+
+#[async_trait]
+impl SigningKeyRepo for PgSigningKeyRepo {
+    async fn active(&self) -> Result<Vec<StoredSigningKey>, AppError> {
+        let query = format!(
+            "SELECT {} FROM signing_keys WHERE expires_at IS NULL OR expires_at > NOW() \
+             ORDER BY expires_at IS NOT NULL, created_at DESC",
+            SIGNING_KEY_COLUMNS
+        );
+
+        sqlx::query_as::<_, StoredSigningKey>(&query)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error("Failed to load signing keys"))
+    }
+
+    async fn rotate(
+        &self,
+        key: NewSigningKey,
+        grace_until: DateTime<Utc>,
+    ) -> Result<bool, AppError> {
+        // Rows of the update are read before the insert, so the new key stays current
+        let inserted: Option<String> = sqlx::query_scalar(
+            "WITH inserted AS ( \
+                 INSERT INTO signing_keys (kid, document) VALUES ($1, $2) \
+                 ON CONFLICT (kid) DO NOTHING RETURNING kid \
+             ), retired AS ( \
+                 UPDATE signing_keys SET expires_at = $3 \
+                 WHERE expires_at IS NULL AND EXISTS (SELECT 1 FROM inserted) \
+             ) \
+             SELECT kid FROM inserted",
+        )
+        .bind(&key.kid)
+        .bind(&key.document)
+        .bind(grace_until)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error("Failed to rotate signing key"))?;
+
+        Ok(inserted.is_some())
+    }
+
+    async fn purge(&self) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM signing_keys WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(db_error("Failed to purge signing keys"))?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,86 @@
+//! Postgres tenant repository module.
+//!
+//! Module stores the tenants in the `tenants` table,
+//! the `default` tenant is created by the migration.
+
+// Imports from external crates
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+// Local imports
+use super::db_error;
+use crate::core::db::DbExecutor;
+use crate::core::err::AppError;
+use crate::core::repo::{tenant_not_found, tenant_taken, TenantRepo};
+use crate::core::tenancy::Tenant;
+
+/// Columns selected into `Tenant`.
+const TENANT_COLUMNS: &str = "id, name, disabled, created_at";
+
+impl<'r> FromRow<'r, PgRow> for Tenant {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Tenant {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            disabled: row.try_get("disabled")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+/// ## Postgres tenant repository struct.
+#[derive(Debug, Clone)]
+pub struct PgTenantRepo {
+    db: DbExecutor,
+}
+
+impl PgTenantRepo {
+    /// ## Creates a new `PgTenantRepo` instance.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        PgTenantRepo { db: db.into() }
+    }
+}
+
+#[async_trait]
+impl TenantRepo for PgTenantRepo {
+    async fn create(&self, id: &str, name: &str) -> Result<Tenant, AppError> {
+        let query = format!(
+            "INSERT INTO tenants (id, name) VALUES ($1, $2) \
+             ON CONFLICT (id) DO NOTHING RETURNING {}",
+            TENANT_COLUMNS
+        );
+
+        sqlx::query_as::<_, Tenant>(&query)
+            .bind(id)
+            .bind(name)
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to create tenant"))?
+            .ok_or_else(|| tenant_taken(id))
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Tenant>, AppError> {
+        let query = format!("SELECT {} FROM tenants WHERE id = $1", TENANT_COLUMNS);
+
+        sqlx::query_as::<_, Tenant>(&query)
+            .bind(id)
+            .fetch_optional(self.db.read())
+            .await
+            .map_err(db_error("Failed to load tenant"))
+    }
+
+    async fn set_disabled(&self, id: &str, disabled: bool) -> Result<Tenant, AppError> {
+        let query = format!(
+            "UPDATE tenants SET disabled = $2 WHERE id = $1 RETURNING {}",
+            TENANT_COLUMNS
+        );
+
+        sqlx::query_as::<_, Tenant>(&query)
+            .bind(id)
+            .bind(disabled)
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to update tenant"))?
+            .ok_or_else(|| tenant_not_found(id))
+    }
+}
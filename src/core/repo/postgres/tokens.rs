@@ -0,0 +1,239 @@
+//! Postgres token repository module.
+//!
+//! Module stores issued tokens in the `tokens` table,
+//! only the hash of a token is stored and looked up.
+//! Tokens of deleted users and of users of other tenants
+//! are not found.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, FromRow, Row};
+use uuid::Uuid;
+
+// Local imports
+use super::{db_error, live_user};
+use crate::core::db::DbExecutor;
+use crate::core::err::AppError;
+use crate::core::repo::{hash_token, NewToken, Token, TokenKind, TokenRepo};
+use crate::core::tenancy::current_tenant;
+
+/// Columns selected into `Token`.
+const TOKEN_COLUMNS: &str = "id, kind, user_id, session_id, token_hash, created_at, expires_at";
+
+/// Condition of tokens that are neither revoked nor expired.
+const ACTIVE: &str = "revoked_at IS NULL AND expires_at > NOW()";
+
+/// Columns of `t` returned with the tenant of `u` by the expiry updates.
+const EXPIRY_RETURNING: &str = "RETURNING t.id, t.kind, t.user_id, t.session_id, t.token_hash, \
+                                t.created_at, t.expires_at, u.tenant_id";
+
+/// ## Token with the tenant of its user row struct.
+#[derive(FromRow)]
+struct TenantToken {
+    #[sqlx(flatten)]
+    token: Token,
+    tenant_id: String,
+}
+
+impl<'r> FromRow<'r, PgRow> for Token {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let kind: String = row.try_get("kind")?;
+
+        Ok(Token {
+            id: row.try_get("id")?,
+            kind: kind
+                .parse()
+                .map_err(|e: AppError| sqlx::Error::ColumnDecode {
+                    index: "kind".to_string(),
+                    source: e.to_string().into(),
+                })?,
+            user_id: row.try_get("user_id")?,
+            session_id: row.try_get("session_id")?,
+            hash: row.try_get("token_hash")?,
+            created_at: row.try_get("created_at")?,
+            expires_at: row.try_get("expires_at")?,
+        })
+    }
+}
+
+/// ## Postgres token repository struct.
+#[derive(Debug, Clone)]
+pub struct PgTokenRepo {
+    db: DbExecutor,
+}
+
+impl PgTokenRepo {
+    /// ## Creates a new `PgTokenRepo` instance.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        PgTokenRepo { db: db.into() }
+    }
+
+    /// ## Revokes the active tokens of the column value.
+    async fn revoke_by(&self, column: &str, id: Uuid) -> Result<u64, AppError> {
+        let query = format!(
+            "UPDATE tokens SET revoked_at = NOW() WHERE {} = $1 AND {} AND {}",
+            column,
+            ACTIVE,
+            live_user(2)
+        );
+
+        let result = sqlx::query(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to revoke tokens"))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl TokenRepo for PgTokenRepo {
+    async fn store(&self, token: NewToken) -> Result<Token, AppError> {
+        let query = format!(
+            "INSERT INTO tokens (id, kind, user_id, session_id, token_hash, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING {}",
+            TOKEN_COLUMNS
+        );
+
+        sqlx::query_as::<_, Token>(&query)
+            .bind(Uuid::new_v4())
+            .bind(token.kind.as_str())
+            .bind(token.user_id)
+            .bind(token.session_id)
+            .bind(hash_token(&token.token))
+            .bind(token.expires_at)
+            .fetch_one(self.db.write())
+            .await
+            .map_err(db_error("Failed to store token"))
+    }
+
+    async fn find(&self, kind: TokenKind, token: &str) -> Result<Option<Token>, AppError> {
+        let query = format!(
+            "SELECT {} FROM tokens WHERE token_hash = $1 AND kind = $2 AND {} AND {}",
+            TOKEN_COLUMNS,
+            ACTIVE,
+            live_user(3)
+        );
+
+        sqlx::query_as::<_, Token>(&query)
+            .bind(hash_token(token))
+            .bind(kind.as_str())
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to load token"))
+    }
+
+    async fn find_revoked(&self, kind: TokenKind, token: &str) -> Result<Option<Token>, AppError> {
+        let query = format!(
+            "SELECT {} FROM tokens WHERE token_hash = $1 AND kind = $2 \
+             AND revoked_at IS NOT NULL AND expires_at > NOW() AND {}",
+            TOKEN_COLUMNS,
+            live_user(3)
+        );
+
+        sqlx::query_as::<_, Token>(&query)
+            .bind(hash_token(token))
+            .bind(kind.as_str())
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to load token"))
+    }
+
+    async fn list(&self, user_id: Uuid, kind: TokenKind) -> Result<Vec<Token>, AppError> {
+        let query = format!(
+            "SELECT {} FROM tokens WHERE user_id = $1 AND kind = $2 AND {} AND {} \
+             ORDER BY created_at DESC",
+            TOKEN_COLUMNS,
+            ACTIVE,
+            live_user(3)
+        );
+
+        sqlx::query_as::<_, Token>(&query)
+            .bind(user_id)
+            .bind(kind.as_str())
+            .bind(current_tenant())
+            .fetch_all(self.db.read())
+            .await
+            .map_err(db_error("Failed to list tokens"))
+    }
+
+    async fn revoke(&self, id: Uuid) -> Result<bool, AppError> {
+        Ok(self.revoke_by("id", id).await? > 0)
+    }
+
+    async fn revoke_session(&self, session_id: Uuid) -> Result<u64, AppError> {
+        self.revoke_by("session_id", session_id).await
+    }
+
+    async fn revoke_user(&self, user_id: Uuid) -> Result<u64, AppError> {
+        self.revoke_by("user_id", user_id).await
+    }
+
+    async fn purge(&self) -> Result<u64, AppError> {
+        let result =
+            sqlx::query("DELETE FROM tokens WHERE revoked_at IS NOT NULL OR expires_at <= NOW()")
+                .execute(self.db.write())
+                .await
+                .map_err(db_error("Failed to purge tokens"))?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn warn_expiry(
+        &self,
+        kind: TokenKind,
+        created_before: DateTime<Utc>,
+        expires_before: DateTime<Utc>,
+    ) -> Result<Vec<(Token, String)>, AppError> {
+        let query = format!(
+            "UPDATE tokens t SET expiry_warned_at = NOW() FROM users u \
+             WHERE u.id = t.user_id AND u.deleted_at IS NULL AND t.kind = $1 \
+             AND t.revoked_at IS NULL AND t.expires_at > NOW() AND t.expiry_warned_at IS NULL \
+             AND (t.created_at < $2 OR t.expires_at < $3) {}",
+            EXPIRY_RETURNING
+        );
+
+        let tokens = sqlx::query_as::<_, TenantToken>(&query)
+            .bind(kind.as_str())
+            .bind(created_before)
+            .bind(expires_before)
+            .fetch_all(self.db.write())
+            .await
+            .map_err(db_error("Failed to warn about expiring tokens"))?;
+
+        Ok(tokens
+            .into_iter()
+            .map(|row| (row.token, row.tenant_id))
+            .collect())
+    }
+
+    async fn expire_issued_before(
+        &self,
+        kind: TokenKind,
+        created_before: DateTime<Utc>,
+    ) -> Result<Vec<(Token, String)>, AppError> {
+        let query = format!(
+            "UPDATE tokens t SET revoked_at = NOW() FROM users u \
+             WHERE u.id = t.user_id AND u.deleted_at IS NULL AND t.kind = $1 \
+             AND t.revoked_at IS NULL AND t.expires_at > NOW() AND t.created_at < $2 {}",
+            EXPIRY_RETURNING
+        );
+
+        let tokens = sqlx::query_as::<_, TenantToken>(&query)
+            .bind(kind.as_str())
+            .bind(created_before)
+            .fetch_all(self.db.write())
+            .await
+            .map_err(db_error("Failed to expire tokens"))?;
+
+        Ok(tokens
+            .into_iter()
+            .map(|row| (row.token, row.tenant_id))
+            .collect())
+    }
+}
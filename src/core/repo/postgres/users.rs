@@ -0,0 +1,760 @@
+//! Postgres user repository module.
+//!
+//! Module reads and updates users in the `users` table.
+//! List queries are built with `QueryBuilder`, so every
+//! filter value is bound instead of formatted into SQL.
+//! Deleted users keep their row with `deleted_at` set,
+//! every query skips them until they are purged. Every
+//! query is limited to the current tenant, only the purge
+//! job works across tenants.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+// Local imports
+use super::db_error;
+use crate::core::auth::password::{Algorithm, StoredHash};
+use crate::core::auth::rbac::Scope;
+use crate::core::db::{tx::with_transaction, DbExecutor};
+use crate::core::err::AppError;
+use crate::core::events::outbox;
+use crate::core::http::pagination::Window;
+use crate::core::repo::{email_taken, no_pending_email, user_not_found as not_found, UserRepo};
+use crate::core::tenancy::current_tenant;
+use crate::core::users::roles::{grant_with, RoleRepo, RoleSource};
+use crate::core::users::{NewUser, User, UserFilter};
+use crate::core::webhooks::WebhookEvent;
+
+/// Columns selected into `User`.
+const USER_COLUMNS: &str = "id, email, display_name, org_id, disabled, \
+                            password_reset_required, created_at, updated_at";
+
+/// Condition of users that are not deleted.
+const LIVE: &str = "deleted_at IS NULL";
+
+/// ## Returns the condition of live users of the tenant.
+///
+/// ## Parameters
+/// - `param`: `usize` - Number of the parameter the tenant is bound to.
+fn live(param: usize) -> String {
+    format!("{} AND tenant_id = ${}", LIVE, param)
+}
+
+/// Condition of users whose password can expire.
+const EXPIRABLE: &str = "deleted_at IS NULL AND password_hash IS NOT NULL \
+                         AND NOT disabled AND NOT password_reset_required";
+
+/// ## User with its tenant row struct.
+#[derive(sqlx::FromRow)]
+struct TenantUser {
+    #[sqlx(flatten)]
+    user: User,
+    tenant_id: String,
+}
+
+/// ## User with password hash row struct.
+#[derive(sqlx::FromRow)]
+struct CredentialsRow {
+    #[sqlx(flatten)]
+    user: User,
+    password_hash: String,
+    password_algorithm: Option<String>,
+}
+
+/// ## Postgres user repository struct.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::repo::{postgres::PgUserRepo, UserRepo};
+///
+/// # async fn example(pool: sqlx::PgPool) {
+/// let users = PgUserRepo::new(pool);
+/// let emails = users.emails().await;
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgUserRepo {
+    db: DbExecutor,
+    outbox: bool,
+}
+
+impl PgUserRepo {
+    /// ## Creates a new `PgUserRepo` instance.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        PgUserRepo {
+            db: db.into(),
+            outbox: false,
+        }
+    }
+
+    /// ## Stores `user.created` in the outbox with every created user.
+    ///
+    /// ## Parameters
+    /// - `enabled`: `bool` - If the outbox is enabled.
+    pub fn with_outbox(mut self, enabled: bool) -> Self {
+        self.outbox = enabled;
+        self
+    }
+}
+
+/// ## Stores the `user.created` event of the user in the outbox.
+async fn record_created(conn: &mut PgConnection, user: &User) -> Result<(), AppError> {
+    let data = serde_json::json!({
+        "user_id": user.id,
+        "email": user.email,
+        "org_id": user.org_id,
+    });
+
+    outbox::record(conn, WebhookEvent::UserCreated, data)
+        .await
+        .map(|_| ())
+}
+
+/// ## Inserts the user into the tenant on the executor.
+async fn insert<'e>(
+    executor: impl PgExecutor<'e>,
+    user: &NewUser,
+    tenant: &str,
+) -> Result<User, AppError> {
+    let query = format!(
+        "INSERT INTO users \
+         (id, email, display_name, org_id, password_hash, password_algorithm, tenant_id) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {}",
+        USER_COLUMNS
+    );
+
+    sqlx::query_as::<_, User>(&query)
+        .bind(Uuid::new_v4())
+        .bind(&user.email)
+        .bind(&user.display_name)
+        .bind(user.org_id)
+        .bind(user.password.as_ref().map(|hash| hash.hash.as_str()))
+        .bind(user.password.as_ref().map(|hash| hash.algorithm.as_str()))
+        .bind(tenant)
+        .fetch_one(executor)
+        .await
+        .map_err(email_error("Failed to create user"))
+}
+
+#[async_trait]
+impl UserRepo for PgUserRepo {
+    async fn list(
+        &self,
+        filter: &UserFilter,
+        scope: &Scope,
+        window: &Window,
+    ) -> Result<(Vec<User>, i64), AppError> {
+        let users = list_query(filter, scope, window)
+            .build_query_as::<User>()
+            .fetch_all(self.db.read())
+            .await
+            .map_err(db_error("Failed to list users"))?;
+
+        let total: i64 = count_query(filter, scope)
+            .build_query_scalar()
+            .fetch_one(self.db.read())
+            .await
+            .map_err(db_error("Failed to count users"))?;
+
+        Ok((users, total))
+    }
+
+    async fn get(&self, id: Uuid) -> Result<User, AppError> {
+        let query = format!(
+            "SELECT {} FROM users WHERE id = $1 AND {}",
+            USER_COLUMNS,
+            live(2)
+        );
+
+        sqlx::query_as::<_, User>(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .fetch_optional(self.db.read())
+            .await
+            .map_err(db_error("Failed to load user"))?
+            .ok_or_else(|| not_found(id))
+    }
+
+    async fn credentials(&self, email: &str) -> Result<Option<(User, StoredHash)>, AppError> {
+        let query = format!(
+            "SELECT {}, password_hash, password_algorithm FROM users \
+             WHERE email = $1 AND password_hash IS NOT NULL AND {}",
+            USER_COLUMNS,
+            live(2)
+        );
+
+        let Some(row) = sqlx::query_as::<_, CredentialsRow>(&query)
+            .bind(email)
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to load user credentials"))?
+        else {
+            return Ok(None);
+        };
+
+        // Hashes stored before the algorithm was recorded are recognized by their prefix
+        let algorithm = match row.password_algorithm {
+            Some(algorithm) => algorithm.parse()?,
+            None if row.password_hash.starts_with("$2") => Algorithm::Bcrypt,
+            None => Algorithm::Argon2id,
+        };
+
+        Ok(Some((
+            row.user,
+            StoredHash {
+                algorithm,
+                hash: row.password_hash,
+            },
+        )))
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
+        let query = format!(
+            "SELECT {} FROM users WHERE email = $1 AND {}",
+            USER_COLUMNS,
+            live(2)
+        );
+
+        sqlx::query_as::<_, User>(&query)
+            .bind(email)
+            .bind(current_tenant())
+            .fetch_optional(self.db.read())
+            .await
+            .map_err(db_error("Failed to load user"))
+    }
+
+    async fn emails(&self) -> Result<Vec<(Uuid, String)>, AppError> {
+        let query = format!(
+            "SELECT id, email FROM users WHERE {} ORDER BY email",
+            live(1)
+        );
+
+        sqlx::query_as::<_, (Uuid, String)>(&query)
+            .bind(current_tenant())
+            .fetch_all(self.db.read())
+            .await
+            .map_err(db_error("Failed to list user emails"))
+    }
+
+    async fn create(&self, user: NewUser) -> Result<User, AppError> {
+        let tenant = current_tenant();
+        if !self.outbox {
+            return insert(self.db.write(), &user, &tenant).await;
+        }
+
+        with_transaction(self.db.write(), |tx| {
+            Box::pin(async move {
+                let created = insert(&mut *tx, &user, &tenant).await?;
+                record_created(&mut *tx, &created).await?;
+
+                Ok(created)
+            })
+        })
+        .await
+    }
+
+    async fn import(
+        &self,
+        user: NewUser,
+        disabled: bool,
+        roles: &[String],
+    ) -> Result<User, AppError> {
+        let tenant = current_tenant();
+        let roles = roles.to_vec();
+        let outbox = self.outbox;
+
+        with_transaction(self.db.write(), |tx| {
+            Box::pin(async move {
+                let mut created = insert(&mut *tx, &user, &tenant).await?;
+                if disabled {
+                    sqlx::query("UPDATE users SET disabled = TRUE WHERE id = $1")
+                        .bind(created.id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db_error("Failed to disable user"))?;
+                    created.disabled = true;
+                }
+                for role in &roles {
+                    grant_with(&mut *tx, created.id, role, RoleSource::Local).await?;
+                }
+                if outbox {
+                    record_created(&mut *tx, &created).await?;
+                }
+
+                Ok(created)
+            })
+        })
+        .await
+    }
+
+    async fn grant(&self, id: Uuid, role: &str) -> Result<bool, AppError> {
+        grant_with(self.db.write(), id, role, RoleSource::Local).await
+    }
+
+    async fn roles(&self, id: Uuid) -> Result<Vec<String>, AppError> {
+        let grants = RoleRepo::new(self.db.clone()).list_user(id).await?;
+        let mut roles: Vec<String> = grants.into_iter().map(|grant| grant.role).collect();
+        roles.dedup();
+
+        Ok(roles)
+    }
+
+    async fn set_display_name(&self, id: Uuid, name: Option<&str>) -> Result<User, AppError> {
+        let query = format!(
+            "UPDATE users SET display_name = $2, updated_at = NOW() \
+             WHERE id = $1 AND {} RETURNING {}",
+            live(3),
+            USER_COLUMNS
+        );
+
+        sqlx::query_as::<_, User>(&query)
+            .bind(id)
+            .bind(name)
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to update user"))?
+            .ok_or_else(|| not_found(id))
+    }
+
+    async fn set_password(&self, id: Uuid, hash: &StoredHash) -> Result<(), AppError> {
+        let query = format!(
+            "UPDATE users SET password_hash = $2, password_algorithm = $3, \
+             password_changed_at = NOW(), password_expiry_warned_at = NULL, \
+             password_reset_required = FALSE, updated_at = NOW() WHERE id = $1 AND {}",
+            live(4)
+        );
+
+        let result = sqlx::query(&query)
+            .bind(id)
+            .bind(&hash.hash)
+            .bind(hash.algorithm.as_str())
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to update password"))?;
+
+        match result.rows_affected() {
+            0 => Err(not_found(id)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_disabled(&self, id: Uuid, disabled: bool) -> Result<User, AppError> {
+        let query = format!(
+            "UPDATE users SET disabled = $2, updated_at = NOW() WHERE id = $1 AND {} RETURNING {}",
+            live(3),
+            USER_COLUMNS
+        );
+
+        sqlx::query_as::<_, User>(&query)
+            .bind(id)
+            .bind(disabled)
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to update user"))?
+            .ok_or_else(|| not_found(id))
+    }
+
+    async fn require_password_reset(&self, id: Uuid) -> Result<User, AppError> {
+        let query = format!(
+            "UPDATE users SET password_reset_required = TRUE, updated_at = NOW() \
+             WHERE id = $1 AND {} RETURNING {}",
+            live(2),
+            USER_COLUMNS
+        );
+
+        sqlx::query_as::<_, User>(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to update user"))?
+            .ok_or_else(|| not_found(id))
+    }
+
+    async fn password_changed_at(&self, id: Uuid) -> Result<Option<DateTime<Utc>>, AppError> {
+        let query = format!(
+            "SELECT CASE WHEN password_hash IS NULL THEN NULL ELSE password_changed_at END \
+             FROM users WHERE id = $1 AND {}",
+            live(2)
+        );
+
+        sqlx::query_scalar::<_, Option<DateTime<Utc>>>(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(db_error("Failed to load user"))?
+            .ok_or_else(|| not_found(id))
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+        let query = format!(
+            "UPDATE users SET deleted_at = NOW(), updated_at = NOW() WHERE id = $1 AND {}",
+            live(2)
+        );
+
+        let result = sqlx::query(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to delete user"))?;
+
+        match result.rows_affected() {
+            0 => Err(not_found(id)),
+            _ => Ok(()),
+        }
+    }
+
+    // Purge job runs outside of requests, for every tenant
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM users WHERE deleted_at < $1")
+            .bind(before)
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to purge deleted users"))?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn set_pending_email(&self, id: Uuid, email: &str) -> Result<(), AppError> {
+        let query = format!(
+            "UPDATE users SET pending_email = $2, previous_email = email, updated_at = NOW() \
+             WHERE id = $1 AND {}",
+            live(3)
+        );
+
+        let result = sqlx::query(&query)
+            .bind(id)
+            .bind(email)
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to store pending email"))?;
+
+        match result.rows_affected() {
+            0 => Err(not_found(id)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn confirm_pending_email(&self, id: Uuid) -> Result<User, AppError> {
+        let query = format!(
+            "UPDATE users SET email = pending_email, pending_email = NULL, updated_at = NOW() \
+             WHERE id = $1 AND pending_email IS NOT NULL AND {} RETURNING {}",
+            live(2),
+            USER_COLUMNS
+        );
+
+        sqlx::query_as::<_, User>(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(email_error("Failed to update email"))?
+            .ok_or_else(|| no_pending_email(id))
+    }
+
+    async fn undo_email_change(&self, id: Uuid) -> Result<User, AppError> {
+        let query = format!(
+            "UPDATE users SET email = previous_email, previous_email = NULL, \
+             pending_email = NULL, updated_at = NOW() \
+             WHERE id = $1 AND previous_email IS NOT NULL AND {} RETURNING {}",
+            live(2),
+            USER_COLUMNS
+        );
+
+        sqlx::query_as::<_, User>(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .fetch_optional(self.db.write())
+            .await
+            .map_err(email_error("Failed to restore email"))?
+            .ok_or_else(|| no_pending_email(id))
+    }
+
+    async fn schedule_deletion(
+        &self,
+        id: Uuid,
+        delete_after: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let query = format!(
+            "UPDATE users SET delete_after = $2, updated_at = NOW() WHERE id = $1 AND {}",
+            live(3)
+        );
+
+        let result = sqlx::query(&query)
+            .bind(id)
+            .bind(delete_after)
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to schedule deletion"))?;
+
+        match result.rows_affected() {
+            0 => Err(not_found(id)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn cancel_deletion(&self, id: Uuid) -> Result<bool, AppError> {
+        let query = format!(
+            "UPDATE users SET delete_after = NULL, updated_at = NOW() \
+             WHERE id = $1 AND delete_after IS NOT NULL AND {}",
+            live(2)
+        );
+
+        let result = sqlx::query(&query)
+            .bind(id)
+            .bind(current_tenant())
+            .execute(self.db.write())
+            .await
+            .map_err(db_error("Failed to cancel deletion"))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Deletion job runs outside of requests, for every tenant
+    async fn purge_scheduled(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<(Uuid, String)>, AppError> {
+        sqlx::query_as("DELETE FROM users WHERE delete_after <= $1 RETURNING id, tenant_id")
+            .bind(before)
+            .fetch_all(self.db.write())
+            .await
+            .map_err(db_error("Failed to purge scheduled users"))
+    }
+
+    async fn warn_password_expiry(
+        &self,
+        changed_before: DateTime<Utc>,
+    ) -> Result<Vec<(User, String)>, AppError> {
+        let query = format!(
+            "UPDATE users SET password_expiry_warned_at = NOW() \
+             WHERE password_changed_at < $1 AND password_expiry_warned_at IS NULL AND {} \
+             RETURNING {}, tenant_id",
+            EXPIRABLE, USER_COLUMNS
+        );
+
+        let users = sqlx::query_as::<_, TenantUser>(&query)
+            .bind(changed_before)
+            .fetch_all(self.db.write())
+            .await
+            .map_err(db_error("Failed to warn about expiring passwords"))?;
+
+        Ok(users
+            .into_iter()
+            .map(|row| (row.user, row.tenant_id))
+            .collect())
+    }
+
+    async fn expire_passwords(
+        &self,
+        changed_before: DateTime<Utc>,
+    ) -> Result<Vec<(User, String)>, AppError> {
+        let query = format!(
+            "UPDATE users SET password_reset_required = TRUE, updated_at = NOW() \
+             WHERE password_changed_at < $1 AND {} RETURNING {}, tenant_id",
+            EXPIRABLE, USER_COLUMNS
+        );
+
+        let users = sqlx::query_as::<_, TenantUser>(&query)
+            .bind(changed_before)
+            .fetch_all(self.db.write())
+            .await
+            .map_err(db_error("Failed to expire passwords"))?;
+
+        Ok(users
+            .into_iter()
+            .map(|row| (row.user, row.tenant_id))
+            .collect())
+    }
+}
+
+/// ## Maps a sqlx error, a duplicate email into `users.email_taken`.
+fn email_error(message: &'static str) -> impl FnOnce(sqlx::Error) -> AppError {
+    move |e| match e.as_database_error() {
+        Some(error) if error.is_unique_violation() => email_taken(),
+        _ => db_error(message)(e),
+    }
+}
+
+/// ## Builds the list query.
+fn list_query<'a>(
+    filter: &'a UserFilter,
+    scope: &'a Scope,
+    window: &Window,
+) -> QueryBuilder<'a, Postgres> {
+    let mut query = QueryBuilder::new(format!("SELECT {} FROM users", USER_COLUMNS));
+
+    push_filters(&mut query, filter, scope);
+    window.push_keyset(&mut query);
+    window.push_order(&mut query);
+
+    query
+}
+
+/// ## Builds the count query.
+fn count_query<'a>(filter: &'a UserFilter, scope: &'a Scope) -> QueryBuilder<'a, Postgres> {
+    let mut query = QueryBuilder::new("SELECT COUNT(*) FROM users");
+
+    push_filters(&mut query, filter, scope);
+
+    query
+}
+
+/// ## Appends the `WHERE` clause of the filter and scope.
+fn push_filters<'a>(query: &mut QueryBuilder<'a, Postgres>, filter: &'a UserFilter, scope: &Scope) {
+    query.push(" WHERE ");
+    query.push(LIVE);
+    query.push(" AND tenant_id = ");
+    query.push_bind(current_tenant());
+
+    if let Scope::Orgs(orgs) = scope {
+        query.push(" AND org_id = ANY(");
+        query.push_bind(orgs.iter().copied().collect::<Vec<Uuid>>());
+        query.push(")");
+    }
+    if let Some(email) = &filter.email {
+        query.push(" AND email ILIKE ");
+        query.push_bind(format!("%{}%", escape_like(email)));
+    }
+    if let Some(org_id) = filter.org_id {
+        query.push(" AND org_id = ");
+        query.push_bind(org_id);
+    }
+    if let Some(disabled) = filter.disabled {
+        query.push(" AND disabled = ");
+        query.push_bind(disabled);
+    }
+}
+
+/// ## Escapes the `LIKE` wildcards of the value.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::http::pagination::{Cursor, Pagination};
+    use crate::core::users::USER_SORTING;
+    use sqlx::postgres::PgPoolOptions;
+    use std::{collections::HashSet, time::Duration};
+
+    /// Returns the sqlx error the query failed with.
+    fn sqlx_error(e: &AppError) -> &sqlx::Error {
+        e.source
+            .as_ref()
+            .and_then(|source| source.downcast_ref())
+            .unwrap()
+    }
+
+    // Test checks if an unfiltered list query only pages the users.
+    #[test]
+    fn test_list_query_unfiltered() {
+        let filter = UserFilter::default();
+        let window = Pagination::default().window(&USER_SORTING).unwrap();
+        let query = list_query(&filter, &Scope::All, &window);
+
+        assert_eq!(
+            query.sql(),
+            format!(
+                "SELECT {} FROM users WHERE {} ORDER BY created_at, id LIMIT $2 OFFSET $3",
+                USER_COLUMNS,
+                live(1)
+            )
+        );
+    }
+
+    // Test checks if the cursor follows the filters and the sort decides the order.
+    #[test]
+    fn test_list_query_cursor() {
+        let filter = UserFilter {
+            disabled: Some(false),
+            ..UserFilter::default()
+        };
+        let cursor = Cursor {
+            sort: "-email".to_string(),
+            key: "ada@example.com".to_string(),
+            id: Uuid::new_v4().to_string(),
+        };
+        let pagination = Pagination {
+            page: None,
+            cursor: Some(cursor.encode()),
+            sort: Some("-email".to_string()),
+            ..Pagination::default()
+        };
+        let window = pagination.window(&USER_SORTING).unwrap();
+        let query = list_query(&filter, &Scope::All, &window);
+
+        assert_eq!(
+            query.sql(),
+            format!(
+                "SELECT {} FROM users WHERE deleted_at IS NULL AND tenant_id = $1 \
+                 AND disabled = $2 AND (email, id) < (CAST($3 AS text), CAST($4 AS uuid)) \
+                 ORDER BY email DESC, id DESC LIMIT $5 OFFSET $6",
+                USER_COLUMNS
+            )
+        );
+    }
+
+    // Test checks if filters and the organization scope are bound as parameters.
+    #[test]
+    fn test_list_query_filtered() {
+        let filter = UserFilter {
+            email: Some("50%_off".to_string()),
+            org_id: Some(Uuid::new_v4()),
+            disabled: Some(true),
+        };
+        let scope = Scope::Orgs(HashSet::from([Uuid::new_v4()]));
+        let query = count_query(&filter, &scope);
+
+        assert_eq!(
+            query.sql(),
+            "SELECT COUNT(*) FROM users WHERE deleted_at IS NULL AND tenant_id = $1 \
+             AND org_id = ANY($2) AND email ILIKE $3 AND org_id = $4 AND disabled = $5"
+        );
+    }
+
+    // Test checks if LIKE wildcards in the email filter are escaped.
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("50%_off\\"), "50\\%\\_off\\\\");
+        assert_eq!(escape_like("user@example.com"), "user@example.com");
+    }
+
+    // Test checks if lookups are read from a replica and credentials from the primary.
+    #[tokio::test]
+    async fn test_read_routing() {
+        let primary = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://127.0.0.1:1/primary")
+            .unwrap();
+        let replica = PgPoolOptions::new()
+            .connect_lazy("postgres://127.0.0.1:1/replica")
+            .unwrap();
+        replica.close().await;
+        let users = PgUserRepo::new(DbExecutor::new(
+            primary,
+            vec![("replica".to_string(), replica)],
+        ));
+
+        let read = users.emails().await.unwrap_err();
+        assert!(matches!(sqlx_error(&read), sqlx::Error::PoolClosed));
+
+        let write = users.credentials("ada@example.com").await.unwrap_err();
+        assert!(matches!(sqlx_error(&write), sqlx::Error::PoolTimedOut));
+    }
+}
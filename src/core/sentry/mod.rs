@@ -0,0 +1,658 @@
+//! Sentry module.
+//!
+//! Server errors and panics are sent as events to the
+//! envelope endpoint of the project named by the DSN, e.g.
+//! `https://<key>@o0.ingest.sentry.io/<project>`. `init`
+//! installs the client once at startup, after which
+//! every `AppError` answered with a 5xx is captured by
+//! `capture_error`, tagged with its kind and code. The
+//! `context` middleware records the method and route of
+//! the request for the events, `authenticate` its user.
+//! Errors are sent in tasks of the current Tokio runtime,
+//! events outside of a runtime are dropped with a warning.
+//! `flush` waits for the tasks still sending on shutdown.
+//! Panics are sent before the hook returns, from a thread
+//! of their own, so crashes that end the process, e.g. on
+//! the main thread or with `panic = abort`, are reported.
+
+// Imports from external crates
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use serde_json::{json, Value as Json};
+use std::{
+    sync::{mpsc, Arc, Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+// Local imports
+use super::config::SentrySettings;
+use super::env::{map::EnvMap, vars::RequiredEnvVar};
+use super::err::{AppError, ErrorKind};
+use super::http::{
+    client_ip::ClientIp, principal::current_user_id, request_id::current_request_id,
+};
+use super::panic::{CrashReporter, PanicReport};
+use super::tenancy::current_tenant;
+use super::types::secret::Secret;
+
+/// Timeout of the requests to Sentry.
+pub const SENTRY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Client name sent with the events.
+const CLIENT: &str = concat!("axum_auth/", env!("CARGO_PKG_VERSION"));
+
+/// Name of the thread sending crash reports.
+const CRASH_REPORT_THREAD: &str = "sentry-crash-report";
+
+/// Client installed by `init`.
+static SENTRY: OnceLock<SentryClient> = OnceLock::new();
+
+tokio::task_local! {
+    /// Request handled by the current task.
+    static REQUEST: RequestContext;
+}
+
+/// ## Request context struct.
+///
+/// ## Fields
+/// + `method`: `String` - Method of the request.
+/// + `route`: `String` - Matched route, or the path without one.
+/// + `client_ip`: `Option<String>` - Resolved address of the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    pub method: String,
+    pub route: String,
+    pub client_ip: Option<String>,
+}
+
+/// ## Sentry client struct.
+///
+/// ## Fields
+/// + `envelope_url`: `String` - Envelope endpoint of the project.
+/// + `key`: `Secret<String>` - Public key of the DSN.
+/// + `settings`: `SentrySettings` - Environment, sampling and user context.
+/// + `client`: `reqwest::Client` - HTTP client.
+/// + `in_flight`: `Arc<Mutex<JoinSet<()>>>` - Tasks still sending events.
+#[derive(Debug, Clone)]
+pub struct SentryClient {
+    envelope_url: String,
+    key: Secret<String>,
+    settings: SentrySettings,
+    client: reqwest::Client,
+    in_flight: Arc<Mutex<JoinSet<()>>>,
+}
+
+impl SentryClient {
+    /// ## Creates the client of a Sentry project.
+    ///
+    /// ## Parameters
+    /// - `dsn`: `&str` - DSN of the project.
+    /// - `settings`: `&SentrySettings` - Sentry settings.
+    ///
+    /// ## Returns
+    /// + `Result<SentryClient, AppError>`
+    ///     - `Ok(SentryClient)`: New client.
+    ///     - `Err(AppError)`: If the DSN has no key or project, or the
+    ///       HTTP client can not be built.
+    ///
+    /// ## Examples
+    /// ```
+    /// use axum_auth::core::config::SentrySettings;
+    /// use axum_auth::core::sentry::SentryClient;
+    ///
+    /// let client = SentryClient::new(
+    ///     "https://key@o0.ingest.sentry.io/42",
+    ///     &SentrySettings::default(),
+    /// ).unwrap();
+    /// assert_eq!(client.envelope_url(), "https://o0.ingest.sentry.io/api/42/envelope/");
+    /// ```
+    pub fn new(dsn: &str, settings: &SentrySettings) -> Result<Self, AppError> {
+        let invalid =
+            || AppError::config("Sentry DSN must look like https://<key>@<host>/<project>");
+        let url = reqwest::Url::parse(dsn).map_err(|_| invalid())?;
+        let host = url.host_str().ok_or_else(invalid)?;
+        let (prefix, project) = url
+            .path()
+            .trim_end_matches('/')
+            .rsplit_once('/')
+            .ok_or_else(invalid)?;
+        if url.username().is_empty() || project.is_empty() {
+            return Err(invalid());
+        }
+        let port = url
+            .port()
+            .map(|port| format!(":{}", port))
+            .unwrap_or_default();
+
+        let client = http_client()?;
+
+        Ok(SentryClient {
+            envelope_url: format!(
+                "{}://{}{}{}/api/{}/envelope/",
+                url.scheme(),
+                host,
+                port,
+                prefix,
+                project
+            ),
+            key: Secret::new(url.username().to_string()),
+            settings: settings.clone(),
+            client,
+            in_flight: Arc::default(),
+        })
+    }
+
+    /// ## Returns the envelope endpoint of the project.
+    pub fn envelope_url(&self) -> &str {
+        &self.envelope_url
+    }
+
+    /// ## Checks if an error is sent, by the sample rate.
+    pub fn is_sampled(&self) -> bool {
+        let (random, _) = Uuid::new_v4().as_u64_pair();
+
+        (random as f64 / u64::MAX as f64) < self.settings.sample_rate
+    }
+
+    /// ## Builds the event of a server error.
+    ///
+    /// ## Parameters
+    /// - `error`: `&AppError` - Error answered with a 5xx.
+    ///
+    /// ## Returns
+    /// - `serde_json::Value`: Event tagged with the kind and code of
+    ///   the error, with the context of the current request.
+    pub fn error_event(&self, error: &AppError) -> Json {
+        let mut event = self.event("error", &format!("{:?}", error.kind), &error.message);
+        event["tags"]["kind"] = json!(format!("{:?}", error.kind));
+        event["tags"]["code"] = json!(error.code);
+        event["extra"]["error"] = json!(error.to_string());
+        if let Some(backtrace) = &error.backtrace {
+            event["extra"]["backtrace"] = json!(backtrace.to_string());
+        }
+
+        event
+    }
+
+    /// ## Builds the event of a panic.
+    ///
+    /// ## Parameters
+    /// - `report`: `&PanicReport` - Report of the panic.
+    ///
+    /// ## Returns
+    /// - `serde_json::Value`: Fatal event with the panic as unhandled
+    ///   exception.
+    pub fn panic_event(&self, report: &PanicReport) -> Json {
+        let mut event = self.event("fatal", "panic", &report.message);
+        event["culprit"] = json!(report.location);
+        event["exception"]["values"][0]["mechanism"]["handled"] = json!(false);
+        event["tags"]["thread"] = json!(report.thread);
+        event["extra"]["backtrace"] = json!(report.backtrace);
+
+        event
+    }
+
+    /// ## Builds an event with the context of the current request.
+    fn event(&self, level: &str, exception: &str, message: &str) -> Json {
+        let mut event = json!({
+            "event_id": Uuid::new_v4().simple().to_string(),
+            "timestamp": Utc::now().to_rfc3339(),
+            "platform": "native",
+            "level": level,
+            "release": CLIENT.replace('/', "@"),
+            "environment": self.settings.environment,
+            "exception": {
+                "values": [{
+                    "type": exception,
+                    "value": message,
+                    "mechanism": { "type": "generic", "handled": true },
+                }],
+            },
+            "tags": {
+                "request_id": current_request_id(),
+                "tenant": current_tenant(),
+            },
+            "extra": {},
+        });
+
+        let request = REQUEST.try_with(RequestContext::clone).ok();
+        if let Some(request) = &request {
+            event["transaction"] = json!(format!("{} {}", request.method, request.route));
+            event["request"] = json!({ "method": request.method, "url": request.route });
+        }
+        if self.settings.send_user {
+            if let Some(id) = current_user_id() {
+                event["user"] = json!({
+                    "id": id,
+                    "ip_address": request.and_then(|request| request.client_ip),
+                });
+            }
+        }
+
+        event
+    }
+
+    /// ## Sends an event to Sentry.
+    ///
+    /// Event is the only item of an envelope.
+    /// ## Parameters
+    /// - `event`: `&serde_json::Value` - Event to send.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If Sentry accepted the event.
+    ///     - `Err(AppError)`: `External` if the request failed.
+    pub async fn send(&self, event: &Json) -> Result<(), AppError> {
+        let auth = format!(
+            "Sentry sentry_version=7, sentry_key={}, sentry_client={}",
+            self.key.expose(),
+            CLIENT
+        );
+        let failed = |e: reqwest::Error| {
+            AppError::new(
+                ErrorKind::External,
+                "Sentry API failed".to_string(),
+                Some(Box::new(e)),
+            )
+        };
+
+        self.client
+            .post(&self.envelope_url)
+            .header("X-Sentry-Auth", auth)
+            .header("Content-Type", "application/x-sentry-envelope")
+            .body(envelope(event))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(failed)?;
+
+        Ok(())
+    }
+
+    /// ## Sends an event and waits for the answer.
+    ///
+    /// Event is sent from a thread with a runtime of its own,
+    /// so the function can be called from the panic hook, on
+    /// any thread and inside or outside of a runtime. Waiting
+    /// is bounded by `SENTRY_TIMEOUT`.
+    ///
+    /// ## Parameters
+    /// - `event`: `serde_json::Value` - Event to send.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If Sentry accepted the event.
+    ///     - `Err(AppError)`: `External` if the request failed or
+    ///       timed out.
+    pub fn send_blocking(&self, event: Json) -> Result<(), AppError> {
+        let (done, answer) = mpsc::channel();
+        let client = self.clone();
+
+        thread::Builder::new()
+            .name(CRASH_REPORT_THREAD.to_string())
+            .spawn(move || {
+                // Connections of the shared client belong to the server runtime
+                let result = http_client().and_then(|http| {
+                    let client = SentryClient {
+                        client: http,
+                        ..client
+                    };
+                    tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .map_err(|e| {
+                            AppError::internal("Failed to start the Sentry runtime").with_source(e)
+                        })?
+                        .block_on(client.send(&event))
+                });
+                // Errors are not Send, only their description crosses threads
+                let _ = done.send(result.map_err(|e| e.to_string()));
+            })
+            .map_err(|e| {
+                AppError::internal("Failed to start the Sentry crash report thread").with_source(e)
+            })?;
+
+        let answer = answer
+            .recv_timeout(SENTRY_TIMEOUT)
+            .unwrap_or_else(|_| Err("Sentry did not answer in time".to_string()));
+
+        answer.map_err(|message| AppError::new(ErrorKind::External, message, None))
+    }
+
+    /// ## Sends an event in a task of the current runtime.
+    fn spawn(&self, event: Json) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("Event not sent to Sentry, no runtime is running");
+            return;
+        };
+
+        let client = self.clone();
+        let mut in_flight = self.in_flight.lock().expect("Sentry tasks lock poisoned");
+        // Forget the tasks that are done
+        while in_flight.try_join_next().is_some() {}
+        in_flight.spawn_on(
+            async move {
+                if let Err(e) = client.send(&event).await {
+                    tracing::warn!(error = %e, "Failed to send the event to Sentry");
+                }
+            },
+            &runtime,
+        );
+    }
+
+    /// ## Waits for the events that are still being sent.
+    ///
+    /// ## Parameters
+    /// - `timeout`: `Duration` - Longest time to wait, the events
+    ///   still unsent then are dropped with a warning.
+    pub async fn flush(&self, timeout: Duration) {
+        let mut in_flight =
+            std::mem::take(&mut *self.in_flight.lock().expect("Sentry tasks lock poisoned"));
+
+        let drained = tokio::time::timeout(timeout, async {
+            while in_flight.join_next().await.is_some() {}
+        })
+        .await;
+        if drained.is_err() {
+            tracing::warn!(
+                unsent = in_flight.len(),
+                "Events not sent to Sentry before the shutdown"
+            );
+        }
+    }
+}
+
+impl CrashReporter for SentryClient {
+    fn report(&self, report: &PanicReport) {
+        // A panic while sending is not reported again
+        if report.thread == CRASH_REPORT_THREAD {
+            return;
+        }
+
+        if let Err(e) = self.send_blocking(self.panic_event(report)) {
+            tracing::warn!(error = %e, "Failed to send the crash report to Sentry");
+        }
+    }
+}
+
+/// ## Returns the envelope carrying the event.
+///
+/// Envelope is the header, the header of the event item and
+/// the event, each on a line of its own.
+fn envelope(event: &Json) -> String {
+    let payload = event.to_string();
+    let header = json!({
+        "event_id": event["event_id"],
+        "sent_at": Utc::now().to_rfc3339(),
+    });
+    let item = json!({ "type": "event", "length": payload.len() });
+
+    format!("{}\n{}\n{}\n", header, item, payload)
+}
+
+/// ## Builds the HTTP client of the requests to Sentry.
+fn http_client() -> Result<reqwest::Client, AppError> {
+    reqwest::Client::builder()
+        .timeout(SENTRY_TIMEOUT)
+        .user_agent(CLIENT)
+        .build()
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                "Failed to build the Sentry client".to_string(),
+                Some(Box::new(e)),
+            )
+        })
+}
+
+/// ## Installs the Sentry client.
+///
+/// ## Parameters
+/// - `settings`: `&SentrySettings` - Sentry settings.
+/// - `env`: `&EnvMap` - Environment holding the DSN.
+///
+/// ## Returns
+/// + `Result<Option<Arc<dyn CrashReporter>>, AppError>`
+///     - `Ok(Some(Arc<dyn CrashReporter>))`: Reporter of the panics, if
+///       Sentry is enabled.
+///     - `Ok(None)`: If Sentry is disabled.
+///     - `Err(AppError)`: If the DSN is missing or invalid.
+pub fn init(
+    settings: &SentrySettings,
+    env: &EnvMap,
+) -> Result<Option<Arc<dyn CrashReporter>>, AppError> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let client = SentryClient::new(env.get(&RequiredEnvVar::SentryDsn)?, settings)?;
+    if SENTRY.set(client.clone()).is_err() {
+        tracing::warn!("Sentry client was already installed");
+    }
+
+    Ok(Some(Arc::new(client)))
+}
+
+/// ## Captures a server error.
+///
+/// Function does nothing until `init` installed the client,
+/// or when the error is not sampled.
+///
+/// ## Parameters
+/// - `error`: `&AppError` - Error answered with a 5xx.
+pub fn capture_error(error: &AppError) {
+    let Some(client) = SENTRY.get() else {
+        return;
+    };
+    if client.is_sampled() {
+        client.spawn(client.error_event(error));
+    }
+}
+
+/// ## Waits for the captured errors that are still being sent.
+///
+/// Function is registered as the `sentry` shutdown hook,
+/// it does nothing until `init` installed the client.
+pub async fn flush() {
+    if let Some(client) = SENTRY.get() {
+        client.flush(SENTRY_TIMEOUT).await;
+    }
+}
+
+/// ## Request context middleware.
+///
+/// Function is used with `axum::middleware::from_fn`
+/// and records the method, route and client address of
+/// the request for the events captured while handling it.
+pub async fn context(request: Request, next: Next) -> Response {
+    let context = RequestContext {
+        method: request.method().to_string(),
+        route: request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_else(|| request.uri().path().to_string()),
+        client_ip: request
+            .extensions()
+            .get::<ClientIp>()
+            .map(|ip| ip.0.to_string()),
+    };
+
+    REQUEST.scope(context, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, routing::post, Router};
+    use std::sync::Mutex;
+
+    fn settings() -> SentrySettings {
+        SentrySettings {
+            enabled: true,
+            environment: "staging".to_string(),
+            ..SentrySettings::default()
+        }
+    }
+
+    // Test checks if DSNs are turned into envelope endpoints and invalid ones are rejected
+    #[test]
+    fn test_dsn() {
+        let client = SentryClient::new("http://key@localhost:9000/sentry/7", &settings()).unwrap();
+        assert_eq!(
+            client.envelope_url(),
+            "http://localhost:9000/sentry/api/7/envelope/"
+        );
+
+        assert!(SentryClient::new("https://o0.ingest.sentry.io/42", &settings()).is_err());
+        assert!(SentryClient::new("https://key@o0.ingest.sentry.io", &settings()).is_err());
+        assert!(SentryClient::new("not a dsn", &settings()).is_err());
+    }
+
+    // Test checks if error events carry the kind, code, request and user
+    #[tokio::test]
+    async fn test_error_event() {
+        let client = SentryClient::new("https://key@o0.ingest.sentry.io/42", &settings()).unwrap();
+        let error = AppError::internal("Database pool exhausted").with_code("db.pool_exhausted");
+        let request = RequestContext {
+            method: "GET".to_string(),
+            route: "/users/{id}".to_string(),
+            client_ip: Some("203.0.113.7".to_string()),
+        };
+
+        let event = REQUEST
+            .scope(request, async { client.error_event(&error) })
+            .await;
+
+        assert_eq!(event["level"], "error");
+        assert_eq!(event["environment"], "staging");
+        assert_eq!(event["tags"]["kind"], "Internal");
+        assert_eq!(event["tags"]["code"], "db.pool_exhausted");
+        assert_eq!(event["transaction"], "GET /users/{id}");
+        assert_eq!(
+            event["exception"]["values"][0]["value"],
+            "Database pool exhausted"
+        );
+        assert!(event.get("user").is_none());
+
+        let unsampled = SentryClient::new(
+            "https://key@o0.ingest.sentry.io/42",
+            &SentrySettings {
+                sample_rate: 0.0,
+                ..settings()
+            },
+        )
+        .unwrap();
+        assert!(!unsampled.is_sampled());
+        assert!(client.is_sampled());
+    }
+
+    type Received = Arc<Mutex<Vec<(String, Json)>>>;
+
+    /// Starts a Sentry API recording the received events.
+    async fn sentry_api() -> (Received, std::net::SocketAddr) {
+        let received: Received = Arc::default();
+        let router = Router::new()
+            .route(
+                "/api/42/envelope/",
+                post(
+                    |State(received): State<Received>, headers: HeaderMap, body: String| async move {
+                        let auth = headers["x-sentry-auth"].to_str().unwrap().to_string();
+                        let lines: Vec<Json> = body
+                            .lines()
+                            .map(|line| serde_json::from_str(line).unwrap())
+                            .collect();
+                        assert_eq!(lines[0]["event_id"], lines[2]["event_id"]);
+                        assert_eq!(lines[1]["type"], "event");
+                        received.lock().unwrap().push((auth, lines[2].clone()));
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        (received, address)
+    }
+
+    fn report() -> PanicReport {
+        PanicReport {
+            message: "index out of bounds".to_string(),
+            location: Some("src/lib.rs:1:1".to_string()),
+            thread: "tokio-runtime-worker".to_string(),
+            request_id: Some("abc-123".to_string()),
+            backtrace: None,
+        }
+    }
+
+    // Test checks if events are sent with the key of the DSN
+    #[tokio::test]
+    async fn test_send() {
+        let (received, address) = sentry_api().await;
+        let client =
+            SentryClient::new(&format!("http://public@{}/42", address), &settings()).unwrap();
+        client.send(&client.panic_event(&report())).await.unwrap();
+
+        let (auth, event) = received.lock().unwrap().pop().unwrap();
+        assert!(auth.contains("sentry_key=public"));
+        assert_eq!(event["level"], "fatal");
+        assert_eq!(
+            event["exception"]["values"][0]["value"],
+            "index out of bounds"
+        );
+        assert_eq!(
+            event["exception"]["values"][0]["mechanism"]["handled"],
+            false
+        );
+    }
+
+    // Test checks if captured errors are sent by the time the client is flushed
+    #[tokio::test]
+    async fn test_flush() {
+        let (received, address) = sentry_api().await;
+        let client =
+            SentryClient::new(&format!("http://public@{}/42", address), &settings()).unwrap();
+
+        for _ in 0..3 {
+            client.spawn(client.error_event(&AppError::internal("Database pool exhausted")));
+        }
+        client.flush(SENTRY_TIMEOUT).await;
+
+        assert_eq!(received.lock().unwrap().len(), 3);
+        assert!(client.in_flight.lock().unwrap().is_empty());
+    }
+
+    // Test checks if a crash report is sent before the reporter returns
+    // and is bounded when Sentry does not answer
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_report_is_synchronous() {
+        let (received, address) = sentry_api().await;
+        let client =
+            SentryClient::new(&format!("http://public@{}/42", address), &settings()).unwrap();
+
+        client.report(&report());
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        client.report(&PanicReport {
+            thread: CRASH_REPORT_THREAD.to_string(),
+            ..report()
+        });
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        // Nothing listens on the port of a dropped listener
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = listener.local_addr().unwrap();
+        drop(listener);
+        let client =
+            SentryClient::new(&format!("http://public@{}/42", closed), &settings()).unwrap();
+        assert!(client.send_blocking(client.panic_event(&report())).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! CORS module.
+//!
+//! Module builds the `CorsLayer` from the `[cors]`
+//! section of the configuration.
+
+// Imports from external crates
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+// Local imports
+use crate::core::config::{CorsSettings, CORS_WILDCARD};
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## Builds the CORS layer.
+///
+/// Browsers ignore credentials for wildcard responses,
+/// so wildcards combined with `allow_credentials` are
+/// rejected instead of silently not working.
+///
+/// ## Parameters
+/// - `settings`: `&CorsSettings` - CORS settings.
+///
+/// ## Returns
+/// + `Result<Option<CorsLayer>, AppError>`
+///     - `Ok(Some(CorsLayer))`: If at least one origin is allowed.
+///     - `Ok(None)`: If CORS is disabled.
+///     - `Err(AppError)`: If the settings are invalid.
+pub fn cors_layer(settings: &CorsSettings) -> Result<Option<CorsLayer>, AppError> {
+    if settings.allowed_origins.is_empty() {
+        return Ok(None);
+    }
+
+    let origins = allow_origin(settings)?;
+    let methods = allow_methods(settings)?;
+    let headers = allow_headers(settings)?;
+
+    let layer = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .max_age(Duration::from_secs(settings.max_age))
+        .allow_credentials(settings.allow_credentials);
+
+    Ok(Some(layer))
+}
+
+/// ## Builds the allowed origins.
+fn allow_origin(settings: &CorsSettings) -> Result<AllowOrigin, AppError> {
+    if is_wildcard(&settings.allowed_origins) {
+        reject_wildcard_with_credentials(settings, "origin")?;
+        return Ok(AllowOrigin::any());
+    }
+
+    let origins = settings
+        .allowed_origins
+        .iter()
+        .map(|origin| {
+            // Origin is a scheme and an authority, without path or query
+            let is_origin = (origin.starts_with("http://") || origin.starts_with("https://"))
+                && origin.matches('/').count() == 2
+                && !origin.contains(['?', '#']);
+
+            match (is_origin, HeaderValue::from_str(origin)) {
+                (true, Ok(value)) => Ok(value),
+                _ => Err(invalid(format!("Invalid CORS origin '{}'", origin))),
+            }
+        })
+        .collect::<Result<Vec<HeaderValue>, AppError>>()?;
+
+    Ok(AllowOrigin::list(origins))
+}
+
+/// ## Builds the allowed methods.
+fn allow_methods(settings: &CorsSettings) -> Result<AllowMethods, AppError> {
+    if is_wildcard(&settings.allowed_methods) {
+        reject_wildcard_with_credentials(settings, "method")?;
+        return Ok(AllowMethods::any());
+    }
+
+    let methods = settings
+        .allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| invalid(format!("Invalid CORS method '{}'", method)))
+        })
+        .collect::<Result<Vec<Method>, AppError>>()?;
+
+    Ok(AllowMethods::list(methods))
+}
+
+/// ## Builds the allowed headers.
+fn allow_headers(settings: &CorsSettings) -> Result<AllowHeaders, AppError> {
+    if is_wildcard(&settings.allowed_headers) {
+        reject_wildcard_with_credentials(settings, "header")?;
+        return Ok(AllowHeaders::any());
+    }
+
+    let headers = settings
+        .allowed_headers
+        .iter()
+        .map(|header| {
+            HeaderName::from_bytes(header.as_bytes())
+                .map_err(|_| invalid(format!("Invalid CORS header '{}'", header)))
+        })
+        .collect::<Result<Vec<HeaderName>, AppError>>()?;
+
+    Ok(AllowHeaders::list(headers))
+}
+
+/// ## Checks if the values contain the wildcard.
+fn is_wildcard(values: &[String]) -> bool {
+    values.iter().any(|value| value == CORS_WILDCARD)
+}
+
+/// ## Rejects a wildcard when credentials are allowed.
+fn reject_wildcard_with_credentials(settings: &CorsSettings, what: &str) -> Result<(), AppError> {
+    if settings.allow_credentials {
+        return Err(invalid(format!(
+            "Wildcard CORS {} can not be combined with allow_credentials",
+            what
+        )));
+    }
+
+    Ok(())
+}
+
+/// ## Builds an invalid CORS settings error.
+fn invalid(message: String) -> AppError {
+    AppError::new(ErrorKind::Config, message, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn settings(origins: &[&str], allow_credentials: bool) -> CorsSettings {
+        CorsSettings {
+            allowed_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            allow_credentials,
+            ..CorsSettings::default()
+        }
+    }
+
+    // Test checks if no layer is built when no origin is allowed.
+    #[test]
+    fn test_cors_layer_disabled() {
+        let result = cors_layer(&CorsSettings::default()).unwrap();
+
+        assert!(result.is_none());
+    }
+
+    // Test checks if a wildcard origin combined with credentials is rejected.
+    #[test]
+    fn test_cors_layer_wildcard_with_credentials() {
+        let result = cors_layer(&settings(&["*"], true)).unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Config);
+        assert!(cors_layer(&settings(&["*"], false)).unwrap().is_some());
+    }
+
+    // Test checks if wildcard methods and headers combined with credentials are rejected.
+    #[test]
+    fn test_cors_layer_wildcard_methods_headers_with_credentials() {
+        let mut methods = settings(&["https://app.example.com"], true);
+        methods.allowed_methods = vec!["*".to_string()];
+
+        let mut headers = settings(&["https://app.example.com"], true);
+        headers.allowed_headers = vec!["*".to_string()];
+
+        assert!(cors_layer(&methods).is_err());
+        assert!(cors_layer(&headers).is_err());
+    }
+
+    // Test checks if invalid origins, methods and headers are rejected.
+    #[test]
+    fn test_cors_layer_invalid_values() {
+        let mut method = settings(&["https://app.example.com"], false);
+        method.allowed_methods = vec!["GE T".to_string()];
+
+        let mut header = settings(&["https://app.example.com"], false);
+        header.allowed_headers = vec!["bad header".to_string()];
+
+        assert!(cors_layer(&settings(&["app.example.com"], false)).is_err());
+        assert!(cors_layer(&settings(&["https://app.example.com/path"], false)).is_err());
+        assert!(cors_layer(&method).is_err());
+        assert!(cors_layer(&header).is_err());
+    }
+
+    // Test checks if allowed origins receive CORS headers on preflight requests.
+    #[tokio::test]
+    async fn test_cors_layer_preflight() {
+        let layer = cors_layer(&settings(&["https://app.example.com"], true))
+            .unwrap()
+            .unwrap();
+        let router: Router = Router::new().route("/", get(|| async {})).layer(layer);
+
+        let request = Request::builder()
+            .method("OPTIONS")
+            .uri("/")
+            .header("origin", "https://app.example.com")
+            .header("access-control-request-method", "POST")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let headers = response.headers();
+
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "https://app.example.com"
+        );
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+        assert_eq!(headers["access-control-max-age"], "600");
+    }
+}
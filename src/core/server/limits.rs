@@ -0,0 +1,245 @@
+//! Request limits module.
+//!
+//! Middleware sheds requests beyond the concurrency
+//! limit with 503, rejects bodies larger than the
+//! maximum size with 413 and answers requests that take
+//! longer than the timeout with 408. Every rejection is
+//! rendered as the standard error body.
+
+// Imports from external crates
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::header::CONTENT_LENGTH,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Semaphore;
+
+// Local imports
+use crate::core::config::ServerSettings;
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## Request limits struct.
+///
+/// ## Fields
+/// + `timeout`: `Duration` - Time a request may take.
+/// + `max_body_size`: `usize` - Bytes a request body may have.
+/// + `permits`: `Option<Arc<Semaphore>>` - Requests that may be
+///   handled at once, unlimited when `None`.
+///
+/// ## Examples
+/// ```
+/// use axum::{middleware, Router};
+/// use axum_auth::core::config::ServerSettings;
+/// use axum_auth::core::server::limits::{limits, Limits};
+/// use std::sync::Arc;
+///
+/// let state = Arc::new(Limits::new(&ServerSettings::default()));
+/// let router: Router = Router::new().layer(middleware::from_fn_with_state(state, limits));
+/// ```
+#[derive(Debug)]
+pub struct Limits {
+    pub timeout: Duration,
+    pub max_body_size: usize,
+    pub permits: Option<Arc<Semaphore>>,
+}
+
+impl Limits {
+    /// ## Creates the limits of the server settings.
+    pub fn new(settings: &ServerSettings) -> Self {
+        Limits {
+            timeout: Duration::from_secs(settings.request_timeout),
+            max_body_size: settings.max_body_size,
+            permits: settings
+                .concurrency_limit
+                .map(|limit| Arc::new(Semaphore::new(limit))),
+        }
+    }
+}
+
+/// ## Request limits middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`
+/// inside the request ID layer. Bodies declaring their length
+/// are checked against the header and streamed, bodies without
+/// a length are buffered up to the maximum size.
+pub async fn limits(State(limits): State<Arc<Limits>>, request: Request, next: Next) -> Response {
+    // Permit is held until the response is returned
+    let _permit = match &limits.permits {
+        Some(permits) => match permits.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => return overloaded().into_response(),
+        },
+        None => None,
+    };
+
+    let handled = async {
+        let request = limit_body(request, limits.max_body_size).await?;
+
+        Ok::<_, AppError>(next.run(request).await)
+    };
+
+    match tokio::time::timeout(limits.timeout, handled).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => e.into_response(),
+        Err(_) => timed_out().into_response(),
+    }
+}
+
+/// ## Rejects bodies larger than the maximum size.
+///
+/// ## Returns
+/// + `Result<Request, AppError>`
+///     - `Ok(Request)`: Request with the body streamed, or buffered
+///       when it has no length.
+///     - `Err(AppError)`: If the body is too large.
+async fn limit_body(request: Request, max_body_size: usize) -> Result<Request, AppError> {
+    let length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+
+    match length {
+        Some(length) if length > max_body_size as u64 => Err(too_large(max_body_size)),
+        Some(_) => Ok(request),
+        None => {
+            let (parts, body) = request.into_parts();
+            let bytes = to_bytes(body, max_body_size)
+                .await
+                .map_err(|_| too_large(max_body_size))?;
+
+            Ok(Request::from_parts(parts, Body::from(bytes)))
+        }
+    }
+}
+
+/// ## Returns the error of a request beyond the concurrency limit.
+fn overloaded() -> AppError {
+    AppError::new(
+        ErrorKind::Internal,
+        "Server is busy, try again later".to_string(),
+        None,
+    )
+    .with_code("server.overloaded")
+    .with_status_hint(503)
+}
+
+/// ## Returns the error of a body larger than the maximum size.
+fn too_large(max_body_size: usize) -> AppError {
+    AppError::validation(format!(
+        "Request body is larger than {} bytes",
+        max_body_size
+    ))
+    .with_code("request.body_too_large")
+    .with_status_hint(413)
+}
+
+/// ## Returns the error of a request taking longer than the timeout.
+fn timed_out() -> AppError {
+    AppError::new(ErrorKind::Io, "Request timed out".to_string(), None)
+        .with_code("request.timeout")
+        .with_status_hint(408)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Bytes,
+        http::{Request as HttpRequest, StatusCode},
+        middleware,
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    /// Returns a router echoing the body, sleeping for the milliseconds of the path.
+    fn router(settings: Limits) -> Router {
+        Router::new()
+            .route(
+                "/:millis",
+                post(
+                    |axum::extract::Path(millis): axum::extract::Path<u64>, body: Bytes| async move {
+                        tokio::time::sleep(Duration::from_millis(millis)).await;
+                        body
+                    },
+                ),
+            )
+            .layer(middleware::from_fn_with_state(Arc::new(settings), limits))
+    }
+
+    /// Returns the limits used by the tests.
+    fn test_limits(permits: Option<usize>) -> Limits {
+        Limits {
+            timeout: Duration::from_millis(200),
+            max_body_size: 8,
+            permits: permits.map(|limit| Arc::new(Semaphore::new(limit))),
+        }
+    }
+
+    /// Returns the status and the body of the response.
+    async fn send(router: Router, request: HttpRequest<Body>) -> (StatusCode, String) {
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    /// Returns a request to the path without a body length.
+    fn request(uri: &str, body: &'static str) -> HttpRequest<Body> {
+        HttpRequest::post(uri).body(Body::from(body)).unwrap()
+    }
+
+    // Test checks if bodies larger than the maximum are rejected with or without a length.
+    #[tokio::test]
+    async fn test_body_size() {
+        let (status, body) = send(router(test_limits(None)), request("/0", "12345678")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "12345678");
+
+        let (status, body) = send(router(test_limits(None)), request("/0", "123456789")).await;
+
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(body.contains("\"code\":\"request.body_too_large\""));
+
+        // Declared length is checked before the body is read
+        let declared = HttpRequest::post("/0")
+            .header(CONTENT_LENGTH, "1024")
+            .body(Body::empty())
+            .unwrap();
+
+        assert_eq!(
+            send(router(test_limits(None)), declared).await.0,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    // Test checks if requests taking longer than the timeout are answered with 408.
+    #[tokio::test]
+    async fn test_timeout() {
+        let (status, body) = send(router(test_limits(None)), request("/1000", "")).await;
+
+        assert_eq!(status, StatusCode::REQUEST_TIMEOUT);
+        assert!(body.contains("\"code\":\"request.timeout\""));
+    }
+
+    // Test checks if requests beyond the concurrency limit are shed with 503.
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let router = router(test_limits(Some(1)));
+
+        let slow = tokio::spawn(send(router.clone(), request("/100", "")));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let (status, body) = send(router.clone(), request("/0", "")).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body.contains("\"code\":\"server.overloaded\""));
+        assert_eq!(slow.await.unwrap().0, StatusCode::OK);
+        assert_eq!(send(router, request("/0", "")).await.0, StatusCode::OK);
+    }
+}
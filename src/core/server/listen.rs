@@ -0,0 +1,274 @@
+//! Listener module.
+//!
+//! Module opens the socket the server accepts connections
+//! on. Besides `host` and `port`, `server.listen` names a
+//! Unix socket for a reverse proxy on the same machine, or
+//! `systemd` to take the first socket passed by socket
+//! activation (`LISTEN_FDS`). Unix peers have no address,
+//! they are seen as `127.0.0.1`, so `client_ip.trusted_proxies`
+//! covering loopback keeps resolving the forwarded address.
+
+// Imports from external crates
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use {
+    axum::{extract::ConnectInfo, Extension, Router},
+    hyper_util::{
+        rt::{TokioExecutor, TokioIo},
+        server::{conn::auto::Builder, graceful::GracefulShutdown},
+        service::TowerToHyperService,
+    },
+    std::{net::SocketAddr, os::unix::fs::FileTypeExt, time::Duration},
+    tokio::net::UnixListener,
+};
+
+// Local imports
+use super::bind;
+use super::shutdown::ShutdownCoordinator;
+use crate::core::config::ServerSettings;
+use crate::core::err::{AppError, ErrorKind};
+
+/// ## Listener enum.
+///
+/// # Variants
+/// - `Tcp` - TCP socket, bound or passed by systemd.
+/// - `Unix` - Unix socket, bound or passed by systemd.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// ## Opens the listener of the settings.
+///
+/// ## Parameters
+/// - `settings`: `&ServerSettings` - Server settings.
+///
+/// ## Returns
+/// + `Result<Listener, AppError>`
+///     - `Ok(Listener)`: Socket of `server.listen`, or of `host` and `port`.
+///     - `Err(AppError)`: If the socket can not be bound or systemd
+///       passed none.
+pub async fn listen(settings: &ServerSettings) -> Result<Listener, AppError> {
+    if let Some(path) = settings.unix_socket() {
+        return bind_unix(path, settings.socket_permissions()?);
+    }
+    if settings.socket_activated() {
+        return activated();
+    }
+
+    Ok(Listener::Tcp(bind(settings).await?))
+}
+
+/// ## Binds the Unix socket with the permissions.
+///
+/// Socket file left by a stopped server is replaced,
+/// a socket a server still accepts on is not. The umask
+/// is narrowed while binding, so the socket never exists
+/// with wider permissions than `mode`.
+///
+/// ## Parameters
+/// - `path`: `&str` - Path of the socket file.
+/// - `mode`: `u32` - Permission bits of the socket.
+///
+/// ## Returns
+/// + `Result<Listener, AppError>`
+///     - `Ok(Listener)`: Bound Unix listener.
+///     - `Err(AppError)`: If the path is in use, not a socket or not writable.
+#[cfg(unix)]
+pub fn bind_unix(path: &str, mode: u32) -> Result<Listener, AppError> {
+    let failed = |e: std::io::Error| {
+        AppError::new(
+            ErrorKind::Io,
+            format!("Failed to bind the server to 'unix:{}'", path),
+            Some(Box::new(e)),
+        )
+    };
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(AppError::config(format!(
+                "'{}' of server.listen exists and is not a socket",
+                path
+            )));
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(AppError::new(
+                ErrorKind::Io,
+                format!("Socket 'unix:{}' is used by another server", path),
+                None,
+            ));
+        }
+        std::fs::remove_file(path).map_err(failed)?;
+    }
+
+    // SAFETY: umask only changes the mode of files created by the process
+    let umask = unsafe { libc::umask(!mode as libc::mode_t & 0o777) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+
+    Ok(Listener::Unix(listener.map_err(failed)?))
+}
+
+/// ## Binds the Unix socket with the permissions.
+#[cfg(not(unix))]
+pub fn bind_unix(path: &str, _mode: u32) -> Result<Listener, AppError> {
+    Err(AppError::config(format!(
+        "Unix socket 'unix:{}' is not supported on this platform",
+        path
+    )))
+}
+
+/// ## Takes the first socket passed by systemd.
+///
+/// ## Returns
+/// + `Result<Listener, AppError>`
+///     - `Ok(Listener)`: TCP or Unix listener of `LISTEN_FDS`.
+///     - `Err(AppError)`: If no socket was passed or it is not a stream socket.
+fn activated() -> Result<Listener, AppError> {
+    let mut fds = listenfd::ListenFd::from_env();
+    let failed = |e: std::io::Error| {
+        AppError::new(
+            ErrorKind::Io,
+            "Failed to take the socket passed by systemd".to_string(),
+            Some(Box::new(e)),
+        )
+    };
+
+    if let Ok(Some(listener)) = fds.take_tcp_listener(0) {
+        listener.set_nonblocking(true).map_err(failed)?;
+        return Ok(Listener::Tcp(
+            TcpListener::from_std(listener).map_err(failed)?,
+        ));
+    }
+    #[cfg(unix)]
+    if let Ok(Some(listener)) = fds.take_unix_listener(0) {
+        listener.set_nonblocking(true).map_err(failed)?;
+        return Ok(Listener::Unix(
+            UnixListener::from_std(listener).map_err(failed)?,
+        ));
+    }
+
+    Err(AppError::config(match fds.len() {
+        0 => "server.listen is 'systemd', but LISTEN_FDS passes no socket",
+        _ => "Socket passed by systemd is not a TCP or Unix stream socket",
+    }))
+}
+
+/// ## Serves requests on the Unix socket until the shutdown.
+///
+/// In-flight requests are drained for at most the grace
+/// period of the coordinator, like `drain` does for TCP.
+/// Cleanup hooks are not executed.
+///
+/// ## Parameters
+/// - `listener`: `UnixListener` - Bound listener.
+/// - `router`: `Router` - Application router.
+/// - `coordinator`: `ShutdownCoordinator` - Shutdown coordinator.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the server stopped gracefully.
+///     - `Err(AppError)`: If the server failed while running.
+#[cfg(unix)]
+pub async fn drain_unix(
+    listener: UnixListener,
+    router: Router,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError> {
+    let peer = ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0)));
+    let service = TowerToHyperService::new(router.layer(Extension(peer)));
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // Errors like too many open files pass
+                    tracing::warn!(error = %e, "Failed to accept a connection");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = coordinator.wait() => break,
+        };
+
+        let builder = builder.clone();
+        let service = service.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+            if let Err(e) = watcher.watch(connection).await {
+                tracing::debug!(error = %e, "Connection closed with an error");
+            }
+        });
+    }
+
+    // Stop accepting before draining the connections
+    drop(listener);
+    if tokio::time::timeout(coordinator.grace_period(), graceful.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            grace_period = ?coordinator.grace_period(),
+            "Grace period elapsed, aborting in-flight requests"
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Test checks if requests are served on the socket with the permissions and a loopback peer.
+    #[tokio::test]
+    async fn test_drain_unix() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.sock");
+        let path = path.to_str().unwrap();
+        // Stale socket of a stopped server is replaced
+        drop(std::os::unix::net::UnixListener::bind(path).unwrap());
+        let Listener::Unix(listener) = bind_unix(path, 0o600).unwrap() else {
+            panic!("Unix listener expected");
+        };
+        let mode = std::fs::metadata(path).unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(bind_unix(path, 0o600).is_err());
+
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let router = Router::new().route(
+            "/ip",
+            get(|ConnectInfo(client): ConnectInfo<SocketAddr>| async move { client.to_string() }),
+        );
+        let client = async {
+            let mut stream = tokio::net::UnixStream::connect(path).await.unwrap();
+            stream
+                .write_all(b"GET /ip HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response).await;
+
+            coordinator.trigger();
+            response
+        };
+
+        let (result, response) =
+            tokio::join!(drain_unix(listener, router, coordinator.clone()), client);
+
+        assert_eq!(result, Ok(()));
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("127.0.0.1:0"));
+    }
+}
@@ -0,0 +1,635 @@
+//! HTTP server module.
+//!
+//! Module builds the application router, binds the
+//! listener and serves requests until the shutdown
+//! coordinator signals the server to stop.
+
+// References to submodules
+pub mod cors;
+pub mod limits;
+pub mod listen;
+pub mod proxy;
+pub mod shutdown;
+pub mod tls;
+
+// Imports from external crates
+use axum::{extract::Request, middleware, Router};
+use axum_server::{
+    accept::{Accept, DefaultAcceptor},
+    service::SendService,
+    Handle,
+};
+use hyper::body::Incoming;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+};
+
+// Local imports
+use super::config::{ProxyProtocolSettings, ServerSettings};
+use super::err::{AppError, ErrorKind};
+use super::http::access_log::{access_log, AccessLog};
+use super::http::client_ip::{client_ip, ClientIpResolver};
+use super::http::error::error_format;
+use super::http::ip_filter::{ip_filter, IpFilter};
+use super::http::journal::{journal, Journal};
+use super::http::locale::message_language;
+use super::http::request_id::request_id;
+use super::http::trace::{trace, Sampler};
+use super::metrics;
+use super::panic;
+use super::state::AppState;
+use crate::routes;
+use crate::routes::registry::RouteRegistry;
+use limits::{limits, Limits};
+use listen::Listener;
+use proxy::ProxyAcceptor;
+use shutdown::ShutdownCoordinator;
+
+/// ## Registers every route of the server.
+///
+/// ## Parameters
+/// - `state`: `&AppState` - Configuration, database, webhooks and signing keys.
+///
+/// ## Returns
+/// + `Result<RouteRegistry, AppError>`
+///     - `Ok(RouteRegistry)`: Routes of the application, the OpenAPI
+///       document, the key set and the metrics.
+///     - `Err(AppError)`: If a route can not be built from the configuration.
+pub fn registry(state: &AppState) -> Result<RouteRegistry, AppError> {
+    let config = &state.config;
+    let mut registry = routes::registry(state)?
+        .merge(routes::openapi::registry(&config.openapi))
+        .merge(routes::jwks::routes().mount("", state.keys.clone(), None));
+
+    if config.metrics.enabled {
+        let mut metrics = routes::metrics::routes(config.database.query_metrics).mount(
+            "",
+            metrics::install()?,
+            None,
+        );
+        if config.ip_filter.enabled && config.ip_filter.metrics {
+            let filter = Arc::new(IpFilter::new(&config.ip_filter)?);
+            metrics = metrics.layer("ip_filter", |router| {
+                router.layer(middleware::from_fn_with_state(filter, ip_filter))
+            });
+        }
+        registry = registry.merge(metrics);
+    }
+
+    Ok(registry)
+}
+
+/// ## Builds the application router.
+///
+/// Routes of `registry` are wrapped in the layers
+/// every request passes through.
+///
+/// ## Parameters
+/// - `state`: `&AppState` - Configuration, database, webhooks and signing keys.
+///
+/// ## Returns
+/// + `Result<Router, AppError>`
+///     - `Ok(Router)`: Router with all application routes and layers.
+///     - `Err(AppError)`: If a layer can not be built from the configuration.
+pub fn router(state: &AppState) -> Result<Router, AppError> {
+    let config = &state.config;
+    let mut router = registry(state)?.into_router();
+
+    // Panics are caught right around the handlers, so
+    // every layer sees the 500 response instead
+    router = router.layer(panic::catch_panic());
+
+    // Sentry context is recorded inside the routes,
+    // where the matched route is known
+    #[cfg(feature = "sentry")]
+    if config.observability.sentry.enabled {
+        router = router.layer(middleware::from_fn(crate::core::sentry::context));
+    }
+
+    if let Some(cors) = cors::cors_layer(&config.cors)? {
+        router = router.layer(cors);
+    }
+
+    // Journal runs inside the request ID layer,
+    // so entries carry the ID of the request
+    let log = AccessLog::new(&config.access_log);
+    if config.journal.enabled {
+        let state = Arc::new(Journal::open(&config.journal)?.with_redactor(log.redactor().clone()));
+        tracing::warn!("Recording requests into '{}'", config.journal.dir);
+
+        router = router.layer(middleware::from_fn_with_state(state, journal));
+    }
+
+    // Limits run inside the access log, so shed and
+    // rejected requests are logged with their status
+    let state = Arc::new(Limits::new(&config.server));
+    router = router.layer(middleware::from_fn_with_state(state, limits));
+
+    // Access log runs inside the trace span, so its
+    // lines are part of the sampled request traces
+    if config.access_log.enabled {
+        router = router.layer(middleware::from_fn_with_state(Arc::new(log), access_log));
+    }
+
+    // Trace span is opened right inside the request ID
+    // layer, so it covers every other layer
+    let sampler = Arc::new(Sampler::new(&config.trace));
+    router = router.layer(middleware::from_fn_with_state(sampler, trace));
+
+    // Client address is resolved before the trace span
+    // is opened, so spans, logs and routes share it
+    let resolver = Arc::new(ClientIpResolver::new(&config.client_ip)?);
+    router = router.layer(middleware::from_fn_with_state(resolver, client_ip));
+
+    // Error format is negotiated for every inner layer
+    let errors = Arc::new(config.errors.clone());
+    router = router.layer(middleware::from_fn_with_state(errors, error_format));
+
+    // Error messages of every inner layer are translated
+    router = router.layer(middleware::from_fn(message_language));
+
+    // Request ID is the outermost layer, so every
+    // response and log line carries the ID
+    Ok(router.layer(middleware::from_fn(request_id)))
+}
+
+/// ## Binds the TCP listener.
+///
+/// Function binds the listener to the host and
+/// port from the server settings.
+///
+/// ## Parameters
+/// - `settings`: `&ServerSettings` - Server settings.
+///
+/// ## Returns
+/// + `Result<TcpListener, AppError>`
+///     - `Ok(TcpListener)`: If the listener was bound.
+///     - `Err(AppError)`: If the address is invalid or in use.
+pub async fn bind(settings: &ServerSettings) -> Result<TcpListener, AppError> {
+    let addr = format!("{}:{}", settings.host, settings.port);
+
+    TcpListener::bind(&addr).await.map_err(|e| {
+        AppError::new(
+            ErrorKind::Io,
+            format!("Failed to bind the server to '{}'", addr),
+            Some(Box::new(e)),
+        )
+    })
+}
+
+/// ## Starts the server.
+///
+/// Function opens the listener of `server.listen` and
+/// serves the application over HTTP, or over HTTPS when
+/// TLS is enabled. With `redirect_http_port` set, a second
+/// plain HTTP listener redirects clients to HTTPS.
+/// Cleanup hooks run once both listeners stopped.
+///
+/// ## Parameters
+/// - `settings`: `&ServerSettings` - Server settings.
+/// - `router`: `Router` - Application router.
+/// - `coordinator`: `ShutdownCoordinator` - Shutdown coordinator.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the server stopped gracefully.
+///     - `Err(AppError)`: If the server failed to start or while running.
+pub async fn start(
+    settings: &ServerSettings,
+    router: Router,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError> {
+    let proxy = &settings.proxy_protocol;
+    if proxy.enabled {
+        tracing::info!("Reading PROXY protocol headers of trusted proxies");
+    }
+
+    let listener = match listen::listen(settings).await? {
+        Listener::Tcp(listener) => listener,
+        #[cfg(unix)]
+        Listener::Unix(listener) => {
+            return start_unix(settings, listener, router, coordinator).await
+        }
+    };
+    let addr = listener
+        .local_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|_| format!("{}:{}", settings.host, settings.port));
+
+    if !settings.tls.enabled {
+        tracing::info!("Listening on http://{}", addr);
+
+        let result = drain_proxied(listener, router, proxy, coordinator.clone()).await;
+
+        // Release resources even if the server failed
+        coordinator.run_hooks().await;
+
+        return result;
+    }
+
+    let config = tls::rustls_config(&settings.tls).await?;
+    tracing::info!("Listening on https://{}", addr);
+
+    let redirect = match settings.tls.redirect_http_port {
+        Some(port) => {
+            let redirect_settings = ServerSettings {
+                host: settings.host.clone(),
+                port,
+                ..ServerSettings::default()
+            };
+            let listener = bind(&redirect_settings).await?;
+            tracing::info!("Redirecting http://{}:{} to HTTPS", settings.host, port);
+
+            Some(listener)
+        }
+        None => None,
+    };
+
+    let (result, redirect_result) = tokio::join!(
+        tls::drain_tls(listener, router, config, proxy, coordinator.clone()),
+        async {
+            match redirect {
+                Some(listener) => {
+                    drain_proxied(
+                        listener,
+                        tls::redirect_router(settings.port),
+                        proxy,
+                        coordinator.clone(),
+                    )
+                    .await
+                }
+                None => Ok(()),
+            }
+        }
+    );
+
+    // Release resources even if the server failed
+    coordinator.run_hooks().await;
+
+    result.and(redirect_result)
+}
+
+/// ## Serves the application on a Unix socket.
+///
+/// TLS and PROXY headers are left to the local proxy.
+/// A socket file bound by the server is removed once
+/// it stopped, one passed by systemd is kept.
+#[cfg(unix)]
+async fn start_unix(
+    settings: &ServerSettings,
+    listener: tokio::net::UnixListener,
+    router: Router,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError> {
+    if settings.tls.enabled {
+        return Err(AppError::config(
+            "TLS can not be enabled when listening on a Unix socket",
+        ));
+    }
+    if settings.proxy_protocol.enabled {
+        tracing::warn!("PROXY protocol headers are not read on Unix sockets");
+    }
+    tracing::info!("Listening on {}", settings.listen);
+
+    let result = listen::drain_unix(listener, router, coordinator.clone()).await;
+    if let Some(path) = settings.unix_socket() {
+        let _ = std::fs::remove_file(path);
+    }
+
+    // Release resources even if the server failed
+    coordinator.run_hooks().await;
+
+    result
+}
+
+/// ## Serves the application.
+///
+/// Function serves requests until the shutdown is
+/// triggered. After that it stops accepting new
+/// connections and waits for the in-flight requests
+/// for at most the grace period of the coordinator.
+/// Requests still running after the grace period are
+/// abandoned and dropped together with the runtime.
+/// Finally the cleanup hooks registered on the
+/// coordinator are executed.
+///
+/// ## Parameters
+/// - `listener`: `TcpListener` - Bound listener.
+/// - `router`: `Router` - Application router.
+/// - `coordinator`: `ShutdownCoordinator` - Shutdown coordinator.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the server stopped gracefully.
+///     - `Err(AppError)`: If the server failed while running.
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError> {
+    let result = drain(listener, router, coordinator.clone()).await;
+
+    // Release resources even if the server failed
+    coordinator.run_hooks().await;
+
+    result
+}
+
+/// ## Serves requests until the shutdown.
+///
+/// Same as `serve`, without running the cleanup hooks.
+async fn drain(
+    listener: TcpListener,
+    router: Router,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError> {
+    let shutdown = coordinator.clone();
+    let server = axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { shutdown.wait().await });
+    let mut server = tokio::spawn(async move { server.await });
+
+    // Serve until the shutdown is triggered or
+    // the server stops on its own
+    let result = tokio::select! {
+        result = &mut server => Some(result),
+        _ = coordinator.wait() => None,
+    };
+
+    // Drain in-flight requests within the grace period
+    let result = match result {
+        Some(result) => result,
+        None => match tokio::time::timeout(coordinator.grace_period(), &mut server).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!(
+                    grace_period = ?coordinator.grace_period(),
+                    "Grace period elapsed, aborting in-flight requests"
+                );
+                server.abort();
+                Ok(Ok(()))
+            }
+        },
+    };
+
+    match result {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(AppError::new(
+            ErrorKind::Internal,
+            "Server stopped with an error".to_string(),
+            Some(Box::new(e)),
+        )),
+        Err(e) => Err(AppError::new(
+            ErrorKind::Internal,
+            "Server task failed".to_string(),
+            Some(Box::new(e)),
+        )),
+    }
+}
+
+/// ## Serves requests until the shutdown, reading PROXY headers.
+///
+/// Same as `drain` when the proxy protocol is disabled.
+async fn drain_proxied(
+    listener: TcpListener,
+    router: Router,
+    proxy: &ProxyProtocolSettings,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError> {
+    match proxy.enabled {
+        true => {
+            let acceptor = ProxyAcceptor::new(DefaultAcceptor::new(), proxy)?;
+            drain_accepted(listener, router, acceptor, coordinator).await
+        }
+        false => drain(listener, router, coordinator).await,
+    }
+}
+
+/// ## Serves requests accepted by the acceptor until the shutdown.
+///
+/// In-flight requests are drained for at most the grace
+/// period of the coordinator. Cleanup hooks are not
+/// executed, see `serve`.
+///
+/// ## Parameters
+/// - `listener`: `TcpListener` - Bound listener.
+/// - `router`: `Router` - Application router.
+/// - `acceptor`: `A` - Acceptor of the connections, e.g. TLS.
+/// - `coordinator`: `ShutdownCoordinator` - Shutdown coordinator.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the server stopped gracefully.
+///     - `Err(AppError)`: If the server failed while running.
+pub async fn drain_accepted<A>(
+    listener: TcpListener,
+    router: Router,
+    acceptor: A,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError>
+where
+    A: Accept<TcpStream, Router> + Clone + Send + Sync + 'static,
+    A::Stream: AsyncRead + AsyncWrite + Unpin + Send,
+    A::Service: SendService<Request<Incoming>> + Send,
+    A::Future: Send,
+{
+    let handle = Handle::new();
+
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        // Notification is lost if the server is not listening yet
+        shutdown.listening().await;
+        coordinator.wait().await;
+        shutdown.graceful_shutdown(Some(coordinator.grace_period()));
+    });
+
+    let listener = listener.into_std().map_err(|e| {
+        AppError::new(
+            ErrorKind::Internal,
+            "Failed to convert the listener".to_string(),
+            Some(Box::new(e)),
+        )
+    })?;
+
+    axum_server::from_tcp(listener)
+        .acceptor(acceptor)
+        .handle(handle)
+        .serve(router.into_make_service())
+        .await
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                "Server stopped with an error".to_string(),
+                Some(Box::new(e)),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::ConnectInfo, routing::get};
+    use std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Router with a route that takes a while to respond.
+    fn slow_router(delay: Duration) -> Router {
+        Router::new().route(
+            "/slow",
+            get(move || async move {
+                tokio::time::sleep(delay).await;
+                "done"
+            }),
+        )
+    }
+
+    // Sends a request over a raw TCP connection and returns the response.
+    async fn send_request(addr: SocketAddr, path: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response).await;
+        response
+    }
+
+    // Test checks if in-flight requests are completed before the server stops.
+    #[tokio::test]
+    async fn test_serve_drains_in_flight_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(5));
+
+        let (result, response) = tokio::join!(
+            serve(
+                listener,
+                slow_router(Duration::from_millis(200)),
+                coordinator.clone(),
+            ),
+            async {
+                let request = tokio::spawn(send_request(addr, "/slow"));
+
+                // Give the request time to reach the handler
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                coordinator.trigger();
+
+                request.await.unwrap()
+            }
+        );
+
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("done"));
+        assert_eq!(result, Ok(()));
+    }
+
+    // Test checks if in-flight requests are aborted after the grace period.
+    #[tokio::test]
+    async fn test_serve_aborts_after_grace_period() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let coordinator = ShutdownCoordinator::new(Duration::from_millis(50));
+
+        let request = tokio::spawn(send_request(addr, "/slow"));
+        let trigger = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            coordinator.trigger();
+        };
+
+        let (result, _) = tokio::time::timeout(Duration::from_secs(2), async {
+            tokio::join!(
+                serve(
+                    listener,
+                    slow_router(Duration::from_secs(10)),
+                    coordinator.clone(),
+                ),
+                trigger
+            )
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(result, Ok(()));
+        assert!(!request.is_finished());
+
+        request.abort();
+    }
+
+    // Test checks if cleanup hooks run after the server stopped.
+    #[tokio::test]
+    async fn test_serve_runs_hooks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let flag = closed.clone();
+        coordinator.on_shutdown("close pool", async move {
+            flag.store(true, Ordering::SeqCst);
+        });
+        coordinator.trigger();
+
+        let result = serve(listener, Router::new(), coordinator).await;
+
+        assert_eq!(result, Ok(()));
+        assert!(closed.load(Ordering::SeqCst));
+    }
+
+    // Test checks if the client address of the PROXY header reaches the handlers.
+    #[tokio::test]
+    async fn test_drain_proxied_client_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let router = Router::new().route(
+            "/ip",
+            get(|ConnectInfo(client): ConnectInfo<SocketAddr>| async move { client.to_string() }),
+        );
+        let proxy = ProxyProtocolSettings {
+            enabled: true,
+            trusted_proxies: vec!["127.0.0.0/8".to_string()],
+            ..ProxyProtocolSettings::default()
+        };
+
+        let client = async {
+            let mut header = proxy::SIGNATURE.to_vec();
+            header.extend([0x21, 0x11, 0x00, 0x0c, 203, 0, 113, 7, 127, 0, 0, 1]);
+            header.extend(51234u16.to_be_bytes());
+            header.extend(addr.port().to_be_bytes());
+            header.extend(b"GET /ip HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+
+            // Wait for the server to listen
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&header).await.unwrap();
+
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response).await;
+
+            coordinator.trigger();
+            response
+        };
+
+        let (result, response) = tokio::join!(
+            drain_proxied(listener, router, &proxy, coordinator.clone()),
+            client
+        );
+
+        assert_eq!(result, Ok(()));
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("203.0.113.7:51234"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Proxy protocol module.
+//!
+//! Module reads the PROXY protocol v2 header sent by
+//! load balancers like HAProxy or AWS NLB in front of
+//! the listener. The client address of the header is
+//! attached to every request of the connection as
+//! `ConnectInfo<SocketAddr>`, so the real client address
+//! is known without HTTP forwarding headers.
+
+// Imports from external crates
+use axum::extract::ConnectInfo;
+use axum_server::accept::Accept;
+use ipnetwork::IpNetwork;
+use std::{
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    net::TcpStream,
+};
+use tower_http::add_extension::AddExtension;
+
+// Local imports
+use crate::core::config::ProxyProtocolSettings;
+use crate::core::err::AppError;
+
+/// Signature every PROXY protocol v2 header starts with.
+pub const SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Length of the fixed part of the header.
+const HEADER_LEN: usize = 16;
+
+/// ## Reads the PROXY protocol v2 header.
+///
+/// ## Parameters
+/// - `stream`: `&mut R` - Connection, positioned at its first byte.
+///
+/// ## Returns
+/// + `io::Result<Option<SocketAddr>>`
+///     - `Ok(Some(SocketAddr))`: Address of the client.
+///     - `Ok(None)`: If the header carries no address, e.g. for
+///       health checks of the proxy itself.
+///     - `Err(io::Error)`: If the header is missing or malformed.
+pub async fn read_header<R>(stream: &mut R) -> io::Result<Option<SocketAddr>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut header = [0u8; HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+
+    if header[..12] != SIGNATURE {
+        return Err(invalid("missing PROXY protocol v2 signature"));
+    }
+    if header[12] >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    let command = header[12] & 0x0f;
+    let family = header[13];
+    let len = u16::from_be_bytes([header[14], header[15]]) as usize;
+
+    // Addresses are followed by optional TLVs, which are skipped
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+
+    match command {
+        // LOCAL, the proxy connected on its own behalf
+        0x0 => Ok(None),
+        // PROXY, the connection was relayed for a client
+        0x1 => addresses(family, &payload),
+        _ => Err(invalid("unknown PROXY protocol command")),
+    }
+}
+
+/// ## Parses the source address of the header.
+fn addresses(family: u8, payload: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let (ip, port): (IpAddr, &[u8]) = match family >> 4 {
+        // AF_INET, source and destination address, then the ports
+        0x1 => {
+            let payload = payload
+                .get(..12)
+                .ok_or_else(|| invalid("truncated IPv4 addresses"))?;
+            let octets: [u8; 4] = payload[..4].try_into().unwrap_or_default();
+
+            (Ipv4Addr::from(octets).into(), &payload[8..10])
+        }
+        // AF_INET6
+        0x2 => {
+            let payload = payload
+                .get(..36)
+                .ok_or_else(|| invalid("truncated IPv6 addresses"))?;
+            let octets: [u8; 16] = payload[..16].try_into().unwrap_or_default();
+
+            (Ipv6Addr::from(octets).into(), &payload[32..34])
+        }
+        // AF_UNSPEC and AF_UNIX carry no usable client address
+        _ => return Ok(None),
+    };
+
+    Ok(Some(SocketAddr::new(
+        ip,
+        u16::from_be_bytes([port[0], port[1]]),
+    )))
+}
+
+/// ## Creates an invalid data error.
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// ## Client address acceptor struct.
+///
+/// Acceptor attaches the client address to the requests
+/// of the connection and then hands the connection to the
+/// inner acceptor, e.g. the TLS acceptor. With the proxy
+/// protocol enabled, connections from trusted proxies have
+/// to start with a PROXY protocol v2 header, others are
+/// served with their peer address.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::ProxyProtocolSettings;
+/// use axum_auth::core::server::proxy::ProxyAcceptor;
+/// use axum_server::accept::DefaultAcceptor;
+///
+/// let settings = ProxyProtocolSettings {
+///     enabled: true,
+///     trusted_proxies: vec!["10.0.0.0/8".to_string()],
+///     ..ProxyProtocolSettings::default()
+/// };
+/// let acceptor = ProxyAcceptor::new(DefaultAcceptor::new(), &settings).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProxyAcceptor<A> {
+    inner: A,
+    enabled: bool,
+    trusted: Arc<Vec<IpNetwork>>,
+    timeout: Duration,
+}
+
+impl<A> ProxyAcceptor<A> {
+    /// ## Creates a new `ProxyAcceptor` instance.
+    ///
+    /// ## Parameters
+    /// - `inner`: `A` - Acceptor the connection is handed to.
+    /// - `settings`: `&ProxyProtocolSettings` - Proxy protocol settings.
+    ///
+    /// ## Returns
+    /// + `Result<ProxyAcceptor<A>, AppError>`
+    ///     - `Ok(ProxyAcceptor<A>)`: Acceptor.
+    ///     - `Err(AppError)`: If a trusted proxy is not a valid network.
+    pub fn new(inner: A, settings: &ProxyProtocolSettings) -> Result<Self, AppError> {
+        Ok(ProxyAcceptor {
+            inner,
+            enabled: settings.enabled,
+            trusted: Arc::new(settings.networks()?),
+            timeout: Duration::from_secs(settings.header_timeout),
+        })
+    }
+
+    /// ## Checks if the peer has to send a header.
+    fn expects_header(&self, peer: IpAddr) -> bool {
+        self.enabled
+            && (self.trusted.is_empty() || self.trusted.iter().any(|net| net.contains(peer)))
+    }
+}
+
+impl<A, S> Accept<TcpStream, S> for ProxyAcceptor<A>
+where
+    A: Accept<TcpStream, AddExtension<S, ConnectInfo<SocketAddr>>> + Clone + Send + 'static,
+    A::Future: Send,
+    S: Send + 'static,
+{
+    type Stream = A::Stream;
+    type Service = A::Service;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(A::Stream, A::Service)>> + Send>>;
+
+    fn accept(&self, mut stream: TcpStream, service: S) -> Self::Future {
+        let acceptor = self.clone();
+
+        Box::pin(async move {
+            let peer = stream.peer_addr()?;
+            let mut client = peer;
+
+            if acceptor.expects_header(peer.ip()) {
+                let header = tokio::time::timeout(acceptor.timeout, read_header(&mut stream))
+                    .await
+                    .map_err(|_| invalid("timed out waiting for the PROXY protocol header"))
+                    .and_then(|result| result)
+                    .inspect_err(|e| {
+                        tracing::debug!(peer = %peer, error = %e, "Rejected proxied connection")
+                    })?;
+
+                client = header.unwrap_or(peer);
+            }
+
+            let service = AddExtension::new(service, ConnectInfo(client));
+            acceptor.inner.accept(stream, service).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(command: u8, family: u8, payload: &[u8]) -> Vec<u8> {
+        let mut header = SIGNATURE.to_vec();
+        header.push(0x20 | command);
+        header.push(family);
+        header.extend((payload.len() as u16).to_be_bytes());
+        header.extend(payload);
+        header
+    }
+
+    // Test checks if the client address is read from IPv4 and IPv6 headers.
+    #[tokio::test]
+    async fn test_read_header_addresses() {
+        let mut payload = vec![203, 0, 113, 7, 10, 0, 0, 1];
+        payload.extend(51234u16.to_be_bytes());
+        payload.extend(443u16.to_be_bytes());
+        // TLVs after the addresses are skipped
+        payload.extend([0x04, 0x00, 0x01, 0xff]);
+        let mut stream = header(0x1, 0x11, &payload);
+        stream.extend(b"GET / HTTP/1.1\r\n");
+
+        let mut reader = stream.as_slice();
+        let client = read_header(&mut reader).await.unwrap();
+
+        assert_eq!(client, Some("203.0.113.7:51234".parse().unwrap()));
+        assert_eq!(reader, b"GET / HTTP/1.1\r\n");
+
+        let ip: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let mut payload = ip.octets().to_vec();
+        payload.extend(Ipv6Addr::LOCALHOST.octets());
+        payload.extend(8080u16.to_be_bytes());
+        payload.extend(443u16.to_be_bytes());
+
+        let client = read_header(&mut header(0x1, 0x21, &payload).as_slice())
+            .await
+            .unwrap();
+
+        assert_eq!(client, Some("[2001:db8::1]:8080".parse().unwrap()));
+    }
+
+    // Test checks if LOCAL headers carry no address and malformed headers are rejected.
+    #[tokio::test]
+    async fn test_read_header_invalid() {
+        let local = read_header(&mut header(0x0, 0x00, &[]).as_slice())
+            .await
+            .unwrap();
+
+        assert_eq!(local, None);
+
+        let mut plain: &[u8] = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let result = read_header(&mut plain).await.unwrap_err();
+
+        assert_eq!(result.kind(), io::ErrorKind::InvalidData);
+
+        let truncated = header(0x1, 0x11, &[203, 0, 113]);
+        let result = read_header(&mut truncated.as_slice()).await.unwrap_err();
+
+        assert_eq!(result.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // Test checks if only trusted peers have to send a header.
+    #[test]
+    fn test_expects_header() {
+        let mut settings = ProxyProtocolSettings {
+            enabled: true,
+            trusted_proxies: vec!["10.0.0.0/8".to_string()],
+            ..ProxyProtocolSettings::default()
+        };
+        let acceptor = ProxyAcceptor::new((), &settings).unwrap();
+
+        assert!(acceptor.expects_header("10.1.2.3".parse().unwrap()));
+        assert!(!acceptor.expects_header("192.0.2.1".parse().unwrap()));
+
+        settings.trusted_proxies.clear();
+        let acceptor = ProxyAcceptor::new((), &settings).unwrap();
+
+        assert!(acceptor.expects_header("192.0.2.1".parse().unwrap()));
+
+        settings.enabled = false;
+        let acceptor = ProxyAcceptor::new((), &settings).unwrap();
+
+        assert!(!acceptor.expects_header("10.1.2.3".parse().unwrap()));
+    }
+}
@@ -0,0 +1,243 @@
+//! Module coordinates graceful shutdown of the application.
+//!
+//! The coordinator listens for termination signals, notifies
+//! the server to stop accepting new connections and runs the
+//! registered cleanup hooks (closing the database pool,
+//! flushing logs, ...) once the server has drained.
+
+// Imports from external crates
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::watch;
+
+/// Boxed future executed as part of the shutdown sequence.
+type ShutdownHook = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// ## Shutdown coordinator struct.
+///
+/// Struct is cheap to clone, every clone shares the same
+/// shutdown state, so it can be handed to the server and
+/// to any background task that has to react on shutdown.
+///
+/// ## Examples
+/// ```
+/// use std::time::Duration;
+/// use axum_auth::core::server::shutdown::ShutdownCoordinator;
+///
+/// let coordinator = ShutdownCoordinator::new(Duration::from_secs(30));
+///
+/// coordinator.on_shutdown("say goodbye", async {
+///     println!("Goodbye!");
+/// });
+/// coordinator.trigger();
+///
+/// assert!(coordinator.is_triggered());
+/// ```
+#[derive(Clone)]
+pub struct ShutdownCoordinator {
+    inner: Arc<Inner>,
+}
+
+/// Shared state of the shutdown coordinator.
+struct Inner {
+    grace_period: Duration,
+    sender: watch::Sender<bool>,
+    hooks: Mutex<Vec<(String, ShutdownHook)>>,
+}
+
+impl ShutdownCoordinator {
+    /// ## Creates a new `ShutdownCoordinator` instance.
+    ///
+    /// ## Parameters
+    /// - `grace_period`: Maximum time to wait for in-flight
+    ///   requests after the shutdown was triggered.
+    ///
+    /// ## Returns
+    /// - New `ShutdownCoordinator` instance.
+    pub fn new(grace_period: Duration) -> Self {
+        let (sender, _) = watch::channel(false);
+
+        ShutdownCoordinator {
+            inner: Arc::new(Inner {
+                grace_period,
+                sender,
+                hooks: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// ## Returns the configured grace period.
+    pub fn grace_period(&self) -> Duration {
+        self.inner.grace_period
+    }
+
+    /// ## Registers a cleanup hook.
+    ///
+    /// Hooks are executed one by one in the order of
+    /// registration, after the server stopped serving
+    /// requests.
+    ///
+    /// ## Parameters
+    /// - `name`: Name of the hook, used in log messages.
+    /// - `hook`: Future to execute on shutdown.
+    pub fn on_shutdown<F>(&self, name: &str, hook: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.inner
+            .hooks
+            .lock()
+            .expect("Shutdown hooks lock poisoned")
+            .push((name.to_string(), Box::pin(hook)));
+    }
+
+    /// ## Triggers the shutdown.
+    ///
+    /// Function notifies every task waiting on the
+    /// coordinator, calling it more than once has no
+    /// additional effect.
+    pub fn trigger(&self) {
+        self.inner.sender.send_replace(true);
+    }
+
+    /// ## Checks if the shutdown was triggered.
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.sender.borrow()
+    }
+
+    /// ## Waits until the shutdown is triggered.
+    ///
+    /// Future resolves immediately if the shutdown
+    /// was already triggered.
+    pub async fn wait(&self) {
+        let mut receiver = self.inner.sender.subscribe();
+
+        // Sender lives as long as the coordinator, so the
+        // only possible error is unreachable here
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    /// ## Listens for termination signals.
+    ///
+    /// Function spawns a task that triggers the shutdown
+    /// when the process receives SIGINT (Ctrl+C) or, on
+    /// Unix systems, SIGTERM.
+    pub fn listen_for_signals(&self) {
+        let coordinator = self.clone();
+
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            tracing::info!("Shutdown signal received, stopping the server");
+            coordinator.trigger();
+        });
+    }
+
+    /// ## Runs the registered cleanup hooks.
+    ///
+    /// Hooks are consumed, so running them a second
+    /// time is a no-op.
+    pub async fn run_hooks(&self) {
+        let hooks = std::mem::take(
+            &mut *self
+                .inner
+                .hooks
+                .lock()
+                .expect("Shutdown hooks lock poisoned"),
+        );
+
+        for (name, hook) in hooks {
+            tracing::info!(hook = %name, "Running shutdown hook");
+            hook.await;
+        }
+    }
+}
+
+/// ## Waits for a termination signal.
+///
+/// Function resolves on SIGINT (Ctrl+C) on every
+/// platform and on SIGTERM on Unix systems.
+async fn wait_for_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if waiting tasks are notified when the shutdown is triggered.
+    #[tokio::test]
+    async fn test_trigger_notifies_waiters() {
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let waiter = coordinator.clone();
+
+        let handle = tokio::spawn(async move { waiter.wait().await });
+        coordinator.trigger();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), handle).await;
+
+        assert!(result.is_ok());
+        assert!(coordinator.is_triggered());
+    }
+
+    // Test checks if waiting resolves immediately after the shutdown was triggered.
+    #[tokio::test]
+    async fn test_wait_after_trigger() {
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        coordinator.trigger();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), coordinator.wait()).await;
+
+        assert!(result.is_ok());
+    }
+
+    // Test checks if hooks are executed in the order of registration and only once.
+    #[tokio::test]
+    async fn test_run_hooks_in_order() {
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let calls: Arc<Mutex<Vec<&str>>> = Arc::new(Mutex::new(Vec::new()));
+
+        for name in ["first", "second"] {
+            let calls = calls.clone();
+            coordinator.on_shutdown(name, async move {
+                calls.lock().unwrap().push(name);
+            });
+        }
+
+        coordinator.run_hooks().await;
+        coordinator.run_hooks().await;
+
+        assert_eq!(*calls.lock().unwrap(), vec!["first", "second"]);
+    }
+}
@@ -0,0 +1,286 @@
+//! TLS module.
+//!
+//! Module serves the application over HTTPS using
+//! rustls and provides the plain HTTP listener that
+//! redirects clients to HTTPS.
+
+// Imports from external crates
+use axum::{
+    extract::Request,
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    version::{TLS12, TLS13},
+    ServerConfig, SupportedProtocolVersion,
+};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+// Local imports
+use super::drain_accepted;
+use super::proxy::ProxyAcceptor;
+use super::shutdown::ShutdownCoordinator;
+use crate::core::config::{ProxyProtocolSettings, TlsSettings};
+use crate::core::err::{AppError, ErrorKind};
+
+// Re-exports of the policy
+pub use crate::core::config::tls::{
+    active_policy, weaknesses, ActiveTlsPolicy, TlsPolicy, TlsVersion, TlsWeakness,
+    TLS12_CIPHER_SUITES, TLS13_CIPHER_SUITES, WEAK_CIPHER_MARKERS,
+};
+
+/// Default HTTPS port, omitted from redirect URLs.
+pub const DEFAULT_HTTPS_PORT: u16 = 443;
+
+/// ## Loads the rustls configuration.
+///
+/// Function reads the PEM files and restricts the
+/// protocol versions and cipher suites to the active
+/// policy, see `active_policy`.
+///
+/// ## Parameters
+/// - `settings`: `&TlsSettings` - TLS settings.
+///
+/// ## Returns
+/// + `Result<RustlsConfig, AppError>`
+///     - `Ok(RustlsConfig)`: If the certificate and key were loaded.
+///     - `Err(AppError)`: If the files are not valid PEM files.
+pub async fn rustls_config(settings: &TlsSettings) -> Result<RustlsConfig, AppError> {
+    let active = active_policy(settings);
+    let load_error = |e: Box<dyn std::error::Error>| {
+        AppError::new(
+            ErrorKind::Config,
+            format!(
+                "Failed to load TLS certificate '{}' and key '{}'",
+                settings.cert_path, settings.key_path
+            ),
+            Some(e),
+        )
+    };
+
+    let certs = CertificateDer::pem_file_iter(&settings.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| load_error(Box::new(e)))?;
+    let key =
+        PrivateKeyDer::from_pem_file(&settings.key_path).map_err(|e| load_error(Box::new(e)))?;
+
+    let mut provider = rustls::crypto::ring::default_provider();
+    provider.cipher_suites.retain(|suite| {
+        suite
+            .suite()
+            .as_str()
+            .is_some_and(|name| active.cipher_suites.iter().any(|active| active == name))
+    });
+
+    let versions: &[&SupportedProtocolVersion] = match active.min_version {
+        TlsVersion::Tls13 => &[&TLS13],
+        _ => &[&TLS12, &TLS13],
+    };
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(versions)
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| load_error(Box::new(e)))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(RustlsConfig::from_config(Arc::new(config)))
+}
+
+/// ## Serves the application over HTTPS.
+///
+/// Function serves requests until the shutdown is
+/// triggered, then drains in-flight requests for at
+/// most the grace period of the coordinator. Cleanup
+/// hooks are not executed, see `core::server::serve`.
+///
+/// ## Parameters
+/// - `listener`: `TcpListener` - Bound listener.
+/// - `router`: `Router` - Application router.
+/// - `config`: `RustlsConfig` - Certificate and key.
+/// - `proxy`: `&ProxyProtocolSettings` - PROXY protocol settings,
+///   the header is read before the TLS handshake.
+/// - `coordinator`: `ShutdownCoordinator` - Shutdown coordinator.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the server stopped gracefully.
+///     - `Err(AppError)`: If the server failed while running.
+pub async fn drain_tls(
+    listener: TcpListener,
+    router: Router,
+    config: RustlsConfig,
+    proxy: &ProxyProtocolSettings,
+    coordinator: ShutdownCoordinator,
+) -> Result<(), AppError> {
+    let acceptor = ProxyAcceptor::new(RustlsAcceptor::new(config), proxy)?;
+
+    drain_accepted(listener, router, acceptor, coordinator).await
+}
+
+/// ## Builds the HTTP to HTTPS redirect router.
+///
+/// Every request is answered with a permanent redirect
+/// to the same host, path and query on the HTTPS port.
+///
+/// ## Parameters
+/// - `https_port`: `u16` - Port of the HTTPS listener.
+///
+/// ## Returns
+/// - `Router`: Redirect router.
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |request: Request| async move { redirect(request, https_port) })
+}
+
+/// ## Redirects the request to HTTPS.
+fn redirect(request: Request, https_port: u16) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Uri>().ok())
+        .and_then(|uri| uri.host().map(str::to_string));
+
+    let Some(host) = host else {
+        return (StatusCode::BAD_REQUEST, "Missing or invalid Host header").into_response();
+    };
+
+    let authority = match https_port {
+        DEFAULT_HTTPS_PORT => host,
+        port => format!("{}:{}", host, port),
+    };
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+
+    Redirect::permanent(&format!("https://{}{}", authority, path)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get};
+    use std::{io::Write, sync::Arc, time::Duration};
+    use tempfile::NamedTempFile;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tower::ServiceExt;
+
+    fn write_pem(contents: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    async fn redirect_location(https_port: u16, host: &str, uri: &str) -> String {
+        let request = Request::builder()
+            .uri(uri)
+            .header(header::HOST, host)
+            .body(Body::empty())
+            .unwrap();
+        let response = redirect_router(https_port).oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+
+        response.headers()[header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    // Test checks if requests are redirected to the HTTPS port.
+    #[tokio::test]
+    async fn test_redirect() {
+        assert_eq!(
+            redirect_location(443, "example.com:80", "/login?next=%2F").await,
+            "https://example.com/login?next=%2F"
+        );
+        assert_eq!(
+            redirect_location(8443, "example.com", "/").await,
+            "https://example.com:8443/"
+        );
+    }
+
+    // Test checks if requests without a Host header are rejected.
+    #[tokio::test]
+    async fn test_redirect_missing_host() {
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = redirect_router(443).oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Test checks if invalid PEM files are rejected.
+    #[tokio::test]
+    async fn test_rustls_config_invalid() {
+        let cert = write_pem("not a certificate");
+        let key = write_pem("not a key");
+        let settings = TlsSettings {
+            enabled: true,
+            cert_path: cert.path().to_str().unwrap().to_string(),
+            key_path: key.path().to_str().unwrap().to_string(),
+            ..TlsSettings::default()
+        };
+
+        let result = rustls_config(&settings).await.unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Config);
+    }
+
+    // Test checks if requests are served over HTTPS.
+    #[tokio::test]
+    async fn test_drain_tls() {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert = write_pem(&certified.cert.pem());
+        let key = write_pem(&certified.key_pair.serialize_pem());
+        let settings = TlsSettings {
+            enabled: true,
+            cert_path: cert.path().to_str().unwrap().to_string(),
+            key_path: key.path().to_str().unwrap().to_string(),
+            ..TlsSettings::default()
+        };
+
+        let config = rustls_config(&settings).await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let router = Router::new().route("/", get(|| async { "secure" }));
+        let proxy = ProxyProtocolSettings::default();
+
+        let client = async {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.add(certified.cert.der().clone()).unwrap();
+            let client_config = rustls::ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+
+            let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let domain = rustls::pki_types::ServerName::try_from("localhost").unwrap();
+            let mut stream = connector.connect(domain, stream).await.unwrap();
+
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = Vec::new();
+            let _ = stream.read_to_end(&mut response).await;
+
+            coordinator.trigger();
+            String::from_utf8(response).unwrap()
+        };
+
+        let (result, response) = tokio::join!(
+            drain_tls(listener, router, config, &proxy, coordinator.clone()),
+            client
+        );
+
+        assert_eq!(result, Ok(()));
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("secure"));
+    }
+}
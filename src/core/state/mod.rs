@@ -0,0 +1,225 @@
+//! Application state module.
+//!
+//! `AppState` holds what the server is built from: the
+//! configuration, the database executor, the webhook
+//! queue, the signing keys, the mail provider, in
+//! development the captured mail, and the configuration
+//! published by the reload watcher. Run modes and tests build
+//! it with `AppStateBuilder`, so every dependency of the
+//! router is passed in explicitly instead of being read
+//! from a global.
+
+// Imports from external crates
+use std::sync::Arc;
+use tokio::sync::watch;
+
+// Local imports
+use super::auth::keys::KeyManager;
+use super::config::{secrets::MasterKey, AppConfig};
+use super::db::DbExecutor;
+use super::err::AppError;
+use super::mail::{templates::MailTemplates, MailCapture, Mailer};
+use super::repo::postgres::PgSigningKeyRepo;
+use super::webhooks::Webhooks;
+
+/// ## Application state struct.
+///
+/// ## Fields
+/// + `config`: `Arc<AppConfig>` - Application configuration.
+/// + `db`: `DbExecutor` - Database primary and read replicas.
+/// + `webhooks`: `Webhooks` - Queue of the webhook events.
+/// + `keys`: `Arc<KeyManager>` - Keys the access tokens are signed with.
+/// + `mailer`: `Option<Arc<dyn Mailer>>` - Mailer of `mail.provider`,
+///   `None` when mail leaves as webhook events.
+/// + `mailbox`: `Option<MailCapture>` - Mail captured instead of sent,
+///   only set in development without a mail provider.
+/// + `updates`: `Option<watch::Receiver<Arc<AppConfig>>>` - Configuration
+///   with the hot-reloaded changes applied, `None` when `reload` is
+///   disabled.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::config::{read_config, DEFAULT_CONFIG_FILE};
+/// use axum_auth::core::state::AppState;
+/// use sqlx::postgres::PgPoolOptions;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let pool = PgPoolOptions::new()
+///     .connect_lazy("postgres://localhost/axum_auth")
+///     .unwrap();
+/// let state = AppState::builder(read_config(DEFAULT_CONFIG_FILE, None).unwrap())
+///     .db(pool)
+///     .build()
+///     .unwrap();
+///
+/// let key = state.keys.current().await.unwrap();
+/// assert_eq!(key.kid(), state.config.tokens.key_id);
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<AppConfig>,
+    pub db: DbExecutor,
+    pub webhooks: Webhooks,
+    pub keys: Arc<KeyManager>,
+    pub mailer: Option<Arc<dyn Mailer>>,
+    pub mailbox: Option<MailCapture>,
+    pub updates: Option<watch::Receiver<Arc<AppConfig>>>,
+}
+
+impl AppState {
+    /// ## Starts building the state of the configuration.
+    ///
+    /// ## Parameters
+    /// - `config`: `impl Into<Arc<AppConfig>>` - Application configuration.
+    pub fn builder(config: impl Into<Arc<AppConfig>>) -> AppStateBuilder {
+        AppStateBuilder {
+            config: config.into(),
+            db: None,
+            webhooks: None,
+            keys: None,
+            mailer: None,
+            updates: None,
+        }
+    }
+}
+
+/// ## Application state builder struct.
+///
+/// The database is required. The webhook queue and the
+/// signing keys are built from the configuration when
+/// they are not set, rotated keys are stored in the
+/// database when `key_rotation` is enabled. Mail of the
+/// webhook events is sent by the mailer when it is set,
+/// and captured in development otherwise.
+pub struct AppStateBuilder {
+    config: Arc<AppConfig>,
+    db: Option<DbExecutor>,
+    webhooks: Option<Webhooks>,
+    keys: Option<Arc<KeyManager>>,
+    mailer: Option<Arc<dyn Mailer>>,
+    updates: Option<watch::Receiver<Arc<AppConfig>>>,
+}
+
+impl AppStateBuilder {
+    /// ## Sets the database executor or pool.
+    pub fn db(mut self, db: impl Into<DbExecutor>) -> Self {
+        self.db = Some(db.into());
+        self
+    }
+
+    /// ## Sets the webhook queue.
+    pub fn webhooks(mut self, webhooks: Webhooks) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// ## Sets the signing keys of the access tokens.
+    pub fn keys(mut self, keys: Arc<KeyManager>) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    /// ## Sets the mailer of the mail provider.
+    pub fn mailer(mut self, mailer: Arc<dyn Mailer>) -> Self {
+        self.mailer = Some(mailer);
+        self
+    }
+
+    /// ## Sets the configuration published by the reload watcher.
+    pub fn updates(mut self, updates: watch::Receiver<Arc<AppConfig>>) -> Self {
+        self.updates = Some(updates);
+        self
+    }
+
+    /// ## Builds the state.
+    ///
+    /// ## Returns
+    /// + `Result<AppState, AppError>`
+    ///     - `Ok(AppState)`: State with every dependency set.
+    ///     - `Err(AppError)`: `Internal` if the database is not set,
+    ///       or the error of loading the signing key.
+    pub fn build(self) -> Result<AppState, AppError> {
+        let Some(db) = self.db else {
+            return Err(AppError::internal(
+                "Database of the application state is not set",
+            ));
+        };
+        let mut webhooks = match self.webhooks {
+            Some(webhooks) => webhooks,
+            None => Webhooks::new(db.write().clone(), &self.config.webhooks)
+                .with_outbox(self.config.outbox.enabled),
+        };
+        if self.config.mail.enabled {
+            let templates =
+                MailTemplates::load(&self.config.mail, self.config.app.is_development())?;
+            webhooks = webhooks.with_templates(Arc::new(templates));
+        }
+        let mailbox = match self.mailer.is_none() && self.config.app.is_development() {
+            true => Some(MailCapture::default()),
+            false => None,
+        };
+        if let Some(mailer) = &self.mailer {
+            webhooks = webhooks.with_mailer(mailer.clone());
+        }
+        if let Some(mailbox) = &mailbox {
+            webhooks = webhooks.with_mailer(Arc::new(mailbox.clone()));
+        }
+        let keys = match self.keys {
+            Some(keys) => keys,
+            None => {
+                let mut keys = KeyManager::load(&self.config.tokens)?;
+                if self.config.key_rotation.enabled {
+                    let store = Arc::new(PgSigningKeyRepo::new(db.write().clone()));
+                    let master_key = MasterKey::required("key_rotation")?;
+                    keys = keys.with_store(store, master_key, &self.config.key_rotation);
+                }
+                Arc::new(keys)
+            }
+        };
+
+        Ok(AppState {
+            config: self.config,
+            db,
+            webhooks,
+            keys,
+            mailer: self.mailer,
+            mailbox,
+            updates: self.updates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::jwt::SigningKey;
+    use crate::core::config::{read_config, DEFAULT_CONFIG_FILE};
+    use crate::core::err::ErrorKind;
+    use sqlx::postgres::PgPoolOptions;
+
+    fn pool() -> sqlx::PgPool {
+        PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap()
+    }
+
+    // Test checks if the database is required and set dependencies are kept.
+    #[tokio::test]
+    async fn test_build() {
+        let config = Arc::new(read_config(DEFAULT_CONFIG_FILE, None).unwrap());
+        let result = AppState::builder(config.clone()).build();
+
+        assert_eq!(result.err().unwrap().kind, ErrorKind::Internal);
+
+        let keys = Arc::new(KeyManager::new(SigningKey::generate("rotated").unwrap()));
+        let state = AppState::builder(config)
+            .db(pool())
+            .keys(keys.clone())
+            .build()
+            .unwrap();
+
+        assert!(Arc::ptr_eq(&state.keys, &keys));
+        assert_eq!(state.keys.current().await.unwrap().kid(), "rotated");
+    }
+}
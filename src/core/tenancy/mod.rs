@@ -0,0 +1,434 @@
+//! Tenancy module.
+//!
+//! Users, sessions and audit records belong to a tenant.
+//! `resolve_tenant` identifies the tenant of a request by
+//! header, subdomain or path and runs the rest of the
+//! request inside `with_tenant`. Repositories add
+//! `current_tenant()` to every query, so handlers never
+//! pass the tenant around. Outside of a request, e.g. in
+//! the background jobs, the `default` tenant is used.
+
+// Imports from external crates
+use axum::{
+    extract::{OriginalUri, Request, State},
+    http::{header::HOST, request::Parts, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::{future::Future, sync::Arc};
+
+// Local imports
+use crate::core::config::{TenancySettings, TenantStrategy};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::repo::TenantRepo;
+
+/// Tenant of requests and jobs without a resolved tenant.
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Maximum length of a tenant ID, the length of a DNS label.
+pub const MAX_TENANT_ID_LEN: usize = 63;
+
+tokio::task_local! {
+    /// Tenant of the request handled by the current task.
+    static TENANT: String;
+}
+
+/// ## Tenant struct.
+///
+/// ## Fields
+/// + `id`: `String` - ID of the tenant, a lowercase DNS label.
+/// + `name`: `String` - Display name of the tenant.
+/// + `disabled`: `bool` - Requests of disabled tenants are rejected.
+/// + `created_at`: `DateTime<Utc>` - Time the tenant was created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tenant {
+    pub id: String,
+    pub name: String,
+    pub disabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// ## Returns the tenant of the current task.
+///
+/// ## Returns
+/// - `String`: Tenant set by `with_tenant`, `default` when
+///   called outside of it.
+pub fn current_tenant() -> String {
+    TENANT
+        .try_with(|tenant| tenant.clone())
+        .unwrap_or_else(|_| DEFAULT_TENANT.to_string())
+}
+
+/// ## Runs the future as the tenant.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::tenancy::{current_tenant, with_tenant};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// assert_eq!(with_tenant("acme", async { current_tenant() }).await, "acme");
+/// assert_eq!(current_tenant(), "default");
+/// # });
+/// ```
+pub async fn with_tenant<F: Future>(tenant: impl Into<String>, future: F) -> F::Output {
+    TENANT.scope(tenant.into(), future).await
+}
+
+/// ## Validates a tenant ID.
+///
+/// IDs are lowercase DNS labels, so every strategy,
+/// subdomains included, can carry them.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the ID is valid.
+///     - `Err(AppError)`: `Validation` if the ID is empty, too long
+///       or not a lowercase DNS label.
+pub fn validate_tenant_id(id: &str) -> Result<(), AppError> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_TENANT_ID_LEN
+        && !id.starts_with('-')
+        && !id.ends_with('-')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    match valid {
+        true => Ok(()),
+        false => Err(AppError::validation(format!(
+            "Tenant ID '{}' must be a lowercase DNS label of at most {} characters",
+            id, MAX_TENANT_ID_LEN
+        ))),
+    }
+}
+
+/// ## Tenant resolver struct.
+///
+/// ## Examples
+/// ```
+/// use axum::{middleware, Router};
+/// use axum_auth::core::config::TenancySettings;
+/// use axum_auth::core::repo::memory::MemoryTenantRepo;
+/// use axum_auth::core::tenancy::{resolve_tenant, TenantResolver};
+/// use std::sync::Arc;
+///
+/// let resolver = Arc::new(TenantResolver::new(
+///     &TenancySettings::default(),
+///     Arc::new(MemoryTenantRepo::default()),
+/// ));
+/// let router: Router = Router::new().layer(middleware::from_fn_with_state(resolver, resolve_tenant));
+/// ```
+pub struct TenantResolver {
+    settings: TenancySettings,
+    tenants: Arc<dyn TenantRepo>,
+}
+
+impl TenantResolver {
+    /// ## Creates a new `TenantResolver` instance.
+    ///
+    /// ## Parameters
+    /// - `settings`: `&TenancySettings` - Strategy of the resolution.
+    /// - `tenants`: `Arc<dyn TenantRepo>` - Known tenants.
+    pub fn new(settings: &TenancySettings, tenants: Arc<dyn TenantRepo>) -> Self {
+        TenantResolver {
+            settings: settings.clone(),
+            tenants,
+        }
+    }
+
+    /// ## Reads the tenant ID of the request.
+    ///
+    /// Path of the `path` strategy is read from the
+    /// `OriginalUri`, so the resolver also works inside
+    /// nested routers.
+    ///
+    /// ## Returns
+    /// + `Option<String>`
+    ///     - `Some(String)`: Lowercased ID sent with the request.
+    ///     - `None`: If the request names no tenant.
+    pub fn identify(&self, parts: &Parts) -> Option<String> {
+        let id = match self.settings.strategy {
+            TenantStrategy::Header => parts
+                .headers
+                .get(self.settings.header.as_str())?
+                .to_str()
+                .ok()?
+                .trim()
+                .to_string(),
+            TenantStrategy::Subdomain => {
+                let host = parts
+                    .headers
+                    .get(HOST)
+                    .and_then(|host| host.to_str().ok())
+                    .or_else(|| parts.uri.host())?;
+                let host = host.split(':').next()?.trim_end_matches('.');
+                let domain = self.settings.domain.trim_matches('.');
+                let label = host
+                    .to_ascii_lowercase()
+                    .strip_suffix(&domain.to_ascii_lowercase())?
+                    .strip_suffix('.')?
+                    .to_string();
+                (!label.contains('.')).then_some(label)?
+            }
+            TenantStrategy::Path => {
+                let path = parts
+                    .extensions
+                    .get::<OriginalUri>()
+                    .map_or(parts.uri.path(), |uri| uri.path());
+                path.strip_prefix(self.settings.path_prefix.as_str())?
+                    .strip_prefix('/')?
+                    .split('/')
+                    .next()?
+                    .to_string()
+            }
+        };
+
+        Some(id.to_ascii_lowercase()).filter(|id| !id.is_empty())
+    }
+
+    /// ## Resolves the tenant of the request.
+    ///
+    /// ## Returns
+    /// + `Result<String, AppError>`
+    ///     - `Ok(String)`: ID of an enabled tenant.
+    ///     - `Err(AppError)`: `tenant.missing` if the request names no
+    ///       tenant, `tenant.unknown` if the tenant does not exist or
+    ///       is disabled.
+    pub async fn resolve(&self, parts: &Parts) -> Result<String, AppError> {
+        let Some(id) = self.identify(parts) else {
+            return Err(AppError::validation("Tenant of the request is missing")
+                .with_code("tenant.missing"));
+        };
+        let unknown = || {
+            AppError::new(
+                ErrorKind::NotFound,
+                format!("Tenant '{}' not found", id),
+                None,
+            )
+            .with_code("tenant.unknown")
+        };
+        if validate_tenant_id(&id).is_err() {
+            return Err(unknown());
+        }
+
+        match self.tenants.get(&id).await? {
+            Some(tenant) if !tenant.disabled => Ok(tenant.id),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+/// ## Tenant resolution middleware.
+///
+/// Function is used with `axum::middleware::from_fn_with_state`.
+/// Requests without a known, enabled tenant are rejected,
+/// the others are handled as their tenant.
+pub async fn resolve_tenant(
+    State(resolver): State<Arc<TenantResolver>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let tenant = match resolver.resolve(&parts).await {
+        Ok(tenant) => tenant,
+        Err(e) => return e.into_response(),
+    };
+
+    with_tenant(tenant, next.run(Request::from_parts(parts, body))).await
+}
+
+/// ## Removes the tenant segment of the request path.
+///
+/// Function is used with `axum::middleware::map_request`
+/// in front of the routers nested below the `path`
+/// strategy prefix, so `/acme/me` is routed as `/me`.
+/// Handlers never see the tenant as a path parameter.
+pub async fn strip_tenant(mut request: Request) -> Request {
+    let uri = request.uri();
+    let rest = uri
+        .path()
+        .trim_start_matches('/')
+        .split_once('/')
+        .map_or("", |(_, rest)| rest);
+    let path_and_query = match uri.query() {
+        Some(query) => format!("/{}?{}", rest, query),
+        None => format!("/{}", rest),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    if let Ok(path_and_query) = path_and_query.parse() {
+        parts.path_and_query = Some(path_and_query);
+    }
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *request.uri_mut() = uri;
+    }
+
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::repo::memory::MemoryTenantRepo;
+    use axum::{body::Body, extract::Path, http::StatusCode, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn resolver(settings: TenancySettings) -> TenantResolver {
+        TenantResolver::new(&settings, Arc::new(MemoryTenantRepo::default()))
+    }
+
+    fn parts(request: axum::http::request::Builder) -> Parts {
+        request.body(()).unwrap().into_parts().0
+    }
+
+    // Test checks if tenant IDs must be lowercase DNS labels.
+    #[test]
+    fn test_validate_tenant_id() {
+        for id in ["acme", "acme-2", "0"] {
+            assert!(validate_tenant_id(id).is_ok(), "{}", id);
+        }
+        for id in ["", "Acme", "-acme", "acme-", "ac.me", &"a".repeat(64)] {
+            assert!(validate_tenant_id(id).is_err(), "{}", id);
+        }
+    }
+
+    // Test checks if every strategy reads the tenant from its part of the request.
+    #[test]
+    fn test_identify() {
+        let header = resolver(TenancySettings::default());
+        assert_eq!(
+            header.identify(&parts(Request::get("/me").header("x-tenant-id", " Acme "))),
+            Some("acme".to_string())
+        );
+        assert_eq!(header.identify(&parts(Request::get("/me"))), None);
+
+        let subdomain = resolver(TenancySettings {
+            strategy: TenantStrategy::Subdomain,
+            domain: "auth.example.com".to_string(),
+            ..TenancySettings::default()
+        });
+        for (host, tenant) in [
+            ("acme.auth.example.com", Some("acme")),
+            ("ACME.auth.example.com:8443", Some("acme")),
+            ("auth.example.com", None),
+            ("a.b.auth.example.com", None),
+            ("acme.example.org", None),
+        ] {
+            assert_eq!(
+                subdomain.identify(&parts(Request::get("/me").header(HOST, host))),
+                tenant.map(str::to_string),
+                "{}",
+                host
+            );
+        }
+
+        let path = resolver(TenancySettings {
+            strategy: TenantStrategy::Path,
+            ..TenancySettings::default()
+        });
+        assert_eq!(
+            path.identify(&parts(Request::get("/t/acme/me"))),
+            Some("acme".to_string())
+        );
+        assert_eq!(
+            path.identify(&parts(Request::get("/tenants/acme/me"))),
+            None
+        );
+        assert_eq!(path.identify(&parts(Request::get("/me"))), None);
+    }
+
+    // Test checks if the tenant segment is removed and the query kept.
+    #[tokio::test]
+    async fn test_strip_tenant() {
+        for (uri, stripped) in [
+            ("/acme/me", "/me"),
+            ("/acme/admin/users?page=2", "/admin/users?page=2"),
+            ("/acme", "/"),
+        ] {
+            let request = strip_tenant(Request::get(uri).body(Body::empty()).unwrap()).await;
+
+            assert_eq!(request.uri(), stripped);
+        }
+    }
+
+    // Test checks if the path strategy routes below the tenant segment without a path parameter.
+    #[tokio::test]
+    async fn test_path_strategy() {
+        let tenants = Arc::new(MemoryTenantRepo::default());
+        tenants.insert("acme", false);
+        let settings = TenancySettings {
+            strategy: TenantStrategy::Path,
+            ..TenancySettings::default()
+        };
+        let resolver = Arc::new(TenantResolver::new(&settings, tenants));
+        let scoped = Router::new()
+            .route(
+                "/items/:id",
+                get(|Path(id): Path<u32>| async move { format!("{}:{}", current_tenant(), id) }),
+            )
+            .layer(middleware::from_fn_with_state(resolver, resolve_tenant));
+        let app: Router = Router::new().nest_service(
+            "/t",
+            Router::new()
+                .fallback_service(scoped)
+                .layer(middleware::map_request(strip_tenant)),
+        );
+
+        for (uri, status, body) in [
+            ("/t/acme/items/7", StatusCode::OK, "acme:7"),
+            ("/t/other/items/7", StatusCode::NOT_FOUND, ""),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), status, "{}", uri);
+            if status == StatusCode::OK {
+                let bytes = axum::body::to_bytes(response.into_body(), 1024)
+                    .await
+                    .unwrap();
+                assert_eq!(bytes, body);
+            }
+        }
+    }
+
+    // Test checks if handlers run as the resolved tenant and unknown tenants are rejected.
+    #[tokio::test]
+    async fn test_resolve_tenant() {
+        let tenants = Arc::new(MemoryTenantRepo::default());
+        tenants.insert("acme", false);
+        tenants.insert("closed", true);
+        let resolver = Arc::new(TenantResolver::new(&TenancySettings::default(), tenants));
+        let app = Router::new()
+            .route("/tenant", get(|| async { current_tenant() }))
+            .layer(middleware::from_fn_with_state(resolver, resolve_tenant));
+
+        for (tenant, status) in [
+            (Some("acme"), StatusCode::OK),
+            (Some("closed"), StatusCode::NOT_FOUND),
+            (Some("other"), StatusCode::NOT_FOUND),
+            (None, StatusCode::BAD_REQUEST),
+        ] {
+            let mut request = Request::get("/tenant");
+            if let Some(tenant) = tenant {
+                request = request.header("x-tenant-id", tenant);
+            }
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), status, "{:?}", tenant);
+            if status == StatusCode::OK {
+                let body = axum::body::to_bytes(response.into_body(), 1024)
+                    .await
+                    .unwrap();
+                assert_eq!(body, "acme");
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Secret value module.
+//!
+//! Module contains the `Secret` newtype for passwords and
+//! keys. Its `Debug` and `Display` print `***`, so debug
+//! prints of the configuration, log lines and error
+//! messages can't leak the value. The value is read with
+//! `expose`, which makes every use of it easy to find.
+
+// Imports from external crates
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Text printed instead of the value.
+pub const REDACTED: &str = "***";
+
+/// ## Secret value struct.
+///
+/// Struct wraps a sensitive value. It (de)serializes as
+/// the plain value, so configuration files keep their
+/// format, but it is never formatted.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::types::secret::Secret;
+///
+/// let password = Secret::new("hunter2".to_string());
+///
+/// assert_eq!(format!("{:?}", password), "***");
+/// assert_eq!(password.to_string(), "***");
+/// assert_eq!(password.expose(), "hunter2");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    /// ## Creates a new `Secret` instance.
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// ## Returns a reference to the value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// ## Returns the value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl Secret<String> {
+    /// ## Returns the value as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// ## Returns `true` if the value is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Settings {
+        user: String,
+        password: Secret<String>,
+    }
+
+    // Test checks if the value never shows up in debug prints of the containing struct.
+    #[test]
+    fn test_debug_of_container() {
+        let settings = Settings {
+            user: "admin".to_string(),
+            password: "hunter2".into(),
+        };
+
+        let debug = format!("{:?} {:#?}", settings, settings);
+
+        assert!(debug.contains("admin"));
+        assert!(debug.contains(REDACTED));
+        assert!(!debug.contains("hunter2"));
+    }
+
+    // Test checks if the value (de)serializes as the plain value.
+    #[test]
+    fn test_serde_transparent() {
+        let settings: Settings =
+            serde_json::from_str(r#"{"user":"admin","password":"hunter2"}"#).unwrap();
+
+        assert_eq!(settings.password.expose(), "hunter2");
+        assert_eq!(
+            serde_json::to_string(&settings).unwrap(),
+            r#"{"user":"admin","password":"hunter2"}"#
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Users module.
+//!
+//! Module contains the user model and the repository
+//! of their role grants. Users are stored through the
+//! `core::repo::UserRepo` trait.
+
+// References to submodules
+pub mod roles;
+
+// Imports from external crates
+use sqlx::{postgres::PgRow, FromRow, Row};
+use uuid::Uuid;
+
+// Local imports
+use crate::core::auth::password::StoredHash;
+use crate::core::http::pagination::{timestamp_key, Keyed, SortField, Sorting};
+
+// Re-exports of the wire types
+pub use crate::api::users::User;
+
+/// Maps the row into `User`, `api` types do not
+/// depend on sqlx.
+impl<'r> FromRow<'r, PgRow> for User {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(User {
+            id: row.try_get("id")?,
+            email: row.try_get("email")?,
+            display_name: row.try_get("display_name")?,
+            org_id: row.try_get("org_id")?,
+            disabled: row.try_get("disabled")?,
+            password_reset_required: row.try_get("password_reset_required")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+            created_at_display: None,
+            updated_at_display: None,
+        })
+    }
+}
+
+/// Sortable fields of the user list, oldest first by default.
+pub const USER_SORTING: Sorting = Sorting {
+    fields: &[
+        SortField {
+            name: "created_at",
+            column: "created_at",
+            cast: "timestamptz",
+        },
+        SortField {
+            name: "updated_at",
+            column: "updated_at",
+            cast: "timestamptz",
+        },
+        SortField {
+            name: "email",
+            column: "email",
+            cast: "text",
+        },
+    ],
+    default: "created_at",
+    id: SortField {
+        name: "id",
+        column: "id",
+        cast: "uuid",
+    },
+};
+
+impl Keyed for User {
+    fn sort_key(&self, field: &str) -> String {
+        match field {
+            "updated_at" => timestamp_key(self.updated_at),
+            "email" => self.email.clone(),
+            _ => timestamp_key(self.created_at),
+        }
+    }
+
+    fn id_key(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+/// ## User filter struct.
+///
+/// Filters that are not set match every user.
+///
+/// ## Fields
+/// + `email`: `Option<String>` - Case-insensitive part of the email.
+/// + `org_id`: `Option<Uuid>` - Organization of the users.
+/// + `disabled`: `Option<bool>` - Disabled state of the users.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserFilter {
+    pub email: Option<String>,
+    pub org_id: Option<Uuid>,
+    pub disabled: Option<bool>,
+}
+
+/// ## New user struct.
+///
+/// ## Fields
+/// + `email`: `String` - Email address, unique among the users.
+/// + `display_name`: `Option<String>` - Name shown instead of the email.
+/// + `org_id`: `Option<Uuid>` - Organization of the user.
+/// + `password`: `Option<StoredHash>` - Password hash, `None` for users
+///   that sign in through an external provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewUser {
+    pub email: String,
+    pub display_name: Option<String>,
+    pub org_id: Option<Uuid>,
+    pub password: Option<StoredHash>,
+}
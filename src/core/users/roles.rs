@@ -0,0 +1,319 @@
+//! Role repository module.
+//!
+//! Module reads and updates role grants in the `user_roles`
+//! table. Every grant records its source, so automated
+//! syncs only ever change the grants they created.
+//! Grants of deleted users and of users of other tenants
+//! are not listed. Roles other than the built-in ones are
+//! defined through `core::repo::RoleDefinitionRepo`.
+
+// Imports from external crates
+use sqlx::PgExecutor;
+use std::{fmt, str::FromStr};
+use uuid::Uuid;
+
+// Local imports
+use crate::core::auth::rbac::{ADMIN_ROLE, ORG_ADMIN_ROLE};
+use crate::core::db::DbExecutor;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::repo::postgres::live_user;
+use crate::core::tenancy::current_tenant;
+
+// Re-exports of the wire types
+pub use crate::api::roles::Role;
+
+/// Maximum length of a role name.
+const MAX_ROLE_NAME_LEN: usize = 64;
+
+/// ## Source of a role grant enum.
+///
+/// # Variants
+/// - `Local` - `local`, granted by an administrator.
+/// - `Directory` - `directory`, granted by the directory group sync.
+/// - `Scim` - `scim`, granted by an identity provider through SCIM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoleSource {
+    Local,
+    Directory,
+    Scim,
+}
+
+impl RoleSource {
+    /// ## Returns the name stored in the `source` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoleSource::Local => "local",
+            RoleSource::Directory => "directory",
+            RoleSource::Scim => "scim",
+        }
+    }
+}
+
+impl fmt::Display for RoleSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for RoleSource {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "local" => Ok(RoleSource::Local),
+            "directory" => Ok(RoleSource::Directory),
+            "scim" => Ok(RoleSource::Scim),
+            _ => Err(AppError::new(
+                ErrorKind::Validation,
+                format!("Unknown role source '{}'", value),
+                None,
+            )),
+        }
+    }
+}
+
+/// ## Role grant struct.
+///
+/// ## Fields
+/// + `user_id`: `Uuid` - User the role is granted to.
+/// + `role`: `String` - Name of the role.
+/// + `source`: `RoleSource` - Who manages the grant.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RoleGrant {
+    pub user_id: Uuid,
+    pub role: String,
+    pub source: RoleSource,
+}
+
+/// ## Role grant row struct.
+#[derive(sqlx::FromRow)]
+struct RoleGrantRow {
+    user_id: Uuid,
+    role: String,
+    source: String,
+}
+
+impl TryFrom<RoleGrantRow> for RoleGrant {
+    type Error = AppError;
+
+    fn try_from(row: RoleGrantRow) -> Result<Self, Self::Error> {
+        Ok(RoleGrant {
+            user_id: row.user_id,
+            role: row.role,
+            source: row.source.parse()?,
+        })
+    }
+}
+
+/// ## Role repository struct.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::users::roles::RoleRepo;
+///
+/// # async fn example(pool: sqlx::PgPool) {
+/// let roles = RoleRepo::new(pool);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RoleRepo {
+    db: DbExecutor,
+}
+
+impl RoleRepo {
+    /// ## Creates a new `RoleRepo` instance.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        RoleRepo { db: db.into() }
+    }
+
+    /// ## Lists every role grant.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<RoleGrant>, AppError>`
+    ///     - `Ok(Vec<RoleGrant>)`: Grants of every user of the tenant.
+    ///     - `Err(AppError)`: If the query failed or a source is unknown.
+    pub async fn list(&self) -> Result<Vec<RoleGrant>, AppError> {
+        let query = format!(
+            "SELECT user_id, role, source FROM user_roles WHERE {} ORDER BY user_id, role",
+            live_user(1)
+        );
+
+        let rows = sqlx::query_as::<_, RoleGrantRow>(&query)
+            .bind(current_tenant())
+            .fetch_all(self.db.read())
+            .await
+            .map_err(db_error("Failed to list roles"))?;
+
+        rows.into_iter().map(RoleGrant::try_from).collect()
+    }
+
+    /// ## Lists the role grants of the user.
+    ///
+    /// ## Returns
+    /// + `Result<Vec<RoleGrant>, AppError>`
+    ///     - `Ok(Vec<RoleGrant>)`: Grants of the user, ordered by role.
+    ///     - `Err(AppError)`: If the query failed or a source is unknown.
+    pub async fn list_user(&self, user_id: Uuid) -> Result<Vec<RoleGrant>, AppError> {
+        let query = format!(
+            "SELECT user_id, role, source FROM user_roles WHERE user_id = $1 AND {} ORDER BY role",
+            live_user(2)
+        );
+
+        let rows = sqlx::query_as::<_, RoleGrantRow>(&query)
+            .bind(user_id)
+            .bind(current_tenant())
+            .fetch_all(self.db.read())
+            .await
+            .map_err(db_error("Failed to list roles"))?;
+
+        rows.into_iter().map(RoleGrant::try_from).collect()
+    }
+
+    /// ## Grants the role to the user.
+    ///
+    /// ## Returns
+    /// + `Result<bool, AppError>`
+    ///     - `Ok(bool)`: `false` if the user already had the role.
+    ///     - `Err(AppError)`: If the insert failed.
+    pub async fn grant(
+        &self,
+        user_id: Uuid,
+        role: &str,
+        source: RoleSource,
+    ) -> Result<bool, AppError> {
+        grant_with(self.db.write(), user_id, role, source).await
+    }
+
+    /// ## Revokes the role of the user granted by the source.
+    ///
+    /// Grants of other sources are kept.
+    ///
+    /// ## Returns
+    /// + `Result<bool, AppError>`
+    ///     - `Ok(bool)`: `false` if there was no such grant.
+    ///     - `Err(AppError)`: If the delete failed.
+    pub async fn revoke(
+        &self,
+        user_id: Uuid,
+        role: &str,
+        source: RoleSource,
+    ) -> Result<bool, AppError> {
+        let result =
+            sqlx::query("DELETE FROM user_roles WHERE user_id = $1 AND role = $2 AND source = $3")
+                .bind(user_id)
+                .bind(role)
+                .bind(source.as_str())
+                .execute(self.db.write())
+                .await
+                .map_err(db_error("Failed to revoke role"))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// ## Grants the role to the user on the executor.
+///
+/// Unlike `RoleRepo::grant`, the grant can be part of
+/// a transaction.
+///
+/// ## Parameters
+/// - `executor`: `impl PgExecutor` - Pool or connection, e.g. of a transaction.
+/// - `user_id`: `Uuid` - User the role is granted to.
+/// - `role`: `&str` - Name of the role.
+/// - `source`: `RoleSource` - Who manages the grant.
+///
+/// ## Returns
+/// + `Result<bool, AppError>`
+///     - `Ok(bool)`: `false` if the user already had the role.
+///     - `Err(AppError)`: If the insert failed.
+pub async fn grant_with<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+    role: &str,
+    source: RoleSource,
+) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        "INSERT INTO user_roles (user_id, role, source) VALUES ($1, $2, $3) \
+         ON CONFLICT (user_id, role) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(role)
+    .bind(source.as_str())
+    .execute(executor)
+    .await
+    .map_err(db_error("Failed to grant role"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// ## Checks if the role is built into the RBAC policy.
+///
+/// Built-in roles can be granted but not defined.
+pub fn is_builtin(role: &str) -> bool {
+    role == ADMIN_ROLE || role == ORG_ADMIN_ROLE
+}
+
+/// ## Validates the name of a role definition.
+///
+/// Names are up to 64 lowercase letters, digits, `_`
+/// and `-`, and must not be the name of a built-in role.
+///
+/// ## Parameters
+/// - `name`: `&str` - Name of the role.
+///
+/// ## Returns
+/// + `Result<(), AppError>`
+///     - `Ok(())`: If the name can be defined.
+///     - `Err(AppError)`: `roles.invalid_name` if the name is malformed,
+///       `roles.reserved` if it is a built-in role.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::users::roles::validate_role_name;
+///
+/// assert!(validate_role_name("support-agent").is_ok());
+/// assert!(validate_role_name("Support Agent").is_err());
+/// assert!(validate_role_name("admin").is_err());
+/// ```
+pub fn validate_role_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_ROLE_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if !valid {
+        return Err(
+            AppError::validation(format!("Role name '{}' is invalid", name))
+                .with_code("roles.invalid_name"),
+        );
+    }
+
+    if is_builtin(name) {
+        return Err(AppError::validation(format!("Role '{}' is built in", name))
+            .with_code("roles.reserved"));
+    }
+
+    Ok(())
+}
+
+/// ## Maps a sqlx error into a database error.
+fn db_error(message: &'static str) -> impl FnOnce(sqlx::Error) -> AppError {
+    move |e| AppError::new(ErrorKind::Db, message.to_string(), Some(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test checks if every source is parsed back from its name.
+    #[test]
+    fn test_role_source_round_trip() {
+        for source in [RoleSource::Local, RoleSource::Directory, RoleSource::Scim] {
+            assert_eq!(source.as_str().parse::<RoleSource>(), Ok(source));
+        }
+
+        let result = "ldap".parse::<RoleSource>().unwrap_err();
+
+        assert_eq!(result.kind, ErrorKind::Validation);
+    }
+}
@@ -0,0 +1,971 @@
+//! Webhooks module.
+//!
+//! Module notifies external systems about account
+//! lifecycle events. Events are queued in a bounded
+//! in-memory queue by the request that caused them and
+//! posted as signed JSON by a sender task of the server.
+//! Events that overflow the queue and failed deliveries
+//! are stored in the `webhook_deliveries` table and
+//! retried with exponential backoff by the `webhooks` job
+//! of the worker, deliveries that failed every attempt
+//! are moved to the `webhook_dead_letters` table.
+//!
+//! With the outbox enabled, `user.created` and
+//! `login.succeeded` are stored by the state change and
+//! relayed into `webhook_deliveries` by the worker instead.
+//! With a `Mailer` set, events carrying mail are rendered
+//! and sent to it, the endpoints still get the event. With mail
+//! templates set and no `Mailer`, the rendered email is
+//! added to the data of the event as `mail`.
+
+// Imports from external crates
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::Value as Json;
+use sha2::Sha256;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::{sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+// Local imports
+use super::config::{WebhookEndpoint, WebhookSettings};
+use super::err::{AppError, ErrorKind};
+use super::events::outbox::{self, OutboxEvent, Relay};
+use super::mail::{render, templates::MailTemplates, Mailer};
+use super::queue::{NotificationQueue, Spill};
+use super::worker::{cron::Schedule, Job};
+
+// Re-exports of the wire types
+pub use crate::api::webhooks::{WebhookEvent, WebhookPayload};
+
+/// Header carrying the signature of the payload.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Header carrying the name of the event.
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+
+/// Header carrying the ID of the event.
+pub const ID_HEADER: &str = "X-Webhook-Id";
+
+/// Deliveries claimed by one run of the job.
+const BATCH_SIZE: i64 = 100;
+
+/// ## Signs the payload.
+///
+/// Signature is the hex encoded HMAC-SHA256 of
+/// `{timestamp}.{body}`, sent as `t={timestamp},v1={hex}`.
+/// Receivers recompute it and reject old timestamps, so
+/// captured requests can not be replayed.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::webhooks::{sign, verify};
+///
+/// let signature = sign("0123456789abcdef", 1767225600, b"{}");
+///
+/// assert!(signature.starts_with("t=1767225600,v1="));
+/// assert!(verify("0123456789abcdef", &signature, b"{}"));
+/// ```
+///
+/// ## Parameters
+/// - `secret`: `&str` - Secret of the endpoint.
+/// - `timestamp`: `i64` - Unix time of the request.
+/// - `body`: `&[u8]` - Body of the request.
+///
+/// ## Returns
+/// - `String`: Value of the `X-Webhook-Signature` header.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mac = mac(secret, timestamp, body).finalize().into_bytes();
+
+    format!("t={},v1={}", timestamp, hex::encode(mac))
+}
+
+/// ## Verifies the signature of the payload.
+///
+/// Comparison runs in constant time. Checking the age of
+/// the timestamp is left to the receiver.
+///
+/// ## Parameters
+/// - `secret`: `&str` - Secret of the endpoint.
+/// - `header`: `&str` - Value of the `X-Webhook-Signature` header.
+/// - `body`: `&[u8]` - Body of the request.
+///
+/// ## Returns
+/// - `bool`: If the signature matches the body.
+pub fn verify(secret: &str, header: &str, body: &[u8]) -> bool {
+    let mut timestamp = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signature = hex::decode(value).ok(),
+            _ => {}
+        }
+    }
+
+    match (timestamp, signature) {
+        (Some(timestamp), Some(signature)) => mac(secret, timestamp, body)
+            .verify_slice(&signature)
+            .is_ok(),
+        _ => false,
+    }
+}
+
+/// ## Computes the MAC of the timestamp and body.
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// ## Returns the delay before the next attempt.
+///
+/// Delay starts at `backoff` and doubles with every
+/// failed attempt, up to `max_backoff`.
+///
+/// ## Parameters
+/// - `settings`: `&WebhookSettings` - Backoff settings.
+/// - `attempts`: `u32` - Failed attempts so far, at least 1.
+///
+/// ## Returns
+/// - `Duration`: Delay before the next attempt.
+pub fn backoff(settings: &WebhookSettings, attempts: u32) -> Duration {
+    let factor = 1u64
+        .checked_shl(attempts.saturating_sub(1))
+        .unwrap_or(u64::MAX);
+
+    Duration::from_secs(
+        settings
+            .backoff
+            .saturating_mul(factor)
+            .min(settings.max_backoff),
+    )
+}
+
+/// ## Webhook service struct.
+///
+/// Queues events for the endpoints subscribed to them.
+/// Does nothing when webhooks are disabled. Clones share
+/// the same queue.
+///
+/// ## Examples
+/// ```no_run
+/// use axum_auth::core::config::WebhookSettings;
+/// use axum_auth::core::webhooks::{WebhookEvent, Webhooks};
+///
+/// # async fn example(pool: sqlx::PgPool) {
+/// let webhooks = Webhooks::new(pool, &WebhookSettings::default());
+/// let data = serde_json::json!({ "user_id": uuid::Uuid::new_v4() });
+///
+/// webhooks.emit(WebhookEvent::UserCreated, data).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Webhooks {
+    pool: PgPool,
+    client: reqwest::Client,
+    settings: Arc<WebhookSettings>,
+    queue: NotificationQueue<Delivery>,
+    outbox: bool,
+    mailer: Option<Arc<dyn Mailer>>,
+    templates: Option<Arc<MailTemplates>>,
+}
+
+impl Webhooks {
+    /// ## Creates a new `Webhooks` instance.
+    ///
+    /// Events are only posted once the sender was started,
+    /// until then they wait in the queue.
+    pub fn new(pool: PgPool, settings: &WebhookSettings) -> Self {
+        let spill = Arc::new(SpillToDb { pool: pool.clone() });
+
+        Webhooks {
+            queue: NotificationQueue::new("webhooks", &settings.queue, Some(spill)),
+            pool,
+            client: reqwest::Client::new(),
+            settings: Arc::new(settings.clone()),
+            outbox: false,
+            mailer: None,
+            templates: None,
+        }
+    }
+
+    /// ## Leaves the events of the outbox to the state changes.
+    ///
+    /// ## Parameters
+    /// - `enabled`: `bool` - If the outbox is enabled.
+    pub fn with_outbox(mut self, enabled: bool) -> Self {
+        self.outbox = enabled;
+        self
+    }
+
+    /// ## Sends the events carrying mail to the mailer as well.
+    ///
+    /// ## Parameters
+    /// - `mailer`: `Arc<dyn Mailer>` - Receiver of the rendered emails.
+    pub fn with_mailer(mut self, mailer: Arc<dyn Mailer>) -> Self {
+        self.mailer = Some(mailer);
+        self
+    }
+
+    /// ## Renders the emails from the templates instead of the built-in texts.
+    ///
+    /// ## Parameters
+    /// - `templates`: `Arc<MailTemplates>` - Registry of the mail templates.
+    pub fn with_templates(mut self, templates: Arc<MailTemplates>) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
+    /// ## Checks if the events of the outbox are stored by the state changes.
+    pub fn outbox(&self) -> bool {
+        self.outbox
+    }
+
+    /// ## Returns the names of the endpoints receiving the event.
+    pub fn subscribers(&self, event: WebhookEvent) -> Vec<String> {
+        match self.settings.enabled {
+            true => self
+                .settings
+                .endpoints
+                .iter()
+                .filter(|endpoint| endpoint.subscribes(event))
+                .map(|endpoint| endpoint.name.clone())
+                .collect(),
+            false => Vec::new(),
+        }
+    }
+
+    /// ## Queues the event for every subscribed endpoint.
+    ///
+    /// Events of the outbox are skipped while it is enabled,
+    /// they were stored with the state change. Events carrying
+    /// mail are sent by the mailer, if one is set, and still
+    /// delivered to the endpoints, otherwise the email rendered
+    /// from the templates is added as `mail`.
+    ///
+    /// ## Parameters
+    /// - `event`: `WebhookEvent` - Kind of the event.
+    /// - `data`: `serde_json::Value` - Event specific data.
+    ///
+    /// ## Returns
+    /// + `Result<(), AppError>`
+    ///     - `Ok(())`: If the event was queued, nobody subscribed to it
+    ///       or it is left to the outbox.
+    ///     - `Err(AppError)`: If the event overflowed the queue and
+    ///       the insert failed.
+    pub async fn emit(&self, event: WebhookEvent, mut data: Json) -> Result<(), AppError> {
+        if self.mailer.is_some() || self.templates.is_some() {
+            let email = match &self.templates {
+                Some(templates) => templates.render(event, &data),
+                None => render(event, &data),
+            };
+            match (email, &self.mailer) {
+                (Some(email), Some(mailer)) => mailer.send(email).await?,
+                (Some(email), None) => {
+                    if let Json::Object(fields) = &mut data {
+                        let mail =
+                            serde_json::json!({ "subject": email.subject, "body": email.body });
+                        fields.insert("mail".to_string(), mail);
+                    }
+                }
+                (None, _) => {}
+            }
+        }
+
+        let endpoints = self.subscribers(event);
+        if endpoints.is_empty() || (self.outbox && outbox::EVENTS.contains(&event)) {
+            return Ok(());
+        }
+
+        let payload = WebhookPayload {
+            id: Uuid::new_v4(),
+            event: event.as_str().to_string(),
+            occurred_at: Utc::now(),
+            data,
+        };
+        let body = serde_json::to_value(&payload).map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                "Failed to serialize webhook payload".to_string(),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        for endpoint in endpoints {
+            let delivery = Delivery {
+                id: 0,
+                event_id: payload.id,
+                endpoint,
+                event: payload.event.clone(),
+                payload: body.clone(),
+                attempts: 0,
+            };
+            self.queue.push(delivery).await?;
+        }
+
+        Ok(())
+    }
+
+    /// ## Starts the sender of the queued events.
+    ///
+    /// Sender posts one delivery at a time, a failed
+    /// delivery is stored for the retries of the worker.
+    ///
+    /// ## Returns
+    /// - `Option<JoinHandle<()>>`: Sender task, `None` when webhooks
+    ///   are disabled. Task ends once the queue was drained.
+    pub fn start(&self) -> Option<JoinHandle<()>> {
+        if !self.settings.enabled {
+            return None;
+        }
+
+        let webhooks = self.clone();
+        Some(tokio::spawn(async move {
+            while let Some(delivery) = webhooks.queue.pop().await {
+                webhooks.send(delivery).await;
+            }
+        }))
+    }
+
+    /// ## Drains the queue on shutdown.
+    ///
+    /// Queued deliveries are stored for the worker, then the
+    /// delivery in flight is awaited.
+    ///
+    /// ## Parameters
+    /// - `sender`: `Option<JoinHandle<()>>` - Sender returned by `start`.
+    pub async fn drain(&self, sender: Option<JoinHandle<()>>) {
+        let pending = self.queue.close();
+        if !pending.is_empty() {
+            tracing::info!("Storing {} queued webhook deliveries", pending.len());
+        }
+
+        for delivery in &pending {
+            if let Err(e) = store(&self.pool, delivery, None, Duration::ZERO).await {
+                tracing::error!(event_id = %delivery.event_id, "{}", e);
+            }
+        }
+
+        if let Some(sender) = sender {
+            let _ = sender.await;
+        }
+    }
+
+    /// ## Posts the delivery, stores it for a retry if it failed.
+    async fn send(&self, delivery: Delivery) {
+        let timeout = Duration::from_secs(self.settings.timeout);
+        let endpoint = self
+            .settings
+            .endpoints
+            .iter()
+            .find(|endpoint| endpoint.name == delivery.endpoint);
+        let Some(endpoint) = endpoint else {
+            return;
+        };
+
+        let Err(error) = deliver(&self.client, endpoint, &delivery, timeout).await else {
+            return;
+        };
+        let failed = Delivery {
+            attempts: 1,
+            ..delivery
+        };
+
+        let result = match self.settings.max_attempts > 1 {
+            true => {
+                let delay = backoff(&self.settings, 1);
+                tracing::debug!(
+                    endpoint = failed.endpoint,
+                    event_id = %failed.event_id,
+                    retry_in = delay.as_secs(),
+                    error,
+                    "Webhook delivery failed"
+                );
+
+                store(&self.pool, &failed, Some(&error), delay).await
+            }
+            false => dead_letter(&self.pool, &failed, &error).await,
+        };
+        if let Err(e) = result {
+            tracing::error!(event_id = %failed.event_id, "{}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl Relay for Webhooks {
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+
+    /// ## Stores a delivery per subscribed endpoint for the worker.
+    ///
+    /// Events that are no webhook event are skipped.
+    async fn relay(&self, conn: &mut PgConnection, event: &OutboxEvent) -> Result<(), AppError> {
+        let Ok(kind) = event.event.parse::<WebhookEvent>() else {
+            return Ok(());
+        };
+
+        for endpoint in self.subscribers(kind) {
+            let delivery = Delivery {
+                id: 0,
+                event_id: event.event_id,
+                endpoint,
+                event: event.event.clone(),
+                payload: event.payload.clone(),
+                attempts: 0,
+            };
+            store(&mut *conn, &delivery, None, Duration::ZERO).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Spill target storing deliveries for the worker.
+struct SpillToDb {
+    pool: PgPool,
+}
+
+#[async_trait]
+impl Spill<Delivery> for SpillToDb {
+    async fn spill(&self, delivery: Delivery) -> Result<(), AppError> {
+        store(&self.pool, &delivery, None, Duration::ZERO).await
+    }
+}
+
+/// ## Stores the delivery for the worker.
+///
+/// ## Parameters
+/// - `executor`: `impl PgExecutor` - Pool or connection of the delivery tables.
+/// - `delivery`: `&Delivery` - Delivery to store, its ID is ignored.
+/// - `error`: `Option<&str>` - Error of the last attempt.
+/// - `delay`: `Duration` - Delay before the worker attempts it.
+async fn store<'e>(
+    executor: impl PgExecutor<'e>,
+    delivery: &Delivery,
+    error: Option<&str>,
+    delay: Duration,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO webhook_deliveries \
+             (event_id, endpoint, event, payload, attempts, last_error, next_attempt_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, NOW() + make_interval(secs => $7))",
+    )
+    .bind(delivery.event_id)
+    .bind(&delivery.endpoint)
+    .bind(&delivery.event)
+    .bind(&delivery.payload)
+    .bind(delivery.attempts)
+    .bind(error)
+    .bind(delay.as_secs_f64())
+    .execute(executor)
+    .await
+    .map(|_| ())
+    .map_err(db_error("Failed to queue webhook event"))
+}
+
+/// ## Stores a delivery that failed every attempt.
+async fn dead_letter(pool: &PgPool, delivery: &Delivery, error: &str) -> Result<(), AppError> {
+    tracing::warn!(
+        endpoint = delivery.endpoint,
+        event = delivery.event,
+        event_id = %delivery.event_id,
+        attempts = delivery.attempts,
+        error,
+        "Webhook delivery dead-lettered"
+    );
+
+    sqlx::query(
+        "INSERT INTO webhook_dead_letters \
+             (event_id, endpoint, event, payload, attempts, last_error, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, NOW())",
+    )
+    .bind(delivery.event_id)
+    .bind(&delivery.endpoint)
+    .bind(&delivery.event)
+    .bind(&delivery.payload)
+    .bind(delivery.attempts)
+    .bind(error)
+    .execute(pool)
+    .await
+    .map(|_| ())
+    .map_err(db_error("Failed to dead-letter webhook delivery"))
+}
+
+/// ## Queued delivery struct.
+///
+/// ## Fields
+/// + `id`: `i64` - ID of the delivery.
+/// + `event_id`: `Uuid` - ID of the event, shared by its deliveries.
+/// + `endpoint`: `String` - Name of the endpoint.
+/// + `event`: `String` - Name of the event.
+/// + `payload`: `serde_json::Value` - Body of the request.
+/// + `attempts`: `i32` - Failed attempts so far.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct Delivery {
+    pub id: i64,
+    pub event_id: Uuid,
+    pub endpoint: String,
+    pub event: String,
+    pub payload: Json,
+    pub attempts: i32,
+}
+
+/// ## Posts the delivery to the endpoint.
+///
+/// ## Parameters
+/// - `client`: `&reqwest::Client` - HTTP client.
+/// - `endpoint`: `&WebhookEndpoint` - Endpoint the delivery is posted to.
+/// - `delivery`: `&Delivery` - Delivery to post.
+/// - `timeout`: `Duration` - Time the endpoint may take to respond.
+///
+/// ## Returns
+/// + `Result<(), String>`
+///     - `Ok(())`: If the endpoint responded with a 2xx status.
+///     - `Err(String)`: Why the delivery failed.
+pub async fn deliver(
+    client: &reqwest::Client,
+    endpoint: &WebhookEndpoint,
+    delivery: &Delivery,
+    timeout: Duration,
+) -> Result<(), String> {
+    let body = delivery.payload.to_string();
+    let signature = sign(
+        endpoint.secret.expose(),
+        Utc::now().timestamp(),
+        body.as_bytes(),
+    );
+
+    let response = client
+        .post(&endpoint.url)
+        .timeout(timeout)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, signature)
+        .header(EVENT_HEADER, &delivery.event)
+        .header(ID_HEADER, delivery.event_id.to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(format!("Endpoint responded with {}", response.status())),
+    }
+}
+
+/// ## Webhook delivery job struct.
+///
+/// Every run claims the due deliveries, so several
+/// workers never post the same delivery at once. Claimed
+/// deliveries of a crashed worker are retried once the
+/// claim expired.
+pub struct WebhookDispatcher {
+    pool: PgPool,
+    client: reqwest::Client,
+    settings: WebhookSettings,
+    schedule: Schedule,
+}
+
+impl WebhookDispatcher {
+    /// ## Creates a new `WebhookDispatcher` instance.
+    ///
+    /// ## Parameters
+    /// - `pool`: `PgPool` - Connection pool of the delivery tables.
+    /// - `settings`: `WebhookSettings` - Endpoints, retries and timeouts.
+    /// - `schedule`: `Schedule` - Schedule of the delivery runs.
+    pub fn new(pool: PgPool, settings: WebhookSettings, schedule: Schedule) -> Self {
+        WebhookDispatcher {
+            pool,
+            client: reqwest::Client::new(),
+            settings,
+            schedule,
+        }
+    }
+
+    /// ## Claims the due deliveries.
+    ///
+    /// Claim lasts until the whole batch could have timed out.
+    async fn claim(&self) -> Result<Vec<Delivery>, AppError> {
+        let claim = self.settings.timeout.saturating_mul(BATCH_SIZE as u64) as f64;
+
+        sqlx::query_as::<_, Delivery>(
+            "UPDATE webhook_deliveries SET next_attempt_at = NOW() + make_interval(secs => $1) \
+             WHERE id IN ( \
+                 SELECT id FROM webhook_deliveries WHERE next_attempt_at <= NOW() \
+                 ORDER BY next_attempt_at, id LIMIT $2 FOR UPDATE SKIP LOCKED \
+             ) \
+             RETURNING id, event_id, endpoint, event, payload, attempts",
+        )
+        .bind(claim)
+        .bind(BATCH_SIZE)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error("Failed to claim webhook deliveries"))
+    }
+
+    /// ## Removes a delivered delivery.
+    async fn complete(&self, delivery: &Delivery) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM webhook_deliveries WHERE id = $1")
+            .bind(delivery.id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(db_error("Failed to complete webhook delivery"))
+    }
+
+    /// ## Schedules the next attempt or dead-letters the delivery.
+    async fn fail(&self, delivery: &Delivery, error: &str, last: bool) -> Result<(), AppError> {
+        let attempts = delivery.attempts.saturating_add(1);
+
+        if last || attempts as u32 >= self.settings.max_attempts {
+            tracing::warn!(
+                endpoint = delivery.endpoint,
+                event = delivery.event,
+                event_id = %delivery.event_id,
+                attempts,
+                error,
+                "Webhook delivery dead-lettered"
+            );
+
+            return sqlx::query(
+                "WITH failed AS ( \
+                     DELETE FROM webhook_deliveries WHERE id = $1 \
+                     RETURNING event_id, endpoint, event, payload, created_at \
+                 ) \
+                 INSERT INTO webhook_dead_letters \
+                     (event_id, endpoint, event, payload, attempts, last_error, created_at) \
+                 SELECT event_id, endpoint, event, payload, $2, $3, created_at FROM failed",
+            )
+            .bind(delivery.id)
+            .bind(attempts)
+            .bind(error)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(db_error("Failed to dead-letter webhook delivery"));
+        }
+
+        let delay = backoff(&self.settings, attempts as u32);
+        tracing::debug!(
+            endpoint = delivery.endpoint,
+            event_id = %delivery.event_id,
+            attempts,
+            retry_in = delay.as_secs(),
+            error,
+            "Webhook delivery failed"
+        );
+
+        sqlx::query(
+            "UPDATE webhook_deliveries \
+             SET attempts = $2, last_error = $3, next_attempt_at = NOW() + make_interval(secs => $4) \
+             WHERE id = $1",
+        )
+        .bind(delivery.id)
+        .bind(attempts)
+        .bind(error)
+        .bind(delay.as_secs_f64())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(db_error("Failed to schedule webhook retry"))
+    }
+}
+
+#[async_trait]
+impl Job for WebhookDispatcher {
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+
+    fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    async fn run(&self) -> Result<(), AppError> {
+        let timeout = Duration::from_secs(self.settings.timeout);
+        let deliveries = self.claim().await?;
+
+        for delivery in deliveries {
+            let endpoint = self
+                .settings
+                .endpoints
+                .iter()
+                .find(|endpoint| endpoint.name == delivery.endpoint);
+
+            // Deliveries of removed endpoints can never succeed
+            let (result, last) = match endpoint {
+                Some(endpoint) => (
+                    deliver(&self.client, endpoint, &delivery, timeout).await,
+                    false,
+                ),
+                None => (Err("Endpoint is no longer configured".to_string()), true),
+            };
+
+            match result {
+                Ok(()) => self.complete(&delivery).await?,
+                Err(error) => self.fail(&delivery, &error, last).await?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Maps a database error into `AppError`.
+fn db_error(message: &'static str) -> impl FnOnce(sqlx::Error) -> AppError {
+    move |e| AppError::new(ErrorKind::Db, message.to_string(), Some(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::MailSettings;
+    use crate::core::mail::MailCapture;
+    use axum::{http::HeaderMap, routing::post, Router};
+    use std::sync::Mutex;
+
+    fn endpoint(url: String) -> WebhookEndpoint {
+        WebhookEndpoint {
+            name: "crm".to_string(),
+            url,
+            secret: "0123456789abcdef".into(),
+            events: vec!["user.deleted".to_string()],
+        }
+    }
+
+    fn delivery() -> Delivery {
+        Delivery {
+            id: 1,
+            event_id: Uuid::new_v4(),
+            endpoint: "crm".to_string(),
+            event: "user.deleted".to_string(),
+            payload: serde_json::json!({ "event": "user.deleted", "data": {} }),
+            attempts: 0,
+        }
+    }
+
+    // Test checks if signatures are verified only with the same secret and body.
+    #[test]
+    fn test_sign_and_verify() {
+        let signature = sign("0123456789abcdef", 1767225600, b"{\"id\":1}");
+
+        assert_eq!(
+            signature,
+            "t=1767225600,v1=96bc78ebf60f981dd9cb5ee972dbe168dc7bab4cd951d9a644ec33f9a6ad4510"
+        );
+        assert!(verify("0123456789abcdef", &signature, b"{\"id\":1}"));
+        assert!(!verify("0123456789abcdef", &signature, b"{\"id\":2}"));
+        assert!(!verify("fedcba9876543210", &signature, b"{\"id\":1}"));
+        assert!(!verify("0123456789abcdef", "v1=00", b"{\"id\":1}"));
+    }
+
+    // Test checks if the backoff doubles and is capped.
+    #[test]
+    fn test_backoff() {
+        let settings = WebhookSettings {
+            backoff: 30,
+            max_backoff: 600,
+            ..WebhookSettings::default()
+        };
+
+        let delays: Vec<u64> = (1..=6)
+            .map(|attempts| backoff(&settings, attempts).as_secs())
+            .collect();
+
+        assert_eq!(delays, [30, 60, 120, 240, 480, 600]);
+        assert_eq!(backoff(&settings, 200).as_secs(), 600);
+    }
+
+    // Test checks if only enabled, subscribed endpoints receive the event.
+    #[tokio::test]
+    async fn test_subscribers() {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+        let mut settings = WebhookSettings {
+            enabled: true,
+            endpoints: vec![
+                endpoint("https://crm.example.com".to_string()),
+                WebhookEndpoint {
+                    name: "siem".to_string(),
+                    events: Vec::new(),
+                    ..endpoint("https://siem.example.com".to_string())
+                },
+            ],
+            ..WebhookSettings::default()
+        };
+
+        let webhooks = Webhooks::new(pool.clone(), &settings);
+
+        assert_eq!(
+            webhooks.subscribers(WebhookEvent::UserDeleted),
+            ["crm", "siem"]
+        );
+        assert_eq!(webhooks.subscribers(WebhookEvent::LoginFailed), ["siem"]);
+
+        settings.enabled = false;
+        let webhooks = Webhooks::new(pool, &settings);
+
+        assert!(webhooks.subscribers(WebhookEvent::UserDeleted).is_empty());
+    }
+
+    // Test checks if events of the outbox are only queued without it.
+    #[tokio::test]
+    async fn test_emit_outbox() {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+        let settings = WebhookSettings {
+            enabled: true,
+            endpoints: vec![WebhookEndpoint {
+                events: Vec::new(),
+                ..endpoint("https://crm.example.com".to_string())
+            }],
+            ..WebhookSettings::default()
+        };
+        let data = serde_json::json!({ "user_id": Uuid::nil() });
+
+        let webhooks = Webhooks::new(pool.clone(), &settings);
+        webhooks
+            .emit(WebhookEvent::UserCreated, data.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(webhooks.queue.len(), 1);
+
+        let webhooks = Webhooks::new(pool, &settings).with_outbox(true);
+        for event in [WebhookEvent::UserCreated, WebhookEvent::UserDeleted] {
+            webhooks.emit(event, data.clone()).await.unwrap();
+        }
+
+        assert_eq!(webhooks.queue.len(), 1);
+    }
+
+    // Test checks if events carrying mail go to the mailer and still to the endpoints.
+    #[tokio::test]
+    async fn test_emit_mailer() {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+        let settings = WebhookSettings {
+            enabled: true,
+            endpoints: vec![WebhookEndpoint {
+                events: Vec::new(),
+                ..endpoint("https://mailer.example.com".to_string())
+            }],
+            ..WebhookSettings::default()
+        };
+        let capture = MailCapture::default();
+        let webhooks = Webhooks::new(pool, &settings).with_mailer(Arc::new(capture.clone()));
+        let data = serde_json::json!({ "user_id": Uuid::nil(), "email": "ada@example.com" });
+
+        for event in [WebhookEvent::MagicLinkRequested, WebhookEvent::UserCreated] {
+            webhooks.emit(event, data.clone()).await.unwrap();
+        }
+
+        assert_eq!(capture.list().len(), 1);
+        assert_eq!(webhooks.queue.len(), 2);
+    }
+
+    // Test checks if the email rendered from the templates is added to the event data.
+    #[tokio::test]
+    async fn test_emit_templates() {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+        let settings = WebhookSettings {
+            enabled: true,
+            endpoints: vec![WebhookEndpoint {
+                events: Vec::new(),
+                ..endpoint("https://mailer.example.com".to_string())
+            }],
+            ..WebhookSettings::default()
+        };
+        let mail = MailSettings {
+            enabled: true,
+            ..MailSettings::default()
+        };
+        let templates = Arc::new(MailTemplates::load(&mail, false).unwrap());
+        let webhooks = Webhooks::new(pool, &settings).with_templates(templates);
+        let data = serde_json::json!({
+            "email": "ada@example.com",
+            "link": "https://example.com/verify?token=abc",
+            "locale": "de-DE",
+        });
+
+        webhooks
+            .emit(WebhookEvent::MagicLinkRequested, data)
+            .await
+            .unwrap();
+
+        let delivery = webhooks.queue.pop().await.unwrap();
+        assert_eq!(
+            delivery.payload["data"]["mail"]["subject"],
+            "Ihr Anmeldelink"
+        );
+    }
+
+    // Test checks if the delivery is posted with a valid signature.
+    #[tokio::test]
+    async fn test_deliver() {
+        let received = Arc::new(Mutex::new(None));
+        let store = received.clone();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(move |headers: HeaderMap, body: String| async move {
+                    *store.lock().unwrap() = Some((headers, body));
+                    "ok"
+                }),
+            )
+            .route(
+                "/broken",
+                post(|| async { axum::http::StatusCode::SERVICE_UNAVAILABLE }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::new();
+        let delivery = delivery();
+        let timeout = Duration::from_secs(5);
+
+        let result = deliver(
+            &client,
+            &endpoint(format!("http://{}/hook", addr)),
+            &delivery,
+            timeout,
+        )
+        .await;
+
+        assert_eq!(result, Ok(()));
+
+        let (headers, body) = received.lock().unwrap().take().unwrap();
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+
+        assert!(verify("0123456789abcdef", signature, body.as_bytes()));
+        assert_eq!(headers[EVENT_HEADER], "user.deleted");
+        assert_eq!(headers[ID_HEADER], delivery.event_id.to_string());
+
+        let result = deliver(
+            &client,
+            &endpoint(format!("http://{}/broken", addr)),
+            &delivery,
+            timeout,
+        )
+        .await;
+
+        assert_eq!(
+            result,
+            Err("Endpoint responded with 503 Service Unavailable".to_string())
+        );
+    }
+}
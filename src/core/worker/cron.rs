@@ -0,0 +1,518 @@
+//! Cron schedule module.
+//!
+//! Module parses the cron expressions of the job schedules.
+//! Expressions have five fields (minute, hour, day of month,
+//! month, day of week) or six with a leading seconds field,
+//! and support `*`, lists, ranges, steps, month and weekday
+//! names and the `@hourly`, `@daily`, `@weekly`, `@monthly`
+//! and `@yearly` macros. A `CRON_TZ=<zone>` prefix overrides
+//! the timezone the expression is evaluated in.
+
+// Imports from external crates
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Timelike, Utc,
+};
+use chrono_tz::Tz;
+use std::{fmt, str::FromStr};
+
+// Local imports
+use crate::core::err::{AppError, ErrorKind};
+
+/// Prefix of the timezone override.
+pub const TZ_PREFIX: &str = "CRON_TZ=";
+
+/// Years searched for the next run before giving up,
+/// covers the leap day of `0 0 29 2 *`.
+const SEARCH_YEARS: i32 = 5;
+
+/// Names of the months, January is 1.
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Names of the weekdays, Sunday is 0.
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// ## Cron field kind struct.
+///
+/// ## Fields
+/// + `name`: `&'static str` - Name used in error messages.
+/// + `min`: `u32` - Smallest value.
+/// + `max`: `u32` - Largest value.
+/// + `names`: `&'static [&'static str]` - Names of the values from `min`.
+#[derive(Debug, Clone, Copy)]
+struct FieldKind {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+}
+
+const SECOND: FieldKind = FieldKind {
+    name: "second",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+const MINUTE: FieldKind = FieldKind {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+const HOUR: FieldKind = FieldKind {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+};
+const DAY: FieldKind = FieldKind {
+    name: "day of month",
+    min: 1,
+    max: 31,
+    names: &[],
+};
+const MONTH: FieldKind = FieldKind {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &MONTHS,
+};
+// 7 is accepted as Sunday and folded into 0
+const WEEKDAY: FieldKind = FieldKind {
+    name: "day of week",
+    min: 0,
+    max: 7,
+    names: &WEEKDAYS,
+};
+
+/// ## Cron expression struct.
+///
+/// Every field is a bit set of the matching values.
+/// Like in Vixie cron, a time matches when both the day
+/// of month and day of week match, or either of them
+/// when both are restricted.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::worker::cron::Cron;
+///
+/// let cron: Cron = "30 2 * * mon-fri".parse().unwrap();
+///
+/// assert!("0 24 * * *".parse::<Cron>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    expression: String,
+    timezone: Option<Tz>,
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// ## Returns the timezone of the `CRON_TZ` prefix.
+    pub fn timezone(&self) -> Option<Tz> {
+        self.timezone
+    }
+
+    /// ## Returns the first matching local time after the given time.
+    ///
+    /// ## Parameters
+    /// - `after`: `NaiveDateTime` - Local time to search from, excluded.
+    ///
+    /// ## Returns
+    /// - `Option<NaiveDateTime>`: Next matching time, `None` if
+    ///   the expression does not match within the search window.
+    pub fn next_local(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut time = after.with_nanosecond(0)? + Duration::seconds(1);
+        let limit = time.year() + SEARCH_YEARS;
+
+        while time.year() <= limit {
+            let date = time.date();
+
+            if !has(self.months, date.month()) {
+                time = first_of_next_month(date)?.and_time(NaiveTime::MIN);
+            } else if !self.matches_day(date) {
+                time = date.succ_opt()?.and_time(NaiveTime::MIN);
+            } else if !has(self.hours, time.hour()) {
+                time = time.with_minute(0)?.with_second(0)? + Duration::hours(1);
+            } else if !has(self.minutes, time.minute()) {
+                time = time.with_second(0)? + Duration::minutes(1);
+            } else if !has(self.seconds, time.second()) {
+                time += Duration::seconds(1);
+            } else {
+                return Some(time);
+            }
+        }
+
+        None
+    }
+
+    /// ## Checks if the day of month and day of week match.
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl FromStr for Cron {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| {
+            AppError::new(
+                ErrorKind::Config,
+                format!("Invalid cron expression '{}': {}", value, reason),
+                None,
+            )
+        };
+
+        let mut expression = value.trim();
+        let mut timezone = None;
+        if let Some(rest) = expression.strip_prefix(TZ_PREFIX) {
+            let (zone, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            timezone = Some(
+                zone.parse::<Tz>()
+                    .map_err(|_| invalid(format!("unknown timezone '{}'", zone)))?,
+            );
+            expression = rest.trim();
+        }
+
+        let expanded = match expression {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            macro_name if macro_name.starts_with('@') => {
+                return Err(invalid(format!("unknown macro '{}'", macro_name)));
+            }
+            fields => fields,
+        };
+
+        let mut fields: Vec<&str> = expanded.split_whitespace().collect();
+        match fields.len() {
+            5 => fields.insert(0, "0"),
+            6 => {}
+            count => return Err(invalid(format!("expected 5 or 6 fields, found {}", count))),
+        }
+
+        let parse =
+            |index: usize, kind: FieldKind| parse_field(fields[index], kind).map_err(invalid);
+        let seconds = parse(0, SECOND)?;
+        let minutes = parse(1, MINUTE)?;
+        let hours = parse(2, HOUR)?;
+        let days = parse(3, DAY)?;
+        let months = parse(4, MONTH)?;
+        let mut weekdays = parse(5, WEEKDAY)?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+
+        Ok(Cron {
+            expression: value.trim().to_string(),
+            timezone,
+            seconds,
+            minutes,
+            hours,
+            days,
+            months,
+            weekdays,
+            any_day: fields[3].starts_with('*'),
+            any_weekday: fields[5].starts_with('*'),
+        })
+    }
+}
+
+/// ## Parses a field into the bit set of its values.
+fn parse_field(field: &str, kind: FieldKind) -> Result<u64, String> {
+    let mut bits = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid {} step '{}'", kind.name, step))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let (start, end) = match range {
+            "*" => (kind.min, kind.max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (value(start, kind)?, value(end, kind)?),
+                // A single value with a step runs up to the maximum
+                None if step.is_some() => (value(range, kind)?, kind.max),
+                None => {
+                    let value = value(range, kind)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!(
+                "{} range '{}' starts after it ends",
+                kind.name, range
+            ));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+/// ## Parses a value or name of the field.
+fn value(value: &str, kind: FieldKind) -> Result<u32, String> {
+    if let Some(index) = kind
+        .names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(value))
+    {
+        return Ok(kind.min + index as u32);
+    }
+
+    match value.parse::<u32>() {
+        Ok(number) if (kind.min..=kind.max).contains(&number) => Ok(number),
+        Ok(number) => Err(format!(
+            "{} {} is out of range {}-{}",
+            kind.name, number, kind.min, kind.max
+        )),
+        Err(_) => Err(format!("invalid {} '{}'", kind.name, value)),
+    }
+}
+
+/// ## Checks if the bit of the value is set.
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// ## Returns the first day of the month after the date.
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    match date.month() {
+        12 => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd_opt(date.year(), month + 1, 1),
+    }
+}
+
+/// ## Job schedule struct.
+///
+/// Cron expression together with the timezone it is
+/// evaluated in, so `0 3 * * *` runs at 3 AM local time
+/// across daylight saving changes. Local times skipped by
+/// a change do not run, repeated local times run once.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::core::worker::cron::Schedule;
+/// use chrono::{DateTime, Utc};
+///
+/// let schedule = Schedule::new("0 3 * * *", "Europe/Berlin").unwrap();
+/// let after = "2026-07-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+///
+/// assert_eq!(
+///     schedule.next(after),
+///     Some("2026-07-02T01:00:00Z".parse().unwrap())
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    pub cron: Cron,
+    pub timezone: Tz,
+}
+
+impl Schedule {
+    /// ## Creates a new `Schedule` instance.
+    ///
+    /// ## Parameters
+    /// - `expression`: `&str` - Cron expression, optionally with a `CRON_TZ=` prefix.
+    /// - `timezone`: `&str` - IANA timezone used without a prefix.
+    ///
+    /// ## Returns
+    /// + `Result<Schedule, AppError>`
+    ///     - `Ok(Schedule)`: Parsed schedule.
+    ///     - `Err(AppError)`: If the expression or timezone is invalid,
+    ///       or the expression never matches.
+    pub fn new(expression: &str, timezone: &str) -> Result<Self, AppError> {
+        let cron = expression.parse::<Cron>()?;
+        let timezone = match cron.timezone() {
+            Some(timezone) => timezone,
+            None => timezone.parse::<Tz>().map_err(|_| {
+                AppError::new(
+                    ErrorKind::Config,
+                    format!("Unknown schedule timezone '{}'", timezone),
+                    None,
+                )
+            })?,
+        };
+
+        // Expressions like `0 0 30 2 *` parse but never run
+        if cron.next_local(DateTime::UNIX_EPOCH.naive_utc()).is_none() {
+            return Err(AppError::new(
+                ErrorKind::Config,
+                format!("Cron expression '{}' never matches", cron),
+                None,
+            ));
+        }
+
+        Ok(Schedule { cron, timezone })
+    }
+
+    /// ## Returns the next run after the given time.
+    ///
+    /// ## Returns
+    /// - `Option<DateTime<Utc>>`: Time of the next run, `None` if
+    ///   the expression does not match within the search window.
+    pub fn next(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut local = after.with_timezone(&self.timezone).naive_local();
+
+        loop {
+            local = self.cron.next_local(local)?;
+
+            let candidates = match self.timezone.from_local_datetime(&local) {
+                LocalResult::Single(time) => vec![time],
+                LocalResult::Ambiguous(earliest, latest) => vec![earliest, latest],
+                // Skipped by a daylight saving change
+                LocalResult::None => continue,
+            };
+            if let Some(time) = candidates.into_iter().find(|time| *time > after) {
+                return Some(time.with_timezone(&Utc));
+            }
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.cron.timezone() {
+            Some(_) => write!(f, "{}", self.cron),
+            None => write!(f, "{} ({})", self.cron, self.timezone),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> String {
+        Schedule::new(expression, "UTC")
+            .unwrap()
+            .next(at(after))
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    // Test checks if lists, ranges, steps, names and macros are matched.
+    #[test]
+    fn test_next_fields() {
+        let after = "2026-03-04T10:17:42Z";
+
+        assert_eq!(next("* * * * *", after), "2026-03-04T10:18:00+00:00");
+        assert_eq!(next("*/15 * * * * *", after), "2026-03-04T10:17:45+00:00");
+        assert_eq!(next("5,40 9-11 * * *", after), "2026-03-04T10:40:00+00:00");
+        assert_eq!(next("0 12 * jun mon", after), "2026-06-01T12:00:00+00:00");
+        assert_eq!(next("0 0 1-31/10 * *", after), "2026-03-11T00:00:00+00:00");
+        assert_eq!(next("@monthly", after), "2026-04-01T00:00:00+00:00");
+        assert_eq!(next("@weekly", after), "2026-03-08T00:00:00+00:00");
+        assert_eq!(next("0 0 * * 7", after), "2026-03-08T00:00:00+00:00");
+        assert_eq!(next("0 0 29 2 *", after), "2028-02-29T00:00:00+00:00");
+    }
+
+    // Test checks if restricted day of month and day of week match either.
+    #[test]
+    fn test_next_day_or_weekday() {
+        // Wednesday the 4th, next is Friday the 6th or the 10th
+        assert_eq!(
+            next("0 0 10 * fri", "2026-03-04T10:00:00Z"),
+            "2026-03-06T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 10 * *", "2026-03-04T10:00:00Z"),
+            "2026-03-10T00:00:00+00:00"
+        );
+    }
+
+    // Test checks if schedules follow the timezone across daylight saving changes.
+    #[test]
+    fn test_next_timezone() {
+        let berlin = Schedule::new("30 2 * * *", "Europe/Berlin").unwrap();
+
+        // 02:30 does not exist on 2026-03-29 in Berlin
+        assert_eq!(
+            berlin.next(at("2026-03-28T02:00:00Z")),
+            Some(at("2026-03-30T00:30:00Z"))
+        );
+        // 02:30 exists twice on 2026-10-25, it runs once
+        let first = berlin.next(at("2026-10-24T02:00:00Z")).unwrap();
+
+        assert_eq!(first, at("2026-10-25T00:30:00Z"));
+        assert_eq!(berlin.next(first), Some(at("2026-10-26T01:30:00Z")));
+
+        let prefixed = Schedule::new("CRON_TZ=America/New_York 0 9 * * *", "UTC").unwrap();
+
+        assert_eq!(prefixed.timezone, Tz::America__New_York);
+        assert_eq!(
+            prefixed.next(at("2026-07-01T00:00:00Z")),
+            Some(at("2026-07-01T13:00:00Z"))
+        );
+    }
+
+    // Test checks if invalid expressions are rejected with the reason.
+    #[test]
+    fn test_invalid_expressions() {
+        for (expression, reason) in [
+            ("* * * *", "expected 5 or 6 fields, found 4"),
+            ("0 24 * * *", "hour 24 is out of range 0-23"),
+            ("*/0 * * * *", "invalid minute step '0'"),
+            ("0 0 * foo *", "invalid month 'foo'"),
+            (
+                "0 0 5-1 * *",
+                "day of month range '5-1' starts after it ends",
+            ),
+            ("@reboot", "unknown macro '@reboot'"),
+            (
+                "CRON_TZ=Mars/Base 0 0 * * *",
+                "unknown timezone 'Mars/Base'",
+            ),
+        ] {
+            let error = Schedule::new(expression, "UTC").unwrap_err();
+
+            assert_eq!(
+                error.message,
+                format!("Invalid cron expression '{}': {}", expression, reason)
+            );
+        }
+
+        assert!(Schedule::new("0 0 30 2 *", "UTC").is_err());
+        assert!(Schedule::new("@daily", "Mars/Base").is_err());
+    }
+}
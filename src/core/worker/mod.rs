@@ -0,0 +1,196 @@
+//! Background worker module.
+//!
+//! Module runs the background jobs in the `worker` run
+//! mode, so they can be scaled independently of the HTTP
+//! server. Every job runs on its own cron schedule until
+//! the shutdown coordinator signals the worker to stop.
+//!
+//! Only `cron` is available without the `server`
+//! feature, the configuration validates schedules.
+
+// References to submodules
+pub mod cron;
+
+// Imports from external crates
+#[cfg(feature = "server")]
+use async_trait::async_trait;
+#[cfg(feature = "server")]
+use chrono::Utc;
+#[cfg(feature = "server")]
+use std::sync::Arc;
+#[cfg(feature = "server")]
+use tokio::task::JoinSet;
+
+// Local imports
+#[cfg(feature = "server")]
+use super::err::AppError;
+#[cfg(feature = "server")]
+use super::server::shutdown::ShutdownCoordinator;
+#[cfg(feature = "server")]
+use cron::Schedule;
+
+/// ## Background job trait.
+///
+/// ## Examples
+/// ```
+/// use async_trait::async_trait;
+/// use axum_auth::core::err::AppError;
+/// use axum_auth::core::worker::{cron::Schedule, Job};
+///
+/// struct Heartbeat(Schedule);
+///
+/// #[async_trait]
+/// impl Job for Heartbeat {
+///     fn name(&self) -> &str {
+///         "heartbeat"
+///     }
+///
+///     fn schedule(&self) -> &Schedule {
+///         &self.0
+///     }
+///
+///     async fn run(&self) -> Result<(), AppError> {
+///         tracing::info!("Worker is alive");
+///         Ok(())
+///     }
+/// }
+/// ```
+#[cfg(feature = "server")]
+#[async_trait]
+pub trait Job: Send + Sync {
+    /// Name of the job, used in log messages.
+    fn name(&self) -> &str;
+
+    /// Schedule of the runs, the first run waits for
+    /// the next matching time.
+    fn schedule(&self) -> &Schedule;
+
+    /// Runs the job once. Errors are logged and the
+    /// job runs again at the next scheduled time.
+    async fn run(&self) -> Result<(), AppError>;
+}
+
+/// ## Background worker struct.
+#[cfg(feature = "server")]
+#[derive(Default)]
+pub struct Worker {
+    jobs: Vec<Arc<dyn Job>>,
+}
+
+#[cfg(feature = "server")]
+impl Worker {
+    /// ## Creates a worker without jobs.
+    pub fn new() -> Self {
+        Worker::default()
+    }
+
+    /// ## Registers a job.
+    pub fn register(mut self, job: impl Job + 'static) -> Self {
+        self.jobs.push(Arc::new(job));
+        self
+    }
+
+    /// ## Returns the names of the registered jobs.
+    pub fn job_names(&self) -> Vec<&str> {
+        self.jobs.iter().map(|job| job.name()).collect()
+    }
+
+    /// ## Runs the jobs until shutdown.
+    ///
+    /// A run that is in progress when the shutdown is
+    /// triggered is finished first. Cleanup hooks run once
+    /// every job stopped.
+    ///
+    /// ## Parameters
+    /// - `coordinator`: `ShutdownCoordinator` - Shutdown coordinator.
+    pub async fn run(self, coordinator: ShutdownCoordinator) {
+        tracing::info!(jobs = ?self.job_names(), "Worker started");
+        for job in &self.jobs {
+            tracing::info!(job = job.name(), schedule = %job.schedule(), "Job scheduled");
+        }
+
+        let mut tasks = JoinSet::new();
+        for job in self.jobs {
+            tasks.spawn(schedule(job, coordinator.clone()));
+        }
+
+        coordinator.wait().await;
+        while tasks.join_next().await.is_some() {}
+
+        coordinator.run_hooks().await;
+    }
+}
+
+/// ## Runs the job on its schedule until shutdown.
+///
+/// Next run is computed after every run, so runs that
+/// take longer than the schedule skip the missed times.
+#[cfg(feature = "server")]
+async fn schedule(job: Arc<dyn Job>, coordinator: ShutdownCoordinator) {
+    loop {
+        let now = Utc::now();
+        let Some(next) = job.schedule().next(now) else {
+            tracing::warn!(job = job.name(), "Job has no further scheduled runs");
+            break;
+        };
+        let wait = (next - now).to_std().unwrap_or_default();
+
+        tokio::select! {
+            _ = coordinator.wait() => break,
+            _ = tokio::time::sleep(wait) => {}
+        }
+
+        if let Err(e) = job.run().await {
+            tracing::warn!(job = job.name(), error = %e, "Job failed");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    struct Counter(Arc<AtomicUsize>, Schedule);
+
+    #[async_trait]
+    impl Job for Counter {
+        fn name(&self) -> &str {
+            "counter"
+        }
+
+        fn schedule(&self) -> &Schedule {
+            &self.1
+        }
+
+        async fn run(&self) -> Result<(), AppError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    // Test checks if jobs run repeatedly and stop on shutdown.
+    #[tokio::test]
+    async fn test_worker_runs_until_shutdown() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let every_second = Schedule::new("* * * * * *", "UTC").unwrap();
+        let worker = Worker::new().register(Counter(runs.clone(), every_second));
+
+        assert_eq!(worker.job_names(), ["counter"]);
+
+        let trigger = coordinator.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(2100)).await;
+            trigger.trigger();
+        });
+        worker.run(coordinator).await;
+
+        let stopped = runs.load(Ordering::SeqCst);
+        assert!(stopped >= 2);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), stopped);
+    }
+}
@@ -1,71 +1,44 @@
+//! Authentication server built on axum.
+//!
+//! Without features the crate is a library that loads
+//! and validates the configuration and environment of a
+//! deployment. Everything else is behind cargo features,
+//! all of them enabled by default:
+//!
+//! + `postgres` - Connection pool and migrations, `core::db`.
+//! + `server` - HTTP server, routes, client and background
+//!   jobs. Implies `postgres`.
+//! + `redis` - Redis health checks. Implies `server`.
+//! + `cli` - Command line and the `axum_auth` binary.
+//!   Implies `server`.
+//!
+//! Library users leave out axum, sqlx and tokio with:
+//!
+//! ```toml
+//! axum_auth = { version = "0.1", default-features = false }
+//! ```
+
 // References to submodules
-// pub mod env;
-// pub mod err;
+pub mod api;
 pub mod core;
 pub mod strings;
 
-// Imports of local modules
-use core::config::get_config;
-use core::config::APP_CONFIG;
-use core::config::CONFIG_FILE_PATH;
-use core::env::vars::{EnvVar, RequiredEnvVar};
-use core::err::{AppError, ErrorKind};
-
-/// Runs the application.
-///
-/// Function loads environment variables from file
-/// and validates them against specified environment
-/// variables. Then it builds a connection pool and
-/// starts the HTTP server.
-///
-/// # Examples
-/// ```
-/// use axum_auth::run_app;
-///
-/// run_app();
-/// ```
-///
-/// #Returns
-/// + `Result<(), AppError>`
-///   - `()`: If the function runs successfully.
-///   - `AppError`: If the function fails to run.
-pub async fn run_app() -> Result<(), AppError> {
-    // * Temporary code
-    let config_file_path: String = "./custom_config.toml".to_string();
-    // Set the configuration file path
-    set_config_file_path(config_file_path)?;
-
-    // Check if the configuration is loaded and
-    // if it is valid
-    let app_config = get_config(&APP_CONFIG)?;
-
-    println!("App Config: {:?}", app_config);
-
-    // Load environment variables from file
-    // core::env::load(
-    //     &app_config.app.env_file_path,
-    //     &app_config.app.prefix,
-    //     RequiredEnvVar::all(),
-    // )?;
-
-    // println!("DB_HOST: {}", RequiredEnvVar::DbHost.value());
-
-    Ok(())
-}
-
-// * Temporary code
-fn set_config_file_path(path: String) -> Result<(), AppError> {
-    // !! This is a simulation, this parameter will come
-    // !! from the command line arguments
-    if let Err(_) = CONFIG_FILE_PATH.set(path) {
-        let kind = ErrorKind::ConfigFilePath;
-        let message = "Failed to set configuration file path".to_string();
-        let source = None;
-
-        let err = AppError::new(kind, message, source);
-
-        return Err(err);
-    }
-
-    Ok(())
-}
+#[cfg(feature = "postgres")]
+mod app;
+#[cfg(feature = "server")]
+pub mod client;
+#[cfg(feature = "server")]
+pub mod routes;
+
+// Re-exports of the configuration and environment library
+pub use core::config::{read_config, AppConfig, CONFIG_FILE_PATH};
+pub use core::env::{load as load_env, map::EnvMap};
+pub use core::err::{AppError, ErrorKind};
+
+// Re-exports of the run modes
+#[cfg(feature = "postgres")]
+pub use app::run_migrations;
+#[cfg(feature = "cli")]
+pub use app::{run, run_client, run_doctor, run_tenant, run_token};
+#[cfg(feature = "server")]
+pub use app::{run_app, run_worker};
@@ -1,13 +1,16 @@
 // std library imports
 use std::process;
 
+// Imports from external crates
+use clap::Parser;
+
 // Local imports
-use axum_auth::run_app;
+use axum_auth::{core::cli::Cli, run};
 
 #[tokio::main]
 async fn main() {
-    match run_app().await {
-        Ok(_) => println!("Application stopped with no error reported."),
+    match run(Cli::parse()).await {
+        Ok(_) => eprintln!("Application stopped with no error reported."),
         Err(e) => {
             eprintln!("Application reported an error: {}", e);
             process::exit(1);
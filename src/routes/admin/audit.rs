@@ -0,0 +1,124 @@
+//! Audit log routes.
+//!
+//! Audit log is not scoped to organizations, so only
+//! global admins may query it.
+
+// Imports from external crates
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+
+// Local imports
+use super::AdminState;
+use crate::core::audit::{AuditFilter, AuditRecord, AUDIT_SORTING};
+use crate::core::auth::rbac::{Channel, Permission, Principal};
+use crate::core::err::AppError;
+use crate::core::http::locale::Localizer;
+use crate::core::http::pagination::{Paginated, Pagination};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::audit::{AuditPage, ListAuditEventsQuery};
+
+/// ## Declares the audit log routes.
+pub fn routes() -> Routes<AdminState> {
+    Routes::new().get("/audit", AuthPolicy::Admin, list_audit_events)
+}
+
+/// ## Lists audit events.
+///
+/// Events are returned newest first unless `sort` is
+/// sent. Page through large logs with `cursor`, pages
+/// by number get slower the further they are.
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    tag = "admin",
+    params(ListAuditEventsQuery),
+    responses((status = 200, description = "Page of audit events", body = Paginated<AuditRecord>))
+)]
+pub async fn list_audit_events(
+    State(state): State<AdminState>,
+    principal: Principal,
+    localizer: Localizer,
+    pagination: Pagination,
+    Query(query): Query<ListAuditEventsQuery>,
+) -> Result<Json<AuditPage>, AppError> {
+    state
+        .policy
+        .authorize(&principal, Permission::ReadAuditLog, None, Channel::Rest)?;
+
+    let window = pagination.window(&AUDIT_SORTING)?;
+    let filter = AuditFilter {
+        user_id: query.user_id,
+        event: query.event.as_deref().map(str::parse).transpose()?,
+        from: query.from,
+        to: query.to,
+    };
+
+    let rows = state.audit.query(&filter, &window).await?;
+
+    Ok(Json(localizer.apply(window.paginate(rows))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::rbac::{RoleGrant, ADMIN_ROLE};
+    use crate::core::webhooks::Webhooks;
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use sqlx::postgres::PgPoolOptions;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    async fn send(uri: &str, principal: Option<Principal>) -> StatusCode {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+        let mut request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        if let Some(principal) = principal {
+            request.extensions_mut().insert(principal);
+        }
+
+        crate::routes::admin::router(AdminState::new(
+            pool.clone(),
+            Webhooks::new(pool, &Default::default()),
+        ))
+        .oneshot(request)
+        .await
+        .unwrap()
+        .status()
+    }
+
+    // Test checks if unauthenticated requests are rejected.
+    #[tokio::test]
+    async fn test_list_audit_events_unauthenticated() {
+        assert_eq!(send("/audit", None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    // Test checks if org admins can not read the global audit log.
+    #[tokio::test]
+    async fn test_list_audit_events_org_admin_forbidden() {
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![RoleGrant::org(ADMIN_ROLE, Uuid::new_v4())],
+        };
+
+        assert_eq!(send("/audit", Some(principal)).await, StatusCode::FORBIDDEN);
+    }
+
+    // Test checks if unknown event names are rejected before querying.
+    #[tokio::test]
+    async fn test_list_audit_events_unknown_event() {
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![RoleGrant::global(ADMIN_ROLE)],
+        };
+
+        assert_eq!(
+            send("/audit?event=login.maybe", Some(principal)).await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Administration routes.
+//!
+//! Every route requires an authenticated principal and
+//! is authorized through the RBAC `Policy`.
+
+// References to submodules
+pub mod audit;
+pub mod roles;
+pub mod users;
+
+// Imports from external crates
+use axum::Router;
+use std::sync::Arc;
+
+// Local imports
+use crate::core::audit::AuditLog;
+use crate::core::auth::rbac::Policy;
+use crate::core::db::DbExecutor;
+use crate::core::repo::postgres::{PgRoleDefinitionRepo, PgUserRepo};
+use crate::core::repo::{RoleDefinitionRepo, UserRepo};
+use crate::core::users::roles::RoleRepo;
+use crate::core::webhooks::Webhooks;
+use crate::routes::registry::Routes;
+
+/// ## Administration state struct.
+///
+/// ## Fields
+/// + `users`: `Arc<dyn UserRepo>` - User repository.
+/// + `roles`: `RoleRepo` - Role grants of the users.
+/// + `role_definitions`: `Arc<dyn RoleDefinitionRepo>` - Roles defined
+///   by administrators.
+/// + `audit`: `AuditLog` - Audit log of the administrative actions.
+/// + `policy`: `Arc<Policy>` - RBAC policy of the administrative actions.
+/// + `webhooks`: `Webhooks` - Webhooks of the account lifecycle events.
+#[derive(Clone)]
+pub struct AdminState {
+    pub users: Arc<dyn UserRepo>,
+    pub roles: RoleRepo,
+    pub role_definitions: Arc<dyn RoleDefinitionRepo>,
+    pub audit: AuditLog,
+    pub policy: Arc<Policy>,
+    pub webhooks: Webhooks,
+}
+
+impl AdminState {
+    /// ## Creates a new `AdminState` with the default policy.
+    ///
+    /// ## Parameters
+    /// - `db`: `impl Into<DbExecutor>` - Executor or pool of the users,
+    ///   roles and audit log.
+    /// - `webhooks`: `Webhooks` - Queue of the webhook events.
+    pub fn new(db: impl Into<DbExecutor>, webhooks: Webhooks) -> Self {
+        let db = db.into();
+
+        AdminState {
+            users: Arc::new(PgUserRepo::new(db.clone()).with_outbox(webhooks.outbox())),
+            roles: RoleRepo::new(db.clone()),
+            role_definitions: Arc::new(PgRoleDefinitionRepo::new(db.clone())),
+            audit: AuditLog::new(db),
+            policy: Arc::new(Policy::default()),
+            webhooks,
+        }
+    }
+}
+
+/// ## Declares the administration routes.
+///
+/// ## Returns
+/// - `Routes<AdminState>`: Routes to nest under `/admin`.
+pub fn routes() -> Routes<AdminState> {
+    users::routes()
+        .merge(roles::routes())
+        .merge(audit::routes())
+}
+
+/// ## Builds the administration router.
+///
+/// ## Parameters
+/// - `state`: `AdminState` - Shared state of the routes.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `/admin`.
+pub fn router(state: AdminState) -> Router {
+    routes().router(None).with_state(state)
+}
@@ -0,0 +1,644 @@
+//! Role administration routes.
+//!
+//! Role definitions belong to the whole tenant, so only
+//! global role managers may change them, while org
+//! admins may list them and grant them to the users of
+//! their organizations. Definitions are updated with the
+//! version they were read at, an outdated version is
+//! rejected with `409`. Every change is audited.
+
+// Imports from external crates
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+// Local imports
+use super::AdminState;
+use crate::core::audit::{AuditEntry, AuditEvent};
+use crate::core::auth::rbac::{Channel, Permission, Principal, RoleGrant, Scope, ADMIN_ROLE};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::client_ip::ClientIp;
+use crate::core::repo::{role_not_found, NewRole};
+use crate::core::users::roles::{is_builtin, validate_role_name, Role, RoleSource};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::roles::{AssignRoleRequest, CreateRoleRequest, UpdateRoleRequest, UserRole};
+
+/// ## Declares the role administration routes.
+pub fn routes() -> Routes<AdminState> {
+    Routes::new()
+        .get("/roles", AuthPolicy::Admin, list_roles)
+        .post("/roles", AuthPolicy::Admin, create_role)
+        .get("/roles/:name", AuthPolicy::Admin, get_role)
+        .put("/roles/:name", AuthPolicy::Admin, update_role)
+        .delete("/roles/:name", AuthPolicy::Admin, delete_role)
+        .get("/users/:id/roles", AuthPolicy::Admin, list_user_roles)
+        .post("/users/:id/roles", AuthPolicy::Admin, assign_role)
+        .delete("/users/:id/roles/:role", AuthPolicy::Admin, revoke_role)
+}
+
+/// ## Lists the defined roles.
+///
+/// Built-in roles are not listed.
+#[utoipa::path(
+    get,
+    path = "/admin/roles",
+    tag = "admin",
+    responses((status = 200, description = "Defined roles", body = Vec<Role>))
+)]
+pub async fn list_roles(
+    State(state): State<AdminState>,
+    principal: Principal,
+) -> Result<Json<Vec<Role>>, AppError> {
+    authorize_read(&state, &principal)?;
+
+    Ok(Json(state.role_definitions.list().await?))
+}
+
+/// ## Defines a role.
+#[utoipa::path(
+    post,
+    path = "/admin/roles",
+    tag = "admin",
+    request_body = CreateRoleRequest,
+    responses((status = 201, description = "Defined role", body = Role))
+)]
+pub async fn create_role(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Json(request): Json<CreateRoleRequest>,
+) -> Result<(StatusCode, Json<Role>), AppError> {
+    authorize_write(&state, &principal)?;
+    validate_role_name(&request.name)?;
+    let permissions = parse_permissions(&request.permissions)?;
+
+    let role = state
+        .role_definitions
+        .create(NewRole {
+            name: request.name,
+            description: request.description,
+            permissions,
+        })
+        .await?;
+    audit(&state, &principal, client, AuditEvent::RoleCreated, &role).await?;
+
+    Ok((StatusCode::CREATED, Json(role)))
+}
+
+/// ## Returns a role definition.
+#[utoipa::path(
+    get,
+    path = "/admin/roles/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Name of the role")),
+    responses((status = 200, description = "Role", body = Role))
+)]
+pub async fn get_role(
+    State(state): State<AdminState>,
+    principal: Principal,
+    Path(name): Path<String>,
+) -> Result<Json<Role>, AppError> {
+    authorize_read(&state, &principal)?;
+
+    let role = state
+        .role_definitions
+        .get(&name)
+        .await?
+        .ok_or_else(|| role_not_found(&name))?;
+
+    Ok(Json(role))
+}
+
+/// ## Replaces a role definition.
+///
+/// Update has to send the version it is based on, the
+/// role is only updated if nobody changed it since.
+#[utoipa::path(
+    put,
+    path = "/admin/roles/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Name of the role")),
+    request_body = UpdateRoleRequest,
+    responses(
+        (status = 200, description = "Updated role", body = Role),
+        (status = 409, description = "Role was changed since the sent version")
+    )
+)]
+pub async fn update_role(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Path(name): Path<String>,
+    Json(request): Json<UpdateRoleRequest>,
+) -> Result<Json<Role>, AppError> {
+    authorize_write(&state, &principal)?;
+    let permissions = parse_permissions(&request.permissions)?;
+
+    let role = state
+        .role_definitions
+        .update(
+            NewRole {
+                name,
+                description: request.description,
+                permissions,
+            },
+            request.version,
+        )
+        .await?;
+    audit(&state, &principal, client, AuditEvent::RoleUpdated, &role).await?;
+
+    Ok(Json(role))
+}
+
+/// ## Deletes a role definition.
+///
+/// Grants of the role are kept, but grant no permissions
+/// until the role is defined again.
+#[utoipa::path(
+    delete,
+    path = "/admin/roles/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Name of the role")),
+    responses((status = 204, description = "Role deleted"))
+)]
+pub async fn delete_role(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    authorize_write(&state, &principal)?;
+
+    let role = state
+        .role_definitions
+        .get(&name)
+        .await?
+        .ok_or_else(|| role_not_found(&name))?;
+    if !state.role_definitions.delete(&name).await? {
+        return Err(role_not_found(&name));
+    }
+    audit(&state, &principal, client, AuditEvent::RoleDeleted, &role).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Lists the roles of a user.
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}/roles",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "ID of the user")),
+    responses((status = 200, description = "Roles of the user", body = Vec<UserRole>))
+)]
+pub async fn list_user_roles(
+    State(state): State<AdminState>,
+    principal: Principal,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<UserRole>>, AppError> {
+    let user = state.users.get(id).await?;
+    state.policy.authorize(
+        &principal,
+        Permission::ManageRoles,
+        user.org_id,
+        Channel::Rest,
+    )?;
+
+    let roles = state
+        .roles
+        .list_user(id)
+        .await?
+        .into_iter()
+        .map(|grant| UserRole {
+            role: grant.role,
+            source: grant.source.to_string(),
+        })
+        .collect();
+
+    Ok(Json(roles))
+}
+
+/// ## Grants a role to a user.
+///
+/// Role has to be built in or defined, and may only
+/// hold permissions the principal holds itself. The
+/// `admin` role is global and is never granted within
+/// an organization.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/roles",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "ID of the user")),
+    request_body = AssignRoleRequest,
+    responses((status = 204, description = "Role granted"))
+)]
+pub async fn assign_role(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<AssignRoleRequest>,
+) -> Result<StatusCode, AppError> {
+    let role = request.role;
+    let permissions = role_permissions(&state, &role)
+        .await?
+        .ok_or_else(|| role_not_found(&role))?;
+    authorize_grant(&state, &principal, id, &role, permissions).await?;
+
+    if state.roles.grant(id, &role, RoleSource::Local).await? {
+        let details = serde_json::json!({ "channel": "rest", "role": role });
+        record(
+            &state,
+            &principal,
+            client,
+            AuditEvent::RoleGranted,
+            Some(id),
+            details,
+        )
+        .await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Revokes a role of a user.
+///
+/// Only grants made by administrators are revoked,
+/// directory grants are managed by the group sync.
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}/roles/{role}",
+    tag = "admin",
+    params(
+        ("id" = Uuid, Path, description = "ID of the user"),
+        ("role" = String, Path, description = "Name of the role")
+    ),
+    responses((status = 204, description = "Role revoked"))
+)]
+pub async fn revoke_role(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Path((id, role)): Path<(Uuid, String)>,
+) -> Result<StatusCode, AppError> {
+    let permissions = role_permissions(&state, &role).await?.unwrap_or_default();
+    authorize_grant(&state, &principal, id, &role, permissions).await?;
+
+    if !state.roles.revoke(id, &role, RoleSource::Local).await? {
+        return Err(AppError::new(
+            ErrorKind::NotFound,
+            format!("User '{}' has no role '{}'", id, role),
+            None,
+        ));
+    }
+    let details = serde_json::json!({ "channel": "rest", "role": role });
+    record(
+        &state,
+        &principal,
+        client,
+        AuditEvent::RoleRevoked,
+        Some(id),
+        details,
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Authorizes reading the role definitions.
+///
+/// Principals managing roles in any organization may
+/// read them, others are denied and audited.
+fn authorize_read(state: &AdminState, principal: &Principal) -> Result<(), AppError> {
+    if state.policy.scope(principal, Permission::ManageRoles) != Scope::Orgs(Default::default()) {
+        return Ok(());
+    }
+
+    state
+        .policy
+        .authorize(principal, Permission::ManageRoles, None, Channel::Rest)
+}
+
+/// ## Authorizes changing the role definitions.
+fn authorize_write(state: &AdminState, principal: &Principal) -> Result<(), AppError> {
+    state
+        .policy
+        .authorize(principal, Permission::ManageRoles, None, Channel::Rest)
+}
+
+/// ## Authorizes granting or revoking the role of the user.
+async fn authorize_grant(
+    state: &AdminState,
+    principal: &Principal,
+    user_id: Uuid,
+    role: &str,
+    permissions: Vec<Permission>,
+) -> Result<(), AppError> {
+    let user = state.users.get(user_id).await?;
+    let org_id = match role {
+        ADMIN_ROLE => None,
+        _ => user.org_id,
+    };
+    let grant = RoleGrant {
+        role: role.to_string(),
+        org_id,
+    };
+
+    state
+        .policy
+        .as_ref()
+        .clone()
+        .with_role(role, permissions)
+        .authorize_grant(principal, &grant, Channel::Rest)
+}
+
+/// ## Returns the permissions of a built-in or defined role.
+///
+/// Built-in roles are known to the policy, so their
+/// permissions are empty. `None` if the role is neither
+/// built in nor defined.
+async fn role_permissions(
+    state: &AdminState,
+    role: &str,
+) -> Result<Option<Vec<Permission>>, AppError> {
+    if is_builtin(role) {
+        return Ok(Some(Vec::new()));
+    }
+
+    match state.role_definitions.get(role).await? {
+        Some(role) => role
+            .permissions
+            .iter()
+            .map(|name| name.parse())
+            .collect::<Result<_, _>>()
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// ## Parses permission names, removing duplicates.
+///
+/// ## Returns
+/// + `Result<Vec<String>, AppError>`
+///     - `Ok(Vec<String>)`: Names in the order of `Permission::ALL`.
+///     - `Err(AppError)`: `roles.unknown_permission` if a name is unknown.
+pub(crate) fn parse_permissions(names: &[String]) -> Result<Vec<String>, AppError> {
+    let permissions = names
+        .iter()
+        .map(|name| name.parse())
+        .collect::<Result<HashSet<Permission>, _>>()?;
+
+    Ok(Permission::ALL
+        .into_iter()
+        .filter(|permission| permissions.contains(permission))
+        .map(|permission| permission.as_str().to_string())
+        .collect())
+}
+
+/// ## Records a change of a role definition in the audit log.
+async fn audit(
+    state: &AdminState,
+    principal: &Principal,
+    client: Option<ClientIp>,
+    event: AuditEvent,
+    role: &Role,
+) -> Result<(), AppError> {
+    let details = serde_json::json!({
+        "channel": "rest",
+        "role": role.name,
+        "version": role.version,
+        "permissions": role.permissions,
+    });
+
+    record(state, principal, client, event, None, details).await
+}
+
+/// ## Records a role change in the audit log.
+///
+/// Client address is resolved by the `client_ip`
+/// middleware from the trusted proxy headers.
+async fn record(
+    state: &AdminState,
+    principal: &Principal,
+    client: Option<ClientIp>,
+    event: AuditEvent,
+    user_id: Option<Uuid>,
+    details: serde_json::Value,
+) -> Result<(), AppError> {
+    let mut entry = AuditEntry::new(event)
+        .actor(principal.user_id)
+        .details(details);
+    if let Some(user_id) = user_id {
+        entry = entry.user(user_id);
+    }
+    if let Some(ClientIp(ip)) = client {
+        entry = entry.ip(&ip.to_string());
+    }
+
+    state.audit.record(entry).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::rbac::ORG_ADMIN_ROLE;
+    use crate::core::repo::memory::{MemoryRoleDefinitionRepo, MemoryUserRepo};
+    use crate::core::repo::RoleDefinitionRepo;
+    use crate::core::users::User;
+    use crate::core::webhooks::Webhooks;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::header::CONTENT_TYPE,
+        Router,
+    };
+    use chrono::Utc;
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    // Router with the users and role definitions in memory, other state never connects.
+    fn memory_router(users: MemoryUserRepo, roles: Arc<MemoryRoleDefinitionRepo>) -> Router {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+
+        crate::routes::admin::router(AdminState {
+            users: Arc::new(users),
+            role_definitions: roles,
+            ..AdminState::new(pool.clone(), Webhooks::new(pool, &Default::default()))
+        })
+    }
+
+    fn user(org_id: Uuid) -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: format!("{}@example.com", Uuid::new_v4()),
+            display_name: None,
+            org_id: Some(org_id),
+            disabled: false,
+            password_reset_required: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_at_display: None,
+            updated_at_display: None,
+        }
+    }
+
+    fn principal(grant: RoleGrant) -> Principal {
+        Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![grant],
+        }
+    }
+
+    async fn send(
+        router: &Router,
+        method: &str,
+        uri: &str,
+        principal: &Principal,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        let body = match body {
+            Some(body) => {
+                request = request.header(CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        let mut request = request.body(body).unwrap();
+        request.extensions_mut().insert(principal.clone());
+
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+        )
+    }
+
+    // Test checks if org admins read the definitions but only global admins change them.
+    #[tokio::test]
+    async fn test_roles_authorization() {
+        let roles = Arc::new(MemoryRoleDefinitionRepo::default());
+        let router = memory_router(MemoryUserRepo::default(), roles);
+        let org_admin = principal(RoleGrant::org(ORG_ADMIN_ROLE, Uuid::new_v4()));
+        let viewer = principal(RoleGrant::global("viewer"));
+        let create = serde_json::json!({ "name": "support" });
+
+        let (status, body) = send(&router, "GET", "/roles", &org_admin, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!([]));
+
+        let (status, _) = send(&router, "GET", "/roles", &viewer, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send(&router, "POST", "/roles", &org_admin, Some(create)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    // Test checks if built-in names and unknown permissions can not be defined.
+    #[tokio::test]
+    async fn test_create_role_invalid() {
+        let roles = Arc::new(MemoryRoleDefinitionRepo::default());
+        let router = memory_router(MemoryUserRepo::default(), roles.clone());
+        let admin = principal(RoleGrant::global(ADMIN_ROLE));
+
+        let reserved = serde_json::json!({ "name": ORG_ADMIN_ROLE });
+        let (status, body) = send(&router, "POST", "/roles", &admin, Some(reserved)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "roles.reserved");
+
+        let unknown = serde_json::json!({
+            "name": "support",
+            "permissions": ["users.manage", "users.impersonate"],
+        });
+        let (status, body) = send(&router, "POST", "/roles", &admin, Some(unknown)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "roles.unknown_permission");
+        assert!(roles.list().await.unwrap().is_empty());
+    }
+
+    // Test checks if updates based on an outdated version are rejected.
+    #[tokio::test]
+    async fn test_update_role_version_conflict() {
+        let roles = Arc::new(MemoryRoleDefinitionRepo::default());
+        roles
+            .create(NewRole {
+                name: "support".to_string(),
+                description: None,
+                permissions: vec!["users.manage".to_string()],
+            })
+            .await
+            .unwrap();
+        let router = memory_router(MemoryUserRepo::default(), roles.clone());
+        let admin = principal(RoleGrant::global(ADMIN_ROLE));
+        let update = serde_json::json!({ "permissions": ["audit.read"], "version": 3 });
+
+        let (status, body) = send(
+            &router,
+            "PUT",
+            "/roles/support",
+            &admin,
+            Some(update.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "roles.version_conflict");
+        assert_eq!(
+            roles.get("support").await.unwrap().unwrap().permissions,
+            ["users.manage"]
+        );
+
+        let (status, _) = send(&router, "PUT", "/roles/unknown", &admin, Some(update)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    // Test checks if org admins can not grant global, undefined or foreign roles.
+    #[tokio::test]
+    async fn test_assign_role_authorization() {
+        let org_id = Uuid::new_v4();
+        let member = user(org_id);
+        let outsider = user(Uuid::new_v4());
+        let users = MemoryUserRepo::default();
+        users.insert(member.clone(), None);
+        users.insert(outsider.clone(), None);
+        let roles = Arc::new(MemoryRoleDefinitionRepo::default());
+        roles
+            .create(NewRole {
+                name: "support".to_string(),
+                description: None,
+                permissions: vec!["users.manage".to_string()],
+            })
+            .await
+            .unwrap();
+        let router = memory_router(users, roles);
+        let org_admin = principal(RoleGrant::org(ORG_ADMIN_ROLE, org_id));
+        let member_roles = format!("/users/{}/roles", member.id);
+
+        let admin = serde_json::json!({ "role": ADMIN_ROLE });
+        let (status, _) = send(&router, "POST", &member_roles, &org_admin, Some(admin)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let undefined = serde_json::json!({ "role": "auditor" });
+        let (status, _) = send(&router, "POST", &member_roles, &org_admin, Some(undefined)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let support = serde_json::json!({ "role": "support" });
+        let uri = format!("/users/{}/roles", outsider.id);
+        let (status, _) = send(&router, "POST", &uri, &org_admin, Some(support)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let uri = format!("/users/{}/roles/support", outsider.id);
+        let (status, _) = send(&router, "DELETE", &uri, &org_admin, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
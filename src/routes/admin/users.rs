@@ -0,0 +1,477 @@
+//! User administration routes.
+//!
+//! Org admins only see and manage the users of their
+//! organizations, global admins manage every user.
+
+// Imports from external crates
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+// Local imports
+use super::AdminState;
+use crate::core::audit::{AuditEntry, AuditEvent};
+use crate::core::auth::rbac::{Channel, Permission, Principal, Scope};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::client_ip::ClientIp;
+use crate::core::http::locale::Localizer;
+use crate::core::http::pagination::{Paginated, Pagination};
+use crate::core::users::{User, UserFilter, USER_SORTING};
+use crate::core::webhooks::WebhookEvent;
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::users::{ListUsersQuery, UserPage};
+
+/// ## Declares the user administration routes.
+pub fn routes() -> Routes<AdminState> {
+    Routes::new()
+        .get("/users", AuthPolicy::Admin, list_users)
+        .get("/users/:id", AuthPolicy::Admin, get_user)
+        .delete("/users/:id", AuthPolicy::Admin, delete_user)
+        .post("/users/:id/disable", AuthPolicy::Admin, disable_user)
+        .post(
+            "/users/:id/password-reset",
+            AuthPolicy::Admin,
+            force_password_reset,
+        )
+}
+
+/// ## Lists users.
+///
+/// Users are paged by `page`, or by `cursor` when it is
+/// sent, and counted on every page.
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    tag = "admin",
+    params(ListUsersQuery),
+    responses((status = 200, description = "Page of users", body = Paginated<User>))
+)]
+pub async fn list_users(
+    State(state): State<AdminState>,
+    principal: Principal,
+    localizer: Localizer,
+    pagination: Pagination,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<UserPage>, AppError> {
+    let scope = state.policy.scope(&principal, Permission::ManageUsers);
+
+    // Principals without any scope are denied and
+    // audited instead of seeing an empty list
+    if scope == Scope::Orgs(Default::default()) {
+        state.policy.authorize(
+            &principal,
+            Permission::ManageUsers,
+            query.org_id,
+            Channel::Rest,
+        )?;
+    }
+
+    let window = pagination.window(&USER_SORTING)?;
+    let filter = UserFilter {
+        email: query.email,
+        org_id: query.org_id,
+        disabled: query.disabled,
+    };
+
+    let (rows, total) = state.users.list(&filter, &scope, &window).await?;
+    let page = UserPage {
+        total: Some(total),
+        ..window.paginate(rows)
+    };
+
+    Ok(Json(localizer.apply(page)))
+}
+
+/// ## Returns a user.
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "ID of the user")),
+    responses((status = 200, description = "User", body = User))
+)]
+pub async fn get_user(
+    State(state): State<AdminState>,
+    principal: Principal,
+    localizer: Localizer,
+    Path(id): Path<Uuid>,
+) -> Result<Json<User>, AppError> {
+    let user = authorized_user(&state, &principal, id).await?;
+
+    Ok(Json(localizer.apply(user)))
+}
+
+/// ## Disables a user.
+///
+/// Disabled users can not log in until enabled again.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/disable",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "ID of the user")),
+    responses((status = 200, description = "Disabled user", body = User))
+)]
+pub async fn disable_user(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    localizer: Localizer,
+    Path(id): Path<Uuid>,
+) -> Result<Json<User>, AppError> {
+    reject_self(&principal, id, "disable")?;
+    authorized_user(&state, &principal, id).await?;
+
+    let user = state.users.set_disabled(id, true).await?;
+    audit(&state, &principal, client, AuditEvent::UserDisabled, id).await?;
+    notify(&state, WebhookEvent::UserDisabled, &user).await?;
+
+    Ok(Json(localizer.apply(user)))
+}
+
+/// ## Forces a password reset.
+///
+/// User has to change the password on the next login.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/password-reset",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "ID of the user")),
+    responses((status = 200, description = "Updated user", body = User))
+)]
+pub async fn force_password_reset(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    localizer: Localizer,
+    Path(id): Path<Uuid>,
+) -> Result<Json<User>, AppError> {
+    authorized_user(&state, &principal, id).await?;
+
+    let user = state.users.require_password_reset(id).await?;
+    audit(
+        &state,
+        &principal,
+        client,
+        AuditEvent::PasswordResetForced,
+        id,
+    )
+    .await?;
+
+    Ok(Json(localizer.apply(user)))
+}
+
+/// ## Deletes a user.
+///
+/// User is soft deleted and removed by the purge job
+/// once `jobs.purge_after` has passed.
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "ID of the user")),
+    responses((status = 204, description = "User deleted"))
+)]
+pub async fn delete_user(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    reject_self(&principal, id, "delete")?;
+    let user = authorized_user(&state, &principal, id).await?;
+
+    state.users.delete(id).await?;
+    audit(&state, &principal, client, AuditEvent::UserDeleted, id).await?;
+    notify(&state, WebhookEvent::UserDeleted, &user).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Loads the user and authorizes the principal to manage it.
+async fn authorized_user(
+    state: &AdminState,
+    principal: &Principal,
+    id: Uuid,
+) -> Result<User, AppError> {
+    let user = state.users.get(id).await?;
+
+    state.policy.authorize(
+        principal,
+        Permission::ManageUsers,
+        user.org_id,
+        Channel::Rest,
+    )?;
+
+    Ok(user)
+}
+
+/// ## Rejects actions that would lock the principal out.
+fn reject_self(principal: &Principal, id: Uuid, action: &str) -> Result<(), AppError> {
+    if principal.user_id != id {
+        return Ok(());
+    }
+
+    Err(AppError::new(
+        ErrorKind::Forbidden,
+        format!("Administrators can not {} their own account", action),
+        None,
+    ))
+}
+
+/// ## Records an administrative change in the audit log.
+///
+/// Client address is resolved by the `client_ip`
+/// middleware from the trusted proxy headers.
+async fn audit(
+    state: &AdminState,
+    principal: &Principal,
+    client: Option<ClientIp>,
+    event: AuditEvent,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    let mut entry = AuditEntry::new(event)
+        .actor(principal.user_id)
+        .user(user_id)
+        .details(serde_json::json!({ "channel": "rest" }));
+    if let Some(ClientIp(ip)) = client {
+        entry = entry.ip(&ip.to_string());
+    }
+
+    state.audit.record(entry).await
+}
+
+/// ## Queues the lifecycle event of the user for the webhooks.
+async fn notify(state: &AdminState, event: WebhookEvent, user: &User) -> Result<(), AppError> {
+    let data = serde_json::json!({
+        "user_id": user.id,
+        "email": user.email,
+        "org_id": user.org_id,
+    });
+
+    state.webhooks.emit(event, data).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::MAX_PER_PAGE;
+    use crate::core::auth::rbac::{RoleGrant, ADMIN_ROLE};
+    use crate::core::repo::memory::MemoryUserRepo;
+    use crate::core::webhooks::Webhooks;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        response::Response,
+        Router,
+    };
+    use chrono::Utc;
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    // Router backed by a pool that never connects.
+    fn test_router() -> Router {
+        memory_router(MemoryUserRepo::default())
+    }
+
+    // Router with the users in memory, other state never connects.
+    fn memory_router(users: MemoryUserRepo) -> Router {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+
+        crate::routes::admin::router(AdminState {
+            users: Arc::new(users),
+            ..AdminState::new(pool.clone(), Webhooks::new(pool, &Default::default()))
+        })
+    }
+
+    fn user(email: &str, org_id: Uuid) -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: email.to_string(),
+            display_name: None,
+            org_id: Some(org_id),
+            disabled: false,
+            password_reset_required: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_at_display: None,
+            updated_at_display: None,
+        }
+    }
+
+    async fn request(
+        router: Router,
+        uri: &str,
+        method: &str,
+        principal: Option<Principal>,
+    ) -> Response {
+        let mut request = Request::builder()
+            .uri(uri)
+            .method(method)
+            .body(Body::empty())
+            .unwrap();
+        if let Some(principal) = principal {
+            request.extensions_mut().insert(principal);
+        }
+
+        router.oneshot(request).await.unwrap()
+    }
+
+    async fn send(uri: &str, method: &str, principal: Option<Principal>) -> StatusCode {
+        request(test_router(), uri, method, principal)
+            .await
+            .status()
+    }
+
+    // Test checks if the page is clamped to valid values.
+    #[tokio::test]
+    async fn test_list_users_query_page() {
+        let users = MemoryUserRepo::default();
+        let org_id = Uuid::new_v4();
+        users.insert(user("ada@example.com", org_id), None);
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![RoleGrant::global(ADMIN_ROLE)],
+        };
+
+        let response = request(
+            memory_router(users),
+            "/users?page=0&per_page=10000",
+            "GET",
+            Some(principal),
+        )
+        .await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let page: UserPage = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!((page.page, page.per_page), (Some(1), MAX_PER_PAGE));
+        assert_eq!(page.total, Some(1));
+    }
+
+    // Test checks if cursors page through users that share a sort key.
+    #[tokio::test]
+    async fn test_list_users_cursor() {
+        let users = MemoryUserRepo::default();
+        let org_id = Uuid::new_v4();
+        let created_at = Utc::now();
+        for email in ["a@example.com", "b@example.com", "c@example.com"] {
+            users.insert(
+                User {
+                    created_at,
+                    ..user(email, org_id)
+                },
+                None,
+            );
+        }
+        let router = memory_router(users);
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![RoleGrant::global(ADMIN_ROLE)],
+        };
+        let mut uri = "/users?per_page=2&cursor=".to_string();
+        let mut emails = Vec::new();
+
+        loop {
+            let response = request(router.clone(), &uri, "GET", Some(principal.clone())).await;
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let page: UserPage = serde_json::from_slice(&body).unwrap();
+            emails.extend(page.items.into_iter().map(|user| user.email));
+
+            match page.next_cursor {
+                Some(cursor) => uri = format!("/users?per_page=2&cursor={}", cursor),
+                None => break,
+            }
+        }
+        emails.sort();
+
+        assert_eq!(emails, ["a@example.com", "b@example.com", "c@example.com"]);
+
+        let response = request(router, "/users?sort=password", "GET", Some(principal)).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Test checks if unauthenticated requests are rejected.
+    #[tokio::test]
+    async fn test_list_users_unauthenticated() {
+        assert_eq!(send("/users", "GET", None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    // Test checks if principals without the admin role are forbidden.
+    #[tokio::test]
+    async fn test_list_users_forbidden() {
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![RoleGrant::global("viewer")],
+        };
+
+        assert_eq!(
+            send("/users", "GET", Some(principal)).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    // Test checks if admins can not delete or disable themselves.
+    #[tokio::test]
+    async fn test_reject_self() {
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![RoleGrant::global(ADMIN_ROLE)],
+        };
+        let uri = format!("/users/{}", principal.user_id);
+
+        assert_eq!(
+            send(&uri, "DELETE", Some(principal.clone())).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            send(&format!("{}/disable", uri), "POST", Some(principal)).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    // Test checks if org admins only list and load the users of their organization.
+    #[tokio::test]
+    async fn test_users_scoped_to_org() {
+        let org_id = Uuid::new_v4();
+        let member = user("member@example.com", org_id);
+        let outsider = user("outsider@example.com", Uuid::new_v4());
+        let users = MemoryUserRepo::default();
+        users.insert(member.clone(), None);
+        users.insert(outsider.clone(), None);
+        let router = memory_router(users);
+        let principal = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![RoleGrant::org(ADMIN_ROLE, org_id)],
+        };
+
+        let response = request(router.clone(), "/users", "GET", Some(principal.clone())).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let page: UserPage = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(page.items, vec![member.clone()]);
+        assert_eq!(page.total, Some(1));
+
+        let uri = format!("/users/{}", member.id);
+        let response = request(router.clone(), &uri, "GET", Some(principal.clone())).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let uri = format!("/users/{}", outsider.id);
+        let response = request(router.clone(), &uri, "GET", Some(principal.clone())).await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let uri = format!("/users/{}", Uuid::new_v4());
+        let response = request(router, &uri, "GET", Some(principal)).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
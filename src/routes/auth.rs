@@ -0,0 +1,136 @@
+//! Authentication routes.
+//!
+//! Browser clients of cookie sessions fetch the CSRF
+//! token of their session from `/auth/csrf`, the token is
+//! returned in the body and set as the CSRF cookie.
+
+// Imports from external crates
+use axum::{
+    extract::State,
+    http::header::{CACHE_CONTROL, SET_COOKIE},
+    response::IntoResponse,
+    Json, Router,
+};
+use std::sync::Arc;
+
+// Local imports
+use crate::core::http::csrf::Csrf;
+use crate::core::http::principal::{Authenticator, CurrentSession};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::sessions::CsrfToken;
+
+/// ## Declares the authentication routes.
+///
+/// ## Returns
+/// - `Routes<Arc<Csrf>>`: Routes to nest under `/auth`.
+pub fn routes() -> Routes<Arc<Csrf>> {
+    Routes::new().get("/csrf", AuthPolicy::User, csrf_token)
+}
+
+/// ## Builds the authentication router.
+///
+/// ## Parameters
+/// - `csrf`: `Arc<Csrf>` - Issuer of the CSRF tokens.
+/// - `authenticator`: `Arc<Authenticator>` - Verifier of the sessions.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `/auth`.
+pub fn router(csrf: Arc<Csrf>, authenticator: Arc<Authenticator>) -> Router {
+    routes().router(Some(&authenticator)).with_state(csrf)
+}
+
+/// ## Returns the CSRF token of the session.
+///
+/// State-changing requests of the session must echo
+/// the token in the CSRF header.
+#[utoipa::path(
+    get,
+    path = "/auth/csrf",
+    tag = "auth",
+    responses((status = 200, description = "CSRF token of the session", body = CsrfToken))
+)]
+pub async fn csrf_token(
+    State(csrf): State<Arc<Csrf>>,
+    session: CurrentSession,
+) -> impl IntoResponse {
+    let token = csrf.token(session.0);
+
+    (
+        [
+            (SET_COOKIE, csrf.set_cookie(&token)),
+            (CACHE_CONTROL, "no-store".to_string()),
+        ],
+        Json(CsrfToken { token }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::{jwt::SigningKey, keys::KeyManager};
+    use crate::core::config::{AuthSettings, SameSite, TokenSettings};
+    use crate::core::http::cookies::Cookies;
+    use crate::core::repo::{memory::MemorySessionRepo, NewSession, SessionRepo};
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{header::COOKIE, StatusCode},
+    };
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    // Test checks if the token of the cookie session is returned and set as cookie.
+    #[tokio::test]
+    async fn test_csrf_token() {
+        let settings = AuthSettings::default();
+        let tokens = TokenSettings::default();
+        let key = SigningKey::generate(&tokens.key_id).unwrap();
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let session = sessions
+            .create(NewSession {
+                user_id: Uuid::new_v4(),
+                ip: None,
+                user_agent: None,
+                expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+            })
+            .await
+            .unwrap();
+        let cookies =
+            Arc::new(Cookies::from_secret(b"cookie-secret", true, SameSite::Lax).unwrap());
+        let csrf = Arc::new(Csrf::new(&settings, cookies.clone()).unwrap());
+        let authenticator = Authenticator::new(Arc::new(KeyManager::new(key)), &tokens, sessions)
+            .with_session_cookie(&settings.session_cookie, cookies.clone());
+        let session_cookie = cookies.signed(&settings.session_cookie, &session.id.to_string());
+        let app = router(csrf.clone(), Arc::new(authenticator));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/csrf")
+                    .header(COOKIE, format!("session={}", session_cookie.value()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = response.headers()[SET_COOKIE].to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let token: CsrfToken = serde_json::from_slice(&body).unwrap();
+
+        assert!(csrf.verify(session.id, &token.token));
+        assert_eq!(cookie, csrf.set_cookie(&token.token));
+
+        let anonymous = app
+            .oneshot(Request::get("/csrf").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,118 @@
+//! Development routes.
+//!
+//! Only mounted when `app.env` is a development
+//! environment. `/dev/mailbox` lists the emails the
+//! server would have sent, so links and tokens can be
+//! followed without a mail service.
+
+// Imports from external crates
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+
+// Local imports
+use crate::core::mail::{Email, MailCapture};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::mail::MailboxQuery;
+
+/// Path of the captured mail.
+pub const MAILBOX_PATH: &str = "/dev/mailbox";
+
+/// ## Declares the development routes.
+pub fn routes() -> Routes<MailCapture> {
+    Routes::new()
+        .get(MAILBOX_PATH, AuthPolicy::Public, mailbox)
+        .delete(MAILBOX_PATH, AuthPolicy::Public, clear_mailbox)
+}
+
+/// ## Lists the captured emails.
+///
+/// Emails are returned newest first, only the latest
+/// hundred are kept.
+#[utoipa::path(
+    get,
+    path = "/dev/mailbox",
+    tag = "dev",
+    params(MailboxQuery),
+    responses((status = 200, description = "Captured emails", body = Vec<Email>))
+)]
+pub async fn mailbox(
+    State(capture): State<MailCapture>,
+    Query(query): Query<MailboxQuery>,
+) -> Json<Vec<Email>> {
+    let emails = capture
+        .list()
+        .into_iter()
+        .filter(|email| {
+            query
+                .to
+                .as_ref()
+                .is_none_or(|to| email.to.eq_ignore_ascii_case(to))
+        })
+        .collect();
+
+    Json(emails)
+}
+
+/// ## Deletes the captured emails.
+#[utoipa::path(
+    delete,
+    path = "/dev/mailbox",
+    tag = "dev",
+    responses((status = 204, description = "Mailbox was emptied"))
+)]
+pub async fn clear_mailbox(State(capture): State<MailCapture>) -> StatusCode {
+    capture.clear();
+
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mail::{render, Mailer};
+    use crate::core::webhooks::WebhookEvent;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    // Test checks if the captured emails are listed by recipient and cleared
+    #[tokio::test]
+    async fn test_mailbox() {
+        let capture = MailCapture::default();
+        for email in ["ada@example.com", "bob@example.com"] {
+            let data = serde_json::json!({ "email": email, "token": "token" });
+            let email = render(WebhookEvent::EmailChangeRequested, &data).unwrap();
+            capture.send(email).await.unwrap();
+        }
+        let router = routes().router(None).with_state(capture.clone());
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/dev/mailbox?to=ADA@example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let emails: Vec<Email> = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].to, "ada@example.com");
+
+        let response = router
+            .oneshot(Request::delete("/dev/mailbox").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(capture.list().is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+//! Device verification routes.
+//!
+//! Page of the OAuth device authorization grant. The
+//! signed in user looks up the user code shown by a
+//! device, sees which client asks for which scopes and
+//! approves or denies it. The device then gets its token,
+//! or `access_denied`, on its next poll of `/oauth/token`.
+
+// Imports from external crates
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json, Router,
+};
+use std::sync::Arc;
+
+// Local imports
+use crate::core::auth::rbac::Principal;
+use crate::core::db::DbExecutor;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::principal::Authenticator;
+use crate::core::repo::postgres::{PgClientRepo, PgDeviceCodeRepo};
+use crate::core::repo::{ClientRepo, DeviceCode, DeviceCodeRepo};
+use crate::routes::oauth::{format_user_code, normalize_user_code};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::oauth::{
+    DeviceVerification, DeviceVerificationQuery, DeviceVerificationRequest,
+};
+
+/// ## Device verification state struct.
+///
+/// ## Fields
+/// + `device_codes`: `Arc<dyn DeviceCodeRepo>` - Codes of the device grant.
+/// + `clients`: `Arc<dyn ClientRepo>` - Clients asking for access.
+#[derive(Clone)]
+pub struct DeviceState {
+    pub device_codes: Arc<dyn DeviceCodeRepo>,
+    pub clients: Arc<dyn ClientRepo>,
+}
+
+impl DeviceState {
+    /// ## Creates a new `DeviceState` backed by Postgres.
+    ///
+    /// ## Parameters
+    /// - `db`: `impl Into<DbExecutor>` - Executor or pool of the repositories.
+    pub fn new(db: impl Into<DbExecutor>) -> Self {
+        let db = db.into();
+
+        DeviceState {
+            device_codes: Arc::new(PgDeviceCodeRepo::new(db.write().clone())),
+            clients: Arc::new(PgClientRepo::new(db)),
+        }
+    }
+}
+
+/// ## Declares the device verification routes.
+///
+/// ## Returns
+/// - `Routes<DeviceState>`: Routes to nest under `/device`.
+pub fn routes() -> Routes<DeviceState> {
+    Routes::new()
+        .get("/", AuthPolicy::User, show_device)
+        .post("/", AuthPolicy::User, verify_device)
+}
+
+/// ## Builds the device verification router.
+///
+/// ## Parameters
+/// - `state`: `DeviceState` - Shared state of the routes.
+/// - `authenticator`: `Arc<Authenticator>` - Verifier of the user's credentials.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `/device`.
+pub fn router(state: DeviceState, authenticator: Arc<Authenticator>) -> Router {
+    routes().router(Some(&authenticator)).with_state(state)
+}
+
+/// ## Shows the device asking for access.
+#[utoipa::path(
+    get,
+    path = "/device",
+    tag = "oauth",
+    params(DeviceVerificationQuery),
+    responses(
+        (status = 200, description = "Client and scopes of the device", body = DeviceVerification),
+        (status = 404, description = "User code is invalid or expired")
+    )
+)]
+pub async fn show_device(
+    State(state): State<DeviceState>,
+    _principal: Principal,
+    Query(query): Query<DeviceVerificationQuery>,
+) -> Result<Json<DeviceVerification>, AppError> {
+    let code = pending(&state, &query.user_code).await?;
+    let client_name = state
+        .clients
+        .get(&code.client_id)
+        .await?
+        .map(|client| client.name)
+        .unwrap_or_else(|| code.client_id.clone());
+
+    Ok(Json(DeviceVerification {
+        user_code: format_user_code(&code.user_code),
+        client_id: code.client_id,
+        client_name,
+        scopes: code.scopes,
+        expires_at: code.expires_at,
+    }))
+}
+
+/// ## Approves or denies the device.
+///
+/// A user code is decided once, by the signed in user.
+#[utoipa::path(
+    post,
+    path = "/device",
+    tag = "oauth",
+    request_body = DeviceVerificationRequest,
+    responses(
+        (status = 204, description = "Decision recorded, the device learns it on its next poll"),
+        (status = 404, description = "User code is invalid, expired or already decided")
+    )
+)]
+pub async fn verify_device(
+    State(state): State<DeviceState>,
+    principal: Principal,
+    Json(request): Json<DeviceVerificationRequest>,
+) -> Result<StatusCode, AppError> {
+    let code = pending(&state, &request.user_code).await?;
+    if !state
+        .device_codes
+        .decide(code.id, principal.user_id, request.approve)
+        .await?
+    {
+        return Err(code_not_found());
+    }
+    tracing::info!(
+        client_id = %code.client_id,
+        user_id = %principal.user_id,
+        approved = request.approve,
+        "Decided device code"
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Returns the pending code of the entered user code.
+async fn pending(state: &DeviceState, user_code: &str) -> Result<DeviceCode, AppError> {
+    state
+        .device_codes
+        .find_pending(&normalize_user_code(user_code))
+        .await?
+        .ok_or_else(code_not_found)
+}
+
+/// ## Builds the error of an unknown or decided user code.
+fn code_not_found() -> AppError {
+    AppError::new(
+        ErrorKind::NotFound,
+        "User code is invalid or expired".to_string(),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::{jwt::SigningKey, keys::KeyManager};
+    use crate::core::config::TokenSettings;
+    use crate::core::repo::memory::{MemoryClientRepo, MemoryDeviceCodeRepo, MemorySessionRepo};
+    use crate::core::repo::{DeviceCodeStatus, NewClient, NewDeviceCode, NewSession, SessionRepo};
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::header::{AUTHORIZATION, CONTENT_TYPE},
+    };
+    use chrono::{Duration, Utc};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    // Verification routes with a pending code of the "tv" client and a signed in user.
+    async fn fixture() -> (Router, Arc<MemoryDeviceCodeRepo>, Uuid, String) {
+        let settings = TokenSettings::default();
+        let key = SigningKey::generate(&settings.key_id).unwrap();
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let clients = Arc::new(MemoryClientRepo::default());
+        let device_codes = Arc::new(MemoryDeviceCodeRepo::default());
+
+        clients
+            .create(NewClient {
+                client_id: "tv".to_string(),
+                secret: "tv-secret".to_string(),
+                name: "Living room TV".to_string(),
+                scopes: vec!["profile".to_string()],
+            })
+            .await
+            .unwrap();
+        device_codes
+            .create(NewDeviceCode {
+                client_id: "tv".to_string(),
+                device_code: "device-code".to_string(),
+                user_code: "BCDFGHJK".to_string(),
+                scopes: vec!["profile".to_string()],
+                expires_at: Utc::now() + Duration::minutes(10),
+            })
+            .await
+            .unwrap();
+
+        let user_id = Uuid::new_v4();
+        let session = sessions
+            .create(NewSession {
+                user_id,
+                ip: None,
+                user_agent: None,
+                expires_at: Utc::now() + Duration::hours(1),
+            })
+            .await
+            .unwrap();
+        let token = key
+            .issue_for_session(&settings, user_id, session.id, &[])
+            .unwrap();
+
+        let state = DeviceState {
+            device_codes: device_codes.clone(),
+            clients,
+        };
+        let authenticator = Arc::new(Authenticator::new(
+            Arc::new(KeyManager::new(key)),
+            &settings,
+            sessions,
+        ));
+
+        (router(state, authenticator), device_codes, user_id, token)
+    }
+
+    async fn send(router: &Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    // Test checks if the entered user code shows the client and is approved once.
+    #[tokio::test]
+    async fn test_verify_device() {
+        let (router, device_codes, user_id, token) = fixture().await;
+        let bearer = format!("Bearer {}", token);
+
+        let (status, body) = send(
+            &router,
+            Request::get("/?user_code=bcdf-ghjk")
+                .header(AUTHORIZATION, &bearer)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["user_code"], "BCDF-GHJK");
+        assert_eq!(body["client_name"], "Living room TV");
+        assert_eq!(body["scopes"], serde_json::json!(["profile"]));
+
+        let approve = || {
+            Request::post("/")
+                .header(AUTHORIZATION, &bearer)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"user_code": "BCDF GHJK", "approve": true}"#))
+                .unwrap()
+        };
+        assert_eq!(send(&router, approve()).await.0, StatusCode::NO_CONTENT);
+        assert_eq!(send(&router, approve()).await.0, StatusCode::NOT_FOUND);
+
+        let code = device_codes
+            .poll("tv", "device-code")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(code.status, DeviceCodeStatus::Approved);
+        assert_eq!(code.user_id, Some(user_id));
+    }
+
+    // Test checks if only signed in users see devices.
+    #[tokio::test]
+    async fn test_verify_device_requires_login() {
+        let (router, _, _, _) = fixture().await;
+
+        let (status, _) = send(
+            &router,
+            Request::get("/?user_code=BCDFGHJK")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,68 @@
+//! Health check routes.
+
+// Imports from external crates
+use axum::{extract::State, http::StatusCode, Json, Router};
+use std::sync::Arc;
+
+// Local imports
+use crate::core::health::{HealthChecker, HealthReport, HealthStatus};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+/// Path of the liveness probe.
+pub const HEALTHZ_PATH: &str = "/healthz";
+
+/// Path of the detailed health report.
+pub const HEALTHZ_DETAILS_PATH: &str = "/healthz/details";
+
+/// ## Declares the health check routes.
+pub fn routes() -> Routes<Arc<HealthChecker>> {
+    Routes::new()
+        .get(HEALTHZ_PATH, AuthPolicy::Public, healthz)
+        .get(HEALTHZ_DETAILS_PATH, AuthPolicy::Public, healthz_details)
+}
+
+/// ## Builds the health check router.
+///
+/// ## Parameters
+/// - `checker`: `Arc<HealthChecker>` - Checker of the detailed report.
+pub fn router(checker: Arc<HealthChecker>) -> Router {
+    routes().router(None).with_state(checker)
+}
+
+/// ## Liveness probe.
+///
+/// Responds as long as the server accepts requests.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "Server is running", body = String, example = "ok"))
+)]
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// ## Detailed health report.
+///
+/// Reports the database and every configured integration.
+/// Results are cached for `health.cache_ttl` seconds.
+#[utoipa::path(
+    get,
+    path = "/healthz/details",
+    tag = "health",
+    responses(
+        (status = 200, description = "Every critical check passed", body = HealthReport),
+        (status = 503, description = "A critical check failed", body = HealthReport)
+    )
+)]
+pub async fn healthz_details(
+    State(checker): State<Arc<HealthChecker>>,
+) -> (StatusCode, Json<HealthReport>) {
+    let report = checker.report().await;
+    let status = match report.status {
+        HealthStatus::Up => StatusCode::OK,
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status, Json(report))
+}
@@ -0,0 +1,89 @@
+//! JSON Web Key Set route.
+
+// Imports from external crates
+use axum::{
+    extract::State,
+    http::header::CACHE_CONTROL,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use std::sync::Arc;
+
+// Local imports
+use crate::core::auth::keys::KeyManager;
+use crate::core::err::AppError;
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::tokens::{Jwk, JwkSet};
+
+/// Path of the key set.
+pub const JWKS_PATH: &str = "/.well-known/jwks.json";
+
+/// Seconds verifiers may cache the key set.
+const MAX_AGE: u64 = 300;
+
+/// ## Declares the key set routes.
+pub fn routes() -> Routes<Arc<KeyManager>> {
+    Routes::new().get(JWKS_PATH, AuthPolicy::Public, jwks)
+}
+
+/// ## Builds the key set router.
+///
+/// ## Parameters
+/// - `keys`: `Arc<KeyManager>` - Keys of the access tokens.
+pub fn router(keys: Arc<KeyManager>) -> Router {
+    routes().router(None).with_state(keys)
+}
+
+/// ## Public keys of the access tokens.
+///
+/// Services verifying access tokens fetch the key named
+/// by the `kid` header of a token from this set. Keys
+/// retired by a rotation stay in it until their grace
+/// period is over.
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    tag = "meta",
+    responses((status = 200, description = "Keys access tokens are signed with", body = JwkSet))
+)]
+pub async fn jwks(State(keys): State<Arc<KeyManager>>) -> Result<Response, AppError> {
+    let keys = keys.jwks().await?;
+
+    Ok((
+        [(CACHE_CONTROL, format!("public, max-age={}", MAX_AGE))],
+        Json(keys),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::jwt::SigningKey;
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use tower::ServiceExt;
+
+    // Test checks if the public keys are served with a cache header.
+    #[tokio::test]
+    async fn test_jwks() {
+        let key = Arc::new(SigningKey::generate("2026-01").unwrap());
+        let app = router(Arc::new(KeyManager::new(key.clone())));
+
+        let response = app
+            .oneshot(Request::get(JWKS_PATH).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=300");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let keys: JwkSet = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(keys.keys, [key.jwk()]);
+    }
+}
@@ -0,0 +1,1168 @@
+//! Magic link routes.
+//!
+//! Passwordless login in two steps. `/auth/magic-link`
+//! sends a single-use link to the address through the
+//! `magic_link.requested` webhook, `/auth/magic-link/verify`
+//! exchanges the token of the link for a new session and
+//! its access token, in the `jwt` auth mode with the first
+//! refresh token of the session. Links are limited per email and per
+//! client address with the shared rate limiter. Unknown and disabled addresses
+//! get the same answer as known ones, so the route can
+//! not be used to find accounts. Clients presenting invalid
+//! tokens are slowed down and asked for a CAPTCHA by the
+//! login throttle, so are clients whose address has a poor
+//! reputation, known abusers are refused. Login windows
+//! and country allowlists of the roles and the tenant are
+//! enforced once the user is known. Users whose password
+//! expired are logged in to change it and nothing else.
+
+// Imports from external crates
+use axum::{
+    extract::State,
+    http::{
+        header::{SET_COOKIE, USER_AGENT},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::Arc,
+};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+// Local imports
+use super::me::{random_token, validate_email};
+use super::refresh::issue_refresh_token;
+use crate::core::audit::{AuditEntry, AuditEvent, AuditLog};
+use crate::core::auth::access::{AccessPolicyEngine, LoginContext};
+use crate::core::auth::anomaly::LoginAnomalies;
+use crate::core::auth::expiry::PASSWORD_CHANGE_SCOPE;
+use crate::core::auth::funnel::{LoginFailure, LoginFunnel, LoginStep};
+use crate::core::auth::issuer::TokenIssuer;
+use crate::core::auth::rate_limit::RateLimiter;
+use crate::core::auth::reputation::{ReputationGuard, ReputationVerdict};
+use crate::core::auth::throttle::LoginThrottle;
+use crate::core::config::{AppConfig, AuthMode, AuthSettings, MagicLinkSettings};
+use crate::core::db::DbExecutor;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::client_ip::ClientIp;
+use crate::core::http::cookies::Cookies;
+use crate::core::repo::postgres::{PgSessionRepo, PgTokenRepo, PgUserRepo};
+use crate::core::repo::{NewSession, NewToken, SessionRepo, TokenKind, TokenRepo, UserRepo};
+use crate::core::tenancy::current_tenant;
+use crate::core::users::User;
+use crate::core::webhooks::{WebhookEvent, Webhooks};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::sessions::{LoginResponse, MagicLinkRequest, VerifyMagicLinkRequest};
+
+/// Scope of the magic link attempts in the rate limiter.
+pub const RATE_LIMIT_SCOPE: &str = "magic_link";
+/// Scope of the magic link attempts per client address in the rate limiter.
+pub const CLIENT_RATE_LIMIT_SCOPE: &str = "magic_link.client";
+
+/// ## Magic link state struct.
+///
+/// ## Fields
+/// + `users`: `Arc<dyn UserRepo>` - User repository.
+/// + `sessions`: `Arc<dyn SessionRepo>` - Sessions created by the links.
+/// + `tokens`: `Arc<dyn TokenRepo>` - Tokens of the links.
+/// + `webhooks`: `Webhooks` - Delivery of the links.
+/// + `limiter`: `Arc<RateLimiter>` - Shared rate limiter.
+/// + `access_tokens`: `TokenIssuer` - Issuer of the access tokens.
+/// + `auth`: `AuthSettings` - Session lifetime and cookie.
+/// + `cookies`: `Arc<Cookies>` - Keys the session cookie is signed with.
+/// + `settings`: `MagicLinkSettings` - Link URL and lifetime, the rate
+///   limits are read from the limiter.
+/// + `anomalies`: `LoginAnomalies` - Reports of logins from new devices
+///   and countries.
+/// + `throttle`: `Arc<LoginThrottle>` - Delays and CAPTCHA of failing clients.
+/// + `reputation`: `Arc<ReputationGuard>` - Reputation of the client addresses.
+/// + `access`: `Arc<AccessPolicyEngine>` - Login windows and country allowlists.
+/// + `audit`: `AuditLog` - Audit log of the logins.
+#[derive(Clone)]
+pub struct MagicLinkState {
+    pub users: Arc<dyn UserRepo>,
+    pub sessions: Arc<dyn SessionRepo>,
+    pub tokens: Arc<dyn TokenRepo>,
+    pub webhooks: Webhooks,
+    pub limiter: Arc<RateLimiter>,
+    pub access_tokens: TokenIssuer,
+    pub auth: AuthSettings,
+    pub cookies: Arc<Cookies>,
+    pub settings: MagicLinkSettings,
+    pub anomalies: LoginAnomalies,
+    pub throttle: Arc<LoginThrottle>,
+    pub reputation: Arc<ReputationGuard>,
+    pub access: Arc<AccessPolicyEngine>,
+    pub audit: AuditLog,
+}
+
+impl MagicLinkState {
+    /// ## Creates a new `MagicLinkState` backed by Postgres.
+    ///
+    /// ## Parameters
+    /// - `config`: `&AppConfig` - Auth and magic link settings.
+    /// - `db`: `impl Into<DbExecutor>` - Executor or pool of the repositories.
+    /// - `webhooks`: `Webhooks` - Queue of the webhook events.
+    /// - `access_tokens`: `TokenIssuer` - Issuer of the access tokens.
+    /// - `limiter`: `Arc<RateLimiter>` - Shared rate limiter, the rules
+    ///   of the magic link scopes are set.
+    /// - `cookies`: `Arc<Cookies>` - Keys of the session cookie.
+    /// - `anomalies`: `LoginAnomalies` - Detector of login anomalies.
+    ///
+    /// ## Returns
+    /// + `Result<MagicLinkState, AppError>`
+    ///     - `Ok(MagicLinkState)`: New state.
+    ///     - `Err(AppError)`: If a blocked address of the reputation
+    ///       settings is invalid, the GeoIP database can not be opened
+    ///       or an access policy is invalid.
+    pub fn new(
+        config: &AppConfig,
+        db: impl Into<DbExecutor>,
+        webhooks: Webhooks,
+        access_tokens: TokenIssuer,
+        limiter: Arc<RateLimiter>,
+        cookies: Arc<Cookies>,
+        anomalies: LoginAnomalies,
+    ) -> Result<Self, AppError> {
+        let db = db.into();
+        let throttle = Arc::new(LoginThrottle::new(&config.login_throttle));
+        let reputation = Arc::new(ReputationGuard::from_settings(&config.reputation)?);
+        let access = Arc::new(AccessPolicyEngine::from_settings(&config.access)?);
+
+        let state = MagicLinkState {
+            users: Arc::new(PgUserRepo::new(db.clone())),
+            sessions: Arc::new(PgSessionRepo::new(db.clone()).with_outbox(config.outbox.enabled)),
+            tokens: Arc::new(PgTokenRepo::new(db.clone())),
+            webhooks,
+            limiter,
+            access_tokens,
+            auth: config.auth.clone(),
+            cookies,
+            settings: config.magic_link.clone(),
+            anomalies,
+            throttle,
+            reputation,
+            access,
+            audit: AuditLog::new(db),
+        };
+        state.apply(config);
+
+        Ok(state)
+    }
+
+    /// ## Applies the rate limits and login throttle of the configuration.
+    pub fn apply(&self, config: &AppConfig) {
+        let settings = &config.magic_link;
+        self.limiter.set_rule(RATE_LIMIT_SCOPE, settings.rate_limit);
+        self.limiter
+            .set_rule(CLIENT_RATE_LIMIT_SCOPE, settings.client_rate_limit);
+        self.throttle.apply(&config.login_throttle);
+    }
+
+    /// ## Applies every configuration published by the reload watcher.
+    ///
+    /// Function spawns a task that runs until the
+    /// watcher stops, so changed limits take effect
+    /// without a restart.
+    ///
+    /// ## Parameters
+    /// - `config`: `watch::Receiver<Arc<AppConfig>>` - Configuration in
+    ///   use, see `reload::watch`.
+    pub fn subscribe(&self, mut config: watch::Receiver<Arc<AppConfig>>) {
+        let state = self.clone();
+
+        tokio::spawn(async move {
+            while config.changed().await.is_ok() {
+                let changed = config.borrow_and_update().clone();
+                state.apply(&changed);
+            }
+        });
+    }
+}
+
+/// ## Declares the magic link routes.
+///
+/// ## Returns
+/// - `Routes<MagicLinkState>`: Routes to nest under `/auth`.
+pub fn routes() -> Routes<MagicLinkState> {
+    Routes::new()
+        .post("/magic-link", AuthPolicy::Public, request_link)
+        .post("/magic-link/verify", AuthPolicy::Public, verify_link)
+}
+
+/// ## Builds the magic link router.
+///
+/// ## Parameters
+/// - `state`: `MagicLinkState` - Shared state of the routes.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `/auth`.
+pub fn router(state: MagicLinkState) -> Router {
+    routes().router(None).with_state(state)
+}
+
+/// ## Sends a login link to the address.
+///
+/// Answer is the same whether or not the address
+/// belongs to an active user.
+#[utoipa::path(
+    post,
+    path = "/auth/magic-link",
+    tag = "auth",
+    request_body = MagicLinkRequest,
+    responses(
+        (status = 202, description = "Link sent if the address belongs to a user"),
+        (status = 429, description = "Too many links requested for the address or by the client")
+    )
+)]
+pub async fn request_link(
+    State(state): State<MagicLinkState>,
+    client: Option<ClientIp>,
+    Json(request): Json<MagicLinkRequest>,
+) -> Result<StatusCode, AppError> {
+    let email = request.email.trim();
+    validate_email(email)?;
+    if let Some(ClientIp(ip)) = client {
+        state
+            .limiter
+            .hit(CLIENT_RATE_LIMIT_SCOPE, &ip.to_string())?;
+    }
+    state.limiter.hit(RATE_LIMIT_SCOPE, &email.to_lowercase())?;
+
+    let user = state.users.find_by_email(email).await?;
+    if let Some(user) = user.filter(|user| !user.disabled) {
+        send_link(&state, &user).await?;
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// ## Logs in with the token of a link.
+///
+/// Token is used up by the first attempt. In the
+/// `session` auth mode the session cookie is set too.
+/// Invalid tokens count as failures of the client.
+/// Clients whose address scores the CAPTCHA threshold of
+/// the reputation checks have to solve one, blocked ones
+/// are refused, so are users outside the login windows or
+/// allowed countries of their roles and tenant. Logins and
+/// rejected attempts are audited, with the reputation score
+/// of the address if it reached the annotation threshold,
+/// and counted by the login funnel.
+/// Users whose password expired only get an access token
+/// for changing it, see `password_change_required`.
+#[utoipa::path(
+    post,
+    path = "/auth/magic-link/verify",
+    tag = "auth",
+    request_body = VerifyMagicLinkRequest,
+    responses(
+        (status = 200, description = "Session of the user", body = LoginResponse),
+        (status = 403, description = "CAPTCHA required after repeated failures of the client or \
+            for an address of poor reputation, the address is blocked, or the login is outside \
+            the login window or allowed countries")
+    )
+)]
+pub async fn verify_link(
+    State(state): State<MagicLinkState>,
+    client: Option<ClientIp>,
+    headers: HeaderMap,
+    Json(request): Json<VerifyMagicLinkRequest>,
+) -> Result<Response, AppError> {
+    let ip = client.map(|ClientIp(ip)| ip);
+    let mut funnel = LoginFunnel::new("magic_link");
+    if let Some(ip) = ip {
+        funnel = funnel.client(ip);
+    }
+
+    let verdict = match ip {
+        Some(ip) => {
+            let verdict = state.reputation.check(ip).await;
+            verdict.inspect_err(funnel.error(LoginStep::CaptchaOk))?
+        }
+        None => ReputationVerdict::Allow,
+    };
+    if let ReputationVerdict::Block(score) = verdict {
+        funnel.failed(LoginStep::CaptchaOk, LoginFailure::Blocked);
+        let details = serde_json::json!({
+            "method": "magic_link",
+            "reason": "reputation.blocked",
+            "reputation_score": score,
+        });
+        audit(&state, AuditEvent::LoginFailed, None, ip, details).await?;
+        return Err(AppError::new(
+            ErrorKind::Forbidden,
+            "Client address is blocked".to_string(),
+            None,
+        )
+        .with_code("reputation.blocked"));
+    }
+    if let Some(ip) = ip {
+        let captcha = matches!(verdict, ReputationVerdict::RequireCaptcha(_));
+        let checked = state
+            .throttle
+            .check(ip, request.captcha_token.as_deref(), captcha)
+            .await;
+        checked.inspect_err(|e| {
+            let reason = match e.code.starts_with("captcha.") {
+                true => LoginFailure::CaptchaFailed,
+                false => LoginFailure::Error,
+            };
+            funnel.failed(LoginStep::CaptchaOk, reason);
+        })?;
+    }
+    funnel.reached(LoginStep::CaptchaOk);
+
+    let token = state
+        .tokens
+        .find(TokenKind::MagicLink, &request.token)
+        .await
+        .inspect_err(funnel.error(LoginStep::TokenValid))?;
+    let revoked = match &token {
+        // Only the request that revokes the token may use it
+        Some(token) => state.tokens.revoke(token.id).await,
+        None => Ok(false),
+    };
+    let revoked = revoked.inspect_err(funnel.error(LoginStep::TokenValid))?;
+    let token = match (token, revoked) {
+        (Some(token), true) => token,
+        _ => return Err(invalid(&state, &funnel, ip).await),
+    };
+    if let Some(ip) = ip {
+        state.throttle.succeeded(ip);
+    }
+    funnel.identify(token.user_id);
+    funnel.reached(LoginStep::TokenValid);
+
+    let user = state
+        .users
+        .get(token.user_id)
+        .await
+        .inspect_err(funnel.error(LoginStep::TokenIssued))?;
+    if user.disabled {
+        funnel.failed(LoginStep::TokenIssued, LoginFailure::UserDisabled);
+        let details = serde_json::json!({ "method": "magic_link", "reason": "user.disabled" });
+        audit(&state, AuditEvent::LoginFailed, Some(user.id), ip, details).await?;
+        return Err(AppError::new(
+            ErrorKind::Forbidden,
+            "User is disabled".to_string(),
+            None,
+        ));
+    }
+    let access = check_access(&state, &user, ip).await;
+    access.inspect_err(|e| {
+        let reason = match e.kind {
+            ErrorKind::LoginWindowDenied | ErrorKind::CountryDenied => LoginFailure::AccessDenied,
+            _ => LoginFailure::Error,
+        };
+        funnel.failed(LoginStep::TokenIssued, reason);
+    })?;
+
+    let response = login(&state, &user, ip, &headers, verdict).await;
+    match &response {
+        Ok(_) => funnel.reached(LoginStep::TokenIssued),
+        Err(_) => funnel.failed(LoginStep::TokenIssued, LoginFailure::Error),
+    }
+
+    response
+}
+
+/// ## Opens the session of the verified link and answers with its tokens.
+async fn login(
+    state: &MagicLinkState,
+    user: &User,
+    ip: Option<IpAddr>,
+    headers: &HeaderMap,
+    verdict: ReputationVerdict,
+) -> Result<Response, AppError> {
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+    let session = state
+        .sessions
+        .create(NewSession {
+            user_id: user.id,
+            ip: ip.map(|ip| ip.to_string()),
+            user_agent: user_agent.map(str::to_string),
+            expires_at: Utc::now() + Duration::seconds(state.auth.session_ttl as i64),
+        })
+        .await?;
+    // Reports are best effort, they never fail the login
+    if let Err(e) = state.anomalies.observe(user, ip, user_agent).await {
+        tracing::warn!(user_id = %user.id, error = %e, "Failed to check login anomalies");
+    }
+    // Expired passwords are changed before anything else,
+    // the login gets neither refresh token nor cookie
+    let password_change_required =
+        user.password_reset_required && state.users.password_changed_at(user.id).await?.is_some();
+    let scopes: &[&str] = match password_change_required {
+        true => &[PASSWORD_CHANGE_SCOPE],
+        false => &[],
+    };
+    let access_token = state
+        .access_tokens
+        .issue_for_session(user.id, session.id, scopes)
+        .await?;
+    let refresh_token = match state.auth.mode {
+        AuthMode::Jwt if !password_change_required => {
+            Some(issue_refresh_token(state.tokens.as_ref(), &session).await?)
+        }
+        _ => None,
+    };
+
+    let data = serde_json::json!({
+        "user_id": user.id,
+        "email": user.email,
+        "session_id": session.id,
+        "method": "magic_link",
+    });
+    state
+        .webhooks
+        .emit(WebhookEvent::LoginSucceeded, data)
+        .await?;
+    let mut details = serde_json::json!({ "method": "magic_link", "session_id": session.id });
+    if let Some(score) = verdict.score() {
+        details["reputation_score"] = score.into();
+    }
+    audit(
+        state,
+        AuditEvent::LoginSucceeded,
+        Some(user.id),
+        ip,
+        details,
+    )
+    .await?;
+
+    let mut response = Json(LoginResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.access_tokens.settings().ttl,
+        session_id: session.id,
+        refresh_token,
+        password_change_required,
+    })
+    .into_response();
+    if state.auth.mode == AuthMode::Session && !password_change_required {
+        let cookie = state
+            .cookies
+            .signed(&state.auth.session_cookie, &session.id.to_string())
+            .max_age(state.auth.session_ttl);
+        response.headers_mut().insert(SET_COOKIE, cookie.header()?);
+    }
+
+    Ok(response)
+}
+
+/// ## Checks the login windows and allowed countries of the user.
+///
+/// Logins of unknown addresses are checked as coming
+/// from no country.
+async fn check_access(
+    state: &MagicLinkState,
+    user: &User,
+    ip: Option<IpAddr>,
+) -> Result<(), AppError> {
+    let roles = state.users.roles(user.id).await?;
+    let tenant = current_tenant();
+    let ctx = LoginContext {
+        roles: &roles,
+        tenant: Some(&tenant),
+        ip: ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        at: Utc::now(),
+    };
+    // Denials carry no source, the error is rebuilt after the audit
+    let (kind, message) = match state.access.check(&ctx) {
+        Ok(()) => return Ok(()),
+        Err(e) => (e.kind, e.message),
+    };
+
+    let reason = match kind {
+        ErrorKind::CountryDenied => "access.country",
+        _ => "access.login_window",
+    };
+    let details = serde_json::json!({ "method": "magic_link", "reason": reason });
+    audit(state, AuditEvent::LoginFailed, Some(user.id), ip, details).await?;
+
+    Err(AppError::new(kind, message, None))
+}
+
+/// ## Counts an invalid token as a failure of the client.
+///
+/// ## Returns
+/// - `AppError`: Rejection of the token, or the error of the audit
+///   log if the failure could not be recorded.
+async fn invalid(state: &MagicLinkState, funnel: &LoginFunnel, ip: Option<IpAddr>) -> AppError {
+    if let Some(ip) = ip {
+        state.throttle.failed(ip);
+    }
+    funnel.failed(LoginStep::TokenValid, LoginFailure::InvalidToken);
+    let details = serde_json::json!({ "method": "magic_link", "reason": "token.invalid" });
+
+    match audit(state, AuditEvent::LoginFailed, None, ip, details).await {
+        Ok(()) => AppError::auth("Magic link is invalid or expired").with_code("token.invalid"),
+        Err(e) => e,
+    }
+}
+
+/// ## Records a login attempt in the audit log.
+async fn audit(
+    state: &MagicLinkState,
+    event: AuditEvent,
+    user_id: Option<Uuid>,
+    ip: Option<IpAddr>,
+    details: serde_json::Value,
+) -> Result<(), AppError> {
+    let mut entry = AuditEntry::new(event).details(details);
+    if let Some(user_id) = user_id {
+        entry = entry.actor(user_id).user(user_id);
+    }
+    if let Some(ip) = ip {
+        entry = entry.ip(&ip.to_string());
+    }
+
+    state.audit.record(entry).await
+}
+
+/// ## Stores a new link token and sends the link.
+async fn send_link(state: &MagicLinkState, user: &User) -> Result<(), AppError> {
+    let token = random_token()?;
+    let expires_at = Utc::now() + Duration::seconds(state.settings.ttl as i64);
+    state
+        .tokens
+        .store(NewToken {
+            kind: TokenKind::MagicLink,
+            user_id: user.id,
+            session_id: None,
+            token: token.clone(),
+            expires_at,
+        })
+        .await?;
+
+    let separator = if state.settings.url.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+    let data = serde_json::json!({
+        "user_id": user.id,
+        "email": user.email,
+        "link": format!("{}{}token={}", state.settings.url, separator, token),
+        "expires_at": expires_at,
+    });
+    state
+        .webhooks
+        .emit(WebhookEvent::MagicLinkRequested, data)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::audit::{AuditFilter, AUDIT_SORTING};
+    use crate::core::auth::access::GeoIpResolver;
+    use crate::core::auth::captcha::CaptchaVerifier;
+    use crate::core::auth::password::{Algorithm, StoredHash};
+    use crate::core::auth::reputation::ReputationProvider;
+    use crate::core::auth::{jwt::SigningKey, keys::KeyManager};
+    use crate::core::config::{
+        read_config, AccessPolicySettings, AccessSettings, LoginAnomalySettings,
+        LoginThrottleSettings, RateLimitRule, ReputationSettings, SameSite, TokenSettings,
+        DEFAULT_CONFIG_FILE,
+    };
+    use crate::core::http::pagination::Pagination;
+    use crate::core::repo::memory::{
+        MemoryDeviceRepo, MemorySessionRepo, MemoryTokenRepo, MemoryUserRepo,
+    };
+    use crate::core::repo::DeviceRepo;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::{
+            header::{CONTENT_TYPE, COOKIE},
+            HeaderValue,
+        },
+    };
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use sqlx::postgres::PgPoolOptions;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    struct Solved;
+
+    #[async_trait::async_trait]
+    impl CaptchaVerifier for Solved {
+        fn name(&self) -> &str {
+            "solved"
+        }
+
+        async fn verify(
+            &self,
+            token: &str,
+            _ip: Option<std::net::IpAddr>,
+        ) -> Result<bool, AppError> {
+            Ok(token == "solved")
+        }
+    }
+
+    // Addresses with a poor reputation.
+    struct Scores;
+
+    #[async_trait::async_trait]
+    impl ReputationProvider for Scores {
+        fn name(&self) -> &str {
+            "scores"
+        }
+
+        async fn score(&self, ip: std::net::IpAddr) -> Result<u8, AppError> {
+            Ok(match ip.to_string().as_str() {
+                "198.51.100.1" => 30,
+                "198.51.100.2" => 60,
+                "198.51.100.3" => 95,
+                _ => 0,
+            })
+        }
+    }
+
+    // Addresses of the documentation range 192.0.2.0/24 are in Germany.
+    struct Countries;
+
+    impl GeoIpResolver for Countries {
+        fn country(&self, ip: std::net::IpAddr) -> Option<String> {
+            ip.to_string()
+                .starts_with("192.0.2.")
+                .then(|| "DE".to_string())
+        }
+    }
+
+    struct Fixture {
+        router: Router,
+        state: MagicLinkState,
+        users: Arc<MemoryUserRepo>,
+        sessions: Arc<MemorySessionRepo>,
+        devices: Arc<MemoryDeviceRepo>,
+        tokens: Arc<MemoryTokenRepo>,
+        cookies: Arc<Cookies>,
+    }
+
+    // Limiter allowing 2 links per address and 3 per client.
+    fn limiter() -> Arc<RateLimiter> {
+        let limiter = RateLimiter::default();
+        limiter.set_rule(
+            RATE_LIMIT_SCOPE,
+            RateLimitRule {
+                limit: 2,
+                window: 3600,
+            },
+        );
+        limiter.set_rule(
+            CLIENT_RATE_LIMIT_SCOPE,
+            RateLimitRule {
+                limit: 3,
+                window: 3600,
+            },
+        );
+        Arc::new(limiter)
+    }
+
+    // Magic link routes with the repositories in memory, webhooks never connect.
+    fn fixture(mode: AuthMode) -> Fixture {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+        let token_settings = TokenSettings::default();
+        let users = Arc::new(MemoryUserRepo::default());
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let tokens = Arc::new(MemoryTokenRepo::default());
+        let devices = Arc::new(MemoryDeviceRepo::default());
+        let webhooks = Webhooks::new(pool, &Default::default());
+        let cookies =
+            Arc::new(Cookies::from_secret(b"cookie-secret", true, SameSite::Lax).unwrap());
+        let state = MagicLinkState {
+            users: users.clone(),
+            sessions: sessions.clone(),
+            tokens: tokens.clone(),
+            webhooks: webhooks.clone(),
+            limiter: limiter(),
+            access_tokens: TokenIssuer::new(
+                Arc::new(KeyManager::new(
+                    SigningKey::generate(&token_settings.key_id).unwrap(),
+                )),
+                &token_settings,
+            ),
+            auth: AuthSettings {
+                mode,
+                ..AuthSettings::default()
+            },
+            cookies: cookies.clone(),
+            settings: MagicLinkSettings::default(),
+            anomalies: LoginAnomalies {
+                devices: devices.clone(),
+                geoip: None,
+                webhooks,
+                settings: LoginAnomalySettings {
+                    enabled: true,
+                    ..LoginAnomalySettings::default()
+                },
+            },
+            throttle: Arc::new(
+                LoginThrottle::new(&LoginThrottleSettings {
+                    enabled: true,
+                    free_failures: 10,
+                    captcha_after: 2,
+                    ..LoginThrottleSettings::default()
+                })
+                .with_captcha(Arc::new(Solved)),
+            ),
+            reputation: Arc::new(ReputationGuard::new(
+                Arc::new(Scores),
+                ReputationSettings {
+                    enabled: true,
+                    ..ReputationSettings::default()
+                },
+            )),
+            access: Arc::new(
+                AccessPolicyEngine::new(
+                    &AccessSettings {
+                        roles: HashMap::from([(
+                            "contractor".to_string(),
+                            AccessPolicySettings {
+                                allowed_countries: Some(vec!["DE".to_string()]),
+                                login_window: None,
+                            },
+                        )]),
+                        ..AccessSettings::default()
+                    },
+                    Some(Arc::new(Countries)),
+                )
+                .unwrap(),
+            ),
+            audit: AuditLog::memory(),
+        };
+
+        Fixture {
+            router: router(state.clone()),
+            state,
+            users,
+            sessions,
+            devices,
+            tokens,
+            cookies,
+        }
+    }
+
+    impl Fixture {
+        fn user(&self, email: &str, disabled: bool) -> Uuid {
+            let id = Uuid::new_v4();
+            self.users.insert(
+                User {
+                    id,
+                    email: email.to_string(),
+                    display_name: None,
+                    org_id: None,
+                    disabled,
+                    password_reset_required: false,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    created_at_display: None,
+                    updated_at_display: None,
+                },
+                None,
+            );
+            id
+        }
+
+        async fn link(&self, user_id: Uuid) -> String {
+            let token = format!("link-{}", user_id);
+            self.tokens
+                .store(NewToken {
+                    kind: TokenKind::MagicLink,
+                    user_id,
+                    session_id: None,
+                    token: token.clone(),
+                    expires_at: Utc::now() + Duration::minutes(5),
+                })
+                .await
+                .unwrap();
+            token
+        }
+
+        async fn post(&self, uri: &str, body: serde_json::Value) -> Response {
+            self.send(uri, body, None).await
+        }
+
+        async fn send(&self, uri: &str, body: serde_json::Value, client: Option<&str>) -> Response {
+            let mut request = Request::post(uri)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            if let Some(client) = client {
+                request
+                    .extensions_mut()
+                    .insert(ClientIp(client.parse().unwrap()));
+            }
+
+            self.router.clone().oneshot(request).await.unwrap()
+        }
+    }
+
+    // Test checks if known and unknown addresses get the same answer until rate limited.
+    #[tokio::test]
+    async fn test_request_link() {
+        let fixture = fixture(AuthMode::Jwt);
+        fixture.user("user@example.com", false);
+        let request = |email: &str| serde_json::json!({ "email": email });
+
+        // Limit is per address, case is ignored
+        for email in ["user@example.com", "nobody@example.com", "USER@example.com"] {
+            let response = fixture.post("/magic-link", request(email)).await;
+            assert_eq!(response.status(), StatusCode::ACCEPTED, "{}", email);
+        }
+
+        let response = fixture
+            .post("/magic-link", request("user@example.com"))
+            .await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = fixture
+            .post("/magic-link", request("nobody@example.com"))
+            .await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = fixture.post("/magic-link", request("not an email")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Test checks if a reloaded rate limit takes effect without a restart.
+    #[tokio::test]
+    async fn test_request_link_reloaded_limit() {
+        let fixture = fixture(AuthMode::Jwt);
+        let request = serde_json::json!({ "email": "user@example.com" });
+        let mut config = read_config(DEFAULT_CONFIG_FILE, None).unwrap();
+        let (updates, receiver) = watch::channel(Arc::new(config.clone()));
+        fixture.state.subscribe(receiver);
+
+        for _ in 0..2 {
+            let response = fixture.post("/magic-link", request.clone()).await;
+            assert_eq!(response.status(), StatusCode::ACCEPTED);
+        }
+        let response = fixture.post("/magic-link", request.clone()).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        config.magic_link.rate_limit = RateLimitRule {
+            limit: 3,
+            window: 3600,
+        };
+        updates.send(Arc::new(config)).unwrap();
+        while fixture.state.limiter.rule(RATE_LIMIT_SCOPE).unwrap().limit != 3 {
+            tokio::task::yield_now().await;
+        }
+
+        let response = fixture.post("/magic-link", request.clone()).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let response = fixture.post("/magic-link", request).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    // Test checks if links are limited per client address across email addresses.
+    #[tokio::test]
+    async fn test_request_link_client_limit() {
+        let fixture = fixture(AuthMode::Jwt);
+        let request = |email: &str| serde_json::json!({ "email": email });
+
+        for email in ["a@example.com", "b@example.com", "c@example.com"] {
+            let response = fixture
+                .send("/magic-link", request(email), Some("192.0.2.10"))
+                .await;
+            assert_eq!(response.status(), StatusCode::ACCEPTED, "{}", email);
+        }
+
+        let response = fixture
+            .send("/magic-link", request("d@example.com"), Some("192.0.2.10"))
+            .await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = fixture
+            .send("/magic-link", request("d@example.com"), Some("192.0.2.11"))
+            .await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    // Test checks if a link logs in once, opens an active session and both attempts are audited.
+    #[tokio::test]
+    async fn test_verify_link_single_use() {
+        let fixture = fixture(AuthMode::Jwt);
+        let user_id = fixture.user("user@example.com", false);
+        let token = fixture.link(user_id).await;
+
+        let response = fixture
+            .post("/magic-link/verify", serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(SET_COOKIE).is_none());
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let login: LoginResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(login.token_type, "Bearer");
+        let refresh_token = login.refresh_token.unwrap();
+        let refresh = fixture
+            .tokens
+            .find(TokenKind::Refresh, &refresh_token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refresh.session_id, Some(login.session_id));
+        let session = fixture
+            .sessions
+            .get(login.session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.user_id, user_id);
+        assert_eq!(fixture.devices.list(user_id).await.unwrap().len(), 1);
+
+        let again = fixture
+            .post("/magic-link/verify", serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(again.status(), StatusCode::UNAUTHORIZED);
+
+        let events = fixture.state.audit.export(user_id).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "login.succeeded");
+        assert_eq!(
+            events[0].details["session_id"],
+            login.session_id.to_string()
+        );
+        let filter = AuditFilter {
+            event: Some(AuditEvent::LoginFailed),
+            ..AuditFilter::default()
+        };
+        let window = Pagination::default().window(&AUDIT_SORTING).unwrap();
+        let failures = fixture.state.audit.query(&filter, &window).await.unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].user_id, None);
+        assert_eq!(failures[0].details["reason"], "token.invalid");
+    }
+
+    // Test checks if the steps and failures of the links are counted by the funnel.
+    #[test]
+    fn test_verify_link_funnel() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let fixture = fixture(AuthMode::Jwt);
+                let user_id = fixture.user("user@example.com", false);
+                let token = fixture.link(user_id).await;
+                for (token, client) in [
+                    ("guessed", "192.0.2.10"),
+                    ("guessed", "198.51.100.3"),
+                    (token.as_str(), "192.0.2.10"),
+                ] {
+                    let body = serde_json::json!({ "token": token });
+                    fixture.send("/magic-link/verify", body, Some(client)).await;
+                }
+            });
+        });
+        let rendered = handle.render();
+
+        for line in [
+            "auth_login_funnel_steps_total{method=\"magic_link\",step=\"captcha_ok\"} 2",
+            "auth_login_funnel_steps_total{method=\"magic_link\",step=\"token_valid\"} 1",
+            "auth_login_funnel_steps_total{method=\"magic_link\",step=\"token_issued\"} 1",
+            "auth_login_funnel_failures_total{method=\"magic_link\",step=\"token_valid\",reason=\"invalid_token\"} 1",
+            "auth_login_funnel_failures_total{method=\"magic_link\",step=\"captcha_ok\",reason=\"blocked\"} 1",
+        ] {
+            assert!(rendered.contains(line), "{}", line);
+        }
+    }
+
+    // Test checks if clients guessing tokens have to solve a CAPTCHA.
+    #[tokio::test]
+    async fn test_verify_link_captcha() {
+        let fixture = fixture(AuthMode::Jwt);
+        let user_id = fixture.user("user@example.com", false);
+        let client = Some("192.0.2.10");
+        let verify = |token: &str, captcha: Option<&str>| {
+            let body = serde_json::json!({ "token": token, "captcha_token": captcha });
+            fixture.send("/magic-link/verify", body, client)
+        };
+
+        for _ in 0..2 {
+            assert_eq!(
+                verify("guessed", None).await.status(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+
+        let token = fixture.link(user_id).await;
+        assert_eq!(verify(&token, None).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            verify(&token, Some("guessed")).await.status(),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            verify(&token, Some("solved")).await.status(),
+            StatusCode::OK
+        );
+
+        // Login forgot the failures of the client
+        let token = fixture.link(user_id).await;
+        assert_eq!(verify(&token, None).await.status(), StatusCode::OK);
+    }
+
+    // Test checks if poor reputations require a CAPTCHA, are annotated and blocked.
+    #[tokio::test]
+    async fn test_verify_link_reputation() {
+        let fixture = fixture(AuthMode::Jwt);
+        let user_id = fixture.user("user@example.com", false);
+        let verify = |token: &str, captcha: Option<&str>, client: &'static str| {
+            let body = serde_json::json!({ "token": token, "captcha_token": captcha });
+            fixture.send("/magic-link/verify", body, Some(client))
+        };
+
+        let token = fixture.link(user_id).await;
+        let response = verify(&token, None, "198.51.100.3").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = verify(&token, None, "198.51.100.2").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "captcha.required");
+        let response = verify(&token, Some("solved"), "198.51.100.2").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let token = fixture.link(user_id).await;
+        let response = verify(&token, None, "198.51.100.1").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let events = fixture.state.audit.export(user_id).await.unwrap();
+        let scores = events
+            .iter()
+            .map(|event| event.details["reputation_score"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(scores, [serde_json::json!(60), serde_json::json!(30)]);
+        let filter = AuditFilter {
+            event: Some(AuditEvent::LoginFailed),
+            ..AuditFilter::default()
+        };
+        let window = Pagination::default().window(&AUDIT_SORTING).unwrap();
+        let failures = fixture.state.audit.query(&filter, &window).await.unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].details["reason"], "reputation.blocked");
+        assert_eq!(failures[0].ip.as_deref(), Some("198.51.100.3"));
+    }
+
+    // Test checks if users are only logged in from the allowed countries of their roles.
+    #[tokio::test]
+    async fn test_verify_link_country_denied() {
+        let fixture = fixture(AuthMode::Jwt);
+        let user_id = fixture.user("user@example.com", false);
+        fixture.users.grant(user_id, "contractor").await.unwrap();
+        let verify = |token: String, client: &'static str| {
+            let body = serde_json::json!({ "token": token });
+            fixture.send("/magic-link/verify", body, Some(client))
+        };
+
+        let response = verify(fixture.link(user_id).await, "203.0.113.7").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "country_denied");
+        assert!(fixture.sessions.list(user_id).await.unwrap().is_empty());
+
+        let response = verify(fixture.link(user_id).await, "192.0.2.10").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let events = fixture.state.audit.export(user_id).await.unwrap();
+        let events = events
+            .iter()
+            .map(|event| (event.event.as_str(), event.details["reason"].as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                ("login.failed", Some("access.country")),
+                ("login.succeeded", None)
+            ]
+        );
+    }
+
+    // Test checks if session mode sets the cookie and disabled users are refused.
+    #[tokio::test]
+    async fn test_verify_link_session_mode() {
+        let fixture = fixture(AuthMode::Session);
+        let user_id = fixture.user("user@example.com", false);
+        let token = fixture.link(user_id).await;
+
+        let response = fixture
+            .post("/magic-link/verify", serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+        assert!(cookie.contains("; HttpOnly; SameSite=Lax; Secure"));
+        let (value, _) = cookie.split_once(';').unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_str(value).unwrap());
+        let session_id = fixture.cookies.get_signed(&headers, "session").unwrap();
+        let session_id = Uuid::parse_str(&session_id).unwrap();
+        assert_eq!(
+            fixture
+                .sessions
+                .get(session_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .user_id,
+            user_id
+        );
+
+        let disabled = fixture.user("disabled@example.com", true);
+        let token = fixture.link(disabled).await;
+        let response = fixture
+            .post("/magic-link/verify", serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let events = fixture.state.audit.export(disabled).await.unwrap();
+        assert_eq!(events[0].event, "login.failed");
+        assert_eq!(events[0].details["reason"], "user.disabled");
+    }
+
+    // Test checks if an expired password only allows changing it.
+    #[tokio::test]
+    async fn test_verify_link_password_expired() {
+        let fixture = fixture(AuthMode::Session);
+        let user_id = fixture.user("user@example.com", false);
+        let hash = StoredHash {
+            algorithm: Algorithm::Argon2id,
+            hash: "hash".to_string(),
+        };
+        fixture.users.set_password(user_id, &hash).await.unwrap();
+        fixture
+            .users
+            .expire_passwords(Utc::now() + Duration::days(1))
+            .await
+            .unwrap();
+        let token = fixture.link(user_id).await;
+
+        let response = fixture
+            .post("/magic-link/verify", serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(SET_COOKIE).is_none());
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let login: LoginResponse = serde_json::from_slice(&body).unwrap();
+        assert!(login.password_change_required);
+        assert_eq!(login.refresh_token, None);
+    }
+}
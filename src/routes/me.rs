@@ -0,0 +1,1196 @@
+//! Account routes of the authenticated user.
+//!
+//! Every route but `/me/email/undo` and
+//! `/me/delete/cancel` is declared for users, so it is
+//! authenticated, the account is the one of the access
+//! token. New email addresses are confirmed
+//! with a token delivered through the
+//! `email.change_requested` webhook, while the old address
+//! is warned through `email.change_warning` with a token
+//! undoing the change. Changing the password ends every
+//! other session. `/me/devices` lists the devices the
+//! user logged in from. `/me/export` sends everything
+//! stored about the user. `/me/delete` ends every session
+//! and schedules the removal of the account, a token
+//! delivered through the `account.deletion_scheduled`
+//! webhook cancels it on the unauthenticated
+//! `/me/delete/cancel` until the grace period is over.
+//! `/me` warns about passwords and API keys about to
+//! expire under `credential_expiry`.
+
+// Imports from external crates
+use axum::{
+    extract::{Path, State},
+    http::{header::CONTENT_DISPOSITION, StatusCode},
+    response::IntoResponse,
+    Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Local imports
+use crate::core::audit::{AuditEntry, AuditEvent, AuditLog};
+use crate::core::auth::expiry;
+use crate::core::auth::funnel::LoginFunnel;
+use crate::core::auth::password::{authenticate, PasswordPolicy, Passwords};
+use crate::core::auth::rbac::Principal;
+use crate::core::config::{AppConfig, CredentialExpirySettings};
+use crate::core::db::DbExecutor;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::client_ip::ClientIp;
+use crate::core::http::locale::Localizer;
+use crate::core::http::principal::{Authenticator, CurrentSession};
+use crate::core::repo::postgres::{PgDeviceRepo, PgSessionRepo, PgTokenRepo, PgUserRepo};
+use crate::core::repo::{
+    Device, DeviceRepo, NewToken, Session, SessionRepo, TokenKind, TokenRepo, UserRepo,
+};
+use crate::core::users::{roles::RoleRepo, User};
+use crate::core::webhooks::{WebhookEvent, Webhooks};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::users::{
+    AccountDeletion, CancelDeletionRequest, ChangeEmailRequest, ChangePasswordRequest,
+    ConfirmEmailRequest, CredentialKind, CredentialWarning, Profile, UndoEmailRequest, UserExport,
+};
+
+/// Length of the email change and undo tokens in bytes.
+const TOKEN_LEN: usize = 32;
+
+/// ## Account state struct.
+///
+/// ## Fields
+/// + `users`: `Arc<dyn UserRepo>` - User repository.
+/// + `sessions`: `Arc<dyn SessionRepo>` - Login sessions of the users.
+/// + `devices`: `Arc<dyn DeviceRepo>` - Devices the users logged in from.
+/// + `tokens`: `Arc<dyn TokenRepo>` - Email change, undo and session tokens.
+/// + `roles`: `RoleRepo` - Role grants of the users.
+/// + `audit`: `AuditLog` - Audit log of the account changes.
+/// + `passwords`: `Passwords` - Password service.
+/// + `policy`: `PasswordPolicy` - Check of the new passwords.
+/// + `webhooks`: `Webhooks` - Webhooks of the account events.
+/// + `email_change_ttl`: `u64` - Lifetime of email change tokens in seconds.
+/// + `email_undo_ttl`: `u64` - Lifetime of email undo tokens in seconds.
+/// + `deletion_grace`: `u64` - Seconds a deleted account can be restored.
+/// + `expiry`: `CredentialExpirySettings` - Maximum ages of the credentials.
+#[derive(Clone)]
+pub struct MeState {
+    pub users: Arc<dyn UserRepo>,
+    pub sessions: Arc<dyn SessionRepo>,
+    pub devices: Arc<dyn DeviceRepo>,
+    pub tokens: Arc<dyn TokenRepo>,
+    pub roles: RoleRepo,
+    pub audit: AuditLog,
+    pub passwords: Passwords,
+    pub policy: PasswordPolicy,
+    pub webhooks: Webhooks,
+    pub email_change_ttl: u64,
+    pub email_undo_ttl: u64,
+    pub deletion_grace: u64,
+    pub expiry: CredentialExpirySettings,
+}
+
+impl MeState {
+    /// ## Creates a new `MeState` backed by Postgres.
+    ///
+    /// ## Parameters
+    /// - `config`: `&AppConfig` - Password, token and job settings.
+    /// - `db`: `impl Into<DbExecutor>` - Executor or pool of the repositories.
+    /// - `webhooks`: `Webhooks` - Queue of the webhook events.
+    ///
+    /// ## Returns
+    /// + `Result<MeState, AppError>`
+    ///     - `Ok(MeState)`: New state.
+    ///     - `Err(AppError)`: If the password settings are invalid or the
+    ///       Bloom filter of breached passwords can not be loaded.
+    pub fn new(
+        config: &AppConfig,
+        db: impl Into<DbExecutor>,
+        webhooks: Webhooks,
+    ) -> Result<Self, AppError> {
+        let db = db.into();
+
+        Ok(MeState {
+            users: Arc::new(PgUserRepo::new(db.clone())),
+            sessions: Arc::new(PgSessionRepo::new(db.clone())),
+            devices: Arc::new(PgDeviceRepo::new(db.clone())),
+            tokens: Arc::new(PgTokenRepo::new(db.clone())),
+            roles: RoleRepo::new(db.clone()),
+            audit: AuditLog::new(db),
+            passwords: Passwords::new(&config.password)?,
+            policy: PasswordPolicy::new(&config.password.breach)?,
+            webhooks,
+            email_change_ttl: config.tokens.email_change_ttl,
+            email_undo_ttl: config.tokens.email_undo_ttl,
+            deletion_grace: config.jobs.deletion_grace,
+            expiry: config.credential_expiry.clone(),
+        })
+    }
+}
+
+/// ## Declares the account routes.
+///
+/// ## Returns
+/// - `Routes<MeState>`: Routes to nest under `/me`.
+pub fn routes() -> Routes<MeState> {
+    Routes::new()
+        .get("/", AuthPolicy::User, get_me)
+        .get("/export", AuthPolicy::User, export_me)
+        .post("/email", AuthPolicy::User, change_email)
+        .post("/email/confirm", AuthPolicy::User, confirm_email)
+        .post("/password", AuthPolicy::User, change_password)
+        .get("/sessions", AuthPolicy::User, list_sessions)
+        .delete("/sessions/:id", AuthPolicy::User, revoke_session)
+        .get("/devices", AuthPolicy::User, list_devices)
+        .post("/delete", AuthPolicy::User, delete_account)
+        .post("/email/undo", AuthPolicy::Public, undo_email)
+        .post("/delete/cancel", AuthPolicy::Public, cancel_deletion)
+}
+
+/// ## Builds the account router.
+///
+/// ## Parameters
+/// - `state`: `MeState` - Shared state of the routes.
+/// - `authenticator`: `Arc<Authenticator>` - Verifier of the access tokens.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `/me`.
+pub fn router(state: MeState, authenticator: Arc<Authenticator>) -> Router {
+    routes().router(Some(&authenticator)).with_state(state)
+}
+
+/// ## Returns the profile of the user.
+///
+/// Profile lists the credentials that expire within
+/// the warning period of `credential_expiry`.
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "me",
+    responses((status = 200, description = "Profile of the user", body = Profile))
+)]
+pub async fn get_me(
+    State(state): State<MeState>,
+    principal: Principal,
+    localizer: Localizer,
+) -> Result<Json<Profile>, AppError> {
+    let user = state.users.get(principal.user_id).await?;
+    let credential_warnings = expiry::warnings(
+        &state.expiry,
+        state.users.as_ref(),
+        state.tokens.as_ref(),
+        &user,
+    )
+    .await?;
+
+    Ok(Json(Profile {
+        user: localizer.apply(user),
+        credential_warnings,
+    }))
+}
+
+/// ## Exports the personal data of the user.
+///
+/// Archive is sent as a JSON attachment.
+#[utoipa::path(
+    get,
+    path = "/me/export",
+    tag = "me",
+    responses((status = 200, description = "Archive of the user's data", body = UserExport))
+)]
+pub async fn export_me(
+    State(state): State<MeState>,
+    principal: Principal,
+) -> Result<impl IntoResponse, AppError> {
+    let user = state.users.get(principal.user_id).await?;
+    let roles = state.roles.list_user(user.id).await?;
+    let sessions = state.sessions.list(user.id).await?;
+    let devices = state.devices.list(user.id).await?;
+    let audit_events = state.audit.export(user.id).await?;
+
+    let export = UserExport {
+        exported_at: Utc::now(),
+        user,
+        roles: roles.into_iter().map(|grant| grant.role).collect(),
+        sessions,
+        devices,
+        audit_events,
+    };
+    let disposition = format!("attachment; filename=\"export-{}.json\"", principal.user_id);
+
+    Ok(([(CONTENT_DISPOSITION, disposition)], Json(export)))
+}
+
+/// ## Requests an email change.
+///
+/// New address is kept pending until it is confirmed
+/// with the token sent to it, the current address stays
+/// in use until then. The current address is warned and
+/// gets a token undoing the change.
+#[utoipa::path(
+    post,
+    path = "/me/email",
+    tag = "me",
+    request_body = ChangeEmailRequest,
+    responses((
+        status = 202,
+        description = "Confirmation token sent to the new address, undo token to the current one"
+    ))
+)]
+pub async fn change_email(
+    State(state): State<MeState>,
+    principal: Principal,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<StatusCode, AppError> {
+    let email = request.email.trim();
+    validate_email(email)?;
+
+    let user = state.users.get(principal.user_id).await?;
+    state.users.set_pending_email(user.id, email).await?;
+
+    let token = store_token(&state, TokenKind::EmailChange, user.id).await?;
+    let data = serde_json::json!({
+        "user_id": user.id,
+        "email": email,
+        "token": token,
+    });
+    state
+        .webhooks
+        .emit(WebhookEvent::EmailChangeRequested, data)
+        .await?;
+
+    let undo_token = store_token(&state, TokenKind::EmailUndo, user.id).await?;
+    let data = serde_json::json!({
+        "user_id": user.id,
+        "email": user.email,
+        "new_email": email,
+        "token": undo_token,
+    });
+    state
+        .webhooks
+        .emit(WebhookEvent::EmailChangeWarning, data)
+        .await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// ## Stores a new email token of the user, returns the token.
+async fn store_token(state: &MeState, kind: TokenKind, user_id: Uuid) -> Result<String, AppError> {
+    let ttl = match kind {
+        TokenKind::EmailUndo => state.email_undo_ttl,
+        TokenKind::DeletionCancel => state.deletion_grace,
+        _ => state.email_change_ttl,
+    };
+    let token = random_token()?;
+    state
+        .tokens
+        .store(NewToken {
+            kind,
+            user_id,
+            session_id: None,
+            token: token.clone(),
+            expires_at: Utc::now() + Duration::seconds(ttl as i64),
+        })
+        .await?;
+
+    Ok(token)
+}
+
+/// ## Confirms the pending email change.
+#[utoipa::path(
+    post,
+    path = "/me/email/confirm",
+    tag = "me",
+    request_body = ConfirmEmailRequest,
+    responses((status = 200, description = "User with the new email", body = User))
+)]
+pub async fn confirm_email(
+    State(state): State<MeState>,
+    principal: Principal,
+    localizer: Localizer,
+    Json(request): Json<ConfirmEmailRequest>,
+) -> Result<Json<User>, AppError> {
+    let token = state
+        .tokens
+        .find(TokenKind::EmailChange, &request.token)
+        .await?
+        .filter(|token| token.user_id == principal.user_id)
+        .ok_or_else(|| {
+            AppError::validation("Email change token is invalid or expired")
+                .with_code("token.invalid")
+        })?;
+
+    let user = state.users.confirm_pending_email(principal.user_id).await?;
+    state.tokens.revoke(token.id).await?;
+
+    Ok(Json(localizer.apply(user)))
+}
+
+/// ## Undoes the email change.
+///
+/// Route is not authenticated, the owner of the old
+/// address may have lost access to the account. The old
+/// address is restored and every session and token of
+/// the user is revoked, so whoever changed the address
+/// is logged out.
+#[utoipa::path(
+    post,
+    path = "/me/email/undo",
+    tag = "me",
+    request_body = UndoEmailRequest,
+    responses((status = 204, description = "Previous email restored, every session ended"))
+)]
+pub async fn undo_email(
+    State(state): State<MeState>,
+    Json(request): Json<UndoEmailRequest>,
+) -> Result<StatusCode, AppError> {
+    let token = state
+        .tokens
+        .find(TokenKind::EmailUndo, &request.token)
+        .await?
+        .ok_or_else(|| {
+            AppError::validation("Email undo token is invalid or expired")
+                .with_code("token.invalid")
+        })?;
+
+    state.users.undo_email_change(token.user_id).await?;
+    state.sessions.revoke_all(token.user_id).await?;
+    state.tokens.revoke_user(token.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Changes the password of the user.
+///
+/// Current password has to be sent along, it is checked
+/// like a login, so an outdated hash is upgraded even if
+/// the new password is refused. New passwords known from
+/// data breaches are refused. Every other
+/// session of the user is revoked, the session of the
+/// request stays logged in. The change is audited.
+#[utoipa::path(
+    post,
+    path = "/me/password",
+    tag = "me",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "New password is too short or appeared in a data breach")
+    )
+)]
+pub async fn change_password(
+    State(state): State<MeState>,
+    principal: Principal,
+    session: Option<CurrentSession>,
+    client: Option<ClientIp>,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<StatusCode, AppError> {
+    let user = state.users.get(principal.user_id).await?;
+    let mut funnel = LoginFunnel::new("password");
+    if let Some(ClientIp(ip)) = client {
+        funnel = funnel.client(ip);
+    }
+    let authenticated = authenticate(
+        state.users.as_ref(),
+        &state.passwords,
+        &mut funnel,
+        &user.email,
+        &request.current_password,
+    )
+    .await;
+    authenticated.map_err(|e| match e.kind {
+        ErrorKind::Auth => AppError::validation("Current password is wrong")
+            .with_code("users.wrong_password")
+            .with_status_hint(403),
+        _ => e,
+    })?;
+
+    state.policy.check(&request.new_password).await?;
+
+    let hash = state.passwords.hash(&request.new_password)?;
+    state.users.set_password(user.id, &hash).await?;
+
+    let current = session.map(|CurrentSession(id)| id);
+    let others = state.sessions.list(user.id).await?;
+    for other in others.into_iter().filter(|other| Some(other.id) != current) {
+        end_session(&state, other.id).await?;
+    }
+
+    let data = serde_json::json!({
+        "user_id": user.id,
+        "email": user.email,
+        "org_id": user.org_id,
+    });
+    state
+        .webhooks
+        .emit(WebhookEvent::PasswordChanged, data)
+        .await?;
+    audit(
+        &state,
+        AuditEvent::PasswordChanged,
+        user.id,
+        client,
+        serde_json::json!({}),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Lists the active sessions of the user.
+///
+/// In the `jwt` auth mode every session is a family of
+/// refresh tokens, `last_seen_at` moves with each refresh.
+#[utoipa::path(
+    get,
+    path = "/me/sessions",
+    tag = "me",
+    responses((status = 200, description = "Active sessions, newest first", body = Vec<Session>))
+)]
+pub async fn list_sessions(
+    State(state): State<MeState>,
+    principal: Principal,
+) -> Result<Json<Vec<Session>>, AppError> {
+    Ok(Json(state.sessions.list(principal.user_id).await?))
+}
+
+/// ## Lists the devices the user logged in from.
+///
+/// Devices are only recorded while login anomalies
+/// are enabled.
+#[utoipa::path(
+    get,
+    path = "/me/devices",
+    tag = "me",
+    responses((status = 200, description = "Known devices, last seen first", body = Vec<Device>))
+)]
+pub async fn list_devices(
+    State(state): State<MeState>,
+    principal: Principal,
+) -> Result<Json<Vec<Device>>, AppError> {
+    Ok(Json(state.devices.list(principal.user_id).await?))
+}
+
+/// ## Revokes a session of the user.
+///
+/// Tokens issued to the session are revoked with it,
+/// in the `jwt` auth mode its refresh token family.
+/// The revocation is audited.
+#[utoipa::path(
+    delete,
+    path = "/me/sessions/{id}",
+    tag = "me",
+    params(("id" = Uuid, Path, description = "ID of the session")),
+    responses((status = 204, description = "Session revoked"))
+)]
+pub async fn revoke_session(
+    State(state): State<MeState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    let owned = state
+        .sessions
+        .get(id)
+        .await?
+        .is_some_and(|session| session.user_id == principal.user_id);
+    if !owned {
+        return Err(AppError::new(
+            ErrorKind::NotFound,
+            format!("Session '{}' not found", id),
+            None,
+        ));
+    }
+
+    end_session(&state, id).await?;
+    audit(
+        &state,
+        AuditEvent::TokenRevoked,
+        principal.user_id,
+        client,
+        serde_json::json!({ "session_id": id }),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Schedules the deletion of the account.
+///
+/// Every session and token of the user is revoked right
+/// away. The account is removed by the deletion job once
+/// `jobs.deletion_grace` has passed, unless the token sent
+/// to the email address cancels it first.
+#[utoipa::path(
+    post,
+    path = "/me/delete",
+    tag = "me",
+    responses((
+        status = 202,
+        description = "Deletion scheduled, cancellation token sent to the email address",
+        body = AccountDeletion
+    ))
+)]
+pub async fn delete_account(
+    State(state): State<MeState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+) -> Result<(StatusCode, Json<AccountDeletion>), AppError> {
+    let user = state.users.get(principal.user_id).await?;
+    let delete_after = Utc::now() + Duration::seconds(state.deletion_grace as i64);
+
+    state.users.schedule_deletion(user.id, delete_after).await?;
+    state.sessions.revoke_all(user.id).await?;
+    state.tokens.revoke_user(user.id).await?;
+
+    let token = store_token(&state, TokenKind::DeletionCancel, user.id).await?;
+    let data = serde_json::json!({
+        "user_id": user.id,
+        "email": user.email,
+        "token": token,
+        "delete_after": delete_after,
+    });
+    state
+        .webhooks
+        .emit(WebhookEvent::AccountDeletionScheduled, data)
+        .await?;
+    audit(
+        &state,
+        AuditEvent::DeletionScheduled,
+        user.id,
+        client,
+        serde_json::json!({ "delete_after": delete_after }),
+    )
+    .await?;
+
+    Ok((StatusCode::ACCEPTED, Json(AccountDeletion { delete_after })))
+}
+
+/// ## Cancels the scheduled deletion of the account.
+///
+/// Route is not authenticated, every session of the
+/// user was ended when the deletion was scheduled.
+/// Revoked sessions and tokens stay revoked.
+#[utoipa::path(
+    post,
+    path = "/me/delete/cancel",
+    tag = "me",
+    request_body = CancelDeletionRequest,
+    responses((status = 204, description = "Deletion cancelled"))
+)]
+pub async fn cancel_deletion(
+    State(state): State<MeState>,
+    client: Option<ClientIp>,
+    Json(request): Json<CancelDeletionRequest>,
+) -> Result<StatusCode, AppError> {
+    let token = state
+        .tokens
+        .find(TokenKind::DeletionCancel, &request.token)
+        .await?
+        .ok_or_else(|| {
+            AppError::validation("Deletion cancellation token is invalid or expired")
+                .with_code("token.invalid")
+        })?;
+
+    state.tokens.revoke(token.id).await?;
+    if !state.users.cancel_deletion(token.user_id).await? {
+        return Err(
+            AppError::validation("Account is not scheduled for deletion")
+                .with_code("users.not_scheduled"),
+        );
+    }
+    audit(
+        &state,
+        AuditEvent::DeletionCancelled,
+        token.user_id,
+        client,
+        serde_json::json!({}),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// ## Records a change of the user's own account in the audit log.
+async fn audit(
+    state: &MeState,
+    event: AuditEvent,
+    user_id: Uuid,
+    client: Option<ClientIp>,
+    details: serde_json::Value,
+) -> Result<(), AppError> {
+    let mut entry = AuditEntry::new(event)
+        .actor(user_id)
+        .user(user_id)
+        .details(details);
+    if let Some(ClientIp(ip)) = client {
+        entry = entry.ip(&ip.to_string());
+    }
+
+    state.audit.record(entry).await
+}
+
+/// ## Revokes the session and its tokens.
+async fn end_session(state: &MeState, id: Uuid) -> Result<(), AppError> {
+    state.sessions.revoke(id).await?;
+    state.tokens.revoke_session(id).await?;
+
+    Ok(())
+}
+
+/// ## Rejects addresses that can not receive mail.
+pub(crate) fn validate_email(email: &str) -> Result<(), AppError> {
+    let valid = email
+        .split_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+        && !email.chars().any(char::is_whitespace);
+
+    match valid {
+        true => Ok(()),
+        false => Err(AppError::validation(format!(
+            "'{}' is not a valid email address",
+            email
+        ))),
+    }
+}
+
+/// ## Generates a random URL safe token.
+pub(crate) fn random_token() -> Result<String, AppError> {
+    let mut bytes = [0u8; TOKEN_LEN];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| AppError::internal("Failed to generate a token"))?;
+
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::breach::BloomFilter;
+    use crate::core::auth::password::{Algorithm, Verification};
+    use crate::core::auth::{jwt::SigningKey, keys::KeyManager};
+    use crate::core::config::{Argon2Settings, PasswordSettings, TokenSettings};
+    use crate::core::repo::memory::{
+        MemoryDeviceRepo, MemorySessionRepo, MemoryTokenRepo, MemoryUserRepo,
+    };
+    use crate::core::repo::DeviceLogin;
+    use crate::core::repo::NewSession;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::header::{AUTHORIZATION, CONTENT_TYPE},
+    };
+    use sqlx::postgres::PgPoolOptions;
+    use tower::ServiceExt;
+
+    struct Fixture {
+        router: Router,
+        key: Arc<SigningKey>,
+        settings: TokenSettings,
+        users: Arc<MemoryUserRepo>,
+        sessions: Arc<MemorySessionRepo>,
+        devices: Arc<MemoryDeviceRepo>,
+        tokens: Arc<MemoryTokenRepo>,
+        audit: AuditLog,
+        passwords: Passwords,
+    }
+
+    // Account routes with the repositories in memory, webhooks never connect.
+    fn fixture() -> Fixture {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+        let settings = TokenSettings::default();
+        let key = Arc::new(SigningKey::generate(&settings.key_id).unwrap());
+        let users = Arc::new(MemoryUserRepo::default());
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let tokens = Arc::new(MemoryTokenRepo::default());
+        let devices = Arc::new(MemoryDeviceRepo::default());
+        let audit = AuditLog::memory();
+        let passwords = Passwords::new(&PasswordSettings {
+            algorithm: Algorithm::Bcrypt,
+            argon2: Argon2Settings::default(),
+            bcrypt_cost: 4,
+            ..PasswordSettings::default()
+        })
+        .unwrap();
+
+        let state = MeState {
+            users: users.clone(),
+            sessions: sessions.clone(),
+            devices: devices.clone(),
+            tokens: tokens.clone(),
+            roles: RoleRepo::new(pool.clone()),
+            audit: audit.clone(),
+            passwords: passwords.clone(),
+            // SHA-1 of "password123"
+            policy: PasswordPolicy::default().with_breaches(Arc::new(
+                BloomFilter::build(
+                    "CBFDAC6008F9CAB4083784CBD1874F76618D2A97:1\n".as_bytes(),
+                    1,
+                    0.001,
+                )
+                .unwrap(),
+            )),
+            webhooks: Webhooks::new(pool, &Default::default()),
+            email_change_ttl: 60,
+            email_undo_ttl: 60,
+            deletion_grace: 60,
+            expiry: CredentialExpirySettings::default(),
+        };
+        let authenticator = Arc::new(Authenticator::new(
+            Arc::new(KeyManager::new(key.clone())),
+            &settings,
+            sessions.clone(),
+        ));
+
+        Fixture {
+            router: router(state, authenticator),
+            key,
+            settings,
+            users,
+            sessions,
+            devices,
+            tokens,
+            audit,
+            passwords,
+        }
+    }
+
+    impl Fixture {
+        fn user(&self, email: &str, password: &str) -> Uuid {
+            let id = Uuid::new_v4();
+            self.users.insert(
+                User {
+                    id,
+                    email: email.to_string(),
+                    display_name: None,
+                    org_id: None,
+                    disabled: false,
+                    password_reset_required: false,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    created_at_display: None,
+                    updated_at_display: None,
+                },
+                Some(self.passwords.hash(password).unwrap()),
+            );
+            id
+        }
+
+        async fn login(&self, user_id: Uuid) -> (Uuid, String) {
+            let session = self
+                .sessions
+                .create(NewSession {
+                    user_id,
+                    ip: None,
+                    user_agent: None,
+                    expires_at: Utc::now() + Duration::hours(1),
+                })
+                .await
+                .unwrap();
+            let token = self
+                .key
+                .issue_for_session(&self.settings, user_id, session.id, &[])
+                .unwrap();
+            (session.id, token)
+        }
+
+        async fn send(
+            &self,
+            method: &str,
+            uri: &str,
+            token: Option<&str>,
+            body: Option<serde_json::Value>,
+        ) -> (StatusCode, serde_json::Value) {
+            let mut request = Request::builder().method(method).uri(uri);
+            if let Some(token) = token {
+                request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+            }
+            let body = match body {
+                Some(body) => {
+                    request = request.header(CONTENT_TYPE, "application/json");
+                    Body::from(body.to_string())
+                }
+                None => Body::empty(),
+            };
+
+            let response = self
+                .router
+                .clone()
+                .oneshot(request.body(body).unwrap())
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+            (
+                status,
+                serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+            )
+        }
+    }
+
+    // Test checks if the routes require an access token.
+    #[tokio::test]
+    async fn test_me_unauthenticated() {
+        let fixture = fixture();
+
+        assert_eq!(
+            fixture.send("GET", "/", None, None).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            fixture.send("GET", "/sessions", None, None).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            fixture.send("GET", "/export", None, None).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    // Test checks if the profile is the one of the token.
+    #[tokio::test]
+    async fn test_get_me() {
+        let fixture = fixture();
+        let user_id = fixture.user("ada@example.com", "correct horse");
+        let (_, token) = fixture.login(user_id).await;
+
+        let (status, body) = fixture.send("GET", "/", Some(&token), None).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["id"], user_id.to_string());
+        assert_eq!(body["email"], "ada@example.com");
+    }
+
+    // Test checks if a new email is only used once confirmed with its token.
+    #[tokio::test]
+    async fn test_change_email() {
+        let fixture = fixture();
+        let user_id = fixture.user("ada@example.com", "correct horse");
+        let (_, token) = fixture.login(user_id).await;
+
+        let invalid = serde_json::json!({ "email": "not an email" });
+        let (status, _) = fixture
+            .send("POST", "/email", Some(&token), Some(invalid))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let breached = serde_json::json!({
+            "current_password": "correct horse",
+            "new_password": "password123",
+        });
+        let (status, body) = fixture
+            .send("POST", "/password", Some(&token), Some(breached))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "password.breached");
+
+        let change = serde_json::json!({ "email": "ada@example.org" });
+        let (status, _) = fixture
+            .send("POST", "/email", Some(&token), Some(change))
+            .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(
+            fixture.users.get(user_id).await.unwrap().email,
+            "ada@example.com"
+        );
+
+        let guess = serde_json::json!({ "token": "guess" });
+        let (status, body) = fixture
+            .send("POST", "/email/confirm", Some(&token), Some(guess))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "token.invalid");
+
+        fixture
+            .tokens
+            .store(NewToken {
+                kind: TokenKind::EmailChange,
+                user_id,
+                session_id: None,
+                token: "emailed".to_string(),
+                expires_at: Utc::now() + Duration::minutes(1),
+            })
+            .await
+            .unwrap();
+        let confirm = serde_json::json!({ "token": "emailed" });
+        let (status, body) = fixture
+            .send(
+                "POST",
+                "/email/confirm",
+                Some(&token),
+                Some(confirm.clone()),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["email"], "ada@example.org");
+
+        let (status, _) = fixture
+            .send("POST", "/email/confirm", Some(&token), Some(confirm))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    // Test checks if the undo token restores the old email without a login and ends every session.
+    #[tokio::test]
+    async fn test_undo_email() {
+        let fixture = fixture();
+        let user_id = fixture.user("ada@example.com", "correct horse");
+        let (session_id, token) = fixture.login(user_id).await;
+
+        let change = serde_json::json!({ "email": "ada@example.org" });
+        let (status, _) = fixture
+            .send("POST", "/email", Some(&token), Some(change))
+            .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        fixture.users.confirm_pending_email(user_id).await.unwrap();
+
+        let guess = serde_json::json!({ "token": "guess" });
+        let (status, body) = fixture.send("POST", "/email/undo", None, Some(guess)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "token.invalid");
+
+        fixture
+            .tokens
+            .store(NewToken {
+                kind: TokenKind::EmailUndo,
+                user_id,
+                session_id: None,
+                token: "warned".to_string(),
+                expires_at: Utc::now() + Duration::minutes(1),
+            })
+            .await
+            .unwrap();
+        let undo = serde_json::json!({ "token": "warned" });
+        let (status, _) = fixture
+            .send("POST", "/email/undo", None, Some(undo.clone()))
+            .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(
+            fixture.users.get(user_id).await.unwrap().email,
+            "ada@example.com"
+        );
+        assert_eq!(fixture.sessions.get(session_id).await.unwrap(), None);
+
+        let (status, _) = fixture.send("POST", "/email/undo", None, Some(undo)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    // Test checks if the password change requires the current password, ends other sessions and is audited.
+    #[tokio::test]
+    async fn test_change_password() {
+        let fixture = fixture();
+        let user_id = fixture.user("ada@example.com", "correct horse");
+        let (current, token) = fixture.login(user_id).await;
+        let (other, other_token) = fixture.login(user_id).await;
+
+        let wrong = serde_json::json!({
+            "current_password": "wrong horse",
+            "new_password": "battery staple",
+        });
+        let (status, body) = fixture
+            .send("POST", "/password", Some(&token), Some(wrong))
+            .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"]["code"], "users.wrong_password");
+
+        let short = serde_json::json!({
+            "current_password": "correct horse",
+            "new_password": "short",
+        });
+        let (status, _) = fixture
+            .send("POST", "/password", Some(&token), Some(short))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let change = serde_json::json!({
+            "current_password": "correct horse",
+            "new_password": "battery staple",
+        });
+        let (status, _) = fixture
+            .send("POST", "/password", Some(&token), Some(change))
+            .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (_, stored) = fixture
+            .users
+            .credentials("ada@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(
+            fixture.passwords.verify("battery staple", &stored).unwrap(),
+            Verification::Invalid
+        );
+        assert!(fixture.sessions.get(current).await.unwrap().is_some());
+        assert!(fixture.sessions.get(other).await.unwrap().is_none());
+        assert_eq!(
+            fixture.send("GET", "/", Some(&other_token), None).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        let events = fixture.audit.export(user_id).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "password.changed");
+    }
+
+    // Test checks if an outdated hash is upgraded by the current password check, even if the new password is refused.
+    #[tokio::test]
+    async fn test_change_password_rehash() {
+        let fixture = fixture();
+        let user_id = fixture.user("ada@example.com", "correct horse");
+        let outdated = Passwords::new(&PasswordSettings {
+            algorithm: Algorithm::Bcrypt,
+            bcrypt_cost: 5,
+            ..PasswordSettings::default()
+        })
+        .unwrap()
+        .hash("correct horse")
+        .unwrap();
+        fixture
+            .users
+            .set_password(user_id, &outdated)
+            .await
+            .unwrap();
+        let (_, token) = fixture.login(user_id).await;
+
+        let short = serde_json::json!({
+            "current_password": "correct horse",
+            "new_password": "short",
+        });
+        let (status, _) = fixture
+            .send("POST", "/password", Some(&token), Some(short))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, stored) = fixture
+            .users
+            .credentials("ada@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(stored.hash.starts_with("$2b$04$"));
+        assert_eq!(
+            fixture.passwords.verify("correct horse", &stored).unwrap(),
+            Verification::Valid { rehash: None }
+        );
+    }
+
+    // Test checks if users only list and revoke their own sessions and revocations are audited.
+    #[tokio::test]
+    async fn test_sessions() {
+        let fixture = fixture();
+        let user_id = fixture.user("ada@example.com", "correct horse");
+        let stranger_id = fixture.user("eve@example.com", "correct horse");
+        let (current, token) = fixture.login(user_id).await;
+        let (other, _) = fixture.login(user_id).await;
+        let (foreign, _) = fixture.login(stranger_id).await;
+
+        let (status, body) = fixture.send("GET", "/sessions", Some(&token), None).await;
+        let mut ids = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|session| session["id"].as_str().unwrap().parse().unwrap())
+            .collect::<Vec<Uuid>>();
+        ids.sort();
+        let mut expected = vec![current, other];
+        expected.sort();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(ids, expected);
+
+        let uri = format!("/sessions/{}", foreign);
+        assert_eq!(
+            fixture.send("DELETE", &uri, Some(&token), None).await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert!(fixture.sessions.get(foreign).await.unwrap().is_some());
+
+        let uri = format!("/sessions/{}", other);
+        assert_eq!(
+            fixture.send("DELETE", &uri, Some(&token), None).await.0,
+            StatusCode::NO_CONTENT
+        );
+        assert!(fixture.sessions.get(other).await.unwrap().is_none());
+        let events = fixture.audit.export(user_id).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "token.revoked");
+        assert_eq!(events[0].details["session_id"], other.to_string());
+    }
+
+    // Test checks if users only list their own devices.
+    #[tokio::test]
+    async fn test_devices() {
+        let fixture = fixture();
+        let user_id = fixture.user("ada@example.com", "correct horse");
+        let stranger_id = fixture.user("eve@example.com", "correct horse");
+        let (_, token) = fixture.login(user_id).await;
+        for (user_id, user_agent) in [(user_id, "Laptop"), (stranger_id, "Phone")] {
+            fixture
+                .devices
+                .record(DeviceLogin {
+                    user_id,
+                    fingerprint: user_agent.to_lowercase(),
+                    user_agent: Some(user_agent.to_string()),
+                    ip: None,
+                    country: Some("DE".to_string()),
+                })
+                .await
+                .unwrap();
+        }
+
+        let (status, body) = fixture.send("GET", "/devices", Some(&token), None).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["user_agent"], "Laptop");
+        assert_eq!(body[0]["countries"], serde_json::json!(["DE"]));
+    }
+
+    // Test checks if the deletion requires a login and its cancellation a valid token.
+    #[tokio::test]
+    async fn test_cancel_deletion() {
+        let fixture = fixture();
+        let user_id = fixture.user("ada@example.com", "correct horse");
+
+        assert_eq!(
+            fixture.send("POST", "/delete", None, None).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let guess = serde_json::json!({ "token": "guess" });
+        let (status, body) = fixture
+            .send("POST", "/delete/cancel", None, Some(guess))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "token.invalid");
+
+        fixture
+            .tokens
+            .store(NewToken {
+                kind: TokenKind::DeletionCancel,
+                user_id,
+                session_id: None,
+                token: "emailed".to_string(),
+                expires_at: Utc::now() + Duration::minutes(1),
+            })
+            .await
+            .unwrap();
+        let cancel = serde_json::json!({ "token": "emailed" });
+        let (status, body) = fixture
+            .send("POST", "/delete/cancel", None, Some(cancel.clone()))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "users.not_scheduled");
+
+        let (_, body) = fixture
+            .send("POST", "/delete/cancel", None, Some(cancel))
+            .await;
+        assert_eq!(body["error"]["code"], "token.invalid");
+    }
+}
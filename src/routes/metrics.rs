@@ -0,0 +1,155 @@
+//! Metrics route.
+
+// Imports from external crates
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json, Router,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
+
+// Local imports
+use crate::core::db::stats::{self, StatementStats};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+/// Path of the metrics endpoint.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Path of the query statistics endpoint.
+pub const QUERIES_PATH: &str = "/metrics/queries";
+
+/// Content type of the Prometheus text format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// ## Declares the metrics routes.
+///
+/// ## Parameters
+/// - `queries`: `bool` - If `database.query_metrics` is set, which
+///   adds the query statistics endpoint.
+pub fn routes(queries: bool) -> Routes<PrometheusHandle> {
+    let routes = Routes::new().get(METRICS_PATH, AuthPolicy::Public, metrics);
+    match queries {
+        true => routes
+            .get(QUERIES_PATH, AuthPolicy::Public, query_stats)
+            .delete(QUERIES_PATH, AuthPolicy::Public, reset_query_stats),
+        false => routes,
+    }
+}
+
+/// ## Builds the metrics router.
+///
+/// ## Parameters
+/// - `handle`: `PrometheusHandle` - Handle of the installed recorder.
+/// - `queries`: `bool` - If the query statistics endpoint is served.
+pub fn router(handle: PrometheusHandle, queries: bool) -> Router {
+    routes(queries).router(None).with_state(handle)
+}
+
+/// ## Metrics in the Prometheus text format.
+///
+/// Served when `metrics.enabled` is set.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "meta",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String))
+)]
+pub async fn metrics(State(handle): State<PrometheusHandle>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], handle.render())
+}
+
+/// ## Totals per database statement, most time spent first.
+///
+/// Served when `metrics.enabled` and `database.query_metrics` are set.
+#[utoipa::path(
+    get,
+    path = "/metrics/queries",
+    tag = "meta",
+    responses((status = 200, description = "Totals per database statement", body = [StatementStats]))
+)]
+pub async fn query_stats() -> Json<Vec<StatementStats>> {
+    Json(stats::snapshot())
+}
+
+/// ## Forgets the totals per database statement.
+///
+/// The metrics of `/metrics` keep counting.
+#[utoipa::path(
+    delete,
+    path = "/metrics/queries",
+    tag = "meta",
+    responses((status = 204, description = "Totals were reset"))
+)]
+pub async fn reset_query_stats() -> StatusCode {
+    stats::reset();
+
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use tower::ServiceExt;
+
+    // Test checks if recorded metrics are rendered in the text format.
+    #[tokio::test]
+    async fn test_metrics() {
+        let handle = crate::core::metrics::install().unwrap();
+        metrics::counter!("test_metrics_route_total").increment(1);
+
+        let response = router(handle, false)
+            .oneshot(
+                Request::builder()
+                    .uri(METRICS_PATH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        assert!(String::from_utf8_lossy(&body).contains("test_metrics_route_total 1"));
+    }
+
+    // Test checks if the query statistics are only served when enabled
+    #[tokio::test]
+    async fn test_query_stats() {
+        let handle = crate::core::metrics::install().unwrap();
+        stats::record(
+            "select test_query_stats",
+            std::time::Duration::from_millis(3),
+            false,
+        );
+
+        let request = || {
+            Request::builder()
+                .uri(QUERIES_PATH)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = router(handle.clone(), false)
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = router(handle, true).oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let stats: Vec<StatementStats> = serde_json::from_slice(&body).unwrap();
+        assert!(stats
+            .iter()
+            .any(|stats| stats.statement == "select test_query_stats"));
+    }
+}
@@ -0,0 +1,268 @@
+//! Routes module.
+//!
+//! Module contains the HTTP handlers of the application.
+//! Every handler is annotated with `#[utoipa::path]` and
+//! listed in `openapi::ApiDoc`, so it shows up in the
+//! OpenAPI document. Submodules declare their routes
+//! with `registry::Routes`, `registry` mounts them.
+
+// References to submodules
+pub mod admin;
+pub mod auth;
+pub mod dev;
+pub mod device;
+pub mod health;
+pub mod jwks;
+pub mod magic_link;
+pub mod me;
+pub mod metrics;
+pub mod oauth;
+pub mod openapi;
+pub mod refresh;
+pub mod registry;
+pub mod scim;
+pub mod static_files;
+#[cfg(feature = "ui")]
+pub mod ui;
+pub mod version;
+
+// Imports from external crates
+use axum::{middleware, Extension, Router};
+use std::sync::Arc;
+
+// Local imports
+use crate::core::auth::anomaly::LoginAnomalies;
+use crate::core::auth::issuer::TokenIssuer;
+use crate::core::auth::rate_limit::RateLimiter;
+use crate::core::config::{AuthMode, TenantStrategy, TokenFormat};
+use crate::core::err::AppError;
+use crate::core::health::HealthChecker;
+use crate::core::http::authz::{authorize, Authorizer};
+use crate::core::http::cookies::Cookies;
+use crate::core::http::csrf::{protect, Csrf};
+use crate::core::http::ip_filter::{ip_filter, IpFilter};
+use crate::core::http::principal::Authenticator;
+use crate::core::repo::postgres::{PgAccessTokenRepo, PgTenantRepo};
+use crate::core::state::AppState;
+use crate::core::tenancy::{resolve_tenant, strip_tenant, TenantResolver};
+use crate::core::users::roles::RoleRepo;
+use registry::RouteRegistry;
+
+/// ## Registers all application routes.
+///
+/// In the `session` auth mode the signed session cookie is
+/// accepted, `/auth/csrf` is added and every state-changing
+/// request is checked for a CSRF token. Magic links are
+/// added to `/auth` when enabled, their rate limits and
+/// login throttle follow the reloaded configuration. With `auth.token_format`
+/// set to `opaque` access tokens are stored and resolved
+/// on every request. `/oauth` serves the
+/// client credentials grant of other services and, with
+/// `device_code` enabled, the device grant whose codes
+/// users approve on `/device`. With `ui` enabled the login
+/// and consent pages are served below `/ui`. Files of
+/// `static_files.dir` are served below `static_files.path`.
+/// In development the captured mail is listed at
+/// `/dev/mailbox`.
+/// Identity providers provision users and role members
+/// through SCIM below `/scim/v2`. With `ip_filter` enabled
+/// `/admin` and `/scim/v2` only serve the allowed client
+/// addresses. Routes matching a pattern of `[authz]`
+/// additionally require one of its roles or scopes.
+///
+/// With tenancy enabled `/auth`, `/me`, `/device`, `/admin`
+/// and SCIM are handled as the tenant of the request. The `path`
+/// strategy serves them below `{path_prefix}/{tenant}`.
+///
+/// ## Parameters
+/// - `state`: `&AppState` - Configuration, database, webhooks and signing keys.
+///
+/// ## Returns
+/// + `Result<RouteRegistry, AppError>`
+///     - `Ok(RouteRegistry)`: Routes of every submodule.
+///     - `Err(AppError)`: If the state of a submodule can not be built.
+pub fn registry(state: &AppState) -> Result<RouteRegistry, AppError> {
+    let config = &state.config;
+    let db = state.db.clone();
+    let webhooks = state.webhooks.clone();
+    let mut checker = HealthChecker::new(&config.health, db.write().clone());
+    if let Some(mailer) = &state.mailer {
+        checker = checker.with_mailer(mailer.clone(), config.mail.critical);
+    }
+    let checker = Arc::new(checker);
+    let me = me::MeState::new(config, db.clone(), webhooks.clone())?;
+    let limiter = Arc::new(RateLimiter::default());
+    let mut access_tokens = TokenIssuer::new(state.keys.clone(), &config.tokens);
+    let mut authenticator =
+        Authenticator::new(state.keys.clone(), &config.tokens, me.sessions.clone());
+    let cookies = Arc::new(Cookies::new(&config.auth, &config.app)?);
+    if config.auth.mode == AuthMode::Session {
+        authenticator =
+            authenticator.with_session_cookie(&config.auth.session_cookie, cookies.clone());
+    }
+    if config.auth.token_format == TokenFormat::Opaque {
+        let store = Arc::new(PgAccessTokenRepo::new(db.write().clone()));
+        access_tokens = access_tokens.with_store(store.clone());
+        authenticator = authenticator.with_opaque_tokens(store);
+    }
+    let authenticator = Arc::new(authenticator);
+    let authorizer = match config.authz.routes.is_empty() {
+        true => None,
+        false => Some(Arc::new(Authorizer::new(
+            &config.authz,
+            authenticator.clone(),
+            RoleRepo::new(db.clone()),
+        )?)),
+    };
+
+    let mut tenant_routes = RouteRegistry::new();
+    if config.magic_link.enabled {
+        let anomalies = LoginAnomalies::new(config, db.clone(), webhooks.clone())?;
+        let links = magic_link::MagicLinkState::new(
+            config,
+            db.clone(),
+            webhooks.clone(),
+            access_tokens.clone(),
+            limiter,
+            cookies.clone(),
+            anomalies,
+        )?;
+        if let Some(updates) = &state.updates {
+            links.subscribe(updates.clone());
+        }
+        tenant_routes = tenant_routes.merge(magic_link::routes().mount("/auth", links, None));
+    }
+    let csrf = match config.auth.mode {
+        AuthMode::Session => {
+            let csrf = Arc::new(Csrf::new(&config.auth, cookies.clone())?);
+            tenant_routes = tenant_routes.merge(auth::routes().mount(
+                "/auth",
+                csrf.clone(),
+                Some(&authenticator),
+            ));
+            Some(csrf)
+        }
+        AuthMode::Jwt => {
+            let state = refresh::RefreshState::new(config, db.clone(), access_tokens.clone());
+            tenant_routes = tenant_routes.merge(refresh::routes().mount("/auth", state, None));
+            None
+        }
+    };
+
+    let admin_state = admin::AdminState::new(db.clone(), webhooks);
+    let mut admin_routes = admin::routes().mount("/admin", admin_state.clone(), None);
+    let mut scim_routes = scim::routes().mount(scim::SCIM_PATH, admin_state, None);
+    if config.ip_filter.enabled {
+        let filter = Arc::new(IpFilter::new(&config.ip_filter)?);
+        let layer = middleware::from_fn_with_state(filter, ip_filter);
+        admin_routes = admin_routes.layer("ip_filter", |router| router.route_layer(layer.clone()));
+        scim_routes = scim_routes.layer("ip_filter", |router| router.route_layer(layer));
+    }
+
+    tenant_routes = tenant_routes
+        .merge(me::routes().mount("/me", me, Some(&authenticator)))
+        .merge(admin_routes)
+        .merge(scim_routes);
+    if config.device_code.enabled {
+        tenant_routes = tenant_routes.merge(device::routes().mount(
+            "/device",
+            device::DeviceState::new(db.clone()),
+            Some(&authenticator),
+        ));
+    }
+    #[cfg(feature = "ui")]
+    if config.ui.enabled {
+        tenant_routes = tenant_routes.merge(
+            ui::routes(config.magic_link.enabled, config.device_code.enabled).mount(
+                "/ui",
+                Arc::new(ui::UiState::new(config)),
+                None,
+            ),
+        );
+    }
+
+    let mut registry = RouteRegistry::new()
+        .merge(health::routes().mount("", checker, None))
+        .merge(version::routes().mount(
+            "",
+            Arc::new(version::version_info(&config.server.tls)),
+            None,
+        ))
+        .merge(oauth::routes(config.device_code.enabled).mount(
+            "/oauth",
+            oauth::OAuthState::new(config, db.clone(), access_tokens),
+            None,
+        ));
+    if let Some(mailbox) = &state.mailbox {
+        registry = registry.merge(dev::routes().mount("", mailbox.clone(), None));
+    }
+    if config.static_files.enabled {
+        let files = Arc::new(static_files::StaticFiles::new(&config.static_files)?);
+        registry =
+            registry.merge(static_files::routes().mount(&config.static_files.path, files, None));
+    }
+
+    // Rules are checked inside the tenant resolution,
+    // so roles are looked up in the tenant of the request
+    let registry = authorized(registry, authorizer.as_ref());
+    let tenant_routes = authorized(tenant_routes, authorizer.as_ref());
+    let mut registry = match &config.tenancy {
+        tenancy if !tenancy.enabled => registry.merge(tenant_routes),
+        tenancy => {
+            let resolver = Arc::new(TenantResolver::new(
+                tenancy,
+                Arc::new(PgTenantRepo::new(db.clone())),
+            ));
+            let tenant_routes = tenant_routes.layer("tenant", |router| {
+                router.layer(middleware::from_fn_with_state(resolver, resolve_tenant))
+            });
+
+            match tenancy.strategy {
+                TenantStrategy::Path => registry.merge(tenant_routes.nest_with(
+                    &format!("{}/:tenant", tenancy.path_prefix),
+                    |router| {
+                        Router::new().nest_service(
+                            &tenancy.path_prefix,
+                            Router::new()
+                                .fallback_service(router)
+                                .layer(middleware::map_request(strip_tenant)),
+                        )
+                    },
+                )),
+                TenantStrategy::Header | TenantStrategy::Subdomain => registry.merge(tenant_routes),
+            }
+        }
+    };
+
+    if let Some(csrf) = csrf {
+        registry = registry.layer("csrf", |router| {
+            router.layer(middleware::from_fn_with_state(csrf, protect))
+        });
+    }
+
+    let localization = Arc::new(config.localization.clone());
+    Ok(registry.map(|router| router.layer(Extension(localization))))
+}
+
+/// ## Wraps the routes a rule of `[authz]` applies to in `authorize`.
+///
+/// ## Parameters
+/// - `registry`: `RouteRegistry` - Routes to authorize.
+/// - `authorizer`: `Option<&Arc<Authorizer>>` - Rules of the routes,
+///   `None` without rules.
+fn authorized(registry: RouteRegistry, authorizer: Option<&Arc<Authorizer>>) -> RouteRegistry {
+    let Some(authorizer) = authorizer else {
+        return registry;
+    };
+
+    registry.layer_matching(
+        "authz",
+        |entry| authorizer.applies(&entry.path),
+        |router| {
+            router.route_layer(middleware::from_fn_with_state(
+                authorizer.clone(),
+                authorize,
+            ))
+        },
+    )
+}
@@ -0,0 +1,1423 @@
+//! OAuth routes.
+//!
+//! Service-to-service authentication with the client
+//! credentials grant. `/oauth/token` exchanges the ID
+//! and secret of a registered client for an access
+//! token with the requested scopes, `/oauth/introspect`
+//! tells resource servers if a token is still active and
+//! `/oauth/revoke` revokes it.
+//! Clients authenticate with HTTP Basic or, as a
+//! fallback, with `client_id` and `client_secret` in the
+//! form body. Errors use the OAuth error body.
+//!
+//! With `device_code` enabled, CLI and IoT clients get
+//! a device and a user code from `/oauth/device/code`
+//! and poll `/oauth/token` until the user approved the
+//! user code on the verification page.
+
+// Imports from external crates
+use axum::{
+    extract::{rejection::FormRejection, State},
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL, PRAGMA, USER_AGENT, WWW_AUTHENTICATE},
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Form, Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Duration, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Local imports
+use crate::core::audit::{AuditEntry, AuditEvent, AuditLog};
+use crate::core::auth::funnel::{LoginFailure, LoginFunnel, LoginStep};
+use crate::core::auth::issuer::TokenIssuer;
+use crate::core::auth::jwt::AccessClaims;
+use crate::core::config::{AppConfig, AuthSettings, DeviceCodeSettings};
+use crate::core::db::DbExecutor;
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::client_ip::ClientIp;
+use crate::core::repo::postgres::{PgClientRepo, PgDeviceCodeRepo, PgSessionRepo, PgUserRepo};
+use crate::core::repo::{
+    Client, ClientRepo, DeviceCode, DeviceCodeRepo, DeviceCodeStatus, NewDeviceCode, NewSession,
+    SessionRepo, UserRepo,
+};
+use crate::core::tenancy::with_tenant;
+use crate::routes::me::random_token;
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::oauth::{
+    DeviceAuthorizationRequest, DeviceAuthorizationResponse, IntrospectionRequest,
+    IntrospectionResponse, OAuthError, RevocationRequest, TokenRequest, TokenResponse,
+};
+
+/// Grant type of service-to-service tokens.
+pub const CLIENT_CREDENTIALS: &str = "client_credentials";
+
+/// Grant type of devices polling for their token.
+pub const DEVICE_CODE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Letters of user codes, without vowels so no words are spelled.
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// Letters of a user code.
+const USER_CODE_LEN: usize = 8;
+
+/// New user codes drawn when a code is already in use.
+const USER_CODE_ATTEMPTS: usize = 3;
+
+/// Scope a client needs to introspect tokens.
+pub const INTROSPECT_SCOPE: &str = "oauth:introspect";
+
+/// ## OAuth state struct.
+///
+/// ## Fields
+/// + `clients`: `Arc<dyn ClientRepo>` - Registered clients.
+/// + `sessions`: `Arc<dyn SessionRepo>` - Sessions of user tokens.
+/// + `users`: `Arc<dyn UserRepo>` - Users approving devices.
+/// + `device_codes`: `Arc<dyn DeviceCodeRepo>` - Codes of the device grant.
+/// + `access_tokens`: `TokenIssuer` - Issuer of the access tokens.
+/// + `auth`: `AuthSettings` - Lifetime of the sessions of devices.
+/// + `device_settings`: `DeviceCodeSettings` - Verification page, lifetime
+///   and poll interval of device codes.
+/// + `audit`: `AuditLog` - Audit log of the revocations.
+#[derive(Clone)]
+pub struct OAuthState {
+    pub clients: Arc<dyn ClientRepo>,
+    pub sessions: Arc<dyn SessionRepo>,
+    pub users: Arc<dyn UserRepo>,
+    pub device_codes: Arc<dyn DeviceCodeRepo>,
+    pub access_tokens: TokenIssuer,
+    pub auth: AuthSettings,
+    pub device_settings: DeviceCodeSettings,
+    pub audit: AuditLog,
+}
+
+impl OAuthState {
+    /// ## Creates a new `OAuthState` backed by Postgres.
+    ///
+    /// ## Parameters
+    /// - `config`: `&AppConfig` - Auth and device code settings.
+    /// - `db`: `impl Into<DbExecutor>` - Executor or pool of the repositories.
+    /// - `access_tokens`: `TokenIssuer` - Issuer of the access tokens.
+    pub fn new(config: &AppConfig, db: impl Into<DbExecutor>, access_tokens: TokenIssuer) -> Self {
+        let db = db.into();
+
+        OAuthState {
+            clients: Arc::new(PgClientRepo::new(db.clone())),
+            sessions: Arc::new(PgSessionRepo::new(db.clone()).with_outbox(config.outbox.enabled)),
+            users: Arc::new(PgUserRepo::new(db.clone())),
+            device_codes: Arc::new(PgDeviceCodeRepo::new(db.write().clone())),
+            access_tokens,
+            auth: config.auth.clone(),
+            device_settings: config.device_code.clone(),
+            audit: AuditLog::new(db),
+        }
+    }
+}
+
+/// ## Declares the OAuth routes.
+///
+/// ## Parameters
+/// - `device_code`: `bool` - Serve `/device/code` of the device grant.
+///
+/// ## Returns
+/// - `Routes<OAuthState>`: Routes to nest under `/oauth`.
+pub fn routes(device_code: bool) -> Routes<OAuthState> {
+    let routes = Routes::new()
+        .post("/token", AuthPolicy::Client, token)
+        .post("/introspect", AuthPolicy::Client, introspect)
+        .post("/revoke", AuthPolicy::Client, revoke);
+
+    match device_code {
+        true => routes.post("/device/code", AuthPolicy::Client, device_authorization),
+        false => routes,
+    }
+}
+
+/// ## Builds the OAuth router.
+///
+/// `/device/code` is only served with `device_code` enabled.
+///
+/// ## Parameters
+/// - `state`: `OAuthState` - Shared state of the routes.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `/oauth`.
+pub fn router(state: OAuthState) -> Router {
+    routes(state.device_settings.enabled)
+        .router(None)
+        .with_state(state)
+}
+
+/// ## Rejection of the OAuth endpoints.
+///
+/// Protocol errors are answered with the OAuth error
+/// body, server errors with the usual `ErrorBody`.
+///
+/// # Variants
+/// - `OAuth` - Protocol error with its status.
+/// - `App` - Error of the server.
+pub enum Rejection {
+    OAuth(StatusCode, OAuthError),
+    App(AppError),
+}
+
+impl Rejection {
+    /// ## Builds a protocol error.
+    fn oauth(status: StatusCode, error: &str, description: &str) -> Self {
+        Rejection::OAuth(
+            status,
+            OAuthError {
+                error: error.to_string(),
+                error_description: Some(description.to_string()),
+            },
+        )
+    }
+
+    /// ## Builds the error of a malformed request.
+    fn invalid_request(description: &str) -> Self {
+        Rejection::oauth(StatusCode::BAD_REQUEST, "invalid_request", description)
+    }
+
+    /// ## Builds the error of an unknown, expired or used grant.
+    fn invalid_grant(description: &str) -> Self {
+        Rejection::oauth(StatusCode::BAD_REQUEST, "invalid_grant", description)
+    }
+
+    /// ## Builds the error of failed client authentication.
+    fn invalid_client() -> Self {
+        Rejection::oauth(
+            StatusCode::UNAUTHORIZED,
+            "invalid_client",
+            "Client authentication failed",
+        )
+    }
+}
+
+impl From<AppError> for Rejection {
+    fn from(error: AppError) -> Self {
+        Rejection::App(error)
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        match self {
+            Rejection::OAuth(status, body) => {
+                let mut response = (status, no_store(), Json(body)).into_response();
+                if status == StatusCode::UNAUTHORIZED {
+                    response.headers_mut().insert(
+                        WWW_AUTHENTICATE,
+                        HeaderValue::from_static("Basic realm=\"oauth\""),
+                    );
+                }
+                response
+            }
+            Rejection::App(error) => error.into_response(),
+        }
+    }
+}
+
+/// ## Issues an access token to a client or device.
+///
+/// The `client_credentials` grant issues a token of the
+/// client. Requested scopes must be allowed for the
+/// client, without `scope` every allowed scope is granted.
+/// With `device_code` enabled, the device grant issues a
+/// token of the user who approved the device, until then
+/// polls are answered with `authorization_pending`, or
+/// `slow_down` when they come faster than the interval.
+#[utoipa::path(
+    post,
+    path = "/oauth/token",
+    tag = "oauth",
+    request_body(content = TokenRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Access token of the client or device", body = TokenResponse),
+        (status = 400, description = "Malformed request, unsupported grant or scope, or a device grant that is not approved", body = OAuthError),
+        (status = 401, description = "Client authentication failed", body = OAuthError)
+    )
+)]
+pub async fn token(
+    State(state): State<OAuthState>,
+    client: Option<ClientIp>,
+    headers: HeaderMap,
+    request: Result<Form<TokenRequest>, FormRejection>,
+) -> Result<Response, Rejection> {
+    let Form(request) =
+        request.map_err(|_| Rejection::invalid_request("Body must be a form with a grant_type"))?;
+    if request.grant_type == DEVICE_CODE && state.device_settings.enabled {
+        return device_token(&state, client, &headers, request).await;
+    }
+    if request.grant_type != CLIENT_CREDENTIALS {
+        return Err(Rejection::oauth(
+            StatusCode::BAD_REQUEST,
+            "unsupported_grant_type",
+            "Grant type is not supported",
+        ));
+    }
+
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    let scopes = granted_scopes(&client, request.scope.as_deref())?;
+
+    let access_token = state
+        .access_tokens
+        .issue(&client.client_id, &scopes)
+        .await?;
+    tracing::info!(client_id = %client.client_id, scope = %scopes.join(" "), "Issued client token");
+
+    let body = TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.access_tokens.settings().ttl,
+        scope: scopes.join(" "),
+    };
+
+    Ok((no_store(), Json(body)).into_response())
+}
+
+/// ## Starts the device authorization of a client.
+///
+/// Device shows the user code and the verification URL
+/// to the user, then polls `/oauth/token` with the device
+/// code. Clients that can not keep a secret only send
+/// their `client_id`.
+#[utoipa::path(
+    post,
+    path = "/oauth/device/code",
+    tag = "oauth",
+    request_body(content = DeviceAuthorizationRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Device and user code", body = DeviceAuthorizationResponse),
+        (status = 400, description = "Malformed request or scope", body = OAuthError),
+        (status = 401, description = "Client authentication failed", body = OAuthError)
+    )
+)]
+pub async fn device_authorization(
+    State(state): State<OAuthState>,
+    headers: HeaderMap,
+    request: Result<Form<DeviceAuthorizationRequest>, FormRejection>,
+) -> Result<Response, Rejection> {
+    let Form(request) =
+        request.map_err(|_| Rejection::invalid_request("Body must be a form with a client_id"))?;
+
+    let client = identify_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    let scopes = granted_scopes(&client, request.scope.as_deref())?;
+
+    let settings = &state.device_settings;
+    let expires_at = Utc::now() + Duration::seconds(settings.ttl as i64);
+    let mut attempts = 1;
+    let (device_code, code) = loop {
+        let device_code = random_token()?;
+        let user_code = user_code()?;
+        let created = state
+            .device_codes
+            .create(NewDeviceCode {
+                client_id: client.client_id.clone(),
+                device_code: device_code.clone(),
+                user_code,
+                scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+                expires_at,
+            })
+            .await;
+        match created {
+            Err(e) if e.code == "oauth.user_code_taken" && attempts < USER_CODE_ATTEMPTS => {
+                attempts += 1
+            }
+            created => break (device_code, created?),
+        }
+    };
+    tracing::info!(client_id = %client.client_id, scope = %scopes.join(" "), "Issued device code");
+
+    let user_code = format_user_code(&code.user_code);
+    let body = DeviceAuthorizationResponse {
+        device_code,
+        verification_uri_complete: format!("{}?user_code={}", settings.verification_url, user_code),
+        verification_uri: settings.verification_url.clone(),
+        user_code,
+        expires_in: settings.ttl,
+        interval: settings.interval,
+    };
+
+    Ok((no_store(), Json(body)).into_response())
+}
+
+/// ## Issues the token of an approved device.
+///
+/// A device code is used once, the token belongs to a
+/// new session of the user in the user's tenant. Polls
+/// are counted by the login funnel.
+async fn device_token(
+    state: &OAuthState,
+    client: Option<ClientIp>,
+    headers: &HeaderMap,
+    request: TokenRequest,
+) -> Result<Response, Rejection> {
+    let Some(device_code) = request.device_code.as_deref() else {
+        return Err(Rejection::invalid_request(
+            "Device grant needs a device_code",
+        ));
+    };
+    let oauth_client = identify_client(
+        state,
+        headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+
+    let mut funnel = LoginFunnel::new("device_code");
+    if let Some(ClientIp(ip)) = client {
+        funnel = funnel.client(ip);
+    }
+
+    let code = state
+        .device_codes
+        .poll(&oauth_client.client_id, device_code)
+        .await
+        .inspect_err(funnel.error(LoginStep::TokenValid))?;
+    let Some(code) = code else {
+        funnel.failed(LoginStep::TokenValid, LoginFailure::InvalidToken);
+        return Err(Rejection::invalid_grant("Device code is invalid"));
+    };
+    let approved = approved(state, &code).await;
+    let (user_id, tenant) = approved.inspect_err(|rejection| poll_failed(&funnel, rejection))?;
+    funnel.identify(user_id);
+    funnel.reached(LoginStep::TokenValid);
+
+    let ip = client.map(|ClientIp(ip)| ip.to_string());
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let session_ttl = state.auth.session_ttl as i64;
+    let session = with_tenant(tenant, async {
+        let user = state.users.get(user_id).await?;
+        if user.disabled {
+            return Err(Rejection::oauth(
+                StatusCode::BAD_REQUEST,
+                "access_denied",
+                "User is disabled",
+            ));
+        }
+
+        Ok(state
+            .sessions
+            .create(NewSession {
+                user_id,
+                ip,
+                user_agent,
+                expires_at: Utc::now() + Duration::seconds(session_ttl),
+            })
+            .await?)
+    })
+    .await;
+    let session = session.inspect_err(|rejection| {
+        let reason = match rejection {
+            Rejection::OAuth(..) => LoginFailure::UserDisabled,
+            Rejection::App(_) => LoginFailure::Error,
+        };
+        funnel.failed(LoginStep::TokenIssued, reason);
+    })?;
+
+    let scopes: Vec<&str> = code.scopes.iter().map(String::as_str).collect();
+    let access_token = state
+        .access_tokens
+        .issue_for_session(user_id, session.id, &scopes)
+        .await
+        .inspect_err(funnel.error(LoginStep::TokenIssued))?;
+    funnel.reached(LoginStep::TokenIssued);
+    tracing::info!(
+        client_id = %oauth_client.client_id,
+        %user_id,
+        session_id = %session.id,
+        "Issued device token"
+    );
+
+    let body = TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.access_tokens.settings().ttl,
+        scope: scopes.join(" "),
+    };
+
+    Ok((no_store(), Json(body)).into_response())
+}
+
+/// ## Records a rejected poll in the login funnel.
+///
+/// Polls of devices still waiting for the user are not
+/// failures, the device keeps polling.
+fn poll_failed(funnel: &LoginFunnel, rejection: &Rejection) {
+    let reason = match rejection {
+        Rejection::OAuth(_, body) => match body.error.as_str() {
+            "authorization_pending" | "slow_down" => return,
+            "expired_token" => LoginFailure::TokenExpired,
+            "access_denied" => LoginFailure::Denied,
+            _ => LoginFailure::InvalidToken,
+        },
+        Rejection::App(_) => LoginFailure::Error,
+    };
+
+    funnel.failed(LoginStep::TokenValid, reason);
+}
+
+/// ## Returns the user and tenant of an approved device code.
+///
+/// Approved and denied codes are deleted, so they are
+/// answered only once.
+async fn approved(state: &OAuthState, code: &DeviceCode) -> Result<(Uuid, String), Rejection> {
+    let now = Utc::now();
+    if code.expires_at <= now {
+        return Err(Rejection::oauth(
+            StatusCode::BAD_REQUEST,
+            "expired_token",
+            "Device code has expired",
+        ));
+    }
+
+    match (code.status, code.user_id, &code.tenant) {
+        (DeviceCodeStatus::Pending, _, _) => {
+            let interval = Duration::seconds(state.device_settings.interval as i64);
+            if code
+                .polled_at
+                .is_some_and(|polled_at| now - polled_at < interval)
+            {
+                Err(Rejection::oauth(
+                    StatusCode::BAD_REQUEST,
+                    "slow_down",
+                    "Device polls faster than the interval",
+                ))
+            } else {
+                Err(Rejection::oauth(
+                    StatusCode::BAD_REQUEST,
+                    "authorization_pending",
+                    "User has not approved the device yet",
+                ))
+            }
+        }
+        (DeviceCodeStatus::Denied, _, _) => {
+            state.device_codes.delete(code.id).await?;
+            Err(Rejection::oauth(
+                StatusCode::BAD_REQUEST,
+                "access_denied",
+                "User denied the device",
+            ))
+        }
+        (DeviceCodeStatus::Approved, Some(user_id), Some(tenant)) => {
+            // Only the poll that deletes the code gets the token
+            if !state.device_codes.delete(code.id).await? {
+                return Err(Rejection::invalid_grant("Device code was already used"));
+            }
+            Ok((user_id, tenant.clone()))
+        }
+        (DeviceCodeStatus::Approved, _, _) => {
+            Err(Rejection::invalid_grant("Device code is invalid"))
+        }
+    }
+}
+
+/// ## Tells if a token is active.
+///
+/// Caller must be a client allowed the `oauth:introspect`
+/// scope. Tokens of revoked sessions and of disabled
+/// clients are inactive.
+#[utoipa::path(
+    post,
+    path = "/oauth/introspect",
+    tag = "oauth",
+    request_body(content = IntrospectionRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "State of the token", body = IntrospectionResponse),
+        (status = 401, description = "Client authentication failed", body = OAuthError),
+        (status = 403, description = "Client may not introspect tokens", body = OAuthError)
+    )
+)]
+pub async fn introspect(
+    State(state): State<OAuthState>,
+    headers: HeaderMap,
+    request: Result<Form<IntrospectionRequest>, FormRejection>,
+) -> Result<Response, Rejection> {
+    let Form(request) =
+        request.map_err(|_| Rejection::invalid_request("Body must be a form with a token"))?;
+
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    if !client.allows(INTROSPECT_SCOPE) {
+        return Err(Rejection::oauth(
+            StatusCode::FORBIDDEN,
+            "insufficient_scope",
+            "Client may not introspect tokens",
+        ));
+    }
+
+    let body = inspect(&state, &request.token).await?;
+
+    Ok((no_store(), Json(body)).into_response())
+}
+
+/// ## Revokes an access token.
+///
+/// Clients revoke the tokens issued to them, clients
+/// allowed the `oauth:introspect` scope any token. Only
+/// opaque tokens are revoked at once, JWTs stay valid
+/// until they expire. Unknown, foreign and invalid tokens
+/// are answered the same (RFC 7009). Revocations are
+/// audited.
+#[utoipa::path(
+    post,
+    path = "/oauth/revoke",
+    tag = "oauth",
+    request_body(content = RevocationRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Token is revoked or was never active"),
+        (status = 401, description = "Client authentication failed", body = OAuthError)
+    )
+)]
+pub async fn revoke(
+    State(state): State<OAuthState>,
+    ip: Option<ClientIp>,
+    headers: HeaderMap,
+    request: Result<Form<RevocationRequest>, FormRejection>,
+) -> Result<Response, Rejection> {
+    let Form(request) =
+        request.map_err(|_| Rejection::invalid_request("Body must be a form with a token"))?;
+
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    let claims = verified(&state, &request.token).await?;
+    let allowed = claims
+        .as_ref()
+        .is_some_and(|claims| claims.sub == client.client_id || client.allows(INTROSPECT_SCOPE));
+    if allowed && state.access_tokens.revoke(&request.token).await? {
+        tracing::info!(client_id = %client.client_id, "Revoked access token");
+        let mut entry = AuditEntry::new(AuditEvent::TokenRevoked)
+            .details(serde_json::json!({ "client_id": client.client_id }));
+        // Tokens of the client credentials grant belong to no user
+        if let Some(user_id) = claims.and_then(|claims| Uuid::parse_str(&claims.sub).ok()) {
+            entry = entry.user(user_id);
+        }
+        if let Some(ClientIp(ip)) = ip {
+            entry = entry.ip(&ip.to_string());
+        }
+        state.audit.record(entry).await?;
+    }
+
+    Ok((StatusCode::OK, no_store()).into_response())
+}
+
+/// ## Authenticates the client of the request.
+///
+/// HTTP Basic credentials win over the ones in the
+/// body, sending both is a malformed request.
+async fn authenticate_client(
+    state: &OAuthState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<Client, Rejection> {
+    let (client_id, client_secret) = match (basic_credentials(headers)?, client_id, client_secret) {
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+            return Err(Rejection::invalid_request(
+                "Client credentials must be sent only once",
+            ))
+        }
+        (Some((id, secret)), None, None) => (id, secret),
+        (None, Some(id), Some(secret)) => (id.to_string(), secret.to_string()),
+        (None, _, _) => return Err(Rejection::invalid_client()),
+    };
+
+    match state.clients.get(&client_id).await? {
+        Some(client) if !client.disabled && client.verify_secret(&client_secret) => Ok(client),
+        _ => Err(Rejection::invalid_client()),
+    }
+}
+
+/// ## Identifies the client of a device.
+///
+/// Clients sending a secret are authenticated, public
+/// clients only need an enabled `client_id`.
+async fn identify_client(
+    state: &OAuthState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<Client, Rejection> {
+    if headers.contains_key(AUTHORIZATION) || client_secret.is_some() {
+        return authenticate_client(state, headers, client_id, client_secret).await;
+    }
+    let Some(client_id) = client_id else {
+        return Err(Rejection::invalid_request("Request needs a client_id"));
+    };
+
+    match state.clients.get(client_id).await? {
+        Some(client) if !client.disabled => Ok(client),
+        _ => Err(Rejection::invalid_client()),
+    }
+}
+
+/// ## Returns the HTTP Basic credentials of the request.
+fn basic_credentials(headers: &HeaderMap) -> Result<Option<(String, String)>, Rejection> {
+    let Some(value) = headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let Some(encoded) = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Basic "))
+    else {
+        return Err(Rejection::invalid_client());
+    };
+
+    STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .and_then(|decoded| {
+            decoded
+                .split_once(':')
+                .map(|(id, secret)| (id.to_string(), secret.to_string()))
+        })
+        .map(Some)
+        .ok_or_else(Rejection::invalid_client)
+}
+
+/// ## Returns the scopes granted to the client.
+fn granted_scopes<'a>(
+    client: &'a Client,
+    requested: Option<&'a str>,
+) -> Result<Vec<&'a str>, Rejection> {
+    let mut scopes: Vec<&str> = match requested.map(str::trim).filter(|scope| !scope.is_empty()) {
+        Some(requested) => requested.split_whitespace().collect(),
+        None => client.scopes.iter().map(String::as_str).collect(),
+    };
+    scopes.sort_unstable();
+    scopes.dedup();
+
+    match scopes.iter().find(|scope| !client.allows(scope)) {
+        Some(scope) => Err(Rejection::oauth(
+            StatusCode::BAD_REQUEST,
+            "invalid_scope",
+            &format!("Scope '{}' is not allowed for the client", scope),
+        )),
+        None => Ok(scopes),
+    }
+}
+
+/// ## Builds the introspection response of the token.
+async fn inspect(state: &OAuthState, token: &str) -> Result<IntrospectionResponse, AppError> {
+    let Some(claims) = verified(state, token).await? else {
+        return Ok(IntrospectionResponse::default());
+    };
+
+    // Tokens of a login session end with the session
+    let client_id = match &claims.sid {
+        Some(sid) => {
+            if !session_active(state, sid, &claims.sub).await? {
+                return Ok(IntrospectionResponse::default());
+            }
+            None
+        }
+        None => match state.clients.get(&claims.sub).await? {
+            Some(client) if client.disabled => return Ok(IntrospectionResponse::default()),
+            Some(client) => Some(client.client_id),
+            None => None,
+        },
+    };
+
+    Ok(active(claims, client_id))
+}
+
+/// ## Returns the claims of a valid token, `None` for invalid ones.
+async fn verified(state: &OAuthState, token: &str) -> Result<Option<AccessClaims>, AppError> {
+    match state.access_tokens.verify(token).await {
+        Ok(claims) => Ok(Some(claims)),
+        Err(e) if e.kind == ErrorKind::Auth => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// ## Checks if the session of a user token is active.
+async fn session_active(state: &OAuthState, sid: &str, sub: &str) -> Result<bool, AppError> {
+    let (Ok(session_id), Ok(user_id)) = (Uuid::parse_str(sid), Uuid::parse_str(sub)) else {
+        return Ok(false);
+    };
+    let session = state.sessions.get(session_id).await?;
+
+    Ok(session.is_some_and(|session| session.user_id == user_id))
+}
+
+/// ## Builds the response of an active token.
+fn active(claims: AccessClaims, client_id: Option<String>) -> IntrospectionResponse {
+    IntrospectionResponse {
+        active: true,
+        scope: Some(claims.scope),
+        client_id,
+        sub: Some(claims.sub),
+        token_type: Some("Bearer".to_string()),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        iss: Some(claims.iss),
+        aud: Some(claims.aud),
+        sid: claims.sid,
+    }
+}
+
+/// ## Draws a random user code.
+fn user_code() -> Result<String, AppError> {
+    let rng = SystemRandom::new();
+    let mut code = String::with_capacity(USER_CODE_LEN);
+
+    while code.len() < USER_CODE_LEN {
+        let mut byte = [0u8; 1];
+        rng.fill(&mut byte)
+            .map_err(|_| AppError::internal("Failed to generate a user code"))?;
+        // Bytes beyond the last full round of letters would favour the first ones
+        let limit = u8::MAX - u8::MAX % USER_CODE_ALPHABET.len() as u8;
+        if byte[0] < limit {
+            code.push(USER_CODE_ALPHABET[byte[0] as usize % USER_CODE_ALPHABET.len()] as char);
+        }
+    }
+
+    Ok(code)
+}
+
+/// ## Normalizes a user code as entered by the user.
+///
+/// Case, dashes and spaces are ignored, so `bcdf-ghjk`
+/// and `BCDFGHJK` are the same code.
+pub(crate) fn normalize_user_code(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// ## Formats a normalized user code for display, e.g. `BCDF-GHJK`.
+pub(crate) fn format_user_code(code: &str) -> String {
+    match code.len() {
+        USER_CODE_LEN => format!("{}-{}", &code[..4], &code[4..]),
+        _ => code.to_string(),
+    }
+}
+
+/// ## Headers keeping tokens out of caches.
+fn no_store() -> [(HeaderName, &'static str); 2] {
+    [(CACHE_CONTROL, "no-store"), (PRAGMA, "no-cache")]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::audit::{AuditFilter, AUDIT_SORTING};
+    use crate::core::auth::jwt::{verify, JwkSet, SigningKey, Validation};
+    use crate::core::auth::keys::KeyManager;
+    use crate::core::config::TokenSettings;
+    use crate::core::http::pagination::Pagination;
+    use crate::core::repo::memory::{
+        MemoryAccessTokenRepo, MemoryClientRepo, MemoryDeviceCodeRepo, MemorySessionRepo,
+        MemoryUserRepo,
+    };
+    use crate::core::repo::NewClient;
+    use crate::core::users::User;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::header::CONTENT_TYPE,
+    };
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use tower::ServiceExt;
+
+    struct Fixture {
+        router: Router,
+        clients: Arc<MemoryClientRepo>,
+        sessions: Arc<MemorySessionRepo>,
+        users: Arc<MemoryUserRepo>,
+        device_codes: Arc<MemoryDeviceCodeRepo>,
+        signing_key: Arc<SigningKey>,
+        token_settings: TokenSettings,
+        audit: AuditLog,
+    }
+
+    // OAuth routes with the repositories in memory, two clients and the device grant.
+    async fn fixture() -> Fixture {
+        fixture_with(None).await
+    }
+
+    // Same routes issuing opaque tokens of the store, if one is given.
+    async fn fixture_with(store: Option<Arc<MemoryAccessTokenRepo>>) -> Fixture {
+        let token_settings = TokenSettings::default();
+        let clients = Arc::new(MemoryClientRepo::default());
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let users = Arc::new(MemoryUserRepo::default());
+        let device_codes = Arc::new(MemoryDeviceCodeRepo::default());
+        let audit = AuditLog::memory();
+        let signing_key = Arc::new(SigningKey::generate(&token_settings.key_id).unwrap());
+        let mut access_tokens = TokenIssuer::new(
+            Arc::new(KeyManager::new(signing_key.clone())),
+            &token_settings,
+        );
+        if let Some(store) = store {
+            access_tokens = access_tokens.with_store(store);
+        }
+
+        for (client_id, scopes) in [
+            ("billing", vec!["users:read", "users:write"]),
+            ("gateway", vec![INTROSPECT_SCOPE]),
+        ] {
+            clients
+                .create(NewClient {
+                    client_id: client_id.to_string(),
+                    secret: format!("{}-secret", client_id),
+                    name: client_id.to_string(),
+                    scopes: scopes.into_iter().map(str::to_string).collect(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let state = OAuthState {
+            clients: clients.clone(),
+            sessions: sessions.clone(),
+            users: users.clone(),
+            device_codes: device_codes.clone(),
+            access_tokens,
+            auth: AuthSettings::default(),
+            device_settings: DeviceCodeSettings {
+                enabled: true,
+                ..DeviceCodeSettings::default()
+            },
+            audit: audit.clone(),
+        };
+
+        Fixture {
+            router: router(state),
+            audit,
+            clients,
+            sessions,
+            users,
+            device_codes,
+            signing_key,
+            token_settings,
+        }
+    }
+
+    impl Fixture {
+        async fn call(
+            &self,
+            path: &str,
+            basic: Option<&str>,
+            form: &str,
+        ) -> (StatusCode, HeaderMap, serde_json::Value) {
+            let mut request =
+                Request::post(path).header(CONTENT_TYPE, "application/x-www-form-urlencoded");
+            if let Some(basic) = basic {
+                request =
+                    request.header(AUTHORIZATION, format!("Basic {}", STANDARD.encode(basic)));
+            }
+
+            let response = self
+                .router
+                .clone()
+                .oneshot(request.body(Body::from(form.to_string())).unwrap())
+                .await
+                .unwrap();
+            let status = response.status();
+            let headers = response.headers().clone();
+            let bytes = to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+
+            (
+                status,
+                headers,
+                serde_json::from_slice(&bytes).unwrap_or_default(),
+            )
+        }
+
+        fn user(&self, disabled: bool) -> Uuid {
+            let id = Uuid::new_v4();
+            self.users.insert(
+                User {
+                    id,
+                    email: format!("{}@example.com", id),
+                    display_name: None,
+                    org_id: None,
+                    disabled,
+                    password_reset_required: false,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    created_at_display: None,
+                    updated_at_display: None,
+                },
+                None,
+            );
+            id
+        }
+
+        async fn poll(&self, device_code: &str) -> (StatusCode, serde_json::Value) {
+            let (status, _, body) = self
+                .call(
+                    "/token",
+                    None,
+                    &format!(
+                        "grant_type={}&client_id=billing&device_code={}",
+                        DEVICE_CODE, device_code
+                    ),
+                )
+                .await;
+            (status, body)
+        }
+
+        async fn introspect(&self, token: &str) -> serde_json::Value {
+            let (status, _, body) = self
+                .call(
+                    "/introspect",
+                    Some("gateway:gateway-secret"),
+                    &format!("token={}", token),
+                )
+                .await;
+            assert_eq!(status, StatusCode::OK);
+            body
+        }
+    }
+
+    // Test checks if a client gets a token with the requested scopes.
+    #[tokio::test]
+    async fn test_token_issued() {
+        let fixture = fixture().await;
+
+        let (status, headers, body) = fixture
+            .call(
+                "/token",
+                Some("billing:billing-secret"),
+                "grant_type=client_credentials&scope=users%3Aread",
+            )
+            .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[CACHE_CONTROL], "no-store");
+        assert_eq!(body["token_type"], "Bearer");
+        assert_eq!(body["scope"], "users:read");
+        assert_eq!(body["expires_in"], fixture.token_settings.ttl);
+
+        let keys = JwkSet {
+            keys: vec![fixture.signing_key.jwk()],
+        };
+        let validation = Validation::new(
+            &fixture.token_settings.issuer,
+            &fixture.token_settings.audience,
+        );
+        let claims = verify(body["access_token"].as_str().unwrap(), &keys, &validation).unwrap();
+        assert_eq!(claims.sub, "billing");
+        assert_eq!(claims.scope, "users:read");
+    }
+
+    // Test checks if credentials in the body work and every allowed scope is the default.
+    #[tokio::test]
+    async fn test_token_body_credentials() {
+        let fixture = fixture().await;
+
+        let (status, _, body) = fixture
+            .call(
+                "/token",
+                None,
+                "grant_type=client_credentials&client_id=billing&client_secret=billing-secret",
+            )
+            .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scope"], "users:read users:write");
+    }
+
+    // Test checks if bad grants, clients and scopes get the matching OAuth error.
+    #[tokio::test]
+    async fn test_token_errors() {
+        let fixture = fixture().await;
+        fixture.clients.set_disabled("gateway", true).await.unwrap();
+
+        for (basic, form, status, error) in [
+            (
+                Some("billing:billing-secret"),
+                "grant_type=password",
+                StatusCode::BAD_REQUEST,
+                "unsupported_grant_type",
+            ),
+            (
+                Some("billing:billing-secret"),
+                "scope=users%3Aread",
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+            ),
+            (
+                Some("billing:wrong"),
+                "grant_type=client_credentials",
+                StatusCode::UNAUTHORIZED,
+                "invalid_client",
+            ),
+            (
+                Some("gateway:gateway-secret"),
+                "grant_type=client_credentials",
+                StatusCode::UNAUTHORIZED,
+                "invalid_client",
+            ),
+            (
+                None,
+                "grant_type=client_credentials",
+                StatusCode::UNAUTHORIZED,
+                "invalid_client",
+            ),
+            (
+                Some("billing:billing-secret"),
+                "grant_type=client_credentials&client_id=billing&client_secret=billing-secret",
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+            ),
+            (
+                Some("billing:billing-secret"),
+                "grant_type=client_credentials&scope=admin",
+                StatusCode::BAD_REQUEST,
+                "invalid_scope",
+            ),
+        ] {
+            let (actual, headers, body) = fixture.call("/token", basic, form).await;
+
+            assert_eq!(actual, status, "{}", form);
+            assert_eq!(body["error"], error, "{}", form);
+            assert_eq!(
+                headers.contains_key(WWW_AUTHENTICATE),
+                status == StatusCode::UNAUTHORIZED
+            );
+        }
+    }
+
+    // Test checks if only active tokens are reported active.
+    #[tokio::test]
+    async fn test_introspect() {
+        let fixture = fixture().await;
+        let (_, _, body) = fixture
+            .call(
+                "/token",
+                Some("billing:billing-secret"),
+                "grant_type=client_credentials",
+            )
+            .await;
+        let client_token = body["access_token"].as_str().unwrap().to_string();
+
+        let body = fixture.introspect(&client_token).await;
+        assert_eq!(body["active"], true);
+        assert_eq!(body["client_id"], "billing");
+        assert_eq!(body["sub"], "billing");
+        assert_eq!(body["scope"], "users:read users:write");
+
+        fixture.clients.set_disabled("billing", true).await.unwrap();
+        assert_eq!(
+            fixture.introspect(&client_token).await,
+            serde_json::json!({"active": false})
+        );
+        assert_eq!(
+            fixture.introspect("not-a-token").await,
+            serde_json::json!({"active": false})
+        );
+    }
+
+    // Test checks if user tokens are active only while their session is.
+    #[tokio::test]
+    async fn test_introspect_session() {
+        let fixture = fixture().await;
+        let user_id = Uuid::new_v4();
+        let session = fixture
+            .sessions
+            .create(NewSession {
+                user_id,
+                ip: None,
+                user_agent: None,
+                expires_at: Utc::now() + Duration::hours(1),
+            })
+            .await
+            .unwrap();
+        let token = fixture
+            .signing_key
+            .issue_for_session(&fixture.token_settings, user_id, session.id, &[])
+            .unwrap();
+
+        let body = fixture.introspect(&token).await;
+        assert_eq!(body["active"], true);
+        assert_eq!(body["sid"], session.id.to_string());
+        assert!(body.get("client_id").is_none());
+
+        fixture.sessions.revoke(session.id).await.unwrap();
+        assert_eq!(fixture.introspect(&token).await["active"], false);
+    }
+
+    // Test checks if opaque tokens are introspected and revoked at once and revocations are audited.
+    #[tokio::test]
+    async fn test_revoke_opaque() {
+        let fixture = fixture_with(Some(Arc::new(MemoryAccessTokenRepo::default()))).await;
+        let (status, _, body) = fixture
+            .call(
+                "/token",
+                Some("billing:billing-secret"),
+                "grant_type=client_credentials",
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let token = body["access_token"].as_str().unwrap().to_string();
+        assert!(token.starts_with("at_"));
+
+        let body = fixture.introspect(&token).await;
+        assert_eq!(body["active"], true);
+        assert_eq!(body["client_id"], "billing");
+
+        let (status, headers, _) = fixture
+            .call(
+                "/revoke",
+                Some("billing:billing-secret"),
+                &format!("token={}", token),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[CACHE_CONTROL], "no-store");
+        assert_eq!(fixture.introspect(&token).await["active"], false);
+        let filter = AuditFilter {
+            event: Some(AuditEvent::TokenRevoked),
+            ..AuditFilter::default()
+        };
+        let window = Pagination::default().window(&AUDIT_SORTING).unwrap();
+        let events = fixture.audit.query(&filter, &window).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].details["client_id"], "billing");
+
+        let (status, _, _) = fixture
+            .call("/revoke", Some("billing:billing-secret"), "token=unknown")
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, body) = fixture
+            .call(
+                "/revoke",
+                Some("billing:wrong"),
+                &format!("token={}", token),
+            )
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "invalid_client");
+    }
+
+    // Test checks if only clients with the introspection scope may introspect.
+    #[tokio::test]
+    async fn test_introspect_requires_scope() {
+        let fixture = fixture().await;
+
+        let (status, _, body) = fixture
+            .call("/introspect", Some("billing:billing-secret"), "token=abc")
+            .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "insufficient_scope");
+
+        let (status, _, body) = fixture
+            .call("/introspect", Some("gateway:wrong"), "token=abc")
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "invalid_client");
+    }
+
+    // Test checks if a public client gets a device code and a displayable user code.
+    #[tokio::test]
+    async fn test_device_authorization() {
+        let fixture = fixture().await;
+
+        let (status, headers, body) = fixture
+            .call("/device/code", None, "client_id=billing&scope=users%3Aread")
+            .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[CACHE_CONTROL], "no-store");
+        assert_eq!(body["verification_uri"], "http://localhost:8080/device");
+        assert_eq!(body["expires_in"], 600);
+        assert_eq!(body["interval"], 5);
+        let user_code = body["user_code"].as_str().unwrap();
+        assert_eq!(
+            body["verification_uri_complete"],
+            format!("http://localhost:8080/device?user_code={}", user_code)
+        );
+
+        let code = fixture
+            .device_codes
+            .find_pending(&normalize_user_code(user_code))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(code.client_id, "billing");
+        assert_eq!(code.scopes, ["users:read"]);
+
+        for (basic, form, error) in [
+            (None, "scope=users%3Aread", "invalid_request"),
+            (None, "client_id=unknown", "invalid_client"),
+            (Some("billing:wrong"), "", "invalid_client"),
+            (None, "client_id=billing&scope=admin", "invalid_scope"),
+        ] {
+            let (_, _, body) = fixture.call("/device/code", basic, form).await;
+            assert_eq!(body["error"], error, "{}", form);
+        }
+    }
+
+    // Test checks if polls are pending until the user approves, then get a user token once.
+    #[tokio::test]
+    async fn test_device_token() {
+        let fixture = fixture().await;
+        let (_, _, body) = fixture
+            .call("/device/code", None, "client_id=billing&scope=users%3Aread")
+            .await;
+        let device_code = body["device_code"].as_str().unwrap().to_string();
+        let user_code = normalize_user_code(body["user_code"].as_str().unwrap());
+
+        let (status, body) = fixture.poll(&device_code).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "authorization_pending");
+        assert_eq!(fixture.poll(&device_code).await.1["error"], "slow_down");
+
+        let user_id = fixture.user(false);
+        let code = fixture
+            .device_codes
+            .find_pending(&user_code)
+            .await
+            .unwrap()
+            .unwrap();
+        fixture
+            .device_codes
+            .decide(code.id, user_id, true)
+            .await
+            .unwrap();
+
+        let (status, body) = fixture.poll(&device_code).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["scope"], "users:read");
+        let token = body["access_token"].as_str().unwrap();
+        let body = fixture.introspect(token).await;
+        assert_eq!(body["active"], true);
+        assert_eq!(body["sub"], user_id.to_string());
+        assert_eq!(fixture.sessions.list(user_id).await.unwrap().len(), 1);
+
+        assert_eq!(fixture.poll(&device_code).await.1["error"], "invalid_grant");
+    }
+
+    // Test checks if denied, expired and disabled-user codes get no token and are counted.
+    #[test]
+    fn test_device_token_refused() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(poll_refused_codes());
+        });
+        let rendered = handle.render();
+
+        let failures = "auth_login_funnel_failures_total{method=\"device_code\"";
+        for (step, reason, count) in [
+            ("token_valid", "denied", 1),
+            ("token_valid", "invalid_token", 2),
+            ("token_valid", "token_expired", 1),
+            ("token_issued", "user_disabled", 1),
+        ] {
+            let line = format!(
+                "{},step=\"{}\",reason=\"{}\"}} {}",
+                failures, step, reason, count
+            );
+            assert!(rendered.contains(&line), "{}", line);
+        }
+        assert!(rendered.contains(
+            "auth_login_funnel_steps_total{method=\"device_code\",step=\"token_valid\"} 1"
+        ));
+    }
+
+    // Polls denied, expired, disabled-user and unknown codes.
+    async fn poll_refused_codes() {
+        let fixture = fixture().await;
+        let new = |device_code: &str, user_code: &str, ttl: i64| NewDeviceCode {
+            client_id: "billing".to_string(),
+            device_code: device_code.to_string(),
+            user_code: user_code.to_string(),
+            scopes: Vec::new(),
+            expires_at: Utc::now() + Duration::seconds(ttl),
+        };
+        let denied = fixture
+            .device_codes
+            .create(new("denied", "BBBBBBBB", 600))
+            .await
+            .unwrap();
+        let disabled = fixture
+            .device_codes
+            .create(new("disabled", "CCCCCCCC", 600))
+            .await
+            .unwrap();
+        fixture
+            .device_codes
+            .create(new("expired", "DDDDDDDD", -1))
+            .await
+            .unwrap();
+        fixture
+            .device_codes
+            .decide(denied.id, fixture.user(false), false)
+            .await
+            .unwrap();
+        fixture
+            .device_codes
+            .decide(disabled.id, fixture.user(true), true)
+            .await
+            .unwrap();
+
+        for (device_code, error) in [
+            ("denied", "access_denied"),
+            ("denied", "invalid_grant"),
+            ("disabled", "access_denied"),
+            ("expired", "expired_token"),
+            ("unknown", "invalid_grant"),
+        ] {
+            let (status, body) = fixture.poll(device_code).await;
+
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], error, "{}", device_code);
+        }
+    }
+
+    // Test checks if user codes use the letters of the alphabet and survive sloppy typing.
+    #[test]
+    fn test_user_code() {
+        let code = user_code().unwrap();
+
+        assert_eq!(code.len(), USER_CODE_LEN);
+        assert!(code.bytes().all(|c| USER_CODE_ALPHABET.contains(&c)));
+        assert_eq!(normalize_user_code(" bcdf-ghjk "), "BCDFGHJK");
+        assert_eq!(format_user_code("BCDFGHJK"), "BCDF-GHJK");
+    }
+}
@@ -0,0 +1,280 @@
+//! OpenAPI routes.
+//!
+//! Module assembles the OpenAPI document from the
+//! annotated handlers and serves it together with
+//! the optional Swagger UI.
+
+// Imports from external crates
+use axum::{http::Method, routing::get, Json, Router};
+use utoipa::{
+    openapi::{OpenApi as OpenApiDocument, Ref, RefOr, Response, ResponseBuilder},
+    Modify, OpenApi,
+};
+use utoipa_swagger_ui::SwaggerUi;
+
+// Local imports
+use super::registry::{AuthPolicy, RouteEntry, RouteRegistry};
+use super::{
+    admin, auth, dev, device, health, jwks, magic_link, me, metrics, oauth, refresh, scim, version,
+};
+use crate::core::audit::AuditRecord;
+use crate::core::config::OpenApiSettings;
+use crate::core::health::HealthReport;
+use crate::core::http::error::{ErrorBody, ErrorDetails, ProblemDetails, PROBLEM_JSON};
+use crate::core::http::pagination::Paginated;
+use crate::core::users::User;
+
+/// Path of the OpenAPI document.
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+/// Path of the Swagger UI.
+pub const SWAGGER_UI_PATH: &str = "/docs";
+
+/// ## OpenAPI document of the application.
+///
+/// New handlers have to be added to `paths`, schemas
+/// of their bodies are collected automatically.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "axum_auth"),
+    paths(
+        health::healthz,
+        health::healthz_details,
+        version::version,
+        jwks::jwks,
+        metrics::metrics,
+        metrics::query_stats,
+        metrics::reset_query_stats,
+        auth::csrf_token,
+        magic_link::request_link,
+        magic_link::verify_link,
+        refresh::refresh,
+        oauth::token,
+        oauth::introspect,
+        oauth::revoke,
+        oauth::device_authorization,
+        device::show_device,
+        device::verify_device,
+        me::get_me,
+        me::export_me,
+        me::change_email,
+        me::confirm_email,
+        me::undo_email,
+        me::change_password,
+        me::list_sessions,
+        me::revoke_session,
+        me::list_devices,
+        me::delete_account,
+        me::cancel_deletion,
+        admin::users::list_users,
+        admin::users::get_user,
+        admin::users::disable_user,
+        admin::users::force_password_reset,
+        admin::users::delete_user,
+        admin::roles::list_roles,
+        admin::roles::create_role,
+        admin::roles::get_role,
+        admin::roles::update_role,
+        admin::roles::delete_role,
+        admin::roles::list_user_roles,
+        admin::roles::assign_role,
+        admin::roles::revoke_role,
+        admin::audit::list_audit_events,
+        scim::list_users,
+        scim::create_user,
+        scim::get_user,
+        scim::patch_user,
+        scim::list_groups,
+        scim::create_group,
+        scim::get_group,
+        scim::patch_group,
+        dev::mailbox,
+        dev::clear_mailbox,
+    ),
+    components(schemas(
+        ErrorBody,
+        ErrorDetails,
+        ProblemDetails,
+        User,
+        Paginated<User>,
+        AuditRecord,
+        Paginated<AuditRecord>,
+        HealthReport,
+        version::VersionInfo,
+        jwks::JwkSet
+    )),
+    modifiers(&ErrorResponses),
+    tags(
+        (name = "health", description = "Liveness and readiness probes"),
+        (name = "meta", description = "Version, deployment information and metrics"),
+        (name = "auth", description = "Passwordless login, refresh tokens and CSRF tokens of cookie sessions"),
+        (name = "oauth", description = "Client credentials and device grants, token introspection and revocation"),
+        (name = "me", description = "Account of the authenticated user"),
+        (name = "admin", description = "Administration, requires the admin role"),
+        (name = "scim", description = "SCIM 2.0 provisioning of users and role members"),
+        (name = "dev", description = "Captured mail, only served in development")
+    )
+)]
+pub struct ApiDoc;
+
+/// ## Documents the JSON error body on every operation.
+///
+/// Every handler error is rendered as `ErrorBody`, or as
+/// `ProblemDetails` when asked for, so both are added as
+/// the `default` response instead of repeating them on
+/// each handler.
+pub struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        let response: RefOr<Response> = ResponseBuilder::new()
+            .description("Error with the request ID, see `ErrorBody`")
+            .content(
+                "application/json",
+                utoipa::openapi::ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name("ErrorBody")))
+                    .build(),
+            )
+            .content(
+                PROBLEM_JSON,
+                utoipa::openapi::ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name("ProblemDetails")))
+                    .build(),
+            )
+            .build()
+            .into();
+
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+                &mut item.options,
+                &mut item.head,
+                &mut item.patch,
+                &mut item.trace,
+            ];
+
+            for operation in operations.into_iter().flatten() {
+                operation
+                    .responses
+                    .responses
+                    .entry("default".to_string())
+                    .or_insert_with(|| response.clone());
+            }
+        }
+    }
+}
+
+/// ## Builds the OpenAPI router.
+///
+/// ## Parameters
+/// - `settings`: `&OpenApiSettings` - OpenAPI settings.
+///
+/// ## Returns
+/// - `Router`: Router serving the document and the Swagger UI,
+///   empty when the document is disabled.
+pub fn router(settings: &OpenApiSettings) -> Router {
+    if !settings.enabled {
+        return Router::new();
+    }
+
+    if settings.swagger_ui {
+        return Router::new()
+            .merge(SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_PATH, ApiDoc::openapi()));
+    }
+
+    let document = ApiDoc::openapi();
+    Router::new().route(OPENAPI_PATH, get(move || async move { Json(document) }))
+}
+
+/// ## Registers the OpenAPI routes.
+///
+/// ## Parameters
+/// - `settings`: `&OpenApiSettings` - OpenAPI settings.
+///
+/// ## Returns
+/// - `RouteRegistry`: Router of `router` with its routes, empty
+///   when the document is disabled.
+pub fn registry(settings: &OpenApiSettings) -> RouteRegistry {
+    let mut entries = Vec::new();
+    if settings.enabled {
+        entries.push(RouteEntry::new(
+            Method::GET,
+            OPENAPI_PATH,
+            AuthPolicy::Public,
+        ));
+    }
+    if settings.enabled && settings.swagger_ui {
+        entries.push(RouteEntry::new(
+            Method::GET,
+            SWAGGER_UI_PATH,
+            AuthPolicy::Public,
+        ));
+    }
+
+    RouteRegistry::from_router(router(settings), entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use tower::ServiceExt;
+
+    async fn status(router: Router, uri: &str) -> StatusCode {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    // Test checks if every operation documents the JSON error body and problem details.
+    #[test]
+    fn test_api_doc_error_responses() {
+        let document = ApiDoc::openapi();
+        let healthz = document.paths.paths[health::HEALTHZ_PATH]
+            .get
+            .as_ref()
+            .unwrap();
+
+        assert!(healthz.responses.responses.contains_key("200"));
+        assert!(healthz.responses.responses.contains_key("default"));
+        let schemas = document.components.unwrap().schemas;
+
+        assert!(schemas.contains_key("ErrorBody"));
+        assert!(schemas.contains_key("ProblemDetails"));
+    }
+
+    // Test checks if the document and the Swagger UI are served according to the settings.
+    #[tokio::test]
+    async fn test_router_settings() {
+        let disabled = OpenApiSettings {
+            enabled: false,
+            swagger_ui: true,
+        };
+        let document = OpenApiSettings::default();
+        let swagger_ui = OpenApiSettings {
+            enabled: true,
+            swagger_ui: true,
+        };
+
+        assert_eq!(
+            status(router(&disabled), OPENAPI_PATH).await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(router(&document), OPENAPI_PATH).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(router(&document), "/docs/").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(router(&swagger_ui), OPENAPI_PATH).await,
+            StatusCode::OK
+        );
+        assert_eq!(status(router(&swagger_ui), "/docs/").await, StatusCode::OK);
+    }
+}
@@ -0,0 +1,333 @@
+//! Refresh token routes.
+//!
+//! In the `jwt` auth mode a login hands out a refresh
+//! token next to the short-lived access token. Refresh
+//! tokens of a login form a family bound to its session,
+//! so stateless access tokens stay manageable: the family
+//! is listed by `/me/sessions` and revoked with the
+//! session by `DELETE /me/sessions/{id}`.
+//!
+//! `/auth/refresh` rotates the token, every refresh token
+//! is used once. A used token presented again means it
+//! leaked, the whole family is revoked.
+
+// Imports from external crates
+use axum::{extract::State, Json, Router};
+use std::sync::Arc;
+
+// Local imports
+use super::me::random_token;
+use crate::core::auth::issuer::TokenIssuer;
+use crate::core::config::AppConfig;
+use crate::core::db::DbExecutor;
+use crate::core::err::AppError;
+use crate::core::repo::postgres::{PgSessionRepo, PgTokenRepo};
+use crate::core::repo::{NewToken, SessionRepo, TokenKind, TokenRepo};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::sessions::{LoginResponse, RefreshRequest, Session};
+
+/// ## Refresh state struct.
+///
+/// ## Fields
+/// + `sessions`: `Arc<dyn SessionRepo>` - Sessions of the token families.
+/// + `tokens`: `Arc<dyn TokenRepo>` - Refresh tokens.
+/// + `access_tokens`: `TokenIssuer` - Issuer of the access tokens.
+#[derive(Clone)]
+pub struct RefreshState {
+    pub sessions: Arc<dyn SessionRepo>,
+    pub tokens: Arc<dyn TokenRepo>,
+    pub access_tokens: TokenIssuer,
+}
+
+impl RefreshState {
+    /// ## Creates a new `RefreshState` backed by Postgres.
+    ///
+    /// ## Parameters
+    /// - `config`: `&AppConfig` - Outbox settings of the sessions.
+    /// - `db`: `impl Into<DbExecutor>` - Executor or pool of the repositories.
+    /// - `access_tokens`: `TokenIssuer` - Issuer of the access tokens.
+    pub fn new(config: &AppConfig, db: impl Into<DbExecutor>, access_tokens: TokenIssuer) -> Self {
+        let db = db.into();
+
+        RefreshState {
+            sessions: Arc::new(PgSessionRepo::new(db.clone()).with_outbox(config.outbox.enabled)),
+            tokens: Arc::new(PgTokenRepo::new(db)),
+            access_tokens,
+        }
+    }
+}
+
+/// ## Declares the refresh token routes.
+///
+/// ## Returns
+/// - `Routes<RefreshState>`: Routes to nest under `/auth`.
+pub fn routes() -> Routes<RefreshState> {
+    Routes::new().post("/refresh", AuthPolicy::Public, refresh)
+}
+
+/// ## Builds the refresh token router.
+///
+/// ## Parameters
+/// - `state`: `RefreshState` - Shared state of the routes.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `/auth`.
+pub fn router(state: RefreshState) -> Router {
+    routes().router(None).with_state(state)
+}
+
+/// ## Issues the next refresh token of the session.
+///
+/// Token expires with the session, so the family never
+/// outlives the login.
+///
+/// ## Parameters
+/// - `tokens`: `&dyn TokenRepo` - Store of the refresh tokens.
+/// - `session`: `&Session` - Session of the family.
+///
+/// ## Returns
+/// + `Result<String, AppError>`
+///     - `Ok(String)`: Refresh token as given to the client.
+///     - `Err(AppError)`: If the token could not be stored.
+pub(crate) async fn issue_refresh_token(
+    tokens: &dyn TokenRepo,
+    session: &Session,
+) -> Result<String, AppError> {
+    let token = random_token()?;
+    tokens
+        .store(NewToken {
+            kind: TokenKind::Refresh,
+            user_id: session.user_id,
+            session_id: Some(session.id),
+            token: token.clone(),
+            expires_at: session.expires_at,
+        })
+        .await?;
+
+    Ok(token)
+}
+
+/// ## Exchanges a refresh token for new tokens.
+///
+/// Refresh token is used up, the answer carries the
+/// next one of the family. Presenting a used token
+/// revokes the session and every token of the family.
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    tag = "auth",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "New access and refresh token of the session", body = LoginResponse),
+        (status = 401, description = "Refresh token is invalid, expired, reused or its session revoked")
+    )
+)]
+pub async fn refresh(
+    State(state): State<RefreshState>,
+    Json(request): Json<RefreshRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    let invalid =
+        || AppError::auth("Refresh token is invalid or expired").with_code("token.invalid");
+
+    let found = state
+        .tokens
+        .find(TokenKind::Refresh, &request.refresh_token)
+        .await?;
+    let Some(token) = found else {
+        return Err(reused(&state, &request.refresh_token)
+            .await?
+            .unwrap_or_else(invalid));
+    };
+    // Only the request that revokes the token may rotate it
+    if !state.tokens.revoke(token.id).await? {
+        return Err(invalid());
+    }
+
+    let session = match token.session_id {
+        Some(session_id) => state.sessions.get(session_id).await?,
+        None => None,
+    };
+    let Some(session) = session.filter(|session| session.user_id == token.user_id) else {
+        return Err(AppError::auth("Session is revoked or expired").with_code("session.revoked"));
+    };
+    if let Err(e) = state.sessions.touch(session.id).await {
+        tracing::warn!(session_id = %session.id, error = %e, "Failed to touch session");
+    }
+
+    let refresh_token = issue_refresh_token(state.tokens.as_ref(), &session).await?;
+    let access_token = state
+        .access_tokens
+        .issue_for_session(session.user_id, session.id, &[])
+        .await?;
+
+    Ok(Json(LoginResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.access_tokens.settings().ttl,
+        session_id: session.id,
+        refresh_token: Some(refresh_token),
+        password_change_required: false,
+    }))
+}
+
+/// ## Revokes the family of a reused refresh token.
+///
+/// ## Returns
+/// + `Result<Option<AppError>, AppError>`
+///     - `Ok(Some(AppError))`: `token.reused` if the token was rotated
+///       while its session is still active, the session is revoked.
+///     - `Ok(None)`: If the token is unknown or its session ended.
+///     - `Err(AppError)`: If a repository failed.
+async fn reused(state: &RefreshState, token: &str) -> Result<Option<AppError>, AppError> {
+    let revoked = state.tokens.find_revoked(TokenKind::Refresh, token).await?;
+    let Some(session_id) = revoked.and_then(|token| token.session_id) else {
+        return Ok(None);
+    };
+    if !state.sessions.revoke(session_id).await? {
+        return Ok(None);
+    }
+    state.tokens.revoke_session(session_id).await?;
+    tracing::warn!(session_id = %session_id, "Refresh token was reused, session revoked");
+
+    Ok(Some(
+        AppError::auth("Refresh token was already used, the session is revoked")
+            .with_code("token.reused"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::{jwt::SigningKey, keys::KeyManager};
+    use crate::core::config::TokenSettings;
+    use crate::core::repo::memory::{MemorySessionRepo, MemoryTokenRepo};
+    use crate::core::repo::NewSession;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::{header::CONTENT_TYPE, StatusCode},
+    };
+    use chrono::{Duration, Utc};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    struct Fixture {
+        router: Router,
+        sessions: Arc<MemorySessionRepo>,
+        tokens: Arc<MemoryTokenRepo>,
+    }
+
+    impl Fixture {
+        async fn login(&self) -> (Session, String) {
+            let session = self
+                .sessions
+                .create(NewSession {
+                    user_id: Uuid::new_v4(),
+                    ip: Some("192.0.2.1".to_string()),
+                    user_agent: Some("Firefox".to_string()),
+                    expires_at: Utc::now() + Duration::days(1),
+                })
+                .await
+                .unwrap();
+            let token = issue_refresh_token(self.tokens.as_ref(), &session)
+                .await
+                .unwrap();
+
+            (session, token)
+        }
+
+        async fn refresh(&self, token: &str) -> Result<LoginResponse, String> {
+            let request = Request::post("/refresh")
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "refresh_token": token }).to_string(),
+                ))
+                .unwrap();
+            let response = self.router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+            match status {
+                StatusCode::OK => Ok(serde_json::from_slice(&body).unwrap()),
+                _ => {
+                    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                    Err(body["error"]["code"].as_str().unwrap().to_string())
+                }
+            }
+        }
+    }
+
+    fn fixture() -> Fixture {
+        let settings = TokenSettings::default();
+        let sessions = Arc::new(MemorySessionRepo::default());
+        let tokens = Arc::new(MemoryTokenRepo::default());
+        let state = RefreshState {
+            sessions: sessions.clone(),
+            tokens: tokens.clone(),
+            access_tokens: TokenIssuer::new(
+                Arc::new(KeyManager::new(
+                    SigningKey::generate(&settings.key_id).unwrap(),
+                )),
+                &settings,
+            ),
+        };
+
+        Fixture {
+            router: router(state),
+            sessions,
+            tokens,
+        }
+    }
+
+    // Test checks if refresh tokens rotate within their session.
+    #[tokio::test]
+    async fn test_refresh_rotates() {
+        let fixture = fixture();
+        let (session, token) = fixture.login().await;
+
+        let refreshed = fixture.refresh(&token).await.unwrap();
+
+        assert_eq!(refreshed.session_id, session.id);
+        let next = refreshed.refresh_token.unwrap();
+        assert_ne!(next, token);
+        assert_eq!(fixture.refresh(&next).await.unwrap().session_id, session.id);
+        assert_eq!(
+            fixture.refresh("guessed").await.unwrap_err(),
+            "token.invalid"
+        );
+    }
+
+    // Test checks if a reused refresh token revokes the whole family.
+    #[tokio::test]
+    async fn test_refresh_reuse_revokes_family() {
+        let fixture = fixture();
+        let (session, token) = fixture.login().await;
+        let (other, other_token) = fixture.login().await;
+        let next = fixture
+            .refresh(&token)
+            .await
+            .unwrap()
+            .refresh_token
+            .unwrap();
+
+        assert_eq!(fixture.refresh(&token).await.unwrap_err(), "token.reused");
+        assert_eq!(fixture.sessions.get(session.id).await.unwrap(), None);
+        assert_eq!(fixture.refresh(&next).await.unwrap_err(), "token.invalid");
+        assert_eq!(fixture.refresh(&token).await.unwrap_err(), "token.invalid");
+
+        // Other families stay until their session is revoked
+        let other_next = fixture.refresh(&other_token).await.unwrap();
+        fixture.sessions.revoke(other.id).await.unwrap();
+        fixture.tokens.revoke_session(other.id).await.unwrap();
+
+        assert_eq!(
+            fixture
+                .refresh(&other_next.refresh_token.unwrap())
+                .await
+                .unwrap_err(),
+            "token.invalid"
+        );
+    }
+}
@@ -0,0 +1,513 @@
+//! Route registry module.
+//!
+//! Route modules declare their routes with `Routes`: the
+//! method, the path, the handler and who may call it.
+//! Routes of users are authenticated by the registry, so
+//! the declaration is what protects them. The registry
+//! collects the routers of the modules together with a
+//! table of every route and the middleware wrapping it,
+//! which the `routes` subcommand prints.
+
+// Imports from external crates
+use axum::{
+    handler::Handler,
+    http::Method,
+    middleware,
+    routing::{self, MethodRouter},
+    Router,
+};
+use std::fmt;
+use std::sync::Arc;
+
+// Local imports
+use crate::core::http::principal::{authenticate, Authenticator};
+
+/// Name of the authentication middleware in the route table.
+pub const AUTHENTICATE: &str = "authenticate";
+
+/// ## Caller a route is meant for.
+///
+/// # Variants
+/// - `Public` - Anyone, no credentials are checked.
+/// - `Client` - Registered OAuth client, its credentials are
+///   checked by the handler.
+/// - `User` - Access token or session cookie of a user, checked
+///   by the registry before the handler runs.
+/// - `Admin` - Principal authorized by the RBAC policy of the handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthPolicy {
+    Public,
+    Client,
+    User,
+    Admin,
+}
+
+impl AuthPolicy {
+    /// ## Returns the name shown in the route table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthPolicy::Public => "public",
+            AuthPolicy::Client => "client",
+            AuthPolicy::User => "user",
+            AuthPolicy::Admin => "admin",
+        }
+    }
+}
+
+/// ## Route of the table struct.
+///
+/// ## Fields
+/// + `method`: `Method` - HTTP method of the route.
+/// + `path`: `String` - Full path, `:name` for path parameters.
+/// + `auth`: `AuthPolicy` - Caller the route is meant for.
+/// + `middleware`: `Vec<&'static str>` - Middleware of the route,
+///   innermost first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteEntry {
+    pub method: Method,
+    pub path: String,
+    pub auth: AuthPolicy,
+    pub middleware: Vec<&'static str>,
+}
+
+impl RouteEntry {
+    /// ## Creates a new `RouteEntry` without middleware.
+    pub fn new(method: Method, path: impl Into<String>, auth: AuthPolicy) -> Self {
+        RouteEntry {
+            method,
+            path: path.into(),
+            auth,
+            middleware: Vec::new(),
+        }
+    }
+}
+
+/// ## Declared route struct.
+struct Route<S> {
+    method: Method,
+    path: &'static str,
+    auth: AuthPolicy,
+    handler: MethodRouter<S>,
+}
+
+/// ## Routes of a module struct.
+///
+/// Routes sharing a path are merged into one method
+/// router, so each route declares a single method.
+///
+/// ## Examples
+/// ```
+/// use axum_auth::routes::registry::{AuthPolicy, Routes};
+///
+/// async fn ping() -> &'static str {
+///     "pong"
+/// }
+///
+/// let routes: Routes<()> = Routes::new().get("/ping", AuthPolicy::Public, ping);
+/// let registry = routes.mount("/debug", (), None);
+///
+/// assert_eq!(registry.entries()[0].path, "/debug/ping");
+/// ```
+pub struct Routes<S> {
+    routes: Vec<Route<S>>,
+}
+
+impl<S> Default for Routes<S> {
+    fn default() -> Self {
+        Routes { routes: Vec::new() }
+    }
+}
+
+impl<S> Routes<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    /// ## Creates an empty set of routes.
+    pub fn new() -> Self {
+        Routes::default()
+    }
+
+    /// ## Adds a `GET` route.
+    pub fn get<H, T>(self, path: &'static str, auth: AuthPolicy, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::GET, path, auth, routing::get(handler))
+    }
+
+    /// ## Adds a `POST` route.
+    pub fn post<H, T>(self, path: &'static str, auth: AuthPolicy, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::POST, path, auth, routing::post(handler))
+    }
+
+    /// ## Adds a `PUT` route.
+    pub fn put<H, T>(self, path: &'static str, auth: AuthPolicy, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::PUT, path, auth, routing::put(handler))
+    }
+
+    /// ## Adds a `PATCH` route.
+    pub fn patch<H, T>(self, path: &'static str, auth: AuthPolicy, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::PATCH, path, auth, routing::patch(handler))
+    }
+
+    /// ## Adds a `DELETE` route.
+    pub fn delete<H, T>(self, path: &'static str, auth: AuthPolicy, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::DELETE, path, auth, routing::delete(handler))
+    }
+
+    /// ## Adds the routes of another module with the same state.
+    pub fn merge(mut self, other: Routes<S>) -> Self {
+        self.routes.extend(other.routes);
+        self
+    }
+
+    /// ## Builds the router of the routes.
+    ///
+    /// Routes of users are wrapped in `authenticate`. Without
+    /// an authenticator they are not, and their handlers
+    /// reject every request that lacks a principal.
+    ///
+    /// ## Parameters
+    /// - `authenticator`: `Option<&Arc<Authenticator>>` - Verifier of
+    ///   the routes of users.
+    pub fn router(self, authenticator: Option<&Arc<Authenticator>>) -> Router<S> {
+        self.routes
+            .into_iter()
+            .fold(Router::new(), |router, route| {
+                let handler = match (route.auth, authenticator) {
+                    (AuthPolicy::User, Some(authenticator)) => route.handler.route_layer(
+                        middleware::from_fn_with_state(authenticator.clone(), authenticate),
+                    ),
+                    _ => route.handler,
+                };
+
+                router.route(route.path, handler)
+            })
+    }
+
+    /// ## Builds the routes with their state into a registry.
+    ///
+    /// ## Parameters
+    /// - `prefix`: `&str` - Path the routes are nested under, empty
+    ///   to serve them as declared.
+    /// - `state`: `S` - Shared state of the handlers.
+    /// - `authenticator`: `Option<&Arc<Authenticator>>` - Verifier of
+    ///   the routes of users.
+    pub fn mount(
+        self,
+        prefix: &str,
+        state: S,
+        authenticator: Option<&Arc<Authenticator>>,
+    ) -> RouteRegistry {
+        let entries = self
+            .routes
+            .iter()
+            .map(|route| {
+                let mut entry =
+                    RouteEntry::new(route.method.clone(), join(prefix, route.path), route.auth);
+                if route.auth == AuthPolicy::User && authenticator.is_some() {
+                    entry.middleware.push(AUTHENTICATE);
+                }
+
+                entry
+            })
+            .collect();
+        let router = self.router(authenticator).with_state(state);
+        let router = match prefix {
+            "" => router,
+            prefix => Router::new().nest(prefix, router),
+        };
+
+        RouteRegistry { router, entries }
+    }
+
+    /// ## Adds a route with its method router.
+    fn route(
+        mut self,
+        method: Method,
+        path: &'static str,
+        auth: AuthPolicy,
+        handler: MethodRouter<S>,
+    ) -> Self {
+        self.routes.push(Route {
+            method,
+            path,
+            auth,
+            handler,
+        });
+        self
+    }
+}
+
+/// ## Route registry struct.
+///
+/// ## Fields
+/// + `router`: `Router` - Router of every registered route.
+/// + `entries`: `Vec<RouteEntry>` - Table of the registered routes.
+#[derive(Debug, Default)]
+pub struct RouteRegistry {
+    router: Router,
+    entries: Vec<RouteEntry>,
+}
+
+impl RouteRegistry {
+    /// ## Creates an empty registry.
+    pub fn new() -> Self {
+        RouteRegistry::default()
+    }
+
+    /// ## Creates a registry of a router not declared with `Routes`.
+    ///
+    /// ## Parameters
+    /// - `router`: `Router` - Router serving the routes.
+    /// - `entries`: `Vec<RouteEntry>` - Routes the router serves.
+    pub fn from_router(router: Router, entries: Vec<RouteEntry>) -> Self {
+        RouteRegistry { router, entries }
+    }
+
+    /// ## Adds the routes of another registry.
+    pub fn merge(mut self, other: RouteRegistry) -> Self {
+        self.router = self.router.merge(other.router);
+        self.entries.extend(other.entries);
+        self
+    }
+
+    /// ## Wraps every route in a middleware listed in the table.
+    ///
+    /// ## Parameters
+    /// - `name`: `&'static str` - Name of the middleware in the table.
+    /// - `wrap`: `F` - Applies the middleware to the router.
+    pub fn layer<F>(mut self, name: &'static str, wrap: F) -> Self
+    where
+        F: FnOnce(Router) -> Router,
+    {
+        self.router = wrap(self.router);
+        for entry in &mut self.entries {
+            entry.middleware.push(name);
+        }
+        self
+    }
+
+    /// ## Wraps every route in a middleware listed in the table for
+    /// the routes it applies to.
+    ///
+    /// ## Parameters
+    /// - `name`: `&'static str` - Name of the middleware in the table.
+    /// - `applies`: `P` - Checks if the middleware applies to a route.
+    /// - `wrap`: `F` - Applies the middleware to the router.
+    pub fn layer_matching<P, F>(mut self, name: &'static str, applies: P, wrap: F) -> Self
+    where
+        P: Fn(&RouteEntry) -> bool,
+        F: FnOnce(Router) -> Router,
+    {
+        self.router = wrap(self.router);
+        for entry in self.entries.iter_mut().filter(|entry| applies(entry)) {
+            entry.middleware.push(name);
+        }
+        self
+    }
+
+    /// ## Serves the routes below a prefix through a wrapping service.
+    ///
+    /// ## Parameters
+    /// - `prefix`: `&str` - Prefix of the paths in the table.
+    /// - `nest`: `F` - Mounts the router below the prefix.
+    pub fn nest_with<F>(mut self, prefix: &str, nest: F) -> Self
+    where
+        F: FnOnce(Router) -> Router,
+    {
+        self.router = nest(self.router);
+        for entry in &mut self.entries {
+            entry.path = join(prefix, &entry.path);
+        }
+        self
+    }
+
+    /// ## Transforms the router without changing the table.
+    pub fn map<F>(mut self, map: F) -> Self
+    where
+        F: FnOnce(Router) -> Router,
+    {
+        self.router = map(self.router);
+        self
+    }
+
+    /// ## Returns the registered routes.
+    pub fn entries(&self) -> &[RouteEntry] {
+        &self.entries
+    }
+
+    /// ## Returns the registered routes sorted by path and method.
+    pub fn table(&self) -> RouteTable {
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| {
+            (a.path.as_str(), a.method.as_str()).cmp(&(b.path.as_str(), b.method.as_str()))
+        });
+
+        RouteTable(entries)
+    }
+
+    /// ## Returns the router of every registered route.
+    pub fn into_router(self) -> Router {
+        self.router
+    }
+}
+
+/// ## Printable route table struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteTable(pub Vec<RouteEntry>);
+
+impl fmt::Display for RouteTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = |column: fn(&RouteEntry) -> usize, header: &str| {
+            self.0
+                .iter()
+                .map(column)
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        };
+        let method = width(|entry| entry.method.as_str().len(), "METHOD");
+        let path = width(|entry| entry.path.len(), "PATH");
+        let auth = width(|entry| entry.auth.as_str().len(), "AUTH");
+
+        write!(
+            f,
+            "{:method$}  {:path$}  {:auth$}  MIDDLEWARE",
+            "METHOD", "PATH", "AUTH"
+        )?;
+        for entry in &self.0 {
+            write!(
+                f,
+                "\n{:method$}  {:path$}  {:auth$}  {}",
+                entry.method.as_str(),
+                entry.path,
+                entry.auth.as_str(),
+                entry.middleware.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Joins a prefix and a path, `/` below a prefix is the prefix itself.
+fn join(prefix: &str, path: &str) -> String {
+    match (prefix, path) {
+        ("", path) => path.to_string(),
+        (prefix, "/") => prefix.to_string(),
+        (prefix, path) => format!("{}{}", prefix, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::{jwt::SigningKey, keys::KeyManager};
+    use crate::core::config::TokenSettings;
+    use crate::core::repo::memory::MemorySessionRepo;
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use tower::ServiceExt;
+
+    async fn ok() -> &'static str {
+        "ok"
+    }
+
+    async fn status(router: &Router, method: Method, uri: &str) -> StatusCode {
+        router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    // Test checks if routes of users are authenticated and public ones are not.
+    #[tokio::test]
+    async fn test_mount_authenticates_users() {
+        let tokens = TokenSettings::default();
+        let key = SigningKey::generate(&tokens.key_id).unwrap();
+        let authenticator = Arc::new(Authenticator::new(
+            Arc::new(KeyManager::new(key)),
+            &tokens,
+            Arc::new(MemorySessionRepo::default()),
+        ));
+        let routes: Routes<()> = Routes::new()
+            .get("/", AuthPolicy::User, ok)
+            .post("/", AuthPolicy::Public, ok)
+            .get("/open", AuthPolicy::Public, ok);
+        let router = routes.mount("/me", (), Some(&authenticator)).into_router();
+
+        assert_eq!(
+            status(&router, Method::GET, "/me").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(status(&router, Method::POST, "/me").await, StatusCode::OK);
+        assert_eq!(
+            status(&router, Method::GET, "/me/open").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(&router, Method::DELETE, "/me").await,
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+    }
+
+    // Test checks if the table lists the full paths with their middleware, sorted.
+    #[test]
+    fn test_table() {
+        let users: Routes<()> = Routes::new()
+            .delete("/users/:id", AuthPolicy::Admin, ok)
+            .get("/users", AuthPolicy::Admin, ok);
+        let health: Routes<()> = Routes::new().get("/healthz", AuthPolicy::Public, ok);
+        let me: Routes<()> = Routes::new().get("/", AuthPolicy::User, ok);
+        let tokens = TokenSettings::default();
+        let key = SigningKey::generate(&tokens.key_id).unwrap();
+        let authenticator = Arc::new(Authenticator::new(
+            Arc::new(KeyManager::new(key)),
+            &tokens,
+            Arc::new(MemorySessionRepo::default()),
+        ));
+        let registry = RouteRegistry::new()
+            .merge(
+                users
+                    .mount("/admin", (), None)
+                    .layer("ip_filter", |router| router),
+            )
+            .merge(me.mount("/me", (), Some(&authenticator)))
+            .merge(health.mount("", (), None))
+            .nest_with("/t/:tenant", |router| router);
+
+        assert_eq!(
+            registry.table().to_string(),
+            "METHOD  PATH                        AUTH    MIDDLEWARE\n\
+             GET     /t/:tenant/admin/users      admin   ip_filter\n\
+             DELETE  /t/:tenant/admin/users/:id  admin   ip_filter\n\
+             GET     /t/:tenant/healthz          public  \n\
+             GET     /t/:tenant/me               user    authenticate"
+        );
+    }
+}
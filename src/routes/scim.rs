@@ -0,0 +1,1107 @@
+//! SCIM provisioning routes.
+//!
+//! Identity providers create, update and deactivate the
+//! users of the tenant and manage the members of its
+//! roles through SCIM 2.0. Users are the rows of `users`,
+//! groups are the defined roles and their members hold
+//! grants with the `scim` source, so grants made by
+//! administrators or the directory sync are never
+//! revoked by the provider.
+//!
+//! The principal of the provider needs global
+//! `users.manage` for users and `roles.manage` for
+//! groups. Errors are rendered as SCIM error messages.
+
+// Imports from external crates
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Local imports
+use super::admin::AdminState;
+use crate::api::{DEFAULT_PER_PAGE, MAX_PER_PAGE};
+use crate::core::audit::{AuditEntry, AuditEvent};
+use crate::core::auth::rbac::{Channel, Permission, Principal, Scope};
+use crate::core::err::{AppError, ErrorKind};
+use crate::core::http::client_ip::ClientIp;
+use crate::core::http::error::status_code;
+use crate::core::http::pagination::Pagination;
+use crate::core::repo::{role_not_found, NewRole};
+use crate::core::users::roles::{validate_role_name, Role, RoleGrant, RoleSource};
+use crate::core::users::{NewUser, User, UserFilter, USER_SORTING};
+use crate::core::webhooks::WebhookEvent;
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::scim::{
+    ScimEmail, ScimError, ScimGroup, ScimListQuery, ScimListResponse, ScimMember, ScimMeta,
+    ScimPatchOperation, ScimPatchRequest, ScimUser, ERROR_SCHEMA, GROUP_SCHEMA, LIST_SCHEMA,
+    USER_SCHEMA,
+};
+
+/// Content type of SCIM messages.
+pub const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+/// Path the routes are nested under.
+pub const SCIM_PATH: &str = "/scim/v2";
+
+/// ## Declares the SCIM routes.
+///
+/// ## Returns
+/// - `Routes<AdminState>`: Routes to nest under `SCIM_PATH`.
+pub fn routes() -> Routes<AdminState> {
+    Routes::new()
+        .get("/Users", AuthPolicy::Admin, list_users)
+        .post("/Users", AuthPolicy::Admin, create_user)
+        .get("/Users/:id", AuthPolicy::Admin, get_user)
+        .patch("/Users/:id", AuthPolicy::Admin, patch_user)
+        .get("/Groups", AuthPolicy::Admin, list_groups)
+        .post("/Groups", AuthPolicy::Admin, create_group)
+        .get("/Groups/:id", AuthPolicy::Admin, get_group)
+        .patch("/Groups/:id", AuthPolicy::Admin, patch_group)
+}
+
+/// ## Builds the SCIM router.
+///
+/// ## Parameters
+/// - `state`: `AdminState` - Shared state of the administration routes.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `SCIM_PATH`.
+pub fn router(state: AdminState) -> Router {
+    routes().router(None).with_state(state)
+}
+
+/// ## SCIM failure struct.
+///
+/// Wraps the `AppError` of a handler, so it is rendered
+/// as a SCIM error message instead of `ErrorBody`.
+#[derive(Debug)]
+pub struct ScimFailure(pub AppError);
+
+impl From<AppError> for ScimFailure {
+    fn from(error: AppError) -> Self {
+        ScimFailure(error)
+    }
+}
+
+impl IntoResponse for ScimFailure {
+    /// Converts the error into a SCIM error message.
+    ///
+    /// Server side errors are logged and only a generic
+    /// detail is sent to the client.
+    fn into_response(self) -> Response {
+        let error = self.0;
+        let status = error
+            .status_hint
+            .and_then(|status| StatusCode::from_u16(status).ok())
+            .unwrap_or_else(|| status_code(&error.kind));
+
+        let detail = match status.is_server_error() {
+            true => {
+                tracing::error!(error = %error, "SCIM request failed");
+                "Internal server error".to_string()
+            }
+            false => error.message.clone(),
+        };
+        let body = ScimError {
+            schemas: vec![ERROR_SCHEMA.to_string()],
+            status: status.as_u16().to_string(),
+            scim_type: scim_type(error.code).map(str::to_string),
+            detail,
+        };
+
+        scim_json(status, body)
+    }
+}
+
+/// ## Returns the SCIM error type of an error code.
+fn scim_type(code: &str) -> Option<&'static str> {
+    match code {
+        "users.email_taken" => Some("uniqueness"),
+        "scim.invalid_filter" => Some("invalidFilter"),
+        "scim.invalid_path" => Some("invalidPath"),
+        "scim.invalid_value" | "roles.invalid_name" | "roles.reserved" => Some("invalidValue"),
+        _ => None,
+    }
+}
+
+/// ## Renders the body with the SCIM content type.
+fn scim_json<T: Serialize>(status: StatusCode, body: T) -> Response {
+    let mut response = (status, Json(body)).into_response();
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(SCIM_CONTENT_TYPE));
+
+    response
+}
+
+/// ## Lists users.
+///
+/// Only `userName eq "..."` filters are supported, the
+/// user name is matched exactly against the email.
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Users",
+    tag = "scim",
+    params(ScimListQuery),
+    responses((status = 200, description = "Page of users", body = ScimListResponse<ScimUser>))
+)]
+pub async fn list_users(
+    State(state): State<AdminState>,
+    principal: Principal,
+    Query(query): Query<ScimListQuery>,
+) -> Result<Response, ScimFailure> {
+    authorize(&state, &principal, Permission::ManageUsers)?;
+    let (start_index, count) = page_bounds(&query);
+
+    if let Some(filter) = &query.filter {
+        let email = parse_filter(filter, "userName")?;
+        let users: Vec<User> = state
+            .users
+            .find_by_email(&email)
+            .await?
+            .into_iter()
+            .collect();
+
+        return Ok(list_response(users.len(), 1, users, to_scim_user));
+    }
+
+    let page = (start_index - 1) / u64::from(count) + 1;
+    let window = Pagination {
+        page: Some(u32::try_from(page).unwrap_or(u32::MAX)),
+        per_page: count,
+        cursor: None,
+        sort: None,
+    }
+    .window(&USER_SORTING)?;
+    let (rows, total) = state
+        .users
+        .list(&UserFilter::default(), &Scope::All, &window)
+        .await?;
+    let users = window.paginate(rows).items;
+    let start_index = (page - 1) * u64::from(count) + 1;
+
+    Ok(list_response(
+        usize::try_from(total).unwrap_or_default(),
+        start_index,
+        users,
+        to_scim_user,
+    ))
+}
+
+/// ## Provisions a user.
+///
+/// User is created without a password and without any
+/// role, inactive users are created disabled in the same
+/// transaction.
+#[utoipa::path(
+    post,
+    path = "/scim/v2/Users",
+    tag = "scim",
+    request_body = ScimUser,
+    responses(
+        (status = 201, description = "Created user", body = ScimUser),
+        (status = 409, description = "User name is already taken", body = ScimError)
+    )
+)]
+pub async fn create_user(
+    State(state): State<AdminState>,
+    principal: Principal,
+    Json(request): Json<ScimUser>,
+) -> Result<Response, ScimFailure> {
+    authorize(&state, &principal, Permission::ManageUsers)?;
+    let email = request.user_name.trim().to_lowercase();
+    if email.is_empty() {
+        return Err(invalid_value("Attribute 'userName' is required").into());
+    }
+
+    let new = NewUser {
+        email,
+        display_name: request.display_name,
+        org_id: None,
+        password: None,
+    };
+    let user = state.users.import(new, !request.active, &[]).await?;
+
+    Ok(scim_json(StatusCode::CREATED, to_scim_user(user)))
+}
+
+/// ## Returns a user.
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Users/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "ID of the user")),
+    responses((status = 200, description = "User", body = ScimUser))
+)]
+pub async fn get_user(
+    State(state): State<AdminState>,
+    principal: Principal,
+    Path(id): Path<Uuid>,
+) -> Result<Response, ScimFailure> {
+    authorize(&state, &principal, Permission::ManageUsers)?;
+
+    let user = state.users.get(id).await?;
+
+    Ok(scim_json(StatusCode::OK, to_scim_user(user)))
+}
+
+/// ## Updates a user.
+///
+/// `active` and `displayName` can be changed, deactivated
+/// users are disabled like by an administrator.
+#[utoipa::path(
+    patch,
+    path = "/scim/v2/Users/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "ID of the user")),
+    request_body = ScimPatchRequest,
+    responses((status = 200, description = "Updated user", body = ScimUser))
+)]
+pub async fn patch_user(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ScimPatchRequest>,
+) -> Result<Response, ScimFailure> {
+    authorize(&state, &principal, Permission::ManageUsers)?;
+    let changes = UserChanges::parse(&request.operations)?;
+
+    let mut user = state.users.get(id).await?;
+    if let Some(name) = changes.display_name {
+        user = state.users.set_display_name(id, name.as_deref()).await?;
+    }
+    if let Some(active) = changes.active.filter(|active| *active == user.disabled) {
+        user = state.users.set_disabled(id, !active).await?;
+
+        if !active {
+            let details = serde_json::json!({ "channel": "scim" });
+            record(
+                &state,
+                &principal,
+                client,
+                AuditEvent::UserDisabled,
+                Some(id),
+                details,
+            )
+            .await?;
+            let data = serde_json::json!({
+                "user_id": user.id,
+                "email": user.email,
+                "org_id": user.org_id,
+            });
+            state
+                .webhooks
+                .emit(WebhookEvent::UserDisabled, data)
+                .await?;
+        }
+    }
+
+    Ok(scim_json(StatusCode::OK, to_scim_user(user)))
+}
+
+/// ## Lists groups.
+///
+/// Only `displayName eq "..."` filters are supported.
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Groups",
+    tag = "scim",
+    params(ScimListQuery),
+    responses((status = 200, description = "Page of groups", body = ScimListResponse<ScimGroup>))
+)]
+pub async fn list_groups(
+    State(state): State<AdminState>,
+    principal: Principal,
+    Query(query): Query<ScimListQuery>,
+) -> Result<Response, ScimFailure> {
+    authorize(&state, &principal, Permission::ManageRoles)?;
+    let (start_index, count) = page_bounds(&query);
+    let display_name = query
+        .filter
+        .as_deref()
+        .map(|filter| parse_filter(filter, "displayName"))
+        .transpose()?;
+
+    let roles: Vec<Role> = state
+        .role_definitions
+        .list()
+        .await?
+        .into_iter()
+        .filter(|role| {
+            display_name
+                .as_deref()
+                .is_none_or(|name| group_name(role) == name)
+        })
+        .collect();
+    let total = roles.len();
+    let roles = roles
+        .into_iter()
+        .skip(usize::try_from(start_index - 1).unwrap_or(usize::MAX))
+        .take(count as usize)
+        .collect::<Vec<_>>();
+
+    let grants = state.roles.list().await?;
+    let emails: HashMap<Uuid, String> = state.users.emails().await?.into_iter().collect();
+
+    Ok(list_response(total, start_index, roles, |role| {
+        to_scim_group(role, &grants, &emails)
+    }))
+}
+
+/// ## Provisions a group.
+///
+/// Group is mapped onto the role named after its display
+/// name, e.g. `Support Agents` onto `support-agents`. The
+/// role is defined without permissions if it does not
+/// exist yet, an existing role is taken over.
+#[utoipa::path(
+    post,
+    path = "/scim/v2/Groups",
+    tag = "scim",
+    request_body = ScimGroup,
+    responses((status = 201, description = "Created group", body = ScimGroup))
+)]
+pub async fn create_group(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Json(request): Json<ScimGroup>,
+) -> Result<Response, ScimFailure> {
+    authorize(&state, &principal, Permission::ManageRoles)?;
+    let name = role_name(&request.display_name);
+    validate_role_name(&name)?;
+
+    let existing = state.role_definitions.get(&name).await?;
+    let role = match existing {
+        Some(role) => role,
+        None => {
+            let role = state
+                .role_definitions
+                .create(NewRole {
+                    name,
+                    description: Some(request.display_name),
+                    permissions: Vec::new(),
+                })
+                .await?;
+            let details = serde_json::json!({ "channel": "scim", "role": role.name });
+            record(
+                &state,
+                &principal,
+                client,
+                AuditEvent::RoleCreated,
+                None,
+                details,
+            )
+            .await?;
+            role
+        }
+    };
+    for member in &request.members {
+        grant_member(&state, &principal, client, &role.name, member.value).await?;
+    }
+
+    let group = load_group(&state, role).await?;
+
+    Ok(scim_json(StatusCode::CREATED, group))
+}
+
+/// ## Returns a group.
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Groups/{id}",
+    tag = "scim",
+    params(("id" = String, Path, description = "Name of the role")),
+    responses((status = 200, description = "Group", body = ScimGroup))
+)]
+pub async fn get_group(
+    State(state): State<AdminState>,
+    principal: Principal,
+    Path(name): Path<String>,
+) -> Result<Response, ScimFailure> {
+    authorize(&state, &principal, Permission::ManageRoles)?;
+
+    let role = state
+        .role_definitions
+        .get(&name)
+        .await?
+        .ok_or_else(|| role_not_found(&name))?;
+    let group = load_group(&state, role).await?;
+
+    Ok(scim_json(StatusCode::OK, group))
+}
+
+/// ## Updates a group.
+///
+/// Members are added, removed or replaced, and the
+/// display name is kept as the role description. Only
+/// grants made through SCIM are revoked.
+#[utoipa::path(
+    patch,
+    path = "/scim/v2/Groups/{id}",
+    tag = "scim",
+    params(("id" = String, Path, description = "Name of the role")),
+    request_body = ScimPatchRequest,
+    responses((status = 200, description = "Updated group", body = ScimGroup))
+)]
+pub async fn patch_group(
+    State(state): State<AdminState>,
+    principal: Principal,
+    client: Option<ClientIp>,
+    Path(name): Path<String>,
+    Json(request): Json<ScimPatchRequest>,
+) -> Result<Response, ScimFailure> {
+    authorize(&state, &principal, Permission::ManageRoles)?;
+
+    let mut role = state
+        .role_definitions
+        .get(&name)
+        .await?
+        .ok_or_else(|| role_not_found(&name))?;
+    let changes = GroupChange::parse(&request.operations)?;
+    for change in changes {
+        match change {
+            GroupChange::DisplayName(display_name) => {
+                role = state
+                    .role_definitions
+                    .update(
+                        NewRole {
+                            name: role.name,
+                            description: Some(display_name),
+                            permissions: role.permissions,
+                        },
+                        role.version,
+                    )
+                    .await?;
+            }
+            GroupChange::Add(ids) => {
+                for id in ids {
+                    grant_member(&state, &principal, client, &name, id).await?;
+                }
+            }
+            GroupChange::Remove(ids) => {
+                let ids = match ids {
+                    Some(ids) => ids,
+                    None => scim_members(&state, &name).await?,
+                };
+                for id in ids {
+                    revoke_member(&state, &principal, client, &name, id).await?;
+                }
+            }
+            GroupChange::Replace(ids) => {
+                let members = scim_members(&state, &name).await?;
+                for id in members {
+                    if !ids.contains(&id) {
+                        revoke_member(&state, &principal, client, &name, id).await?;
+                    }
+                }
+                for id in ids {
+                    grant_member(&state, &principal, client, &name, id).await?;
+                }
+            }
+        }
+    }
+    let group = load_group(&state, role).await?;
+
+    Ok(scim_json(StatusCode::OK, group))
+}
+
+/// ## Changes of a user patch struct.
+///
+/// ## Fields
+/// + `active`: `Option<bool>` - New active state.
+/// + `display_name`: `Option<Option<String>>` - New display name,
+///   `Some(None)` removes it.
+#[derive(Debug, Default, PartialEq)]
+struct UserChanges {
+    active: Option<bool>,
+    display_name: Option<Option<String>>,
+}
+
+impl UserChanges {
+    /// ## Collects the changes of the operations, later ones win.
+    fn parse(operations: &[ScimPatchOperation]) -> Result<Self, AppError> {
+        let mut changes = UserChanges::default();
+
+        for operation in operations {
+            match (operation_kind(operation)?, operation.path.as_deref()) {
+                (Operation::Remove, Some(path)) if path.eq_ignore_ascii_case("displayName") => {
+                    changes.display_name = Some(None);
+                }
+                (Operation::Remove, path) => return Err(invalid_path(path.unwrap_or_default())),
+                (_, Some(path)) => {
+                    changes.set(path, operation.value.as_ref().unwrap_or(&Value::Null))?
+                }
+                (_, None) => {
+                    for (name, value) in object_value(operation)? {
+                        changes.set(name, value)?;
+                    }
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// ## Sets an attribute, `externalId` is ignored.
+    fn set(&mut self, name: &str, value: &Value) -> Result<(), AppError> {
+        match name {
+            _ if name.eq_ignore_ascii_case("active") => {
+                // Some providers send booleans as strings
+                let active = match value {
+                    Value::Bool(active) => *active,
+                    Value::String(active) if active.eq_ignore_ascii_case("true") => true,
+                    Value::String(active) if active.eq_ignore_ascii_case("false") => false,
+                    _ => return Err(invalid_value("Attribute 'active' must be a boolean")),
+                };
+                self.active = Some(active);
+            }
+            _ if name.eq_ignore_ascii_case("displayName") => {
+                let display_name = match value {
+                    Value::String(name) => Some(name.clone()),
+                    Value::Null => None,
+                    _ => return Err(invalid_value("Attribute 'displayName' must be a string")),
+                };
+                self.display_name = Some(display_name);
+            }
+            _ if name.eq_ignore_ascii_case("externalId") => {}
+            _ => return Err(invalid_path(name)),
+        }
+
+        Ok(())
+    }
+}
+
+/// ## Change of a group patch enum.
+///
+/// # Variants
+/// - `DisplayName` - Replaces the display name.
+/// - `Add` - Adds the members.
+/// - `Remove` - Removes the members, every SCIM member when `None`.
+/// - `Replace` - Replaces the SCIM members.
+#[derive(Debug, PartialEq)]
+enum GroupChange {
+    DisplayName(String),
+    Add(Vec<Uuid>),
+    Remove(Option<Vec<Uuid>>),
+    Replace(Vec<Uuid>),
+}
+
+impl GroupChange {
+    /// ## Parses the operations in order.
+    fn parse(operations: &[ScimPatchOperation]) -> Result<Vec<Self>, AppError> {
+        let mut changes = Vec::new();
+
+        for operation in operations {
+            let kind = operation_kind(operation)?;
+            let Some(path) = operation.path.as_deref() else {
+                for (name, value) in object_value(operation)? {
+                    changes.extend(GroupChange::attribute(kind, name, Some(value))?);
+                }
+                continue;
+            };
+
+            // Members are removed by `members[value eq "..."]`
+            let change = match path.split_once('[') {
+                Some((members, filter))
+                    if kind == Operation::Remove
+                        && members.eq_ignore_ascii_case("members")
+                        && filter.ends_with(']') =>
+                {
+                    let id = parse_filter(&filter[..filter.len() - 1], "value")?;
+                    let id = id
+                        .parse()
+                        .map_err(|_| invalid_value(format!("Member '{}' is invalid", id)))?;
+                    Some(GroupChange::Remove(Some(vec![id])))
+                }
+                _ => GroupChange::attribute(kind, path, operation.value.as_ref())?,
+            };
+            changes.extend(change);
+        }
+
+        Ok(changes)
+    }
+
+    /// ## Builds the change of an attribute, `id` and `externalId` are ignored.
+    fn attribute(
+        kind: Operation,
+        name: &str,
+        value: Option<&Value>,
+    ) -> Result<Option<Self>, AppError> {
+        let change = match kind {
+            _ if name.eq_ignore_ascii_case("id") || name.eq_ignore_ascii_case("externalId") => None,
+            Operation::Remove if name.eq_ignore_ascii_case("members") => {
+                Some(GroupChange::Remove(value.map(member_ids).transpose()?))
+            }
+            Operation::Add if name.eq_ignore_ascii_case("members") => {
+                Some(GroupChange::Add(member_ids(value.unwrap_or(&Value::Null))?))
+            }
+            Operation::Replace if name.eq_ignore_ascii_case("members") => Some(
+                GroupChange::Replace(member_ids(value.unwrap_or(&Value::Null))?),
+            ),
+            Operation::Add | Operation::Replace if name.eq_ignore_ascii_case("displayName") => {
+                match value {
+                    Some(Value::String(name)) => Some(GroupChange::DisplayName(name.clone())),
+                    _ => return Err(invalid_value("Attribute 'displayName' must be a string")),
+                }
+            }
+            _ => return Err(invalid_path(name)),
+        };
+
+        Ok(change)
+    }
+}
+
+/// ## Patch operation enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Add,
+    Replace,
+    Remove,
+}
+
+/// ## Parses the `op` of the operation, in any case.
+fn operation_kind(operation: &ScimPatchOperation) -> Result<Operation, AppError> {
+    match operation.op.to_ascii_lowercase().as_str() {
+        "add" => Ok(Operation::Add),
+        "replace" => Ok(Operation::Replace),
+        "remove" => Ok(Operation::Remove),
+        _ => Err(invalid_value(format!(
+            "Operation '{}' is unknown",
+            operation.op
+        ))),
+    }
+}
+
+/// ## Returns the attributes of an operation without `path`.
+fn object_value(
+    operation: &ScimPatchOperation,
+) -> Result<&serde_json::Map<String, Value>, AppError> {
+    match &operation.value {
+        Some(Value::Object(attributes)) => Ok(attributes),
+        _ => Err(invalid_value(
+            "Operations without 'path' need an object value",
+        )),
+    }
+}
+
+/// ## Parses the IDs of a `members` value.
+fn member_ids(value: &Value) -> Result<Vec<Uuid>, AppError> {
+    serde_json::from_value::<Vec<ScimMember>>(value.clone())
+        .map(|members| members.into_iter().map(|member| member.value).collect())
+        .map_err(|e| invalid_value("Attribute 'members' is invalid").with_source(e))
+}
+
+/// ## Parses an equality filter of the attribute.
+///
+/// ## Parameters
+/// - `filter`: `&str` - Filter of the request, e.g. `userName eq "ada"`.
+/// - `attribute`: `&str` - Only attribute that may be filtered.
+///
+/// ## Returns
+/// + `Result<String, AppError>`
+///     - `Ok(String)`: Compared value, unquoted.
+///     - `Err(AppError)`: `scim.invalid_filter` for any other filter.
+fn parse_filter(filter: &str, attribute: &str) -> Result<String, AppError> {
+    let invalid = || {
+        AppError::validation(format!("Filter '{}' is not supported", filter))
+            .with_code("scim.invalid_filter")
+    };
+
+    let mut parts = filter.trim().splitn(3, ' ');
+    let (Some(name), Some(op), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid());
+    };
+    if !name.eq_ignore_ascii_case(attribute) || !op.eq_ignore_ascii_case("eq") {
+        return Err(invalid());
+    }
+
+    serde_json::from_str::<String>(value.trim()).map_err(|_| invalid())
+}
+
+/// ## Returns the 1-based start index and the page size of the query.
+fn page_bounds(query: &ScimListQuery) -> (u64, u32) {
+    let start_index = query.start_index.unwrap_or(1).max(1);
+    let count = query
+        .count
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    (start_index, count)
+}
+
+/// ## Builds a list response of the resources.
+fn list_response<T, R: Serialize>(
+    total: usize,
+    start_index: u64,
+    items: Vec<T>,
+    to_resource: impl FnMut(T) -> R,
+) -> Response {
+    let resources: Vec<R> = items.into_iter().map(to_resource).collect();
+
+    scim_json(
+        StatusCode::OK,
+        ScimListResponse {
+            schemas: vec![LIST_SCHEMA.to_string()],
+            total_results: total as u64,
+            start_index,
+            items_per_page: resources.len() as u64,
+            resources,
+        },
+    )
+}
+
+/// ## Converts a user into its SCIM resource.
+fn to_scim_user(user: User) -> ScimUser {
+    ScimUser {
+        schemas: vec![USER_SCHEMA.to_string()],
+        id: Some(user.id),
+        emails: vec![ScimEmail {
+            value: user.email.clone(),
+            primary: true,
+        }],
+        user_name: user.email,
+        display_name: user.display_name,
+        active: !user.disabled,
+        meta: Some(ScimMeta {
+            resource_type: "User".to_string(),
+            created: user.created_at,
+            last_modified: user.updated_at,
+            location: format!("{}/Users/{}", SCIM_PATH, user.id),
+        }),
+    }
+}
+
+/// ## Converts a role into its SCIM group.
+fn to_scim_group(role: Role, grants: &[RoleGrant], emails: &HashMap<Uuid, String>) -> ScimGroup {
+    let members = grants
+        .iter()
+        .filter(|grant| grant.role == role.name)
+        .map(|grant| ScimMember {
+            value: grant.user_id,
+            display: emails.get(&grant.user_id).cloned(),
+        })
+        .collect();
+
+    ScimGroup {
+        schemas: vec![GROUP_SCHEMA.to_string()],
+        display_name: group_name(&role).to_string(),
+        members,
+        meta: Some(ScimMeta {
+            resource_type: "Group".to_string(),
+            created: role.created_at,
+            last_modified: role.updated_at,
+            location: format!("{}/Groups/{}", SCIM_PATH, role.name),
+        }),
+        id: Some(role.name),
+    }
+}
+
+/// ## Loads the members of the role into its SCIM group.
+async fn load_group(state: &AdminState, role: Role) -> Result<ScimGroup, AppError> {
+    let grants = state.roles.list().await?;
+    let emails: HashMap<Uuid, String> = state.users.emails().await?.into_iter().collect();
+
+    Ok(to_scim_group(role, &grants, &emails))
+}
+
+/// ## Returns the users holding the role through SCIM.
+async fn scim_members(state: &AdminState, role: &str) -> Result<Vec<Uuid>, AppError> {
+    Ok(state
+        .roles
+        .list()
+        .await?
+        .into_iter()
+        .filter(|grant| grant.role == role && grant.source == RoleSource::Scim)
+        .map(|grant| grant.user_id)
+        .collect())
+}
+
+/// ## Returns the display name of the group of a role.
+fn group_name(role: &Role) -> &str {
+    role.description.as_deref().unwrap_or(&role.name)
+}
+
+/// ## Derives the role name of a group display name.
+///
+/// Letters are lowercased, runs of other characters
+/// are replaced with `-`.
+fn role_name(display_name: &str) -> String {
+    display_name
+        .to_lowercase()
+        .split(|c: char| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// ## Grants the role to an existing user through SCIM.
+async fn grant_member(
+    state: &AdminState,
+    principal: &Principal,
+    client: Option<ClientIp>,
+    role: &str,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    state.users.get(user_id).await?;
+
+    if state.roles.grant(user_id, role, RoleSource::Scim).await? {
+        let details = serde_json::json!({ "channel": "scim", "role": role });
+        record(
+            state,
+            principal,
+            client,
+            AuditEvent::RoleGranted,
+            Some(user_id),
+            details,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// ## Revokes the SCIM grant of the role.
+async fn revoke_member(
+    state: &AdminState,
+    principal: &Principal,
+    client: Option<ClientIp>,
+    role: &str,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    if state.roles.revoke(user_id, role, RoleSource::Scim).await? {
+        let details = serde_json::json!({ "channel": "scim", "role": role });
+        record(
+            state,
+            principal,
+            client,
+            AuditEvent::RoleRevoked,
+            Some(user_id),
+            details,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// ## Authorizes the provider globally.
+fn authorize(
+    state: &AdminState,
+    principal: &Principal,
+    permission: Permission,
+) -> Result<(), AppError> {
+    state
+        .policy
+        .authorize(principal, permission, None, Channel::Scim)
+}
+
+/// ## Builds an `invalidValue` error.
+fn invalid_value(message: impl Into<String>) -> AppError {
+    AppError::validation(message).with_code("scim.invalid_value")
+}
+
+/// ## Builds an `invalidPath` error.
+fn invalid_path(path: &str) -> AppError {
+    AppError::new(
+        ErrorKind::Validation,
+        format!("Attribute '{}' can not be changed", path),
+        None,
+    )
+    .with_code("scim.invalid_path")
+}
+
+/// ## Records a provisioning change in the audit log.
+async fn record(
+    state: &AdminState,
+    principal: &Principal,
+    client: Option<ClientIp>,
+    event: AuditEvent,
+    user_id: Option<Uuid>,
+    details: serde_json::Value,
+) -> Result<(), AppError> {
+    let mut entry = AuditEntry::new(event)
+        .actor(principal.user_id)
+        .details(details);
+    if let Some(user_id) = user_id {
+        entry = entry.user(user_id);
+    }
+    if let Some(ClientIp(ip)) = client {
+        entry = entry.ip(&ip.to_string());
+    }
+
+    state.audit.record(entry).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::rbac::{RoleGrant as PolicyGrant, ADMIN_ROLE};
+    use crate::core::repo::memory::MemoryUserRepo;
+    use crate::core::webhooks::Webhooks;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+    };
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    // Router with the users in memory, other state never connects.
+    fn memory_router(users: MemoryUserRepo) -> Router {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/axum_auth")
+            .unwrap();
+
+        router(AdminState {
+            users: Arc::new(users),
+            ..AdminState::new(pool.clone(), Webhooks::new(pool, &Default::default()))
+        })
+    }
+
+    fn admin() -> Principal {
+        Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![PolicyGrant::global(ADMIN_ROLE)],
+        }
+    }
+
+    async fn send(
+        router: &Router,
+        method: &str,
+        uri: &str,
+        principal: Principal,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, SCIM_CONTENT_TYPE)
+            .body(body)
+            .unwrap();
+        request.extensions_mut().insert(principal);
+
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    // Test checks if provisioned users are found by their user name and are unique.
+    #[tokio::test]
+    async fn test_scim_create_user() {
+        let router = memory_router(MemoryUserRepo::default());
+        let user = serde_json::json!({
+            "schemas": [USER_SCHEMA],
+            "userName": "Ada@example.com",
+            "displayName": "Ada",
+        });
+
+        let (status, body) = send(&router, "POST", "/Users", admin(), Some(user.clone())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["userName"], "ada@example.com");
+        assert_eq!(body["active"], true);
+
+        let uri = "/Users?filter=userName%20eq%20%22ada@example.com%22";
+        let (status, body) = send(&router, "GET", uri, admin(), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["totalResults"], 1);
+        assert_eq!(body["Resources"][0]["displayName"], "Ada");
+
+        let (status, body) = send(&router, "POST", "/Users", admin(), Some(user)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["scimType"], "uniqueness");
+        assert_eq!(body["schemas"][0], ERROR_SCHEMA);
+
+        let inactive = serde_json::json!({
+            "schemas": [USER_SCHEMA],
+            "userName": "grace@example.com",
+            "active": false,
+        });
+        let (status, body) = send(&router, "POST", "/Users", admin(), Some(inactive)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["active"], false);
+    }
+
+    // Test checks if deactivated users are disabled and unsupported filters rejected.
+    #[tokio::test]
+    async fn test_scim_patch_user() {
+        let router = memory_router(MemoryUserRepo::default());
+        let user = serde_json::json!({ "userName": "ada@example.com" });
+        let (_, body) = send(&router, "POST", "/Users", admin(), Some(user)).await;
+        let uri = format!("/Users/{}", body["id"].as_str().unwrap());
+
+        let patch = serde_json::json!({
+            "schemas": [crate::api::scim::PATCH_SCHEMA],
+            "Operations": [{ "op": "Replace", "value": { "displayName": "Ada L." } }],
+        });
+        let (status, body) = send(&router, "PATCH", &uri, admin(), Some(patch)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["displayName"], "Ada L.");
+
+        let patch = serde_json::json!({
+            "Operations": [{ "op": "replace", "path": "password", "value": "secret" }],
+        });
+        let (status, body) = send(&router, "PATCH", &uri, admin(), Some(patch)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["scimType"], "invalidPath");
+
+        let uri = "/Users?filter=emails%20co%20%22example%22";
+        let (status, body) = send(&router, "GET", uri, admin(), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["scimType"], "invalidFilter");
+    }
+
+    // Test checks if providers without a global permission are denied.
+    #[tokio::test]
+    async fn test_scim_authorization() {
+        let router = memory_router(MemoryUserRepo::default());
+        let viewer = Principal {
+            user_id: Uuid::new_v4(),
+            grants: vec![PolicyGrant::global("viewer")],
+        };
+
+        let (status, body) = send(&router, "GET", "/Users", viewer, None).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["status"], "403");
+    }
+
+    // Test checks if group patches are parsed into member changes.
+    #[test]
+    fn test_group_change_parse() {
+        let id = Uuid::new_v4();
+        let patch: ScimPatchRequest = serde_json::from_value(serde_json::json!({
+            "Operations": [
+                { "op": "add", "path": "members", "value": [{ "value": id }] },
+                { "op": "remove", "path": format!("members[value eq \"{}\"]", id) },
+                { "op": "replace", "value": { "id": "support", "displayName": "Support" } },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            GroupChange::parse(&patch.operations).unwrap(),
+            [
+                GroupChange::Add(vec![id]),
+                GroupChange::Remove(Some(vec![id])),
+                GroupChange::DisplayName("Support".to_string()),
+            ]
+        );
+        assert_eq!(role_name("Support Agents (EU)"), "support-agents-eu");
+    }
+}
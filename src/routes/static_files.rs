@@ -0,0 +1,294 @@
+//! Static files routes.
+//!
+//! Files of `static_files.dir` are served below
+//! `static_files.path` with an `ETag` and `Last-Modified`
+//! header. Requests repeating them in `If-None-Match` or
+//! `If-Modified-Since` get `304 Not Modified` without the
+//! body. Names carrying a content hash are cached for a
+//! year as `immutable`, other files for `max_age`.
+
+// Imports from external crates
+use axum::{
+    extract::{Path, State},
+    http::{
+        header::{
+            CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+        },
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+// Local imports
+use crate::core::config::StaticFilesSettings;
+use crate::core::err::{AppError, ErrorKind};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+/// Seconds files with a content hash in their name are cached.
+pub const IMMUTABLE_MAX_AGE: u64 = 31_536_000;
+
+/// Minimum length of the content hash in a file name.
+const MIN_HASH_LENGTH: usize = 8;
+
+/// Format of the HTTP dates.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// ## Static files struct.
+///
+/// ## Fields
+/// + `root`: `PathBuf` - Canonical path of the directory.
+/// + `max_age`: `u64` - Seconds files without a hash are cached.
+pub struct StaticFiles {
+    root: PathBuf,
+    max_age: u64,
+}
+
+impl StaticFiles {
+    /// ## Creates the static files of the settings.
+    ///
+    /// ## Returns
+    /// + `Result<StaticFiles, AppError>`
+    ///     - `Ok(StaticFiles)`: Files of the directory.
+    ///     - `Err(AppError)`: If the directory can not be resolved.
+    pub fn new(settings: &StaticFilesSettings) -> Result<Self, AppError> {
+        let root = std::fs::canonicalize(&settings.dir).map_err(|e| {
+            AppError::new(
+                ErrorKind::Config,
+                format!("Failed to resolve static_files.dir '{}'", settings.dir),
+                Some(Box::new(e)),
+            )
+        })?;
+
+        Ok(StaticFiles {
+            root,
+            max_age: settings.max_age,
+        })
+    }
+
+    /// ## Resolves the path of a request inside the directory.
+    ///
+    /// Hidden files, `..` and links leaving the
+    /// directory are never resolved.
+    async fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let hidden = path
+            .split('/')
+            .any(|part| part.is_empty() || part.starts_with('.') || part.contains('\\'));
+        if hidden {
+            return None;
+        }
+
+        let file = tokio::fs::canonicalize(self.root.join(path)).await.ok()?;
+        match file.starts_with(&self.root) && file.is_file() {
+            true => Some(file),
+            false => None,
+        }
+    }
+
+    /// ## Returns the `Cache-Control` header of a file.
+    fn cache_control(&self, name: &str) -> String {
+        match is_hashed(name) {
+            true => format!("public, max-age={}, immutable", IMMUTABLE_MAX_AGE),
+            false => format!("public, max-age={}", self.max_age),
+        }
+    }
+}
+
+/// ## Declares the static files routes.
+///
+/// ## Returns
+/// - `Routes<Arc<StaticFiles>>`: Routes to nest under `static_files.path`.
+pub fn routes() -> Routes<Arc<StaticFiles>> {
+    Routes::new().get("/*path", AuthPolicy::Public, serve)
+}
+
+/// ## Builds the static files router.
+///
+/// ## Parameters
+/// - `files`: `Arc<StaticFiles>` - Directory of the files.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `static_files.path`.
+pub fn router(files: Arc<StaticFiles>) -> Router {
+    routes().router(None).with_state(files)
+}
+
+/// ## Serves a file of the directory.
+pub async fn serve(
+    State(files): State<Arc<StaticFiles>>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let not_found = || AppError::new(ErrorKind::NotFound, "File not found".to_string(), None);
+
+    let file = files.resolve(&path).await.ok_or_else(not_found)?;
+    let metadata = tokio::fs::metadata(&file).await.map_err(|_| not_found())?;
+    let modified: DateTime<Utc> = metadata.modified().unwrap_or(UNIX_EPOCH).into();
+    let etag = format!(
+        "\"{:x}-{:x}\"",
+        metadata.len(),
+        modified.timestamp_nanos_opt().unwrap_or_default()
+    );
+    let cache = [
+        (ETAG, etag.clone()),
+        (LAST_MODIFIED, modified.format(HTTP_DATE).to_string()),
+        (CACHE_CONTROL, files.cache_control(&path)),
+    ];
+
+    if not_modified(&headers, &etag, modified) {
+        return Ok((StatusCode::NOT_MODIFIED, cache).into_response());
+    }
+
+    let body = tokio::fs::read(&file).await.map_err(|e| {
+        AppError::new(
+            ErrorKind::Io,
+            format!("Failed to read static file '{}'", path),
+            Some(Box::new(e)),
+        )
+    })?;
+
+    Ok((cache, [(CONTENT_TYPE, content_type(&path))], body).into_response())
+}
+
+/// ## Checks if the copy of the client is current.
+///
+/// `If-None-Match` wins over `If-Modified-Since`,
+/// like RFC 9110 asks for.
+fn not_modified(headers: &HeaderMap, etag: &str, modified: DateTime<Utc>) -> bool {
+    if let Some(value) = headers.get(IF_NONE_MATCH) {
+        return value.to_str().is_ok_and(|value| {
+            value
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        });
+    }
+
+    headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .is_some_and(|since| modified.timestamp() <= since.timestamp())
+}
+
+/// ## Checks if the file name carries a content hash.
+///
+/// Hash is a hexadecimal part of the name, separated
+/// by `.` or `-`, e.g. `app.3f9a1c2b.js`.
+pub fn is_hashed(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let Some((stem, _)) = name.rsplit_once('.') else {
+        return false;
+    };
+
+    stem.split(['.', '-'])
+        .skip(1)
+        .any(|part| part.len() >= MIN_HASH_LENGTH && part.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// ## Returns the content type of the file extension.
+fn content_type(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension);
+
+    match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request};
+    use tower::ServiceExt;
+
+    // Test checks if files are served with validators, revalidated and kept inside the directory.
+    #[tokio::test]
+    async fn test_serve() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.3f9a1c2b.js"), "console.log(1)").unwrap();
+        std::fs::write(dir.path().join("logo.svg"), "<svg/>").unwrap();
+        std::fs::write(dir.path().join(".env"), "SECRET=1").unwrap();
+        let files = StaticFiles::new(&StaticFilesSettings {
+            enabled: true,
+            dir: dir.path().to_str().unwrap().to_string(),
+            ..StaticFilesSettings::default()
+        })
+        .unwrap();
+        let app = router(Arc::new(files));
+        let get = |uri: &str, header: Option<(&str, String)>| {
+            let mut request = Request::get(uri);
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
+            }
+            let app = app.clone();
+
+            async move {
+                app.oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let response = get("/logo.svg", None).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "image/svg+xml");
+        assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=3600");
+        let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+        let modified = response.headers()[LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let revalidated = get("/logo.svg", Some(("if-none-match", format!("W/{}", etag)))).await;
+
+        assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(revalidated.headers()[ETAG], etag.as_str());
+        assert_eq!(
+            get("/logo.svg", Some(("if-modified-since", modified)))
+                .await
+                .status(),
+            StatusCode::NOT_MODIFIED
+        );
+        assert_eq!(
+            get(
+                "/logo.svg",
+                Some(("if-none-match", "\"other\"".to_string()))
+            )
+            .await
+            .status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            get("/app.3f9a1c2b.js", None).await.headers()[CACHE_CONTROL],
+            "public, max-age=31536000, immutable"
+        );
+        for uri in ["/.env", "/../logo.svg", "/missing.css", "/"] {
+            assert_eq!(
+                get(uri, None).await.status(),
+                StatusCode::NOT_FOUND,
+                "{}",
+                uri
+            );
+        }
+        assert!(is_hashed("assets/index-0a1b2c3d4e.css"));
+        assert!(!is_hashed("jquery-3.7.1.min.js"));
+    }
+}
@@ -0,0 +1,345 @@
+//! Embedded UI routes.
+//!
+//! Server-rendered pages under `/ui` for services without
+//! a frontend of their own. `/ui/login` requests a magic
+//! link, `/ui/verify` is the page the link opens and
+//! `/ui/consent` lets the signed in user approve or deny
+//! a device. The forms post to the JSON routes with a small
+//! script, so rate limits, CSRF checks and webhooks stay
+//! in one place. Routes are called with relative URLs,
+//! which keeps the pages working below a tenant path.
+//!
+//! Pages are not API routes and not part of the OpenAPI
+//! document. There are no registration and password reset
+//! pages: users are provisioned by administrators, SCIM
+//! or the import, and a reset is forced by an administrator
+//! and done through `/me/password`, so no public route
+//! exists a page could post to.
+
+// Imports from external crates
+use axum::{
+    extract::{Query, State},
+    http::header::{CACHE_CONTROL, X_FRAME_OPTIONS},
+    response::{Html, IntoResponse, Response},
+    Router,
+};
+use maud::{html, Markup, PreEscaped, DOCTYPE};
+use serde::Deserialize;
+use std::sync::Arc;
+
+// Local imports
+use crate::core::config::{AppConfig, UiSettings};
+use crate::routes::registry::{AuthPolicy, Routes};
+
+/// Script submitting the forms of the pages to the JSON routes.
+const SCRIPT: &str = r#"
+const status = document.getElementById("status");
+const message = async (response) => {
+  try {
+    return (await response.json()).error.message;
+  } catch {
+    return response.statusText;
+  }
+};
+const device = document.querySelector("[data-device]");
+if (device) {
+  fetch(device.dataset.device, { credentials: "same-origin" }).then(async (response) => {
+    if (!response.ok) {
+      status.textContent = response.status === 401 ? device.dataset.signin : await message(response);
+      return;
+    }
+    const details = await response.json();
+    device.querySelector("[data-client]").textContent = details.client_name;
+    device.querySelector("[data-scopes]").textContent = details.scopes.join(", ") || "-";
+    device.hidden = false;
+  });
+}
+document.querySelectorAll("form[data-action]").forEach((form) => {
+  form.addEventListener("submit", async (event) => {
+    event.preventDefault();
+    const body = Object.fromEntries(new FormData(form));
+    if (event.submitter && event.submitter.name) {
+      body[event.submitter.name] = event.submitter.value === "true";
+    }
+    const headers = { "Content-Type": "application/json" };
+    if (form.dataset.csrf) {
+      const csrf = await fetch("../auth/csrf", { credentials: "same-origin" });
+      if (csrf.ok) headers[form.dataset.csrf] = (await csrf.json()).token;
+    }
+    const response = await fetch(form.dataset.action, {
+      method: "POST",
+      credentials: "same-origin",
+      headers,
+      body: JSON.stringify(body),
+    });
+    status.textContent = response.ok ? form.dataset.done : await message(response);
+    if (response.ok) form.closest("[data-flow]").hidden = true;
+  });
+});
+"#;
+
+/// ## UI state struct.
+///
+/// ## Fields
+/// + `settings`: `UiSettings` - Title, logo and colors of the pages.
+/// + `csrf_header`: `String` - Header the CSRF token is echoed in.
+/// + `magic_link`: `bool` - Pages may link to the login page.
+pub struct UiState {
+    pub settings: UiSettings,
+    pub csrf_header: String,
+    pub magic_link: bool,
+}
+
+impl UiState {
+    /// ## Creates the UI state of the configuration.
+    pub fn new(config: &AppConfig) -> Self {
+        UiState {
+            settings: config.ui.clone(),
+            csrf_header: config.auth.csrf_header.clone(),
+            magic_link: config.magic_link.enabled,
+        }
+    }
+}
+
+/// ## Query of the verification page struct.
+///
+/// ## Fields
+/// + `token`: `Option<String>` - Token the magic link carries.
+#[derive(Debug, Deserialize)]
+pub struct VerifyQuery {
+    pub token: Option<String>,
+}
+
+/// ## Query of the consent page struct.
+///
+/// ## Fields
+/// + `user_code`: `Option<String>` - User code shown by the device.
+#[derive(Debug, Deserialize)]
+pub struct ConsentQuery {
+    pub user_code: Option<String>,
+}
+
+/// ## Declares the UI routes.
+///
+/// ## Parameters
+/// - `magic_link`: `bool` - Add the login and verification pages.
+/// - `device_code`: `bool` - Add the consent page.
+///
+/// ## Returns
+/// - `Routes<Arc<UiState>>`: Routes to nest under `/ui`.
+pub fn routes(magic_link: bool, device_code: bool) -> Routes<Arc<UiState>> {
+    let mut routes = Routes::new();
+    if magic_link {
+        routes = routes.get("/login", AuthPolicy::Public, login).get(
+            "/verify",
+            AuthPolicy::Public,
+            verify,
+        );
+    }
+    if device_code {
+        routes = routes.get("/consent", AuthPolicy::Public, consent);
+    }
+
+    routes
+}
+
+/// ## Builds the UI router.
+///
+/// ## Parameters
+/// - `state`: `Arc<UiState>` - Settings of the pages.
+/// - `device_code`: `bool` - Add the consent page.
+///
+/// ## Returns
+/// - `Router`: Router to nest under `/ui`.
+pub fn router(state: Arc<UiState>, device_code: bool) -> Router {
+    routes(state.magic_link, device_code)
+        .router(None)
+        .with_state(state)
+}
+
+/// ## Page requesting a magic link.
+pub async fn login(State(ui): State<Arc<UiState>>) -> Response {
+    let body = html! {
+        div data-flow {
+            form data-action="../auth/magic-link"
+                data-done="If the address belongs to an account, a sign-in link is on its way." {
+                label for="email" { "Email" }
+                input #email type="email" name="email" autocomplete="email" required;
+                button type="submit" { "Send sign-in link" }
+            }
+        }
+    };
+
+    page(&ui.settings, "Sign in", body)
+}
+
+/// ## Page the magic link opens.
+///
+/// Token is only used when the user continues, so
+/// scanners opening the link do not use it up.
+pub async fn verify(State(ui): State<Arc<UiState>>, Query(query): Query<VerifyQuery>) -> Response {
+    let body = match query.token.filter(|token| !token.is_empty()) {
+        Some(token) => html! {
+            div data-flow {
+                form data-action="../auth/magic-link/verify" data-done="You are signed in." {
+                    input type="hidden" name="token" value=(token);
+                    button type="submit" { "Continue" }
+                }
+            }
+        },
+        None => html! {
+            p { "The link is incomplete." }
+            a href="login" { "Request a new link" }
+        },
+    };
+
+    page(&ui.settings, "Finish signing in", body)
+}
+
+/// ## Page approving or denying a device.
+pub async fn consent(
+    State(ui): State<Arc<UiState>>,
+    Query(query): Query<ConsentQuery>,
+) -> Response {
+    let signin = match ui.magic_link {
+        true => "Sign in on the login page first, then open this page again.",
+        false => "Sign in first, then open this page again.",
+    };
+    let body = match query.user_code.filter(|code| !code.is_empty()) {
+        Some(code) => {
+            let details = format!(
+                "../device?user_code={}",
+                url::form_urlencoded::byte_serialize(code.as_bytes()).collect::<String>()
+            );
+
+            html! {
+                div data-flow data-device=(details) data-signin=(signin) hidden {
+                    p { strong data-client {} " asks for access to your account." }
+                    p { "Scopes: " span data-scopes {} }
+                    form data-action="../device" data-csrf=(ui.csrf_header)
+                        data-done="Your decision was sent, you can return to the device." {
+                        input type="hidden" name="user_code" value=(code);
+                        button type="submit" name="approve" value="true" { "Approve" }
+                        button.secondary type="submit" name="approve" value="false" { "Deny" }
+                    }
+                }
+            }
+        }
+        None => html! {
+            form method="get" {
+                label for="user_code" { "Code shown by the device" }
+                input #user_code name="user_code" autocomplete="off" required;
+                button type="submit" { "Continue" }
+            }
+        },
+    };
+
+    page(&ui.settings, "Connect a device", body)
+}
+
+/// ## Renders a page with the theme of the settings.
+///
+/// Pages are never cached and never framed, so the
+/// consent can not be clicked through another site.
+fn page(settings: &UiSettings, heading: &str, body: Markup) -> Response {
+    let markup = html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { (heading) " - " (settings.title) }
+                style { (PreEscaped(stylesheet(settings))) }
+                @if !settings.stylesheet_url.is_empty() {
+                    link rel="stylesheet" href=(settings.stylesheet_url);
+                }
+            }
+            body {
+                main {
+                    @if !settings.logo_url.is_empty() {
+                        img.logo src=(settings.logo_url) alt=(settings.title);
+                    }
+                    h1 { (settings.title) }
+                    h2 { (heading) }
+                    (body)
+                    p #status role="status" {}
+                }
+                script { (PreEscaped(SCRIPT)) }
+            }
+        }
+    };
+
+    (
+        [(CACHE_CONTROL, "no-store"), (X_FRAME_OPTIONS, "DENY")],
+        Html(markup.into_string()),
+    )
+        .into_response()
+}
+
+/// ## Returns the built-in stylesheet in the colors of the settings.
+///
+/// Colors are validated as `#rrggbb` when the
+/// configuration is loaded.
+fn stylesheet(settings: &UiSettings) -> String {
+    format!(
+        "body{{margin:0;min-height:100vh;display:flex;align-items:center;justify-content:center;\
+         background:{background};font-family:system-ui,sans-serif;color:#111827}}\
+         main{{width:100%;max-width:24rem;padding:2rem;background:#fff;border-radius:.5rem;\
+         box-shadow:0 1px 3px rgba(0,0,0,.15)}}\
+         .logo{{max-height:3rem}}h1{{font-size:1.25rem}}h2{{font-size:1rem;font-weight:500}}\
+         label{{display:block;margin-bottom:.25rem}}\
+         input{{box-sizing:border-box;width:100%;padding:.5rem;margin-bottom:1rem}}\
+         button{{padding:.5rem 1rem;margin-right:.5rem;border:0;border-radius:.25rem;\
+         background:{primary};color:#fff;cursor:pointer}}\
+         button.secondary{{background:transparent;color:{primary};border:1px solid {primary}}}\
+         a{{color:{primary}}}",
+        background = settings.background_color,
+        primary = settings.primary_color,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::StatusCode,
+    };
+    use tower::ServiceExt;
+
+    // Test checks if pages are themed, escape the query and exist only for enabled flows.
+    #[tokio::test]
+    async fn test_pages() {
+        let state = Arc::new(UiState {
+            settings: UiSettings {
+                title: "Acme".to_string(),
+                primary_color: "#e11d48".to_string(),
+                ..UiSettings::default()
+            },
+            csrf_header: "x-csrf-token".to_string(),
+            magic_link: false,
+        });
+        let app = router(state, true);
+        let get = |uri: &str| {
+            let app = app.clone();
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+
+            async move { app.oneshot(request).await.unwrap() }
+        };
+
+        let response = get("/consent?user_code=%3Cb%3EABCD").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[X_FRAME_OPTIONS], "DENY");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(body.contains("<title>Connect a device - Acme</title>"));
+        assert!(body.contains("background:#e11d48"));
+        assert!(body.contains("value=\"&lt;b&gt;ABCD\""));
+        assert!(body.contains("../device?user_code=%3Cb%3EABCD"));
+        assert!(body.contains("data-csrf=\"x-csrf-token\""));
+        assert_eq!(get("/login").await.status(), StatusCode::NOT_FOUND);
+    }
+}
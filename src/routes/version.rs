@@ -0,0 +1,105 @@
+//! Version route.
+
+// Imports from external crates
+use axum::{extract::State, Json, Router};
+use std::sync::Arc;
+
+// Local imports
+use crate::core::config::TlsSettings;
+use crate::core::server::tls::active_policy;
+use crate::routes::registry::{AuthPolicy, Routes};
+
+// Re-exports of the wire types
+pub use crate::api::version::{TlsInfo, VersionInfo};
+
+/// Path of the version endpoint.
+pub const VERSION_PATH: &str = "/version";
+
+/// ## Declares the version routes.
+pub fn routes() -> Routes<Arc<VersionInfo>> {
+    Routes::new().get(VERSION_PATH, AuthPolicy::Public, version)
+}
+
+/// ## Builds the version router.
+///
+/// ## Parameters
+/// - `tls`: `&TlsSettings` - TLS settings the server was started with.
+pub fn router(tls: &TlsSettings) -> Router {
+    routes()
+        .router(None)
+        .with_state(Arc::new(version_info(tls)))
+}
+
+/// ## Builds the version information.
+///
+/// ## Parameters
+/// - `tls`: `&TlsSettings` - TLS settings the server was started with.
+///
+/// ## Returns
+/// - `VersionInfo`: Version and the active TLS policy.
+pub fn version_info(tls: &TlsSettings) -> VersionInfo {
+    let tls = match tls.enabled {
+        true => {
+            let active = active_policy(tls);
+            TlsInfo {
+                enabled: true,
+                policy: Some(active.policy.as_str().to_string()),
+                min_version: Some(active.min_version.as_str().to_string()),
+                cipher_suites: active.cipher_suites,
+            }
+        }
+        false => TlsInfo::default(),
+    };
+
+    VersionInfo {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tls,
+    }
+}
+
+/// ## Version of the server.
+///
+/// Reports the crate version and the TLS policy the
+/// server accepts connections with.
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "meta",
+    responses((status = 200, description = "Version of the server", body = VersionInfo))
+)]
+pub async fn version(State(info): State<Arc<VersionInfo>>) -> Json<VersionInfo> {
+    Json(info.as_ref().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::server::tls::{TlsPolicy, TlsVersion};
+
+    // Test checks if the reported policy leaves out the weak settings.
+    #[test]
+    fn test_version_info_tls() {
+        let tls = TlsSettings {
+            enabled: true,
+            policy: TlsPolicy::Intermediate,
+            min_version: Some(TlsVersion::Tls10),
+            cipher_suites: vec![
+                "TLS13_AES_128_GCM_SHA256".to_string(),
+                "TLS_RSA_WITH_AES_128_CBC_SHA".to_string(),
+            ],
+            ..TlsSettings::default()
+        };
+
+        let info = version_info(&tls);
+
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.tls.policy.as_deref(), Some("intermediate"));
+        assert_eq!(info.tls.min_version.as_deref(), Some("1.2"));
+        assert_eq!(info.tls.cipher_suites, ["TLS13_AES_128_GCM_SHA256"]);
+        assert_eq!(
+            version_info(&TlsSettings::default()).tls,
+            TlsInfo::default()
+        );
+    }
+}
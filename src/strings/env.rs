@@ -25,4 +25,22 @@ pub mod vars {
 
     // Database ssl root certificate
     pub const PATH_TO_DB_SSL_ROOT_CERT: &str = "PATH_TO_DB_SSL_ROOT_CERT";
+
+    // SMTP user of the mail provider
+    pub const MAIL_SMTP_USER: &str = "MAIL_SMTP_USER";
+
+    // SMTP password of the mail provider
+    pub const MAIL_SMTP_PASS: &str = "MAIL_SMTP_PASS";
+
+    // SendGrid API key of the mail provider
+    pub const MAIL_SENDGRID_API_KEY: &str = "MAIL_SENDGRID_API_KEY";
+
+    // AWS access key ID of the SES mail provider
+    pub const MAIL_SES_ACCESS_KEY_ID: &str = "MAIL_SES_ACCESS_KEY_ID";
+
+    // AWS secret access key of the SES mail provider
+    pub const MAIL_SES_SECRET_ACCESS_KEY: &str = "MAIL_SES_SECRET_ACCESS_KEY";
+
+    // DSN of the Sentry project crash reports are sent to
+    pub const SENTRY_DSN: &str = "SENTRY_DSN";
 }
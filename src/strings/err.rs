@@ -1,3 +0,0 @@
-//! Module contains error messages.
-
-pub const INVALID_VALUE_FOR_TYPE: &str = "Invalid value for type";
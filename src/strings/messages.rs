@@ -0,0 +1,369 @@
+//! Message catalog module.
+//!
+//! Messages sent to clients, keyed by error code and
+//! translated into the languages of the supported
+//! locales. English is the default language, every
+//! message has an English text.
+
+/// Default language of the messages.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// ## Message struct.
+///
+/// ## Fields
+/// + `code`: `&'static str` - Error code the message belongs to.
+/// + `texts`: `&'static [(&'static str, &'static str)]` - Text per language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    pub code: &'static str,
+    pub texts: &'static [(&'static str, &'static str)],
+}
+
+/// Code of the message of malformed values.
+pub const INVALID_VALUE_FOR_TYPE: &str = "validation.invalid_type";
+
+/// Messages of the error codes.
+pub const CATALOG: &[Message] = &[
+    Message {
+        code: "auth",
+        texts: &[
+            ("en", "Authentication is required"),
+            ("de", "Anmeldung erforderlich"),
+            ("fr", "Authentification requise"),
+            ("es", "Se requiere autenticación"),
+            ("ja", "認証が必要です"),
+        ],
+    },
+    Message {
+        code: "forbidden",
+        texts: &[
+            ("en", "You are not allowed to perform this action"),
+            ("de", "Sie dürfen diese Aktion nicht ausführen"),
+            ("fr", "Vous n'êtes pas autorisé à effectuer cette action"),
+            ("es", "No tiene permiso para realizar esta acción"),
+            ("ja", "この操作を実行する権限がありません"),
+        ],
+    },
+    Message {
+        code: "login_window_denied",
+        texts: &[
+            ("en", "Login is not allowed at this time"),
+            ("de", "Anmeldung ist zu dieser Zeit nicht erlaubt"),
+            ("fr", "La connexion n'est pas autorisée à cette heure"),
+            ("es", "No se permite iniciar sesión a esta hora"),
+            ("ja", "この時間帯はログインできません"),
+        ],
+    },
+    Message {
+        code: "country_denied",
+        texts: &[
+            ("en", "Login is not allowed from your country"),
+            ("de", "Anmeldung aus Ihrem Land ist nicht erlaubt"),
+            ("fr", "La connexion depuis votre pays n'est pas autorisée"),
+            ("es", "No se permite iniciar sesión desde su país"),
+            ("ja", "お住まいの国からはログインできません"),
+        ],
+    },
+    Message {
+        code: "not_found",
+        texts: &[
+            ("en", "Resource not found"),
+            ("de", "Ressource nicht gefunden"),
+            ("fr", "Ressource introuvable"),
+            ("es", "Recurso no encontrado"),
+            ("ja", "リソースが見つかりません"),
+        ],
+    },
+    Message {
+        code: "validation",
+        texts: &[
+            ("en", "Request is invalid"),
+            ("de", "Anfrage ist ungültig"),
+            ("fr", "La requête est invalide"),
+            ("es", "La solicitud no es válida"),
+            ("ja", "リクエストが無効です"),
+        ],
+    },
+    Message {
+        code: INVALID_VALUE_FOR_TYPE,
+        texts: &[
+            ("en", "Invalid value for type"),
+            ("de", "Ungültiger Wert für Typ"),
+            ("fr", "Valeur invalide pour le type"),
+            ("es", "Valor no válido para el tipo"),
+            ("ja", "型に対して無効な値です"),
+        ],
+    },
+    Message {
+        code: "external",
+        texts: &[
+            ("en", "An upstream service is unavailable"),
+            ("de", "Ein vorgelagerter Dienst ist nicht erreichbar"),
+            ("fr", "Un service en amont est indisponible"),
+            ("es", "Un servicio externo no está disponible"),
+            ("ja", "上流のサービスを利用できません"),
+        ],
+    },
+    Message {
+        code: "internal",
+        texts: &[
+            ("en", "Internal server error"),
+            ("de", "Interner Serverfehler"),
+            ("fr", "Erreur interne du serveur"),
+            ("es", "Error interno del servidor"),
+            ("ja", "内部サーバーエラー"),
+        ],
+    },
+    Message {
+        code: "token.invalid",
+        texts: &[
+            ("en", "Token is invalid"),
+            ("de", "Token ist ungültig"),
+            ("fr", "Le jeton est invalide"),
+            ("es", "El token no es válido"),
+            ("ja", "トークンが無効です"),
+        ],
+    },
+    Message {
+        code: "token.expired",
+        texts: &[
+            ("en", "Token is expired"),
+            ("de", "Token ist abgelaufen"),
+            ("fr", "Le jeton a expiré"),
+            ("es", "El token ha caducado"),
+            ("ja", "トークンの有効期限が切れています"),
+        ],
+    },
+    Message {
+        code: "token.unknown_key",
+        texts: &[
+            ("en", "Token is signed with an unknown key"),
+            ("de", "Token ist mit einem unbekannten Schlüssel signiert"),
+            ("fr", "Le jeton est signé avec une clé inconnue"),
+            ("es", "El token está firmado con una clave desconocida"),
+            ("ja", "トークンが不明な鍵で署名されています"),
+        ],
+    },
+    Message {
+        code: "token.insufficient_scope",
+        texts: &[
+            ("en", "Token lacks the required scope"),
+            ("de", "Dem Token fehlt der erforderliche Scope"),
+            ("fr", "Le jeton n'a pas la portée requise"),
+            ("es", "El token no tiene el alcance requerido"),
+            ("ja", "トークンに必要なスコープがありません"),
+        ],
+    },
+    Message {
+        code: "users.email_taken",
+        texts: &[
+            ("en", "Email address is already taken"),
+            ("de", "E-Mail-Adresse ist bereits vergeben"),
+            ("fr", "L'adresse e-mail est déjà utilisée"),
+            ("es", "La dirección de correo ya está en uso"),
+            ("ja", "このメールアドレスは既に使用されています"),
+        ],
+    },
+    Message {
+        code: "users.wrong_password",
+        texts: &[
+            ("en", "Current password is wrong"),
+            ("de", "Aktuelles Passwort ist falsch"),
+            ("fr", "Le mot de passe actuel est incorrect"),
+            ("es", "La contraseña actual es incorrecta"),
+            ("ja", "現在のパスワードが正しくありません"),
+        ],
+    },
+    Message {
+        code: "users.not_scheduled",
+        texts: &[
+            ("en", "Account is not scheduled for deletion"),
+            ("de", "Konto ist nicht zur Löschung vorgemerkt"),
+            ("fr", "Le compte n'est pas programmé pour suppression"),
+            ("es", "La cuenta no está programada para su eliminación"),
+            ("ja", "アカウントは削除予定ではありません"),
+        ],
+    },
+    Message {
+        code: "session.revoked",
+        texts: &[
+            ("en", "Session is revoked or expired"),
+            ("de", "Sitzung ist widerrufen oder abgelaufen"),
+            ("fr", "La session est révoquée ou expirée"),
+            ("es", "La sesión está revocada o ha caducado"),
+            ("ja", "セッションは取り消されたか期限切れです"),
+        ],
+    },
+    Message {
+        code: "csrf.invalid",
+        texts: &[
+            ("en", "CSRF token is missing or invalid"),
+            ("de", "CSRF-Token fehlt oder ist ungültig"),
+            ("fr", "Le jeton CSRF est absent ou invalide"),
+            ("es", "El token CSRF falta o no es válido"),
+            ("ja", "CSRFトークンがないか無効です"),
+        ],
+    },
+    Message {
+        code: "rate_limited",
+        texts: &[
+            ("en", "Too many attempts, try again later"),
+            ("de", "Zu viele Versuche, bitte später erneut versuchen"),
+            ("fr", "Trop de tentatives, réessayez plus tard"),
+            ("es", "Demasiados intentos, inténtelo más tarde"),
+            ("ja", "試行回数が多すぎます。後でもう一度お試しください"),
+        ],
+    },
+    Message {
+        code: "request.timeout",
+        texts: &[
+            ("en", "Request timed out"),
+            ("de", "Zeitüberschreitung der Anfrage"),
+            ("fr", "La requête a expiré"),
+            ("es", "La solicitud ha caducado"),
+            ("ja", "リクエストがタイムアウトしました"),
+        ],
+    },
+    Message {
+        code: "request.body_too_large",
+        texts: &[
+            ("en", "Request body is too large"),
+            ("de", "Anfragetext ist zu groß"),
+            ("fr", "Le corps de la requête est trop volumineux"),
+            ("es", "El cuerpo de la solicitud es demasiado grande"),
+            ("ja", "リクエスト本文が大きすぎます"),
+        ],
+    },
+    Message {
+        code: "server.overloaded",
+        texts: &[
+            ("en", "Server is busy, try again later"),
+            (
+                "de",
+                "Server ist ausgelastet, bitte später erneut versuchen",
+            ),
+            ("fr", "Le serveur est occupé, réessayez plus tard"),
+            ("es", "El servidor está ocupado, inténtelo más tarde"),
+            (
+                "ja",
+                "サーバーが混み合っています。後でもう一度お試しください",
+            ),
+        ],
+    },
+    Message {
+        code: "ip.denied",
+        texts: &[
+            ("en", "Access from this address is not allowed"),
+            ("de", "Zugriff von dieser Adresse ist nicht erlaubt"),
+            ("fr", "L'accès depuis cette adresse n'est pas autorisé"),
+            ("es", "No se permite el acceso desde esta dirección"),
+            ("ja", "このアドレスからのアクセスは許可されていません"),
+        ],
+    },
+    Message {
+        code: "authz.denied",
+        texts: &[
+            ("en", "Route requires another role or scope"),
+            (
+                "de",
+                "Die Route erfordert eine andere Rolle oder einen anderen Scope",
+            ),
+            ("fr", "La route requiert un autre rôle ou une autre portée"),
+            ("es", "La ruta requiere otro rol u otro alcance"),
+            ("ja", "このルートには別のロールまたはスコープが必要です"),
+        ],
+    },
+    Message {
+        code: "pagination.invalid_sort",
+        texts: &[
+            ("en", "List can not be sorted by this field"),
+            ("de", "Liste kann nicht nach diesem Feld sortiert werden"),
+            ("fr", "La liste ne peut pas être triée par ce champ"),
+            ("es", "La lista no se puede ordenar por este campo"),
+            ("ja", "このフィールドでは並べ替えできません"),
+        ],
+    },
+    Message {
+        code: "pagination.invalid_cursor",
+        texts: &[
+            ("en", "Cursor is invalid"),
+            ("de", "Cursor ist ungültig"),
+            ("fr", "Le curseur est invalide"),
+            ("es", "El cursor no es válido"),
+            ("ja", "カーソルが無効です"),
+        ],
+    },
+    Message {
+        code: "tenant.missing",
+        texts: &[
+            ("en", "The tenant of the request is missing"),
+            ("de", "Der Mandant der Anfrage fehlt"),
+            ("fr", "Le locataire de la requête est manquant"),
+            ("es", "Falta el inquilino de la solicitud"),
+            ("ja", "リクエストのテナントが指定されていません"),
+        ],
+    },
+    Message {
+        code: "tenant.unknown",
+        texts: &[
+            ("en", "The tenant was not found"),
+            ("de", "Der Mandant wurde nicht gefunden"),
+            ("fr", "Le locataire est introuvable"),
+            ("es", "No se encontró el inquilino"),
+            ("ja", "テナントが見つかりません"),
+        ],
+    },
+    Message {
+        code: "roles.version_conflict",
+        texts: &[
+            (
+                "en",
+                "The role was changed in the meantime, reload it and try again",
+            ),
+            (
+                "de",
+                "Die Rolle wurde zwischenzeitlich geändert, bitte neu laden und erneut versuchen",
+            ),
+            (
+                "fr",
+                "Le rôle a été modifié entre-temps, rechargez-le et réessayez",
+            ),
+            (
+                "es",
+                "El rol se modificó mientras tanto, recárguelo e inténtelo de nuevo",
+            ),
+            (
+                "ja",
+                "ロールは別の操作で変更されました。再読み込みしてからもう一度お試しください",
+            ),
+        ],
+    },
+];
+
+/// ## Returns the message of the code in the language.
+///
+/// ## Parameters
+/// - `code`: `&str` - Error code, e.g. `token.expired`.
+/// - `language`: `&str` - Language subtag, e.g. `de`.
+///
+/// ## Returns
+/// + `Option<&'static str>`
+///     - `Some(&'static str)`: Text of the message.
+///     - `None`: If the code has no message in the language.
+pub fn lookup(code: &str, language: &str) -> Option<&'static str> {
+    CATALOG
+        .iter()
+        .find(|message| message.code == code)?
+        .texts
+        .iter()
+        .find(|(other, _)| *other == language)
+        .map(|(_, text)| *text)
+}
+
+/// ## Returns the English message of the code.
+///
+/// Used for logs and messages built on the server, which
+/// are always English. Unknown codes return the code.
+pub fn english(code: &'static str) -> &'static str {
+    lookup(code, DEFAULT_LANGUAGE).unwrap_or(code)
+}
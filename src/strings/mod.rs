@@ -1,3 +1,3 @@
 pub mod env;
-pub mod err;
+pub mod messages;
 pub mod postgres;
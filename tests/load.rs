@@ -1,16 +1,20 @@
 // Tests for the `load` function in the `env` module
 
-use serial_test::serial;
-use std::{collections::HashMap, env, io::Write};
+use std::{collections::HashSet, io::Write};
 use tempfile::NamedTempFile;
 
-// use axum_auth::{
-//     env::load,
-//     err::{AppError, ErrorKind},
-// };
+use axum_auth::{
+    core::{
+        config::UnknownEnvVars,
+        env::{load, map::EnvMap, vars::EnvVar},
+        types::AppType,
+    },
+    AppError, ErrorKind,
+};
 
-// * Prefix for environment variables
-const PREFIX: &str = "TEST_";
+// * Prefix for environment variables, no process
+// * variable uses it, so the tests can run in parallel
+const PREFIX: &str = "LOAD_TEST_";
 
 // * Environment variables
 const VAR_1: &str = "VAR_1";
@@ -25,32 +29,56 @@ const EXTRA_VAL: &str = "EXTRA_VAL";
 const VAL_INVALID: &str = "INVALID";
 
 // * Environment variables to validate
-// const VARS: [EnvVar; 3] = [
-//     EnvVar {
-//         name: VAR_1,
-//         val_type: EnvVarType::String,
-//     },
-//     EnvVar {
-//         name: VAR_2,
-//         val_type: EnvVarType::String,
-//     },
-//     EnvVar {
-//         name: VAR_3,
-//         val_type: EnvVarType::U16,
-//     },
-// ];
-
-fn clean_up() {
-    // Clean up environment variables after/before each test
-    // for var in VARS.iter() {
-    //     env::remove_var(format!("{}{}", PREFIX, var.name));
-    // }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TestVar {
+    Var1,
+    Var2,
+    Var3,
 }
 
-fn run_clean_test<F: FnOnce()>(test_fn: F) {
-    clean_up();
-    test_fn();
-    clean_up();
+impl TestVar {
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Var1 => VAR_1,
+            Self::Var2 => VAR_2,
+            Self::Var3 => VAR_3,
+        }
+    }
+}
+
+impl EnvVar for TestVar {
+    type VarType = Self;
+
+    fn all() -> HashSet<Self> {
+        HashSet::from([Self::Var1, Self::Var2, Self::Var3])
+    }
+
+    fn name(&self, prefix: &str) -> String {
+        format!("{}{}", prefix, self.key())
+    }
+
+    fn type_(&self) -> AppType {
+        match self {
+            Self::Var1 | Self::Var2 => AppType::String,
+            Self::Var3 => AppType::U16,
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        "Test variable"
+    }
+
+    fn example(&self) -> &'static str {
+        VAL_3
+    }
+
+    fn verify(&self, env: &EnvMap) -> Result<(), AppError> {
+        self.type_().verify(env.get(self)?)
+    }
+
+    fn verify_all(env: &EnvMap) -> Result<(), AppError> {
+        Self::all().iter().try_for_each(|var| var.verify(env))
+    }
 }
 
 fn create_env_file(contents: &str) -> NamedTempFile {
@@ -69,159 +97,133 @@ fn make_file_contents(prefix: &str, vars: Vec<(&str, &str)>) -> String {
     contents
 }
 
+fn load_file(file: &NamedTempFile, unknown_vars: UnknownEnvVars) -> Result<EnvMap, AppError> {
+    load(
+        &[file.path().to_str().unwrap()],
+        PREFIX,
+        TestVar::all(),
+        unknown_vars,
+    )
+}
+
 // Tests the `load` function when the file path is valid,
 // the file contains all the required environment variables
 // and the types of the variables are correct.
-#[serial]
 #[test]
 fn test_env_load() {
-    run_clean_test(|| {
-        // let file_contents: String =
-        //     make_file_contents(PREFIX, vec![(VAR_1, VAL_1), (VAR_2, VAL_2), (VAR_3, VAL_3)]);
+    let file_contents: String =
+        make_file_contents(PREFIX, vec![(VAR_1, VAL_1), (VAR_2, VAL_2), (VAR_3, VAL_3)]);
 
-        // let file: NamedTempFile = create_env_file(file_contents.as_str());
+    let file: NamedTempFile = create_env_file(file_contents.as_str());
 
-        // let app_vars: HashMap<&str, String> =
-        //     load(file.path().to_str().unwrap(), PREFIX, &VARS).unwrap();
+    let env: EnvMap = load_file(&file, UnknownEnvVars::Strict).unwrap();
 
-        // assert_eq!(app_vars.len(), 3);
-        // assert_eq!(app_vars.get(VAR_1).unwrap(), VAL_1);
-        // assert_eq!(app_vars.get(VAR_2).unwrap(), VAL_2);
-        // assert_eq!(app_vars.get(VAR_3).unwrap(), VAL_3);
-    });
+    assert_eq!(env.with_prefix(PREFIX).len(), 3);
+    assert_eq!(env.get(&TestVar::Var1).unwrap(), VAL_1);
+    assert_eq!(env.get(&TestVar::Var2).unwrap(), VAL_2);
+    assert_eq!(env.get(&TestVar::Var3).unwrap(), VAL_3);
 }
 
 // Tests the `load` function when the file path is valid,
 // the file contains all the required environment variables
 // but the types of the variables are incorrect.
-#[serial]
 #[test]
 fn test_env_load_invalid_type() {
-    run_clean_test(|| {
-        // let file_contents: String = make_file_contents(
-        //     PREFIX,
-        //     vec![(VAR_1, VAL_1), (VAR_2, VAL_2), (VAR_3, VAL_INVALID)],
-        // );
+    let file_contents: String = make_file_contents(
+        PREFIX,
+        vec![(VAR_1, VAL_1), (VAR_2, VAL_2), (VAR_3, VAL_INVALID)],
+    );
 
-        // let file: NamedTempFile = create_env_file(file_contents.as_str());
+    let file: NamedTempFile = create_env_file(file_contents.as_str());
 
-        // let result: AppError = load(file.path().to_str().unwrap(), PREFIX, &VARS).unwrap_err();
-        // let expected: AppError = EnvVarType::U16.verify(VAL_INVALID).unwrap_err();
+    let result: AppError = load_file(&file, UnknownEnvVars::Strict).unwrap_err();
+    let expected: AppError = AppType::U16.verify(VAL_INVALID).unwrap_err();
 
-        // assert_eq!(result, expected);
-    });
+    assert_eq!(result, expected);
 }
 
 // Tests the `load` function when the file path is valid,
 // the file contains all the required environment variables
 // but the file contains more variables than required.
-#[serial]
 #[test]
 fn test_env_load_exceeding() {
-    run_clean_test(|| {
-        // let file_contents: String = make_file_contents(
-        //     PREFIX,
-        //     vec![
-        //         (VAR_1, VAL_1),
-        //         (VAR_2, VAL_2),
-        //         (VAR_3, VAL_3),
-        //         (EXTRA_VAR, EXTRA_VAL),
-        //     ],
-        // );
-
-        // let file: NamedTempFile = create_env_file(file_contents.as_str());
-
-        // let app_vars: HashMap<&str, String> =
-        //     load(file.path().to_str().unwrap(), PREFIX, &VARS).unwrap();
-
-        // assert_eq!(app_vars.len(), 3);
-        // assert_eq!(app_vars.get(VAR_1).unwrap(), VAL_1);
-        // assert_eq!(app_vars.get(VAR_2).unwrap(), VAL_2);
-        // assert_eq!(app_vars.get(VAR_3).unwrap(), VAL_3);
-    });
+    let file_contents: String = make_file_contents(
+        PREFIX,
+        vec![
+            (VAR_1, VAL_1),
+            (VAR_2, VAL_2),
+            (VAR_3, VAL_3),
+            (EXTRA_VAR, EXTRA_VAL),
+        ],
+    );
+
+    let file: NamedTempFile = create_env_file(file_contents.as_str());
+
+    let result: AppError = load_file(&file, UnknownEnvVars::Strict).unwrap_err();
+    assert_eq!(result.code, "env.unknown_vars");
+
+    let env: EnvMap = load_file(&file, UnknownEnvVars::Ignore).unwrap();
+    assert_eq!(env.get(&TestVar::Var1).unwrap(), VAL_1);
+    assert_eq!(env.get(&TestVar::Var2).unwrap(), VAL_2);
+    assert_eq!(env.get(&TestVar::Var3).unwrap(), VAL_3);
 }
 
 // Tests the `load` function when the file path is valid,
 // but the file contains less variables than required.
-#[serial]
 #[test]
 fn test_env_load_missing() {
-    run_clean_test(|| {
-        // let file_contents: String =
-        //     make_file_contents(PREFIX, vec![(VAR_1, VAL_1), (VAR_2, VAL_2)]);
-
-        // let file: NamedTempFile = create_env_file(file_contents.as_str());
+    let file_contents: String = make_file_contents(PREFIX, vec![(VAR_1, VAL_1), (VAR_2, VAL_2)]);
 
-        // let result: AppError = load(file.path().to_str().unwrap(), PREFIX, &VARS).unwrap_err();
+    let file: NamedTempFile = create_env_file(file_contents.as_str());
 
-        // let kind: ErrorKind = ErrorKind::Env;
-        // let message: String = format!("Missing environment variables: '{}'", VAR_3);
-        // let source = None;
+    let result: AppError = load_file(&file, UnknownEnvVars::Strict).unwrap_err();
 
-        // let expected: AppError = AppError {
-        //     kind,
-        //     message,
-        //     source,
-        // };
-
-        // assert_eq!(result, expected);
-    });
+    assert_eq!(result.kind, ErrorKind::Env);
+    assert_eq!(
+        result.message,
+        format!("Missing environment variables: '{}{}'", PREFIX, VAR_3)
+    );
 }
 
 // Tests the `load` function when the file path is valid,
 // the file contains more variables than required but also
 // has missing variables.
-#[serial]
 #[test]
 fn test_env_load_exceeding_and_missing() {
-    run_clean_test(|| {
-        // let file_contents: String = make_file_contents(
-        //     PREFIX,
-        //     vec![(VAR_1, VAL_1), (VAR_2, VAL_2), (EXTRA_VAR, EXTRA_VAL)],
-        // );
-
-        // let file: NamedTempFile = create_env_file(file_contents.as_str());
-
-        // let result: AppError = load(file.path().to_str().unwrap(), PREFIX, &VARS).unwrap_err();
-
-        // let kind: ErrorKind = ErrorKind::Env;
-        // let message: String = format!("Missing environment variables: '{}'", VAR_3);
-        // let source = None;
-
-        // let expected: AppError = AppError {
-        //     kind,
-        //     message,
-        //     source,
-        // };
-
-        // assert_eq!(result, expected);
-    });
+    let file_contents: String = make_file_contents(
+        PREFIX,
+        vec![(VAR_1, VAL_1), (VAR_2, VAL_2), (EXTRA_VAR, EXTRA_VAL)],
+    );
+
+    let file: NamedTempFile = create_env_file(file_contents.as_str());
+
+    let result: AppError = load_file(&file, UnknownEnvVars::Ignore).unwrap_err();
+
+    assert_eq!(result.kind, ErrorKind::Env);
+    assert!(
+        result.message.starts_with(&format!(
+            "Missing environment variables: '{}{}",
+            PREFIX, VAR_3
+        )),
+        "{}",
+        result.message
+    );
 }
 
 // Tests the `load` function when the file path is invalid.
-#[serial]
 #[test]
 fn test_env_load_non_existent() {
-    run_clean_test(|| {
-        // let file_path: &str = "non_existent_file";
-
-        // let result: AppError = load(file_path, PREFIX, &VARS).unwrap_err();
-
-        // let kind: ErrorKind = ErrorKind::Env;
-        // let message: String = format!(
-        //     "Failed to load environment file at specified path: '{}'",
-        //     file_path
-        // );
-
-        // let e: dotenvy::Error = dotenvy::from_filename(file_path).unwrap_err();
-        // let source = Some(Box::new(e) as Box<dyn std::error::Error>);
-
-        // let expected: AppError = AppError {
-        //     kind,
-        //     message,
-        //     source,
-        // };
-
-        // assert_eq!(result, expected);
-    });
+    let file_path: &str = "non_existent_file";
+
+    let result: AppError =
+        load(&[file_path], PREFIX, TestVar::all(), UnknownEnvVars::Strict).unwrap_err();
+
+    assert_eq!(result.kind, ErrorKind::Env);
+    assert_eq!(
+        result.message,
+        format!(
+            "Failed to load environment file at specified path: '{}'",
+            file_path
+        )
+    );
 }